@@ -0,0 +1,40 @@
+//! Per-launch access tokens for the network servers (`g3 serve`,
+//! `g3 mcp --listen`).
+//!
+//! Anything that can reach a listening port could otherwise drive the agent,
+//! including web pages in a local browser. A fresh random token is printed at
+//! startup and clients must present it before they are served.
+
+use rand::RngCore;
+
+/// A new random token, 32 bytes as hex
+pub fn generate() -> String {
+    let mut bytes = [0u8; 32];
+    rand::thread_rng().fill_bytes(&mut bytes);
+    hex::encode(bytes)
+}
+
+/// Whether `given` is `expected`, compared in constant time
+pub fn matches(expected: &str, given: &str) -> bool {
+    expected.len() == given.len()
+        && expected
+            .bytes()
+            .zip(given.bytes())
+            .fold(0u8, |diff, (a, b)| diff | (a ^ b))
+            == 0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tokens() {
+        let token = generate();
+        assert_eq!(token.len(), 64);
+        assert_ne!(token, generate());
+        assert!(matches(&token, &token.clone()));
+        assert!(!matches(&token, &token[1..]));
+        assert!(!matches(&token, &generate()));
+    }
+}
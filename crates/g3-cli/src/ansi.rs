@@ -0,0 +1,251 @@
+//! ANSI escape sequences in agent and tool output.
+//!
+//! Commands run by the agent (cargo, git, test runners) color their output
+//! with SGR escape sequences, which the TUIs would otherwise draw as
+//! `^[[31m` garbage. [`to_line`] turns a line into ratatui spans styled by
+//! its SGR sequences, or strips them when `[ui] ansi = "strip"`; every other
+//! escape sequence (cursor movement, OSC titles and links) is dropped.
+
+use g3_config::AnsiMode;
+use ratatui::style::{Color, Modifier, Style};
+use ratatui::text::{Line, Span};
+use std::sync::RwLock;
+
+static MODE: RwLock<AnsiMode> = RwLock::new(AnsiMode::Render);
+
+/// Render or strip escape sequences from now on, per `[ui] ansi`
+pub fn set_mode(mode: AnsiMode) {
+    *MODE.write().unwrap_or_else(|e| e.into_inner()) = mode;
+}
+
+pub fn mode() -> AnsiMode {
+    *MODE.read().unwrap_or_else(|e| e.into_inner())
+}
+
+/// `text` as a line styled by its SGR sequences on top of `base`, or as
+/// plain text in `base` if escape sequences are stripped
+pub fn to_line(text: &str, base: Style) -> Line<'static> {
+    render(text, base, mode())
+}
+
+/// `text` without escape sequences
+pub fn strip(text: &str) -> String {
+    parse(text, Style::default())
+        .into_iter()
+        .map(|(text, _)| text)
+        .collect()
+}
+
+fn render(text: &str, base: Style, mode: AnsiMode) -> Line<'static> {
+    if !text.contains('\x1b') {
+        return Line::from(Span::styled(text.to_string(), base));
+    }
+    match mode {
+        AnsiMode::Render => Line::from(
+            parse(text, base)
+                .into_iter()
+                .map(|(text, style)| Span::styled(text, style))
+                .collect::<Vec<_>>(),
+        ),
+        AnsiMode::Strip => Line::from(Span::styled(strip(text), base)),
+    }
+}
+
+/// The runs of `text` between escape sequences, each styled by the SGR
+/// sequences before it
+fn parse(text: &str, base: Style) -> Vec<(String, Style)> {
+    let mut runs = Vec::new();
+    let mut run = String::new();
+    let mut style = base;
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c != '\x1b' {
+            // Other control characters (carriage returns, bells) would
+            // break the layout
+            if !c.is_control() || c == '\t' {
+                run.push(c);
+            }
+            continue;
+        }
+        match chars.next() {
+            // CSI: parameters up to a final byte in @..~
+            Some('[') => {
+                let mut params = String::new();
+                for c in chars.by_ref() {
+                    if ('@'..='~').contains(&c) {
+                        if c == 'm' {
+                            if !run.is_empty() {
+                                runs.push((std::mem::take(&mut run), style));
+                            }
+                            style = apply_sgr(style, base, &params);
+                        }
+                        break;
+                    }
+                    params.push(c);
+                }
+            }
+            // OSC: up to BEL or ESC \
+            Some(']') => {
+                while let Some(c) = chars.next() {
+                    if c == '\x07' {
+                        break;
+                    }
+                    if c == '\x1b' && chars.peek() == Some(&'\\') {
+                        chars.next();
+                        break;
+                    }
+                }
+            }
+            // Two-character sequences, e.g. ESC ( B
+            _ => {}
+        }
+    }
+    if !run.is_empty() {
+        runs.push((run, style));
+    }
+    runs
+}
+
+/// `style` after the SGR parameters `params` (e.g. "1;31"); resetting goes
+/// back to `base`
+fn apply_sgr(mut style: Style, base: Style, params: &str) -> Style {
+    let codes: Vec<u16> = params
+        .split([';', ':'])
+        .map(|code| code.parse().unwrap_or(0))
+        .collect();
+    let mut codes = codes.iter().copied();
+    while let Some(code) = codes.next() {
+        style = match code {
+            0 => base,
+            1 => style.add_modifier(Modifier::BOLD),
+            2 => style.add_modifier(Modifier::DIM),
+            3 => style.add_modifier(Modifier::ITALIC),
+            4 => style.add_modifier(Modifier::UNDERLINED),
+            5 | 6 => style.add_modifier(Modifier::SLOW_BLINK),
+            7 => style.add_modifier(Modifier::REVERSED),
+            9 => style.add_modifier(Modifier::CROSSED_OUT),
+            22 => style.remove_modifier(Modifier::BOLD | Modifier::DIM),
+            23 => style.remove_modifier(Modifier::ITALIC),
+            24 => style.remove_modifier(Modifier::UNDERLINED),
+            25 => style.remove_modifier(Modifier::SLOW_BLINK),
+            27 => style.remove_modifier(Modifier::REVERSED),
+            29 => style.remove_modifier(Modifier::CROSSED_OUT),
+            30..=37 => style.fg(indexed(code - 30)),
+            38 => match extended(&mut codes) {
+                Some(color) => style.fg(color),
+                None => style,
+            },
+            39 => style.fg(base.fg.unwrap_or(Color::Reset)),
+            40..=47 => style.bg(indexed(code - 40)),
+            48 => match extended(&mut codes) {
+                Some(color) => style.bg(color),
+                None => style,
+            },
+            49 => style.bg(base.bg.unwrap_or(Color::Reset)),
+            90..=97 => style.fg(indexed(code - 90 + 8)),
+            100..=107 => style.bg(indexed(code - 100 + 8)),
+            _ => style,
+        };
+    }
+    style
+}
+
+/// The color of a 38/48 sequence: `5;n` (256 colors) or `2;r;g;b`
+fn extended(codes: &mut impl Iterator<Item = u16>) -> Option<Color> {
+    match codes.next()? {
+        5 => Some(indexed(codes.next()?)),
+        2 => {
+            let mut channel = || codes.next().map(|c| c.min(255) as u8);
+            Some(Color::Rgb(channel()?, channel()?, channel()?))
+        }
+        _ => None,
+    }
+}
+
+/// Color `index` of the 256-color palette, by name for the 16 basic ones
+fn indexed(index: u16) -> Color {
+    const BASIC: [Color; 16] = [
+        Color::Black,
+        Color::Red,
+        Color::Green,
+        Color::Yellow,
+        Color::Blue,
+        Color::Magenta,
+        Color::Cyan,
+        Color::Gray,
+        Color::DarkGray,
+        Color::LightRed,
+        Color::LightGreen,
+        Color::LightYellow,
+        Color::LightBlue,
+        Color::LightMagenta,
+        Color::LightCyan,
+        Color::White,
+    ];
+    BASIC
+        .get(index as usize)
+        .copied()
+        .unwrap_or(Color::Indexed(index.min(255) as u8))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn spans(line: &Line) -> Vec<(String, Style)> {
+        line.spans
+            .iter()
+            .map(|span| (span.content.to_string(), span.style))
+            .collect()
+    }
+
+    #[test]
+    fn test_render_sgr() {
+        let base = Style::default().fg(Color::Green);
+        let line = render(
+            "\x1b[1;31merror\x1b[0m: expected `;`, found \x1b[38;5;208m`}`\x1b[39m!",
+            base,
+            AnsiMode::Render,
+        );
+        assert_eq!(
+            spans(&line),
+            vec![
+                (
+                    "error".to_string(),
+                    base.fg(Color::Red).add_modifier(Modifier::BOLD)
+                ),
+                (": expected `;`, found ".to_string(), base),
+                ("`}`".to_string(), base.fg(Color::Indexed(208))),
+                ("!".to_string(), base),
+            ]
+        );
+
+        let rgb = render("\x1b[48;2;40;42;54mcode", base, AnsiMode::Render);
+        assert_eq!(rgb.spans[0].style, base.bg(Color::Rgb(40, 42, 54)));
+        assert_eq!(
+            render("\x1b[92mok", base, AnsiMode::Render).spans[0].style,
+            base.fg(Color::LightGreen)
+        );
+    }
+
+    #[test]
+    fn test_other_sequences_are_dropped() {
+        let text =
+            "\x1b]0;cargo build\x07\x1b[2K\rCompiling \x1b]8;;https://x\x1b\\g3\x1b]8;;\x1b\\";
+        assert_eq!(strip(text), "Compiling g3");
+        let line = render(text, Style::default(), AnsiMode::Render);
+        assert_eq!(line.to_string(), "Compiling g3");
+    }
+
+    #[test]
+    fn test_strip_mode() {
+        let base = Style::default().fg(Color::Green);
+        let line = render("\x1b[31mfailed\x1b[0m", base, AnsiMode::Strip);
+        assert_eq!(spans(&line), vec![("failed".to_string(), base)]);
+        // Text without escape sequences is passed through untouched
+        assert_eq!(
+            spans(&render("plain\r", base, AnsiMode::Render)),
+            vec![("plain\r".to_string(), base)]
+        );
+    }
+}
@@ -0,0 +1,681 @@
+//! `g3 serve`: drive the agent from editors and CI over a JSON-RPC/WebSocket API.
+//!
+//! Clients connect to `ws://127.0.0.1:<port>/` with the access token printed at
+//! startup, as an `Authorization: Bearer <token>` header or a `?token=<token>`
+//! query parameter. Upgrades from a browser page not served from this machine
+//! (an `Origin` other than localhost) are refused, so a web page can't drive
+//! the agent. Clients then exchange JSON-RPC 2.0 messages, one per text frame:
+//!
+//! - `task/submit {"task": "..."}` queues a task and returns its `task_id`
+//! - `tool/approve {"approval_id": N, "approved": true}` answers an approval request
+//! - `transcript/get` returns the conversation as of the last finished task
+//! - `ping`
+//!
+//! While tasks run, every connected client receives `event` notifications.
+//! Their `params` follow the retro TUI's `TuiMessage` taxonomy (agent output,
+//! tool output/detail/complete, status, context, SSE, error, exit), tagged by
+//! `type`, plus task lifecycle events and approval requests.
+//!
+//! There is one agent per server and tasks run one at a time, in submission
+//! order. Tool calls that would prompt the user (`ask` permissions, destructive
+//! shell commands) are sent to clients as `approval_request` events and
+//! declined if nobody answers within five minutes or no client is connected.
+
+use anyhow::Result;
+use axum::extract::ws::{Message as WsMessage, WebSocket, WebSocketUpgrade};
+use axum::extract::{Query, State};
+use axum::http::{header, HeaderMap, StatusCode};
+use axum::response::{IntoResponse, Response};
+use axum::routing::get;
+use axum::Router;
+use g3_core::ui_writer::UiWriter;
+use g3_core::Agent;
+use g3_providers::Message;
+use serde::Serialize;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::sync::{broadcast, mpsc};
+use tracing::{debug, error, info, warn};
+
+use crate::{access_token, Cli};
+
+/// How long a tool call waits for a client to approve it
+const APPROVAL_TIMEOUT: Duration = Duration::from_secs(300);
+
+const PARSE_ERROR: i64 = -32700;
+const INVALID_REQUEST: i64 = -32600;
+const METHOD_NOT_FOUND: i64 = -32601;
+const INVALID_PARAMS: i64 = -32602;
+
+/// An event streamed to clients. Mirrors `retro_tui::TuiMessage` variant for
+/// variant, with the task and approval events only a remote client needs.
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ApiEvent {
+    AgentOutput {
+        text: String,
+    },
+    ToolOutput {
+        name: String,
+        caption: String,
+        content: String,
+    },
+    ToolDetailUpdate {
+        name: String,
+        content: String,
+    },
+    ToolComplete {
+        name: String,
+        success: bool,
+        duration_ms: u128,
+        caption: String,
+    },
+    SystemStatus {
+        message: String,
+    },
+    ContextUpdate {
+        used: u32,
+        total: u32,
+        percentage: f32,
+    },
+    #[serde(rename = "sse_received")]
+    SSEReceived,
+    Error {
+        message: String,
+    },
+    Exit,
+    TaskStarted {
+        task_id: u64,
+    },
+    TaskComplete {
+        task_id: u64,
+        success: bool,
+        response: String,
+    },
+    ApprovalRequest {
+        approval_id: u64,
+        message: String,
+    },
+}
+
+/// Run `g3 serve`: accept clients on `127.0.0.1:port` and run their tasks
+pub async fn run_api_server(cli: &Cli, port: u16) -> Result<()> {
+    use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt, EnvFilter};
+    let level = if cli.verbose { "debug" } else { "info" };
+    tracing_subscriber::registry()
+        .with(tracing_subscriber::fmt::layer().with_writer(std::io::stderr))
+        .with(
+            EnvFilter::from_default_env()
+                .add_directive(format!("g3_core={}", level).parse().unwrap())
+                .add_directive(format!("g3_cli={}", level).parse().unwrap())
+                .add_directive(format!("g3_execution={}", level).parse().unwrap())
+                .add_directive(format!("g3_providers={}", level).parse().unwrap()),
+        )
+        .init();
+
+    if let Some(workspace) = &cli.workspace {
+        std::env::set_current_dir(workspace)?;
+    }
+    let workspace_dir = std::env::current_dir()?;
+    let config = crate::config_loader::load_config(cli)?;
+
+    let combined_content = match (
+        crate::read_agents_config(&workspace_dir),
+        crate::read_project_readme(&workspace_dir),
+    ) {
+        (Some(agents), Some(readme)) => Some(format!("{}\n\n{}", agents, readme)),
+        (agents, readme) => agents.or(readme),
+    };
+
+    let (events, _) = broadcast::channel(1024);
+    let approvals = Arc::new(Approvals::default());
+    let ui_writer = ApiUiWriter::new(events.clone(), approvals.clone());
+    let mut agent =
+        Agent::new_with_readme_and_quiet(config, ui_writer, combined_content, cli.quiet).await?;
+
+    let (jobs, mut job_rx) = mpsc::unbounded_channel::<Job>();
+    let state = ServerState {
+        events: events.clone(),
+        approvals,
+        jobs,
+        next_task_id: Arc::new(AtomicU64::new(1)),
+        transcript: Arc::new(Mutex::new(snapshot(&agent))),
+        token: Arc::new(access_token::generate()),
+    };
+    let transcript = state.transcript.clone();
+    let token = state.token.clone();
+
+    let app = Router::new().route("/", get(ws_handler)).with_state(state);
+    let listener = tokio::net::TcpListener::bind(("127.0.0.1", port)).await?;
+    info!(
+        "g3 API server listening on ws://{}/",
+        listener.local_addr()?
+    );
+    eprintln!("API access token: {}", token);
+    tokio::spawn(async move {
+        if let Err(e) = axum::serve(listener, app).await {
+            error!("API server stopped: {}", e);
+        }
+    });
+
+    // The agent stays on this task, so tasks from every client run in order
+    while let Some(job) = job_rx.recv().await {
+        let _ = events.send(ApiEvent::TaskStarted {
+            task_id: job.task_id,
+        });
+        let result = agent
+            .execute_task_with_timing(&job.task, None, false, false, false, true, None)
+            .await;
+        let event = match result {
+            Ok(result) => ApiEvent::TaskComplete {
+                task_id: job.task_id,
+                success: true,
+                response: result.response,
+            },
+            Err(e) => {
+                let _ = events.send(ApiEvent::Error {
+                    message: e.to_string(),
+                });
+                ApiEvent::TaskComplete {
+                    task_id: job.task_id,
+                    success: false,
+                    response: String::new(),
+                }
+            }
+        };
+        let context = agent.get_context_window();
+        let _ = events.send(ApiEvent::ContextUpdate {
+            used: context.used_tokens,
+            total: context.total_tokens,
+            percentage: context.percentage_used(),
+        });
+        *transcript.lock().unwrap() = snapshot(&agent);
+        let _ = events.send(event);
+    }
+
+    let _ = events.send(ApiEvent::Exit);
+    Ok(())
+}
+
+/// A submitted task waiting for the agent
+struct Job {
+    task_id: u64,
+    task: String,
+}
+
+#[derive(Clone)]
+struct ServerState {
+    events: broadcast::Sender<ApiEvent>,
+    approvals: Arc<Approvals>,
+    jobs: mpsc::UnboundedSender<Job>,
+    next_task_id: Arc<AtomicU64>,
+    transcript: Arc<Mutex<Vec<Message>>>,
+    /// Token clients must present to connect
+    token: Arc<String>,
+}
+
+fn snapshot<W: UiWriter>(agent: &Agent<W>) -> Vec<Message> {
+    agent.get_context_window().conversation_history.clone()
+}
+
+async fn ws_handler(
+    ws: WebSocketUpgrade,
+    headers: HeaderMap,
+    Query(query): Query<HashMap<String, String>>,
+    State(state): State<ServerState>,
+) -> Response {
+    if let Err((status, reason)) = authorize(&headers, query.get("token"), &state.token) {
+        warn!("Refused API client: {}", reason);
+        return (status, reason).into_response();
+    }
+    ws.on_upgrade(move |socket| serve_client(socket, state))
+}
+
+/// Check a connection's `Origin` and access token before it is upgraded
+fn authorize(
+    headers: &HeaderMap,
+    query_token: Option<&String>,
+    token: &str,
+) -> Result<(), (StatusCode, &'static str)> {
+    if let Some(origin) = headers.get(header::ORIGIN) {
+        if !origin.to_str().is_ok_and(is_local_origin) {
+            return Err((
+                StatusCode::FORBIDDEN,
+                "Cross-origin connections are not allowed",
+            ));
+        }
+    }
+    let bearer = headers
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "));
+    match bearer.or(query_token.map(String::as_str)) {
+        Some(given) if access_token::matches(token, given.trim()) => Ok(()),
+        _ => Err((StatusCode::UNAUTHORIZED, "Missing or invalid access token")),
+    }
+}
+
+/// Whether `origin` (`scheme://host[:port]`) is a page served from this machine
+fn is_local_origin(origin: &str) -> bool {
+    let Some((_, authority)) = origin.split_once("://") else {
+        return false;
+    };
+    let host = match authority.strip_prefix('[') {
+        Some(rest) => rest.split(']').next().unwrap_or_default(),
+        None => authority.split(':').next().unwrap_or_default(),
+    };
+    host.eq_ignore_ascii_case("localhost")
+        || host
+            .parse::<std::net::IpAddr>()
+            .is_ok_and(|ip| ip.is_loopback())
+}
+
+/// Serve one client until it disconnects
+async fn serve_client(mut socket: WebSocket, state: ServerState) {
+    info!("API client connected");
+    let mut events = state.events.subscribe();
+    loop {
+        tokio::select! {
+            message = socket.recv() => {
+                let text = match message {
+                    Some(Ok(WsMessage::Text(text))) => text,
+                    Some(Ok(WsMessage::Close(_))) | None => break,
+                    Some(Ok(_)) => continue,
+                    Some(Err(e)) => {
+                        debug!("API client read failed: {}", e);
+                        break;
+                    }
+                };
+                if let Some(response) = handle_message(&state, &text) {
+                    if socket.send(WsMessage::Text(response.to_string())).await.is_err() {
+                        break;
+                    }
+                }
+            }
+            event = events.recv() => {
+                let event = match event {
+                    Ok(event) => event,
+                    Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                        warn!("API client fell behind, dropped {} events", skipped);
+                        continue;
+                    }
+                    Err(broadcast::error::RecvError::Closed) => break,
+                };
+                let notification = json!({ "jsonrpc": "2.0", "method": "event", "params": event });
+                if socket.send(WsMessage::Text(notification.to_string())).await.is_err() {
+                    break;
+                }
+            }
+        }
+    }
+    info!("API client disconnected");
+}
+
+/// Handle one frame; notifications get no response
+fn handle_message(state: &ServerState, text: &str) -> Option<Value> {
+    let message: Value = match serde_json::from_str(text) {
+        Ok(message) => message,
+        Err(e) => {
+            return Some(error_response(
+                Value::Null,
+                PARSE_ERROR,
+                format!("Parse error: {}", e),
+            ))
+        }
+    };
+    let id = message.get("id").cloned();
+    let Some(method) = message.get("method").and_then(Value::as_str) else {
+        return Some(error_response(
+            id.unwrap_or(Value::Null),
+            INVALID_REQUEST,
+            "Missing method",
+        ));
+    };
+    let params = message.get("params").cloned().unwrap_or(Value::Null);
+    let result = dispatch(state, method, &params);
+    let id = id?;
+    Some(match result {
+        Ok(result) => json!({ "jsonrpc": "2.0", "id": id, "result": result }),
+        Err((code, message)) => error_response(id, code, message),
+    })
+}
+
+fn dispatch(state: &ServerState, method: &str, params: &Value) -> Result<Value, (i64, String)> {
+    match method {
+        "ping" => Ok(json!({})),
+        "task/submit" => {
+            let task = params
+                .get("task")
+                .and_then(Value::as_str)
+                .filter(|task| !task.trim().is_empty())
+                .ok_or((INVALID_PARAMS, "Missing task".to_string()))?;
+            let task_id = state.next_task_id.fetch_add(1, Ordering::SeqCst);
+            state
+                .jobs
+                .send(Job {
+                    task_id,
+                    task: task.to_string(),
+                })
+                .map_err(|_| (INVALID_REQUEST, "Server is shutting down".to_string()))?;
+            Ok(json!({ "task_id": task_id }))
+        }
+        "tool/approve" => {
+            let approval_id = params
+                .get("approval_id")
+                .and_then(Value::as_u64)
+                .ok_or((INVALID_PARAMS, "Missing approval_id".to_string()))?;
+            let approved = params
+                .get("approved")
+                .and_then(Value::as_bool)
+                .ok_or((INVALID_PARAMS, "Missing approved".to_string()))?;
+            if !state.approvals.resolve(approval_id, approved) {
+                return Err((
+                    INVALID_PARAMS,
+                    format!("No pending approval {}", approval_id),
+                ));
+            }
+            Ok(json!({}))
+        }
+        "transcript/get" => {
+            let messages = state.transcript.lock().unwrap().clone();
+            Ok(json!({ "messages": messages }))
+        }
+        _ => Err((METHOD_NOT_FOUND, format!("Method not found: {}", method))),
+    }
+}
+
+fn error_response(id: Value, code: i64, message: impl Into<String>) -> Value {
+    json!({
+        "jsonrpc": "2.0",
+        "id": id,
+        "error": { "code": code, "message": message.into() },
+    })
+}
+
+/// Tool calls waiting for a client's answer
+#[derive(Default)]
+struct Approvals {
+    next_id: AtomicU64,
+    pending: Mutex<HashMap<u64, std::sync::mpsc::Sender<bool>>>,
+}
+
+impl Approvals {
+    fn register(&self) -> (u64, std::sync::mpsc::Receiver<bool>) {
+        let id = self.next_id.fetch_add(1, Ordering::SeqCst) + 1;
+        let (tx, rx) = std::sync::mpsc::channel();
+        self.pending.lock().unwrap().insert(id, tx);
+        (id, rx)
+    }
+
+    /// Answer a pending approval; false if it's unknown or already answered
+    fn resolve(&self, id: u64, approved: bool) -> bool {
+        match self.pending.lock().unwrap().remove(&id) {
+            Some(tx) => tx.send(approved).is_ok(),
+            None => false,
+        }
+    }
+
+    fn cancel(&self, id: u64) {
+        self.pending.lock().unwrap().remove(&id);
+    }
+}
+
+/// The tool call currently being reported
+struct CurrentTool {
+    name: String,
+    caption: String,
+    started: Instant,
+    failed: bool,
+}
+
+/// UiWriter that turns agent output into `ApiEvent`s for every connected client
+struct ApiUiWriter {
+    events: broadcast::Sender<ApiEvent>,
+    approvals: Arc<Approvals>,
+    current_tool: Mutex<Option<CurrentTool>>,
+}
+
+impl ApiUiWriter {
+    fn new(events: broadcast::Sender<ApiEvent>, approvals: Arc<Approvals>) -> Self {
+        Self {
+            events,
+            approvals,
+            current_tool: Mutex::new(None),
+        }
+    }
+
+    fn send(&self, event: ApiEvent) {
+        // No receivers just means no client is connected right now
+        let _ = self.events.send(event);
+    }
+
+    fn output(&self, text: &str) {
+        if !text.is_empty() {
+            self.send(ApiEvent::AgentOutput {
+                text: text.to_string(),
+            });
+        }
+    }
+
+    fn tool_line(&self, line: &str) {
+        let mut current = self.current_tool.lock().unwrap();
+        if let Some(tool) = current.as_mut() {
+            tool.failed |= line.starts_with("❌");
+            self.send(ApiEvent::ToolDetailUpdate {
+                name: tool.name.clone(),
+                content: line.to_string(),
+            });
+        }
+    }
+}
+
+impl UiWriter for ApiUiWriter {
+    fn print(&self, message: &str) {
+        self.output(message);
+    }
+
+    fn println(&self, message: &str) {
+        self.output(&format!("{}\n", message));
+    }
+
+    fn print_inline(&self, message: &str) {
+        self.output(message);
+    }
+
+    fn print_system_prompt(&self, _prompt: &str) {}
+
+    fn print_context_status(&self, message: &str) {
+        self.send(ApiEvent::SystemStatus {
+            message: message.to_string(),
+        });
+    }
+
+    fn print_context_thinning(&self, message: &str) {
+        self.send(ApiEvent::SystemStatus {
+            message: message.to_string(),
+        });
+    }
+
+    fn print_system_status(&self, message: &str) {
+        self.send(ApiEvent::SystemStatus {
+            message: message.to_string(),
+        });
+    }
+
+    fn print_tool_header(&self, tool_name: &str, _tool_args: Option<&serde_json::Value>) {
+        *self.current_tool.lock().unwrap() = Some(CurrentTool {
+            name: tool_name.to_string(),
+            caption: String::new(),
+            started: Instant::now(),
+            failed: false,
+        });
+    }
+
+    fn print_tool_arg(&self, key: &str, value: &str) {
+        // The first argument is the one worth showing, as in the console header
+        if let Some(tool) = self.current_tool.lock().unwrap().as_mut() {
+            if tool.caption.is_empty() {
+                tool.caption = format!("{}: {}", key, value.lines().next().unwrap_or(""));
+            }
+        }
+    }
+
+    fn print_tool_output_header(&self) {
+        if let Some(tool) = self.current_tool.lock().unwrap().as_ref() {
+            self.send(ApiEvent::ToolOutput {
+                name: tool.name.clone(),
+                caption: tool.caption.clone(),
+                content: String::new(),
+            });
+        }
+    }
+
+    fn update_tool_output_line(&self, line: &str) {
+        self.tool_line(line);
+    }
+
+    fn print_tool_output_line(&self, line: &str) {
+        self.tool_line(line);
+    }
+
+    fn print_tool_output_summary(&self, _hidden_count: usize) {}
+
+    fn print_tool_timing(&self, _duration_str: &str, _tokens_delta: u32, _context_percentage: f32) {
+        if let Some(tool) = self.current_tool.lock().unwrap().take() {
+            self.send(ApiEvent::ToolComplete {
+                name: tool.name,
+                success: !tool.failed,
+                duration_ms: tool.started.elapsed().as_millis(),
+                caption: tool.caption,
+            });
+        }
+    }
+
+    fn print_agent_prompt(&self) {}
+
+    fn print_agent_response(&self, content: &str) {
+        self.output(content);
+    }
+
+    fn notify_sse_received(&self) {
+        self.send(ApiEvent::SSEReceived);
+    }
+
+    fn flush(&self) {}
+
+    fn wants_full_output(&self) -> bool {
+        true
+    }
+
+    fn prompt_user_yes_no(&self, message: &str) -> bool {
+        if self.events.receiver_count() == 0 {
+            warn!("Declining, no API client to ask: {}", message);
+            return false;
+        }
+        let (approval_id, answer) = self.approvals.register();
+        self.send(ApiEvent::ApprovalRequest {
+            approval_id,
+            message: message.to_string(),
+        });
+        // The agent runs on the main thread, outside the runtime's workers, so
+        // blocking here doesn't hold up the clients that will answer
+        let approved = answer.recv_timeout(APPROVAL_TIMEOUT).unwrap_or(false);
+        self.approvals.cancel(approval_id);
+        approved
+    }
+
+    fn prompt_user_choice(&self, message: &str, _options: &[&str]) -> usize {
+        warn!("Choosing the first option, no API support for: {}", message);
+        0
+    }
+
+    fn print_final_output(&self, summary: &str) {
+        self.output(summary);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_event_wire_format() {
+        let event = serde_json::to_value(ApiEvent::ToolComplete {
+            name: "shell".to_string(),
+            success: true,
+            duration_ms: 12,
+            caption: "command: ls".to_string(),
+        })
+        .unwrap();
+        assert_eq!(event["type"], "tool_complete");
+        assert_eq!(event["duration_ms"], 12);
+
+        let event = serde_json::to_value(ApiEvent::SSEReceived).unwrap();
+        assert_eq!(event, json!({ "type": "sse_received" }));
+    }
+
+    #[test]
+    fn test_local_origins() {
+        assert!(is_local_origin("http://localhost:3000"));
+        assert!(is_local_origin("http://127.0.0.1"));
+        assert!(is_local_origin("vscode-webview://[::1]:8080"));
+        assert!(!is_local_origin("https://evil.example"));
+        assert!(!is_local_origin("http://localhost.evil.example"));
+        assert!(!is_local_origin("null"));
+    }
+
+    #[test]
+    fn test_authorize() {
+        let token = "secret";
+        let headers = |pairs: &[(header::HeaderName, &str)]| {
+            let mut headers = HeaderMap::new();
+            for (name, value) in pairs {
+                headers.insert(name, value.parse().unwrap());
+            }
+            headers
+        };
+        let bearer = headers(&[(header::AUTHORIZATION, "Bearer secret")]);
+        assert!(authorize(&bearer, None, token).is_ok());
+        assert!(authorize(&HeaderMap::new(), Some(&"secret".to_string()), token).is_ok());
+
+        let refused = |result: Result<(), (StatusCode, &str)>| result.unwrap_err().0;
+        assert_eq!(
+            refused(authorize(&HeaderMap::new(), None, token)),
+            StatusCode::UNAUTHORIZED
+        );
+        assert_eq!(
+            refused(authorize(
+                &HeaderMap::new(),
+                Some(&"guess".to_string()),
+                token
+            )),
+            StatusCode::UNAUTHORIZED
+        );
+        let cross_origin = headers(&[
+            (header::AUTHORIZATION, "Bearer secret"),
+            (header::ORIGIN, "https://evil.example"),
+        ]);
+        assert_eq!(
+            refused(authorize(&cross_origin, None, token)),
+            StatusCode::FORBIDDEN
+        );
+        let local = headers(&[
+            (header::AUTHORIZATION, "Bearer secret"),
+            (header::ORIGIN, "http://localhost:5173"),
+        ]);
+        assert!(authorize(&local, None, token).is_ok());
+    }
+
+    #[test]
+    fn test_approvals() {
+        let approvals = Approvals::default();
+        let (id, answer) = approvals.register();
+        assert!(approvals.resolve(id, true));
+        assert!(answer.recv().unwrap());
+        assert!(!approvals.resolve(id, false));
+        assert!(!approvals.resolve(id + 1, true));
+    }
+}
@@ -0,0 +1,202 @@
+//! `/copy`: put agent output on the system clipboard.
+//!
+//! Text goes through the native clipboard (via `arboard`) on a local
+//! desktop, and through an OSC 52 escape sequence otherwise: over SSH, where
+//! the native clipboard would be the remote machine's, and wherever no
+//! clipboard service is running. OSC 52 asks the terminal itself to set the
+//! clipboard, so it reaches the user's machine through SSH and tmux, as long
+//! as the terminal allows it.
+
+use anyhow::Result;
+use base64::Engine;
+use std::fmt;
+use std::io::Write;
+use std::str::FromStr;
+use std::sync::{Mutex, OnceLock};
+use tracing::debug;
+
+/// Typed in chat mode or the retro TUI's input line
+pub const COPY_COMMAND: &str = "/copy";
+
+/// Also accepted for [`COPY_COMMAND`], like the retro TUI's `:` commands
+pub const COPY_ALIAS: &str = ":copy";
+
+/// Largest text sent through OSC 52; many terminals drop longer sequences
+const OSC52_MAX_BYTES: usize = 74_994;
+
+/// What `/copy` copies
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CopyTarget {
+    /// The agent's last response
+    Last,
+    /// The last fenced code block of the output
+    Code,
+    /// The lines selected with the mouse in the retro TUI
+    Selection,
+}
+
+impl FromStr for CopyTarget {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "last" => Ok(CopyTarget::Last),
+            "code" => Ok(CopyTarget::Code),
+            "selection" => Ok(CopyTarget::Selection),
+            _ => anyhow::bail!("Unknown copy target '{}'", s),
+        }
+    }
+}
+
+/// The target of a `/copy [last|code|selection]` command (or `:copy`),
+/// `last` if none is given. Returns `None` if `input` isn't a copy command.
+pub fn parse_copy_command(input: &str) -> Option<Result<CopyTarget, String>> {
+    let mut words = input.split_whitespace();
+    if !matches!(words.next(), Some(COPY_COMMAND | COPY_ALIAS)) {
+        return None;
+    }
+    let usage = format!("Usage: {} [last|code|selection]", COPY_COMMAND);
+    Some(match (words.next(), words.next()) {
+        (None, _) => Ok(CopyTarget::Last),
+        (Some(target), None) => target.parse().map_err(|_| usage),
+        _ => Err(usage),
+    })
+}
+
+/// The contents of the last complete fenced code block in `text`, without
+/// its fences
+pub fn last_code_block(text: &str) -> Option<String> {
+    let mut last = None;
+    let mut block: Option<Vec<&str>> = None;
+    for line in text.lines() {
+        if line.trim_start().starts_with("```") {
+            match block.take() {
+                Some(lines) => last = Some(lines.join("\n")),
+                None => block = Some(Vec::new()),
+            }
+        } else if let Some(lines) = block.as_mut() {
+            lines.push(line);
+        }
+    }
+    last
+}
+
+/// How the text reached the clipboard
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CopyMethod {
+    Native,
+    Osc52,
+}
+
+impl fmt::Display for CopyMethod {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CopyMethod::Native => write!(f, "system clipboard"),
+            CopyMethod::Osc52 => write!(f, "OSC 52"),
+        }
+    }
+}
+
+/// Put `text` on the clipboard: natively on a local desktop, else through
+/// the terminal
+pub fn copy(text: &str) -> Result<CopyMethod> {
+    if !in_ssh_session() {
+        match copy_native(text) {
+            Ok(()) => return Ok(CopyMethod::Native),
+            Err(e) => debug!("Native clipboard unavailable, using OSC 52: {}", e),
+        }
+    }
+    let sequence = osc52_sequence(text, std::env::var_os("TMUX").is_some())?;
+    let mut stdout = std::io::stdout().lock();
+    stdout.write_all(sequence.as_bytes())?;
+    stdout.flush()?;
+    Ok(CopyMethod::Osc52)
+}
+
+/// `/copy`'s confirmation, e.g. "📋 Copied 12 lines (OSC 52)"
+pub fn copied_message(text: &str, method: CopyMethod) -> String {
+    format!("📋 Copied {} lines ({})", text.lines().count(), method)
+}
+
+fn in_ssh_session() -> bool {
+    ["SSH_CONNECTION", "SSH_TTY", "SSH_CLIENT"]
+        .iter()
+        .any(|name| std::env::var_os(name).is_some())
+}
+
+fn copy_native(text: &str) -> Result<()> {
+    // On X11 the clipboard is served by its owner, so keep it for the session
+    static CLIPBOARD: OnceLock<Mutex<Option<arboard::Clipboard>>> = OnceLock::new();
+
+    let mut clipboard = CLIPBOARD
+        .get_or_init(Default::default)
+        .lock()
+        .unwrap_or_else(|e| e.into_inner());
+    if clipboard.is_none() {
+        *clipboard = Some(arboard::Clipboard::new()?);
+    }
+    if let Some(clipboard) = clipboard.as_mut() {
+        clipboard.set_text(text)?;
+    }
+    Ok(())
+}
+
+/// The escape sequence asking the terminal to copy `text`; inside tmux,
+/// wrapped so tmux passes it on to the terminal
+fn osc52_sequence(text: &str, tmux: bool) -> Result<String> {
+    if text.len() > OSC52_MAX_BYTES {
+        anyhow::bail!(
+            "{} bytes is too much to copy through the terminal (at most {})",
+            text.len(),
+            OSC52_MAX_BYTES
+        );
+    }
+    let encoded = base64::engine::general_purpose::STANDARD.encode(text);
+    let sequence = format!("\x1b]52;c;{}\x07", encoded);
+    Ok(if tmux {
+        format!("\x1bPtmux;{}\x1b\\", sequence.replace('\x1b', "\x1b\x1b"))
+    } else {
+        sequence
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_copy_command() {
+        assert_eq!(parse_copy_command("/copy"), Some(Ok(CopyTarget::Last)));
+        assert_eq!(parse_copy_command("/copy code"), Some(Ok(CopyTarget::Code)));
+        assert_eq!(
+            parse_copy_command(" /copy selection "),
+            Some(Ok(CopyTarget::Selection))
+        );
+        assert_eq!(parse_copy_command(":copy code"), Some(Ok(CopyTarget::Code)));
+        assert!(matches!(parse_copy_command("/copy all"), Some(Err(_))));
+        assert!(matches!(parse_copy_command("/copy code now"), Some(Err(_))));
+        assert_eq!(parse_copy_command("copy code"), None);
+    }
+
+    #[test]
+    fn test_last_code_block() {
+        let text = "Two options:\n```rust\nfn a() {}\n```\nor\n```\nfn b() {}\n\nfn c() {}\n```\n";
+        assert_eq!(last_code_block(text).unwrap(), "fn b() {}\n\nfn c() {}");
+        // An unterminated block is still being streamed
+        assert_eq!(
+            last_code_block("```\nfn a() {}\n```\n```\nfn b").unwrap(),
+            "fn a() {}"
+        );
+        assert_eq!(last_code_block("no code"), None);
+    }
+
+    #[test]
+    fn test_osc52_sequence() {
+        assert_eq!(osc52_sequence("hi", false).unwrap(), "\x1b]52;c;aGk=\x07");
+        assert_eq!(
+            osc52_sequence("hi", true).unwrap(),
+            "\x1bPtmux;\x1b\x1b]52;c;aGk=\x07\x1b\\"
+        );
+        assert!(osc52_sequence(&"x".repeat(OSC52_MAX_BYTES + 1), false).is_err());
+    }
+}
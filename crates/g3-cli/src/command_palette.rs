@@ -0,0 +1,333 @@
+//! Ctrl+P command palette for the retro TUI.
+//!
+//! Lists the built-in commands with the keys or slash commands that also run
+//! them (checked against the registry in `commands`, like the `:help`
+//! overlay), filtered fuzzily as the user types. Commands that need a choice
+//! (a theme, a provider) open a nested list; Esc steps back out.
+
+use crate::commands::hint;
+use crate::i18n::{t, Msg};
+
+/// What the user picked in the palette
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PaletteCommand {
+    SwitchTheme(String),
+    ToggleActivityPanel,
+    ToggleLogPane,
+    ExportTranscript,
+    ShowUsage,
+    ShowContext,
+    PauseAgent,
+    SwitchProvider(String),
+    Compact,
+    Undo,
+    Clear,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PaletteAction {
+    Run(PaletteCommand),
+    /// Open a nested list
+    Menu(Vec<PaletteEntry>),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PaletteEntry {
+    pub label: String,
+    pub detail: String,
+    /// Key or command that does the same outside the palette
+    pub keys: Option<&'static str>,
+    pub action: PaletteAction,
+}
+
+impl PaletteEntry {
+    fn run(label: &str, detail: &str, keys: Option<&'static str>, command: PaletteCommand) -> Self {
+        Self {
+            label: label.to_string(),
+            detail: detail.to_string(),
+            keys,
+            action: PaletteAction::Run(command),
+        }
+    }
+
+    fn menu(label: &str, detail: &str, entries: Vec<PaletteEntry>) -> Self {
+        Self {
+            label: label.to_string(),
+            detail: detail.to_string(),
+            keys: None,
+            action: PaletteAction::Menu(entries),
+        }
+    }
+}
+
+/// The built-in commands; `themes` and `providers` fill the nested lists
+pub fn builtin_entries(themes: &[String], providers: &[String]) -> Vec<PaletteEntry> {
+    let choices = |names: &[String], command: fn(String) -> PaletteCommand| {
+        names
+            .iter()
+            .map(|name| PaletteEntry::run(name, "", None, command(name.clone())))
+            .collect()
+    };
+    vec![
+        PaletteEntry::menu(
+            t(Msg::PaletteSwitchTheme),
+            t(Msg::PaletteSwitchThemeDetail),
+            choices(themes, PaletteCommand::SwitchTheme),
+        ),
+        PaletteEntry::run(
+            t(Msg::PaletteToggleActivity),
+            t(Msg::PaletteToggleActivityDetail),
+            None,
+            PaletteCommand::ToggleActivityPanel,
+        ),
+        PaletteEntry::run(
+            t(Msg::PaletteToggleLogs),
+            t(Msg::PaletteToggleLogsDetail),
+            hint("Ctrl+L"),
+            PaletteCommand::ToggleLogPane,
+        ),
+        PaletteEntry::run(
+            t(Msg::PaletteExportTranscript),
+            t(Msg::PaletteExportTranscriptDetail),
+            Some("g3 export"),
+            PaletteCommand::ExportTranscript,
+        ),
+        PaletteEntry::run(
+            t(Msg::PaletteShowUsage),
+            t(Msg::PaletteShowUsageDetail),
+            hint("/stats"),
+            PaletteCommand::ShowUsage,
+        ),
+        PaletteEntry::run(
+            t(Msg::PaletteShowContext),
+            t(Msg::PaletteShowContextDetail),
+            hint("/context"),
+            PaletteCommand::ShowContext,
+        ),
+        PaletteEntry::run(
+            t(Msg::PalettePauseAgent),
+            t(Msg::PalettePauseAgentDetail),
+            hint("Ctrl+C"),
+            PaletteCommand::PauseAgent,
+        ),
+        PaletteEntry::menu(
+            t(Msg::PaletteSwitchProvider),
+            t(Msg::PaletteSwitchProviderDetail),
+            choices(providers, PaletteCommand::SwitchProvider),
+        ),
+        PaletteEntry::run(
+            t(Msg::PaletteCompact),
+            t(Msg::PaletteCompactDetail),
+            hint("/compact"),
+            PaletteCommand::Compact,
+        ),
+        PaletteEntry::run(
+            t(Msg::PaletteUndo),
+            t(Msg::PaletteUndoDetail),
+            hint("/undo"),
+            PaletteCommand::Undo,
+        ),
+        PaletteEntry::run(
+            t(Msg::PaletteClear),
+            t(Msg::PaletteClearDetail),
+            hint("/clear"),
+            PaletteCommand::Clear,
+        ),
+    ]
+}
+
+/// An open palette: the lists entered so far, the query and the selection
+#[derive(Debug, Clone)]
+pub struct CommandPalette {
+    /// Title and entries of each list, outermost first
+    levels: Vec<(String, Vec<PaletteEntry>)>,
+    query: String,
+    selected: usize,
+}
+
+impl CommandPalette {
+    pub fn new(entries: Vec<PaletteEntry>) -> Self {
+        Self {
+            levels: vec![(t(Msg::PaletteCommands).to_string(), entries)],
+            query: String::new(),
+            selected: 0,
+        }
+    }
+
+    pub fn query(&self) -> &str {
+        &self.query
+    }
+
+    pub fn selected(&self) -> usize {
+        self.selected
+    }
+
+    /// Titles of the open lists, e.g. `Commands › Switch theme`
+    pub fn breadcrumb(&self) -> String {
+        self.levels
+            .iter()
+            .map(|(title, _)| title.as_str())
+            .collect::<Vec<_>>()
+            .join(" › ")
+    }
+
+    /// Entries of the current list matching the query, best match first
+    pub fn matches(&self) -> Vec<&PaletteEntry> {
+        let (_, entries) = self.levels.last().expect("the palette always has a list");
+        let mut scored: Vec<(i32, usize, &PaletteEntry)> = entries
+            .iter()
+            .enumerate()
+            .filter_map(|(index, entry)| {
+                fuzzy_score(&self.query, &entry.label).map(|score| (score, index, entry))
+            })
+            .collect();
+        // Ties keep the list's own order
+        scored.sort_by(|a, b| b.0.cmp(&a.0).then(a.1.cmp(&b.1)));
+        scored.into_iter().map(|(_, _, entry)| entry).collect()
+    }
+
+    pub fn push_char(&mut self, ch: char) {
+        self.query.push(ch);
+        self.selected = 0;
+    }
+
+    pub fn pop_char(&mut self) {
+        self.query.pop();
+        self.selected = 0;
+    }
+
+    pub fn select_previous(&mut self) {
+        self.selected = self.selected.saturating_sub(1);
+    }
+
+    pub fn select_next(&mut self) {
+        let count = self.matches().len();
+        if self.selected + 1 < count {
+            self.selected += 1;
+        }
+    }
+
+    /// Choose the selected entry: a command to run, or None after opening
+    /// a nested list or when nothing matches
+    pub fn choose(&mut self) -> Option<PaletteCommand> {
+        let entry = self
+            .matches()
+            .get(self.selected)
+            .map(|entry| (*entry).clone())?;
+        match entry.action {
+            PaletteAction::Run(command) => Some(command),
+            PaletteAction::Menu(entries) => {
+                self.levels.push((entry.label, entries));
+                self.query.clear();
+                self.selected = 0;
+                None
+            }
+        }
+    }
+
+    /// Clear the query, or leave the nested list; false when the palette
+    /// should close
+    pub fn back(&mut self) -> bool {
+        if !self.query.is_empty() {
+            self.query.clear();
+            self.selected = 0;
+            return true;
+        }
+        if self.levels.len() > 1 {
+            self.levels.pop();
+            self.selected = 0;
+            return true;
+        }
+        false
+    }
+}
+
+/// How well `query` matches `text` as a case-insensitive subsequence; None
+/// if it doesn't. Consecutive characters and word starts score higher.
+pub fn fuzzy_score(query: &str, text: &str) -> Option<i32> {
+    let text: Vec<char> = text.chars().flat_map(char::to_lowercase).collect();
+    let mut score = 0;
+    let mut position = 0;
+    let mut previous: Option<usize> = None;
+    for ch in query.chars().flat_map(char::to_lowercase) {
+        if ch.is_whitespace() {
+            continue;
+        }
+        let found = position + text[position..].iter().position(|&c| c == ch)?;
+        score += 1;
+        if previous.is_some_and(|previous| previous + 1 == found) {
+            score += 5;
+        }
+        if found == 0 || !text[found - 1].is_alphanumeric() {
+            score += 3;
+        }
+        score -= (found - position) as i32;
+        previous = Some(found);
+        position = found + 1;
+    }
+    Some(score)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn palette() -> CommandPalette {
+        CommandPalette::new(builtin_entries(
+            &["retro".to_string(), "dracula".to_string()],
+            &[
+                "anthropic.default".to_string(),
+                "openai.default".to_string(),
+            ],
+        ))
+    }
+
+    #[test]
+    fn test_fuzzy_score() {
+        assert!(fuzzy_score("", "Show usage").is_some());
+        assert!(fuzzy_score("usg", "Show usage").is_some());
+        assert_eq!(fuzzy_score("xyz", "Show usage"), None);
+        assert!(fuzzy_score("sh us", "Show usage") > fuzzy_score("sh us", "Switch theme"));
+        assert!(
+            fuzzy_score("theme", "Switch theme") > fuzzy_score("theme", "Toggle activity panel")
+        );
+    }
+
+    #[test]
+    fn test_query_filters_and_ranks() {
+        let mut palette = palette();
+        assert_eq!(palette.matches().len(), 11);
+        for ch in "exp".chars() {
+            palette.push_char(ch);
+        }
+        assert_eq!(palette.matches()[0].label, "Export transcript");
+        assert_eq!(palette.choose(), Some(PaletteCommand::ExportTranscript));
+    }
+
+    #[test]
+    fn test_nested_lists() {
+        let mut palette = palette();
+        for ch in "provider".chars() {
+            palette.push_char(ch);
+        }
+        assert_eq!(palette.choose(), None);
+        assert_eq!(palette.breadcrumb(), "Commands › Switch provider");
+        assert_eq!(palette.query(), "");
+
+        palette.select_next();
+        palette.select_next();
+        assert_eq!(palette.selected(), 1);
+        assert_eq!(
+            palette.choose(),
+            Some(PaletteCommand::SwitchProvider("openai.default".to_string()))
+        );
+
+        palette.push_char('z');
+        assert!(palette.matches().is_empty());
+        assert_eq!(palette.choose(), None);
+        assert!(palette.back());
+        assert!(palette.back());
+        assert_eq!(palette.breadcrumb(), "Commands");
+        assert!(!palette.back());
+    }
+}
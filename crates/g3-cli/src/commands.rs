@@ -0,0 +1,213 @@
+//! The interactive commands and retro TUI keys, in one registry.
+//!
+//! `/help` in chat mode, the command list in machine mode, the retro TUI's
+//! `:help` overlay and the key hints in its command palette are all
+//! generated from [`COMMANDS`] and [`TUI_KEYS`], so a command added here is
+//! documented everywhere at once and a removed one disappears from all of
+//! them.
+
+use crate::clipboard::COPY_COMMAND;
+use crate::i18n::{t, Msg};
+
+/// A command of the interactive session
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CommandSpec {
+    /// What the user types, e.g. "/undo"
+    pub name: &'static str,
+    /// Arguments, as shown in the help
+    pub args: &'static str,
+    pub help: Msg,
+}
+
+impl CommandSpec {
+    const fn new(name: &'static str, args: &'static str, help: Msg) -> Self {
+        Self { name, args, help }
+    }
+
+    /// The command with its arguments, e.g. "/undo [n]"
+    pub fn usage(&self) -> String {
+        if self.args.is_empty() {
+            self.name.to_string()
+        } else {
+            format!("{} {}", self.name, self.args)
+        }
+    }
+}
+
+/// Commands of the interactive (chat) session, in the order they're listed
+pub const COMMANDS: &[CommandSpec] = &[
+    CommandSpec::new("/compact", "", Msg::HelpCompact),
+    CommandSpec::new("/thinnify", "", Msg::HelpThinnify),
+    CommandSpec::new("/skinnify", "", Msg::HelpSkinnify),
+    CommandSpec::new("/clear", "", Msg::HelpClear),
+    CommandSpec::new("/readme", "", Msg::HelpReadme),
+    CommandSpec::new("/stats", "", Msg::HelpStats),
+    CommandSpec::new("/undo", "[n]", Msg::HelpUndo),
+    CommandSpec::new("/context", "[pin|unpin|evict <n|id>]", Msg::HelpContext),
+    CommandSpec::new("/branch", "[new|switch|discard <name>]", Msg::HelpBranch),
+    CommandSpec::new(COPY_COMMAND, "[last|code|selection]", Msg::HelpCopy),
+    CommandSpec::new("/model", "<provider>", Msg::HelpModel),
+    CommandSpec::new("/logs", "[error|warn|info|debug]", Msg::HelpLogs),
+    CommandSpec::new("/help", "", Msg::HelpHelp),
+    CommandSpec::new("exit/quit", "", Msg::HelpExit),
+];
+
+/// Typed in the retro TUI's input line to open or close the help overlay
+pub const HELP_COMMAND: &str = ":help";
+
+/// Typed in the retro TUI's input line to open the conversation branches
+pub const BRANCHES_COMMAND: &str = ":branches";
+
+/// Typed in the retro TUI's input line, with an optional count, to revert
+/// the agent's last file edits like `/undo`
+pub const UNDO_COMMAND: &str = ":undo";
+
+/// A key binding of the retro TUI
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct KeySpec {
+    pub keys: &'static str,
+    pub help: Msg,
+}
+
+pub const TUI_KEYS: &[KeySpec] = &[
+    KeySpec {
+        keys: "Enter",
+        help: Msg::KeySend,
+    },
+    KeySpec {
+        keys: "Ctrl+P",
+        help: Msg::KeyPalette,
+    },
+    KeySpec {
+        keys: HELP_COMMAND,
+        help: Msg::KeyHelp,
+    },
+    KeySpec {
+        keys: "Esc",
+        help: Msg::KeyClose,
+    },
+    KeySpec {
+        keys: "PgUp/PgDn",
+        help: Msg::KeyScroll,
+    },
+    KeySpec {
+        keys: "Ctrl+Home/End",
+        help: Msg::KeyScrollEnds,
+    },
+    KeySpec {
+        keys: "Ctrl+C",
+        help: Msg::PalettePauseAgentDetail,
+    },
+    KeySpec {
+        keys: "y/n/a/d",
+        help: Msg::KeyApproval,
+    },
+    KeySpec {
+        keys: "Ctrl+L",
+        help: Msg::KeyLogs,
+    },
+    KeySpec {
+        keys: BRANCHES_COMMAND,
+        help: Msg::KeyBranches,
+    },
+    KeySpec {
+        keys: UNDO_COMMAND,
+        help: Msg::HelpUndo,
+    },
+    KeySpec {
+        keys: "Mouse drag",
+        help: Msg::KeySelect,
+    },
+    KeySpec {
+        keys: "Ctrl+O",
+        help: Msg::KeyFollowLink,
+    },
+];
+
+/// The registered command `name`
+pub fn command(name: &str) -> Option<&'static CommandSpec> {
+    COMMANDS.iter().find(|spec| spec.name == name)
+}
+
+/// `name` if it is a registered command or TUI key, for key hints that
+/// must not outlive what they name
+pub fn hint(name: &'static str) -> Option<&'static str> {
+    let registered = command(name).is_some() || TUI_KEYS.iter().any(|spec| spec.keys == name);
+    registered.then_some(name)
+}
+
+/// The slash commands, space-separated, for machine mode
+pub fn command_names() -> String {
+    COMMANDS
+        .iter()
+        .map(|spec| spec.name)
+        .filter(|name| name.starts_with('/'))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// (usage, description) rows of the commands, in the current locale
+pub fn command_rows() -> Vec<(String, &'static str)> {
+    COMMANDS
+        .iter()
+        .map(|spec| (spec.usage(), t(spec.help)))
+        .collect()
+}
+
+/// (keys, description) rows of the retro TUI keys, in the current locale
+pub fn key_rows() -> Vec<(String, &'static str)> {
+    TUI_KEYS
+        .iter()
+        .map(|spec| (spec.keys.to_string(), t(spec.help)))
+        .collect()
+}
+
+/// `/help` in chat mode
+pub fn chat_help() -> String {
+    let mut text = t(Msg::ChatHelpTitle).to_string();
+    for line in align(&command_rows()) {
+        text.push_str("\n  ");
+        text.push_str(&line);
+    }
+    text
+}
+
+/// Rows as "usage  - description", the descriptions lined up
+pub fn align(rows: &[(String, &str)]) -> Vec<String> {
+    let width = rows
+        .iter()
+        .map(|(usage, _)| usage.chars().count())
+        .max()
+        .unwrap_or(0);
+    rows.iter()
+        .map(|(usage, help)| format!("{:<width$} - {}", usage, help, width = width))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_chat_help_lists_every_command() {
+        let help = chat_help();
+        for spec in COMMANDS {
+            assert!(help.contains(&spec.usage()), "{}", spec.name);
+        }
+        assert!(help.contains("/undo [n]"));
+        assert_eq!(
+            align(&[("/a".to_string(), "one"), ("/bcd".to_string(), "two")]),
+            vec!["/a   - one", "/bcd - two"]
+        );
+    }
+
+    #[test]
+    fn test_registry() {
+        assert_eq!(hint("/stats"), Some("/stats"));
+        assert_eq!(hint("Ctrl+C"), Some("Ctrl+C"));
+        assert_eq!(hint("/missing"), None);
+        assert!(command_names().starts_with("/compact /thinnify"));
+        assert!(!command_names().contains("exit"));
+        assert_eq!(key_rows().len(), TUI_KEYS.len());
+    }
+}
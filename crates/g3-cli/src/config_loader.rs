@@ -0,0 +1,224 @@
+//! Configuration loading for the CLI.
+//!
+//! Settings come from five layers, each overriding the ones before it:
+//! 1. built-in defaults
+//! 2. the user config (`--config`, `./g3.toml`, `~/.config/g3/config.toml`
+//!    or `~/.g3.toml`)
+//! 3. the project config, `.g3/config.toml` in the workspace, which may
+//!    only choose providers, tighten permissions and set the planner's
+//!    verification commands and excludes
+//! 4. the `[profiles.<name>]` table selected with `--profile` (or
+//!    `G3_PROFILE`), e.g. a cheap provider or stricter permissions
+//! 5. command-line flags (`--provider`, `--model`, `--webdriver`, ...)
+//!
+//! The user and project files are merged table by table before parsing, so
+//! a project file only needs the keys it changes. `g3 config show` lists
+//! the layers in effect; `--effective` also prints the merged result.
+//! `g3 auth login` stores credentials so they can stay out of both files,
+//! and `g3 config migrate` converts files in the old provider format.
+
+use anyhow::{Context, Result};
+use g3_config::{migrate, Config, PROJECT_CONFIG_PATH};
+use g3_core::utils::generate_unified_diff_with_labels;
+use std::io::IsTerminal;
+use std::path::{Path, PathBuf};
+
+use crate::Cli;
+use crate::{ansi, i18n, links};
+
+/// Load the config for the current directory with the CLI flags applied
+pub fn load_config(cli: &Cli) -> Result<Config> {
+    let mut config = Config::load_with_overrides(
+        cli.config.as_deref(),
+        cli.provider.clone(),
+        cli.model.clone(),
+    )?;
+
+    // Apply macax flag override
+    if cli.macax {
+        config.macax.enabled = true;
+    }
+
+    // Apply webdriver flag override
+    if cli.webdriver {
+        config.webdriver.enabled = true;
+    }
+
+    // Apply chrome-headless flag override
+    if cli.chrome_headless {
+        config.webdriver.enabled = true;
+        config.webdriver.browser = g3_config::WebDriverBrowser::ChromeHeadless;
+    }
+
+    // Apply safari flag override
+    if cli.safari {
+        config.webdriver.enabled = true;
+        config.webdriver.browser = g3_config::WebDriverBrowser::Safari;
+    }
+
+    // Apply no-auto-compact flag override
+    if cli.manual_compact {
+        config.agent.auto_compact = false;
+    }
+
+    if cli.explain {
+        config.agent.read_only = true;
+    }
+
+    i18n::set_locale(config.ui.locale);
+    ansi::set_mode(config.ui.ansi);
+    links::set_url_template(config.ui.link_url.clone());
+
+    Ok(config)
+}
+
+/// `g3 config show`: list the config layers, and with `effective` print the
+/// merged config (credentials masked)
+pub fn show_config(cli: &Cli, effective: bool) -> Result<()> {
+    println!("Configuration layers (later layers override earlier ones):");
+    println!("  1. defaults");
+    match Config::user_config_path(cli.config.as_deref()) {
+        Some(path) => println!("  2. user:    {}", path),
+        None => println!("  2. user:    (none)"),
+    }
+    if Path::new(PROJECT_CONFIG_PATH).exists() {
+        println!("  3. project: {}", PROJECT_CONFIG_PATH);
+    } else {
+        println!("  3. project: (no {})", PROJECT_CONFIG_PATH);
+    }
+    match Config::active_profile() {
+        Some(profile) => println!("  4. profile: [profiles.{}]", profile),
+        None => println!("  4. profile: (none)"),
+    }
+    let flags = cli_flags(cli);
+    if flags.is_empty() {
+        println!("  5. flags:   (none)");
+    } else {
+        println!("  5. flags:   {}", flags.join(" "));
+    }
+
+    if effective {
+        let config = load_config(cli)?;
+        println!("\n{}", config.to_redacted_toml()?);
+    }
+    Ok(())
+}
+
+/// `g3 config migrate`: rewrite the user and project config files that use
+/// the old provider format, printing the diff of each and keeping the
+/// original next to it as `<file>.bak`. With `dry_run` nothing is written.
+pub fn migrate_config(cli: &Cli, dry_run: bool) -> Result<()> {
+    let mut files: Vec<String> = Config::user_config_path(cli.config.as_deref())
+        .into_iter()
+        .collect();
+    if Path::new(PROJECT_CONFIG_PATH).exists() {
+        files.push(PROJECT_CONFIG_PATH.to_string());
+    }
+
+    let mut changed = 0;
+    for file in &files {
+        let content =
+            std::fs::read_to_string(file).with_context(|| format!("Failed to read {}", file))?;
+        let migrated =
+            migrate::migrate(&content).with_context(|| format!("Failed to migrate {}", file))?;
+        let Some(migrated) = migrated else {
+            println!("✅ {} already uses the current format", file);
+            continue;
+        };
+        changed += 1;
+
+        let diff = generate_unified_diff_with_labels(
+            &content,
+            &migrated,
+            3,
+            &format!("a/{}", file),
+            &format!("b/{}", file),
+        );
+        print!("{}", diff);
+        if dry_run {
+            continue;
+        }
+        let backup = backup_path(Path::new(file));
+        std::fs::copy(file, &backup)
+            .with_context(|| format!("Failed to back up {} to {}", file, backup.display()))?;
+        std::fs::write(file, migrated).with_context(|| format!("Failed to write {}", file))?;
+        println!(
+            "🔧 Migrated {} (original saved as {})",
+            file,
+            backup.display()
+        );
+    }
+
+    if files.is_empty() {
+        println!("No config file to migrate");
+    } else if dry_run && changed > 0 {
+        println!("Dry run: no file was changed");
+    }
+    Ok(())
+}
+
+/// `<path>.bak`, or `<path>.bak.<n>` for the first n not taken
+fn backup_path(path: &Path) -> PathBuf {
+    let backup = format!("{}.bak", path.display());
+    std::iter::once(PathBuf::from(&backup))
+        .chain((1..).map(|n| PathBuf::from(format!("{}.{}", backup, n))))
+        .find(|candidate| !candidate.exists())
+        .expect("some backup name is free")
+}
+
+/// `g3 auth login <provider>`: read an API key without echoing it and store
+/// it in the configured secrets backend. Reads a line from stdin instead
+/// when it isn't a terminal, for scripts.
+pub fn auth_login(cli: &Cli, provider: &str) -> Result<()> {
+    let config = Config::load(cli.config.as_deref())?;
+    let reference = config.credential_reference(provider)?;
+    let store = config.secrets.store();
+
+    let secret = if std::io::stdin().is_terminal() {
+        rpassword::prompt_password(format!("API key for {}: ", reference))?
+    } else {
+        let mut line = String::new();
+        std::io::stdin().read_line(&mut line)?;
+        line
+    };
+    let secret = secret.trim();
+    if secret.is_empty() {
+        anyhow::bail!("No API key given");
+    }
+
+    store.set(&reference, secret)?;
+    println!(
+        "🔑 Stored the credential for {} in the {}",
+        reference,
+        store.name()
+    );
+    println!(
+        "   Leave its api_key out of the config file (or set {} to override it)",
+        g3_config::secrets::env_var_name(&reference)
+    );
+    Ok(())
+}
+
+/// The command-line flags that override config settings
+fn cli_flags(cli: &Cli) -> Vec<String> {
+    let mut flags = Vec::new();
+    if let Some(provider) = &cli.provider {
+        flags.push(format!("--provider {}", provider));
+    }
+    if let Some(model) = &cli.model {
+        flags.push(format!("--model {}", model));
+    }
+    for (set, flag) in [
+        (cli.macax, "--macax"),
+        (cli.webdriver, "--webdriver"),
+        (cli.chrome_headless, "--chrome-headless"),
+        (cli.safari, "--safari"),
+        (cli.manual_compact, "--manual-compact"),
+        (cli.explain, "--explain"),
+    ] {
+        if set {
+            flags.push(flag.to_string());
+        }
+    }
+    flags
+}
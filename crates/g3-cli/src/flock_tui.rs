@@ -0,0 +1,565 @@
+//! Live view of a flock run, enabled with `--flock-tui`.
+//!
+//! Each segment gets its own pane with its most recent output. Panes scroll
+//! independently, and a segment can be "adopted": its pane fills the screen
+//! and lines typed below it go to the segment's stdin, so its prompts can be
+//! answered while the other segments keep running.
+//!
+//! A supervised flock waiting at a barrier shows what it is waiting to do
+//! next; `a` lets it go on and `x` stops it.
+
+use anyhow::Result;
+use crossterm::{
+    event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers},
+    execute,
+    terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
+};
+use g3_ensembles::status::SegmentState;
+use g3_ensembles::{Approvals, Barrier, FlockEvent, SegmentInputs};
+use ratatui::{
+    backend::CrosstermBackend,
+    layout::{Constraint, Direction, Layout, Rect},
+    style::{Color, Modifier, Style},
+    text::Line,
+    widgets::{Block, Borders, Paragraph},
+    Frame, Terminal,
+};
+use std::collections::VecDeque;
+use std::io;
+use std::time::Duration;
+use tokio::sync::mpsc::{self, error::TryRecvError};
+
+use crate::ansi;
+use crate::i18n::{t, tf, Msg};
+
+/// Output lines kept per segment
+const MAX_PANE_LINES: usize = 500;
+
+/// Lines moved by PgUp/PgDn
+const PAGE_LINES: usize = 10;
+
+struct Pane {
+    segment_id: usize,
+    /// Output lines, and whether each came from stderr
+    lines: VecDeque<(String, bool)>,
+    /// Lines scrolled up from the bottom; 0 follows new output
+    scroll: usize,
+    state: SegmentState,
+}
+
+impl Pane {
+    fn new(segment_id: usize) -> Self {
+        Self {
+            segment_id,
+            lines: VecDeque::new(),
+            scroll: 0,
+            state: SegmentState::Pending,
+        }
+    }
+
+    fn push(&mut self, line: String, is_error: bool) {
+        if self.lines.len() == MAX_PANE_LINES {
+            self.lines.pop_front();
+        }
+        self.lines.push_back((line, is_error));
+        // Keep a scrolled-back pane on the same lines while output arrives
+        if self.scroll > 0 {
+            self.scroll = (self.scroll + 1).min(self.max_scroll());
+        }
+    }
+
+    fn max_scroll(&self) -> usize {
+        self.lines.len().saturating_sub(1)
+    }
+
+    fn scroll_up(&mut self, lines: usize) {
+        self.scroll = (self.scroll + lines).min(self.max_scroll());
+    }
+
+    fn scroll_down(&mut self, lines: usize) {
+        self.scroll = self.scroll.saturating_sub(lines);
+    }
+
+    /// The lines that fit in `height` rows at the current scroll position
+    fn visible(&self, height: usize) -> impl Iterator<Item = &(String, bool)> {
+        let end = self.lines.len() - self.scroll.min(self.lines.len());
+        let start = end.saturating_sub(height);
+        self.lines.range(start..end)
+    }
+}
+
+/// What the event loop should do after a key press
+#[derive(Debug, PartialEq, Eq)]
+enum Action {
+    None,
+    /// Write a line to a segment's stdin
+    Send {
+        segment_id: usize,
+        line: String,
+    },
+    /// Answer the barrier the flock is waiting at
+    Answer(bool),
+    Close,
+}
+
+/// Everything the view shows; kept apart from the terminal so it can be tested
+struct FlockView {
+    panes: Vec<Pane>,
+    /// Index of the pane that scroll keys act on
+    focused: usize,
+    /// Index of the pane shown full screen with an input line
+    adopted: Option<usize>,
+    input: String,
+    /// Latest flock-level progress message
+    status: String,
+    /// The barrier a supervised flock is waiting at, and what it has done so far
+    approval: Option<(Barrier, String)>,
+    /// The flock has finished and no more events will arrive
+    finished: bool,
+}
+
+impl FlockView {
+    fn new(num_segments: usize) -> Self {
+        Self {
+            panes: (1..=num_segments).map(Pane::new).collect(),
+            focused: 0,
+            adopted: None,
+            input: String::new(),
+            status: t(Msg::FlockStarting).to_string(),
+            approval: None,
+            finished: false,
+        }
+    }
+
+    fn pane_mut(&mut self, segment_id: usize) -> Option<&mut Pane> {
+        self.panes
+            .iter_mut()
+            .find(|pane| pane.segment_id == segment_id)
+    }
+
+    fn apply(&mut self, event: FlockEvent) {
+        match event {
+            FlockEvent::Progress(message) => {
+                // The final report spans many lines; the status bar shows the first
+                if let Some(line) = message.lines().find(|line| !line.trim().is_empty()) {
+                    self.status = line.trim().to_string();
+                }
+            }
+            FlockEvent::SegmentOutput {
+                segment_id,
+                line,
+                is_error,
+            } => {
+                if let Some(pane) = self.pane_mut(segment_id) {
+                    if pane.state == SegmentState::Pending {
+                        pane.state = SegmentState::Running;
+                    }
+                    pane.push(line, is_error);
+                }
+            }
+            FlockEvent::SegmentFinished { segment_id, state } => {
+                if let Some(pane) = self.pane_mut(segment_id) {
+                    pane.state = state;
+                }
+            }
+            FlockEvent::AwaitingApproval { barrier, summary } => {
+                self.status = tf(
+                    Msg::FlockWaitingForApproval,
+                    &[("barrier", &barrier.to_string())],
+                );
+                self.approval = Some((barrier, summary));
+            }
+        }
+    }
+
+    fn handle_key(&mut self, key: KeyEvent) -> Action {
+        if key.modifiers.contains(KeyModifiers::CONTROL) && key.code == KeyCode::Char('c') {
+            return Action::Close;
+        }
+        if self.approval.is_some() && self.adopted.is_none() {
+            if let KeyCode::Char(c @ ('a' | 'x')) = key.code {
+                self.approval = None;
+                return Action::Answer(c == 'a');
+            }
+        }
+        if self.panes.is_empty() {
+            return match key.code {
+                KeyCode::Char('q') | KeyCode::Esc => Action::Close,
+                _ => Action::None,
+            };
+        }
+
+        if let Some(adopted) = self.adopted {
+            match key.code {
+                KeyCode::Esc => {
+                    self.adopted = None;
+                    self.input.clear();
+                }
+                KeyCode::Enter => {
+                    let line = std::mem::take(&mut self.input);
+                    return Action::Send {
+                        segment_id: self.panes[adopted].segment_id,
+                        line,
+                    };
+                }
+                KeyCode::Backspace => {
+                    self.input.pop();
+                }
+                KeyCode::Char(c) => self.input.push(c),
+                _ => self.scroll(adopted, key.code),
+            }
+            return Action::None;
+        }
+
+        match key.code {
+            KeyCode::Char('q') | KeyCode::Esc => return Action::Close,
+            KeyCode::Tab | KeyCode::Right => {
+                self.focused = (self.focused + 1) % self.panes.len();
+            }
+            KeyCode::BackTab | KeyCode::Left => {
+                self.focused = (self.focused + self.panes.len() - 1) % self.panes.len();
+            }
+            KeyCode::Char(c @ '1'..='9') => {
+                let index = c as usize - '1' as usize;
+                if index < self.panes.len() {
+                    self.focused = index;
+                }
+            }
+            KeyCode::Enter => self.adopted = Some(self.focused),
+            code => self.scroll(self.focused, code),
+        }
+        Action::None
+    }
+
+    fn scroll(&mut self, index: usize, code: KeyCode) {
+        let pane = &mut self.panes[index];
+        match code {
+            KeyCode::Up => pane.scroll_up(1),
+            KeyCode::Down => pane.scroll_down(1),
+            KeyCode::PageUp => pane.scroll_up(PAGE_LINES),
+            KeyCode::PageDown => pane.scroll_down(PAGE_LINES),
+            KeyCode::Home => pane.scroll = pane.max_scroll(),
+            KeyCode::End => pane.scroll = 0,
+            _ => {}
+        }
+    }
+
+    fn draw(&self, frame: &mut Frame) {
+        let chunks = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([
+                Constraint::Length(1),
+                Constraint::Min(0),
+                Constraint::Length(1),
+            ])
+            .split(frame.area());
+
+        let title = if self.finished {
+            format!("🦅 G3 Flock · {} · {}", t(Msg::FlockFinished), self.status)
+        } else {
+            format!("🦅 G3 Flock · {}", self.status)
+        };
+        frame.render_widget(
+            Paragraph::new(title).style(Style::default().add_modifier(Modifier::BOLD)),
+            chunks[0],
+        );
+
+        let help = t(if self.adopted.is_some() {
+            Msg::FlockAdoptedKeyHelp
+        } else if self.approval.is_some() {
+            Msg::FlockApprovalKeyHelp
+        } else {
+            Msg::FlockKeyHelp
+        });
+        frame.render_widget(
+            Paragraph::new(help).style(Style::default().fg(Color::DarkGray)),
+            chunks[2],
+        );
+
+        match (self.adopted, &self.approval) {
+            (Some(index), _) => self.draw_adopted(frame, chunks[1], index),
+            (None, Some((barrier, summary))) => {
+                let height = (summary.lines().count() as u16 + 2).min(chunks[1].height / 2);
+                let areas = Layout::default()
+                    .direction(Direction::Vertical)
+                    .constraints([Constraint::Min(0), Constraint::Length(height)])
+                    .split(chunks[1]);
+                self.draw_grid(frame, areas[0]);
+                let approval = Paragraph::new(summary.as_str()).block(
+                    Block::default()
+                        .borders(Borders::ALL)
+                        .border_style(Style::default().fg(Color::Magenta))
+                        .title(format!(
+                            " {} ",
+                            tf(Msg::FlockApproveTitle, &[("barrier", &barrier.to_string())])
+                        )),
+                );
+                frame.render_widget(approval, areas[1]);
+            }
+            (None, None) => self.draw_grid(frame, chunks[1]),
+        }
+    }
+
+    fn draw_grid(&self, frame: &mut Frame, area: Rect) {
+        if self.panes.is_empty() {
+            return;
+        }
+        let (columns, rows) = grid_size(self.panes.len());
+        let row_areas = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints(vec![Constraint::Ratio(1, rows as u32); rows])
+            .split(area);
+        for (row, row_area) in row_areas.iter().enumerate() {
+            let cells = Layout::default()
+                .direction(Direction::Horizontal)
+                .constraints(vec![Constraint::Ratio(1, columns as u32); columns])
+                .split(*row_area);
+            for (column, cell) in cells.iter().enumerate() {
+                let index = row * columns + column;
+                if let Some(pane) = self.panes.get(index) {
+                    draw_pane(frame, *cell, pane, index == self.focused);
+                }
+            }
+        }
+    }
+
+    fn draw_adopted(&self, frame: &mut Frame, area: Rect, index: usize) {
+        let chunks = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Min(0), Constraint::Length(3)])
+            .split(area);
+        let pane = &self.panes[index];
+        draw_pane(frame, chunks[0], pane, true);
+
+        let input = Paragraph::new(format!("{}▏", self.input)).block(
+            Block::default().borders(Borders::ALL).title(format!(
+                " {} ",
+                tf(
+                    Msg::FlockInputTitle,
+                    &[("segment", &pane.segment_id.to_string())]
+                )
+            )),
+        );
+        frame.render_widget(input, chunks[1]);
+    }
+}
+
+/// Columns and rows of a near-square grid holding `panes` panes
+fn grid_size(panes: usize) -> (usize, usize) {
+    let columns = (1..)
+        .find(|columns| columns * columns >= panes)
+        .unwrap_or(1);
+    let rows = panes.div_ceil(columns).max(1);
+    (columns, rows)
+}
+
+fn draw_pane(frame: &mut Frame, area: Rect, pane: &Pane, focused: bool) {
+    let segment = pane.segment_id.to_string();
+    let state = pane.state.to_string();
+    let mut title = format!(
+        " {} ",
+        tf(
+            Msg::FlockSegmentTitle,
+            &[("segment", &segment), ("state", &state)]
+        )
+    );
+    if pane.scroll > 0 {
+        title.push_str(&format!("· ↑{} ", pane.scroll));
+    }
+    let border = if focused {
+        Style::default().fg(Color::Yellow)
+    } else {
+        Style::default().fg(Color::DarkGray)
+    };
+    let block = Block::default()
+        .borders(Borders::ALL)
+        .border_style(border)
+        .title(title);
+
+    let height = area.height.saturating_sub(2) as usize;
+    let lines: Vec<Line> = pane
+        .visible(height)
+        .map(|(line, is_error)| {
+            let style = if *is_error {
+                Style::default().fg(Color::Red)
+            } else {
+                Style::default()
+            };
+            ansi::to_line(line, style)
+        })
+        .collect();
+    frame.render_widget(Paragraph::new(lines).block(block), area);
+}
+
+/// Show the flock's segments until the user closes the view. Blocks, so run
+/// it off the async runtime. Closing the view while the flock waits at a
+/// barrier rejects it.
+pub fn run(
+    mut events: mpsc::UnboundedReceiver<FlockEvent>,
+    inputs: SegmentInputs,
+    approvals: Approvals,
+    num_segments: usize,
+) -> Result<()> {
+    enable_raw_mode()?;
+    let mut stdout = io::stdout();
+    execute!(stdout, EnterAlternateScreen)?;
+    let mut terminal = Terminal::new(CrosstermBackend::new(stdout))?;
+
+    let mut view = FlockView::new(num_segments);
+    let result = event_loop(&mut terminal, &mut view, &mut events, &inputs, &approvals);
+    approvals.answer(false);
+
+    disable_raw_mode()?;
+    execute!(terminal.backend_mut(), LeaveAlternateScreen)?;
+    terminal.show_cursor()?;
+    result
+}
+
+fn event_loop(
+    terminal: &mut Terminal<CrosstermBackend<io::Stdout>>,
+    view: &mut FlockView,
+    events: &mut mpsc::UnboundedReceiver<FlockEvent>,
+    inputs: &SegmentInputs,
+    approvals: &Approvals,
+) -> Result<()> {
+    loop {
+        loop {
+            match events.try_recv() {
+                Ok(event) => view.apply(event),
+                Err(TryRecvError::Empty) => break,
+                Err(TryRecvError::Disconnected) => {
+                    view.finished = true;
+                    break;
+                }
+            }
+        }
+
+        terminal.draw(|frame| view.draw(frame))?;
+
+        if !event::poll(Duration::from_millis(50))? {
+            continue;
+        }
+        let Event::Key(key) = event::read()? else {
+            continue;
+        };
+        if key.kind != KeyEventKind::Press {
+            continue;
+        }
+        match view.handle_key(key) {
+            Action::None => {}
+            Action::Close => return Ok(()),
+            Action::Answer(approved) => {
+                approvals.answer(approved);
+            }
+            Action::Send { segment_id, line } => {
+                if !inputs.send(segment_id, &line) {
+                    if let Some(pane) = view.pane_mut(segment_id) {
+                        pane.push(t(Msg::FlockInputDropped).to_string(), true);
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(code: KeyCode) -> KeyEvent {
+        KeyEvent::new(code, KeyModifiers::NONE)
+    }
+
+    fn output(segment_id: usize, line: &str) -> FlockEvent {
+        FlockEvent::SegmentOutput {
+            segment_id,
+            line: line.to_string(),
+            is_error: false,
+        }
+    }
+
+    #[test]
+    fn test_panes_scroll_independently() {
+        let mut view = FlockView::new(2);
+        for i in 0..20 {
+            view.apply(output(1, &format!("one {}", i)));
+            view.apply(output(2, &format!("two {}", i)));
+        }
+        assert_eq!(view.panes[0].state, SegmentState::Running);
+
+        view.handle_key(key(KeyCode::PageUp));
+        assert_eq!(view.panes[0].scroll, PAGE_LINES);
+        assert_eq!(view.panes[1].scroll, 0);
+
+        // New output doesn't move a scrolled-back pane
+        view.apply(output(1, "one 20"));
+        let visible: Vec<&str> = view.panes[0]
+            .visible(2)
+            .map(|(line, _)| line.as_str())
+            .collect();
+        assert_eq!(visible, vec!["one 8", "one 9"]);
+
+        view.handle_key(key(KeyCode::Tab));
+        view.handle_key(key(KeyCode::Up));
+        assert_eq!(view.panes[1].scroll, 1);
+        view.handle_key(key(KeyCode::Char('1')));
+        view.handle_key(key(KeyCode::End));
+        assert_eq!(view.panes[0].scroll, 0);
+    }
+
+    #[test]
+    fn test_adopted_segment_receives_typed_lines() {
+        let mut view = FlockView::new(3);
+        view.handle_key(key(KeyCode::Char('2')));
+        view.handle_key(key(KeyCode::Enter));
+        assert_eq!(view.adopted, Some(1));
+
+        // Keys that move focus in the grid are typed into the input instead
+        for c in "y1".chars() {
+            assert_eq!(view.handle_key(key(KeyCode::Char(c))), Action::None);
+        }
+        view.handle_key(key(KeyCode::Backspace));
+        assert_eq!(
+            view.handle_key(key(KeyCode::Enter)),
+            Action::Send {
+                segment_id: 2,
+                line: "y".to_string()
+            }
+        );
+        assert!(view.input.is_empty());
+
+        view.handle_key(key(KeyCode::Esc));
+        assert_eq!(view.adopted, None);
+        assert_eq!(view.handle_key(key(KeyCode::Char('q'))), Action::Close);
+    }
+
+    #[test]
+    fn test_barrier_is_answered_from_the_grid() {
+        let mut view = FlockView::new(2);
+        view.apply(FlockEvent::AwaitingApproval {
+            barrier: Barrier::AfterPlanning,
+            summary: "Segment 1: parser\nSegment 2: cli".to_string(),
+        });
+        assert_eq!(view.status, "Waiting for approval (after-planning)");
+
+        // Typing into an adopted segment doesn't answer
+        view.handle_key(key(KeyCode::Enter));
+        assert_eq!(view.handle_key(key(KeyCode::Char('a'))), Action::None);
+        view.handle_key(key(KeyCode::Esc));
+
+        assert_eq!(
+            view.handle_key(key(KeyCode::Char('a'))),
+            Action::Answer(true)
+        );
+        assert!(view.approval.is_none());
+        assert_eq!(view.handle_key(key(KeyCode::Char('x'))), Action::None);
+    }
+
+    #[test]
+    fn test_grid_size() {
+        assert_eq!(grid_size(1), (1, 1));
+        assert_eq!(grid_size(2), (2, 1));
+        assert_eq!(grid_size(4), (2, 2));
+        assert_eq!(grid_size(5), (3, 2));
+    }
+}
@@ -0,0 +1,670 @@
+//! User-facing strings of the CLI and the TUIs, by locale.
+//!
+//! Every message is a [`Msg`] with a text per [`Locale`]; the matches are
+//! exhaustive, so a new message doesn't build until it has been translated.
+//! The locale comes from `[ui] locale` and is set once the config is loaded.
+//! Texts with `{name}` placeholders are filled in by [`tf`]. The retro TUI's
+//! WEYLAND-YUTANI boot banner is part of its theme and stays in English.
+
+use g3_config::Locale;
+use g3_core::prompt_templates::interpolate;
+use g3_providers::ErrorCategory;
+use std::sync::RwLock;
+
+static LOCALE: RwLock<Locale> = RwLock::new(Locale::En);
+
+/// Use `locale` for every message from now on
+pub fn set_locale(locale: Locale) {
+    *LOCALE.write().unwrap_or_else(|e| e.into_inner()) = locale;
+}
+
+pub fn locale() -> Locale {
+    *LOCALE.read().unwrap_or_else(|e| e.into_inner())
+}
+
+/// The recovery hint shown under an error of `category`
+pub fn error_hint(category: ErrorCategory) -> Msg {
+    match category {
+        ErrorCategory::RateLimit => Msg::ErrorHintRateLimit,
+        ErrorCategory::Network => Msg::ErrorHintNetwork,
+        ErrorCategory::Timeout => Msg::ErrorHintTimeout,
+        ErrorCategory::Server => Msg::ErrorHintServer,
+        ErrorCategory::Overloaded => Msg::ErrorHintOverloaded,
+        ErrorCategory::ContextLength => Msg::ErrorHintContextLength,
+        ErrorCategory::Auth => Msg::ErrorHintAuth,
+        ErrorCategory::InvalidRequest => Msg::ErrorHintInvalidRequest,
+        ErrorCategory::Tool => Msg::ErrorHintTool,
+        ErrorCategory::Git => Msg::ErrorHintGit,
+    }
+}
+
+/// The text of `msg` in the current locale
+pub fn t(msg: Msg) -> &'static str {
+    msg.text(locale())
+}
+
+/// The text of `msg` in the current locale, with `vars` filled in
+pub fn tf(msg: Msg, vars: &[(&str, &str)]) -> String {
+    interpolate(t(msg), vars)
+}
+
+/// Whether `answer` to a yes/no prompt means yes; English answers are
+/// accepted in every locale
+pub fn is_yes(answer: &str) -> bool {
+    let answer = answer.trim().to_lowercase();
+    let localized: &[&str] = match locale() {
+        Locale::En => &[],
+        Locale::Es => &["s", "si", "sí"],
+    };
+    answer == "y" || answer == "yes" || localized.contains(&answer.as_str())
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Msg {
+    // Interactive sessions
+    ChatHelpTitle,
+    HelpCompact,
+    HelpThinnify,
+    HelpSkinnify,
+    HelpClear,
+    HelpReadme,
+    HelpStats,
+    HelpUndo,
+    HelpContext,
+    HelpBranch,
+    HelpCopy,
+    HelpModel,
+    HelpLogs,
+    HelpHelp,
+    HelpExit,
+    AccumulativeHelp,
+    UnknownCommand,
+    Goodbye,
+    InterruptedGoodbye,
+    OperationCancelled,
+    AutonomousRunCancelled,
+    TaskError,
+    ErrorHintRateLimit,
+    ErrorHintNetwork,
+    ErrorHintTimeout,
+    ErrorHintServer,
+    ErrorHintOverloaded,
+    ErrorHintContextLength,
+    ErrorHintAuth,
+    ErrorHintInvalidRequest,
+    ErrorHintTool,
+    ErrorHintGit,
+    // Prompts
+    YesNoSuffix,
+    ApprovalChoices,
+    SelectOption,
+    InvalidChoice,
+    // Status output
+    ContextOptimized,
+    SummarizingWork,
+    SummaryHeader,
+    // Retro TUI
+    StatusLabel,
+    StatusReady,
+    StatusProcessing,
+    ContextLabel,
+    CommandInputTitle,
+    ToolDetailTitle,
+    ActivityTitle,
+    ApprovalTitle,
+    ApprovalTool,
+    ApprovalArgs,
+    ApprovalRisk,
+    ApprovalAllow,
+    ApprovalDeny,
+    ApprovalAlways,
+    ApprovalDenyAlways,
+    PaletteNoMatches,
+    PaletteSelect,
+    PaletteRun,
+    PaletteBack,
+    // Command palette
+    PaletteCommands,
+    PaletteSwitchTheme,
+    PaletteSwitchThemeDetail,
+    PaletteToggleActivity,
+    PaletteToggleActivityDetail,
+    PaletteToggleLogs,
+    PaletteToggleLogsDetail,
+    PaletteExportTranscript,
+    PaletteExportTranscriptDetail,
+    PaletteShowUsage,
+    PaletteShowUsageDetail,
+    PaletteShowContext,
+    PaletteShowContextDetail,
+    PalettePauseAgent,
+    PalettePauseAgentDetail,
+    PaletteSwitchProvider,
+    PaletteSwitchProviderDetail,
+    PaletteCompact,
+    PaletteCompactDetail,
+    PaletteUndo,
+    PaletteUndoDetail,
+    PaletteClear,
+    PaletteClearDetail,
+    PaletteBusy,
+    // Retro TUI help overlay
+    HelpOverlayTitle,
+    HelpKeysTitle,
+    KeySend,
+    KeyPalette,
+    KeyHelp,
+    KeyClose,
+    KeyScroll,
+    KeyScrollEnds,
+    KeyApproval,
+    KeyLogs,
+    LogPaneTitle,
+    KeyBranches,
+    BranchesTitle,
+    BranchesKeyHelp,
+    KeySelect,
+    KeyFollowLink,
+    // Flock TUI
+    FlockKeyHelp,
+    FlockApprovalKeyHelp,
+    FlockAdoptedKeyHelp,
+    FlockStarting,
+    FlockWaitingForApproval,
+    FlockFinished,
+    FlockApproveTitle,
+    FlockInputTitle,
+    FlockSegmentTitle,
+    FlockInputDropped,
+}
+
+impl Msg {
+    /// Every message, for checking the catalog
+    #[cfg(test)]
+    pub const ALL: &'static [Msg] = &[
+        Msg::ChatHelpTitle,
+        Msg::HelpCompact,
+        Msg::HelpThinnify,
+        Msg::HelpSkinnify,
+        Msg::HelpClear,
+        Msg::HelpReadme,
+        Msg::HelpStats,
+        Msg::HelpUndo,
+        Msg::HelpContext,
+        Msg::HelpBranch,
+        Msg::HelpCopy,
+        Msg::HelpModel,
+        Msg::HelpLogs,
+        Msg::HelpHelp,
+        Msg::HelpExit,
+        Msg::AccumulativeHelp,
+        Msg::UnknownCommand,
+        Msg::Goodbye,
+        Msg::InterruptedGoodbye,
+        Msg::OperationCancelled,
+        Msg::AutonomousRunCancelled,
+        Msg::TaskError,
+        Msg::ErrorHintRateLimit,
+        Msg::ErrorHintNetwork,
+        Msg::ErrorHintTimeout,
+        Msg::ErrorHintServer,
+        Msg::ErrorHintOverloaded,
+        Msg::ErrorHintContextLength,
+        Msg::ErrorHintAuth,
+        Msg::ErrorHintInvalidRequest,
+        Msg::ErrorHintTool,
+        Msg::ErrorHintGit,
+        Msg::YesNoSuffix,
+        Msg::ApprovalChoices,
+        Msg::SelectOption,
+        Msg::InvalidChoice,
+        Msg::ContextOptimized,
+        Msg::SummarizingWork,
+        Msg::SummaryHeader,
+        Msg::StatusLabel,
+        Msg::StatusReady,
+        Msg::StatusProcessing,
+        Msg::ContextLabel,
+        Msg::CommandInputTitle,
+        Msg::ToolDetailTitle,
+        Msg::ActivityTitle,
+        Msg::ApprovalTitle,
+        Msg::ApprovalTool,
+        Msg::ApprovalArgs,
+        Msg::ApprovalRisk,
+        Msg::ApprovalAllow,
+        Msg::ApprovalDeny,
+        Msg::ApprovalAlways,
+        Msg::ApprovalDenyAlways,
+        Msg::PaletteNoMatches,
+        Msg::PaletteSelect,
+        Msg::PaletteRun,
+        Msg::PaletteBack,
+        Msg::PaletteCommands,
+        Msg::PaletteSwitchTheme,
+        Msg::PaletteSwitchThemeDetail,
+        Msg::PaletteToggleActivity,
+        Msg::PaletteToggleActivityDetail,
+        Msg::PaletteToggleLogs,
+        Msg::PaletteToggleLogsDetail,
+        Msg::PaletteExportTranscript,
+        Msg::PaletteExportTranscriptDetail,
+        Msg::PaletteShowUsage,
+        Msg::PaletteShowUsageDetail,
+        Msg::PaletteShowContext,
+        Msg::PaletteShowContextDetail,
+        Msg::PalettePauseAgent,
+        Msg::PalettePauseAgentDetail,
+        Msg::PaletteSwitchProvider,
+        Msg::PaletteSwitchProviderDetail,
+        Msg::PaletteCompact,
+        Msg::PaletteCompactDetail,
+        Msg::PaletteUndo,
+        Msg::PaletteUndoDetail,
+        Msg::PaletteClear,
+        Msg::PaletteClearDetail,
+        Msg::PaletteBusy,
+        Msg::HelpOverlayTitle,
+        Msg::HelpKeysTitle,
+        Msg::KeySend,
+        Msg::KeyPalette,
+        Msg::KeyHelp,
+        Msg::KeyClose,
+        Msg::KeyScroll,
+        Msg::KeyScrollEnds,
+        Msg::KeyApproval,
+        Msg::KeyLogs,
+        Msg::LogPaneTitle,
+        Msg::KeyBranches,
+        Msg::BranchesTitle,
+        Msg::BranchesKeyHelp,
+        Msg::KeySelect,
+        Msg::KeyFollowLink,
+        Msg::FlockKeyHelp,
+        Msg::FlockApprovalKeyHelp,
+        Msg::FlockAdoptedKeyHelp,
+        Msg::FlockStarting,
+        Msg::FlockWaitingForApproval,
+        Msg::FlockFinished,
+        Msg::FlockApproveTitle,
+        Msg::FlockInputTitle,
+        Msg::FlockSegmentTitle,
+        Msg::FlockInputDropped,
+    ];
+
+    pub fn text(self, locale: Locale) -> &'static str {
+        match locale {
+            Locale::En => self.en(),
+            Locale::Es => self.es(),
+        }
+    }
+
+    fn en(self) -> &'static str {
+        match self {
+            Msg::ChatHelpTitle => "📖 Control Commands:",
+            Msg::HelpCompact => "Trigger auto-summarization (compacts conversation history)",
+            Msg::HelpThinnify => {
+                "Trigger context thinning (replaces large tool results with file references)"
+            }
+            Msg::HelpSkinnify => {
+                "Trigger full context thinning (like /thinnify but for entire context, not just first third)"
+            }
+            Msg::HelpClear => "Clear session and start fresh (discards continuation artifacts)",
+            Msg::HelpReadme => "Reload README.md and AGENTS.md from disk",
+            Msg::HelpStats => "Show detailed context and performance statistics",
+            Msg::HelpUndo => "Revert the agent's last n file edits (default 1)",
+            Msg::HelpContext => "List context messages, or pin, unpin or evict one by number or id",
+            Msg::HelpBranch => {
+                "List conversation branches, or fork a new one, switch to one or discard one"
+            }
+            Msg::HelpCopy => {
+                "Copy the last response, its last code block or the TUI selection to the clipboard"
+            }
+            Msg::HelpModel => {
+                "Switch to another provider (e.g. openai.default) for the next turns, keeping the conversation"
+            }
+            Msg::HelpLogs => "Show recent log events at a level or above (default debug)",
+            Msg::HelpHelp => "Show this help message",
+            Msg::HelpExit => "Exit the interactive session",
+            Msg::AccumulativeHelp => {
+                "📖 Available Commands:
+  /requirements - Show all accumulated requirements
+  /chat         - Switch to interactive chat mode
+  /help         - Show this help message
+  exit/quit     - Exit the session"
+            }
+            Msg::UnknownCommand => {
+                "❌ Unknown command: {command}. Type /help for available commands."
+            }
+            Msg::Goodbye => "👋 Goodbye!",
+            Msg::InterruptedGoodbye => "👋 Interrupted. Goodbye!",
+            Msg::OperationCancelled => "⚠️  Operation cancelled by user",
+            Msg::AutonomousRunCancelled => "⚠️  Autonomous run cancelled by user",
+            Msg::TaskError => "❌ Error: {error}",
+            Msg::ErrorHintRateLimit => {
+                "💡 The provider is rate limiting requests. Wait a moment, set [providers.rate_limits], or configure fallback_providers."
+            }
+            Msg::ErrorHintNetwork => {
+                "💡 Check the network connection and the provider's host or base_url."
+            }
+            Msg::ErrorHintTimeout => {
+                "💡 The provider took too long to answer. Try again, or raise [agent] timeout_seconds."
+            }
+            Msg::ErrorHintServer => {
+                "💡 The provider failed on its side. Try again shortly, or configure fallback_providers."
+            }
+            Msg::ErrorHintOverloaded => {
+                "💡 The model is overloaded. Try again shortly, or switch providers with /model."
+            }
+            Msg::ErrorHintContextLength => {
+                "💡 The conversation no longer fits the model. Run /compact or /thinnify, or switch to a model with a larger context window."
+            }
+            Msg::ErrorHintAuth => {
+                "💡 The provider rejected the credentials. Check the api_key, or store one with `g3 auth login <provider>`."
+            }
+            Msg::ErrorHintInvalidRequest => {
+                "💡 The provider rejected the request. Check the model name and settings of the provider config."
+            }
+            Msg::ErrorHintTool => {
+                "💡 A tool failed. Fix the cause shown above and send the request again."
+            }
+            Msg::ErrorHintGit => {
+                "💡 Check `git status` in the codebase and resolve what git reports."
+            }
+            Msg::YesNoSuffix => "[y/N]",
+            Msg::ApprovalChoices => "[y]es / [N]o / [a]lways / [d]eny always:",
+            Msg::SelectOption => "Select an option (1-{count}):",
+            Msg::InvalidChoice => "Invalid choice. Please select (1-{count}):",
+            Msg::ContextOptimized => "Context optimized successfully",
+            Msg::SummarizingWork => "summarizing work done...",
+            Msg::SummaryHeader => "Summary",
+            Msg::StatusLabel => "STATUS",
+            Msg::StatusReady => "READY",
+            Msg::StatusProcessing => "PROCESSING",
+            Msg::ContextLabel => "CONTEXT",
+            Msg::CommandInputTitle => "COMMAND INPUT",
+            Msg::ToolDetailTitle => "TOOL DETAIL",
+            Msg::ActivityTitle => "ACTIVITY",
+            Msg::ApprovalTitle => "TOOL APPROVAL REQUIRED",
+            Msg::ApprovalTool => "TOOL",
+            Msg::ApprovalArgs => "ARGS",
+            Msg::ApprovalRisk => "RISK",
+            Msg::ApprovalAllow => "ALLOW",
+            Msg::ApprovalDeny => "DENY",
+            Msg::ApprovalAlways => "ALWAYS",
+            Msg::ApprovalDenyAlways => "DENY ALWAYS",
+            Msg::PaletteNoMatches => "NO MATCHING COMMANDS",
+            Msg::PaletteSelect => "SELECT",
+            Msg::PaletteRun => "RUN",
+            Msg::PaletteBack => "BACK",
+            Msg::PaletteCommands => "Commands",
+            Msg::PaletteSwitchTheme => "Switch theme",
+            Msg::PaletteSwitchThemeDetail => "Change the terminal colors",
+            Msg::PaletteToggleActivity => "Toggle activity panel",
+            Msg::PaletteToggleActivityDetail => "Show or hide tool activity while the agent works",
+            Msg::PaletteToggleLogs => "Toggle log pane",
+            Msg::PaletteToggleLogsDetail => "Show or hide recent debug and warning events",
+            Msg::PaletteExportTranscript => "Export transcript",
+            Msg::PaletteExportTranscriptDetail => "Write this session as Markdown",
+            Msg::PaletteShowUsage => "Show usage",
+            Msg::PaletteShowUsageDetail => "Context, token and cost statistics",
+            Msg::PaletteShowContext => "Show context",
+            Msg::PaletteShowContextDetail => {
+                "Every message in the context window; pin or evict one"
+            }
+            Msg::PalettePauseAgent => "Pause agent",
+            Msg::PalettePauseAgentDetail => "Interrupt the current turn",
+            Msg::PaletteSwitchProvider => "Switch provider",
+            Msg::PaletteSwitchProviderDetail => "Use another configured provider for the next turns",
+            Msg::PaletteCompact => "Compact context",
+            Msg::PaletteCompactDetail => "Summarize the conversation so far",
+            Msg::PaletteUndo => "Undo last edit",
+            Msg::PaletteUndoDetail => "Revert the agent's last file edit",
+            Msg::PaletteClear => "Clear session",
+            Msg::PaletteClearDetail => "Start fresh",
+            Msg::PaletteBusy => "⚠️  Wait for the task to finish, or press Ctrl+C to stop it",
+            Msg::HelpOverlayTitle => "HELP",
+            Msg::HelpKeysTitle => "Keys",
+            Msg::KeySend => "Send the input line",
+            Msg::KeyPalette => "Open the command palette",
+            Msg::KeyHelp => "Show or hide this help",
+            Msg::KeyClose => "Close the help, the palette or a nested list",
+            Msg::KeyScroll => "Scroll the output",
+            Msg::KeyScrollEnds => "Jump to the top or the end of the output",
+            Msg::KeyApproval => "Answer a tool approval: yes, no, always, deny always",
+            Msg::KeyLogs => "Show or hide the log pane (:logs <level> filters it)",
+            Msg::LogPaneTitle => "LOGS",
+            Msg::KeyBranches => "Show the conversation branches to switch to or discard",
+            Msg::BranchesTitle => "BRANCHES",
+            Msg::BranchesKeyHelp => "↑/↓ select · Enter switch · d discard · Esc close",
+            Msg::KeySelect => "Select output lines for /copy selection",
+            Msg::KeyFollowLink => "Open the file:line on the selected line in $EDITOR",
+            Msg::FlockKeyHelp => {
+                "Tab/←/→/1-9 focus · ↑/↓/PgUp/PgDn scroll · End follow · Enter adopt · q close"
+            }
+            Msg::FlockApprovalKeyHelp => {
+                "a approve · x reject and stop the flock · Tab/1-9 focus · ↑/↓ scroll · q close (rejects)"
+            }
+            Msg::FlockAdoptedKeyHelp => {
+                "Enter send line · ↑/↓/PgUp/PgDn scroll · Esc back to all segments"
+            }
+            Msg::FlockStarting => "Starting flock...",
+            Msg::FlockWaitingForApproval => "Waiting for approval ({barrier})",
+            Msg::FlockFinished => "finished",
+            Msg::FlockApproveTitle => "Approve continuing {barrier}?",
+            Msg::FlockInputTitle => "Input to segment {segment}",
+            Msg::FlockSegmentTitle => "Segment {segment} · {state}",
+            Msg::FlockInputDropped => "(segment is not running; input dropped)",
+        }
+    }
+
+    fn es(self) -> &'static str {
+        match self {
+            Msg::ChatHelpTitle => "📖 Comandos de control:",
+            Msg::HelpCompact => "Resumir ahora el historial de la conversación",
+            Msg::HelpThinnify => {
+                "Aligerar el contexto (sustituye resultados de herramientas grandes por referencias a archivos)"
+            }
+            Msg::HelpSkinnify => {
+                "Aligerar todo el contexto (como /thinnify, pero no solo el primer tercio)"
+            }
+            Msg::HelpClear => {
+                "Borrar la sesión y empezar de cero (descarta los datos de continuación)"
+            }
+            Msg::HelpReadme => "Volver a leer README.md y AGENTS.md del disco",
+            Msg::HelpStats => "Mostrar estadísticas detalladas de contexto y rendimiento",
+            Msg::HelpUndo => {
+                "Revertir las últimas n ediciones de archivos del agente (1 por defecto)"
+            }
+            Msg::HelpContext => {
+                "Listar los mensajes del contexto, o fijar, soltar o descartar uno por número o id"
+            }
+            Msg::HelpBranch => {
+                "Listar las ramas de la conversación, o crear una, cambiar a una o descartar una"
+            }
+            Msg::HelpCopy => {
+                "Copiar la última respuesta, su último bloque de código o la selección de la TUI al portapapeles"
+            }
+            Msg::HelpModel => {
+                "Cambiar a otro proveedor (p. ej. openai.default) en los próximos turnos, conservando la conversación"
+            }
+            Msg::HelpLogs => {
+                "Mostrar los eventos de registro recientes de un nivel o superior (debug por defecto)"
+            }
+            Msg::HelpHelp => "Mostrar esta ayuda",
+            Msg::HelpExit => "Salir de la sesión interactiva",
+            Msg::AccumulativeHelp => {
+                "📖 Comandos disponibles:
+  /requirements - Mostrar todos los requisitos acumulados
+  /chat         - Cambiar al modo de chat interactivo
+  /help         - Mostrar esta ayuda
+  exit/quit     - Salir de la sesión"
+            }
+            Msg::UnknownCommand => {
+                "❌ Comando desconocido: {command}. Escribe /help para ver los comandos disponibles."
+            }
+            Msg::Goodbye => "👋 ¡Hasta luego!",
+            Msg::InterruptedGoodbye => "👋 Interrumpido. ¡Hasta luego!",
+            Msg::OperationCancelled => "⚠️  Operación cancelada por el usuario",
+            Msg::AutonomousRunCancelled => "⚠️  Ejecución autónoma cancelada por el usuario",
+            Msg::TaskError => "❌ Error: {error}",
+            Msg::ErrorHintRateLimit => {
+                "💡 El proveedor está limitando las peticiones. Espera un momento, define [providers.rate_limits] o configura fallback_providers."
+            }
+            Msg::ErrorHintNetwork => {
+                "💡 Revisa la conexión de red y el host o base_url del proveedor."
+            }
+            Msg::ErrorHintTimeout => {
+                "💡 El proveedor tardó demasiado en responder. Inténtalo de nuevo o aumenta [agent] timeout_seconds."
+            }
+            Msg::ErrorHintServer => {
+                "💡 El proveedor falló por su parte. Inténtalo de nuevo en breve o configura fallback_providers."
+            }
+            Msg::ErrorHintOverloaded => {
+                "💡 El modelo está sobrecargado. Inténtalo de nuevo en breve o cambia de proveedor con /model."
+            }
+            Msg::ErrorHintContextLength => {
+                "💡 La conversación ya no cabe en el modelo. Ejecuta /compact o /thinnify, o cambia a un modelo con una ventana de contexto mayor."
+            }
+            Msg::ErrorHintAuth => {
+                "💡 El proveedor rechazó las credenciales. Revisa la api_key o guarda una con `g3 auth login <provider>`."
+            }
+            Msg::ErrorHintInvalidRequest => {
+                "💡 El proveedor rechazó la petición. Revisa el nombre del modelo y los ajustes de la configuración del proveedor."
+            }
+            Msg::ErrorHintTool => {
+                "💡 Falló una herramienta. Corrige la causa indicada arriba y vuelve a enviar la petición."
+            }
+            Msg::ErrorHintGit => {
+                "💡 Revisa `git status` en el código y resuelve lo que indique git."
+            }
+            Msg::YesNoSuffix => "[s/N]",
+            Msg::ApprovalChoices => "[y] sí / [N] no / [a] siempre / [d] denegar siempre:",
+            Msg::SelectOption => "Elige una opción (1-{count}):",
+            Msg::InvalidChoice => "Opción no válida. Elige una (1-{count}):",
+            Msg::ContextOptimized => "Contexto optimizado correctamente",
+            Msg::SummarizingWork => "resumiendo el trabajo realizado...",
+            Msg::SummaryHeader => "Resumen",
+            Msg::StatusLabel => "ESTADO",
+            Msg::StatusReady => "LISTO",
+            Msg::StatusProcessing => "PROCESANDO",
+            Msg::ContextLabel => "CONTEXTO",
+            Msg::CommandInputTitle => "ENTRADA DE COMANDOS",
+            Msg::ToolDetailTitle => "DETALLE DE HERRAMIENTA",
+            Msg::ActivityTitle => "ACTIVIDAD",
+            Msg::ApprovalTitle => "SE REQUIERE APROBAR LA HERRAMIENTA",
+            Msg::ApprovalTool => "HERRAMIENTA",
+            Msg::ApprovalArgs => "ARGUMENTOS",
+            Msg::ApprovalRisk => "RIESGO",
+            Msg::ApprovalAllow => "PERMITIR",
+            Msg::ApprovalDeny => "DENEGAR",
+            Msg::ApprovalAlways => "SIEMPRE",
+            Msg::ApprovalDenyAlways => "DENEGAR SIEMPRE",
+            Msg::PaletteNoMatches => "NINGÚN COMANDO COINCIDE",
+            Msg::PaletteSelect => "ELEGIR",
+            Msg::PaletteRun => "EJECUTAR",
+            Msg::PaletteBack => "VOLVER",
+            Msg::PaletteCommands => "Comandos",
+            Msg::PaletteSwitchTheme => "Cambiar tema",
+            Msg::PaletteSwitchThemeDetail => "Cambiar los colores del terminal",
+            Msg::PaletteToggleActivity => "Mostrar u ocultar actividad",
+            Msg::PaletteToggleActivityDetail => {
+                "Panel con la actividad de las herramientas mientras el agente trabaja"
+            }
+            Msg::PaletteToggleLogs => "Mostrar u ocultar registro",
+            Msg::PaletteToggleLogsDetail => "Eventos recientes de depuración y advertencias",
+            Msg::PaletteExportTranscript => "Exportar transcripción",
+            Msg::PaletteExportTranscriptDetail => "Guardar esta sesión como Markdown",
+            Msg::PaletteShowUsage => "Mostrar uso",
+            Msg::PaletteShowUsageDetail => "Estadísticas de contexto, tokens y coste",
+            Msg::PaletteShowContext => "Mostrar contexto",
+            Msg::PaletteShowContextDetail => {
+                "Cada mensaje de la ventana de contexto; fija o expulsa uno"
+            }
+            Msg::PalettePauseAgent => "Pausar agente",
+            Msg::PalettePauseAgentDetail => "Interrumpir el turno actual",
+            Msg::PaletteSwitchProvider => "Cambiar proveedor",
+            Msg::PaletteSwitchProviderDetail => {
+                "Usar otro proveedor configurado en los próximos turnos"
+            }
+            Msg::PaletteCompact => "Compactar contexto",
+            Msg::PaletteCompactDetail => "Resumir la conversación hasta ahora",
+            Msg::PaletteUndo => "Deshacer última edición",
+            Msg::PaletteUndoDetail => "Revertir la última edición de archivo del agente",
+            Msg::PaletteClear => "Borrar sesión",
+            Msg::PaletteClearDetail => "Empezar de cero",
+            Msg::PaletteBusy => "⚠️  Espera a que termine la tarea o pulsa Ctrl+C para detenerla",
+            Msg::HelpOverlayTitle => "AYUDA",
+            Msg::HelpKeysTitle => "Teclas",
+            Msg::KeySend => "Enviar la línea de entrada",
+            Msg::KeyPalette => "Abrir la paleta de comandos",
+            Msg::KeyHelp => "Mostrar u ocultar esta ayuda",
+            Msg::KeyClose => "Cerrar la ayuda, la paleta o una lista anidada",
+            Msg::KeyScroll => "Desplazar la salida",
+            Msg::KeyScrollEnds => "Saltar al principio o al final de la salida",
+            Msg::KeyApproval => "Responder a una aprobación: sí, no, siempre, denegar siempre",
+            Msg::KeyLogs => "Mostrar u ocultar el panel de registro (:logs <nivel> lo filtra)",
+            Msg::LogPaneTitle => "REGISTRO",
+            Msg::KeyBranches => "Mostrar las ramas de la conversación para cambiar o descartar",
+            Msg::BranchesTitle => "RAMAS",
+            Msg::BranchesKeyHelp => "↑/↓ elegir · Enter cambiar · d descartar · Esc cerrar",
+            Msg::KeySelect => "Seleccionar líneas de la salida para /copy selection",
+            Msg::KeyFollowLink => "Abrir el archivo:línea de la línea elegida en $EDITOR",
+            Msg::FlockKeyHelp => {
+                "Tab/←/→/1-9 enfocar · ↑/↓/RePág/AvPág desplazar · Fin seguir · Enter adoptar · q cerrar"
+            }
+            Msg::FlockApprovalKeyHelp => {
+                "a aprobar · x rechazar y detener el flock · Tab/1-9 enfocar · ↑/↓ desplazar · q cerrar (rechaza)"
+            }
+            Msg::FlockAdoptedKeyHelp => {
+                "Enter enviar línea · ↑/↓/RePág/AvPág desplazar · Esc volver a todos los segmentos"
+            }
+            Msg::FlockStarting => "Iniciando el flock...",
+            Msg::FlockWaitingForApproval => "Esperando aprobación ({barrier})",
+            Msg::FlockFinished => "terminado",
+            Msg::FlockApproveTitle => "¿Aprobar que continúe {barrier}?",
+            Msg::FlockInputTitle => "Entrada para el segmento {segment}",
+            Msg::FlockSegmentTitle => "Segmento {segment} · {state}",
+            Msg::FlockInputDropped => "(el segmento no está en ejecución; entrada descartada)",
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// `{placeholders}` in `text`
+    fn placeholders(text: &str) -> Vec<&str> {
+        let mut names: Vec<&str> = text
+            .split('{')
+            .skip(1)
+            .filter_map(|rest| rest.split_once('}').map(|(name, _)| name))
+            .collect();
+        names.sort();
+        names
+    }
+
+    #[test]
+    fn test_every_locale_keeps_the_placeholders() {
+        for msg in Msg::ALL {
+            let en = msg.text(Locale::En);
+            let es = msg.text(Locale::Es);
+            assert!(!es.is_empty(), "{:?}", msg);
+            assert_eq!(placeholders(en), placeholders(es), "{:?}", msg);
+        }
+    }
+
+    #[test]
+    fn test_text_in_a_locale() {
+        assert_eq!(Msg::StatusReady.text(Locale::Es), "LISTO");
+        assert_eq!(
+            interpolate(Msg::FlockInputTitle.text(Locale::Es), &[("segment", "2")]),
+            "Entrada para el segmento 2"
+        );
+        assert_eq!(
+            interpolate(Msg::UnknownCommand.text(Locale::En), &[("command", "/x")]),
+            "❌ Unknown command: /x. Type /help for available commands."
+        );
+    }
+}
@@ -2,6 +2,7 @@
 pub mod filter_json;
 
 use anyhow::Result;
+use crossterm::event::{Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
 use crossterm::style::{Color, ResetColor, SetForegroundColor};
 use std::time::{Duration, Instant};
 
@@ -257,8 +258,8 @@ fn extract_coach_feedback_from_logs(
     );
 }
 
-use clap::Parser;
-use g3_config::Config;
+use clap::{CommandFactory, Parser, Subcommand};
+use g3_config::{Config, PROFILE_ENV};
 use g3_core::{project::Project, ui_writer::UiWriter, Agent, DiscoveryOptions};
 use rustyline::error::ReadlineError;
 use rustyline::DefaultEditor;
@@ -269,13 +270,37 @@ use std::process::exit;
 use tokio_util::sync::CancellationToken;
 use tracing::{debug, error};
 
+use g3_core::branches::{self, BranchAction};
+use g3_core::context_view::{self, ContextAction};
 use g3_core::error_handling::{classify_error, ErrorType, RecoverableError};
+use g3_core::notifier::{Notification, Notifier};
+use g3_core::prompt_templates::{self, PromptRegistry, TemplateSource};
+use g3_providers::G3Error;
+use i18n::{t, tf, Msg};
 mod simple_output;
 mod ui_writer_impl;
 use simple_output::SimpleOutput;
 mod machine_ui_writer;
 use machine_ui_writer::MachineUiWriter;
-use ui_writer_impl::ConsoleUiWriter;
+mod mcp_server;
+mod access_token;
+mod ansi;
+mod api_server;
+mod clipboard;
+mod command_palette;
+mod commands;
+mod config_loader;
+mod flock_tui;
+mod i18n;
+mod links;
+mod log_pane;
+mod markdown_table;
+mod replay;
+mod retro_tui;
+mod telemetry;
+mod theme;
+use retro_tui::RetroTui;
+use ui_writer_impl::{ConsoleUiWriter, RetroTuiWriter};
 
 #[derive(Parser, Clone)]
 #[command(name = "g3")]
@@ -290,6 +315,12 @@ pub struct Cli {
     #[arg(long = "manual-compact")]
     pub manual_compact: bool,
 
+    /// Explain mode: answer questions about the codebase with read and search
+    /// tools only; writes, shell commands and browser or desktop control are
+    /// refused
+    #[arg(long)]
+    pub explain: bool,
+
     /// Show the system prompt being sent to the LLM
     #[arg(long)]
     pub show_prompt: bool,
@@ -302,6 +333,11 @@ pub struct Cli {
     #[arg(short, long)]
     pub config: Option<String>,
 
+    /// Settings profile: the `[profiles.<name>]` table of the config, laid
+    /// over the rest of it (also G3_PROFILE)
+    #[arg(long, value_name = "NAME")]
+    pub profile: Option<String>,
+
     /// Workspace directory (defaults to current directory)
     #[arg(short, long)]
     pub workspace: Option<PathBuf>,
@@ -321,6 +357,16 @@ pub struct Cli {
     #[arg(long, value_name = "TEXT")]
     pub requirements: Option<String>,
 
+    /// Flock workspace whose knowledge base goes into the system context;
+    /// set by flock mode for its segment workers
+    #[arg(long, value_name = "DIR")]
+    pub flock_knowledge: Option<PathBuf>,
+
+    /// This worker's segment in that flock, which lets it ask other segments
+    /// for reviews; set by flock mode
+    #[arg(long, value_name = "N", requires = "flock_knowledge")]
+    pub flock_segment: Option<usize>,
+
     /// Enable accumulative autonomous mode (default is chat mode)
     #[arg(long)]
     pub auto: bool,
@@ -333,7 +379,16 @@ pub struct Cli {
     #[arg(long)]
     pub machine: bool,
 
-    /// Override the configured provider (anthropic, databricks, embedded, openai)
+    /// Run the interactive session in the full-screen retro TUI
+    #[arg(long, conflicts_with_all = ["machine", "autonomous", "auto", "task"])]
+    pub retro: bool,
+
+    /// Color theme of the retro TUI: retro, dracula, or a theme JSON file
+    /// (a path, or a name under ~/.config/g3/themes/); overrides [ui] theme
+    #[arg(long, value_name = "NAME|FILE", requires = "retro")]
+    pub theme: Option<String>,
+
+    /// Override the configured provider (anthropic, databricks, embedded, openai, local, gemini, bedrock, mock)
     #[arg(long, value_name = "PROVIDER")]
     pub provider: Option<String>,
 
@@ -341,11 +396,20 @@ pub struct Cli {
     #[arg(long, value_name = "MODEL")]
     pub model: Option<String>,
 
+    /// List the models served by the selected local provider and exit
+    #[arg(long)]
+    pub list_models: bool,
+
+    /// Export trace spans (agent turns, provider requests, tool calls, flock
+    /// segments) to this OTLP/gRPC collector; defaults to OTEL_EXPORTER_OTLP_ENDPOINT
+    #[arg(long, value_name = "URL")]
+    pub otlp_endpoint: Option<String>,
+
     /// Disable log file creation (no logs/ directory or session logs)
     #[arg(long)]
     pub quiet: bool,
 
-    /// Enable macOS Accessibility API tools for native app automation
+    /// Enable macOS Accessibility API (or AT-SPI on Linux) tools for native app automation
     #[arg(long)]
     pub macax: bool,
 
@@ -377,6 +441,41 @@ pub struct Cli {
     #[arg(long, default_value = "5")]
     pub flock_max_turns: usize,
 
+    /// Show flock segments in live log panes, with the option to adopt one
+    /// and answer its prompts
+    #[arg(long, requires = "project")]
+    pub flock_tui: bool,
+
+    /// Budget for the whole flock in USD, checked against the segments'
+    /// budgets in flock-segments.json
+    #[arg(long, value_name = "USD", requires = "project")]
+    pub flock_budget: Option<f64>,
+
+    /// Treat a flock segment as stalled after this many minutes without
+    /// output or tool activity
+    #[arg(long, value_name = "MINUTES", requires = "project")]
+    pub flock_stall_minutes: Option<u64>,
+
+    /// What to do with a stalled segment: nudge, restart or abort
+    #[arg(
+        long,
+        value_name = "ACTION",
+        default_value = "nudge",
+        requires = "flock_stall_minutes"
+    )]
+    pub flock_stall_action: g3_ensembles::StallAction,
+
+    /// Supervise the flock: wait for approval at these barriers
+    /// (after-planning, after-segments; comma-separated, all if none given)
+    #[arg(
+        long,
+        value_name = "BARRIERS",
+        num_args = 0..,
+        value_delimiter = ',',
+        requires = "project"
+    )]
+    pub flock_supervise: Option<Vec<g3_ensembles::Barrier>>,
+
     /// Enable planning mode for requirements-driven development
     #[arg(long, conflicts_with_all = ["autonomous", "auto", "chat"])]
     pub planning: bool,
@@ -389,6 +488,30 @@ pub struct Cli {
     #[arg(long)]
     pub no_git: bool,
 
+    /// Name of the plan to work on; each named plan keeps its own history,
+    /// requirements and todo files (for planning mode)
+    #[arg(long, value_name = "NAME", requires = "planning")]
+    pub plan: Option<String>,
+
+    /// Directory holding plans, relative to the codepath (default: `[planner]
+    /// plan_dir`, or g3-plan)
+    #[arg(long, value_name = "PATH", requires = "planning")]
+    pub plan_dir: Option<PathBuf>,
+
+    /// Refine requirements and write plan_report.md without implementing
+    /// them (for planning mode)
+    #[arg(long, requires = "planning")]
+    pub dry_run: bool,
+
+    /// Seed new_requirements.md from a GitHub issue URL (for planning mode)
+    #[arg(long, value_name = "ISSUE_URL", requires = "planning", conflicts_with = "import_json")]
+    pub import_github: Option<String>,
+
+    /// Seed new_requirements.md from an issue exported as JSON, with `title`,
+    /// `body`, `url` and `comments` fields (for planning mode)
+    #[arg(long, value_name = "PATH", requires = "planning")]
+    pub import_json: Option<PathBuf>,
+
     /// Enable fast codebase discovery before first LLM turn
     #[arg(long, value_name = "PATH")]
     pub codebase_fast_start: Option<PathBuf>,
@@ -396,21 +519,393 @@ pub struct Cli {
     /// Run as a specialized agent (loads prompt from agents/<name>.md)
     #[arg(long, value_name = "NAME", conflicts_with_all = ["autonomous", "auto", "chat", "planning"])]
     pub agent: Option<String>,
+
+    /// Resume a session from its journal (.g3/sessions/<SESSION_ID>/journal.jsonl),
+    /// restoring the full context window and transcript
+    #[arg(long, value_name = "SESSION_ID", conflicts_with_all = ["autonomous", "auto", "planning", "agent"])]
+    pub resume: Option<String>,
+
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+#[derive(Subcommand, Clone)]
+pub enum Command {
+    /// Report token usage and estimated cost from the usage ledger (~/.g3/usage.jsonl)
+    Usage {
+        /// Only include requests from the last N days
+        #[arg(long, value_name = "DAYS")]
+        days: Option<u32>,
+    },
+    /// Serve code_search, str_replace, background_process and the
+    /// computer-control tools to other agents over MCP (stdio by default)
+    Mcp {
+        /// Accept MCP clients over TCP on this address (e.g. 127.0.0.1:7410)
+        /// instead of stdio. Clients send the access token printed at startup
+        /// as their first line
+        #[arg(long, value_name = "ADDR")]
+        listen: Option<String>,
+        /// Allow --listen on an address other clients on the network can reach
+        #[arg(long, requires = "listen")]
+        allow_remote: bool,
+    },
+    /// Revert the agent's last file edits (write_file, str_replace), newest
+    /// first, independent of git state
+    Undo {
+        /// How many edits to revert
+        #[arg(default_value_t = 1)]
+        count: usize,
+        /// Session whose edits to revert (default: the one that edited last)
+        #[arg(long, value_name = "SESSION_ID")]
+        session: Option<String>,
+    },
+    /// Export a session's conversation as a Markdown or HTML transcript, to
+    /// attach to a PR or design doc
+    Export {
+        /// Session to export (default: the most recent one)
+        #[arg(value_name = "SESSION_ID")]
+        session: Option<String>,
+        /// Transcript format: markdown or html
+        #[arg(long, value_name = "FORMAT", default_value = "markdown")]
+        format: String,
+        /// Write the transcript to this file instead of stdout
+        #[arg(long, short, value_name = "FILE")]
+        output: Option<PathBuf>,
+    },
+    /// Play a journaled session back in the terminal, for demos, looking
+    /// into a model's behavior, or renderer fixtures
+    Replay {
+        /// Session id, or path of a journal.jsonl
+        #[arg(value_name = "SESSION_OR_FILE")]
+        target: String,
+        /// Play back this many times faster than the session ran
+        #[arg(long, value_name = "FACTOR", default_value_t = 1.0)]
+        speed: f64,
+        /// Wait for Enter before each entry
+        #[arg(long, conflicts_with_all = ["speed", "instant"])]
+        step: bool,
+        /// Print the whole transcript without pauses
+        #[arg(long, conflicts_with = "speed")]
+        instant: bool,
+    },
+    /// List the messages in a session's context window with their tokens,
+    /// age and pin, or pin, unpin or evict one of them
+    Context {
+        /// Session to inspect (default: the most recent one)
+        #[arg(value_name = "SESSION_ID")]
+        session: Option<String>,
+        /// Pin a message, by its number in the list or its id
+        #[arg(long, value_name = "MESSAGE", conflicts_with_all = ["unpin", "evict"])]
+        pin: Option<String>,
+        /// Unpin a message
+        #[arg(long, value_name = "MESSAGE", conflicts_with = "evict")]
+        unpin: Option<String>,
+        /// Drop a message from the context for good
+        #[arg(long, value_name = "MESSAGE")]
+        evict: Option<String>,
+    },
+    /// Drive the agent over a JSON-RPC/WebSocket API on 127.0.0.1 (submit
+    /// tasks, stream events, approve tool calls, fetch the transcript)
+    Serve {
+        /// Port to listen on
+        #[arg(long, value_name = "N", default_value_t = 7420)]
+        port: u16,
+    },
+    /// Inspect the layered configuration (defaults, user config, the
+    /// project's .g3/config.toml, command-line flags)
+    Config {
+        #[command(subcommand)]
+        action: ConfigCommand,
+    },
+    /// Manage provider credentials kept outside the config file
+    Auth {
+        #[command(subcommand)]
+        action: AuthCommand,
+    },
+    /// Inspect a flock before running it
+    Flock {
+        #[command(subcommand)]
+        action: FlockCommand,
+    },
+    /// List the agent's and planner's prompts, or copy one into
+    /// ~/.g3/prompts/ to customize it
+    Prompts {
+        #[command(subcommand)]
+        action: PromptsCommand,
+    },
+    /// Measure code_search throughput and diff-apply latency on a synthetic
+    /// repository, and print the results as JSON
+    Bench {
+        /// Rust files in the synthetic repository
+        #[arg(long, value_name = "N", default_value_t = 500)]
+        files: usize,
+        /// Timed runs of each benchmark
+        #[arg(long, value_name = "N", default_value_t = 10)]
+        iterations: usize,
+        /// Write the JSON results to this file instead of stdout
+        #[arg(long, short, value_name = "FILE")]
+        output: Option<PathBuf>,
+    },
+    /// Print a shell completion script, e.g.
+    /// `g3 completions zsh > ~/.zfunc/_g3`
+    Completions {
+        /// bash, zsh, fish, elvish or powershell
+        shell: clap_complete::Shell,
+    },
+}
+
+#[derive(Subcommand, Clone)]
+pub enum PromptsCommand {
+    /// List every prompt template, its placeholders and whether it is
+    /// overridden (and the override valid)
+    List,
+    /// Write a built-in prompt to ~/.g3/prompts/<NAME>.md to edit
+    Export {
+        /// Template name, as listed by `g3 prompts list`
+        name: String,
+    },
+}
+
+#[derive(Subcommand, Clone)]
+pub enum FlockCommand {
+    /// Validate the flock and print its schedule, workspace layout and
+    /// estimated cost without starting any agent
+    Plan {
+        /// Project directory (a git repository with flock-requirements.md)
+        #[arg(long, value_name = "PATH")]
+        project: PathBuf,
+        /// Flock workspace directory where segment copies would be created
+        #[arg(long, value_name = "PATH")]
+        flock_workspace: PathBuf,
+        /// Number of segments (default: the segments in flock-segments.json)
+        #[arg(long, value_name = "N")]
+        segments: Option<usize>,
+        /// Maximum turns per segment
+        #[arg(long, value_name = "N", default_value_t = 5)]
+        max_turns: usize,
+        /// Budget for the whole flock in USD
+        #[arg(long, value_name = "USD")]
+        budget: Option<f64>,
+    },
+}
+
+#[derive(Subcommand, Clone)]
+pub enum AuthCommand {
+    /// Store a provider's API key in the configured secrets backend (OS
+    /// keyring by default), so it can be left out of the config file
+    Login {
+        /// Provider as "<provider_type>.<config_name>" (e.g. anthropic.default),
+        /// or an openai_compatible provider's name
+        provider: String,
+    },
+}
+
+#[derive(Subcommand, Clone)]
+pub enum ConfigCommand {
+    /// List the config layers in effect for the workspace
+    Show {
+        /// Also print the merged configuration, credentials masked
+        #[arg(long)]
+        effective: bool,
+    },
+    /// Convert user and project config files from the old provider format
+    /// to `[providers.<type>.<name>]`, keeping a backup of each original
+    Migrate {
+        /// Print the diff without changing any file
+        #[arg(long)]
+        dry_run: bool,
+    },
 }
 
 pub async fn run() -> Result<()> {
     let cli = Cli::parse();
 
+    // Every config load from here on, the planner's and flock workers'
+    // included, picks the profile up
+    if let Some(profile) = &cli.profile {
+        std::env::set_var(PROFILE_ENV, profile);
+    }
+
+    if let Some(Command::Completions { shell }) = &cli.command {
+        clap_complete::generate(*shell, &mut Cli::command(), "g3", &mut std::io::stdout());
+        return Ok(());
+    }
+
+    if let Some(Command::Usage { days }) = &cli.command {
+        return print_usage_report(*days);
+    }
+
+    if let Some(Command::Undo { count, session }) = &cli.command {
+        if let Some(workspace) = &cli.workspace {
+            std::env::set_current_dir(workspace)?;
+        }
+        return undo_edits(*count, session.clone());
+    }
+
+    if let Some(Command::Export {
+        session,
+        format,
+        output,
+    }) = &cli.command
+    {
+        if let Some(workspace) = &cli.workspace {
+            std::env::set_current_dir(workspace)?;
+        }
+        return export_session(session.clone(), format, output.as_deref());
+    }
+
+    if let Some(Command::Replay {
+        target,
+        speed,
+        step,
+        instant,
+    }) = &cli.command
+    {
+        if let Some(workspace) = &cli.workspace {
+            std::env::set_current_dir(workspace)?;
+        }
+        let pace = match (step, instant) {
+            (true, _) => replay::Pace::Step,
+            (_, true) => replay::Pace::Instant,
+            _ => replay::Pace::Speed(*speed),
+        };
+        return replay::replay(target, pace);
+    }
+
+    if let Some(Command::Context {
+        session,
+        pin,
+        unpin,
+        evict,
+    }) = &cli.command
+    {
+        if let Some(workspace) = &cli.workspace {
+            std::env::set_current_dir(workspace)?;
+        }
+        let action = [
+            (ContextAction::Pin, pin),
+            (ContextAction::Unpin, unpin),
+            (ContextAction::Evict, evict),
+        ]
+        .into_iter()
+        .find_map(|(action, selector)| Some((action, selector.clone()?)));
+        return session_context(session.clone(), action);
+    }
+
+    if let Some(Command::Bench {
+        files,
+        iterations,
+        output,
+    }) = &cli.command
+    {
+        return run_benchmarks(*files, *iterations, output.as_deref()).await;
+    }
+
+    if let Some(Command::Mcp {
+        listen,
+        allow_remote,
+    }) = &cli.command
+    {
+        return mcp_server::run_mcp_server(&cli, listen.clone(), *allow_remote).await;
+    }
+
+    if let Some(Command::Serve { port }) = &cli.command {
+        return api_server::run_api_server(&cli, *port).await;
+    }
+
+    if let Some(Command::Config {
+        action: ConfigCommand::Show { effective },
+    }) = &cli.command
+    {
+        if let Some(workspace) = &cli.workspace {
+            std::env::set_current_dir(workspace)?;
+        }
+        return config_loader::show_config(&cli, *effective);
+    }
+
+    if let Some(Command::Config {
+        action: ConfigCommand::Migrate { dry_run },
+    }) = &cli.command
+    {
+        if let Some(workspace) = &cli.workspace {
+            std::env::set_current_dir(workspace)?;
+        }
+        return config_loader::migrate_config(&cli, *dry_run);
+    }
+
+    if let Some(Command::Auth {
+        action: AuthCommand::Login { provider },
+    }) = &cli.command
+    {
+        if let Some(workspace) = &cli.workspace {
+            std::env::set_current_dir(workspace)?;
+        }
+        return config_loader::auth_login(&cli, provider);
+    }
+
+    if let Some(Command::Flock {
+        action:
+            FlockCommand::Plan {
+                project,
+                flock_workspace,
+                segments,
+                max_turns,
+                budget,
+            },
+    }) = &cli.command
+    {
+        return print_flock_plan(
+            project.clone(),
+            flock_workspace.clone(),
+            *segments,
+            *max_turns,
+            *budget,
+        );
+    }
+
+    if let Some(Command::Prompts { action }) = &cli.command {
+        return match action {
+            PromptsCommand::List => list_prompt_templates(),
+            PromptsCommand::Export { name } => export_prompt_template(name),
+        };
+    }
+
+    // Export spans over OTLP if a collector is configured; flushed when run() returns
+    let telemetry = telemetry::Telemetry::from_cli(&cli)?;
+
     // Check if flock mode is enabled
     if let (Some(project_dir), Some(flock_workspace), Some(num_segments)) =
         (&cli.project, &cli.flock_workspace, cli.segments)
     {
+        if let Some(telemetry) = &telemetry {
+            telemetry.install();
+        }
+        // The segments load their own config; this one only sets the
+        // language of the flock TUI
+        if cli.flock_tui {
+            let _ = config_loader::load_config(&cli);
+        }
         // Run flock mode
         return run_flock_mode(
             project_dir.clone(),
             flock_workspace.clone(),
             num_segments,
             cli.flock_max_turns,
+            cli.flock_budget,
+            cli.flock_stall_minutes.map(|minutes| {
+                g3_ensembles::WatchdogConfig::new(
+                    Duration::from_secs(minutes * 60),
+                    cli.flock_stall_action,
+                )
+            }),
+            cli.flock_supervise.as_ref().map(|barriers| {
+                if barriers.is_empty() {
+                    g3_ensembles::Barrier::ALL.to_vec()
+                } else {
+                    barriers.clone()
+                }
+            }),
+            cli.flock_tui,
         )
         .await;
     }
@@ -425,11 +920,30 @@ pub async fn run() -> Result<()> {
         // Expand ~ in codepath if provided
         // The expand_codepath function in g3_planner handles tilde expansion
         let codepath = cli.codepath.clone();
+        let plan_dir = match &cli.plan_dir {
+            Some(plan_dir) => Some(plan_dir.clone()),
+            None => Config::load(cli.config.as_deref())?
+                .planner
+                .plan_dir
+                .map(PathBuf::from),
+        };
+        if let Some(telemetry) = &telemetry {
+            telemetry.install();
+        }
+        let import = match (&cli.import_github, &cli.import_json) {
+            (Some(url), _) => Some(g3_planner::issue_import::ImportSource::GitHub(url.clone())),
+            (None, Some(path)) => Some(g3_planner::issue_import::ImportSource::Json(path.clone())),
+            (None, None) => None,
+        };
         return g3_planner::run_planning_mode(
             codepath,
             cli.workspace.clone(),
             cli.no_git,
+            cli.dry_run,
             cli.config.as_deref(),
+            plan_dir,
+            cli.plan.clone(),
+            import,
         )
         .await;
     }
@@ -456,10 +970,14 @@ pub async fn run() -> Result<()> {
         .await;
     }
 
+    if cli.list_models {
+        return list_local_models(&cli).await;
+    }
+
     // Only initialize logging if not in retro mode
-    if !cli.machine {
+    if !cli.machine && !cli.retro {
         // Initialize logging with filtering
-        use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt, EnvFilter};
+        use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt, EnvFilter, Layer};
 
         // Create a filter that suppresses llama_cpp logs unless in verbose mode
         let filter = if cli.verbose {
@@ -481,18 +999,20 @@ pub async fn run() -> Result<()> {
         };
 
         tracing_subscriber::registry()
-            .with(tracing_subscriber::fmt::layer())
-            .with(filter)
+            .with(tracing_subscriber::fmt::layer().with_filter(filter))
+            .with(log_pane::layer())
+            .with(telemetry.as_ref().map(|telemetry| telemetry.layer()))
             .init();
     } else {
-        // In retro mode, we don't want any logging output to interfere with the TUI
-        // We'll use a no-op subscriber
-        use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt, EnvFilter};
-
-        // Create a filter that suppresses ALL logs in retro mode
-        let filter = EnvFilter::from_default_env().add_directive("off".parse().unwrap()); // Turn off all logging
+        // In retro mode, we don't want any logging output to interfere with the TUI.
+        // Nothing is printed: events are only kept for /logs, and spans still
+        // reach the collector
+        use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
 
-        tracing_subscriber::registry().with(filter).init();
+        tracing_subscriber::registry()
+            .with(log_pane::layer())
+            .with(telemetry.as_ref().map(|telemetry| telemetry.layer()))
+            .init();
     }
 
     // Set up workspace directory
@@ -513,6 +1033,14 @@ pub async fn run() -> Result<()> {
     // Then load README for project context
     let readme_content = read_project_readme(&workspace_dir);
 
+    // Flock segment workers also get the decisions shared across the flock
+    let knowledge_content = cli
+        .flock_knowledge
+        .as_ref()
+        .map(|dir| g3_ensembles::KnowledgeBase::new(dir).render())
+        .transpose()?
+        .flatten();
+
     // Create project model
     let project = if cli.autonomous {
         if let Some(requirements_text) = &cli.requirements {
@@ -534,38 +1062,7 @@ pub async fn run() -> Result<()> {
     project.enter_workspace()?;
 
     // Load configuration with CLI overrides
-    let mut config = Config::load_with_overrides(
-        cli.config.as_deref(),
-        cli.provider.clone(),
-        cli.model.clone(),
-    )?;
-
-    // Apply macax flag override
-    if cli.macax {
-        config.macax.enabled = true;
-    }
-
-    // Apply webdriver flag override
-    if cli.webdriver {
-        config.webdriver.enabled = true;
-    }
-
-    // Apply chrome-headless flag override
-    if cli.chrome_headless {
-        config.webdriver.enabled = true;
-        config.webdriver.browser = g3_config::WebDriverBrowser::ChromeHeadless;
-    }
-
-    // Apply safari flag override
-    if cli.safari {
-        config.webdriver.enabled = true;
-        config.webdriver.browser = g3_config::WebDriverBrowser::Safari;
-    }
-
-    // Apply no-auto-compact flag override
-    if cli.manual_compact {
-        config.agent.auto_compact = false;
-    }
+    let config = config_loader::load_config(&cli)?;
 
     // Validate provider if specified
     if let Some(ref provider) = cli.provider {
@@ -582,13 +1079,11 @@ pub async fn run() -> Result<()> {
     // Initialize agent
     // ui_writer will be created conditionally based on machine mode
 
-    // Combine AGENTS.md and README content if both exist
-    let combined_content = match (agents_content.clone(), readme_content.clone()) {
-        (Some(agents), Some(readme)) => Some(format!("{}\n\n{}", agents, readme)),
-        (Some(agents), None) => Some(agents),
-        (None, Some(readme)) => Some(readme),
-        (None, None) => None,
-    };
+    // Combine AGENTS.md, README and flock knowledge content that exists
+    let combined_content = [agents_content, readme_content, knowledge_content]
+        .into_iter()
+        .flatten()
+        .reduce(|combined, content| format!("{}\n\n{}", combined, content));
 
     // Execute task, autonomous mode, or start interactive mode based on machine mode
     if cli.machine {
@@ -596,7 +1091,7 @@ pub async fn run() -> Result<()> {
 
         let ui_writer = MachineUiWriter::new();
 
-        let agent = if cli.autonomous {
+        let mut agent = if cli.autonomous {
             Agent::new_autonomous_with_readme_and_quiet(
                 config.clone(),
                 ui_writer,
@@ -614,6 +1109,19 @@ pub async fn run() -> Result<()> {
             .await?
         };
 
+        if let (Some(flock_workspace), Some(segment_id)) = (&cli.flock_knowledge, cli.flock_segment)
+        {
+            agent.set_review_mailbox(g3_core::peer_review::ReviewMailbox::new(
+                flock_workspace,
+                segment_id,
+            ));
+        }
+
+        if let Some(session_id) = &cli.resume {
+            let transcript = agent.resume_session(session_id)?;
+            println!("SESSION_RESUMED: {} ({} transcript entries)", session_id, transcript.len());
+        }
+
         run_with_machine_mode(agent, cli, project).await?;
     } else {
         // Normal mode - use ConsoleUiWriter
@@ -632,9 +1140,33 @@ pub async fn run() -> Result<()> {
             return Ok(());
         }
 
+        if cli.retro {
+            // Retro mode - the full-screen TUI, with RetroTuiWriter
+            let theme =
+                theme::ColorTheme::load(cli.theme.as_deref().or(config.ui.theme.as_deref()))?;
+            let tui = RetroTui::start(theme).await?;
+            let mut agent = Agent::new_with_readme_and_quiet(
+                config.clone(),
+                RetroTuiWriter::new(tui.clone()),
+                combined_content.clone(),
+                cli.quiet,
+            )
+            .await?;
+
+            if let Some(session_id) = &cli.resume {
+                let transcript = agent.resume_session(session_id)?;
+                for entry in &transcript {
+                    tui.output(&format!("\n{}\n", replay::render(entry)));
+                }
+                tui.output(&format!("\nSession {} resumed\n", session_id));
+            }
+
+            return run_interactive_retro(agent, tui, cli.show_prompt, cli.show_code).await;
+        }
+
         let ui_writer = ConsoleUiWriter::new();
 
-        let agent = if cli.autonomous {
+        let mut agent = if cli.autonomous {
             Agent::new_autonomous_with_readme_and_quiet(
                 config.clone(),
                 ui_writer,
@@ -652,12 +1184,279 @@ pub async fn run() -> Result<()> {
             .await?
         };
 
+        if let (Some(flock_workspace), Some(segment_id)) = (&cli.flock_knowledge, cli.flock_segment)
+        {
+            agent.set_review_mailbox(g3_core::peer_review::ReviewMailbox::new(
+                flock_workspace,
+                segment_id,
+            ));
+        }
+
+        if let Some(session_id) = &cli.resume {
+            let transcript = agent.resume_session(session_id)?;
+            print_resumed_transcript(session_id, &transcript, &SimpleOutput::new());
+        }
+
         run_with_console_mode(agent, cli, project, combined_content).await?;
     }
 
     Ok(())
 }
 
+/// Replay a resumed session's transcript, so it continues where it stopped
+fn print_resumed_transcript(
+    session_id: &str,
+    transcript: &[g3_core::session_journal::TranscriptEntry],
+    output: &SimpleOutput,
+) {
+    output.print(&format!("🔄 Resuming session {}", session_id));
+    for entry in transcript {
+        output.print_smart(&replay::render(entry));
+    }
+    output.print("");
+    output.print(&format!("✅ Session {} resumed", session_id));
+}
+
+/// The agent's prompt templates, then the planner's
+fn prompt_registries() -> [PromptRegistry; 2] {
+    [prompt_templates::core(), g3_planner::prompts::registry()]
+}
+
+/// Print every prompt template with its placeholders and source
+fn list_prompt_templates() -> Result<()> {
+    for registry in prompt_registries() {
+        for template in registry.templates() {
+            let source = match registry.source(template.name) {
+                Ok(TemplateSource::BuiltIn) => "built-in".to_string(),
+                Ok(TemplateSource::Override(path)) => format!("override {}", path.display()),
+                Err(e) => format!("⚠️ {:#}", e),
+            };
+            let placeholders: Vec<String> = template
+                .placeholders
+                .iter()
+                .map(|name| format!("{{{}}}", name))
+                .collect();
+            println!("{:<22} {}", template.name, template.description);
+            if !placeholders.is_empty() {
+                println!("{:<22} placeholders: {}", "", placeholders.join(" "));
+            }
+            println!("{:<22} {}", "", source);
+        }
+    }
+    println!(
+        "\nOverrides: {}",
+        g3_core::paths::get_prompts_dir().display()
+    );
+    Ok(())
+}
+
+/// Copy the built-in prompt `name` into the overrides directory
+fn export_prompt_template(name: &str) -> Result<()> {
+    let Some(registry) = prompt_registries()
+        .into_iter()
+        .find(|registry| registry.template(name).is_ok())
+    else {
+        anyhow::bail!("Unknown prompt template '{}'; see `g3 prompts list`", name);
+    };
+    let path = registry.export(name)?;
+    println!(
+        "Wrote {}; edit it to override the built-in prompt",
+        path.display()
+    );
+    Ok(())
+}
+
+/// Print token usage and cost totals from the usage ledger
+fn print_usage_report(days: Option<u32>) -> Result<()> {
+    let ledger = g3_core::usage::UsageLedger::at_default_path();
+    let since = days.map(|days| chrono::Utc::now() - chrono::Duration::days(days as i64));
+    let report = g3_core::usage::UsageReport::from_records(&ledger.read()?, since);
+    print!("{}", report);
+    println!("\nLedger: {}", ledger.path().display());
+    Ok(())
+}
+
+/// `g3 undo`: revert the newest edits recorded in a session's edit journal
+fn undo_edits(count: usize, session: Option<String>) -> Result<()> {
+    let Some(session_id) = session.or_else(g3_core::edit_journal::latest_session_with_edits)
+    else {
+        println!("No edits to undo");
+        return Ok(());
+    };
+    let report = g3_core::edit_journal::EditJournal::for_session(&session_id).undo(count)?;
+    print!("{}", report);
+    if !report.undone.is_empty() {
+        println!("Session: {}", session_id);
+    }
+    Ok(())
+}
+
+fn export_session(session: Option<String>, format: &str, output: Option<&Path>) -> Result<()> {
+    let format: g3_core::transcript_export::ExportFormat = format.parse()?;
+    let Some(session_id) = session.or_else(g3_core::session_journal::latest_session) else {
+        anyhow::bail!("No sessions to export");
+    };
+    let transcript = g3_core::transcript_export::export_session(&session_id, format)?;
+    match output {
+        Some(path) => {
+            std::fs::write(path, transcript)
+                .map_err(|e| anyhow::anyhow!("Failed to write {}: {}", path.display(), e))?;
+            println!("Exported session {} to {}", session_id, path.display());
+        }
+        None => print!("{}", transcript),
+    }
+    Ok(())
+}
+
+/// `g3 bench`: run the benchmarks and print or save their JSON report
+async fn run_benchmarks(files: usize, iterations: usize, output: Option<&Path>) -> Result<()> {
+    if iterations == 0 {
+        anyhow::bail!("--iterations must be at least 1");
+    }
+    let options = g3_core::bench::BenchOptions { files, iterations };
+    let report = g3_core::bench::run(&options).await?;
+    let json = serde_json::to_string_pretty(&report)?;
+    match output {
+        Some(path) => {
+            std::fs::write(path, json + "\n")
+                .map_err(|e| anyhow::anyhow!("Failed to write {}: {}", path.display(), e))?;
+            for result in &report.results {
+                println!(
+                    "{:<18} {:>10.2} ms  {:>12.0} {}",
+                    result.name, result.median_ms, result.throughput, result.throughput_unit
+                );
+            }
+            println!("Wrote benchmark results to {}", path.display());
+        }
+        None => println!("{}", json),
+    }
+    Ok(())
+}
+
+/// `g3 context`: list a journaled session's messages, or change one of them
+fn session_context(session: Option<String>, action: Option<(ContextAction, String)>) -> Result<()> {
+    let Some(session_id) = session.or_else(g3_core::session_journal::latest_session) else {
+        anyhow::bail!("No sessions to inspect");
+    };
+    match action {
+        Some((action, selector)) => {
+            let done = context_view::edit_session(&session_id, action, &selector)?;
+            println!("{}", done);
+        }
+        None => {
+            let now = chrono::Local::now().time();
+            print!("{}", context_view::session_breakdown(&session_id, now)?);
+            println!("\nSession: {}", session_id);
+        }
+    }
+    Ok(())
+}
+
+/// The action of a `/context [pin|unpin|evict <message>]` command: `None`
+/// to list the messages. Returns `None` if `input` isn't a /context command.
+fn parse_context_command(input: &str) -> Option<Result<Option<(ContextAction, String)>, String>> {
+    let mut words = input.split_whitespace();
+    if words.next() != Some("/context") {
+        return None;
+    }
+    let usage = "Usage: /context [pin|unpin|evict <n|id>]".to_string();
+    Some(match (words.next(), words.next(), words.next()) {
+        (None, _, _) => Ok(None),
+        (Some(action), Some(selector), None) => action
+            .parse()
+            .map(|action| Some((action, selector.to_string())))
+            .map_err(|_| usage),
+        _ => Err(usage),
+    })
+}
+
+/// The action of a `/branch [new|switch|discard <name>]` command: `None` to
+/// list the branches. Returns `None` if `input` isn't a /branch command.
+fn parse_branch_command(input: &str) -> Option<Result<Option<(BranchAction, String)>, String>> {
+    let mut words = input.split_whitespace();
+    if words.next() != Some("/branch") {
+        return None;
+    }
+    let usage = "Usage: /branch [new|switch|discard <name>]".to_string();
+    Some(match (words.next(), words.next(), words.next()) {
+        (None, _, _) => Ok(None),
+        (Some(action), Some(name), None) => action
+            .parse()
+            .map(|action| Some((action, name.to_string())))
+            .map_err(|_| usage),
+        _ => Err(usage),
+    })
+}
+
+/// The edit count of an `/undo [n]` command, or `None` if `input` isn't one
+fn parse_undo_command(input: &str) -> Option<Result<usize, String>> {
+    let mut words = input.split_whitespace();
+    if words.next() != Some("/undo") {
+        return None;
+    }
+    Some(match (words.next(), words.next()) {
+        (None, _) => Ok(1),
+        (Some(count), None) => count
+            .parse()
+            .map_err(|_| format!("Invalid edit count '{}'. Usage: /undo [n]", count)),
+        _ => Err("Usage: /undo [n]".to_string()),
+    })
+}
+
+/// The level of a `/logs [level]` command, or `None` if `input` isn't one
+fn parse_logs_command(input: &str) -> Option<Result<tracing::Level, String>> {
+    let mut words = input.split_whitespace();
+    if words.next() != Some("/logs") {
+        return None;
+    }
+    let usage = "Usage: /logs [error|warn|info|debug]";
+    Some(match (words.next(), words.next()) {
+        (None, _) => Ok(tracing::Level::DEBUG),
+        (Some(level), None) => log_pane::parse_level(level)
+            .ok_or_else(|| format!("Unknown level '{}'. {}", level, usage)),
+        _ => Err(usage.to_string()),
+    })
+}
+
+/// Print the models of the selected `local` provider's server
+async fn list_local_models(cli: &Cli) -> Result<()> {
+    let config = Config::load_with_overrides(cli.config.as_deref(), cli.provider.clone(), None)?;
+    let (provider_type, config_name) =
+        Config::parse_provider_reference(&config.providers.default_provider)?;
+    let local_config = match config.get_local_config(&config_name) {
+        Some(local_config) if provider_type == "local" => local_config,
+        _ => anyhow::bail!(
+            "--list-models needs a local provider, e.g. --provider local.{}",
+            config
+                .providers
+                .local
+                .keys()
+                .next()
+                .map(String::as_str)
+                .unwrap_or("default")
+        ),
+    };
+
+    let provider = g3_providers::LocalProvider::new_with_name(
+        config.providers.default_provider.clone(),
+        local_config.model.clone(),
+        local_config.base_url.clone(),
+        local_config.api_key.clone(),
+        local_config.max_tokens,
+        local_config.temperature,
+        local_config.native_tools.unwrap_or(true),
+    )?;
+    for model in provider.list_models().await? {
+        let marker = if model == local_config.model {
+            " (configured)"
+        } else {
+            ""
+        };
+        println!("{}{}", model, marker);
+    }
+    Ok(())
+}
+
 /// Run agent mode - loads a specialized agent prompt and executes a single task
 async fn run_agent_mode(
     agent_name: &str,
@@ -752,11 +1551,16 @@ async fn run_agent_mode(
 }
 
 /// Run flock mode - parallel multi-agent development
+#[allow(clippy::too_many_arguments)]
 async fn run_flock_mode(
     project_dir: PathBuf,
     flock_workspace: PathBuf,
     num_segments: usize,
     max_turns: usize,
+    budget: Option<f64>,
+    watchdog: Option<g3_ensembles::WatchdogConfig>,
+    barriers: Option<Vec<g3_ensembles::Barrier>>,
+    tui: bool,
 ) -> Result<()> {
     let output = SimpleOutput::new();
 
@@ -770,13 +1574,53 @@ async fn run_flock_mode(
     output.print("");
 
     // Create flock configuration
-    let config = g3_ensembles::FlockConfig::new(project_dir, flock_workspace, num_segments)?
-        .with_max_turns(max_turns);
+    let mut config =
+        g3_ensembles::FlockConfig::new(project_dir, flock_workspace.clone(), num_segments)?
+            .with_max_turns(max_turns);
+    if let Some(budget) = budget {
+        config = config.with_budget(budget);
+    }
+    if let Some(watchdog) = watchdog {
+        config = config.with_watchdog(watchdog);
+    }
+    if let Some(barriers) = barriers {
+        config = config.with_barriers(barriers);
+    }
+    if let Err(e) = config.validate() {
+        output.print(&format!("\n❌ Flock mode failed: {}", e));
+        return Ok(());
+    }
 
     // Create and run flock mode
     let mut flock = g3_ensembles::FlockMode::new(config)?;
 
-    match flock.run().await {
+    let result = if tui {
+        let (events_tx, events_rx) = tokio::sync::mpsc::unbounded_channel();
+        flock = flock.with_events(events_tx);
+        let inputs = flock.segment_inputs();
+        let approvals = flock.approvals();
+        let run = tokio::spawn(async move { flock.run().await });
+
+        tokio::task::spawn_blocking(move || {
+            flock_tui::run(events_rx, inputs, approvals, num_segments)
+        })
+        .await??;
+        if !run.is_finished() {
+            output.print("⏳ Waiting for the remaining segments to finish...");
+        }
+        let result = run.await?;
+
+        // The report went to the closed view; print it again
+        let status_file = flock_workspace.join("flock-status.json");
+        if let Ok(status) = g3_ensembles::FlockStatus::load_from_file(&status_file) {
+            output.print(&status.generate_report());
+        }
+        result
+    } else {
+        flock.run().await
+    };
+
+    match result {
         Ok(_) => output.print("\n✅ Flock mode completed successfully"),
         Err(e) => output.print(&format!("\n❌ Flock mode failed: {}", e)),
     }
@@ -784,6 +1628,44 @@ async fn run_flock_mode(
     Ok(())
 }
 
+/// `g3 flock plan`: validate a flock and show what running it would do
+fn print_flock_plan(
+    project_dir: PathBuf,
+    flock_workspace: PathBuf,
+    num_segments: Option<usize>,
+    max_turns: usize,
+    budget: Option<f64>,
+) -> Result<()> {
+    let segments = g3_ensembles::plan::load_segments(&project_dir)?;
+    let num_segments = match num_segments {
+        Some(num_segments) => num_segments,
+        None if !segments.is_empty() => segments.len(),
+        None => anyhow::bail!(
+            "--segments is required when the project has no {}",
+            g3_ensembles::plan::SEGMENTS_FILE
+        ),
+    };
+
+    let mut config = g3_ensembles::FlockConfig::new(project_dir, flock_workspace, num_segments)?
+        .with_max_turns(max_turns);
+    if let Some(budget) = budget {
+        config = config.with_budget(budget);
+    }
+
+    let validation = config.validate();
+    println!("{}", config.plan());
+    match validation {
+        Ok(()) => {
+            println!("✅ Flock configuration is valid");
+            Ok(())
+        }
+        Err(e) => {
+            println!("❌ {}", e);
+            exit(1);
+        }
+    }
+}
+
 /// Accumulative autonomous mode: accumulates requirements from user input
 /// and runs autonomous mode after each input
 async fn run_accumulative_mode(
@@ -848,7 +1730,7 @@ async fn run_accumulative_mode(
                 }
 
                 if input == "exit" || input == "quit" {
-                    output.print("\n👋 Goodbye!");
+                    output.print(&format!("\n{}", t(Msg::Goodbye)));
                     break;
                 }
 
@@ -857,11 +1739,9 @@ async fn run_accumulative_mode(
                     match input.as_str() {
                         "/help" => {
                             output.print("");
-                            output.print("📖 Available Commands:");
-                            output.print("  /requirements - Show all accumulated requirements");
-                            output.print("  /chat         - Switch to interactive chat mode");
-                            output.print("  /help         - Show this help message");
-                            output.print("  exit/quit     - Exit the session");
+                            for line in t(Msg::AccumulativeHelp).lines() {
+                                output.print(line);
+                            }
                             output.print("");
                             continue;
                         }
@@ -906,39 +1786,8 @@ async fn run_accumulative_mode(
                                     (None, existing) => existing,
                                 };
 
-                            // Load configuration
-                            let mut config = Config::load_with_overrides(
-                                cli.config.as_deref(),
-                                cli.provider.clone(),
-                                cli.model.clone(),
-                            )?;
-
-                            // Apply macax flag override
-                            if cli.macax {
-                                config.macax.enabled = true;
-                            }
-
-                            // Apply webdriver flag override
-                            if cli.webdriver {
-                                config.webdriver.enabled = true;
-                            }
-
-                            // Apply chrome-headless flag override
-                            if cli.chrome_headless {
-                                config.webdriver.enabled = true;
-                                config.webdriver.browser = g3_config::WebDriverBrowser::ChromeHeadless;
-                            }
-
-                            // Apply safari flag override
-                            if cli.safari {
-                                config.webdriver.enabled = true;
-                                config.webdriver.browser = g3_config::WebDriverBrowser::Safari;
-                            }
-
-                            // Apply no-auto-compact flag override
-                            if cli.manual_compact {
-                                config.agent.auto_compact = false;
-                            }
+                            // Load configuration with CLI overrides
+                            let config = config_loader::load_config(&cli)?;
 
                             // Create agent for interactive mode with requirements context
                             let ui_writer = ConsoleUiWriter::new();
@@ -961,14 +1810,11 @@ async fn run_accumulative_mode(
                             .await?;
 
                             // After returning from interactive mode, exit
-                            output.print("\n👋 Goodbye!");
+                            output.print(&format!("\n{}", t(Msg::Goodbye)));
                             break;
                         }
                         _ => {
-                            output.print(&format!(
-                                "❌ Unknown command: {}. Type /help for available commands.",
-                                input
-                            ));
+                            output.print(&tf(Msg::UnknownCommand, &[("command", &input)]));
                             continue;
                         }
                     }
@@ -1014,38 +1860,7 @@ async fn run_accumulative_mode(
                 project.enter_workspace()?;
 
                 // Load configuration with CLI overrides
-                let mut config = Config::load_with_overrides(
-                    cli.config.as_deref(),
-                    cli.provider.clone(),
-                    cli.model.clone(),
-                )?;
-
-                // Apply macax flag override
-                if cli.macax {
-                    config.macax.enabled = true;
-                }
-
-                // Apply webdriver flag override
-                if cli.webdriver {
-                    config.webdriver.enabled = true;
-                }
-
-                // Apply chrome-headless flag override
-                if cli.chrome_headless {
-                    config.webdriver.enabled = true;
-                    config.webdriver.browser = g3_config::WebDriverBrowser::ChromeHeadless;
-                }
-
-                // Apply safari flag override
-                if cli.safari {
-                    config.webdriver.enabled = true;
-                    config.webdriver.browser = g3_config::WebDriverBrowser::Safari;
-                }
-
-                // Apply no-auto-compact flag override
-                if cli.manual_compact {
-                    config.agent.auto_compact = false;
-                }
+                let config = config_loader::load_config(&cli)?;
 
                 // Create agent for this autonomous run
                 let ui_writer = ConsoleUiWriter::new();
@@ -1069,7 +1884,7 @@ async fn run_accumulative_mode(
                     cli.codebase_fast_start.clone(),
                     ) => result,
                     _ = tokio::signal::ctrl_c() => {
-                        output.print("\n⚠️  Autonomous run cancelled by user (Ctrl+C)");
+                        output.print(&format!("\n{} (Ctrl+C)", t(Msg::AutonomousRunCancelled)));
                         Ok(())
                     }
                 };
@@ -1087,11 +1902,11 @@ async fn run_accumulative_mode(
                 }
             }
             Err(ReadlineError::Interrupted) => {
-                output.print("\n👋 Interrupted. Goodbye!");
+                output.print(&format!("\n{}", t(Msg::InterruptedGoodbye)));
                 break;
             }
             Err(ReadlineError::Eof) => {
-                output.print("\n👋 Goodbye!");
+                output.print(&format!("\n{}", t(Msg::Goodbye)));
                 break;
             }
             Err(err) => {
@@ -1394,8 +2209,13 @@ async fn run_interactive<W: UiWriter>(
 ) -> Result<()> {
     let output = SimpleOutput::new();
 
-    // Check for session continuation
-    if let Ok(Some(continuation)) = g3_core::load_continuation() {
+    // Check for session continuation, unless a session was already resumed with --resume
+    let continuation = if agent.get_session_id().is_none() {
+        g3_core::load_continuation()
+    } else {
+        Ok(None)
+    };
+    if let Ok(Some(continuation)) = continuation {
         output.print("");
         output.print("🔄 Previous session detected!");
         output.print(&format!(
@@ -1518,6 +2338,8 @@ async fn run_interactive<W: UiWriter>(
     // Track multiline input
     let mut multiline_buffer = String::new();
     let mut in_multiline = false;
+    // What `/copy` copies
+    let mut last_response: Option<String> = None;
 
     loop {
         // Display context window progress bar before each prompt
@@ -1560,7 +2382,11 @@ async fn run_interactive<W: UiWriter>(
                     }
 
                     // Process the multiline input
-                    execute_task(&mut agent, &input, show_prompt, show_code, &output).await;
+                    if let Some(response) =
+                        execute_task(&mut agent, &input, show_prompt, show_code, &output).await
+                    {
+                        last_response = Some(response);
+                    }
                 } else {
                     // Single line input
                     let input = line.trim().to_string();
@@ -1576,22 +2402,35 @@ async fn run_interactive<W: UiWriter>(
                     // Add to history
                     rl.add_history_entry(&input)?;
 
+                    if let Some(target) = clipboard::parse_copy_command(&input) {
+                        match target
+                            .and_then(|target| copy_from_response(last_response.as_deref(), target))
+                        {
+                            Ok(done) => output.print(&done),
+                            Err(e) => output.print(&format!("❌ {}", e)),
+                        }
+                        continue;
+                    }
+
+                    if let Some(count) = parse_undo_command(&input) {
+                        match count {
+                            Ok(count) => match agent.undo_edits(count) {
+                                Ok(report) => output.print(report.to_string().trim_end()),
+                                Err(e) => output.print(&format!("❌ Error undoing edits: {}", e)),
+                            },
+                            Err(usage) => output.print(&format!("❌ {}", usage)),
+                        }
+                        continue;
+                    }
+
                     // Check for control commands
                     if input.starts_with('/') {
                         match input.as_str() {
                             "/help" => {
                                 output.print("");
-                                output.print("📖 Control Commands:");
-                                output.print("  /compact   - Trigger auto-summarization (compacts conversation history)");
-                                output.print("  /thinnify  - Trigger context thinning (replaces large tool results with file references)");
-                                output.print("  /skinnify  - Trigger full context thinning (like /thinnify but for entire context, not just first third)");
-                                output.print("  /clear     - Clear session and start fresh (discards continuation artifacts)");
-                                output.print(
-                                    "  /readme    - Reload README.md and AGENTS.md from disk",
-                                );
-                                output.print("  /stats     - Show detailed context and performance statistics");
-                                output.print("  /help      - Show this help message");
-                                output.print("  exit/quit  - Exit the interactive session");
+                                for line in commands::chat_help().lines() {
+                                    output.print(line);
+                                }
                                 output.print("");
                                 continue;
                             }
@@ -1648,18 +2487,82 @@ async fn run_interactive<W: UiWriter>(
                                 output.print(&stats);
                                 continue;
                             }
+                            command if command == "/model" || command.starts_with("/model ") => {
+                                match command.split_whitespace().nth(1) {
+                                    Some(provider_ref) => {
+                                        match agent.switch_provider(provider_ref).await {
+                                            Ok(warnings) => {
+                                                agent.print_provider_banner("Switched to");
+                                                for warning in warnings {
+                                                    output.print(&format!("⚠️ {}", warning));
+                                                }
+                                            }
+                                            Err(e) => output.print(&format!("❌ {}", e)),
+                                        }
+                                    }
+                                    None => agent.print_provider_banner("Current"),
+                                }
+                                continue;
+                            }
+                            command if parse_logs_command(command).is_some() => {
+                                match parse_logs_command(command).unwrap() {
+                                    Ok(level) => {
+                                        let events = log_pane::recent(level, log_pane::CHAT_LIMIT);
+                                        if events.is_empty() {
+                                            output.print(&format!(
+                                                "📜 No events at {} or above recorded yet",
+                                                level
+                                            ));
+                                        }
+                                        for event in events {
+                                            output.print(&event.to_string());
+                                        }
+                                    }
+                                    Err(usage) => output.print(&format!("❌ {}", usage)),
+                                }
+                                continue;
+                            }
+                            command if parse_context_command(command).is_some() => {
+                                match parse_context_command(command).unwrap() {
+                                    Ok(None) => output.print(agent.context_breakdown().trim_end()),
+                                    Ok(Some((action, selector))) => {
+                                        match agent.edit_context(action, &selector) {
+                                            Ok(done) => output.print(&done),
+                                            Err(e) => output.print(&format!("❌ {}", e)),
+                                        }
+                                    }
+                                    Err(usage) => output.print(&format!("❌ {}", usage)),
+                                }
+                                continue;
+                            }
+                            command if parse_branch_command(command).is_some() => {
+                                match parse_branch_command(command).unwrap() {
+                                    Ok(None) => output.print(
+                                        branches::render(&agent.branch_summaries()).trim_end(),
+                                    ),
+                                    Ok(Some((action, name))) => {
+                                        match agent.edit_branches(action, &name) {
+                                            Ok(done) => output.print(&done),
+                                            Err(e) => output.print(&format!("❌ {}", e)),
+                                        }
+                                    }
+                                    Err(usage) => output.print(&format!("❌ {}", usage)),
+                                }
+                                continue;
+                            }
                             _ => {
-                                output.print(&format!(
-                                    "❌ Unknown command: {}. Type /help for available commands.",
-                                    input
-                                ));
+                                output.print(&tf(Msg::UnknownCommand, &[("command", &input)]));
                                 continue;
                             }
                         }
                     }
 
                     // Process the single line input
-                    execute_task(&mut agent, &input, show_prompt, show_code, &output).await;
+                    if let Some(response) =
+                        execute_task(&mut agent, &input, show_prompt, show_code, &output).await
+                    {
+                        last_response = Some(response);
+                    }
                 }
             }
             Err(ReadlineError::Interrupted) => {
@@ -1690,43 +2593,98 @@ async fn run_interactive<W: UiWriter>(
         let _ = rl.save_history(history_path);
     }
 
-    output.print("👋 Goodbye!");
+    output.print(t(Msg::Goodbye));
     Ok(())
 }
 
+/// Run one task, cancelling it on Ctrl+C. The task keeps running after the
+/// interrupt until it has aborted its provider stream and recorded the
+/// partial response, so control returns to the prompt with a clean context.
+async fn execute_until_interrupted<W: UiWriter>(
+    agent: &mut Agent<W>,
+    input: &str,
+    show_prompt: bool,
+    show_code: bool,
+    cancellation_token: &CancellationToken,
+) -> Result<g3_core::TaskResult> {
+    let task = agent.execute_task_with_timing_cancellable(
+        input,
+        None,
+        false,
+        show_prompt,
+        show_code,
+        true,
+        cancellation_token.clone(),
+        None,
+    );
+    tokio::pin!(task);
+    tokio::select! {
+        result = &mut task => result,
+        _ = tokio::signal::ctrl_c() => {
+            cancellation_token.cancel();
+            task.await
+        }
+    }
+}
+
+/// Copy `target` of the last response to the clipboard (`/copy` in chat
+/// mode), returning the confirmation to print
+fn copy_from_response(
+    last_response: Option<&str>,
+    target: clipboard::CopyTarget,
+) -> Result<String, String> {
+    let response = last_response.ok_or("No response to copy yet")?;
+    let text = match target {
+        clipboard::CopyTarget::Last => response.to_string(),
+        clipboard::CopyTarget::Code => {
+            clipboard::last_code_block(response).ok_or("No code block in the last response")?
+        }
+        clipboard::CopyTarget::Selection => {
+            return Err(
+                "Chat mode has no selection to copy; select text in the terminal instead"
+                    .to_string(),
+            )
+        }
+    };
+    let method = clipboard::copy(&text).map_err(|e| e.to_string())?;
+    Ok(clipboard::copied_message(&text, method))
+}
+
+/// Run `input` as a task, printing the response; returns the response if
+/// the task succeeded
 async fn execute_task<W: UiWriter>(
     agent: &mut Agent<W>,
     input: &str,
     show_prompt: bool,
     show_code: bool,
     output: &SimpleOutput,
-) {
+) -> Option<String> {
     const MAX_TIMEOUT_RETRIES: u32 = 3;
     let mut attempt = 0;
+    let started = Instant::now();
     // Show thinking indicator immediately
     output.print("🤔 Thinking...");
     // Note: flush is handled internally by println
 
     // Create cancellation token for this request
     let cancellation_token = CancellationToken::new();
-    let cancel_token_clone = cancellation_token.clone();
 
     loop {
         attempt += 1;
 
         // Execute task with cancellation support
-        let execution_result = tokio::select! {
-            result = agent.execute_task_with_timing_cancellable(
-                input, None, false, show_prompt, show_code, true, cancellation_token.clone(), None
-            ) => {
-                result
-            }
-            _ = tokio::signal::ctrl_c() => {
-                cancel_token_clone.cancel();
-                output.print("\n⚠️  Operation cancelled by user (Ctrl+C)");
-                return;
-            }
-        };
+        let execution_result = execute_until_interrupted(
+            agent,
+            input,
+            show_prompt,
+            show_code,
+            &cancellation_token,
+        )
+        .await;
+        if cancellation_token.is_cancelled() {
+            output.print(&format!("\n{} (Ctrl+C)", t(Msg::OperationCancelled)));
+            return None;
+        }
 
         match execution_result {
             Ok(result) => {
@@ -1734,12 +2692,13 @@ async fn execute_task<W: UiWriter>(
                     output.print(&format!("✅ Request succeeded after {} attempts", attempt));
                 }
                 output.print_smart(&result.response);
-                return;
+                notify_task_finished(agent.get_config(), started, true);
+                return Some(result.response);
             }
             Err(e) => {
                 if e.to_string().contains("cancelled") {
-                    output.print("⚠️  Operation cancelled by user");
-                    return;
+                    output.print(t(Msg::OperationCancelled));
+                    return None;
                 }
 
                 // Check if this is a timeout error that we should retry
@@ -1766,12 +2725,377 @@ async fn execute_task<W: UiWriter>(
 
                 // For non-timeout errors or after max retries, handle as before
                 handle_execution_error(&e, input, output, attempt);
-                return;
+                notify_task_finished(agent.get_config(), started, false);
+                return None;
             }
         }
     }
 }
 
+/// Announce the end of a task that ran long enough for the user to have
+/// looked away
+fn notify_task_finished(config: &Config, started: Instant, success: bool) {
+    Notifier::new(config.notifications.clone()).notify(&Notification::TaskFinished {
+        elapsed: started.elapsed(),
+        success,
+    });
+}
+
+/// Interactive mode in the full-screen retro TUI (`--retro`)
+async fn run_interactive_retro(
+    mut agent: Agent<RetroTuiWriter>,
+    tui: RetroTui,
+    show_prompt: bool,
+    show_code: bool,
+) -> Result<()> {
+    if let Ok((provider, model)) = agent.get_provider_info() {
+        tui.update_provider_info(&provider, &model);
+    }
+    tui.set_palette_providers(agent.get_config().provider_references());
+    update_retro_context(&agent, &tui);
+
+    let mut events = tui.spawn_input();
+    while let Some(event) = events.recv().await {
+        let key = match event {
+            Event::Key(key) => key,
+            Event::Mouse(mouse) => {
+                tui.mouse_event(mouse);
+                continue;
+            }
+            _ => continue,
+        };
+        if key.kind != KeyEventKind::Press {
+            continue;
+        }
+        let ctrl = key.modifiers.contains(KeyModifiers::CONTROL);
+        if tui.has_open_palette() && !ctrl {
+            if let Some(command) = tui.palette_key(key.code) {
+                run_retro_palette_command(&mut agent, &tui, command).await;
+            }
+            continue;
+        }
+        if tui.has_open_branches() && !ctrl {
+            if let Some((action, name)) = tui.branches_key(key.code) {
+                let reply = agent
+                    .edit_branches(action, &name)
+                    .unwrap_or_else(|e| format!("❌ {}", e));
+                tui.output(&format!("{}\n", reply.trim_end()));
+                update_retro_context(&agent, &tui);
+            }
+            continue;
+        }
+        if retro_scroll_key(&tui, &key) {
+            continue;
+        }
+        match key.code {
+            KeyCode::Esc if tui.has_open_help() => tui.toggle_help(),
+            KeyCode::Char('c') | KeyCode::Char('d') if ctrl => break,
+            KeyCode::Char('p') if ctrl => tui.open_palette(),
+            KeyCode::Char('l') if ctrl => tui.toggle_log_pane(),
+            KeyCode::Char('o') if ctrl => tui.follow_link(),
+            KeyCode::Char('w') if ctrl => tui.delete_word(),
+            KeyCode::Char('k') if ctrl => tui.delete_to_end(),
+            KeyCode::Char('a') if ctrl => tui.cursor_home(),
+            KeyCode::Char('e') if ctrl => tui.cursor_end(),
+            KeyCode::Char(c) if !ctrl => tui.insert_char(c),
+            KeyCode::Backspace => tui.backspace(),
+            KeyCode::Delete => tui.delete_char(),
+            KeyCode::Left => tui.cursor_left(),
+            KeyCode::Right => tui.cursor_right(),
+            KeyCode::Home => tui.cursor_home(),
+            KeyCode::End => tui.cursor_end(),
+            KeyCode::Enter => {
+                let (input, _) = tui.get_input_state();
+                let input = input.trim().to_string();
+                tui.update_input("");
+                if input.is_empty() {
+                    continue;
+                }
+                if input == "exit" || input == "quit" {
+                    break;
+                }
+                if RetroTui::is_help_command(&input) || input == "/help" {
+                    tui.toggle_help();
+                    continue;
+                }
+                if tui.log_command(&input) || tui.copy_command(&input) {
+                    continue;
+                }
+                if RetroTui::is_branches_command(&input) {
+                    tui.open_branches(agent.branch_summaries());
+                    continue;
+                }
+                tui.output(&format!("\ng3> {}\n", input));
+                if let Some(undo) = RetroTui::undo_command(&input) {
+                    run_retro_command(&mut agent, &tui, &undo).await;
+                } else if input.starts_with('/') {
+                    run_retro_command(&mut agent, &tui, &input).await;
+                } else {
+                    run_retro_task(
+                        &mut agent,
+                        &tui,
+                        &mut events,
+                        &input,
+                        show_prompt,
+                        show_code,
+                    )
+                    .await;
+                }
+            }
+            _ => {}
+        }
+    }
+
+    tui.exit();
+    Ok(())
+}
+
+/// Run one of the chat mode's slash commands in the retro TUI, showing its
+/// result in the output area
+async fn run_retro_command(agent: &mut Agent<RetroTuiWriter>, tui: &RetroTui, input: &str) {
+    let reply = match input {
+        "/compact" => {
+            tui.output("🗜️ Triggering manual summarization...\n");
+            match agent.force_summarize().await {
+                Ok(true) => "✅ Summarization completed successfully".to_string(),
+                Ok(false) => "⚠️ Summarization failed".to_string(),
+                Err(e) => format!("❌ Error during summarization: {}", e),
+            }
+        }
+        "/thinnify" => agent.force_thin(),
+        "/skinnify" => agent.force_thin_all(),
+        "/clear" => {
+            agent.clear_session();
+            "✅ Session cleared. Starting fresh.".to_string()
+        }
+        "/readme" => match agent.reload_readme() {
+            Ok(true) => "✅ README content reloaded successfully".to_string(),
+            Ok(false) => "⚠️ No README was loaded at startup, cannot reload".to_string(),
+            Err(e) => format!("❌ Error reloading README: {}", e),
+        },
+        "/stats" => agent.get_stats(),
+        command if parse_undo_command(command).is_some() => {
+            match parse_undo_command(command).unwrap() {
+                Ok(count) => match agent.undo_edits(count) {
+                    Ok(report) => report.to_string(),
+                    Err(e) => format!("❌ Error undoing edits: {}", e),
+                },
+                Err(usage) => format!("❌ {}", usage),
+            }
+        }
+        command if command == "/model" || command.starts_with("/model ") => {
+            match command.split_whitespace().nth(1) {
+                Some(provider_ref) => match agent.switch_provider(provider_ref).await {
+                    Ok(warnings) => {
+                        agent.print_provider_banner("Switched to");
+                        if let Ok((provider, model)) = agent.get_provider_info() {
+                            tui.update_provider_info(&provider, &model);
+                        }
+                        warnings
+                            .iter()
+                            .map(|warning| format!("⚠️ {}\n", warning))
+                            .collect()
+                    }
+                    Err(e) => format!("❌ {}", e),
+                },
+                None => {
+                    agent.print_provider_banner("Current");
+                    String::new()
+                }
+            }
+        }
+        command if parse_context_command(command).is_some() => {
+            match parse_context_command(command).unwrap() {
+                Ok(None) => agent.context_breakdown(),
+                Ok(Some((action, selector))) => agent
+                    .edit_context(action, &selector)
+                    .unwrap_or_else(|e| format!("❌ {}", e)),
+                Err(usage) => format!("❌ {}", usage),
+            }
+        }
+        command if parse_branch_command(command).is_some() => {
+            match parse_branch_command(command).unwrap() {
+                Ok(None) => branches::render(&agent.branch_summaries()),
+                Ok(Some((action, name))) => agent
+                    .edit_branches(action, &name)
+                    .unwrap_or_else(|e| format!("❌ {}", e)),
+                Err(usage) => format!("❌ {}", usage),
+            }
+        }
+        _ => tf(Msg::UnknownCommand, &[("command", input)]),
+    };
+    if !reply.is_empty() {
+        tui.output(&format!("{}\n", reply.trim_end()));
+    }
+    update_retro_context(agent, tui);
+}
+
+/// Run a command chosen in the retro TUI's command palette. Switching the
+/// theme and toggling panels were already done by the palette; the agent
+/// commands run like the slash commands their key hints name.
+async fn run_retro_palette_command(
+    agent: &mut Agent<RetroTuiWriter>,
+    tui: &RetroTui,
+    command: command_palette::PaletteCommand,
+) {
+    use command_palette::PaletteCommand;
+
+    let input = match command {
+        PaletteCommand::ShowUsage => "/stats".to_string(),
+        PaletteCommand::ShowContext => "/context".to_string(),
+        PaletteCommand::Compact => "/compact".to_string(),
+        PaletteCommand::Undo => "/undo".to_string(),
+        PaletteCommand::Clear => "/clear".to_string(),
+        PaletteCommand::SwitchProvider(provider_ref) => format!("/model {}", provider_ref),
+        PaletteCommand::ExportTranscript => {
+            let reply = match export_retro_transcript(agent) {
+                Ok(path) => format!("✅ Exported this session to {}", path.display()),
+                Err(e) => format!("❌ {}", e),
+            };
+            tui.output(&format!("{}\n", reply));
+            return;
+        }
+        // Nothing is running to pause
+        PaletteCommand::PauseAgent => return,
+        PaletteCommand::SwitchTheme(_)
+        | PaletteCommand::ToggleActivityPanel
+        | PaletteCommand::ToggleLogPane => return,
+    };
+    tui.output(&format!("\ng3> {}\n", input));
+    run_retro_command(agent, tui, &input).await;
+}
+
+/// Write the running session as Markdown to `<session id>.md` in the
+/// working directory, as `g3 export` would
+fn export_retro_transcript(agent: &Agent<RetroTuiWriter>) -> Result<PathBuf> {
+    let session_id = agent
+        .get_session_id()
+        .ok_or_else(|| anyhow::anyhow!("No session to export"))?;
+    let format = g3_core::transcript_export::ExportFormat::Markdown;
+    let transcript = g3_core::transcript_export::export_session(session_id, format)?;
+    let path = PathBuf::from(format!("{}.{}", session_id, format.extension()));
+    std::fs::write(&path, transcript)
+        .map_err(|e| anyhow::anyhow!("Failed to write {}: {}", path.display(), e))?;
+    Ok(path)
+}
+
+/// Scroll the retro TUI's output for the scrolling keys; returns whether
+/// `key` was one
+fn retro_scroll_key(tui: &RetroTui, key: &KeyEvent) -> bool {
+    let ctrl = key.modifiers.contains(KeyModifiers::CONTROL);
+    match key.code {
+        KeyCode::Up => tui.scroll_up(),
+        KeyCode::Down => tui.scroll_down(),
+        KeyCode::PageUp => tui.scroll_page_up(),
+        KeyCode::PageDown => tui.scroll_page_down(),
+        KeyCode::Home if ctrl => tui.scroll_home(),
+        KeyCode::End if ctrl => tui.scroll_end(),
+        _ => return false,
+    }
+    true
+}
+
+/// Run `input` as a task in the retro TUI; returns the response if the task
+/// succeeded. Keys keep arriving while it runs: Ctrl+C cancels it, the
+/// output can be scrolled or selected, Ctrl+L shows the log pane, and the
+/// command palette opens with Ctrl+P, though only its theme, panel and pause
+/// commands run until the task is done.
+async fn run_retro_task(
+    agent: &mut Agent<RetroTuiWriter>,
+    tui: &RetroTui,
+    events: &mut tokio::sync::mpsc::UnboundedReceiver<Event>,
+    input: &str,
+    show_prompt: bool,
+    show_code: bool,
+) -> Option<String> {
+    use command_palette::PaletteCommand;
+
+    let started = Instant::now();
+    let cancellation_token = CancellationToken::new();
+    tui.status("PROCESSING");
+
+    let result = {
+        let task = agent.execute_task_with_timing_cancellable(
+            input,
+            None,
+            false,
+            show_prompt,
+            show_code,
+            true,
+            cancellation_token.clone(),
+            None,
+        );
+        tokio::pin!(task);
+        loop {
+            tokio::select! {
+                result = &mut task => break result,
+                Some(event) = events.recv() => {
+                    let key = match event {
+                        Event::Key(key) => key,
+                        Event::Mouse(mouse) => {
+                            tui.mouse_event(mouse);
+                            continue;
+                        }
+                        _ => continue,
+                    };
+                    if key.kind != KeyEventKind::Press {
+                        continue;
+                    }
+                    let ctrl = key.modifiers.contains(KeyModifiers::CONTROL);
+                    if tui.has_open_palette() && !ctrl {
+                        match tui.palette_key(key.code) {
+                            Some(PaletteCommand::PauseAgent) => cancellation_token.cancel(),
+                            Some(
+                                PaletteCommand::SwitchTheme(_)
+                                | PaletteCommand::ToggleActivityPanel
+                                | PaletteCommand::ToggleLogPane,
+                            )
+                            | None => {}
+                            Some(_) => tui.output(&format!("\n{}\n", t(Msg::PaletteBusy))),
+                        }
+                        continue;
+                    }
+                    if retro_scroll_key(tui, &key) {
+                        continue;
+                    }
+                    match key.code {
+                        KeyCode::Char('c') if ctrl => cancellation_token.cancel(),
+                        KeyCode::Char('p') if ctrl => tui.open_palette(),
+                        KeyCode::Char('l') if ctrl => tui.toggle_log_pane(),
+                        _ => {}
+                    }
+                }
+            }
+        }
+    };
+
+    tui.output("\n");
+    tui.status("READY");
+    update_retro_context(agent, tui);
+    if cancellation_token.is_cancelled() {
+        tui.output(&format!("{} (Ctrl+C)\n", t(Msg::OperationCancelled)));
+        return None;
+    }
+    notify_task_finished(agent.get_config(), started, result.is_ok());
+    match result {
+        Ok(result) => Some(result.response),
+        Err(e) => {
+            tui.error(&e.to_string());
+            None
+        }
+    }
+}
+
+/// Show the agent's context window use in the retro TUI's status bar
+fn update_retro_context(agent: &Agent<RetroTuiWriter>, tui: &RetroTui) {
+    let context = agent.get_context_window();
+    tui.update_context(
+        context.used_tokens,
+        context.total_tokens,
+        context.percentage_used(),
+    );
+}
+
 async fn run_interactive_machine(
     mut agent: Agent<MachineUiWriter>,
     show_prompt: bool,
@@ -1820,6 +3144,26 @@ async fn run_interactive_machine(
                 // Add to history
                 rl.add_history_entry(&input)?;
 
+                if let Some(count) = parse_undo_command(&input) {
+                    println!("COMMAND: undo");
+                    match count {
+                        Ok(count) => match agent.undo_edits(count) {
+                            Ok(report) => {
+                                for edit in &report.undone {
+                                    println!("UNDONE: {}", edit.path.display());
+                                }
+                                if let Some(edit) = &report.blocked_by {
+                                    println!("BLOCKED: {}", edit.path.display());
+                                }
+                                println!("RESULT: {} edits undone", report.undone.len());
+                            }
+                            Err(e) => println!("ERROR: {}", e),
+                        },
+                        Err(usage) => println!("ERROR: {}", usage),
+                    }
+                    continue;
+                }
+
                 // Check for control commands
                 if input.starts_with('/') {
                     match input.as_str() {
@@ -1872,7 +3216,70 @@ async fn run_interactive_machine(
                         }
                         "/help" => {
                             println!("COMMAND: help");
-                            println!("AVAILABLE_COMMANDS: /compact /thinnify /skinnify /clear /readme /stats /help");
+                            println!("AVAILABLE_COMMANDS: {}", commands::command_names());
+                            continue;
+                        }
+                        command if command == "/model" || command.starts_with("/model ") => {
+                            println!("COMMAND: model");
+                            if let Some(provider_ref) = command.split_whitespace().nth(1) {
+                                match agent.switch_provider(provider_ref).await {
+                                    Ok(warnings) => {
+                                        for warning in warnings {
+                                            println!("WARNING: {}", warning);
+                                        }
+                                    }
+                                    Err(e) => println!("ERROR: {}", e),
+                                }
+                            }
+                            match agent.get_provider_info() {
+                                Ok((provider, model)) => {
+                                    println!("PROVIDER: {}", provider);
+                                    println!("MODEL: {}", model);
+                                }
+                                Err(e) => println!("ERROR: {}", e),
+                            }
+                            continue;
+                        }
+                        command if parse_logs_command(command).is_some() => {
+                            println!("COMMAND: logs");
+                            match parse_logs_command(command).unwrap() {
+                                Ok(level) => {
+                                    for event in log_pane::recent(level, log_pane::CHAT_LIMIT) {
+                                        println!("LOG: {}", event);
+                                    }
+                                }
+                                Err(usage) => println!("ERROR: {}", usage),
+                            }
+                            continue;
+                        }
+                        command if parse_context_command(command).is_some() => {
+                            println!("COMMAND: context");
+                            match parse_context_command(command).unwrap() {
+                                Ok(None) => print!("{}", agent.context_breakdown()),
+                                Ok(Some((action, selector))) => {
+                                    match agent.edit_context(action, &selector) {
+                                        Ok(done) => println!("RESULT: {}", done),
+                                        Err(e) => println!("ERROR: {}", e),
+                                    }
+                                }
+                                Err(usage) => println!("ERROR: {}", usage),
+                            }
+                            continue;
+                        }
+                        command if parse_branch_command(command).is_some() => {
+                            println!("COMMAND: branch");
+                            match parse_branch_command(command).unwrap() {
+                                Ok(None) => {
+                                    print!("{}", branches::render(&agent.branch_summaries()))
+                                }
+                                Ok(Some((action, name))) => {
+                                    match agent.edit_branches(action, &name) {
+                                        Ok(done) => println!("RESULT: {}", done),
+                                        Err(e) => println!("ERROR: {}", e),
+                                    }
+                                }
+                                Err(usage) => println!("ERROR: {}", usage),
+                            }
                             continue;
                         }
                         _ => {
@@ -1916,24 +3323,23 @@ async fn execute_task_machine(
 
     // Create cancellation token for this request
     let cancellation_token = CancellationToken::new();
-    let cancel_token_clone = cancellation_token.clone();
 
     loop {
         attempt += 1;
 
         // Execute task with cancellation support
-        let execution_result = tokio::select! {
-            result = agent.execute_task_with_timing_cancellable(
-                input, None, false, show_prompt, show_code, true, cancellation_token.clone(), None
-            ) => {
-                result
-            }
-            _ = tokio::signal::ctrl_c() => {
-                cancel_token_clone.cancel();
-                println!("CANCELLED");
-                return;
-            }
-        };
+        let execution_result = execute_until_interrupted(
+            agent,
+            input,
+            show_prompt,
+            show_code,
+            &cancellation_token,
+        )
+        .await;
+        if cancellation_token.is_cancelled() {
+            println!("CANCELLED");
+            return;
+        }
 
         match execution_result {
             Ok(result) => {
@@ -1975,6 +3381,14 @@ async fn execute_task_machine(
 
                 // For non-timeout errors or after max retries
                 println!("ERROR: {}", e);
+                if let Some(error) = G3Error::find(&e) {
+                    println!(
+                        "ERROR_CATEGORY: {} retryable={}",
+                        error.category(),
+                        error.is_retryable()
+                    );
+                    println!("HINT: {}", error.hint());
+                }
                 if attempt > 1 {
                     println!("FAILED_AFTER_RETRIES: {}", attempt);
                 }
@@ -2006,10 +3420,14 @@ fn handle_execution_error(e: &anyhow::Error, input: &str, output: &SimpleOutput,
     error!("Error type: {}", std::any::type_name_of_val(&e));
 
     // Display user-friendly error message
-    output.print(&format!("❌ Error: {}", e));
+    output.print(&tf(Msg::TaskError, &[("error", &e.to_string())]));
 
-    // If it's a stream error, provide helpful guidance
-    if e.to_string().contains("No response received") || e.to_string().contains("timed out") {
+    // Tell the user what they can do about it; untyped stream errors get
+    // general guidance
+    if let Some(error) = G3Error::find(e) {
+        output.print(t(i18n::error_hint(error.category())));
+    } else if e.to_string().contains("No response received") || e.to_string().contains("timed out")
+    {
         output.print("💡 This may be a temporary issue. Please try again or check the logs for more details.");
         output.print("   Log files are saved in the '.g3/sessions/' directory.");
     }
@@ -2503,6 +3921,8 @@ async fn run_autonomous(
 
         // Surface provider info for coach agent
         coach_agent.print_provider_banner("Coach");
+        // The coach prompt carries the requirements, so keep it through context retention
+        coach_agent.pin_next_task();
 
         // Ensure coach agent is also in the workspace directory
         project.enter_workspace()?;
@@ -2812,6 +4232,11 @@ Remember: Be clear in your review and concise in your feedback. APPROVE iff the
     output.print(&generate_turn_histogram(&turn_metrics));
     output.print(&"=".repeat(60));
 
+    // Flock workers run quietly; their failures are announced by the flock
+    if !quiet {
+        notify_task_finished(agent.get_config(), start_time, implementation_approved);
+    }
+
     if implementation_approved {
         output.print(&format!(
             "\n🎉 Autonomous mode completed successfully (total loop time: {})",
@@ -0,0 +1,178 @@
+//! Clickable file references in agent and tool output.
+//!
+//! A `path/to/file.rs:42` (or `:42:7`) in the output is wrapped in an OSC 8
+//! escape sequence, which terminals that support it (iTerm2, WezTerm,
+//! kitty, GNOME Terminal, Windows Terminal...) draw as a link. The URL comes
+//! from the `[ui] link_url` template, so an editor's URL scheme
+//! (`vscode://file{path}:{line}`) opens the file at the line; an empty
+//! template turns links off. Only references to files that exist are
+//! linked, which keeps version numbers and URLs from turning into links.
+
+use g3_config::DEFAULT_LINK_URL;
+use regex::Regex;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use std::sync::{OnceLock, RwLock};
+
+static URL_TEMPLATE: RwLock<Option<String>> = RwLock::new(None);
+
+/// Link file references with `template` from now on, per `[ui] link_url`
+pub fn set_url_template(template: String) {
+    *URL_TEMPLATE.write().unwrap_or_else(|e| e.into_inner()) = Some(template);
+}
+
+fn url_template() -> String {
+    URL_TEMPLATE
+        .read()
+        .unwrap_or_else(|e| e.into_inner())
+        .clone()
+        .unwrap_or_else(|| DEFAULT_LINK_URL.to_string())
+}
+
+/// A reference to a line of a file, as found in some text
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FileRef {
+    /// Where the reference is in the text, in bytes
+    pub range: Range<usize>,
+    /// The file, absolute
+    pub path: PathBuf,
+    pub line: u32,
+    pub column: Option<u32>,
+}
+
+fn pattern() -> &'static Regex {
+    static PATTERN: OnceLock<Regex> = OnceLock::new();
+    PATTERN.get_or_init(|| {
+        Regex::new(r"([\w.~/-]*\.[A-Za-z0-9]+):(\d+)(?::(\d+))?").expect("valid file reference")
+    })
+}
+
+/// The references in `text` to files that exist, relative paths resolved
+/// against `base`
+pub fn find_file_refs(text: &str, base: &Path) -> Vec<FileRef> {
+    pattern()
+        .captures_iter(text)
+        .filter_map(|captures| {
+            let path = &captures[1];
+            let path = match path.strip_prefix("~/") {
+                Some(rest) => dirs::home_dir()?.join(rest),
+                None => base.join(path),
+            };
+            if !path.is_file() {
+                return None;
+            }
+            Some(FileRef {
+                range: captures.get(0)?.range(),
+                path,
+                line: captures[2].parse().ok()?,
+                column: captures.get(3).and_then(|c| c.as_str().parse().ok()),
+            })
+        })
+        .collect()
+}
+
+/// `template` filled in for `file_ref`
+pub fn url(template: &str, file_ref: &FileRef) -> String {
+    let path = file_ref
+        .path
+        .to_string_lossy()
+        .replace('%', "%25")
+        .replace(' ', "%20")
+        .replace('#', "%23")
+        .replace('?', "%3F");
+    template
+        .replace("{path}", &path)
+        .replace("{line}", &file_ref.line.to_string())
+        .replace("{column}", &file_ref.column.unwrap_or(1).to_string())
+}
+
+/// `text` as an OSC 8 link to `url`
+pub fn hyperlink(url: &str, text: &str) -> String {
+    format!("\x1b]8;;{}\x1b\\{}\x1b]8;;\x1b\\", url, text)
+}
+
+/// The URL file references link to, or `None` if links are off
+pub fn link_template() -> Option<String> {
+    Some(url_template()).filter(|template| !template.is_empty())
+}
+
+/// `text` with its file references turned into links
+pub fn linkify(text: &str) -> String {
+    link_refs(text, false)
+}
+
+/// A chunk of streamed `text` with its file references turned into links,
+/// except ones at the very start or end, which may be part of a longer
+/// reference split between chunks
+pub fn linkify_chunk(text: &str) -> String {
+    link_refs(text, true)
+}
+
+fn link_refs(text: &str, streamed: bool) -> String {
+    let Some(template) = link_template() else {
+        return text.to_string();
+    };
+    let Ok(base) = std::env::current_dir() else {
+        return text.to_string();
+    };
+    let mut linked = String::with_capacity(text.len());
+    let mut end = 0;
+    for file_ref in find_file_refs(text, &base) {
+        if streamed && (file_ref.range.start == 0 || file_ref.range.end == text.len()) {
+            continue;
+        }
+        linked.push_str(&text[end..file_ref.range.start]);
+        linked.push_str(&hyperlink(
+            &url(&template, &file_ref),
+            &text[file_ref.range.clone()],
+        ));
+        end = file_ref.range.end;
+    }
+    linked.push_str(&text[end..]);
+    linked
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn base() -> PathBuf {
+        PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+    }
+
+    #[test]
+    fn test_find_file_refs() {
+        let text =
+            "error at src/links.rs:42:7, see (Cargo.toml:3) and v1.2:3 or https://x.io/a.rs:1";
+        let refs = find_file_refs(text, &base());
+        assert_eq!(refs.len(), 2);
+        assert_eq!(&text[refs[0].range.clone()], "src/links.rs:42:7");
+        assert_eq!(refs[0].path, base().join("src/links.rs"));
+        assert_eq!((refs[0].line, refs[0].column), (42, Some(7)));
+        assert_eq!(&text[refs[1].range.clone()], "Cargo.toml:3");
+        assert_eq!(refs[1].column, None);
+        assert!(find_file_refs("src/missing.rs:1", &base()).is_empty());
+    }
+
+    #[test]
+    fn test_url() {
+        let file_ref = FileRef {
+            range: 0..0,
+            path: PathBuf::from("/home/me/my project/lib.rs"),
+            line: 42,
+            column: None,
+        };
+        assert_eq!(
+            url(DEFAULT_LINK_URL, &file_ref),
+            "file:///home/me/my%20project/lib.rs"
+        );
+        assert_eq!(
+            url("vscode://file{path}:{line}:{column}", &file_ref),
+            "vscode://file/home/me/my%20project/lib.rs:42:1"
+        );
+        assert_eq!(
+            hyperlink("file:///a.rs", "a.rs:1"),
+            "\x1b]8;;file:///a.rs\x1b\\a.rs:1\x1b]8;;\x1b\\"
+        );
+    }
+}
@@ -0,0 +1,175 @@
+//! Recent log events, kept in memory for the in-session log view.
+//!
+//! [`layer`] records G3's own debug and higher events (provider retries,
+//! cache hits, README watcher reloads...) and anyone's warnings into a
+//! bounded buffer, whatever `RUST_LOG` or `--verbose` let through to the
+//! terminal. `/logs [level]` in chat mode and the retro TUI's log pane
+//! (Ctrl+L, `:logs <level>`) show them, so an issue can be looked into
+//! without restarting with `RUST_LOG` and losing the session.
+
+use std::collections::VecDeque;
+use std::fmt::{self, Write as _};
+use std::sync::Mutex;
+use tracing::field::{Field, Visit};
+use tracing::{Event, Level, Subscriber};
+use tracing_subscriber::filter::filter_fn;
+use tracing_subscriber::layer::Context;
+use tracing_subscriber::registry::LookupSpan;
+use tracing_subscriber::Layer;
+
+/// Events kept; older ones are dropped
+const CAPACITY: usize = 500;
+
+/// Events `/logs` prints
+pub const CHAT_LIMIT: usize = 50;
+
+/// Typed in the retro TUI's input line to set the log pane's level
+pub const LOGS_COMMAND: &str = ":logs";
+
+static EVENTS: Mutex<VecDeque<LogEvent>> = Mutex::new(VecDeque::new());
+
+/// One recorded event
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LogEvent {
+    pub time: chrono::DateTime<chrono::Local>,
+    pub level: Level,
+    pub target: String,
+    /// The message followed by the event's other fields as `name=value`
+    pub message: String,
+}
+
+impl fmt::Display for LogEvent {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} {:>5} {}: {}",
+            self.time.format("%H:%M:%S"),
+            self.level,
+            self.target,
+            self.message
+        )
+    }
+}
+
+/// A layer recording events into the buffer [`recent`] reads
+pub fn layer<S>() -> impl Layer<S>
+where
+    S: Subscriber + for<'span> LookupSpan<'span>,
+{
+    Recorder.with_filter(filter_fn(|meta| {
+        meta.is_event()
+            && (*meta.level() <= Level::WARN
+                || (*meta.level() <= Level::DEBUG && meta.target().starts_with("g3_")))
+    }))
+}
+
+/// The last `limit` events at `level` or more severe, oldest first
+pub fn recent(level: Level, limit: usize) -> Vec<LogEvent> {
+    let events = EVENTS.lock().unwrap_or_else(|e| e.into_inner());
+    let mut matching: Vec<LogEvent> = events
+        .iter()
+        .rev()
+        .filter(|event| event.level <= level)
+        .take(limit)
+        .cloned()
+        .collect();
+    matching.reverse();
+    matching
+}
+
+/// The level named `name` (error, warn, info, debug)
+pub fn parse_level(name: &str) -> Option<Level> {
+    name.trim()
+        .parse()
+        .ok()
+        .filter(|level| *level != Level::TRACE)
+}
+
+fn record(event: LogEvent) {
+    let mut events = EVENTS.lock().unwrap_or_else(|e| e.into_inner());
+    if events.len() == CAPACITY {
+        events.pop_front();
+    }
+    events.push_back(event);
+}
+
+struct Recorder;
+
+impl<S: Subscriber> Layer<S> for Recorder {
+    fn on_event(&self, event: &Event<'_>, _ctx: Context<'_, S>) {
+        let mut visitor = MessageVisitor::default();
+        event.record(&mut visitor);
+        let meta = event.metadata();
+        record(LogEvent {
+            time: chrono::Local::now(),
+            level: *meta.level(),
+            target: meta.target().to_string(),
+            message: visitor.message + &visitor.fields,
+        });
+    }
+}
+
+#[derive(Default)]
+struct MessageVisitor {
+    message: String,
+    fields: String,
+}
+
+impl Visit for MessageVisitor {
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        if field.name() == "message" {
+            let _ = write!(self.message, "{:?}", value);
+        } else {
+            let _ = write!(self.fields, " {}={:?}", field.name(), value);
+        }
+    }
+
+    fn record_str(&mut self, field: &Field, value: &str) {
+        if field.name() == "message" {
+            self.message.push_str(value);
+        } else {
+            let _ = write!(self.fields, " {}={}", field.name(), value);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tracing_subscriber::layer::SubscriberExt;
+
+    #[test]
+    fn test_records_debug_events_of_g3() {
+        let subscriber = tracing_subscriber::registry().with(layer());
+        tracing::subscriber::with_default(subscriber, || {
+            tracing::debug!(target: "g3_providers::cache", key = "abc", "Cache hit");
+            tracing::debug!(target: "hyper::client", "Noise");
+            tracing::warn!(target: "hyper::client", "Connection reset");
+        });
+
+        let events = recent(Level::DEBUG, CAPACITY);
+        let hit = events
+            .iter()
+            .find(|event| event.target == "g3_providers::cache")
+            .unwrap();
+        assert_eq!(hit.message, "Cache hit key=abc");
+        assert!(hit
+            .to_string()
+            .ends_with("DEBUG g3_providers::cache: Cache hit key=abc"));
+        assert!(!events.iter().any(|event| event.message == "Noise"));
+        assert!(recent(Level::WARN, CAPACITY)
+            .iter()
+            .any(|event| event.message == "Connection reset"));
+        assert!(!recent(Level::WARN, CAPACITY)
+            .iter()
+            .any(|event| event.target == "g3_providers::cache"));
+    }
+
+    #[test]
+    fn test_parse_level() {
+        assert_eq!(parse_level("warn"), Some(Level::WARN));
+        assert_eq!(parse_level(" DEBUG "), Some(Level::DEBUG));
+        assert_eq!(parse_level("trace"), None);
+        assert_eq!(parse_level("loud"), None);
+    }
+}
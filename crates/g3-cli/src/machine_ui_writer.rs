@@ -40,6 +40,10 @@ impl UiWriter for MachineUiWriter {
         println!("CONTEXT_THINNING: {}", message);
     }
 
+    fn print_system_status(&self, message: &str) {
+        println!("SYSTEM_STATUS: {}", message);
+    }
+
     fn print_tool_header(&self, tool_name: &str, _tool_args: Option<&serde_json::Value>) {
         println!("TOOL_CALL: {}", tool_name);
     }
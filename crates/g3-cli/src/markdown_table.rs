@@ -0,0 +1,380 @@
+//! GFM tables in the retro TUI's output and the console's markdown.
+//!
+//! The output area draws markdown line by line, but a table needs all of its
+//! rows to size its columns. [`find_tables`] locates the tables in the output
+//! (a header row, a `|---|:---:|` delimiter row and the rows after it, outside
+//! code fences), and [`render`] lays one out for the available width: columns
+//! as wide as their widest cell, narrowed when the table doesn't fit, with
+//! cells wrapped within them and aligned as the delimiter row says. Tables are
+//! drawn without borders; a rule under the header separates it from the body.
+//! Console output renders its markdown with termimad, and [`term_text`] lays
+//! out the tables in it the same way.
+
+use std::ops::Range;
+use termimad::{FmtText, MadSkin};
+
+/// Spaces between columns
+const COLUMN_GAP: usize = 2;
+
+/// Narrowest a column is squeezed to
+const MIN_COLUMN_WIDTH: usize = 3;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ColumnAlign {
+    Left,
+    Center,
+    Right,
+}
+
+/// The cells of a table row, or `None` if `line` has no `|`. Leading and
+/// trailing pipes are optional and `\|` is a literal pipe.
+pub fn split_row(line: &str) -> Option<Vec<String>> {
+    let line = line.trim();
+    if !line.contains('|') {
+        return None;
+    }
+    let line = line.strip_prefix('|').unwrap_or(line);
+    let line = match line.strip_suffix('|') {
+        Some(rest) if !rest.ends_with('\\') => rest,
+        _ => line,
+    };
+
+    let mut cells = Vec::new();
+    let mut cell = String::new();
+    let mut chars = line.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' if chars.peek() == Some(&'|') => {
+                cell.push('|');
+                chars.next();
+            }
+            '|' => cells.push(std::mem::take(&mut cell).trim().to_string()),
+            _ => cell.push(c),
+        }
+    }
+    cells.push(cell.trim().to_string());
+    Some(cells)
+}
+
+/// The column alignments of a delimiter row such as `| :--- | ---: |`
+pub fn parse_delimiter(line: &str) -> Option<Vec<ColumnAlign>> {
+    split_row(line)?
+        .iter()
+        .map(|cell| {
+            let dashes = cell.trim_start_matches(':').trim_end_matches(':');
+            if dashes.is_empty() || !dashes.chars().all(|c| c == '-') {
+                return None;
+            }
+            Some(match (cell.starts_with(':'), cell.ends_with(':')) {
+                (true, true) => ColumnAlign::Center,
+                (false, true) => ColumnAlign::Right,
+                _ => ColumnAlign::Left,
+            })
+        })
+        .collect()
+}
+
+/// The line ranges of the tables in `lines`, each from its header row to
+/// its last row
+pub fn find_tables(lines: &[String]) -> Vec<Range<usize>> {
+    let mut tables = Vec::new();
+    let mut in_code_block = false;
+    let mut index = 0;
+    while index < lines.len() {
+        let line = &lines[index];
+        if line.trim_start().starts_with("```") {
+            in_code_block = !in_code_block;
+        }
+        let header = (!in_code_block)
+            .then(|| split_row(line))
+            .flatten()
+            .map(|cells| cells.len());
+        let delimiter = lines
+            .get(index + 1)
+            .and_then(|line| parse_delimiter(line))
+            .map(|aligns| aligns.len());
+        match (header, delimiter) {
+            (Some(columns), Some(aligns)) if columns == aligns => {
+                let mut end = index + 2;
+                while end < lines.len() && split_row(&lines[end]).is_some() {
+                    end += 1;
+                }
+                tables.push(index..end);
+                index = end;
+            }
+            _ => index += 1,
+        }
+    }
+    tables
+}
+
+/// Cell text as shown: without the `**` and backticks of inline markdown
+fn cell_text(cell: &str) -> String {
+    cell.replace("**", "").replace('`', "")
+}
+
+/// Column widths for `rows` in at most `max_width` columns: the widest cell
+/// of each column, or, if that doesn't fit, the space shared out so narrow
+/// columns keep their width and wide ones split the rest
+pub fn column_widths(rows: &[Vec<String>], max_width: usize) -> Vec<usize> {
+    let columns = rows.first().map_or(0, Vec::len);
+    let natural: Vec<usize> = (0..columns)
+        .map(|column| {
+            rows.iter()
+                .filter_map(|row| row.get(column))
+                .map(|cell| cell.chars().count())
+                .max()
+                .unwrap_or(0)
+                .max(1)
+        })
+        .collect();
+    let gaps = COLUMN_GAP * columns.saturating_sub(1);
+    if natural.iter().sum::<usize>() + gaps <= max_width {
+        return natural;
+    }
+
+    let mut available = max_width.saturating_sub(gaps);
+    let mut widths = vec![0; columns];
+    let mut open: Vec<usize> = (0..columns).collect();
+    // Give every column that fits in an equal share its natural width, then
+    // split what's left among the others
+    loop {
+        let share = (available / open.len().max(1)).max(MIN_COLUMN_WIDTH);
+        let (fitting, wide): (Vec<usize>, Vec<usize>) =
+            open.iter().partition(|&&column| natural[column] <= share);
+        if fitting.is_empty() {
+            for column in wide {
+                widths[column] = share;
+            }
+            return widths;
+        }
+        for column in fitting {
+            widths[column] = natural[column];
+            available = available.saturating_sub(natural[column]);
+        }
+        if wide.is_empty() {
+            return widths;
+        }
+        open = wide;
+    }
+}
+
+/// `text` wrapped at word boundaries into lines of at most `width`
+/// characters, breaking words longer than that
+fn wrap(text: &str, width: usize) -> Vec<String> {
+    let width = width.max(1);
+    let mut lines = Vec::new();
+    let mut line = String::new();
+    for word in text.split_whitespace() {
+        let mut word: Vec<char> = word.chars().collect();
+        let line_len = line.chars().count();
+        if line_len > 0 && line_len + 1 + word.len() > width {
+            lines.push(std::mem::take(&mut line));
+        }
+        while word.len() > width {
+            if !line.is_empty() {
+                lines.push(std::mem::take(&mut line));
+            }
+            lines.push(word.drain(..width).collect());
+        }
+        if !line.is_empty() {
+            line.push(' ');
+        }
+        line.extend(word);
+    }
+    if !line.is_empty() || lines.is_empty() {
+        lines.push(line);
+    }
+    lines
+}
+
+fn pad(text: &str, width: usize, align: ColumnAlign) -> String {
+    let space = width.saturating_sub(text.chars().count());
+    let (left, right) = match align {
+        ColumnAlign::Left => (0, space),
+        ColumnAlign::Right => (space, 0),
+        ColumnAlign::Center => (space / 2, space - space / 2),
+    };
+    format!("{}{}{}", " ".repeat(left), text, " ".repeat(right))
+}
+
+/// The table in `lines` (as found by [`find_tables`]) laid out in at most
+/// `max_width` columns: the display lines of each source line, in order, so
+/// the header's come first and the delimiter row's are the rule under it
+pub fn render(lines: &[String], max_width: usize) -> Vec<Vec<String>> {
+    let Some(aligns) = lines.get(1).and_then(|line| parse_delimiter(line)) else {
+        return lines.iter().map(|line| vec![line.clone()]).collect();
+    };
+    let columns = aligns.len();
+    let rows: Vec<Vec<String>> = lines
+        .iter()
+        .enumerate()
+        .filter(|(index, _)| *index != 1)
+        .map(|(_, line)| {
+            let mut cells: Vec<String> = split_row(line)
+                .unwrap_or_default()
+                .iter()
+                .map(|cell| cell_text(cell))
+                .collect();
+            cells.resize(columns, String::new());
+            cells
+        })
+        .collect();
+    let widths = column_widths(&rows, max_width);
+    let gap = " ".repeat(COLUMN_GAP);
+
+    let render_row = |cells: &[String]| -> Vec<String> {
+        let wrapped: Vec<Vec<String>> = cells
+            .iter()
+            .zip(&widths)
+            .map(|(cell, &width)| wrap(cell, width))
+            .collect();
+        let height = wrapped.iter().map(Vec::len).max().unwrap_or(1);
+        (0..height)
+            .map(|line| {
+                wrapped
+                    .iter()
+                    .zip(&widths)
+                    .zip(&aligns)
+                    .map(|((cell, &width), &align)| {
+                        pad(cell.get(line).map_or("", String::as_str), width, align)
+                    })
+                    .collect::<Vec<_>>()
+                    .join(&gap)
+                    .trim_end()
+                    .to_string()
+            })
+            .collect()
+    };
+
+    let rule = widths
+        .iter()
+        .map(|&width| "─".repeat(width))
+        .collect::<Vec<_>>()
+        .join(&gap);
+    let mut rendered = vec![render_row(&rows[0]), vec![rule]];
+    rendered.extend(rows[1..].iter().map(|row| render_row(row)));
+    rendered
+}
+
+/// `markdown` formatted by termimad's `skin` for `width` columns, except for
+/// its tables, which are laid out by [`render`] with a bold header
+pub fn term_text(skin: &MadSkin, markdown: &str, width: usize) -> String {
+    let lines: Vec<String> = markdown.lines().map(str::to_string).collect();
+    let format = |text: &[String]| FmtText::from(skin, &text.join("\n"), Some(width)).to_string();
+    let mut formatted = String::new();
+    let mut start = 0;
+    for table in find_tables(&lines) {
+        if start < table.start {
+            let text = &lines[start..table.start];
+            formatted.push_str(&format(text));
+            // termimad drops the blank lines ending the text
+            let blank = text.iter().rev().take_while(|line| line.trim().is_empty());
+            formatted.push_str(&"\n".repeat(blank.count()));
+        }
+        for (index, display_lines) in render(&lines[table.clone()], width).iter().enumerate() {
+            for line in display_lines {
+                match index {
+                    0 => formatted.push_str(&skin.bold.apply_to(line).to_string()),
+                    _ => formatted.push_str(line),
+                }
+                formatted.push('\n');
+            }
+        }
+        start = table.end;
+    }
+    if start < lines.len() {
+        formatted.push_str(&format(&lines[start..]));
+    }
+    formatted
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lines(text: &str) -> Vec<String> {
+        text.lines().map(str::to_string).collect()
+    }
+
+    #[test]
+    fn test_split_row_and_delimiter() {
+        assert_eq!(
+            split_row("| a | b \\| c |  |").unwrap(),
+            vec!["a", "b | c", ""]
+        );
+        assert_eq!(split_row("a | b").unwrap(), vec!["a", "b"]);
+        assert_eq!(split_row("no pipes"), None);
+        assert_eq!(
+            parse_delimiter("|:---|:-:|--:|---|").unwrap(),
+            vec![
+                ColumnAlign::Left,
+                ColumnAlign::Center,
+                ColumnAlign::Right,
+                ColumnAlign::Left
+            ]
+        );
+        assert_eq!(parse_delimiter("| a | --- |"), None);
+    }
+
+    #[test]
+    fn test_find_tables() {
+        let output = lines(
+            "Options:\n| Crate | Speed |\n|---|---|\n| nom | fast |\n| pest | ok |\n\n\
+             ```\n| a | b |\n|---|---|\n```\n| not | a table |\n| x |\n",
+        );
+        assert_eq!(find_tables(&output), vec![1..5]);
+    }
+
+    #[test]
+    fn test_render_aligns_columns() {
+        let table = lines("| Crate | Speed | Stars |\n|:--|:-:|--:|\n| **nom** | fast | 9000 |\n| pest | ok | 4 |");
+        let rendered = render(&table, 80);
+        assert_eq!(rendered.len(), 4);
+        assert_eq!(rendered[0], vec!["Crate  Speed  Stars"]);
+        assert_eq!(rendered[1], vec!["─────  ─────  ─────"]);
+        assert_eq!(rendered[2], vec!["nom    fast    9000"]);
+        assert_eq!(rendered[3], vec!["pest    ok        4"]);
+    }
+
+    #[test]
+    fn test_render_wraps_to_width() {
+        let table = lines(
+            "| Option | Notes |\n|---|---|\n| nom | Parser combinators, zero-copy and very fast |",
+        );
+        let rendered = render(&table, 24);
+        assert!(rendered
+            .iter()
+            .flatten()
+            .all(|line| line.chars().count() <= 24));
+        assert_eq!(
+            rendered[2],
+            vec![
+                "nom     Parser",
+                "        combinators,",
+                "        zero-copy and",
+                "        very fast",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_column_widths() {
+        let rows = vec![vec!["a".to_string(), "x".repeat(50), "y".repeat(50)]];
+        assert_eq!(column_widths(&rows, 200), vec![1, 50, 50]);
+        // The narrow column keeps its width, the wide ones share the rest
+        assert_eq!(column_widths(&rows, 45), vec![1, 20, 20]);
+        assert_eq!(wrap("abcdefgh", 3), vec!["abc", "def", "gh"]);
+    }
+
+    #[test]
+    fn test_term_text_lays_out_tables() {
+        let markdown = "Results:\n\n| Crate | Stars |\n|---|--:|\n| nom | 9000 |\n\nDone.";
+        let formatted = term_text(&MadSkin::no_style(), markdown, 40);
+        let lines: Vec<&str> = formatted.lines().map(str::trim_end).collect();
+        assert_eq!(
+            lines,
+            vec!["Results:", "", "Crate  Stars", "─────  ─────", "nom     9000", "", "Done."]
+        );
+    }
+}
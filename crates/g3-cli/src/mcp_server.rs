@@ -0,0 +1,461 @@
+//! `g3 mcp`: G3's tools as a Model Context Protocol server.
+//!
+//! Other agent frontends (Claude Desktop, IDE plugins) connect over stdio, or
+//! over TCP with `--listen`, and call code_search, str_replace (unified diff
+//! application), background_process and, when enabled, the computer-control
+//! tools. Messages are newline-delimited JSON-RPC 2.0; the first line that
+//! isn't closes the connection.
+//!
+//! `--listen` only binds loopback addresses unless `--allow-remote` is given,
+//! and a TCP client's first line must be the access token printed at startup.
+//!
+//! Every client gets its own agent, and TCP clients are served concurrently.
+//! A client's requests are handled as they arrive, so a ping isn't held up by
+//! a slow tool call, but its tool calls run one at a time. Calls still go
+//! through the `[permissions]` policy; nobody can answer "ask" prompts or
+//! destructive-command confirmations here, so those calls are declined.
+
+use anyhow::{bail, Result};
+use g3_config::Config;
+use g3_core::permissions::{self, ToolClass};
+use g3_core::ui_writer::UiWriter;
+use g3_core::{Agent, ToolCall};
+use g3_providers::Tool;
+use serde_json::{json, Value};
+use std::cell::Cell;
+use std::net::SocketAddr;
+use std::rc::Rc;
+use tokio::io::{AsyncBufReadExt, AsyncRead, AsyncWrite, AsyncWriteExt, BufReader};
+use tokio::sync::{mpsc, Mutex};
+use tracing::{debug, info, warn};
+
+use crate::{access_token, Cli};
+
+/// Protocol versions we speak, newest first
+const PROTOCOL_VERSIONS: &[&str] = &["2025-06-18", "2025-03-26", "2024-11-05"];
+
+const PARSE_ERROR: i64 = -32700;
+const INVALID_REQUEST: i64 = -32600;
+const METHOD_NOT_FOUND: i64 = -32601;
+const INVALID_PARAMS: i64 = -32602;
+const SERVER_NOT_INITIALIZED: i64 = -32002;
+
+/// Run `g3 mcp`: serve stdio, or every client that connects to `listen`
+pub async fn run_mcp_server(cli: &Cli, listen: Option<String>, allow_remote: bool) -> Result<()> {
+    // stdout carries the protocol, so logs go to stderr
+    use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt, EnvFilter};
+    let level = if cli.verbose { "debug" } else { "info" };
+    tracing_subscriber::registry()
+        .with(tracing_subscriber::fmt::layer().with_writer(std::io::stderr))
+        .with(
+            EnvFilter::from_default_env()
+                .add_directive(format!("g3_core={}", level).parse().unwrap())
+                .add_directive(format!("g3_cli={}", level).parse().unwrap())
+                .add_directive(format!("g3_execution={}", level).parse().unwrap())
+                .add_directive(format!("g3_computer_control={}", level).parse().unwrap()),
+        )
+        .init();
+
+    if let Some(workspace) = &cli.workspace {
+        std::env::set_current_dir(workspace)?;
+    }
+    let config = crate::config_loader::load_config(cli)?;
+
+    // Agents aren't shared between tasks, so everything runs on one thread
+    let local = tokio::task::LocalSet::new();
+    local
+        .run_until(async move {
+            let Some(addr) = listen else {
+                info!("MCP server on stdio");
+                return serve_connection(tokio::io::stdin(), tokio::io::stdout(), config, None)
+                    .await;
+            };
+            let addrs: Vec<SocketAddr> = tokio::net::lookup_host(&addr).await?.collect();
+            check_listen_addrs(&addrs, allow_remote)?;
+            let listener = tokio::net::TcpListener::bind(addrs.as_slice()).await?;
+            let token = Rc::new(access_token::generate());
+            info!("MCP server listening on {}", listener.local_addr()?);
+            eprintln!("MCP access token (send it as the first line): {}", token);
+            loop {
+                let (stream, peer) = listener.accept().await?;
+                info!("MCP client connected from {}", peer);
+                let config = config.clone();
+                let token = token.clone();
+                tokio::task::spawn_local(async move {
+                    let (reader, writer) = stream.into_split();
+                    if let Err(e) = serve_connection(reader, writer, config, Some(&token)).await {
+                        warn!("MCP client {} failed: {}", peer, e);
+                    }
+                    info!("MCP client {} disconnected", peer);
+                });
+            }
+        })
+        .await
+}
+
+/// Refuse addresses reachable from other machines unless `allow_remote`
+fn check_listen_addrs(addrs: &[SocketAddr], allow_remote: bool) -> Result<()> {
+    if addrs.is_empty() {
+        bail!("The listen address resolved to nothing");
+    }
+    if let Some(addr) = addrs.iter().find(|addr| !addr.ip().is_loopback()) {
+        if !allow_remote {
+            bail!(
+                "Refusing to listen on {}, which isn't a loopback address; pass --allow-remote to expose the tools to the network",
+                addr
+            );
+        }
+        warn!("MCP server reachable from the network on {}", addr);
+    }
+    Ok(())
+}
+
+/// Whether `message` is a JSON-RPC 2.0 message or batch of them
+fn is_json_rpc(message: &Value) -> bool {
+    let is_message = |message: &Value| message.get("jsonrpc") == Some(&json!("2.0"));
+    match message {
+        Value::Array(batch) => batch.iter().all(is_message),
+        message => is_message(message),
+    }
+}
+
+/// Serve one client until it disconnects. With a `token`, the client's first
+/// line must be that token.
+async fn serve_connection<R, W>(
+    reader: R,
+    mut writer: W,
+    config: Config,
+    token: Option<&str>,
+) -> Result<()>
+where
+    R: AsyncRead + Unpin,
+    W: AsyncWrite + Unpin + 'static,
+{
+    let mut lines = BufReader::new(reader).lines();
+    if let Some(token) = token {
+        let given = lines.next_line().await?.unwrap_or_default();
+        if !access_token::matches(token, given.trim()) {
+            let error = error_response(Value::Null, INVALID_REQUEST, "Invalid access token");
+            writer.write_all(format!("{}\n", error).as_bytes()).await?;
+            bail!("Client sent an invalid access token");
+        }
+    }
+
+    let session = Rc::new(Session::new(config).await?);
+
+    // Responses are written by a single task, so concurrent ones don't interleave
+    let (tx, mut rx) = mpsc::unbounded_channel::<Value>();
+    let writer_task = tokio::task::spawn_local(async move {
+        while let Some(message) = rx.recv().await {
+            let line = format!("{}\n", message);
+            if writer.write_all(line.as_bytes()).await.is_err() || writer.flush().await.is_err() {
+                break;
+            }
+        }
+    });
+
+    while let Some(line) = lines.next_line().await? {
+        if line.trim().is_empty() {
+            continue;
+        }
+        // Whatever isn't JSON-RPC ends the connection, so a client speaking
+        // another protocol (an HTTP request, say) gets no further
+        let message: Value = match serde_json::from_str(&line) {
+            Ok(message) if is_json_rpc(&message) => message,
+            Ok(_) => {
+                let _ = tx.send(error_response(
+                    Value::Null,
+                    INVALID_REQUEST,
+                    "Not a JSON-RPC 2.0 message",
+                ));
+                break;
+            }
+            Err(e) => {
+                let _ = tx.send(error_response(
+                    Value::Null,
+                    PARSE_ERROR,
+                    format!("Parse error: {}", e),
+                ));
+                break;
+            }
+        };
+
+        let session = session.clone();
+        let tx = tx.clone();
+        tokio::task::spawn_local(async move {
+            let response = match message {
+                Value::Array(batch) if batch.is_empty() => {
+                    Some(error_response(Value::Null, INVALID_REQUEST, "Empty batch"))
+                }
+                Value::Array(batch) => {
+                    let mut responses = Vec::new();
+                    for message in batch {
+                        responses.extend(session.handle(message).await);
+                    }
+                    (!responses.is_empty()).then_some(Value::Array(responses))
+                }
+                message => session.handle(message).await,
+            };
+            if let Some(response) = response {
+                let _ = tx.send(response);
+            }
+        });
+    }
+
+    // Let in-flight calls finish and their responses be written
+    drop(tx);
+    let _ = writer_task.await;
+    Ok(())
+}
+
+/// Tools offered to MCP clients
+fn is_exposed(tool_name: &str) -> bool {
+    matches!(
+        tool_name,
+        "code_search" | "str_replace" | "background_process"
+    ) || permissions::classify(tool_name) == ToolClass::ComputerControl
+}
+
+/// The version to answer `initialize` with: the client's if we speak it,
+/// otherwise our newest, which the client may then reject
+fn negotiate_version(requested: Option<&str>) -> &'static str {
+    PROTOCOL_VERSIONS
+        .iter()
+        .copied()
+        .find(|version| Some(*version) == requested)
+        .unwrap_or(PROTOCOL_VERSIONS[0])
+}
+
+fn response(id: Value, result: Value) -> Value {
+    json!({ "jsonrpc": "2.0", "id": id, "result": result })
+}
+
+fn error_response(id: Value, code: i64, message: impl Into<String>) -> Value {
+    json!({
+        "jsonrpc": "2.0",
+        "id": id,
+        "error": { "code": code, "message": message.into() },
+    })
+}
+
+/// One connected client
+struct Session {
+    agent: Mutex<Agent<McpUiWriter>>,
+    tools: Vec<Tool>,
+    initialized: Cell<bool>,
+}
+
+impl Session {
+    async fn new(config: Config) -> Result<Self> {
+        let agent = Agent::new_with_readme_and_quiet(config, McpUiWriter, None, true).await?;
+        let tools = agent
+            .tool_definitions()
+            .into_iter()
+            .filter(|tool| is_exposed(&tool.name))
+            .collect();
+        Ok(Self {
+            agent: Mutex::new(agent),
+            tools,
+            initialized: Cell::new(false),
+        })
+    }
+
+    /// Handle one message; notifications get no response
+    async fn handle(&self, message: Value) -> Option<Value> {
+        let id = message.get("id").cloned();
+        let Some(method) = message.get("method").and_then(Value::as_str) else {
+            // We never send requests, so a response from the client is stray
+            if message.get("result").is_some() || message.get("error").is_some() {
+                return None;
+            }
+            return Some(error_response(
+                id.unwrap_or(Value::Null),
+                INVALID_REQUEST,
+                "Missing method",
+            ));
+        };
+        let Some(id) = id else {
+            debug!("MCP notification: {}", method);
+            return None;
+        };
+
+        let params = message.get("params").cloned().unwrap_or(Value::Null);
+        Some(match self.dispatch(method, params).await {
+            Ok(result) => response(id, result),
+            Err((code, message)) => error_response(id, code, message),
+        })
+    }
+
+    async fn dispatch(&self, method: &str, params: Value) -> Result<Value, (i64, String)> {
+        match method {
+            "initialize" => {
+                if let Some(client) = params.get("clientInfo") {
+                    info!("MCP client: {}", client);
+                }
+                let requested = params.get("protocolVersion").and_then(Value::as_str);
+                self.initialized.set(true);
+                Ok(json!({
+                    "protocolVersion": negotiate_version(requested),
+                    "capabilities": { "tools": { "listChanged": false } },
+                    "serverInfo": { "name": "g3", "version": env!("CARGO_PKG_VERSION") },
+                }))
+            }
+            "ping" => Ok(json!({})),
+            _ if !self.initialized.get() => {
+                Err((SERVER_NOT_INITIALIZED, "Server not initialized".to_string()))
+            }
+            "tools/list" => {
+                let tools: Vec<Value> = self
+                    .tools
+                    .iter()
+                    .map(|tool| {
+                        json!({
+                            "name": tool.name,
+                            "description": tool.description,
+                            "inputSchema": tool.input_schema,
+                        })
+                    })
+                    .collect();
+                Ok(json!({ "tools": tools }))
+            }
+            "tools/call" => self.call_tool(params).await,
+            _ => Err((METHOD_NOT_FOUND, format!("Method not found: {}", method))),
+        }
+    }
+
+    async fn call_tool(&self, params: Value) -> Result<Value, (i64, String)> {
+        let name = params
+            .get("name")
+            .and_then(Value::as_str)
+            .ok_or((INVALID_PARAMS, "Missing tool name".to_string()))?;
+        if !self.tools.iter().any(|tool| tool.name == name) {
+            return Err((INVALID_PARAMS, format!("Unknown tool: {}", name)));
+        }
+
+        let tool_call = ToolCall {
+            tool: name.to_string(),
+            args: params
+                .get("arguments")
+                .cloned()
+                .unwrap_or_else(|| json!({})),
+        };
+        let result = self.agent.lock().await.execute_tool_call(&tool_call).await;
+        let (text, is_error) = match result {
+            Ok(text) => {
+                let is_error = text.starts_with("❌");
+                (text, is_error)
+            }
+            Err(e) => (format!("❌ {}", e), true),
+        };
+        Ok(json!({
+            "content": [{ "type": "text", "text": text }],
+            "isError": is_error,
+        }))
+    }
+}
+
+/// UI for agents serving MCP clients: stdout carries the protocol, so nothing
+/// is printed, and nobody is there to approve tool calls
+struct McpUiWriter;
+
+impl UiWriter for McpUiWriter {
+    fn print(&self, _message: &str) {}
+    fn println(&self, _message: &str) {}
+    fn print_inline(&self, _message: &str) {}
+    fn print_system_prompt(&self, _prompt: &str) {}
+    fn print_context_status(&self, _message: &str) {}
+    fn print_context_thinning(&self, _message: &str) {}
+    fn print_tool_header(&self, _tool_name: &str, _tool_args: Option<&serde_json::Value>) {}
+    fn print_tool_arg(&self, _key: &str, _value: &str) {}
+    fn print_tool_output_header(&self) {}
+    fn update_tool_output_line(&self, _line: &str) {}
+    fn print_tool_output_line(&self, _line: &str) {}
+    fn print_tool_output_summary(&self, _hidden_count: usize) {}
+    fn print_tool_timing(&self, _duration_str: &str, _tokens_delta: u32, _context_percentage: f32) {
+    }
+    fn print_agent_prompt(&self) {}
+    fn print_agent_response(&self, _content: &str) {}
+    fn notify_sse_received(&self) {}
+    fn flush(&self) {}
+    fn prompt_user_yes_no(&self, message: &str) -> bool {
+        warn!("Declining, no user to ask: {}", message);
+        false
+    }
+    fn prompt_user_choice(&self, _message: &str, _options: &[&str]) -> usize {
+        0
+    }
+    fn print_final_output(&self, _summary: &str) {}
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_negotiate_version() {
+        assert_eq!(negotiate_version(Some("2024-11-05")), "2024-11-05");
+        assert_eq!(negotiate_version(Some("1999-01-01")), PROTOCOL_VERSIONS[0]);
+        assert_eq!(negotiate_version(None), PROTOCOL_VERSIONS[0]);
+    }
+
+    #[test]
+    fn test_listen_addrs_must_be_loopback() {
+        let local: SocketAddr = "127.0.0.1:7410".parse().unwrap();
+        let local_v6: SocketAddr = "[::1]:7410".parse().unwrap();
+        let remote: SocketAddr = "0.0.0.0:7410".parse().unwrap();
+        assert!(check_listen_addrs(&[local, local_v6], false).is_ok());
+        assert!(check_listen_addrs(&[local, remote], false).is_err());
+        assert!(check_listen_addrs(&[remote], true).is_ok());
+        assert!(check_listen_addrs(&[], true).is_err());
+    }
+
+    #[test]
+    fn test_is_json_rpc() {
+        assert!(is_json_rpc(
+            &json!({"jsonrpc": "2.0", "method": "ping", "id": 1})
+        ));
+        assert!(is_json_rpc(&json!([{"jsonrpc": "2.0", "method": "ping"}])));
+        assert!(is_json_rpc(&json!([])));
+        assert!(!is_json_rpc(&json!({"method": "ping", "id": 1})));
+        assert!(!is_json_rpc(&json!([{"jsonrpc": "2.0"}, 1])));
+        assert!(!is_json_rpc(&json!("GET / HTTP/1.1")));
+    }
+
+    #[tokio::test]
+    async fn test_wrong_token_closes_connection() {
+        let (client, server) = tokio::io::duplex(1024);
+        let (server_read, server_write) = tokio::io::split(server);
+        let (client_read, mut client_write) = tokio::io::split(client);
+        client_write.write_all(b"not-the-token\n").await.unwrap();
+
+        let local = tokio::task::LocalSet::new();
+        let result = local
+            .run_until(serve_connection(
+                server_read,
+                server_write,
+                Config::default(),
+                Some("secret"),
+            ))
+            .await;
+        assert!(result.is_err());
+
+        let mut lines = BufReader::new(client_read).lines();
+        let reply: Value =
+            serde_json::from_str(&lines.next_line().await.unwrap().unwrap()).unwrap();
+        assert_eq!(reply["error"]["message"], "Invalid access token");
+        assert_eq!(lines.next_line().await.unwrap(), None);
+    }
+
+    #[test]
+    fn test_exposed_tools() {
+        assert!(is_exposed("code_search"));
+        assert!(is_exposed("str_replace"));
+        assert!(is_exposed("background_process"));
+        assert!(is_exposed("take_screenshot"));
+        assert!(is_exposed("macax_press_key"));
+        assert!(!is_exposed("shell"));
+        assert!(!is_exposed("write_file"));
+        assert!(!is_exposed("webdriver_navigate"));
+        assert!(!is_exposed("todo_write"));
+    }
+}
@@ -0,0 +1,141 @@
+//! `g3 replay`: play a journaled session back in the terminal.
+//!
+//! The transcript of a session journal (see `g3_core::session_journal`) is
+//! printed the way `--resume` shows it, paced like the live session: each
+//! tool call takes as long as it took, the rest a short pause, all scaled by
+//! `--speed`. `--step` waits for Enter before each entry instead, for demos
+//! and for going through a model's behavior one step at a time, and
+//! `--instant` prints everything at once, so the output can be kept as a
+//! fixture for the renderer.
+
+use anyhow::Result;
+use g3_core::session_journal::{read_transcript, TranscriptEntry};
+use std::io::BufRead;
+use std::path::PathBuf;
+use std::time::Duration;
+
+use crate::simple_output::SimpleOutput;
+
+/// Pause before a task or a response, at speed 1
+const ENTRY_DELAY: Duration = Duration::from_millis(800);
+
+/// Longest pause for a tool call, at speed 1
+const MAX_TOOL_DELAY: Duration = Duration::from_secs(5);
+
+/// How fast to play a session back
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Pace {
+    /// Real time multiplied by this factor
+    Speed(f64),
+    /// Wait for Enter before each entry
+    Step,
+    /// No pauses
+    Instant,
+}
+
+/// The journal `target` names: a journal file, or else a session id
+fn journal_path(target: &str) -> PathBuf {
+    let path = PathBuf::from(target);
+    if path.is_file() {
+        path
+    } else {
+        g3_core::paths::get_session_journal_file(target)
+    }
+}
+
+/// Play back the session journaled at `target` (a file or a session id)
+pub fn replay(target: &str, pace: Pace) -> Result<()> {
+    if let Pace::Speed(speed) = pace {
+        if !speed.is_finite() || speed <= 0.0 {
+            anyhow::bail!("--speed must be a positive number, got {}", speed);
+        }
+    }
+    let path = journal_path(target);
+    let transcript = read_transcript(&path)?;
+    let output = SimpleOutput::new();
+
+    output.print(&format!(
+        "📼 Replaying {} ({} entries)",
+        path.display(),
+        transcript.len()
+    ));
+    if pace == Pace::Step {
+        output.print("   Enter: next entry, q then Enter: stop");
+    }
+    let mut stdin = std::io::stdin().lock();
+    for entry in &transcript {
+        match pace {
+            Pace::Speed(speed) => std::thread::sleep(delay(entry, speed)),
+            Pace::Step => {
+                let mut line = String::new();
+                if stdin.read_line(&mut line)? == 0 || line.trim() == "q" {
+                    output.print("⏹️ Replay stopped");
+                    return Ok(());
+                }
+            }
+            Pace::Instant => {}
+        }
+        output.print_smart(&render(entry));
+    }
+    output.print("");
+    output.print("✅ Replay finished");
+    Ok(())
+}
+
+/// `entry` as the session showed it
+pub fn render(entry: &TranscriptEntry) -> String {
+    match entry {
+        TranscriptEntry::Input(text) => format!("\ng3> {}", text),
+        TranscriptEntry::Output(text) => text.clone(),
+        TranscriptEntry::ToolCall {
+            tool,
+            duration,
+            success,
+        } => format!(
+            "  {} {} ({})",
+            if *success { "✓" } else { "✗" },
+            tool,
+            crate::format_elapsed_time(*duration)
+        ),
+    }
+}
+
+/// How long to wait before showing `entry` at `speed`
+fn delay(entry: &TranscriptEntry, speed: f64) -> Duration {
+    let delay = match entry {
+        TranscriptEntry::ToolCall { duration, .. } => (*duration).min(MAX_TOOL_DELAY),
+        _ => ENTRY_DELAY,
+    };
+    delay.div_f64(speed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tool_call(ms: u64) -> TranscriptEntry {
+        TranscriptEntry::ToolCall {
+            tool: "shell".to_string(),
+            duration: Duration::from_millis(ms),
+            success: false,
+        }
+    }
+
+    #[test]
+    fn test_delay_follows_speed() {
+        let input = TranscriptEntry::Input("fix the parser".to_string());
+        assert_eq!(delay(&input, 1.0), ENTRY_DELAY);
+        assert_eq!(delay(&input, 2.0), ENTRY_DELAY / 2);
+        assert_eq!(delay(&tool_call(1200), 4.0), Duration::from_millis(300));
+        assert_eq!(delay(&tool_call(60_000), 1.0), MAX_TOOL_DELAY);
+    }
+
+    #[test]
+    fn test_render() {
+        assert_eq!(
+            render(&TranscriptEntry::Input("fix the parser".to_string())),
+            "\ng3> fix the parser"
+        );
+        assert!(render(&tool_call(1200)).starts_with("  ✗ shell ("));
+    }
+}
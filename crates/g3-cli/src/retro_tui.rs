@@ -1,32 +1,51 @@
 use anyhow::Result;
 use crossterm::{
-    event::{DisableMouseCapture, EnableMouseCapture},
+    event::{
+        self, DisableMouseCapture, EnableMouseCapture, Event, KeyCode, KeyEventKind, KeyModifiers,
+        MouseButton, MouseEvent, MouseEventKind,
+    },
     execute,
     terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
 };
 use ratatui::{
-    backend::CrosstermBackend,
+    backend::{Backend, CrosstermBackend},
+    buffer::Buffer,
     layout::{Alignment, Constraint, Direction, Layout, Rect},
     style::{Color, Modifier, Style},
     text::{Line, Span},
-    widgets::{Block, Borders, Paragraph, Scrollbar, ScrollbarOrientation, ScrollbarState, Wrap},
+    widgets::{Block, Borders, Clear, Paragraph, Scrollbar, ScrollbarOrientation, ScrollbarState, Wrap},
     Frame, Terminal,
 };
+use g3_core::branches::{self, BranchAction, BranchSummary};
+use g3_core::permissions::{ApprovalDecision, ApprovalRequest};
 use std::io;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::{Arc, Mutex};
 use std::time::{Duration, Instant};
-use tokio::sync::mpsc;
+use tokio::sync::{mpsc, oneshot};
 use std::collections::VecDeque;
 
-use crate::theme::ColorTheme;
+use crate::ansi;
+use crate::clipboard::{self, CopyTarget};
+use crate::command_palette::{builtin_entries, CommandPalette, PaletteAction, PaletteCommand};
+use crate::commands::{self, BRANCHES_COMMAND, HELP_COMMAND, UNDO_COMMAND};
+use crate::i18n::{t, Msg};
+use crate::links::{self, FileRef};
+use crate::log_pane::{self, LOGS_COMMAND};
+use crate::markdown_table;
+use crate::theme::{ColorTheme, BUILTIN_THEMES};
 
 // Color theme will be loaded dynamically
 
 // Scrolling configuration
 const SCROLL_PAST_END_BUFFER: usize = 10; // Extra lines to allow scrolling past the end
 
+/// How long the input thread waits for an event before checking whether it
+/// was paused
+const INPUT_POLL: Duration = Duration::from_millis(50);
+
 /// Message types for communication between threads
-#[derive(Debug, Clone)]
+#[derive(Debug)]
 pub enum TuiMessage {
     AgentOutput(String),
     ToolOutput {
@@ -51,10 +70,24 @@ pub enum TuiMessage {
         percentage: f32,
     },
     SSEReceived, // New message type for SSE events (including pings)
+    /// Open the approval dialog; the decision is sent back on the channel
+    ApprovalRequest(ApprovalRequest, oneshot::Sender<ApprovalDecision>),
     Error(String),
     Exit,
 }
 
+/// A tool call waiting for the user to press y, n, a or d
+struct ApprovalDialog {
+    request: ApprovalRequest,
+    reply: oneshot::Sender<ApprovalDecision>,
+}
+
+/// The `:branches` view: the conversation branches, one selected
+struct BranchesView {
+    rows: Vec<BranchSummary>,
+    selected: usize,
+}
+
 /// Shared state for the retro terminal
 struct TerminalState {
     /// Color theme
@@ -111,6 +144,28 @@ struct TerminalState {
     sse_count: u32,
     /// Last token count for rate calculation
     last_token_count: u32,
+    /// Open tool approval dialog, which takes all key presses
+    approval: Option<ApprovalDialog>,
+    /// Open command palette, which takes key presses unless a dialog is open
+    palette: Option<CommandPalette>,
+    /// Providers offered by the palette's "Switch provider"
+    palette_providers: Vec<String>,
+    /// Keep the activity area closed even while processing
+    activity_hidden: bool,
+    /// The `:help` overlay is open
+    help_open: bool,
+    /// The log pane is open
+    log_pane_open: bool,
+    /// Least severe level the log pane shows
+    log_level: tracing::Level,
+    /// Open `:branches` view, which takes key presses unless a dialog is open
+    branches: Option<BranchesView>,
+    /// Where the output area was last drawn, to map mouse rows to lines
+    output_area: Rect,
+    /// Output lines selected with the mouse (anchor, end), for `/copy selection`
+    selection: Option<(usize, usize)>,
+    /// First output line of the current or last response, for `/copy last`
+    response_start: usize,
 }
 
 impl TerminalState {
@@ -150,9 +205,44 @@ impl TerminalState {
             _session_start: Instant::now(),
             last_token_count: 0,
             sse_count: 0,
+            approval: None,
+            palette: None,
+            palette_providers: Vec::new(),
+            activity_hidden: false,
+            help_open: false,
+            log_pane_open: false,
+            log_level: tracing::Level::DEBUG,
+            branches: None,
+            output_area: Rect::default(),
+            selection: None,
+            response_start: 0,
         }
     }
 
+    /// Answer the open approval dialog with y, n, a or d; Esc denies.
+    /// Returns false if no dialog is open or the key means nothing to it.
+    fn answer_approval(&mut self, key: char) -> bool {
+        let decision = match key {
+            '\u{1b}' => ApprovalDecision::Deny,
+            key => match ApprovalDecision::from_key(key) {
+                Some(decision) => decision,
+                None => return false,
+            },
+        };
+        match self.approval.take() {
+            Some(dialog) => {
+                let _ = dialog.reply.send(decision);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Show the activity area while processing, unless the user hid it
+    fn update_activity_target(&mut self) {
+        self.activity_animation_target = if self.is_processing && !self.activity_hidden { 1.0 } else { 0.0 };
+    }
+
     /// Format tool call output
     fn format_tool_output(&mut self, tool_name: &str, caption: &str, content: &str) {
         // Add tool header bar to main output
@@ -406,7 +496,9 @@ impl TerminalState {
 
     /// Add text to output history
     fn add_output(&mut self, text: &str) {
-        let mut lines = text.lines();
+        // Not `lines()`: a trailing newline must start a new line for the
+        // next chunk of a streamed response
+        let mut lines = text.split('\n').map(|line| line.strip_suffix('\r').unwrap_or(line));
 
         // Remove any existing cursor from the last line before adding new content
         if let Some(last) = self.output_history.last_mut() {
@@ -459,6 +551,121 @@ impl TerminalState {
             }
         }
     }
+
+    /// The output line drawn at screen `row`, if it is in the output area
+    fn line_at(&self, row: u16) -> Option<usize> {
+        let area = self.output_area;
+        // The output area is padded by a line at the top and bottom
+        if row <= area.y || row + 1 >= area.y + area.height {
+            return None;
+        }
+        let visible_height = area.height.saturating_sub(2) as usize;
+        let scroll = output_scroll(
+            self.output_history.len(),
+            visible_height,
+            self.scroll_offset,
+        );
+        let line = scroll + (row - area.y - 1) as usize;
+        (line < self.output_history.len()).then_some(line)
+    }
+
+    fn is_selected(&self, line: usize) -> bool {
+        self.selection
+            .is_some_and(|(anchor, end)| (anchor.min(end)..=anchor.max(end)).contains(&line))
+    }
+
+    /// `lines` of the output as plain text, without escape sequences, tool
+    /// status markers or the processing cursor
+    fn plain_text(&self, lines: impl IntoIterator<Item = usize>) -> String {
+        lines
+            .into_iter()
+            .filter_map(|index| self.output_history.get(index))
+            .map(|line| {
+                let line = ansi::strip(line);
+                let line = line.trim_end_matches('█');
+                ["[TOOL_HEADER]", "[SUCCESS]", "[FAILED]"]
+                    .iter()
+                    .find_map(|marker| line.strip_prefix(marker))
+                    .map(str::trim)
+                    .unwrap_or(line)
+                    .to_string()
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+            .trim()
+            .to_string()
+    }
+
+    /// What `/copy <target>` copies
+    fn copy_text(&self, target: CopyTarget) -> Result<String, &'static str> {
+        let text = match target {
+            CopyTarget::Last => self.plain_text(self.response_start..self.output_history.len()),
+            CopyTarget::Code => {
+                return clipboard::last_code_block(&self.plain_text(0..self.output_history.len()))
+                    .ok_or("No code block in the output");
+            }
+            CopyTarget::Selection => {
+                let (anchor, end) = self
+                    .selection
+                    .ok_or("Nothing selected; drag over the output with the mouse first")?;
+                self.plain_text(anchor.min(end)..=anchor.max(end))
+            }
+        };
+        if text.is_empty() {
+            return Err("Nothing to copy");
+        }
+        Ok(text)
+    }
+
+    /// The file reference Ctrl+O opens: the last one on the selected lines,
+    /// or in the whole output if none are selected
+    fn link_under_cursor(&self) -> Result<FileRef, &'static str> {
+        let base = std::env::current_dir().map_err(|_| "No working directory")?;
+        let len = self.output_history.len();
+        let lines = match self.selection {
+            Some((anchor, end)) => anchor.min(end).min(len)..(anchor.max(end) + 1).min(len),
+            None => 0..len,
+        };
+        self.output_history[lines]
+            .iter()
+            .rev()
+            .find_map(|line| links::find_file_refs(&ansi::strip(line), &base).pop())
+            .ok_or("No file reference like path/to/file.rs:42 to open")
+    }
+}
+
+/// The arguments that open `file_ref` at its line in `editor`, which editors
+/// take in one of three ways
+fn editor_args(editor: &str, file_ref: &FileRef) -> Vec<String> {
+    let path = file_ref.path.display().to_string();
+    let column = file_ref.column.unwrap_or(1);
+    let name = std::path::Path::new(editor)
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    match name.as_str() {
+        "code" | "code-insiders" | "codium" | "cursor" | "windsurf" => vec![
+            "--goto".to_string(),
+            format!("{}:{}:{}", path, file_ref.line, column),
+        ],
+        "zed" | "subl" | "hx" | "helix" => {
+            vec![format!("{}:{}:{}", path, file_ref.line, column)]
+        }
+        _ => vec![format!("+{}", file_ref.line), path],
+    }
+}
+
+/// First output line shown for `scroll_offset`: scrolling may go
+/// [`SCROLL_PAST_END_BUFFER`] lines past the end, so no content is cut off
+fn output_scroll(total_lines: usize, visible_height: usize, scroll_offset: usize) -> usize {
+    if total_lines <= visible_height {
+        // If all content fits, no scrolling needed
+        return 0;
+    }
+    let max_scroll_with_buffer = total_lines
+        .saturating_sub(visible_height)
+        .saturating_add(SCROLL_PAST_END_BUFFER);
+    scroll_offset.min(max_scroll_with_buffer)
 }
 
 /// Public interface for the retro terminal
@@ -467,6 +674,28 @@ pub struct RetroTui {
     tx: mpsc::UnboundedSender<TuiMessage>,
     state: Arc<Mutex<TerminalState>>,
     terminal: Arc<Mutex<Terminal<CrosstermBackend<io::Stdout>>>>,
+    input_pause: Arc<InputPause>,
+    _restore: Arc<RestoreTerminal>,
+}
+
+/// Keeps the thread of [`RetroTui::spawn_input`] off the terminal while
+/// another program uses it, so the editor of Ctrl+O gets its keys
+#[derive(Default)]
+struct InputPause {
+    paused: AtomicBool,
+    /// Held by the input thread while it polls and reads
+    reading: Mutex<()>,
+}
+
+/// Restores the terminal once the last [`RetroTui`] handle is dropped; the
+/// agent's UiWriter holds one of them
+struct RestoreTerminal;
+
+impl Drop for RestoreTerminal {
+    fn drop(&mut self) {
+        let _ = disable_raw_mode();
+        let _ = execute!(io::stdout(), LeaveAlternateScreen, DisableMouseCapture);
+    }
 }
 
 impl RetroTui {
@@ -527,7 +756,7 @@ impl RetroTui {
                             state.status_line = status;
                             state.is_processing = state.status_line == "PROCESSING";
                             // Set animation target based on processing state
-                            state.activity_animation_target = if state.is_processing { 1.0 } else { 0.0 };
+                            state.update_activity_target();
                             
                             // Clear input buffer when entering PROCESSING mode
                             if !was_processing && state.is_processing {
@@ -548,6 +777,7 @@ impl RetroTui {
                                 if let Some(last) = state.output_history.last_mut() {
                                     last.push('█');
                                 }
+                                state.response_start = state.output_history.len();
                             }
                         }
                         TuiMessage::ContextUpdate {
@@ -588,8 +818,11 @@ impl RetroTui {
                                 state.sse_wave_history.pop_front();
                             }
                         }
+                        TuiMessage::ApprovalRequest(request, reply) => {
+                            state.approval = Some(ApprovalDialog { request, reply });
+                        }
                         TuiMessage::Error(err) => {
-                            state.add_output(&format!("ERROR: {}", err));
+                            state.add_output(&format!("\nERROR: {}\n", err));
                         }
                         TuiMessage::Exit => {
                             state.should_exit = true;
@@ -654,12 +887,66 @@ impl RetroTui {
             tx,
             state,
             terminal,
+            input_pause: Arc::new(InputPause::default()),
+            _restore: Arc::new(RestoreTerminal),
         })
     }
 
-    /// Draw the terminal UI
-    fn draw(
-        terminal: &mut Terminal<CrosstermBackend<io::Stdout>>,
+    /// Read terminal events on a thread of their own and pass them on, so
+    /// keys still arrive while the caller is busy running a task. Keys that
+    /// answer the approval dialog are taken here: the caller's task is the
+    /// one waiting for the answer. Ctrl+C denies the call and is passed on
+    /// too, to interrupt the task.
+    pub fn spawn_input(&self) -> mpsc::UnboundedReceiver<Event> {
+        let (tx, rx) = mpsc::unbounded_channel();
+        let state = self.state.clone();
+        let pause = self.input_pause.clone();
+        std::thread::spawn(move || loop {
+            if pause.paused.load(Ordering::SeqCst) {
+                std::thread::sleep(INPUT_POLL);
+                continue;
+            }
+            let event = {
+                let _reading = pause.reading.lock();
+                match event::poll(INPUT_POLL) {
+                    Ok(true) => event::read(),
+                    Ok(false) => continue,
+                    Err(e) => Err(e),
+                }
+            };
+            let Ok(event) = event else {
+                break;
+            };
+            if let Event::Key(key) = &event {
+                let ctrl = key.modifiers.contains(KeyModifiers::CONTROL);
+                let interrupt = ctrl && key.code == KeyCode::Char('c');
+                let answer = match key.code {
+                    _ if key.kind != KeyEventKind::Press => None,
+                    KeyCode::Esc => Some('\u{1b}'),
+                    KeyCode::Char(_) if interrupt => Some('\u{1b}'),
+                    KeyCode::Char(c) if !ctrl => Some(c),
+                    _ => None,
+                };
+                let answered = answer.is_some_and(|answer| {
+                    state
+                        .lock()
+                        .map(|mut state| state.answer_approval(answer))
+                        .unwrap_or(false)
+                });
+                if answered && !interrupt {
+                    continue;
+                }
+            }
+            if tx.send(event).is_err() {
+                break;
+            }
+        });
+        rx
+    }
+
+    /// Draw the terminal UI; generic so tests can draw into a `TestBackend`
+    fn draw<B: Backend>(
+        terminal: &mut Terminal<B>,
         state: &mut TerminalState,
     ) -> Result<()> {
         terminal.draw(|f| {
@@ -715,6 +1002,7 @@ impl RetroTui {
             Self::draw_input_area(f, chunks[0], &state.input_buffer, state.cursor_position, state.cursor_blink, state.is_processing, &state.theme);
 
             // Draw main output area
+            state.output_area = chunks[1];
             Self::draw_output_area(f, chunks[1], state, &state.output_history, state.scroll_offset, &state.theme);
             
             // Draw activity area only if it's visible (during animation or when shown)
@@ -739,6 +1027,27 @@ impl RetroTui {
                 state.status_blink,
                 &state.theme,
             );
+
+            if state.log_pane_open {
+                Self::draw_log_pane(f, chunks[1], state.log_level, &state.theme);
+            }
+
+            if state.help_open {
+                Self::draw_help_overlay(f, size, &state.theme);
+            }
+
+            if let Some(view) = &state.branches {
+                Self::draw_branches_view(f, size, view, &state.theme);
+            }
+
+            if let Some(palette) = &state.palette {
+                Self::draw_command_palette(f, size, palette, &state.theme);
+            }
+
+            // The approval dialog goes on top of everything else
+            if let Some(dialog) = &state.approval {
+                Self::draw_approval_dialog(f, size, &dialog.request, &state.theme);
+            }
         })?;
 
         Ok(())
@@ -800,7 +1109,7 @@ impl RetroTui {
             .style(Style::default().fg(theme.terminal_green.to_color()))
             .block(
                 Block::default()
-                    .title(" COMMAND INPUT ")
+                    .title(format!(" {} ", t(Msg::CommandInputTitle)))
                     .title_alignment(Alignment::Center)
                     .borders(Borders::ALL)
                     .border_style(Style::default().fg(theme.terminal_dim_green.to_color()))
@@ -811,6 +1120,39 @@ impl RetroTui {
     }
 
     /// Draw the main output area
+    /// Turn the file references drawn in `area` into OSC 8 links. Like
+    /// ratatui's hyperlink example, each cell pair of a reference gets the
+    /// link around both its characters and the second cell is skipped, so
+    /// the escape sequence doesn't throw off ratatui's cell widths.
+    fn link_file_refs(buffer: &mut Buffer, area: Rect) {
+        let (Some(template), Ok(base)) = (links::link_template(), std::env::current_dir()) else {
+            return;
+        };
+        for y in area.top()..area.bottom() {
+            let mut text = String::new();
+            let mut starts = Vec::new();
+            for x in area.left()..area.right() {
+                starts.push((x, text.len()));
+                text.push_str(buffer[(x, y)].symbol());
+            }
+            for file_ref in links::find_file_refs(&text, &base) {
+                let url = links::url(&template, &file_ref);
+                let cells: Vec<u16> = starts
+                    .iter()
+                    .filter(|(_, start)| file_ref.range.contains(start))
+                    .map(|&(x, _)| x)
+                    .collect();
+                for pair in cells.chunks(2) {
+                    let text: String = pair.iter().map(|&x| buffer[(x, y)].symbol()).collect();
+                    buffer[(pair[0], y)].set_symbol(&links::hyperlink(&url, &text));
+                    if let Some(&x) = pair.get(1) {
+                        buffer[(x, y)].set_skip(true);
+                    }
+                }
+            }
+        }
+    }
+
     fn draw_output_area(
         f: &mut Frame,
         area: Rect,
@@ -823,135 +1165,181 @@ impl RetroTui {
         let visible_height = area.height.saturating_sub(2) as usize; // Account for padding
         let total_lines = output_history.len();
 
-        // Calculate the proper scroll position
-        let scroll = if total_lines <= visible_height {
-            // If all content fits, no scrolling needed
-            0
-        } else {
-            // Allow scrolling SCROLL_PAST_END_BUFFER lines past the normal end
-            // This provides a buffer to ensure no content is cut off
-            let max_scroll_with_buffer = total_lines.saturating_sub(visible_height).saturating_add(SCROLL_PAST_END_BUFFER);
-            
-            // If the requested scroll would show past the end, adjust it
-            if scroll_offset > max_scroll_with_buffer {
-                max_scroll_with_buffer
-            } else {
-                scroll_offset
-            }
-        };
+        let scroll = output_scroll(total_lines, visible_height, scroll_offset);
 
         let mut in_code_block = false;
 
-        // Get visible lines
-        let visible_lines: Vec<Line> = output_history
-            .iter()
-            .skip(scroll)
-            .take(visible_height)
-            .map(|line| {
-                // Check if this is a tool header line
-                if line.starts_with("[TOOL_HEADER]") {
-                    // Extract the actual header text
-                    let cleaned = line.replace("[TOOL_HEADER]", "");
-                    // Style with amber background and black text
-                    return Line::from(Span::styled(
-                        format!(" {}", cleaned),
-                        Style::default()
-                            .bg(theme.terminal_amber.to_color()) 
-                            .fg(Color::Black)
-                            .add_modifier(Modifier::BOLD),
-                    ));
-                } else if line.starts_with("[SUCCESS]") {
-                    // Extract the actual header text
-                    let cleaned = line.replace("[SUCCESS]", "");
-                    // Style with green background for successful tool completion
-                    return Line::from(Span::styled(
-                        format!(" {}", cleaned),
-                        Style::default()
-                            .bg(theme.terminal_success.to_color())  // Use dedicated success color
-                            .fg(Color::Black)
-                            .add_modifier(Modifier::BOLD),
-                    ));
-                } else if line.starts_with("[FAILED]") {
-                    // Extract the actual header text
-                    let cleaned = line.replace("[FAILED]", "");
-                    // Style with red background for failed tool completion
-                    return Line::from(Span::styled(
-                        format!(" {}", cleaned),
-                        Style::default()
-                            .bg(theme.terminal_red.to_color())
-                            .fg(Color::Black)
-                            .add_modifier(Modifier::BOLD),
-                    ));
-                }
+        // Style a line of output
+        let mut style_line = |line: &String| {
+            // A lone padding space would wrap into two rows
+            if line.is_empty() {
+                return Line::default();
+            }
 
-                // Check for code block boundaries
-                if line.starts_with("```") {
-                    in_code_block = !in_code_block;
-                }
+            // Colored command output keeps its own colors
+            if line.contains('\x1b') {
+                return ansi::to_line(
+                    &format!(" {}", line),
+                    Style::default().fg(theme.terminal_green.to_color()),
+                );
+            }
 
-                // If we're in a code block, style it appropriately
-                if in_code_block && !line.starts_with("```") {
-                    return Line::from(Span::styled(
-                        format!(" {}", line),
-                        Style::default()
-                            .fg(theme.terminal_cyan.to_color())
-                            .bg(Color::Rgb(40, 42, 54)),
-                    ));
-                }
+            // Check if this is a tool header line
+            if line.starts_with("[TOOL_HEADER]") {
+                // Extract the actual header text
+                let cleaned = line.replace("[TOOL_HEADER]", "");
+                // Style with amber background and black text
+                return Line::from(Span::styled(
+                    format!(" {}", cleaned),
+                    Style::default()
+                        .bg(theme.terminal_amber.to_color()) 
+                        .fg(Color::Black)
+                        .add_modifier(Modifier::BOLD),
+                ));
+            } else if line.starts_with("[SUCCESS]") {
+                // Extract the actual header text
+                let cleaned = line.replace("[SUCCESS]", "");
+                // Style with green background for successful tool completion
+                return Line::from(Span::styled(
+                    format!(" {}", cleaned),
+                    Style::default()
+                        .bg(theme.terminal_success.to_color())  // Use dedicated success color
+                        .fg(Color::Black)
+                        .add_modifier(Modifier::BOLD),
+                ));
+            } else if line.starts_with("[FAILED]") {
+                // Extract the actual header text
+                let cleaned = line.replace("[FAILED]", "");
+                // Style with red background for failed tool completion
+                return Line::from(Span::styled(
+                    format!(" {}", cleaned),
+                    Style::default()
+                        .bg(theme.terminal_red.to_color())
+                        .fg(Color::Black)
+                        .add_modifier(Modifier::BOLD),
+                ));
+            }
 
-                // Check if this is a box border line
-                if line.starts_with("┌")
-                    || line.starts_with("└")
-                    || line.starts_with("│")
-                    || line.starts_with("├")
-                {
-                    return Line::from(Span::styled(
-                        format!(" {}", line),
-                        Style::default().fg(theme.terminal_dim_green.to_color()),
-                    ));
-                }
+            // Check for code block boundaries
+            if line.starts_with("```") {
+                in_code_block = !in_code_block;
+            }
 
-                // Don't apply markdown parsing to tool status lines - preserve their original styling
-                if line.starts_with("[SUCCESS]") || line.starts_with("[FAILED]") || line.starts_with("[TOOL_HEADER]") {
-                    // These are already handled above, this shouldn't be reached
-                    // but just in case, return the line as-is with appropriate color
-                    return Line::from(Span::styled(
-                        format!(" {}", line),
-                        Style::default().fg(theme.terminal_green.to_color()),
-                    ));
-                }
+            // If we're in a code block, style it appropriately
+            if in_code_block && !line.starts_with("```") {
+                return Line::from(Span::styled(
+                    format!(" {}", line),
+                    Style::default()
+                        .fg(theme.terminal_cyan.to_color())
+                        .bg(Color::Rgb(40, 42, 54)),
+                ));
+            }
 
-                // Check if line contains markdown formatting
-                if line.contains("**") || line.contains('`') || line.starts_with('#') {
-                    // Use the markdown parser
-                    return state.parse_markdown_line(line);
-                }
+            // Check if this is a box border line
+            if line.starts_with("┌")
+                || line.starts_with("└")
+                || line.starts_with("│")
+                || line.starts_with("├")
+            {
+                return Line::from(Span::styled(
+                    format!(" {}", line),
+                    Style::default().fg(theme.terminal_dim_green.to_color()),
+                ));
+            }
 
-                // Apply different colors based on content (existing logic)
-                let style = if line.starts_with("ERROR:") {
-                    Style::default()
-                        .fg(theme.terminal_red.to_color())
-                        .add_modifier(Modifier::BOLD)
-                } else if line.starts_with('>') {
-                    Style::default().fg(theme.terminal_cyan.to_color())
-                } else if line.starts_with("SYSTEM:")
-                    || line.starts_with("WEYLAND")
-                    || line.starts_with("MU/TH/UR")
-                {
-                    Style::default()
+            // Don't apply markdown parsing to tool status lines - preserve their original styling
+            if line.starts_with("[SUCCESS]") || line.starts_with("[FAILED]") || line.starts_with("[TOOL_HEADER]") {
+                // These are already handled above, this shouldn't be reached
+                // but just in case, return the line as-is with appropriate color
+                return Line::from(Span::styled(
+                    format!(" {}", line),
+                    Style::default().fg(theme.terminal_green.to_color()),
+                ));
+            }
+
+            // Check if line contains markdown formatting
+            if line.contains("**") || line.contains('`') || line.starts_with('#') {
+                // Use the markdown parser
+                return state.parse_markdown_line(line);
+            }
+
+            // Apply different colors based on content (existing logic)
+            let style = if line.starts_with("ERROR:") {
+                Style::default()
+                    .fg(theme.terminal_red.to_color())
+                    .add_modifier(Modifier::BOLD)
+            } else if line.starts_with('>') {
+                Style::default().fg(theme.terminal_cyan.to_color())
+            } else if line.starts_with("SYSTEM:")
+                || line.starts_with("WEYLAND")
+                || line.starts_with("MU/TH/UR")
+            {
+                Style::default()
+                    .fg(theme.terminal_amber.to_color())
+                    .add_modifier(Modifier::BOLD)
+            } else if line.starts_with("SYSTEM INITIALIZED")
+                || line.starts_with("AWAITING COMMAND")
+            {
+                Style::default()
+                    .fg(theme.terminal_dim_green.to_color())
+                    .add_modifier(Modifier::BOLD)
+            } else {
+                Style::default().fg(theme.terminal_green.to_color())
+            };
+
+            Line::from(Span::styled(format!(" {}", line), style))
+        };
+
+        // Tables are laid out as a whole, and a row of one can take up
+        // several lines on screen
+        let table_width = area.width.saturating_sub(4) as usize;
+        let tables = markdown_table::find_tables(output_history);
+        let end = (scroll + visible_height).min(total_lines);
+        let mut rows: Vec<(usize, Line)> = Vec::new();
+        let mut index = scroll;
+        while index < end {
+            let Some(table) = tables.iter().find(|table| table.contains(&index)) else {
+                rows.push((index, style_line(&output_history[index])));
+                index += 1;
+                continue;
+            };
+            let rendered = markdown_table::render(&output_history[table.clone()], table_width);
+            for (row, lines) in rendered
+                .into_iter()
+                .enumerate()
+                .skip(index - table.start)
+                .take(end - index)
+            {
+                let style = match row {
+                    0 => Style::default()
                         .fg(theme.terminal_amber.to_color())
-                        .add_modifier(Modifier::BOLD)
-                } else if line.starts_with("SYSTEM INITIALIZED")
-                    || line.starts_with("AWAITING COMMAND")
-                {
-                    Style::default()
-                        .fg(theme.terminal_dim_green.to_color())
-                        .add_modifier(Modifier::BOLD)
-                } else {
-                    Style::default().fg(theme.terminal_green.to_color())
+                        .add_modifier(Modifier::BOLD),
+                    1 => Style::default().fg(theme.terminal_dim_green.to_color()),
+                    _ => Style::default().fg(theme.terminal_green.to_color()),
                 };
+                rows.extend(lines.into_iter().map(|text| {
+                    (
+                        table.start + row,
+                        Line::from(Span::styled(format!(" {}", text), style)),
+                    )
+                }));
+            }
+            index = table.end.min(end);
+        }
+        // When following the output, keep its end in view
+        if end == total_lines && rows.len() > visible_height {
+            rows.drain(..rows.len() - visible_height);
+        }
+        rows.truncate(visible_height);
 
-                Line::from(Span::styled(format!(" {}", line), style))
+        let visible_lines: Vec<Line> = rows
+            .into_iter()
+            .map(|(index, line)| {
+                if state.is_selected(index) {
+                    line.patch_style(Style::default().add_modifier(Modifier::REVERSED))
+                } else {
+                    line
+                }
             })
             .collect();
 
@@ -967,6 +1355,13 @@ impl RetroTui {
             .wrap(Wrap { trim: false });
 
         f.render_widget(output, area);
+        Self::link_file_refs(
+            f.buffer_mut(),
+            area.inner(ratatui::layout::Margin {
+                vertical: 1,
+                horizontal: 1,
+            }),
+        );
 
         // Draw scrollbar if needed
         if total_lines > visible_height {
@@ -1056,7 +1451,7 @@ impl RetroTui {
                     } else {
                         Style::default().fg(fade_color(theme.terminal_green.to_color()))
                     };
-                    Line::from(Span::styled(format!(" {}", line), style))
+                    ansi::to_line(&format!(" {}", line), style)
                 })
                 .collect()
         };
@@ -1064,7 +1459,7 @@ impl RetroTui {
         let tool_output = Paragraph::new(visible_lines)
             .block(
                 Block::default()
-                    .title(" TOOL DETAIL ")
+                    .title(format!(" {} ", t(Msg::ToolDetailTitle)))
                     .title_alignment(Alignment::Center)
                     .borders(Borders::ALL)
                     .border_style(Style::default().fg(fade_color(theme.terminal_dim_green.to_color())))
@@ -1102,7 +1497,7 @@ impl RetroTui {
         
         // Create the chart block
         let block = Block::default()
-            .title(" ACTIVITY ")
+            .title(format!(" {} ", t(Msg::ActivityTitle)))
             .title_alignment(Alignment::Center)
             .borders(Borders::ALL)
             .border_style(Style::default().fg(fade_color(theme.terminal_dim_green.to_color())))
@@ -1218,16 +1613,20 @@ impl RetroTui {
 
         let (_, model) = provider_info;
 
-        // Determine status color based on status text
+        // Determine status color based on status text; the two states the
+        // TUI tracks are shown in the configured locale
         let (status_color, status_text) = if status_line == "PROCESSING" {
             // Blink the PROCESSING status
             if status_blink {
-                (theme.terminal_dark_amber.to_color(), status_line)
+                (
+                    theme.terminal_dark_amber.to_color(),
+                    t(Msg::StatusProcessing),
+                )
             } else {
                 (theme.terminal_bg.to_color(), "         ") // Hide text by matching background
             }
         } else if status_line == "READY" {
-            (theme.terminal_pale_blue.to_color(), status_line)
+            (theme.terminal_pale_blue.to_color(), t(Msg::StatusReady))
         } else {
             // Default to amber for other statuses
             (theme.terminal_amber.to_color(), status_line)
@@ -1236,7 +1635,7 @@ impl RetroTui {
         // Build the status line with different colored spans
         let status_spans = vec![
             Span::styled(
-                " STATUS: ",
+                format!(" {}: ", t(Msg::StatusLabel)),
                 Style::default()
                     .fg(theme.terminal_amber.to_color())
                     .add_modifier(Modifier::BOLD),
@@ -1248,7 +1647,7 @@ impl RetroTui {
                     .add_modifier(Modifier::BOLD),
             ),
             Span::styled(
-                " | CONTEXT: ",
+                format!(" | {}: ", t(Msg::ContextLabel)),
                 Style::default()
                     .fg(theme.terminal_amber.to_color())
                     .add_modifier(Modifier::BOLD),
@@ -1282,6 +1681,359 @@ impl RetroTui {
         f.render_widget(status, area);
     }
 
+    /// Draw the tool approval dialog centered over `area`
+    fn draw_approval_dialog(f: &mut Frame, area: Rect, request: &ApprovalRequest, theme: &ColorTheme) {
+        let width = area.width.saturating_sub(8).clamp(20, 100);
+        let height = area.height.saturating_sub(4).clamp(8, 30);
+        let dialog_area = Rect {
+            x: area.x + area.width.saturating_sub(width) / 2,
+            y: area.y + area.height.saturating_sub(height) / 2,
+            width: width.min(area.width),
+            height: height.min(area.height),
+        };
+
+        let label = Style::default()
+            .fg(theme.terminal_amber.to_color())
+            .add_modifier(Modifier::BOLD);
+        let text = Style::default().fg(theme.terminal_green.to_color());
+
+        // Field names padded to the longest one in the current locale
+        let field_width = [Msg::ApprovalTool, Msg::ApprovalArgs, Msg::ApprovalRisk]
+            .iter()
+            .map(|msg| t(*msg).chars().count() + 1)
+            .max()
+            .unwrap_or(0);
+        let field =
+            |msg: Msg| format!(" {:<width$}  ", format!("{}:", t(msg)), width = field_width);
+
+        let mut lines = vec![
+            Line::from(vec![
+                Span::styled(field(Msg::ApprovalTool), label),
+                Span::styled(format!("{} ({})", request.tool.to_uppercase(), request.class), text),
+            ]),
+            Line::from(vec![
+                Span::styled(field(Msg::ApprovalArgs), label),
+                Span::styled(request.args_preview.clone(), text),
+            ]),
+        ];
+        if let Some(risk) = &request.risk {
+            for (i, risk_line) in risk.lines().enumerate() {
+                let prefix = if i == 0 {
+                    field(Msg::ApprovalRisk)
+                } else {
+                    " ".repeat(field_width + 3)
+                };
+                lines.push(Line::from(vec![
+                    Span::styled(prefix, label),
+                    Span::styled(
+                        risk_line.to_uppercase(),
+                        Style::default()
+                            .fg(theme.terminal_red.to_color())
+                            .add_modifier(Modifier::BOLD),
+                    ),
+                ]));
+            }
+        }
+        if let Some(diff) = &request.diff {
+            lines.push(Line::from(""));
+            for diff_line in diff.lines() {
+                let color = if diff_line.starts_with("+++") || diff_line.starts_with("---") {
+                    theme.terminal_amber.to_color()
+                } else if diff_line.starts_with('+') {
+                    theme.terminal_success.to_color()
+                } else if diff_line.starts_with('-') {
+                    theme.terminal_red.to_color()
+                } else if diff_line.starts_with("@@") {
+                    theme.terminal_cyan.to_color()
+                } else {
+                    theme.terminal_dim_green.to_color()
+                };
+                lines.push(Line::from(Span::styled(format!(" {}", diff_line), Style::default().fg(color))));
+            }
+        }
+
+        // Keep the key legend visible below the (possibly clipped) body
+        let chunks = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Min(1), Constraint::Length(1)])
+            .split(Block::default().borders(Borders::ALL).inner(dialog_area));
+
+        let key = |k: &'static str, action: Msg| {
+            vec![
+                Span::styled(
+                    k,
+                    Style::default()
+                        .bg(theme.terminal_amber.to_color())
+                        .fg(Color::Black)
+                        .add_modifier(Modifier::BOLD),
+                ),
+                Span::styled(format!(" {}  ", t(action)), text),
+            ]
+        };
+        let legend: Vec<Span> = [
+            key(" Y ", Msg::ApprovalAllow),
+            key(" N ", Msg::ApprovalDeny),
+            key(" A ", Msg::ApprovalAlways),
+            key(" D ", Msg::ApprovalDenyAlways),
+        ]
+        .into_iter()
+        .flatten()
+        .collect();
+
+        f.render_widget(Clear, dialog_area);
+        f.render_widget(
+            Block::default()
+                .title(format!(" {} ", t(Msg::ApprovalTitle)))
+                .title_alignment(Alignment::Center)
+                .borders(Borders::ALL)
+                .border_style(Style::default().fg(theme.terminal_amber.to_color()))
+                .style(Style::default().bg(theme.terminal_bg.to_color())),
+            dialog_area,
+        );
+        f.render_widget(Paragraph::new(lines).wrap(Wrap { trim: false }), chunks[0]);
+        f.render_widget(Paragraph::new(Line::from(legend)).alignment(Alignment::Center), chunks[1]);
+    }
+
+    /// Draw the command palette near the top of the screen
+    fn draw_command_palette(f: &mut Frame, area: Rect, palette: &CommandPalette, theme: &ColorTheme) {
+        let matches = palette.matches();
+        let width = area.width.saturating_sub(8).clamp(20, 72);
+        let height = (matches.len() as u16 + 5).clamp(6, area.height.saturating_sub(4).max(6));
+        let palette_area = Rect {
+            x: area.x + area.width.saturating_sub(width) / 2,
+            y: area.y + area.height.saturating_sub(height) / 4,
+            width: width.min(area.width),
+            height: height.min(area.height),
+        };
+
+        let label = Style::default()
+            .fg(theme.terminal_amber.to_color())
+            .add_modifier(Modifier::BOLD);
+        let text = Style::default().fg(theme.terminal_green.to_color());
+        let dim = Style::default().fg(theme.terminal_dim_green.to_color());
+
+        let chunks = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Length(1), Constraint::Min(1), Constraint::Length(1)])
+            .split(Block::default().borders(Borders::ALL).inner(palette_area));
+
+        let query = Line::from(vec![
+            Span::styled(" > ", label),
+            Span::styled(palette.query().to_string(), text),
+            Span::styled("█", text),
+        ]);
+
+        // Keep the selection in view when the list is taller than the box
+        let visible = chunks[1].height as usize;
+        let first = palette.selected().saturating_sub(visible.saturating_sub(1));
+        let mut lines: Vec<Line> = matches
+            .iter()
+            .enumerate()
+            .skip(first)
+            .take(visible)
+            .map(|(i, entry)| {
+                let selected = i == palette.selected();
+                let style = if selected {
+                    Style::default()
+                        .bg(theme.terminal_amber.to_color())
+                        .fg(Color::Black)
+                        .add_modifier(Modifier::BOLD)
+                } else {
+                    text
+                };
+                let mut spans = vec![Span::styled(format!(" {} ", entry.label.to_uppercase()), style)];
+                if let PaletteAction::Menu(_) = entry.action {
+                    spans.push(Span::styled("› ", style));
+                }
+                if !entry.detail.is_empty() {
+                    spans.push(Span::styled(format!(" {}", entry.detail), dim));
+                }
+                if let Some(keys) = entry.keys {
+                    spans.push(Span::styled(format!("  [{}]", keys), label));
+                }
+                Line::from(spans)
+            })
+            .collect();
+        if lines.is_empty() {
+            lines.push(Line::from(Span::styled(
+                format!(" {}", t(Msg::PaletteNoMatches)),
+                dim,
+            )));
+        }
+
+        let legend = Line::from(vec![
+            Span::styled("↑↓", label),
+            Span::styled(format!(" {}  ", t(Msg::PaletteSelect)), dim),
+            Span::styled("ENTER", label),
+            Span::styled(format!(" {}  ", t(Msg::PaletteRun)), dim),
+            Span::styled("ESC", label),
+            Span::styled(format!(" {}", t(Msg::PaletteBack)), dim),
+        ]);
+
+        f.render_widget(Clear, palette_area);
+        f.render_widget(
+            Block::default()
+                .title(format!(" {} ", palette.breadcrumb().to_uppercase()))
+                .title_alignment(Alignment::Center)
+                .borders(Borders::ALL)
+                .border_style(Style::default().fg(theme.terminal_amber.to_color()))
+                .style(Style::default().bg(theme.terminal_bg.to_color())),
+            palette_area,
+        );
+        f.render_widget(Paragraph::new(query), chunks[0]);
+        f.render_widget(Paragraph::new(lines), chunks[1]);
+        f.render_widget(Paragraph::new(legend).alignment(Alignment::Center), chunks[2]);
+    }
+
+    /// Draw the `:help` overlay: the chat commands and the keys, from the
+    /// same registry as the palette's key hints
+    fn draw_help_overlay(f: &mut Frame, area: Rect, theme: &ColorTheme) {
+        let sections = [
+            (t(Msg::PaletteCommands), commands::command_rows()),
+            (t(Msg::HelpKeysTitle), commands::key_rows()),
+        ];
+        let rows = sections
+            .iter()
+            .map(|(_, rows)| rows.len() + 2)
+            .sum::<usize>() as u16;
+        let width = area.width.saturating_sub(8).clamp(20, 100);
+        let height = (rows + 2).min(area.height);
+        let help_area = Rect {
+            x: area.x + area.width.saturating_sub(width) / 2,
+            y: area.y + area.height.saturating_sub(height) / 2,
+            width: width.min(area.width),
+            height,
+        };
+
+        let label = Style::default()
+            .fg(theme.terminal_amber.to_color())
+            .add_modifier(Modifier::BOLD);
+        let text = Style::default().fg(theme.terminal_green.to_color());
+        let dim = Style::default().fg(theme.terminal_dim_green.to_color());
+
+        let mut lines: Vec<Line> = Vec::new();
+        for (title, rows) in &sections {
+            lines.push(Line::from(Span::styled(
+                format!(" {}", title.to_uppercase()),
+                label,
+            )));
+            let width = rows
+                .iter()
+                .map(|(usage, _)| usage.chars().count())
+                .max()
+                .unwrap_or(0);
+            for (usage, help) in rows {
+                lines.push(Line::from(vec![
+                    Span::styled(format!("  {:<width$}  ", usage, width = width), text),
+                    Span::styled(help.to_string(), dim),
+                ]));
+            }
+            lines.push(Line::from(""));
+        }
+
+        f.render_widget(Clear, help_area);
+        f.render_widget(
+            Paragraph::new(lines).wrap(Wrap { trim: false }).block(
+                Block::default()
+                    .title(format!(" {} ", t(Msg::HelpOverlayTitle)))
+                    .title_alignment(Alignment::Center)
+                    .borders(Borders::ALL)
+                    .border_style(Style::default().fg(theme.terminal_amber.to_color()))
+                    .style(Style::default().bg(theme.terminal_bg.to_color())),
+            ),
+            help_area,
+        );
+    }
+
+    /// Draw the `:branches` view in the middle of the screen, the selected
+    /// branch highlighted
+    fn draw_branches_view(f: &mut Frame, area: Rect, view: &BranchesView, theme: &ColorTheme) {
+        let width = area.width.saturating_sub(8).clamp(20, 100);
+        let height = (view.rows.len() as u16 + 4).min(area.height);
+        let view_area = Rect {
+            x: area.x + area.width.saturating_sub(width) / 2,
+            y: area.y + area.height.saturating_sub(height) / 2,
+            width: width.min(area.width),
+            height,
+        };
+
+        let text = Style::default().fg(theme.terminal_green.to_color());
+        let selected = Style::default()
+            .fg(theme.terminal_bg.to_color())
+            .bg(theme.terminal_amber.to_color())
+            .add_modifier(Modifier::BOLD);
+        let dim = Style::default().fg(theme.terminal_dim_green.to_color());
+
+        let mut lines: Vec<Line> = branches::render(&view.rows)
+            .lines()
+            .enumerate()
+            .map(|(index, row)| {
+                let style = if index == view.selected {
+                    selected
+                } else {
+                    text
+                };
+                Line::from(Span::styled(format!(" {}", row), style))
+            })
+            .collect();
+        lines.push(Line::from(""));
+        lines.push(Line::from(Span::styled(
+            format!(" {}", t(Msg::BranchesKeyHelp)),
+            dim,
+        )));
+
+        f.render_widget(Clear, view_area);
+        f.render_widget(
+            Paragraph::new(lines).block(
+                Block::default()
+                    .title(format!(" {} ", t(Msg::BranchesTitle)))
+                    .title_alignment(Alignment::Center)
+                    .borders(Borders::ALL)
+                    .border_style(Style::default().fg(theme.terminal_amber.to_color()))
+                    .style(Style::default().bg(theme.terminal_bg.to_color())),
+            ),
+            view_area,
+        );
+    }
+
+    /// Draw the log pane over the lower half of the output area, newest
+    /// events at the bottom
+    fn draw_log_pane(f: &mut Frame, area: Rect, level: tracing::Level, theme: &ColorTheme) {
+        let height = (area.height / 2).max(3).min(area.height);
+        let pane_area = Rect {
+            x: area.x,
+            y: area.y + area.height - height,
+            width: area.width,
+            height,
+        };
+
+        let dim = Style::default().fg(theme.terminal_dim_green.to_color());
+        let warn = Style::default().fg(theme.terminal_amber.to_color());
+        let lines: Vec<Line> = log_pane::recent(level, height.saturating_sub(2) as usize)
+            .iter()
+            .map(|event| {
+                let style = if event.level <= tracing::Level::WARN {
+                    warn
+                } else {
+                    dim
+                };
+                Line::from(Span::styled(event.to_string(), style))
+            })
+            .collect();
+
+        f.render_widget(Clear, pane_area);
+        f.render_widget(
+            Paragraph::new(lines).block(
+                Block::default()
+                    .title(format!(" {} [{}] ", t(Msg::LogPaneTitle), level))
+                    .borders(Borders::ALL)
+                    .border_style(Style::default().fg(theme.terminal_amber.to_color()))
+                    .style(Style::default().bg(theme.terminal_bg.to_color())),
+            ),
+            pane_area,
+        );
+    }
+
     /// Send output to the terminal
     pub fn output(&self, text: &str) {
         let _ = self.tx.send(TuiMessage::AgentOutput(text.to_string()));
@@ -1345,6 +2097,305 @@ impl RetroTui {
         let _ = self.tx.send(TuiMessage::Error(error.to_string()));
     }
 
+    /// Show the approval dialog and wait until the user decides. Called
+    /// from the agent's task, which holds its worker thread meanwhile
+    /// (`block_in_place` hands the thread's other tasks, the draw loop among
+    /// them, to the rest of the runtime); the key presses that answer come
+    /// from [`RetroTui::spawn_input`]'s thread.
+    pub fn request_approval(&self, request: &ApprovalRequest) -> ApprovalDecision {
+        let (reply, decision) = oneshot::channel();
+        if self.tx.send(TuiMessage::ApprovalRequest(request.clone(), reply)).is_err() {
+            return ApprovalDecision::Deny;
+        }
+        // A closed dialog without an answer (e.g. on exit) refuses the call
+        tokio::task::block_in_place(|| decision.blocking_recv()).unwrap_or(ApprovalDecision::Deny)
+    }
+
+    /// Set the providers the palette offers to switch to, e.g. the
+    /// configured `anthropic.default`, `openai.fast`
+    pub fn set_palette_providers(&self, providers: Vec<String>) {
+        if let Ok(mut state) = self.state.lock() {
+            state.palette_providers = providers;
+        }
+    }
+
+    /// Open the command palette (Ctrl+P)
+    pub fn open_palette(&self) {
+        if let Ok(mut state) = self.state.lock() {
+            let entries = builtin_entries(
+                &BUILTIN_THEMES.iter().map(|theme| theme.to_string()).collect::<Vec<_>>(),
+                &state.palette_providers,
+            );
+            state.palette = Some(CommandPalette::new(entries));
+        }
+    }
+
+    /// Whether the command palette is open and key presses should go to
+    /// [`RetroTui::palette_key`] instead of the input line
+    pub fn has_open_palette(&self) -> bool {
+        self.state.lock().map(|state| state.palette.is_some()).unwrap_or(false)
+    }
+
+    /// Pass a key to the open command palette. Returns the command the user
+    /// chose, which closes the palette; the caller runs it. Theme and
+    /// activity panel commands have already been applied to the TUI.
+    pub fn palette_key(&self, key: KeyCode) -> Option<PaletteCommand> {
+        let mut state = self.state.lock().ok()?;
+        let palette = state.palette.as_mut()?;
+        let command = match key {
+            KeyCode::Esc => {
+                if !palette.back() {
+                    state.palette = None;
+                }
+                None
+            }
+            KeyCode::Enter => palette.choose(),
+            KeyCode::Up => {
+                palette.select_previous();
+                None
+            }
+            KeyCode::Down | KeyCode::Tab => {
+                palette.select_next();
+                None
+            }
+            KeyCode::Backspace => {
+                palette.pop_char();
+                None
+            }
+            KeyCode::Char(c) => {
+                palette.push_char(c);
+                None
+            }
+            _ => None,
+        }?;
+
+        state.palette = None;
+        match &command {
+            PaletteCommand::SwitchTheme(name) => match ColorTheme::load(Some(name)) {
+                Ok(theme) => state.theme = theme,
+                Err(e) => state.output_history.push(format!("ERROR: {}", e)),
+            },
+            PaletteCommand::ToggleActivityPanel => {
+                state.activity_hidden = !state.activity_hidden;
+                state.update_activity_target();
+            }
+            PaletteCommand::ToggleLogPane => state.log_pane_open = !state.log_pane_open,
+            _ => {}
+        }
+        Some(command)
+    }
+
+    /// Whether `input` is the command that opens the help overlay
+    pub fn is_help_command(input: &str) -> bool {
+        input.trim() == HELP_COMMAND
+    }
+
+    /// Open the `:help` overlay, or close it if it is open
+    pub fn toggle_help(&self) {
+        if let Ok(mut state) = self.state.lock() {
+            state.help_open = !state.help_open;
+        }
+    }
+
+    /// Whether the help overlay is open; Esc should go to
+    /// [`RetroTui::toggle_help`] to close it
+    pub fn has_open_help(&self) -> bool {
+        self.state
+            .lock()
+            .map(|state| state.help_open)
+            .unwrap_or(false)
+    }
+
+    /// Show the log pane (Ctrl+L), or hide it if it is shown
+    pub fn toggle_log_pane(&self) {
+        if let Ok(mut state) = self.state.lock() {
+            state.log_pane_open = !state.log_pane_open;
+        }
+    }
+
+    /// Handle `input` if it is `:logs <level>` (or chat mode's `/logs`):
+    /// show the log pane filtered to that level. Returns whether it was
+    pub fn log_command(&self, input: &str) -> bool {
+        let mut words = input.split_whitespace();
+        if !matches!(words.next(), Some(LOGS_COMMAND | "/logs")) {
+            return false;
+        }
+        if let Ok(mut state) = self.state.lock() {
+            match words.next().map(log_pane::parse_level) {
+                None => state.log_pane_open = true,
+                Some(Some(level)) => {
+                    state.log_level = level;
+                    state.log_pane_open = true;
+                }
+                Some(None) => state.output_history.push(format!(
+                    "ERROR: Usage: {} [error|warn|info|debug]",
+                    LOGS_COMMAND
+                )),
+            }
+        }
+        true
+    }
+
+    /// Select output lines by dragging over them with the left mouse button,
+    /// for `/copy selection`; the terminal's own selection doesn't work
+    /// while the TUI captures the mouse
+    pub fn mouse_event(&self, event: MouseEvent) {
+        let Ok(mut state) = self.state.lock() else {
+            return;
+        };
+        match event.kind {
+            MouseEventKind::Down(MouseButton::Left) => {
+                state.selection = state.line_at(event.row).map(|line| (line, line));
+            }
+            MouseEventKind::Drag(MouseButton::Left) => {
+                if let (Some((anchor, _)), Some(line)) = (state.selection, state.line_at(event.row))
+                {
+                    state.selection = Some((anchor, line));
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// Handle `input` if it is `/copy [last|code|selection]` (or `:copy`):
+    /// put the last response, the last code block or the selected lines on
+    /// the system clipboard. Returns whether it was
+    pub fn copy_command(&self, input: &str) -> bool {
+        let Some(target) = clipboard::parse_copy_command(input) else {
+            return false;
+        };
+        if let Ok(mut state) = self.state.lock() {
+            let copied = target.and_then(|target| {
+                let text = state.copy_text(target)?;
+                clipboard::copy(&text)
+                    .map(|method| clipboard::copied_message(&text, method))
+                    .map_err(|e| e.to_string())
+            });
+            match copied {
+                Ok(done) => state.output_history.push(format!("SYSTEM: {}", done)),
+                Err(e) => state.output_history.push(format!("ERROR: {}", e)),
+            }
+        }
+        true
+    }
+
+    /// Open the file reference on the selected output line, or the last one
+    /// in the output (Ctrl+O), in `$VISUAL` / `$EDITOR` at its line. The TUI
+    /// gives the terminal to the editor until it exits, and the input thread
+    /// stops reading from it meanwhile.
+    pub fn follow_link(&self) {
+        let Ok(mut state) = self.state.lock() else {
+            return;
+        };
+        let file_ref = match state.link_under_cursor() {
+            Ok(file_ref) => file_ref,
+            Err(e) => {
+                state.output_history.push(format!("ERROR: {}", e));
+                return;
+            }
+        };
+        let Ok(mut term) = self.terminal.lock() else {
+            return;
+        };
+        self.input_pause.paused.store(true, Ordering::SeqCst);
+        let reading = self.input_pause.reading.lock();
+        let _ = disable_raw_mode();
+        let _ = execute!(
+            term.backend_mut(),
+            LeaveAlternateScreen,
+            DisableMouseCapture
+        );
+
+        let command = g3_planner::editor::editor_command();
+        let opened = match command.split_first() {
+            Some((program, args)) => std::process::Command::new(program)
+                .args(args)
+                .args(editor_args(program, &file_ref))
+                .status()
+                .map_err(|e| format!("Failed to launch editor '{}': {}", program, e))
+                .and_then(|status| {
+                    if status.success() {
+                        Ok(())
+                    } else {
+                        Err(format!("Editor '{}' exited with {}", program, status))
+                    }
+                }),
+            None => Err("No editor configured".to_string()),
+        };
+
+        let _ = enable_raw_mode();
+        let _ = execute!(term.backend_mut(), EnterAlternateScreen, EnableMouseCapture);
+        let _ = term.clear();
+        drop(reading);
+        self.input_pause.paused.store(false, Ordering::SeqCst);
+        if let Err(e) = opened {
+            state.output_history.push(format!("ERROR: {}", e));
+        }
+    }
+
+    /// Whether `input` is the command that opens the `:branches` view
+    pub fn is_branches_command(input: &str) -> bool {
+        input.trim() == BRANCHES_COMMAND
+    }
+
+    /// The `/undo [n]` command `input` stands for if it is `:undo [n]`; the
+    /// caller runs it like the chat command
+    pub fn undo_command(input: &str) -> Option<String> {
+        let count = input.trim().strip_prefix(UNDO_COMMAND)?;
+        (count.is_empty() || count.starts_with(' ')).then(|| format!("/undo{}", count))
+    }
+
+    /// Open the `:branches` view on `rows` (from
+    /// `Agent::branch_summaries`), the current branch selected
+    pub fn open_branches(&self, rows: Vec<BranchSummary>) {
+        if let Ok(mut state) = self.state.lock() {
+            let selected = rows.iter().position(|row| row.current).unwrap_or(0);
+            state.branches = Some(BranchesView { rows, selected });
+        }
+    }
+
+    /// Whether the `:branches` view is open and key presses should go to
+    /// [`RetroTui::branches_key`] instead of the input line
+    pub fn has_open_branches(&self) -> bool {
+        self.state
+            .lock()
+            .map(|state| state.branches.is_some())
+            .unwrap_or(false)
+    }
+
+    /// Pass a key to the open `:branches` view. Returns the action the user
+    /// chose on the selected branch (Enter switches to it, `d` discards it),
+    /// which closes the view; the caller runs it with `Agent::edit_branches`.
+    pub fn branches_key(&self, key: KeyCode) -> Option<(BranchAction, String)> {
+        let mut state = self.state.lock().ok()?;
+        let view = state.branches.as_mut()?;
+        let action = match key {
+            KeyCode::Esc => {
+                state.branches = None;
+                return None;
+            }
+            KeyCode::Up => {
+                view.selected = view.selected.saturating_sub(1);
+                return None;
+            }
+            KeyCode::Down | KeyCode::Tab => {
+                view.selected = (view.selected + 1).min(view.rows.len().saturating_sub(1));
+                return None;
+            }
+            KeyCode::Enter => BranchAction::Switch,
+            KeyCode::Char('d') => BranchAction::Discard,
+            _ => return None,
+        };
+        let row = view.rows.get(view.selected)?;
+        if row.current {
+            // Nothing to switch to, and the current branch can't be discarded
+            return None;
+        }
+        let name = row.name.clone();
+        state.branches = None;
+        Some((action, name))
+    }
+
     /// Signal exit
     pub fn exit(&self) {
         let _ = self.tx.send(TuiMessage::Exit);
@@ -1552,16 +2603,298 @@ impl RetroTui {
     }
 }
 
-impl Drop for RetroTui {
-    fn drop(&mut self) {
-        // Restore terminal
-        let _ = disable_raw_mode();
-        if let Ok(mut term) = self.terminal.lock() {
-            let _ = execute!(
-                term.backend_mut(),
-                LeaveAlternateScreen,
-                DisableMouseCapture
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use insta::{assert_debug_snapshot, assert_snapshot};
+    use ratatui::backend::TestBackend;
+
+    /// Terminal sizes every full-screen snapshot is taken at
+    const SIZES: [(u16, u16); 3] = [(80, 24), (120, 40), (50, 20)];
+
+    fn state() -> TerminalState {
+        let mut state = TerminalState::new(ColorTheme::default());
+        state.provider_info = ("mock".to_string(), "mock-model".to_string());
+        state.context_info = (12_000, 200_000, 6.0);
+        state
+    }
+
+    fn render(state: &mut TerminalState, width: u16, height: u16) -> Terminal<TestBackend> {
+        let mut terminal = Terminal::new(TestBackend::new(width, height)).unwrap();
+        RetroTui::draw(&mut terminal, state).unwrap();
+        terminal
+    }
+
+    /// Snapshot the whole screen at each of [`SIZES`]
+    fn assert_screens(name: &str, setup: impl Fn(&mut TerminalState)) {
+        for (width, height) in SIZES {
+            let mut state = state();
+            setup(&mut state);
+            let terminal = render(&mut state, width, height);
+            insta::with_settings!({ snapshot_suffix => format!("{}x{}", width, height) }, {
+                assert_snapshot!(name, terminal.backend());
+            });
+        }
+    }
+
+    #[test]
+    fn test_startup_screen() {
+        assert_screens("startup", |_| {});
+    }
+
+    #[test]
+    fn test_markdown_output() {
+        assert_screens("markdown", |state| {
+            state.add_output(
+                "\n# Plan\n## Steps\n### Details\nUse **bold** and *italic* with `code`.\n\
+                 ```rust\nfn main() {}\n```\n> quoted\nERROR: build failed\n\
+                 ┌──────┐\n│ box  │\n└──────┘\n",
             );
+        });
+    }
+
+    #[test]
+    fn test_tool_headers() {
+        assert_screens("tool_headers", |state| {
+            state.format_tool_output("read_file", "src/main.rs", "fn main() {}");
+            state.update_tool_completion("read_file", true, 42, "src/main.rs");
+            state.format_tool_output("shell", "cargo build", "error[E0425]");
+            state.update_tool_completion("shell", false, 2_500, "cargo build");
+            state.format_tool_output("write_file", "src/lib.rs", "");
+        });
+    }
+
+    #[test]
+    fn test_long_output_scrolls_to_the_end() {
+        let fill = |state: &mut TerminalState| {
+            for number in 1..=100 {
+                state.add_output(&format!("\nline {}", number));
+            }
+        };
+        assert_screens("scrolled_to_end", fill);
+
+        // Snapshots would also accept a last line that's cut off
+        for (width, height) in SIZES {
+            let mut state = state();
+            fill(&mut state);
+            let screen = render(&mut state, width, height).backend().to_string();
+            assert!(
+                screen.contains("line 100"),
+                "{}x{}:\n{}",
+                width,
+                height,
+                screen
+            );
+        }
+    }
+
+    #[test]
+    fn test_streamed_output_keeps_line_breaks() {
+        let mut state = state();
+        for chunk in ["\nHello ", "world\n", "Next", " line\r\n\n", "Done"] {
+            state.add_output(chunk);
+        }
+        assert_eq!(
+            state.output_history[state.output_history.len() - 4..],
+            ["Hello world", "Next line", "", "Done"]
+        );
+    }
+
+    #[test]
+    fn test_answer_approval() {
+        let mut state = state();
+        assert!(!state.answer_approval('y'));
+
+        let (reply, mut decision) = oneshot::channel();
+        state.approval = Some(ApprovalDialog {
+            request: ApprovalRequest {
+                tool: "shell".to_string(),
+                class: g3_core::permissions::ToolClass::Shell,
+                args_preview: r#"{"command":"rm -rf build"}"#.to_string(),
+                risk: None,
+                diff: None,
+            },
+            reply,
+        });
+        // Other keys leave the dialog open
+        assert!(!state.answer_approval('x'));
+        assert!(state.approval.is_some());
+        assert!(state.answer_approval('A'));
+        assert!(state.approval.is_none());
+        assert_eq!(decision.try_recv().unwrap(), ApprovalDecision::AlwaysAllow);
+    }
+
+    #[test]
+    fn test_branches_view() {
+        let row = |name: &str, parent: Option<&str>, current| BranchSummary {
+            name: name.to_string(),
+            parent: parent.map(str::to_string),
+            created_at: chrono::Utc::now(),
+            fork_point: 2,
+            messages: 4,
+            current,
+        };
+        let mut state = state();
+        state.branches = Some(BranchesView {
+            rows: vec![row("main", None, true), row("retry", Some("main"), false)],
+            selected: 1,
+        });
+        // Branch times are local, so the screen is checked instead of snapshotted
+        for (width, height) in SIZES {
+            let screen = render(&mut state, width, height).backend().to_string();
+            assert!(screen.contains("* main - 4 messages"), "{}", screen);
+            assert!(screen.contains("retry - 4 messages"), "{}", screen);
+        }
+    }
+
+    #[test]
+    fn test_undo_command() {
+        assert_eq!(RetroTui::undo_command(":undo").as_deref(), Some("/undo"));
+        assert_eq!(RetroTui::undo_command(" :undo 3 ").as_deref(), Some("/undo 3"));
+        assert_eq!(RetroTui::undo_command(":undone"), None);
+        assert_eq!(RetroTui::undo_command("/undo"), None);
+    }
+
+    #[test]
+    fn test_copy_text() {
+        let mut state = state();
+        state.add_output("\nWhat should I fix?");
+        state.response_start = state.output_history.len();
+        state.add_output("\nHere is the fix:\n```rust\nfn main() {}\n```\n");
+        state.format_tool_output("shell", "cargo build", "");
+
+        let last = state.copy_text(CopyTarget::Last).unwrap();
+        assert!(last.starts_with("Here is the fix:\n```rust"));
+        assert!(last.ends_with("SHELL | cargo build"));
+        assert_eq!(state.copy_text(CopyTarget::Code).unwrap(), "fn main() {}");
+        assert!(state.copy_text(CopyTarget::Selection).is_err());
+
+        // Mouse rows map to output lines inside the output area's padding
+        render(&mut state, 80, 24);
+        let top = state.output_area.y;
+        assert_eq!(state.line_at(top), None);
+        assert_eq!(state.line_at(top + 1), Some(0));
+        state.selection = Some((10, 8));
+        assert!(state.is_selected(9) && !state.is_selected(7));
+        assert_eq!(
+            state.copy_text(CopyTarget::Selection).unwrap(),
+            "```rust\nfn main() {}\n```"
+        );
+    }
+
+    #[test]
+    fn test_ansi_output() {
+        let mut state = state();
+        state.add_output("\n\x1b[1;31merror\x1b[0m: could not compile `g3`");
+        state.format_tool_output("shell", "cargo test", "\x1b[32mtest ok\x1b[0m");
+        state.activity_animation = 1.0;
+        let screen = render(&mut state, 80, 24).backend().to_string();
+        assert!(
+            screen.contains("error: could not compile `g3`"),
+            "{}",
+            screen
+        );
+        assert!(screen.contains("test ok"), "{}", screen);
+        assert!(
+            !screen.contains("[31m") && !screen.contains("[32m"),
+            "{}",
+            screen
+        );
+    }
+
+    #[test]
+    fn test_markdown_table() {
+        let mut state = state();
+        state.add_output(
+            "\n| Crate | Speed | Stars |\n|:--|:-:|--:|\n| **nom** | fast | 9000 |\n| pest | ok | 4 |",
+        );
+        let screen = render(&mut state, 80, 24).backend().to_string();
+        assert!(screen.contains("Crate  Speed  Stars"), "{}", screen);
+        assert!(screen.contains("─────  ─────  ─────"), "{}", screen);
+        assert!(screen.contains("nom    fast    9000"), "{}", screen);
+        assert!(screen.contains("pest    ok        4"), "{}", screen);
+        assert!(!screen.contains("|:--"), "{}", screen);
+    }
+
+    #[test]
+    fn test_file_links() {
+        let mut state = state();
+        state.add_output("\nerror[E0308]: mismatched types\n  --> src/lib.rs:10:5\n");
+        let terminal = render(&mut state, 80, 24);
+        let linked: Vec<&str> = terminal
+            .backend()
+            .buffer()
+            .content
+            .iter()
+            .map(|cell| cell.symbol())
+            .filter(|symbol| symbol.starts_with("\x1b]8;;file://"))
+            .collect();
+        assert_eq!(linked.len(), "src/lib.rs:10:5".len().div_ceil(2));
+        assert!(
+            linked[0].contains("/src/lib.rs\x1b\\sr\x1b]8;;"),
+            "{:?}",
+            linked
+        );
+
+        // Ctrl+O opens the last reference, or the one on the selected line
+        let file_ref = state.link_under_cursor().unwrap();
+        assert!(file_ref.path.ends_with("src/lib.rs"));
+        assert_eq!((file_ref.line, file_ref.column), (10, Some(5)));
+        state.selection = Some((0, 0));
+        assert!(state.link_under_cursor().is_err());
+        assert_eq!(editor_args("/usr/bin/nvim", &file_ref)[0], "+10");
+        assert_eq!(
+            editor_args("code", &file_ref)[1],
+            format!("{}:10:5", file_ref.path.display())
+        );
+    }
+
+    #[test]
+    fn test_status_bar() {
+        let cases = [
+            ("ready", "READY", true),
+            ("processing", "PROCESSING", true),
+            ("processing_blink_off", "PROCESSING", false),
+            ("custom", "CONTEXT THINNED", true),
+        ];
+        for (name, status, blink) in cases {
+            for width in [120, 80, 40] {
+                let mut terminal = Terminal::new(TestBackend::new(width, 1)).unwrap();
+                terminal
+                    .draw(|f| {
+                        RetroTui::draw_status_bar(
+                            f,
+                            f.area(),
+                            status,
+                            (150_000, 200_000, 75.0),
+                            &("mock".to_string(), "mock-model".to_string()),
+                            blink,
+                            &ColorTheme::default(),
+                        )
+                    })
+                    .unwrap();
+                insta::with_settings!({ snapshot_suffix => format!("{}_{}", name, width) }, {
+                    assert_snapshot!("status_bar", terminal.backend());
+                });
+            }
         }
     }
+
+    #[test]
+    fn test_parse_markdown_line() {
+        let state = state();
+        let lines: Vec<Line> = [
+            "# Title",
+            "## Section",
+            "### Subsection",
+            "```rust",
+            "Use **bold**, *italic* and `code` together",
+            "**unterminated bold",
+            "[SUCCESS] READ_FILE | src/main.rs | 42ms",
+        ]
+        .iter()
+        .map(|line| state.parse_markdown_line(line))
+        .collect();
+        assert_debug_snapshot!(lines);
+    }
 }
@@ -0,0 +1,178 @@
+//! Optional OpenTelemetry export of G3's trace spans.
+//!
+//! With `--otlp-endpoint` or `OTEL_EXPORTER_OTLP_ENDPOINT` set, the spans
+//! g3-core and g3-ensembles emit are sent over OTLP/gRPC:
+//! - `agent_turn`: one task, from the user's message to the final response
+//! - `provider_request`: one completion, with token counts as attributes
+//! - `tool_call`: one tool execution, with its duration and outcome
+//! - `flock_segment`: one flock worker, with its final state and token usage
+//!
+//! `OTEL_SERVICE_NAME` names the service (default: g3). Without an endpoint
+//! nothing is exported and the spans cost next to nothing.
+//!
+//! The exporter is behind the `otel` cargo feature (on by default). Without
+//! it `--otlp-endpoint` is an error and the environment variable is ignored.
+
+#[cfg(feature = "otel")]
+use anyhow::Context;
+use anyhow::Result;
+#[cfg(feature = "otel")]
+use opentelemetry::trace::TracerProvider as _;
+#[cfg(feature = "otel")]
+use opentelemetry::KeyValue;
+#[cfg(feature = "otel")]
+use opentelemetry_otlp::WithExportConfig;
+#[cfg(feature = "otel")]
+use opentelemetry_sdk::trace::TracerProvider;
+#[cfg(feature = "otel")]
+use opentelemetry_sdk::{runtime, Resource};
+use tracing::{Metadata, Subscriber};
+use tracing_subscriber::filter::filter_fn;
+use tracing_subscriber::registry::LookupSpan;
+use tracing_subscriber::Layer;
+
+use crate::Cli;
+
+/// Standard OpenTelemetry variable for the collector endpoint
+#[cfg(feature = "otel")]
+const ENDPOINT_ENV: &str = "OTEL_EXPORTER_OTLP_ENDPOINT";
+
+/// Exports spans for as long as it lives; dropping it flushes the rest
+#[cfg(feature = "otel")]
+pub struct Telemetry {
+    provider: TracerProvider,
+}
+
+/// Never exists in builds without the `otel` feature
+#[cfg(not(feature = "otel"))]
+pub enum Telemetry {}
+
+/// Whether `meta` is one of G3's own spans, the only thing exported
+fn is_g3_span(meta: &Metadata<'_>) -> bool {
+    meta.is_span() && meta.target().starts_with("g3_")
+}
+
+#[cfg(not(feature = "otel"))]
+impl Telemetry {
+    /// Refuse `--otlp-endpoint`; there is no exporter to start
+    pub fn from_cli(cli: &Cli) -> Result<Option<Self>> {
+        match &cli.otlp_endpoint {
+            Some(endpoint) => anyhow::bail!(
+                "Can't export spans to {}: g3 was built without the `otel` feature",
+                endpoint
+            ),
+            None => Ok(None),
+        }
+    }
+
+    pub fn layer<S>(&self) -> impl Layer<S>
+    where
+        S: Subscriber + for<'span> LookupSpan<'span>,
+    {
+        tracing_subscriber::layer::Identity::new().with_filter(filter_fn(is_g3_span))
+    }
+
+    pub fn install(&self) {
+        match *self {}
+    }
+}
+
+#[cfg(feature = "otel")]
+impl Telemetry {
+    /// Start exporting if an endpoint is configured
+    pub fn from_cli(cli: &Cli) -> Result<Option<Self>> {
+        let Some(endpoint) = cli
+            .otlp_endpoint
+            .clone()
+            .or_else(|| std::env::var(ENDPOINT_ENV).ok())
+            .filter(|endpoint| !endpoint.is_empty())
+        else {
+            return Ok(None);
+        };
+        let service_name = std::env::var("OTEL_SERVICE_NAME").unwrap_or_else(|_| "g3".to_string());
+
+        let provider = opentelemetry_otlp::new_pipeline()
+            .tracing()
+            .with_exporter(
+                opentelemetry_otlp::new_exporter()
+                    .tonic()
+                    .with_endpoint(&endpoint),
+            )
+            .with_trace_config(opentelemetry_sdk::trace::Config::default().with_resource(
+                Resource::new(vec![
+                    KeyValue::new("service.name", service_name),
+                    KeyValue::new("service.version", env!("CARGO_PKG_VERSION")),
+                ]),
+            ))
+            .install_batch(runtime::Tokio)
+            .with_context(|| format!("Failed to set up OTLP export to {}", endpoint))?;
+        Ok(Some(Self { provider }))
+    }
+
+    /// A tracing layer exporting G3's own spans, whatever the log filter is
+    pub fn layer<S>(&self) -> impl Layer<S>
+    where
+        S: Subscriber + for<'span> LookupSpan<'span>,
+    {
+        tracing_opentelemetry::layer()
+            .with_tracer(self.provider.tracer("g3"))
+            .with_filter(filter_fn(is_g3_span))
+    }
+
+    /// Install the export layer as the only subscriber, for modes that set
+    /// up no logging of their own
+    pub fn install(&self) {
+        use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
+        let _ = tracing_subscriber::registry().with(self.layer()).try_init();
+    }
+}
+
+#[cfg(feature = "otel")]
+impl Drop for Telemetry {
+    fn drop(&mut self) {
+        if let Err(e) = self.provider.shutdown() {
+            eprintln!("Warning: Failed to flush OTLP spans: {}", e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::{Arc, Mutex};
+    use tracing::span::{Attributes, Id};
+    use tracing_subscriber::layer::{Context, SubscriberExt};
+
+    /// Records the name of every span and event it sees
+    struct Names(Arc<Mutex<Vec<&'static str>>>);
+
+    impl<S: Subscriber> Layer<S> for Names {
+        fn on_new_span(&self, attrs: &Attributes<'_>, _id: &Id, _ctx: Context<'_, S>) {
+            self.0.lock().unwrap().push(attrs.metadata().name());
+        }
+
+        fn on_event(&self, event: &tracing::Event<'_>, _ctx: Context<'_, S>) {
+            self.0.lock().unwrap().push(event.metadata().name());
+        }
+    }
+
+    #[test]
+    fn test_only_g3_spans_pass_the_filter() {
+        let names = Arc::new(Mutex::new(Vec::new()));
+        let subscriber = tracing_subscriber::registry()
+            .with(Names(names.clone()).with_filter(filter_fn(is_g3_span)));
+
+        tracing::subscriber::with_default(subscriber, || {
+            tracing::info_span!(target: "g3_core", "agent_turn").in_scope(|| {
+                tracing::info!(target: "g3_core", "not a span");
+                let _ = tracing::info_span!(target: "h2", "stream").entered();
+                let _ = tracing::info_span!(target: "hyper::client", "request").entered();
+                let _ =
+                    tracing::info_span!(target: "g3_ensembles::flock", "flock_segment").entered();
+            });
+            let _ = tracing::info_span!(target: "tonic", "export").entered();
+        });
+
+        assert_eq!(*names.lock().unwrap(), vec!["agent_turn", "flock_segment"]);
+    }
+}
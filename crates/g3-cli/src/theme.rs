@@ -4,6 +4,9 @@ use std::fs;
 use std::path::Path;
 use anyhow::Result;
 
+/// Themes [`ColorTheme::load`] knows by name
+pub const BUILTIN_THEMES: &[&str] = &["retro", "dracula"];
+
 /// Color theme configuration for the retro TUI
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ColorTheme {
@@ -121,7 +124,7 @@ impl ColorTheme {
         }
     }
     
-    /// Get a theme by name or from file
+    /// Get a theme by name (see [`BUILTIN_THEMES`]) or from file
     pub fn load(theme_name: Option<&str>) -> Result<Self> {
         match theme_name {
             None => Ok(Self::default()),
@@ -2,6 +2,7 @@ use crossterm::style::Color;
 use crossterm::style::{SetForegroundColor, ResetColor};
 use std::io::{self, Write};
 use termimad::MadSkin;
+use crate::i18n::{t, Msg};
 
 /// Simple output handler with markdown support
 pub struct SimpleOutput {
@@ -122,7 +123,7 @@ impl SimpleOutput {
         println!();
         
         // Add a subtle "success" indicator line
-        println!("\x1b[2;36m   └─ Context optimized successfully\x1b[0m");
+        println!("\x1b[2;36m   └─ {}\x1b[0m", t(Msg::ContextOptimized));
         println!();
         
         let _ = io::stdout().flush();
@@ -1,6 +1,11 @@
 use crate::filter_json::{filter_json_tool_calls, reset_json_tool_state};
+use crate::i18n::{self, t, tf, Msg};
+use crate::links;
+use crate::markdown_table;
+use crate::retro_tui::RetroTui;
+use g3_core::permissions::{ApprovalDecision, ApprovalRequest};
 use g3_core::ui_writer::UiWriter;
-use std::io::{self, Write};
+use std::io::{self, IsTerminal, Write};
 use termimad::MadSkin;
 
 /// Console implementation of UiWriter that prints to stdout
@@ -22,6 +27,72 @@ impl ConsoleUiWriter {
     }
 }
 
+/// `text` with its file references as OSC 8 links, if stdout is a terminal
+/// to click them in; `streamed` text may end in the middle of one
+fn link_files(text: &str, streamed: bool) -> String {
+    if !io::stdout().is_terminal() {
+        text.to_string()
+    } else if streamed {
+        links::linkify_chunk(text)
+    } else {
+        links::linkify(text)
+    }
+}
+
+/// The most important argument of a tool call, shortened to one line, to
+/// show next to the tool's name
+fn tool_caption(tool_name: &str, args: &[(String, String)]) -> Option<String> {
+    // Find the most important argument - prioritize file_path if available
+    let important_arg = args
+        .iter()
+        .find(|(k, _)| k == "file_path")
+        .or_else(|| args.iter().find(|(k, _)| k == "command" || k == "path"))
+        .or_else(|| args.first());
+
+    let (_, value) = important_arg?;
+    // For multi-line values, only show the first line
+    let first_line = value.lines().next().unwrap_or("");
+
+    // Truncate long values for display
+    let display_value = if first_line.len() > 80 {
+        // Use char_indices to safely truncate at character boundary
+        let truncate_at = first_line
+            .char_indices()
+            .nth(77)
+            .map(|(i, _)| i)
+            .unwrap_or(first_line.len());
+        format!("{}...", &first_line[..truncate_at])
+    } else {
+        first_line.to_string()
+    };
+
+    // Add range information for read_file tool calls
+    let header_suffix = if tool_name == "read_file" {
+        // Check if start or end parameters are present
+        let has_start = args.iter().any(|(k, _)| k == "start");
+        let has_end = args.iter().any(|(k, _)| k == "end");
+
+        if has_start || has_end {
+            let start_val = args
+                .iter()
+                .find(|(k, _)| k == "start")
+                .map(|(_, v)| v.as_str())
+                .unwrap_or("0");
+            let end_val = args
+                .iter()
+                .find(|(k, _)| k == "end")
+                .map(|(_, v)| v.as_str())
+                .unwrap_or("end");
+            format!(" [{}..{}]", start_val, end_val)
+        } else {
+            String::new()
+        }
+    } else {
+        String::new()
+    };
+    Some(format!("{}{}", display_value, header_suffix))
+}
+
 impl UiWriter for ConsoleUiWriter {
     fn print(&self, message: &str) {
         print!("{}", message);
@@ -73,12 +144,17 @@ impl UiWriter for ConsoleUiWriter {
         println!();
 
         // Add a subtle "success" indicator line
-        println!("\x1b[2;36m   └─ Context optimized successfully\x1b[0m");
+        println!("\x1b[2;36m   └─ {}\x1b[0m", t(Msg::ContextOptimized));
         println!();
 
         let _ = io::stdout().flush();
     }
 
+    fn print_system_status(&self, message: &str) {
+        // Bold yellow so a provider switch stands out from the response text
+        println!("\n\x1b[1;33m{}\x1b[0m", message);
+    }
+
     fn print_tool_header(&self, tool_name: &str, _tool_args: Option<&serde_json::Value>) {
         // Store the tool name and clear args for collection
         *self.current_tool_name.lock().unwrap() = Some(tool_name.to_string());
@@ -112,60 +188,11 @@ impl UiWriter for ConsoleUiWriter {
         // Now print the tool header with the most important arg in bold green
         if let Some(tool_name) = self.current_tool_name.lock().unwrap().as_ref() {
             let args = self.current_tool_args.lock().unwrap();
-
-            // Find the most important argument - prioritize file_path if available
-            let important_arg = args
-                .iter()
-                .find(|(k, _)| k == "file_path")
-                .or_else(|| args.iter().find(|(k, _)| k == "command" || k == "path"))
-                .or_else(|| args.first());
-
-            if let Some((_, value)) = important_arg {
-                // For multi-line values, only show the first line
-                let first_line = value.lines().next().unwrap_or("");
-
-                // Truncate long values for display
-                let display_value = if first_line.len() > 80 {
-                    // Use char_indices to safely truncate at character boundary
-                    let truncate_at = first_line
-                        .char_indices()
-                        .nth(77)
-                        .map(|(i, _)| i)
-                        .unwrap_or(first_line.len());
-                    format!("{}...", &first_line[..truncate_at])
-                } else {
-                    first_line.to_string()
-                };
-
-                // Add range information for read_file tool calls
-                let header_suffix = if tool_name == "read_file" {
-                    // Check if start or end parameters are present
-                    let has_start = args.iter().any(|(k, _)| k == "start");
-                    let has_end = args.iter().any(|(k, _)| k == "end");
-
-                    if has_start || has_end {
-                        let start_val = args
-                            .iter()
-                            .find(|(k, _)| k == "start")
-                            .map(|(_, v)| v.as_str())
-                            .unwrap_or("0");
-                        let end_val = args
-                            .iter()
-                            .find(|(k, _)| k == "end")
-                            .map(|(_, v)| v.as_str())
-                            .unwrap_or("end");
-                        format!(" [{}..{}]", start_val, end_val)
-                    } else {
-                        String::new()
-                    }
-                } else {
-                    String::new()
-                };
-
+            if let Some(caption) = tool_caption(tool_name, &args) {
                 // Print with bold green tool name, purple (non-bold) for pipe and args
                 println!(
-                    "┌─\x1b[1;32m {}\x1b[0m\x1b[35m | {}{}\x1b[0m",
-                    tool_name, display_value, header_suffix
+                    "┌─\x1b[1;32m {}\x1b[0m\x1b[35m | {}\x1b[0m",
+                    tool_name, caption
                 );
             } else {
                 // Print with bold green formatting using ANSI escape codes
@@ -198,7 +225,25 @@ impl UiWriter for ConsoleUiWriter {
         if line.starts_with("📝 TODO list:") {
             return;
         }
-        println!("│ \x1b[2m{}\x1b[0m", line);
+        println!("│ \x1b[2m{}\x1b[0m", link_files(line, false));
+    }
+
+    fn print_diff_preview(&self, diff: &str) {
+        for line in diff.lines() {
+            let color = if line.starts_with("+++") || line.starts_with("---") {
+                "\x1b[1m" // Bold for file headers
+            } else if line.starts_with('+') {
+                "\x1b[32m"
+            } else if line.starts_with('-') {
+                "\x1b[31m"
+            } else if line.starts_with("@@") {
+                "\x1b[36m"
+            } else {
+                "\x1b[2m"
+            };
+            println!("│ {}{}\x1b[0m", color, line);
+        }
+        let _ = io::stdout().flush();
     }
 
     fn print_tool_output_summary(&self, count: usize) {
@@ -265,7 +310,7 @@ impl UiWriter for ConsoleUiWriter {
     }
 
     fn print_agent_response(&self, content: &str) {
-        print!("{}", content);
+        print!("{}", link_files(content, true));
         let _ = io::stdout().flush();
     }
 
@@ -278,16 +323,27 @@ impl UiWriter for ConsoleUiWriter {
     }
 
     fn prompt_user_yes_no(&self, message: &str) -> bool {
-        print!("{} [y/N] ", message);
+        print!("{} {} ", message, t(Msg::YesNoSuffix));
         let _ = io::stdout().flush();
 
         let mut input = String::new();
-        if io::stdin().read_line(&mut input).is_ok() {
-            let trimmed = input.trim().to_lowercase();
-            trimmed == "y" || trimmed == "yes"
-        } else {
-            false
+        io::stdin().read_line(&mut input).is_ok() && i18n::is_yes(&input)
+    }
+
+    fn prompt_tool_approval(&self, request: &ApprovalRequest) -> ApprovalDecision {
+        print!("{} {} ", request.prompt(), t(Msg::ApprovalChoices));
+        let _ = io::stdout().flush();
+
+        let mut input = String::new();
+        if io::stdin().read_line(&mut input).is_err() {
+            return ApprovalDecision::Deny;
         }
+        input
+            .trim()
+            .chars()
+            .next()
+            .and_then(ApprovalDecision::from_key)
+            .unwrap_or(ApprovalDecision::Deny)
     }
 
     fn prompt_user_choice(&self, message: &str, options: &[&str]) -> usize {
@@ -295,7 +351,8 @@ impl UiWriter for ConsoleUiWriter {
         for (i, option) in options.iter().enumerate() {
             println!("  [{}] {}", i + 1, option);
         }
-        print!("Select an option (1-{}): ", options.len());
+        let count = options.len().to_string();
+        print!("{} ", tf(Msg::SelectOption, &[("count", &count)]));
         let _ = io::stdout().flush();
 
         loop {
@@ -307,7 +364,7 @@ impl UiWriter for ConsoleUiWriter {
                     }
                 }
             }
-            print!("Invalid choice. Please select (1-{}): ", options.len());
+            print!("{} ", tf(Msg::InvalidChoice, &[("count", &count)]));
             let _ = io::stdout().flush();
         }
     }
@@ -315,7 +372,7 @@ impl UiWriter for ConsoleUiWriter {
     fn print_final_output(&self, summary: &str) {
         // Show spinner while "formatting"
         let spinner_frames = ['⠋', '⠙', '⠹', '⠸', '⠼', '⠴', '⠦', '⠧', '⠇', '⠏'];
-        let message = "summarizing work done...";
+        let message = t(Msg::SummarizingWork);
 
         // Brief spinner animation (about 0.5 seconds)
         for i in 0..5 {
@@ -340,16 +397,17 @@ impl UiWriter for ConsoleUiWriter {
         skin.inline_code.set_fg(termimad::crossterm::style::Color::Yellow);
 
         // Print a header separator
-        println!("\x1b[1;35m━━━ Summary ━━━\x1b[0m");
+        let header = format!("━━━ {} ━━━", t(Msg::SummaryHeader));
+        println!("\x1b[1;35m{}\x1b[0m", header);
         println!();
 
         // Render the markdown
-        let rendered = skin.term_text(summary);
-        print!("{}", rendered);
+        let (width, _) = termimad::terminal_size();
+        print!("{}", markdown_table::term_text(&skin, summary, width as usize));
 
         // Print a footer separator
         println!();
-        println!("\x1b[1;35m━━━━━━━━━━━━━━━\x1b[0m");
+        println!("\x1b[1;35m{}\x1b[0m", "━".repeat(header.chars().count()));
     }
 
     fn filter_json_tool_calls(&self, content: &str) -> String {
@@ -362,3 +420,155 @@ impl UiWriter for ConsoleUiWriter {
         reset_json_tool_state();
     }
 }
+
+/// The tool call the retro TUI is showing
+struct RetroTool {
+    name: String,
+    args: Vec<(String, String)>,
+    caption: String,
+    output: String,
+    started: std::time::Instant,
+}
+
+/// UiWriter for `--retro`: everything goes to the full-screen TUI, tool
+/// calls as a header in the output area with their output in the tool panel
+pub struct RetroTuiWriter {
+    tui: RetroTui,
+    current_tool: std::sync::Mutex<Option<RetroTool>>,
+}
+
+impl RetroTuiWriter {
+    pub fn new(tui: RetroTui) -> Self {
+        Self {
+            tui,
+            current_tool: std::sync::Mutex::new(None),
+        }
+    }
+
+    fn add_tool_output(&self, line: &str) {
+        if let Some(tool) = self.current_tool.lock().unwrap().as_mut() {
+            tool.output.push_str(line);
+            tool.output.push('\n');
+            self.tui.update_tool_detail(&tool.name, &tool.output);
+        }
+    }
+}
+
+impl UiWriter for RetroTuiWriter {
+    fn print(&self, message: &str) {
+        self.tui.output(message);
+    }
+
+    fn println(&self, message: &str) {
+        self.tui.output(&format!("{}\n", message));
+    }
+
+    fn print_inline(&self, message: &str) {
+        self.tui.output(message);
+    }
+
+    fn print_system_prompt(&self, prompt: &str) {
+        self.tui.output(&format!("\nSystem Prompt:\n{}\n", prompt));
+    }
+
+    fn print_context_status(&self, message: &str) {
+        self.tui.output(&format!("\n{}\n", message));
+    }
+
+    fn print_context_thinning(&self, message: &str) {
+        self.tui.output(&format!("\n✨ {} ✨\n", message));
+    }
+
+    fn print_tool_header(&self, tool_name: &str, _tool_args: Option<&serde_json::Value>) {
+        *self.current_tool.lock().unwrap() = Some(RetroTool {
+            name: tool_name.to_string(),
+            args: Vec::new(),
+            caption: String::new(),
+            output: String::new(),
+            started: std::time::Instant::now(),
+        });
+    }
+
+    fn print_tool_arg(&self, key: &str, value: &str) {
+        if let Some(tool) = self.current_tool.lock().unwrap().as_mut() {
+            tool.args.push((key.to_string(), value.to_string()));
+        }
+    }
+
+    fn print_tool_output_header(&self) {
+        if let Some(tool) = self.current_tool.lock().unwrap().as_mut() {
+            tool.caption = tool_caption(&tool.name, &tool.args).unwrap_or_default();
+            self.tui.tool_output(&tool.name, &tool.caption, "");
+        }
+    }
+
+    fn update_tool_output_line(&self, line: &str) {
+        if let Some(tool) = self.current_tool.lock().unwrap().as_ref() {
+            self.tui.update_tool_detail(&tool.name, line);
+        }
+    }
+
+    fn print_tool_output_line(&self, line: &str) {
+        self.add_tool_output(line);
+    }
+
+    fn print_tool_output_summary(&self, count: usize) {
+        self.add_tool_output(&format!(
+            "({} line{})",
+            count,
+            if count == 1 { "" } else { "s" }
+        ));
+    }
+
+    fn print_tool_timing(&self, _duration_str: &str, _tokens_delta: u32, _context_percentage: f32) {
+        if let Some(tool) = self.current_tool.lock().unwrap().take() {
+            self.tui.tool_complete(
+                &tool.name,
+                true,
+                tool.started.elapsed().as_millis(),
+                &tool.caption,
+            );
+        }
+    }
+
+    fn print_agent_prompt(&self) {}
+
+    fn print_agent_response(&self, content: &str) {
+        self.tui.output(content);
+    }
+
+    fn notify_sse_received(&self) {
+        self.tui.sse_received();
+    }
+
+    fn flush(&self) {}
+
+    fn prompt_user_yes_no(&self, message: &str) -> bool {
+        // The TUI takes no free-form answers; refuse rather than guess
+        self.tui.output(&format!("\n{} no\n", message));
+        false
+    }
+
+    fn prompt_tool_approval(&self, request: &ApprovalRequest) -> ApprovalDecision {
+        self.tui.request_approval(request)
+    }
+
+    fn prompt_user_choice(&self, message: &str, options: &[&str]) -> usize {
+        let first = options.first().copied().unwrap_or_default();
+        self.tui.output(&format!("\n{} {}\n", message, first));
+        0
+    }
+
+    fn print_final_output(&self, summary: &str) {
+        let header = format!("━━━ {} ━━━", t(Msg::SummaryHeader));
+        self.tui.output(&format!("\n{}\n\n{}\n", header, summary));
+    }
+
+    fn filter_json_tool_calls(&self, content: &str) -> String {
+        filter_json_tool_calls(content)
+    }
+
+    fn reset_json_filter(&self) {
+        reset_json_tool_state();
+    }
+}
@@ -1,19 +1,28 @@
 // Suppress warnings from objc crate macros
 #![allow(unexpected_cfgs)]
 
+pub mod linux;
 pub mod macax;
 pub mod ocr;
 pub mod platform;
+pub mod recording;
 pub mod types;
 pub mod webdriver;
 
 // Re-export webdriver types for convenience
 pub use webdriver::{
-    chrome::ChromeDriver, safari::SafariDriver, WebDriverController, WebElement,
+    chrome::{ChromeDriver, ConsoleLogEntry},
+    network::{NetworkRecorder, NetworkRequest},
+    safari::SafariDriver,
+    WebDriverController, WebElement,
 };
 
 // Re-export macax types for convenience
-pub use macax::{AXApplication, AXElement, MacAxController};
+pub use macax::{AXApplication, AXElement, AXSnapshot, MacAxController};
+
+pub use linux::LinuxAxController;
+
+pub use recording::{Recorder, RecordedAction, Recording, ReplayOptions, ReplayReport};
 
 use anyhow::Result;
 use async_trait::async_trait;
@@ -29,6 +38,22 @@ pub trait ComputerController: Send + Sync {
         window_id: Option<&str>,
     ) -> Result<()>;
 
+    // Window management, implemented on macOS and on Linux (wmctrl on X11,
+    // swaymsg on sway); elsewhere the defaults refuse
+    /// On-screen application windows, front to back
+    fn list_windows(&self) -> Result<Vec<WindowInfo>> {
+        Err(window_management_unsupported())
+    }
+    /// Bring the window best matching `title` (see [`types::find_window`])
+    /// to the front
+    fn focus_window(&self, _title: &str) -> Result<WindowInfo> {
+        Err(window_management_unsupported())
+    }
+    /// Move and resize the window with id `window_id` from `list_windows`
+    fn move_resize_window(&self, _window_id: &str, _bounds: Rect) -> Result<()> {
+        Err(window_management_unsupported())
+    }
+
     // OCR operations
     async fn extract_text_from_screen(&self, region: Rect, window_id: &str) -> Result<String>;
     async fn extract_text_from_image(&self, path: &str) -> Result<String>;
@@ -42,9 +67,98 @@ pub trait ComputerController: Send + Sync {
     // Mouse operations
     fn move_mouse(&self, x: i32, y: i32) -> Result<()>;
     fn click_at(&self, x: i32, y: i32, app_name: Option<&str>) -> Result<()>;
+
+    /// Screen point to click for text found by [`Self::find_text_in_app`].
+    /// The default is the center of the box in top-left-origin coordinates.
+    fn text_click_point(&self, location: &TextLocation) -> (i32, i32) {
+        (
+            location.x + location.width / 2,
+            location.y + location.height / 2,
+        )
+    }
+
+    /// Find `text` on screen in an application's window with OCR and click
+    /// it. A fallback for apps that expose no accessibility data.
+    async fn click_text(&self, app_name: &str, text: &str) -> Result<TextLocation> {
+        let location = self
+            .find_text_in_app(app_name, text)
+            .await?
+            .ok_or_else(|| anyhow::anyhow!("Could not find '{}' in {}", text, app_name))?;
+        if location.width == 0 || location.height == 0 {
+            anyhow::bail!(
+                "Invalid bounding box dimensions: width={}, height={}",
+                location.width,
+                location.height
+            );
+        }
+
+        let (x, y) = self.text_click_point(&location);
+        self.click_at(x, y, Some(app_name))?;
+        Ok(location)
+    }
+}
+
+/// Desktop UI automation through the platform accessibility API
+/// (macOS Accessibility or AT-SPI on Linux)
+pub trait AccessibilityController: Send + Sync {
+    fn list_applications(&self) -> Result<Vec<AXApplication>>;
+    fn get_frontmost_app(&self) -> Result<AXApplication>;
+    fn activate_app(&self, app_name: &str) -> Result<()>;
+    fn get_ui_tree(&self, app_name: &str, max_depth: usize) -> Result<String>;
+    fn snapshot_tree(&self, app_name: &str, max_depth: usize) -> Result<AXSnapshot>;
+
+    // Elements are matched by role and, optionally, title and identifier
+    fn find_elements(
+        &self,
+        app_name: &str,
+        role: Option<&str>,
+        title: Option<&str>,
+        identifier: Option<&str>,
+    ) -> Result<Vec<AXElement>>;
+    fn click_element(
+        &self,
+        app_name: &str,
+        role: &str,
+        title: Option<&str>,
+        identifier: Option<&str>,
+    ) -> Result<()>;
+    fn set_value(
+        &self,
+        app_name: &str,
+        role: &str,
+        value: &str,
+        title: Option<&str>,
+        identifier: Option<&str>,
+    ) -> Result<()>;
+    fn get_value(
+        &self,
+        app_name: &str,
+        role: &str,
+        title: Option<&str>,
+        identifier: Option<&str>,
+    ) -> Result<String>;
+
+    // Keyboard input
+    fn type_text(&self, app_name: &str, text: &str) -> Result<()>;
+    fn press_key(&self, app_name: &str, key: &str, modifiers: Vec<&str>) -> Result<()>;
 }
 
 // Platform-specific constructor
+pub fn create_accessibility_controller() -> Result<Box<dyn AccessibilityController>> {
+    #[cfg(target_os = "linux")]
+    return Ok(Box::new(LinuxAxController::new()?));
+
+    #[cfg(not(target_os = "linux"))]
+    return Ok(Box::new(MacAxController::new()?));
+}
+
+// Platform-specific constructor
+fn window_management_unsupported() -> anyhow::Error {
+    anyhow::anyhow!(
+        "Window management is not supported on this platform (only macOS, and Linux with wmctrl or sway)"
+    )
+}
+
 pub fn create_controller() -> Result<Box<dyn ComputerController>> {
     #[cfg(target_os = "macos")]
     return Ok(Box::new(platform::macos::MacOSController::new()?));
@@ -0,0 +1,452 @@
+//! AT-SPI access through the `gi.repository.Atspi` Python bindings
+//!
+//! Every query runs a small Python helper that talks to the accessibility
+//! bus and prints its result as JSON. Elements are addressed by their path of
+//! child indexes from the application, which the helper resolves again for
+//! actions.
+
+use crate::macax::{AXApplication, AXElement, AXNode};
+use anyhow::{Context, Result};
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+use serde_json::Value;
+use std::process::Command;
+
+const HELPER: &str = r#"
+import json
+import sys
+
+import gi
+
+gi.require_version("Atspi", "2.0")
+from gi.repository import Atspi
+
+
+def fail(message):
+    sys.stderr.write(message)
+    sys.exit(1)
+
+
+def applications():
+    desktop = Atspi.get_desktop(0)
+    for i in range(desktop.get_child_count()):
+        app = desktop.get_child_at_index(i)
+        if app is not None:
+            yield app
+
+
+def app_info(app):
+    return {"name": app.get_name(), "pid": app.get_process_id()}
+
+
+def find_app(name):
+    for app in applications():
+        if app.get_name() == name:
+            return app
+    fail("Application '%s' not found" % name)
+
+
+def resolve(app, path):
+    element = app
+    for index in path:
+        element = element.get_child_at_index(index)
+        if element is None:
+            fail("Element no longer exists")
+    return element
+
+
+def value_of(element):
+    text = element.get_text_iface()
+    if text is not None:
+        return text.get_text(0, text.get_character_count())
+    value = element.get_value_iface()
+    if value is not None:
+        return str(value.get_current_value())
+    return None
+
+
+def node(element, index, depth, max_depth):
+    states = element.get_state_set()
+    component = element.get_component_iface()
+    extents = None
+    if component is not None:
+        rect = component.get_extents(Atspi.CoordType.SCREEN)
+        extents = [rect.x, rect.y, rect.width, rect.height]
+    try:
+        identifier = element.get_accessible_id()
+    except Exception:
+        identifier = None
+    count = element.get_child_count()
+    children = []
+    if depth + 1 < max_depth:
+        for i in range(count):
+            try:
+                child = element.get_child_at_index(i)
+                if child is not None:
+                    children.append(node(child, i, depth + 1, max_depth))
+            except Exception:
+                # The child went away while we were walking the tree
+                pass
+    return {
+        "index": index,
+        "role": element.get_role_name(),
+        "name": element.get_name() or None,
+        "description": element.get_description() or None,
+        "id": identifier or None,
+        "value": value_of(element),
+        "enabled": states.contains(Atspi.StateType.ENABLED),
+        "focused": states.contains(Atspi.StateType.FOCUSED),
+        "extents": extents,
+        "child_count": count,
+        "children": children,
+    }
+
+
+def click(element):
+    action = element.get_action_iface()
+    if action is None:
+        return False
+    names = [action.get_action_name(i) for i in range(action.get_n_actions())]
+    for preferred in ("click", "press", "activate", "jump", "toggle"):
+        if preferred in names:
+            return action.do_action(names.index(preferred))
+    return bool(names) and action.do_action(0)
+
+
+def main(command, args):
+    if command == "apps":
+        return [app_info(app) for app in applications()]
+    if command == "active":
+        for app in applications():
+            for i in range(app.get_child_count()):
+                window = app.get_child_at_index(i)
+                if window is not None and window.get_state_set().contains(Atspi.StateType.ACTIVE):
+                    return app_info(app)
+        fail("No active application")
+
+    app = find_app(args["app"])
+    if command == "tree":
+        return node(app, 0, 0, args["max_depth"])
+
+    element = resolve(app, args["path"])
+    if command == "click":
+        return {"done": click(element)}
+    if command == "focus":
+        component = element.get_component_iface()
+        return {"done": component is not None and component.grab_focus()}
+    if command == "get_value":
+        return {"value": value_of(element)}
+    if command == "set_value":
+        editable = element.get_editable_text_iface()
+        if editable is not None:
+            return {"done": editable.set_text_contents(args["value"])}
+        value = element.get_value_iface()
+        if value is not None:
+            return {"done": value.set_current_value(float(args["value"]))}
+        fail("Element has no editable text or value")
+    fail("Unknown command: " + command)
+
+
+try:
+    result = main(sys.argv[1], json.loads(sys.argv[2]))
+except Exception as error:
+    fail(str(error))
+print(json.dumps(result))
+"#;
+
+/// An accessible object as reported by the helper
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct AtspiNode {
+    /// Position among the parent's children
+    #[serde(default)]
+    pub index: usize,
+    /// AT-SPI role name, e.g. "push button" or "text"
+    pub role: String,
+    pub name: Option<String>,
+    pub description: Option<String>,
+    pub id: Option<String>,
+    pub value: Option<String>,
+    #[serde(default)]
+    pub enabled: bool,
+    #[serde(default)]
+    pub focused: bool,
+    /// Screen coordinates as `[x, y, width, height]`
+    pub extents: Option<[i32; 4]>,
+    #[serde(default)]
+    pub child_count: usize,
+    #[serde(default)]
+    pub children: Vec<AtspiNode>,
+}
+
+/// Criteria for finding elements: all given parts must match
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ElementFilter<'a> {
+    /// Case-insensitive substring of the role name
+    pub role: Option<&'a str>,
+    /// Substring of the accessible name
+    pub title: Option<&'a str>,
+    /// Substring of the accessible id
+    pub identifier: Option<&'a str>,
+}
+
+impl ElementFilter<'_> {
+    fn matches(&self, node: &AtspiNode) -> bool {
+        let contains = |value: &Option<String>, filter: Option<&str>| match filter {
+            Some(filter) => value.as_ref().is_some_and(|value| value.contains(filter)),
+            None => true,
+        };
+        let role_matches = self
+            .role
+            .is_none_or(|role| node.role.to_lowercase().contains(&role.to_lowercase()));
+        role_matches && contains(&node.name, self.title) && contains(&node.id, self.identifier)
+    }
+}
+
+impl AtspiNode {
+    pub fn to_element(&self) -> AXElement {
+        AXElement {
+            role: self.role.clone(),
+            title: self.name.clone(),
+            value: self.value.clone(),
+            label: self.description.clone(),
+            identifier: self.id.clone(),
+            enabled: self.enabled,
+            focused: self.focused,
+            position: self.extents.map(|[x, y, _, _]| (x as f64, y as f64)),
+            size: self
+                .extents
+                .map(|[_, _, width, height]| (width as f64, height as f64)),
+            children_count: self.child_count,
+        }
+    }
+
+    pub fn to_ax_node(&self) -> AXNode {
+        AXNode {
+            element: self.to_element(),
+            children: self.children.iter().map(AtspiNode::to_ax_node).collect(),
+        }
+    }
+
+    /// Center of the element on screen, if it has a visible area
+    pub fn center(&self) -> Option<(i32, i32)> {
+        match self.extents {
+            Some([x, y, width, height]) if width > 0 && height > 0 => {
+                Some((x + width / 2, y + height / 2))
+            }
+            _ => None,
+        }
+    }
+
+    /// Descendants matching `filter` in tree order, with their paths of child
+    /// indexes from this node
+    pub fn find(&self, filter: &ElementFilter) -> Vec<(Vec<usize>, &AtspiNode)> {
+        fn walk<'a>(
+            node: &'a AtspiNode,
+            path: &mut Vec<usize>,
+            filter: &ElementFilter,
+            out: &mut Vec<(Vec<usize>, &'a AtspiNode)>,
+        ) {
+            for child in &node.children {
+                path.push(child.index);
+                if filter.matches(child) {
+                    out.push((path.clone(), child));
+                }
+                walk(child, path, filter, out);
+                path.pop();
+            }
+        }
+
+        let mut out = Vec::new();
+        walk(self, &mut Vec::new(), filter, &mut out);
+        out
+    }
+
+    /// Append an indented outline of this node and its descendants
+    pub fn write_tree(&self, output: &mut String, depth: usize) {
+        output.push_str(&format!("{}Role: {}", "  ".repeat(depth), self.role));
+        if let Some(ref name) = self.name {
+            output.push_str(&format!(", Title: {}", name));
+        }
+        if let Some(ref id) = self.id {
+            output.push_str(&format!(", ID: {}", id));
+        }
+        output.push('\n');
+        for child in &self.children {
+            child.write_tree(output, depth + 1);
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct AppInfo {
+    name: String,
+    pid: i32,
+}
+
+impl From<AppInfo> for AXApplication {
+    fn from(app: AppInfo) -> Self {
+        AXApplication {
+            name: app.name,
+            bundle_id: None,
+            pid: app.pid,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ActionResult {
+    #[serde(default)]
+    pub done: bool,
+    #[serde(default)]
+    pub value: Option<String>,
+}
+
+pub fn list_applications() -> Result<Vec<AXApplication>> {
+    let apps: Vec<AppInfo> = run("apps", Value::Null)?;
+    Ok(apps.into_iter().map(AXApplication::from).collect())
+}
+
+/// The application owning the active window
+pub fn active_application() -> Result<AXApplication> {
+    let app: AppInfo = run("active", Value::Null)?;
+    Ok(app.into())
+}
+
+pub fn tree(app_name: &str, max_depth: usize) -> Result<AtspiNode> {
+    run(
+        "tree",
+        serde_json::json!({ "app": app_name, "max_depth": max_depth }),
+    )
+}
+
+/// Run `action` ("click", "focus", "get_value" or "set_value") on the element
+/// at `path` in `app_name`
+pub fn perform(
+    app_name: &str,
+    path: &[usize],
+    action: &str,
+    value: Option<&str>,
+) -> Result<ActionResult> {
+    run(
+        action,
+        serde_json::json!({ "app": app_name, "path": path, "value": value }),
+    )
+}
+
+fn run<T: DeserializeOwned>(command: &str, args: Value) -> Result<T> {
+    let output = Command::new("python3")
+        .arg("-c")
+        .arg(HELPER)
+        .arg(command)
+        .arg(args.to_string())
+        .output()
+        .context("Failed to run python3 for AT-SPI access")?;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        if stderr.contains("No module named 'gi'") || stderr.contains("Namespace Atspi") {
+            anyhow::bail!(
+                "AT-SPI Python bindings are not installed (install python3-gi and gir1.2-atspi-2.0)"
+            );
+        }
+        anyhow::bail!("{}", stderr.trim());
+    }
+
+    serde_json::from_slice(&output.stdout).context("Invalid output from the AT-SPI helper")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn sample_tree() -> AtspiNode {
+        serde_json::from_value(json!({
+            "index": 0,
+            "role": "application",
+            "name": "gedit",
+            "child_count": 1,
+            "children": [{
+                "index": 0,
+                "role": "frame",
+                "name": "Untitled Document 1",
+                "enabled": true,
+                "extents": [0, 0, 800, 600],
+                "child_count": 3,
+                "children": [
+                    {
+                        "index": 0,
+                        "role": "push button",
+                        "name": "Open",
+                        "enabled": true,
+                        "extents": [10, 20, 80, 30],
+                        "child_count": 0
+                    },
+                    {
+                        "index": 2,
+                        "role": "text",
+                        "id": "editor",
+                        "value": "hello",
+                        "enabled": true,
+                        "focused": true,
+                        "extents": [0, 60, 800, 540],
+                        "child_count": 0
+                    }
+                ]
+            }]
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_find_elements() {
+        let tree = sample_tree();
+
+        let buttons = tree.find(&ElementFilter {
+            role: Some("Button"),
+            ..Default::default()
+        });
+        assert_eq!(buttons.len(), 1);
+        assert_eq!(buttons[0].0, vec![0, 0]);
+        assert_eq!(buttons[0].1.name.as_deref(), Some("Open"));
+        assert_eq!(buttons[0].1.center(), Some((50, 35)));
+
+        // Paths use the child index reported by AT-SPI, not the position in
+        // the (possibly incomplete) children list
+        let editors = tree.find(&ElementFilter {
+            identifier: Some("edit"),
+            ..Default::default()
+        });
+        assert_eq!(editors.len(), 1);
+        assert_eq!(editors[0].0, vec![0, 2]);
+
+        let none = tree.find(&ElementFilter {
+            role: Some("text"),
+            title: Some("Open"),
+            ..Default::default()
+        });
+        assert!(none.is_empty());
+    }
+
+    #[test]
+    fn test_conversion_to_ax_types() {
+        let tree = sample_tree();
+        let node = tree.to_ax_node();
+        assert_eq!(node.element.role, "application");
+        assert_eq!(node.children[0].children.len(), 2);
+
+        let editor = &node.children[0].children[1].element;
+        assert_eq!(editor.identifier.as_deref(), Some("editor"));
+        assert_eq!(editor.value.as_deref(), Some("hello"));
+        assert!(editor.focused);
+        assert_eq!(editor.position, Some((0.0, 60.0)));
+        assert_eq!(editor.size, Some((800.0, 540.0)));
+
+        let mut outline = String::new();
+        tree.write_tree(&mut outline, 0);
+        assert_eq!(
+            outline,
+            "Role: application, Title: gedit\n  Role: frame, Title: Untitled Document 1\n    Role: push button, Title: Open\n    Role: text, ID: editor\n"
+        );
+    }
+}
@@ -0,0 +1,260 @@
+//! Keyboard, mouse and screenshot commands for X11 and Wayland
+//!
+//! X11 sessions use `xdotool` and ImageMagick's `import`. Wayland doesn't let
+//! clients inject input into other windows, so there we use `wtype` (virtual
+//! keyboard protocol), `ydotool` (uinput) and `grim` (wlroots screencopy).
+
+use crate::types::Rect;
+use anyhow::{Context, Result};
+use std::process::Command;
+
+/// The display server of the current session
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DisplayServer {
+    X11,
+    Wayland,
+}
+
+impl DisplayServer {
+    /// Detect the display server from the session environment
+    pub fn detect() -> Option<Self> {
+        Self::from_env(
+            std::env::var("XDG_SESSION_TYPE").ok().as_deref(),
+            std::env::var_os("WAYLAND_DISPLAY").is_some(),
+            std::env::var_os("DISPLAY").is_some(),
+        )
+    }
+
+    fn from_env(
+        session_type: Option<&str>,
+        wayland_display: bool,
+        x_display: bool,
+    ) -> Option<Self> {
+        match session_type {
+            Some("wayland") if wayland_display => Some(Self::Wayland),
+            Some("x11") if x_display => Some(Self::X11),
+            _ if wayland_display => Some(Self::Wayland),
+            _ if x_display => Some(Self::X11),
+            _ => None,
+        }
+    }
+}
+
+/// Command typing `text` into the focused window
+pub fn type_command(display: DisplayServer, text: &str) -> Vec<String> {
+    match display {
+        DisplayServer::X11 => args(&["xdotool", "type", "--delay", "12", "--", text]),
+        DisplayServer::Wayland => args(&["wtype", "--", text]),
+    }
+}
+
+/// Command pressing `key` with `modifiers` held, using the same key and
+/// modifier names as the macOS controller ("command" maps to Ctrl)
+pub fn key_command(display: DisplayServer, key: &str, modifiers: &[&str]) -> Result<Vec<String>> {
+    let key = keysym(key);
+    let modifiers = modifiers
+        .iter()
+        .map(|modifier| modifier_name(display, modifier))
+        .collect::<Result<Vec<_>>>()?;
+
+    Ok(match display {
+        DisplayServer::X11 => {
+            let mut combo = modifiers;
+            combo.push(key);
+            args(&["xdotool", "key", "--clearmodifiers", &combo.join("+")])
+        }
+        DisplayServer::Wayland => {
+            let mut command = args(&["wtype"]);
+            for modifier in &modifiers {
+                command.extend(args(&["-M", modifier]));
+            }
+            command.extend(args(&["-k", &key]));
+            for modifier in modifiers.iter().rev() {
+                command.extend(args(&["-m", modifier]));
+            }
+            command
+        }
+    })
+}
+
+/// Commands moving the pointer to `(x, y)` and clicking the left button
+pub fn click_commands(display: DisplayServer, x: i32, y: i32) -> Vec<Vec<String>> {
+    let (x, y) = (x.to_string(), y.to_string());
+    match display {
+        DisplayServer::X11 => vec![args(&["xdotool", "mousemove", &x, &y, "click", "1"])],
+        DisplayServer::Wayland => vec![
+            args(&["ydotool", "mousemove", "--absolute", "-x", &x, "-y", &y]),
+            // 0xC0 = left button down, then up
+            args(&["ydotool", "click", "0xC0"]),
+        ],
+    }
+}
+
+/// Command saving a PNG of the screen (or `region` of it) to `path`
+pub fn screenshot_command(display: DisplayServer, path: &str, region: Option<Rect>) -> Vec<String> {
+    match (display, region) {
+        (DisplayServer::X11, None) => args(&["import", "-window", "root", path]),
+        (DisplayServer::X11, Some(r)) => {
+            let crop = format!("{}x{}+{}+{}", r.width, r.height, r.x, r.y);
+            args(&["import", "-window", "root", "-crop", &crop, path])
+        }
+        (DisplayServer::Wayland, None) => args(&["grim", path]),
+        (DisplayServer::Wayland, Some(r)) => {
+            let geometry = format!("{},{} {}x{}", r.x, r.y, r.width, r.height);
+            args(&["grim", "-g", &geometry, path])
+        }
+    }
+}
+
+/// Command raising the windows of process `pid`; Wayland has no equivalent
+pub fn activate_command(display: DisplayServer, pid: i32) -> Option<Vec<String>> {
+    match display {
+        DisplayServer::X11 => Some(args(&[
+            "xdotool",
+            "search",
+            "--onlyvisible",
+            "--pid",
+            &pid.to_string(),
+            "windowactivate",
+        ])),
+        DisplayServer::Wayland => None,
+    }
+}
+
+/// Run a command built by one of the functions above
+pub fn run(command: &[String]) -> Result<()> {
+    output(command).map(|_| ())
+}
+
+/// Run a command and return its standard output
+pub fn output(command: &[String]) -> Result<String> {
+    let (program, arguments) = command.split_first().context("Empty command")?;
+    let output = match Command::new(program).args(arguments).output() {
+        Ok(output) => output,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+            anyhow::bail!("{} is not installed", program)
+        }
+        Err(e) => return Err(e).with_context(|| format!("Failed to run {}", program)),
+    };
+
+    if !output.status.success() {
+        anyhow::bail!(
+            "{} failed: {}",
+            program,
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+pub(crate) fn args(parts: &[&str]) -> Vec<String> {
+    parts.iter().map(|part| part.to_string()).collect()
+}
+
+/// X keysym name for a key (both xdotool and wtype take keysym names)
+fn keysym(key: &str) -> String {
+    let name = match key.to_lowercase().as_str() {
+        "return" | "enter" => "Return",
+        "tab" => "Tab",
+        "space" => "space",
+        "delete" | "backspace" => "BackSpace",
+        "forwarddelete" => "Delete",
+        "escape" | "esc" => "Escape",
+        "up" => "Up",
+        "down" => "Down",
+        "left" => "Left",
+        "right" => "Right",
+        "home" => "Home",
+        "end" => "End",
+        "pageup" => "Page_Up",
+        "pagedown" => "Page_Down",
+        lower if lower.len() > 1 && lower.starts_with('f') && lower[1..].parse::<u8>().is_ok() => {
+            return lower.to_uppercase();
+        }
+        _ => key,
+    };
+    name.to_string()
+}
+
+fn modifier_name(display: DisplayServer, modifier: &str) -> Result<String> {
+    let name = match modifier.to_lowercase().as_str() {
+        "command" | "cmd" | "control" | "ctrl" => "ctrl",
+        "option" | "alt" => "alt",
+        "shift" => "shift",
+        "super" | "meta" | "win" => match display {
+            DisplayServer::X11 => "super",
+            DisplayServer::Wayland => "logo",
+        },
+        _ => anyhow::bail!("Unknown modifier: {}", modifier),
+    };
+    Ok(name.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_detect_display_server() {
+        use DisplayServer::*;
+        assert_eq!(
+            DisplayServer::from_env(Some("wayland"), true, true),
+            Some(Wayland)
+        );
+        // XDG_SESSION_TYPE wins over a stale WAYLAND_DISPLAY
+        assert_eq!(DisplayServer::from_env(Some("x11"), true, true), Some(X11));
+        assert_eq!(DisplayServer::from_env(None, false, true), Some(X11));
+        assert_eq!(
+            DisplayServer::from_env(Some("tty"), true, false),
+            Some(Wayland)
+        );
+        assert_eq!(DisplayServer::from_env(None, false, false), None);
+    }
+
+    #[test]
+    fn test_key_commands() {
+        assert_eq!(
+            key_command(DisplayServer::X11, "s", &["command", "shift"]).unwrap(),
+            args(&["xdotool", "key", "--clearmodifiers", "ctrl+shift+s"])
+        );
+        assert_eq!(
+            key_command(DisplayServer::Wayland, "return", &["cmd"]).unwrap(),
+            args(&["wtype", "-M", "ctrl", "-k", "Return", "-m", "ctrl"])
+        );
+        assert_eq!(
+            key_command(DisplayServer::X11, "F5", &[]).unwrap(),
+            args(&["xdotool", "key", "--clearmodifiers", "F5"])
+        );
+        assert!(key_command(DisplayServer::X11, "a", &["hyper"]).is_err());
+    }
+
+    #[test]
+    fn test_screenshot_and_click_commands() {
+        let region = Rect {
+            x: 10,
+            y: 20,
+            width: 300,
+            height: 200,
+        };
+        assert_eq!(
+            screenshot_command(DisplayServer::X11, "/tmp/s.png", Some(region)),
+            args(&[
+                "import",
+                "-window",
+                "root",
+                "-crop",
+                "300x200+10+20",
+                "/tmp/s.png"
+            ])
+        );
+        assert_eq!(
+            screenshot_command(DisplayServer::Wayland, "/tmp/s.png", Some(region)),
+            args(&["grim", "-g", "10,20 300x200", "/tmp/s.png"])
+        );
+        assert_eq!(click_commands(DisplayServer::Wayland, 5, 7).len(), 2);
+        assert_eq!(
+            click_commands(DisplayServer::X11, 5, 7),
+            vec![args(&["xdotool", "mousemove", "5", "7", "click", "1"])]
+        );
+    }
+}
@@ -0,0 +1,245 @@
+//! Linux desktop control: AT-SPI for UI elements, xdotool/wtype/ydotool for
+//! input and import/grim for screenshots
+
+pub mod atspi;
+pub mod input;
+pub mod window;
+
+pub use input::DisplayServer;
+
+use crate::macax::{AXApplication, AXElement, AXSnapshot};
+use crate::ocr::{best_match, tesseract::TesseractOCR, OCREngine};
+use crate::types::{find_window, Rect, TextLocation, WindowInfo};
+use crate::AccessibilityController;
+use anyhow::{Context, Result};
+use atspi::{AtspiNode, ElementFilter};
+
+/// How deep `find_elements` and element actions search the tree
+const SEARCH_DEPTH: usize = 20;
+
+/// Linux accessibility controller, the AT-SPI counterpart of
+/// [`crate::MacAxController`]
+pub struct LinuxAxController {
+    display: DisplayServer,
+}
+
+impl LinuxAxController {
+    pub fn new() -> Result<Self> {
+        #[cfg(target_os = "linux")]
+        {
+            let display = DisplayServer::detect().context(
+                "No X11 or Wayland display found (DISPLAY and WAYLAND_DISPLAY are unset)",
+            )?;
+            Ok(Self { display })
+        }
+
+        #[cfg(not(target_os = "linux"))]
+        {
+            anyhow::bail!("AT-SPI is only available on Linux")
+        }
+    }
+
+    pub fn display_server(&self) -> DisplayServer {
+        self.display
+    }
+
+    /// Save a PNG screenshot of the screen, or of `region` of it
+    pub fn take_screenshot(&self, path: &str, region: Option<Rect>) -> Result<()> {
+        input::run(&input::screenshot_command(self.display, path, region))
+    }
+
+    /// Left-click at screen coordinates
+    pub fn click_at(&self, x: i32, y: i32) -> Result<()> {
+        for command in input::click_commands(self.display, x, y) {
+            input::run(&command)?;
+        }
+        Ok(())
+    }
+
+    /// Top-level windows, in the window manager's order
+    pub fn list_windows(&self) -> Result<Vec<WindowInfo>> {
+        window::list_windows(self.display)
+    }
+
+    /// Bring the window best matching `title` to the front
+    pub fn focus_window(&self, title: &str) -> Result<WindowInfo> {
+        let windows = self.list_windows()?;
+        let window = find_window(&windows, title)
+            .cloned()
+            .ok_or_else(|| anyhow::anyhow!("No window matching '{}'", title))?;
+        window::focus_window(self.display, &window.id)?;
+        Ok(window)
+    }
+
+    /// Move and resize the window with id `window_id` from `list_windows`
+    pub fn move_resize_window(&self, window_id: &str, bounds: Rect) -> Result<()> {
+        window::move_resize_window(self.display, window_id, bounds)
+    }
+
+    /// Find `text` on screen with tesseract OCR and click its center, for
+    /// windows that expose no AT-SPI data
+    pub async fn click_text(&self, text: &str) -> Result<TextLocation> {
+        let file = std::env::temp_dir().join(format!("g3_click_text_{}.png", uuid::Uuid::new_v4()));
+        let path = file.to_string_lossy();
+        self.take_screenshot(&path, None)?;
+        let locations = TesseractOCR::new()?
+            .extract_text_with_locations(&path)
+            .await;
+        let _ = std::fs::remove_file(&file);
+
+        // A full-screen capture, so image coordinates are screen coordinates
+        let location = best_match(&locations?, text)
+            .ok_or_else(|| anyhow::anyhow!("Could not find '{}' on screen", text))?;
+        self.click_at(
+            location.x + location.width / 2,
+            location.y + location.height / 2,
+        )?;
+        Ok(location)
+    }
+
+    /// Find the first element matching the criteria, with its path
+    fn find_element(
+        &self,
+        app_name: &str,
+        role: &str,
+        title: Option<&str>,
+        identifier: Option<&str>,
+    ) -> Result<(Vec<usize>, AtspiNode)> {
+        let tree = atspi::tree(app_name, SEARCH_DEPTH)?;
+        let filter = ElementFilter {
+            role: Some(role),
+            title,
+            identifier,
+        };
+        tree.find(&filter)
+            .into_iter()
+            .next()
+            .map(|(path, node)| (path, node.clone()))
+            .context("Element not found")
+    }
+}
+
+impl AccessibilityController for LinuxAxController {
+    fn list_applications(&self) -> Result<Vec<AXApplication>> {
+        atspi::list_applications()
+    }
+
+    fn get_frontmost_app(&self) -> Result<AXApplication> {
+        atspi::active_application()
+    }
+
+    fn activate_app(&self, app_name: &str) -> Result<()> {
+        let app = atspi::list_applications()?
+            .into_iter()
+            .find(|a| a.name == app_name)
+            .ok_or_else(|| anyhow::anyhow!("Application '{}' not found", app_name))?;
+
+        match input::activate_command(self.display, app.pid) {
+            Some(command) => input::run(&command),
+            // Wayland compositors only let the application raise itself;
+            // asking its first window for focus is the closest we get
+            None => {
+                let result = atspi::perform(app_name, &[0], "focus", None)?;
+                if !result.done {
+                    anyhow::bail!("Failed to activate application");
+                }
+                Ok(())
+            }
+        }
+    }
+
+    fn get_ui_tree(&self, app_name: &str, max_depth: usize) -> Result<String> {
+        let mut output = format!("Application: {}\n", app_name);
+        if max_depth > 0 {
+            atspi::tree(app_name, max_depth)?.write_tree(&mut output, 0);
+        }
+        Ok(output)
+    }
+
+    fn snapshot_tree(&self, app_name: &str, max_depth: usize) -> Result<AXSnapshot> {
+        Ok(AXSnapshot {
+            app_name: app_name.to_string(),
+            root: atspi::tree(app_name, max_depth.max(1))?.to_ax_node(),
+        })
+    }
+
+    fn find_elements(
+        &self,
+        app_name: &str,
+        role: Option<&str>,
+        title: Option<&str>,
+        identifier: Option<&str>,
+    ) -> Result<Vec<AXElement>> {
+        let tree = atspi::tree(app_name, SEARCH_DEPTH)?;
+        let filter = ElementFilter {
+            role,
+            title,
+            identifier,
+        };
+        Ok(tree
+            .find(&filter)
+            .into_iter()
+            .map(|(_, node)| node.to_element())
+            .collect())
+    }
+
+    fn click_element(
+        &self,
+        app_name: &str,
+        role: &str,
+        title: Option<&str>,
+        identifier: Option<&str>,
+    ) -> Result<()> {
+        let (path, node) = self.find_element(app_name, role, title, identifier)?;
+        if atspi::perform(app_name, &path, "click", None)?.done {
+            return Ok(());
+        }
+
+        // No usable AT-SPI action: click the middle of the element instead
+        let (x, y) = node
+            .center()
+            .context("Element has no click action and no position on screen")?;
+        self.click_at(x, y)
+    }
+
+    fn set_value(
+        &self,
+        app_name: &str,
+        role: &str,
+        value: &str,
+        title: Option<&str>,
+        identifier: Option<&str>,
+    ) -> Result<()> {
+        let (path, _) = self.find_element(app_name, role, title, identifier)?;
+        if !atspi::perform(app_name, &path, "set_value", Some(value))?.done {
+            anyhow::bail!("Failed to set value");
+        }
+        Ok(())
+    }
+
+    fn get_value(
+        &self,
+        app_name: &str,
+        role: &str,
+        title: Option<&str>,
+        identifier: Option<&str>,
+    ) -> Result<String> {
+        let (path, _) = self.find_element(app_name, role, title, identifier)?;
+        Ok(atspi::perform(app_name, &path, "get_value", None)?
+            .value
+            .unwrap_or_else(|| "<non-text value>".to_string()))
+    }
+
+    fn type_text(&self, app_name: &str, text: &str) -> Result<()> {
+        self.activate_app(app_name)?;
+        // Give the window manager time to move focus
+        std::thread::sleep(std::time::Duration::from_millis(200));
+        input::run(&input::type_command(self.display, text))
+    }
+
+    fn press_key(&self, app_name: &str, key: &str, modifiers: Vec<&str>) -> Result<()> {
+        self.activate_app(app_name)?;
+        std::thread::sleep(std::time::Duration::from_millis(100));
+        input::run(&input::key_command(self.display, key, &modifiers)?)
+    }
+}
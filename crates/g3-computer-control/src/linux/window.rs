@@ -0,0 +1,218 @@
+//! Window management with `wmctrl` on X11 and `swaymsg` on sway (Wayland has
+//! no common protocol for managing other clients' windows)
+
+use super::input::{self, args, DisplayServer};
+use crate::types::{Rect, WindowInfo};
+use anyhow::{Context, Result};
+use serde_json::Value;
+
+pub fn list_windows(display: DisplayServer) -> Result<Vec<WindowInfo>> {
+    match display {
+        DisplayServer::X11 => Ok(parse_wmctrl(&input::output(&args(&["wmctrl", "-lpG"]))?)),
+        DisplayServer::Wayland => {
+            let tree = serde_json::from_str(&input::output(&args(&["swaymsg", "-t", "get_tree"]))?)
+                .context("Invalid output from swaymsg")?;
+            Ok(parse_sway_tree(&tree))
+        }
+    }
+}
+
+pub fn focus_window(display: DisplayServer, window_id: &str) -> Result<()> {
+    input::run(&match display {
+        DisplayServer::X11 => args(&["wmctrl", "-i", "-a", window_id]),
+        DisplayServer::Wayland => args(&["swaymsg", &format!("[con_id={}] focus", window_id)]),
+    })
+}
+
+pub fn move_resize_window(display: DisplayServer, window_id: &str, bounds: Rect) -> Result<()> {
+    input::run(&move_resize_command(display, window_id, bounds))
+}
+
+fn move_resize_command(display: DisplayServer, window_id: &str, bounds: Rect) -> Vec<String> {
+    match display {
+        DisplayServer::X11 => {
+            // Gravity 0 keeps the window's own gravity
+            let geometry = format!(
+                "0,{},{},{},{}",
+                bounds.x, bounds.y, bounds.width, bounds.height
+            );
+            args(&["wmctrl", "-i", "-r", window_id, "-e", &geometry])
+        }
+        DisplayServer::Wayland => {
+            // Tiled windows can't be placed, so float the window first
+            let commands = format!(
+                "[con_id={}] floating enable, move absolute position {} px {} px, resize set {} px {} px",
+                window_id, bounds.x, bounds.y, bounds.width, bounds.height
+            );
+            args(&["swaymsg", &commands])
+        }
+    }
+}
+
+/// Parse `wmctrl -lpG`: id, desktop, pid, x, y, width, height, host, title
+fn parse_wmctrl(output: &str) -> Vec<WindowInfo> {
+    output
+        .lines()
+        .filter_map(|line| {
+            let mut rest = line.trim_start();
+            let mut fields = Vec::with_capacity(8);
+            for _ in 0..8 {
+                let end = rest.find(char::is_whitespace).unwrap_or(rest.len());
+                if end == 0 {
+                    return None;
+                }
+                fields.push(&rest[..end]);
+                rest = rest[end..].trim_start();
+            }
+            let number = |i: usize| fields[i].parse::<i32>().ok();
+
+            // Desktop -1 holds sticky windows such as panels and docks
+            if fields[1] == "-1" {
+                return None;
+            }
+            let pid = number(2).filter(|pid| *pid > 0);
+            Some(WindowInfo {
+                id: fields[0].to_string(),
+                title: rest.trim_end().to_string(),
+                app_name: pid.and_then(process_name).unwrap_or_default(),
+                pid,
+                bounds: Rect {
+                    x: number(3)?,
+                    y: number(4)?,
+                    width: number(5)?,
+                    height: number(6)?,
+                },
+            })
+        })
+        .collect()
+}
+
+/// Collect the application windows of a `swaymsg -t get_tree` tree
+fn parse_sway_tree(tree: &Value) -> Vec<WindowInfo> {
+    fn walk(node: &Value, windows: &mut Vec<WindowInfo>) {
+        if let (Some(id), Some(pid)) = (node["id"].as_i64(), node["pid"].as_i64()) {
+            let rect = &node["rect"];
+            let number = |key: &str| rect[key].as_i64().unwrap_or_default() as i32;
+            windows.push(WindowInfo {
+                id: id.to_string(),
+                title: node["name"].as_str().unwrap_or_default().to_string(),
+                // Native Wayland clients have an app_id, XWayland ones a class
+                app_name: node["app_id"]
+                    .as_str()
+                    .or_else(|| node["window_properties"]["class"].as_str())
+                    .unwrap_or_default()
+                    .to_string(),
+                pid: Some(pid as i32),
+                bounds: Rect {
+                    x: number("x"),
+                    y: number("y"),
+                    width: number("width"),
+                    height: number("height"),
+                },
+            });
+        }
+        for key in ["nodes", "floating_nodes"] {
+            for child in node[key].as_array().into_iter().flatten() {
+                walk(child, windows);
+            }
+        }
+    }
+
+    let mut windows = Vec::new();
+    walk(tree, &mut windows);
+    windows
+}
+
+fn process_name(pid: i32) -> Option<String> {
+    let name = std::fs::read_to_string(format!("/proc/{}/comm", pid)).ok()?;
+    Some(name.trim().to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_parse_wmctrl() {
+        let output = "\
+0x01e00003 -1 1203   0    0    1920 32   laptop Top Panel
+0x03a00007  0 0      10   40   1280 720  laptop notes.txt  -  gedit
+0x04200001  1 2231   0    0    800  600  laptop
+";
+        let windows = parse_wmctrl(output);
+        assert_eq!(windows.len(), 2);
+
+        assert_eq!(windows[0].id, "0x03a00007");
+        assert_eq!(windows[0].title, "notes.txt  -  gedit");
+        assert_eq!(windows[0].pid, None);
+        assert_eq!((windows[0].bounds.x, windows[0].bounds.y), (10, 40));
+        assert_eq!(
+            (windows[0].bounds.width, windows[0].bounds.height),
+            (1280, 720)
+        );
+
+        assert_eq!(windows[1].title, "");
+        assert_eq!(windows[1].pid, Some(2231));
+    }
+
+    #[test]
+    fn test_parse_sway_tree() {
+        let tree = json!({
+            "id": 1,
+            "type": "root",
+            "nodes": [{
+                "id": 3,
+                "type": "output",
+                "nodes": [{
+                    "id": 4,
+                    "type": "workspace",
+                    "nodes": [{
+                        "id": 7,
+                        "type": "con",
+                        "pid": 4321,
+                        "name": "Terminal",
+                        "app_id": "foot",
+                        "rect": { "x": 0, "y": 0, "width": 960, "height": 1080 }
+                    }],
+                    "floating_nodes": [{
+                        "id": 9,
+                        "type": "floating_con",
+                        "pid": 5555,
+                        "name": "Settings",
+                        "window_properties": { "class": "Steam" },
+                        "rect": { "x": 100, "y": 80, "width": 640, "height": 480 }
+                    }]
+                }]
+            }]
+        });
+
+        let windows = parse_sway_tree(&tree);
+        assert_eq!(windows.len(), 2);
+        assert_eq!(windows[0].id, "7");
+        assert_eq!(windows[0].app_name, "foot");
+        assert_eq!(windows[1].app_name, "Steam");
+        assert_eq!(windows[1].bounds.width, 640);
+    }
+
+    #[test]
+    fn test_move_resize_command() {
+        let bounds = Rect {
+            x: 10,
+            y: 20,
+            width: 800,
+            height: 600,
+        };
+        assert_eq!(
+            move_resize_command(DisplayServer::X11, "0x03a00007", bounds),
+            args(&["wmctrl", "-i", "-r", "0x03a00007", "-e", "0,10,20,800,600"])
+        );
+        assert_eq!(
+            move_resize_command(DisplayServer::Wayland, "7", bounds),
+            args(&[
+                "swaymsg",
+                "[con_id=7] floating enable, move absolute position 10 px 20 px, resize set 800 px 600 px"
+            ])
+        );
+    }
+}
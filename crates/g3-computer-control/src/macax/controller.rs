@@ -1,4 +1,5 @@
-use super::{AXApplication, AXElement};
+use super::{AXApplication, AXElement, AXNode, AXSnapshot};
+use crate::AccessibilityController;
 use anyhow::{Context, Result};
 use std::collections::HashMap;
 
@@ -19,6 +20,14 @@ pub struct MacAxController {
     app_cache: std::sync::Mutex<HashMap<String, AXUIElement>>,
 }
 
+// SAFETY: AXUIElement is a Core Foundation reference, whose retain/release
+// is thread-safe, and the Accessibility API may be called from any thread.
+// The cache is only touched behind its mutex.
+#[cfg(target_os = "macos")]
+unsafe impl Send for MacAxController {}
+#[cfg(target_os = "macos")]
+unsafe impl Sync for MacAxController {}
+
 impl MacAxController {
     pub fn new() -> Result<Self> {
         #[cfg(target_os = "macos")]
@@ -301,6 +310,69 @@ impl MacAxController {
         Ok(())
     }
 
+    /// Capture the accessibility tree of an application, for comparing with
+    /// [`super::diff_snapshots`]
+    #[cfg(target_os = "macos")]
+    pub fn snapshot_tree(&self, app_name: &str, max_depth: usize) -> Result<AXSnapshot> {
+        let app_element = self.get_app_element(app_name)?;
+        Ok(AXSnapshot {
+            app_name: app_name.to_string(),
+            root: Self::build_snapshot_node(&app_element, 0, max_depth),
+        })
+    }
+
+    #[cfg(not(target_os = "macos"))]
+    pub fn snapshot_tree(&self, _app_name: &str, _max_depth: usize) -> Result<AXSnapshot> {
+        anyhow::bail!("Not supported on this platform")
+    }
+
+    #[cfg(target_os = "macos")]
+    fn build_snapshot_node(element: &AXUIElement, depth: usize, max_depth: usize) -> AXNode {
+        let mut children = Vec::new();
+        if depth + 1 < max_depth {
+            if let Ok(elements) = element.children() {
+                for i in 0..elements.len() {
+                    if let Some(child) = elements.get(i) {
+                        children.push(Self::build_snapshot_node(&child, depth + 1, max_depth));
+                    }
+                }
+            }
+        }
+
+        AXNode {
+            element: Self::element_info(element),
+            children,
+        }
+    }
+
+    /// Read the attributes of an element
+    #[cfg(target_os = "macos")]
+    fn element_info(element: &AXUIElement) -> AXElement {
+        AXElement {
+            role: element
+                .role()
+                .ok()
+                .map(|s| s.to_string())
+                .unwrap_or_else(|| "Unknown".to_string()),
+            title: element.title().ok().map(|s| s.to_string()),
+            value: element
+                .value()
+                .ok()
+                .and_then(|v| v.downcast::<CFString>().map(|s| s.to_string())),
+            label: element.description().ok().map(|s| s.to_string()),
+            identifier: element.identifier().ok().map(|s| s.to_string()),
+            enabled: element.enabled().ok().map(|b| b.into()).unwrap_or(false),
+            focused: element.focused().ok().map(|b| b.into()).unwrap_or(false),
+            position: None,
+            size: None,
+            children_count: element
+                .children()
+                .ok()
+                .map(|arr| arr.len() as usize)
+                .unwrap_or(0),
+        }
+    }
+
     /// Find UI elements in an application
     #[cfg(target_os = "macos")]
     pub fn find_elements(
@@ -723,6 +795,77 @@ impl MacAxController {
     }
 }
 
+impl AccessibilityController for MacAxController {
+    fn list_applications(&self) -> Result<Vec<AXApplication>> {
+        MacAxController::list_applications(self)
+    }
+
+    fn get_frontmost_app(&self) -> Result<AXApplication> {
+        MacAxController::get_frontmost_app(self)
+    }
+
+    fn activate_app(&self, app_name: &str) -> Result<()> {
+        MacAxController::activate_app(self, app_name)
+    }
+
+    fn get_ui_tree(&self, app_name: &str, max_depth: usize) -> Result<String> {
+        MacAxController::get_ui_tree(self, app_name, max_depth)
+    }
+
+    fn snapshot_tree(&self, app_name: &str, max_depth: usize) -> Result<AXSnapshot> {
+        MacAxController::snapshot_tree(self, app_name, max_depth)
+    }
+
+    fn find_elements(
+        &self,
+        app_name: &str,
+        role: Option<&str>,
+        title: Option<&str>,
+        identifier: Option<&str>,
+    ) -> Result<Vec<AXElement>> {
+        MacAxController::find_elements(self, app_name, role, title, identifier)
+    }
+
+    fn click_element(
+        &self,
+        app_name: &str,
+        role: &str,
+        title: Option<&str>,
+        identifier: Option<&str>,
+    ) -> Result<()> {
+        MacAxController::click_element(self, app_name, role, title, identifier)
+    }
+
+    fn set_value(
+        &self,
+        app_name: &str,
+        role: &str,
+        value: &str,
+        title: Option<&str>,
+        identifier: Option<&str>,
+    ) -> Result<()> {
+        MacAxController::set_value(self, app_name, role, value, title, identifier)
+    }
+
+    fn get_value(
+        &self,
+        app_name: &str,
+        role: &str,
+        title: Option<&str>,
+        identifier: Option<&str>,
+    ) -> Result<String> {
+        MacAxController::get_value(self, app_name, role, title, identifier)
+    }
+
+    fn type_text(&self, app_name: &str, text: &str) -> Result<()> {
+        MacAxController::type_text(self, app_name, text)
+    }
+
+    fn press_key(&self, app_name: &str, key: &str, modifiers: Vec<&str>) -> Result<()> {
+        MacAxController::press_key(self, app_name, key, modifiers)
+    }
+}
+
 #[cfg(target_os = "macos")]
 struct ElementCollector<'a> {
     role_filter: Option<String>,
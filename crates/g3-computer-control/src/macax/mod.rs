@@ -1,6 +1,8 @@
 pub mod controller;
+pub mod snapshot;
 
 pub use controller::MacAxController;
+pub use snapshot::{diff_snapshots, AXChange, AXDiff, AXFieldChange, AXNode, AXSnapshot};
 
 use serde::{Deserialize, Serialize};
 
@@ -8,7 +10,7 @@ use serde::{Deserialize, Serialize};
 mod tests;
 
 /// Represents an accessibility element in the UI hierarchy
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct AXElement {
     pub role: String,
     pub title: Option<String>,
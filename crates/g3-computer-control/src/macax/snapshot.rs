@@ -0,0 +1,224 @@
+//! Accessibility tree snapshots and diffs
+//!
+//! Take a [`AXSnapshot`] before and after a UI action and compare them with
+//! [`diff_snapshots`] to check that the action changed the interface.
+
+use super::AXElement;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// An element and its children
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AXNode {
+    pub element: AXElement,
+    pub children: Vec<AXNode>,
+}
+
+/// The accessibility tree of an application at one point in time
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AXSnapshot {
+    pub app_name: String,
+    pub root: AXNode,
+}
+
+impl AXSnapshot {
+    /// Number of elements in the snapshot
+    pub fn element_count(&self) -> usize {
+        fn count(node: &AXNode) -> usize {
+            1 + node.children.iter().map(count).sum::<usize>()
+        }
+        count(&self.root)
+    }
+
+    /// Every element, in tree order, keyed by its path from the root
+    fn elements_by_path(&self) -> Vec<(String, &AXElement)> {
+        fn walk<'a>(node: &'a AXNode, path: String, out: &mut Vec<(String, &'a AXElement)>) {
+            out.push((path.clone(), &node.element));
+            // Siblings with the same role and name are told apart by position
+            let mut seen: HashMap<String, usize> = HashMap::new();
+            for child in &node.children {
+                let step = path_step(&child.element);
+                let index = seen.entry(step.clone()).or_default();
+                let child_path = match *index {
+                    0 => format!("{}/{}", path, step),
+                    n => format!("{}/{}#{}", path, step, n + 1),
+                };
+                *index += 1;
+                walk(child, child_path, out);
+            }
+        }
+
+        let mut out = Vec::new();
+        walk(&self.root, path_step(&self.root.element), &mut out);
+        out
+    }
+}
+
+/// Path component of an element: its role and, if it has one, identifier or title
+fn path_step(element: &AXElement) -> String {
+    match element.identifier.as_ref().or(element.title.as_ref()) {
+        Some(name) if !name.is_empty() => format!("{}[{}]", element.role, name),
+        _ => element.role.clone(),
+    }
+}
+
+/// One difference between two snapshots
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum AXChange {
+    Added {
+        path: String,
+        element: AXElement,
+    },
+    Removed {
+        path: String,
+        element: AXElement,
+    },
+    Changed {
+        path: String,
+        /// The element as it is now
+        element: AXElement,
+        fields: Vec<AXFieldChange>,
+    },
+}
+
+/// An attribute that differs between two snapshots of an element
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AXFieldChange {
+    /// Attribute name, e.g. "value" or "enabled"
+    pub field: String,
+    pub before: String,
+    pub after: String,
+}
+
+impl AXChange {
+    /// One-line description, e.g. `~ AXWindow/AXTextField[name]: value "" -> "Bob"`
+    pub fn describe(&self) -> String {
+        match self {
+            AXChange::Added { path, .. } => format!("+ {}", path),
+            AXChange::Removed { path, .. } => format!("- {}", path),
+            AXChange::Changed { path, fields, .. } => {
+                let details: Vec<String> = fields
+                    .iter()
+                    .map(|change| format!("{} {} -> {}", change.field, change.before, change.after))
+                    .collect();
+                format!("~ {}: {}", path, details.join(", "))
+            }
+        }
+    }
+}
+
+/// Differences between two snapshots of the same application
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct AXDiff {
+    pub changes: Vec<AXChange>,
+}
+
+impl AXDiff {
+    pub fn is_empty(&self) -> bool {
+        self.changes.is_empty()
+    }
+
+    /// Human-readable summary, one line per change
+    pub fn summary(&self) -> String {
+        if self.is_empty() {
+            return "No changes".to_string();
+        }
+        let count = |kind: fn(&AXChange) -> bool| self.changes.iter().filter(|c| kind(c)).count();
+        let mut out = format!(
+            "{} added, {} removed, {} changed\n",
+            count(|c| matches!(c, AXChange::Added { .. })),
+            count(|c| matches!(c, AXChange::Removed { .. })),
+            count(|c| matches!(c, AXChange::Changed { .. }))
+        );
+        for change in &self.changes {
+            out.push_str(&change.describe());
+            out.push('\n');
+        }
+        out
+    }
+}
+
+/// Compare two snapshots. Elements are matched by their path of roles and
+/// identifiers (or titles); `children_count` isn't compared since added and
+/// removed children are reported themselves.
+pub fn diff_snapshots(before: &AXSnapshot, after: &AXSnapshot) -> AXDiff {
+    let before_elements = before.elements_by_path();
+    let after_elements = after.elements_by_path();
+    let before_by_path: HashMap<&str, &AXElement> = before_elements
+        .iter()
+        .map(|(path, element)| (path.as_str(), *element))
+        .collect();
+    let after_by_path: HashMap<&str, &AXElement> = after_elements
+        .iter()
+        .map(|(path, element)| (path.as_str(), *element))
+        .collect();
+
+    let mut changes = Vec::new();
+    for (path, element) in &after_elements {
+        match before_by_path.get(path.as_str()) {
+            None => changes.push(AXChange::Added {
+                path: path.clone(),
+                element: (*element).clone(),
+            }),
+            Some(previous) => {
+                let fields = changed_fields(previous, element);
+                if !fields.is_empty() {
+                    changes.push(AXChange::Changed {
+                        path: path.clone(),
+                        element: (*element).clone(),
+                        fields,
+                    });
+                }
+            }
+        }
+    }
+    for (path, element) in &before_elements {
+        if !after_by_path.contains_key(path.as_str()) {
+            changes.push(AXChange::Removed {
+                path: path.clone(),
+                element: (*element).clone(),
+            });
+        }
+    }
+
+    AXDiff { changes }
+}
+
+/// The role is part of the path, so it never differs between matched elements
+const COMPARED_FIELDS: [&str; 7] = [
+    "title", "value", "label", "enabled", "focused", "position", "size",
+];
+
+fn changed_fields(before: &AXElement, after: &AXElement) -> Vec<AXFieldChange> {
+    COMPARED_FIELDS
+        .iter()
+        .map(|field| AXFieldChange {
+            field: field.to_string(),
+            before: field_value(before, field),
+            after: field_value(after, field),
+        })
+        .filter(|change| change.before != change.after)
+        .collect()
+}
+
+fn field_value(element: &AXElement, field: &str) -> String {
+    let optional = |value: &Option<String>| match value {
+        Some(value) => format!("{:?}", value),
+        None => "none".to_string(),
+    };
+    let pair = |value: Option<(f64, f64)>| match value {
+        Some((a, b)) => format!("({:.0}, {:.0})", a, b),
+        None => "none".to_string(),
+    };
+    match field {
+        "title" => optional(&element.title),
+        "value" => optional(&element.value),
+        "label" => optional(&element.label),
+        "enabled" => element.enabled.to_string(),
+        "focused" => element.focused.to_string(),
+        "position" => pair(element.position),
+        "size" => pair(element.size),
+        _ => String::new(),
+    }
+}
@@ -1,7 +1,27 @@
 #[cfg(test)]
 mod tests {
+    use crate::macax::{diff_snapshots, AXChange, AXNode, AXSnapshot};
     use crate::{AXElement, MacAxController};
 
+    fn element(role: &str, title: Option<&str>, value: Option<&str>) -> AXElement {
+        AXElement {
+            role: role.to_string(),
+            title: title.map(str::to_string),
+            value: value.map(str::to_string),
+            label: None,
+            identifier: None,
+            enabled: true,
+            focused: false,
+            position: None,
+            size: None,
+            children_count: 0,
+        }
+    }
+
+    fn node(element: AXElement, children: Vec<AXNode>) -> AXNode {
+        AXNode { element, children }
+    }
+
     #[test]
     fn test_ax_element_to_string() {
         let element = AXElement {
@@ -34,4 +54,56 @@ mod tests {
         let result = MacAxController::new();
         assert!(result.is_ok());
     }
+
+    #[test]
+    fn test_diff_snapshots() {
+        let snapshot = |children: Vec<AXNode>| AXSnapshot {
+            app_name: "TextEdit".to_string(),
+            root: node(
+                element("AXApplication", Some("TextEdit"), None),
+                vec![node(element("AXWindow", Some("Untitled"), None), children)],
+            ),
+        };
+        let before = snapshot(vec![
+            node(element("AXTextArea", None, Some("")), vec![]),
+            node(element("AXButton", None, None), vec![]),
+            node(element("AXButton", None, None), vec![]),
+        ]);
+        let mut focused = element("AXTextArea", None, Some("Hello"));
+        focused.focused = true;
+        let after = snapshot(vec![
+            node(focused, vec![]),
+            node(element("AXButton", None, None), vec![]),
+            node(element("AXSheet", Some("Save"), None), vec![]),
+        ]);
+
+        assert!(diff_snapshots(&before, &before).is_empty());
+
+        let diff = diff_snapshots(&before, &after);
+        assert_eq!(diff.changes.len(), 3);
+        match &diff.changes[0] {
+            AXChange::Changed { path, fields, .. } => {
+                assert_eq!(
+                    path,
+                    "AXApplication[TextEdit]/AXWindow[Untitled]/AXTextArea"
+                );
+                let names: Vec<&str> = fields.iter().map(|c| c.field.as_str()).collect();
+                assert_eq!(names, vec!["value", "focused"]);
+            }
+            other => panic!("Expected a change, got {:?}", other),
+        }
+        assert_eq!(
+            diff.changes[1].describe(),
+            "+ AXApplication[TextEdit]/AXWindow[Untitled]/AXSheet[Save]"
+        );
+        assert_eq!(
+            diff.changes[2].describe(),
+            "- AXApplication[TextEdit]/AXWindow[Untitled]/AXButton#2"
+        );
+
+        let summary = diff.summary();
+        assert!(summary.starts_with("1 added, 1 removed, 1 changed\n"));
+        assert!(summary.contains("value \"\" -> \"Hello\", focused false -> true"));
+        assert_eq!(after.element_count(), 5);
+    }
 }
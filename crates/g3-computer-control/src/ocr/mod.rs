@@ -24,3 +24,149 @@ pub use vision::AppleVisionOCR as DefaultOCR;
 
 #[cfg(not(target_os = "macos"))]
 pub use tesseract::TesseractOCR as DefaultOCR;
+
+/// Pick the OCR result that best matches `search`, ignoring case: an exact
+/// match beats a whole-word match beats a substring match, and ties go to the
+/// higher confidence. Engines such as tesseract report single words, so for
+/// multi-word searches consecutive results on the same line are also tried
+/// as one phrase.
+pub fn best_match(locations: &[TextLocation], search: &str) -> Option<TextLocation> {
+    let search = search.trim().to_lowercase();
+    if search.is_empty() {
+        return None;
+    }
+    let word_count = search.split_whitespace().count();
+
+    let mut candidates: Vec<TextLocation> = locations.to_vec();
+    if word_count > 1 {
+        candidates.extend(
+            locations
+                .windows(word_count)
+                .filter(|words| words.windows(2).all(|pair| same_line(&pair[0], &pair[1])))
+                .map(merge),
+        );
+    }
+
+    candidates
+        .into_iter()
+        .filter_map(|location| {
+            let rank = match_rank(&location.text.to_lowercase(), &search)?;
+            Some((rank, location))
+        })
+        .max_by(|(rank_a, a), (rank_b, b)| {
+            rank_a
+                .cmp(rank_b)
+                .then(a.confidence.total_cmp(&b.confidence))
+        })
+        .map(|(_, location)| location)
+}
+
+/// 3 for an exact match, 2 if `search` appears as whole words, 1 for a
+/// substring; both arguments are lowercase
+fn match_rank(text: &str, search: &str) -> Option<u8> {
+    let text_words = words(text);
+    let search_words = words(search);
+    if text.trim() == search {
+        Some(3)
+    } else if !search_words.is_empty()
+        && text_words
+            .windows(search_words.len())
+            .any(|run| run == search_words.as_slice())
+    {
+        Some(2)
+    } else if text.contains(search) {
+        Some(1)
+    } else {
+        None
+    }
+}
+
+fn words(text: &str) -> Vec<&str> {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .collect()
+}
+
+/// Whether two boxes (in image coordinates) overlap vertically
+fn same_line(a: &TextLocation, b: &TextLocation) -> bool {
+    a.y < b.y + b.height && b.y < a.y + a.height
+}
+
+/// One location covering consecutive words
+fn merge(words: &[TextLocation]) -> TextLocation {
+    let left = words.iter().map(|w| w.x).min().unwrap_or_default();
+    let top = words.iter().map(|w| w.y).min().unwrap_or_default();
+    let right = words
+        .iter()
+        .map(|w| w.x + w.width)
+        .max()
+        .unwrap_or_default();
+    let bottom = words
+        .iter()
+        .map(|w| w.y + w.height)
+        .max()
+        .unwrap_or_default();
+    TextLocation {
+        text: words
+            .iter()
+            .map(|w| w.text.as_str())
+            .collect::<Vec<_>>()
+            .join(" "),
+        x: left,
+        y: top,
+        width: right - left,
+        height: bottom - top,
+        confidence: words
+            .iter()
+            .map(|w| w.confidence)
+            .fold(f32::INFINITY, f32::min),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn word(text: &str, x: i32, y: i32, confidence: f32) -> TextLocation {
+        TextLocation {
+            text: text.to_string(),
+            x,
+            y,
+            width: 40,
+            height: 12,
+            confidence,
+        }
+    }
+
+    #[test]
+    fn test_best_match_prefers_exact_words() {
+        let locations = vec![
+            word("Submitted", 0, 0, 0.99),
+            word("Submit form", 0, 50, 0.6),
+            word("submit", 0, 100, 0.8),
+            word("Submit", 0, 150, 0.9),
+        ];
+        assert_eq!(best_match(&locations, "Submit").unwrap().y, 150);
+        assert_eq!(best_match(&locations, " submit form ").unwrap().y, 50);
+        assert_eq!(best_match(&locations, "mitted").unwrap().y, 0);
+        assert!(best_match(&locations, "Cancel").is_none());
+        assert!(best_match(&locations, "").is_none());
+    }
+
+    #[test]
+    fn test_best_match_joins_words_on_a_line() {
+        let locations = vec![
+            word("Save", 10, 100, 0.9),
+            word("As...", 55, 102, 0.7),
+            word("Save", 10, 200, 0.9),
+        ];
+        let location = best_match(&locations, "Save As").unwrap();
+        assert_eq!(location.text, "Save As...");
+        assert_eq!((location.x, location.y), (10, 100));
+        assert_eq!((location.width, location.height), (85, 14));
+        assert_eq!(location.confidence, 0.7);
+
+        // "As..." and the second "Save" are on different lines
+        assert!(best_match(&locations, "As Save").is_none());
+    }
+}
@@ -1,5 +1,6 @@
+use crate::linux::{window, DisplayServer};
 use crate::{types::*, ComputerController};
-use anyhow::Result;
+use anyhow::{Context, Result};
 use async_trait::async_trait;
 use tesseract::Tesseract;
 use uuid::Uuid;
@@ -16,6 +17,13 @@ impl LinuxController {
     }
 }
 
+/// Window management goes through `wmctrl` or `swaymsg`, so it needs to know
+/// which display server the session runs
+fn display_server() -> Result<DisplayServer> {
+    DisplayServer::detect()
+        .context("No X11 or Wayland display found (DISPLAY and WAYLAND_DISPLAY are unset)")
+}
+
 #[async_trait]
 impl ComputerController for LinuxController {
     async fn move_mouse(&self, _x: i32, _y: i32) -> Result<()> {
@@ -38,12 +46,25 @@ impl ComputerController for LinuxController {
         anyhow::bail!("Linux implementation not yet available")
     }
 
-    async fn list_windows(&self) -> Result<Vec<Window>> {
-        anyhow::bail!("Linux implementation not yet available")
+    fn list_windows(&self) -> Result<Vec<WindowInfo>> {
+        window::list_windows(display_server()?)
     }
 
-    async fn focus_window(&self, _window_id: &str) -> Result<()> {
-        anyhow::bail!("Linux implementation not yet available")
+    fn focus_window(&self, title: &str) -> Result<WindowInfo> {
+        let display = display_server()?;
+        let windows = window::list_windows(display)?;
+        let window = find_window(&windows, title).cloned().ok_or_else(|| {
+            anyhow::anyhow!(
+                "No window matching '{}'. Use list_windows to see available windows.",
+                title
+            )
+        })?;
+        window::focus_window(display, &window.id)?;
+        Ok(window)
+    }
+
+    fn move_resize_window(&self, window_id: &str, bounds: Rect) -> Result<()> {
+        window::move_resize_window(display_server()?, window_id, bounds)
     }
 
     async fn get_window_bounds(&self, _window_id: &str) -> Result<Rect> {
@@ -1,6 +1,6 @@
-use crate::ocr::{DefaultOCR, OCREngine};
+use crate::ocr::{best_match, DefaultOCR, OCREngine};
 use crate::{
-    types::{Rect, TextLocation},
+    types::{find_window, Rect, TextLocation, WindowInfo},
     ComputerController,
 };
 use anyhow::{Context, Result};
@@ -67,112 +67,124 @@ impl ComputerController for MacOSController {
 
         let app_name = window_id.unwrap(); // Safe because we checked is_none() above
 
+        // A window id from list_windows selects that exact window; anything
+        // else is looked up as an application name
+        let listed_window = self.list_windows()?.into_iter().find(|w| w.id == app_name);
+
         // Get the window ID for the specified application
-        let cg_window_id = unsafe {
-            let window_list =
-                CGWindowListCopyWindowInfo(kCGWindowListOptionOnScreenOnly, kCGNullWindowID);
+        let cg_window_id = match listed_window {
+            Some(window) => window
+                .id
+                .parse::<u32>()
+                .ok()
+                .map(|id| (id, window.app_name)),
+            None => unsafe {
+                let window_list =
+                    CGWindowListCopyWindowInfo(kCGWindowListOptionOnScreenOnly, kCGNullWindowID);
 
-            let array = CFArray::<CFDictionary>::wrap_under_create_rule(window_list);
-            let count = array.len();
+                let array = CFArray::<CFDictionary>::wrap_under_create_rule(window_list);
+                let count = array.len();
 
-            let mut found_window_id: Option<(u32, String)> = None; // (id, owner)
-            let app_name_lower = app_name.to_lowercase();
+                let mut found_window_id: Option<(u32, String)> = None; // (id, owner)
+                let app_name_lower = app_name.to_lowercase();
 
-            for i in 0..count {
-                let dict = array.get(i).unwrap();
+                for i in 0..count {
+                    let dict = array.get(i).unwrap();
 
-                // Get owner name
-                let owner_key = CFString::from_static_string("kCGWindowOwnerName");
-                let owner: String = if let Some(value) = dict.find(owner_key.to_void()) {
-                    let s: CFString = TCFType::wrap_under_get_rule(*value as *const _);
-                    s.to_string()
-                } else {
-                    continue;
-                };
-
-                tracing::debug!(
-                    "Checking window: owner='{}', looking for '{}'",
-                    owner,
-                    app_name
-                );
-                let owner_lower = owner.to_lowercase();
-
-                // Normalize by removing spaces for exact matching
-                let app_name_normalized = app_name_lower.replace(" ", "");
-                let owner_normalized = owner_lower.replace(" ", "");
-
-                // ONLY accept exact matches (case-insensitive, with or without spaces)
-                // This prevents "Goose" from matching "GooseStudio"
-                let is_match =
-                    owner_lower == app_name_lower || owner_normalized == app_name_normalized;
+                    // Get owner name
+                    let owner_key = CFString::from_static_string("kCGWindowOwnerName");
+                    let owner: String = if let Some(value) = dict.find(owner_key.to_void()) {
+                        let s: CFString = TCFType::wrap_under_get_rule(*value as *const _);
+                        s.to_string()
+                    } else {
+                        continue;
+                    };
 
-                if is_match {
-                    // Get window ID
-                    let window_id_key = CFString::from_static_string("kCGWindowNumber");
-                    if let Some(value) = dict.find(window_id_key.to_void()) {
-                        let num: core_foundation::number::CFNumber =
-                            TCFType::wrap_under_get_rule(*value as *const _);
-                        if let Some(id) = num.to_i64() {
-                            // Get window layer to filter out menu bar windows
-                            let layer_key = CFString::from_static_string("kCGWindowLayer");
-                            let layer: i32 = if let Some(value) = dict.find(layer_key.to_void()) {
-                                let num: core_foundation::number::CFNumber =
-                                    TCFType::wrap_under_get_rule(*value as *const _);
-                                num.to_i32().unwrap_or(0)
-                            } else {
-                                0
-                            };
-
-                            // Get window bounds to verify it's a real window
-                            let bounds_key = CFString::from_static_string("kCGWindowBounds");
-                            let has_real_bounds =
-                                if let Some(value) = dict.find(bounds_key.to_void()) {
-                                    let bounds_dict: CFDictionary =
+                    tracing::debug!(
+                        "Checking window: owner='{}', looking for '{}'",
+                        owner,
+                        app_name
+                    );
+                    let owner_lower = owner.to_lowercase();
+
+                    // Normalize by removing spaces for exact matching
+                    let app_name_normalized = app_name_lower.replace(" ", "");
+                    let owner_normalized = owner_lower.replace(" ", "");
+
+                    // ONLY accept exact matches (case-insensitive, with or without spaces)
+                    // This prevents "Goose" from matching "GooseStudio"
+                    let is_match =
+                        owner_lower == app_name_lower || owner_normalized == app_name_normalized;
+
+                    if is_match {
+                        // Get window ID
+                        let window_id_key = CFString::from_static_string("kCGWindowNumber");
+                        if let Some(value) = dict.find(window_id_key.to_void()) {
+                            let num: core_foundation::number::CFNumber =
+                                TCFType::wrap_under_get_rule(*value as *const _);
+                            if let Some(id) = num.to_i64() {
+                                // Get window layer to filter out menu bar windows
+                                let layer_key = CFString::from_static_string("kCGWindowLayer");
+                                let layer: i32 = if let Some(value) = dict.find(layer_key.to_void())
+                                {
+                                    let num: core_foundation::number::CFNumber =
                                         TCFType::wrap_under_get_rule(*value as *const _);
-                                    let width_key = CFString::from_static_string("Width");
-                                    let height_key = CFString::from_static_string("Height");
-
-                                    if let (Some(w_val), Some(h_val)) = (
-                                        bounds_dict.find(width_key.to_void()),
-                                        bounds_dict.find(height_key.to_void()),
-                                    ) {
-                                        let w_num: core_foundation::number::CFNumber =
-                                            TCFType::wrap_under_get_rule(*w_val as *const _);
-                                        let h_num: core_foundation::number::CFNumber =
-                                            TCFType::wrap_under_get_rule(*h_val as *const _);
-                                        let width = w_num.to_f64().unwrap_or(0.0);
-                                        let height = h_num.to_f64().unwrap_or(0.0);
-                                        // Real windows should be at least 100x100 pixels
-                                        width >= 100.0 && height >= 100.0
-                                    } else {
-                                        false
-                                    }
+                                    num.to_i32().unwrap_or(0)
                                 } else {
-                                    false
+                                    0
                                 };
 
-                            // Only accept windows that are:
-                            // 1. At layer 0 (normal windows, not menu bar)
-                            // 2. Have real bounds (width and height >= 100)
-                            if layer == 0 && has_real_bounds {
-                                tracing::debug!("Found valid window: ID {} for app '{}' (layer={}, bounds valid)", id, owner, layer);
-                                found_window_id = Some((id as u32, owner.clone()));
-                                break;
-                            } else {
-                                tracing::debug!(
-                                    "Skipping window ID {} for '{}': layer={}, has_real_bounds={}",
-                                    id,
-                                    owner,
-                                    layer,
-                                    has_real_bounds
-                                );
+                                // Get window bounds to verify it's a real window
+                                let bounds_key = CFString::from_static_string("kCGWindowBounds");
+                                let has_real_bounds =
+                                    if let Some(value) = dict.find(bounds_key.to_void()) {
+                                        let bounds_dict: CFDictionary =
+                                            TCFType::wrap_under_get_rule(*value as *const _);
+                                        let width_key = CFString::from_static_string("Width");
+                                        let height_key = CFString::from_static_string("Height");
+
+                                        if let (Some(w_val), Some(h_val)) = (
+                                            bounds_dict.find(width_key.to_void()),
+                                            bounds_dict.find(height_key.to_void()),
+                                        ) {
+                                            let w_num: core_foundation::number::CFNumber =
+                                                TCFType::wrap_under_get_rule(*w_val as *const _);
+                                            let h_num: core_foundation::number::CFNumber =
+                                                TCFType::wrap_under_get_rule(*h_val as *const _);
+                                            let width = w_num.to_f64().unwrap_or(0.0);
+                                            let height = h_num.to_f64().unwrap_or(0.0);
+                                            // Real windows should be at least 100x100 pixels
+                                            width >= 100.0 && height >= 100.0
+                                        } else {
+                                            false
+                                        }
+                                    } else {
+                                        false
+                                    };
+
+                                // Only accept windows that are:
+                                // 1. At layer 0 (normal windows, not menu bar)
+                                // 2. Have real bounds (width and height >= 100)
+                                if layer == 0 && has_real_bounds {
+                                    tracing::debug!("Found valid window: ID {} for app '{}' (layer={}, bounds valid)", id, owner, layer);
+                                    found_window_id = Some((id as u32, owner.clone()));
+                                    break;
+                                } else {
+                                    tracing::debug!(
+                                        "Skipping window ID {} for '{}': layer={}, has_real_bounds={}",
+                                        id,
+                                        owner,
+                                        layer,
+                                        has_real_bounds
+                                    );
+                                }
                             }
                         }
                     }
                 }
-            }
 
-            found_window_id
+                found_window_id
+            },
         };
 
         let (cg_window_id, matched_owner) = cg_window_id.ok_or_else(|| {
@@ -215,6 +227,86 @@ impl ComputerController for MacOSController {
         Ok(())
     }
 
+    fn list_windows(&self) -> Result<Vec<WindowInfo>> {
+        let mut windows = Vec::new();
+        unsafe {
+            let window_list =
+                CGWindowListCopyWindowInfo(kCGWindowListOptionOnScreenOnly, kCGNullWindowID);
+            let array = CFArray::<CFDictionary>::wrap_under_create_rule(window_list);
+
+            for i in 0..array.len() {
+                let Some(dict) = array.get(i) else {
+                    continue;
+                };
+
+                // Layer 0 holds normal windows; the menu bar, dock and
+                // overlays are above it
+                if dict_number(&dict, "kCGWindowLayer").unwrap_or(0) != 0 {
+                    continue;
+                }
+                let (Some(id), Some(app_name), Some(bounds)) = (
+                    dict_number(&dict, "kCGWindowNumber"),
+                    dict_string(&dict, "kCGWindowOwnerName"),
+                    dict_bounds(&dict),
+                ) else {
+                    continue;
+                };
+                // Real windows should be at least 100x100 pixels
+                if bounds.width < 100 || bounds.height < 100 {
+                    continue;
+                }
+
+                windows.push(WindowInfo {
+                    id: id.to_string(),
+                    // Only readable with the Screen Recording permission
+                    title: dict_string(&dict, "kCGWindowName").unwrap_or_default(),
+                    app_name,
+                    pid: dict_number(&dict, "kCGWindowOwnerPID").map(|pid| pid as i32),
+                    bounds,
+                });
+            }
+        }
+        Ok(windows)
+    }
+
+    fn focus_window(&self, title: &str) -> Result<WindowInfo> {
+        let windows = self.list_windows()?;
+        let window = find_window(&windows, title).cloned().ok_or_else(|| {
+            anyhow::anyhow!(
+                "No window matching '{}'. Use list_windows to see available windows.",
+                title
+            )
+        })?;
+        let pid = window.pid.context("Window has no owning process")?;
+
+        run_osascript(&window_script(
+            pid,
+            &window.title,
+            true,
+            &["perform action \"AXRaise\"".to_string()],
+        ))?;
+        Ok(window)
+    }
+
+    fn move_resize_window(&self, window_id: &str, bounds: Rect) -> Result<()> {
+        let window = self
+            .list_windows()?
+            .into_iter()
+            .find(|w| w.id == window_id)
+            .ok_or_else(|| anyhow::anyhow!("No window with id {}", window_id))?;
+        let pid = window.pid.context("Window has no owning process")?;
+
+        run_osascript(&window_script(
+            pid,
+            &window.title,
+            false,
+            &[
+                format!("set position to {{{}, {}}}", bounds.x, bounds.y),
+                format!("set size to {{{}, {}}}", bounds.width, bounds.height),
+            ],
+        ))
+    }
+
     async fn extract_text_from_screen(&self, region: Rect, window_id: &str) -> Result<String> {
         // Take screenshot of region first
         let temp_path = format!("/tmp/g3_ocr_{}.png", uuid::Uuid::new_v4());
@@ -273,18 +365,23 @@ impl ComputerController for MacOSController {
         // Clean up temp file
         let _ = std::fs::remove_file(&temp_path);
 
-        // Find matching text (case-insensitive)
-        let search_lower = search_text.to_lowercase();
-        for location in locations {
-            if location.text.to_lowercase().contains(&search_lower) {
-                // Transform coordinates from screenshot space to screen space
-                let transformed =
-                    transform_screenshot_to_screen_coords(location, window_bounds, screenshot_dims);
-                return Ok(Some(transformed));
-            }
-        }
+        // Find the best matching text (case-insensitive) and transform its
+        // coordinates from screenshot space to screen space
+        Ok(best_match(&locations, search_text).map(|location| {
+            transform_screenshot_to_screen_coords(location, window_bounds, screenshot_dims)
+        }))
+    }
 
-        Ok(None)
+    fn text_click_point(&self, location: &TextLocation) -> (i32, i32) {
+        // Location coordinates are in NSScreen space (Y=0 at BOTTOM, increases
+        // UPWARD): location.y is the TOP edge of the bounding box, and width
+        // and height are already scaled to screen space.
+        // X: Use right edge instead of center (Vision OCR bounding box seems offset)
+        // Y: top edge - half of scaled height (subtract because Y increases upward)
+        (
+            location.x + location.width,
+            location.y - location.height / 2,
+        )
     }
 
     fn move_mouse(&self, x: i32, y: i32) -> Result<()> {
@@ -494,6 +591,76 @@ impl MacOSController {
     }
 }
 
+/// Read a string entry of a CGWindowList dictionary
+unsafe fn dict_string(dict: &CFDictionary, key: &'static str) -> Option<String> {
+    let key = CFString::from_static_string(key);
+    let value = dict.find(key.to_void())?;
+    let s: CFString = TCFType::wrap_under_get_rule(*value as *const _);
+    Some(s.to_string())
+}
+
+/// Read a number entry of a CGWindowList dictionary
+unsafe fn dict_number(dict: &CFDictionary, key: &'static str) -> Option<i64> {
+    let key = CFString::from_static_string(key);
+    let value = dict.find(key.to_void())?;
+    let num: core_foundation::number::CFNumber = TCFType::wrap_under_get_rule(*value as *const _);
+    num.to_i64()
+}
+
+/// Read the `kCGWindowBounds` of a CGWindowList dictionary
+unsafe fn dict_bounds(dict: &CFDictionary) -> Option<Rect> {
+    let key = CFString::from_static_string("kCGWindowBounds");
+    let value = dict.find(key.to_void())?;
+    let bounds: CFDictionary = TCFType::wrap_under_get_rule(*value as *const _);
+    Some(Rect {
+        x: dict_number(&bounds, "X")? as i32,
+        y: dict_number(&bounds, "Y")? as i32,
+        width: dict_number(&bounds, "Width")? as i32,
+        height: dict_number(&bounds, "Height")? as i32,
+    })
+}
+
+/// AppleScript running `commands` on a window through System Events. Without
+/// a title (no Screen Recording permission) the process's front window is used.
+fn window_script(pid: i32, title: &str, bring_to_front: bool, commands: &[String]) -> String {
+    let process = format!("(first process whose unix id is {})", pid);
+    let window = if title.is_empty() {
+        format!("window 1 of {}", process)
+    } else {
+        let escaped = title.replace('\\', "\\\\").replace('"', "\\\"");
+        format!(
+            "(first window of {} whose name is \"{}\")",
+            process, escaped
+        )
+    };
+
+    let mut script = String::from("tell application \"System Events\"\n");
+    if bring_to_front {
+        script.push_str(&format!("    set frontmost of {} to true\n", process));
+    }
+    script.push_str(&format!("    tell {}\n", window));
+    for command in commands {
+        script.push_str(&format!("        {}\n", command));
+    }
+    script.push_str("    end tell\nend tell\n");
+    script
+}
+
+fn run_osascript(script: &str) -> Result<()> {
+    let output = std::process::Command::new("osascript")
+        .arg("-e")
+        .arg(script)
+        .output()
+        .context("Failed to run osascript")?;
+    if !output.status.success() {
+        anyhow::bail!(
+            "osascript failed (the terminal needs the Accessibility permission): {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    Ok(())
+}
+
 /// Get image dimensions from a PNG file
 fn get_image_dimensions(path: &str) -> Result<(i32, i32)> {
     use std::fs::File;
@@ -43,3 +43,32 @@ mod window_matching_tests {
         }
     }
 }
+
+mod window_script_tests {
+    use super::super::window_script;
+
+    #[test]
+    fn test_window_script_escapes_title() {
+        let script = window_script(
+            42,
+            r#"Say "hi" \ bye"#,
+            true,
+            &["perform action \"AXRaise\"".to_string()],
+        );
+        assert_eq!(
+            script,
+            "tell application \"System Events\"\n    \
+             set frontmost of (first process whose unix id is 42) to true\n    \
+             tell (first window of (first process whose unix id is 42) whose name is \"Say \\\"hi\\\" \\\\ bye\")\n        \
+             perform action \"AXRaise\"\n    \
+             end tell\nend tell\n"
+        );
+    }
+
+    #[test]
+    fn test_window_script_without_title() {
+        let script = window_script(7, "", false, &["set size to {800, 600}".to_string()]);
+        assert!(script.contains("    tell window 1 of (first process whose unix id is 7)\n"));
+        assert!(!script.contains("frontmost"));
+    }
+}
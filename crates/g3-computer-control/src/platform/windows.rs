@@ -37,14 +37,6 @@ impl ComputerController for WindowsController {
         anyhow::bail!("Windows implementation not yet available")
     }
 
-    async fn list_windows(&self) -> Result<Vec<Window>> {
-        anyhow::bail!("Windows implementation not yet available")
-    }
-
-    async fn focus_window(&self, _window_id: &str) -> Result<()> {
-        anyhow::bail!("Windows implementation not yet available")
-    }
-
     async fn get_window_bounds(&self, _window_id: &str) -> Result<Rect> {
         anyhow::bail!("Windows implementation not yet available")
     }
@@ -0,0 +1,741 @@
+//! Recording and replay of desktop UI flows
+//!
+//! A [`Recorder`] collects the actions performed while it's active into a
+//! [`Recording`], a JSON script that can be saved, edited and replayed:
+//!
+//! ```json
+//! {
+//!   "name": "save a note",
+//!   "actions": [
+//!     { "action": "activate_app", "app": "TextEdit" },
+//!     { "action": "type_text", "app": "TextEdit", "text": "Hello" },
+//!     { "action": "press_key", "app": "TextEdit", "key": "s", "modifiers": ["command"] },
+//!     { "action": "assert_element", "app": "TextEdit", "element": { "role": "AXSheet" } },
+//!     { "action": "click_text", "app": "TextEdit", "text": "Save" },
+//!     { "action": "assert_value", "app": "TextEdit",
+//!       "element": { "role": "AXTextArea" }, "expected": "Hello" }
+//!   ]
+//! }
+//! ```
+//!
+//! Element actions and checkpoints go through an [`AccessibilityController`];
+//! coordinate clicks, OCR and window actions through a [`ComputerController`].
+//! Replay stops at the first action that still fails after its retries.
+
+use crate::types::Rect;
+use crate::webdriver::script::StepStatus;
+use crate::{AccessibilityController, ComputerController};
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+/// A UI element, matched like the accessibility tools do: role and, if
+/// given, title and identifier
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ElementRef {
+    pub role: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub identifier: Option<String>,
+}
+
+impl std::fmt::Display for ElementRef {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.role)?;
+        if let Some(ref title) = self.title {
+            write!(f, " {:?}", title)?;
+        }
+        if let Some(ref identifier) = self.identifier {
+            write!(f, " #{}", identifier)?;
+        }
+        Ok(())
+    }
+}
+
+/// One recorded action or checkpoint
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "action", rename_all = "snake_case")]
+pub enum RecordedAction {
+    ActivateApp {
+        app: String,
+    },
+    FocusWindow {
+        title: String,
+    },
+    /// Click at screen coordinates
+    Click {
+        x: i32,
+        y: i32,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        app: Option<String>,
+    },
+    /// Click text found on screen with OCR
+    ClickText {
+        app: String,
+        text: String,
+    },
+    ClickElement {
+        app: String,
+        element: ElementRef,
+    },
+    SetValue {
+        app: String,
+        element: ElementRef,
+        value: String,
+    },
+    TypeText {
+        app: String,
+        text: String,
+    },
+    PressKey {
+        app: String,
+        key: String,
+        #[serde(default)]
+        modifiers: Vec<String>,
+    },
+    MoveResizeWindow {
+        window_id: String,
+        bounds: Rect,
+    },
+    Wait {
+        ms: u64,
+    },
+    /// Checkpoint: the element's value equals `expected`
+    AssertValue {
+        app: String,
+        element: ElementRef,
+        expected: String,
+    },
+    /// Checkpoint: the element exists
+    AssertElement {
+        app: String,
+        element: ElementRef,
+    },
+    /// Checkpoint: OCR finds `text` in the application's window
+    AssertText {
+        app: String,
+        text: String,
+    },
+}
+
+impl RecordedAction {
+    /// Whether this is an assertion rather than an input
+    pub fn is_checkpoint(&self) -> bool {
+        matches!(
+            self,
+            RecordedAction::AssertValue { .. }
+                | RecordedAction::AssertElement { .. }
+                | RecordedAction::AssertText { .. }
+        )
+    }
+
+    /// Short description for reports, e.g. `click_text "Save" in TextEdit`
+    pub fn describe(&self) -> String {
+        match self {
+            RecordedAction::ActivateApp { app } => format!("activate_app {}", app),
+            RecordedAction::FocusWindow { title } => format!("focus_window {:?}", title),
+            RecordedAction::Click { x, y, .. } => format!("click ({}, {})", x, y),
+            RecordedAction::ClickText { app, text } => {
+                format!("click_text {:?} in {}", text, app)
+            }
+            RecordedAction::ClickElement { app, element } => {
+                format!("click_element {} in {}", element, app)
+            }
+            RecordedAction::SetValue { app, element, .. } => {
+                format!("set_value {} in {}", element, app)
+            }
+            RecordedAction::TypeText { app, .. } => format!("type_text in {}", app),
+            RecordedAction::PressKey {
+                app,
+                key,
+                modifiers,
+            } => {
+                let mut combo = modifiers.clone();
+                combo.push(key.clone());
+                format!("press_key {} in {}", combo.join("+"), app)
+            }
+            RecordedAction::MoveResizeWindow { window_id, .. } => {
+                format!("move_resize_window {}", window_id)
+            }
+            RecordedAction::Wait { ms } => format!("wait {} ms", ms),
+            RecordedAction::AssertValue {
+                app,
+                element,
+                expected,
+            } => format!("assert_value {} in {} == {:?}", element, app, expected),
+            RecordedAction::AssertElement { app, element } => {
+                format!("assert_element {} in {}", element, app)
+            }
+            RecordedAction::AssertText { app, text } => {
+                format!("assert_text {:?} in {}", text, app)
+            }
+        }
+    }
+}
+
+/// A replayable sequence of actions
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Recording {
+    pub name: String,
+    pub actions: Vec<RecordedAction>,
+}
+
+impl Recording {
+    pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let json = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read recording {}", path.display()))?;
+        serde_json::from_str(&json).with_context(|| format!("Invalid recording {}", path.display()))
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<()> {
+        let path = path.as_ref();
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(path, serde_json::to_string_pretty(self)?)
+            .with_context(|| format!("Failed to write recording {}", path.display()))
+    }
+
+    /// Number of checkpoints in the recording
+    pub fn checkpoint_count(&self) -> usize {
+        self.actions.iter().filter(|a| a.is_checkpoint()).count()
+    }
+}
+
+/// Collects actions while a recording is in progress; recording while
+/// inactive does nothing, so callers can report every action unconditionally
+#[derive(Debug, Default)]
+pub struct Recorder {
+    recording: Option<Recording>,
+}
+
+impl Recorder {
+    /// Start a new recording, discarding one in progress
+    pub fn start(&mut self, name: &str) {
+        self.recording = Some(Recording {
+            name: name.to_string(),
+            actions: Vec::new(),
+        });
+    }
+
+    pub fn is_recording(&self) -> bool {
+        self.recording.is_some()
+    }
+
+    pub fn record(&mut self, action: RecordedAction) {
+        if let Some(recording) = self.recording.as_mut() {
+            recording.actions.push(action);
+        }
+    }
+
+    /// The recording in progress
+    pub fn current(&self) -> Option<&Recording> {
+        self.recording.as_ref()
+    }
+
+    /// Finish recording
+    pub fn stop(&mut self) -> Option<Recording> {
+        self.recording.take()
+    }
+}
+
+/// The controllers a replay runs against
+#[derive(Clone, Copy, Default)]
+pub struct ReplayTargets<'a> {
+    pub accessibility: Option<&'a dyn AccessibilityController>,
+    pub computer: Option<&'a dyn ComputerController>,
+}
+
+/// How recordings are replayed
+#[derive(Debug, Clone)]
+pub struct ReplayOptions {
+    /// Extra attempts for a failing action
+    pub retries: u32,
+    /// Pause between attempts
+    pub retry_delay: Duration,
+    /// Pause after every action, giving the UI time to react
+    pub step_delay: Duration,
+}
+
+impl Default for ReplayOptions {
+    fn default() -> Self {
+        Self {
+            retries: 2,
+            retry_delay: Duration::from_millis(500),
+            step_delay: Duration::from_millis(300),
+        }
+    }
+}
+
+/// Report for one replayed action
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ActionReport {
+    pub action: RecordedAction,
+    pub status: StepStatus,
+    /// Number of attempts made (0 if skipped)
+    pub attempts: u32,
+    pub duration: Duration,
+    /// Error of the last attempt, if the action failed
+    pub error: Option<String>,
+}
+
+/// Report for a whole replay
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReplayReport {
+    pub name: String,
+    pub actions: Vec<ActionReport>,
+    pub duration: Duration,
+}
+
+impl ReplayReport {
+    /// Whether every action passed
+    pub fn passed(&self) -> bool {
+        self.actions
+            .iter()
+            .all(|action| action.status == StepStatus::Passed)
+    }
+
+    /// Human-readable summary, one line per action
+    pub fn summary(&self) -> String {
+        let passed = self
+            .actions
+            .iter()
+            .filter(|action| action.status == StepStatus::Passed)
+            .count();
+        let mut out = format!(
+            "{} {}: {}/{} actions passed in {} ms\n",
+            if self.passed() { "✅" } else { "❌" },
+            self.name,
+            passed,
+            self.actions.len(),
+            self.duration.as_millis()
+        );
+        for (i, report) in self.actions.iter().enumerate() {
+            let line = match report.status {
+                StepStatus::Passed => format!("{}. ✅ {}", i + 1, report.action.describe()),
+                StepStatus::Failed => format!(
+                    "{}. ❌ {} ({} attempt(s)): {}",
+                    i + 1,
+                    report.action.describe(),
+                    report.attempts,
+                    report.error.as_deref().unwrap_or("failed")
+                ),
+                StepStatus::Skipped => {
+                    format!("{}. ⏭️ {} (skipped)", i + 1, report.action.describe())
+                }
+            };
+            out.push_str(&line);
+            out.push('\n');
+        }
+        out
+    }
+}
+
+/// Replay `recording`, stopping at the first action that fails
+pub async fn replay(
+    recording: &Recording,
+    targets: ReplayTargets<'_>,
+    options: &ReplayOptions,
+) -> ReplayReport {
+    let replay_start = Instant::now();
+    let mut reports = Vec::with_capacity(recording.actions.len());
+    let mut failed = false;
+
+    for action in &recording.actions {
+        if failed {
+            reports.push(ActionReport {
+                action: action.clone(),
+                status: StepStatus::Skipped,
+                attempts: 0,
+                duration: Duration::ZERO,
+                error: None,
+            });
+            continue;
+        }
+
+        let action_start = Instant::now();
+        let mut attempts = 0;
+        let error = loop {
+            attempts += 1;
+            match run_action(action, targets).await {
+                Ok(()) => break None,
+                Err(e) if attempts > options.retries => break Some(format!("{:#}", e)),
+                Err(_) => tokio::time::sleep(options.retry_delay).await,
+            }
+        };
+
+        failed = error.is_some();
+        reports.push(ActionReport {
+            action: action.clone(),
+            status: if failed {
+                StepStatus::Failed
+            } else {
+                StepStatus::Passed
+            },
+            attempts,
+            duration: action_start.elapsed(),
+            error,
+        });
+        if !failed {
+            tokio::time::sleep(options.step_delay).await;
+        }
+    }
+
+    ReplayReport {
+        name: recording.name.clone(),
+        actions: reports,
+        duration: replay_start.elapsed(),
+    }
+}
+
+async fn run_action(action: &RecordedAction, targets: ReplayTargets<'_>) -> Result<()> {
+    let accessibility = || {
+        targets
+            .accessibility
+            .context("Accessibility control is not available")
+    };
+    let computer = || {
+        targets
+            .computer
+            .context("Computer control is not available")
+    };
+
+    match action {
+        RecordedAction::ActivateApp { app } => accessibility()?.activate_app(app),
+        RecordedAction::FocusWindow { title } => computer()?.focus_window(title).map(|_| ()),
+        RecordedAction::Click { x, y, app } => computer()?.click_at(*x, *y, app.as_deref()),
+        RecordedAction::ClickText { app, text } => {
+            computer()?.click_text(app, text).await.map(|_| ())
+        }
+        RecordedAction::ClickElement { app, element } => accessibility()?.click_element(
+            app,
+            &element.role,
+            element.title.as_deref(),
+            element.identifier.as_deref(),
+        ),
+        RecordedAction::SetValue {
+            app,
+            element,
+            value,
+        } => accessibility()?.set_value(
+            app,
+            &element.role,
+            value,
+            element.title.as_deref(),
+            element.identifier.as_deref(),
+        ),
+        RecordedAction::TypeText { app, text } => accessibility()?.type_text(app, text),
+        RecordedAction::PressKey {
+            app,
+            key,
+            modifiers,
+        } => accessibility()?.press_key(app, key, modifiers.iter().map(String::as_str).collect()),
+        RecordedAction::MoveResizeWindow { window_id, bounds } => {
+            computer()?.move_resize_window(window_id, *bounds)
+        }
+        RecordedAction::Wait { ms } => {
+            tokio::time::sleep(Duration::from_millis(*ms)).await;
+            Ok(())
+        }
+        RecordedAction::AssertValue {
+            app,
+            element,
+            expected,
+        } => {
+            let value = accessibility()?.get_value(
+                app,
+                &element.role,
+                element.title.as_deref(),
+                element.identifier.as_deref(),
+            )?;
+            if &value != expected {
+                anyhow::bail!("Expected {:?}, found {:?}", expected, value);
+            }
+            Ok(())
+        }
+        RecordedAction::AssertElement { app, element } => {
+            let found = accessibility()?.find_elements(
+                app,
+                Some(&element.role),
+                element.title.as_deref(),
+                element.identifier.as_deref(),
+            )?;
+            if found.is_empty() {
+                anyhow::bail!("No {} in {}", element, app);
+            }
+            Ok(())
+        }
+        RecordedAction::AssertText { app, text } => {
+            if computer()?.find_text_in_app(app, text).await?.is_none() {
+                anyhow::bail!("Text {:?} not found in {}", text, app);
+            }
+            Ok(())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::macax::{AXApplication, AXElement, AXSnapshot};
+    use std::sync::Mutex;
+
+    /// An application with a single text field
+    #[derive(Default)]
+    struct FakeApp {
+        text: Mutex<String>,
+        calls: Mutex<Vec<String>>,
+    }
+
+    impl FakeApp {
+        fn call(&self, name: &str) {
+            self.calls.lock().unwrap().push(name.to_string());
+        }
+    }
+
+    impl AccessibilityController for FakeApp {
+        fn list_applications(&self) -> Result<Vec<AXApplication>> {
+            Ok(Vec::new())
+        }
+
+        fn get_frontmost_app(&self) -> Result<AXApplication> {
+            anyhow::bail!("no applications")
+        }
+
+        fn activate_app(&self, _app_name: &str) -> Result<()> {
+            self.call("activate_app");
+            Ok(())
+        }
+
+        fn get_ui_tree(&self, _app_name: &str, _max_depth: usize) -> Result<String> {
+            Ok(String::new())
+        }
+
+        fn snapshot_tree(&self, _app_name: &str, _max_depth: usize) -> Result<AXSnapshot> {
+            anyhow::bail!("no snapshots")
+        }
+
+        fn find_elements(
+            &self,
+            _app_name: &str,
+            role: Option<&str>,
+            _title: Option<&str>,
+            _identifier: Option<&str>,
+        ) -> Result<Vec<AXElement>> {
+            self.call("find_elements");
+            if role != Some("AXTextField") {
+                return Ok(Vec::new());
+            }
+            Ok(vec![AXElement {
+                role: "AXTextField".to_string(),
+                title: None,
+                value: Some(self.text.lock().unwrap().clone()),
+                label: None,
+                identifier: None,
+                enabled: true,
+                focused: true,
+                position: None,
+                size: None,
+                children_count: 0,
+            }])
+        }
+
+        fn click_element(
+            &self,
+            _app_name: &str,
+            _role: &str,
+            _title: Option<&str>,
+            _identifier: Option<&str>,
+        ) -> Result<()> {
+            self.call("click_element");
+            Ok(())
+        }
+
+        fn set_value(
+            &self,
+            _app_name: &str,
+            _role: &str,
+            value: &str,
+            _title: Option<&str>,
+            _identifier: Option<&str>,
+        ) -> Result<()> {
+            self.call("set_value");
+            *self.text.lock().unwrap() = value.to_string();
+            Ok(())
+        }
+
+        fn get_value(
+            &self,
+            _app_name: &str,
+            _role: &str,
+            _title: Option<&str>,
+            _identifier: Option<&str>,
+        ) -> Result<String> {
+            self.call("get_value");
+            Ok(self.text.lock().unwrap().clone())
+        }
+
+        fn type_text(&self, _app_name: &str, text: &str) -> Result<()> {
+            self.call("type_text");
+            self.text.lock().unwrap().push_str(text);
+            Ok(())
+        }
+
+        fn press_key(&self, _app_name: &str, _key: &str, _modifiers: Vec<&str>) -> Result<()> {
+            self.call("press_key");
+            Ok(())
+        }
+    }
+
+    fn field() -> ElementRef {
+        ElementRef {
+            role: "AXTextField".to_string(),
+            title: None,
+            identifier: None,
+        }
+    }
+
+    fn options() -> ReplayOptions {
+        ReplayOptions {
+            retries: 1,
+            retry_delay: Duration::ZERO,
+            step_delay: Duration::ZERO,
+        }
+    }
+
+    #[test]
+    fn test_recording_json() {
+        let json = r#"{
+            "name": "note",
+            "actions": [
+                { "action": "activate_app", "app": "Notes" },
+                { "action": "press_key", "app": "Notes", "key": "n", "modifiers": ["command"] },
+                { "action": "click", "x": 10, "y": 20 },
+                { "action": "assert_value", "app": "Notes",
+                  "element": { "role": "AXTextArea", "identifier": "body" }, "expected": "" }
+            ]
+        }"#;
+        let recording: Recording = serde_json::from_str(json).unwrap();
+        assert_eq!(recording.actions.len(), 4);
+        assert_eq!(recording.checkpoint_count(), 1);
+        assert_eq!(
+            recording.actions[2],
+            RecordedAction::Click {
+                x: 10,
+                y: 20,
+                app: None
+            }
+        );
+        assert_eq!(
+            recording.actions[1].describe(),
+            "press_key command+n in Notes"
+        );
+
+        let round_trip: Recording =
+            serde_json::from_str(&serde_json::to_string(&recording).unwrap()).unwrap();
+        assert_eq!(round_trip, recording);
+    }
+
+    #[test]
+    fn test_recorder() {
+        let mut recorder = Recorder::default();
+        recorder.record(RecordedAction::Wait { ms: 10 });
+        assert!(!recorder.is_recording());
+
+        recorder.start("flow");
+        recorder.record(RecordedAction::ActivateApp {
+            app: "Notes".to_string(),
+        });
+        assert_eq!(recorder.current().unwrap().actions.len(), 1);
+
+        let recording = recorder.stop().unwrap();
+        assert_eq!(recording.name, "flow");
+        assert_eq!(recording.actions.len(), 1);
+        assert!(recorder.stop().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_replay_passes_checkpoints() {
+        let app = FakeApp::default();
+        let recording = Recording {
+            name: "fill".to_string(),
+            actions: vec![
+                RecordedAction::ActivateApp {
+                    app: "Form".to_string(),
+                },
+                RecordedAction::SetValue {
+                    app: "Form".to_string(),
+                    element: field(),
+                    value: "Hello".to_string(),
+                },
+                RecordedAction::TypeText {
+                    app: "Form".to_string(),
+                    text: ", world".to_string(),
+                },
+                RecordedAction::AssertElement {
+                    app: "Form".to_string(),
+                    element: field(),
+                },
+                RecordedAction::AssertValue {
+                    app: "Form".to_string(),
+                    element: field(),
+                    expected: "Hello, world".to_string(),
+                },
+            ],
+        };
+        let targets = ReplayTargets {
+            accessibility: Some(&app),
+            computer: None,
+        };
+
+        let report = replay(&recording, targets, &options()).await;
+        assert!(report.passed(), "{}", report.summary());
+        assert_eq!(
+            *app.calls.lock().unwrap(),
+            [
+                "activate_app",
+                "set_value",
+                "type_text",
+                "find_elements",
+                "get_value"
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_replay_stops_at_failed_checkpoint() {
+        let app = FakeApp::default();
+        let recording = Recording {
+            name: "fill".to_string(),
+            actions: vec![
+                RecordedAction::AssertValue {
+                    app: "Form".to_string(),
+                    element: field(),
+                    expected: "Hello".to_string(),
+                },
+                RecordedAction::ClickText {
+                    app: "Form".to_string(),
+                    text: "Submit".to_string(),
+                },
+            ],
+        };
+        let targets = ReplayTargets {
+            accessibility: Some(&app),
+            computer: None,
+        };
+
+        let report = replay(&recording, targets, &options()).await;
+        assert!(!report.passed());
+        assert_eq!(report.actions[0].status, StepStatus::Failed);
+        assert_eq!(report.actions[0].attempts, 2);
+        assert_eq!(
+            report.actions[0].error.as_deref(),
+            Some("Expected \"Hello\", found \"\"")
+        );
+        assert_eq!(report.actions[1].status, StepStatus::Skipped);
+        assert!(report.summary().contains("0/2 actions passed"));
+    }
+}
@@ -1,6 +1,6 @@
 use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub struct Rect {
     pub x: i32,
     pub y: i32,
@@ -17,3 +17,85 @@ pub struct TextLocation {
     pub height: i32,
     pub confidence: f32,
 }
+
+/// A top-level window on screen
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WindowInfo {
+    /// Platform window id: the CGWindowID on macOS, the X11 window id or the
+    /// sway container id on Linux
+    pub id: String,
+    pub title: String,
+    /// Name of the owning application
+    pub app_name: String,
+    pub pid: Option<i32>,
+    /// Position and size in screen coordinates (origin at the top left)
+    pub bounds: Rect,
+}
+
+/// Find the window best matching `query`, ignoring case: an exact title
+/// beats an exact application name beats a substring of the title.
+pub fn find_window<'a>(windows: &'a [WindowInfo], query: &str) -> Option<&'a WindowInfo> {
+    let query = query.to_lowercase();
+    if query.is_empty() {
+        return None;
+    }
+    let rank = |window: &WindowInfo| {
+        let title = window.title.to_lowercase();
+        if title == query {
+            Some(3)
+        } else if window.app_name.to_lowercase() == query {
+            Some(2)
+        } else if title.contains(&query) {
+            Some(1)
+        } else {
+            None
+        }
+    };
+
+    // max_by_key keeps the last of equal elements; windows are listed front
+    // to back, so prefer the first
+    windows
+        .iter()
+        .rev()
+        .filter_map(|window| Some((rank(window)?, window)))
+        .max_by_key(|(rank, _)| *rank)
+        .map(|(_, window)| window)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn window(id: &str, title: &str, app_name: &str) -> WindowInfo {
+        WindowInfo {
+            id: id.to_string(),
+            title: title.to_string(),
+            app_name: app_name.to_string(),
+            pid: None,
+            bounds: Rect {
+                x: 0,
+                y: 0,
+                width: 800,
+                height: 600,
+            },
+        }
+    }
+
+    #[test]
+    fn test_find_window() {
+        let windows = vec![
+            window("1", "notes.txt - Editor", "TextEdit"),
+            window("2", "Terminal", "iTerm2"),
+            window("3", "Downloads", "Finder"),
+            window("4", "todo.txt - Editor", "TextEdit"),
+        ];
+
+        assert_eq!(find_window(&windows, "terminal").unwrap().id, "2");
+        assert_eq!(find_window(&windows, "Finder").unwrap().id, "3");
+        // Several windows match: the frontmost one wins
+        assert_eq!(find_window(&windows, "textedit").unwrap().id, "1");
+        assert_eq!(find_window(&windows, "todo").unwrap().id, "4");
+        assert!(find_window(&windows, "Safari").is_none());
+        assert!(find_window(&windows, "").is_none());
+    }
+}
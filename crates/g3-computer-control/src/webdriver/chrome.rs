@@ -1,13 +1,50 @@
-use super::{WebDriverController, WebElement};
+use super::network::NetworkRecorder;
+use super::{
+    execute_cdp, find_in_shadow, read_log, save_screenshot, switch_to_frame, WebDriverController,
+    WebElement,
+};
 use anyhow::{Context, Result};
 use async_trait::async_trait;
+use base64::Engine;
 use fantoccini::{Client, ClientBuilder};
-use serde_json::Value;
-use std::time::Duration;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// A browser console entry
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ConsoleLogEntry {
+    /// "SEVERE" (errors), "WARNING", "INFO" or "DEBUG"
+    pub level: String,
+    pub message: String,
+    /// Where the entry came from, e.g. "javascript" for uncaught exceptions,
+    /// "console-api" for `console.*` calls or "network" for failed loads
+    pub source: Option<String>,
+    pub timestamp: SystemTime,
+}
+
+impl ConsoleLogEntry {
+    /// Parse an entry of the WebDriver "browser" log
+    fn from_log_entry(entry: &Value) -> Option<Self> {
+        Some(Self {
+            level: entry["level"].as_str().unwrap_or("INFO").to_string(),
+            message: entry["message"].as_str()?.to_string(),
+            source: entry["source"].as_str().map(str::to_string),
+            timestamp: UNIX_EPOCH
+                + Duration::from_millis(entry["timestamp"].as_u64().unwrap_or_default()),
+        })
+    }
+
+    /// Whether this is an error, such as an uncaught exception or `console.error`
+    pub fn is_error(&self) -> bool {
+        self.level == "SEVERE"
+    }
+}
 
 /// ChromeDriver WebDriver controller with headless support
 pub struct ChromeDriver {
     client: Client,
+    network: NetworkRecorder,
 }
 
 impl ChromeDriver {
@@ -54,6 +91,12 @@ impl ChromeDriver {
             ]),
         );
 
+        // Forward DevTools network events to the performance log (see `network()`)
+        chrome_options.insert(
+            "perfLoggingPrefs".to_string(),
+            json!({ "enableNetwork": true, "enablePage": false }),
+        );
+
         // If a custom Chrome binary is specified, use it
         if let Some(binary) = chrome_binary {
             chrome_options.insert("binary".to_string(), Value::String(binary.to_string()));
@@ -63,6 +106,10 @@ impl ChromeDriver {
             "goog:chromeOptions".to_string(),
             Value::Object(chrome_options),
         );
+        caps.insert(
+            "goog:loggingPrefs".to_string(),
+            json!({ "browser": "ALL", "performance": "ALL" }),
+        );
 
         // Use a timeout for the connection attempt to avoid hanging indefinitely
         let mut builder = ClientBuilder::native();
@@ -75,7 +122,23 @@ impl ChromeDriver {
             .context("Connection to ChromeDriver timed out after 30 seconds")?
             .context("Failed to connect to ChromeDriver")?;
 
-        Ok(Self { client })
+        let network = NetworkRecorder::new(client.clone());
+        Ok(Self { client, network })
+    }
+
+    /// Browser console entries logged since the last call (or since the
+    /// session started)
+    pub async fn get_console_logs(&mut self) -> Result<Vec<ConsoleLogEntry>> {
+        let entries = read_log(&self.client, "browser").await?;
+        Ok(entries
+            .iter()
+            .filter_map(ConsoleLogEntry::from_log_entry)
+            .collect())
+    }
+
+    /// Network request recording for this session
+    pub fn network(&mut self) -> &mut NetworkRecorder {
+        &mut self.network
     }
 
     /// Go back in browser history
@@ -228,6 +291,23 @@ impl WebDriverController for ChromeDriver {
             .collect())
     }
 
+    async fn find_in_shadow(
+        &mut self,
+        host_selector: &str,
+        inner_selector: &str,
+    ) -> Result<WebElement> {
+        find_in_shadow(&self.client, host_selector, inner_selector).await
+    }
+
+    async fn switch_to_frame(&mut self, selector: &str) -> Result<()> {
+        switch_to_frame(&self.client, selector).await
+    }
+
+    async fn switch_to_parent_frame(&mut self) -> Result<()> {
+        self.client.enter_parent_frame().await?;
+        Ok(())
+    }
+
     async fn execute_script(&mut self, script: &str, args: Vec<Value>) -> Result<Value> {
         Ok(self.client.execute(script, args).await?)
     }
@@ -238,20 +318,39 @@ impl WebDriverController for ChromeDriver {
 
     async fn screenshot(&mut self, path: &str) -> Result<()> {
         let screenshot_data = self.client.screenshot().await?;
+        save_screenshot(path, &screenshot_data)
+    }
 
-        // Expand tilde in path
-        let expanded_path = shellexpand::tilde(path);
-        let path_str = expanded_path.as_ref();
-
-        // Create parent directories if needed
-        if let Some(parent) = std::path::Path::new(path_str).parent() {
-            std::fs::create_dir_all(parent)
-                .context("Failed to create parent directories for screenshot")?;
-        }
-
-        std::fs::write(path_str, screenshot_data).context("Failed to write screenshot to file")?;
-
-        Ok(())
+    async fn screenshot_full_page(&mut self, path: &str) -> Result<()> {
+        // Capture a clip the size of the whole document
+        let metrics = execute_cdp(&self.client, "Page.getLayoutMetrics", json!({})).await?;
+        let size = if metrics["cssContentSize"].is_object() {
+            &metrics["cssContentSize"]
+        } else {
+            &metrics["contentSize"]
+        };
+        let (width, height) = match (size["width"].as_f64(), size["height"].as_f64()) {
+            (Some(width), Some(height)) => (width, height),
+            _ => anyhow::bail!("Failed to get the page size: {}", metrics),
+        };
+
+        let screenshot = execute_cdp(
+            &self.client,
+            "Page.captureScreenshot",
+            json!({
+                "format": "png",
+                "captureBeyondViewport": true,
+                "clip": { "x": 0, "y": 0, "width": width, "height": height, "scale": 1 }
+            }),
+        )
+        .await?;
+        let data = screenshot["data"]
+            .as_str()
+            .context("Screenshot response has no image data")?;
+        let screenshot_data = base64::engine::general_purpose::STANDARD
+            .decode(data)
+            .context("Failed to decode screenshot")?;
+        save_screenshot(path, &screenshot_data)
     }
 
     async fn close(&mut self) -> Result<()> {
@@ -264,3 +363,32 @@ impl WebDriverController for ChromeDriver {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_console_log_entry_from_log_entry() {
+        let entry = ConsoleLogEntry::from_log_entry(&json!({
+            "level": "SEVERE",
+            "message": "http://localhost:3000/app.js 12:5 Uncaught TypeError: x is undefined",
+            "source": "javascript",
+            "timestamp": 1700000000123u64
+        }))
+        .unwrap();
+        assert!(entry.is_error());
+        assert_eq!(entry.source.as_deref(), Some("javascript"));
+        assert_eq!(
+            entry.timestamp,
+            UNIX_EPOCH + Duration::from_millis(1700000000123)
+        );
+
+        let entry =
+            ConsoleLogEntry::from_log_entry(&json!({ "level": "INFO", "message": "hi" })).unwrap();
+        assert!(!entry.is_error());
+        assert_eq!(entry.source, None);
+
+        assert!(ConsoleLogEntry::from_log_entry(&json!({ "level": "INFO" })).is_none());
+    }
+}
@@ -1,8 +1,12 @@
 pub mod safari;
 pub mod chrome;
+pub mod network;
+pub mod script;
 
-use anyhow::Result;
+use anyhow::{Context, Result};
 use async_trait::async_trait;
+use fantoccini::wd::WebDriverCompatibleCommand;
+use fantoccini::Client;
 use serde_json::Value;
 
 /// WebDriver controller for browser automation
@@ -23,6 +27,23 @@ pub trait WebDriverController: Send + Sync {
     /// Find multiple elements by CSS selector
     async fn find_elements(&mut self, selector: &str) -> Result<Vec<WebElement>>;
 
+    /// Find an element inside the open shadow root of `host_selector`.
+    ///
+    /// For nested shadow DOM, separate the hosts with `>>>`, e.g.
+    /// `"my-app >>> settings-panel"`.
+    async fn find_in_shadow(
+        &mut self,
+        host_selector: &str,
+        inner_selector: &str,
+    ) -> Result<WebElement>;
+
+    /// Switch to the iframe matching a CSS selector. Later lookups happen
+    /// inside that frame.
+    async fn switch_to_frame(&mut self, selector: &str) -> Result<()>;
+
+    /// Switch back to the parent of the current frame
+    async fn switch_to_parent_frame(&mut self) -> Result<()>;
+
     /// Execute JavaScript in the browser
     async fn execute_script(&mut self, script: &str, args: Vec<Value>) -> Result<Value>;
 
@@ -32,6 +53,17 @@ pub trait WebDriverController: Send + Sync {
     /// Take a screenshot and save to path
     async fn screenshot(&mut self, path: &str) -> Result<()>;
 
+    /// Take a screenshot of the element matching a CSS selector and save to path
+    async fn screenshot_element(&mut self, selector: &str, path: &str) -> Result<()> {
+        let element = self.find_element(selector).await?;
+        let data = element.screenshot().await?;
+        save_screenshot(path, &data)
+    }
+
+    /// Take a screenshot of the whole page, including what is scrolled out of
+    /// view, and save to path
+    async fn screenshot_full_page(&mut self, path: &str) -> Result<()>;
+
     /// Close the current window/tab
     async fn close(&mut self) -> Result<()>;
 
@@ -98,6 +130,11 @@ impl WebElement {
         Ok(self.inner.is_selected().await?)
     }
 
+    /// Take a PNG screenshot of just this element
+    pub async fn screenshot(&self) -> Result<Vec<u8>> {
+        Ok(self.inner.screenshot().await?)
+    }
+
     /// Find a child element by CSS selector
     pub async fn find_element(&mut self, selector: &str) -> Result<WebElement> {
         let elem = self.inner.find(fantoccini::Locator::Css(selector)).await?;
@@ -116,3 +153,154 @@ impl WebElement {
             .collect())
     }
 }
+
+/// Key of element references in WebDriver JSON (W3C spec)
+const ELEMENT_KEY: &str = "element-6066-11e4-a52e-4f735466cecf";
+
+/// Walks `hosts` shadow roots down from the document and queries the innermost one
+const FIND_IN_SHADOW_SCRIPT: &str = "
+    const [hosts, selector] = arguments;
+    let root = document;
+    for (const host of hosts) {
+        const element = root.querySelector(host);
+        if (!element) {
+            throw new Error('Shadow host not found: ' + host);
+        }
+        if (!element.shadowRoot) {
+            throw new Error('Element has no open shadow root: ' + host);
+        }
+        root = element.shadowRoot;
+    }
+    return root.querySelector(selector);
+";
+
+pub(crate) async fn find_in_shadow(
+    client: &Client,
+    host_selector: &str,
+    inner_selector: &str,
+) -> Result<WebElement> {
+    let hosts: Vec<&str> = host_selector.split(">>>").map(str::trim).collect();
+    if hosts.iter().any(|host| host.is_empty()) {
+        anyhow::bail!("Invalid shadow host selector: {}", host_selector);
+    }
+    let result = client
+        .execute(
+            FIND_IN_SHADOW_SCRIPT,
+            vec![serde_json::json!(hosts), Value::from(inner_selector)],
+        )
+        .await
+        .with_context(|| format!("Failed to search the shadow root of {}", host_selector))?;
+    let element_id = result[ELEMENT_KEY].as_str().with_context(|| {
+        format!(
+            "No element matching '{}' in the shadow root of '{}'",
+            inner_selector, host_selector
+        )
+    })?;
+    Ok(WebElement {
+        inner: fantoccini::elements::Element::from_element_id(
+            client.clone(),
+            element_id.to_string().into(),
+        ),
+    })
+}
+
+pub(crate) async fn switch_to_frame(client: &Client, selector: &str) -> Result<()> {
+    let frame = client
+        .find(fantoccini::Locator::Css(selector))
+        .await
+        .with_context(|| format!("Failed to find frame with selector: {}", selector))?;
+    frame.enter_frame().await?;
+    Ok(())
+}
+
+/// Write PNG screenshot data to `path`, creating parent directories as needed
+pub(crate) fn save_screenshot(path: &str, data: &[u8]) -> Result<()> {
+    // Expand tilde in path
+    let expanded_path = shellexpand::tilde(path);
+    let path_str = expanded_path.as_ref();
+
+    // Create parent directories if needed
+    if let Some(parent) = std::path::Path::new(path_str).parent() {
+        std::fs::create_dir_all(parent)
+            .context("Failed to create parent directories for screenshot")?;
+    }
+
+    std::fs::write(path_str, data).context("Failed to write screenshot to file")?;
+
+    Ok(())
+}
+
+/// ChromeDriver's (legacy) "get log" command, `POST /session/{id}/se/log`
+#[derive(Debug)]
+struct GetLog {
+    log_type: String,
+}
+
+impl WebDriverCompatibleCommand for GetLog {
+    fn endpoint(
+        &self,
+        base_url: &url::Url,
+        session_id: Option<&str>,
+    ) -> std::result::Result<url::Url, url::ParseError> {
+        base_url.join(&format!(
+            "session/{}/se/log",
+            session_id.unwrap_or_default()
+        ))
+    }
+
+    fn method_and_body(&self, _request_url: &url::Url) -> (http::Method, Option<String>) {
+        let body = serde_json::json!({ "type": self.log_type }).to_string();
+        (http::Method::POST, Some(body))
+    }
+}
+
+/// Read (and drain) a WebDriver log such as "performance" or "browser"
+pub(crate) async fn read_log(client: &Client, log_type: &str) -> Result<Vec<Value>> {
+    let entries = client
+        .issue_cmd(GetLog {
+            log_type: log_type.to_string(),
+        })
+        .await
+        .with_context(|| format!("Failed to read the {} log", log_type))?;
+    match entries {
+        Value::Array(entries) => Ok(entries),
+        other => anyhow::bail!("Unexpected {} log response: {}", log_type, other),
+    }
+}
+
+/// ChromeDriver's `POST /session/{id}/goog/cdp/execute` command, which runs a
+/// Chrome DevTools Protocol method
+#[derive(Debug)]
+struct ExecuteCdp {
+    method: String,
+    params: Value,
+}
+
+impl WebDriverCompatibleCommand for ExecuteCdp {
+    fn endpoint(
+        &self,
+        base_url: &url::Url,
+        session_id: Option<&str>,
+    ) -> std::result::Result<url::Url, url::ParseError> {
+        base_url.join(&format!(
+            "session/{}/goog/cdp/execute",
+            session_id.unwrap_or_default()
+        ))
+    }
+
+    fn method_and_body(&self, _request_url: &url::Url) -> (http::Method, Option<String>) {
+        let body = serde_json::json!({ "cmd": self.method, "params": self.params }).to_string();
+        (http::Method::POST, Some(body))
+    }
+}
+
+/// Run a DevTools Protocol method (Chrome only) and return its result
+pub(crate) async fn execute_cdp(client: &Client, method: &str, params: Value) -> Result<Value> {
+    client
+        .issue_cmd(ExecuteCdp {
+            method: method.to_string(),
+            params,
+        })
+        .await
+        .with_context(|| format!("DevTools command {} failed", method))
+}
@@ -0,0 +1,303 @@
+//! Network request capture for Chrome
+//!
+//! ChromeDriver forwards Chrome DevTools Protocol `Network.*` events to the
+//! WebDriver performance log when the session is created with
+//! `goog:loggingPrefs`. [`NetworkRecorder`] reads that log and pieces the
+//! events of each request together.
+
+use super::read_log;
+use anyhow::{Context, Result};
+use fantoccini::Client;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// A request made by the page
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct NetworkRequest {
+    /// DevTools request id
+    pub id: String,
+    pub method: String,
+    pub url: String,
+    /// Resource type reported by Chrome, e.g. "XHR", "Fetch" or "Document"
+    pub resource_type: Option<String>,
+    /// HTTP status, once the response headers arrived
+    pub status: Option<u16>,
+    pub mime_type: Option<String>,
+    /// When the request was sent
+    pub started_at: Option<SystemTime>,
+    /// Time from sending the request until the response finished loading
+    pub duration: Option<Duration>,
+    /// Decoded response body size in bytes
+    pub body_size: u64,
+    /// Set if the request failed, e.g. "net::ERR_CONNECTION_REFUSED"
+    pub error: Option<String>,
+    /// Whether loading finished (or failed)
+    pub finished: bool,
+}
+
+impl NetworkRequest {
+    fn new(id: &str, params: &Value) -> Self {
+        let request = &params["request"];
+        Self {
+            id: id.to_string(),
+            method: request["method"].as_str().unwrap_or("GET").to_string(),
+            url: request["url"].as_str().unwrap_or_default().to_string(),
+            resource_type: params["type"].as_str().map(str::to_string),
+            status: None,
+            mime_type: None,
+            started_at: params["wallTime"]
+                .as_f64()
+                .filter(|secs| secs.is_finite() && *secs >= 0.0)
+                .map(|secs| UNIX_EPOCH + Duration::from_secs_f64(secs)),
+            duration: None,
+            body_size: 0,
+            error: None,
+            finished: false,
+        }
+    }
+
+    fn set_response(&mut self, response: &Value) {
+        self.status = response["status"].as_u64().map(|status| status as u16);
+        self.mime_type = response["mimeType"].as_str().map(str::to_string);
+    }
+}
+
+/// Records the network requests of a Chrome session
+#[derive(Debug, Default)]
+pub struct NetworkRecorder {
+    client: Option<Client>,
+    requests: Vec<NetworkRequest>,
+    /// Index into `requests` and start timestamp (monotonic seconds) of
+    /// requests that haven't finished yet, by request id
+    in_flight: HashMap<String, (usize, f64)>,
+}
+
+impl NetworkRecorder {
+    /// Create a recorder for `client`, whose session must have the
+    /// performance log enabled
+    pub fn new(client: Client) -> Self {
+        Self {
+            client: Some(client),
+            ..Self::default()
+        }
+    }
+
+    /// Start (or restart) recording. Requests made before this call are
+    /// discarded.
+    pub async fn start_recording(&mut self) -> Result<()> {
+        self.read_events().await?;
+        self.requests.clear();
+        self.in_flight.clear();
+        Ok(())
+    }
+
+    /// All requests made since [`Self::start_recording`], in the order they
+    /// were sent. Requests that are still loading have `finished == false`.
+    pub async fn get_requests(&mut self) -> Result<Vec<NetworkRequest>> {
+        for event in self.read_events().await? {
+            self.handle_event(&event);
+        }
+        Ok(self.requests.clone())
+    }
+
+    /// Read the DevTools events logged since the last read
+    async fn read_events(&self) -> Result<Vec<Value>> {
+        let client = self
+            .client
+            .as_ref()
+            .context("Network recording is not available for this session")?;
+        let entries = read_log(client, "performance").await?;
+        Ok(entries
+            .iter()
+            .filter_map(|entry| entry["message"].as_str())
+            .filter_map(|message| serde_json::from_str::<Value>(message).ok())
+            .filter_map(|mut message| message.get_mut("message").map(Value::take))
+            .collect())
+    }
+
+    /// Apply one DevTools event (`{"method": ..., "params": ...}`)
+    fn handle_event(&mut self, event: &Value) {
+        let params = &event["params"];
+        let Some(id) = params["requestId"].as_str() else {
+            return;
+        };
+        let timestamp = params["timestamp"].as_f64().unwrap_or_default();
+
+        match event["method"].as_str().unwrap_or_default() {
+            "Network.requestWillBeSent" => {
+                // A redirect reuses the request id: finish the previous hop
+                if !params["redirectResponse"].is_null() {
+                    if let Some(request) = self.finish(id, timestamp) {
+                        request.set_response(&params["redirectResponse"]);
+                    }
+                }
+                self.in_flight
+                    .insert(id.to_string(), (self.requests.len(), timestamp));
+                self.requests.push(NetworkRequest::new(id, params));
+            }
+            "Network.responseReceived" => {
+                if let Some(request) = self.in_flight_mut(id) {
+                    request.set_response(&params["response"]);
+                }
+            }
+            "Network.dataReceived" => {
+                if let Some(request) = self.in_flight_mut(id) {
+                    request.body_size += params["dataLength"].as_u64().unwrap_or_default();
+                }
+            }
+            "Network.loadingFinished" => {
+                if let Some(request) = self.finish(id, timestamp) {
+                    if request.body_size == 0 {
+                        request.body_size = params["encodedDataLength"]
+                            .as_f64()
+                            .map(|size| size as u64)
+                            .unwrap_or_default();
+                    }
+                }
+            }
+            "Network.loadingFailed" => {
+                if let Some(request) = self.finish(id, timestamp) {
+                    request.error = params["errorText"].as_str().map(str::to_string);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn in_flight_mut(&mut self, id: &str) -> Option<&mut NetworkRequest> {
+        let (index, _) = self.in_flight.get(id)?;
+        self.requests.get_mut(*index)
+    }
+
+    /// Mark an in-flight request as finished at `timestamp`
+    fn finish(&mut self, id: &str, timestamp: f64) -> Option<&mut NetworkRequest> {
+        let (index, started) = self.in_flight.remove(id)?;
+        let request = self.requests.get_mut(index)?;
+        request.finished = true;
+        let elapsed = timestamp - started;
+        if elapsed.is_finite() && elapsed >= 0.0 {
+            request.duration = Some(Duration::from_secs_f64(elapsed));
+        }
+        Some(request)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn record(events: &[Value]) -> Vec<NetworkRequest> {
+        let mut recorder = NetworkRecorder::default();
+        for event in events {
+            recorder.handle_event(event);
+        }
+        recorder.requests
+    }
+
+    fn sent(id: &str, method: &str, url: &str, timestamp: f64) -> Value {
+        json!({
+            "method": "Network.requestWillBeSent",
+            "params": {
+                "requestId": id,
+                "request": { "method": method, "url": url },
+                "type": "Fetch",
+                "timestamp": timestamp,
+                "wallTime": 1700000000.5
+            }
+        })
+    }
+
+    #[test]
+    fn test_completed_request() {
+        let requests = record(&[
+            sent("1", "POST", "https://example.com/api/items", 10.0),
+            json!({
+                "method": "Network.responseReceived",
+                "params": {
+                    "requestId": "1",
+                    "timestamp": 10.1,
+                    "response": { "status": 201, "mimeType": "application/json" }
+                }
+            }),
+            json!({
+                "method": "Network.dataReceived",
+                "params": { "requestId": "1", "timestamp": 10.2, "dataLength": 120 }
+            }),
+            json!({
+                "method": "Network.dataReceived",
+                "params": { "requestId": "1", "timestamp": 10.2, "dataLength": 30 }
+            }),
+            json!({
+                "method": "Network.loadingFinished",
+                "params": { "requestId": "1", "timestamp": 10.25, "encodedDataLength": 400 }
+            }),
+        ]);
+
+        assert_eq!(requests.len(), 1);
+        let request = &requests[0];
+        assert_eq!(request.method, "POST");
+        assert_eq!(request.url, "https://example.com/api/items");
+        assert_eq!(request.resource_type.as_deref(), Some("Fetch"));
+        assert_eq!(request.status, Some(201));
+        assert_eq!(request.mime_type.as_deref(), Some("application/json"));
+        assert_eq!(request.body_size, 150);
+        assert!(request.finished);
+        let duration = request.duration.unwrap();
+        assert!((duration.as_secs_f64() - 0.25).abs() < 1e-6);
+        assert_eq!(
+            request.started_at,
+            Some(UNIX_EPOCH + Duration::from_secs_f64(1700000000.5))
+        );
+    }
+
+    #[test]
+    fn test_failed_pending_and_redirected_requests() {
+        let requests = record(&[
+            sent("1", "GET", "https://example.com/down", 1.0),
+            json!({
+                "method": "Network.loadingFailed",
+                "params": {
+                    "requestId": "1",
+                    "timestamp": 1.5,
+                    "errorText": "net::ERR_CONNECTION_REFUSED"
+                }
+            }),
+            sent("2", "GET", "https://example.com/slow", 2.0),
+            sent("3", "GET", "http://example.com/old", 3.0),
+            json!({
+                "method": "Network.requestWillBeSent",
+                "params": {
+                    "requestId": "3",
+                    "request": { "method": "GET", "url": "https://example.com/new" },
+                    "timestamp": 3.1,
+                    "redirectResponse": { "status": 301 }
+                }
+            }),
+            // Events of requests made before recording started are ignored
+            json!({
+                "method": "Network.loadingFinished",
+                "params": { "requestId": "0", "timestamp": 3.2 }
+            }),
+        ]);
+
+        assert_eq!(requests.len(), 4);
+        assert_eq!(
+            requests[0].error.as_deref(),
+            Some("net::ERR_CONNECTION_REFUSED")
+        );
+        assert!(requests[0].finished);
+        assert_eq!(requests[0].status, None);
+
+        assert!(!requests[1].finished);
+        assert_eq!(requests[1].duration, None);
+
+        assert_eq!(requests[2].url, "http://example.com/old");
+        assert_eq!(requests[2].status, Some(301));
+        assert!(requests[2].finished);
+        assert_eq!(requests[3].url, "https://example.com/new");
+        assert!(!requests[3].finished);
+    }
+}
@@ -1,4 +1,4 @@
-use super::{WebDriverController, WebElement};
+use super::{find_in_shadow, save_screenshot, switch_to_frame, WebDriverController, WebElement};
 use anyhow::{Context, Result};
 use async_trait::async_trait;
 use fantoccini::{Client, ClientBuilder};
@@ -194,6 +194,23 @@ impl WebDriverController for SafariDriver {
             .collect())
     }
 
+    async fn find_in_shadow(
+        &mut self,
+        host_selector: &str,
+        inner_selector: &str,
+    ) -> Result<WebElement> {
+        find_in_shadow(&self.client, host_selector, inner_selector).await
+    }
+
+    async fn switch_to_frame(&mut self, selector: &str) -> Result<()> {
+        switch_to_frame(&self.client, selector).await
+    }
+
+    async fn switch_to_parent_frame(&mut self) -> Result<()> {
+        self.client.enter_parent_frame().await?;
+        Ok(())
+    }
+
     async fn execute_script(&mut self, script: &str, args: Vec<Value>) -> Result<Value> {
         Ok(self.client.execute(script, args).await?)
     }
@@ -204,19 +221,44 @@ impl WebDriverController for SafariDriver {
 
     async fn screenshot(&mut self, path: &str) -> Result<()> {
         let screenshot_data = self.client.screenshot().await?;
+        save_screenshot(path, &screenshot_data)
+    }
 
-        // Expand tilde in path
-        let expanded_path = shellexpand::tilde(path);
-        let path_str = expanded_path.as_ref();
-
-        // Create parent directories if needed
-        if let Some(parent) = std::path::Path::new(path_str).parent() {
-            std::fs::create_dir_all(parent)
-                .context("Failed to create parent directories for screenshot")?;
-        }
-
-        std::fs::write(path_str, screenshot_data).context("Failed to write screenshot to file")?;
-
+    async fn screenshot_full_page(&mut self, path: &str) -> Result<()> {
+        // Safari has no full-page capture: grow the window to fit the page,
+        // take a screenshot and restore the window size
+        let (window_width, window_height) = self.client.get_window_size().await?;
+        let page = self
+            .client
+            .execute(
+                "const doc = document.documentElement;
+                 return [
+                     Math.max(doc.scrollWidth, window.innerWidth),
+                     Math.max(doc.scrollHeight, window.innerHeight),
+                     window.outerWidth - window.innerWidth,
+                     window.outerHeight - window.innerHeight
+                 ];",
+                vec![],
+            )
+            .await?;
+        let dimension = |i: usize| {
+            let value = page[i].as_f64().unwrap_or_default().ceil() as u64;
+            u32::try_from(value).unwrap_or(u32::MAX)
+        };
+        let width = dimension(0).saturating_add(dimension(2));
+        let height = dimension(1).saturating_add(dimension(3));
+
+        self.client.set_window_size(width, height).await?;
+        let screenshot_data = self.client.screenshot().await;
+        let restored = self
+            .client
+            .set_window_size(
+                u32::try_from(window_width).unwrap_or(u32::MAX),
+                u32::try_from(window_height).unwrap_or(u32::MAX),
+            )
+            .await;
+        save_screenshot(path, &screenshot_data?)?;
+        restored.context("Failed to restore the window size")?;
         Ok(())
     }
 
@@ -0,0 +1,489 @@
+//! Declarative browser automation scripts
+//!
+//! A script is a JSON list of steps, run in order against any
+//! [`WebDriverController`]:
+//!
+//! ```json
+//! [
+//!   { "action": "navigate", "url": "http://localhost:3000/login" },
+//!   { "action": "type", "selector": "#email", "text": "me@example.com" },
+//!   { "action": "click", "selector": "button[type=submit]" },
+//!   { "action": "wait_for", "selector": ".dashboard", "timeout_ms": 5000 },
+//!   { "action": "assert_text", "selector": "h1", "text": "Welcome" },
+//!   { "action": "screenshot", "path": "tmp/dashboard.png" }
+//! ]
+//! ```
+//!
+//! Failed steps are retried, and the run stops at the first step that still
+//! fails. The [`ScriptReport`] has the outcome and timing of every step.
+
+use super::WebDriverController;
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::time::{Duration, Instant};
+
+/// One step of a script
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "action", rename_all = "snake_case")]
+pub enum Step {
+    /// Go to a URL
+    Navigate { url: String },
+    /// Wait until an element matching `selector` is displayed
+    WaitFor {
+        selector: String,
+        /// Defaults to [`ScriptOptions::wait_timeout`]
+        #[serde(default)]
+        timeout_ms: Option<u64>,
+    },
+    /// Click an element
+    Click { selector: String },
+    /// Type into an input element, clearing it first unless `clear` is false
+    Type {
+        selector: String,
+        text: String,
+        #[serde(default = "default_clear")]
+        clear: bool,
+    },
+    /// Check that an element's text contains `text`
+    AssertText { selector: String, text: String },
+    /// Save a screenshot of the viewport, or the whole page
+    Screenshot {
+        path: String,
+        #[serde(default)]
+        full_page: bool,
+    },
+}
+
+fn default_clear() -> bool {
+    true
+}
+
+impl Step {
+    /// Short description for reports, e.g. `click #submit`
+    pub fn describe(&self) -> String {
+        match self {
+            Step::Navigate { url } => format!("navigate {}", url),
+            Step::WaitFor { selector, .. } => format!("wait_for {}", selector),
+            Step::Click { selector } => format!("click {}", selector),
+            Step::Type { selector, .. } => format!("type {}", selector),
+            Step::AssertText { selector, text } => {
+                format!("assert_text {} contains {:?}", selector, text)
+            }
+            Step::Screenshot { path, .. } => format!("screenshot {}", path),
+        }
+    }
+}
+
+/// Parse a script: either a list of steps or `{"steps": [...]}`
+pub fn parse_steps(script: &Value) -> Result<Vec<Step>> {
+    let steps = match script {
+        Value::Object(object) => object
+            .get("steps")
+            .context("Script object has no 'steps' list")?,
+        other => other,
+    };
+    serde_json::from_value(steps.clone()).context("Invalid script steps")
+}
+
+/// How scripts are run
+#[derive(Debug, Clone)]
+pub struct ScriptOptions {
+    /// Extra attempts for a failing step (`wait_for` waits instead)
+    pub retries: u32,
+    /// Pause between attempts
+    pub retry_delay: Duration,
+    /// How long `wait_for` waits unless the step sets `timeout_ms`
+    pub wait_timeout: Duration,
+}
+
+impl Default for ScriptOptions {
+    fn default() -> Self {
+        Self {
+            retries: 2,
+            retry_delay: Duration::from_millis(500),
+            wait_timeout: Duration::from_secs(10),
+        }
+    }
+}
+
+/// Outcome of a step
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StepStatus {
+    Passed,
+    Failed,
+    /// Not run because an earlier step failed
+    Skipped,
+}
+
+/// Report for one step
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StepReport {
+    pub step: Step,
+    pub status: StepStatus,
+    /// Number of attempts made (0 if skipped)
+    pub attempts: u32,
+    pub duration: Duration,
+    /// Error of the last attempt, if the step failed
+    pub error: Option<String>,
+}
+
+/// Report for a whole script
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScriptReport {
+    pub steps: Vec<StepReport>,
+    pub duration: Duration,
+}
+
+impl ScriptReport {
+    /// Whether every step passed
+    pub fn passed(&self) -> bool {
+        self.steps
+            .iter()
+            .all(|step| step.status == StepStatus::Passed)
+    }
+
+    /// Human-readable summary, one line per step
+    pub fn summary(&self) -> String {
+        let passed = self
+            .steps
+            .iter()
+            .filter(|step| step.status == StepStatus::Passed)
+            .count();
+        let mut out = format!(
+            "{} {}/{} steps passed in {} ms\n",
+            if self.passed() { "✅" } else { "❌" },
+            passed,
+            self.steps.len(),
+            self.duration.as_millis()
+        );
+        for (i, report) in self.steps.iter().enumerate() {
+            let line = match report.status {
+                StepStatus::Passed => format!(
+                    "{}. ✅ {} ({} ms)",
+                    i + 1,
+                    report.step.describe(),
+                    report.duration.as_millis()
+                ),
+                StepStatus::Failed => format!(
+                    "{}. ❌ {} ({} ms, {} attempt(s)): {}",
+                    i + 1,
+                    report.step.describe(),
+                    report.duration.as_millis(),
+                    report.attempts,
+                    report.error.as_deref().unwrap_or("failed")
+                ),
+                StepStatus::Skipped => {
+                    format!("{}. ⏭️ {} (skipped)", i + 1, report.step.describe())
+                }
+            };
+            out.push_str(&line);
+            out.push('\n');
+        }
+        out
+    }
+}
+
+/// Run `steps` against `driver`, stopping at the first step that fails
+pub async fn run_script<C>(driver: &mut C, steps: &[Step], options: &ScriptOptions) -> ScriptReport
+where
+    C: WebDriverController + ?Sized,
+{
+    let script_start = Instant::now();
+    let mut reports = Vec::with_capacity(steps.len());
+    let mut failed = false;
+
+    for step in steps {
+        if failed {
+            reports.push(StepReport {
+                step: step.clone(),
+                status: StepStatus::Skipped,
+                attempts: 0,
+                duration: Duration::ZERO,
+                error: None,
+            });
+            continue;
+        }
+
+        let step_start = Instant::now();
+        // wait_for polls until its timeout, so retrying it would only wait longer
+        let max_attempts = match step {
+            Step::WaitFor { .. } => 1,
+            _ => options.retries + 1,
+        };
+        let mut attempts = 0;
+        let error = loop {
+            attempts += 1;
+            match run_step(driver, step, options).await {
+                Ok(()) => break None,
+                Err(e) if attempts >= max_attempts => break Some(format!("{:#}", e)),
+                Err(_) => tokio::time::sleep(options.retry_delay).await,
+            }
+        };
+
+        failed = error.is_some();
+        reports.push(StepReport {
+            step: step.clone(),
+            status: if failed {
+                StepStatus::Failed
+            } else {
+                StepStatus::Passed
+            },
+            attempts,
+            duration: step_start.elapsed(),
+            error,
+        });
+    }
+
+    ScriptReport {
+        steps: reports,
+        duration: script_start.elapsed(),
+    }
+}
+
+async fn run_step<C>(driver: &mut C, step: &Step, options: &ScriptOptions) -> Result<()>
+where
+    C: WebDriverController + ?Sized,
+{
+    match step {
+        Step::Navigate { url } => driver.navigate(url).await,
+        Step::WaitFor {
+            selector,
+            timeout_ms,
+        } => {
+            let timeout = timeout_ms
+                .map(Duration::from_millis)
+                .unwrap_or(options.wait_timeout);
+            let start = Instant::now();
+            loop {
+                if let Ok(element) = driver.find_element(selector).await {
+                    if element.is_displayed().await.unwrap_or(false) {
+                        return Ok(());
+                    }
+                }
+                if start.elapsed() >= timeout {
+                    anyhow::bail!("Timeout waiting for element: {}", selector);
+                }
+                tokio::time::sleep(Duration::from_millis(100)).await;
+            }
+        }
+        Step::Click { selector } => driver.find_element(selector).await?.click().await,
+        Step::Type {
+            selector,
+            text,
+            clear,
+        } => {
+            let mut element = driver.find_element(selector).await?;
+            if *clear {
+                element.clear().await?;
+            }
+            element.send_keys(text).await
+        }
+        Step::AssertText { selector, text } => {
+            let actual = driver.find_element(selector).await?.text().await?;
+            if !actual.contains(text.as_str()) {
+                anyhow::bail!("Expected text containing {:?}, found {:?}", text, actual);
+            }
+            Ok(())
+        }
+        Step::Screenshot { path, full_page } => {
+            if *full_page {
+                driver.screenshot_full_page(path).await
+            } else {
+                driver.screenshot(path).await
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::webdriver::WebElement;
+    use async_trait::async_trait;
+    use serde_json::json;
+
+    /// A browser without elements that fails the first `failures` navigations
+    #[derive(Default)]
+    struct FakeDriver {
+        failures: u32,
+        visited: Vec<String>,
+        screenshots: Vec<String>,
+    }
+
+    #[async_trait]
+    impl WebDriverController for FakeDriver {
+        async fn navigate(&mut self, url: &str) -> Result<()> {
+            if self.failures > 0 {
+                self.failures -= 1;
+                anyhow::bail!("connection refused");
+            }
+            self.visited.push(url.to_string());
+            Ok(())
+        }
+
+        async fn current_url(&self) -> Result<String> {
+            Ok(self.visited.last().cloned().unwrap_or_default())
+        }
+
+        async fn title(&self) -> Result<String> {
+            Ok(String::new())
+        }
+
+        async fn find_element(&mut self, selector: &str) -> Result<WebElement> {
+            anyhow::bail!("no such element: {}", selector)
+        }
+
+        async fn find_elements(&mut self, _selector: &str) -> Result<Vec<WebElement>> {
+            Ok(Vec::new())
+        }
+
+        async fn find_in_shadow(
+            &mut self,
+            host_selector: &str,
+            _inner_selector: &str,
+        ) -> Result<WebElement> {
+            anyhow::bail!("no such element: {}", host_selector)
+        }
+
+        async fn switch_to_frame(&mut self, selector: &str) -> Result<()> {
+            anyhow::bail!("no such frame: {}", selector)
+        }
+
+        async fn switch_to_parent_frame(&mut self) -> Result<()> {
+            Ok(())
+        }
+
+        async fn execute_script(&mut self, _script: &str, _args: Vec<Value>) -> Result<Value> {
+            Ok(Value::Null)
+        }
+
+        async fn page_source(&self) -> Result<String> {
+            Ok(String::new())
+        }
+
+        async fn screenshot(&mut self, path: &str) -> Result<()> {
+            self.screenshots.push(path.to_string());
+            Ok(())
+        }
+
+        async fn screenshot_full_page(&mut self, path: &str) -> Result<()> {
+            self.screenshots.push(format!("full:{}", path));
+            Ok(())
+        }
+
+        async fn close(&mut self) -> Result<()> {
+            Ok(())
+        }
+
+        async fn quit(self) -> Result<()> {
+            Ok(())
+        }
+    }
+
+    fn options() -> ScriptOptions {
+        ScriptOptions {
+            retries: 2,
+            retry_delay: Duration::from_millis(1),
+            wait_timeout: Duration::from_millis(50),
+        }
+    }
+
+    #[test]
+    fn test_parse_steps() {
+        let steps = parse_steps(&json!({
+            "steps": [
+                { "action": "navigate", "url": "http://localhost:3000" },
+                { "action": "type", "selector": "#q", "text": "rust" },
+                { "action": "wait_for", "selector": ".results", "timeout_ms": 2000 },
+                { "action": "screenshot", "path": "tmp/page.png", "full_page": true }
+            ]
+        }))
+        .unwrap();
+        assert_eq!(
+            steps,
+            vec![
+                Step::Navigate {
+                    url: "http://localhost:3000".to_string()
+                },
+                Step::Type {
+                    selector: "#q".to_string(),
+                    text: "rust".to_string(),
+                    clear: true
+                },
+                Step::WaitFor {
+                    selector: ".results".to_string(),
+                    timeout_ms: Some(2000)
+                },
+                Step::Screenshot {
+                    path: "tmp/page.png".to_string(),
+                    full_page: true
+                },
+            ]
+        );
+
+        assert!(parse_steps(&json!([{ "action": "hover", "selector": "a" }])).is_err());
+        assert!(parse_steps(&json!([{ "action": "click" }])).is_err());
+        assert!(parse_steps(&json!({ "actions": [] })).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_run_script_retries_and_stops_at_failure() {
+        let steps = parse_steps(&json!([
+            { "action": "navigate", "url": "http://localhost:3000" },
+            { "action": "screenshot", "path": "tmp/a.png" },
+            { "action": "wait_for", "selector": "#missing" },
+            { "action": "click", "selector": "#next" }
+        ]))
+        .unwrap();
+        let mut driver = FakeDriver {
+            failures: 2,
+            ..FakeDriver::default()
+        };
+
+        let report = run_script(&mut driver, &steps, &options()).await;
+
+        assert!(!report.passed());
+        let statuses: Vec<_> = report.steps.iter().map(|step| step.status).collect();
+        assert_eq!(
+            statuses,
+            vec![
+                StepStatus::Passed,
+                StepStatus::Passed,
+                StepStatus::Failed,
+                StepStatus::Skipped
+            ]
+        );
+        assert_eq!(report.steps[0].attempts, 3);
+        assert_eq!(report.steps[2].attempts, 1);
+        assert!(report.steps[2]
+            .error
+            .as_deref()
+            .unwrap()
+            .contains("Timeout waiting for element: #missing"));
+        assert_eq!(driver.visited, vec!["http://localhost:3000"]);
+        assert_eq!(driver.screenshots, vec!["tmp/a.png"]);
+
+        let summary = report.summary();
+        assert!(summary.starts_with("❌ 2/4 steps passed"));
+        assert!(summary.contains("4. ⏭️ click #next (skipped)"));
+    }
+
+    #[tokio::test]
+    async fn test_run_script_gives_up_after_retries() {
+        let steps = vec![Step::Navigate {
+            url: "http://localhost:3000".to_string(),
+        }];
+        let mut driver = FakeDriver {
+            failures: 5,
+            ..FakeDriver::default()
+        };
+
+        let report = run_script(&mut driver, &steps, &options()).await;
+
+        assert_eq!(report.steps[0].status, StepStatus::Failed);
+        assert_eq!(report.steps[0].attempts, 3);
+        assert_eq!(report.steps[0].error.as_deref(), Some("connection refused"));
+    }
+}
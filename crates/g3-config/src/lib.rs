@@ -3,6 +3,11 @@ use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::path::Path;
 
+pub mod migrate;
+pub mod secrets;
+
+use secrets::{Redactor, SecretsConfig};
+
 /// Main configuration structure
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Config {
@@ -11,6 +16,22 @@ pub struct Config {
     pub computer_control: ComputerControlConfig,
     pub webdriver: WebDriverConfig,
     pub macax: MacAxConfig,
+    #[serde(default)]
+    pub planner: PlanningConfig,
+    #[serde(default)]
+    pub permissions: PermissionsConfig,
+    #[serde(default)]
+    pub secrets: SecretsConfig,
+    #[serde(default)]
+    pub lsp: LspConfig,
+    #[serde(default)]
+    pub notifications: NotificationsConfig,
+    #[serde(default)]
+    pub ui: UiConfig,
+    /// Named bundles of settings (`[profiles.<name>]`), each laid over the
+    /// rest of the config when selected with `--profile` or `G3_PROFILE`
+    #[serde(default)]
+    pub profiles: HashMap<String, toml::Value>,
 }
 
 /// Provider configuration with named configs per provider type
@@ -28,6 +49,11 @@ pub struct ProvidersConfig {
     /// Provider for player in autonomous mode (optional, falls back to default_provider)
     pub player: Option<String>,
     
+    /// Providers to fail over to, in order, when the default provider keeps
+    /// failing with rate limits, server errors or timeouts
+    #[serde(default)]
+    pub fallback_providers: Vec<String>,
+    
     /// Named Anthropic provider configs
     #[serde(default)]
     pub anthropic: HashMap<String, AnthropicConfig>,
@@ -47,19 +73,125 @@ pub struct ProvidersConfig {
     /// Multiple named OpenAI-compatible providers (e.g., openrouter, groq, etc.)
     #[serde(default)]
     pub openai_compatible: HashMap<String, OpenAIConfig>,
+    
+    /// Named local OpenAI-compatible server configs (Ollama, vLLM, LM Studio)
+    #[serde(default)]
+    pub local: HashMap<String, LocalConfig>,
+    
+    /// Named Google Gemini provider configs
+    #[serde(default)]
+    pub gemini: HashMap<String, GeminiConfig>,
+    
+    /// Named AWS Bedrock provider configs
+    #[serde(default)]
+    pub bedrock: HashMap<String, BedrockConfig>,
+
+    /// Named mock providers answering from fixture files, for tests
+    #[serde(default)]
+    pub mock: HashMap<String, MockConfig>,
+
+    /// Client-side quotas keyed by "<provider_type>.<config_name>"; calls
+    /// that would exceed them wait instead of hitting the provider's 429s
+    #[serde(default)]
+    pub rate_limits: HashMap<String, RateLimitConfig>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct RateLimitConfig {
+    pub requests_per_minute: Option<u32>,
+    /// Prompt and completion tokens combined
+    pub tokens_per_minute: Option<u32>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct OpenAIConfig {
+    /// Leave out to use the stored credential (see `secrets`)
+    #[serde(default)]
+    pub api_key: String,
+    pub model: String,
+    pub base_url: Option<String>,
+    pub max_tokens: Option<u32>,
+    pub temperature: Option<f32>,
+}
+
+/// An OpenAI-compatible server that needs no API key, such as Ollama, vLLM
+/// or LM Studio
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LocalConfig {
+    pub model: String,
+    /// Defaults to Ollama's endpoint, http://localhost:11434/v1
+    pub base_url: Option<String>,
+    /// Only needed if the server was started with one (e.g. vLLM --api-key)
+    pub api_key: Option<String>,
+    pub max_tokens: Option<u32>,
+    pub temperature: Option<f32>,
+    /// Context window the server runs the model with
+    pub context_length: Option<u32>,
+    /// Whether the model supports native tool calls (default: true); set to
+    /// false to describe tools in the prompt instead
+    pub native_tools: Option<bool>,
+    /// The model's Hugging Face `tokenizer.json`, for exact context token
+    /// counts; without it they are estimated from text length
+    pub tokenizer: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GeminiConfig {
+    /// Leave out to use the stored credential (see `secrets`)
+    #[serde(default)]
     pub api_key: String,
     pub model: String,
+    /// Defaults to https://generativelanguage.googleapis.com/v1beta
     pub base_url: Option<String>,
     pub max_tokens: Option<u32>,
     pub temperature: Option<f32>,
+    /// Overrides the model's known context window
+    pub context_length: Option<u32>,
+}
+
+/// A model hosted on AWS Bedrock. Credentials fall back to the standard AWS
+/// environment variables and shared credentials file when not set here.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BedrockConfig {
+    /// Model or inference profile id, e.g. "us.anthropic.claude-3-7-sonnet-20250219-v1:0"
+    pub model: String,
+    /// Defaults to AWS_REGION, then us-east-1
+    pub region: Option<String>,
+    pub access_key_id: Option<String>,
+    pub secret_access_key: Option<String>,
+    pub session_token: Option<String>,
+    /// Profile in ~/.aws/credentials to read credentials from
+    pub profile: Option<String>,
+    pub max_tokens: Option<u32>,
+    pub temperature: Option<f32>,
+    /// Overrides the model's known context window
+    pub context_length: Option<u32>,
+    /// Whether the model supports tool use (default: true)
+    pub native_tools: Option<bool>,
+}
+
+/// A provider that answers each request with a canned response from a
+/// fixture directory, for end-to-end tests without network or API keys
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MockConfig {
+    /// Directory of `<request hash>.json` responses, relative to the
+    /// working directory
+    pub fixtures_dir: String,
+    /// Model name to report (default: "mock")
+    pub model: Option<String>,
+    pub max_tokens: Option<u32>,
+    pub temperature: Option<f32>,
+    /// Context window to assume (default: 200000)
+    pub context_length: Option<u32>,
+    /// Whether fixtures return native tool calls (default: true); set to
+    /// false for fixtures whose tool calls are JSON in the content
+    pub native_tools: Option<bool>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct AnthropicConfig {
+    /// Leave out to use the stored credential (see `secrets`)
+    #[serde(default)]
     pub api_key: String,
     pub model: String,
     pub max_tokens: Option<u32>,
@@ -102,12 +234,84 @@ pub struct AgentConfig {
     pub autonomous_max_retry_attempts: u32,
     #[serde(default = "default_check_todo_staleness")]
     pub check_todo_staleness: bool,
+    /// Keep a `.g3.bak` copy of the previous content when the agent edits a file
+    #[serde(default)]
+    pub keep_file_backups: bool,
+    /// Context usage (percent) at which older history is condensed into a
+    /// summary note; 0 disables compaction
+    #[serde(default = "default_compaction_threshold")]
+    pub compaction_threshold: u32,
+    #[serde(default)]
+    pub retention: RetentionConfig,
+    /// Recent commits listed in the git context message added at session
+    /// start; 0 leaves the message out
+    #[serde(default = "default_git_context_commits")]
+    pub git_context_commits: usize,
+    /// Explain mode: only read and search tools run, so the agent can answer
+    /// questions about the codebase but can't change it
+    #[serde(default)]
+    pub read_only: bool,
+    /// Refuse file edits that would leave a source file with a syntax error
+    #[serde(default = "default_syntax_check")]
+    pub syntax_check: bool,
+    /// Largest file, in bytes, that read_file returns whole; larger files
+    /// are read in ranges. 0 disables the limit
+    #[serde(default = "default_max_read_bytes")]
+    pub max_read_bytes: usize,
 }
 
 fn default_check_todo_staleness() -> bool {
     true
 }
 
+fn default_compaction_threshold() -> u32 {
+    75
+}
+
+fn default_git_context_commits() -> usize {
+    10
+}
+
+fn default_syntax_check() -> bool {
+    true
+}
+
+fn default_max_read_bytes() -> usize {
+    100_000
+}
+
+/// What to drop when the context window overflows (`[agent.retention]`).
+/// Without a policy the agent falls back to LLM summarization.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RetentionConfig {
+    /// Policy for all modes: "keep-pinned", "sliding-window", "importance"
+    /// or "summarize-on-overflow"
+    pub policy: Option<String>,
+    /// Per-mode overrides, applied by `for_planner`, `for_coach` and `for_player`
+    pub planner: Option<String>,
+    pub coach: Option<String>,
+    pub player: Option<String>,
+    /// Messages kept by the sliding-window policy
+    #[serde(default = "default_window_messages")]
+    pub window_messages: usize,
+}
+
+fn default_window_messages() -> usize {
+    40
+}
+
+impl Default for RetentionConfig {
+    fn default() -> Self {
+        Self {
+            policy: None,
+            planner: None,
+            coach: None,
+            player: None,
+            window_messages: default_window_messages(),
+        }
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ComputerControlConfig {
     pub enabled: bool,
@@ -144,6 +348,260 @@ pub struct MacAxConfig {
     pub enabled: bool,
 }
 
+/// Whether tools of a class may run
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum ToolPermission {
+    #[default]
+    Allow,
+    Deny,
+    /// Ask the user before each call; refused in autonomous mode
+    Ask,
+}
+
+/// Per-class tool policies (`[permissions]` section); everything is allowed
+/// by default, inside the workspace
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PermissionsConfig {
+    /// read_file, read_image, code_search and the TODO tools
+    #[serde(default)]
+    pub read_only: ToolPermission,
+    /// write_file and str_replace
+    #[serde(default)]
+    pub write: ToolPermission,
+    /// shell, background_process and code_coverage
+    #[serde(default)]
+    pub shell: ToolPermission,
+    /// The webdriver_* browser tools
+    #[serde(default)]
+    pub network: ToolPermission,
+    /// Screenshots, window management, macax_*, vision_* and recording_*
+    #[serde(default)]
+    pub computer_control: ToolPermission,
+    /// Refuse file tool paths that resolve outside the workspace
+    #[serde(default = "default_workspace_only")]
+    pub workspace_only: bool,
+    /// Files and directories outside the workspace that file tools may use
+    /// anyway (`~` is expanded)
+    #[serde(default)]
+    pub allowed_paths: Vec<String>,
+}
+
+fn default_workspace_only() -> bool {
+    true
+}
+
+impl Default for PermissionsConfig {
+    fn default() -> Self {
+        Self {
+            read_only: ToolPermission::default(),
+            write: ToolPermission::default(),
+            shell: ToolPermission::default(),
+            network: ToolPermission::default(),
+            computer_control: ToolPermission::default(),
+            workspace_only: default_workspace_only(),
+            allowed_paths: Vec::new(),
+        }
+    }
+}
+
+/// Language servers behind the get_diagnostics and hover tools (`[lsp]` section)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LspConfig {
+    #[serde(default = "default_lsp_enabled")]
+    pub enabled: bool,
+    /// How long get_diagnostics waits for a server to report
+    #[serde(default = "default_diagnostics_timeout_secs")]
+    pub diagnostics_timeout_secs: u64,
+    /// Server command per language ("rust", "python", "typescript"),
+    /// replacing the default (e.g. "pyright-langserver --stdio")
+    #[serde(default)]
+    pub servers: HashMap<String, String>,
+}
+
+fn default_lsp_enabled() -> bool {
+    true
+}
+
+fn default_diagnostics_timeout_secs() -> u64 {
+    10
+}
+
+impl Default for LspConfig {
+    fn default() -> Self {
+        Self {
+            enabled: default_lsp_enabled(),
+            diagnostics_timeout_secs: default_diagnostics_timeout_secs(),
+            servers: HashMap::new(),
+        }
+    }
+}
+
+/// How a notification is played
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum NotificationMode {
+    /// A short system sound, or `sound` if set
+    #[default]
+    Sound,
+    /// The event read aloud with the OS text-to-speech
+    Speech,
+    /// The terminal bell
+    Bell,
+}
+
+/// Audio notifications for events worth interrupting the user for
+/// (`[notifications]` section)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NotificationsConfig {
+    #[serde(default)]
+    pub enabled: bool,
+    #[serde(default)]
+    pub mode: NotificationMode,
+    /// Sound file to play instead of the system sound
+    #[serde(default)]
+    pub sound: Option<String>,
+    /// Tasks shorter than this finish silently
+    #[serde(default = "default_long_task_secs")]
+    pub long_task_secs: u64,
+    #[serde(default = "default_notify_on_event")]
+    pub on_task_finished: bool,
+    /// A tool call or a supervised flock waiting for the user to allow it
+    #[serde(default = "default_notify_on_event")]
+    pub on_approval: bool,
+    #[serde(default = "default_notify_on_event")]
+    pub on_segment_failure: bool,
+}
+
+fn default_long_task_secs() -> u64 {
+    60
+}
+
+fn default_notify_on_event() -> bool {
+    true
+}
+
+impl Default for NotificationsConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            mode: NotificationMode::default(),
+            sound: None,
+            long_task_secs: default_long_task_secs(),
+            on_task_finished: default_notify_on_event(),
+            on_approval: default_notify_on_event(),
+            on_segment_failure: default_notify_on_event(),
+        }
+    }
+}
+
+/// Language of the CLI and TUI messages
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum Locale {
+    #[default]
+    En,
+    Es,
+}
+
+/// What the TUIs do with ANSI escape sequences in agent and tool output
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum AnsiMode {
+    /// Draw SGR colors and styles
+    #[default]
+    Render,
+    /// Show the text without them
+    Strip,
+}
+
+/// URL that file references in output link to, unless `[ui] link_url` says
+/// otherwise
+pub const DEFAULT_LINK_URL: &str = "file://{path}";
+
+/// Terminal interface settings (`[ui]` section)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UiConfig {
+    #[serde(default)]
+    pub locale: Locale,
+    #[serde(default)]
+    pub ansi: AnsiMode,
+    /// Template of the OSC 8 links drawn over `path/to/file.rs:42` in the
+    /// output, with `{path}` (absolute), `{line}` and `{column}`; empty to
+    /// draw no links
+    #[serde(default = "default_link_url")]
+    pub link_url: String,
+    /// Color theme of the retro TUI: retro, dracula, or a theme JSON file
+    /// (a path, or a name under ~/.config/g3/themes/); `--theme` overrides it
+    #[serde(default)]
+    pub theme: Option<String>,
+}
+
+fn default_link_url() -> String {
+    DEFAULT_LINK_URL.to_string()
+}
+
+impl Default for UiConfig {
+    fn default() -> Self {
+        Self {
+            locale: Locale::default(),
+            ansi: AnsiMode::default(),
+            link_url: default_link_url(),
+            theme: None,
+        }
+    }
+}
+
+/// Settings for planning mode (`[planner]` section)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PlanningConfig {
+    /// Extra patterns to exclude from planner commits, on top of .gitignore
+    /// (e.g. "*.generated.rs", "fixtures/")
+    #[serde(default)]
+    pub extra_excludes: Vec<String>,
+    /// Shell commands that must all succeed before the planner offers to
+    /// complete an implementation (e.g. "cargo test", "npm test")
+    #[serde(default)]
+    pub verification_commands: Vec<String>,
+    /// How long a verification command may run before it and every process
+    /// it started are killed and it counts as failed
+    #[serde(default = "default_verification_timeout_secs")]
+    pub verification_timeout_secs: u64,
+    /// Commit with `--no-verify`, skipping pre-commit and commit-msg hooks
+    #[serde(default)]
+    pub no_verify: bool,
+    /// Cache responses to the planner's deterministic (temperature 0) calls
+    /// in ~/.g3/cache/responses, so re-running a plan reuses them
+    #[serde(default)]
+    pub response_cache: bool,
+    /// Checkpoint the workspace before each player turn and roll the turn
+    /// back if a verification command that passed before it now fails
+    #[serde(default)]
+    pub rollback_failed_turns: bool,
+    /// Directory holding plans, relative to the codepath, when `--plan-dir`
+    /// isn't given (default: g3-plan)
+    #[serde(default)]
+    pub plan_dir: Option<String>,
+}
+
+fn default_verification_timeout_secs() -> u64 {
+    600
+}
+
+impl Default for PlanningConfig {
+    fn default() -> Self {
+        Self {
+            extra_excludes: Vec::new(),
+            verification_commands: Vec::new(),
+            verification_timeout_secs: default_verification_timeout_secs(),
+            no_verify: false,
+            response_cache: false,
+            rollback_failed_turns: false,
+            plan_dir: None,
+        }
+    }
+}
+
 impl Default for MacAxConfig {
     fn default() -> Self {
         Self { enabled: false }
@@ -193,11 +651,17 @@ impl Default for Config {
                 planner: None,
                 coach: None,
                 player: None,
+                fallback_providers: Vec::new(),
                 anthropic: HashMap::new(),
                 openai: HashMap::new(),
                 databricks: databricks_configs,
                 embedded: HashMap::new(),
                 openai_compatible: HashMap::new(),
+                local: HashMap::new(),
+                gemini: HashMap::new(),
+                bedrock: HashMap::new(),
+                mock: HashMap::new(),
+                rate_limits: HashMap::new(),
             },
             agent: AgentConfig {
                 max_context_length: None,
@@ -209,10 +673,24 @@ impl Default for Config {
                 max_retry_attempts: 3,
                 autonomous_max_retry_attempts: 6,
                 check_todo_staleness: true,
+                keep_file_backups: false,
+                compaction_threshold: 75,
+                retention: RetentionConfig::default(),
+                git_context_commits: 10,
+                read_only: false,
+                syntax_check: true,
+                max_read_bytes: 100_000,
             },
             computer_control: ComputerControlConfig::default(),
             webdriver: WebDriverConfig::default(),
             macax: MacAxConfig::default(),
+            planner: PlanningConfig::default(),
+            permissions: PermissionsConfig::default(),
+            secrets: SecretsConfig::default(),
+            lsp: LspConfig::default(),
+            notifications: NotificationsConfig::default(),
+            ui: UiConfig::default(),
+            profiles: HashMap::new(),
         }
     }
 }
@@ -220,7 +698,8 @@ impl Default for Config {
 /// Error message for old config format
 const OLD_CONFIG_FORMAT_ERROR: &str = r#"Your configuration file uses an old format that is no longer supported.
 
-Please update your configuration to use the new provider format:
+Run `g3 config migrate` to convert it (the original is kept as a backup), or
+update it by hand to the new provider format:
 
 ```toml
 [providers]
@@ -248,21 +727,43 @@ model = "gpt-5"
 Each mode (planner, coach, player) can specify a full path like "<provider_type>.<config_name>".
 If not specified, they fall back to `default_provider`."#;
 
+/// Project-local config, relative to the workspace; layered over the user config
+pub const PROJECT_CONFIG_PATH: &str = ".g3/config.toml";
+
+/// The settings the project config may change, by section. A checked-out
+/// repository isn't trusted, so it can pick among the user's providers and
+/// tighten permissions, but not define providers (endpoints, credentials)
+/// or loosen anything
+const PROJECT_SETTINGS: [(&str, &[&str]); 4] = [
+    ("providers", &["default_provider", "planner", "coach", "player"]),
+    (
+        "permissions",
+        &["read_only", "write", "shell", "network", "computer_control", "workspace_only"],
+    ),
+    (
+        "planner",
+        &["verification_commands", "verification_timeout_secs", "extra_excludes"],
+    ),
+    ("ui", &["theme"]),
+];
+
+/// User config locations, searched in order when no path is given
+const USER_CONFIG_PATHS: [&str; 3] = ["./g3.toml", "~/.config/g3/config.toml", "~/.g3.toml"];
+
+/// Selects a `[profiles.<name>]` bundle; set by `g3 --profile <name>`
+pub const PROFILE_ENV: &str = "G3_PROFILE";
+
+/// Keys whose values are credentials, hidden by [`Config::to_redacted_toml`]
+const SECRET_KEYS: [&str; 4] = ["api_key", "token", "secret_access_key", "session_token"];
+
 impl Config {
+    /// Load the user config (or the defaults) with the project config in the
+    /// current directory, [`PROJECT_CONFIG_PATH`], layered over it
     pub fn load(config_path: Option<&str>) -> Result<Self> {
-        // Check if any config file exists
-        let config_exists = if let Some(path) = config_path {
-            Path::new(path).exists()
-        } else {
-            let default_paths = ["./g3.toml", "~/.config/g3/config.toml", "~/.g3.toml"];
-            default_paths.iter().any(|path| {
-                let expanded_path = shellexpand::tilde(path);
-                Path::new(expanded_path.as_ref()).exists()
-            })
-        };
+        let project_layer = Self::read_layer(Path::new(PROJECT_CONFIG_PATH))?;
 
         // If no config exists, create and save a default config
-        if !config_exists {
+        let Some(path) = Self::user_config_path(config_path) else {
             let default_config = Self::default();
 
             let config_dir = dirs::home_dir()
@@ -285,42 +786,182 @@ impl Config {
                 );
             }
 
-            return Ok(default_config);
+            return match project_layer {
+                Some(layer) => Self::from_layers(toml::Value::try_from(&default_config)?, layer),
+                None => Ok(default_config),
+            };
+        };
+
+        let empty = || toml::Value::Table(Default::default());
+        let user_layer = Self::read_layer(Path::new(&path))?.unwrap_or_else(empty);
+        Self::from_layers(user_layer, project_layer.unwrap_or_else(empty))
+    }
+
+    /// The profile [`PROFILE_ENV`] selects, if set
+    pub fn active_profile() -> Option<String> {
+        std::env::var(PROFILE_ENV)
+            .ok()
+            .filter(|name| !name.is_empty())
+    }
+
+    /// The user config file `load` reads: `config_path` if it exists,
+    /// otherwise the first existing default location
+    pub fn user_config_path(config_path: Option<&str>) -> Option<String> {
+        if let Some(path) = config_path {
+            return Path::new(path).exists().then(|| path.to_string());
+        }
+        USER_CONFIG_PATHS.iter().find_map(|path| {
+            let expanded_path = shellexpand::tilde(path);
+            if Path::new(expanded_path.as_ref()).exists() {
+                Some(expanded_path.to_string())
+            } else {
+                None
+            }
+        })
+    }
+
+    /// Read one config file as a TOML table, if it exists
+    fn read_layer(path: &Path) -> Result<Option<toml::Value>> {
+        if !path.exists() {
+            return Ok(None);
+        }
+        let config_content = std::fs::read_to_string(path)?;
+
+        // Check for old format (direct provider config without named configs)
+        if Self::is_old_format(&config_content) {
+            anyhow::bail!("{}", OLD_CONFIG_FORMAT_ERROR);
+        }
+
+        let layer = config_content
+            .parse::<toml::Value>()
+            .map_err(|e| anyhow::anyhow!("Failed to parse {}: {}", path.display(), e))?;
+        Ok(Some(layer))
+    }
+
+    /// Build a config from `base` with the project `layer` merged over it,
+    /// then the profile named by [`PROFILE_ENV`], if any, filling in
+    /// credentials left out of all of them from the secrets store. Project
+    /// settings outside [`PROJECT_SETTINGS`], and permissions looser than
+    /// `base`, are ignored with a warning
+    pub fn from_layers(mut base: toml::Value, layer: toml::Value) -> Result<Self> {
+        let (layer, ignored) = restrict_project_layer(&base, layer);
+        if !ignored.is_empty() {
+            eprintln!(
+                "Warning: ignoring {} settings a project can't change: {}",
+                PROJECT_CONFIG_PATH,
+                ignored.join(", ")
+            );
+        }
+        merge_toml(&mut base, layer);
+        if let Some(profile) = Self::active_profile() {
+            apply_profile(&mut base, &profile)?;
         }
+        let mut config: Config = base.try_into()?;
+
+        // Validate the default_provider format
+        config.validate_provider_reference(&config.providers.default_provider)?;
+        for fallback in &config.providers.fallback_providers {
+            config.validate_provider_reference(fallback)?;
+        }
+        for provider in config.providers.rate_limits.keys() {
+            config.validate_provider_reference(provider)?;
+        }
+
+        config.resolve_secrets();
+        Ok(config)
+    }
 
-        // Load config from file
-        let config_path_to_load = if let Some(path) = config_path {
-            Some(path.to_string())
-        } else {
-            let default_paths = ["./g3.toml", "~/.config/g3/config.toml", "~/.g3.toml"];
-            default_paths.iter().find_map(|path| {
-                let expanded_path = shellexpand::tilde(path);
-                if Path::new(expanded_path.as_ref()).exists() {
-                    Some(expanded_path.to_string())
-                } else {
-                    None
+    /// Fill in each provider credential that is empty in the config from the
+    /// environment or the configured secrets store. Databricks configs using
+    /// OAuth and Bedrock configs (AWS credential chain) are left alone.
+    ///
+    /// A store that can't be read (e.g. no keyring on a headless machine) is
+    /// treated as empty, with a warning if a required API key is missing.
+    pub fn resolve_secrets(&mut self) {
+        let secrets = self.secrets.clone();
+        let fill = |slot: &mut String, reference: String| {
+            if slot.is_empty() {
+                match secrets.lookup(&reference) {
+                    Ok(Some(secret)) => *slot = secret,
+                    Ok(None) => {}
+                    Err(e) => eprintln!("Warning: {:#}", e),
                 }
-            })
+            }
         };
-
-        if let Some(path) = config_path_to_load {
-            // Read and parse the config file
-            let config_content = std::fs::read_to_string(&path)?;
-            
-            // Check for old format (direct provider config without named configs)
-            if Self::is_old_format(&config_content) {
-                anyhow::bail!("{}", OLD_CONFIG_FORMAT_ERROR);
+        let optional = |reference: String| secrets.lookup(&reference).ok().flatten();
+        for (name, config) in self.providers.anthropic.iter_mut() {
+            fill(&mut config.api_key, format!("anthropic.{}", name));
+        }
+        for (name, config) in self.providers.openai.iter_mut() {
+            fill(&mut config.api_key, format!("openai.{}", name));
+        }
+        for (name, config) in self.providers.gemini.iter_mut() {
+            fill(&mut config.api_key, format!("gemini.{}", name));
+        }
+        for (name, config) in self.providers.openai_compatible.iter_mut() {
+            fill(&mut config.api_key, name.clone());
+        }
+        for (name, config) in self.providers.databricks.iter_mut() {
+            if config.token.is_none() && config.use_oauth != Some(true) {
+                config.token = optional(format!("databricks.{}", name));
             }
-            
-            let config: Config = toml::from_str(&config_content)?;
-            
-            // Validate the default_provider format
-            config.validate_provider_reference(&config.providers.default_provider)?;
-            
-            return Ok(config);
         }
+        for (name, config) in self.providers.local.iter_mut() {
+            if config.api_key.is_none() {
+                config.api_key = optional(format!("local.{}", name));
+            }
+        }
+    }
 
-        Ok(Self::default())
+    /// The name a provider's credential is stored under: a configured
+    /// "<provider_type>.<config_name>", or an openai_compatible provider's name
+    pub fn credential_reference(&self, provider: &str) -> Result<String> {
+        if self.providers.openai_compatible.contains_key(provider) {
+            return Ok(provider.to_string());
+        }
+        self.validate_provider_reference(provider)?;
+        let (provider_type, _) = Self::parse_provider_reference(provider)?;
+        match provider_type.as_str() {
+            name if self.providers.openai_compatible.contains_key(name) => Ok(provider_type),
+            "embedded" => anyhow::bail!("Embedded models don't use credentials"),
+            "mock" => anyhow::bail!("Mock providers don't use credentials"),
+            "bedrock" => anyhow::bail!(
+                "Bedrock uses the AWS credential chain (environment, ~/.aws/credentials); configure it there"
+            ),
+            _ => Ok(provider.to_string()),
+        }
+    }
+
+    /// Every credential in the config, for redaction
+    pub fn secret_values(&self) -> Vec<String> {
+        let providers = &self.providers;
+        providers
+            .anthropic
+            .values()
+            .map(|c| Some(c.api_key.clone()))
+            .chain(providers.openai.values().map(|c| Some(c.api_key.clone())))
+            .chain(providers.openai_compatible.values().map(|c| Some(c.api_key.clone())))
+            .chain(providers.gemini.values().map(|c| Some(c.api_key.clone())))
+            .chain(providers.databricks.values().map(|c| c.token.clone()))
+            .chain(providers.local.values().map(|c| c.api_key.clone()))
+            .chain(providers.bedrock.values().flat_map(|c| {
+                [c.secret_access_key.clone(), c.session_token.clone()]
+            }))
+            .flatten()
+            .filter(|secret| !secret.is_empty())
+            .collect()
+    }
+
+    /// Masks this config's credentials in logs and transcripts
+    pub fn redactor(&self) -> Redactor {
+        Redactor::new(self.secret_values())
+    }
+
+    /// The config as TOML, with API keys and other credentials masked
+    pub fn to_redacted_toml(&self) -> Result<String> {
+        let mut value = toml::Value::try_from(self)?;
+        redact_secrets(&mut value);
+        Ok(toml::to_string_pretty(&value)?)
     }
 
     /// Check if the config content uses the old format
@@ -414,11 +1055,47 @@ impl Config {
                     );
                 }
             }
+            "local" => {
+                if !self.providers.local.contains_key(config_name) {
+                    anyhow::bail!(
+                        "Provider config 'local.{}' not found. Available: {:?}",
+                        config_name,
+                        self.providers.local.keys().collect::<Vec<_>>()
+                    );
+                }
+            }
+            "gemini" => {
+                if !self.providers.gemini.contains_key(config_name) {
+                    anyhow::bail!(
+                        "Provider config 'gemini.{}' not found. Available: {:?}",
+                        config_name,
+                        self.providers.gemini.keys().collect::<Vec<_>>()
+                    );
+                }
+            }
+            "bedrock" => {
+                if !self.providers.bedrock.contains_key(config_name) {
+                    anyhow::bail!(
+                        "Provider config 'bedrock.{}' not found. Available: {:?}",
+                        config_name,
+                        self.providers.bedrock.keys().collect::<Vec<_>>()
+                    );
+                }
+            }
+            "mock" => {
+                if !self.providers.mock.contains_key(config_name) {
+                    anyhow::bail!(
+                        "Provider config 'mock.{}' not found. Available: {:?}",
+                        config_name,
+                        self.providers.mock.keys().collect::<Vec<_>>()
+                    );
+                }
+            }
             _ => {
                 // Check openai_compatible providers
                 if !self.providers.openai_compatible.contains_key(provider_type) {
                     anyhow::bail!(
-                        "Unknown provider type '{}'. Valid types: anthropic, openai, databricks, embedded, or openai_compatible names",
+                        "Unknown provider type '{}'. Valid types: anthropic, openai, databricks, embedded, local, gemini, bedrock, mock, or openai_compatible names",
                         provider_type
                     );
                 }
@@ -440,6 +1117,36 @@ impl Config {
         Ok((parts[0].to_string(), parts[1].to_string()))
     }
 
+    /// Every configured provider as a sorted list of "<provider_type>.<config_name>"
+    /// references, the form `/model` and `--provider` take
+    pub fn provider_references(&self) -> Vec<String> {
+        let providers = &self.providers;
+        let typed = [
+            ("anthropic", providers.anthropic.keys().collect::<Vec<_>>()),
+            ("openai", providers.openai.keys().collect()),
+            ("databricks", providers.databricks.keys().collect()),
+            ("embedded", providers.embedded.keys().collect()),
+            ("local", providers.local.keys().collect()),
+            ("gemini", providers.gemini.keys().collect()),
+            ("bedrock", providers.bedrock.keys().collect()),
+            ("mock", providers.mock.keys().collect()),
+        ];
+        let mut references: Vec<String> = typed
+            .iter()
+            .flat_map(|(provider_type, names)| {
+                names.iter().map(move |name| format!("{}.{}", provider_type, name))
+            })
+            .chain(
+                providers
+                    .openai_compatible
+                    .keys()
+                    .map(|name| format!("{}.default", name)),
+            )
+            .collect();
+        references.sort();
+        references
+    }
+
     pub fn save(&self, path: &str) -> Result<()> {
         let toml_string = toml::to_string_pretty(self)?;
         std::fs::write(path, toml_string)?;
@@ -507,6 +1214,46 @@ impl Config {
                         ));
                     }
                 }
+                "local" => {
+                    if let Some(ref mut local_config) = config.providers.local.get_mut(&config_name) {
+                        local_config.model = model;
+                    } else {
+                        return Err(anyhow::anyhow!(
+                            "Provider config 'local.{}' not found.",
+                            config_name
+                        ));
+                    }
+                }
+                "gemini" => {
+                    if let Some(ref mut gemini_config) = config.providers.gemini.get_mut(&config_name) {
+                        gemini_config.model = model;
+                    } else {
+                        return Err(anyhow::anyhow!(
+                            "Provider config 'gemini.{}' not found.",
+                            config_name
+                        ));
+                    }
+                }
+                "bedrock" => {
+                    if let Some(ref mut bedrock_config) = config.providers.bedrock.get_mut(&config_name) {
+                        bedrock_config.model = model;
+                    } else {
+                        return Err(anyhow::anyhow!(
+                            "Provider config 'bedrock.{}' not found.",
+                            config_name
+                        ));
+                    }
+                }
+                "mock" => {
+                    if let Some(ref mut mock_config) = config.providers.mock.get_mut(&config_name) {
+                        mock_config.model = Some(model);
+                    } else {
+                        return Err(anyhow::anyhow!(
+                            "Provider config 'mock.{}' not found.",
+                            config_name
+                        ));
+                    }
+                }
                 _ => {
                     // Check openai_compatible
                     if let Some(ref mut compat_config) = config.providers.openai_compatible.get_mut(&provider_type) {
@@ -558,19 +1305,33 @@ impl Config {
         Ok(config)
     }
 
+    /// Use a mode's retention policy override, if it has one
+    fn with_retention_override(mut self, policy: &Option<String>) -> Self {
+        if policy.is_some() {
+            self.agent.retention.policy = policy.clone();
+        }
+        self
+    }
+
     /// Create a copy of the config for planner mode
     pub fn for_planner(&self) -> Result<Self> {
-        self.with_provider_override(self.get_planner_provider())
+        Ok(self
+            .with_provider_override(self.get_planner_provider())?
+            .with_retention_override(&self.agent.retention.planner))
     }
 
     /// Create a copy of the config for coach mode in autonomous execution
     pub fn for_coach(&self) -> Result<Self> {
-        self.with_provider_override(self.get_coach_provider())
+        Ok(self
+            .with_provider_override(self.get_coach_provider())?
+            .with_retention_override(&self.agent.retention.coach))
     }
 
     /// Create a copy of the config for player mode in autonomous execution
     pub fn for_player(&self) -> Result<Self> {
-        self.with_provider_override(self.get_player_provider())
+        Ok(self
+            .with_provider_override(self.get_player_provider())?
+            .with_retention_override(&self.agent.retention.player))
     }
 
     /// Get Anthropic config by name
@@ -593,6 +1354,26 @@ impl Config {
         self.providers.embedded.get(name)
     }
 
+    /// Get local server config by name
+    pub fn get_local_config(&self, name: &str) -> Option<&LocalConfig> {
+        self.providers.local.get(name)
+    }
+
+    /// Get Gemini config by name
+    pub fn get_gemini_config(&self, name: &str) -> Option<&GeminiConfig> {
+        self.providers.gemini.get(name)
+    }
+
+    /// Get Bedrock config by name
+    pub fn get_bedrock_config(&self, name: &str) -> Option<&BedrockConfig> {
+        self.providers.bedrock.get(name)
+    }
+
+    /// Get mock provider config by name
+    pub fn get_mock_config(&self, name: &str) -> Option<&MockConfig> {
+        self.providers.mock.get(name)
+    }
+
     /// Get the current default provider's config
     pub fn get_default_provider_config(&self) -> Result<ProviderConfigRef<'_>> {
         let (provider_type, config_name) = Self::parse_provider_reference(
@@ -620,6 +1401,26 @@ impl Config {
                     .map(ProviderConfigRef::Embedded)
                     .ok_or_else(|| anyhow::anyhow!("Embedded config '{}' not found", config_name))
             }
+            "local" => {
+                self.providers.local.get(&config_name)
+                    .map(ProviderConfigRef::Local)
+                    .ok_or_else(|| anyhow::anyhow!("Local config '{}' not found", config_name))
+            }
+            "gemini" => {
+                self.providers.gemini.get(&config_name)
+                    .map(ProviderConfigRef::Gemini)
+                    .ok_or_else(|| anyhow::anyhow!("Gemini config '{}' not found", config_name))
+            }
+            "bedrock" => {
+                self.providers.bedrock.get(&config_name)
+                    .map(ProviderConfigRef::Bedrock)
+                    .ok_or_else(|| anyhow::anyhow!("Bedrock config '{}' not found", config_name))
+            }
+            "mock" => {
+                self.providers.mock.get(&config_name)
+                    .map(ProviderConfigRef::Mock)
+                    .ok_or_else(|| anyhow::anyhow!("Mock config '{}' not found", config_name))
+            }
             _ => {
                 self.providers.openai_compatible.get(&provider_type)
                     .map(ProviderConfigRef::OpenAICompatible)
@@ -629,6 +1430,124 @@ impl Config {
     }
 }
 
+/// Merge the table `[profiles.<name>]` of `config` over the rest of it
+pub fn apply_profile(config: &mut toml::Value, name: &str) -> Result<()> {
+    let profiles = config.get("profiles").and_then(toml::Value::as_table);
+    let Some(profile) = profiles.and_then(|profiles| profiles.get(name)).cloned() else {
+        let mut names: Vec<&str> = profiles
+            .map(|profiles| profiles.keys().map(String::as_str).collect())
+            .unwrap_or_default();
+        names.sort_unstable();
+        if names.is_empty() {
+            anyhow::bail!("Unknown profile '{}': the config has no [profiles]", name);
+        }
+        anyhow::bail!(
+            "Unknown profile '{}'. Profiles in the config: {}",
+            name,
+            names.join(", ")
+        );
+    };
+    if !profile.is_table() {
+        anyhow::bail!("[profiles.{}] must be a table of settings", name);
+    }
+    if profile.get("profiles").is_some() {
+        anyhow::bail!("[profiles.{}] can't define profiles itself", name);
+    }
+    merge_toml(config, profile);
+    Ok(())
+}
+
+/// Split the project `layer` into what [`PROJECT_SETTINGS`] lets it set over
+/// `base` and the names of the settings left out
+fn restrict_project_layer(base: &toml::Value, layer: toml::Value) -> (toml::Value, Vec<String>) {
+    let mut allowed = toml::map::Map::new();
+    let mut ignored = Vec::new();
+    let toml::Value::Table(layer) = layer else {
+        return (toml::Value::Table(allowed), ignored);
+    };
+    for (section, value) in layer {
+        let keys = PROJECT_SETTINGS
+            .iter()
+            .find(|(name, _)| *name == section)
+            .map(|(_, keys)| *keys);
+        let (Some(keys), toml::Value::Table(table)) = (keys, value) else {
+            ignored.push(section);
+            continue;
+        };
+        let mut kept = toml::map::Map::new();
+        for (key, value) in table {
+            let permitted = keys.contains(&key.as_str())
+                && (section != "permissions" || tightens_permission(base, &key, &value));
+            if permitted {
+                kept.insert(key, value);
+            } else {
+                ignored.push(format!("{}.{}", section, key));
+            }
+        }
+        allowed.insert(section, toml::Value::Table(kept));
+    }
+    (toml::Value::Table(allowed), ignored)
+}
+
+/// Whether setting `[permissions] key = value` is at least as strict as
+/// what `base` has
+fn tightens_permission(base: &toml::Value, key: &str, value: &toml::Value) -> bool {
+    if key == "workspace_only" {
+        return value.as_bool() == Some(true);
+    }
+    let strictness = |permission: ToolPermission| match permission {
+        ToolPermission::Allow => 0,
+        ToolPermission::Ask => 1,
+        ToolPermission::Deny => 2,
+    };
+    let Ok(project) = value.clone().try_into::<ToolPermission>() else {
+        return false;
+    };
+    let user = base
+        .get("permissions")
+        .and_then(|permissions| permissions.get(key))
+        .and_then(|user| user.clone().try_into::<ToolPermission>().ok())
+        .unwrap_or_default();
+    strictness(project) >= strictness(user)
+}
+
+/// Merge `layer` into `base`: tables are merged key by key, any other value
+/// (including arrays) in `layer` replaces the one in `base`
+pub fn merge_toml(base: &mut toml::Value, layer: toml::Value) {
+    match (base, layer) {
+        (toml::Value::Table(base), toml::Value::Table(layer)) => {
+            for (key, value) in layer {
+                match base.get_mut(&key) {
+                    Some(existing) => merge_toml(existing, value),
+                    None => {
+                        base.insert(key, value);
+                    }
+                }
+            }
+        }
+        (base, layer) => *base = layer,
+    }
+}
+
+fn redact_secrets(value: &mut toml::Value) {
+    match value {
+        toml::Value::Table(table) => {
+            for (key, value) in table.iter_mut() {
+                match value {
+                    toml::Value::String(secret)
+                        if SECRET_KEYS.contains(&key.as_str()) && !secret.is_empty() =>
+                    {
+                        *secret = "********".to_string();
+                    }
+                    _ => redact_secrets(value),
+                }
+            }
+        }
+        toml::Value::Array(items) => items.iter_mut().for_each(redact_secrets),
+        _ => {}
+    }
+}
+
 /// Reference to a provider configuration
 #[derive(Debug)]
 pub enum ProviderConfigRef<'a> {
@@ -636,6 +1555,10 @@ pub enum ProviderConfigRef<'a> {
     OpenAI(&'a OpenAIConfig),
     Databricks(&'a DatabricksConfig),
     Embedded(&'a EmbeddedConfig),
+    Local(&'a LocalConfig),
+    Gemini(&'a GeminiConfig),
+    Bedrock(&'a BedrockConfig),
+    Mock(&'a MockConfig),
     OpenAICompatible(&'a OpenAIConfig),
 }
 
@@ -0,0 +1,233 @@
+//! Conversion of configs written in the old provider format.
+//!
+//! The old format configured one provider per type directly under
+//! `[providers.<type>]` and referred to it by type alone
+//! (`default_provider = "anthropic"`). `g3 config migrate` moves each such
+//! table to `[providers.<type>.default]` and turns the references into
+//! `"<type>.default"`.
+//!
+//! The file is rewritten line by line so comments and key order survive;
+//! if the rewritten text doesn't parse to the expected config (e.g. the
+//! providers were written as inline tables), the converted config is
+//! serialized from scratch instead, without comments.
+
+use anyhow::{bail, Result};
+use toml::value::Table;
+use toml::Value;
+
+/// Name given to the config a legacy provider table becomes
+pub const MIGRATED_CONFIG_NAME: &str = "default";
+
+/// Provider types that had a single, unnamed config in the old format
+const PROVIDER_TYPES: [&str; 7] = [
+    "anthropic",
+    "openai",
+    "databricks",
+    "embedded",
+    "local",
+    "gemini",
+    "bedrock",
+];
+
+/// Keys of `[providers]` holding a provider reference
+const REFERENCE_KEYS: [&str; 5] = [
+    "default_provider",
+    "planner",
+    "coach",
+    "player",
+    "fallback_providers",
+];
+
+/// `content` converted to the named-provider format; None if it has no
+/// legacy provider table
+pub fn migrate(content: &str) -> Result<Option<String>> {
+    let value: Value = content.parse()?;
+    let (expected, migrated) = convert(value)?;
+    if migrated.is_empty() {
+        return Ok(None);
+    }
+    let rewritten = rewrite(content, &migrated);
+    if rewritten.parse::<Value>().ok().as_ref() == Some(&expected) {
+        return Ok(Some(rewritten));
+    }
+    Ok(Some(toml::to_string_pretty(&expected)?))
+}
+
+/// The converted config and the provider types that were moved
+fn convert(mut value: Value) -> Result<(Value, Vec<&'static str>)> {
+    let mut migrated = Vec::new();
+    let Some(providers) = value.get_mut("providers").and_then(Value::as_table_mut) else {
+        return Ok((value, migrated));
+    };
+    for provider_type in PROVIDER_TYPES {
+        let Some(table) = providers
+            .get_mut(provider_type)
+            .and_then(Value::as_table_mut)
+        else {
+            continue;
+        };
+        let settings: Vec<String> = table
+            .iter()
+            .filter(|(_, value)| !value.is_table())
+            .map(|(key, _)| key.clone())
+            .collect();
+        if settings.is_empty() {
+            continue;
+        }
+        if table.contains_key(MIGRATED_CONFIG_NAME) {
+            bail!(
+                "[providers.{0}] has both settings of its own and a [providers.{0}.{1}] \
+                 config; merge them by hand",
+                provider_type,
+                MIGRATED_CONFIG_NAME
+            );
+        }
+        let config: Table = settings
+            .into_iter()
+            .filter_map(|key| table.remove(&key).map(|value| (key, value)))
+            .collect();
+        table.insert(MIGRATED_CONFIG_NAME.to_string(), Value::Table(config));
+        migrated.push(provider_type);
+    }
+
+    for key in REFERENCE_KEYS {
+        match providers.get_mut(key) {
+            Some(Value::String(reference)) => rename_reference(reference, &migrated),
+            Some(Value::Array(references)) => {
+                for reference in references {
+                    if let Value::String(reference) = reference {
+                        rename_reference(reference, &migrated);
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+    Ok((value, migrated))
+}
+
+fn rename_reference(reference: &mut String, migrated: &[&str]) {
+    if migrated.contains(&reference.as_str()) {
+        reference.push('.');
+        reference.push_str(MIGRATED_CONFIG_NAME);
+    }
+}
+
+/// `content` with the `[providers.<type>]` headers of `migrated` renamed
+/// and their references in `[providers]` extended
+fn rewrite(content: &str, migrated: &[&str]) -> String {
+    let mut out = String::with_capacity(content.len());
+    let mut section = String::new();
+    for line in content.split_inclusive('\n') {
+        let trimmed = line.trim_start();
+        if let Some(header) = table_header(trimmed) {
+            section = header.to_string();
+            let provider_type = header.strip_prefix("providers.").unwrap_or_default();
+            if migrated.contains(&provider_type) {
+                let indent = &line[..line.len() - trimmed.len()];
+                let rest = &trimmed[trimmed.find(']').map_or(0, |i| i + 1)..];
+                out.push_str(&format!(
+                    "{}[{}.{}]{}",
+                    indent, header, MIGRATED_CONFIG_NAME, rest
+                ));
+                continue;
+            }
+        } else if section == "providers" {
+            let mut line = line.to_string();
+            for provider_type in migrated {
+                line = line.replace(
+                    &format!("\"{}\"", provider_type),
+                    &format!("\"{}.{}\"", provider_type, MIGRATED_CONFIG_NAME),
+                );
+            }
+            out.push_str(&line);
+            continue;
+        }
+        out.push_str(line);
+    }
+    out
+}
+
+/// The name of the table a `[name]` header line opens
+fn table_header(line: &str) -> Option<&str> {
+    if line.starts_with("[[") {
+        return None;
+    }
+    let name = line.strip_prefix('[')?.split(']').next()?;
+    Some(name.trim())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LEGACY: &str = r#"# My g3 config
+[providers]
+default_provider = "anthropic"
+coach = "databricks"  # the coach runs on Databricks
+fallback_providers = ["openai", "anthropic.planner"]
+
+[providers.anthropic]
+api_key = "sk-test"
+model = "claude-sonnet-4-5"
+
+[providers.anthropic.planner]
+api_key = "sk-test"
+model = "claude-opus-4-5"
+
+[providers.databricks]
+host = "https://example.databricks.com"
+model = "test-model"
+
+[providers.openai]
+api_key = "sk-openai"  # personal key
+model = "gpt-5"
+
+[agent]
+max_context_length = 100000
+"#;
+
+    #[test]
+    fn test_migrate_keeps_comments() {
+        let migrated = migrate(LEGACY).unwrap().unwrap();
+        assert!(migrated.starts_with("# My g3 config\n"));
+        assert!(migrated.contains("default_provider = \"anthropic.default\"\n"));
+        assert!(migrated.contains("coach = \"databricks.default\"  # the coach"));
+        assert!(migrated.contains(r#"["openai.default", "anthropic.planner"]"#));
+        assert!(migrated.contains("[providers.anthropic.default]\napi_key = \"sk-test\""));
+        assert!(migrated.contains("[providers.anthropic.planner]\n"));
+        assert!(migrated.contains("api_key = \"sk-openai\"  # personal key"));
+
+        let value: Value = migrated.parse().unwrap();
+        assert_eq!(
+            value["providers"]["databricks"]["default"]["host"].as_str(),
+            Some("https://example.databricks.com")
+        );
+        assert_eq!(
+            value["agent"]["max_context_length"].as_integer(),
+            Some(100000)
+        );
+        assert_eq!(migrate(&migrated).unwrap(), None);
+    }
+
+    #[test]
+    fn test_migrate_inline_tables() {
+        let legacy = "[providers]\ndefault_provider = \"openai\"\nopenai = { api_key = \"k\", model = \"gpt-5\" }\n";
+        let migrated = migrate(legacy).unwrap().unwrap();
+        let value: Value = migrated.parse().unwrap();
+        assert_eq!(
+            value["providers"]["default_provider"].as_str(),
+            Some("openai.default")
+        );
+        assert_eq!(
+            value["providers"]["openai"]["default"]["model"].as_str(),
+            Some("gpt-5")
+        );
+    }
+
+    #[test]
+    fn test_migrate_conflict() {
+        let legacy = "[providers.anthropic]\nmodel = \"a\"\n\n[providers.anthropic.default]\nmodel = \"b\"\n";
+        assert!(migrate(legacy).is_err());
+    }
+}
@@ -0,0 +1,383 @@
+//! Provider credentials kept out of the config file.
+//!
+//! A provider's credential (`api_key`, or `token` for Databricks) can be left
+//! out of the TOML. [`crate::Config::load`] then looks it up by the provider
+//! reference (e.g. `anthropic.default`), in order:
+//! 1. the environment: `G3_SECRET_<TYPE>_<NAME>`, e.g. `G3_SECRET_ANTHROPIC_DEFAULT`
+//! 2. the store chosen by `[secrets] backend`: the OS keyring (default) or an
+//!    encrypted file unlocked by `G3_SECRETS_PASSPHRASE`
+//!
+//! `g3 auth login <provider>` writes to that same store. A [`Redactor`] built
+//! from the resolved credentials masks them in session logs and transcripts.
+
+use anyhow::{anyhow, bail, Context, Result};
+use chacha20poly1305::aead::{Aead, KeyInit};
+use chacha20poly1305::{ChaCha20Poly1305, Key, Nonce};
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+
+/// Keyring service name credentials are stored under
+const KEYRING_SERVICE: &str = "g3";
+
+/// Environment variable holding the encrypted file's passphrase
+pub const PASSPHRASE_ENV: &str = "G3_SECRETS_PASSPHRASE";
+
+/// Secrets shorter than this are not redacted, to avoid masking common words
+const MIN_REDACTED_LEN: usize = 8;
+
+/// Where `g3 auth login` stores credentials and `Config::load` finds them
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum SecretsBackend {
+    /// The OS keyring (macOS Keychain, Windows Credential Manager, or Secret
+    /// Service with the `secret-service` feature)
+    #[default]
+    Keyring,
+    /// Only `G3_SECRET_*` environment variables
+    Env,
+    /// A passphrase-encrypted file
+    File,
+}
+
+/// Settings for credential storage (`[secrets]` section)
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SecretsConfig {
+    #[serde(default)]
+    pub backend: SecretsBackend,
+    /// Encrypted file for the `file` backend (default: ~/.g3/secrets.enc)
+    pub file: Option<String>,
+}
+
+impl SecretsConfig {
+    /// The store credentials are read from after the environment
+    pub fn store(&self) -> Box<dyn SecretStore> {
+        match self.backend {
+            SecretsBackend::Keyring => Box::new(KeyringStore),
+            SecretsBackend::Env => Box::new(EnvStore),
+            SecretsBackend::File => Box::new(EncryptedFileStore::new(self.file_path())),
+        }
+    }
+
+    fn file_path(&self) -> PathBuf {
+        match &self.file {
+            Some(path) => PathBuf::from(shellexpand::tilde(path).as_ref()),
+            None => dirs::home_dir()
+                .unwrap_or_else(|| PathBuf::from("."))
+                .join(".g3")
+                .join("secrets.enc"),
+        }
+    }
+
+    /// Find the credential for a provider reference: the environment first,
+    /// then the configured store
+    pub fn lookup(&self, reference: &str) -> Result<Option<String>> {
+        if let Some(secret) = EnvStore.get(reference)? {
+            return Ok(Some(secret));
+        }
+        if self.backend == SecretsBackend::Env {
+            return Ok(None);
+        }
+        let store = self.store();
+        store
+            .get(reference)
+            .with_context(|| format!("Could not read {} from the {}", reference, store.name()))
+    }
+}
+
+/// A place credentials can be stored, keyed by provider reference
+pub trait SecretStore {
+    /// Human-readable name, for messages
+    fn name(&self) -> &'static str;
+    fn get(&self, reference: &str) -> Result<Option<String>>;
+    fn set(&self, reference: &str, secret: &str) -> Result<()>;
+}
+
+/// The environment variable a provider's credential is read from
+pub fn env_var_name(reference: &str) -> String {
+    let name: String = reference
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() {
+                c.to_ascii_uppercase()
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    format!("G3_SECRET_{}", name)
+}
+
+/// Reads `G3_SECRET_*` environment variables; can't store anything
+pub struct EnvStore;
+
+impl SecretStore for EnvStore {
+    fn name(&self) -> &'static str {
+        "environment"
+    }
+
+    fn get(&self, reference: &str) -> Result<Option<String>> {
+        Ok(std::env::var(env_var_name(reference))
+            .ok()
+            .filter(|secret| !secret.is_empty()))
+    }
+
+    fn set(&self, reference: &str, _secret: &str) -> Result<()> {
+        bail!(
+            "The env secrets backend can't store credentials; set {} instead",
+            env_var_name(reference)
+        )
+    }
+}
+
+/// The OS keyring
+pub struct KeyringStore;
+
+impl KeyringStore {
+    fn entry(reference: &str) -> Result<keyring::Entry> {
+        // Without a native backend keyring falls back to an in-memory mock
+        // that would silently lose what `g3 auth login` stores
+        if cfg!(not(any(
+            target_os = "macos",
+            target_os = "windows",
+            feature = "secret-service"
+        ))) {
+            bail!(
+                "This build has no OS keyring on this platform; rebuild with the \
+                 secret-service feature or set [secrets] backend to \"file\" or \"env\""
+            );
+        }
+        Ok(keyring::Entry::new(KEYRING_SERVICE, reference)?)
+    }
+}
+
+impl SecretStore for KeyringStore {
+    fn name(&self) -> &'static str {
+        "OS keyring"
+    }
+
+    fn get(&self, reference: &str) -> Result<Option<String>> {
+        match Self::entry(reference)?.get_password() {
+            Ok(secret) => Ok(Some(secret)),
+            Err(keyring::Error::NoEntry) => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    fn set(&self, reference: &str, secret: &str) -> Result<()> {
+        Self::entry(reference)?
+            .set_password(secret)
+            .context("Failed to write to the OS keyring")
+    }
+}
+
+/// Credentials encrypted with ChaCha20-Poly1305 under a key derived from
+/// [`PASSPHRASE_ENV`] with Argon2
+pub struct EncryptedFileStore {
+    path: PathBuf,
+}
+
+#[derive(Default, Serialize, Deserialize)]
+struct SecretsFile {
+    /// Argon2 salt, hex
+    salt: String,
+    /// Provider reference -> hex of nonce followed by ciphertext
+    entries: BTreeMap<String, String>,
+}
+
+impl EncryptedFileStore {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self { path: path.into() }
+    }
+
+    fn read(&self) -> Result<SecretsFile> {
+        if !self.path.exists() {
+            let mut salt = [0u8; 16];
+            rand::thread_rng().fill_bytes(&mut salt);
+            return Ok(SecretsFile {
+                salt: hex::encode(salt),
+                entries: BTreeMap::new(),
+            });
+        }
+        let content = std::fs::read_to_string(&self.path)
+            .with_context(|| format!("Failed to read {}", self.path.display()))?;
+        serde_json::from_str(&content)
+            .with_context(|| format!("Failed to parse {}", self.path.display()))
+    }
+
+    fn cipher(file: &SecretsFile) -> Result<ChaCha20Poly1305> {
+        let passphrase = std::env::var(PASSPHRASE_ENV)
+            .map_err(|_| anyhow!("Set {} to unlock the secrets file", PASSPHRASE_ENV))?;
+        let salt = hex::decode(&file.salt).context("Corrupt secrets file salt")?;
+        let mut key = [0u8; 32];
+        argon2::Argon2::default()
+            .hash_password_into(passphrase.as_bytes(), &salt, &mut key)
+            .map_err(|e| anyhow!("Failed to derive the secrets key: {}", e))?;
+        Ok(ChaCha20Poly1305::new(&Key::from(key)))
+    }
+
+    fn decrypt(cipher: &ChaCha20Poly1305, reference: &str, entry: &str) -> Result<String> {
+        let bytes = hex::decode(entry).context("Corrupt secrets file entry")?;
+        if bytes.len() < 12 {
+            bail!("Corrupt secrets file entry for {}", reference);
+        }
+        let (nonce, ciphertext) = bytes.split_at(12);
+        let nonce: [u8; 12] = nonce.try_into()?;
+        let plaintext = cipher
+            .decrypt(&Nonce::from(nonce), ciphertext)
+            .map_err(|_| anyhow!("Wrong {} or corrupt secrets file", PASSPHRASE_ENV))?;
+        Ok(String::from_utf8(plaintext)?)
+    }
+}
+
+impl SecretStore for EncryptedFileStore {
+    fn name(&self) -> &'static str {
+        "encrypted secrets file"
+    }
+
+    fn get(&self, reference: &str) -> Result<Option<String>> {
+        if !self.path.exists() {
+            return Ok(None);
+        }
+        let file = self.read()?;
+        let Some(entry) = file.entries.get(reference) else {
+            return Ok(None);
+        };
+        Self::decrypt(&Self::cipher(&file)?, reference, entry).map(Some)
+    }
+
+    fn set(&self, reference: &str, secret: &str) -> Result<()> {
+        let mut file = self.read()?;
+        let cipher = Self::cipher(&file)?;
+        // An entry under another passphrase would make the file unreadable
+        // as a whole, so check the passphrase against one that's there
+        if let Some((existing, entry)) = file.entries.iter().next() {
+            Self::decrypt(&cipher, existing, entry)
+                .context("Not writing the secrets file with a different passphrase")?;
+        }
+        let mut nonce = [0u8; 12];
+        rand::thread_rng().fill_bytes(&mut nonce);
+        let ciphertext = cipher
+            .encrypt(&Nonce::from(nonce), secret.as_bytes())
+            .map_err(|_| anyhow!("Failed to encrypt the credential"))?;
+        file.entries.insert(
+            reference.to_string(),
+            hex::encode([nonce.as_slice(), &ciphertext].concat()),
+        );
+
+        if let Some(parent) = self.path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(&self.path, serde_json::to_string_pretty(&file)?)
+            .with_context(|| format!("Failed to write {}", self.path.display()))?;
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            std::fs::set_permissions(&self.path, std::fs::Permissions::from_mode(0o600))?;
+        }
+        Ok(())
+    }
+}
+
+/// Masks known credentials in text written to logs and transcripts
+#[derive(Debug, Clone, Default)]
+pub struct Redactor {
+    secrets: Vec<String>,
+}
+
+impl Redactor {
+    pub fn new(secrets: impl IntoIterator<Item = String>) -> Self {
+        let mut secrets: Vec<String> = secrets
+            .into_iter()
+            .filter(|secret| secret.len() >= MIN_REDACTED_LEN)
+            .collect();
+        secrets.sort();
+        secrets.dedup();
+        // Longest first, so a secret containing another is masked whole
+        secrets.sort_by_key(|secret| std::cmp::Reverse(secret.len()));
+        Self { secrets }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.secrets.is_empty()
+    }
+
+    /// `text` with every known credential replaced by `********`
+    pub fn redact<'a>(&self, text: &'a str) -> Cow<'a, str> {
+        let mut text = Cow::Borrowed(text);
+        for secret in &self.secrets {
+            if text.contains(secret.as_str()) {
+                text = Cow::Owned(text.replace(secret.as_str(), "********"));
+            }
+        }
+        text
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_env_var_name() {
+        assert_eq!(
+            env_var_name("anthropic.default"),
+            "G3_SECRET_ANTHROPIC_DEFAULT"
+        );
+        assert_eq!(
+            env_var_name("openrouter.my-key"),
+            "G3_SECRET_OPENROUTER_MY_KEY"
+        );
+    }
+
+    #[test]
+    fn test_encrypted_file_round_trip() {
+        let temp_dir = TempDir::new().unwrap();
+        let store = EncryptedFileStore::new(temp_dir.path().join("secrets.enc"));
+        std::env::set_var(PASSPHRASE_ENV, "correct horse");
+
+        assert_eq!(store.get("anthropic.default").unwrap(), None);
+        store.set("anthropic.default", "sk-ant-123456").unwrap();
+        store.set("openai.default", "sk-openai-654321").unwrap();
+        assert_eq!(
+            store.get("anthropic.default").unwrap().as_deref(),
+            Some("sk-ant-123456")
+        );
+
+        let on_disk = std::fs::read_to_string(temp_dir.path().join("secrets.enc")).unwrap();
+        assert!(!on_disk.contains("sk-ant-123456"));
+
+        std::env::set_var(PASSPHRASE_ENV, "wrong");
+        assert!(store.get("anthropic.default").is_err());
+        assert!(store.set("gemini.default", "gemini-key-000000").is_err());
+        assert_eq!(
+            std::fs::read_to_string(temp_dir.path().join("secrets.enc")).unwrap(),
+            on_disk
+        );
+        std::env::remove_var(PASSPHRASE_ENV);
+    }
+
+    #[test]
+    #[cfg(not(any(target_os = "macos", target_os = "windows", feature = "secret-service")))]
+    fn test_keyring_without_backend_is_refused() {
+        let err = KeyringStore
+            .set("anthropic.default", "sk-ant-123456")
+            .unwrap_err();
+        assert!(err.to_string().contains("secret-service"), "{}", err);
+        assert!(KeyringStore.get("anthropic.default").is_err());
+    }
+
+    #[test]
+    fn test_redactor_masks_known_secrets() {
+        let redactor = Redactor::new(vec!["sk-ant-123456".to_string(), "short".to_string()]);
+        assert_eq!(
+            redactor.redact(r#"{"api_key":"sk-ant-123456"} short"#),
+            r#"{"api_key":"********"} short"#
+        );
+        assert!(matches!(redactor.redact("nothing here"), Cow::Borrowed(_)));
+        assert!(Redactor::default().is_empty());
+    }
+}
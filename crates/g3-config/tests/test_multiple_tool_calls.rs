@@ -1,6 +1,6 @@
 #[cfg(test)]
 mod test_multiple_tool_calls {
-    use g3_config::{AgentConfig, Config};
+    use g3_config::{AgentConfig, Config, RetentionConfig};
 
     #[test]
     fn test_config_has_multiple_tool_calls_field() {
@@ -27,6 +27,13 @@ mod test_multiple_tool_calls {
             max_retry_attempts: 3,
             autonomous_max_retry_attempts: 6,
             check_todo_staleness: true,
+            keep_file_backups: false,
+            compaction_threshold: 75,
+            retention: RetentionConfig::default(),
+            git_context_commits: 10,
+            read_only: false,
+            syntax_check: true,
+            max_read_bytes: 100_000,
         };
 
         // Test serialization
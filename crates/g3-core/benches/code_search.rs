@@ -0,0 +1,30 @@
+//! Tree-sitter search throughput over the synthetic repository of
+//! `g3_core::bench`; `g3 bench` measures the same search.
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use g3_core::bench::{generate_repo, search_request};
+use g3_core::code_search::execute_code_search;
+use tempfile::TempDir;
+
+fn code_search(c: &mut Criterion) {
+    let runtime = tokio::runtime::Runtime::new().unwrap();
+    let mut group = c.benchmark_group("code_search");
+    for files in [50, 500] {
+        let dir = TempDir::new().unwrap();
+        generate_repo(dir.path(), files).unwrap();
+        let request = search_request(dir.path());
+        group.throughput(Throughput::Elements(files as u64));
+        group.bench_with_input(
+            BenchmarkId::from_parameter(files),
+            &request,
+            |b, request| {
+                b.to_async(&runtime)
+                    .iter(|| execute_code_search(request.clone()))
+            },
+        );
+    }
+    group.finish();
+}
+
+criterion_group!(benches, code_search);
+criterion_main!(benches);
@@ -0,0 +1,29 @@
+//! Latency of applying a multi-hunk diff to a large file, verbatim and
+//! fuzzily; `g3 bench` measures the same diffs.
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use g3_core::bench::{synthetic_diff, synthetic_source, FUNCTIONS_PER_FILE};
+use g3_core::utils::{apply_unified_diff_with_options, DiffApplyOptions};
+
+fn diff_apply(c: &mut Criterion) {
+    let mut group = c.benchmark_group("diff_apply");
+    for functions in [FUNCTIONS_PER_FILE, FUNCTIONS_PER_FILE * 25] {
+        let source = synthetic_source(0, functions);
+        group.throughput(Throughput::Elements(source.lines().count() as u64));
+        for (name, reindent, options) in [
+            ("exact", false, DiffApplyOptions::exact()),
+            ("fuzzy", true, DiffApplyOptions::default()),
+        ] {
+            let diff = synthetic_diff(&source, reindent);
+            group.bench_with_input(BenchmarkId::new(name, functions), &diff, |b, diff| {
+                b.iter(|| {
+                    apply_unified_diff_with_options(&source, diff, None, None, &options).unwrap()
+                })
+            });
+        }
+    }
+    group.finish();
+}
+
+criterion_group!(benches, diff_apply);
+criterion_main!(benches);
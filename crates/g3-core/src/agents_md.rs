@@ -0,0 +1,186 @@
+//! Per-directory AGENTS.md files, layered like .gitignore.
+//!
+//! The workspace root's AGENTS.md is loaded with the README at startup. A
+//! subdirectory can add its own AGENTS.md with instructions for the files
+//! under it. When a tool call touches a path, every AGENTS.md between the
+//! root and that path is discovered and kept in one pinned system message,
+//! rendered parent-first so deeper instructions read as overrides of the
+//! ones above them. A file whose content repeats one already loaded (e.g. a
+//! copy of the root AGENTS.md) is skipped.
+
+use std::collections::{BTreeMap, HashSet};
+use std::path::{Component, Path, PathBuf};
+
+/// File names recognised as agent instructions, in order of preference
+const AGENTS_FILE_NAMES: &[&str] = &["AGENTS.md", "agents.md"];
+
+/// The nested AGENTS.md files found so far under one workspace root
+#[derive(Debug)]
+pub struct AgentsMdLayers {
+    root: PathBuf,
+    /// Directory relative to the root -> its AGENTS.md content
+    loaded: BTreeMap<PathBuf, String>,
+    /// Directories already looked at, found or not
+    checked: HashSet<PathBuf>,
+    /// Trimmed contents of the root's and every loaded file, for deduplication
+    contents: HashSet<String>,
+}
+
+impl AgentsMdLayers {
+    /// Layers under `root`, whose own AGENTS.md is already in context
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        let root = root.into();
+        let mut contents = HashSet::new();
+        if let Some((_, content)) = read_agents_file(&root) {
+            contents.insert(content.trim().to_string());
+        }
+        let mut checked = HashSet::new();
+        checked.insert(PathBuf::new());
+        Self {
+            root,
+            loaded: BTreeMap::new(),
+            checked,
+            contents,
+        }
+    }
+
+    /// Load the AGENTS.md files between the root and `path` (a file or a
+    /// directory; relative paths are resolved against `base`). Returns the
+    /// newly loaded files, relative to the root.
+    pub fn discover(&mut self, path: &Path, base: &Path) -> Vec<PathBuf> {
+        let path = base.join(path);
+        let Some(relative) = relative_to(&path, &self.root) else {
+            return Vec::new();
+        };
+        // A path that isn't an existing directory is taken to be a file
+        let dir = if self.root.join(&relative).is_dir() {
+            relative
+        } else {
+            relative.parent().map(Path::to_path_buf).unwrap_or_default()
+        };
+
+        let mut found = Vec::new();
+        let mut current = PathBuf::new();
+        for component in dir.components() {
+            current.push(component);
+            if !self.checked.insert(current.clone()) {
+                continue;
+            }
+            let Some((name, content)) = read_agents_file(&self.root.join(&current)) else {
+                continue;
+            };
+            if !self.contents.insert(content.trim().to_string()) {
+                continue;
+            }
+            found.push(current.join(name));
+            self.loaded.insert(current.clone(), content);
+        }
+        found
+    }
+
+    /// The loaded instructions as one system message, parent directories first
+    pub fn render(&self) -> String {
+        let mut message = String::from(
+            "📂 Directory Instructions (from nested AGENTS.md files). Each applies to the files \
+             under its directory and takes precedence over the instructions above it:\n",
+        );
+        for (dir, content) in &self.loaded {
+            message.push_str(&format!("\n## {}/\n\n{}\n", dir.display(), content.trim()));
+        }
+        message
+    }
+}
+
+/// The AGENTS.md in `dir`, as (file name, content)
+fn read_agents_file(dir: &Path) -> Option<(&'static str, String)> {
+    AGENTS_FILE_NAMES.iter().find_map(|name| {
+        let content = std::fs::read_to_string(dir.join(name)).ok()?;
+        (!content.trim().is_empty()).then_some((*name, content))
+    })
+}
+
+/// `path` relative to `root`, lexically normalized; `None` if it leaves the root
+fn relative_to(path: &Path, root: &Path) -> Option<PathBuf> {
+    let relative = path.strip_prefix(root).ok()?;
+    let mut normalized = PathBuf::new();
+    for component in relative.components() {
+        match component {
+            Component::Normal(part) => normalized.push(part),
+            Component::CurDir => {}
+            Component::ParentDir => {
+                if !normalized.pop() {
+                    return None;
+                }
+            }
+            Component::RootDir | Component::Prefix(_) => return None,
+        }
+    }
+    Some(normalized)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn write(root: &Path, path: &str, content: &str) {
+        let path = root.join(path);
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(path, content).unwrap();
+    }
+
+    #[test]
+    fn test_discovers_nested_files_parent_first() {
+        let temp_dir = TempDir::new().unwrap();
+        let root = temp_dir.path();
+        write(root, "AGENTS.md", "Root rules");
+        write(root, "crates/AGENTS.md", "Crate rules");
+        write(root, "crates/core/AGENTS.md", "Core rules");
+        write(root, "crates/core/src/lib.rs", "");
+        write(root, "docs/AGENTS.md", "Docs rules");
+
+        let mut layers = AgentsMdLayers::new(root);
+        let found = layers.discover(Path::new("crates/core/src/lib.rs"), root);
+        assert_eq!(
+            found,
+            vec![
+                PathBuf::from("crates/AGENTS.md"),
+                PathBuf::from("crates/core/AGENTS.md")
+            ]
+        );
+        // Already loaded
+        assert!(layers
+            .discover(&root.join("crates/core/src/lib.rs"), root)
+            .is_empty());
+        assert_eq!(
+            layers.discover(Path::new("docs"), root),
+            vec![PathBuf::from("docs/AGENTS.md")]
+        );
+
+        let message = layers.render();
+        let crates = message.find("Crate rules").unwrap();
+        let core = message.find("Core rules").unwrap();
+        let docs = message.find("Docs rules").unwrap();
+        assert!(crates < core && core < docs);
+        assert!(!message.contains("Root rules"));
+    }
+
+    #[test]
+    fn test_skips_duplicates_and_paths_outside_root() {
+        let temp_dir = TempDir::new().unwrap();
+        let root = temp_dir.path();
+        write(root, "AGENTS.md", "Shared rules");
+        write(root, "app/AGENTS.md", "Shared rules\n");
+        write(root, "app/web/AGENTS.md", "Web rules");
+
+        let mut layers = AgentsMdLayers::new(root);
+        assert_eq!(
+            layers.discover(Path::new("app/web/index.ts"), root),
+            vec![PathBuf::from("app/web/AGENTS.md")]
+        );
+        assert!(layers
+            .discover(Path::new("../elsewhere/main.rs"), root)
+            .is_empty());
+        assert!(layers.discover(Path::new("/etc/hosts"), root).is_empty());
+    }
+}
@@ -3,25 +3,294 @@
 //! This module provides a way to launch processes in the background with:
 //! - Automatic log capture to files (stdout/stderr combined)
 //! - Named process tracking for easy reference
-//! - Process lifecycle management (start, stop via shell)
+//! - Process lifecycle management (start, graceful [`BackgroundProcessManager::stop`], signals)
+//! - Optional health checks and automatic restarts (see [`ProcessOptions`])
+//! - Environment variables and free-port allocation via the [`PORT_PLACEHOLDER`]
+//! - Delayed and periodic runs ([`BackgroundProcessManager::start_after`],
+//!   [`BackgroundProcessManager::start_every`])
+//! - CPU and memory limits and usage stats (see [`crate::resource_limits`])
+//! - A registry persisted to `<log_dir>/processes.json`, so processes that
+//!   outlive a crashed agent are re-adopted by the next one
 //!
 //! The design is intentionally minimal - only one tool (`background_process`) is exposed.
 //! Users can use the regular `shell` tool to:
 //! - Read logs: `cat /path/to/logs.txt` or `tail -100 /path/to/logs.txt`
-//! - Stop processes: `kill <pid>` or `pkill -f <name>`
+//! - Stop processes: `kill -- -<pid>` (each process leads its own process group)
 //! - Check status: `ps aux | grep <name>`
 
+use crate::resource_limits::{self, LimitBackend, ProcessStats, ResourceLimits};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
-use std::fs::{self, OpenOptions};
-use std::io::Write;
-use std::path::PathBuf;
+use std::fs::{self, File, OpenOptions};
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::net::{SocketAddr, TcpStream, ToSocketAddrs};
+use std::path::{Path, PathBuf};
 use std::process::{Child, Command, Stdio};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
 use std::sync::{Arc, Mutex};
-use std::time::{SystemTime, UNIX_EPOCH};
+use std::thread;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 use tracing::debug;
 
-/// Information about a running background process
+/// How often the monitor thread wakes up to check on a process
+const MONITOR_TICK: Duration = Duration::from_millis(100);
+
+/// Upper bound for the exponential restart backoff
+const MAX_BACKOFF: Duration = Duration::from_secs(60);
+
+/// Timeout for a single TCP or HTTP health probe
+const PROBE_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// How often [`BackgroundProcessManager::stop`] checks whether the process group has exited
+const STOP_POLL: Duration = Duration::from_millis(50);
+
+/// File in the log directory that records tracked processes
+pub const REGISTRY_FILE: &str = "processes.json";
+
+/// Replaced with a free TCP port in the command, environment values and health check URL
+pub const PORT_PLACEHOLDER: &str = "{PORT}";
+
+/// Signal number of SIGTERM
+#[cfg(unix)]
+pub const SIGTERM: i32 = libc::SIGTERM;
+
+/// Signal number of SIGKILL
+#[cfg(unix)]
+pub const SIGKILL: i32 = libc::SIGKILL;
+
+/// How to tell whether a running process is healthy
+#[derive(Debug, Clone)]
+pub enum HealthCheck {
+    /// A TCP connection to `127.0.0.1:<port>` succeeds. Port 0 probes the
+    /// port allocated for [`PORT_PLACEHOLDER`].
+    TcpPort(u16),
+    /// An HTTP GET returns a 2xx or 3xx status (plain `http://` URLs only)
+    Http(String),
+    /// The pattern has appeared in the log output of the current run
+    LogPattern(Regex),
+}
+
+impl std::fmt::Display for HealthCheck {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            HealthCheck::TcpPort(0) => write!(f, "TCP port {}", PORT_PLACEHOLDER),
+            HealthCheck::TcpPort(port) => write!(f, "TCP port {}", port),
+            HealthCheck::Http(url) => write!(f, "HTTP GET {}", url),
+            HealthCheck::LogPattern(pattern) => write!(f, "log pattern /{}/", pattern),
+        }
+    }
+}
+
+/// A health check and how often to run it
 #[derive(Debug, Clone)]
+pub struct HealthCheckConfig {
+    pub check: HealthCheck,
+    /// Time between checks
+    pub interval: Duration,
+    /// Time after a (re)start before failed checks count, so servers can boot
+    pub startup_grace: Duration,
+    /// Consecutive failed checks before the process is considered failed
+    pub failure_threshold: u32,
+}
+
+impl HealthCheckConfig {
+    /// A check with default timing: every 5s, after a 10s grace period, failing after 3 misses
+    pub fn new(check: HealthCheck) -> Self {
+        Self {
+            check,
+            interval: Duration::from_secs(5),
+            startup_grace: Duration::from_secs(10),
+            failure_threshold: 3,
+        }
+    }
+}
+
+/// What to do when a process exits with an error or fails its health check
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum RestartPolicy {
+    /// Leave the process stopped
+    #[default]
+    Never,
+    /// Restart up to `max_restarts` times, waiting `backoff` before the first
+    /// restart and doubling the wait for each further one
+    OnFailure {
+        max_restarts: u32,
+        backoff: Duration,
+    },
+}
+
+/// Optional behaviour for [`BackgroundProcessManager::start_with_options`]
+#[derive(Debug, Clone, Default)]
+pub struct ProcessOptions {
+    pub health_check: Option<HealthCheckConfig>,
+    pub restart: RestartPolicy,
+    /// Extra environment variables for the process
+    pub env: HashMap<String, String>,
+    /// CPU and memory limits
+    pub limits: Option<ResourceLimits>,
+}
+
+impl ProcessOptions {
+    /// Parse the optional `health_check`, `restart`, `max_restarts`,
+    /// `restart_backoff_secs`, `env`, `memory_limit_mb` and `cpu_limit`
+    /// arguments of the `background_process` tool
+    pub fn from_tool_args(args: &serde_json::Value) -> Result<Self, String> {
+        let mut options = ProcessOptions::default();
+
+        if let Some(env) = args.get("env").filter(|v| !v.is_null()) {
+            let env = env.as_object().ok_or("env must be an object")?;
+            for (key, value) in env {
+                let value = match value {
+                    serde_json::Value::String(s) => s.clone(),
+                    serde_json::Value::Number(n) => n.to_string(),
+                    serde_json::Value::Bool(b) => b.to_string(),
+                    _ => return Err(format!("env.{} must be a string", key)),
+                };
+                options.env.insert(key.clone(), value);
+            }
+        }
+
+        if let Some(check) = args.get("health_check").filter(|v| !v.is_null()) {
+            let health_check = if let Some(port) = check.get("tcp_port") {
+                let port = if port.as_str() == Some(PORT_PLACEHOLDER) {
+                    0
+                } else {
+                    port.as_u64()
+                        .and_then(|p| u16::try_from(p).ok())
+                        .filter(|p| *p != 0)
+                        .ok_or("health_check.tcp_port must be a port number or \"{PORT}\"")?
+                };
+                HealthCheck::TcpPort(port)
+            } else if let Some(url) = check.get("http").and_then(|v| v.as_str()) {
+                if !url.starts_with("http://") {
+                    return Err("health_check.http must be an http:// URL".to_string());
+                }
+                HealthCheck::Http(url.to_string())
+            } else if let Some(pattern) = check.get("log_pattern").and_then(|v| v.as_str()) {
+                HealthCheck::LogPattern(
+                    Regex::new(pattern)
+                        .map_err(|e| format!("Invalid health_check.log_pattern: {}", e))?,
+                )
+            } else {
+                return Err(
+                    "health_check must have one of 'tcp_port', 'http' or 'log_pattern'".to_string(),
+                );
+            };
+
+            let mut config = HealthCheckConfig::new(health_check);
+            if let Some(secs) = check.get("interval_secs").and_then(|v| v.as_f64()) {
+                config.interval = Duration::from_secs_f64(secs.max(0.1));
+            }
+            if let Some(secs) = check.get("startup_grace_secs").and_then(|v| v.as_f64()) {
+                config.startup_grace = Duration::from_secs_f64(secs.max(0.0));
+            }
+            options.health_check = Some(config);
+        }
+
+        match args.get("restart").and_then(|v| v.as_str()) {
+            None | Some("never") => {}
+            Some("on-failure") => {
+                let max_restarts = args
+                    .get("max_restarts")
+                    .and_then(|v| v.as_u64())
+                    .unwrap_or(3) as u32;
+                let backoff = args
+                    .get("restart_backoff_secs")
+                    .and_then(|v| v.as_f64())
+                    .unwrap_or(1.0);
+                options.restart = RestartPolicy::OnFailure {
+                    max_restarts,
+                    backoff: Duration::from_secs_f64(backoff.max(0.0)),
+                };
+            }
+            Some(other) => {
+                return Err(format!(
+                    "Unknown restart policy '{}' (expected 'never' or 'on-failure')",
+                    other
+                ))
+            }
+        }
+
+        let memory_mb = args.get("memory_limit_mb").filter(|v| !v.is_null());
+        let cpu_cores = args.get("cpu_limit").filter(|v| !v.is_null());
+        if memory_mb.is_some() || cpu_cores.is_some() {
+            let memory_bytes = memory_mb
+                .map(|v| {
+                    v.as_u64()
+                        .filter(|mb| *mb > 0)
+                        .map(|mb| mb * 1024 * 1024)
+                        .ok_or("memory_limit_mb must be a positive integer")
+                })
+                .transpose()?;
+            let cpu_cores = cpu_cores
+                .map(|v| {
+                    v.as_f64()
+                        .filter(|cores| *cores > 0.0)
+                        .ok_or("cpu_limit must be a positive number of cores")
+                })
+                .transpose()?;
+            options.limits = Some(ResourceLimits {
+                memory_bytes,
+                cpu_cores,
+            });
+        }
+
+        Ok(options)
+    }
+
+    /// Whether a monitor thread is needed for these options
+    fn needs_monitor(&self) -> bool {
+        self.health_check.is_some() || self.restart != RestartPolicy::Never
+    }
+
+    /// Whether [`PORT_PLACEHOLDER`] is used anywhere in the options
+    fn uses_port(&self) -> bool {
+        self.env.values().any(|v| v.contains(PORT_PLACEHOLDER))
+            || match self.health_check.as_ref().map(|c| &c.check) {
+                Some(HealthCheck::TcpPort(0)) => true,
+                Some(HealthCheck::Http(url)) => url.contains(PORT_PLACEHOLDER),
+                _ => false,
+            }
+    }
+
+    /// Substitute the allocated port for [`PORT_PLACEHOLDER`]
+    fn resolve_port(&mut self, port: u16) {
+        let port_str = port.to_string();
+        for value in self.env.values_mut() {
+            *value = value.replace(PORT_PLACEHOLDER, &port_str);
+        }
+        if let Some(config) = self.health_check.as_mut() {
+            match &mut config.check {
+                HealthCheck::TcpPort(p) if *p == 0 => *p = port,
+                HealthCheck::Http(url) => *url = url.replace(PORT_PLACEHOLDER, &port_str),
+                _ => {}
+            }
+        }
+    }
+}
+
+/// When a scheduled command runs
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Schedule {
+    /// Once, after a delay
+    After(Duration),
+    /// Immediately and then repeatedly at this interval
+    Every(Duration),
+}
+
+/// Result of the most recent health check
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum HealthStatus {
+    /// No health check configured, or none has run since the last (re)start
+    #[default]
+    Unknown,
+    Healthy,
+    Unhealthy,
+}
+
+/// Information about a running background process
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ProcessInfo {
     /// User-provided name for the process
     pub name: String,
@@ -35,6 +304,144 @@ pub struct ProcessInfo {
     pub started_at: u64,
     /// Working directory where the process was started
     pub working_dir: PathBuf,
+    /// Port allocated for [`PORT_PLACEHOLDER`], if the command used it
+    #[serde(default)]
+    pub port: Option<u16>,
+    /// Number of automatic restarts so far
+    #[serde(default)]
+    pub restarts: u32,
+    /// Result of the most recent health check
+    #[serde(default)]
+    pub health: HealthStatus,
+    /// OS-reported start time of `pid`, used to detect PID reuse
+    #[serde(default)]
+    pub process_start: Option<String>,
+    /// Requested CPU and memory limits
+    #[serde(default)]
+    pub limits: Option<ResourceLimits>,
+    /// How `limits` are enforced
+    #[serde(default)]
+    pub limit_backend: Option<LimitBackend>,
+    /// For scheduled commands, when they run
+    #[serde(default)]
+    pub schedule: Option<Schedule>,
+    /// Unix timestamp of the next scheduled run, if one is pending
+    #[serde(default)]
+    pub next_run: Option<u64>,
+    /// Number of times a scheduled command has been launched
+    #[serde(default)]
+    pub runs: u32,
+}
+
+/// Distinguishes managers within one agent process (e.g. coach and player)
+static NEXT_MANAGER_ID: AtomicU64 = AtomicU64::new(0);
+
+/// A registry entry: a process and the manager that tracks it
+#[derive(Debug, Serialize, Deserialize)]
+struct RegistryEntry {
+    owner_pid: u32,
+    owner_start: Option<String>,
+    #[serde(default)]
+    owner_manager: u64,
+    #[serde(flatten)]
+    process: ProcessInfo,
+}
+
+/// The `processes.json` file shared by all managers using a log directory.
+///
+/// Each manager rewrites only its own entries. Entries whose owning agent has
+/// exited are adopted by the next manager if the process is still running.
+#[derive(Debug)]
+struct Registry {
+    path: PathBuf,
+    owner_pid: u32,
+    owner_start: Option<String>,
+    owner_manager: u64,
+}
+
+impl Registry {
+    fn new(log_dir: &Path) -> Self {
+        let owner_pid = std::process::id();
+        Self {
+            path: log_dir.join(REGISTRY_FILE),
+            owner_pid,
+            owner_start: process_start_time(owner_pid),
+            owner_manager: NEXT_MANAGER_ID.fetch_add(1, Ordering::Relaxed),
+        }
+    }
+
+    fn read(&self) -> Vec<RegistryEntry> {
+        let Ok(content) = fs::read_to_string(&self.path) else {
+            return Vec::new();
+        };
+        serde_json::from_str(&content).unwrap_or_else(|e| {
+            debug!(
+                "Ignoring unreadable process registry {:?}: {}",
+                self.path, e
+            );
+            Vec::new()
+        })
+    }
+
+    /// Whether the entry belongs to another manager whose agent is still running
+    fn owned_by_live_agent(&self, entry: &RegistryEntry) -> bool {
+        let own = entry.owner_pid == self.owner_pid && entry.owner_manager == self.owner_manager;
+        !own && entry.owner_start.is_some()
+            && process_start_time(entry.owner_pid) == entry.owner_start
+    }
+
+    /// Take over entries of exited agents whose processes are still running
+    fn adopt(&self) -> HashMap<String, ProcessInfo> {
+        let mut adopted = HashMap::new();
+        for entry in self.read() {
+            if self.owned_by_live_agent(&entry) {
+                continue;
+            }
+            if process_matches(&entry.process) {
+                // Scheduling isn't resumed, only the running process is adopted
+                let mut process = entry.process;
+                process.next_run = None;
+                debug!(
+                    "Re-adopted background process '{}' (PID: {})",
+                    process.name, process.pid
+                );
+                adopted.entry(process.name.clone()).or_insert(process);
+            } else {
+                debug!(
+                    "Dropping stale background process '{}' (PID: {})",
+                    entry.process.name, entry.process.pid
+                );
+            }
+        }
+        adopted
+    }
+
+    /// Write this manager's processes, keeping the entries of other live agents
+    fn save(&self, processes: &HashMap<String, ProcessInfo>) {
+        let mut entries: Vec<RegistryEntry> = self
+            .read()
+            .into_iter()
+            .filter(|entry| self.owned_by_live_agent(entry))
+            .collect();
+        entries.extend(processes.values().map(|info| RegistryEntry {
+            owner_pid: self.owner_pid,
+            owner_start: self.owner_start.clone(),
+            owner_manager: self.owner_manager,
+            process: info.clone(),
+        }));
+
+        let result = serde_json::to_string_pretty(&entries)
+            .map_err(std::io::Error::other)
+            .and_then(|json| {
+                // Write then rename so readers never see a partial file
+                let tmp = self.path.with_extension(format!("json.{}", self.owner_pid));
+                fs::write(&tmp, json)?;
+                fs::rename(&tmp, &self.path)
+            });
+        if let Err(e) = result {
+            debug!("Failed to save process registry {:?}: {}", self.path, e);
+        }
+    }
 }
 
 /// Manages background processes launched by the agent
@@ -46,20 +453,37 @@ pub struct BackgroundProcessManager {
     children: Arc<Mutex<HashMap<String, Child>>>,
     /// Directory where log files are stored
     log_dir: PathBuf,
+    /// Set on cleanup so monitor threads stop restarting processes
+    shutdown: Arc<AtomicBool>,
+    /// Persisted copy of `processes`
+    registry: Arc<Registry>,
+    /// Map of process name -> previous CPU sample, for [`Self::stats`]
+    cpu_samples: Mutex<HashMap<String, (Instant, Duration)>>,
 }
 
 impl BackgroundProcessManager {
-    /// Create a new background process manager
+    /// Create a new background process manager.
+    ///
+    /// Processes recorded in the registry by an agent that has since exited
+    /// are re-adopted if they are still running. Adopted processes can be
+    /// listed, signalled and stopped, but are not health-checked or restarted.
     pub fn new(log_dir: PathBuf) -> Self {
         // Ensure log directory exists
         if let Err(e) = fs::create_dir_all(&log_dir) {
             debug!("Failed to create log directory {:?}: {}", log_dir, e);
         }
 
+        let registry = Registry::new(&log_dir);
+        let processes = registry.adopt();
+        registry.save(&processes);
+
         Self {
-            processes: Arc::new(Mutex::new(HashMap::new())),
+            processes: Arc::new(Mutex::new(processes)),
             children: Arc::new(Mutex::new(HashMap::new())),
             log_dir,
+            shutdown: Arc::new(AtomicBool::new(false)),
+            registry: Arc::new(registry),
+            cpu_samples: Mutex::new(HashMap::new()),
         }
     }
 
@@ -78,6 +502,161 @@ impl BackgroundProcessManager {
         command: &str,
         working_dir: &PathBuf,
     ) -> Result<ProcessInfo, String> {
+        self.start_with_options(name, command, working_dir, ProcessOptions::default())
+    }
+
+    /// Start a new background process with environment variables, a health
+    /// check and/or restart policy.
+    ///
+    /// If [`PORT_PLACEHOLDER`] appears in the command, an environment value or
+    /// the health check, a free port is allocated and substituted everywhere.
+    /// A monitor thread runs the health check and restarts the process as the
+    /// restart policy allows; restart events are appended to the log file.
+    pub fn start_with_options(
+        &self,
+        name: &str,
+        command: &str,
+        working_dir: &PathBuf,
+        mut options: ProcessOptions,
+    ) -> Result<ProcessInfo, String> {
+        let (mut info, log_offset) = self.create_entry(name, command, working_dir, &mut options)?;
+
+        let (child, limit_backend) =
+            spawn_logged(&info.command, working_dir, &options, &info.log_file)?;
+        info.pid = child.id();
+        info.process_start = process_start_time(info.pid);
+        info.limit_backend = limit_backend;
+
+        // Store process info and child handle
+        {
+            let mut processes = self.processes.lock().unwrap();
+            processes.insert(name.to_string(), info.clone());
+            self.registry.save(&processes);
+        }
+        {
+            let mut children = self.children.lock().unwrap();
+            children.insert(name.to_string(), child);
+        }
+
+        debug!(
+            "Started background process '{}' (PID: {}) with logs at {:?}",
+            name, info.pid, info.log_file
+        );
+
+        if options.needs_monitor() {
+            Monitor {
+                name: name.to_string(),
+                options,
+                log_offset,
+                processes: Arc::clone(&self.processes),
+                children: Arc::clone(&self.children),
+                shutdown: Arc::clone(&self.shutdown),
+                registry: Arc::clone(&self.registry),
+            }
+            .spawn();
+        }
+
+        Ok(info)
+    }
+
+    /// Run `command` once, after `delay`
+    pub fn start_after(
+        &self,
+        name: &str,
+        command: &str,
+        working_dir: &PathBuf,
+        delay: Duration,
+    ) -> Result<ProcessInfo, String> {
+        self.schedule_with_options(
+            name,
+            command,
+            working_dir,
+            Schedule::After(delay),
+            ProcessOptions::default(),
+        )
+    }
+
+    /// Run `command` now and then every `interval`, e.g. for periodic jobs.
+    /// A run is skipped if the previous one is still going.
+    pub fn start_every(
+        &self,
+        name: &str,
+        command: &str,
+        working_dir: &PathBuf,
+        interval: Duration,
+    ) -> Result<ProcessInfo, String> {
+        self.schedule_with_options(
+            name,
+            command,
+            working_dir,
+            Schedule::Every(interval),
+            ProcessOptions::default(),
+        )
+    }
+
+    /// Schedule `command` to run as `schedule` says, with `options`.
+    ///
+    /// The task is tracked (and listed with its `next_run`) from now on, with
+    /// a PID of 0 until it first runs. All runs append to the same log file.
+    /// Health checks and restarts only apply to [`Schedule::After`]; each run
+    /// of a [`Schedule::Every`] task is left to finish on its own.
+    pub fn schedule_with_options(
+        &self,
+        name: &str,
+        command: &str,
+        working_dir: &PathBuf,
+        schedule: Schedule,
+        mut options: ProcessOptions,
+    ) -> Result<ProcessInfo, String> {
+        if matches!(schedule, Schedule::Every(interval) if interval.is_zero()) {
+            return Err("The interval of a periodic task must be positive".to_string());
+        }
+        let (mut info, log_offset) = self.create_entry(name, command, working_dir, &mut options)?;
+        let first_run = match schedule {
+            Schedule::After(delay) => delay,
+            Schedule::Every(_) => Duration::ZERO,
+        };
+        info.schedule = Some(schedule);
+        info.next_run = Some(unix_time_after(first_run));
+
+        {
+            let mut processes = self.processes.lock().unwrap();
+            processes.insert(name.to_string(), info.clone());
+            self.registry.save(&processes);
+        }
+        debug!(
+            "Scheduled background process '{}' ({:?}) with logs at {:?}",
+            name, schedule, info.log_file
+        );
+
+        let scheduler = Scheduler {
+            monitor: Monitor {
+                name: name.to_string(),
+                options,
+                log_offset,
+                processes: Arc::clone(&self.processes),
+                children: Arc::clone(&self.children),
+                shutdown: Arc::clone(&self.shutdown),
+                registry: Arc::clone(&self.registry),
+            },
+            schedule,
+            first_run: Instant::now() + first_run,
+        };
+        thread::spawn(move || scheduler.run());
+
+        Ok(info)
+    }
+
+    /// Check the name, allocate a port if needed and create the log file.
+    /// Returns the entry (not yet running) and where the command output starts
+    /// in the log.
+    fn create_entry(
+        &self,
+        name: &str,
+        command: &str,
+        working_dir: &PathBuf,
+        options: &mut ProcessOptions,
+    ) -> Result<(ProcessInfo, u64), String> {
         // Check if a process with this name already exists
         {
             let processes = self.processes.lock().unwrap();
@@ -89,6 +668,18 @@ impl BackgroundProcessManager {
             }
         }
 
+        let port = if command.contains(PORT_PLACEHOLDER) || options.uses_port() {
+            let port = self.allocate_port()?;
+            options.resolve_port(port);
+            Some(port)
+        } else {
+            None
+        };
+        let command = match port {
+            Some(port) => command.replace(PORT_PLACEHOLDER, &port.to_string()),
+            None => command.to_string(),
+        };
+
         // Create log file with timestamp
         let timestamp = SystemTime::now()
             .duration_since(UNIX_EPOCH)
@@ -112,52 +703,52 @@ impl BackgroundProcessManager {
             writeln!(file, "Name: {}", name).ok();
             writeln!(file, "Command: {}", command).ok();
             writeln!(file, "Working Directory: {:?}", working_dir).ok();
+            if let Some(port) = port {
+                writeln!(file, "Port: {}", port).ok();
+            }
             writeln!(file, "Started: {}", timestamp).ok();
             writeln!(file, "================================\n").ok();
         }
 
-        // Clone the file handle for stderr
-        let log_handle_stderr = log_handle
-            .try_clone()
-            .map_err(|e| format!("Failed to clone log file handle: {}", e))?;
-
-        // Spawn the process
-        let child = Command::new("bash")
-            .arg("-c")
-            .arg(command)
-            .current_dir(working_dir)
-            .stdout(Stdio::from(log_handle))
-            .stderr(Stdio::from(log_handle_stderr))
-            .spawn()
-            .map_err(|e| format!("Failed to spawn process: {}", e))?;
-
-        let pid = child.id();
+        drop(log_handle);
 
         let info = ProcessInfo {
             name: name.to_string(),
-            command: command.to_string(),
-            pid,
+            command,
+            pid: 0,
             log_file: log_file.clone(),
             started_at: timestamp,
             working_dir: working_dir.clone(),
+            port,
+            restarts: 0,
+            health: HealthStatus::Unknown,
+            process_start: None,
+            limits: options.limits,
+            limit_backend: None,
+            schedule: None,
+            next_run: None,
+            runs: 0,
         };
+        // Log pattern checks skip the header (which includes the command)
+        Ok((info, log_len(&log_file)))
+    }
 
-        // Store process info and child handle
-        {
-            let mut processes = self.processes.lock().unwrap();
-            processes.insert(name.to_string(), info.clone());
-        }
-        {
-            let mut children = self.children.lock().unwrap();
-            children.insert(name.to_string(), child);
+    /// Find a free local TCP port that no tracked process was given
+    fn allocate_port(&self) -> Result<u16, String> {
+        let in_use: Vec<u16> = {
+            let processes = self.processes.lock().unwrap();
+            processes.values().filter_map(|info| info.port).collect()
+        };
+        for _ in 0..10 {
+            let port = std::net::TcpListener::bind(("127.0.0.1", 0))
+                .and_then(|listener| listener.local_addr())
+                .map_err(|e| format!("Failed to allocate a port: {}", e))?
+                .port();
+            if !in_use.contains(&port) {
+                return Ok(port);
+            }
         }
-
-        debug!(
-            "Started background process '{}' (PID: {}) with logs at {:?}",
-            name, pid, log_file
-        );
-
-        Ok(info)
+        Err("Failed to allocate a free port".to_string())
     }
 
     /// List all tracked background processes
@@ -182,69 +773,784 @@ impl BackgroundProcessManager {
                 Err(_) => false,      // Error checking, assume not running
             }
         } else {
-            false
+            // Adopted processes have no child handle
+            self.get(name).as_ref().is_some_and(process_matches)
+        }
+    }
+
+    /// Current memory and CPU use of a process and everything it spawned
+    pub fn stats(&self, name: &str) -> Result<ProcessStats, String> {
+        let info = self
+            .get(name)
+            .ok_or_else(|| format!("No background process named '{}'", name))?;
+        if !self.is_running(name) {
+            return Err(format!("Background process '{}' is not running", name));
         }
+        let (rss_bytes, cpu_time) = resource_limits::usage(info.pid, info.limit_backend.as_ref())?;
+
+        // CPU percent since the previous sample, or on average since start
+        let now = Instant::now();
+        let mut samples = self.cpu_samples.lock().unwrap();
+        let (elapsed, cpu_delta) = match samples.get(name) {
+            Some((at, cpu)) if cpu_time >= *cpu => (now - *at, cpu_time - *cpu),
+            _ => {
+                let started = UNIX_EPOCH + Duration::from_secs(info.started_at);
+                let elapsed = SystemTime::now()
+                    .duration_since(started)
+                    .unwrap_or_default();
+                (elapsed, cpu_time)
+            }
+        };
+        samples.insert(name.to_string(), (now, cpu_time));
+        let cpu_percent = if elapsed.is_zero() {
+            0.0
+        } else {
+            cpu_delta.as_secs_f64() / elapsed.as_secs_f64() * 100.0
+        };
+
+        Ok(ProcessStats {
+            rss_bytes,
+            cpu_time,
+            cpu_percent,
+        })
     }
 
     /// Remove a process from tracking (call after it has been killed)
     pub fn remove(&self, name: &str) -> Option<ProcessInfo> {
         let info = {
             let mut processes = self.processes.lock().unwrap();
-            processes.remove(name)
+            let info = processes.remove(name);
+            self.registry.save(&processes);
+            info
         };
         {
             let mut children = self.children.lock().unwrap();
             children.remove(name);
         }
+        self.cpu_samples.lock().unwrap().remove(name);
+        if let Some(backend) = info.as_ref().and_then(|i| i.limit_backend.as_ref()) {
+            resource_limits::release(backend);
+        }
         info
     }
 
-    /// Clean up all processes on shutdown
+    /// Stop a process and everything it spawned.
+    ///
+    /// Sends SIGTERM to the process group, waits up to `grace_period` for it to
+    /// exit, then sends SIGKILL. The process is no longer tracked afterwards.
+    /// On platforms without signals the process is killed immediately.
+    pub fn stop(&self, name: &str, grace_period: Duration) -> Result<ProcessInfo, String> {
+        // Untrack first so the monitor thread doesn't restart it
+        let info = {
+            let mut processes = self.processes.lock().unwrap();
+            let info = processes
+                .remove(name)
+                .ok_or_else(|| format!("No background process named '{}'", name))?;
+            self.registry.save(&processes);
+            info
+        };
+        let mut child = {
+            let mut children = self.children.lock().unwrap();
+            children.remove(name)
+        };
+        // An adopted process may have exited, and its PID been reused, since
+        self.cpu_samples.lock().unwrap().remove(name);
+        if child.is_none() && !process_matches(&info) {
+            return Ok(info);
+        }
+
+        let graceful = terminate_group(info.pid, child.as_mut(), grace_period);
+        if let Some(backend) = &info.limit_backend {
+            resource_limits::release(backend);
+        }
+        append_log_event(
+            &info.log_file,
+            if graceful {
+                "process stopped (SIGTERM)"
+            } else {
+                "process killed (SIGKILL after grace period)"
+            },
+        );
+        debug!(
+            "Stopped background process '{}' (PID: {}, graceful: {})",
+            name, info.pid, graceful
+        );
+        Ok(info)
+    }
+
+    /// Send `signal` (e.g. `libc::SIGHUP`) to the process group of a tracked process
+    pub fn signal(&self, name: &str, signal: i32) -> Result<(), String> {
+        let pid = {
+            let processes = self.processes.lock().unwrap();
+            processes
+                .get(name)
+                .map(|info| info.pid)
+                .ok_or_else(|| format!("No background process named '{}'", name))?
+        };
+        // Scheduled tasks that haven't run yet have no process (PID 0)
+        if !self.is_running(name) {
+            return Err(format!("Background process '{}' is not running", name));
+        }
+        signal_group(pid, signal).map_err(|e| format!("Failed to signal process '{}': {}", name, e))
+    }
+
+    /// Clean up all processes (including adopted ones) on shutdown
     pub fn cleanup(&self) {
+        self.shutdown.store(true, Ordering::SeqCst);
         let mut children = self.children.lock().unwrap();
+        let mut processes = self.processes.lock().unwrap();
         for (name, mut child) in children.drain() {
             debug!("Cleaning up background process '{}'", name);
-            let _ = child.kill();
+            kill_group(child.id(), Some(&mut child));
+            if let Some(backend) = processes.remove(&name).and_then(|i| i.limit_backend) {
+                resource_limits::release(&backend);
+            }
         }
+        for (name, info) in processes.drain() {
+            if process_matches(&info) {
+                debug!("Cleaning up adopted background process '{}'", name);
+                kill_group(info.pid, None);
+            }
+            if let Some(backend) = &info.limit_backend {
+                resource_limits::release(backend);
+            }
+        }
+        self.registry.save(&processes);
     }
 }
 
-impl Drop for BackgroundProcessManager {
-    fn drop(&mut self) {
-        self.cleanup();
-    }
-}
+/// Spawn `command` with the environment and limits of `options`, and stdout
+/// and stderr appended to `log_file`
+fn spawn_logged(
+    command: &str,
+    working_dir: &Path,
+    options: &ProcessOptions,
+    log_file: &Path,
+) -> Result<(Child, Option<LimitBackend>), String> {
+    let log_handle = OpenOptions::new()
+        .append(true)
+        .open(log_file)
+        .map_err(|e| format!("Failed to open log file: {}", e))?;
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use std::thread;
-    use std::time::Duration;
+    // Clone the file handle for stderr
+    let log_handle_stderr = log_handle
+        .try_clone()
+        .map_err(|e| format!("Failed to clone log file handle: {}", e))?;
 
-    #[test]
-    fn test_start_and_list_process() {
-        let temp_dir = std::env::temp_dir().join("g3_bg_test");
-        let _ = fs::remove_dir_all(&temp_dir);
-        fs::create_dir_all(&temp_dir).unwrap();
+    let mut cmd = Command::new("bash");
+    cmd.arg("-c")
+        .arg(command)
+        .current_dir(working_dir)
+        .envs(&options.env)
+        .stdout(Stdio::from(log_handle))
+        .stderr(Stdio::from(log_handle_stderr));
 
-        let manager = BackgroundProcessManager::new(temp_dir.clone());
+    // Lead a new process group so signals reach grandchildren (e.g. node
+    // servers started by npm) instead of orphaning them
+    #[cfg(unix)]
+    {
+        use std::os::unix::process::CommandExt;
+        cmd.process_group(0);
+    }
 
-        // Start a simple process that sleeps
-        let result = manager.start("test_sleep", "sleep 10", &temp_dir);
-        assert!(result.is_ok());
+    let limits = options.limits.as_ref();
+    let backend = limits.map(|limits| resource_limits::prepare(&mut cmd, limits));
+    let mut child = cmd.spawn().map_err(|e| {
+        if let Some(backend) = &backend {
+            resource_limits::release(backend);
+        }
+        format!("Failed to spawn process: {}", e)
+    })?;
 
-        let info = result.unwrap();
-        assert_eq!(info.name, "test_sleep");
-        assert!(info.pid > 0);
-        assert!(info.log_file.exists());
+    if let (Some(backend), Some(limits)) = (&backend, limits) {
+        if let Err(e) = resource_limits::attach(backend, &child, limits) {
+            kill_group(child.id(), Some(&mut child));
+            resource_limits::release(backend);
+            return Err(e);
+        }
+        append_log_event(
+            log_file,
+            &format!(
+                "resource limits: {} via {}",
+                limits,
+                backend.describe(limits)
+            ),
+        );
+    }
+    Ok((child, backend))
+}
 
-        // List should contain our process
-        let list = manager.list();
-        assert_eq!(list.len(), 1);
-        assert_eq!(list[0].name, "test_sleep");
+/// Send `signal` to the process group led by `pid`
+#[cfg(unix)]
+fn signal_group(pid: u32, signal: i32) -> std::io::Result<()> {
+    let pgid = libc::pid_t::try_from(pid)
+        .map_err(|_| std::io::Error::new(std::io::ErrorKind::InvalidInput, "invalid PID"))?;
+    // SAFETY: kill() has no memory-safety preconditions; a negative PID
+    // addresses the process group
+    if unsafe { libc::kill(-pgid, signal) } == 0 {
+        Ok(())
+    } else {
+        Err(std::io::Error::last_os_error())
+    }
+}
 
-        // Should be running
-        assert!(manager.is_running("test_sleep"));
+#[cfg(not(unix))]
+fn signal_group(_pid: u32, _signal: i32) -> std::io::Result<()> {
+    Err(std::io::Error::new(
+        std::io::ErrorKind::Unsupported,
+        "signals are not supported on this platform",
+    ))
+}
+
+/// Whether any process of the group led by `pid` is still alive
+fn group_alive(pid: u32) -> bool {
+    #[cfg(unix)]
+    {
+        signal_group(pid, 0).is_ok()
+    }
+    #[cfg(not(unix))]
+    {
+        let _ = pid;
+        false
+    }
+}
+
+/// Kill the process group led by `pid` immediately. `child` is the handle of
+/// the leader, if this manager spawned it.
+fn kill_group(pid: u32, child: Option<&mut Child>) {
+    #[cfg(unix)]
+    let _ = signal_group(pid, SIGKILL);
+    #[cfg(not(unix))]
+    let _ = pid;
+    if let Some(child) = child {
+        let _ = child.kill();
+        let _ = child.wait();
+    }
+}
+
+/// SIGTERM the process group led by `pid`, escalating to SIGKILL after
+/// `grace_period`. Returns true if the group exited within the grace period.
+fn terminate_group(pid: u32, mut child: Option<&mut Child>, grace_period: Duration) -> bool {
+    #[cfg(unix)]
+    if signal_group(pid, SIGTERM).is_err() {
+        // The group is already gone
+        if let Some(child) = child {
+            let _ = child.wait();
+        }
+        return true;
+    }
+
+    let deadline = Instant::now() + grace_period;
+    loop {
+        // Reap the leader so it doesn't linger as a zombie group member
+        let leader_exited = match child.as_mut() {
+            Some(child) => !matches!(child.try_wait(), Ok(None)),
+            None => true,
+        };
+        if leader_exited && !group_alive(pid) {
+            return true;
+        }
+        if Instant::now() >= deadline {
+            break;
+        }
+        thread::sleep(STOP_POLL.min(deadline - Instant::now()));
+    }
+
+    kill_group(pid, child);
+    false
+}
+
+/// An OS-reported start time for `pid`, or None if no such process is running.
+/// Comparing start times tells a still-running process from a reused PID.
+fn process_start_time(pid: u32) -> Option<String> {
+    #[cfg(target_os = "linux")]
+    {
+        let stat = fs::read_to_string(format!("/proc/{}/stat", pid)).ok()?;
+        // Fields after the parenthesised command name start at field 3 (state);
+        // starttime is field 22
+        let fields: Vec<&str> = stat[stat.rfind(')')? + 1..].split_whitespace().collect();
+        if fields.first() == Some(&"Z") {
+            return None;
+        }
+        fields.get(19).map(|start| start.to_string())
+    }
+    #[cfg(all(unix, not(target_os = "linux")))]
+    {
+        let output = Command::new("ps")
+            .args(["-o", "lstart=", "-p", &pid.to_string()])
+            .output()
+            .ok()?;
+        let start = String::from_utf8_lossy(&output.stdout).trim().to_string();
+        (output.status.success() && !start.is_empty()).then_some(start)
+    }
+    #[cfg(not(unix))]
+    {
+        let _ = pid;
+        None
+    }
+}
+
+/// Whether the process recorded in `info` is still the one running under its PID
+fn process_matches(info: &ProcessInfo) -> bool {
+    info.process_start.is_some() && process_start_time(info.pid) == info.process_start
+}
+
+/// Append a line describing a lifecycle event to the process log
+fn append_log_event(log_file: &Path, event: &str) {
+    let timestamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_secs();
+    if let Ok(mut file) = OpenOptions::new().append(true).open(log_file) {
+        writeln!(file, "\n=== [{}] {} ===", timestamp, event).ok();
+    }
+}
+
+/// Size of the log file, where the output of the next run starts
+fn log_len(log_file: &Path) -> u64 {
+    fs::metadata(log_file).map(|m| m.len()).unwrap_or(0)
+}
+
+/// Whether `pattern` matches the log output written after `offset`
+fn log_matches(log_file: &Path, offset: u64, pattern: &Regex) -> bool {
+    let Ok(mut file) = File::open(log_file) else {
+        return false;
+    };
+    let mut content = Vec::new();
+    if file.seek(SeekFrom::Start(offset)).is_err() || file.read_to_end(&mut content).is_err() {
+        return false;
+    }
+    pattern.is_match(&String::from_utf8_lossy(&content))
+}
+
+/// Whether the process was stopped on purpose (`kill <pid>` or Ctrl+C),
+/// which should not trigger a restart
+fn stopped_by_user(status: &std::process::ExitStatus) -> bool {
+    #[cfg(unix)]
+    {
+        use std::os::unix::process::ExitStatusExt;
+        // SIGINT and SIGTERM
+        matches!(status.signal(), Some(2) | Some(15))
+    }
+    #[cfg(not(unix))]
+    {
+        let _ = status;
+        false
+    }
+}
+
+fn tcp_port_open(port: u16) -> bool {
+    let addr = SocketAddr::from(([127, 0, 0, 1], port));
+    TcpStream::connect_timeout(&addr, PROBE_TIMEOUT).is_ok()
+}
+
+/// Whether an HTTP status line (e.g. `HTTP/1.1 200 OK`) reports success
+fn http_status_ok(status_line: &str) -> bool {
+    status_line
+        .split_whitespace()
+        .nth(1)
+        .and_then(|code| code.parse::<u16>().ok())
+        .is_some_and(|code| (200..400).contains(&code))
+}
+
+/// Minimal HTTP/1.0 GET, enough to probe a local dev server
+fn http_get_ok(url: &str) -> bool {
+    let Some(rest) = url.strip_prefix("http://") else {
+        return false;
+    };
+    let (host, path) = match rest.find('/') {
+        Some(i) => (&rest[..i], &rest[i..]),
+        None => (rest, "/"),
+    };
+    let address = if host.contains(':') {
+        host.to_string()
+    } else {
+        format!("{}:80", host)
+    };
+    let Some(addr) = address.to_socket_addrs().ok().and_then(|mut a| a.next()) else {
+        return false;
+    };
+    let Ok(mut stream) = TcpStream::connect_timeout(&addr, PROBE_TIMEOUT) else {
+        return false;
+    };
+    stream.set_read_timeout(Some(PROBE_TIMEOUT)).ok();
+    stream.set_write_timeout(Some(PROBE_TIMEOUT)).ok();
+    let request = format!(
+        "GET {} HTTP/1.0\r\nHost: {}\r\nConnection: close\r\n\r\n",
+        path, host
+    );
+    if stream.write_all(request.as_bytes()).is_err() {
+        return false;
+    }
+    let mut response = [0u8; 64];
+    let Ok(n) = stream.read(&mut response) else {
+        return false;
+    };
+    let response = String::from_utf8_lossy(&response[..n]);
+    http_status_ok(response.lines().next().unwrap_or_default())
+}
+
+/// Watches one process: runs its health check and restarts it on failure
+struct Monitor {
+    name: String,
+    options: ProcessOptions,
+    /// Where the output of the first run starts in the log
+    log_offset: u64,
+    processes: Arc<Mutex<HashMap<String, ProcessInfo>>>,
+    children: Arc<Mutex<HashMap<String, Child>>>,
+    shutdown: Arc<AtomicBool>,
+    registry: Arc<Registry>,
+}
+
+impl Monitor {
+    fn spawn(self) {
+        thread::spawn(move || self.run());
+    }
+
+    fn run(self) {
+        let Some(info) = self.info() else {
+            return;
+        };
+        let log_file = info.log_file.clone();
+        let mut run_started = Instant::now();
+        let mut log_offset = self.log_offset;
+        let mut last_check: Option<Instant> = None;
+        let mut failures = 0;
+
+        loop {
+            thread::sleep(MONITOR_TICK);
+            if self.stopped() {
+                return;
+            }
+
+            // Exit detection
+            let exit_status = {
+                let mut children = self.children.lock().unwrap();
+                match children.get_mut(&self.name) {
+                    Some(child) => child.try_wait().ok().flatten(),
+                    None => return,
+                }
+            };
+            let failure = match exit_status {
+                Some(status) if status.success() => {
+                    debug!("Background process '{}' exited cleanly", self.name);
+                    return;
+                }
+                Some(status) if stopped_by_user(&status) => {
+                    append_log_event(&log_file, &format!("process stopped ({})", status));
+                    return;
+                }
+                Some(status) => Some(format!("process exited ({})", status)),
+                None => self.check_health(
+                    run_started,
+                    log_offset,
+                    &log_file,
+                    &mut last_check,
+                    &mut failures,
+                ),
+            };
+            let Some(reason) = failure else {
+                continue;
+            };
+
+            // Failure: restart if the policy allows it
+            let RestartPolicy::OnFailure {
+                max_restarts,
+                backoff,
+            } = self.options.restart
+            else {
+                if exit_status.is_some() {
+                    return;
+                }
+                // Health check failed but restarts are disabled; keep reporting
+                failures = 0;
+                continue;
+            };
+            let restarts = self.info().map(|info| info.restarts).unwrap_or(0);
+            if restarts >= max_restarts {
+                append_log_event(
+                    &log_file,
+                    &format!("{}; giving up after {} restart(s)", reason, restarts),
+                );
+                return;
+            }
+
+            let delay = backoff
+                .checked_mul(1 << restarts.min(16))
+                .unwrap_or(MAX_BACKOFF)
+                .min(MAX_BACKOFF);
+            append_log_event(
+                &log_file,
+                &format!(
+                    "{}; restarting in {:.1}s (restart {}/{})",
+                    reason,
+                    delay.as_secs_f64(),
+                    restarts + 1,
+                    max_restarts
+                ),
+            );
+
+            // Wait for the backoff in ticks, so removal and shutdown are noticed
+            let deadline = Instant::now() + delay;
+            while Instant::now() < deadline {
+                thread::sleep(MONITOR_TICK.min(deadline - Instant::now()));
+                if self.stopped() {
+                    return;
+                }
+            }
+
+            if !self.restart(&info.command, &info.working_dir, &log_file, &mut log_offset) {
+                return;
+            }
+            run_started = Instant::now();
+            last_check = None;
+            failures = 0;
+        }
+    }
+
+    fn info(&self) -> Option<ProcessInfo> {
+        self.processes.lock().unwrap().get(&self.name).cloned()
+    }
+
+    /// Whether monitoring should end (manager shut down or process removed)
+    fn stopped(&self) -> bool {
+        self.shutdown.load(Ordering::SeqCst)
+            || !self.processes.lock().unwrap().contains_key(&self.name)
+    }
+
+    /// Run the health check if it is due. Returns a failure reason once the
+    /// failure threshold is reached.
+    fn check_health(
+        &self,
+        run_started: Instant,
+        log_offset: u64,
+        log_file: &Path,
+        last_check: &mut Option<Instant>,
+        failures: &mut u32,
+    ) -> Option<String> {
+        let config = self.options.health_check.as_ref()?;
+        if last_check.is_some_and(|at| at.elapsed() < config.interval) {
+            return None;
+        }
+        *last_check = Some(Instant::now());
+
+        let healthy = match &config.check {
+            HealthCheck::TcpPort(port) => tcp_port_open(*port),
+            HealthCheck::Http(url) => http_get_ok(url),
+            HealthCheck::LogPattern(pattern) => log_matches(log_file, log_offset, pattern),
+        };
+        let in_grace = run_started.elapsed() < config.startup_grace;
+        if healthy {
+            *failures = 0;
+        } else if !in_grace {
+            *failures += 1;
+        }
+
+        let status = if healthy {
+            HealthStatus::Healthy
+        } else if in_grace {
+            HealthStatus::Unknown
+        } else {
+            HealthStatus::Unhealthy
+        };
+        if let Some(info) = self.processes.lock().unwrap().get_mut(&self.name) {
+            info.health = status;
+        }
+
+        (*failures >= config.failure_threshold)
+            .then(|| format!("health check failed {} time(s) in a row", failures))
+    }
+
+    /// Kill the current child and spawn the command again. Returns false if
+    /// the process is no longer tracked or could not be restarted.
+    fn restart(
+        &self,
+        command: &str,
+        working_dir: &Path,
+        log_file: &Path,
+        log_offset: &mut u64,
+    ) -> bool {
+        // Hold the children lock so a concurrent remove() can't interleave
+        let mut children = self.children.lock().unwrap();
+        let mut processes = self.processes.lock().unwrap();
+        let Some(info) = processes.get_mut(&self.name) else {
+            return false;
+        };
+        if let Some(mut old) = children.remove(&self.name) {
+            kill_group(old.id(), Some(&mut old));
+        }
+        if let Some(backend) = info.limit_backend.take() {
+            resource_limits::release(&backend);
+        }
+
+        // Log pattern checks only look at output from the new run
+        *log_offset = log_len(log_file);
+        match spawn_logged(command, working_dir, &self.options, log_file) {
+            Ok((child, limit_backend)) => {
+                info.pid = child.id();
+                info.limit_backend = limit_backend;
+                info.restarts += 1;
+                info.health = HealthStatus::Unknown;
+                info.process_start = process_start_time(info.pid);
+                debug!(
+                    "Restarted background process '{}' (PID: {})",
+                    self.name, info.pid
+                );
+                children.insert(self.name.clone(), child);
+                self.registry.save(&processes);
+                true
+            }
+            Err(e) => {
+                append_log_event(log_file, &format!("Restart failed: {}", e));
+                false
+            }
+        }
+    }
+}
+
+/// Launches a scheduled command whenever it is due
+struct Scheduler {
+    /// Shared state, and the monitor for one-off runs with health checks or restarts
+    monitor: Monitor,
+    schedule: Schedule,
+    first_run: Instant,
+}
+
+impl Scheduler {
+    fn run(self) {
+        let interval = match self.schedule {
+            Schedule::After(_) => None,
+            Schedule::Every(interval) => Some(interval),
+        };
+        let mut due = self.first_run;
+        loop {
+            // Wait in ticks, so removal and shutdown are noticed
+            while Instant::now() < due {
+                thread::sleep(MONITOR_TICK.min(due - Instant::now()));
+                if self.monitor.stopped() {
+                    return;
+                }
+            }
+            if self.monitor.stopped() {
+                return;
+            }
+
+            let Some(interval) = interval else {
+                if self.launch(None) && self.monitor.options.needs_monitor() {
+                    self.monitor.run();
+                }
+                return;
+            };
+
+            // Keep to the original cadence, skipping runs that were missed
+            while due <= Instant::now() {
+                due += interval;
+            }
+            let next_run = unix_time_after(due - Instant::now());
+            if !self.launch(Some(next_run)) && self.monitor.info().is_none() {
+                return;
+            }
+        }
+    }
+
+    /// Spawn a run unless the previous one is still going. Returns false if
+    /// the task is no longer tracked or the command could not be started.
+    fn launch(&self, next_run: Option<u64>) -> bool {
+        let monitor = &self.monitor;
+        let mut children = monitor.children.lock().unwrap();
+        let mut processes = monitor.processes.lock().unwrap();
+        let Some(info) = processes.get_mut(&monitor.name) else {
+            return false;
+        };
+        info.next_run = next_run;
+
+        let repeating = next_run.is_some();
+        if let Some(previous) = children.get_mut(&monitor.name) {
+            if matches!(previous.try_wait(), Ok(None)) {
+                append_log_event(&info.log_file, "previous run still running; skipping");
+                monitor.registry.save(&processes);
+                return true;
+            }
+        }
+        if let Some(backend) = info.limit_backend.take() {
+            resource_limits::release(&backend);
+        }
+        if repeating {
+            append_log_event(&info.log_file, &format!("run {}", info.runs + 1));
+        }
+
+        let launched = match spawn_logged(
+            &info.command,
+            &info.working_dir,
+            &monitor.options,
+            &info.log_file,
+        ) {
+            Ok((child, limit_backend)) => {
+                info.pid = child.id();
+                info.process_start = process_start_time(info.pid);
+                info.limit_backend = limit_backend;
+                info.runs += 1;
+                debug!(
+                    "Launched scheduled process '{}' (PID: {}, run {})",
+                    monitor.name, info.pid, info.runs
+                );
+                children.insert(monitor.name.clone(), child);
+                true
+            }
+            Err(e) => {
+                append_log_event(&info.log_file, &format!("Run failed: {}", e));
+                false
+            }
+        };
+        monitor.registry.save(&processes);
+        launched
+    }
+}
+
+/// Unix timestamp `delay` from now
+fn unix_time_after(delay: Duration) -> u64 {
+    (SystemTime::now() + delay)
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs()
+}
+
+impl Drop for BackgroundProcessManager {
+    fn drop(&mut self) {
+        self.cleanup();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::thread;
+    use std::time::Duration;
+
+    #[test]
+    fn test_start_and_list_process() {
+        let temp_dir = std::env::temp_dir().join("g3_bg_test");
+        let _ = fs::remove_dir_all(&temp_dir);
+        fs::create_dir_all(&temp_dir).unwrap();
+
+        let manager = BackgroundProcessManager::new(temp_dir.clone());
+
+        // Start a simple process that sleeps
+        let result = manager.start("test_sleep", "sleep 10", &temp_dir);
+        assert!(result.is_ok());
+
+        let info = result.unwrap();
+        assert_eq!(info.name, "test_sleep");
+        assert!(info.pid > 0);
+        assert!(info.log_file.exists());
+
+        // List should contain our process
+        let list = manager.list();
+        assert_eq!(list.len(), 1);
+        assert_eq!(list[0].name, "test_sleep");
+
+        // Should be running
+        assert!(manager.is_running("test_sleep"));
 
         // Cleanup
         manager.cleanup();
@@ -276,4 +1582,675 @@ mod tests {
         manager.cleanup();
         let _ = fs::remove_dir_all(&temp_dir);
     }
+
+    /// Poll `condition` until it holds or `timeout` passes
+    fn wait_until(timeout: Duration, condition: impl Fn() -> bool) -> bool {
+        let deadline = Instant::now() + timeout;
+        while Instant::now() < deadline {
+            if condition() {
+                return true;
+            }
+            thread::sleep(Duration::from_millis(50));
+        }
+        condition()
+    }
+
+    #[test]
+    fn test_restart_on_failure_until_max_restarts() {
+        let temp_dir = std::env::temp_dir().join("g3_bg_test_restart");
+        let _ = fs::remove_dir_all(&temp_dir);
+        fs::create_dir_all(&temp_dir).unwrap();
+
+        let manager = BackgroundProcessManager::new(temp_dir.clone());
+        let options = ProcessOptions {
+            health_check: None,
+            restart: RestartPolicy::OnFailure {
+                max_restarts: 2,
+                backoff: Duration::from_millis(10),
+            },
+            ..ProcessOptions::default()
+        };
+        let info = manager
+            .start_with_options("flaky", "echo run >> runs.txt; exit 1", &temp_dir, options)
+            .unwrap();
+
+        let log_file = info.log_file.clone();
+        assert!(wait_until(Duration::from_secs(10), || {
+            fs::read_to_string(&log_file)
+                .unwrap_or_default()
+                .contains("giving up after 2 restart(s)")
+        }));
+        assert_eq!(manager.get("flaky").unwrap().restarts, 2);
+        let runs = fs::read_to_string(temp_dir.join("runs.txt")).unwrap();
+        assert_eq!(runs.lines().count(), 3);
+        let log = fs::read_to_string(&log_file).unwrap();
+        assert!(log.contains("restarting in 0.0s (restart 1/2)"));
+
+        manager.cleanup();
+        let _ = fs::remove_dir_all(&temp_dir);
+    }
+
+    #[test]
+    fn test_log_pattern_health_check() {
+        let temp_dir = std::env::temp_dir().join("g3_bg_test_log_health");
+        let _ = fs::remove_dir_all(&temp_dir);
+        fs::create_dir_all(&temp_dir).unwrap();
+
+        let manager = BackgroundProcessManager::new(temp_dir.clone());
+        let mut check = HealthCheckConfig::new(HealthCheck::LogPattern(
+            Regex::new(r"Listening on \d+").unwrap(),
+        ));
+        check.interval = Duration::from_millis(50);
+        let options = ProcessOptions {
+            health_check: Some(check),
+            ..ProcessOptions::default()
+        };
+        manager
+            .start_with_options(
+                "server",
+                "sleep 0.3; echo 'Listening on 8080'; sleep 10",
+                &temp_dir,
+                options,
+            )
+            .unwrap();
+
+        assert!(wait_until(Duration::from_secs(5), || {
+            manager.get("server").unwrap().health == HealthStatus::Healthy
+        }));
+
+        manager.cleanup();
+        let _ = fs::remove_dir_all(&temp_dir);
+    }
+
+    #[test]
+    fn test_failing_tcp_health_check_restarts() {
+        let temp_dir = std::env::temp_dir().join("g3_bg_test_tcp_health");
+        let _ = fs::remove_dir_all(&temp_dir);
+        fs::create_dir_all(&temp_dir).unwrap();
+
+        // A port nothing listens on
+        let port = std::net::TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap()
+            .port();
+
+        let manager = BackgroundProcessManager::new(temp_dir.clone());
+        let mut check = HealthCheckConfig::new(HealthCheck::TcpPort(port));
+        check.interval = Duration::from_millis(50);
+        check.startup_grace = Duration::ZERO;
+        check.failure_threshold = 2;
+        let options = ProcessOptions {
+            health_check: Some(check),
+            restart: RestartPolicy::OnFailure {
+                max_restarts: 1,
+                backoff: Duration::ZERO,
+            },
+            ..ProcessOptions::default()
+        };
+        let info = manager
+            .start_with_options("no_port", "sleep 30", &temp_dir, options)
+            .unwrap();
+
+        assert!(wait_until(Duration::from_secs(5), || {
+            fs::read_to_string(&info.log_file)
+                .unwrap_or_default()
+                .contains("giving up after 1 restart(s)")
+        }));
+        let restarted = manager.get("no_port").unwrap();
+        assert_eq!(restarted.restarts, 1);
+        assert_ne!(restarted.pid, info.pid);
+        assert_eq!(restarted.health, HealthStatus::Unhealthy);
+
+        manager.cleanup();
+        let _ = fs::remove_dir_all(&temp_dir);
+    }
+
+    #[test]
+    fn test_killed_process_is_not_restarted() {
+        let temp_dir = std::env::temp_dir().join("g3_bg_test_kill");
+        let _ = fs::remove_dir_all(&temp_dir);
+        fs::create_dir_all(&temp_dir).unwrap();
+
+        let manager = BackgroundProcessManager::new(temp_dir.clone());
+        let options = ProcessOptions {
+            health_check: None,
+            restart: RestartPolicy::OnFailure {
+                max_restarts: 3,
+                backoff: Duration::ZERO,
+            },
+            ..ProcessOptions::default()
+        };
+        let info = manager
+            .start_with_options("killed", "exec sleep 30", &temp_dir, options)
+            .unwrap();
+        Command::new("kill")
+            .arg(info.pid.to_string())
+            .status()
+            .unwrap();
+
+        assert!(wait_until(Duration::from_secs(5), || {
+            fs::read_to_string(&info.log_file)
+                .unwrap_or_default()
+                .contains("process stopped")
+        }));
+        assert_eq!(manager.get("killed").unwrap().restarts, 0);
+
+        manager.cleanup();
+        let _ = fs::remove_dir_all(&temp_dir);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_stop_terminates_process_group() {
+        let temp_dir = std::env::temp_dir().join("g3_bg_test_stop");
+        let _ = fs::remove_dir_all(&temp_dir);
+        fs::create_dir_all(&temp_dir).unwrap();
+
+        let manager = BackgroundProcessManager::new(temp_dir.clone());
+        // The grandchild would be orphaned if only the shell were killed
+        let info = manager
+            .start("tree", "sleep 30 & echo $! > child.pid; wait", &temp_dir)
+            .unwrap();
+        let pid_file = temp_dir.join("child.pid");
+        assert!(wait_until(Duration::from_secs(5), || {
+            fs::read_to_string(&pid_file).is_ok_and(|pid| !pid.trim().is_empty())
+        }));
+        let grandchild = fs::read_to_string(&pid_file).unwrap().trim().to_string();
+
+        let stopped = manager.stop("tree", Duration::from_secs(5)).unwrap();
+        assert_eq!(stopped.pid, info.pid);
+        assert!(manager.get("tree").is_none());
+        assert!(!group_alive(info.pid));
+        let status = Command::new("kill")
+            .args(["-0", &grandchild])
+            .stderr(Stdio::null())
+            .status()
+            .unwrap();
+        assert!(!status.success(), "grandchild {} still running", grandchild);
+        assert!(fs::read_to_string(&info.log_file)
+            .unwrap()
+            .contains("process stopped (SIGTERM)"));
+
+        assert!(manager.stop("tree", Duration::ZERO).is_err());
+        let _ = fs::remove_dir_all(&temp_dir);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_stop_escalates_to_sigkill() {
+        let temp_dir = std::env::temp_dir().join("g3_bg_test_stop_kill");
+        let _ = fs::remove_dir_all(&temp_dir);
+        fs::create_dir_all(&temp_dir).unwrap();
+
+        let manager = BackgroundProcessManager::new(temp_dir.clone());
+        let info = manager
+            .start(
+                "stubborn",
+                "trap '' TERM; echo ready-$((40 + 2)); while true; do sleep 0.1; done",
+                &temp_dir,
+            )
+            .unwrap();
+        assert!(wait_until(Duration::from_secs(5), || {
+            fs::read_to_string(&info.log_file)
+                .unwrap_or_default()
+                .contains("ready-42")
+        }));
+
+        manager
+            .stop("stubborn", Duration::from_millis(300))
+            .unwrap();
+        assert!(!group_alive(info.pid));
+        assert!(fs::read_to_string(&info.log_file)
+            .unwrap()
+            .contains("SIGKILL after grace period"));
+
+        let _ = fs::remove_dir_all(&temp_dir);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_signal_process() {
+        let temp_dir = std::env::temp_dir().join("g3_bg_test_signal");
+        let _ = fs::remove_dir_all(&temp_dir);
+        fs::create_dir_all(&temp_dir).unwrap();
+
+        let manager = BackgroundProcessManager::new(temp_dir.clone());
+        let info = manager
+            .start(
+                "reloadable",
+                "trap 'echo reloaded-$((40 + 2))' HUP; echo ready-$((40 + 2)); while true; do sleep 0.1; done",
+                &temp_dir,
+            )
+            .unwrap();
+        assert!(wait_until(Duration::from_secs(5), || {
+            fs::read_to_string(&info.log_file)
+                .unwrap_or_default()
+                .contains("ready-42")
+        }));
+
+        manager.signal("reloadable", libc::SIGHUP).unwrap();
+        assert!(wait_until(Duration::from_secs(5), || {
+            fs::read_to_string(&info.log_file)
+                .unwrap_or_default()
+                .contains("reloaded-42")
+        }));
+        assert!(manager.is_running("reloadable"));
+        assert!(manager.signal("missing", libc::SIGHUP).is_err());
+
+        manager.cleanup();
+        let _ = fs::remove_dir_all(&temp_dir);
+    }
+
+    #[test]
+    fn test_env_and_port_placeholder() {
+        let temp_dir = std::env::temp_dir().join("g3_bg_test_port");
+        let _ = fs::remove_dir_all(&temp_dir);
+        fs::create_dir_all(&temp_dir).unwrap();
+
+        let manager = BackgroundProcessManager::new(temp_dir.clone());
+        let mut check = HealthCheckConfig::new(HealthCheck::TcpPort(0));
+        check.interval = Duration::from_millis(50);
+        let options = ProcessOptions {
+            health_check: Some(check),
+            env: HashMap::from([
+                ("GREETING".to_string(), "hello".to_string()),
+                ("APP_URL".to_string(), "http://localhost:{PORT}".to_string()),
+            ]),
+            ..ProcessOptions::default()
+        };
+        let info = manager
+            .start_with_options(
+                "web",
+                "echo \"$GREETING $APP_URL\"; echo 'serving on {PORT}'; sleep 10",
+                &temp_dir,
+                options,
+            )
+            .unwrap();
+        let port = info.port.expect("port should be allocated");
+        assert!(info.command.contains(&format!("serving on {}", port)));
+
+        assert!(wait_until(Duration::from_secs(5), || {
+            fs::read_to_string(&info.log_file)
+                .unwrap_or_default()
+                .contains(&format!("hello http://localhost:{}", port))
+        }));
+        // The health check probes the allocated port
+        let _listener = std::net::TcpListener::bind(("127.0.0.1", port)).unwrap();
+        assert!(wait_until(Duration::from_secs(5), || {
+            manager.get("web").unwrap().health == HealthStatus::Healthy
+        }));
+
+        // A second server gets a different port
+        let other = manager
+            .start("web2", "sleep 10 # {PORT}", &temp_dir)
+            .unwrap();
+        assert_ne!(other.port, Some(port));
+        assert!(manager
+            .start("plain", "sleep 10", &temp_dir)
+            .unwrap()
+            .port
+            .is_none());
+
+        manager.cleanup();
+        let _ = fs::remove_dir_all(&temp_dir);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_registry_readopts_running_processes() {
+        use std::os::unix::process::CommandExt;
+
+        let temp_dir = std::env::temp_dir().join("g3_bg_test_registry");
+        let _ = fs::remove_dir_all(&temp_dir);
+        fs::create_dir_all(&temp_dir).unwrap();
+
+        // A server left behind by an agent that has exited
+        let mut child = Command::new("sleep")
+            .arg("30")
+            .process_group(0)
+            .spawn()
+            .unwrap();
+        let pid = child.id();
+        let reaper = thread::spawn(move || child.wait());
+        let server = ProcessInfo {
+            name: "server".to_string(),
+            command: "sleep 30".to_string(),
+            pid,
+            log_file: temp_dir.join("server.log"),
+            started_at: 0,
+            working_dir: temp_dir.clone(),
+            port: Some(4000),
+            restarts: 0,
+            health: HealthStatus::Unknown,
+            process_start: process_start_time(pid),
+            limits: None,
+            limit_backend: None,
+            schedule: None,
+            next_run: None,
+            runs: 0,
+        };
+        // Its PID has since been reused by an unrelated process
+        let reused = ProcessInfo {
+            name: "reused".to_string(),
+            pid: std::process::id(),
+            process_start: Some("0".to_string()),
+            ..server.clone()
+        };
+        let exited_agent = Registry {
+            owner_start: None,
+            ..Registry::new(&temp_dir)
+        };
+        exited_agent.save(&HashMap::from([
+            ("server".to_string(), server),
+            ("reused".to_string(), reused),
+        ]));
+
+        // Processes of a manager that is still running are left alone
+        let live = BackgroundProcessManager::new(temp_dir.clone());
+        assert!(live.get("server").is_some());
+        live.start("other", "sleep 30", &temp_dir).unwrap();
+
+        let manager = BackgroundProcessManager::new(temp_dir.clone());
+        assert!(manager.get("other").is_none());
+        assert!(manager.get("server").is_none(), "adopted by `live`");
+        drop(manager);
+
+        // Once `live` exits, its processes are gone too
+        drop(live);
+        assert!(reaper.join().unwrap().is_ok());
+        let manager = BackgroundProcessManager::new(temp_dir.clone());
+        assert!(manager.list().is_empty());
+        let registry = fs::read_to_string(temp_dir.join(REGISTRY_FILE)).unwrap();
+        assert_eq!(registry.trim(), "[]");
+
+        let _ = fs::remove_dir_all(&temp_dir);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_adopted_process_can_be_stopped() {
+        use std::os::unix::process::CommandExt;
+
+        let temp_dir = std::env::temp_dir().join("g3_bg_test_adopt_stop");
+        let _ = fs::remove_dir_all(&temp_dir);
+        fs::create_dir_all(&temp_dir).unwrap();
+
+        let mut child = Command::new("sleep")
+            .arg("30")
+            .process_group(0)
+            .spawn()
+            .unwrap();
+        let pid = child.id();
+        let reaper = thread::spawn(move || child.wait());
+        let info = ProcessInfo {
+            name: "server".to_string(),
+            command: "sleep 30".to_string(),
+            pid,
+            log_file: temp_dir.join("server.log"),
+            started_at: 0,
+            working_dir: temp_dir.clone(),
+            port: None,
+            restarts: 0,
+            health: HealthStatus::Unknown,
+            process_start: process_start_time(pid),
+            limits: None,
+            limit_backend: None,
+            schedule: None,
+            next_run: None,
+            runs: 0,
+        };
+        fs::write(&info.log_file, "").unwrap();
+        Registry {
+            owner_start: None,
+            ..Registry::new(&temp_dir)
+        }
+        .save(&HashMap::from([("server".to_string(), info)]));
+
+        let manager = BackgroundProcessManager::new(temp_dir.clone());
+        assert_eq!(manager.get("server").unwrap().pid, pid);
+        assert!(manager.is_running("server"));
+
+        manager.stop("server", Duration::from_secs(5)).unwrap();
+        assert!(reaper.join().unwrap().is_ok());
+        assert!(!group_alive(pid));
+        let registry = fs::read_to_string(temp_dir.join(REGISTRY_FILE)).unwrap();
+        assert!(!registry.contains("server"));
+
+        let _ = fs::remove_dir_all(&temp_dir);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_stats_reports_usage() {
+        let temp_dir = std::env::temp_dir().join("g3_bg_test_stats");
+        let _ = fs::remove_dir_all(&temp_dir);
+        fs::create_dir_all(&temp_dir).unwrap();
+
+        let manager = BackgroundProcessManager::new(temp_dir.clone());
+        manager.start("idle", "sleep 10", &temp_dir).unwrap();
+
+        let stats = manager.stats("idle").unwrap();
+        assert!(stats.rss_bytes > 0);
+        assert!(stats.cpu_percent >= 0.0);
+        thread::sleep(Duration::from_millis(100));
+        assert!(manager.stats("idle").unwrap().cpu_time >= stats.cpu_time);
+        assert!(manager.stats("missing").is_err());
+
+        manager.cleanup();
+        let _ = fs::remove_dir_all(&temp_dir);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_memory_limit_stops_runaway_process() {
+        let temp_dir = std::env::temp_dir().join("g3_bg_test_memory_limit");
+        let _ = fs::remove_dir_all(&temp_dir);
+        fs::create_dir_all(&temp_dir).unwrap();
+
+        let manager = BackgroundProcessManager::new(temp_dir.clone());
+        let options = ProcessOptions {
+            limits: Some(ResourceLimits {
+                memory_bytes: Some(100 * 1024 * 1024),
+                cpu_cores: None,
+            }),
+            ..ProcessOptions::default()
+        };
+        // Buffers 300 MB in the shell itself
+        let info = manager
+            .start_with_options(
+                "hog",
+                "x=$(head -c 300000000 /dev/zero | tr '\\0' a); echo filled-$((1 + 1))",
+                &temp_dir,
+                options,
+            )
+            .unwrap();
+        assert!(info.limit_backend.is_some());
+
+        assert!(wait_until(Duration::from_secs(30), || !manager.is_running("hog")));
+        let log = fs::read_to_string(&info.log_file).unwrap();
+        assert!(log.contains("resource limits: memory 100 MiB"));
+        assert!(!log.contains("filled-2"));
+
+        manager.cleanup();
+        let _ = fs::remove_dir_all(&temp_dir);
+    }
+
+    #[test]
+    fn test_start_after_runs_once_after_delay() {
+        let temp_dir = std::env::temp_dir().join("g3_bg_test_start_after");
+        let _ = fs::remove_dir_all(&temp_dir);
+        fs::create_dir_all(&temp_dir).unwrap();
+
+        let manager = BackgroundProcessManager::new(temp_dir.clone());
+        let info = manager
+            .start_after(
+                "delayed",
+                "echo done > done.txt",
+                &temp_dir,
+                Duration::from_millis(500),
+            )
+            .unwrap();
+        assert_eq!(info.pid, 0);
+        assert_eq!(
+            info.schedule,
+            Some(Schedule::After(Duration::from_millis(500)))
+        );
+
+        let listed = manager.list();
+        assert_eq!(listed.len(), 1);
+        assert!(listed[0].next_run.is_some());
+        assert!(!manager.is_running("delayed"));
+        #[cfg(unix)]
+        assert!(manager.signal("delayed", SIGTERM).is_err());
+
+        assert!(wait_until(Duration::from_secs(10), || {
+            temp_dir.join("done.txt").exists()
+        }));
+        let info = manager.get("delayed").unwrap();
+        assert_eq!(info.runs, 1);
+        assert_ne!(info.pid, 0);
+        assert_eq!(info.next_run, None);
+
+        manager.cleanup();
+        let _ = fs::remove_dir_all(&temp_dir);
+    }
+
+    #[test]
+    fn test_start_every_runs_repeatedly() {
+        let temp_dir = std::env::temp_dir().join("g3_bg_test_start_every");
+        let _ = fs::remove_dir_all(&temp_dir);
+        fs::create_dir_all(&temp_dir).unwrap();
+
+        let manager = BackgroundProcessManager::new(temp_dir.clone());
+        manager
+            .start_every(
+                "periodic",
+                "echo tick >> ticks.txt",
+                &temp_dir,
+                Duration::from_millis(200),
+            )
+            .unwrap();
+
+        let ticks = temp_dir.join("ticks.txt");
+        assert!(wait_until(Duration::from_secs(10), || {
+            fs::read_to_string(&ticks)
+                .unwrap_or_default()
+                .lines()
+                .count()
+                >= 3
+        }));
+        let info = manager.get("periodic").unwrap();
+        assert!(info.runs >= 3);
+        assert!(info.next_run.is_some());
+        let log = fs::read_to_string(&info.log_file).unwrap();
+        assert!(log.contains("run 2"));
+
+        // Removing the task stops further runs
+        manager.stop("periodic", Duration::from_secs(1)).unwrap();
+        thread::sleep(Duration::from_millis(300));
+        let count = fs::read_to_string(&ticks).unwrap().lines().count();
+        thread::sleep(Duration::from_millis(500));
+        assert_eq!(fs::read_to_string(&ticks).unwrap().lines().count(), count);
+
+        assert!(manager
+            .start_every("zero", "true", &temp_dir, Duration::ZERO)
+            .is_err());
+
+        manager.cleanup();
+        let _ = fs::remove_dir_all(&temp_dir);
+    }
+
+    #[test]
+    fn test_stop_cancels_pending_task() {
+        let temp_dir = std::env::temp_dir().join("g3_bg_test_cancel_scheduled");
+        let _ = fs::remove_dir_all(&temp_dir);
+        fs::create_dir_all(&temp_dir).unwrap();
+
+        let manager = BackgroundProcessManager::new(temp_dir.clone());
+        manager
+            .start_after(
+                "pending",
+                "echo ran > ran.txt",
+                &temp_dir,
+                Duration::from_millis(300),
+            )
+            .unwrap();
+        manager.stop("pending", Duration::from_secs(1)).unwrap();
+        assert!(manager.get("pending").is_none());
+
+        thread::sleep(Duration::from_millis(800));
+        assert!(!temp_dir.join("ran.txt").exists());
+
+        manager.cleanup();
+        let _ = fs::remove_dir_all(&temp_dir);
+    }
+
+    #[test]
+    fn test_http_status_ok() {
+        assert!(http_status_ok("HTTP/1.1 200 OK"));
+        assert!(http_status_ok("HTTP/1.0 302 Found"));
+        assert!(!http_status_ok("HTTP/1.1 503 Service Unavailable"));
+        assert!(!http_status_ok("garbage"));
+    }
+
+    #[test]
+    fn test_options_from_tool_args() {
+        let options = ProcessOptions::from_tool_args(&serde_json::json!({
+            "health_check": {"http": "http://localhost:3000/health", "interval_secs": 2},
+            "restart": "on-failure",
+            "max_restarts": 5
+        }))
+        .unwrap();
+        let check = options.health_check.unwrap();
+        assert!(
+            matches!(check.check, HealthCheck::Http(ref url) if url == "http://localhost:3000/health")
+        );
+        assert_eq!(check.interval, Duration::from_secs(2));
+        assert_eq!(
+            options.restart,
+            RestartPolicy::OnFailure {
+                max_restarts: 5,
+                backoff: Duration::from_secs(1)
+            }
+        );
+
+        let with_env = ProcessOptions::from_tool_args(&serde_json::json!({
+            "env": {"NODE_ENV": "development", "PORT": "{PORT}", "WORKERS": 2},
+            "health_check": {"tcp_port": "{PORT}"}
+        }))
+        .unwrap();
+        assert_eq!(with_env.env["WORKERS"], "2");
+        assert!(with_env.uses_port());
+        assert!(matches!(
+            with_env.health_check.unwrap().check,
+            HealthCheck::TcpPort(0)
+        ));
+
+        let limited = ProcessOptions::from_tool_args(&serde_json::json!({
+            "memory_limit_mb": 512,
+            "cpu_limit": 1.5
+        }))
+        .unwrap();
+        assert_eq!(
+            limited.limits,
+            Some(ResourceLimits {
+                memory_bytes: Some(512 * 1024 * 1024),
+                cpu_cores: Some(1.5)
+            })
+        );
+        assert!(
+            ProcessOptions::from_tool_args(&serde_json::json!({"memory_limit_mb": 0})).is_err()
+        );
+
+        let defaults = ProcessOptions::from_tool_args(&serde_json::json!({})).unwrap();
+        assert!(defaults.health_check.is_none());
+        assert_eq!(defaults.restart, RestartPolicy::Never);
+
+        assert!(ProcessOptions::from_tool_args(&serde_json::json!({"restart": "always"})).is_err());
+        assert!(ProcessOptions::from_tool_args(
+            &serde_json::json!({"health_check": {"tcp_port": 70000}})
+        )
+        .is_err());
+    }
 }
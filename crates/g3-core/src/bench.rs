@@ -0,0 +1,313 @@
+//! Performance measurements of code search and diff application.
+//!
+//! Both `g3 bench` and the criterion benchmarks in `benches/` run on the
+//! same synthetic repository ([`generate_repo`]) and diff
+//! ([`synthetic_diff`]), so their numbers can be compared. [`run`] measures
+//! tree-sitter search throughput over the repository and the latency of
+//! applying the diff verbatim and fuzzily, and returns a [`BenchReport`]
+//! that serializes to JSON for tracking regressions across releases.
+
+use anyhow::{Context, Result};
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+use crate::code_search::{execute_code_search, CodeSearchRequest, SearchSpec};
+use crate::utils::{apply_unified_diff_with_options, DiffApplyOptions};
+
+/// Functions per generated file
+pub const FUNCTIONS_PER_FILE: usize = 40;
+
+/// Lines changed by [`synthetic_diff`], one hunk each
+pub const DIFF_HUNKS: usize = 20;
+
+/// What to measure, and how much of it
+#[derive(Debug, Clone)]
+pub struct BenchOptions {
+    /// Rust files in the synthetic repository
+    pub files: usize,
+    /// Timed runs of each benchmark, after one warm-up run
+    pub iterations: usize,
+}
+
+impl Default for BenchOptions {
+    fn default() -> Self {
+        Self {
+            files: 500,
+            iterations: 10,
+        }
+    }
+}
+
+/// Timings of one benchmark
+#[derive(Debug, Clone, Serialize)]
+pub struct BenchResult {
+    pub name: String,
+    pub iterations: usize,
+    pub mean_ms: f64,
+    pub median_ms: f64,
+    pub min_ms: f64,
+    pub max_ms: f64,
+    /// Units of `throughput_unit` per second, at the mean
+    pub throughput: f64,
+    pub throughput_unit: &'static str,
+}
+
+impl BenchResult {
+    fn new(name: &str, times: &[Duration], units: f64, throughput_unit: &'static str) -> Self {
+        let mut ms: Vec<f64> = times.iter().map(|t| t.as_secs_f64() * 1000.0).collect();
+        ms.sort_by(f64::total_cmp);
+        let mean_ms = ms.iter().sum::<f64>() / ms.len().max(1) as f64;
+        Self {
+            name: name.to_string(),
+            iterations: ms.len(),
+            mean_ms,
+            median_ms: ms.get(ms.len() / 2).copied().unwrap_or_default(),
+            min_ms: ms.first().copied().unwrap_or_default(),
+            max_ms: ms.last().copied().unwrap_or_default(),
+            throughput: if mean_ms > 0.0 {
+                units / (mean_ms / 1000.0)
+            } else {
+                0.0
+            },
+            throughput_unit,
+        }
+    }
+}
+
+/// Everything `g3 bench` measured
+#[derive(Debug, Clone, Serialize)]
+pub struct BenchReport {
+    pub version: &'static str,
+    pub timestamp: String,
+    pub files: usize,
+    pub lines: usize,
+    pub results: Vec<BenchResult>,
+}
+
+/// Size of a generated repository
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RepoStats {
+    pub files: usize,
+    pub lines: usize,
+}
+
+/// Write `files` Rust files of [`FUNCTIONS_PER_FILE`] functions each under
+/// `dir`, spread over subdirectories like a real crate
+pub fn generate_repo(dir: &Path, files: usize) -> Result<RepoStats> {
+    let mut lines = 0;
+    for index in 0..files {
+        let module_dir = dir.join(format!("module_{}", index / 50));
+        std::fs::create_dir_all(&module_dir)
+            .with_context(|| format!("Failed to create {}", module_dir.display()))?;
+        let source = synthetic_source(index, FUNCTIONS_PER_FILE);
+        lines += source.lines().count();
+        std::fs::write(module_dir.join(format!("file_{}.rs", index)), source)?;
+    }
+    Ok(RepoStats { files, lines })
+}
+
+/// A Rust file of `functions` small functions and a struct
+pub fn synthetic_source(index: usize, functions: usize) -> String {
+    let mut source = format!(
+        "//! Generated module {0}\n\nuse std::collections::HashMap;\n\n\
+         pub struct Record{0} {{\n    pub id: u64,\n    pub name: String,\n}}\n",
+        index
+    );
+    for function in 0..functions {
+        source.push_str(&format!(
+            "\n/// Computes value {1} of module {0}\n\
+             pub fn compute_{0}_{1}(input: &HashMap<String, u64>) -> u64 {{\n    \
+             let base = input.get(\"key_{1}\").copied().unwrap_or({1});\n    \
+             let scaled = base * {2};\n    \
+             scaled + {1}\n}}\n",
+            index,
+            function,
+            function % 7 + 1
+        ));
+    }
+    source
+}
+
+/// A unified diff changing [`DIFF_HUNKS`] functions of `source`, spread
+/// over the file; with `reindent`, its context lines are indented
+/// differently from the file, so only fuzzy matching applies it
+pub fn synthetic_diff(source: &str, reindent: bool) -> String {
+    let lines: Vec<&str> = source.lines().collect();
+    let targets: Vec<usize> = lines
+        .iter()
+        .enumerate()
+        .filter(|(_, line)| line.trim_start().starts_with("let scaled"))
+        .map(|(number, _)| number)
+        .collect();
+    let step = (targets.len() / DIFF_HUNKS).max(1);
+
+    let mut diff = String::new();
+    for &number in targets.iter().step_by(step).take(DIFF_HUNKS) {
+        let context = |line: &str| {
+            if reindent {
+                format!(" {}", line.trim_start())
+            } else {
+                format!(" {}", line)
+            }
+        };
+        diff.push_str(&format!("@@ -{},3 +{},3 @@\n", number, number));
+        diff.push_str(&context(lines[number - 1]));
+        diff.push('\n');
+        diff.push_str(&format!("-{}\n", lines[number]));
+        diff.push_str(&format!("+{}\n", lines[number].replace(" * ", " * 2 * ")));
+        diff.push_str(&context(lines[number + 1]));
+        diff.push('\n');
+    }
+    diff
+}
+
+/// The code_search request [`run`] times: every function in `dir`
+pub fn search_request(dir: &Path) -> CodeSearchRequest {
+    CodeSearchRequest {
+        searches: vec![SearchSpec {
+            name: "functions".to_string(),
+            query: "(function_item name: (identifier) @name)".to_string(),
+            language: "rust".to_string(),
+            paths: vec![dir.to_string_lossy().into_owned()],
+            context_lines: 0,
+        }],
+        max_concurrency: 1,
+        // Stopping at a match limit would skip files
+        max_matches_per_search: usize::MAX,
+    }
+}
+
+/// Run the benchmarks on a synthetic repository in a temporary directory
+pub async fn run(options: &BenchOptions) -> Result<BenchReport> {
+    let dir = scratch_dir();
+    let result = run_in(&dir, options).await;
+    let _ = std::fs::remove_dir_all(&dir);
+    result
+}
+
+fn scratch_dir() -> PathBuf {
+    std::env::temp_dir().join(format!("g3-bench-{}", std::process::id()))
+}
+
+async fn run_in(dir: &Path, options: &BenchOptions) -> Result<BenchReport> {
+    let stats = generate_repo(dir, options.files)?;
+    let mut results = Vec::new();
+
+    let request = search_request(dir);
+    let mut times = Vec::new();
+    for iteration in 0..=options.iterations {
+        let start = Instant::now();
+        let response = execute_code_search(request.clone()).await?;
+        // The first run warms the file system cache
+        if iteration > 0 {
+            times.push(start.elapsed());
+        }
+        if let Some(error) = response.searches.first().and_then(|s| s.error.as_ref()) {
+            anyhow::bail!("Benchmark search failed: {}", error);
+        }
+    }
+    results.push(BenchResult::new(
+        "code_search",
+        &times,
+        stats.files as f64,
+        "files/s",
+    ));
+
+    // One large file: the size the agent's diffs are hardest to place in
+    let source = synthetic_source(0, FUNCTIONS_PER_FILE * 25);
+    let source_lines = source.lines().count() as f64;
+    for (name, reindent, diff_options) in [
+        ("diff_apply_exact", false, DiffApplyOptions::exact()),
+        ("diff_apply_fuzzy", true, DiffApplyOptions::default()),
+    ] {
+        let diff = synthetic_diff(&source, reindent);
+        let mut times = Vec::new();
+        for iteration in 0..=options.iterations {
+            let start = Instant::now();
+            apply_unified_diff_with_options(&source, &diff, None, None, &diff_options)
+                .with_context(|| format!("Benchmark {} failed", name))?;
+            if iteration > 0 {
+                times.push(start.elapsed());
+            }
+        }
+        results.push(BenchResult::new(name, &times, source_lines, "lines/s"));
+    }
+
+    Ok(BenchReport {
+        version: env!("CARGO_PKG_VERSION"),
+        timestamp: chrono::Utc::now().to_rfc3339(),
+        files: stats.files,
+        lines: stats.lines,
+        results,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_synthetic_diff_applies() {
+        let source = synthetic_source(3, 100);
+        let diff = synthetic_diff(&source, false);
+        assert_eq!(diff.matches("@@ -").count(), DIFF_HUNKS);
+
+        let applied =
+            apply_unified_diff_with_options(&source, &diff, None, None, &DiffApplyOptions::exact())
+                .unwrap();
+        assert_eq!(applied.content.matches(" * 2 * ").count(), DIFF_HUNKS);
+
+        let reindented = synthetic_diff(&source, true);
+        assert!(apply_unified_diff_with_options(
+            &source,
+            &reindented,
+            None,
+            None,
+            &DiffApplyOptions::exact()
+        )
+        .is_err());
+        assert!(apply_unified_diff_with_options(
+            &source,
+            &reindented,
+            None,
+            None,
+            &DiffApplyOptions::default()
+        )
+        .is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_run_reports_every_benchmark() {
+        let dir = TempDir::new().unwrap();
+        let report = run_in(
+            dir.path(),
+            &BenchOptions {
+                files: 3,
+                iterations: 1,
+            },
+        )
+        .await
+        .unwrap();
+        assert_eq!(report.files, 3);
+        let names: Vec<&str> = report.results.iter().map(|r| r.name.as_str()).collect();
+        assert_eq!(
+            names,
+            ["code_search", "diff_apply_exact", "diff_apply_fuzzy"]
+        );
+        assert!(report.results.iter().all(|r| r.iterations == 1));
+        let json = serde_json::to_value(&report).unwrap();
+        assert_eq!(json["results"][0]["throughput_unit"], "files/s");
+    }
+
+    #[test]
+    fn test_bench_result_stats() {
+        let times = [30, 10, 20].map(Duration::from_millis);
+        let result = BenchResult::new("x", &times, 100.0, "files/s");
+        assert_eq!(result.median_ms, 20.0);
+        assert_eq!(result.min_ms, 10.0);
+        assert_eq!(result.max_ms, 30.0);
+        assert!((result.throughput - 5000.0).abs() < 1e-6);
+    }
+}
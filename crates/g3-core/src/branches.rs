@@ -0,0 +1,326 @@
+//! Named branches of a conversation, for trying an alternative and coming
+//! back.
+//!
+//! `/branch new <name>` forks the current context window: the conversation
+//! so far is kept on the branch it was on, and the new branch continues from
+//! the same point. `/branch switch <name>` puts the current conversation away
+//! and brings another one back; `/branch discard <name>` drops one for good.
+//! Only the current branch lives in the [`ContextWindow`]; the others are
+//! kept here, and all of them are journaled (see
+//! [`crate::session_journal::JournalEntry::Branches`]) so `g3 --resume`
+//! brings them back.
+
+use anyhow::{bail, Result};
+use serde::{Deserialize, Serialize};
+use std::fmt::Write;
+use std::str::FromStr;
+
+use crate::session_journal::JournalMessage;
+use crate::ContextWindow;
+
+/// The branch every session starts on
+pub const MAIN_BRANCH: &str = "main";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BranchAction {
+    New,
+    Switch,
+    Discard,
+}
+
+impl FromStr for BranchAction {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "new" => Ok(BranchAction::New),
+            "switch" => Ok(BranchAction::Switch),
+            "discard" => Ok(BranchAction::Discard),
+            _ => bail!("Unknown branch action '{}'. Use new, switch or discard", s),
+        }
+    }
+}
+
+/// One line of the conversation
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Branch {
+    pub name: String,
+    /// The branch this one was forked from; None for [`MAIN_BRANCH`]
+    pub parent: Option<String>,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+    /// Messages shared with the parent when the branch was forked
+    pub fork_point: usize,
+    /// The conversation, while the branch isn't the current one
+    #[serde(default)]
+    pub messages: Vec<JournalMessage>,
+    #[serde(default)]
+    pub used_tokens: u32,
+}
+
+impl Branch {
+    fn new(name: &str, parent: Option<String>, fork_point: usize) -> Self {
+        Self {
+            name: name.to_string(),
+            parent,
+            created_at: chrono::Utc::now(),
+            fork_point,
+            messages: Vec::new(),
+            used_tokens: 0,
+        }
+    }
+}
+
+/// A branch, as listed
+#[derive(Debug, Clone, PartialEq)]
+pub struct BranchSummary {
+    pub name: String,
+    pub parent: Option<String>,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+    pub fork_point: usize,
+    pub messages: usize,
+    pub current: bool,
+}
+
+/// The branches of a session
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Branches {
+    pub current: String,
+    /// Every branch, the current one included, in the order they were made
+    pub branches: Vec<Branch>,
+}
+
+impl Default for Branches {
+    fn default() -> Self {
+        Self {
+            current: MAIN_BRANCH.to_string(),
+            branches: vec![Branch::new(MAIN_BRANCH, None, 0)],
+        }
+    }
+}
+
+impl Branches {
+    /// Fork the conversation in `context` into a new branch `name` and make
+    /// it the current one; `context` is left as it is
+    pub fn fork(&mut self, name: &str, context: &ContextWindow) -> Result<String> {
+        if name.is_empty() || name.chars().any(char::is_whitespace) {
+            bail!("Branch names can't be empty or contain spaces");
+        }
+        if self.position(name).is_some() {
+            bail!("Branch '{}' already exists", name);
+        }
+        self.save_current(context);
+        let fork_point = context.conversation_history.len();
+        self.branches
+            .push(Branch::new(name, Some(self.current.clone()), fork_point));
+        let parent = std::mem::replace(&mut self.current, name.to_string());
+        Ok(format!(
+            "🌿 Forked branch '{}' from '{}' at message {}",
+            name, parent, fork_point
+        ))
+    }
+
+    /// Put the conversation in `context` away on the current branch and
+    /// load branch `name`'s into it
+    pub fn switch(&mut self, name: &str, context: &mut ContextWindow) -> Result<String> {
+        if name == self.current {
+            bail!("Already on branch '{}'", name);
+        }
+        let Some(index) = self.position(name) else {
+            bail!(
+                "No branch '{}'. Branches: {}",
+                name,
+                self.names().join(", ")
+            );
+        };
+        self.save_current(context);
+
+        let branch = &mut self.branches[index];
+        let messages = std::mem::take(&mut branch.messages);
+        context.pinned_message_ids = messages
+            .iter()
+            .filter(|m| m.pinned && !m.id.is_empty())
+            .map(|m| m.id.clone())
+            .collect();
+        context.conversation_history = messages
+            .into_iter()
+            .map(JournalMessage::into_message)
+            .collect();
+        context.used_tokens = branch.used_tokens;
+        self.current = name.to_string();
+        Ok(format!(
+            "🌿 Switched to branch '{}' ({} messages)",
+            name,
+            context.conversation_history.len()
+        ))
+    }
+
+    /// Drop branch `name`, which can't be the current one. Branches forked
+    /// from it are kept, as forks of its parent.
+    pub fn discard(&mut self, name: &str) -> Result<String> {
+        if name == self.current {
+            bail!(
+                "Can't discard the current branch '{}'; switch to another first",
+                name
+            );
+        }
+        let Some(index) = self.position(name) else {
+            bail!("No branch '{}'", name);
+        };
+        let discarded = self.branches.remove(index);
+        for branch in &mut self.branches {
+            if branch.parent.as_deref() == Some(name) {
+                branch.parent = discarded.parent.clone();
+            }
+        }
+        Ok(format!(
+            "🗑️ Discarded branch '{}' ({} messages)",
+            name,
+            discarded.messages.len()
+        ))
+    }
+
+    /// Every branch, the current one measured in `context`
+    pub fn summaries(&self, context: &ContextWindow) -> Vec<BranchSummary> {
+        self.branches
+            .iter()
+            .map(|branch| {
+                let current = branch.name == self.current;
+                BranchSummary {
+                    name: branch.name.clone(),
+                    parent: branch.parent.clone(),
+                    created_at: branch.created_at,
+                    fork_point: branch.fork_point,
+                    messages: if current {
+                        context.conversation_history.len()
+                    } else {
+                        branch.messages.len()
+                    },
+                    current,
+                }
+            })
+            .collect()
+    }
+
+    fn names(&self) -> Vec<&str> {
+        self.branches.iter().map(|b| b.name.as_str()).collect()
+    }
+
+    fn position(&self, name: &str) -> Option<usize> {
+        self.branches.iter().position(|b| b.name == name)
+    }
+
+    fn save_current(&mut self, context: &ContextWindow) {
+        if let Some(index) = self.position(&self.current) {
+            let branch = &mut self.branches[index];
+            branch.messages = context
+                .conversation_history
+                .iter()
+                .map(|m| JournalMessage::new(m, context))
+                .collect();
+            branch.used_tokens = context.used_tokens;
+        }
+    }
+}
+
+/// The branches as a list, the current one marked with `*`
+pub fn render(summaries: &[BranchSummary]) -> String {
+    let mut out = String::new();
+    for summary in summaries {
+        let _ = write!(
+            out,
+            "{} {} - {} messages",
+            if summary.current { "*" } else { " " },
+            summary.name,
+            summary.messages
+        );
+        if let Some(parent) = &summary.parent {
+            let _ = write!(
+                out,
+                ", forked from {} at message {}",
+                parent, summary.fork_point
+            );
+        }
+        let _ = writeln!(
+            out,
+            " ({})",
+            summary
+                .created_at
+                .with_timezone(&chrono::Local)
+                .format("%H:%M:%S")
+        );
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use g3_providers::{Message, MessageRole};
+
+    fn context() -> ContextWindow {
+        let mut context = ContextWindow::new(10_000);
+        context.add_pinned_message(Message::new(MessageRole::System, "You are G3".to_string()));
+        context.add_message(Message::new(
+            MessageRole::User,
+            "Fix the parser".to_string(),
+        ));
+        context
+    }
+
+    #[test]
+    fn test_fork_switch_and_discard() {
+        let mut context = context();
+        let mut branches = Branches::default();
+
+        branches.fork("retry", &context).unwrap();
+        assert_eq!(branches.current, "retry");
+        assert!(branches.fork("retry", &context).is_err());
+        assert!(branches.fork("two words", &context).is_err());
+
+        // Try an alternative on the new branch
+        context.add_message(Message::new(
+            MessageRole::User,
+            "Rewrite it with nom instead".to_string(),
+        ));
+        assert_eq!(context.conversation_history.len(), 3);
+
+        branches.switch(MAIN_BRANCH, &mut context).unwrap();
+        assert_eq!(context.conversation_history.len(), 2);
+        assert!(context.is_pinned(&context.conversation_history[0]));
+        assert!(branches.switch(MAIN_BRANCH, &mut context).is_err());
+        assert!(branches.switch("missing", &mut context).is_err());
+
+        let summaries = branches.summaries(&context);
+        assert_eq!(summaries.len(), 2);
+        assert!(summaries[0].current);
+        assert_eq!(summaries[1].messages, 3);
+        assert_eq!(summaries[1].fork_point, 2);
+        let list = render(&summaries);
+        assert!(list.starts_with("* main - 2 messages"));
+        assert!(list.contains("  retry - 3 messages, forked from main at message 2"));
+
+        branches.switch("retry", &mut context).unwrap();
+        assert_eq!(
+            context.conversation_history[2].content,
+            "Rewrite it with nom instead"
+        );
+        assert!(branches.discard("retry").is_err());
+        branches.switch(MAIN_BRANCH, &mut context).unwrap();
+        branches.discard("retry").unwrap();
+        assert_eq!(branches.summaries(&context).len(), 1);
+    }
+
+    #[test]
+    fn test_discard_keeps_forks_of_the_branch() {
+        let mut context = context();
+        let mut branches = Branches::default();
+        branches.fork("a", &context).unwrap();
+        branches.fork("b", &context).unwrap();
+        branches.switch(MAIN_BRANCH, &mut context).unwrap();
+
+        branches.discard("a").unwrap();
+        let b = &branches.branches[branches.position("b").unwrap()];
+        assert_eq!(b.parent.as_deref(), Some(MAIN_BRANCH));
+        assert!("merge".parse::<BranchAction>().is_err());
+    }
+}
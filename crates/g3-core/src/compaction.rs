@@ -0,0 +1,431 @@
+//! Hierarchical compaction of old conversation history.
+//!
+//! Once the context window passes the compaction threshold, the older half of
+//! the conversation is condensed into a system note: one line per user
+//! message, assistant turn and tool call, with a short excerpt of each tool
+//! result. Notes from earlier compactions are kept, and when there are more
+//! than [`MAX_NOTES`] the two oldest are merged into one higher-level note that
+//! keeps only the step lines, so old history shrinks in layers instead of
+//! growing without bound.
+//!
+//! System messages (system prompt, README, summaries) are never compacted,
+//! and the latest TODO list in the compacted range is carried into the note in
+//! full.
+
+use g3_providers::{Message, MessageRole};
+
+/// First words of every compaction note
+pub const NOTE_HEADER: &str = "Compacted history";
+
+/// Notes kept before the oldest two are merged
+const MAX_NOTES: usize = 3;
+
+/// Fewest messages worth compacting
+const MIN_MESSAGES: usize = 4;
+
+/// Step lines kept in a merged note
+const MAX_NOTE_LINES: usize = 60;
+
+const STEP_CHARS: usize = 160;
+const RESULT_CHARS: usize = 120;
+const ARG_CHARS: usize = 60;
+
+const TODO_HEADER: &str = "Latest TODO list:";
+
+/// Result of [`compact`]
+#[derive(Debug)]
+pub struct Compaction {
+    pub history: Vec<Message>,
+    /// Conversation messages folded into the new note
+    pub messages_compacted: usize,
+    /// Older notes merged into higher-level ones
+    pub notes_merged: usize,
+}
+
+/// Whether a message is a note written by [`compact`]
+pub fn is_note(message: &Message) -> bool {
+    matches!(message.role, MessageRole::System) && message.content.starts_with(NOTE_HEADER)
+}
+
+/// Condense the older half of `history` into a note. Returns `None` when
+/// there is too little conversation to be worth compacting.
+pub fn compact(history: &[Message]) -> Option<Compaction> {
+    // The leading system messages (system prompt, README, earlier notes) stay on top
+    let start = history
+        .iter()
+        .position(|m| !matches!(m.role, MessageRole::System))?;
+    let conversation: Vec<usize> = (start..history.len())
+        .filter(|&i| !matches!(history[i].role, MessageRole::System))
+        .collect();
+    if conversation.len() < MIN_MESSAGES * 2 {
+        return None;
+    }
+
+    // End the range just before a user message (not a tool result), so
+    // what's left of the conversation still opens with the user
+    let mut end = conversation[conversation.len() / 2];
+    while end < history.len() && !is_user_turn(&history[end]) {
+        end += 1;
+    }
+    if end >= history.len() {
+        return None;
+    }
+
+    let range = &history[start..end];
+    let (note, messages_compacted) = digest(range);
+    if messages_compacted < MIN_MESSAGES {
+        return None;
+    }
+
+    let mut compacted = history[..start].to_vec();
+    compacted.push(note);
+    compacted.extend(
+        range
+            .iter()
+            .filter(|m| matches!(m.role, MessageRole::System))
+            .cloned(),
+    );
+    compacted.extend_from_slice(&history[end..]);
+
+    let notes_merged = merge_notes(&mut compacted);
+    Some(Compaction {
+        history: compacted,
+        messages_compacted,
+        notes_merged,
+    })
+}
+
+fn is_user_turn(message: &Message) -> bool {
+    matches!(message.role, MessageRole::User) && !message.content.starts_with("Tool result:")
+}
+
+/// Build a level-1 note from the conversation messages in `messages`
+fn digest(messages: &[Message]) -> (Message, usize) {
+    let mut lines = Vec::new();
+    let mut todo = None;
+    let mut last_tool: Option<String> = None;
+    let mut count = 0;
+
+    for message in messages {
+        match message.role {
+            MessageRole::System => continue,
+            MessageRole::Assistant => {
+                let (text, tool_call) = split_tool_call(&message.content);
+                if !text.is_empty() {
+                    lines.push(format!("- Assistant: {}", excerpt(text, STEP_CHARS)));
+                }
+                last_tool = None;
+                if let Some((tool, args)) = tool_call {
+                    if tool == "todo_write" {
+                        if let Some(content) = args.get("content").and_then(|c| c.as_str()) {
+                            todo = Some(content.to_string());
+                        }
+                    }
+                    // The TODO list itself is carried separately
+                    if tool.starts_with("todo_") {
+                        lines.push(format!("- Called {}", tool));
+                    } else {
+                        lines.push(format!("- Called {}({})", tool, summarize_args(&args)));
+                    }
+                    last_tool = Some(tool);
+                }
+            }
+            MessageRole::User => match message.content.strip_prefix("Tool result:") {
+                Some(result) if last_tool.as_deref() == Some("todo_read") => {
+                    todo = Some(result.trim().to_string());
+                }
+                Some(result) => lines.push(format!(
+                    "  → {} ({} lines)",
+                    excerpt(result, RESULT_CHARS),
+                    result.trim().lines().count()
+                )),
+                None => lines.push(format!("- User: {}", excerpt(&message.content, STEP_CHARS))),
+            },
+        }
+        count += 1;
+    }
+
+    (note(1, count, &lines, todo.as_deref()), count)
+}
+
+fn note(level: usize, messages: usize, lines: &[String], todo: Option<&str>) -> Message {
+    let mut content = format!(
+        "{} (level {}, {} messages). Earlier steps of this conversation, condensed:\n{}",
+        NOTE_HEADER,
+        level,
+        messages,
+        lines.join("\n")
+    );
+    if let Some(todo) = todo {
+        content.push_str(&format!("\n\n{}\n{}", TODO_HEADER, todo));
+    }
+    Message::new(MessageRole::System, content)
+}
+
+/// A note's level, message count, step lines and TODO list
+struct ParsedNote<'a> {
+    level: usize,
+    messages: usize,
+    lines: Vec<&'a str>,
+    todo: Option<&'a str>,
+}
+
+fn parse_note(content: &str) -> ParsedNote<'_> {
+    let (body, todo) = match content.split_once(&format!("\n\n{}\n", TODO_HEADER)) {
+        Some((body, todo)) => (body, Some(todo)),
+        None => (content, None),
+    };
+    let mut lines = body.lines();
+    let header = lines.next().unwrap_or_default();
+    let numbers: Vec<usize> = header
+        .split(|c: char| !c.is_ascii_digit())
+        .filter_map(|n| n.parse().ok())
+        .take(2)
+        .collect();
+    ParsedNote {
+        level: numbers.first().copied().unwrap_or(1),
+        messages: numbers.get(1).copied().unwrap_or(0),
+        lines: lines.collect(),
+        todo,
+    }
+}
+
+/// Merge the oldest notes pairwise until at most [`MAX_NOTES`] remain.
+/// Returns how many notes were merged away.
+fn merge_notes(history: &mut Vec<Message>) -> usize {
+    let mut merged = 0;
+    loop {
+        let notes: Vec<usize> = (0..history.len())
+            .filter(|&i| is_note(&history[i]))
+            .collect();
+        if notes.len() <= MAX_NOTES {
+            return merged;
+        }
+
+        let older = parse_note(&history[notes[0]].content);
+        let newer = parse_note(&history[notes[1]].content);
+
+        // Keep the steps and drop the tool result excerpts
+        let steps: Vec<&str> = older
+            .lines
+            .iter()
+            .chain(&newer.lines)
+            .copied()
+            .filter(|line| line.starts_with("- "))
+            .collect();
+        let lines: Vec<String> = if steps.len() > MAX_NOTE_LINES {
+            let head = MAX_NOTE_LINES / 4;
+            let tail = MAX_NOTE_LINES - head - 1;
+            steps[..head]
+                .iter()
+                .map(|line| line.to_string())
+                .chain(std::iter::once(format!(
+                    "- … {} more steps",
+                    steps.len() - head - tail
+                )))
+                .chain(
+                    steps[steps.len() - tail..]
+                        .iter()
+                        .map(|line| line.to_string()),
+                )
+                .collect()
+        } else {
+            steps.iter().map(|line| line.to_string()).collect()
+        };
+
+        let merged_note = note(
+            older.level.max(newer.level) + 1,
+            older.messages + newer.messages,
+            &lines,
+            newer.todo.or(older.todo),
+        );
+        history[notes[0]] = merged_note;
+        history.remove(notes[1]);
+        merged += 1;
+    }
+}
+
+/// Split an assistant message into its text and its JSON tool call, if any
+fn split_tool_call(content: &str) -> (&str, Option<(String, serde_json::Value)>) {
+    let Some(start) = content.find(r#"{"tool""#) else {
+        return (content.trim(), None);
+    };
+    let parsed = serde_json::Deserializer::from_str(&content[start..])
+        .into_iter::<serde_json::Value>()
+        .next()
+        .and_then(|value| value.ok());
+    match parsed {
+        Some(value) => {
+            let tool = value["tool"].as_str().unwrap_or("unknown").to_string();
+            let args = value.get("args").cloned().unwrap_or_default();
+            (content[..start].trim(), Some((tool, args)))
+        }
+        None => (content.trim(), None),
+    }
+}
+
+/// Short `key: value` list of a tool call's arguments
+fn summarize_args(args: &serde_json::Value) -> String {
+    let Some(object) = args.as_object() else {
+        return String::new();
+    };
+    object
+        .iter()
+        .take(3)
+        .map(|(key, value)| {
+            let value = match value {
+                serde_json::Value::String(s) => excerpt(s, ARG_CHARS),
+                other => excerpt(&other.to_string(), ARG_CHARS),
+            };
+            format!("{}: {}", key, value)
+        })
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// The first non-empty line of `text`, cut to `max_chars`
+fn excerpt(text: &str, max_chars: usize) -> String {
+    let line = text
+        .lines()
+        .map(str::trim)
+        .find(|line| !line.is_empty())
+        .unwrap_or_default();
+    match line.char_indices().nth(max_chars) {
+        Some((end, _)) => format!("{}…", &line[..end]),
+        None => line.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn message(role: MessageRole, content: &str) -> Message {
+        Message::new(role, content.to_string())
+    }
+
+    /// System prompt and README, then `turns` tool-using exchanges
+    fn history(turns: usize) -> Vec<Message> {
+        let mut history = vec![
+            message(MessageRole::System, "You are G3"),
+            message(MessageRole::System, "Project README: demo"),
+        ];
+        for turn in 0..turns {
+            history.push(message(
+                MessageRole::User,
+                &format!("Task {}: fix the parser", turn),
+            ));
+            history.push(message(
+                MessageRole::Assistant,
+                &format!(
+                    "Reading the file.\n\n{{\"tool\": \"read_file\", \"args\": {{\"file_path\": \"src/parser_{}.rs\"}}}}",
+                    turn
+                ),
+            ));
+            history.push(message(
+                MessageRole::User,
+                &format!(
+                    "Tool result: fn parse() {{\n{}\n}}",
+                    "    // body\n".repeat(50)
+                ),
+            ));
+        }
+        history
+    }
+
+    #[test]
+    fn test_compacts_older_half_into_note() {
+        let original = history(6);
+        let compaction = compact(&original).unwrap();
+        let compacted = &compaction.history;
+
+        // System prompt and README untouched, note right after them
+        assert_eq!(compacted[0].content, "You are G3");
+        assert_eq!(compacted[1].content, "Project README: demo");
+        assert!(is_note(&compacted[2]));
+        assert_eq!(compaction.messages_compacted, 9);
+        assert_eq!(compaction.notes_merged, 0);
+
+        let note = &compacted[2].content;
+        assert!(note.starts_with("Compacted history (level 1, 9 messages)"));
+        assert!(note.contains("- User: Task 0: fix the parser"));
+        assert!(note.contains("- Assistant: Reading the file."));
+        assert!(note.contains("- Called read_file(file_path: src/parser_0.rs)"));
+        assert!(note.contains("  → fn parse() { (53 lines)"));
+
+        // The newer half is kept verbatim and still opens with the user
+        assert!(matches!(compacted[3].role, MessageRole::User));
+        assert_eq!(compacted[3].content, "Task 3: fix the parser");
+        assert_eq!(compacted.len(), 3 + 9);
+    }
+
+    #[test]
+    fn test_too_little_history_is_left_alone() {
+        assert!(compact(&history(2)).is_none());
+    }
+
+    #[test]
+    fn test_latest_todo_list_is_kept_in_full() {
+        let mut original = history(1);
+        original.push(message(
+            MessageRole::Assistant,
+            r#"{"tool": "todo_write", "args": {"content": "- [ ] old plan"}}"#,
+        ));
+        original.push(message(
+            MessageRole::User,
+            "Tool result: ✅ TODO list updated",
+        ));
+        original.push(message(
+            MessageRole::Assistant,
+            r#"{"tool": "todo_read", "args": {}}"#,
+        ));
+        original.push(message(
+            MessageRole::User,
+            "Tool result: - [x] parse headers\n- [ ] parse body",
+        ));
+        original.extend(history(3).into_iter().skip(2));
+
+        let compaction = compact(&original).unwrap();
+        let note = &compaction.history[2].content;
+        assert!(note.ends_with("Latest TODO list:\n- [x] parse headers\n- [ ] parse body"));
+        assert!(!note.contains("old plan"));
+    }
+
+    #[test]
+    fn test_notes_merge_into_higher_levels() {
+        let mut current = history(6);
+        let mut merged = 0;
+        for round in 0..4 {
+            // Keep the conversation growing between compactions
+            current.extend(history(6).into_iter().skip(2));
+            let compaction = compact(&current).unwrap();
+            merged += compaction.notes_merged;
+            current = compaction.history;
+            assert!(
+                current.iter().filter(|m| is_note(m)).count() <= MAX_NOTES,
+                "round {}",
+                round
+            );
+        }
+        assert_eq!(merged, 1);
+
+        let levels: Vec<usize> = current
+            .iter()
+            .filter(|m| is_note(m))
+            .map(|m| parse_note(&m.content).level)
+            .collect();
+        assert_eq!(levels, vec![2, 1, 1]);
+
+        // Merged notes keep the steps but drop the tool result excerpts
+        let merged_note = &current[2].content;
+        assert!(merged_note.contains("- Called read_file"));
+        assert!(!merged_note.contains("→"));
+        assert_eq!(current[0].content, "You are G3");
+    }
+
+    #[test]
+    fn test_excerpt_cuts_on_char_boundary() {
+        assert_eq!(excerpt("\n  héllo wörld\nsecond", 5), "héllo…");
+        assert_eq!(excerpt("short", 10), "short");
+    }
+}
@@ -0,0 +1,296 @@
+//! What is taking up the context window, message by message.
+//!
+//! `/context` and `g3 context` list every message in the history with its
+//! role, estimated tokens, age and pin, and let the user pin, unpin or evict
+//! one by its number in the list or its id. Pinned messages survive
+//! retention and compaction; evicting drops a message for good, so pinned
+//! ones must be unpinned first.
+//!
+//! Message ids start with the local time the message was created (`HHMMSS`),
+//! which is where ages come from; like the ids, they wrap after a day.
+
+use anyhow::{bail, Context, Result};
+use chrono::{NaiveTime, Timelike};
+use g3_providers::MessageRole;
+use std::fmt::Write;
+use std::str::FromStr;
+use std::time::Duration;
+
+use crate::paths::get_session_journal_file;
+use crate::session_journal::SessionJournal;
+use crate::ContextWindow;
+
+/// Characters of each message shown in the list
+const PREVIEW_CHARS: usize = 60;
+
+/// One message of the history, as listed
+#[derive(Debug, Clone, PartialEq)]
+pub struct ContextEntry {
+    /// Position in the history, from 1
+    pub number: usize,
+    pub id: String,
+    pub role: MessageRole,
+    pub tokens: u32,
+    pub age: Option<Duration>,
+    pub pinned: bool,
+    /// Start of the message's first non-empty line
+    pub preview: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ContextAction {
+    Pin,
+    Unpin,
+    Evict,
+}
+
+impl FromStr for ContextAction {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "pin" => Ok(ContextAction::Pin),
+            "unpin" => Ok(ContextAction::Unpin),
+            "evict" => Ok(ContextAction::Evict),
+            _ => bail!("Unknown context action '{}'. Use pin, unpin or evict", s),
+        }
+    }
+}
+
+/// List the messages in `context`, with ages as of `now`
+pub fn entries(context: &ContextWindow, now: NaiveTime) -> Vec<ContextEntry> {
+    context
+        .conversation_history
+        .iter()
+        .enumerate()
+        .map(|(index, message)| ContextEntry {
+            number: index + 1,
+            id: message.id.clone(),
+            role: message.role.clone(),
+            tokens: context.count_tokens(&message.content),
+            age: message_age(&message.id, now),
+            pinned: context.is_pinned(message),
+            preview: preview(&message.content),
+        })
+        .collect()
+}
+
+/// How long ago the message with id `id` was created; None for ids without
+/// a time
+pub fn message_age(id: &str, now: NaiveTime) -> Option<Duration> {
+    let created = NaiveTime::parse_from_str(id.get(..6)?, "%H%M%S").ok()?;
+    let seconds = (now.num_seconds_from_midnight() as i64
+        - created.num_seconds_from_midnight() as i64)
+        .rem_euclid(24 * 60 * 60);
+    Some(Duration::from_secs(seconds as u64))
+}
+
+/// The id of the message `selector` names: its number in the list (`3` or
+/// `#3`) or its id
+pub fn resolve(context: &ContextWindow, selector: &str) -> Result<String> {
+    let history = &context.conversation_history;
+    if let Ok(number) = selector.trim_start_matches('#').parse::<usize>() {
+        return match history.get(number.wrapping_sub(1)) {
+            Some(message) if !message.id.is_empty() => Ok(message.id.clone()),
+            Some(_) => bail!("Message #{} has no id", number),
+            None => bail!(
+                "No message #{}; the context has {} messages",
+                number,
+                history.len()
+            ),
+        };
+    }
+    history
+        .iter()
+        .find(|message| message.id == selector)
+        .map(|message| message.id.clone())
+        .with_context(|| format!("No message with id '{}' in the context", selector))
+}
+
+/// Pin, unpin or evict the message `selector` names; returns what was done
+pub fn apply(context: &mut ContextWindow, action: ContextAction, selector: &str) -> Result<String> {
+    let id = resolve(context, selector)?;
+    Ok(match action {
+        ContextAction::Pin => {
+            context.pin(&id);
+            format!("📌 Pinned message {}", id)
+        }
+        ContextAction::Unpin => {
+            context.unpin(&id);
+            format!("Unpinned message {}", id)
+        }
+        ContextAction::Evict => {
+            let tokens = context.evict(&id)?;
+            format!("🗑️ Evicted message {}, freeing ~{} tokens", id, tokens)
+        }
+    })
+}
+
+/// The list as a table under a usage summary
+pub fn render(context: &ContextWindow, entries: &[ContextEntry]) -> String {
+    let mut out = String::new();
+    let pinned = entries.iter().filter(|entry| entry.pinned).count();
+    if context.total_tokens > 0 {
+        let _ = write!(
+            out,
+            "Context: {} / {} tokens ({:.1}%)",
+            context.used_tokens,
+            context.total_tokens,
+            context.percentage_used()
+        );
+    } else {
+        let _ = write!(out, "Context: {} tokens", context.used_tokens);
+    }
+    let _ = writeln!(out, ", {} messages, {} pinned\n", entries.len(), pinned);
+
+    let _ = writeln!(
+        out,
+        "{:>4}  {:<10} {:<9} {:>7} {:>8}  {:<3} Message",
+        "#", "Id", "Role", "Tokens", "Age", "Pin"
+    );
+    for entry in entries {
+        let role = match entry.role {
+            MessageRole::System => "system",
+            MessageRole::User => "user",
+            MessageRole::Assistant => "assistant",
+        };
+        let _ = writeln!(
+            out,
+            "{:>4}  {:<10} {:<9} {:>7} {:>8}  {:<3} {}",
+            entry.number,
+            entry.id,
+            role,
+            entry.tokens,
+            entry.age.map(format_age).unwrap_or_else(|| "?".to_string()),
+            if entry.pinned { "📌" } else { "" },
+            entry.preview
+        );
+    }
+    out
+}
+
+/// List the messages of the journaled session `session_id`
+pub fn session_breakdown(session_id: &str, now: NaiveTime) -> Result<String> {
+    let (_, context) = restore(session_id)?;
+    Ok(render(&context, &entries(&context, now)))
+}
+
+/// Pin, unpin or evict a message of the journaled session `session_id`.
+/// The change is journaled, so `g3 --resume` picks it up.
+pub fn edit_session(session_id: &str, action: ContextAction, selector: &str) -> Result<String> {
+    let (mut journal, mut context) = restore(session_id)?;
+    let done = apply(&mut context, action, selector)?;
+    journal.record_snapshot(&context)?;
+    Ok(done)
+}
+
+fn restore(session_id: &str) -> Result<(SessionJournal, ContextWindow)> {
+    let (journal, restored) = SessionJournal::restore(get_session_journal_file(session_id))?;
+    let mut context = ContextWindow::new(0);
+    context.conversation_history = restored.history;
+    context.pinned_message_ids = restored.pinned_message_ids;
+    context.used_tokens = restored.used_tokens;
+    context.cumulative_tokens = restored.cumulative_tokens;
+    Ok((journal, context))
+}
+
+fn preview(content: &str) -> String {
+    let line = content
+        .lines()
+        .map(str::trim)
+        .find(|line| !line.is_empty())
+        .unwrap_or("");
+    match line.char_indices().nth(PREVIEW_CHARS) {
+        Some((end, _)) => format!("{}…", &line[..end]),
+        None => line.to_string(),
+    }
+}
+
+fn format_age(age: Duration) -> String {
+    let seconds = age.as_secs();
+    match seconds {
+        0..=59 => format!("{}s", seconds),
+        60..=3599 => format!("{}m {:02}s", seconds / 60, seconds % 60),
+        _ => format!("{}h {:02}m", seconds / 3600, seconds % 3600 / 60),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use g3_providers::Message;
+
+    fn message(role: MessageRole, id: &str, content: &str) -> Message {
+        let mut message = Message::new(role, content.to_string());
+        message.id = id.to_string();
+        message
+    }
+
+    fn context() -> ContextWindow {
+        let mut context = ContextWindow::new(10_000);
+        context.add_pinned_message(message(MessageRole::System, "090000-abc", "You are G3"));
+        context.add_message(message(
+            MessageRole::User,
+            "093000-def",
+            "\nTask: fix the parser\nIt panics on empty input",
+        ));
+        context.add_message(message(MessageRole::Assistant, "235959-ghi", "Done"));
+        context
+    }
+
+    #[test]
+    fn test_entries() {
+        let context = context();
+        let now = NaiveTime::from_hms_opt(10, 0, 0).unwrap();
+        let entries = entries(&context, now);
+        assert_eq!(entries.len(), 3);
+        assert!(entries[0].pinned);
+        assert_eq!(entries[0].age, Some(Duration::from_secs(3600)));
+        assert_eq!(entries[1].number, 2);
+        assert_eq!(entries[1].preview, "Task: fix the parser");
+        assert!(!entries[1].pinned);
+        // Created before midnight
+        assert_eq!(entries[2].age, Some(Duration::from_secs(36001)));
+        assert_eq!(message_age("summary", now), None);
+
+        let table = render(&context, &entries);
+        assert!(table.starts_with("Context: "));
+        assert!(table.contains("3 messages, 1 pinned"));
+        assert!(table.contains("1h 00m"));
+    }
+
+    #[test]
+    fn test_pin_unpin_and_evict() {
+        let mut context = context();
+        assert_eq!(resolve(&context, "#2").unwrap(), "093000-def");
+        assert_eq!(resolve(&context, "235959-ghi").unwrap(), "235959-ghi");
+        assert!(resolve(&context, "4").is_err());
+        assert!(resolve(&context, "0").is_err());
+        assert!(resolve(&context, "missing").is_err());
+
+        apply(&mut context, ContextAction::Pin, "2").unwrap();
+        assert!(context.is_pinned(&context.conversation_history[1]));
+        assert!(apply(&mut context, ContextAction::Evict, "2").is_err());
+        apply(&mut context, ContextAction::Unpin, "2").unwrap();
+
+        let used = context.used_tokens;
+        apply(&mut context, ContextAction::Evict, "2").unwrap();
+        assert_eq!(context.conversation_history.len(), 2);
+        assert!(context.used_tokens < used);
+        assert!("drop".parse::<ContextAction>().is_err());
+    }
+
+    #[test]
+    fn test_edits_to_a_session_are_journaled() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let path = dir.path().join("journal.jsonl");
+        let mut journal = SessionJournal::new(&path);
+        let mut context = context();
+        journal.record_context(&context).unwrap();
+
+        apply(&mut context, ContextAction::Pin, "3").unwrap();
+        journal.record_snapshot(&context).unwrap();
+        let (_, restored) = SessionJournal::restore(&path).unwrap();
+        assert!(restored.pinned_message_ids.contains("235959-ghi"));
+    }
+}
@@ -0,0 +1,277 @@
+//! Journal of the agent's file edits, unwound by `g3 undo` and `/undo`.
+//!
+//! Every successful write_file and str_replace appends the file's content
+//! before and after the edit to `.g3/sessions/<session_id>/edits.jsonl`.
+//! Undoing pops the newest edits off the journal and puts the old content
+//! back, regardless of git state. An edit whose file has changed since it was
+//! made is left alone, and so is everything older than it, so undo never
+//! discards work it didn't record.
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use tracing::warn;
+
+use crate::paths::{get_g3_dir, get_session_edits_file};
+use crate::utils::atomic_write_with_backup;
+
+/// One file mutation made by a tool
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FileEdit {
+    /// Absolute path of the edited file
+    pub path: PathBuf,
+    /// The tool that made the edit
+    pub tool: String,
+    /// Content before the edit; `None` if the edit created the file
+    pub before: Option<String>,
+    pub after: String,
+    pub timestamp: String,
+}
+
+impl FileEdit {
+    pub fn new(path: impl AsRef<Path>, tool: &str, before: Option<String>, after: String) -> Self {
+        let path = path.as_ref();
+        let path = if path.is_absolute() {
+            path.to_path_buf()
+        } else {
+            std::env::current_dir().unwrap_or_default().join(path)
+        };
+        Self {
+            path,
+            tool: tool.to_string(),
+            before,
+            after,
+            timestamp: chrono::Utc::now().to_rfc3339(),
+        }
+    }
+}
+
+/// What an undo did
+#[derive(Debug, Default)]
+pub struct UndoReport {
+    /// Edits reverted, newest first
+    pub undone: Vec<FileEdit>,
+    /// The edit undo stopped at because its file changed since
+    pub blocked_by: Option<FileEdit>,
+}
+
+impl fmt::Display for UndoReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.undone.is_empty() && self.blocked_by.is_none() {
+            return write!(f, "No edits to undo");
+        }
+        for edit in &self.undone {
+            let action = if edit.before.is_none() {
+                "removed"
+            } else {
+                "restored"
+            };
+            writeln!(
+                f,
+                "↩️  {} {} (from {})",
+                action,
+                edit.path.display(),
+                edit.tool
+            )?;
+        }
+        if let Some(edit) = &self.blocked_by {
+            writeln!(
+                f,
+                "⚠️  Stopped at {}: it changed after the {} edit, so older edits were kept",
+                edit.path.display(),
+                edit.tool
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// The edits of one session, oldest first
+#[derive(Debug, Clone)]
+pub struct EditJournal {
+    path: PathBuf,
+}
+
+impl EditJournal {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self { path: path.into() }
+    }
+
+    pub fn for_session(session_id: &str) -> Self {
+        Self::new(get_session_edits_file(session_id))
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Append one edit, creating the file if needed
+    pub fn record(&self, edit: &FileEdit) -> Result<()> {
+        if let Some(parent) = self.path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let mut file = std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)
+            .with_context(|| format!("Failed to open edit journal {}", self.path.display()))?;
+        writeln!(file, "{}", serde_json::to_string(edit)?)?;
+        Ok(())
+    }
+
+    /// All recorded edits, oldest first; unreadable lines are skipped
+    pub fn edits(&self) -> Result<Vec<FileEdit>> {
+        if !self.path.exists() {
+            return Ok(Vec::new());
+        }
+        let contents = std::fs::read_to_string(&self.path)
+            .with_context(|| format!("Failed to read edit journal {}", self.path.display()))?;
+        Ok(contents
+            .lines()
+            .filter(|line| !line.trim().is_empty())
+            .filter_map(|line| match serde_json::from_str(line) {
+                Ok(edit) => Some(edit),
+                Err(e) => {
+                    warn!("Skipping unreadable edit journal line: {}", e);
+                    None
+                }
+            })
+            .collect())
+    }
+
+    /// Revert the newest `count` edits and drop them from the journal
+    pub fn undo(&self, count: usize) -> Result<UndoReport> {
+        let mut edits = self.edits()?;
+        let mut report = UndoReport::default();
+
+        while report.undone.len() < count {
+            let Some(edit) = edits.pop() else { break };
+            let current = std::fs::read_to_string(&edit.path).ok();
+            if current.as_deref() != Some(edit.after.as_str()) {
+                report.blocked_by = Some(edit.clone());
+                edits.push(edit);
+                break;
+            }
+            match &edit.before {
+                Some(before) => {
+                    atomic_write_with_backup(&edit.path, before, false)
+                        .with_context(|| format!("Failed to restore {}", edit.path.display()))?;
+                }
+                None => std::fs::remove_file(&edit.path)
+                    .with_context(|| format!("Failed to remove {}", edit.path.display()))?,
+            }
+            report.undone.push(edit);
+        }
+
+        if !report.undone.is_empty() {
+            let mut contents = String::new();
+            for edit in &edits {
+                contents.push_str(&serde_json::to_string(edit)?);
+                contents.push('\n');
+            }
+            atomic_write_with_backup(&self.path, contents, false)?;
+        }
+        Ok(report)
+    }
+}
+
+/// The session whose edit journal changed most recently, for `g3 undo`
+/// without `--session`
+pub fn latest_session_with_edits() -> Option<String> {
+    let sessions = std::fs::read_dir(get_g3_dir().join("sessions")).ok()?;
+    sessions
+        .filter_map(|entry| entry.ok())
+        .filter_map(|entry| {
+            let modified = entry
+                .path()
+                .join("edits.jsonl")
+                .metadata()
+                .and_then(|meta| meta.modified())
+                .ok()?;
+            Some((modified, entry.file_name().to_string_lossy().into_owned()))
+        })
+        .max()
+        .map(|(_, session_id)| session_id)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn write(path: &Path, content: &str) {
+        std::fs::write(path, content).unwrap();
+    }
+
+    #[test]
+    fn test_undo_restores_newest_edits_first() {
+        let dir = TempDir::new().unwrap();
+        let journal = EditJournal::new(dir.path().join("edits.jsonl"));
+        let file = dir.path().join("main.rs");
+        let created = dir.path().join("new.rs");
+
+        write(&file, "fn main() {}\n");
+        journal
+            .record(&FileEdit::new(
+                &file,
+                "str_replace",
+                Some("fn main() {}\n".into()),
+                "v1".into(),
+            ))
+            .unwrap();
+        write(&file, "v1");
+        journal
+            .record(&FileEdit::new(
+                &file,
+                "write_file",
+                Some("v1".into()),
+                "v2".into(),
+            ))
+            .unwrap();
+        write(&file, "v2");
+        journal
+            .record(&FileEdit::new(&created, "write_file", None, "new".into()))
+            .unwrap();
+        write(&created, "new");
+
+        let report = journal.undo(2).unwrap();
+        assert_eq!(report.undone.len(), 2);
+        assert!(report.blocked_by.is_none());
+        assert!(!created.exists());
+        assert_eq!(std::fs::read_to_string(&file).unwrap(), "v1");
+        assert_eq!(journal.edits().unwrap().len(), 1);
+
+        journal.undo(5).unwrap();
+        assert_eq!(std::fs::read_to_string(&file).unwrap(), "fn main() {}\n");
+        assert!(journal.edits().unwrap().is_empty());
+        assert_eq!(journal.undo(1).unwrap().to_string(), "No edits to undo");
+    }
+
+    #[test]
+    fn test_undo_stops_at_files_changed_since() {
+        let dir = TempDir::new().unwrap();
+        let journal = EditJournal::new(dir.path().join("edits.jsonl"));
+        let file = dir.path().join("lib.rs");
+
+        journal
+            .record(&FileEdit::new(
+                &file,
+                "write_file",
+                Some("old".into()),
+                "agent".into(),
+            ))
+            .unwrap();
+        write(&file, "agent, then the user");
+
+        let report = journal.undo(1).unwrap();
+        assert!(report.undone.is_empty());
+        assert_eq!(report.blocked_by.unwrap().path, file);
+        assert_eq!(
+            std::fs::read_to_string(&file).unwrap(),
+            "agent, then the user"
+        );
+        assert_eq!(journal.edits().unwrap().len(), 1);
+    }
+}
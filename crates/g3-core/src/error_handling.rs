@@ -1,12 +1,14 @@
 //! Error handling module for G3 with retry logic and detailed logging
 //!
 //! This module provides:
-//! - Classification of errors as recoverable or non-recoverable
+//! - Classification of errors as recoverable or non-recoverable, by the
+//!   category of the [`G3Error`] they carry or else by their message
 //! - Retry logic with exponential backoff and jitter for recoverable errors
 //! - Detailed error logging with context information
 //! - Request/response capture for debugging
 
 use anyhow::Result;
+use g3_providers::{ErrorCategory, G3Error};
 use serde::{Deserialize, Serialize};
 use std::time::Duration;
 use tracing::{debug, error, warn};
@@ -189,6 +191,22 @@ pub enum RecoverableError {
 
 /// Classify an error as recoverable or non-recoverable
 pub fn classify_error(error: &anyhow::Error) -> ErrorType {
+    if let Some(error) = G3Error::find(error) {
+        let recoverable = match error.category() {
+            ErrorCategory::RateLimit => RecoverableError::RateLimit,
+            ErrorCategory::Network => RecoverableError::NetworkError,
+            ErrorCategory::Server => RecoverableError::ServerError,
+            ErrorCategory::Overloaded => RecoverableError::ModelBusy,
+            ErrorCategory::Timeout => RecoverableError::Timeout,
+            ErrorCategory::ContextLength => RecoverableError::ContextLengthExceeded,
+            ErrorCategory::Auth
+            | ErrorCategory::InvalidRequest
+            | ErrorCategory::Tool
+            | ErrorCategory::Git => return ErrorType::NonRecoverable,
+        };
+        return ErrorType::Recoverable(recoverable);
+    }
+
     let error_str = error.to_string().to_lowercase();
 
     // Check for recoverable error patterns
@@ -495,6 +513,25 @@ mod tests {
         assert_eq!(classify_error(&error), ErrorType::NonRecoverable);
     }
 
+    #[test]
+    fn test_typed_error_classification() {
+        use reqwest::StatusCode;
+
+        // The category wins over the message
+        let error = G3Error::api("Anthropic", StatusCode::BAD_REQUEST, "connection limit");
+        assert_eq!(classify_error(&error.into()), ErrorType::NonRecoverable);
+
+        let error = G3Error::api("OpenAI", StatusCode::SERVICE_UNAVAILABLE, "try later");
+        assert_eq!(
+            classify_error(&error.into()),
+            ErrorType::Recoverable(RecoverableError::ModelBusy)
+        );
+
+        let error = anyhow::Error::new(G3Error::tool("shell", "rate limit of the tool"))
+            .context("Task failed");
+        assert_eq!(classify_error(&error), ErrorType::NonRecoverable);
+    }
+
     #[test]
     fn test_retry_delay_calculation() {
         // Test that delays increase exponentially
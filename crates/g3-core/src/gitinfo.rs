@@ -0,0 +1,226 @@
+//! Recent repository activity, summarized for the model.
+//!
+//! At session start the agent adds a system message below the README with
+//! the current branch, how far it is ahead of or behind its upstream, the
+//! last few commits and any files with unresolved merge conflicts, so the
+//! model knows what changed recently without running git itself. The
+//! `git_context` tool gives the same summary on demand, optionally limited to
+//! commits touching given paths.
+//!
+//! Everything is read through the `git` CLI; outside a repository (or
+//! without git installed) there is simply no summary.
+
+use anyhow::{bail, Context, Result};
+use std::fmt;
+use std::path::Path;
+use std::process::Command;
+
+/// Header of the system message added at session start
+pub const GIT_CONTEXT_HEADER: &str = "🌿 Git Context";
+
+/// Commits listed when the caller doesn't say
+pub const DEFAULT_COMMITS: usize = 10;
+
+/// How the current branch relates to its upstream
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Upstream {
+    pub name: String,
+    pub ahead: usize,
+    pub behind: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GitSummary {
+    /// Branch name, or `(detached HEAD at <sha>)`
+    pub branch: String,
+    pub upstream: Option<Upstream>,
+    /// `<short sha> <date> <author>: <subject>`, newest first
+    pub commits: Vec<String>,
+    /// Paths the commits were limited to, if any
+    pub paths: Vec<String>,
+    /// Files with unresolved merge conflicts
+    pub conflicts: Vec<String>,
+}
+
+/// Summarize the repository containing `dir`, or `None` if there is none.
+/// `paths` limits the commits to those touching them.
+pub fn summarize(dir: &Path, paths: &[String], max_commits: usize) -> Option<GitSummary> {
+    match git(dir, &["rev-parse", "--is-inside-work-tree"]) {
+        Ok(inside) if inside == "true" => {}
+        _ => return None,
+    }
+
+    let branch = match git(dir, &["branch", "--show-current"]) {
+        Ok(branch) if !branch.is_empty() => branch,
+        _ => match git(dir, &["rev-parse", "--short", "HEAD"]) {
+            Ok(sha) => format!("(detached HEAD at {})", sha),
+            // No commits yet
+            Err(_) => "(no commits yet)".to_string(),
+        },
+    };
+
+    let upstream = git(dir, &["rev-parse", "--abbrev-ref", "@{upstream}"])
+        .ok()
+        .and_then(|name| {
+            let counts = git(
+                dir,
+                &["rev-list", "--left-right", "--count", "@{upstream}...HEAD"],
+            )
+            .ok()?;
+            let (behind, ahead) = counts.split_once('\t')?;
+            Some(Upstream {
+                name,
+                ahead: ahead.trim().parse().ok()?,
+                behind: behind.trim().parse().ok()?,
+            })
+        });
+
+    let mut log_args = vec![
+        "log".to_string(),
+        format!("-n{}", max_commits),
+        "--date=short".to_string(),
+        "--format=%h %ad %an: %s".to_string(),
+        "--".to_string(),
+    ];
+    log_args.extend(paths.iter().cloned());
+    let log_args: Vec<&str> = log_args.iter().map(String::as_str).collect();
+    // Fails in a repository without commits
+    let commits = git(dir, &log_args).map(lines).unwrap_or_default();
+
+    let conflicts = git(dir, &["diff", "--name-only", "--diff-filter=U"])
+        .map(lines)
+        .unwrap_or_default();
+
+    Some(GitSummary {
+        branch,
+        upstream,
+        commits,
+        paths: paths.to_vec(),
+        conflicts,
+    })
+}
+
+impl fmt::Display for GitSummary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Branch: {}", self.branch)?;
+        match &self.upstream {
+            Some(upstream) if upstream.ahead == 0 && upstream.behind == 0 => {
+                write!(f, " (up to date with {})", upstream.name)?
+            }
+            Some(upstream) => write!(
+                f,
+                " ({} ahead, {} behind {})",
+                upstream.ahead, upstream.behind, upstream.name
+            )?,
+            None => write!(f, " (no upstream)")?,
+        }
+        writeln!(f)?;
+
+        if !self.conflicts.is_empty() {
+            writeln!(f, "\n⚠️ Unresolved merge conflicts:")?;
+            for path in &self.conflicts {
+                writeln!(f, "- {}", path)?;
+            }
+        }
+
+        if self.paths.is_empty() {
+            writeln!(f, "\nRecent commits:")?;
+        } else {
+            writeln!(f, "\nRecent commits touching {}:", self.paths.join(", "))?;
+        }
+        if self.commits.is_empty() {
+            writeln!(f, "(none)")?;
+        }
+        for commit in &self.commits {
+            writeln!(f, "- {}", commit)?;
+        }
+        Ok(())
+    }
+}
+
+/// Run git in `dir` and return its trimmed stdout
+fn git(dir: &Path, args: &[&str]) -> Result<String> {
+    let output = Command::new("git")
+        .args(args)
+        .current_dir(dir)
+        .output()
+        .context("Failed to run git")?;
+    if !output.status.success() {
+        bail!(
+            "git {} failed: {}",
+            args.join(" "),
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+fn lines(output: String) -> Vec<String> {
+    output
+        .lines()
+        .filter(|line| !line.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn run(dir: &Path, args: &[&str]) {
+        let status = Command::new("git")
+            .args(args)
+            .current_dir(dir)
+            .env("GIT_AUTHOR_NAME", "Test")
+            .env("GIT_AUTHOR_EMAIL", "test@example.com")
+            .env("GIT_COMMITTER_NAME", "Test")
+            .env("GIT_COMMITTER_EMAIL", "test@example.com")
+            .status()
+            .unwrap();
+        assert!(status.success(), "git {:?} failed", args);
+    }
+
+    fn commit(dir: &Path, file: &str, message: &str) {
+        let path = dir.join(file);
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(&path, message).unwrap();
+        run(dir, &["add", "."]);
+        run(dir, &["commit", "-q", "-m", message]);
+    }
+
+    #[test]
+    fn test_outside_a_repository() {
+        let temp_dir = TempDir::new().unwrap();
+        assert_eq!(summarize(temp_dir.path(), &[], 5), None);
+    }
+
+    #[test]
+    fn test_summary_of_recent_commits() {
+        let temp_dir = TempDir::new().unwrap();
+        let dir = temp_dir.path();
+        run(dir, &["init", "-q", "-b", "main"]);
+        commit(dir, "src/lib.rs", "Add library");
+        commit(dir, "README.md", "Write README");
+        commit(dir, "src/main.rs", "Add binary");
+
+        let summary = summarize(dir, &[], 2).unwrap();
+        assert_eq!(summary.branch, "main");
+        assert_eq!(summary.upstream, None);
+        assert_eq!(summary.commits.len(), 2);
+        assert!(summary.commits[0].ends_with("Test: Add binary"));
+        assert!(summary.conflicts.is_empty());
+
+        let scoped = summarize(dir, &["src".to_string()], 10).unwrap();
+        let subjects: Vec<&str> = scoped
+            .commits
+            .iter()
+            .map(|commit| commit.rsplit(": ").next().unwrap())
+            .collect();
+        assert_eq!(subjects, vec!["Add binary", "Add library"]);
+
+        let text = scoped.to_string();
+        assert!(text.starts_with("Branch: main (no upstream)\n"));
+        assert!(text.contains("Recent commits touching src:\n"));
+    }
+}
@@ -0,0 +1,182 @@
+// Tests for interrupting a response while it is streaming
+
+#[cfg(test)]
+mod tests {
+    use crate::ui_writer::UiWriter;
+    use crate::usage::UsageLedger;
+    use crate::{Agent, INTERRUPTED_MARKER};
+    use anyhow::Result;
+    use g3_config::Config;
+    use g3_providers::{
+        CompletionChunk, CompletionRequest, CompletionResponse, CompletionStream, LLMProvider,
+        MessageRole, Usage,
+    };
+    use serial_test::serial;
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::Arc;
+    use tempfile::TempDir;
+    use tokio::sync::{mpsc, Notify};
+    use tokio_util::sync::CancellationToken;
+
+    /// Streams one chunk with usage, then stalls until the stream is dropped
+    struct StallingProvider {
+        aborted: Arc<AtomicBool>,
+    }
+
+    #[async_trait::async_trait]
+    impl LLMProvider for StallingProvider {
+        async fn complete(&self, _request: CompletionRequest) -> Result<CompletionResponse> {
+            anyhow::bail!("only streams")
+        }
+
+        async fn stream(&self, _request: CompletionRequest) -> Result<CompletionStream> {
+            let (tx, rx) = mpsc::channel(4);
+            tx.send(Ok(CompletionChunk {
+                content: "Here is the first half of".to_string(),
+                finished: false,
+                tool_calls: None,
+                usage: Some(Usage {
+                    prompt_tokens: 120,
+                    completion_tokens: 7,
+                    total_tokens: 127,
+                }),
+            }))
+            .await?;
+            let aborted = self.aborted.clone();
+            tokio::spawn(async move {
+                tx.closed().await;
+                aborted.store(true, Ordering::SeqCst);
+            });
+            Ok(tokio_stream::wrappers::ReceiverStream::new(rx))
+        }
+
+        fn name(&self) -> &str {
+            "stalling"
+        }
+
+        fn model(&self) -> &str {
+            "stalling-model"
+        }
+
+        fn max_tokens(&self) -> u32 {
+            1024
+        }
+
+        fn temperature(&self) -> f32 {
+            0.0
+        }
+    }
+
+    /// Signals every chunk the agent receives
+    struct ChunkSignal(Arc<Notify>);
+
+    impl UiWriter for ChunkSignal {
+        fn print(&self, _message: &str) {}
+        fn println(&self, _message: &str) {}
+        fn print_inline(&self, _message: &str) {}
+        fn print_system_prompt(&self, _prompt: &str) {}
+        fn print_context_status(&self, _message: &str) {}
+        fn print_context_thinning(&self, _message: &str) {}
+        fn print_tool_header(&self, _tool_name: &str, _tool_args: Option<&serde_json::Value>) {}
+        fn print_tool_arg(&self, _key: &str, _value: &str) {}
+        fn print_tool_output_header(&self) {}
+        fn update_tool_output_line(&self, _line: &str) {}
+        fn print_tool_output_line(&self, _line: &str) {}
+        fn print_tool_output_summary(&self, _hidden_count: usize) {}
+        fn print_tool_timing(
+            &self,
+            _duration_str: &str,
+            _tokens_delta: u32,
+            _context_percentage: f32,
+        ) {
+        }
+        fn print_agent_prompt(&self) {}
+        fn print_agent_response(&self, _content: &str) {}
+        fn notify_sse_received(&self) {
+            self.0.notify_one();
+        }
+        fn flush(&self) {}
+        fn wants_full_output(&self) -> bool {
+            false
+        }
+        fn prompt_user_yes_no(&self, _message: &str) -> bool {
+            true
+        }
+        fn prompt_user_choice(&self, _message: &str, _options: &[&str]) -> usize {
+            0
+        }
+        fn print_final_output(&self, _summary: &str) {}
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn test_interrupt_mid_stream_keeps_partial_response_and_usage() {
+        // The agent keeps its logs under the current directory
+        let temp_dir = TempDir::new().unwrap();
+        let original_dir = std::env::current_dir().unwrap();
+        std::env::set_current_dir(temp_dir.path()).unwrap();
+        let chunk_received = Arc::new(Notify::new());
+        let signal = chunk_received.clone();
+        let mut agent = Agent::new_with_quiet(Config::default(), ChunkSignal(signal), true)
+            .await
+            .unwrap();
+        let aborted = Arc::new(AtomicBool::new(false));
+        agent.providers.register(StallingProvider {
+            aborted: aborted.clone(),
+        });
+        agent.providers.set_default("stalling").unwrap();
+        agent.usage_ledger = UsageLedger::new(temp_dir.path().join("usage.jsonl"));
+        // Room for the system prompt, so the turn goes straight to streaming
+        agent.context_window.total_tokens = 200_000;
+
+        // Interrupt once the first chunk is in, while the stream is stalled
+        let cancellation_token = CancellationToken::new();
+        let interrupt = cancellation_token.clone();
+        tokio::spawn(async move {
+            chunk_received.notified().await;
+            interrupt.cancel();
+        });
+        let task = agent.execute_task_with_timing_cancellable(
+            "Explain the build",
+            None,
+            false,
+            false,
+            false,
+            false,
+            cancellation_token.clone(),
+            None,
+        );
+        let result = tokio::time::timeout(std::time::Duration::from_secs(10), task)
+            .await
+            .expect("the interrupt should end the task");
+        assert!(result.is_err());
+
+        // The provider stream was dropped, not drained
+        tokio::time::timeout(std::time::Duration::from_secs(5), async {
+            while !aborted.load(Ordering::SeqCst) {
+                tokio::task::yield_now().await;
+            }
+        })
+        .await
+        .expect("the provider stream should be aborted");
+
+        let last = agent
+            .get_context_window()
+            .conversation_history
+            .last()
+            .unwrap();
+        assert!(matches!(last.role, MessageRole::Assistant));
+        assert_eq!(
+            last.content,
+            format!("Here is the first half of\n\n{}", INTERRUPTED_MARKER)
+        );
+
+        let usage = agent.get_session_usage();
+        assert_eq!(usage.requests, 1);
+        assert_eq!(usage.prompt_tokens, 120);
+        assert_eq!(usage.completion_tokens, 7);
+        assert!(temp_dir.path().join("usage.jsonl").exists());
+
+        std::env::set_current_dir(original_dir).unwrap();
+    }
+}
@@ -0,0 +1,334 @@
+//! Lenient JSON repair for model output.
+//!
+//! LLMs frequently emit almost-valid JSON for tool calls. This module repairs
+//! the most common defects before parsing:
+//! - Trailing commas before `}` or `]`
+//! - Single-quoted strings
+//! - Unescaped newlines, tabs and other control characters inside strings
+//! - Unescaped double quotes inside string values
+//! - Truncated output (unterminated strings, dangling keys, unclosed objects/arrays)
+//!
+//! Valid JSON is always returned unchanged, and every repair that was applied
+//! is reported so callers can log or surface it.
+
+use serde::de::DeserializeOwned;
+use tracing::debug;
+
+/// A kind of repair applied to malformed JSON.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum JsonRepairKind {
+    /// Removed a comma directly before `}` or `]`
+    TrailingComma,
+    /// Converted a single-quoted string to a double-quoted one
+    SingleQuotes,
+    /// Escaped a raw newline, tab or other control character inside a string
+    UnescapedControlCharacter,
+    /// Escaped a double quote that appeared inside a string value
+    UnescapedInnerQuote,
+    /// Closed a string that was cut off
+    UnterminatedString,
+    /// Completed a key that had no value by adding `null`
+    DanglingKey,
+    /// Closed objects or arrays that were cut off
+    UnclosedStructure,
+}
+
+impl std::fmt::Display for JsonRepairKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let description = match self {
+            JsonRepairKind::TrailingComma => "removed trailing comma",
+            JsonRepairKind::SingleQuotes => "converted single quotes",
+            JsonRepairKind::UnescapedControlCharacter => "escaped control character",
+            JsonRepairKind::UnescapedInnerQuote => "escaped inner quote",
+            JsonRepairKind::UnterminatedString => "closed unterminated string",
+            JsonRepairKind::DanglingKey => "completed dangling key",
+            JsonRepairKind::UnclosedStructure => "closed truncated object/array",
+        };
+        write!(f, "{}", description)
+    }
+}
+
+/// The output of [`repair_json`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct JsonRepair {
+    /// The repaired JSON text
+    pub json: String,
+    /// Repairs that were applied, in the order first encountered
+    pub repairs: Vec<JsonRepairKind>,
+}
+
+impl JsonRepair {
+    /// Whether any repair was needed.
+    pub fn was_repaired(&self) -> bool {
+        !self.repairs.is_empty()
+    }
+
+    fn record(&mut self, kind: JsonRepairKind) {
+        if !self.repairs.contains(&kind) {
+            self.repairs.push(kind);
+        }
+    }
+}
+
+/// A value parsed by [`parse_lenient`], with the repairs needed to parse it.
+#[derive(Debug, Clone)]
+pub struct LenientParse<T> {
+    pub value: T,
+    /// Empty if the input was valid JSON
+    pub repairs: Vec<JsonRepairKind>,
+}
+
+/// Parse JSON, repairing common model-output defects if strict parsing fails.
+///
+/// Returns the error from parsing the repaired text if it is still invalid.
+pub fn parse_lenient<T: DeserializeOwned>(
+    input: &str,
+) -> Result<LenientParse<T>, serde_json::Error> {
+    match serde_json::from_str::<T>(input) {
+        Ok(value) => Ok(LenientParse {
+            value,
+            repairs: Vec::new(),
+        }),
+        Err(strict_err) => {
+            let repair = repair_json(input);
+            if !repair.was_repaired() {
+                return Err(strict_err);
+            }
+            let value = serde_json::from_str::<T>(&repair.json)?;
+            debug!("Parsed JSON after repairs: {:?}", repair.repairs);
+            Ok(LenientParse {
+                value,
+                repairs: repair.repairs,
+            })
+        }
+    }
+}
+
+/// Repair common defects in model-produced JSON.
+///
+/// This is a single pass over the input that tracks string and nesting state;
+/// it does not validate the result.
+pub fn repair_json(input: &str) -> JsonRepair {
+    let mut repair = JsonRepair {
+        json: String::with_capacity(input.len() + 8),
+        repairs: Vec::new(),
+    };
+    let chars: Vec<char> = input.chars().collect();
+    let mut stack: Vec<char> = Vec::new();
+    // Delimiter of the string we are inside ('"' or '\''), if any
+    let mut string_delim: Option<char> = None;
+    let mut i = 0;
+
+    while i < chars.len() {
+        let ch = chars[i];
+
+        if let Some(delim) = string_delim {
+            match ch {
+                '\\' => match chars.get(i + 1) {
+                    Some('\'') if delim == '\'' => {
+                        repair.json.push('\'');
+                        i += 1;
+                    }
+                    Some(&next) => {
+                        repair.json.push('\\');
+                        repair.json.push(next);
+                        i += 1;
+                    }
+                    // Dangling escape at the end of truncated input
+                    None => {}
+                },
+                c if c == delim => {
+                    if closes_string(&chars, i + 1) {
+                        repair.json.push('"');
+                        string_delim = None;
+                    } else if c == '"' {
+                        repair.json.push_str("\\\"");
+                        repair.record(JsonRepairKind::UnescapedInnerQuote);
+                    } else {
+                        // Apostrophe inside a single-quoted string
+                        repair.json.push('\'');
+                    }
+                }
+                '"' => {
+                    // Double quote inside a single-quoted string
+                    repair.json.push_str("\\\"");
+                }
+                '\n' => push_escaped_control(&mut repair, "\\n"),
+                '\r' => push_escaped_control(&mut repair, "\\r"),
+                '\t' => push_escaped_control(&mut repair, "\\t"),
+                c if (c as u32) < 0x20 => {
+                    push_escaped_control(&mut repair, &format!("\\u{:04x}", c as u32))
+                }
+                c => repair.json.push(c),
+            }
+            i += 1;
+            continue;
+        }
+
+        match ch {
+            '"' => {
+                string_delim = Some('"');
+                repair.json.push('"');
+            }
+            '\'' => {
+                string_delim = Some('\'');
+                repair.json.push('"');
+                repair.record(JsonRepairKind::SingleQuotes);
+            }
+            '{' => {
+                stack.push('}');
+                repair.json.push(ch);
+            }
+            '[' => {
+                stack.push(']');
+                repair.json.push(ch);
+            }
+            '}' | ']' => {
+                if stack.last() == Some(&ch) {
+                    stack.pop();
+                }
+                repair.json.push(ch);
+            }
+            ',' => {
+                let next = chars[i + 1..].iter().find(|c| !c.is_whitespace());
+                if matches!(next, Some('}') | Some(']')) {
+                    repair.record(JsonRepairKind::TrailingComma);
+                } else {
+                    repair.json.push(ch);
+                }
+            }
+            c => repair.json.push(c),
+        }
+        i += 1;
+    }
+
+    if string_delim.is_some() {
+        repair.json.push('"');
+        repair.record(JsonRepairKind::UnterminatedString);
+    }
+
+    if !stack.is_empty() {
+        // Drop whatever dangles after the last complete member
+        let trimmed_len = repair.json.trim_end().len();
+        repair.json.truncate(trimmed_len);
+        if repair.json.ends_with(',') {
+            repair.json.pop();
+        } else if repair.json.ends_with(':') {
+            repair.json.push_str("null");
+            repair.record(JsonRepairKind::DanglingKey);
+        }
+        while let Some(close) = stack.pop() {
+            repair.json.push(close);
+        }
+        repair.record(JsonRepairKind::UnclosedStructure);
+    }
+
+    repair
+}
+
+/// Whether a quote at `chars[next - 1]` closes its string.
+///
+/// A closing quote is followed (after optional whitespace) by a structural
+/// character or the end of input; anything else means the quote is part of
+/// the string value.
+fn closes_string(chars: &[char], next: usize) -> bool {
+    match chars[next..].iter().find(|c| !c.is_whitespace()) {
+        None => true,
+        Some(c) => matches!(c, ',' | ':' | '}' | ']'),
+    }
+}
+
+fn push_escaped_control(repair: &mut JsonRepair, escaped: &str) {
+    repair.json.push_str(escaped);
+    repair.record(JsonRepairKind::UnescapedControlCharacter);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn valid_json_is_unchanged() {
+        let input = r#"{"tool": "shell", "args": {"command": "echo \"hi\"", "list": [1, 2]}}"#;
+        let repair = repair_json(input);
+        assert_eq!(repair.json, input);
+        assert!(!repair.was_repaired());
+    }
+
+    #[test]
+    fn removes_trailing_commas() {
+        let repair = repair_json(r#"{"a": [1, 2, ], "b": 3, }"#);
+        assert_eq!(repair.json, r#"{"a": [1, 2 ], "b": 3 }"#);
+        assert_eq!(repair.repairs, vec![JsonRepairKind::TrailingComma]);
+    }
+
+    #[test]
+    fn converts_single_quotes() {
+        let repair = repair_json("{'key': 'value'}");
+        assert_eq!(repair.json, "{\"key\": \"value\"}");
+        assert_eq!(repair.repairs, vec![JsonRepairKind::SingleQuotes]);
+    }
+
+    #[test]
+    fn single_quoted_strings_keep_apostrophes_and_escape_double_quotes() {
+        let repair = repair_json(r#"{'msg': 'it's "fine"'}"#);
+        assert_eq!(repair.json, r#"{"msg": "it's \"fine\""}"#);
+    }
+
+    #[test]
+    fn escapes_raw_newlines_in_strings() {
+        let repair = repair_json("{\"content\": \"line1\nline2\tend\"}");
+        assert_eq!(repair.json, "{\"content\": \"line1\\nline2\\tend\"}");
+        assert_eq!(
+            repair.repairs,
+            vec![JsonRepairKind::UnescapedControlCharacter]
+        );
+    }
+
+    #[test]
+    fn escapes_nested_quotes_in_shell_commands() {
+        let repair = repair_json(r#"{"command": "echo "hello world""}"#);
+        assert_eq!(repair.json, r#"{"command": "echo \"hello world\""}"#);
+        assert_eq!(repair.repairs, vec![JsonRepairKind::UnescapedInnerQuote]);
+    }
+
+    #[test]
+    fn closes_truncated_objects() {
+        let repair = repair_json(r#"{"tool": "write_file", "args": {"content": "partial"#);
+        assert_eq!(
+            repair.json,
+            r#"{"tool": "write_file", "args": {"content": "partial"}}"#
+        );
+        assert_eq!(
+            repair.repairs,
+            vec![
+                JsonRepairKind::UnterminatedString,
+                JsonRepairKind::UnclosedStructure
+            ]
+        );
+
+        let repair = repair_json(r#"{"tool": "shell", "args": {"command":"#);
+        assert_eq!(
+            repair.json,
+            r#"{"tool": "shell", "args": {"command":null}}"#
+        );
+        assert!(repair.repairs.contains(&JsonRepairKind::DanglingKey));
+
+        let repair = repair_json(r#"{"items": [1, 2,"#);
+        assert_eq!(repair.json, r#"{"items": [1, 2]}"#);
+    }
+
+    #[test]
+    fn parse_lenient_reports_repairs() {
+        let parsed: LenientParse<serde_json::Value> =
+            parse_lenient("{'tool': 'shell', 'args': {'command': 'ls',},}").unwrap();
+        assert_eq!(parsed.value["args"]["command"], "ls");
+        assert!(parsed.repairs.contains(&JsonRepairKind::SingleQuotes));
+        assert!(parsed.repairs.contains(&JsonRepairKind::TrailingComma));
+
+        let parsed: LenientParse<serde_json::Value> = parse_lenient(r#"{"ok": true}"#).unwrap();
+        assert!(parsed.repairs.is_empty());
+
+        assert!(parse_lenient::<serde_json::Value>("not json at all").is_err());
+    }
+}
@@ -1,14 +1,39 @@
+pub mod agents_md;
 pub mod background_process;
+pub mod bench;
+pub mod branches;
 pub mod code_search;
+pub mod compaction;
+pub mod context_view;
+pub mod edit_journal;
 pub mod error_handling;
 pub mod feedback_extraction;
+pub mod gitinfo;
+pub mod json_repair;
+pub mod lsp;
+pub mod notifier;
+pub mod path_jail;
 pub mod paths;
+pub mod peer_review;
+pub mod permissions;
 pub mod project;
+pub mod prompt_templates;
+pub mod readme_watcher;
+pub mod repo_map;
+pub mod resource_limits;
+pub mod retention;
 pub mod retry;
 pub mod session_continuation;
+pub mod session_journal;
+pub mod shell_safety;
 pub mod streaming_parser;
+pub mod syntax_check;
 pub mod task_result;
+pub mod todo;
+pub mod tool_schema;
+pub mod transcript_export;
 pub mod ui_writer;
+pub mod usage;
 pub mod utils;
 pub mod webdriver_session;
 
@@ -29,29 +54,47 @@ mod tilde_expansion_tests;
 
 #[cfg(test)]
 mod error_handling_test;
+
+#[cfg(test)]
+mod interrupt_test;
 mod prompts;
 
 use anyhow::Result;
-use g3_computer_control::WebDriverController;
-use g3_config::Config;
+use g3_computer_control::{RecordedAction, WebDriverController};
+use g3_config::{secrets::Redactor, Config, ToolPermission};
 use g3_execution::CodeExecutor;
-use g3_providers::{CacheControl, CompletionRequest, Message, MessageRole, ProviderRegistry, Tool};
-use prompts::{get_system_prompt_for_native, SYSTEM_PROMPT_FOR_NON_NATIVE_TOOL_USE};
+use g3_providers::{
+    CacheControl, CompletionRequest, G3Error, Message, MessageRole, ProviderRegistry, TokenCounter,
+    Tool,
+};
+use prompts::default_system_prompt;
 #[allow(unused_imports)]
 use regex::Regex;
 use serde::{Deserialize, Serialize};
 use serde_json::json;
+use retention::RetentionPolicy;
+use std::collections::HashSet;
 use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
 use std::time::{Duration, Instant};
 use tokio_util::sync::CancellationToken;
-use tracing::{debug, error, warn};
+use tracing::{debug, error, info_span, warn, Instrument};
 
 // Re-export path utilities for backward compatibility
 pub use paths::{
     G3_WORKSPACE_PATH_ENV, ensure_session_dir, get_context_summary_file, get_g3_dir, get_logs_dir,
     get_session_file, get_session_logs_dir, get_thinned_dir, logs_dir,
 };
-use paths::{get_todo_path, get_session_todo_path};
+use paths::{get_session_journal_file, get_todo_path, get_session_todo_path};
+use session_journal::{JournalEntry, SessionJournal, TranscriptEntry};
+
+/// Appended to the partial assistant message of an interrupted response
+pub const INTERRUPTED_MARKER: &str = "[Response interrupted by the user]";
+
+/// Larger screenshots are left on disk instead of being sent to the model;
+/// providers reject images over about 5 MB
+const MAX_ATTACHED_IMAGE_BYTES: usize = 5 * 1024 * 1024;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ToolCall {
@@ -66,6 +109,8 @@ pub use webdriver_session::WebDriverSession;
 /// Options for fast-start discovery execution
 #[derive(Debug, Clone)]
 pub struct DiscoveryOptions<'a> {
+    /// Tool call messages to play back, each optionally followed by a User
+    /// message with its precomputed result
     pub messages: &'a [Message],
     pub fast_start_path: Option<&'a str>,
 }
@@ -90,6 +135,10 @@ pub struct ContextWindow {
     pub cumulative_tokens: u32, // Track cumulative tokens across all interactions
     pub conversation_history: Vec<Message>,
     pub last_thinning_percentage: u32, // Track the last percentage at which we thinned
+    pub last_compaction_percentage: u32, // Track the last percentage at which we compacted
+    pub pinned_message_ids: HashSet<String>, // Messages no retention policy may drop
+    pub retention_policy: Option<Arc<dyn RetentionPolicy>>,
+    pub token_counter: Option<Arc<dyn TokenCounter>>, // The active model's tokenizer, if known
 }
 
 impl ContextWindow {
@@ -100,6 +149,10 @@ impl ContextWindow {
             cumulative_tokens: 0,
             conversation_history: Vec::new(),
             last_thinning_percentage: 0,
+            last_compaction_percentage: 0,
+            pinned_message_ids: HashSet::new(),
+            retention_policy: None,
+            token_counter: None,
         }
     }
 
@@ -115,8 +168,8 @@ impl ContextWindow {
             return;
         }
 
-        // Use provided token count if available, otherwise estimate
-        let token_count = tokens.unwrap_or_else(|| Self::estimate_tokens(&message.content));
+        // Use provided token count if available, otherwise count
+        let token_count = tokens.unwrap_or_else(|| self.count_tokens(&message.content));
         self.used_tokens += token_count;
         self.cumulative_tokens += token_count;
         self.conversation_history.push(message);
@@ -144,8 +197,27 @@ impl ContextWindow {
         );
     }
 
+    /// Tokens `text` takes up: counted with the model's tokenizer when the
+    /// provider has one, estimated otherwise
+    pub fn count_tokens(&self, text: &str) -> u32 {
+        match &self.token_counter {
+            Some(counter) => counter.count(text),
+            None => Self::estimate_tokens(text),
+        }
+    }
+
+    /// Count with `counter` from now on (e.g. after a provider switch),
+    /// recounting the history with it
+    pub fn set_token_counter(&mut self, counter: Option<Arc<dyn TokenCounter>>) {
+        if let Some(counter) = &counter {
+            debug!("Counting context tokens with {}", counter.name());
+        }
+        self.token_counter = counter;
+        self.recalculate_tokens();
+    }
+
     /// More accurate token estimation
-    fn estimate_tokens(text: &str) -> u32 {
+    pub(crate) fn estimate_tokens(text: &str) -> u32 {
         // Better heuristic:
         // - Average English text: ~4 characters per token
         // - Code/JSON: ~3 characters per token (more symbols)
@@ -183,6 +255,47 @@ impl ContextWindow {
         }
     }
 
+    /// Replace the README system message (always at index 1, right after the
+    /// system prompt) with `content`, keeping its id, pin and position.
+    /// Returns false if the history has no README message.
+    pub fn replace_readme(&mut self, content: String) -> bool {
+        let Some(readme_id) = self
+            .conversation_history
+            .get(1)
+            .filter(|m| {
+                matches!(m.role, MessageRole::System)
+                    && (m.content.contains("Project README")
+                        || m.content.contains("Agent Configuration"))
+            })
+            .map(|m| m.id.clone())
+        else {
+            return false;
+        };
+        self.replace_content(&readme_id, content)
+    }
+
+    /// Replace the content of the message with id `message_id` in place.
+    /// Returns false if no message has that id.
+    pub fn replace_content(&mut self, message_id: &str, content: String) -> bool {
+        let new_tokens = self.count_tokens(&content);
+        let Some(index) = self
+            .conversation_history
+            .iter()
+            .position(|m| m.id == message_id)
+        else {
+            return false;
+        };
+        let old_tokens = self.count_tokens(&self.conversation_history[index].content);
+        self.conversation_history[index].content = content;
+        self.used_tokens = self.used_tokens.saturating_sub(old_tokens) + new_tokens;
+        true
+    }
+
+    /// The conversation as a shareable Markdown or HTML transcript
+    pub fn export(&self, format: transcript_export::ExportFormat) -> String {
+        transcript_export::render(self, format, &[], None)
+    }
+
     /// Clear the conversation history while preserving system messages
     /// Used by /clear command to start fresh
     pub fn clear_conversation(&mut self) {
@@ -195,9 +308,10 @@ impl ContextWindow {
         
         self.conversation_history = system_messages;
         self.used_tokens = self.conversation_history.iter()
-            .map(|m| Self::estimate_tokens(&m.content))
+            .map(|m| self.count_tokens(&m.content))
             .sum();
         self.last_thinning_percentage = 0;
+        self.last_compaction_percentage = 0;
     }
 
     pub fn remaining_tokens(&self) -> u32 {
@@ -256,6 +370,14 @@ Format this as a detailed but concise summary that can be used to resume the con
             }
         });
 
+        let pinned_messages: Vec<Message> = self
+            .conversation_history
+            .iter()
+            .skip(if readme_message.is_some() { 2 } else { 1 })
+            .filter(|m| self.is_pinned(m))
+            .cloned()
+            .collect();
+
         // Clear the conversation history
         self.conversation_history.clear();
         self.used_tokens = 0;
@@ -270,6 +392,11 @@ Format this as a detailed but concise summary that can be used to resume the con
             self.add_message(readme);
         }
 
+        // Re-add any other pinned messages (e.g. the current requirements)
+        for message in pinned_messages {
+            self.add_message(message);
+        }
+
         // Add the summary as a system message
         let summary_message = Message::new(
             MessageRole::System,
@@ -282,6 +409,8 @@ Format this as a detailed but concise summary that can be used to resume the con
             self.add_message(Message::new(MessageRole::User, user_msg));
         }
 
+        self.last_compaction_percentage = 0;
+
         let new_chars: usize = self
             .conversation_history
             .iter()
@@ -797,11 +926,189 @@ Format this as a detailed but concise summary that can be used to resume the con
         }
     }
 
+    /// Check if we should compact the oldest history into a summary note
+    /// Triggers once usage reaches `threshold` percent (0 disables), then again
+    /// every further 10% if compaction didn't bring usage back under it
+    pub fn should_compact(&self, threshold: u32) -> bool {
+        let current_percentage = self.percentage_used() as u32;
+
+        if threshold == 0 || current_percentage < threshold {
+            return false;
+        }
+
+        self.last_compaction_percentage == 0
+            || current_percentage >= self.last_compaction_percentage + 10
+    }
+
+    /// Condense the older half of the conversation into a system note, merging
+    /// older notes into higher-level ones (see the compaction module)
+    /// Returns a summary message about what was compacted
+    pub fn compact_history(&mut self, threshold: u32) -> (String, usize) {
+        let current_percentage = self.percentage_used() as u32;
+
+        // Pinned messages (e.g. the requirements) stay out of the note
+        let split =
+            retention::pinned_prefix_len(&self.conversation_history, &self.pinned_message_ids);
+        let Some(compacted) = compaction::compact(&self.conversation_history[split..]) else {
+            self.last_compaction_percentage = current_percentage;
+            return (
+                format!(
+                    "ℹ Context compaction triggered at {}% but the conversation is too short to compact",
+                    current_percentage
+                ),
+                0,
+            );
+        };
+
+        let old_chars: usize = self
+            .conversation_history
+            .iter()
+            .map(|m| m.content.len())
+            .sum();
+        self.conversation_history.truncate(split);
+        self.conversation_history.extend(compacted.history);
+        self.recalculate_tokens();
+        let new_chars: usize = self
+            .conversation_history
+            .iter()
+            .map(|m| m.content.len())
+            .sum();
+        let chars_saved = old_chars.saturating_sub(new_chars);
+
+        // Only back off if compaction didn't get us under the threshold
+        let new_percentage = self.percentage_used() as u32;
+        self.last_compaction_percentage = if new_percentage >= threshold {
+            new_percentage
+        } else {
+            0
+        };
+
+        let merged = if compacted.notes_merged > 0 {
+            format!(", {} older notes merged", compacted.notes_merged)
+        } else {
+            String::new()
+        };
+        (
+            format!(
+                "🗜️ Context compacted at {}%: {} older messages condensed into a summary note{}, ~{} chars saved",
+                current_percentage, compacted.messages_compacted, merged, chars_saved
+            ),
+            chars_saved,
+        )
+    }
+
+    /// Add a message that no retention policy or compaction may drop
+    pub fn add_pinned_message(&mut self, message: Message) {
+        self.pin(&message.id);
+        self.add_message(message);
+    }
+
+    /// Pin a message already in the history by its id
+    pub fn pin(&mut self, message_id: &str) {
+        if !message_id.is_empty() {
+            self.pinned_message_ids.insert(message_id.to_string());
+        }
+    }
+
+    pub fn unpin(&mut self, message_id: &str) {
+        self.pinned_message_ids.remove(message_id);
+    }
+
+    /// Drop a message by its id (`/context evict`), returning the tokens it
+    /// took. Pinned messages have to be unpinned first.
+    pub fn evict(&mut self, message_id: &str) -> Result<u32> {
+        let Some(index) = self
+            .conversation_history
+            .iter()
+            .position(|m| m.id == message_id)
+        else {
+            anyhow::bail!("No message with id '{}' in the context", message_id);
+        };
+        if self.is_pinned(&self.conversation_history[index]) {
+            anyhow::bail!("Message {} is pinned; unpin it first", message_id);
+        }
+        let message = self.conversation_history.remove(index);
+        let tokens = self.count_tokens(&message.content);
+        self.used_tokens = self.used_tokens.saturating_sub(tokens);
+        Ok(tokens)
+    }
+
+    pub fn is_pinned(&self, message: &Message) -> bool {
+        retention::is_pinned(message, &self.pinned_message_ids)
+    }
+
+    /// Set the policy that decides what to drop once the context overflows;
+    /// `None` leaves it to LLM summarization
+    pub fn set_retention_policy(&mut self, policy: Option<Arc<dyn RetentionPolicy>>) {
+        self.retention_policy = policy;
+    }
+
+    /// Apply the retention policy, trimming the history to half the window
+    /// Returns None if no policy is set, otherwise a summary message about
+    /// what was dropped and the chars saved
+    pub fn apply_retention(&mut self) -> Option<(String, usize)> {
+        let policy = self.retention_policy.clone()?;
+        let current_percentage = self.percentage_used() as u32;
+
+        let old_len = self.conversation_history.len();
+        let old_chars: usize = self
+            .conversation_history
+            .iter()
+            .map(|m| m.content.len())
+            .sum();
+        self.conversation_history = policy.retain(
+            &self.conversation_history,
+            &self.pinned_message_ids,
+            self.retention_budget(),
+        );
+        self.recalculate_tokens();
+        let new_chars: usize = self
+            .conversation_history
+            .iter()
+            .map(|m| m.content.len())
+            .sum();
+        let chars_saved = old_chars.saturating_sub(new_chars);
+
+        Some((
+            format!(
+                "✂️ Context at {}%: {} retention went from {} to {} messages, ~{} chars saved",
+                current_percentage,
+                policy.name(),
+                old_len,
+                self.conversation_history.len(),
+                chars_saved
+            ),
+            chars_saved,
+        ))
+    }
+
+    /// Half the window, in the length estimates retention policies measure
+    /// messages with
+    fn retention_budget(&self) -> u32 {
+        let budget = self.total_tokens / 2;
+        if self.token_counter.is_none() {
+            return budget;
+        }
+        let (estimated, counted) =
+            self.conversation_history
+                .iter()
+                .fold((0u64, 0u64), |(estimated, counted), m| {
+                    (
+                        estimated + Self::estimate_tokens(&m.content) as u64,
+                        counted + self.count_tokens(&m.content) as u64,
+                    )
+                });
+        if counted == 0 {
+            return budget;
+        }
+        (budget as u64 * estimated / counted).min(u32::MAX as u64) as u32
+    }
+
     /// Recalculate token usage based on current conversation history
     fn recalculate_tokens(&mut self) {
         let mut total = 0;
         for message in &self.conversation_history {
-            total += Self::estimate_tokens(&message.content);
+            total += self.count_tokens(&message.content);
         }
         self.used_tokens = total;
 
@@ -860,15 +1167,55 @@ pub struct Agent<W: UiWriter> {
         >,
     >,
     webdriver_process: std::sync::Arc<tokio::sync::RwLock<Option<tokio::process::Child>>>,
-    macax_controller:
-        std::sync::Arc<tokio::sync::RwLock<Option<g3_computer_control::MacAxController>>>,
+    macax_controller: std::sync::Arc<
+        tokio::sync::RwLock<Option<Box<dyn g3_computer_control::AccessibilityController>>>,
+    >,
+    /// Latest accessibility tree snapshot per application, for macax_snapshot diffs
+    macax_snapshots: std::collections::HashMap<String, g3_computer_control::macax::AXSnapshot>,
+    /// UI flow being recorded with recording_start, if any
+    recorder: g3_computer_control::Recorder,
     tool_call_count: usize,
     requirements_sha: Option<String>,
+    /// Where request_review sends review requests, for a flock segment's worker
+    review_mailbox: Option<peer_review::ReviewMailbox>,
     /// Working directory for tool execution (set by --codebase-fast-start)
     working_dir: Option<String>,
     background_process_manager: std::sync::Arc<background_process::BackgroundProcessManager>,
     /// Pending images to attach to the next user message
     pending_images: Vec<g3_providers::ImageContent>,
+    /// Pin the next task message (e.g. the requirements) in the context window
+    pin_next_task: bool,
+    /// Provider switches reported by the failover wrapper, not yet shown
+    provider_status: std::sync::Arc<std::sync::Mutex<Vec<String>>>,
+    /// Ledger every completion's token usage and cost is appended to
+    usage_ledger: usage::UsageLedger,
+    /// Token usage and cost of this session's completions
+    session_usage: usage::UsageTotals,
+    /// Journal of this session's activity, replayed by `g3 --resume`
+    session_journal: Option<SessionJournal>,
+    /// Conversation branches; the current one is the context window
+    branches: branches::Branches,
+    /// Tool whose last call failed schema validation and was sent back for a retry
+    schema_retry_tool: Option<String>,
+    /// Masks the configured credentials in session logs and transcripts
+    redactor: Redactor,
+    /// Trace span of the provider request being streamed; closed when its
+    /// usage is recorded, so it covers the whole stream
+    provider_span: Option<tracing::Span>,
+    /// Flags edits to README.md and AGENTS.md, reloaded before the next task
+    readme_watcher: Option<readme_watcher::ReadmeWatcher>,
+    /// AGENTS.md files of the subdirectories tool calls have touched
+    agents_md: agents_md::AgentsMdLayers,
+    /// Pinned system message holding those files' instructions
+    agents_md_message_id: Option<String>,
+    /// Language servers behind get_diagnostics and hover, started on first use
+    lsp: lsp::LspManager,
+    /// Plays a sound or announcement when a tool call needs approval
+    notifier: notifier::Notifier,
+    /// "Always" answers to tool approval prompts
+    session_approvals: permissions::SessionApprovals,
+    /// Cancellation token of the task in flight; checked between stream chunks
+    cancellation_token: CancellationToken,
 }
 
 impl<W: UiWriter> Agent<W> {
@@ -950,7 +1297,7 @@ impl<W: UiWriter> Agent<W> {
 
         // In autonomous mode, we need to register both coach and player providers
         // Otherwise, only register the default provider
-        let providers_to_register: Vec<String> = if is_autonomous {
+        let mut providers_to_register: Vec<String> = if is_autonomous {
             let mut providers = vec![config.providers.default_provider.clone()];
             if let Some(coach) = &config.providers.coach {
                 if !providers.contains(coach) {
@@ -967,105 +1314,48 @@ impl<W: UiWriter> Agent<W> {
             vec![config.providers.default_provider.clone()]
         };
 
-        // Only register providers that are configured AND selected
-        // This prevents unnecessary initialization of heavy providers like embedded models
-
-        // Helper to check if a provider ref should be registered
-        let should_register = |provider_type: &str, config_name: &str| -> bool {
-            let full_ref = format!("{}.{}", provider_type, config_name);
-            providers_to_register.iter().any(|p| p == &full_ref || p.starts_with(&format!("{}.", provider_type)))
-        };
-
-        // Register embedded providers from HashMap
-        for (name, embedded_config) in &config.providers.embedded {
-            if should_register("embedded", name) {
-                let embedded_provider = g3_providers::EmbeddedProvider::new(
-                    embedded_config.model_path.clone(),
-                    embedded_config.model_type.clone(),
-                    embedded_config.context_length,
-                    embedded_config.max_tokens,
-                    embedded_config.temperature,
-                    embedded_config.gpu_layers,
-                    embedded_config.threads,
-                )?;
-                providers.register(embedded_provider);
-            }
-        }
-
-        // Register OpenAI providers from HashMap
-        for (name, openai_config) in &config.providers.openai {
-            if should_register("openai", name) {
-                let openai_provider = g3_providers::OpenAIProvider::new_with_name(
-                    format!("openai.{}", name),
-                    openai_config.api_key.clone(),
-                    Some(openai_config.model.clone()),
-                    openai_config.base_url.clone(),
-                    openai_config.max_tokens,
-                    openai_config.temperature,
-                )?;
-                providers.register(openai_provider);
-            }
-        }
-
-        // Register OpenAI-compatible providers (e.g., OpenRouter, Groq, etc.)
-        for (name, openai_config) in &config.providers.openai_compatible {
-            if should_register(name, "default") {
-                let openai_provider = g3_providers::OpenAIProvider::new_with_name(
-                    name.clone(),
-                    openai_config.api_key.clone(),
-                    Some(openai_config.model.clone()),
-                    openai_config.base_url.clone(),
-                    openai_config.max_tokens,
-                    openai_config.temperature,
-                )?;
-                providers.register(openai_provider);
+        // Fallback providers are registered too, then wrapped with the default below
+        for fallback in &config.providers.fallback_providers {
+            if !providers_to_register.contains(fallback) {
+                providers_to_register.push(fallback.clone());
             }
         }
 
-        // Register Anthropic providers from HashMap
-        for (name, anthropic_config) in &config.providers.anthropic {
-            if should_register("anthropic", name) {
-                let anthropic_provider = g3_providers::AnthropicProvider::new_with_name(
-                    format!("anthropic.{}", name),
-                    anthropic_config.api_key.clone(),
-                    Some(anthropic_config.model.clone()),
-                    anthropic_config.max_tokens,
-                    anthropic_config.temperature,
-                    anthropic_config.cache_config.clone(),
-                    anthropic_config.enable_1m_context,
-                    anthropic_config.thinking_budget_tokens,
-                )?;
-                providers.register(anthropic_provider);
+        Self::register_providers(&config, &providers_to_register, &mut providers).await?;
+
+        let provider_status = std::sync::Arc::new(std::sync::Mutex::new(Vec::new()));
+
+        Self::limit_provider_rates(&config, &mut providers, &provider_status);
+
+        // Wrap the default provider and its fallbacks so requests fail over between them
+        if !config.providers.fallback_providers.is_empty() {
+            let default_name = &config.providers.default_provider;
+            let mut chain = vec![providers
+                .remove(default_name)
+                .ok_or_else(|| anyhow::anyhow!("Provider '{}' not found", default_name))?];
+            for fallback in &config.providers.fallback_providers {
+                // Coach and player are looked up by name, so they can't be moved into the chain
+                let in_use = fallback == default_name
+                    || config.providers.coach.as_ref() == Some(fallback)
+                    || config.providers.player.as_ref() == Some(fallback);
+                if in_use {
+                    warn!("Skipping fallback provider '{}': it is already in use", fallback);
+                    continue;
+                }
+                if let Some(provider) = providers.remove(fallback) {
+                    chain.push(provider);
+                }
             }
-        }
-
-        // Register Databricks providers from HashMap
-        for (name, databricks_config) in &config.providers.databricks {
-            if should_register("databricks", name) {
-                let databricks_provider = if let Some(token) = &databricks_config.token {
-                    // Use token-based authentication
-                    g3_providers::DatabricksProvider::from_token_with_name(
-                        format!("databricks.{}", name),
-                        databricks_config.host.clone(),
-                        token.clone(),
-                        databricks_config.model.clone(),
-                        databricks_config.max_tokens,
-                        databricks_config.temperature,
-                    )?
-                } else {
-                    // Use OAuth authentication
-                    g3_providers::DatabricksProvider::from_oauth_with_name(
-                        format!("databricks.{}", name),
-                        databricks_config.host.clone(),
-                        databricks_config.model.clone(),
-                        databricks_config.max_tokens,
-                        databricks_config.temperature,
-                    )
-                    .await?
-                };
 
-                providers.register(databricks_provider);
-            }
+            let status_queue = provider_status.clone();
+            let failover = g3_providers::FailoverProvider::new(chain)?.with_status_callback(
+                std::sync::Arc::new(move |status: &str| {
+                    if let Ok(mut queue) = status_queue.lock() {
+                        queue.push(status.to_string());
+                    }
+                }),
+            );
+            providers.register(failover);
         }
 
         // Set default provider
@@ -1081,6 +1371,8 @@ impl<W: UiWriter> Agent<W> {
         let context_length =
             Self::get_configured_context_length(&config, &providers, &mut context_warnings)?;
         let mut context_window = ContextWindow::new(context_length);
+        context_window.set_retention_policy(retention::policy_from_config(&config.agent.retention)?);
+        context_window.set_token_counter(providers.get(None)?.token_counter());
 
         // Surface any context warnings to the user via UI
         for warning in context_warnings {
@@ -1097,23 +1389,55 @@ impl<W: UiWriter> Agent<W> {
             // Use custom system prompt (for agent mode)
             custom_prompt
         } else {
-            // Use default system prompt based on provider capabilities
-            if provider_has_native_tool_calling {
-                // For native tool calling providers, use a more explicit system prompt
-                get_system_prompt_for_native(config.agent.allow_multiple_tool_calls)
-            } else {
-                // For non-native providers (embedded models), use JSON format instructions
-                SYSTEM_PROMPT_FOR_NON_NATIVE_TOOL_USE.to_string()
-            }
+            // Use default system prompt based on provider capabilities: native
+            // tool calling providers get a more explicit prompt, non-native ones
+            // (embedded models) JSON format instructions. Either may be
+            // overridden in ~/.g3/prompts/.
+            default_system_prompt(
+                provider_has_native_tool_calling,
+                config.agent.allow_multiple_tool_calls,
+            )?
         };
 
         let system_message = Message::new(MessageRole::System, system_prompt);
-        context_window.add_message(system_message);
+        context_window.add_pinned_message(system_message);
+
+        if config.agent.read_only {
+            let explain_prompt = prompt_templates::core().text("explain_mode")?;
+            context_window.add_pinned_message(Message::new(MessageRole::System, explain_prompt));
+        }
 
         // If README content is provided, add it as a second system message (after the main system prompt)
-        if let Some(readme) = readme_content {
+        // and watch the files it came from, so mid-session edits are picked up
+        let readme_watcher = if let Some(readme) = readme_content {
             let readme_message = Message::new(MessageRole::System, readme);
-            context_window.add_message(readme_message);
+            context_window.add_pinned_message(readme_message);
+            match std::env::current_dir()
+                .map_err(anyhow::Error::from)
+                .and_then(|dir| readme_watcher::ReadmeWatcher::new(&dir))
+            {
+                Ok(watcher) => Some(watcher),
+                Err(e) => {
+                    warn!("README hot reload disabled: {}", e);
+                    None
+                }
+            }
+        } else {
+            None
+        };
+
+        // Summarize recent git activity below the README
+        if config.agent.git_context_commits > 0 {
+            let dir = std::env::current_dir().unwrap_or_else(|_| PathBuf::from("."));
+            if let Some(summary) =
+                gitinfo::summarize(&dir, &[], config.agent.git_context_commits)
+            {
+                let git_message = Message::new(
+                    MessageRole::System,
+                    format!("{} (at session start):\n\n{}", gitinfo::GIT_CONTEXT_HEADER, summary),
+                );
+                context_window.add_pinned_message(git_message);
+            }
         }
 
         // NOTE: TODO lists are now session-scoped and stored in .g3/sessions/<session_id>/todo.g3.md
@@ -1135,11 +1459,17 @@ impl<W: UiWriter> Agent<W> {
 
         // Capture macax_enabled before moving config
         let macax_enabled = config.macax.enabled;
+        let lsp = lsp::LspManager::new(
+            std::env::current_dir().unwrap_or_else(|_| PathBuf::from(".")),
+            config.lsp.clone(),
+        );
+        let notifier = notifier::Notifier::new(config.notifications.clone());
 
         Ok(Self {
             providers,
             context_window,
             auto_compact: config.agent.auto_compact,
+            redactor: config.redactor(),
             pending_90_summarization: false,
             thinning_events: Vec::new(),
             summarization_events: Vec::new(),
@@ -1157,19 +1487,39 @@ impl<W: UiWriter> Agent<W> {
             webdriver_process: std::sync::Arc::new(tokio::sync::RwLock::new(None)),
             macax_controller: {
                 std::sync::Arc::new(tokio::sync::RwLock::new(if macax_enabled {
-                    Some(g3_computer_control::MacAxController::new()?)
+                    Some(g3_computer_control::create_accessibility_controller()?)
                 } else {
                     None
                 }))
             },
+            macax_snapshots: std::collections::HashMap::new(),
+            recorder: g3_computer_control::Recorder::default(),
             tool_call_count: 0,
             requirements_sha: None,
+            review_mailbox: None,
             working_dir: None,
             background_process_manager: std::sync::Arc::new(
                 background_process::BackgroundProcessManager::new(
                     paths::get_logs_dir().join("background_processes")
                 )),
             pending_images: Vec::new(),
+            pin_next_task: false,
+            provider_status,
+            usage_ledger: usage::UsageLedger::at_default_path(),
+            session_usage: usage::UsageTotals::default(),
+            session_journal: None,
+            branches: branches::Branches::default(),
+            schema_retry_tool: None,
+            provider_span: None,
+            readme_watcher,
+            agents_md: agents_md::AgentsMdLayers::new(
+                std::env::current_dir().unwrap_or_else(|_| PathBuf::from(".")),
+            ),
+            agents_md_message_id: None,
+            lsp,
+            notifier,
+            session_approvals: permissions::SessionApprovals::default(),
+            cancellation_token: CancellationToken::new(),
         })
     }
 
@@ -1246,6 +1596,10 @@ impl<W: UiWriter> Agent<W> {
             "openai" => config.providers.openai.get(config_name)?.max_tokens,
             "databricks" => config.providers.databricks.get(config_name)?.max_tokens,
             "embedded" => config.providers.embedded.get(config_name)?.max_tokens,
+            "local" => config.providers.local.get(config_name)?.max_tokens,
+            "gemini" => config.providers.gemini.get(config_name)?.max_tokens,
+            "bedrock" => config.providers.bedrock.get(config_name)?.max_tokens,
+            "mock" => config.providers.mock.get(config_name)?.max_tokens,
             _ => None,
         }
     }
@@ -1266,6 +1620,10 @@ impl<W: UiWriter> Agent<W> {
             "openai" => config.providers.openai.get(config_name)?.temperature,
             "databricks" => config.providers.databricks.get(config_name)?.temperature,
             "embedded" => config.providers.embedded.get(config_name)?.temperature,
+            "local" => config.providers.local.get(config_name)?.temperature,
+            "gemini" => config.providers.gemini.get(config_name)?.temperature,
+            "bedrock" => config.providers.bedrock.get(config_name)?.temperature,
+            "mock" => config.providers.mock.get(config_name)?.temperature,
             _ => None,
         }
     }
@@ -1562,29 +1920,242 @@ impl<W: UiWriter> Agent<W> {
         }
     }
 
-    fn get_configured_context_length(
+    /// Create and register the configured providers `providers_to_register`
+    /// names, along with the other configs of the same provider types
+    async fn register_providers(
         config: &Config,
-        providers: &ProviderRegistry,
-        warnings: &mut Vec<String>,
-    ) -> Result<u32> {
-        // First, check if there's a global max_context_length override in agent config
-        if let Some(max_context_length) = config.agent.max_context_length {
-            debug!(
-                "Using configured agent.max_context_length: {}",
-                max_context_length
-            );
-            return Ok(max_context_length);
-        }
+        providers_to_register: &[String],
+        providers: &mut ProviderRegistry,
+    ) -> Result<()> {
+        // Only register providers that are configured AND selected
+        // This prevents unnecessary initialization of heavy providers like embedded models
 
-        // Get the active provider to determine context length
-        let provider = providers.get(None)?;
-        let provider_name = provider.name();
-        let model_name = provider.model();
+        // Helper to check if a provider ref should be registered
+        let should_register = |provider_type: &str, config_name: &str| -> bool {
+            let full_ref = format!("{}.{}", provider_type, config_name);
+            providers_to_register.iter().any(|p| p == &full_ref || p.starts_with(&format!("{}.", provider_type)))
+        };
 
-        // Parse provider name to get type and config name
-        let parts: Vec<&str> = provider_name.split('.').collect();
-        let (provider_type, config_name) = if parts.len() == 2 {
-            (parts[0], parts[1])
+        // Register embedded providers from HashMap
+        for (name, embedded_config) in &config.providers.embedded {
+            if should_register("embedded", name) {
+                let embedded_provider = g3_providers::EmbeddedProvider::new(
+                    embedded_config.model_path.clone(),
+                    embedded_config.model_type.clone(),
+                    embedded_config.context_length,
+                    embedded_config.max_tokens,
+                    embedded_config.temperature,
+                    embedded_config.gpu_layers,
+                    embedded_config.threads,
+                )?;
+                providers.register(embedded_provider);
+            }
+        }
+
+        // Register OpenAI providers from HashMap
+        for (name, openai_config) in &config.providers.openai {
+            if should_register("openai", name) {
+                let openai_provider = g3_providers::OpenAIProvider::new_with_name(
+                    format!("openai.{}", name),
+                    openai_config.api_key.clone(),
+                    Some(openai_config.model.clone()),
+                    openai_config.base_url.clone(),
+                    openai_config.max_tokens,
+                    openai_config.temperature,
+                )?;
+                providers.register(openai_provider);
+            }
+        }
+
+        // Register OpenAI-compatible providers (e.g., OpenRouter, Groq, etc.)
+        for (name, openai_config) in &config.providers.openai_compatible {
+            if should_register(name, "default") {
+                let openai_provider = g3_providers::OpenAIProvider::new_with_name(
+                    name.clone(),
+                    openai_config.api_key.clone(),
+                    Some(openai_config.model.clone()),
+                    openai_config.base_url.clone(),
+                    openai_config.max_tokens,
+                    openai_config.temperature,
+                )?;
+                providers.register(openai_provider);
+            }
+        }
+
+        // Register local OpenAI-compatible servers (Ollama, vLLM, LM Studio)
+        for (name, local_config) in &config.providers.local {
+            if should_register("local", name) {
+                let mut local_provider = g3_providers::LocalProvider::new_with_name(
+                    format!("local.{}", name),
+                    local_config.model.clone(),
+                    local_config.base_url.clone(),
+                    local_config.api_key.clone(),
+                    local_config.max_tokens,
+                    local_config.temperature,
+                    local_config.native_tools.unwrap_or(true),
+                )?;
+                if let Some(tokenizer) = &local_config.tokenizer {
+                    let path = shellexpand::tilde(tokenizer);
+                    local_provider =
+                        local_provider.with_tokenizer(std::path::Path::new(path.as_ref()))?;
+                }
+                providers.register(local_provider);
+            }
+        }
+
+        // Register Google Gemini providers
+        for (name, gemini_config) in &config.providers.gemini {
+            if should_register("gemini", name) {
+                let gemini_provider = g3_providers::GeminiProvider::new_with_name(
+                    format!("gemini.{}", name),
+                    gemini_config.api_key.clone(),
+                    Some(gemini_config.model.clone()),
+                    gemini_config.base_url.clone(),
+                    gemini_config.max_tokens,
+                    gemini_config.temperature,
+                )?;
+                providers.register(gemini_provider);
+            }
+        }
+
+        // Register AWS Bedrock providers
+        for (name, bedrock_config) in &config.providers.bedrock {
+            if should_register("bedrock", name) {
+                let credentials = g3_providers::bedrock::AwsCredentials::resolve(
+                    bedrock_config.access_key_id.clone(),
+                    bedrock_config.secret_access_key.clone(),
+                    bedrock_config.session_token.clone(),
+                    bedrock_config.profile.as_deref(),
+                )?;
+                let bedrock_provider = g3_providers::BedrockProvider::new_with_name(
+                    format!("bedrock.{}", name),
+                    bedrock_config.model.clone(),
+                    g3_providers::bedrock::resolve_region(bedrock_config.region.clone()),
+                    credentials,
+                    bedrock_config.max_tokens,
+                    bedrock_config.temperature,
+                    bedrock_config.native_tools.unwrap_or(true),
+                )?;
+                providers.register(bedrock_provider);
+            }
+        }
+
+        // Register mock providers answering from fixture files
+        for (name, mock_config) in &config.providers.mock {
+            if should_register("mock", name) {
+                let mock_provider = g3_providers::MockProvider::new_with_name(
+                    format!("mock.{}", name),
+                    &mock_config.fixtures_dir,
+                    mock_config.model.clone(),
+                    mock_config.max_tokens,
+                    mock_config.temperature,
+                    mock_config.native_tools.unwrap_or(true),
+                );
+                providers.register(mock_provider);
+            }
+        }
+
+        // Register Anthropic providers from HashMap
+        for (name, anthropic_config) in &config.providers.anthropic {
+            if should_register("anthropic", name) {
+                let anthropic_provider = g3_providers::AnthropicProvider::new_with_name(
+                    format!("anthropic.{}", name),
+                    anthropic_config.api_key.clone(),
+                    Some(anthropic_config.model.clone()),
+                    anthropic_config.max_tokens,
+                    anthropic_config.temperature,
+                    anthropic_config.cache_config.clone(),
+                    anthropic_config.enable_1m_context,
+                    anthropic_config.thinking_budget_tokens,
+                )?;
+                providers.register(anthropic_provider);
+            }
+        }
+
+        // Register Databricks providers from HashMap
+        for (name, databricks_config) in &config.providers.databricks {
+            if should_register("databricks", name) {
+                let databricks_provider = if let Some(token) = &databricks_config.token {
+                    // Use token-based authentication
+                    g3_providers::DatabricksProvider::from_token_with_name(
+                        format!("databricks.{}", name),
+                        databricks_config.host.clone(),
+                        token.clone(),
+                        databricks_config.model.clone(),
+                        databricks_config.max_tokens,
+                        databricks_config.temperature,
+                    )?
+                } else {
+                    // Use OAuth authentication
+                    g3_providers::DatabricksProvider::from_oauth_with_name(
+                        format!("databricks.{}", name),
+                        databricks_config.host.clone(),
+                        databricks_config.model.clone(),
+                        databricks_config.max_tokens,
+                        databricks_config.temperature,
+                    )
+                    .await?
+                };
+
+                providers.register(databricks_provider);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Wrap every provider in `providers` that has a `[providers.rate_limits]`
+    /// entry, reporting waits to `provider_status`
+    fn limit_provider_rates(
+        config: &Config,
+        providers: &mut ProviderRegistry,
+        provider_status: &std::sync::Arc<std::sync::Mutex<Vec<String>>>,
+    ) {
+        // Queue calls to rate-limited providers instead of letting them hit 429s
+        for (name, limit) in &config.providers.rate_limits {
+            let Some(provider) = providers.remove(name) else {
+                continue;
+            };
+            let status_queue = provider_status.clone();
+            let limited = g3_providers::RateLimitedProvider::new(
+                provider,
+                g3_providers::RateLimit {
+                    requests_per_minute: limit.requests_per_minute,
+                    tokens_per_minute: limit.tokens_per_minute,
+                },
+            )
+            .with_status_callback(std::sync::Arc::new(move |status: &str| {
+                if let Ok(mut queue) = status_queue.lock() {
+                    queue.push(status.to_string());
+                }
+            }));
+            providers.register(limited);
+        }
+    }
+
+    fn get_configured_context_length(
+        config: &Config,
+        providers: &ProviderRegistry,
+        warnings: &mut Vec<String>,
+    ) -> Result<u32> {
+        // First, check if there's a global max_context_length override in agent config
+        if let Some(max_context_length) = config.agent.max_context_length {
+            debug!(
+                "Using configured agent.max_context_length: {}",
+                max_context_length
+            );
+            return Ok(max_context_length);
+        }
+
+        // Get the active provider to determine context length
+        let provider = providers.get(None)?;
+        let provider_name = provider.name();
+        let model_name = provider.model();
+
+        // Parse provider name to get type and config name
+        let parts: Vec<&str> = provider_name.split('.').collect();
+        let (provider_type, config_name) = if parts.len() == 2 {
+            (parts[0], parts[1])
         } else {
             // Fallback for simple provider names
             (provider_name, "default")
@@ -1649,6 +2220,57 @@ impl<W: UiWriter> Agent<W> {
                     16384 // Conservative default for other Databricks models
                 }
             }
+            "local" => {
+                // Local servers pick the context size at load time (e.g. Ollama's
+                // num_ctx), so only the config knows it
+                match config
+                    .providers
+                    .local
+                    .get(config_name)
+                    .and_then(|c| c.context_length)
+                {
+                    Some(context_length) => context_length,
+                    None => {
+                        warnings.push(format!(
+                            "No context_length configured for provider={}, assuming 32768 tokens",
+                            provider_name
+                        ));
+                        32768
+                    }
+                }
+            }
+            "gemini" => {
+                // max_tokens only caps the output here; the window is far larger
+                config
+                    .providers
+                    .gemini
+                    .get(config_name)
+                    .and_then(|c| c.context_length)
+                    .unwrap_or_else(|| g3_providers::gemini::context_window(model_name))
+            }
+            "bedrock" => {
+                let configured = config
+                    .providers
+                    .bedrock
+                    .get(config_name)
+                    .and_then(|c| c.context_length);
+                match configured.or_else(|| g3_providers::bedrock::context_window(model_name)) {
+                    Some(context_length) => context_length,
+                    None => {
+                        warnings.push(format!(
+                            "Unknown context window for Bedrock model {}, assuming 32768 tokens; set context_length to override",
+                            model_name
+                        ));
+                        32768
+                    }
+                }
+            }
+            "mock" => config
+                .providers
+                .mock
+                .get(config_name)
+                .and_then(|c| c.context_length)
+                .unwrap_or(200000),
             _ => config.agent.fallback_default_max_tokens as u32,
         };
 
@@ -1670,11 +2292,123 @@ impl<W: UiWriter> Agent<W> {
         self.providers.get(None)
     }
 
+    /// Use `provider_ref` (e.g. `openai.default`) for the following turns,
+    /// keeping the conversation. The context window takes the new model's
+    /// size, and the switch is refused if the conversation doesn't fit it.
+    /// Returns warnings about the new window.
+    pub async fn switch_provider(&mut self, provider_ref: &str) -> Result<Vec<String>> {
+        let config = self.config.with_provider_override(provider_ref)?;
+        if self.providers.get(Some(provider_ref)).is_err() {
+            let mut registered = ProviderRegistry::new();
+            Self::register_providers(&config, &[provider_ref.to_string()], &mut registered).await?;
+            Self::limit_provider_rates(&config, &mut registered, &self.provider_status);
+            let provider = registered
+                .remove(provider_ref)
+                .ok_or_else(|| anyhow::anyhow!("Provider '{}' not found", provider_ref))?;
+            self.providers.register_boxed(provider);
+        }
+
+        let previous = self.providers.get(None)?;
+        let (previous_name, was_native) = (
+            previous.name().to_string(),
+            previous.has_native_tool_calling(),
+        );
+        self.providers.set_default(provider_ref)?;
+        // Measure the conversation with the new model's tokenizer
+        let previous_counter = self.context_window.token_counter.clone();
+        self.context_window
+            .set_token_counter(self.providers.get(None)?.token_counter());
+
+        let mut warnings = Vec::new();
+        let context_length =
+            match Self::get_configured_context_length(&config, &self.providers, &mut warnings) {
+                Ok(length) if self.context_window.used_tokens < length => length,
+                Ok(length) => {
+                    let used_tokens = self.context_window.used_tokens;
+                    self.providers.set_default(&previous_name)?;
+                    self.context_window.set_token_counter(previous_counter);
+                    anyhow::bail!(
+                        "The conversation uses ~{} tokens, more than the {}-token window of {}; \
+                         /compact or /context evict first",
+                        used_tokens,
+                        length,
+                        provider_ref
+                    );
+                }
+                Err(e) => {
+                    self.providers.set_default(&previous_name)?;
+                    self.context_window.set_token_counter(previous_counter);
+                    return Err(e);
+                }
+            };
+        self.context_window.total_tokens = context_length;
+        if self.context_window.should_summarize() {
+            warnings.push(format!(
+                "Context is at {:.0}% of the new window",
+                self.context_window.percentage_used()
+            ));
+        }
+
+        // The default system prompts describe different tool-call formats
+        let native = self.providers.get(None)?.has_native_tool_calling();
+        if native != was_native {
+            let allow_multiple = config.agent.allow_multiple_tool_calls;
+            let old_prompt = default_system_prompt(was_native, allow_multiple)?;
+            let system_prompt_id = self
+                .context_window
+                .conversation_history
+                .first()
+                .filter(|m| m.content == old_prompt)
+                .map(|m| m.id.clone());
+            match system_prompt_id {
+                Some(id) => {
+                    let new_prompt = default_system_prompt(native, allow_multiple)?;
+                    self.context_window.replace_content(&id, new_prompt);
+                }
+                None => warnings.push(
+                    "The custom system prompt was kept; it may describe the other tool-call format"
+                        .to_string(),
+                ),
+            }
+        }
+
+        self.config = config;
+        self.checkpoint_session();
+        debug!(
+            "Switched provider from {} to {}",
+            previous_name, provider_ref
+        );
+        Ok(warnings)
+    }
+
     /// Get the current session ID for this agent
     pub fn get_session_id(&self) -> Option<&str> {
         self.session_id.as_deref()
     }
 
+    /// Token usage and estimated cost of this session so far
+    pub fn get_session_usage(&self) -> &usage::UsageTotals {
+        &self.session_usage
+    }
+
+    /// Add a completion's usage to the session totals and the usage ledger
+    fn record_usage(&mut self, usage: &g3_providers::Usage) {
+        let (provider_name, model) = match self.providers.get(None) {
+            Ok(provider) => (provider.name().to_string(), provider.model().to_string()),
+            Err(_) => return,
+        };
+        if let Some(span) = self.provider_span.take() {
+            span.record("input_tokens", usage.prompt_tokens);
+            span.record("output_tokens", usage.completion_tokens);
+            span.record("total_tokens", usage.total_tokens);
+        }
+        let record = usage::UsageRecord::new(self.session_id.clone(), &provider_name, &model, usage);
+        self.session_usage.add(&record);
+        if let Err(e) = self.usage_ledger.append(&record) {
+            warn!("Failed to record token usage: {}", e);
+        }
+    }
+
     pub async fn execute_task(
         &mut self,
         description: &str,
@@ -1743,6 +2477,12 @@ impl<W: UiWriter> Agent<W> {
         cancellation_token: CancellationToken,
         discovery_options: Option<DiscoveryOptions<'_>>,
     ) -> Result<TaskResult> {
+        let span = info_span!(
+            "agent_turn",
+            session_id = self.session_id.as_deref().unwrap_or(""),
+            autonomous = self.is_autonomous,
+            task_chars = description.len(),
+        );
         // Execute the task directly without splitting
         self.execute_single_task(
             description,
@@ -1752,6 +2492,7 @@ impl<W: UiWriter> Agent<W> {
             cancellation_token,
             discovery_options,
         )
+        .instrument(span)
         .await
     }
 
@@ -1771,6 +2512,11 @@ impl<W: UiWriter> Agent<W> {
         // Validate that the system prompt is the first message (critical invariant)
         self.validate_system_prompt_is_first();
 
+        // Pick up edits to README.md/AGENTS.md made since the last task
+        self.reload_changed_readme();
+
+        self.cancellation_token = cancellation_token.clone();
+
         // Generate session ID based on the initial prompt if this is a new session
         if self.session_id.is_none() {
             self.session_id = Some(self.generate_session_id(description));
@@ -1811,7 +2557,15 @@ impl<W: UiWriter> Agent<W> {
             user_message.images = std::mem::take(&mut self.pending_images);
         }
         
-        self.context_window.add_message(user_message);
+        if std::mem::take(&mut self.pin_next_task) {
+            self.context_window.add_pinned_message(user_message);
+        } else {
+            self.context_window.add_message(user_message);
+        }
+        self.record_journal_entry(JournalEntry::Input {
+            text: description.to_string(),
+        });
+        self.checkpoint_session();
 
         // Execute fast-discovery tool calls if provided (immediately after user message)
         if let Some(ref options) = discovery_options {
@@ -1823,18 +2577,26 @@ impl<W: UiWriter> Agent<W> {
             }
             let provider = self.providers.get(None)?;
             let supports_cache = provider.supports_cache_control();
-            let message_count = options.messages.len();
+            let mut discovery_msgs = options.messages.iter().peekable();
 
-            for (idx, discovery_msg) in options.messages.iter().enumerate() {
+            while let Some(discovery_msg) = discovery_msgs.next() {
                 if let Ok(tool_call) = serde_json::from_str::<ToolCall>(&discovery_msg.content) {
                     self.add_message_to_context(discovery_msg.clone());
-                    let result = self
-                        .execute_tool_call_in_dir(&tool_call, options.fast_start_path)
-                        .await
-                        .unwrap_or_else(|e| format!("Error: {}", e));
+                    // A User message right after the tool call is its precomputed
+                    // result (the planner runs discovery commands in a sandbox)
+                    let precomputed = discovery_msgs
+                        .next_if(|msg| matches!(msg.role, MessageRole::User))
+                        .map(|msg| msg.content.clone());
+                    let result = match precomputed {
+                        Some(result) => result,
+                        None => self
+                            .execute_tool_call_in_dir(&tool_call, options.fast_start_path)
+                            .await
+                            .unwrap_or_else(|e| format!("Error: {}", e)),
+                    };
 
                     // Add cache_control to the last user message if provider supports it (anthropic)
-                    let is_last = idx == message_count - 1;
+                    let is_last = discovery_msgs.peek().is_none();
                     let result_message = if supports_cache
                         && is_last
                         && self.count_cache_controls_in_history() < 4
@@ -1861,11 +2623,7 @@ impl<W: UiWriter> Agent<W> {
         let _has_native_tool_calling = provider.has_native_tool_calling();
         let _supports_cache_control = provider.supports_cache_control();
         let tools = if provider.has_native_tool_calling() {
-            Some(Self::create_tool_definitions(
-                self.config.webdriver.enabled,
-                self.config.macax.enabled,
-                self.config.computer_control.enabled,
-            ))
+            Some(self.tool_definitions())
         } else {
             None
         };
@@ -1891,11 +2649,13 @@ impl<W: UiWriter> Agent<W> {
 
         // Time the LLM call with cancellation support and streaming
         let llm_start = Instant::now();
+        // Biased so a stream being read gets to record its partial response
+        // before the future is dropped; the second branch only interrupts
+        // work that doesn't watch the token itself (e.g. a running tool)
         let result = tokio::select! {
+            biased;
             result = self.stream_completion(request, show_timing) => result,
             _ = cancellation_token.cancelled() => {
-                // Save context window on cancellation
-                self.save_context_window("cancelled");
                 Err(anyhow::anyhow!("Operation cancelled by user"))
             }
         };
@@ -1903,8 +2663,12 @@ impl<W: UiWriter> Agent<W> {
         let task_result = match result {
             Ok(result) => result,
             Err(e) => {
-                // Save context window on error
-                self.save_context_window("error");
+                // Save context window on cancellation or error
+                if cancellation_token.is_cancelled() {
+                    self.save_context_window("cancelled");
+                } else {
+                    self.save_context_window("error");
+                }
                 return Err(e);
             }
         };
@@ -1927,6 +2691,9 @@ impl<W: UiWriter> Agent<W> {
         if !response_content.trim().is_empty() {
             let assistant_message = Message::new(MessageRole::Assistant, response_content.clone());
             self.context_window.add_message(assistant_message);
+            self.record_journal_entry(JournalEntry::Output {
+                text: response_content.clone(),
+            });
         } else {
             debug!("Assistant response was empty (likely only tool execution), skipping message addition");
         }
@@ -1936,12 +2703,18 @@ impl<W: UiWriter> Agent<W> {
 
         // Check if we need to do 90% auto-compaction
         if self.pending_90_summarization {
-            self.ui_writer
-                .print_context_status("\n⚡ Context window reached 90% - auto-compacting...\n");
-            if let Err(e) = self.force_summarize().await {
-                warn!("Failed to auto-compact at 90%: {}", e);
+            // A retention policy, if one is set, drops messages instead of summarizing
+            if let Some((retention_summary, chars_saved)) = self.context_window.apply_retention() {
+                self.thinning_events.push(chars_saved);
+                self.ui_writer.print_context_thinning(&retention_summary);
             } else {
-                self.ui_writer.println("");
+                self.ui_writer
+                    .print_context_status("\n⚡ Context window reached 90% - auto-compacting...\n");
+                if let Err(e) = self.force_summarize().await {
+                    warn!("Failed to auto-compact at 90%: {}", e);
+                } else {
+                    self.ui_writer.println("");
+                }
             }
             self.pending_90_summarization = false;
         }
@@ -1976,12 +2749,14 @@ impl<W: UiWriter> Agent<W> {
     }
 
     /// Save the entire context window to a per-session file
-    fn save_context_window(&self, status: &str) {
+    fn save_context_window(&mut self, status: &str) {
         // Skip logging if quiet mode is enabled
         if self.quiet {
             return;
         }
 
+        self.checkpoint_session();
+
         let timestamp = std::time::SystemTime::now()
             .duration_since(std::time::UNIX_EPOCH)
             .unwrap_or_default()
@@ -2019,7 +2794,8 @@ impl<W: UiWriter> Agent<W> {
 
         match serde_json::to_string_pretty(&context_data) {
             Ok(json_content) => {
-                if let Err(e) = std::fs::write(&filename, &json_content) {
+                let json_content = self.redactor.redact(&json_content);
+                if let Err(e) = std::fs::write(&filename, json_content.as_ref()) {
                     error!("Failed to save context window to {:?}: {}", &filename, e);
                 }
             }
@@ -2029,6 +2805,108 @@ impl<W: UiWriter> Agent<W> {
         }
     }
 
+    /// Journal the context window's changes since the last checkpoint
+    fn checkpoint_session(&mut self) {
+        if self.quiet {
+            return;
+        }
+        let Some(session_id) = self.session_id.as_deref() else {
+            return;
+        };
+        let journal = self
+            .session_journal
+            .get_or_insert_with(|| {
+                SessionJournal::new(get_session_journal_file(session_id))
+                    .with_redactor(self.redactor.clone())
+            });
+        if let Err(e) = journal.record_context(&self.context_window) {
+            error!("Failed to write session journal: {}", e);
+        }
+    }
+
+    /// Journal the whole context window, for changes such as pins that
+    /// [`Agent::checkpoint_session`] doesn't see
+    fn snapshot_session(&mut self) {
+        if self.quiet {
+            return;
+        }
+        let Some(session_id) = self.session_id.as_deref() else {
+            return;
+        };
+        let journal = self
+            .session_journal
+            .get_or_insert_with(|| {
+                SessionJournal::new(get_session_journal_file(session_id))
+                    .with_redactor(self.redactor.clone())
+            });
+        if let Err(e) = journal.record_snapshot(&self.context_window) {
+            error!("Failed to write session journal: {}", e);
+        }
+    }
+
+    /// Append an entry to the session journal
+    fn record_journal_entry(&mut self, entry: JournalEntry) {
+        if self.quiet {
+            return;
+        }
+        let Some(session_id) = self.session_id.as_deref() else {
+            return;
+        };
+        let journal = self
+            .session_journal
+            .get_or_insert_with(|| {
+                SessionJournal::new(get_session_journal_file(session_id))
+                    .with_redactor(self.redactor.clone())
+            });
+        if let Err(e) = journal.append(&entry) {
+            error!("Failed to write session journal: {}", e);
+        }
+    }
+
+    /// Record a file mutation in the session's edit journal, so `/undo` and
+    /// `g3 undo` can revert it
+    fn record_file_edit(&self, path: &str, tool: &str, before: Option<String>, after: &str) {
+        let Some(session_id) = self.session_id.as_deref() else {
+            return;
+        };
+        let edit = edit_journal::FileEdit::new(path, tool, before, after.to_string());
+        if let Err(e) = edit_journal::EditJournal::for_session(session_id).record(&edit) {
+            error!("Failed to write edit journal: {}", e);
+        }
+    }
+
+    /// Revert this session's last `count` file edits (`/undo`)
+    pub fn undo_edits(&self, count: usize) -> Result<edit_journal::UndoReport> {
+        match self.session_id.as_deref() {
+            Some(session_id) => edit_journal::EditJournal::for_session(session_id).undo(count),
+            None => Ok(edit_journal::UndoReport::default()),
+        }
+    }
+
+    /// Restore a session from its journal (`g3 --resume`): the full context
+    /// window and tool-call history. Returns the transcript for display;
+    /// later activity is appended to the same journal.
+    pub fn resume_session(&mut self, session_id: &str) -> Result<Vec<TranscriptEntry>> {
+        let (journal, restored) = SessionJournal::restore(get_session_journal_file(session_id))
+            .map_err(|e| anyhow::anyhow!("Cannot resume session '{}': {}", session_id, e))?;
+
+        self.tool_call_metrics.extend(restored.tool_calls());
+        self.context_window.conversation_history = restored.history;
+        self.context_window.pinned_message_ids = restored.pinned_message_ids;
+        self.context_window.used_tokens = restored.used_tokens;
+        self.context_window.cumulative_tokens = restored.cumulative_tokens;
+        self.branches = restored.branches.unwrap_or_default();
+        self.session_id = Some(session_id.to_string());
+        self.session_journal = Some(journal.with_redactor(self.redactor.clone()));
+
+        debug!(
+            "Resumed session {} with {} messages",
+            session_id,
+            self.context_window.conversation_history.len()
+        );
+        Ok(restored.transcript)
+    }
+
     /// Format token count in compact form (e.g., 1K, 2M, 100b, 200K) and clamp to 4 chars right-aligned
     fn format_token_count(tokens: u32) -> String {
         let mut raw = if tokens >= 1_000_000_000 {
@@ -2094,7 +2972,7 @@ impl<W: UiWriter> Agent<W> {
             let _timestamp = chrono::Local::now().format("%Y-%m-%d %H:%M:%S").to_string();
 
             // Estimate tokens for this message
-            let message_tokens = ContextWindow::estimate_tokens(&message.content);
+            let message_tokens = self.context_window.count_tokens(&message.content);
 
             // Format token count
             let token_str = Self::format_token_count(message_tokens);
@@ -2137,7 +3015,8 @@ impl<W: UiWriter> Agent<W> {
 
         // Write to file
         let summary_content = summary_lines.join("");
-        if let Err(e) = std::fs::write(&filename, summary_content) {
+        let summary_content = self.redactor.redact(&summary_content);
+        if let Err(e) = std::fs::write(&filename, summary_content.as_ref()) {
             error!(
                 "Failed to write context window summary to {:?}: {}",
                 &filename, e
@@ -2179,6 +3058,55 @@ impl<W: UiWriter> Agent<W> {
         &self.context_window
     }
 
+    /// The context window message by message (`/context`)
+    pub fn context_breakdown(&self) -> String {
+        let entries = context_view::entries(&self.context_window, chrono::Local::now().time());
+        context_view::render(&self.context_window, &entries)
+    }
+
+    /// Pin, unpin or evict a message of the context window, by its number in
+    /// [`Agent::context_breakdown`] or its id
+    pub fn edit_context(
+        &mut self,
+        action: context_view::ContextAction,
+        selector: &str,
+    ) -> Result<String> {
+        let done = context_view::apply(&mut self.context_window, action, selector)?;
+        self.snapshot_session();
+        Ok(done)
+    }
+
+    /// The conversation branches (`/branch`), in the order they were made
+    pub fn branch_summaries(&self) -> Vec<branches::BranchSummary> {
+        self.branches.summaries(&self.context_window)
+    }
+
+    /// Fork the context window into a new branch, switch to another branch
+    /// or discard one
+    pub fn edit_branches(&mut self, action: branches::BranchAction, name: &str) -> Result<String> {
+        let done = match action {
+            branches::BranchAction::New => self.branches.fork(name, &self.context_window)?,
+            branches::BranchAction::Switch => {
+                self.branches.switch(name, &mut self.context_window)?
+            }
+            branches::BranchAction::Discard => self.branches.discard(name)?,
+        };
+        self.record_journal_entry(JournalEntry::Branches(self.branches.clone()));
+        self.snapshot_session();
+        Ok(done)
+    }
+
+    /// Pin the next task's user message, so no retention policy or
+    /// compaction drops it (used for the requirements in autonomous mode)
+    pub fn pin_next_task(&mut self) {
+        self.pin_next_task = true;
+    }
+
+    /// Override the configured retention policy for this agent
+    pub fn set_retention_policy(&mut self, policy: Option<Arc<dyn RetentionPolicy>>) {
+        self.context_window.set_retention_policy(policy);
+    }
+
     /// Add a message directly to the context window.
     /// Used for injecting discovery messages before the first LLM turn.
     pub fn add_message_to_context(&mut self, message: Message) {
@@ -2363,6 +3291,7 @@ impl<W: UiWriter> Agent<W> {
         // Get the summary
         match provider.complete(summary_request).await {
             Ok(summary_response) => {
+                self.record_usage(&summary_response.usage);
                 self.ui_writer
                     .print_context_status("✅ Context compacted successfully.\n");
 
@@ -2410,31 +3339,14 @@ impl<W: UiWriter> Agent<W> {
         message
     }
 
-    /// Reload README.md and AGENTS.md and replace the first system message
+    /// Reload README.md and AGENTS.md and replace the README system message
     /// Returns Ok(true) if README was found and reloaded, Ok(false) if no README was present initially
     pub fn reload_readme(&mut self) -> Result<bool> {
         debug!("Manual README reload triggered");
 
-        // Check if the second message in conversation history is a system message with README content
-        // (The first message should always be the system prompt)
-        let has_readme = self
-            .context_window
-            .conversation_history
-            .get(1) // Check the SECOND message (index 1)
-            .map(|m| {
-                matches!(m.role, MessageRole::System)
-                    && (m.content.contains("Project README")
-                        || m.content.contains("Agent Configuration"))
-            })
-            .unwrap_or(false);
-
         // Validate that the system prompt is still first
         self.validate_system_prompt_is_first();
 
-        if !has_readme {
-            return Ok(false);
-        }
-
         // Try to load README.md and AGENTS.md
         let mut combined_content = String::new();
         let mut found_any = false;
@@ -2452,20 +3364,34 @@ impl<W: UiWriter> Agent<W> {
             found_any = true;
         }
 
-        if found_any {
-            // Replace the second message (README) with the new content
-            if let Some(first_msg) = self.context_window.conversation_history.get_mut(1) {
-                first_msg.content = combined_content;
-                debug!("README content reloaded successfully");
-                Ok(true)
-            } else {
-                Ok(false)
-            }
+        // The second message (index 1) is only replaced if it already holds the README
+        if found_any && self.context_window.replace_readme(combined_content) {
+            debug!("README content reloaded successfully");
+            self.checkpoint_session();
+            Ok(true)
         } else {
             Ok(false)
         }
     }
 
+    /// Reload the README message if the watcher saw README.md or AGENTS.md change
+    fn reload_changed_readme(&mut self) {
+        let changed = self
+            .readme_watcher
+            .as_ref()
+            .is_some_and(|watcher| watcher.take_changed());
+        if !changed {
+            return;
+        }
+        match self.reload_readme() {
+            Ok(true) => self
+                .ui_writer
+                .print_context_status("📚 README.md/AGENTS.md changed on disk, reloaded into context"),
+            Ok(false) => debug!("README changed on disk but no README message to replace"),
+            Err(e) => warn!("Failed to reload README: {}", e),
+        }
+    }
+
     /// Get detailed context statistics
     pub fn get_stats(&self) -> String {
         let mut stats = String::new();
@@ -2497,6 +3423,13 @@ impl<W: UiWriter> Agent<W> {
             "   • Last Thinning:     {:>10}%\n",
             self.context_window.last_thinning_percentage
         ));
+        stats.push_str(&format!(
+            "   • Token Counting:    {:>10}\n",
+            self.context_window
+                .token_counter
+                .as_ref()
+                .map_or("estimated", |counter| counter.name())
+        ));
         stats.push('\n');
 
         // Context optimization metrics
@@ -2546,6 +3479,24 @@ impl<W: UiWriter> Agent<W> {
         }
         stats.push('\n');
 
+        // Token usage and cost
+        stats.push_str("💰 Token Usage:\n");
+        stats.push_str(&format!(
+            "   • Requests:          {:>10}\n",
+            self.session_usage.requests
+        ));
+        stats.push_str(&format!(
+            "   • Prompt Tokens:     {:>10}\n",
+            self.session_usage.prompt_tokens
+        ));
+        stats.push_str(&format!(
+            "   • Completion Tokens: {:>10}\n",
+            self.session_usage.completion_tokens
+        ));
+        let cost = format!("${:.4}", self.session_usage.cost_usd);
+        stats.push_str(&format!("   • Estimated Cost:    {:>10}\n", cost));
+        stats.push('\n');
+
         // Conversation history
         stats.push_str("💬 Conversation History:\n");
         stats.push_str(&format!(
@@ -2634,10 +3585,31 @@ impl<W: UiWriter> Agent<W> {
         &self.config
     }
 
+    /// The tools this agent can execute, as offered to native tool-calling providers
+    /// The tools offered to the model; only the read-only ones in explain mode
+    pub fn tool_definitions(&self) -> Vec<Tool> {
+        let mut tools = Self::create_tool_definitions(
+            self.config.webdriver.enabled,
+            self.config.macax.enabled,
+            self.config.computer_control.enabled,
+            self.config.lsp.enabled,
+            self.review_mailbox.is_some(),
+        );
+        if self.config.agent.read_only {
+            tools.retain(|tool| permissions::runs_read_only(&tool.name));
+        }
+        tools
+    }
+
     pub fn set_requirements_sha(&mut self, sha: String) {
         self.requirements_sha = Some(sha);
     }
 
+    /// Offer the request_review tool, for an agent working on a flock segment
+    pub fn set_review_mailbox(&mut self, mailbox: peer_review::ReviewMailbox) {
+        self.review_mailbox = Some(mailbox);
+    }
+
     /// Save a session continuation artifact
     /// Called when final_output is invoked to enable session resumption
     pub fn save_session_continuation(&self, final_output_summary: Option<String>) {
@@ -2699,7 +3671,6 @@ impl<W: UiWriter> Agent<W> {
         &mut self,
         continuation: &crate::session_continuation::SessionContinuation,
     ) -> Result<bool> {
-        use std::path::PathBuf;
         
         let session_log_path = PathBuf::from(&continuation.session_log_path);
         
@@ -2766,6 +3737,20 @@ impl<W: UiWriter> Agent<W> {
         Ok(false)
     }
 
+    /// Keep what was streamed of an interrupted response in the context,
+    /// marked so the model knows it was cut off rather than finished
+    fn record_interrupted_response(&mut self, partial: &str) {
+        self.ui_writer.flush();
+        self.ui_writer.print_context_status("\n⏹️  Generation interrupted");
+        if partial.trim().is_empty() {
+            return;
+        }
+        self.context_window.add_message(Message::new(
+            MessageRole::Assistant,
+            format!("{}\n\n{}", partial.trim(), INTERRUPTED_MARKER),
+        ));
+    }
+
     async fn stream_completion(
         &mut self,
         request: CompletionRequest,
@@ -2780,6 +3765,8 @@ impl<W: UiWriter> Agent<W> {
         enable_webdriver: bool,
         enable_macax: bool,
         enable_computer_control: bool,
+        enable_lsp: bool,
+        enable_review: bool,
     ) -> Vec<Tool> {
         let mut tools = vec![
             Tool {
@@ -2798,7 +3785,7 @@ impl<W: UiWriter> Agent<W> {
             },
             Tool {
                 name: "background_process".to_string(),
-                description: "Launch a long-running process in the background (e.g., game servers, dev servers). The process runs independently and logs are captured to a file. Use the regular 'shell' tool to read logs (cat/tail), check status (ps), or stop the process (kill). Returns the PID and log file path.".to_string(),
+                description: "Launch a long-running process in the background (e.g., game servers, dev servers). The process runs independently and logs are captured to a file. Optionally set a health check and an 'on-failure' restart policy so the process recovers automatically. Use the regular 'shell' tool to read logs (cat/tail), check status (ps), or stop the process and its children (kill -- -<pid>, since it leads its own process group). Returns the PID and log file path.".to_string(),
                 input_schema: json!({
                     "type": "object",
                     "properties": {
@@ -2808,11 +3795,71 @@ impl<W: UiWriter> Agent<W> {
                         },
                         "command": {
                             "type": "string",
-                            "description": "The shell command to execute in the background"
+                            "description": "The shell command to execute in the background. '{PORT}' is replaced with a free port (e.g. 'npm run dev -- --port {PORT}')."
+                        },
+                        "env": {
+                            "type": "object",
+                            "description": "Optional environment variables for the process. Values may use '{PORT}'.",
+                            "additionalProperties": { "type": "string" }
                         },
                         "working_dir": {
                             "type": "string",
                             "description": "Optional working directory. Defaults to current directory if not specified."
+                        },
+                        "health_check": {
+                            "type": "object",
+                            "description": "Optional health check. Set exactly one of 'tcp_port', 'http' or 'log_pattern'.",
+                            "properties": {
+                                "tcp_port": {
+                                    "type": ["integer", "string"],
+                                    "description": "Healthy while a TCP connection to 127.0.0.1:<port> succeeds. Use \"{PORT}\" for the allocated port."
+                                },
+                                "http": {
+                                    "type": "string",
+                                    "description": "Healthy while a GET to this http:// URL returns 2xx or 3xx"
+                                },
+                                "log_pattern": {
+                                    "type": "string",
+                                    "description": "Healthy once this regex appears in the log output"
+                                },
+                                "interval_secs": {
+                                    "type": "number",
+                                    "description": "Seconds between checks (default 5)"
+                                },
+                                "startup_grace_secs": {
+                                    "type": "number",
+                                    "description": "Seconds after a (re)start before failed checks count (default 10)"
+                                }
+                            }
+                        },
+                        "restart": {
+                            "type": "string",
+                            "enum": ["never", "on-failure"],
+                            "description": "Restart policy when the process exits with an error or fails its health check (default 'never')"
+                        },
+                        "max_restarts": {
+                            "type": "integer",
+                            "description": "Maximum automatic restarts with 'on-failure' (default 3)"
+                        },
+                        "restart_backoff_secs": {
+                            "type": "number",
+                            "description": "Wait before the first restart, doubled for each further one (default 1)"
+                        },
+                        "memory_limit_mb": {
+                            "type": "integer",
+                            "description": "Optional memory limit in MiB for the process and its children"
+                        },
+                        "cpu_limit": {
+                            "type": "number",
+                            "description": "Optional CPU limit in cores (e.g. 0.5). Enforced where cgroups v2 or job objects are available."
+                        },
+                        "delay_secs": {
+                            "type": "number",
+                            "description": "Run the command once after this many seconds instead of now"
+                        },
+                        "every_secs": {
+                            "type": "number",
+                            "description": "Run the command now and then every this many seconds (e.g. a periodic build check). A run is skipped while the previous one is still going."
                         }
                     },
                     "required": ["name", "command"]
@@ -2820,7 +3867,7 @@ impl<W: UiWriter> Agent<W> {
             },
             Tool {
                 name: "read_file".to_string(),
-                description: "Read the contents of a file. For image files (png, jpg, jpeg, gif, bmp, tiff, webp), automatically extracts text using OCR. For text files, optionally read a specific character range.".to_string(),
+                description: "Read the contents of a file. For image files (png, jpg, jpeg, gif, bmp, tiff, webp), automatically extracts text using OCR. For text files, optionally read a specific character range. Binary files are refused, and large files must be read in ranges.".to_string(),
                 input_schema: json!({
                     "type": "object",
                     "properties": {
@@ -2875,7 +3922,7 @@ impl<W: UiWriter> Agent<W> {
             },
             Tool {
                 name: "str_replace".to_string(),
-                description: "Apply a unified diff to a file. Supports multiple hunks and context lines. Optionally constrain the search to a [start, end) character range (0-indexed; end is EXCLUSIVE). Useful to disambiguate matches or limit scope in large files.".to_string(),
+                description: "Apply a unified diff to a file. Supports multiple hunks and context lines. Optionally constrain the search to a [start, end) character range (0-indexed; end is EXCLUSIVE). Useful to disambiguate matches or limit scope in large files. Hunks whose context does not match verbatim (e.g. indentation drift) are fuzz-applied and reported in the result.".to_string(),
                 input_schema: json!({
                     "type": "object",
                     "properties": {
@@ -2894,6 +3941,14 @@ impl<W: UiWriter> Agent<W> {
                         "end": {
                             "type": "integer",
                             "description": "Ending character position in the file (0-indexed, EXCLUSIVE - character at this position is NOT included). If omitted, searches to end of file."
+                        },
+                        "global_search": {
+                            "type": "boolean",
+                            "description": "Hunks with @@ line numbers only match near those lines. Set to true to also search the rest of the file if a hunk is not found there. Defaults to false."
+                        },
+                        "dry_run": {
+                            "type": "boolean",
+                            "description": "Preview the edit without writing the file: returns the resulting diff so you can check it, then call str_replace again without dry_run to apply it. Defaults to false."
                         }
                     },
                     "required": ["file_path", "diff"]
@@ -2925,7 +3980,7 @@ impl<W: UiWriter> Agent<W> {
                         },
                         "window_id": {
                             "type": "string",
-                            "description": "REQUIRED: Application name to capture (e.g., 'Safari', 'Terminal', 'Google Chrome'). The tool will capture the frontmost window of that application using its native window ID."
+                            "description": "REQUIRED: Application name to capture (e.g., 'Safari', 'Terminal', 'Google Chrome'), or a window id from list_windows. For an application name the tool will capture the frontmost window of that application using its native window ID."
                         },
                         "region": {
                             "type": "object",
@@ -2976,6 +4031,26 @@ impl<W: UiWriter> Agent<W> {
                     "required": ["content"]
                 }),
             },
+            Tool {
+                name: "git_context".to_string(),
+                description: "Summarize recent repository activity: the current branch and how far it is ahead of or behind its upstream, the most recent commits (optionally only those touching given paths) and any files with unresolved merge conflicts. A summary of the whole repository is already in your context from session start; use this to look at specific paths or after commits were made.".to_string(),
+                input_schema: json!({
+                    "type": "object",
+                    "properties": {
+                        "paths": {
+                            "type": "array",
+                            "items": { "type": "string" },
+                            "description": "Only list commits touching these files or directories"
+                        },
+                        "commits": {
+                            "type": "integer",
+                            "minimum": 1,
+                            "description": "How many recent commits to list (default: 10)"
+                        }
+                    },
+                    "required": []
+                }),
+            },
             Tool {
                 name: "code_coverage".to_string(),
                 description: "Generate a code coverage report for the entire workspace using cargo llvm-cov. This runs all tests with coverage instrumentation and returns a summary of coverage statistics. Requires llvm-tools-preview and cargo-llvm-cov to be installed (they will be auto-installed if missing).".to_string(),
@@ -3016,6 +4091,72 @@ impl<W: UiWriter> Agent<W> {
             }),
         });
 
+        // Add language server tools if enabled
+        if enable_lsp {
+            tools.extend(vec![
+                Tool {
+                    name: "get_diagnostics".to_string(),
+                    description: "Get compiler errors and warnings for files from a language server (rust-analyzer, pyright or typescript-language-server), without running a full build. Call it on the files you changed right after editing them. A project directory returns everything the server reports for it.".to_string(),
+                    input_schema: json!({
+                        "type": "object",
+                        "properties": {
+                            "paths": {
+                                "type": "array",
+                                "items": { "type": "string" },
+                                "description": "Source files (.rs, .py, .ts, .tsx, .js) or project directories to check"
+                            }
+                        },
+                        "required": ["paths"]
+                    }),
+                },
+                Tool {
+                    name: "hover".to_string(),
+                    description: "Show the type, signature and documentation of a symbol as the language server sees it, e.g. the inferred type of a variable or the signature of a function from a dependency.".to_string(),
+                    input_schema: json!({
+                        "type": "object",
+                        "properties": {
+                            "file_path": {
+                                "type": "string",
+                                "description": "File in which the symbol appears"
+                            },
+                            "symbol": {
+                                "type": "string",
+                                "description": "The symbol, e.g. `config` or `Agent::new`"
+                            },
+                            "line": {
+                                "type": "integer",
+                                "minimum": 1,
+                                "description": "Line the symbol is on, if it appears more than once (default: its first occurrence)"
+                            }
+                        },
+                        "required": ["file_path", "symbol"]
+                    }),
+                },
+            ]);
+        }
+
+        // Add peer review for flock segment workers
+        if enable_review {
+            tools.push(Tool {
+                name: "request_review".to_string(),
+                description: "Ask another segment of this flock to review your changes. Your diff is sent to a reviewer working in that segment's workspace, who checks it against their module's interfaces and the flock's conventions; its comments are returned. Blocks until the review arrives (up to 15 minutes), so commit or save your work first.".to_string(),
+                input_schema: json!({
+                    "type": "object",
+                    "properties": {
+                        "segment": {
+                            "type": "string",
+                            "description": "The segment to review your changes: its module name or number"
+                        },
+                        "note": {
+                            "type": "string",
+                            "description": "What the reviewer should focus on, e.g. an interface you changed"
+                        }
+                    },
+                    "required": ["segment"]
+                }),
+            });
+        }
+
         // Add WebDriver tools if enabled
         if enable_webdriver {
             tools.extend(vec![
@@ -3069,6 +4210,10 @@ impl<W: UiWriter> Agent<W> {
                             "selector": {
                                 "type": "string",
                                 "description": "CSS selector to find the element (e.g., 'h1', '.class-name', '#id')"
+                            },
+                            "shadow_host": {
+                                "type": "string",
+                                "description": "Optional CSS selector of the shadow DOM host to search inside (use '>>>' between hosts for nested shadow roots)"
                             }
                         },
                         "required": ["selector"]
@@ -3097,6 +4242,10 @@ impl<W: UiWriter> Agent<W> {
                             "selector": {
                                 "type": "string",
                                 "description": "CSS selector for the element to click"
+                            },
+                            "shadow_host": {
+                                "type": "string",
+                                "description": "Optional CSS selector of the shadow DOM host to search inside (use '>>>' between hosts for nested shadow roots)"
                             }
                         },
                         "required": ["selector"]
@@ -3119,11 +4268,38 @@ impl<W: UiWriter> Agent<W> {
                             "clear_first": {
                                 "type": "boolean",
                                 "description": "Whether to clear the element before typing (default: true)"
+                            },
+                            "shadow_host": {
+                                "type": "string",
+                                "description": "Optional CSS selector of the shadow DOM host to search inside (use '>>>' between hosts for nested shadow roots)"
                             }
                         },
                         "required": ["selector", "text"]
                     }),
                 },
+                Tool {
+                    name: "webdriver_switch_to_frame".to_string(),
+                    description: "Switch into an iframe so that later webdriver calls act on its content".to_string(),
+                    input_schema: json!({
+                        "type": "object",
+                        "properties": {
+                            "selector": {
+                                "type": "string",
+                                "description": "CSS selector of the iframe element"
+                            }
+                        },
+                        "required": ["selector"]
+                    }),
+                },
+                Tool {
+                    name: "webdriver_switch_to_parent_frame".to_string(),
+                    description: "Switch from the current iframe back to its parent frame".to_string(),
+                    input_schema: json!({
+                        "type": "object",
+                        "properties": {},
+                        "required": []
+                    }),
+                },
                 Tool {
                     name: "webdriver_execute_script".to_string(),
                     description: "Execute JavaScript code in the browser and return the result".to_string(),
@@ -3138,6 +4314,34 @@ impl<W: UiWriter> Agent<W> {
                         "required": ["script"]
                     }),
                 },
+                Tool {
+                    name: "webdriver_run_script".to_string(),
+                    description: "Run a list of browser steps in one call, with retries and a report of each step's outcome and timing. Prefer this over many single webdriver calls for multi-step flows.".to_string(),
+                    input_schema: json!({
+                        "type": "object",
+                        "properties": {
+                            "steps": {
+                                "type": "array",
+                                "description": "Steps to run in order. Each has an 'action': navigate {url}, wait_for {selector, timeout_ms?}, click {selector}, type {selector, text, clear?}, assert_text {selector, text}, screenshot {path, full_page?}",
+                                "items": {
+                                    "type": "object",
+                                    "properties": {
+                                        "action": {
+                                            "type": "string",
+                                            "enum": ["navigate", "wait_for", "click", "type", "assert_text", "screenshot"]
+                                        }
+                                    },
+                                    "required": ["action"]
+                                }
+                            },
+                            "retries": {
+                                "type": "integer",
+                                "description": "Extra attempts for a failing step (default: 2)"
+                            }
+                        },
+                        "required": ["steps"]
+                    }),
+                },
                 Tool {
                     name: "webdriver_get_page_source".to_string(),
                     description: "Get the rendered HTML source of the current page. Returns the current DOM state after JavaScript execution.".to_string(),
@@ -3158,13 +4362,21 @@ impl<W: UiWriter> Agent<W> {
                 },
                 Tool {
                     name: "webdriver_screenshot".to_string(),
-                    description: "Take a screenshot of the browser window".to_string(),
+                    description: "Take a screenshot of the browser window, the whole page or a single element".to_string(),
                     input_schema: json!({
                         "type": "object",
                         "properties": {
                             "path": {
                                 "type": "string",
                                 "description": "Path where to save the screenshot (e.g., '/tmp/screenshot.png')"
+                            },
+                            "selector": {
+                                "type": "string",
+                                "description": "Optional CSS selector of the element to capture"
+                            },
+                            "full_page": {
+                                "type": "boolean",
+                                "description": "Capture the whole page, including what is scrolled out of view (default: false)"
                             }
                         },
                         "required": ["path"]
@@ -3197,6 +4409,43 @@ impl<W: UiWriter> Agent<W> {
                         "required": []
                     }),
                 },
+                Tool {
+                    name: "webdriver_get_console_logs".to_string(),
+                    description: "Get the browser console entries (errors, warnings, console.log output) logged since the last call (Chrome only). Use this after interacting with a page to see JavaScript errors.".to_string(),
+                    input_schema: json!({
+                        "type": "object",
+                        "properties": {
+                            "errors_only": {
+                                "type": "boolean",
+                                "description": "Only return errors (uncaught exceptions, console.error, failed loads). Default: false"
+                            }
+                        },
+                        "required": []
+                    }),
+                },
+                Tool {
+                    name: "webdriver_start_network_recording".to_string(),
+                    description: "Start recording the network requests made by the page (Chrome only). Requests made before this call are discarded.".to_string(),
+                    input_schema: json!({
+                        "type": "object",
+                        "properties": {},
+                        "required": []
+                    }),
+                },
+                Tool {
+                    name: "webdriver_get_network_requests".to_string(),
+                    description: "List the network requests recorded since webdriver_start_network_recording, with method, status, URL, timing and body size. Use this to verify the API calls a page makes.".to_string(),
+                    input_schema: json!({
+                        "type": "object",
+                        "properties": {
+                            "url_contains": {
+                                "type": "string",
+                                "description": "Only list requests whose URL contains this text (e.g. '/api/')"
+                            }
+                        },
+                        "required": []
+                    }),
+                },
                 Tool {
                     name: "webdriver_quit".to_string(),
                     description: "Close the browser and end the WebDriver session".to_string(),
@@ -3271,6 +4520,25 @@ impl<W: UiWriter> Agent<W> {
                 },
             ]);
 
+            tools.push(Tool {
+                name: "macax_snapshot".to_string(),
+                description: "Snapshot the accessibility tree of an application. Every snapshot after the first reports what was added, removed or changed since the previous one, so take one before and one after a UI action to verify its effect.".to_string(),
+                input_schema: json!({
+                    "type": "object",
+                    "properties": {
+                        "app_name": {
+                            "type": "string",
+                            "description": "Name of the application"
+                        },
+                        "max_depth": {
+                            "type": "integer",
+                            "description": "How many levels of the tree to capture (default: 10)"
+                        }
+                    },
+                    "required": ["app_name"]
+                }),
+            });
+
             // Add type_text tool for typing arbitrary text
             tools.push(Tool {
                 name: "macax_type_text".to_string(),
@@ -3316,6 +4584,50 @@ impl<W: UiWriter> Agent<W> {
 
         // Add vision-guided tools (requires computer control)
         if enable_computer_control {
+            tools.push(Tool {
+                name: "list_windows".to_string(),
+                description: "List the on-screen application windows with their ids, titles, owning applications and bounds. Use the ids with take_screenshot and move_resize_window. Supported on macOS, and on Linux with wmctrl (X11) or sway.".to_string(),
+                input_schema: json!({
+                    "type": "object",
+                    "properties": {},
+                    "required": []
+                }),
+            });
+
+            tools.push(Tool {
+                name: "focus_window".to_string(),
+                description: "Bring a window to the front, matching its title or application name (exact matches first, then part of the title). Supported on macOS, and on Linux with wmctrl (X11) or sway.".to_string(),
+                input_schema: json!({
+                    "type": "object",
+                    "properties": {
+                        "title": {
+                            "type": "string",
+                            "description": "Window title, part of it, or the application name"
+                        }
+                    },
+                    "required": ["title"]
+                }),
+            });
+
+            tools.push(Tool {
+                name: "move_resize_window".to_string(),
+                description: "Move and resize a window, e.g. to get a predictable layout before taking screenshots. Supported on macOS, and on Linux with wmctrl (X11) or sway.".to_string(),
+                input_schema: json!({
+                    "type": "object",
+                    "properties": {
+                        "window_id": {
+                            "type": "string",
+                            "description": "Window id from list_windows"
+                        },
+                        "x": {"type": "integer"},
+                        "y": {"type": "integer"},
+                        "width": {"type": "integer"},
+                        "height": {"type": "integer"}
+                    },
+                    "required": ["window_id", "x", "y", "width", "height"]
+                }),
+            });
+
             // Add vision-guided tools
             tools.push(Tool {
                 name: "vision_find_text".to_string(),
@@ -3384,10 +4696,108 @@ impl<W: UiWriter> Agent<W> {
             });
         }
 
+        // Add UI flow recording tools (requires macax or computer control)
+        if enable_macax || enable_computer_control {
+            tools.extend(vec![
+                Tool {
+                    name: "recording_start".to_string(),
+                    description: "Start recording a UI flow. Successful app, key, typing, click-text and window actions are recorded until recording_stop, so the flow can be replayed later with recording_replay.".to_string(),
+                    input_schema: json!({
+                        "type": "object",
+                        "properties": {
+                            "name": {
+                                "type": "string",
+                                "description": "Name of the flow (e.g., 'create invoice')"
+                            }
+                        },
+                        "required": ["name"]
+                    }),
+                },
+                Tool {
+                    name: "recording_checkpoint".to_string(),
+                    description: "Add an assertion to the recording in progress. With text: the text must be visible in the app (OCR). With role and expected: the element's value must equal expected. With role only: the element must exist.".to_string(),
+                    input_schema: json!({
+                        "type": "object",
+                        "properties": {
+                            "app_name": {
+                                "type": "string",
+                                "description": "Name of the application"
+                            },
+                            "text": {
+                                "type": "string",
+                                "description": "Text that must be visible on screen"
+                            },
+                            "role": {
+                                "type": "string",
+                                "description": "Accessibility role of the element (e.g., 'AXTextField', 'text')"
+                            },
+                            "title": {
+                                "type": "string",
+                                "description": "Optional: element title"
+                            },
+                            "identifier": {
+                                "type": "string",
+                                "description": "Optional: element identifier"
+                            },
+                            "expected": {
+                                "type": "string",
+                                "description": "Optional: value the element must have"
+                            }
+                        },
+                        "required": ["app_name"]
+                    }),
+                },
+                Tool {
+                    name: "recording_stop".to_string(),
+                    description: "Stop recording and save the flow as a JSON script".to_string(),
+                    input_schema: json!({
+                        "type": "object",
+                        "properties": {
+                            "path": {
+                                "type": "string",
+                                "description": "Where to save the recording (e.g., 'flows/invoice.json')"
+                            }
+                        },
+                        "required": ["path"]
+                    }),
+                },
+                Tool {
+                    name: "recording_replay".to_string(),
+                    description: "Replay a recorded UI flow, checking its checkpoints. Failing actions are retried; the replay stops at the first one that keeps failing and reports every action.".to_string(),
+                    input_schema: json!({
+                        "type": "object",
+                        "properties": {
+                            "path": {
+                                "type": "string",
+                                "description": "Path of a recording saved with recording_stop"
+                            },
+                            "retries": {
+                                "type": "integer",
+                                "description": "Extra attempts per failing action (default: 2)"
+                            }
+                        },
+                        "required": ["path"]
+                    }),
+                },
+            ]);
+        }
+
         tools
     }
 
     /// Helper method to stream with retry logic
+    /// Show any provider switches made by the failover wrapper and any
+    /// waits imposed by rate limits
+    fn print_provider_status(&self) {
+        let statuses = match self.provider_status.lock() {
+            Ok(mut queue) => std::mem::take(&mut *queue),
+            Err(_) => return,
+        };
+        for status in statuses {
+            self.ui_writer.print_system_status(&status);
+        }
+    }
+
     async fn stream_with_retry(
         &self,
         request: &CompletionRequest,
@@ -3405,8 +4815,21 @@ impl<W: UiWriter> Agent<W> {
         loop {
             attempt += 1;
             let provider = self.providers.get(None)?;
+            // Poll for status while waiting so a throttled request shows up
+            // as it's queued, not after it's finally sent
+            let stream = provider.stream(request.clone());
+            tokio::pin!(stream);
+            let result = loop {
+                tokio::select! {
+                    result = &mut stream => break result,
+                    _ = tokio::time::sleep(Duration::from_millis(250)) => {
+                        self.print_provider_status();
+                    }
+                }
+            };
+            self.print_provider_status();
 
-            match provider.stream(request.clone()).await {
+            match result {
                 Ok(stream) => {
                     if attempt > 1 {
                         debug!("Stream started successfully after {} attempts", attempt);
@@ -3490,6 +4913,16 @@ impl<W: UiWriter> Agent<W> {
                 }
             }
 
+            // A retention policy, if one is set, drops messages instead of summarizing
+            if self.context_window.should_summarize() {
+                if let Some((retention_summary, chars_saved)) =
+                    self.context_window.apply_retention()
+                {
+                    self.thinning_events.push(chars_saved);
+                    self.ui_writer.print_context_thinning(&retention_summary);
+                }
+            }
+
             // Only proceed with summarization if still needed after thinning
             if self.context_window.should_summarize() {
                 // Notify user about summarization
@@ -3577,6 +5010,7 @@ impl<W: UiWriter> Agent<W> {
                 // Get the summary
                 match provider.complete(summary_request).await {
                     Ok(summary_response) => {
+                        self.record_usage(&summary_response.usage);
                         self.ui_writer.print_context_status(
                             "✅ Context compacted successfully. Continuing...\n",
                         );
@@ -3661,6 +5095,17 @@ impl<W: UiWriter> Agent<W> {
                 request.max_tokens
             );
 
+            self.provider_span = Some(info_span!(
+                "provider_request",
+                provider = %provider_name,
+                model = %provider_model,
+                iteration = iteration_count,
+                messages = request.messages.len(),
+                input_tokens = tracing::field::Empty,
+                output_tokens = tracing::field::Empty,
+                total_tokens = tracing::field::Empty,
+            ));
+
             // Try to get stream with retry logic
             let mut stream = match self.stream_with_retry(&request, &error_context).await {
                 Ok(s) => s,
@@ -3698,8 +5143,25 @@ impl<W: UiWriter> Agent<W> {
             let mut raw_chunks: Vec<String> = Vec::new(); // Store raw chunks for debugging
             let mut _last_error: Option<String> = None;
             let mut accumulated_usage: Option<g3_providers::Usage> = None;
-
-            while let Some(chunk_result) = stream.next().await {
+            let cancellation_token = self.cancellation_token.clone();
+
+            loop {
+                // Checked before every chunk so an interrupt aborts the
+                // provider stream instead of waiting for it to finish
+                let chunk_result = tokio::select! {
+                    biased;
+                    _ = cancellation_token.cancelled() => {
+                        if let Some(usage) = accumulated_usage.take() {
+                            self.record_usage(&usage);
+                        }
+                        self.record_interrupted_response(&current_response);
+                        return Err(anyhow::anyhow!("Operation cancelled by user"));
+                    }
+                    chunk_result = stream.next() => chunk_result,
+                };
+                let Some(chunk_result) = chunk_result else {
+                    break;
+                };
                 match chunk_result {
                     Ok(chunk) => {
                         // Notify UI about SSE received (including pings)
@@ -3878,6 +5340,19 @@ impl<W: UiWriter> Agent<W> {
                                 self.ui_writer.print_context_thinning(&thin_summary);
                             }
 
+                            // Condense the oldest history once usage passes the compaction threshold
+                            let compaction_threshold = self.config.agent.compaction_threshold;
+                            if self.auto_compact
+                                && self.context_window.should_compact(compaction_threshold)
+                            {
+                                let (compact_summary, chars_saved) =
+                                    self.context_window.compact_history(compaction_threshold);
+                                if chars_saved > 0 {
+                                    self.thinning_events.push(chars_saved);
+                                    self.ui_writer.print_context_thinning(&compact_summary);
+                                }
+                            }
+
                             // Track what we've already displayed before getting new text
                             // This prevents re-displaying old content after tool execution
                             let already_displayed_chars = current_response.chars().count();
@@ -3995,6 +5470,11 @@ impl<W: UiWriter> Agent<W> {
                                 exec_duration,
                                 tool_success,
                             ));
+                            self.record_journal_entry(JournalEntry::ToolCall {
+                                tool: tool_call.tool.clone(),
+                                duration_ms: exec_duration.as_millis() as u64,
+                                success: tool_success,
+                            });
 
                             // Display tool execution result with proper indentation
                             if tool_call.tool == "final_output" {
@@ -4126,6 +5606,7 @@ impl<W: UiWriter> Agent<W> {
 
                             self.context_window.add_message(tool_message);
                             self.context_window.add_message(result_message);
+                            self.checkpoint_session();
 
                             // Check if this was a final_output tool call
                             if tool_call.tool == "final_output" {
@@ -4172,11 +5653,7 @@ impl<W: UiWriter> Agent<W> {
                             // Ensure tools are included for native providers in subsequent iterations
                             let provider_for_tools = self.providers.get(None)?;
                             if provider_for_tools.has_native_tool_calling() {
-                                request.tools = Some(Self::create_tool_definitions(
-                                    self.config.webdriver.enabled,
-                                    self.config.macax.enabled,
-                                    self.config.computer_control.enabled,
-                                ));
+                                request.tools = Some(self.tool_definitions());
                             }
 
                             // DO NOT add final_display_content to full_response here!
@@ -4421,6 +5898,11 @@ impl<W: UiWriter> Agent<W> {
                                 // Return empty string to avoid duplication
                                 full_response = String::new();
 
+                                // This return skips the usage update after the stream loop
+                                if let Some(usage) = accumulated_usage.take() {
+                                    self.record_usage(&usage);
+                                }
+
                                 // Save context window BEFORE returning
                                 self.save_context_window("completed");
                                 let _ttft =
@@ -4434,6 +5916,7 @@ impl<W: UiWriter> Agent<W> {
                                         _ttft,
                                         turn_tokens,
                                         self.context_window.percentage_used(),
+                                        self.session_usage.cost_usd,
                                     );
                                     format!(
                                         "{}\n\n{}",
@@ -4509,10 +5992,11 @@ impl<W: UiWriter> Agent<W> {
             if let Some(usage) = accumulated_usage {
                 debug!("Updating context window with actual usage from stream");
                 self.context_window.update_usage_from_response(&usage);
+                self.record_usage(&usage);
             } else {
                 // Fall back to estimation if no usage data was provided
                 debug!("No usage data from stream, using estimation");
-                let estimated_tokens = ContextWindow::estimate_tokens(&current_response);
+                let estimated_tokens = self.context_window.count_tokens(&current_response);
                 self.context_window.add_streaming_tokens(estimated_tokens);
             }
 
@@ -4694,6 +6178,7 @@ impl<W: UiWriter> Agent<W> {
                         _ttft,
                         turn_tokens,
                         self.context_window.percentage_used(),
+                        self.session_usage.cost_usd,
                     );
                     format!(
                         "{}\n\n{}",
@@ -4721,6 +6206,7 @@ impl<W: UiWriter> Agent<W> {
                 _ttft,
                 turn_tokens,
                 self.context_window.percentage_used(),
+                self.session_usage.cost_usd,
             );
             format!(
                 "{}\n\n{}",
@@ -4731,33 +6217,431 @@ impl<W: UiWriter> Agent<W> {
             full_response
         };
 
-        Ok(TaskResult::new(final_response, self.context_window.clone()))
-    }
+        Ok(TaskResult::new(final_response, self.context_window.clone()))
+    }
+
+    pub async fn execute_tool(&mut self, tool_call: &ToolCall) -> Result<String> {
+        // Increment tool call count
+        self.tool_call_count += 1;
+        self.execute_tool_in_dir(tool_call, None).await
+    }
+
+    /// Execute a tool with an optional working directory (for discovery commands)
+    pub async fn execute_tool_in_dir(
+        &mut self,
+        tool_call: &ToolCall,
+        working_dir: Option<&str>,
+    ) -> Result<String> {
+        // Only increment tool call count if not already incremented by execute_tool
+        if working_dir.is_some() {
+            self.tool_call_count += 1;
+        }
+
+        let span = info_span!(
+            "tool_call",
+            tool = %tool_call.tool,
+            duration_ms = tracing::field::Empty,
+            success = tracing::field::Empty,
+        );
+        let start = Instant::now();
+        let result = match self.check_tool_permission(tool_call, working_dir) {
+            Some(rejection) => Ok(rejection),
+            None => match self.check_tool_args(tool_call) {
+                Ok(Some(retry)) => Ok(retry),
+                Ok(None) => {
+                    self.execute_tool_inner_in_dir(tool_call, working_dir)
+                        .instrument(span.clone())
+                        .await
+                }
+                Err(e) => Err(e),
+            },
+        };
+        // A tool error ends the turn; record which tool failed, unless the
+        // error is already typed (e.g. a provider error)
+        let result = result.map_err(|e| match G3Error::find(&e) {
+            Some(_) => e,
+            None => G3Error::tool(&tool_call.tool, format!("{:#}", e)).into(),
+        });
+        if result.is_ok() {
+            self.load_nested_agents_md(tool_call, working_dir);
+        }
+        span.record("duration_ms", start.elapsed().as_millis() as u64);
+        span.record(
+            "success",
+            matches!(&result, Ok(output) if !output.starts_with('❌')),
+        );
+        let log_str = match &result {
+            Ok(s) => s.clone(),
+            Err(e) => format!("ERROR: {}", e),
+        };
+        debug!("Tool {} completed: {}", tool_call.tool, &log_str.chars().take(100).collect::<String>());
+        result
+    }
+
+    /// Pick up the AGENTS.md files of the directories a tool call touched
+    /// and merge them into the pinned directory-instructions message
+    fn load_nested_agents_md(&mut self, tool_call: &ToolCall, working_dir: Option<&str>) {
+        let mut paths: Vec<&str> = Vec::new();
+        if let Some(path) = tool_call.args.get("file_path").and_then(|v| v.as_str()) {
+            paths.push(path);
+        }
+        if let Some(list) = tool_call.args.get("file_paths").and_then(|v| v.as_array()) {
+            paths.extend(list.iter().filter_map(|v| v.as_str()));
+        }
+        if let Some(dir) = working_dir {
+            paths.push(dir);
+        }
+        if paths.is_empty() {
+            return;
+        }
+
+        let base = match working_dir {
+            Some(dir) => PathBuf::from(dir),
+            None => std::env::current_dir().unwrap_or_else(|_| PathBuf::from(".")),
+        };
+        let found: Vec<PathBuf> = paths
+            .iter()
+            .flat_map(|path| {
+                let path = shellexpand::tilde(path);
+                self.agents_md.discover(Path::new(path.as_ref()), &base)
+            })
+            .collect();
+        if found.is_empty() {
+            return;
+        }
+
+        let content = self.agents_md.render();
+        let replaced = self
+            .agents_md_message_id
+            .as_deref()
+            .is_some_and(|id| self.context_window.replace_content(id, content.clone()));
+        if !replaced {
+            let message = Message::new(MessageRole::System, content);
+            self.agents_md_message_id = Some(message.id.clone());
+            self.context_window.add_pinned_message(message);
+        }
+        let names: Vec<String> = found.iter().map(|path| path.display().to_string()).collect();
+        self.ui_writer
+            .print_context_status(&format!("📂 Loaded directory instructions from {}", names.join(", ")));
+    }
+
+    /// Apply the `[permissions]` policy for the tool's class.
+    ///
+    /// Returns a rejection message if the tool must not run. As with
+    /// destructive shell commands, "ask" can't be answered in autonomous mode
+    /// and rejects the call. An "always" answer settles the tool for the
+    /// rest of the session.
+    fn check_tool_permission(
+        &mut self,
+        tool_call: &ToolCall,
+        working_dir: Option<&str>,
+    ) -> Option<String> {
+        if let Some(rejection) = self.check_path_jail(tool_call, working_dir) {
+            return Some(rejection);
+        }
+        // A dry run writes nothing; the call that applies the edit is checked
+        if Self::is_dry_run(tool_call) {
+            return None;
+        }
+        let (class, permission) = permissions::check(&self.config.permissions, &tool_call.tool);
+        if self.config.agent.read_only && !permissions::runs_read_only(&tool_call.tool) {
+            debug!("Tool {} refused in explain mode", tool_call.tool);
+            return Some(format!(
+                "❌ Tool {} not executed: explain mode only runs read and search tools",
+                tool_call.tool
+            ));
+        }
+        match permission {
+            ToolPermission::Allow => None,
+            ToolPermission::Deny => {
+                debug!("Tool {} denied ({} tools are denied)", tool_call.tool, class);
+                Some(format!(
+                    "❌ Tool {} not executed: {} tools are denied by the permissions policy",
+                    tool_call.tool, class
+                ))
+            }
+            ToolPermission::Ask => {
+                if self.is_autonomous {
+                    return Some(format!(
+                        "❌ Tool {} not executed: {} tools need approval, which isn't available in autonomous mode",
+                        tool_call.tool, class
+                    ));
+                }
+                let allowed = match self.session_approvals.get(&tool_call.tool) {
+                    Some(allowed) => allowed,
+                    None => {
+                        let request = self.approval_request(tool_call, class, working_dir);
+                        self.notifier.notify(&notifier::Notification::ApprovalNeeded {
+                            tool: tool_call.tool.clone(),
+                        });
+                        let decision = self.ui_writer.prompt_tool_approval(&request);
+                        self.session_approvals.record(&tool_call.tool, decision);
+                        decision.allows()
+                    }
+                };
+                if allowed {
+                    None
+                } else {
+                    Some(format!(
+                        "❌ Tool {} not executed: user declined the {} tool call",
+                        tool_call.tool, class
+                    ))
+                }
+            }
+        }
+    }
+
+    /// Returns a refusal if read_file should not return `path` (or its
+    /// `[start, end)` range) as text: binary files, and reads larger than
+    /// `[agent] max_read_bytes`. Missing files are left to the read itself.
+    fn check_read(&self, path: &str, start: Option<usize>, end: Option<usize>) -> Option<String> {
+        let size = std::fs::metadata(path).ok()?.len() as usize;
+        if utils::is_binary_file(path).unwrap_or(false) {
+            return Some(format!(
+                "❌ '{}' is a binary file ({} bytes); read_file only returns text. Use read_image for images, or inspect it with a shell command such as `file` or `xxd | head`",
+                path, size
+            ));
+        }
+        let limit = self.config.agent.max_read_bytes;
+        let from = start.unwrap_or(0);
+        let requested = end.unwrap_or(size).min(size).saturating_sub(from);
+        if limit == 0 || requested <= limit {
+            return None;
+        }
+        debug!("Refused reading {} bytes of {}", requested, path);
+        Some(if start.is_none() && end.is_none() {
+            format!(
+                "❌ '{}' is {} bytes, more than read_file returns at once ({} bytes, [agent] max_read_bytes). Read it in ranges with start and end (e.g. start=0, end={}), or search it with code_search or grep first",
+                path, size, limit, limit
+            )
+        } else {
+            format!(
+                "❌ The requested range of '{}' is {} bytes, more than read_file returns at once ({} bytes, [agent] max_read_bytes). Read a smaller range, e.g. start={}, end={}",
+                path,
+                requested,
+                limit,
+                from,
+                from + limit
+            )
+        })
+    }
+
+    /// Returns a rejection message if writing `after` over `before` would
+    /// leave the source file `path` with a syntax error (`[agent] syntax_check`)
+    fn check_syntax(&self, path: &str, before: Option<&str>, after: &str) -> Option<String> {
+        if !self.config.agent.syntax_check {
+            return None;
+        }
+        let error = syntax_check::check_edit(Path::new(path), before, after)?;
+        debug!("Edit of {} refused: syntax error at {}", path, error);
+        Some(format!(
+            "❌ Edit not applied: it would leave '{}' with a syntax error at {}. The file is unchanged; fix the edit and try again.",
+            path, error
+        ))
+    }
+
+    /// Returns a rejection message if a file tool call names a path outside
+    /// the workspace (`[permissions] workspace_only`)
+    fn check_path_jail(&self, tool_call: &ToolCall, working_dir: Option<&str>) -> Option<String> {
+        let permissions = &self.config.permissions;
+        if !permissions.workspace_only {
+            return None;
+        }
+        let root = working_dir
+            .map(PathBuf::from)
+            .or_else(|| std::env::current_dir().ok())?;
+        let jail = path_jail::PathJail::new(&root, &permissions.allowed_paths);
+        let outside = jail.violations(tool_call);
+        if outside.is_empty() {
+            return None;
+        }
+        debug!(
+            "Tool {} refused: {:?} outside the workspace",
+            tool_call.tool, outside
+        );
+        Some(format!(
+            "❌ Tool {} not executed: {} is outside the workspace {}, and the permissions policy keeps file tools inside it. If the file is needed, ask the user to add it to [permissions] allowed_paths",
+            tool_call.tool,
+            outside.join(", "),
+            jail.root().display()
+        ))
+    }
+
+    /// True for a str_replace call that only previews its edit
+    fn is_dry_run(tool_call: &ToolCall) -> bool {
+        tool_call.tool == "str_replace"
+            && tool_call
+                .args
+                .get("dry_run")
+                .and_then(|v| v.as_bool())
+                .unwrap_or(false)
+    }
+
+    /// Validate the call's arguments against the tool's input schema.
+    ///
+    /// The first malformed call to a tool is answered with the validation
+    /// errors and the schema, so the model can correct it on the next turn;
+    /// if the corrected call is still malformed, the error is surfaced.
+    /// Tools without a definition (e.g. unknown tools) are not checked here.
+    fn check_tool_args(&mut self, tool_call: &ToolCall) -> Result<Option<String>> {
+        let Some(tool) = self
+            .tool_definitions()
+            .into_iter()
+            .find(|tool| tool.name == tool_call.tool)
+        else {
+            return Ok(None);
+        };
+
+        let args = tool_schema::normalize_args(&tool_call.args);
+        let errors = tool_schema::validate(&tool.input_schema, &args);
+        if errors.is_empty() {
+            self.schema_retry_tool = None;
+            return Ok(None);
+        }
+
+        warn!("Invalid arguments for tool {}: {:?}", tool_call.tool, errors);
+        if self.schema_retry_tool.take().as_deref() == Some(tool_call.tool.as_str()) {
+            return Err(anyhow::anyhow!(
+                "Tool {} called with invalid arguments after a retry: {}",
+                tool_call.tool,
+                errors.join("; ")
+            ));
+        }
+        self.schema_retry_tool = Some(tool_call.tool.clone());
+        Ok(Some(format!(
+            "❌ Invalid arguments for tool {}:\n  - {}\nExpected input schema:\n{}\nFix the arguments and call the tool again.",
+            tool_call.tool,
+            errors.join("\n  - "),
+            serde_json::to_string_pretty(&tool.input_schema).unwrap_or_default()
+        )))
+    }
+
+    /// Describe a tool call for the user to approve: its arguments, the risk
+    /// of a shell command and the diff a file edit would make.
+    fn approval_request(
+        &self,
+        tool_call: &ToolCall,
+        class: permissions::ToolClass,
+        working_dir: Option<&str>,
+    ) -> permissions::ApprovalRequest {
+        const MAX_DIFF_LINES: usize = 40;
+
+        let args = serde_json::to_string(&tool_call.args).unwrap_or_default();
+        let args_preview: String = if args.chars().count() > 200 {
+            format!("{}...", args.chars().take(200).collect::<String>())
+        } else {
+            args
+        };
+
+        let arg = |name: &str| tool_call.args.get(name).and_then(|v| v.as_str());
+        let risk = match (tool_call.tool.as_str(), arg("command")) {
+            ("shell" | "background_process", Some(command)) => {
+                let risk = shell_safety::analyze_command(command);
+                (risk.level > shell_safety::RiskLevel::Safe).then(|| {
+                    format!(
+                        "{}\n{}",
+                        risk.level,
+                        risk.describe(shell_safety::RiskLevel::Caution)
+                    )
+                })
+            }
+            _ => None,
+        };
+
+        let diff = match (tool_call.tool.as_str(), arg("file_path")) {
+            ("write_file", Some(file_path)) => arg("content").map(|content| {
+                let base = working_dir
+                    .map(PathBuf::from)
+                    .or_else(|| std::env::current_dir().ok())
+                    .unwrap_or_else(|| PathBuf::from("."));
+                let path = base.join(shellexpand::tilde(file_path).as_ref());
+                // A new file diffs against nothing
+                let old = std::fs::read_to_string(&path).unwrap_or_default();
+                utils::generate_unified_diff_with_labels(
+                    &old,
+                    content,
+                    utils::DEFAULT_DIFF_CONTEXT,
+                    file_path,
+                    file_path,
+                )
+            }),
+            ("str_replace", Some(_)) => arg("diff").map(str::to_string),
+            _ => None,
+        }
+        .filter(|diff| !diff.is_empty())
+        .map(|diff| {
+            let total = diff.lines().count();
+            if total <= MAX_DIFF_LINES {
+                diff
+            } else {
+                let shown: Vec<&str> = diff.lines().take(MAX_DIFF_LINES).collect();
+                format!(
+                    "{}\n... ({} more lines)",
+                    shown.join("\n"),
+                    total - MAX_DIFF_LINES
+                )
+            }
+        });
 
-    pub async fn execute_tool(&mut self, tool_call: &ToolCall) -> Result<String> {
-        // Increment tool call count
-        self.tool_call_count += 1;
-        self.execute_tool_in_dir(tool_call, None).await
+        permissions::ApprovalRequest {
+            tool: tool_call.tool.clone(),
+            class,
+            args_preview,
+            risk,
+            diff,
+        }
     }
 
-    /// Execute a tool with an optional working directory (for discovery commands)
-    pub async fn execute_tool_in_dir(
-        &mut self,
-        tool_call: &ToolCall,
-        working_dir: Option<&str>,
-    ) -> Result<String> {
-        // Only increment tool call count if not already incremented by execute_tool
-        if working_dir.is_some() {
-            self.tool_call_count += 1;
+    /// Analyze a shell command and ask for confirmation if it looks destructive.
+    ///
+    /// Returns a rejection message if the command must not run. In autonomous
+    /// mode there is nobody to ask, so dangerous commands are always rejected.
+    /// An "always" answer settles that exact command for the session.
+    fn check_command_safety(&mut self, command: &str) -> Option<String> {
+        let risk = shell_safety::analyze_command(command);
+        if !risk.requires_confirmation() {
+            if risk.level > shell_safety::RiskLevel::Safe {
+                debug!("Shell command flagged ({}): {:?}", risk.level, risk.findings);
+            }
+            return None;
         }
 
-        let result = self.execute_tool_inner_in_dir(tool_call, working_dir).await;
-        let log_str = match &result {
-            Ok(s) => s.clone(),
-            Err(e) => format!("ERROR: {}", e),
+        let reasons = risk.describe(shell_safety::RiskLevel::Dangerous);
+        warn!("Potentially destructive shell command: {}\n{}", command, reasons);
+        if self.is_autonomous {
+            return Some(format!(
+                "❌ Refusing to run potentially destructive command in autonomous mode:\n{}",
+                reasons
+            ));
+        }
+
+        let key = format!("shell: {}", command);
+        let allowed = match self.session_approvals.get(&key) {
+            Some(allowed) => allowed,
+            None => {
+                let request = permissions::ApprovalRequest {
+                    tool: "shell".to_string(),
+                    class: permissions::ToolClass::Shell,
+                    args_preview: command.to_string(),
+                    risk: Some(format!("{} (potentially destructive)\n{}", risk.level, reasons)),
+                    diff: None,
+                };
+                self.notifier.notify(&notifier::Notification::ApprovalNeeded {
+                    tool: "shell".to_string(),
+                });
+                let decision = self.ui_writer.prompt_tool_approval(&request);
+                self.session_approvals.record(&key, decision);
+                decision.allows()
+            }
         };
-        debug!("Tool {} completed: {}", tool_call.tool, &log_str.chars().take(100).collect::<String>());
-        result
+        if allowed {
+            None
+        } else {
+            Some(format!(
+                "❌ Command not executed: user declined potentially destructive command\n{}",
+                reasons
+            ))
+        }
     }
 
     async fn execute_tool_inner_in_dir(
@@ -4786,8 +6670,9 @@ impl<W: UiWriter> Agent<W> {
                     debug!("Found command parameter: {:?}", command);
                     if let Some(command_str) = command.as_str() {
                         debug!("Command string: {}", command_str);
-                        // Use shell escaping to handle filenames with spaces and special characters
-                        let escaped_command = shell_escape_command(command_str);
+                        if let Some(rejection) = self.check_command_safety(command_str) {
+                            return Ok(rejection);
+                        }
 
                         let executor = CodeExecutor::new();
 
@@ -4806,10 +6691,10 @@ impl<W: UiWriter> Agent<W> {
                             ui_writer: &self.ui_writer,
                         };
 
-                        debug!("ABOUT TO CALL execute_bash_streaming_in_dir: escaped_command='{}', working_dir={:?}", escaped_command, working_dir);
+                        debug!("ABOUT TO CALL execute_bash_streaming_in_dir: command='{}', working_dir={:?}", command_str, working_dir);
 
                         match executor
-                            .execute_bash_streaming_in_dir(&escaped_command, &receiver, working_dir)
+                            .execute_bash_streaming_in_dir(command_str, &receiver, working_dir)
                             .await
                         {
                             Ok(result) => {
@@ -4856,6 +6741,9 @@ impl<W: UiWriter> Agent<W> {
                     Some(c) => c,
                     None => return Ok("❌ Missing 'command' argument".to_string()),
                 };
+                if let Some(rejection) = self.check_command_safety(command) {
+                    return Ok(rejection);
+                }
 
                 // Use provided working_dir, or fall back to agent's working_dir, or current dir
                 let work_dir = tool_call.args.get("working_dir")
@@ -4864,20 +6752,81 @@ impl<W: UiWriter> Agent<W> {
                     .or_else(|| working_dir.map(std::path::PathBuf::from))
                     .unwrap_or_else(|| std::env::current_dir().unwrap_or_default());
 
-                match self.background_process_manager.start(name, command, &work_dir) {
+                let options = match background_process::ProcessOptions::from_tool_args(&tool_call.args) {
+                    Ok(options) => options,
+                    Err(e) => return Ok(format!("❌ {}", e)),
+                };
+                let mut monitoring = String::new();
+                if let Some(check) = &options.health_check {
+                    monitoring.push_str(&format!("**Health check:** {} every {:?}\n", check.check, check.interval));
+                }
+                if let Some(limits) = &options.limits {
+                    monitoring.push_str(&format!("**Limits:** {}\n", limits));
+                }
+                if let background_process::RestartPolicy::OnFailure { max_restarts, .. } = options.restart {
+                    monitoring.push_str(&format!(
+                        "**Restart:** on failure, up to {} time(s); restart events are appended to the log\n",
+                        max_restarts
+                    ));
+                }
+
+                let seconds = |key: &str| tool_call.args.get(key)
+                    .and_then(|v| v.as_f64())
+                    .filter(|secs| secs.is_finite() && *secs >= 0.0)
+                    .map(std::time::Duration::from_secs_f64);
+                let schedule = match (seconds("delay_secs"), seconds("every_secs")) {
+                    (Some(_), Some(_)) => return Ok("❌ Use either 'delay_secs' or 'every_secs', not both".to_string()),
+                    (Some(delay), None) => Some(background_process::Schedule::After(delay)),
+                    (None, Some(interval)) => Some(background_process::Schedule::Every(interval)),
+                    (None, None) => None,
+                };
+
+                if let Some(schedule) = schedule {
+                    return match self.background_process_manager.schedule_with_options(name, command, &work_dir, schedule, options) {
+                        Ok(info) => {
+                            let when = match schedule {
+                                background_process::Schedule::After(delay) => format!("once, in {:?}", delay),
+                                background_process::Schedule::Every(interval) => format!("now and every {:?}", interval),
+                            };
+                            if let Some(port) = info.port {
+                                monitoring.insert_str(0, &format!("**Port:** {}\n", port));
+                            }
+                            Ok(format!(
+                                "⏰ Background process '{}' scheduled\n\n\
+                                **Runs:** {}\n\
+                                **Log file:** {} (all runs are appended)\n\
+                                **Working dir:** {}\n\
+                                {}\n\
+                                View logs with the shell tool: `tail -100 {}`",
+                                info.name, when,
+                                info.log_file.display(), info.working_dir.display(),
+                                monitoring,
+                                info.log_file.display()
+                            ))
+                        }
+                        Err(e) => Ok(format!("❌ Failed to schedule background process: {}", e)),
+                    };
+                }
+
+                match self.background_process_manager.start_with_options(name, command, &work_dir, options) {
                     Ok(info) => {
+                        if let Some(port) = info.port {
+                            monitoring.insert_str(0, &format!("**Port:** {}\n", port));
+                        }
                         Ok(format!(
                             "✅ Background process '{}' started\n\n\
                             **PID:** {}\n\
                             **Log file:** {}\n\
-                            **Working dir:** {}\n\n\
+                            **Working dir:** {}\n\
+                            {}\n\
                             To interact with this process, use the shell tool:\n\
                             - View logs: `tail -100 {}`\n\
                             - Follow logs: `tail -f {}` (blocks until Ctrl+C)\n\
                             - Check status: `ps -p {}`\n\
-                            - Stop process: `kill {}`",
+                            - Stop process and its children: `kill -- -{}`",
                             info.name, info.pid, 
                             info.log_file.display(), info.working_dir.display(),
+                            monitoring,
                             info.log_file.display(), info.log_file.display(),
                             info.pid, info.pid
                         ))
@@ -4942,6 +6891,10 @@ impl<W: UiWriter> Agent<W> {
                             path_str, start_char, end_char
                         );
 
+                        if let Some(refusal) = self.check_read(path_str, start_char, end_char) {
+                            return Ok(refusal);
+                        }
+
                         match std::fs::read_to_string(path_str) {
                             Ok(content) => {
                                 // Validate and apply range if specified
@@ -5291,8 +7244,17 @@ impl<W: UiWriter> Agent<W> {
                         }
                     }
 
-                    match std::fs::write(path, content) {
-                        Ok(()) => {
+                    let before = std::fs::read_to_string(path).ok();
+                    if let Some(rejection) = self.check_syntax(path, before.as_deref(), content) {
+                        return Ok(rejection);
+                    }
+                    match utils::atomic_write_with_backup(
+                        path,
+                        content,
+                        self.config.agent.keep_file_backups,
+                    ) {
+                        Ok(_) => {
+                            self.record_file_edit(path, "write_file", before, content);
                             let line_count = content.lines().count();
                             let char_count = content.len();
                             Ok(format!(
@@ -5349,27 +7311,92 @@ impl<W: UiWriter> Agent<W> {
                     .and_then(|v| v.as_u64())
                     .map(|n| n as usize);
 
+                // Allow hunks to match away from their @@ line numbers
+                let global_search = args_obj
+                    .get("global_search")
+                    .and_then(|v| v.as_bool())
+                    .unwrap_or(false);
+
+                // Preview the result without writing it
+                let dry_run = Self::is_dry_run(tool_call);
+
                 debug!(
-                    "str_replace: path={}, start={:?}, end={:?}",
-                    file_path, start_char, end_char
+                    "str_replace: path={}, start={:?}, end={:?}, global_search={}, dry_run={}",
+                    file_path, start_char, end_char, global_search, dry_run
                 );
 
+                // A text diff would corrupt a binary file
+                if utils::is_binary_file(&file_path).unwrap_or(false) {
+                    return Ok(format!(
+                        "❌ '{}' is a binary file; str_replace only edits text files",
+                        file_path
+                    ));
+                }
+
                 // Read the existing file
                 let file_content = match std::fs::read_to_string(&file_path) {
                     Ok(content) => content,
                     Err(e) => return Ok(format!("❌ Failed to read file '{}': {}", file_path, e)),
                 };
 
-                // Apply unified diff to content
-                let result =
-                    match apply_unified_diff_to_string(&file_content, diff, start_char, end_char) {
-                        Ok(r) => r,
-                        Err(e) => return Ok(format!("❌ {}", e)),
-                    };
+                // Apply unified diff to content, falling back to fuzzy matching
+                let report = match utils::apply_unified_diff_with_options(
+                    &file_content,
+                    diff,
+                    start_char,
+                    end_char,
+                    &utils::DiffApplyOptions::default().with_global_search(global_search),
+                ) {
+                    Ok(r) => r,
+                    Err(e) => return Ok(format!("❌ {}", e)),
+                };
+
+                if let Some(rejection) =
+                    self.check_syntax(&file_path, Some(&file_content), &report.content)
+                {
+                    return Ok(rejection);
+                }
+
+                if dry_run {
+                    let preview = utils::generate_unified_diff_with_labels(
+                        &file_content,
+                        &report.content,
+                        utils::DEFAULT_DIFF_CONTEXT,
+                        &file_path,
+                        &file_path,
+                    );
+                    if preview.is_empty() {
+                        return Ok("🔍 Dry run: the diff applies but changes nothing".to_string());
+                    }
+                    self.ui_writer.print_diff_preview(&preview);
+                    let fuzzy = report
+                        .fuzzy_summary()
+                        .map(|summary| format!(" ({})", summary))
+                        .unwrap_or_default();
+                    return Ok(format!(
+                        "🔍 Dry run: the diff applies{}; '{}' was not written. Call str_replace again without dry_run to apply it.\n{}",
+                        fuzzy, file_path, preview
+                    ));
+                }
 
                 // Write the result back to the file
-                match std::fs::write(&file_path, &result) {
-                    Ok(()) => Ok("✅ applied unified diff".to_string()),
+                match utils::atomic_write_with_backup(
+                    &file_path,
+                    &report.content,
+                    self.config.agent.keep_file_backups,
+                ) {
+                    Ok(_) => {
+                        self.record_file_edit(
+                            &file_path,
+                            "str_replace",
+                            Some(file_content),
+                            &report.content,
+                        );
+                        match report.fuzzy_summary() {
+                            Some(summary) => Ok(format!("✅ applied unified diff ({})", summary)),
+                            None => Ok("✅ applied unified diff".to_string()),
+                        }
+                    }
                     Err(e) => Ok(format!("❌ Failed to write to file '{}': {}", file_path, e)),
                 }
             }
@@ -5435,9 +7462,10 @@ impl<W: UiWriter> Agent<W> {
                                 format!("{}/{}", temp_dir.trim_end_matches('/'), path)
                             };
 
+                            let attached = self.attach_screenshot(&actual_path);
                             Ok(format!(
-                                "✅ Screenshot of {} saved to: {}",
-                                window_id, actual_path
+                                "✅ Screenshot of {} saved to: {}{}",
+                                window_id, actual_path, attached
                             ))
                         }
                         Err(e) => Ok(format!("❌ Failed to take screenshot: {}", e)),
@@ -5463,6 +7491,166 @@ impl<W: UiWriter> Agent<W> {
                     Ok("❌ Computer control not enabled. Set computer_control.enabled = true in config.".to_string())
                 }
             }
+            "list_windows" => {
+                debug!("Processing list_windows tool call");
+
+                if let Some(controller) = &self.computer_controller {
+                    match controller.list_windows() {
+                        Ok(windows) if windows.is_empty() => Ok("No windows on screen".to_string()),
+                        Ok(windows) => {
+                            let lines: Vec<String> = windows
+                                .iter()
+                                .map(|w| {
+                                    format!(
+                                        "[{}] {} - \"{}\" at ({}, {}) size {}x{}",
+                                        w.id,
+                                        w.app_name,
+                                        w.title,
+                                        w.bounds.x,
+                                        w.bounds.y,
+                                        w.bounds.width,
+                                        w.bounds.height
+                                    )
+                                })
+                                .collect();
+                            Ok(format!("Windows (front to back):\n{}", lines.join("\n")))
+                        }
+                        Err(e) => Ok(format!("❌ Failed to list windows: {}", e)),
+                    }
+                } else {
+                    Ok("❌ Computer control not enabled. Set computer_control.enabled = true in config.".to_string())
+                }
+            }
+            "focus_window" => {
+                debug!("Processing focus_window tool call");
+
+                if let Some(controller) = &self.computer_controller {
+                    let title = tool_call
+                        .args
+                        .get("title")
+                        .and_then(|v| v.as_str())
+                        .ok_or_else(|| anyhow::anyhow!("Missing title parameter"))?;
+
+                    match controller.focus_window(title) {
+                        Ok(window) => {
+                            self.recorder.record(RecordedAction::FocusWindow {
+                                title: title.to_string(),
+                            });
+                            Ok(format!(
+                                "✅ Focused window [{}] {} - \"{}\"",
+                                window.id, window.app_name, window.title
+                            ))
+                        }
+                        Err(e) => Ok(format!("❌ Failed to focus window: {}", e)),
+                    }
+                } else {
+                    Ok("❌ Computer control not enabled. Set computer_control.enabled = true in config.".to_string())
+                }
+            }
+            "move_resize_window" => {
+                debug!("Processing move_resize_window tool call");
+
+                if let Some(controller) = &self.computer_controller {
+                    let window_id = tool_call
+                        .args
+                        .get("window_id")
+                        .and_then(|v| v.as_str())
+                        .ok_or_else(|| anyhow::anyhow!("Missing window_id parameter"))?;
+                    let number = |key: &str| {
+                        tool_call
+                            .args
+                            .get(key)
+                            .and_then(|v| v.as_i64())
+                            .map(|n| n as i32)
+                            .ok_or_else(|| anyhow::anyhow!("Missing {} parameter", key))
+                    };
+                    let bounds = g3_computer_control::types::Rect {
+                        x: number("x")?,
+                        y: number("y")?,
+                        width: number("width")?,
+                        height: number("height")?,
+                    };
+
+                    match controller.move_resize_window(window_id, bounds) {
+                        Ok(()) => {
+                            self.recorder.record(RecordedAction::MoveResizeWindow {
+                                window_id: window_id.to_string(),
+                                bounds,
+                            });
+                            Ok(format!(
+                                "✅ Moved window {} to ({}, {}) with size {}x{}",
+                                window_id, bounds.x, bounds.y, bounds.width, bounds.height
+                            ))
+                        }
+                        Err(e) => Ok(format!("❌ Failed to move window: {}", e)),
+                    }
+                } else {
+                    Ok("❌ Computer control not enabled. Set computer_control.enabled = true in config.".to_string())
+                }
+            }
+            "git_context" => {
+                debug!("Processing git_context tool call");
+                let paths: Vec<String> = tool_call
+                    .args
+                    .get("paths")
+                    .and_then(|v| v.as_array())
+                    .map(|paths| {
+                        paths
+                            .iter()
+                            .filter_map(|p| p.as_str())
+                            .map(|p| shellexpand::tilde(p).into_owned())
+                            .collect()
+                    })
+                    .unwrap_or_default();
+                let commits = tool_call
+                    .args
+                    .get("commits")
+                    .and_then(|v| v.as_u64())
+                    .map(|n| n as usize)
+                    .unwrap_or(gitinfo::DEFAULT_COMMITS);
+                let dir = match working_dir {
+                    Some(dir) => PathBuf::from(dir),
+                    None => std::env::current_dir()?,
+                };
+                match gitinfo::summarize(&dir, &paths, commits) {
+                    Some(summary) => Ok(summary.to_string()),
+                    None => Ok("❌ Not inside a git repository".to_string()),
+                }
+            }
+            "request_review" => {
+                debug!("Processing request_review tool call");
+                let Some(mailbox) = &self.review_mailbox else {
+                    return Ok(
+                        "❌ request_review is only available to the workers of a flock".to_string(),
+                    );
+                };
+                let Some(segment) = tool_call.args.get("segment").and_then(|v| v.as_str()) else {
+                    return Ok("❌ Missing segment argument".to_string());
+                };
+                let note = tool_call
+                    .args
+                    .get("note")
+                    .and_then(|v| v.as_str())
+                    .map(str::to_string);
+                let id = mailbox.submit(segment, note)?;
+                self.ui_writer
+                    .print_context_status(&format!("🔍 Waiting for a review from {}...", segment));
+                match mailbox
+                    .wait_for_response(&id, peer_review::REVIEW_TIMEOUT)
+                    .await
+                {
+                    Ok(peer_review::ReviewResponse {
+                        reviewer: Some(reviewer),
+                        comments: Some(comments),
+                        ..
+                    }) => Ok(format!("Review from segment {}:\n\n{}", reviewer, comments)),
+                    Ok(response) => Ok(format!(
+                        "❌ Review failed: {}",
+                        response.error.unwrap_or_else(|| "no comments".to_string())
+                    )),
+                    Err(e) => Ok(format!("❌ Review failed: {}", e)),
+                }
+            }
             "todo_read" => {
                 debug!("Processing todo_read tool call");
                 // Read from session-specific todo.g3.md if we have a session, else fall back to workspace
@@ -5576,10 +7764,7 @@ impl<W: UiWriter> Agent<W> {
                         }
 
                         // Check if all todos are completed (all checkboxes are checked)
-                        let has_incomplete = content_str.lines().any(|line| {
-                            let trimmed = line.trim();
-                            trimmed.starts_with("- [ ]")
-                        });
+                        let all_done = todo::stats(content_str).all_done();
 
                         // If all todos are complete, delete the file instead of writing
                         // EXCEPT in planner mode (G3_TODO_PATH is set) - preserve for rename to completed_todo_*.md
@@ -5591,7 +7776,7 @@ impl<W: UiWriter> Agent<W> {
                             get_todo_path()
                         };
                         
-                        if !in_planner_mode && !has_incomplete && (content_str.contains("- [x]") || content_str.contains("- [X]")) {
+                        if !in_planner_mode && all_done {
                             if todo_path.exists() {
                                 match std::fs::remove_file(&todo_path) {
                                     Ok(_) => {
@@ -5611,7 +7796,7 @@ impl<W: UiWriter> Agent<W> {
                             }
                         }
 
-                        match std::fs::write(&todo_path, content_str) {
+                        match utils::atomic_write(&todo_path, content_str) {
                             Ok(_) => {
                                 // Also update in-memory content to stay in sync
                                 let mut todo = self.todo_content.write().await;
@@ -5631,6 +7816,58 @@ impl<W: UiWriter> Agent<W> {
                     Ok("❌ Missing content argument".to_string())
                 }
             }
+            "get_diagnostics" | "hover" if !self.config.lsp.enabled => {
+                Ok("❌ Language servers are disabled. Set lsp.enabled = true in config.".to_string())
+            }
+            "get_diagnostics" => {
+                debug!("Processing get_diagnostics tool call");
+                let base = match working_dir {
+                    Some(dir) => PathBuf::from(dir),
+                    None => std::env::current_dir()?,
+                };
+                let paths: Vec<PathBuf> = tool_call
+                    .args
+                    .get("paths")
+                    .and_then(|v| v.as_array())
+                    .map(|paths| {
+                        paths
+                            .iter()
+                            .filter_map(|p| p.as_str())
+                            .map(|p| base.join(shellexpand::tilde(p).as_ref()))
+                            .collect()
+                    })
+                    .unwrap_or_default();
+                if paths.is_empty() {
+                    return Ok("❌ Missing paths argument".to_string());
+                }
+                match self.lsp.diagnostics(&paths).await {
+                    Ok(diagnostics) => Ok(lsp::format_diagnostics(&diagnostics, &base)),
+                    Err(e) => Ok(format!("❌ {:#}", e)),
+                }
+            }
+            "hover" => {
+                debug!("Processing hover tool call");
+                let (Some(file_path), Some(symbol)) = (
+                    tool_call.args.get("file_path").and_then(|v| v.as_str()),
+                    tool_call.args.get("symbol").and_then(|v| v.as_str()),
+                ) else {
+                    return Ok("❌ Missing file_path or symbol argument".to_string());
+                };
+                let base = match working_dir {
+                    Some(dir) => PathBuf::from(dir),
+                    None => std::env::current_dir()?,
+                };
+                let path = base.join(shellexpand::tilde(file_path).as_ref());
+                let line = tool_call
+                    .args
+                    .get("line")
+                    .and_then(|v| v.as_u64())
+                    .map(|n| n as u32);
+                match self.lsp.hover(&path, symbol, line).await {
+                    Ok(text) => Ok(text),
+                    Err(e) => Ok(format!("❌ {:#}", e)),
+                }
+            }
             "code_coverage" => {
                 debug!("Processing code_coverage tool call");
                 self.ui_writer
@@ -5899,9 +8136,14 @@ impl<W: UiWriter> Agent<W> {
                     Some(s) => s,
                     None => return Ok("❌ Missing selector argument".to_string()),
                 };
+                let shadow_host = tool_call.args.get("shadow_host").and_then(|v| v.as_str());
 
                 let mut driver = session.lock().await;
-                match driver.find_element(selector).await {
+                let found = match shadow_host {
+                    Some(host) => driver.find_in_shadow(host, selector).await,
+                    None => driver.find_element(selector).await,
+                };
+                match found {
                     Ok(elem) => match elem.text().await {
                         Ok(text) => Ok(format!("Element text: {}", text)),
                         Err(e) => Ok(format!("❌ Failed to get element text: {}", e)),
@@ -5973,22 +8215,115 @@ impl<W: UiWriter> Agent<W> {
                     }
                 };
 
+                let selector = match tool_call.args.get("selector").and_then(|v| v.as_str()) {
+                    Some(s) => s,
+                    None => return Ok("❌ Missing selector argument".to_string()),
+                };
+                let shadow_host = tool_call.args.get("shadow_host").and_then(|v| v.as_str());
+
+                let mut driver = session.lock().await;
+                let found = match shadow_host {
+                    Some(host) => driver.find_in_shadow(host, selector).await,
+                    None => driver.find_element(selector).await,
+                };
+                match found {
+                    Ok(mut elem) => match elem.click().await {
+                        Ok(_) => Ok(format!("✅ Clicked element '{}'", selector)),
+                        Err(e) => Ok(format!("❌ Failed to click element: {}", e)),
+                    },
+                    Err(e) => Ok(format!("❌ Failed to find element '{}': {}", selector, e)),
+                }
+            }
+            "webdriver_send_keys" => {
+                debug!("Processing webdriver_send_keys tool call");
+
+                if !self.config.webdriver.enabled {
+                    return Ok(
+                        "❌ WebDriver is not enabled. Use --webdriver flag to enable.".to_string(),
+                    );
+                }
+
+                let session_guard = self.webdriver_session.read().await;
+                let session = match session_guard.as_ref() {
+                    Some(s) => s.clone(),
+                    None => {
+                        return Ok(
+                            "❌ No active WebDriver session. Call webdriver_start first."
+                                .to_string(),
+                        )
+                    }
+                };
+
+                let selector = match tool_call.args.get("selector").and_then(|v| v.as_str()) {
+                    Some(s) => s,
+                    None => return Ok("❌ Missing selector argument".to_string()),
+                };
+                let shadow_host = tool_call.args.get("shadow_host").and_then(|v| v.as_str());
+
+                let text = match tool_call.args.get("text").and_then(|v| v.as_str()) {
+                    Some(t) => t,
+                    None => return Ok("❌ Missing text argument".to_string()),
+                };
+
+                let clear_first = tool_call
+                    .args
+                    .get("clear_first")
+                    .and_then(|v| v.as_bool())
+                    .unwrap_or(true);
+
+                let mut driver = session.lock().await;
+                let found = match shadow_host {
+                    Some(host) => driver.find_in_shadow(host, selector).await,
+                    None => driver.find_element(selector).await,
+                };
+                match found {
+                    Ok(mut elem) => {
+                        if clear_first {
+                            if let Err(e) = elem.clear().await {
+                                return Ok(format!("❌ Failed to clear element: {}", e));
+                            }
+                        }
+                        match elem.send_keys(text).await {
+                            Ok(_) => Ok(format!("✅ Sent keys to element '{}'", selector)),
+                            Err(e) => Ok(format!("❌ Failed to send keys: {}", e)),
+                        }
+                    }
+                    Err(e) => Ok(format!("❌ Failed to find element '{}': {}", selector, e)),
+                }
+            }
+            "webdriver_switch_to_frame" => {
+                debug!("Processing webdriver_switch_to_frame tool call");
+
+                if !self.config.webdriver.enabled {
+                    return Ok(
+                        "❌ WebDriver is not enabled. Use --webdriver flag to enable.".to_string(),
+                    );
+                }
+
+                let session_guard = self.webdriver_session.read().await;
+                let session = match session_guard.as_ref() {
+                    Some(s) => s.clone(),
+                    None => {
+                        return Ok(
+                            "❌ No active WebDriver session. Call webdriver_start first."
+                                .to_string(),
+                        )
+                    }
+                };
+
                 let selector = match tool_call.args.get("selector").and_then(|v| v.as_str()) {
                     Some(s) => s,
                     None => return Ok("❌ Missing selector argument".to_string()),
                 };
 
                 let mut driver = session.lock().await;
-                match driver.find_element(selector).await {
-                    Ok(mut elem) => match elem.click().await {
-                        Ok(_) => Ok(format!("✅ Clicked element '{}'", selector)),
-                        Err(e) => Ok(format!("❌ Failed to click element: {}", e)),
-                    },
-                    Err(e) => Ok(format!("❌ Failed to find element '{}': {}", selector, e)),
+                match driver.switch_to_frame(selector).await {
+                    Ok(_) => Ok(format!("✅ Switched to frame '{}'", selector)),
+                    Err(e) => Ok(format!("❌ Failed to switch to frame '{}': {}", selector, e)),
                 }
             }
-            "webdriver_send_keys" => {
-                debug!("Processing webdriver_send_keys tool call");
+            "webdriver_switch_to_parent_frame" => {
+                debug!("Processing webdriver_switch_to_parent_frame tool call");
 
                 if !self.config.webdriver.enabled {
                     return Ok(
@@ -6007,36 +8342,10 @@ impl<W: UiWriter> Agent<W> {
                     }
                 };
 
-                let selector = match tool_call.args.get("selector").and_then(|v| v.as_str()) {
-                    Some(s) => s,
-                    None => return Ok("❌ Missing selector argument".to_string()),
-                };
-
-                let text = match tool_call.args.get("text").and_then(|v| v.as_str()) {
-                    Some(t) => t,
-                    None => return Ok("❌ Missing text argument".to_string()),
-                };
-
-                let clear_first = tool_call
-                    .args
-                    .get("clear_first")
-                    .and_then(|v| v.as_bool())
-                    .unwrap_or(true);
-
                 let mut driver = session.lock().await;
-                match driver.find_element(selector).await {
-                    Ok(mut elem) => {
-                        if clear_first {
-                            if let Err(e) = elem.clear().await {
-                                return Ok(format!("❌ Failed to clear element: {}", e));
-                            }
-                        }
-                        match elem.send_keys(text).await {
-                            Ok(_) => Ok(format!("✅ Sent keys to element '{}'", selector)),
-                            Err(e) => Ok(format!("❌ Failed to send keys: {}", e)),
-                        }
-                    }
-                    Err(e) => Ok(format!("❌ Failed to find element '{}': {}", selector, e)),
+                match driver.switch_to_parent_frame().await {
+                    Ok(_) => Ok("✅ Switched to parent frame".to_string()),
+                    Err(e) => Ok(format!("❌ Failed to switch to parent frame: {}", e)),
                 }
             }
             "webdriver_execute_script" => {
@@ -6070,6 +8379,47 @@ impl<W: UiWriter> Agent<W> {
                     Err(e) => Ok(format!("❌ Failed to execute script: {}", e)),
                 }
             }
+            "webdriver_run_script" => {
+                debug!("Processing webdriver_run_script tool call");
+
+                if !self.config.webdriver.enabled {
+                    return Ok(
+                        "❌ WebDriver is not enabled. Use --webdriver flag to enable.".to_string(),
+                    );
+                }
+
+                let session_guard = self.webdriver_session.read().await;
+                let session = match session_guard.as_ref() {
+                    Some(s) => s.clone(),
+                    None => {
+                        return Ok(
+                            "❌ No active WebDriver session. Call webdriver_start first."
+                                .to_string(),
+                        )
+                    }
+                };
+
+                let steps = match tool_call.args.get("steps") {
+                    Some(steps) => match g3_computer_control::webdriver::script::parse_steps(steps) {
+                        Ok(steps) => steps,
+                        Err(e) => return Ok(format!("❌ {:#}", e)),
+                    },
+                    None => return Ok("❌ Missing steps argument".to_string()),
+                };
+                let mut options = g3_computer_control::webdriver::script::ScriptOptions::default();
+                if let Some(retries) = tool_call.args.get("retries").and_then(|v| v.as_u64()) {
+                    options.retries = retries.min(10) as u32;
+                }
+
+                let mut driver = session.lock().await;
+                let report = g3_computer_control::webdriver::script::run_script(
+                    &mut *driver,
+                    &steps,
+                    &options,
+                )
+                .await;
+                Ok(report.summary())
+            }
             "webdriver_get_page_source" => {
                 debug!("Processing webdriver_get_page_source tool call");
 
@@ -6167,9 +8517,30 @@ impl<W: UiWriter> Agent<W> {
                     None => return Ok("❌ Missing path argument".to_string()),
                 };
 
+                let selector = tool_call.args.get("selector").and_then(|v| v.as_str());
+                let full_page = tool_call
+                    .args
+                    .get("full_page")
+                    .and_then(|v| v.as_bool())
+                    .unwrap_or(false);
+
                 let mut driver = session.lock().await;
-                match driver.screenshot(path).await {
-                    Ok(_) => Ok(format!("✅ Screenshot saved to {}", path)),
+                let result = match (selector, full_page) {
+                    (Some(_), true) => {
+                        return Ok("❌ Use either 'selector' or 'full_page', not both".to_string())
+                    }
+                    (Some(selector), false) => driver.screenshot_element(selector, path).await,
+                    (None, true) => driver.screenshot_full_page(path).await,
+                    (None, false) => driver.screenshot(path).await,
+                };
+                // Release the session before borrowing the agent to attach the image
+                drop(driver);
+                drop(session_guard);
+                match result {
+                    Ok(_) => {
+                        let attached = self.attach_screenshot(path);
+                        Ok(format!("✅ Screenshot saved to {}{}", path, attached))
+                    }
                     Err(e) => Ok(format!("❌ Failed to take screenshot: {}", e)),
                 }
             }
@@ -6251,6 +8622,122 @@ impl<W: UiWriter> Agent<W> {
                     Err(e) => Ok(format!("❌ Failed to refresh page: {}", e)),
                 }
             }
+            "webdriver_get_console_logs" => {
+                debug!("Processing webdriver_get_console_logs tool call");
+
+                if !self.config.webdriver.enabled {
+                    return Ok(
+                        "❌ WebDriver is not enabled. Use --webdriver flag to enable.".to_string(),
+                    );
+                }
+
+                let errors_only = tool_call
+                    .args
+                    .get("errors_only")
+                    .and_then(|v| v.as_bool())
+                    .unwrap_or(false);
+
+                let session_guard = self.webdriver_session.read().await;
+                let session = match session_guard.as_ref() {
+                    Some(s) => s.clone(),
+                    None => {
+                        return Ok(
+                            "❌ No active WebDriver session. Call webdriver_start first."
+                                .to_string(),
+                        )
+                    }
+                };
+
+                let mut driver = session.lock().await;
+                match driver.console_logs().await {
+                    Ok(mut entries) => {
+                        if errors_only {
+                            entries.retain(|entry| entry.is_error());
+                        }
+                        if entries.is_empty() {
+                            Ok("No new console entries".to_string())
+                        } else {
+                            Ok(format!(
+                                "{} console entr{}:\n{}",
+                                entries.len(),
+                                if entries.len() == 1 { "y" } else { "ies" },
+                                webdriver_session::format_console_logs(&entries)
+                            ))
+                        }
+                    }
+                    Err(e) => Ok(format!("❌ Failed to get console logs: {}", e)),
+                }
+            }
+            "webdriver_start_network_recording" => {
+                debug!("Processing webdriver_start_network_recording tool call");
+
+                if !self.config.webdriver.enabled {
+                    return Ok(
+                        "❌ WebDriver is not enabled. Use --webdriver flag to enable.".to_string(),
+                    );
+                }
+
+                let session_guard = self.webdriver_session.read().await;
+                let session = match session_guard.as_ref() {
+                    Some(s) => s.clone(),
+                    None => {
+                        return Ok(
+                            "❌ No active WebDriver session. Call webdriver_start first."
+                                .to_string(),
+                        )
+                    }
+                };
+
+                let mut driver = session.lock().await;
+                match driver.start_network_recording().await {
+                    Ok(_) => Ok("✅ Recording network requests".to_string()),
+                    Err(e) => Ok(format!("❌ Failed to start network recording: {}", e)),
+                }
+            }
+            "webdriver_get_network_requests" => {
+                debug!("Processing webdriver_get_network_requests tool call");
+
+                if !self.config.webdriver.enabled {
+                    return Ok(
+                        "❌ WebDriver is not enabled. Use --webdriver flag to enable.".to_string(),
+                    );
+                }
+
+                let url_contains = tool_call
+                    .args
+                    .get("url_contains")
+                    .and_then(|v| v.as_str());
+
+                let session_guard = self.webdriver_session.read().await;
+                let session = match session_guard.as_ref() {
+                    Some(s) => s.clone(),
+                    None => {
+                        return Ok(
+                            "❌ No active WebDriver session. Call webdriver_start first."
+                                .to_string(),
+                        )
+                    }
+                };
+
+                let mut driver = session.lock().await;
+                match driver.network_requests().await {
+                    Ok(mut requests) => {
+                        if let Some(filter) = url_contains {
+                            requests.retain(|request| request.url.contains(filter));
+                        }
+                        if requests.is_empty() {
+                            Ok("No network requests recorded".to_string())
+                        } else {
+                            Ok(format!(
+                                "{} network request(s):\n{}",
+                                requests.len(),
+                                webdriver_session::format_network_requests(&requests)
+                            ))
+                        }
+                    }
+                    Err(e) => Ok(format!("❌ Failed to get network requests: {}", e)),
+                }
+            }
             "webdriver_quit" => {
                 debug!("Processing webdriver_quit tool call");
 
@@ -6367,7 +8854,12 @@ impl<W: UiWriter> Agent<W> {
                 };
 
                 match controller.activate_app(app_name) {
-                    Ok(_) => Ok(format!("✅ Activated application: {}", app_name)),
+                    Ok(_) => {
+                        self.recorder.record(RecordedAction::ActivateApp {
+                            app: app_name.to_string(),
+                        });
+                        Ok(format!("✅ Activated application: {}", app_name))
+                    }
                     Err(e) => Ok(format!("❌ Failed to activate app: {}", e)),
                 }
             }
@@ -6408,6 +8900,11 @@ impl<W: UiWriter> Agent<W> {
 
                 match controller.press_key(app_name, key, modifiers_vec.clone()) {
                     Ok(_) => {
+                        self.recorder.record(RecordedAction::PressKey {
+                            app: app_name.to_string(),
+                            key: key.to_string(),
+                            modifiers: modifiers_vec.iter().map(|m| m.to_string()).collect(),
+                        });
                         let modifier_str = if modifiers_vec.is_empty() {
                             String::new()
                         } else {
@@ -6447,10 +8944,69 @@ impl<W: UiWriter> Agent<W> {
                 };
 
                 match controller.type_text(app_name, text) {
-                    Ok(_) => Ok(format!("✅ Typed text into {}", app_name)),
+                    Ok(_) => {
+                        self.recorder.record(RecordedAction::TypeText {
+                            app: app_name.to_string(),
+                            text: text.to_string(),
+                        });
+                        Ok(format!("✅ Typed text into {}", app_name))
+                    }
                     Err(e) => Ok(format!("❌ Failed to type text: {}", e)),
                 }
             }
+            "macax_snapshot" => {
+                debug!("Processing macax_snapshot tool call");
+
+                if !self.config.macax.enabled {
+                    return Ok(
+                        "❌ macOS Accessibility is not enabled. Use --macax flag to enable."
+                            .to_string(),
+                    );
+                }
+
+                let app_name = match tool_call.args.get("app_name").and_then(|v| v.as_str()) {
+                    Some(n) => n,
+                    None => return Ok("❌ Missing app_name argument".to_string()),
+                };
+                let max_depth = tool_call
+                    .args
+                    .get("max_depth")
+                    .and_then(|v| v.as_u64())
+                    .map(|n| n as usize)
+                    .unwrap_or(10);
+
+                let controller_guard = self.macax_controller.read().await;
+                let controller = match controller_guard.as_ref() {
+                    Some(c) => c,
+                    None => {
+                        return Ok("❌ macOS Accessibility controller not initialized.".to_string())
+                    }
+                };
+
+                let snapshot = match controller.snapshot_tree(app_name, max_depth) {
+                    Ok(snapshot) => snapshot,
+                    Err(e) => return Ok(format!("❌ Failed to snapshot {}: {}", app_name, e)),
+                };
+                let element_count = snapshot.element_count();
+                match self.macax_snapshots.insert(app_name.to_string(), snapshot) {
+                    Some(previous) => {
+                        let diff = g3_computer_control::macax::diff_snapshots(
+                            &previous,
+                            &self.macax_snapshots[app_name],
+                        );
+                        Ok(format!(
+                            "📸 Snapshot of {} ({} elements). Since the previous snapshot: {}",
+                            app_name,
+                            element_count,
+                            diff.summary()
+                        ))
+                    }
+                    None => Ok(format!(
+                        "📸 Snapshot of {} ({} elements). The next snapshot will report changes since this one.",
+                        app_name, element_count
+                    )),
+                }
+            }
             "vision_find_text" => {
                 debug!("Processing vision_find_text tool call");
 
@@ -6498,52 +9054,133 @@ impl<W: UiWriter> Agent<W> {
                         .and_then(|v| v.as_str())
                         .ok_or_else(|| anyhow::anyhow!("Missing text parameter"))?;
 
-                    match controller.find_text_in_app(app_name, text).await {
-                        Ok(Some(location)) => {
-                            // Click on center of text
-                            // IMPORTANT: location coordinates are in NSScreen space (Y=0 at BOTTOM, increases UPWARD)
-                            // location.x is the LEFT edge of the bounding box
-                            // location.y is the TOP edge of the bounding box (highest Y value in NSScreen space)
-                            // location.width and location.height are already scaled to screen space
-                            // To get center: we need to add half the SCALED width and subtract half the SCALED height
-
-                            if location.width == 0 || location.height == 0 {
-                                return Ok(format!(
-                                    "❌ Invalid bounding box dimensions: width={}, height={}",
-                                    location.width, location.height
-                                ));
-                            }
+                    match controller.click_text(app_name, text).await {
+                        Ok(location) => {
+                            self.recorder.record(RecordedAction::ClickText {
+                                app: app_name.to_string(),
+                                text: text.to_string(),
+                            });
+                            Ok(format!(
+                                "✅ Clicked on '{}' in {} (found '{}' at ({}, {}))",
+                                text, app_name, location.text, location.x, location.y
+                            ))
+                        }
+                        Err(e) => Ok(format!("❌ Failed to click '{}': {}", text, e)),
+                    }
+                } else {
+                    Ok("❌ Computer control not enabled. Set computer_control.enabled = true in config.".to_string())
+                }
+            }
+            "recording_start" => {
+                debug!("Processing recording_start tool call");
 
-                            debug!("[vision_click_text] Location from find_text_in_app: x={}, y={}, width={}, height={}, text='{}'",
-                                location.x, location.y, location.width, location.height, location.text);
+                let name = match tool_call.args.get("name").and_then(|v| v.as_str()) {
+                    Some(n) => n,
+                    None => return Ok("❌ Missing name argument".to_string()),
+                };
 
-                            // Calculate center using the SCALED dimensions
-                            // X: Use right edge instead of center (Vision OCR bounding box seems offset)
-                            // This gives us: left edge + full width = right edge
-                            // Y: top edge - half of scaled height (subtract because Y increases upward)
-                            let click_x = location.x + location.width; // Right edge
-                            let half_height = location.height / 2;
-                            let click_y = location.y - half_height;
+                let discarded = self.recorder.stop();
+                self.recorder.start(name);
+                match discarded {
+                    Some(previous) => Ok(format!(
+                        "✅ Started recording '{}' (discarded unsaved recording '{}')",
+                        name, previous.name
+                    )),
+                    None => Ok(format!("✅ Started recording '{}'", name)),
+                }
+            }
+            "recording_checkpoint" => {
+                debug!("Processing recording_checkpoint tool call");
 
-                            debug!("[vision_click_text] Click position calculation: x={} + {} = {} (right edge), y={} - {} = {}",
-                                location.x, location.width, click_x, location.y, half_height, click_y);
-                            debug!("[vision_click_text] This means: left_edge={}, center={}, right_edge={}",
-                                location.x, click_x, location.x + location.width);
+                if !self.recorder.is_recording() {
+                    return Ok("❌ Not recording. Call recording_start first.".to_string());
+                }
 
-                            match controller.click_at(click_x, click_y, Some(app_name)) {
-                                Ok(_) => Ok(format!(
-                                    "✅ Clicked on '{}' in {} at ({}, {})",
-                                    text, app_name, click_x, click_y
-                                )),
-                                Err(e) => Ok(format!("❌ Failed to click: {}", e)),
-                            }
-                        }
-                        Ok(None) => Ok(format!("❌ Could not find '{}' in {}", text, app_name)),
-                        Err(e) => Ok(format!("❌ Error finding text: {}", e)),
+                let arg = |key: &str| {
+                    tool_call
+                        .args
+                        .get(key)
+                        .and_then(|v| v.as_str())
+                        .map(|s| s.to_string())
+                };
+                let app = match arg("app_name") {
+                    Some(a) => a,
+                    None => return Ok("❌ Missing app_name argument".to_string()),
+                };
+
+                let checkpoint = if let Some(text) = arg("text") {
+                    RecordedAction::AssertText { app, text }
+                } else if let Some(role) = arg("role") {
+                    let element = g3_computer_control::recording::ElementRef {
+                        role,
+                        title: arg("title"),
+                        identifier: arg("identifier"),
+                    };
+                    match arg("expected") {
+                        Some(expected) => RecordedAction::AssertValue {
+                            app,
+                            element,
+                            expected,
+                        },
+                        None => RecordedAction::AssertElement { app, element },
                     }
                 } else {
-                    Ok("❌ Computer control not enabled. Set computer_control.enabled = true in config.".to_string())
+                    return Ok("❌ Provide either text or role".to_string());
+                };
+
+                let description = checkpoint.describe();
+                self.recorder.record(checkpoint);
+                Ok(format!("✅ Added checkpoint: {}", description))
+            }
+            "recording_stop" => {
+                debug!("Processing recording_stop tool call");
+
+                let path = match tool_call.args.get("path").and_then(|v| v.as_str()) {
+                    Some(p) => shellexpand::tilde(p).into_owned(),
+                    None => return Ok("❌ Missing path argument".to_string()),
+                };
+
+                let recording = match self.recorder.stop() {
+                    Some(r) => r,
+                    None => return Ok("❌ Not recording. Call recording_start first.".to_string()),
+                };
+
+                match recording.save(&path) {
+                    Ok(()) => Ok(format!(
+                        "✅ Saved recording '{}' to {} ({} actions, {} checkpoints)",
+                        recording.name,
+                        path,
+                        recording.actions.len(),
+                        recording.checkpoint_count()
+                    )),
+                    Err(e) => Ok(format!("❌ {:#}", e)),
+                }
+            }
+            "recording_replay" => {
+                debug!("Processing recording_replay tool call");
+
+                let path = match tool_call.args.get("path").and_then(|v| v.as_str()) {
+                    Some(p) => shellexpand::tilde(p).into_owned(),
+                    None => return Ok("❌ Missing path argument".to_string()),
+                };
+
+                let recording = match g3_computer_control::Recording::load(&path) {
+                    Ok(r) => r,
+                    Err(e) => return Ok(format!("❌ {:#}", e)),
+                };
+                let mut options = g3_computer_control::ReplayOptions::default();
+                if let Some(retries) = tool_call.args.get("retries").and_then(|v| v.as_u64()) {
+                    options.retries = retries.min(10) as u32;
                 }
+
+                let controller_guard = self.macax_controller.read().await;
+                let targets = g3_computer_control::recording::ReplayTargets {
+                    accessibility: controller_guard.as_deref(),
+                    computer: self.computer_controller.as_deref(),
+                };
+                let report =
+                    g3_computer_control::recording::replay(&recording, targets, &options).await;
+                Ok(report.summary())
             }
             "extract_text_with_boxes" => {
                 debug!("Processing extract_text_with_boxes tool call");
@@ -6708,6 +9345,39 @@ impl<W: UiWriter> Agent<W> {
         }
     }
 
+    /// Queue a screenshot to be attached to this tool call's result, if the
+    /// provider can take images. Returns a note for the tool's output.
+    fn attach_screenshot(&mut self, path: &str) -> String {
+        if !self
+            .providers
+            .get(None)
+            .map(|provider| provider.supports_vision())
+            .unwrap_or(false)
+        {
+            return String::new();
+        }
+        let bytes = match std::fs::read(shellexpand::tilde(path).as_ref()) {
+            Ok(bytes) => bytes,
+            Err(e) => {
+                warn!("Failed to read screenshot {} to attach it: {}", path, e);
+                return String::new();
+            }
+        };
+        if bytes.len() > MAX_ATTACHED_IMAGE_BYTES {
+            return format!(
+                " (too large to attach: {:.1} MB, use a region or read_image on a smaller copy)",
+                bytes.len() as f64 / (1024.0 * 1024.0)
+            );
+        }
+        let Some(media_type) = g3_providers::ImageContent::media_type_from_bytes(&bytes) else {
+            return String::new();
+        };
+        use base64::Engine;
+        let encoded = base64::engine::general_purpose::STANDARD.encode(&bytes);
+        self.pending_images
+            .push(g3_providers::ImageContent::new(media_type, encoded));
+        " (attached below)".to_string()
+    }
 
     /// Get image dimensions from raw bytes
     fn get_image_dimensions(bytes: &[u8], media_type: &str) -> Option<(u32, u32)> {
@@ -6811,22 +9481,29 @@ impl<W: UiWriter> Agent<W> {
         ttft: Duration,
         turn_tokens: Option<u32>,
         context_percentage: f32,
+        session_cost: f64,
     ) -> String {
         let timing = format!("⏱️ {} | 💭 {}", Self::format_duration(elapsed), Self::format_duration(ttft));
         
         // Add token usage info if available (dimmed)
-        if let Some(tokens) = turn_tokens {
-            format!("{}  \x1b[2m{} ◉ | {:.0}%\x1b[0m", timing, tokens, context_percentage)
+        let usage = if let Some(tokens) = turn_tokens {
+            format!("{}  \x1b[2m{} ◉ | {:.0}%", timing, tokens, context_percentage)
+        } else {
+            format!("{}  \x1b[2m{:.0}%", timing, context_percentage)
+        };
+
+        // Session cost so far, omitted for free (local) models
+        if session_cost > 0.0 {
+            format!("{} | ${:.4}\x1b[0m", usage, session_cost)
         } else {
-            format!("{}  \x1b[2m{:.0}%\x1b[0m", timing, context_percentage)
+            format!("{}\x1b[0m", usage)
         }
     }
 }
 
 
 // Re-export utility functions
-pub use utils::apply_unified_diff_to_string;
-use utils::shell_escape_command;
+pub use utils::{apply_unified_diff_to_string, apply_unified_diff_with_options, generate_unified_diff, merge3, DiffApplyOptions, DiffApplyReport, MergeResult};
 
 // Implement Drop to clean up safaridriver process
 impl<W: UiWriter> Drop for Agent<W> {
@@ -0,0 +1,888 @@
+//! Language server clients for compiler-grade feedback without a full build.
+//!
+//! [`LspManager`] starts rust-analyzer, pyright or typescript-language-server
+//! for the workspace the first time a `get_diagnostics` or `hover` call
+//! touches a file of that language, and keeps it running for the session.
+//! Files are synced from disk before every call, so edits made with any tool
+//! are seen. Diagnostics arrive as `textDocument/publishDiagnostics`
+//! notifications; a call waits until every file it synced has been
+//! republished and the server has gone quiet, or until its timeout.
+
+use anyhow::{anyhow, bail, Context, Result};
+use g3_config::LspConfig;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+use std::sync::atomic::{AtomicI64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::io::{
+    AsyncBufRead, AsyncBufReadExt, AsyncReadExt, AsyncWrite, AsyncWriteExt, BufReader,
+};
+use tokio::process::{Child, ChildStdin, Command};
+use tokio::sync::{oneshot, Notify};
+use tracing::{debug, warn};
+
+/// How long a request (initialize, hover) may take
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Diagnostics count as final once the server has published nothing for this long
+const SETTLE_TIME: Duration = Duration::from_millis(1500);
+
+/// The languages with a server
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum ServerKind {
+    Rust,
+    Python,
+    TypeScript,
+}
+
+impl ServerKind {
+    /// Server for a source file, by extension
+    pub fn for_file(path: &Path) -> Option<Self> {
+        match path.extension()?.to_str()? {
+            "rs" => Some(Self::Rust),
+            "py" | "pyi" => Some(Self::Python),
+            "ts" | "tsx" | "mts" | "cts" | "js" | "jsx" | "mjs" | "cjs" => Some(Self::TypeScript),
+            _ => None,
+        }
+    }
+
+    /// Server for a project directory, by its manifest
+    pub fn for_dir(dir: &Path) -> Option<Self> {
+        let has = |name: &str| dir.join(name).exists();
+        if has("Cargo.toml") {
+            Some(Self::Rust)
+        } else if has("pyproject.toml") || has("setup.py") || has("requirements.txt") {
+            Some(Self::Python)
+        } else if has("tsconfig.json") || has("package.json") {
+            Some(Self::TypeScript)
+        } else {
+            None
+        }
+    }
+
+    /// Key of the server's command in `[lsp.servers]`
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Rust => "rust",
+            Self::Python => "python",
+            Self::TypeScript => "typescript",
+        }
+    }
+
+    pub fn default_command(&self) -> &'static str {
+        match self {
+            Self::Rust => "rust-analyzer",
+            Self::Python => "pyright-langserver --stdio",
+            Self::TypeScript => "typescript-language-server --stdio",
+        }
+    }
+
+    fn language_id(path: &Path) -> &'static str {
+        match path
+            .extension()
+            .and_then(|e| e.to_str())
+            .unwrap_or_default()
+        {
+            "rs" => "rust",
+            "py" | "pyi" => "python",
+            "tsx" => "typescriptreact",
+            "js" | "mjs" | "cjs" => "javascript",
+            "jsx" => "javascriptreact",
+            _ => "typescript",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Severity {
+    Error,
+    Warning,
+    Information,
+    Hint,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Error => "error",
+            Self::Warning => "warning",
+            Self::Information => "info",
+            Self::Hint => "hint",
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diagnostic {
+    pub path: PathBuf,
+    /// One-based
+    pub line: u32,
+    /// One-based, in UTF-16 code units as the server reports it
+    pub column: u32,
+    pub severity: Severity,
+    pub code: Option<String>,
+    /// The tool that produced it, e.g. "rustc" or "Pylance"
+    pub source: Option<String>,
+    pub message: String,
+}
+
+/// `file:12:5: error[E0308]: mismatched types (rustc)`, with paths relative to `root`
+pub fn format_diagnostics(diagnostics: &[Diagnostic], root: &Path) -> String {
+    if diagnostics.is_empty() {
+        return "✅ No diagnostics".to_string();
+    }
+    let count = |severity| {
+        diagnostics
+            .iter()
+            .filter(|d| d.severity == severity)
+            .count()
+    };
+    let mut text = format!(
+        "{} error(s), {} warning(s), {} other\n",
+        count(Severity::Error),
+        count(Severity::Warning),
+        count(Severity::Information) + count(Severity::Hint)
+    );
+    for diagnostic in diagnostics {
+        let path = diagnostic
+            .path
+            .strip_prefix(root)
+            .unwrap_or(&diagnostic.path);
+        text.push_str(&format!(
+            "\n{}:{}:{}: {}",
+            path.display(),
+            diagnostic.line,
+            diagnostic.column,
+            diagnostic.severity
+        ));
+        if let Some(code) = &diagnostic.code {
+            text.push_str(&format!("[{}]", code));
+        }
+        // Multi-line messages (e.g. rustc notes) are indented under their diagnostic
+        text.push_str(&format!(
+            ": {}",
+            diagnostic.message.trim().replace('\n', "\n    ")
+        ));
+        if let Some(source) = &diagnostic.source {
+            text.push_str(&format!(" ({})", source));
+        }
+    }
+    text
+}
+
+/// State shared with the task reading the server's output
+#[derive(Default)]
+struct State {
+    pending: HashMap<i64, oneshot::Sender<Result<Value, String>>>,
+    diagnostics: HashMap<PathBuf, Vec<Diagnostic>>,
+    /// Publications seen per file, to tell fresh diagnostics from stale ones
+    publications: HashMap<PathBuf, u64>,
+    last_publication: Option<Instant>,
+}
+
+/// One running language server
+pub struct LspClient {
+    _child: Child,
+    stdin: Arc<tokio::sync::Mutex<ChildStdin>>,
+    state: Arc<Mutex<State>>,
+    /// Signalled whenever diagnostics are published
+    published: Arc<Notify>,
+    next_id: AtomicI64,
+    /// Version and content of each file sent to the server
+    documents: tokio::sync::Mutex<HashMap<PathBuf, (i32, String)>>,
+}
+
+impl LspClient {
+    /// Start `command` in `root` and complete the initialize handshake
+    pub async fn start(command: &str, root: &Path) -> Result<Self> {
+        let mut words = command.split_whitespace();
+        let program = words.next().context("Empty language server command")?;
+        let mut child = Command::new(program)
+            .args(words)
+            .current_dir(root)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .kill_on_drop(true)
+            .spawn()?;
+        let stdin = Arc::new(tokio::sync::Mutex::new(
+            child.stdin.take().context("No stdin")?,
+        ));
+        let stdout = child.stdout.take().context("No stdout")?;
+
+        let state = Arc::new(Mutex::new(State::default()));
+        let published = Arc::new(Notify::new());
+        tokio::spawn(read_loop(
+            BufReader::new(stdout),
+            stdin.clone(),
+            state.clone(),
+            published.clone(),
+        ));
+
+        let client = Self {
+            _child: child,
+            stdin,
+            state,
+            published,
+            next_id: AtomicI64::new(1),
+            documents: tokio::sync::Mutex::new(HashMap::new()),
+        };
+        let root_uri = path_to_uri(root);
+        client
+            .request(
+                "initialize",
+                json!({
+                    "processId": std::process::id(),
+                    "rootUri": root_uri,
+                    "workspaceFolders": [{ "uri": root_uri, "name": "workspace" }],
+                    "capabilities": {
+                        "textDocument": {
+                            "synchronization": { "didSave": true },
+                            "publishDiagnostics": { "relatedInformation": false },
+                            "hover": { "contentFormat": ["markdown", "plaintext"] }
+                        },
+                        "workspace": { "configuration": true, "workspaceFolders": true }
+                    }
+                }),
+            )
+            .await?;
+        client.notify("initialized", json!({})).await?;
+        Ok(client)
+    }
+
+    async fn send(&self, message: Value) -> Result<()> {
+        let mut stdin = self.stdin.lock().await;
+        write_message(&mut *stdin, &message).await
+    }
+
+    async fn request(&self, method: &str, params: Value) -> Result<Value> {
+        let id = self.next_id.fetch_add(1, Ordering::SeqCst);
+        let (tx, rx) = oneshot::channel();
+        self.lock_state().pending.insert(id, tx);
+        self.send(json!({ "jsonrpc": "2.0", "id": id, "method": method, "params": params }))
+            .await?;
+        match tokio::time::timeout(REQUEST_TIMEOUT, rx).await {
+            Ok(Ok(Ok(result))) => Ok(result),
+            Ok(Ok(Err(message))) => bail!("{} failed: {}", method, message),
+            Ok(Err(_)) => bail!("Language server exited"),
+            Err(_) => {
+                self.lock_state().pending.remove(&id);
+                bail!("{} timed out after {:?}", method, REQUEST_TIMEOUT)
+            }
+        }
+    }
+
+    async fn notify(&self, method: &str, params: Value) -> Result<()> {
+        self.send(json!({ "jsonrpc": "2.0", "method": method, "params": params }))
+            .await
+    }
+
+    fn lock_state(&self) -> std::sync::MutexGuard<'_, State> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Send the file's current content if the server hasn't seen it.
+    /// Returns whether anything was sent.
+    async fn sync(&self, path: &Path) -> Result<bool> {
+        let content = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+        let uri = path_to_uri(path);
+        let mut documents = self.documents.lock().await;
+        match documents.get_mut(path) {
+            Some((_, known)) if *known == content => Ok(false),
+            Some((version, known)) => {
+                *version += 1;
+                *known = content.clone();
+                let version = *version;
+                drop(documents);
+                self.notify(
+                    "textDocument/didChange",
+                    json!({
+                        "textDocument": { "uri": uri, "version": version },
+                        "contentChanges": [{ "text": content }]
+                    }),
+                )
+                .await?;
+                // rust-analyzer reruns cargo check on save
+                self.notify(
+                    "textDocument/didSave",
+                    json!({ "textDocument": { "uri": uri } }),
+                )
+                .await?;
+                Ok(true)
+            }
+            None => {
+                documents.insert(path.to_path_buf(), (1, content.clone()));
+                drop(documents);
+                self.notify(
+                    "textDocument/didOpen",
+                    json!({
+                        "textDocument": {
+                            "uri": uri,
+                            "languageId": ServerKind::language_id(path),
+                            "version": 1,
+                            "text": content
+                        }
+                    }),
+                )
+                .await?;
+                Ok(true)
+            }
+        }
+    }
+
+    /// Diagnostics for `files` once the server has reported on them, plus
+    /// anything published under `dirs`
+    pub async fn diagnostics(
+        &self,
+        files: &[PathBuf],
+        dirs: &[PathBuf],
+        timeout: Duration,
+    ) -> Result<Vec<Diagnostic>> {
+        let mut awaited = Vec::new();
+        for path in files {
+            let seen = self.publications(path);
+            if self.sync(path).await? {
+                awaited.push((path.clone(), seen));
+            }
+        }
+
+        let deadline = Instant::now() + timeout;
+        loop {
+            let published = self.published.notified();
+            let (fresh, quiet_for) = {
+                let state = self.lock_state();
+                let fresh = awaited.iter().all(|(path, seen)| {
+                    state.publications.get(path).copied().unwrap_or_default() > *seen
+                });
+                (fresh, state.last_publication.map(|at| at.elapsed()))
+            };
+            let now = Instant::now();
+            if now >= deadline {
+                debug!("Timed out waiting for diagnostics");
+                break;
+            }
+            let wait = match quiet_for {
+                Some(quiet_for) if fresh && quiet_for >= SETTLE_TIME => break,
+                Some(quiet_for) if fresh => SETTLE_TIME - quiet_for,
+                _ => deadline - now,
+            };
+            let _ = tokio::time::timeout(wait.min(deadline - now), published).await;
+        }
+
+        let state = self.lock_state();
+        let mut diagnostics: Vec<Diagnostic> = state
+            .diagnostics
+            .iter()
+            .filter(|(path, _)| {
+                files.contains(*path) || dirs.iter().any(|dir| path.starts_with(dir))
+            })
+            .flat_map(|(_, diagnostics)| diagnostics.iter().cloned())
+            .collect();
+        diagnostics.sort_by(|a, b| {
+            (a.severity, &a.path, a.line, a.column).cmp(&(b.severity, &b.path, b.line, b.column))
+        });
+        Ok(diagnostics)
+    }
+
+    fn publications(&self, path: &Path) -> u64 {
+        self.lock_state()
+            .publications
+            .get(path)
+            .copied()
+            .unwrap_or_default()
+    }
+
+    /// Hover text at a zero-based position
+    pub async fn hover(&self, path: &Path, line: u32, character: u32) -> Result<Option<String>> {
+        self.sync(path).await?;
+        let result = self
+            .request(
+                "textDocument/hover",
+                json!({
+                    "textDocument": { "uri": path_to_uri(path) },
+                    "position": { "line": line, "character": character }
+                }),
+            )
+            .await?;
+        Ok(hover_text(&result))
+    }
+}
+
+/// Read the server's messages until it exits, answering its requests and
+/// routing responses and diagnostics
+async fn read_loop<R: AsyncBufRead + Unpin>(
+    mut reader: R,
+    stdin: Arc<tokio::sync::Mutex<ChildStdin>>,
+    state: Arc<Mutex<State>>,
+    published: Arc<Notify>,
+) {
+    loop {
+        let message = match read_message(&mut reader).await {
+            Ok(Some(message)) => message,
+            Ok(None) => break,
+            Err(e) => {
+                warn!("Failed to read from language server: {}", e);
+                break;
+            }
+        };
+        if let Some(reply) = handle_message(message, &state, &published) {
+            let mut stdin = stdin.lock().await;
+            if write_message(&mut *stdin, &reply).await.is_err() {
+                break;
+            }
+        }
+    }
+    // Pending requests fail as their senders are dropped
+    state
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .pending
+        .clear();
+}
+
+/// Apply one message from the server; returns the reply to a server request
+fn handle_message(message: Value, state: &Mutex<State>, published: &Notify) -> Option<Value> {
+    let method = message.get("method").and_then(Value::as_str);
+    match (method, message.get("id")) {
+        // A response to one of our requests
+        (None, Some(id)) => {
+            let sender = state
+                .lock()
+                .unwrap_or_else(|e| e.into_inner())
+                .pending
+                .remove(&id.as_i64()?)?;
+            let result = match message.get("error") {
+                Some(error) => Err(error
+                    .get("message")
+                    .and_then(Value::as_str)
+                    .unwrap_or("unknown error")
+                    .to_string()),
+                None => Ok(message.get("result").cloned().unwrap_or(Value::Null)),
+            };
+            let _ = sender.send(result);
+            None
+        }
+        // A request from the server; nothing it asks for is needed, so
+        // settings come back empty and everything else is acknowledged
+        (Some(method), Some(id)) => {
+            let result = match method {
+                "workspace/configuration" => {
+                    let items = message["params"]["items"].as_array().map_or(0, Vec::len);
+                    Value::Array(vec![Value::Null; items])
+                }
+                _ => Value::Null,
+            };
+            Some(json!({ "jsonrpc": "2.0", "id": id, "result": result }))
+        }
+        (Some("textDocument/publishDiagnostics"), None) => {
+            let (path, diagnostics) = parse_published_diagnostics(&message["params"])?;
+            let mut state = state.lock().unwrap_or_else(|e| e.into_inner());
+            *state.publications.entry(path.clone()).or_default() += 1;
+            state.diagnostics.insert(path, diagnostics);
+            state.last_publication = Some(Instant::now());
+            drop(state);
+            published.notify_waiters();
+            None
+        }
+        _ => None,
+    }
+}
+
+fn parse_published_diagnostics(params: &Value) -> Option<(PathBuf, Vec<Diagnostic>)> {
+    let path = uri_to_path(params.get("uri")?.as_str()?)?;
+    let diagnostics = params
+        .get("diagnostics")?
+        .as_array()?
+        .iter()
+        .filter_map(|diagnostic| {
+            let start = &diagnostic["range"]["start"];
+            let severity = match diagnostic.get("severity").and_then(Value::as_u64) {
+                Some(2) => Severity::Warning,
+                Some(3) => Severity::Information,
+                Some(4) => Severity::Hint,
+                _ => Severity::Error,
+            };
+            let code = match diagnostic.get("code") {
+                Some(Value::String(code)) => Some(code.clone()),
+                Some(Value::Number(code)) => Some(code.to_string()),
+                _ => None,
+            };
+            Some(Diagnostic {
+                path: path.clone(),
+                line: start.get("line")?.as_u64()? as u32 + 1,
+                column: start.get("character")?.as_u64()? as u32 + 1,
+                severity,
+                code,
+                source: diagnostic
+                    .get("source")
+                    .and_then(Value::as_str)
+                    .map(str::to_string),
+                message: diagnostic.get("message")?.as_str()?.to_string(),
+            })
+        })
+        .collect();
+    Some((path, diagnostics))
+}
+
+/// The text of a hover result, whichever of the LSP shapes it comes in
+fn hover_text(result: &Value) -> Option<String> {
+    fn marked(value: &Value) -> Option<String> {
+        match value {
+            Value::String(text) => Some(text.clone()),
+            Value::Object(_) => {
+                let text = value.get("value")?.as_str()?;
+                // MarkedString carries a language; MarkupContent a kind
+                Some(match value.get("language").and_then(Value::as_str) {
+                    Some(language) => format!("```{}\n{}\n```", language, text),
+                    None => text.to_string(),
+                })
+            }
+            _ => None,
+        }
+    }
+    let text = match result.get("contents")? {
+        Value::Array(items) => items
+            .iter()
+            .filter_map(marked)
+            .collect::<Vec<_>>()
+            .join("\n\n"),
+        contents => marked(contents)?,
+    };
+    (!text.trim().is_empty()).then_some(text)
+}
+
+async fn read_message<R: AsyncBufRead + Unpin>(reader: &mut R) -> Result<Option<Value>> {
+    let mut content_length = None;
+    loop {
+        let mut line = String::new();
+        if reader.read_line(&mut line).await? == 0 {
+            return Ok(None);
+        }
+        let line = line.trim_end();
+        if line.is_empty() {
+            if content_length.is_some() {
+                break;
+            }
+            continue;
+        }
+        if let Some(length) = line.strip_prefix("Content-Length:") {
+            content_length = Some(length.trim().parse::<usize>()?);
+        }
+    }
+    let mut body = vec![0; content_length.unwrap_or_default()];
+    reader.read_exact(&mut body).await?;
+    Ok(Some(serde_json::from_slice(&body)?))
+}
+
+async fn write_message<W: AsyncWrite + Unpin>(writer: &mut W, message: &Value) -> Result<()> {
+    let body = serde_json::to_string(message)?;
+    writer
+        .write_all(format!("Content-Length: {}\r\n\r\n{}", body.len(), body).as_bytes())
+        .await?;
+    writer.flush().await?;
+    Ok(())
+}
+
+fn path_to_uri(path: &Path) -> String {
+    let mut uri = String::from("file://");
+    for byte in path.to_string_lossy().bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' | b'/' => {
+                uri.push(byte as char)
+            }
+            _ => uri.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+    uri
+}
+
+fn uri_to_path(uri: &str) -> Option<PathBuf> {
+    let encoded = uri.strip_prefix("file://")?.as_bytes();
+    let mut bytes = Vec::with_capacity(encoded.len());
+    let mut i = 0;
+    while i < encoded.len() {
+        if encoded[i] == b'%' && i + 2 < encoded.len() {
+            let hex = std::str::from_utf8(&encoded[i + 1..i + 3]).ok()?;
+            bytes.push(u8::from_str_radix(hex, 16).ok()?);
+            i += 3;
+        } else {
+            bytes.push(encoded[i]);
+            i += 1;
+        }
+    }
+    Some(PathBuf::from(String::from_utf8(bytes).ok()?))
+}
+
+/// Zero-based line and UTF-16 column of `symbol` in `source`: the first
+/// whole-word occurrence, on the one-based `line` if it has one. For a path
+/// like `Agent::new` the position is that of its last segment.
+pub fn find_symbol(source: &str, symbol: &str, line: Option<u32>) -> Option<(u32, u32)> {
+    let name = symbol.rsplit([':', '.']).next().unwrap_or(symbol);
+    let lines: Vec<&str> = source.lines().collect();
+    let preferred = line
+        .and_then(|line| line.checked_sub(1))
+        .filter(|&index| (index as usize) < lines.len());
+    let order: Vec<u32> = preferred
+        .into_iter()
+        .chain((0..lines.len() as u32).filter(|&index| Some(index) != preferred))
+        .collect();
+
+    // The full path anywhere beats its last segment alone
+    for needle in [symbol, name] {
+        for &index in &order {
+            let text = lines[index as usize];
+            if let Some(start) = find_word(text, needle) {
+                let byte = start + needle.len() - name.len();
+                return Some((index, text[..byte].encode_utf16().count() as u32));
+            }
+        }
+    }
+    None
+}
+
+fn find_word(text: &str, word: &str) -> Option<usize> {
+    if word.is_empty() {
+        return None;
+    }
+    let is_ident = |c: char| c.is_alphanumeric() || c == '_';
+    text.match_indices(word)
+        .map(|(start, _)| start)
+        .find(|&start| {
+            let before = text[..start].chars().next_back();
+            let after = text[start + word.len()..].chars().next();
+            !before.is_some_and(is_ident) && !after.is_some_and(is_ident)
+        })
+}
+
+/// Language servers for one workspace, started on first use
+pub struct LspManager {
+    root: PathBuf,
+    config: LspConfig,
+    clients: HashMap<ServerKind, LspClient>,
+}
+
+impl LspManager {
+    pub fn new(root: PathBuf, config: LspConfig) -> Self {
+        Self {
+            root,
+            config,
+            clients: HashMap::new(),
+        }
+    }
+
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    async fn client(&mut self, kind: ServerKind) -> Result<&LspClient> {
+        if !self.clients.contains_key(&kind) {
+            let command = self
+                .config
+                .servers
+                .get(kind.name())
+                .cloned()
+                .unwrap_or_else(|| kind.default_command().to_string());
+            debug!("Starting {} language server: {}", kind.name(), command);
+            let client = LspClient::start(&command, &self.root)
+                .await
+                .with_context(|| {
+                    format!(
+                        "Failed to start the {} language server (`{}`). Is it installed? \
+                     Set [lsp.servers] {} = \"<command>\" to use another one",
+                        kind.name(),
+                        command,
+                        kind.name()
+                    )
+                })?;
+            self.clients.insert(kind, client);
+        }
+        Ok(&self.clients[&kind])
+    }
+
+    /// Diagnostics for files and directories (absolute paths). A directory
+    /// starts the server for its project and returns what it publishes.
+    pub async fn diagnostics(&mut self, paths: &[PathBuf]) -> Result<Vec<Diagnostic>> {
+        let timeout = Duration::from_secs(self.config.diagnostics_timeout_secs);
+        let mut requests: HashMap<ServerKind, (Vec<PathBuf>, Vec<PathBuf>)> = HashMap::new();
+        for path in paths {
+            if path.is_dir() {
+                let kind = ServerKind::for_dir(path)
+                    .or_else(|| ServerKind::for_dir(&self.root))
+                    .ok_or_else(|| anyhow!("No language server for {}", path.display()))?;
+                requests.entry(kind).or_default().1.push(path.clone());
+            } else {
+                let kind = ServerKind::for_file(path)
+                    .ok_or_else(|| anyhow!("No language server for {}", path.display()))?;
+                requests.entry(kind).or_default().0.push(path.clone());
+            }
+        }
+
+        let mut diagnostics = Vec::new();
+        let mut kinds: Vec<ServerKind> = requests.keys().copied().collect();
+        kinds.sort();
+        for kind in kinds {
+            let (files, dirs) = &requests[&kind];
+            let client = self.client(kind).await?;
+            diagnostics.extend(client.diagnostics(files, dirs, timeout).await?);
+        }
+        Ok(diagnostics)
+    }
+
+    /// Hover text (type, signature, docs) for `symbol` in the file at `path`
+    pub async fn hover(&mut self, path: &Path, symbol: &str, line: Option<u32>) -> Result<String> {
+        let kind = ServerKind::for_file(path)
+            .ok_or_else(|| anyhow!("No language server for {}", path.display()))?;
+        let source = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+        let (line, character) = find_symbol(&source, symbol, line)
+            .ok_or_else(|| anyhow!("'{}' not found in {}", symbol, path.display()))?;
+        let client = self.client(kind).await?;
+        Ok(client
+            .hover(path, line, character)
+            .await?
+            .unwrap_or_else(|| {
+                format!(
+                    "No hover information for '{}' (the language server may still be indexing)",
+                    symbol
+                )
+            }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_server_kinds() {
+        assert_eq!(
+            ServerKind::for_file(Path::new("src/lib.rs")),
+            Some(ServerKind::Rust)
+        );
+        assert_eq!(
+            ServerKind::for_file(Path::new("app.tsx")),
+            Some(ServerKind::TypeScript)
+        );
+        assert_eq!(
+            ServerKind::for_file(Path::new("main.py")),
+            Some(ServerKind::Python)
+        );
+        assert_eq!(ServerKind::for_file(Path::new("README.md")), None);
+        assert_eq!(
+            ServerKind::language_id(Path::new("app.tsx")),
+            "typescriptreact"
+        );
+    }
+
+    #[test]
+    fn test_uri_round_trip() {
+        let path = Path::new("/home/me/my project/src/lib.rs");
+        let uri = path_to_uri(path);
+        assert_eq!(uri, "file:///home/me/my%20project/src/lib.rs");
+        assert_eq!(uri_to_path(&uri).unwrap(), path);
+        assert_eq!(uri_to_path("https://example.com"), None);
+    }
+
+    #[tokio::test]
+    async fn test_message_framing() {
+        let (mut client, server) = tokio::io::duplex(1024);
+        let message = json!({ "jsonrpc": "2.0", "id": 1, "result": { "ok": "✓" } });
+        write_message(&mut client, &message).await.unwrap();
+        write_message(&mut client, &json!({ "jsonrpc": "2.0", "method": "exit" }))
+            .await
+            .unwrap();
+        drop(client);
+
+        let mut reader = BufReader::new(server);
+        assert_eq!(read_message(&mut reader).await.unwrap(), Some(message));
+        assert_eq!(
+            read_message(&mut reader).await.unwrap().unwrap()["method"],
+            "exit"
+        );
+        assert_eq!(read_message(&mut reader).await.unwrap(), None);
+    }
+
+    #[test]
+    fn test_handle_messages() {
+        let state = Mutex::new(State::default());
+        let published = Notify::new();
+
+        let (tx, mut rx) = oneshot::channel();
+        state.lock().unwrap().pending.insert(7, tx);
+        let response = json!({ "jsonrpc": "2.0", "id": 7, "result": { "capabilities": {} } });
+        assert_eq!(handle_message(response, &state, &published), None);
+        assert!(rx.try_recv().unwrap().is_ok());
+
+        let request = json!({
+            "jsonrpc": "2.0", "id": 3, "method": "workspace/configuration",
+            "params": { "items": [{}, {}] }
+        });
+        let reply = handle_message(request, &state, &published).unwrap();
+        assert_eq!(reply["id"], 3);
+        assert_eq!(reply["result"], json!([null, null]));
+
+        let notification = json!({
+            "jsonrpc": "2.0",
+            "method": "textDocument/publishDiagnostics",
+            "params": {
+                "uri": "file:///work/src/lib.rs",
+                "diagnostics": [{
+                    "range": { "start": { "line": 11, "character": 4 }, "end": { "line": 11, "character": 9 } },
+                    "severity": 1,
+                    "code": "E0308",
+                    "source": "rustc",
+                    "message": "mismatched types\nexpected `u32`, found `&str`"
+                }]
+            }
+        });
+        assert_eq!(handle_message(notification, &state, &published), None);
+        let state = state.lock().unwrap();
+        let path = PathBuf::from("/work/src/lib.rs");
+        assert_eq!(state.publications[&path], 1);
+        let diagnostics = &state.diagnostics[&path];
+        assert_eq!(diagnostics[0].line, 12);
+        assert_eq!(diagnostics[0].column, 5);
+
+        let text = format_diagnostics(diagnostics, Path::new("/work"));
+        assert!(text.starts_with("1 error(s), 0 warning(s), 0 other\n"));
+        assert!(text.contains(
+            "src/lib.rs:12:5: error[E0308]: mismatched types\n    expected `u32`, found `&str` (rustc)"
+        ));
+    }
+
+    #[test]
+    fn test_hover_text_shapes() {
+        let markup =
+            json!({ "contents": { "kind": "markdown", "value": "```rust\nfn run()\n```" } });
+        assert_eq!(hover_text(&markup).unwrap(), "```rust\nfn run()\n```");
+
+        let marked =
+            json!({ "contents": [{ "language": "python", "value": "def run()" }, "Runs it."] });
+        assert_eq!(
+            hover_text(&marked).unwrap(),
+            "```python\ndef run()\n```\n\nRuns it."
+        );
+
+        assert_eq!(hover_text(&json!({ "contents": "" })), None);
+        assert_eq!(hover_text(&Value::Null), None);
+    }
+
+    #[test]
+    fn test_find_symbol() {
+        let source = "use crate::Agent;\n\nfn build() -> Agent {\n    Agent::new(\"naïve\")\n}\n";
+        assert_eq!(find_symbol(source, "Agent", None), Some((0, 11)));
+        assert_eq!(find_symbol(source, "Agent", Some(3)), Some((2, 14)));
+        assert_eq!(find_symbol(source, "Agent::new", None), Some((3, 11)));
+        // Whole words only
+        assert_eq!(find_symbol(source, "Agen", None), None);
+        assert_eq!(
+            find_symbol("let s = \"é\"; s.len()", "len", None),
+            Some((0, 15))
+        );
+    }
+}
@@ -0,0 +1,227 @@
+//! Audio notifications for users running G3 in the background.
+//!
+//! With `[notifications] enabled = true`, a sound, a spoken announcement or
+//! the terminal bell marks the moments that need the user: a long task
+//! finishing, a tool call or supervised flock waiting for approval and a
+//! flock segment failing.
+//!
+//! Sounds and speech go through the OS's own players (`afplay`/`say` on
+//! macOS, `paplay`/`aplay` and `spd-say`/`espeak` on Linux, PowerShell on
+//! Windows), started in the background so the agent never waits for them.
+//! When none is available the terminal bell rings instead.
+
+use g3_config::{NotificationMode, NotificationsConfig};
+use std::io::Write;
+use std::process::{Command, Stdio};
+use std::time::Duration;
+use tracing::debug;
+
+/// Something the user should hear about
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Notification {
+    /// A task took `elapsed`; only announced past `long_task_secs`
+    TaskFinished { elapsed: Duration, success: bool },
+    /// A tool call is waiting for the user to allow it
+    ApprovalNeeded { tool: String },
+    /// A flock segment ended in failure
+    SegmentFailed { segment_id: usize },
+    /// A supervised flock is waiting at a barrier for the user to approve
+    FlockAwaitingApproval { barrier: String },
+}
+
+impl Notification {
+    /// What speech mode says
+    pub fn message(&self) -> String {
+        match self {
+            Self::TaskFinished { success: true, .. } => "G3 finished the task".to_string(),
+            Self::TaskFinished { success: false, .. } => "G3 task failed".to_string(),
+            Self::ApprovalNeeded { tool } => {
+                format!("G3 needs approval to run {}", tool.replace('_', " "))
+            }
+            Self::SegmentFailed { segment_id } => format!("Flock segment {} failed", segment_id),
+            Self::FlockAwaitingApproval { barrier } => {
+                format!("Flock waiting for approval {}", barrier.replace('-', " "))
+            }
+        }
+    }
+
+    fn wanted(&self, config: &NotificationsConfig) -> bool {
+        match self {
+            Self::TaskFinished { elapsed, .. } => {
+                config.on_task_finished && elapsed.as_secs() >= config.long_task_secs
+            }
+            Self::ApprovalNeeded { .. } | Self::FlockAwaitingApproval { .. } => config.on_approval,
+            Self::SegmentFailed { .. } => config.on_segment_failure,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Notifier {
+    config: NotificationsConfig,
+}
+
+impl Notifier {
+    pub fn new(config: NotificationsConfig) -> Self {
+        Self { config }
+    }
+
+    /// Play `notification` if notifications are on and it is one the user
+    /// asked for. Never blocks and never fails.
+    pub fn notify(&self, notification: &Notification) {
+        if !self.config.enabled || !notification.wanted(&self.config) {
+            return;
+        }
+        debug!("Notifying: {}", notification.message());
+
+        let sound = self
+            .config
+            .sound
+            .as_deref()
+            .map(|sound| shellexpand::tilde(sound).into_owned());
+        let played = player_commands(
+            self.config.mode,
+            sound.as_deref(),
+            &notification.message(),
+            std::env::consts::OS,
+        )
+        .into_iter()
+        .any(|(program, args)| spawn(&program, &args));
+        if !played {
+            ring_bell();
+        }
+    }
+}
+
+/// Candidate commands for a notification on `os`, in order of preference;
+/// empty for the bell
+fn player_commands(
+    mode: NotificationMode,
+    sound: Option<&str>,
+    message: &str,
+    os: &str,
+) -> Vec<(String, Vec<String>)> {
+    let command = |program: &str, args: &[&str]| -> (String, Vec<String>) {
+        (
+            program.to_string(),
+            args.iter().map(|arg| arg.to_string()).collect(),
+        )
+    };
+    match (mode, os) {
+        (NotificationMode::Bell, _) => Vec::new(),
+        (NotificationMode::Speech, "macos") => vec![command("say", &[message])],
+        (NotificationMode::Speech, "windows") => {
+            let script = format!(
+                "Add-Type -AssemblyName System.Speech; \
+                 (New-Object System.Speech.Synthesis.SpeechSynthesizer).Speak('{}')",
+                message.replace('\'', "''")
+            );
+            vec![command("powershell", &["-NoProfile", "-Command", &script])]
+        }
+        (NotificationMode::Speech, _) => vec![
+            command("spd-say", &[message]),
+            command("espeak", &[message]),
+        ],
+        (NotificationMode::Sound, "macos") => vec![command(
+            "afplay",
+            &[sound.unwrap_or("/System/Library/Sounds/Glass.aiff")],
+        )],
+        (NotificationMode::Sound, "windows") => {
+            let script = match sound {
+                Some(sound) => format!(
+                    "(New-Object Media.SoundPlayer '{}').PlaySync()",
+                    sound.replace('\'', "''")
+                ),
+                None => {
+                    "[System.Media.SystemSounds]::Asterisk.Play(); Start-Sleep -Milliseconds 500"
+                        .to_string()
+                }
+            };
+            vec![command("powershell", &["-NoProfile", "-Command", &script])]
+        }
+        (NotificationMode::Sound, _) => {
+            let sound = sound.unwrap_or("/usr/share/sounds/freedesktop/stereo/complete.oga");
+            vec![
+                command("paplay", &[sound]),
+                command("aplay", &["-q", sound]),
+            ]
+        }
+    }
+}
+
+/// Start `program` in the background; false if it couldn't be started
+fn spawn(program: &str, args: &[String]) -> bool {
+    match Command::new(program)
+        .args(args)
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+    {
+        Ok(mut child) => {
+            // Reap it once it's done so it doesn't linger as a zombie
+            std::thread::spawn(move || {
+                let _ = child.wait();
+            });
+            true
+        }
+        Err(e) => {
+            debug!("Could not run {}: {}", program, e);
+            false
+        }
+    }
+}
+
+fn ring_bell() {
+    let mut stderr = std::io::stderr();
+    let _ = stderr.write_all(b"\x07");
+    let _ = stderr.flush();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_short_tasks_and_disabled_events_are_skipped() {
+        let mut config = NotificationsConfig {
+            long_task_secs: 30,
+            ..Default::default()
+        };
+        let short = Notification::TaskFinished {
+            elapsed: Duration::from_secs(5),
+            success: true,
+        };
+        let long = Notification::TaskFinished {
+            elapsed: Duration::from_secs(45),
+            success: true,
+        };
+        assert!(!short.wanted(&config));
+        assert!(long.wanted(&config));
+
+        config.on_approval = false;
+        let approval = Notification::ApprovalNeeded {
+            tool: "write_file".to_string(),
+        };
+        assert!(!approval.wanted(&config));
+        assert_eq!(approval.message(), "G3 needs approval to run write file");
+    }
+
+    #[test]
+    fn test_player_commands_per_platform() {
+        let say = player_commands(NotificationMode::Speech, None, "done", "macos");
+        assert_eq!(say, vec![("say".to_string(), vec!["done".to_string()])]);
+
+        let linux = player_commands(
+            NotificationMode::Sound,
+            Some("/tmp/ding.wav"),
+            "done",
+            "linux",
+        );
+        let programs: Vec<&str> = linux.iter().map(|(program, _)| program.as_str()).collect();
+        assert_eq!(programs, vec!["paplay", "aplay"]);
+        assert_eq!(linux[0].1, vec!["/tmp/ding.wav".to_string()]);
+
+        assert!(player_commands(NotificationMode::Bell, None, "done", "linux").is_empty());
+    }
+}
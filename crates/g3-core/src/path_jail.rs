@@ -0,0 +1,244 @@
+//! Keeps file tools inside the workspace.
+//!
+//! With `[permissions] workspace_only` (the default), every path given to a
+//! file tool must resolve inside the workspace, the directory G3 works in,
+//! once `~`, `..` and symlinks are resolved, so a confused model can't read
+//! `~/.ssh` or edit system files. `allowed_paths` lists the files and
+//! directories outside the workspace that file tools may use anyway:
+//!
+//! ```toml
+//! [permissions]
+//! allowed_paths = ["~/notes", "/tmp"]
+//! ```
+//!
+//! Shell commands are not confined; the shell permission governs those.
+
+use serde_json::Value;
+use std::path::{Component, Path, PathBuf};
+
+use crate::ToolCall;
+
+/// The workspace and the exceptions file tools may reach
+#[derive(Debug, Clone)]
+pub struct PathJail {
+    root: PathBuf,
+    allowed: Vec<PathBuf>,
+}
+
+impl PathJail {
+    /// Confine file tools to `root`, except for `allowed_paths`, which may be
+    /// relative to it
+    pub fn new(root: &Path, allowed_paths: &[String]) -> Self {
+        let root = resolve(root, Path::new("."));
+        let allowed = allowed_paths
+            .iter()
+            .map(|path| resolve(&root, Path::new(shellexpand::tilde(path).as_ref())))
+            .collect();
+        Self { root, allowed }
+    }
+
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// Whether `path`, relative to the workspace, resolves inside it or
+    /// inside one of the allowed paths
+    pub fn contains(&self, path: &str) -> bool {
+        let resolved = resolve(&self.root, Path::new(shellexpand::tilde(path).as_ref()));
+        resolved.starts_with(&self.root)
+            || self
+                .allowed
+                .iter()
+                .any(|allowed| resolved.starts_with(allowed))
+    }
+
+    /// The paths of `tool_call` that resolve outside the jail
+    pub fn violations(&self, tool_call: &ToolCall) -> Vec<String> {
+        tool_paths(&tool_call.tool, &tool_call.args)
+            .into_iter()
+            .filter(|path| !self.contains(path))
+            .collect()
+    }
+}
+
+/// The file paths a call to a file tool names, including the images OCR
+/// reads and where screenshots, recordings and page sources are saved; empty
+/// for other tools
+pub fn tool_paths(tool_name: &str, args: &Value) -> Vec<String> {
+    let strings = |value: Option<&Value>| -> Vec<String> {
+        value
+            .and_then(Value::as_array)
+            .map(|items| {
+                items
+                    .iter()
+                    .filter_map(Value::as_str)
+                    .map(str::to_string)
+                    .collect()
+            })
+            .unwrap_or_default()
+    };
+    match tool_name {
+        "read_file" | "write_file" | "str_replace" | "hover" => {
+            // write_file also takes `path`, `file` or a [path, content] array
+            ["file_path", "path", "file"]
+                .iter()
+                .filter_map(|key| args.get(key))
+                .chain(args.get(0))
+                .filter_map(Value::as_str)
+                .map(str::to_string)
+                .collect()
+        }
+        "take_screenshot"
+        | "webdriver_screenshot"
+        | "recording_stop"
+        | "recording_replay"
+        | "extract_text"
+        | "extract_text_with_boxes" => args
+            .get("path")
+            .and_then(Value::as_str)
+            .map(|path| vec![path.to_string()])
+            .unwrap_or_default(),
+        "webdriver_get_page_source" => args
+            .get("save_to_file")
+            .and_then(Value::as_str)
+            .map(|path| vec![path.to_string()])
+            .unwrap_or_default(),
+        "read_image" => strings(args.get("file_paths")),
+        "get_diagnostics" => strings(args.get("paths")),
+        "code_search" => args
+            .get("searches")
+            .and_then(Value::as_array)
+            .map(|searches| {
+                searches
+                    .iter()
+                    .flat_map(|search| strings(search.get("paths")))
+                    .collect()
+            })
+            .unwrap_or_default(),
+        _ => Vec::new(),
+    }
+}
+
+/// `path` made absolute against `base`, with symlinks resolved as far as it
+/// exists and `..` applied to the rest (e.g. a file about to be created)
+pub fn resolve(base: &Path, path: &Path) -> PathBuf {
+    let absolute = base.join(path);
+    let components: Vec<Component> = absolute.components().collect();
+    for existing in (1..=components.len()).rev() {
+        let prefix: PathBuf = components[..existing].iter().collect();
+        if let Ok(mut resolved) = prefix.canonicalize() {
+            for component in &components[existing..] {
+                match component {
+                    Component::ParentDir => {
+                        resolved.pop();
+                    }
+                    Component::Normal(name) => resolved.push(name),
+                    _ => {}
+                }
+            }
+            return resolved;
+        }
+    }
+    absolute
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn call(tool: &str, args: Value) -> ToolCall {
+        ToolCall {
+            tool: tool.to_string(),
+            args,
+        }
+    }
+
+    #[test]
+    fn test_tool_paths() {
+        assert_eq!(
+            tool_paths("write_file", &json!({"path": "a.rs", "content": ""})),
+            vec!["a.rs"]
+        );
+        assert_eq!(
+            tool_paths("write_file", &json!(["b.rs", "fn main() {}"])),
+            vec!["b.rs"]
+        );
+        assert_eq!(
+            tool_paths(
+                "code_search",
+                &json!({"searches": [{"paths": ["src"]}, {"paths": ["/etc"]}]})
+            ),
+            vec!["src", "/etc"]
+        );
+        assert_eq!(
+            tool_paths(
+                "take_screenshot",
+                &json!({"path": "~/.bashrc", "window_id": "Safari"})
+            ),
+            vec!["~/.bashrc"]
+        );
+        assert_eq!(
+            tool_paths("webdriver_screenshot", &json!({"path": "/etc/motd"})),
+            vec!["/etc/motd"]
+        );
+        assert_eq!(
+            tool_paths("recording_stop", &json!({"path": "flows/login.json"})),
+            vec!["flows/login.json"]
+        );
+        assert_eq!(
+            tool_paths("extract_text", &json!({"path": "../secret.png"})),
+            vec!["../secret.png"]
+        );
+        assert_eq!(
+            tool_paths("extract_text_with_boxes", &json!({"path": "/tmp/shot.png"})),
+            vec!["/tmp/shot.png"]
+        );
+        assert_eq!(
+            tool_paths(
+                "webdriver_get_page_source",
+                &json!({"max_length": 0, "save_to_file": "/etc/cron.d/page"})
+            ),
+            vec!["/etc/cron.d/page"]
+        );
+        assert!(tool_paths("webdriver_get_page_source", &json!({})).is_empty());
+        assert!(tool_paths("shell", &json!({"command": "cat /etc/passwd"})).is_empty());
+    }
+
+    #[test]
+    fn test_jail() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let workspace = dir.path().join("workspace");
+        let notes = dir.path().join("notes");
+        std::fs::create_dir_all(workspace.join("src")).unwrap();
+        std::fs::create_dir_all(&notes).unwrap();
+        let jail = PathJail::new(&workspace, &[notes.display().to_string()]);
+
+        assert!(jail.contains("src/lib.rs"));
+        assert!(jail.contains("new/dir/file.rs"));
+        assert!(jail.contains(&workspace.join("src").display().to_string()));
+        assert!(jail.contains(&notes.join("todo.md").display().to_string()));
+        assert!(!jail.contains("../secret.txt"));
+        assert!(!jail.contains("src/../../secret.txt"));
+        assert!(!jail.contains("missing/../../secret.txt"));
+        assert!(!jail.contains("/etc/passwd"));
+
+        let call = call(
+            "read_image",
+            json!({"file_paths": ["logo.png", "/etc/a.png"]}),
+        );
+        assert_eq!(jail.violations(&call), vec!["/etc/a.png"]);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_symlinks_out_of_the_workspace_are_caught() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let workspace = dir.path().join("workspace");
+        std::fs::create_dir_all(&workspace).unwrap();
+        std::os::unix::fs::symlink(dir.path(), workspace.join("escape")).unwrap();
+        let jail = PathJail::new(&workspace, &[]);
+        assert!(!jail.contains("escape/secret.txt"));
+        assert!(jail.contains("escape/workspace/notes.md"));
+    }
+}
@@ -15,6 +15,9 @@ pub const G3_WORKSPACE_PATH_ENV: &str = "G3_WORKSPACE_PATH";
 /// Environment variable name for custom TODO file path.
 const G3_TODO_PATH_ENV: &str = "G3_TODO_PATH";
 
+/// Environment variable name for custom usage ledger path.
+const G3_USAGE_PATH_ENV: &str = "G3_USAGE_PATH";
+
 /// Get the path to the todo.g3.md file.
 ///
 /// Checks for G3_TODO_PATH environment variable first (used by planning mode),
@@ -91,12 +94,58 @@ pub fn get_session_file(session_id: &str) -> PathBuf {
     get_session_logs_dir(session_id).join("session.json")
 }
 
+/// Get the path to the incremental journal `g3 --resume` replays.
+/// Returns .g3/sessions/<session_id>/journal.jsonl
+pub fn get_session_journal_file(session_id: &str) -> PathBuf {
+    get_session_logs_dir(session_id).join("journal.jsonl")
+}
+
+/// Get the path to the journal of file edits `g3 undo` reverts.
+/// Returns .g3/sessions/<session_id>/edits.jsonl
+pub fn get_session_edits_file(session_id: &str) -> PathBuf {
+    get_session_logs_dir(session_id).join("edits.jsonl")
+}
+
 /// Get the path to the context summary file for a session.
 /// Returns .g3/sessions/<session_id>/context_summary.txt
 pub fn get_context_summary_file(session_id: &str) -> PathBuf {
     get_session_logs_dir(session_id).join("context_summary.txt")
 }
 
+/// Get the directory of cached deterministic LLM responses.
+/// Returns ~/.g3/cache/responses/, shared by all workspaces.
+pub fn get_response_cache_dir() -> PathBuf {
+    dirs::home_dir()
+        .unwrap_or_default()
+        .join(".g3")
+        .join("cache")
+        .join("responses")
+}
+
+/// Get the directory of user prompt overrides.
+/// Returns ~/.g3/prompts/, where `<name>.md` replaces the built-in prompt `name`.
+pub fn get_prompts_dir() -> PathBuf {
+    dirs::home_dir()
+        .unwrap_or_default()
+        .join(".g3")
+        .join("prompts")
+}
+
+/// Get the path to the token usage ledger.
+///
+/// Checks for G3_USAGE_PATH environment variable first, then falls back to
+/// ~/.g3/usage.jsonl, which is shared by all workspaces.
+pub fn get_usage_ledger_path() -> PathBuf {
+    if let Ok(custom_path) = std::env::var(G3_USAGE_PATH_ENV) {
+        PathBuf::from(custom_path)
+    } else {
+        dirs::home_dir()
+            .unwrap_or_default()
+            .join(".g3")
+            .join("usage.jsonl")
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -0,0 +1,195 @@
+//! Code review between the segments of a flock.
+//!
+//! A segment worker's `request_review` tool drops a request into the flock
+//! workspace's `reviews/` directory and waits for the answer. The flock picks
+//! the request up, has a reviewer in the target segment's workspace read the
+//! requester's diff, and writes the reviewer's comments back next to the
+//! request. Requests are `<id>.request.json` and answers `<id>.response.json`.
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+/// Directory in the flock workspace holding requests and answers
+pub const REVIEWS_DIR: &str = "reviews";
+
+/// How long `request_review` waits for the reviewer
+pub const REVIEW_TIMEOUT: Duration = Duration::from_secs(15 * 60);
+
+const POLL_INTERVAL: Duration = Duration::from_secs(2);
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ReviewRequest {
+    pub id: String,
+    /// Segment asking for the review
+    pub requester: usize,
+    /// Segment to review, by module name or number
+    pub target: String,
+    /// What the requester wants the reviewer to look at
+    #[serde(default)]
+    pub note: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ReviewResponse {
+    pub id: String,
+    /// Segment whose workspace the review ran in; None if it couldn't run
+    pub reviewer: Option<usize>,
+    pub comments: Option<String>,
+    pub error: Option<String>,
+}
+
+/// A flock's review requests and answers, as seen from one segment
+#[derive(Debug, Clone)]
+pub struct ReviewMailbox {
+    dir: PathBuf,
+    segment_id: usize,
+}
+
+impl ReviewMailbox {
+    /// The mailbox of segment `segment_id` in the flock at `flock_workspace`
+    pub fn new(flock_workspace: &Path, segment_id: usize) -> Self {
+        Self {
+            dir: flock_workspace.join(REVIEWS_DIR),
+            segment_id,
+        }
+    }
+
+    pub fn segment_id(&self) -> usize {
+        self.segment_id
+    }
+
+    /// Ask for a review of this segment's changes by `target`; returns the
+    /// request's id
+    pub fn submit(&self, target: &str, note: Option<String>) -> Result<String> {
+        let id = format!(
+            "segment-{}-{}",
+            self.segment_id,
+            chrono::Utc::now().format("%Y%m%d%H%M%S%3f")
+        );
+        let request = ReviewRequest {
+            id: id.clone(),
+            requester: self.segment_id,
+            target: target.to_string(),
+            note,
+        };
+        write_json(&self.dir, &format!("{}.request.json", id), &request)?;
+        Ok(id)
+    }
+
+    /// Wait for the answer to request `id`, checking every couple of seconds
+    pub async fn wait_for_response(&self, id: &str, timeout: Duration) -> Result<ReviewResponse> {
+        let path = self.dir.join(format!("{}.response.json", id));
+        let deadline = Instant::now() + timeout;
+        loop {
+            if path.exists() {
+                let json = std::fs::read_to_string(&path)?;
+                return serde_json::from_str(&json).context("Invalid review response");
+            }
+            if Instant::now() >= deadline {
+                anyhow::bail!(
+                    "No review arrived within {} minutes",
+                    timeout.as_secs() / 60
+                );
+            }
+            tokio::time::sleep(POLL_INTERVAL).await;
+        }
+    }
+}
+
+/// Requests in `reviews_dir` that have no answer yet, oldest id first
+pub fn unanswered_requests(reviews_dir: &Path) -> Result<Vec<ReviewRequest>> {
+    let entries = match std::fs::read_dir(reviews_dir) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e.into()),
+    };
+    let mut requests = Vec::new();
+    for entry in entries {
+        let path = entry?.path();
+        let Some(id) = path
+            .file_name()
+            .and_then(|name| name.to_str())
+            .and_then(|name| name.strip_suffix(".request.json"))
+        else {
+            continue;
+        };
+        if reviews_dir.join(format!("{}.response.json", id)).exists() {
+            continue;
+        }
+        let json = std::fs::read_to_string(&path)?;
+        requests.push(
+            serde_json::from_str(&json)
+                .with_context(|| format!("Invalid review request {}", path.display()))?,
+        );
+    }
+    requests.sort_by(|a: &ReviewRequest, b| a.id.cmp(&b.id));
+    Ok(requests)
+}
+
+/// Answer a request in `reviews_dir`
+pub fn respond(reviews_dir: &Path, response: &ReviewResponse) -> Result<()> {
+    write_json(
+        reviews_dir,
+        &format!("{}.response.json", response.id),
+        response,
+    )
+}
+
+/// Write through a temporary file so readers never see half a file
+fn write_json<T: Serialize>(dir: &Path, name: &str, value: &T) -> Result<()> {
+    std::fs::create_dir_all(dir)?;
+    let tmp = dir.join(format!(".{}.tmp", name));
+    std::fs::write(&tmp, serde_json::to_string_pretty(value)?)?;
+    std::fs::rename(&tmp, dir.join(name)).context("Failed to write review file")?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_request_and_response_round_trip() {
+        let flock = tempfile::TempDir::new().unwrap();
+        let reviews_dir = flock.path().join(REVIEWS_DIR);
+        assert!(unanswered_requests(&reviews_dir).unwrap().is_empty());
+
+        let mailbox = ReviewMailbox::new(flock.path(), 2);
+        let id = mailbox
+            .submit("parser", Some("Check the token API".to_string()))
+            .unwrap();
+
+        let requests = unanswered_requests(&reviews_dir).unwrap();
+        assert_eq!(requests.len(), 1);
+        assert_eq!(requests[0].requester, 2);
+        assert_eq!(requests[0].target, "parser");
+
+        let response = ReviewResponse {
+            id: id.clone(),
+            reviewer: Some(1),
+            comments: Some("Looks consistent".to_string()),
+            error: None,
+        };
+        respond(&reviews_dir, &response).unwrap();
+        assert!(unanswered_requests(&reviews_dir).unwrap().is_empty());
+        assert_eq!(
+            mailbox
+                .wait_for_response(&id, Duration::from_secs(1))
+                .await
+                .unwrap(),
+            response
+        );
+    }
+
+    #[tokio::test]
+    async fn test_wait_times_out() {
+        let flock = tempfile::TempDir::new().unwrap();
+        let mailbox = ReviewMailbox::new(flock.path(), 1);
+        assert!(mailbox
+            .wait_for_response("missing", Duration::ZERO)
+            .await
+            .is_err());
+    }
+}
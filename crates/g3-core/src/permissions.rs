@@ -0,0 +1,265 @@
+//! Tool permissions.
+//!
+//! Every tool belongs to a [`ToolClass`]. The `[permissions]` section of the
+//! config sets a [`ToolPermission`] per class: `allow` runs the tool, `deny`
+//! refuses it and `ask` has the agent prompt the user before each call.
+//! The prompt is an [`ApprovalRequest`]; answering it "always" or "deny
+//! always" settles that tool for the rest of the session.
+//!
+//! ```toml
+//! [permissions]
+//! shell = "ask"
+//! network = "deny"
+//! ```
+
+use g3_config::{PermissionsConfig, ToolPermission};
+use std::collections::HashMap;
+use std::fmt;
+
+/// What a tool can touch.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ToolClass {
+    /// Reads files or the agent's own state
+    ReadOnly,
+    /// Modifies files in the workspace
+    Write,
+    /// Runs arbitrary commands
+    Shell,
+    /// Drives a browser
+    Network,
+    /// Sees or controls the desktop
+    ComputerControl,
+}
+
+impl fmt::Display for ToolClass {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ToolClass::ReadOnly => write!(f, "read-only"),
+            ToolClass::Write => write!(f, "write"),
+            ToolClass::Shell => write!(f, "shell"),
+            ToolClass::Network => write!(f, "network"),
+            ToolClass::ComputerControl => write!(f, "computer-control"),
+        }
+    }
+}
+
+/// The class of a tool, by name.
+pub fn classify(tool_name: &str) -> ToolClass {
+    match tool_name {
+        // The TODO tools and final_output only touch the agent's own state,
+        // and request_review only has another flock segment read the diff
+        "read_file" | "read_image" | "code_search" | "git_context" | "get_diagnostics"
+        | "hover" | "todo_read" | "todo_write" | "final_output" | "request_review" => {
+            ToolClass::ReadOnly
+        }
+        "write_file" | "str_replace" => ToolClass::Write,
+        "shell" | "background_process" | "code_coverage" => ToolClass::Shell,
+        name if name.starts_with("webdriver_") => ToolClass::Network,
+        "take_screenshot"
+        | "extract_text"
+        | "extract_text_with_boxes"
+        | "list_windows"
+        | "focus_window"
+        | "move_resize_window" => ToolClass::ComputerControl,
+        name if name.starts_with("macax_")
+            || name.starts_with("vision_")
+            || name.starts_with("recording_") =>
+        {
+            ToolClass::ComputerControl
+        }
+        // Unknown tools are rejected by the agent anyway; treat them as
+        // able to change things
+        _ => ToolClass::Write,
+    }
+}
+
+/// Whether a tool may run in explain mode (`[agent] read_only`), whatever
+/// the permissions policy says. `todo_write` is left out: without a session
+/// it writes `todo.g3.md` in the working directory.
+pub fn runs_read_only(tool_name: &str) -> bool {
+    tool_name != "todo_write" && classify(tool_name) == ToolClass::ReadOnly
+}
+
+/// The configured permission for a class.
+pub fn permission_for(config: &PermissionsConfig, class: ToolClass) -> ToolPermission {
+    match class {
+        ToolClass::ReadOnly => config.read_only,
+        ToolClass::Write => config.write,
+        ToolClass::Shell => config.shell,
+        ToolClass::Network => config.network,
+        ToolClass::ComputerControl => config.computer_control,
+    }
+}
+
+/// Classify a tool and look up its permission.
+pub fn check(config: &PermissionsConfig, tool_name: &str) -> (ToolClass, ToolPermission) {
+    let class = classify(tool_name);
+    (class, permission_for(config, class))
+}
+
+/// A tool call waiting for the user's decision
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ApprovalRequest {
+    pub tool: String,
+    pub class: ToolClass,
+    /// The call's arguments as JSON, shortened for display
+    pub args_preview: String,
+    /// Risk level and reasons of a shell command flagged as destructive
+    pub risk: Option<String>,
+    /// What a write_file or str_replace call would change, as a unified diff
+    pub diff: Option<String>,
+}
+
+impl ApprovalRequest {
+    /// The request as text, for UIs that can only ask a yes/no question
+    pub fn prompt(&self) -> String {
+        let mut prompt = format!(
+            "🔐 {} is a {} tool:\n  {}",
+            self.tool, self.class, self.args_preview
+        );
+        if let Some(risk) = &self.risk {
+            prompt.push_str(&format!("\n⚠️  Risk: {}", risk));
+        }
+        if let Some(diff) = &self.diff {
+            prompt.push_str(&format!("\n{}", diff.trim_end()));
+        }
+        prompt.push_str("\nAllow it?");
+        prompt
+    }
+}
+
+/// The user's answer to an [`ApprovalRequest`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ApprovalDecision {
+    Allow,
+    Deny,
+    /// Allow this tool without asking again this session
+    AlwaysAllow,
+    /// Refuse this tool without asking again this session
+    AlwaysDeny,
+}
+
+impl ApprovalDecision {
+    /// The decision for a key: y, n, a(lways) or d(eny always)
+    pub fn from_key(key: char) -> Option<Self> {
+        match key.to_ascii_lowercase() {
+            'y' => Some(Self::Allow),
+            'n' => Some(Self::Deny),
+            'a' => Some(Self::AlwaysAllow),
+            'd' => Some(Self::AlwaysDeny),
+            _ => None,
+        }
+    }
+
+    pub fn allows(self) -> bool {
+        matches!(self, Self::Allow | Self::AlwaysAllow)
+    }
+}
+
+/// "Always" and "deny always" answers, kept for the rest of the session
+#[derive(Debug, Default)]
+pub struct SessionApprovals {
+    by_key: HashMap<String, bool>,
+}
+
+impl SessionApprovals {
+    /// A remembered answer for `key` (a tool name, or a shell command):
+    /// `Some(true)` to allow, `Some(false)` to deny
+    pub fn get(&self, key: &str) -> Option<bool> {
+        self.by_key.get(key).copied()
+    }
+
+    /// Remember `decision` for `key` if it was an "always" answer
+    pub fn record(&mut self, key: &str, decision: ApprovalDecision) {
+        match decision {
+            ApprovalDecision::AlwaysAllow => {
+                self.by_key.insert(key.to_string(), true);
+            }
+            ApprovalDecision::AlwaysDeny => {
+                self.by_key.insert(key.to_string(), false);
+            }
+            ApprovalDecision::Allow | ApprovalDecision::Deny => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_classify() {
+        assert_eq!(classify("read_file"), ToolClass::ReadOnly);
+        assert_eq!(classify("todo_write"), ToolClass::ReadOnly);
+        assert_eq!(classify("str_replace"), ToolClass::Write);
+        assert_eq!(classify("background_process"), ToolClass::Shell);
+        assert_eq!(classify("webdriver_navigate"), ToolClass::Network);
+        assert_eq!(classify("take_screenshot"), ToolClass::ComputerControl);
+        assert_eq!(classify("macax_type_text"), ToolClass::ComputerControl);
+        assert_eq!(classify("vision_click_text"), ToolClass::ComputerControl);
+        assert_eq!(classify("mystery_tool"), ToolClass::Write);
+    }
+
+    #[test]
+    fn test_read_only_tools() {
+        for tool in ["read_file", "code_search", "hover", "todo_read"] {
+            assert!(runs_read_only(tool), "{}", tool);
+        }
+        for tool in ["write_file", "shell", "webdriver_click", "todo_write", "mystery_tool"] {
+            assert!(!runs_read_only(tool), "{}", tool);
+        }
+    }
+
+    #[test]
+    fn test_check_uses_class_policy() {
+        let config = PermissionsConfig {
+            shell: ToolPermission::Ask,
+            network: ToolPermission::Deny,
+            ..Default::default()
+        };
+        assert_eq!(
+            check(&config, "shell"),
+            (ToolClass::Shell, ToolPermission::Ask)
+        );
+        assert_eq!(
+            check(&config, "webdriver_start"),
+            (ToolClass::Network, ToolPermission::Deny)
+        );
+        assert_eq!(
+            check(&config, "write_file"),
+            (ToolClass::Write, ToolPermission::Allow)
+        );
+        assert_eq!(
+            check(&PermissionsConfig::default(), "macax_press_key").1,
+            ToolPermission::Allow
+        );
+    }
+
+    #[test]
+    fn test_session_approvals_remember_always_answers() {
+        let mut approvals = SessionApprovals::default();
+        approvals.record("shell", ApprovalDecision::Allow);
+        assert_eq!(approvals.get("shell"), None);
+
+        approvals.record("shell", ApprovalDecision::from_key('A').unwrap());
+        approvals.record("write_file", ApprovalDecision::from_key('d').unwrap());
+        assert_eq!(approvals.get("shell"), Some(true));
+        assert_eq!(approvals.get("write_file"), Some(false));
+        assert_eq!(ApprovalDecision::from_key('x'), None);
+    }
+
+    #[test]
+    fn test_approval_prompt_includes_risk_and_diff() {
+        let request = ApprovalRequest {
+            tool: "write_file".to_string(),
+            class: ToolClass::Write,
+            args_preview: "{\"file_path\":\"a.txt\"}".to_string(),
+            risk: None,
+            diff: Some("--- a.txt\n+++ a.txt\n-old\n+new\n".to_string()),
+        };
+        assert_eq!(
+            request.prompt(),
+            "🔐 write_file is a write tool:\n  {\"file_path\":\"a.txt\"}\n--- a.txt\n+++ a.txt\n-old\n+new\nAllow it?"
+        );
+    }
+}
@@ -0,0 +1,244 @@
+//! Named prompt templates that users can override.
+//!
+//! Each crate registers its built-in prompts under a name, together with the
+//! placeholders (`{requirements}`, `{plan_dir}`...) its callers fill in. A
+//! file `~/.g3/prompts/<name>.md` replaces the built-in text, so a team can
+//! change the coaching or refinement style without forking G3. An override
+//! that drops a required placeholder is rejected with an error instead of
+//! silently sending the model a prompt without that context.
+//!
+//! `g3 prompts list` shows every template and which ones are overridden;
+//! `g3 prompts export <name>` copies a built-in prompt into the overrides
+//! directory as a starting point.
+
+use anyhow::{bail, Context, Result};
+use std::fs;
+use std::io::ErrorKind;
+use std::path::PathBuf;
+
+use crate::paths::get_prompts_dir;
+
+/// A built-in prompt
+#[derive(Debug, Clone, Copy)]
+pub struct PromptTemplate {
+    /// Name of the template, and of its override file without `.md`
+    pub name: &'static str,
+    pub description: &'static str,
+    pub text: &'static str,
+    /// Names of the `{placeholders}` an override must keep
+    pub placeholders: &'static [&'static str],
+}
+
+/// Where a template's text comes from
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TemplateSource {
+    BuiltIn,
+    Override(PathBuf),
+}
+
+/// Built-in prompts, looked up by name with user overrides applied
+#[derive(Debug, Clone)]
+pub struct PromptRegistry {
+    templates: &'static [PromptTemplate],
+    override_dir: PathBuf,
+}
+
+impl PromptRegistry {
+    /// A registry of `templates`, overridden from `~/.g3/prompts/`
+    pub fn new(templates: &'static [PromptTemplate]) -> Self {
+        Self {
+            templates,
+            override_dir: get_prompts_dir(),
+        }
+    }
+
+    /// Read overrides from `dir` instead of `~/.g3/prompts/`
+    pub fn with_override_dir(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.override_dir = dir.into();
+        self
+    }
+
+    pub fn templates(&self) -> &'static [PromptTemplate] {
+        self.templates
+    }
+
+    pub fn template(&self, name: &str) -> Result<&'static PromptTemplate> {
+        self.templates
+            .iter()
+            .find(|template| template.name == name)
+            .with_context(|| format!("Unknown prompt template '{}'", name))
+    }
+
+    /// The file that overrides the template `name`, whether or not it exists
+    pub fn override_path(&self, name: &str) -> PathBuf {
+        self.override_dir.join(format!("{}.md", name))
+    }
+
+    /// Where the text of `name` comes from; fails if its override is invalid
+    pub fn source(&self, name: &str) -> Result<TemplateSource> {
+        let template = self.template(name)?;
+        Ok(match self.read_override(template)? {
+            Some(_) => TemplateSource::Override(self.override_path(name)),
+            None => TemplateSource::BuiltIn,
+        })
+    }
+
+    /// The text of `name`, placeholders unfilled
+    pub fn text(&self, name: &str) -> Result<String> {
+        let template = self.template(name)?;
+        Ok(self
+            .read_override(template)?
+            .unwrap_or_else(|| template.text.to_string()))
+    }
+
+    /// The text of `name` with `vars` filled in
+    pub fn render(&self, name: &str, vars: &[(&str, &str)]) -> Result<String> {
+        Ok(interpolate(&self.text(name)?, vars))
+    }
+
+    /// Write the built-in text of `name` to its override file, to be edited
+    pub fn export(&self, name: &str) -> Result<PathBuf> {
+        let template = self.template(name)?;
+        let path = self.override_path(name);
+        if path.exists() {
+            bail!("{} already exists", path.display());
+        }
+        fs::create_dir_all(&self.override_dir)
+            .with_context(|| format!("Failed to create {}", self.override_dir.display()))?;
+        fs::write(&path, format!("{}\n", template.text))
+            .with_context(|| format!("Failed to write {}", path.display()))?;
+        Ok(path)
+    }
+
+    fn read_override(&self, template: &PromptTemplate) -> Result<Option<String>> {
+        let path = self.override_path(template.name);
+        let text = match fs::read_to_string(&path) {
+            Ok(text) => text.trim_end().to_string(),
+            Err(e) if e.kind() == ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e).with_context(|| format!("Failed to read {}", path.display())),
+        };
+        validate(template, &text)
+            .with_context(|| format!("Invalid prompt override {}", path.display()))?;
+        Ok(Some(text))
+    }
+}
+
+/// Check that `text` can stand in for `template`: not empty, and keeping
+/// every required placeholder
+pub fn validate(template: &PromptTemplate, text: &str) -> Result<()> {
+    if text.trim().is_empty() {
+        bail!("The prompt is empty");
+    }
+    let missing: Vec<String> = template
+        .placeholders
+        .iter()
+        .map(|name| format!("{{{}}}", name))
+        .filter(|placeholder| !text.contains(placeholder.as_str()))
+        .collect();
+    if !missing.is_empty() {
+        bail!(
+            "Missing placeholder(s) {}, required by '{}'",
+            missing.join(", "),
+            template.name
+        );
+    }
+    Ok(())
+}
+
+/// Replace each `{name}` in `text` with its value in `vars`, in one pass so
+/// values that contain braces are left alone. Other braces are kept as is.
+pub fn interpolate(text: &str, vars: &[(&str, &str)]) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find('{') {
+        out.push_str(&rest[..start]);
+        let after = &rest[start + 1..];
+        let var = after.find('}').and_then(|end| {
+            let name = &after[..end];
+            vars.iter()
+                .find(|(var, _)| *var == name)
+                .map(|(_, value)| (end, *value))
+        });
+        match var {
+            Some((end, value)) => {
+                out.push_str(value);
+                rest = &after[end + 1..];
+            }
+            None => {
+                out.push('{');
+                rest = after;
+            }
+        }
+    }
+    out.push_str(rest);
+    out
+}
+
+/// The agent's own prompts
+pub fn core() -> PromptRegistry {
+    PromptRegistry::new(crate::prompts::TEMPLATES)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TEMPLATES: &[PromptTemplate] = &[PromptTemplate {
+        name: "summary",
+        description: "Summarize requirements",
+        text: "Summarize:\n{requirements}",
+        placeholders: &["requirements"],
+    }];
+
+    #[test]
+    fn test_interpolate() {
+        let vars = [
+            ("requirements", "Parse {todo_file}"),
+            ("todo_file", "todo.md"),
+        ];
+        assert_eq!(
+            interpolate("{requirements} -> {todo_file} {{SUMMARY}} {}", &vars),
+            "Parse {todo_file} -> todo.md {{SUMMARY}} {}"
+        );
+    }
+
+    #[test]
+    fn test_overrides() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let registry = PromptRegistry::new(TEMPLATES).with_override_dir(dir.path());
+        let vars = [("requirements", "Add a parser")];
+        assert_eq!(registry.source("summary").unwrap(), TemplateSource::BuiltIn);
+        assert_eq!(
+            registry.render("summary", &vars).unwrap(),
+            "Summarize:\nAdd a parser"
+        );
+        assert!(registry.text("missing").is_err());
+
+        fs::write(
+            dir.path().join("summary.md"),
+            "In one line: {requirements}\n",
+        )
+        .unwrap();
+        assert_eq!(
+            registry.render("summary", &vars).unwrap(),
+            "In one line: Add a parser"
+        );
+        assert!(registry.export("summary").is_err());
+
+        fs::write(dir.path().join("summary.md"), "Summarize the requirements").unwrap();
+        let err = registry.source("summary").unwrap_err();
+        assert!(format!("{:#}", err).contains("Missing placeholder(s) {requirements}"));
+    }
+
+    #[test]
+    fn test_export() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let registry = PromptRegistry::new(TEMPLATES).with_override_dir(dir.path().join("prompts"));
+        let path = registry.export("summary").unwrap();
+        assert_eq!(
+            registry.source("summary").unwrap(),
+            TemplateSource::Override(path)
+        );
+        assert_eq!(registry.text("summary").unwrap(), TEMPLATES[0].text);
+    }
+}
@@ -1,4 +1,8 @@
+use anyhow::Result;
 use const_format::concatcp;
+
+use crate::prompt_templates::{self, PromptTemplate};
+
 const CODING_STYLE: &'static str = "# IMPORTANT FOR CODING:
 It is very important that you adhere to these principles when writing code. I will use a code quality tool to assess the code you have generated.
 
@@ -152,6 +156,10 @@ When you need to look up documentation, search for resources, find data online,
 - Parse the HTML text content to find what you need
 - For search engines, look for result links and titles in the HTML
 - Close the WebDriver session when you're done to free resources
+- For multi-step flows (log in, fill a form, check the result), use a single `webdriver_run_script` call instead of many single-step calls
+- Elements inside iframes need `webdriver_switch_to_frame` first; for elements inside shadow DOM, pass the host's selector as `shadow_host`
+- When testing a web app, check `webdriver_get_console_logs` after interacting with the page to catch JavaScript errors (Chrome only)
+- When testing a web app, call `webdriver_start_network_recording` before interacting with the page and `webdriver_get_network_requests` afterwards to check the API calls it made (Chrome only)
 
 # Code Search Guidelines
 
@@ -212,19 +220,22 @@ pub const SYSTEM_PROMPT_FOR_NATIVE_TOOL_USE: &'static str =
 /// Generate system prompt based on whether multiple tool calls are allowed
 pub fn get_system_prompt_for_native(allow_multiple: bool) -> String {
     if allow_multiple {
-        // Replace the "ONE tool" instruction with multiple tools instruction
-        let base = SYSTEM_PROMPT_FOR_NATIVE_TOOL_USE.to_string();
-        base.replace(
-            "2. Call the appropriate tool with the required parameters",
-            "2. Call the appropriate tool(s) with the required parameters - you may call multiple tools in parallel when appropriate. 
+        with_parallel_tool_calls(SYSTEM_PROMPT_FOR_NATIVE_TOOL_USE)
+    } else {
+        SYSTEM_PROMPT_FOR_NATIVE_TOOL_USE.to_string()
+    }
+}
+
+/// Replace the "ONE tool" instruction with multiple tools instruction
+fn with_parallel_tool_calls(prompt: &str) -> String {
+    prompt.replace(
+        "2. Call the appropriate tool with the required parameters",
+        "2. Call the appropriate tool(s) with the required parameters - you may call multiple tools in parallel when appropriate. 
               <use_parallel_tool_calls>
   For maximum efficiency, whenever you perform multiple independent operations, invoke all relevant tools simultaneously rather than sequentially. Prioritize calling tools in parallel whenever possible. For example, when reading 3 files, run 3 tool calls in parallel to read all 3 files into context at the same time. When running multiple read-only commands like `ls` or `list_dir`, always run all of the commands in parallel. Err on the side of maximizing parallel tool calls rather than running too many tools sequentially.
   </use_parallel_tool_calls>
 "
-        )
-    } else {
-        SYSTEM_PROMPT_FOR_NATIVE_TOOL_USE.to_string()
-    }
+    )
 }
 
 const SYSTEM_NON_NATIVE_TOOL_USE: &'static str =
@@ -251,7 +262,10 @@ Short description for providers without native calling specs:
 - **background_process**: Launch a long-running process in the background (e.g., game servers, dev servers)
   - Format: {\"tool\": \"background_process\", \"args\": {\"name\": \"unique_name\", \"command\": \"your_command\"}}
   - Example: {\"tool\": \"background_process\", \"args\": {\"name\": \"game_server\", \"command\": \"./run.sh\"}}
-  - Returns PID and log file path. Use shell tool to read logs (`tail -100 <logfile>`), check status (`ps -p <pid>`), or stop (`kill <pid>`)
+  - Example (auto-restart): {\"tool\": \"background_process\", \"args\": {\"name\": \"dev_server\", \"command\": \"npm run dev\", \"health_check\": {\"tcp_port\": 3000}, \"restart\": \"on-failure\", \"max_restarts\": 3}}
+  - Example (periodic): {\"tool\": \"background_process\", \"args\": {\"name\": \"build_check\", \"command\": \"cargo check\", \"every_secs\": 60}}
+  - Use \"{PORT}\" in the command, \"env\" values or health check to get a free port, e.g. {\"command\": \"npm run dev -- --port {PORT}\", \"env\": {\"NODE_ENV\": \"development\"}}
+  - Returns PID and log file path. Use shell tool to read logs (`tail -100 <logfile>`), check status (`ps -p <pid>`), or stop it with its children (`kill -- -<pid>`)
   - Note: Process runs independently; logs are captured to a file for later inspection
 
 - **read_file**: Read the contents of a file (supports partial reads via start/end)
@@ -282,6 +296,22 @@ Short description for providers without native calling specs:
   - Format: {\"tool\": \"todo_write\", \"args\": {\"content\": \"- [ ] Task 1\\n- [ ] Task 2\"}}
   - Example: {\"tool\": \"todo_write\", \"args\": {\"content\": \"- [ ] Implement feature\\n  - [ ] Write tests\\n  - [ ] Run tests\"}}
 
+- **git_context**: Summarize recent repository activity: branch, ahead/behind upstream, recent commits and merge conflicts
+  - Format: {\"tool\": \"git_context\", \"args\": {\"paths\": [\"optional/path\"], \"commits\": 10}}
+  - Example: {\"tool\": \"git_context\", \"args\": {\"paths\": [\"src/parser\"]}}
+
+- **request_review**: (flock segment workers only) Ask another segment to review your changes; returns its comments
+  - Format: {\"tool\": \"request_review\", \"args\": {\"segment\": \"module name or number\", \"note\": \"optional focus\"}}
+  - Example: {\"tool\": \"request_review\", \"args\": {\"segment\": \"api-server\", \"note\": \"I changed Engine::process\"}}
+
+- **get_diagnostics**: Compiler errors and warnings from a language server, without a full build. Call it on the files you changed after editing them.
+  - Format: {\"tool\": \"get_diagnostics\", \"args\": {\"paths\": [\"src/file.rs\"]}}
+  - Example: {\"tool\": \"get_diagnostics\", \"args\": {\"paths\": [\"src/parser.rs\", \"src/lexer.rs\"]}}
+
+- **hover**: Type, signature and docs of a symbol as the language server sees it
+  - Format: {\"tool\": \"hover\", \"args\": {\"file_path\": \"src/file.rs\", \"symbol\": \"name\", \"line\": 12}}
+  - Example: {\"tool\": \"hover\", \"args\": {\"file_path\": \"src/main.rs\", \"symbol\": \"config\"}}
+
 - **code_search**: Syntax-aware code search using tree-sitter. Supports Rust, Python, JavaScript, TypeScript.
   - Format: {\"tool\": \"code_search\", \"args\": {\"searches\": [{\"name\": \"label\", \"query\": \"tree-sitter query\", \"language\": \"rust|python|javascript|typescript\", \"paths\": [\"src/\"], \"context_lines\": 0}]}}
   - Find functions: {\"tool\": \"code_search\", \"args\": {\"searches\": [{\"name\": \"find_functions\", \"query\": \"(function_item name: (identifier) @name)\", \"language\": \"rust\", \"paths\": [\"src/\"]}]}}
@@ -401,6 +431,52 @@ If you can complete it with 1-2 tool calls, skip TODO.
 pub const SYSTEM_PROMPT_FOR_NON_NATIVE_TOOL_USE: &'static str =
     concatcp!(SYSTEM_NON_NATIVE_TOOL_USE, CODING_STYLE);
 
+/// Pinned after the system prompt in explain mode (`[agent] read_only`)
+pub const EXPLAIN_MODE_PROMPT: &str = "# EXPLAIN MODE (READ-ONLY)
+
+You are answering questions about this codebase, not changing it. Only the read and search \
+tools (read_file, read_image, code_search, git_context, get_diagnostics, hover and \
+todo_read) run in this session; write_file, str_replace, todo_write, shell, \
+background_process and the browser and desktop tools are refused. Explore the code as much as the question needs, then \
+explain it clearly, citing files and line numbers. If the user asks for a change, describe \
+what you would change and where instead of attempting it.";
+
+/// The prompts users can override in `~/.g3/prompts/<name>.md`
+pub const TEMPLATES: &[PromptTemplate] = &[
+    PromptTemplate {
+        name: "system",
+        description: "System prompt for providers with native tool calling",
+        text: SYSTEM_PROMPT_FOR_NATIVE_TOOL_USE,
+        placeholders: &[],
+    },
+    PromptTemplate {
+        name: "system_non_native",
+        description: "System prompt for providers that call tools in JSON text",
+        text: SYSTEM_PROMPT_FOR_NON_NATIVE_TOOL_USE,
+        placeholders: &[],
+    },
+    PromptTemplate {
+        name: "explain_mode",
+        description: "Added after the system prompt in explain mode (--explain)",
+        text: EXPLAIN_MODE_PROMPT,
+        placeholders: &[],
+    },
+];
+
+/// The system prompt a provider starts with, unless a custom one is given
+pub fn default_system_prompt(native: bool, allow_multiple_tool_calls: bool) -> Result<String> {
+    let registry = prompt_templates::core();
+    if !native {
+        return registry.text("system_non_native");
+    }
+    let prompt = registry.text("system")?;
+    Ok(if allow_multiple_tool_calls {
+        with_parallel_tool_calls(&prompt)
+    } else {
+        prompt
+    })
+}
+
 /// The G3 identity line that gets replaced in agent mode
 const G3_IDENTITY_LINE: &str = "You are G3, an AI programming agent of the same skill level as a seasoned engineer at a major technology company. You analyze given tasks and write code to achieve goals.";
 
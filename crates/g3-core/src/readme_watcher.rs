@@ -0,0 +1,92 @@
+//! Watches the workspace's README.md and AGENTS.md during a session.
+//!
+//! Both files are loaded into the second system message when the agent
+//! starts. Without a watcher, edits made mid-session (by the user or by the
+//! agent itself) only reach the model after a manual `/readme`. The agent
+//! checks [`ReadmeWatcher::take_changed`] before each task and reloads the
+//! message in place when either file changed.
+
+use anyhow::{Context, Result};
+use notify::{Event, RecommendedWatcher, RecursiveMode, Watcher};
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+/// Files whose content makes up the README system message
+pub const WATCHED_FILES: &[&str] = &["README.md", "AGENTS.md"];
+
+/// Flags changes to [`WATCHED_FILES`] in one directory
+pub struct ReadmeWatcher {
+    /// Kept alive for as long as the directory should be watched
+    _watcher: RecommendedWatcher,
+    changed: Arc<AtomicBool>,
+}
+
+impl ReadmeWatcher {
+    /// Start watching `dir` (not its subdirectories)
+    pub fn new(dir: &Path) -> Result<Self> {
+        let changed = Arc::new(AtomicBool::new(false));
+        let flag = changed.clone();
+        let mut watcher = notify::recommended_watcher(move |event: notify::Result<Event>| {
+            let Ok(event) = event else {
+                return;
+            };
+            if event.kind.is_access() {
+                return;
+            }
+            if event.paths.iter().any(|path| is_watched(path)) {
+                flag.store(true, Ordering::Relaxed);
+            }
+        })
+        .context("Failed to create README watcher")?;
+        watcher
+            .watch(dir, RecursiveMode::NonRecursive)
+            .with_context(|| format!("Failed to watch {}", dir.display()))?;
+        Ok(Self {
+            _watcher: watcher,
+            changed,
+        })
+    }
+
+    /// Whether a watched file changed since the last call
+    pub fn take_changed(&self) -> bool {
+        self.changed.swap(false, Ordering::Relaxed)
+    }
+}
+
+/// Whether `path` is one of the [`WATCHED_FILES`]
+pub fn is_watched(path: &Path) -> bool {
+    path.file_name()
+        .and_then(|name| name.to_str())
+        .is_some_and(|name| WATCHED_FILES.contains(&name))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::{Duration, Instant};
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_is_watched() {
+        assert!(is_watched(Path::new("/project/README.md")));
+        assert!(is_watched(Path::new("AGENTS.md")));
+        assert!(!is_watched(Path::new("/project/docs/guide.md")));
+        assert!(!is_watched(Path::new("/project/README.md.swp")));
+    }
+
+    #[test]
+    fn test_flags_readme_changes() {
+        let temp_dir = TempDir::new().unwrap();
+        let watcher = ReadmeWatcher::new(temp_dir.path()).unwrap();
+
+        std::fs::write(temp_dir.path().join("notes.txt"), "unrelated").unwrap();
+        std::fs::write(temp_dir.path().join("README.md"), "# Project").unwrap();
+
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while !watcher.take_changed() {
+            assert!(Instant::now() < deadline, "README change was not detected");
+            std::thread::sleep(Duration::from_millis(20));
+        }
+    }
+}
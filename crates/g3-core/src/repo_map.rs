@@ -0,0 +1,499 @@
+//! A compact map of a repository for the discovery phase.
+//!
+//! The map is the directory tree with each source file's top-level symbols
+//! (functions, types, traits, classes, impls...) extracted with tree-sitter,
+//! e.g.
+//!
+//! ```text
+//! src/
+//!   lib.rs: struct Agent, impl Agent, fn main
+//!   utils/
+//!     mod.rs: fn expand_tilde
+//! Cargo.toml
+//! ```
+//!
+//! It is rendered to fit a token budget: symbol lists are shortened first,
+//! then dropped, and only then are files left out.
+
+use std::collections::HashMap;
+use std::path::{Component, Path, PathBuf};
+use tree_sitter::{Language, Node, Parser};
+use walkdir::WalkDir;
+
+use crate::ContextWindow;
+
+/// Token budget for the map in the planner's discovery prompt
+pub const DEFAULT_TOKEN_BUDGET: usize = 8000;
+
+/// Directories that hold dependencies or build output rather than sources
+const SKIPPED_DIRS: &[&str] = &[
+    "target",
+    "node_modules",
+    "vendor",
+    "dist",
+    "build",
+    "__pycache__",
+    "venv",
+];
+
+/// Stop walking after this many files
+const MAX_FILES: usize = 10_000;
+
+/// Larger files are listed without parsing them
+const MAX_PARSE_BYTES: u64 = 512 * 1024;
+
+/// Symbols shown per file, tried in order until the map fits its budget
+const SYMBOL_CAPS: &[usize] = &[usize::MAX, 16, 8, 4, 2, 0];
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct FileEntry {
+    /// Relative to the repository root
+    path: PathBuf,
+    symbols: Vec<String>,
+}
+
+#[derive(Debug, Default)]
+pub struct RepoMap {
+    files: Vec<FileEntry>,
+}
+
+impl RepoMap {
+    /// Walk `root`, skipping hidden and dependency directories, and extract
+    /// the symbols of every file in a supported language
+    pub fn build(root: &Path) -> Self {
+        let mut parsers: HashMap<&'static str, Parser> = HashMap::new();
+        let mut files = Vec::new();
+
+        let walker = WalkDir::new(root)
+            .sort_by_file_name()
+            .into_iter()
+            .filter_entry(|entry| entry.depth() == 0 || !is_skipped(entry));
+        for entry in walker.filter_map(|e| e.ok()) {
+            if !entry.file_type().is_file() {
+                continue;
+            }
+            if files.len() >= MAX_FILES {
+                break;
+            }
+            let Ok(path) = entry.path().strip_prefix(root) else {
+                continue;
+            };
+
+            let parseable = entry
+                .metadata()
+                .is_ok_and(|metadata| metadata.len() <= MAX_PARSE_BYTES);
+            let symbols = match language_for(entry.path()) {
+                Some((name, language)) if parseable => std::fs::read_to_string(entry.path())
+                    .ok()
+                    .and_then(|source| {
+                        let parser = parsers.entry(name).or_default();
+                        parser.set_language(&language).ok()?;
+                        Some(file_symbols(parser, &source))
+                    })
+                    .unwrap_or_default(),
+                _ => Vec::new(),
+            };
+            files.push(FileEntry {
+                path: path.to_path_buf(),
+                symbols,
+            });
+        }
+
+        Self { files }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.files.is_empty()
+    }
+
+    pub fn file_count(&self) -> usize {
+        self.files.len()
+    }
+
+    /// The map as text of at most about `token_budget` tokens
+    pub fn render(&self, token_budget: usize) -> String {
+        let fits = |text: &str| ContextWindow::estimate_tokens(text) as usize <= token_budget;
+        for &cap in SYMBOL_CAPS {
+            let text = render_files(&self.files, cap);
+            if fits(&text) {
+                return text;
+            }
+        }
+
+        // Even bare file names don't fit: keep as many as the budget allows
+        let mut shown = self.files.len();
+        while shown > 0 {
+            shown = shown * 3 / 4;
+            let mut text = render_files(&self.files[..shown], 0);
+            text.push_str(&format!(
+                "... {} more files not shown\n",
+                self.files.len() - shown
+            ));
+            if fits(&text) {
+                return text;
+            }
+        }
+        format!("({} files, too many to list)\n", self.files.len())
+    }
+}
+
+fn is_skipped(entry: &walkdir::DirEntry) -> bool {
+    let name = entry.file_name().to_string_lossy();
+    name.starts_with('.') || (entry.file_type().is_dir() && SKIPPED_DIRS.contains(&name.as_ref()))
+}
+
+/// Tree of `files` (sorted by path) with up to `cap` symbols per file
+fn render_files(files: &[FileEntry], cap: usize) -> String {
+    let mut text = String::new();
+    let mut open_dirs: Vec<&std::ffi::OsStr> = Vec::new();
+    for file in files {
+        let components: Vec<&std::ffi::OsStr> = file
+            .path
+            .components()
+            .filter_map(|component| match component {
+                Component::Normal(part) => Some(part),
+                _ => None,
+            })
+            .collect();
+        let Some((name, dirs)) = components.split_last() else {
+            continue;
+        };
+
+        let common = open_dirs
+            .iter()
+            .zip(dirs)
+            .take_while(|(open, dir)| open == dir)
+            .count();
+        open_dirs.truncate(common);
+        for dir in &dirs[common..] {
+            text.push_str(&format!(
+                "{}{}/\n",
+                "  ".repeat(open_dirs.len()),
+                dir.to_string_lossy()
+            ));
+            open_dirs.push(*dir);
+        }
+
+        text.push_str(&"  ".repeat(open_dirs.len()));
+        text.push_str(&name.to_string_lossy());
+        if cap > 0 && !file.symbols.is_empty() {
+            let shown = file.symbols.len().min(cap);
+            text.push_str(": ");
+            text.push_str(&file.symbols[..shown].join(", "));
+            if shown < file.symbols.len() {
+                text.push_str(&format!(", +{} more", file.symbols.len() - shown));
+            }
+        }
+        text.push('\n');
+    }
+    text
+}
+
+/// Grammar for a file, by extension; the name keys the parser cache
+pub(crate) fn language_for(path: &Path) -> Option<(&'static str, Language)> {
+    let extension = path.extension()?.to_str()?;
+    Some(match extension {
+        "rs" => ("rust", tree_sitter_rust::LANGUAGE.into()),
+        "py" => ("python", tree_sitter_python::LANGUAGE.into()),
+        "js" | "jsx" | "mjs" | "cjs" => ("javascript", tree_sitter_javascript::LANGUAGE.into()),
+        "ts" | "mts" | "cts" => (
+            "typescript",
+            tree_sitter_typescript::LANGUAGE_TYPESCRIPT.into(),
+        ),
+        "tsx" => ("tsx", tree_sitter_typescript::LANGUAGE_TSX.into()),
+        "go" => ("go", tree_sitter_go::LANGUAGE.into()),
+        "java" => ("java", tree_sitter_java::LANGUAGE.into()),
+        "c" | "h" => ("c", tree_sitter_c::LANGUAGE.into()),
+        "cpp" | "cc" | "cxx" | "hpp" | "hh" | "hxx" => ("cpp", tree_sitter_cpp::LANGUAGE.into()),
+        _ => return None,
+    })
+}
+
+/// Top-level symbols of `source`, in order, as `<kind> <name>`
+fn file_symbols(parser: &mut Parser, source: &str) -> Vec<String> {
+    let Some(tree) = parser.parse(source, None) else {
+        return Vec::new();
+    };
+    let mut symbols = Vec::new();
+    collect_symbols(tree.root_node(), source.as_bytes(), &mut symbols);
+    symbols
+}
+
+fn collect_symbols(parent: Node, source: &[u8], symbols: &mut Vec<String>) {
+    let mut cursor = parent.walk();
+    for node in parent.named_children(&mut cursor) {
+        symbol(node, source, symbols);
+    }
+}
+
+fn symbol(node: Node, source: &[u8], symbols: &mut Vec<String>) {
+    let text = |node: Node| node.utf8_text(source).unwrap_or_default().to_string();
+    let field = |name: &str| node.child_by_field_name(name).map(text);
+    let mut push = |kind: &str, name: Option<String>| {
+        if let Some(name) = name.filter(|name| !name.is_empty()) {
+            symbols.push(format!("{} {}", kind, name));
+        }
+    };
+
+    match node.kind() {
+        // Rust
+        "function_item" | "function_signature_item" => push("fn", field("name")),
+        "struct_item" => push("struct", field("name")),
+        "enum_item" => push("enum", field("name")),
+        "union_item" => push("union", field("name")),
+        "trait_item" => push("trait", field("name")),
+        "type_item" => push("type", field("name")),
+        "const_item" => push("const", field("name")),
+        "static_item" => push("static", field("name")),
+        "mod_item" => push("mod", field("name")),
+        "macro_definition" => push("macro", field("name")),
+        "impl_item" => {
+            let target = match (field("trait"), field("type")) {
+                (Some(trait_name), Some(type_name)) => {
+                    Some(format!("{} for {}", trait_name, type_name))
+                }
+                (None, type_name) => type_name,
+                (Some(_), None) => None,
+            };
+            push("impl", target)
+        }
+
+        // Python, C and C++ share `function_definition`; only Python's has a name
+        "function_definition" => match field("name") {
+            Some(name) => push("fn", Some(name)),
+            None => push("fn", declarator_name(node, source)),
+        },
+        "class_definition" => push("class", field("name")),
+        "decorated_definition" => {
+            if let Some(definition) = node.child_by_field_name("definition") {
+                symbol(definition, source, symbols);
+            }
+        }
+
+        // JavaScript, TypeScript, Go and Java
+        "function_declaration" | "generator_function_declaration" | "function_signature" => {
+            push("fn", field("name"))
+        }
+        "class_declaration" | "abstract_class_declaration" => push("class", field("name")),
+        "interface_declaration" => push("interface", field("name")),
+        "type_alias_declaration" => push("type", field("name")),
+        "enum_declaration" => push("enum", field("name")),
+        "record_declaration" => push("record", field("name")),
+        "internal_module" | "module" => push("namespace", field("name")),
+        "export_statement" => {
+            if let Some(declaration) = node.child_by_field_name("declaration") {
+                symbol(declaration, source, symbols);
+            }
+        }
+        "ambient_declaration" => collect_symbols(node, source, symbols),
+        "lexical_declaration" | "variable_declaration" => {
+            let mut cursor = node.walk();
+            for declarator in node.named_children(&mut cursor) {
+                if declarator.kind() == "variable_declarator" {
+                    push("const", declarator.child_by_field_name("name").map(text));
+                }
+            }
+        }
+        "method_declaration" if node.child_by_field_name("receiver").is_some() => {
+            // Go: `func (s *Server) Start()` -> `fn Server.Start`
+            let receiver = node
+                .child_by_field_name("receiver")
+                .and_then(|receiver| receiver.named_child(0))
+                .and_then(|parameter| parameter.child_by_field_name("type"))
+                .map(|receiver_type| text(receiver_type).trim_start_matches('*').to_string());
+            let name = match (receiver, field("name")) {
+                (Some(receiver), Some(name)) => Some(format!("{}.{}", receiver, name)),
+                (None, name) => name,
+                (Some(_), None) => None,
+            };
+            push("fn", name)
+        }
+        "type_declaration" => {
+            let mut cursor = node.walk();
+            for spec in node.named_children(&mut cursor) {
+                let kind = match spec.child_by_field_name("type").map(|t| t.kind()) {
+                    Some("struct_type") => "struct",
+                    Some("interface_type") => "interface",
+                    _ => "type",
+                };
+                push(kind, spec.child_by_field_name("name").map(text));
+            }
+        }
+
+        // C and C++
+        "struct_specifier" | "union_specifier" | "enum_specifier" | "class_specifier"
+            if node.child_by_field_name("body").is_some() =>
+        {
+            let kind = node.kind().trim_end_matches("_specifier");
+            push(kind, field("name"))
+        }
+        "declaration" => match node.child_by_field_name("type") {
+            Some(specifier) if specifier.child_by_field_name("body").is_some() => {
+                symbol(specifier, source, symbols)
+            }
+            _ => {
+                let is_function = node
+                    .child_by_field_name("declarator")
+                    .is_some_and(|declarator| declarator.kind() == "function_declarator");
+                if is_function {
+                    push("fn", declarator_name(node, source));
+                }
+            }
+        },
+        "type_definition" => push("type", declarator_name(node, source)),
+        "namespace_definition" => {
+            push("namespace", field("name"));
+            if let Some(body) = node.child_by_field_name("body") {
+                collect_symbols(body, source, symbols);
+            }
+        }
+        "linkage_specification" => {
+            if let Some(body) = node.child_by_field_name("body") {
+                match body.kind() {
+                    "declaration_list" => collect_symbols(body, source, symbols),
+                    _ => symbol(body, source, symbols),
+                }
+            }
+        }
+        "template_declaration" => collect_symbols(node, source, symbols),
+
+        _ => {}
+    }
+}
+
+/// Name at the bottom of a C/C++ declarator chain, e.g. `main` in
+/// `int *main(void)`
+fn declarator_name(node: Node, source: &[u8]) -> Option<String> {
+    let mut current = node.child_by_field_name("declarator")?;
+    loop {
+        match current.kind() {
+            "identifier"
+            | "field_identifier"
+            | "type_identifier"
+            | "qualified_identifier"
+            | "destructor_name"
+            | "operator_name" => return current.utf8_text(source).ok().map(str::to_string),
+            _ => current = current.child_by_field_name("declarator")?,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn symbols_of(file_name: &str, source: &str) -> Vec<String> {
+        let (_, language) = language_for(Path::new(file_name)).unwrap();
+        let mut parser = Parser::new();
+        parser.set_language(&language).unwrap();
+        file_symbols(&mut parser, source)
+    }
+
+    #[test]
+    fn test_rust_symbols() {
+        let source = r#"
+use std::fmt;
+
+pub struct Agent { name: String }
+enum Mode { Fast, Slow }
+pub trait Tool { fn run(&self); }
+impl Agent { fn new() -> Self { todo!() } }
+impl fmt::Display for Agent {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result { Ok(()) }
+}
+const LIMIT: usize = 3;
+mod tests {}
+pub fn main() {}
+"#;
+        assert_eq!(
+            symbols_of("lib.rs", source),
+            vec![
+                "struct Agent",
+                "enum Mode",
+                "trait Tool",
+                "impl Agent",
+                "impl fmt::Display for Agent",
+                "const LIMIT",
+                "mod tests",
+                "fn main",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_symbols_in_other_languages() {
+        let python = "import os\n\n@cache\ndef load():\n    pass\n\nclass Store:\n    def get(self):\n        pass\n";
+        assert_eq!(
+            symbols_of("store.py", python),
+            vec!["fn load", "class Store"]
+        );
+
+        let typescript = "export interface Props { a: number }\nexport function render() {}\nconst count = 1;\ntype Id = string;\n";
+        assert_eq!(
+            symbols_of("app.ts", typescript),
+            vec!["interface Props", "fn render", "const count", "type Id"]
+        );
+
+        let go = "package main\n\ntype Server struct{}\n\nfunc (s *Server) Start() {}\n\nfunc main() {}\n";
+        assert_eq!(
+            symbols_of("main.go", go),
+            vec!["struct Server", "fn Server.Start", "fn main"]
+        );
+
+        let c = "#include <stdio.h>\n\nstruct point { int x; };\ntypedef int id_t;\nint add(int a, int b);\nstatic int *origin(void) { return 0; }\n";
+        assert_eq!(
+            symbols_of("point.c", c),
+            vec!["struct point", "type id_t", "fn add", "fn origin"]
+        );
+    }
+
+    fn write(root: &Path, path: &str, content: &str) {
+        let path = root.join(path);
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(path, content).unwrap();
+    }
+
+    #[test]
+    fn test_map_skips_hidden_and_build_directories() {
+        let temp_dir = TempDir::new().unwrap();
+        let root = temp_dir.path();
+        write(root, "Cargo.toml", "[package]");
+        write(root, "src/lib.rs", "pub fn run() {}\npub struct Config;");
+        write(root, "src/cli/mod.rs", "fn parse() {}");
+        write(root, "target/debug/build.rs", "fn generated() {}");
+        write(root, ".git/HEAD", "ref: refs/heads/main");
+
+        let map = RepoMap::build(root);
+        assert_eq!(map.file_count(), 3);
+        assert_eq!(
+            map.render(DEFAULT_TOKEN_BUDGET),
+            "Cargo.toml\nsrc/\n  cli/\n    mod.rs: fn parse\n  lib.rs: fn run, struct Config\n"
+        );
+    }
+
+    #[test]
+    fn test_render_shrinks_to_budget() {
+        let files: Vec<FileEntry> = (0..40)
+            .map(|i| FileEntry {
+                path: PathBuf::from(format!("src/module_{:02}.rs", i)),
+                symbols: (0..10).map(|j| format!("fn function_{}", j)).collect(),
+            })
+            .collect();
+        let map = RepoMap { files };
+
+        let full = map.render(100_000);
+        assert!(full.contains("fn function_9"));
+
+        // Symbol lists are cut before any file is left out
+        let shortened = map.render(1500);
+        assert!(ContextWindow::estimate_tokens(&shortened) <= 1500);
+        assert!(shortened.contains("module_39.rs"));
+        assert!(shortened.contains("more"));
+        assert!(!shortened.contains("fn function_9"));
+
+        let truncated = map.render(100);
+        assert!(ContextWindow::estimate_tokens(&truncated) <= 100);
+        assert!(truncated.contains("more files not shown"));
+    }
+}
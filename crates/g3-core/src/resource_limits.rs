@@ -0,0 +1,511 @@
+//! CPU and memory limits for background processes.
+//!
+//! Limits are enforced with the best mechanism the platform offers:
+//! - Linux: a cgroup v2 child of the agent's own cgroup, if that cgroup is
+//!   delegated to the agent (e.g. `systemd-run --user --scope -p Delegate=yes g3`).
+//!   The agent first moves itself into a `g3-agent` leaf, since cgroup v2 only
+//!   enables controllers for children of a cgroup without processes of its own.
+//!   The child joins its cgroup before `exec`, so nothing it starts escapes.
+//! - Windows: a job object
+//! - Other Unix systems, or Linux without a usable cgroup: `setrlimit`, which
+//!   caps the data segment (heap) only; CPU limits are not enforced
+//!
+//! [`crate::background_process::BackgroundProcessManager::stats`] reports the
+//! current memory and CPU use of a process and everything it spawned.
+
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use std::process::{Child, Command};
+use std::time::Duration;
+
+/// Requested limits for a background process
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+pub struct ResourceLimits {
+    /// Maximum memory in bytes
+    pub memory_bytes: Option<u64>,
+    /// Maximum CPU use in cores (e.g. 0.5 for half a core)
+    pub cpu_cores: Option<f64>,
+}
+
+impl std::fmt::Display for ResourceLimits {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let mut parts = Vec::new();
+        if let Some(bytes) = self.memory_bytes {
+            parts.push(format!("memory {} MiB", bytes / (1024 * 1024)));
+        }
+        if let Some(cores) = self.cpu_cores {
+            parts.push(format!("cpu {} core(s)", cores));
+        }
+        write!(f, "{}", parts.join(", "))
+    }
+}
+
+/// How the limits of a process are enforced
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum LimitBackend {
+    /// A cgroup v2 directory, joined by the child before `exec`
+    CgroupV2(PathBuf),
+    /// A Windows job object
+    JobObject,
+    /// `setrlimit` in the child (memory only)
+    Rlimit,
+}
+
+impl LimitBackend {
+    /// Short description for logs, noting limits that could not be applied
+    pub fn describe(&self, limits: &ResourceLimits) -> String {
+        match self {
+            LimitBackend::CgroupV2(dir) => format!("cgroup v2 ({})", dir.display()),
+            LimitBackend::JobObject => "job object".to_string(),
+            LimitBackend::Rlimit if limits.cpu_cores.is_some() => {
+                "rlimit (CPU limit not enforced)".to_string()
+            }
+            LimitBackend::Rlimit => "rlimit".to_string(),
+        }
+    }
+}
+
+/// Current resource use of a background process and its descendants
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ProcessStats {
+    /// Resident memory in bytes
+    pub rss_bytes: u64,
+    /// Total CPU time used
+    pub cpu_time: Duration,
+    /// CPU use in percent of one core, since the previous sample (or since start)
+    pub cpu_percent: f64,
+}
+
+/// Set up `cmd` so the spawned process gets `limits`. Call [`attach`] with the
+/// result once the process is spawned.
+pub(crate) fn prepare(cmd: &mut Command, limits: &ResourceLimits) -> LimitBackend {
+    #[cfg(target_os = "linux")]
+    match cgroup::create(limits).and_then(|dir| cgroup::join_on_spawn(cmd, &dir).map(|_| dir)) {
+        Ok(dir) => return LimitBackend::CgroupV2(dir),
+        Err(e) => tracing::debug!("cgroup v2 limits unavailable, using rlimit: {}", e),
+    }
+
+    #[cfg(unix)]
+    {
+        set_rlimits(cmd, limits);
+        LimitBackend::Rlimit
+    }
+    #[cfg(windows)]
+    {
+        let _ = (cmd, limits);
+        LimitBackend::JobObject
+    }
+}
+
+/// Place the freshly spawned `child` under the limits prepared by [`prepare`],
+/// for backends that can't do so before it runs (job objects)
+pub(crate) fn attach(
+    backend: &LimitBackend,
+    child: &Child,
+    limits: &ResourceLimits,
+) -> Result<(), String> {
+    match backend {
+        #[cfg(windows)]
+        LimitBackend::JobObject => job_object::assign(child, limits),
+        _ => {
+            let _ = (child, limits);
+            Ok(())
+        }
+    }
+}
+
+/// Remove what [`prepare`] created, once the process has exited
+pub(crate) fn release(backend: &LimitBackend) {
+    if let LimitBackend::CgroupV2(dir) = backend {
+        // Fails while members are still exiting; retry briefly
+        for _ in 0..10 {
+            if std::fs::remove_dir(dir).is_ok() || !dir.exists() {
+                return;
+            }
+            std::thread::sleep(Duration::from_millis(20));
+        }
+        tracing::debug!("Failed to remove cgroup {}", dir.display());
+    }
+}
+
+/// Memory and total CPU time of the process group led by `pid`
+pub(crate) fn usage(pid: u32, backend: Option<&LimitBackend>) -> Result<(u64, Duration), String> {
+    #[cfg(target_os = "linux")]
+    if let Some(LimitBackend::CgroupV2(dir)) = backend {
+        return cgroup::usage(dir);
+    }
+    #[cfg(not(target_os = "linux"))]
+    let _ = backend;
+    group_usage(pid)
+}
+
+#[cfg(unix)]
+fn set_rlimits(cmd: &mut Command, limits: &ResourceLimits) {
+    use std::os::unix::process::CommandExt;
+
+    let Some(bytes) = limits.memory_bytes else {
+        return;
+    };
+    let limit = libc::rlimit {
+        rlim_cur: bytes as libc::rlim_t,
+        rlim_max: bytes as libc::rlim_t,
+    };
+    // RLIMIT_AS would also count reserved but unused address space, which
+    // runtimes like the JVM, Go and V8 map generously up front
+    // SAFETY: setrlimit is async-signal-safe and the closure doesn't allocate
+    unsafe {
+        cmd.pre_exec(move || {
+            if libc::setrlimit(libc::RLIMIT_DATA, &limit) != 0 {
+                return Err(std::io::Error::last_os_error());
+            }
+            Ok(())
+        });
+    }
+}
+
+/// Sum memory and CPU over the members of process group `pid` (Linux)
+#[cfg(target_os = "linux")]
+fn group_usage(pid: u32) -> Result<(u64, Duration), String> {
+    // SAFETY: sysconf has no preconditions
+    let (ticks, page_size) = unsafe {
+        (
+            libc::sysconf(libc::_SC_CLK_TCK).max(1) as u64,
+            libc::sysconf(libc::_SC_PAGESIZE).max(1) as u64,
+        )
+    };
+    let entries = std::fs::read_dir("/proc").map_err(|e| format!("Failed to read /proc: {}", e))?;
+
+    let (mut rss, mut cpu_ticks, mut found) = (0, 0, false);
+    for entry in entries.flatten() {
+        if !entry
+            .file_name()
+            .to_string_lossy()
+            .bytes()
+            .all(|b| b.is_ascii_digit())
+        {
+            continue;
+        }
+        let Ok(stat) = std::fs::read_to_string(entry.path().join("stat")) else {
+            continue;
+        };
+        let Some(end) = stat.rfind(')') else {
+            continue;
+        };
+        // Fields after the command name start at field 3 (state): pgrp is
+        // field 5, utime 14, stime 15 and rss 24
+        let fields: Vec<&str> = stat[end + 1..].split_whitespace().collect();
+        let field = |n: usize| fields.get(n - 3).and_then(|v| v.parse::<u64>().ok());
+        if field(5) != Some(pid as u64) {
+            continue;
+        }
+        found = true;
+        cpu_ticks += field(14).unwrap_or(0) + field(15).unwrap_or(0);
+        rss += field(24).unwrap_or(0) * page_size;
+    }
+
+    if !found {
+        return Err(format!("No running processes in group {}", pid));
+    }
+    Ok((
+        rss,
+        Duration::from_secs_f64(cpu_ticks as f64 / ticks as f64),
+    ))
+}
+
+/// Sum memory and CPU over the members of process group `pid` using `ps`
+#[cfg(all(unix, not(target_os = "linux")))]
+fn group_usage(pid: u32) -> Result<(u64, Duration), String> {
+    let output = Command::new("ps")
+        .args(["-A", "-o", "pgid=,rss=,time="])
+        .output()
+        .map_err(|e| format!("Failed to run ps: {}", e))?;
+
+    let (mut rss, mut cpu, mut found) = (0, Duration::ZERO, false);
+    for line in String::from_utf8_lossy(&output.stdout).lines() {
+        let fields: Vec<&str> = line.split_whitespace().collect();
+        let [pgid, rss_kib, time] = fields.as_slice() else {
+            continue;
+        };
+        if pgid.parse::<u32>().ok() != Some(pid) {
+            continue;
+        }
+        found = true;
+        rss += rss_kib.parse::<u64>().unwrap_or(0) * 1024;
+        cpu += parse_ps_time(time).unwrap_or_default();
+    }
+
+    if !found {
+        return Err(format!("No running processes in group {}", pid));
+    }
+    Ok((rss, cpu))
+}
+
+#[cfg(windows)]
+fn group_usage(_pid: u32) -> Result<(u64, Duration), String> {
+    Err("Process stats are not supported on this platform".to_string())
+}
+
+/// Parse a `ps` TIME column: `[[dd-]hh:]mm:ss[.ff]`
+#[cfg_attr(target_os = "linux", allow(dead_code))]
+fn parse_ps_time(time: &str) -> Option<Duration> {
+    let (days, rest) = match time.split_once('-') {
+        Some((days, rest)) => (days.parse::<f64>().ok()?, rest),
+        None => (0.0, time),
+    };
+    let mut secs = 0.0;
+    for part in rest.split(':') {
+        secs = secs * 60.0 + part.parse::<f64>().ok()?;
+    }
+    Some(Duration::from_secs_f64(days * 86400.0 + secs))
+}
+
+#[cfg(target_os = "linux")]
+mod cgroup {
+    use super::ResourceLimits;
+    use std::fs;
+    use std::io::{Error, ErrorKind};
+    use std::os::unix::io::AsRawFd;
+    use std::os::unix::process::CommandExt;
+    use std::path::{Path, PathBuf};
+    use std::process::Command;
+    use std::sync::atomic::{AtomicU64, Ordering};
+    use std::sync::OnceLock;
+    use std::time::Duration;
+
+    const CGROUP_ROOT: &str = "/sys/fs/cgroup";
+
+    /// Leaf the agent moves itself into so its own cgroup can hand out controllers
+    const AGENT_LEAF: &str = "g3-agent";
+
+    /// CFS period for `cpu.max`, in microseconds
+    const CPU_PERIOD_US: u64 = 100_000;
+
+    static NEXT_CGROUP: AtomicU64 = AtomicU64::new(0);
+
+    /// The agent's own cgroup on a unified (v2) hierarchy
+    fn own_cgroup() -> std::io::Result<PathBuf> {
+        let root = Path::new(CGROUP_ROOT);
+        if !root.join("cgroup.controllers").exists() {
+            return Err(Error::new(ErrorKind::Unsupported, "no cgroup v2 hierarchy"));
+        }
+        let membership = fs::read_to_string("/proc/self/cgroup")?;
+        let path = membership
+            .lines()
+            .find_map(|line| line.strip_prefix("0::"))
+            .ok_or_else(|| Error::new(ErrorKind::NotFound, "not in a cgroup v2 group"))?;
+        Ok(root.join(path.trim_start_matches('/')))
+    }
+
+    /// The agent's cgroup with the `memory` and `cpu` controllers enabled for
+    /// its children, set up on first use
+    fn delegated_parent() -> std::io::Result<PathBuf> {
+        static PARENT: OnceLock<Result<PathBuf, String>> = OnceLock::new();
+        PARENT
+            .get_or_init(|| delegate().map_err(|e| e.to_string()))
+            .clone()
+            .map_err(Error::other)
+    }
+
+    fn delegate() -> std::io::Result<PathBuf> {
+        let parent = own_cgroup()?;
+        let available = fs::read_to_string(parent.join("cgroup.controllers"))?;
+        let enabled = fs::read_to_string(parent.join("cgroup.subtree_control"))?;
+        let missing: Vec<&str> = ["memory", "cpu"]
+            .into_iter()
+            .filter(|controller| !enabled.split_whitespace().any(|c| c == *controller))
+            .collect();
+        if missing.is_empty() {
+            return Ok(parent);
+        }
+        if let Some(controller) = missing
+            .iter()
+            .find(|controller| !available.split_whitespace().any(|c| c == **controller))
+        {
+            return Err(Error::new(
+                ErrorKind::Unsupported,
+                format!("the {} controller is not delegated", controller),
+            ));
+        }
+
+        // Moving between cgroups needs write access to their common ancestor,
+        // so this fails unless the agent's cgroup is delegated to it
+        let leaf = parent.join(AGENT_LEAF);
+        match fs::create_dir(&leaf) {
+            Err(e) if e.kind() != ErrorKind::AlreadyExists => return Err(e),
+            _ => {}
+        }
+        fs::write(leaf.join("cgroup.procs"), std::process::id().to_string())?;
+        let controllers: Vec<String> = missing.iter().map(|c| format!("+{}", c)).collect();
+        fs::write(parent.join("cgroup.subtree_control"), controllers.join(" "))?;
+        Ok(parent)
+    }
+
+    /// Create a child cgroup with `limits` applied
+    pub(super) fn create(limits: &ResourceLimits) -> std::io::Result<PathBuf> {
+        let parent = delegated_parent()?;
+
+        let dir = parent.join(format!(
+            "g3-bg-{}-{}",
+            std::process::id(),
+            NEXT_CGROUP.fetch_add(1, Ordering::Relaxed)
+        ));
+        fs::create_dir(&dir)?;
+        let result = (|| {
+            if let Some(bytes) = limits.memory_bytes {
+                fs::write(dir.join("memory.max"), bytes.to_string())?;
+            }
+            if let Some(cores) = limits.cpu_cores {
+                let quota = ((cores * CPU_PERIOD_US as f64) as u64).max(1000);
+                fs::write(dir.join("cpu.max"), format!("{} {}", quota, CPU_PERIOD_US))?;
+            }
+            Ok(())
+        })();
+        if let Err(e) = result {
+            let _ = fs::remove_dir(&dir);
+            return Err(e);
+        }
+        Ok(dir)
+    }
+
+    /// Make the process `cmd` spawns join `dir` before it `exec`s
+    pub(super) fn join_on_spawn(cmd: &mut Command, dir: &Path) -> std::io::Result<()> {
+        // Opened here, since the child may not allocate; the descriptor is
+        // close-on-exec
+        let procs = fs::OpenOptions::new()
+            .write(true)
+            .open(dir.join("cgroup.procs"))
+            .inspect_err(|_| {
+                let _ = fs::remove_dir(dir);
+            })?;
+        // SAFETY: write is async-signal-safe and the closure doesn't allocate;
+        // "0" stands for the writing process
+        unsafe {
+            cmd.pre_exec(move || {
+                if libc::write(procs.as_raw_fd(), b"0".as_ptr().cast(), 1) != 1 {
+                    return Err(Error::last_os_error());
+                }
+                Ok(())
+            });
+        }
+        Ok(())
+    }
+
+    /// Memory and CPU time of everything in the cgroup
+    pub(super) fn usage(dir: &Path) -> Result<(u64, Duration), String> {
+        let read = |file: &str| {
+            fs::read_to_string(dir.join(file))
+                .map_err(|e| format!("Failed to read {}: {}", dir.join(file).display(), e))
+        };
+        let memory = read("memory.current")?
+            .trim()
+            .parse::<u64>()
+            .map_err(|e| format!("Invalid memory.current: {}", e))?;
+        let usage_usec = read("cpu.stat")?
+            .lines()
+            .find_map(|line| line.strip_prefix("usage_usec "))
+            .and_then(|v| v.trim().parse::<u64>().ok())
+            .unwrap_or(0);
+        Ok((memory, Duration::from_micros(usage_usec)))
+    }
+}
+
+#[cfg(windows)]
+mod job_object {
+    use super::ResourceLimits;
+    use std::ffi::c_void;
+    use std::os::windows::io::AsRawHandle;
+    use std::process::Child;
+    use windows_sys::Win32::Foundation::CloseHandle;
+    use windows_sys::Win32::System::JobObjects::{
+        AssignProcessToJobObject, CreateJobObjectW, JobObjectCpuRateControlInformation,
+        JobObjectExtendedLimitInformation, SetInformationJobObject,
+        JOBOBJECT_CPU_RATE_CONTROL_INFORMATION, JOBOBJECT_EXTENDED_LIMIT_INFORMATION,
+        JOB_OBJECT_CPU_RATE_CONTROL_ENABLE, JOB_OBJECT_CPU_RATE_CONTROL_HARD_CAP,
+        JOB_OBJECT_LIMIT_JOB_MEMORY,
+    };
+
+    /// Put `child` into a new job object with `limits`
+    pub(super) fn assign(child: &Child, limits: &ResourceLimits) -> Result<(), String> {
+        let error = |what: &str| format!("{}: {}", what, std::io::Error::last_os_error());
+        // SAFETY: the structs are plain data, zeroed is a valid initial state,
+        // and every pointer passed stays alive for the duration of the call
+        unsafe {
+            let job = CreateJobObjectW(std::ptr::null(), std::ptr::null());
+            if job.is_null() {
+                return Err(error("Failed to create job object"));
+            }
+            let result = (|| {
+                if let Some(bytes) = limits.memory_bytes {
+                    let mut info: JOBOBJECT_EXTENDED_LIMIT_INFORMATION = std::mem::zeroed();
+                    info.BasicLimitInformation.LimitFlags = JOB_OBJECT_LIMIT_JOB_MEMORY;
+                    info.JobMemoryLimit = bytes as usize;
+                    if SetInformationJobObject(
+                        job,
+                        JobObjectExtendedLimitInformation,
+                        &info as *const _ as *const c_void,
+                        std::mem::size_of_val(&info) as u32,
+                    ) == 0
+                    {
+                        return Err(error("Failed to set memory limit"));
+                    }
+                }
+                if let Some(cores) = limits.cpu_cores {
+                    // CpuRate is in hundredths of a percent of all processors
+                    let cpus = std::thread::available_parallelism()
+                        .map(|n| n.get())
+                        .unwrap_or(1) as f64;
+                    let mut info: JOBOBJECT_CPU_RATE_CONTROL_INFORMATION = std::mem::zeroed();
+                    info.ControlFlags =
+                        JOB_OBJECT_CPU_RATE_CONTROL_ENABLE | JOB_OBJECT_CPU_RATE_CONTROL_HARD_CAP;
+                    info.Anonymous.CpuRate = (cores / cpus * 10_000.0).clamp(1.0, 10_000.0) as u32;
+                    if SetInformationJobObject(
+                        job,
+                        JobObjectCpuRateControlInformation,
+                        &info as *const _ as *const c_void,
+                        std::mem::size_of_val(&info) as u32,
+                    ) == 0
+                    {
+                        return Err(error("Failed to set CPU limit"));
+                    }
+                }
+                if AssignProcessToJobObject(job, child.as_raw_handle() as _) == 0 {
+                    return Err(error("Failed to assign process to job object"));
+                }
+                Ok(())
+            })();
+            // The job lives on as long as it has processes
+            CloseHandle(job);
+            result
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_ps_time() {
+        assert_eq!(parse_ps_time("00:05"), Some(Duration::from_secs(5)));
+        assert_eq!(parse_ps_time("1:02:03"), Some(Duration::from_secs(3723)));
+        assert_eq!(
+            parse_ps_time("2-00:00:01"),
+            Some(Duration::from_secs(2 * 86400 + 1))
+        );
+        assert_eq!(parse_ps_time("0:01.50"), Some(Duration::from_millis(1500)));
+        assert_eq!(parse_ps_time("abc"), None);
+    }
+
+    #[test]
+    fn test_limits_display() {
+        let limits = ResourceLimits {
+            memory_bytes: Some(512 * 1024 * 1024),
+            cpu_cores: Some(0.5),
+        };
+        assert_eq!(limits.to_string(), "memory 512 MiB, cpu 0.5 core(s)");
+        assert_eq!(
+            LimitBackend::Rlimit.describe(&limits),
+            "rlimit (CPU limit not enforced)"
+        );
+    }
+}
@@ -0,0 +1,453 @@
+//! Retention policies: what to drop once the context window overflows.
+//!
+//! A [`RetentionPolicy`] picks the messages to keep when the conversation no
+//! longer fits its token budget. Whatever the policy, some messages are never
+//! dropped: pinned messages (the system prompt, README and current
+//! requirements), other system messages (summaries and compaction notes), and
+//! the current turn, from the latest user message on.
+//!
+//! Each mode can use its own policy, set with `[agent.retention]` in the
+//! config or [`crate::Agent::set_retention_policy`].
+
+use anyhow::{bail, Result};
+use g3_config::RetentionConfig;
+use g3_providers::{Message, MessageRole};
+use std::collections::HashSet;
+use std::fmt::Debug;
+use std::ops::Range;
+use std::sync::Arc;
+
+use crate::{compaction, ContextWindow};
+
+/// Names accepted in `[agent.retention]`
+pub const POLICY_NAMES: &[&str] = &[
+    "keep-pinned",
+    "sliding-window",
+    "importance",
+    "summarize-on-overflow",
+];
+
+/// Decides which messages survive when the history is over budget
+pub trait RetentionPolicy: Debug + Send + Sync {
+    fn name(&self) -> &'static str;
+
+    /// Return the messages to keep, in their original order, fitting
+    /// `budget_tokens` if possible. Pinned messages must be kept.
+    fn retain(
+        &self,
+        history: &[Message],
+        pinned: &HashSet<String>,
+        budget_tokens: u32,
+    ) -> Vec<Message>;
+}
+
+/// Build the policy the config selects, `None` when no policy is set
+pub fn policy_from_config(config: &RetentionConfig) -> Result<Option<Arc<dyn RetentionPolicy>>> {
+    let Some(name) = config.policy.as_deref() else {
+        return Ok(None);
+    };
+    let policy: Arc<dyn RetentionPolicy> = match name {
+        "keep-pinned" => Arc::new(KeepPinned),
+        "sliding-window" => Arc::new(SlidingWindow {
+            max_messages: config.window_messages,
+        }),
+        "importance" => Arc::new(ImportanceScored),
+        "summarize-on-overflow" => Arc::new(SummarizeOnOverflow),
+        other => bail!(
+            "Unknown retention policy '{}'. Supported: {}",
+            other,
+            POLICY_NAMES.join(", ")
+        ),
+    };
+    Ok(Some(policy))
+}
+
+/// Drops the oldest unpinned messages first
+#[derive(Debug)]
+pub struct KeepPinned;
+
+impl RetentionPolicy for KeepPinned {
+    fn name(&self) -> &'static str {
+        "keep-pinned"
+    }
+
+    fn retain(
+        &self,
+        history: &[Message],
+        pinned: &HashSet<String>,
+        budget_tokens: u32,
+    ) -> Vec<Message> {
+        let mut plan = Plan::new(history, pinned);
+        let units = plan.units();
+        plan.drop_until_fits(units, budget_tokens);
+        plan.finish()
+    }
+}
+
+/// Keeps only the most recent `max_messages` messages (plus pinned ones),
+/// then drops the oldest of those if still over budget
+#[derive(Debug)]
+pub struct SlidingWindow {
+    pub max_messages: usize,
+}
+
+impl RetentionPolicy for SlidingWindow {
+    fn name(&self) -> &'static str {
+        "sliding-window"
+    }
+
+    fn retain(
+        &self,
+        history: &[Message],
+        pinned: &HashSet<String>,
+        budget_tokens: u32,
+    ) -> Vec<Message> {
+        let mut plan = Plan::new(history, pinned);
+        let units = plan.units();
+
+        // Walk back from the newest message until the window is full
+        let mut in_window = 0;
+        let mut window_start = units.len();
+        for (i, unit) in units.iter().enumerate().rev() {
+            if in_window + unit.len() > self.max_messages {
+                break;
+            }
+            in_window += unit.len();
+            window_start = i;
+        }
+        for unit in &units[..window_start] {
+            plan.drop_unit(unit);
+        }
+        plan.drop_until_fits(units[window_start..].to_vec(), budget_tokens);
+        plan.finish()
+    }
+}
+
+/// Drops the least important messages first: tool output before assistant
+/// text before user messages, errors kept longer, older before newer
+#[derive(Debug)]
+pub struct ImportanceScored;
+
+impl ImportanceScored {
+    fn score(history: &[Message], unit: &Range<usize>, position: f32) -> f32 {
+        let first = &history[unit.start];
+        let kind = match first.role {
+            MessageRole::User => 3.0,
+            _ if unit.len() > 1 => 1.0, // Tool call with its result
+            _ => 2.0,
+        };
+        let mentions_error = history[unit.clone()].iter().any(|m| {
+            let lower = m.content.to_lowercase();
+            lower.contains("error") || lower.contains("failed")
+        });
+        kind + if mentions_error { 1.0 } else { 0.0 } + position
+    }
+}
+
+impl RetentionPolicy for ImportanceScored {
+    fn name(&self) -> &'static str {
+        "importance"
+    }
+
+    fn retain(
+        &self,
+        history: &[Message],
+        pinned: &HashSet<String>,
+        budget_tokens: u32,
+    ) -> Vec<Message> {
+        let mut plan = Plan::new(history, pinned);
+        let units = plan.units();
+        let len = units.len().max(1) as f32;
+        let mut scored: Vec<(f32, Range<usize>)> = units
+            .into_iter()
+            .enumerate()
+            .map(|(i, unit)| (Self::score(history, &unit, i as f32 / len), unit))
+            .collect();
+        scored.sort_by(|a, b| a.0.total_cmp(&b.0));
+        plan.drop_until_fits(
+            scored.into_iter().map(|(_, unit)| unit).collect(),
+            budget_tokens,
+        );
+        plan.finish()
+    }
+}
+
+/// Condenses older history into summary notes (see [`compaction`]), and
+/// only drops messages if that isn't enough
+#[derive(Debug)]
+pub struct SummarizeOnOverflow;
+
+impl RetentionPolicy for SummarizeOnOverflow {
+    fn name(&self) -> &'static str {
+        "summarize-on-overflow"
+    }
+
+    fn retain(
+        &self,
+        history: &[Message],
+        pinned: &HashSet<String>,
+        budget_tokens: u32,
+    ) -> Vec<Message> {
+        let mut history = history.to_vec();
+        // Each pass condenses half of what's left, so a few passes are plenty
+        for _ in 0..3 {
+            if total_tokens(&history) <= budget_tokens {
+                return history;
+            }
+            // Messages up to the last pinned one are left out of the summary
+            let split = pinned_prefix_len(&history, pinned);
+            let Some(compacted) = compaction::compact(&history[split..]) else {
+                break;
+            };
+            history.truncate(split);
+            history.extend(compacted.history);
+        }
+        KeepPinned.retain(&history, pinned, budget_tokens)
+    }
+}
+
+/// Number of leading messages up to and including the last pinned
+/// conversation message; compaction must not fold those into a note
+pub(crate) fn pinned_prefix_len(history: &[Message], pinned: &HashSet<String>) -> usize {
+    history
+        .iter()
+        .rposition(|m| !matches!(m.role, MessageRole::System) && is_pinned(m, pinned))
+        .map_or(0, |i| i + 1)
+}
+
+pub(crate) fn is_pinned(message: &Message, pinned: &HashSet<String>) -> bool {
+    !message.id.is_empty() && pinned.contains(&message.id)
+}
+
+fn total_tokens(history: &[Message]) -> u32 {
+    history
+        .iter()
+        .map(|m| ContextWindow::estimate_tokens(&m.content))
+        .sum()
+}
+
+fn is_tool_result(message: &Message) -> bool {
+    matches!(message.role, MessageRole::User) && message.content.starts_with("Tool result:")
+}
+
+/// Which messages of a history are kept, and what they cost
+struct Plan<'a> {
+    history: &'a [Message],
+    pinned: &'a HashSet<String>,
+    keep: Vec<bool>,
+    tokens: u32,
+    /// Start of the current turn, which is never dropped
+    current_turn: usize,
+}
+
+impl<'a> Plan<'a> {
+    fn new(history: &'a [Message], pinned: &'a HashSet<String>) -> Self {
+        let current_turn = history
+            .iter()
+            .rposition(|m| matches!(m.role, MessageRole::User) && !is_tool_result(m))
+            .unwrap_or(history.len());
+        Self {
+            history,
+            pinned,
+            keep: vec![true; history.len()],
+            tokens: total_tokens(history),
+            current_turn,
+        }
+    }
+
+    /// Messages that are dropped together, oldest first: a tool call with its
+    /// result, or a single message
+    fn units(&self) -> Vec<Range<usize>> {
+        let mut units = Vec::new();
+        let mut i = 0;
+        while i < self.history.len() {
+            let end = if matches!(self.history[i].role, MessageRole::Assistant)
+                && self.history.get(i + 1).is_some_and(is_tool_result)
+            {
+                i + 2
+            } else {
+                i + 1
+            };
+            units.push(i..end);
+            i = end;
+        }
+        units
+    }
+
+    fn is_protected(&self, unit: &Range<usize>) -> bool {
+        unit.end > self.current_turn
+            || self.history[unit.clone()]
+                .iter()
+                .any(|m| matches!(m.role, MessageRole::System) || is_pinned(m, self.pinned))
+    }
+
+    fn drop_unit(&mut self, unit: &Range<usize>) {
+        if self.is_protected(unit) {
+            return;
+        }
+        for i in unit.clone() {
+            if self.keep[i] {
+                self.keep[i] = false;
+                self.tokens = self
+                    .tokens
+                    .saturating_sub(ContextWindow::estimate_tokens(&self.history[i].content));
+            }
+        }
+    }
+
+    /// Drop `units` in the given order until the history fits the budget
+    fn drop_until_fits(&mut self, units: Vec<Range<usize>>, budget_tokens: u32) {
+        for unit in units {
+            if self.tokens <= budget_tokens {
+                break;
+            }
+            self.drop_unit(&unit);
+        }
+    }
+
+    /// The kept messages, with any assistant messages or tool results left
+    /// stranded before the first user message dropped as well
+    fn finish(mut self) -> Vec<Message> {
+        let mut seen_user = false;
+        for i in 0..self.history.len() {
+            let message = &self.history[i];
+            if !self.keep[i] || matches!(message.role, MessageRole::System) {
+                continue;
+            }
+            seen_user |= matches!(message.role, MessageRole::User) && !is_tool_result(message);
+            if !seen_user && !is_pinned(message, self.pinned) && i < self.current_turn {
+                self.keep[i] = false;
+            }
+        }
+        self.history
+            .iter()
+            .zip(&self.keep)
+            .filter(|(_, keep)| **keep)
+            .map(|(message, _)| message.clone())
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn message(role: MessageRole, content: &str) -> Message {
+        Message::new(role, content.to_string())
+    }
+
+    /// System prompt, README, a pinned requirements message, then `turns`
+    /// exchanges of a few hundred tokens each
+    fn history(turns: usize) -> (Vec<Message>, HashSet<String>) {
+        let mut history = vec![
+            message(MessageRole::System, "You are G3"),
+            message(MessageRole::System, "Project README: demo"),
+            message(MessageRole::User, "Requirements: build a parser"),
+        ];
+        let pinned: HashSet<String> = history.iter().map(|m| m.id.clone()).collect();
+        for turn in 0..turns {
+            history.push(message(MessageRole::User, &format!("Step {}", turn)));
+            history.push(message(
+                MessageRole::Assistant,
+                &format!("Working on step {}", turn),
+            ));
+            history.push(message(
+                MessageRole::Assistant,
+                r#"{"tool": "read_file", "args": {"file_path": "src/lib.rs"}}"#,
+            ));
+            history.push(message(
+                MessageRole::User,
+                &format!("Tool result: {}", "line of output\n".repeat(60)),
+            ));
+        }
+        (history, pinned)
+    }
+
+    fn contents(history: &[Message]) -> Vec<&str> {
+        history.iter().map(|m| m.content.as_str()).collect()
+    }
+
+    fn assert_invariants(original: &[Message], kept: &[Message], pinned: &HashSet<String>) {
+        for m in original.iter().filter(|m| is_pinned(m, pinned)) {
+            assert!(kept.iter().any(|k| k.id == m.id), "pinned message dropped");
+        }
+        assert_eq!(kept[0].content, "You are G3");
+        // The current turn survives
+        assert_eq!(
+            kept.last().unwrap().content,
+            original.last().unwrap().content
+        );
+    }
+
+    #[test]
+    fn test_keep_pinned_drops_oldest_first() {
+        let (original, pinned) = history(6);
+        let budget = total_tokens(&original) / 2;
+        let kept = KeepPinned.retain(&original, &pinned, budget);
+
+        assert!(total_tokens(&kept) <= budget);
+        assert_invariants(&original, &kept, &pinned);
+        let kept = contents(&kept);
+        assert!(!kept.contains(&"Step 0"));
+        assert!(kept.contains(&"Step 5"));
+    }
+
+    #[test]
+    fn test_within_budget_keeps_everything() {
+        let (original, pinned) = history(3);
+        let kept = KeepPinned.retain(&original, &pinned, u32::MAX);
+        assert_eq!(kept.len(), original.len());
+    }
+
+    #[test]
+    fn test_sliding_window_keeps_recent_messages() {
+        let (original, pinned) = history(6);
+        let policy = SlidingWindow { max_messages: 8 };
+        let kept = policy.retain(&original, &pinned, u32::MAX);
+
+        assert_invariants(&original, &kept, &pinned);
+        // Pinned and system messages, plus the last two steps
+        assert_eq!(kept.len(), 3 + 8);
+        assert_eq!(kept[3].content, "Step 4");
+    }
+
+    #[test]
+    fn test_importance_drops_tool_output_first() {
+        let (original, pinned) = history(4);
+        let budget = total_tokens(&original) * 3 / 4;
+        let kept = ImportanceScored.retain(&original, &pinned, budget);
+
+        assert!(total_tokens(&kept) <= budget);
+        assert_invariants(&original, &kept, &pinned);
+        // Every user step survives; only tool calls and their output went
+        for step in 0..4 {
+            assert!(contents(&kept).contains(&format!("Step {}", step).as_str()));
+        }
+        assert!(kept.len() < original.len());
+    }
+
+    #[test]
+    fn test_summarize_on_overflow_keeps_pinned_out_of_the_note() {
+        let (original, pinned) = history(8);
+        let budget = total_tokens(&original) / 2;
+        let kept = SummarizeOnOverflow.retain(&original, &pinned, budget);
+
+        assert!(total_tokens(&kept) <= budget);
+        assert_invariants(&original, &kept, &pinned);
+        assert_eq!(kept[2].content, "Requirements: build a parser");
+        assert!(kept.iter().any(compaction::is_note));
+    }
+
+    #[test]
+    fn test_unknown_policy_is_rejected() {
+        let config = RetentionConfig {
+            policy: Some("random".to_string()),
+            ..Default::default()
+        };
+        let err = policy_from_config(&config).unwrap_err().to_string();
+        assert!(err.contains("Supported: keep-pinned"));
+        assert!(policy_from_config(&RetentionConfig::default())
+            .unwrap()
+            .is_none());
+    }
+}
@@ -0,0 +1,532 @@
+//! Incremental session journal, replayed by `g3 --resume <session-id>`.
+//!
+//! While a session runs, every message added to the context window, every
+//! tool call and the visible transcript are appended to
+//! `.g3/sessions/<session_id>/journal.jsonl` as they happen. When the history
+//! is rewritten instead of appended to (thinning, compaction, summaries), the
+//! new history is journaled as a full snapshot. Replaying the journal rebuilds
+//! the session exactly as it was at the last entry, even after a crash.
+//! Conversation branches other than the current one (see [`crate::branches`])
+//! are journaled whole whenever they change.
+
+use anyhow::{bail, Context, Result};
+use g3_config::secrets::Redactor;
+use g3_providers::{Message, MessageRole};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tracing::warn;
+
+use crate::branches::Branches;
+use crate::paths::get_g3_dir;
+use crate::ContextWindow;
+
+/// A message as journaled; unlike [`Message`], keeps its id and pin
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct JournalMessage {
+    pub id: String,
+    pub role: MessageRole,
+    pub content: String,
+    #[serde(default)]
+    pub pinned: bool,
+}
+
+impl JournalMessage {
+    pub(crate) fn new(message: &Message, context: &ContextWindow) -> Self {
+        Self {
+            id: message.id.clone(),
+            role: message.role.clone(),
+            content: message.content.clone(),
+            pinned: context.is_pinned(message),
+        }
+    }
+
+    pub(crate) fn into_message(self) -> Message {
+        let mut message = Message::new(self.role, self.content);
+        if !self.id.is_empty() {
+            message.id = self.id;
+        }
+        message
+    }
+}
+
+/// One line of the journal
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum JournalEntry {
+    /// A message appended to the context window
+    Message {
+        message: JournalMessage,
+        used_tokens: u32,
+        cumulative_tokens: u32,
+    },
+    /// The whole history, after it was rewritten
+    History {
+        messages: Vec<JournalMessage>,
+        used_tokens: u32,
+        cumulative_tokens: u32,
+    },
+    ToolCall {
+        tool: String,
+        duration_ms: u64,
+        success: bool,
+    },
+    /// A task the user entered
+    Input { text: String },
+    /// The agent's response to a task
+    Output { text: String },
+    /// Every conversation branch, after one was made, switched to or dropped
+    Branches(Branches),
+}
+
+impl JournalEntry {
+    /// The entry as the user saw it; None for context changes
+    fn into_transcript(self) -> Option<TranscriptEntry> {
+        match self {
+            JournalEntry::ToolCall {
+                tool,
+                duration_ms,
+                success,
+            } => Some(TranscriptEntry::ToolCall {
+                tool,
+                duration: Duration::from_millis(duration_ms),
+                success,
+            }),
+            JournalEntry::Input { text } => Some(TranscriptEntry::Input(text)),
+            JournalEntry::Output { text } => Some(TranscriptEntry::Output(text)),
+            JournalEntry::Message { .. }
+            | JournalEntry::History { .. }
+            | JournalEntry::Branches(_) => None,
+        }
+    }
+}
+
+/// What the user saw during the session, in order
+#[derive(Debug, Clone, PartialEq)]
+pub enum TranscriptEntry {
+    Input(String),
+    Output(String),
+    ToolCall {
+        tool: String,
+        duration: Duration,
+        success: bool,
+    },
+}
+
+/// A session rebuilt from its journal
+#[derive(Debug)]
+pub struct RestoredSession {
+    pub history: Vec<Message>,
+    pub pinned_message_ids: HashSet<String>,
+    pub used_tokens: u32,
+    pub cumulative_tokens: u32,
+    pub transcript: Vec<TranscriptEntry>,
+    /// None if the session never branched
+    pub branches: Option<Branches>,
+}
+
+impl RestoredSession {
+    /// (tool, duration, success) of every tool call, for the agent's metrics
+    pub fn tool_calls(&self) -> Vec<(String, Duration, bool)> {
+        self.transcript
+            .iter()
+            .filter_map(|entry| match entry {
+                TranscriptEntry::ToolCall {
+                    tool,
+                    duration,
+                    success,
+                } => Some((tool.clone(), *duration, *success)),
+                _ => None,
+            })
+            .collect()
+    }
+}
+
+/// Appends a session's activity to its journal file
+#[derive(Debug)]
+pub struct SessionJournal {
+    path: PathBuf,
+    /// (id, content length) of each journaled message, to tell appends
+    /// from rewrites
+    journaled: Vec<(String, usize)>,
+    /// Masks credentials before entries reach the file
+    redactor: Redactor,
+}
+
+impl SessionJournal {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self {
+            path: path.into(),
+            journaled: Vec::new(),
+            redactor: Redactor::default(),
+        }
+    }
+
+    /// Mask `redactor`'s credentials in every entry written from now on
+    pub fn with_redactor(mut self, redactor: Redactor) -> Self {
+        self.redactor = redactor;
+        self
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Append one entry, creating the file if needed
+    pub fn append(&self, entry: &JournalEntry) -> Result<()> {
+        if let Some(parent) = self.path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let mut file = std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)
+            .with_context(|| format!("Failed to open session journal {}", self.path.display()))?;
+        let line = serde_json::to_string(entry)?;
+        writeln!(file, "{}", self.redactor.redact(&line))?;
+        Ok(())
+    }
+
+    /// Journal what changed in the context window since the last call:
+    /// the new messages, or a snapshot if earlier history was rewritten
+    pub fn record_context(&mut self, context: &ContextWindow) -> Result<()> {
+        let history = &context.conversation_history;
+        let current: Vec<(String, usize)> = history
+            .iter()
+            .map(|m| (m.id.clone(), m.content.len()))
+            .collect();
+
+        if current.starts_with(&self.journaled) {
+            for message in &history[self.journaled.len()..] {
+                self.append(&JournalEntry::Message {
+                    message: JournalMessage::new(message, context),
+                    used_tokens: context.used_tokens,
+                    cumulative_tokens: context.cumulative_tokens,
+                })?;
+            }
+        } else {
+            self.record_snapshot(context)?;
+        }
+        self.journaled = current;
+        Ok(())
+    }
+
+    /// Journal the whole history, e.g. after a pin changed, which
+    /// [`SessionJournal::record_context`] can't tell from no change
+    pub fn record_snapshot(&mut self, context: &ContextWindow) -> Result<()> {
+        let history = &context.conversation_history;
+        self.append(&JournalEntry::History {
+            messages: history
+                .iter()
+                .map(|m| JournalMessage::new(m, context))
+                .collect(),
+            used_tokens: context.used_tokens,
+            cumulative_tokens: context.cumulative_tokens,
+        })?;
+        self.journaled = history
+            .iter()
+            .map(|m| (m.id.clone(), m.content.len()))
+            .collect();
+        Ok(())
+    }
+
+    /// Rebuild a session from the journal at `path`. The returned journal
+    /// keeps appending to the same file.
+    pub fn restore(path: impl Into<PathBuf>) -> Result<(Self, RestoredSession)> {
+        let path = path.into();
+        if !path.exists() {
+            bail!("No session journal at {}", path.display());
+        }
+        let contents = std::fs::read_to_string(&path)
+            .with_context(|| format!("Failed to read session journal {}", path.display()))?;
+        // End a half-written last line, so new entries start on a line of their own
+        if !contents.is_empty() && !contents.ends_with('\n') {
+            let mut file = std::fs::OpenOptions::new().append(true).open(&path)?;
+            writeln!(file)?;
+        }
+
+        let mut messages: Vec<JournalMessage> = Vec::new();
+        let mut used_tokens = 0;
+        let mut cumulative_tokens = 0;
+        let mut transcript = Vec::new();
+        let mut branches = None;
+        for entry in parse_entries(&contents) {
+            match entry {
+                JournalEntry::Message {
+                    message,
+                    used_tokens: used,
+                    cumulative_tokens: cumulative,
+                } => {
+                    messages.push(message);
+                    used_tokens = used;
+                    cumulative_tokens = cumulative;
+                }
+                JournalEntry::History {
+                    messages: snapshot,
+                    used_tokens: used,
+                    cumulative_tokens: cumulative,
+                } => {
+                    messages = snapshot;
+                    used_tokens = used;
+                    cumulative_tokens = cumulative;
+                }
+                JournalEntry::Branches(snapshot) => branches = Some(snapshot),
+                entry => transcript.extend(entry.into_transcript()),
+            }
+        }
+        if messages.is_empty() {
+            bail!("Session journal {} has no messages", path.display());
+        }
+
+        let pinned_message_ids = messages
+            .iter()
+            .filter(|m| m.pinned && !m.id.is_empty())
+            .map(|m| m.id.clone())
+            .collect();
+        let history: Vec<Message> = messages
+            .into_iter()
+            .map(JournalMessage::into_message)
+            .collect();
+        let journal = Self {
+            path,
+            journaled: history
+                .iter()
+                .map(|m| (m.id.clone(), m.content.len()))
+                .collect(),
+            redactor: Redactor::default(),
+        };
+        Ok((
+            journal,
+            RestoredSession {
+                history,
+                pinned_message_ids,
+                used_tokens,
+                cumulative_tokens,
+                transcript,
+                branches,
+            },
+        ))
+    }
+}
+
+/// What the user saw in the session journaled at `path`, read without
+/// restoring the session (for `g3 replay`)
+pub fn read_transcript(path: &Path) -> Result<Vec<TranscriptEntry>> {
+    let contents = std::fs::read_to_string(path)
+        .with_context(|| format!("Failed to read session journal {}", path.display()))?;
+    let transcript: Vec<TranscriptEntry> = parse_entries(&contents)
+        .filter_map(JournalEntry::into_transcript)
+        .collect();
+    if transcript.is_empty() {
+        bail!("Session journal {} has no transcript", path.display());
+    }
+    Ok(transcript)
+}
+
+/// The entries of a journal, skipping lines that don't parse
+fn parse_entries(contents: &str) -> impl Iterator<Item = JournalEntry> + '_ {
+    contents
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .filter_map(|(number, line)| match serde_json::from_str(line) {
+            Ok(entry) => Some(entry),
+            // A crash can leave the last line half-written
+            Err(e) => {
+                warn!("Skipping malformed journal line {}: {}", number + 1, e);
+                None
+            }
+        })
+}
+
+/// The session whose journal was written last, if any
+pub fn latest_session() -> Option<String> {
+    let sessions = std::fs::read_dir(get_g3_dir().join("sessions")).ok()?;
+    sessions
+        .filter_map(|entry| entry.ok())
+        .filter_map(|entry| {
+            let modified = entry
+                .path()
+                .join("journal.jsonl")
+                .metadata()
+                .and_then(|meta| meta.modified())
+                .ok()?;
+            Some((modified, entry.file_name().to_string_lossy().into_owned()))
+        })
+        .max()
+        .map(|(_, session_id)| session_id)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn context() -> ContextWindow {
+        let mut context = ContextWindow::new(10_000);
+        context.add_pinned_message(Message::new(MessageRole::System, "You are G3".to_string()));
+        context.add_message(Message::new(
+            MessageRole::User,
+            "Task: fix the parser".to_string(),
+        ));
+        context
+    }
+
+    #[test]
+    fn test_restores_appended_messages_and_transcript() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("session").join("journal.jsonl");
+        let mut journal = SessionJournal::new(&path);
+        let mut context = context();
+
+        journal.record_context(&context).unwrap();
+        journal
+            .append(&JournalEntry::Input {
+                text: "fix the parser".to_string(),
+            })
+            .unwrap();
+        context.add_message(Message::new(
+            MessageRole::Assistant,
+            r#"{"tool": "read_file", "args": {"file_path": "src/parser.rs"}}"#.to_string(),
+        ));
+        context.add_message(Message::new(
+            MessageRole::User,
+            "Tool result: fn parse() {}".to_string(),
+        ));
+        journal.record_context(&context).unwrap();
+        journal
+            .append(&JournalEntry::ToolCall {
+                tool: "read_file".to_string(),
+                duration_ms: 12,
+                success: true,
+            })
+            .unwrap();
+        // Nothing new: nothing written
+        journal.record_context(&context).unwrap();
+
+        let lines = std::fs::read_to_string(&path).unwrap().lines().count();
+        assert_eq!(lines, 6);
+
+        let (_, restored) = SessionJournal::restore(&path).unwrap();
+        assert_eq!(restored.history.len(), 4);
+        assert_eq!(restored.history[0].id, context.conversation_history[0].id);
+        assert_eq!(restored.history[3].content, "Tool result: fn parse() {}");
+        assert!(restored
+            .pinned_message_ids
+            .contains(&context.conversation_history[0].id));
+        assert_eq!(restored.used_tokens, context.used_tokens);
+        assert_eq!(restored.cumulative_tokens, context.cumulative_tokens);
+        assert_eq!(
+            restored.transcript,
+            vec![
+                TranscriptEntry::Input("fix the parser".to_string()),
+                TranscriptEntry::ToolCall {
+                    tool: "read_file".to_string(),
+                    duration: Duration::from_millis(12),
+                    success: true,
+                },
+            ]
+        );
+        assert_eq!(restored.tool_calls().len(), 1);
+        assert_eq!(read_transcript(&path).unwrap(), restored.transcript);
+    }
+
+    #[test]
+    fn test_rewritten_history_is_snapshotted() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("journal.jsonl");
+        let mut journal = SessionJournal::new(&path);
+        let mut context = context();
+        journal.record_context(&context).unwrap();
+
+        // Rewrite an earlier message in place, as thinning does
+        context.conversation_history[1].content = "Task: fix".to_string();
+        context.add_message(Message::new(MessageRole::Assistant, "Done".to_string()));
+        journal.record_context(&context).unwrap();
+
+        let (mut resumed, restored) = SessionJournal::restore(&path).unwrap();
+        let contents: Vec<&str> = restored
+            .history
+            .iter()
+            .map(|m| m.content.as_str())
+            .collect();
+        assert_eq!(contents, vec!["You are G3", "Task: fix", "Done"]);
+
+        // The resumed journal only appends what's new
+        context.add_message(Message::new(MessageRole::User, "Thanks".to_string()));
+        resumed.record_context(&context).unwrap();
+        let (_, restored) = SessionJournal::restore(&path).unwrap();
+        assert_eq!(restored.history.len(), 4);
+    }
+
+    #[test]
+    fn test_half_written_line_is_skipped() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("journal.jsonl");
+        let mut journal = SessionJournal::new(&path);
+        journal.record_context(&context()).unwrap();
+
+        let mut file = std::fs::OpenOptions::new()
+            .append(true)
+            .open(&path)
+            .unwrap();
+        write!(file, r#"{{"type": "message", "message": {{"id": "#).unwrap();
+
+        let (mut resumed, restored) = SessionJournal::restore(&path).unwrap();
+        assert_eq!(restored.history.len(), 2);
+        assert!(SessionJournal::restore(dir.path().join("missing.jsonl")).is_err());
+
+        // Entries after the resume aren't swallowed by the broken line
+        let mut context = context();
+        context.conversation_history = restored.history;
+        context.add_message(Message::new(MessageRole::Assistant, "Done".to_string()));
+        resumed.record_context(&context).unwrap();
+        let (_, restored) = SessionJournal::restore(&path).unwrap();
+        assert_eq!(restored.history.len(), 3);
+    }
+
+    #[test]
+    fn test_restores_branches() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("journal.jsonl");
+        let mut journal = SessionJournal::new(&path);
+        let mut context = context();
+        journal.record_context(&context).unwrap();
+        let (_, restored) = SessionJournal::restore(&path).unwrap();
+        assert!(restored.branches.is_none());
+
+        let mut branches = Branches::default();
+        branches.fork("retry", &context).unwrap();
+        context.add_message(Message::new(MessageRole::User, "Use nom".to_string()));
+        branches.switch("main", &mut context).unwrap();
+        journal
+            .append(&JournalEntry::Branches(branches.clone()))
+            .unwrap();
+        journal.record_context(&context).unwrap();
+
+        let (_, restored) = SessionJournal::restore(&path).unwrap();
+        assert_eq!(restored.branches, Some(branches));
+        assert_eq!(restored.history.len(), 2);
+        assert!(read_transcript(&path).is_err());
+    }
+
+    #[test]
+    fn test_credentials_are_redacted() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("journal.jsonl");
+        let mut journal = SessionJournal::new(&path)
+            .with_redactor(Redactor::new(vec!["sk-ant-secret-key".to_string()]));
+        let mut context = context();
+        context.add_message(Message::new(
+            MessageRole::User,
+            "Tool result: ANTHROPIC_API_KEY=sk-ant-secret-key".to_string(),
+        ));
+        journal.record_context(&context).unwrap();
+
+        let contents = std::fs::read_to_string(&path).unwrap();
+        assert!(!contents.contains("sk-ant-secret-key"));
+        assert!(contents.contains("ANTHROPIC_API_KEY=********"));
+    }
+}
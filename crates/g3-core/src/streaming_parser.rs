@@ -5,6 +5,7 @@
 
 use tracing::debug;
 
+use crate::json_repair::parse_lenient;
 use crate::ToolCall;
 
 /// Patterns used to detect JSON tool calls in text.
@@ -94,6 +95,27 @@ impl StreamingToolParser {
         })
     }
 
+    /// Parse a JSON tool call, falling back to lenient repair of malformed JSON.
+    fn parse_tool_call_json(json_str: &str) -> Option<ToolCall> {
+        match parse_lenient::<ToolCall>(json_str) {
+            Ok(parsed) => {
+                if !parsed.repairs.is_empty() {
+                    debug!(
+                        "Repaired malformed JSON tool call ({})",
+                        parsed
+                            .repairs
+                            .iter()
+                            .map(|r| r.to_string())
+                            .collect::<Vec<_>>()
+                            .join(", ")
+                    );
+                }
+                Some(parsed.value)
+            }
+            Err(_) => None,
+        }
+    }
+
     /// Process a streaming chunk and return completed tool calls if any.
     pub fn process_chunk(&mut self, chunk: &g3_providers::CompletionChunk) -> Vec<ToolCall> {
         let mut completed_tools = Vec::new();
@@ -168,8 +190,8 @@ impl StreamingToolParser {
                     let json_str = &json_text[..=end_pos];
                     debug!("Attempting to parse JSON tool call: {}", json_str);
 
-                    // Try to parse as a ToolCall
-                    if let Ok(tool_call) = serde_json::from_str::<ToolCall>(json_str) {
+                    // Try to parse as a ToolCall, repairing common JSON defects
+                    if let Some(tool_call) = Self::parse_tool_call_json(json_str) {
                         // Validate that args is an object with reasonable keys
                         if let Some(args_obj) = tool_call.args.as_object() {
                             if Self::has_message_like_keys(args_obj) {
@@ -218,7 +240,7 @@ impl StreamingToolParser {
                 if let Some(end_pos) = Self::find_complete_json_object_end(json_text) {
                     let json_str = &json_text[..=end_pos];
 
-                    if let Ok(tool_call) = serde_json::from_str::<ToolCall>(json_str) {
+                    if let Some(tool_call) = Self::parse_tool_call_json(json_str) {
                         if let Some(args_obj) = tool_call.args.as_object() {
                             if !Self::has_message_like_keys(args_obj) {
                                 debug!(
@@ -289,7 +311,7 @@ impl StreamingToolParser {
             // If the JSON IS complete, it means there's an unexecuted tool call
             if let Some(json_end) = Self::find_complete_json_object_end(json_text) {
                 let json_only = &json_text[..=json_end];
-                return serde_json::from_str::<serde_json::Value>(json_only).is_ok();
+                return parse_lenient::<serde_json::Value>(json_only).is_ok();
             }
         }
         false
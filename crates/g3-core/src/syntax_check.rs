@@ -0,0 +1,133 @@
+//! Syntax gate for file edits.
+//!
+//! Before write_file or str_replace writes a source file, the new content is
+//! parsed with tree-sitter (the grammars the repo map uses). An edit that
+//! would leave the file with a syntax error is refused and the parse error
+//! goes back to the model, so one broken edit doesn't turn into a string of
+//! follow-up fixes. Files that already had errors before the edit, and
+//! languages without a grammar, are not gated.
+
+use std::fmt;
+use std::path::Path;
+use tree_sitter::{Node, Parser};
+
+use crate::repo_map::language_for;
+
+/// Characters of the offending source quoted in the error
+const SNIPPET_CHARS: usize = 40;
+
+/// The first syntax error in a file
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SyntaxError {
+    /// 1-based
+    pub line: usize,
+    /// 1-based, in characters
+    pub column: usize,
+    pub message: String,
+}
+
+impl fmt::Display for SyntaxError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "line {}, column {}: {}",
+            self.line, self.column, self.message
+        )
+    }
+}
+
+/// The first syntax error in `source`, parsed in the language of `path`;
+/// None if it parses, or if there is no grammar for it
+pub fn first_error(path: &Path, source: &str) -> Option<SyntaxError> {
+    let (_, language) = language_for(path)?;
+    let mut parser = Parser::new();
+    parser.set_language(&language).ok()?;
+    let tree = parser.parse(source, None)?;
+    let root = tree.root_node();
+    if !root.has_error() {
+        return None;
+    }
+    let node = find_error(root).unwrap_or(root);
+    let position = node.start_position();
+    let line_start = source[..node.start_byte()].rfind('\n').map_or(0, |i| i + 1);
+    let message = if node.is_missing() {
+        format!("missing `{}`", node.kind())
+    } else {
+        let text = source[node.start_byte()..node.end_byte()]
+            .lines()
+            .map(str::trim)
+            .find(|line| !line.is_empty());
+        match text {
+            Some(text) => format!("unexpected `{}`", snippet(text)),
+            None => "unexpected end of input".to_string(),
+        }
+    };
+    Some(SyntaxError {
+        line: position.row + 1,
+        column: source[line_start..node.start_byte()].chars().count() + 1,
+        message,
+    })
+}
+
+/// The error an edit of `path` from `before` (None for a new file) to
+/// `after` would introduce
+pub fn check_edit(path: &Path, before: Option<&str>, after: &str) -> Option<SyntaxError> {
+    let error = first_error(path, after)?;
+    match before {
+        Some(before) if first_error(path, before).is_some() => None,
+        _ => Some(error),
+    }
+}
+
+/// The first error or missing node, in source order
+fn find_error(node: Node) -> Option<Node> {
+    if node.is_error() || node.is_missing() {
+        return Some(node);
+    }
+    let mut cursor = node.walk();
+    let children: Vec<Node> = node.children(&mut cursor).collect();
+    children
+        .into_iter()
+        .filter(|child| child.has_error())
+        .find_map(find_error)
+}
+
+fn snippet(text: &str) -> String {
+    match text.char_indices().nth(SNIPPET_CHARS) {
+        Some((end, _)) => format!("{}…", &text[..end]),
+        None => text.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_first_error() {
+        let path = Path::new("src/lib.rs");
+        assert_eq!(first_error(path, "fn main() {\n    run();\n}\n"), None);
+
+        let error = first_error(path, "fn main() {\n    let x = ;\n}\n").unwrap();
+        assert_eq!(error.line, 2);
+        assert!(error.to_string().starts_with("line 2, column "));
+
+        let error = first_error(Path::new("app.py"), "def run(:\n    pass\n").unwrap();
+        assert_eq!(error.line, 1);
+
+        // No grammar
+        assert_eq!(first_error(Path::new("notes.md"), "fn main() {"), None);
+    }
+
+    #[test]
+    fn test_check_edit() {
+        let path = Path::new("main.go");
+        let valid = "package main\n\nfunc main() {}\n";
+        let broken = "package main\n\nfunc main() {\n";
+        assert!(check_edit(path, Some(valid), broken).is_some());
+        assert!(check_edit(path, None, broken).is_some());
+        assert_eq!(check_edit(path, Some(valid), valid), None);
+        // Already broken before the edit
+        assert_eq!(check_edit(path, Some(broken), broken), None);
+    }
+}
@@ -0,0 +1,479 @@
+//! Structured view of a `todo.g3.md` checklist.
+//!
+//! The TODO file is plain markdown written by the model: headings, free
+//! text and `- [ ]` / `- [x]` checkboxes, nested by indentation. [`TodoList`]
+//! parses it into typed tasks so code can query and edit it instead of
+//! matching on raw lines:
+//! - every task gets a positional id (`2`, `2.1`, `2.1.3`), renumbered after
+//!   each edit
+//! - indented lines under a task that aren't checkboxes are its notes
+//! - other lines (headings, prose) are kept in place as text
+//!
+//! [`TodoList::to_markdown`] writes the list back in one canonical form
+//! (`- ` bullets, two spaces per nesting level, single blank lines), so
+//! parsing and serializing again gives the same text.
+
+use anyhow::{anyhow, bail, Result};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// Indentation per nesting level when serializing
+const INDENT: &str = "  ";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TaskStatus {
+    Pending,
+    Done,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Task {
+    /// Position in the list, e.g. `2.1` for the first subtask of the second task
+    pub id: String,
+    pub title: String,
+    pub status: TaskStatus,
+    /// Non-checkbox lines indented under the task
+    pub notes: Vec<String>,
+    pub subtasks: Vec<Task>,
+}
+
+impl Task {
+    fn new(title: &str, status: TaskStatus) -> Self {
+        Self {
+            id: String::new(),
+            title: title.to_string(),
+            status,
+            notes: Vec::new(),
+            subtasks: Vec::new(),
+        }
+    }
+
+    pub fn is_done(&self) -> bool {
+        self.status == TaskStatus::Done
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TodoItem {
+    /// A heading, prose or blank line outside any task
+    Text(String),
+    Task(Task),
+}
+
+/// Completion counts over every task and subtask
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TodoStats {
+    pub total: usize,
+    pub done: usize,
+}
+
+impl TodoStats {
+    pub fn pending(&self) -> usize {
+        self.total - self.done
+    }
+
+    /// Whether there are tasks and all of them are done
+    pub fn all_done(&self) -> bool {
+        self.total > 0 && self.done == self.total
+    }
+
+    pub fn percent(&self) -> f64 {
+        if self.total == 0 {
+            0.0
+        } else {
+            self.done as f64 * 100.0 / self.total as f64
+        }
+    }
+}
+
+impl fmt::Display for TodoStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}/{} tasks done ({:.0}%)",
+            self.done,
+            self.total,
+            self.percent()
+        )
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TodoList {
+    pub items: Vec<TodoItem>,
+}
+
+impl TodoList {
+    pub fn parse(markdown: &str) -> Self {
+        let mut items: Vec<TodoItem> = Vec::new();
+        // Indentation of each open task, outermost first; the innermost is
+        // the last task of the last item, followed down through subtasks
+        let mut open: Vec<usize> = Vec::new();
+        let mut blank_lines = 0;
+
+        for line in markdown.lines() {
+            if line.trim().is_empty() {
+                blank_lines += 1;
+                continue;
+            }
+            let indent = indentation(line);
+            let checkbox = parse_checkbox(line.trim());
+
+            // Close the tasks this line isn't nested under
+            while open
+                .last()
+                .is_some_and(|&open_indent| open_indent >= indent)
+            {
+                open.pop();
+            }
+            let nested = !open.is_empty();
+            // Blank lines inside a task are dropped; runs of them elsewhere
+            // become one
+            if !nested && blank_lines > 0 {
+                items.push(TodoItem::Text(String::new()));
+            }
+            blank_lines = 0;
+
+            match (checkbox, nested) {
+                (Some((status, title)), true) => {
+                    innermost(&mut items, open.len())
+                        .subtasks
+                        .push(Task::new(title, status));
+                    open.push(indent);
+                }
+                (Some((status, title)), false) => {
+                    items.push(TodoItem::Task(Task::new(title, status)));
+                    open.push(indent);
+                }
+                (None, true) => innermost(&mut items, open.len())
+                    .notes
+                    .push(line.trim().to_string()),
+                (None, false) => items.push(TodoItem::Text(line.trim_end().to_string())),
+            }
+        }
+
+        let mut list = Self { items };
+        list.renumber();
+        list
+    }
+
+    /// The list in canonical markdown
+    pub fn to_markdown(&self) -> String {
+        let mut out = String::new();
+        for item in &self.items {
+            match item {
+                TodoItem::Text(text) => {
+                    out.push_str(text);
+                    out.push('\n');
+                }
+                TodoItem::Task(task) => write_task(&mut out, task, 0),
+            }
+        }
+        out
+    }
+
+    /// Every task and subtask, depth first
+    pub fn tasks(&self) -> Vec<&Task> {
+        fn collect<'a>(task: &'a Task, tasks: &mut Vec<&'a Task>) {
+            tasks.push(task);
+            for subtask in &task.subtasks {
+                collect(subtask, tasks);
+            }
+        }
+        let mut tasks = Vec::new();
+        for item in &self.items {
+            if let TodoItem::Task(task) = item {
+                collect(task, &mut tasks);
+            }
+        }
+        tasks
+    }
+
+    pub fn task(&self, id: &str) -> Option<&Task> {
+        self.tasks().into_iter().find(|task| task.id == id)
+    }
+
+    pub fn stats(&self) -> TodoStats {
+        let tasks = self.tasks();
+        TodoStats {
+            total: tasks.len(),
+            done: tasks.iter().filter(|task| task.is_done()).count(),
+        }
+    }
+
+    /// Append a pending task, as a subtask of `parent` or at the end of the
+    /// list. Returns the new task's id.
+    pub fn add(&mut self, parent: Option<&str>, title: &str) -> Result<String> {
+        let task = Task::new(title.trim(), TaskStatus::Pending);
+        match parent {
+            Some(parent_id) => {
+                self.task_mut(parent_id)?.subtasks.push(task);
+                self.renumber();
+                let parent = self.task_mut(parent_id)?;
+                Ok(parent
+                    .subtasks
+                    .last()
+                    .map(|task| task.id.clone())
+                    .unwrap_or_default())
+            }
+            None => {
+                self.items.push(TodoItem::Task(task));
+                self.renumber();
+                Ok(self.top_level_count().to_string())
+            }
+        }
+    }
+
+    /// Mark a task and all of its subtasks done
+    pub fn complete(&mut self, id: &str) -> Result<()> {
+        fn complete_all(task: &mut Task) {
+            task.status = TaskStatus::Done;
+            task.subtasks.iter_mut().for_each(complete_all);
+        }
+        complete_all(self.task_mut(id)?);
+        Ok(())
+    }
+
+    /// Mark a task pending again
+    pub fn reopen(&mut self, id: &str) -> Result<()> {
+        self.task_mut(id)?.status = TaskStatus::Pending;
+        Ok(())
+    }
+
+    /// Move a task to `position` (0-based) among its siblings. Top-level
+    /// tasks move relative to each other; headings and text stay put.
+    pub fn reorder(&mut self, id: &str, position: usize) -> Result<()> {
+        match id.rsplit_once('.') {
+            Some((parent_id, _)) => {
+                let index = sibling_index(id)?;
+                let siblings = &mut self.task_mut(parent_id)?.subtasks;
+                if index >= siblings.len() {
+                    bail!("No task {}", id);
+                }
+                let task = siblings.remove(index);
+                siblings.insert(position.min(siblings.len()), task);
+            }
+            None => {
+                let index = sibling_index(id)?;
+                let slots: Vec<usize> = self
+                    .items
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, item)| matches!(item, TodoItem::Task(_)))
+                    .map(|(slot, _)| slot)
+                    .collect();
+                let &from = slots.get(index).ok_or_else(|| anyhow!("No task {}", id))?;
+                let position = position.min(slots.len() - 1);
+                let task = self.items.remove(from);
+                // Moving up, this is the slot of the task it goes before;
+                // moving down, slots after `from` shifted down by one, so it
+                // lands right after the task that held `position`
+                self.items.insert(slots[position], task);
+            }
+        }
+        self.renumber();
+        Ok(())
+    }
+
+    fn task_mut(&mut self, id: &str) -> Result<&mut Task> {
+        let mut indices = id.split('.').map(|part| {
+            part.parse::<usize>()
+                .ok()
+                .filter(|&n| n > 0)
+                .ok_or_else(|| anyhow!("Invalid task id '{}'", id))
+        });
+        let first = indices.next().transpose()?.unwrap_or(0);
+        let mut task = self
+            .items
+            .iter_mut()
+            .filter_map(|item| match item {
+                TodoItem::Task(task) => Some(task),
+                TodoItem::Text(_) => None,
+            })
+            .nth(first.saturating_sub(1))
+            .ok_or_else(|| anyhow!("No task {}", id))?;
+        for index in indices {
+            task = task
+                .subtasks
+                .get_mut(index? - 1)
+                .ok_or_else(|| anyhow!("No task {}", id))?;
+        }
+        Ok(task)
+    }
+
+    fn top_level_count(&self) -> usize {
+        self.items
+            .iter()
+            .filter(|item| matches!(item, TodoItem::Task(_)))
+            .count()
+    }
+
+    fn renumber(&mut self) {
+        fn renumber_task(task: &mut Task, id: String) {
+            for (i, subtask) in task.subtasks.iter_mut().enumerate() {
+                renumber_task(subtask, format!("{}.{}", id, i + 1));
+            }
+            task.id = id;
+        }
+        let tasks = self.items.iter_mut().filter_map(|item| match item {
+            TodoItem::Task(task) => Some(task),
+            TodoItem::Text(_) => None,
+        });
+        for (i, task) in tasks.enumerate() {
+            renumber_task(task, (i + 1).to_string());
+        }
+    }
+}
+
+/// Completion stats of a TODO file's content
+pub fn stats(markdown: &str) -> TodoStats {
+    TodoList::parse(markdown).stats()
+}
+
+fn write_task(out: &mut String, task: &Task, depth: usize) {
+    let checkbox = if task.is_done() { "[x]" } else { "[ ]" };
+    out.push_str(&format!(
+        "{}- {} {}\n",
+        INDENT.repeat(depth),
+        checkbox,
+        task.title
+    ));
+    for note in &task.notes {
+        out.push_str(&format!("{}{}\n", INDENT.repeat(depth + 1), note));
+    }
+    for subtask in &task.subtasks {
+        write_task(out, subtask, depth + 1);
+    }
+}
+
+/// The `depth`-th open task: the last task of the last item, then its last
+/// subtask, and so on
+fn innermost(items: &mut [TodoItem], depth: usize) -> &mut Task {
+    let mut task = match items.last_mut() {
+        Some(TodoItem::Task(task)) => task,
+        _ => unreachable!("open tasks always end the item list"),
+    };
+    for _ in 1..depth {
+        task = task
+            .subtasks
+            .last_mut()
+            .expect("every open task has its open subtask last");
+    }
+    task
+}
+
+/// Leading whitespace width, a tab counting as four spaces
+fn indentation(line: &str) -> usize {
+    line.chars()
+        .take_while(|c| c.is_whitespace())
+        .map(|c| if c == '\t' { 4 } else { 1 })
+        .sum()
+}
+
+/// A `- [ ] title` / `* [x] title` line as (status, title)
+fn parse_checkbox(trimmed: &str) -> Option<(TaskStatus, &str)> {
+    let rest = trimmed
+        .strip_prefix("- ")
+        .or_else(|| trimmed.strip_prefix("* "))
+        .or_else(|| trimmed.strip_prefix("+ "))?
+        .trim_start();
+    let status = match rest.get(..3)? {
+        "[ ]" => TaskStatus::Pending,
+        "[x]" | "[X]" => TaskStatus::Done,
+        _ => return None,
+    };
+    Some((status, rest[3..].trim()))
+}
+
+/// 0-based index of a task among its siblings, from the last part of its id
+fn sibling_index(id: &str) -> Result<usize> {
+    id.rsplit('.')
+        .next()
+        .and_then(|part| part.parse::<usize>().ok())
+        .filter(|&n| n > 0)
+        .map(|n| n - 1)
+        .ok_or_else(|| anyhow!("Invalid task id '{}'", id))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TODO: &str = "# Plan
+
+## Milestone 1
+- [x] Add config struct
+  Lives in g3-config
+  - [x] Parse TOML
+  - [ ] Validate fields
+- [ ] Wire into CLI
+
+
+## Milestone 2
+* [X] Write docs
+";
+
+    #[test]
+    fn test_parse_nesting_notes_and_ids() {
+        let list = TodoList::parse(TODO);
+        let ids: Vec<&str> = list.tasks().iter().map(|task| task.id.as_str()).collect();
+        assert_eq!(ids, vec!["1", "1.1", "1.2", "2", "3"]);
+
+        let config = list.task("1").unwrap();
+        assert_eq!(config.title, "Add config struct");
+        assert_eq!(config.notes, vec!["Lives in g3-config"]);
+        assert_eq!(config.subtasks.len(), 2);
+        assert_eq!(list.task("1.2").unwrap().status, TaskStatus::Pending);
+        assert!(list.task("3").unwrap().is_done());
+
+        let stats = list.stats();
+        assert_eq!(stats, TodoStats { total: 5, done: 3 });
+        assert_eq!(stats.pending(), 2);
+        assert!(!stats.all_done());
+        assert_eq!(stats.to_string(), "3/5 tasks done (60%)");
+    }
+
+    #[test]
+    fn test_serialization_is_canonical_and_stable() {
+        let markdown = TodoList::parse(TODO).to_markdown();
+        assert_eq!(
+            markdown,
+            "# Plan\n\n## Milestone 1\n- [x] Add config struct\n  Lives in g3-config\n  - [x] Parse TOML\n  - [ ] Validate fields\n- [ ] Wire into CLI\n\n## Milestone 2\n- [x] Write docs\n"
+        );
+        assert_eq!(TodoList::parse(&markdown).to_markdown(), markdown);
+    }
+
+    #[test]
+    fn test_add_complete_and_reorder() {
+        let mut list = TodoList::parse(TODO);
+
+        assert_eq!(list.add(Some("2"), "Add --config flag").unwrap(), "2.1");
+        assert_eq!(list.add(None, "Release").unwrap(), "4");
+        list.complete("1.2").unwrap();
+        list.complete("2").unwrap();
+        assert!(list.task("2.1").unwrap().is_done());
+
+        // Move "Release" to the front of the top-level tasks; headings stay put
+        list.reorder("4", 0).unwrap();
+        assert_eq!(list.task("1").unwrap().title, "Release");
+        assert_eq!(list.task("2").unwrap().title, "Add config struct");
+        assert!(list
+            .to_markdown()
+            .starts_with("# Plan\n\n## Milestone 1\n- [ ] Release\n"));
+
+        list.reorder("2.2", 0).unwrap();
+        assert_eq!(list.task("2.1").unwrap().title, "Validate fields");
+
+        list.reopen("3").unwrap();
+        assert_eq!(list.stats(), TodoStats { total: 7, done: 5 });
+        assert!(list.complete("9").is_err());
+        assert!(list.add(Some("x"), "Nope").is_err());
+    }
+
+    #[test]
+    fn test_stats_without_tasks() {
+        assert_eq!(stats("Just some text"), TodoStats::default());
+        assert!(!stats("").all_done());
+        assert!(stats("- [x] Done\n    - [X] Also done").all_done());
+    }
+}
@@ -0,0 +1,248 @@
+//! Validation of tool-call arguments against the tools' JSON Schemas.
+//!
+//! Every built-in tool declares an `input_schema` in its definition. Before a
+//! tool runs, its arguments are checked against that schema, so a malformed
+//! call is rejected with errors the model can act on instead of reaching a
+//! handler that has to guess what was meant. The agent sends the errors back
+//! for one corrected attempt; a second malformed call in a row is an error.
+//!
+//! Only the parts of JSON Schema the tool definitions use are supported:
+//! `type` (a name or a list of names), `properties`, `required`,
+//! `additionalProperties`, `items`, `enum`, `minimum` and `maximum`.
+
+use serde_json::Value;
+
+/// Check `value` against `schema`, returning one message per problem found
+pub fn validate(schema: &Value, value: &Value) -> Vec<String> {
+    let mut errors = Vec::new();
+    check(schema, value, "", &mut errors);
+    errors
+}
+
+/// The arguments of a call, with a missing (null) object treated as empty
+pub fn normalize_args(args: &Value) -> Value {
+    if args.is_null() {
+        Value::Object(Default::default())
+    } else {
+        args.clone()
+    }
+}
+
+fn check(schema: &Value, value: &Value, path: &str, errors: &mut Vec<String>) {
+    let Some(schema) = schema.as_object() else {
+        return;
+    };
+    let at = |path: &str| {
+        if path.is_empty() {
+            "arguments".to_string()
+        } else {
+            format!("`{}`", path)
+        }
+    };
+
+    if let Some(expected) = schema.get("type") {
+        let types: Vec<&str> = match expected {
+            Value::String(name) => vec![name.as_str()],
+            Value::Array(names) => names.iter().filter_map(Value::as_str).collect(),
+            _ => Vec::new(),
+        };
+        if !types.is_empty() && !types.iter().any(|name| has_type(value, name)) {
+            errors.push(format!(
+                "{}: expected {}, got {}",
+                at(path),
+                types.join(" or "),
+                type_name(value)
+            ));
+            return;
+        }
+    }
+
+    if let Some(allowed) = schema.get("enum").and_then(Value::as_array) {
+        if !allowed.contains(value) {
+            let allowed: Vec<String> = allowed.iter().map(Value::to_string).collect();
+            errors.push(format!(
+                "{}: {} is not one of {}",
+                at(path),
+                value,
+                allowed.join(", ")
+            ));
+        }
+    }
+
+    if let Some(number) = value.as_f64() {
+        if let Some(minimum) = schema.get("minimum").and_then(Value::as_f64) {
+            if number < minimum {
+                errors.push(format!("{}: must be at least {}", at(path), minimum));
+            }
+        }
+        if let Some(maximum) = schema.get("maximum").and_then(Value::as_f64) {
+            if number > maximum {
+                errors.push(format!("{}: must be at most {}", at(path), maximum));
+            }
+        }
+    }
+
+    if let Some(object) = value.as_object() {
+        let properties = schema.get("properties").and_then(Value::as_object);
+        if let Some(required) = schema.get("required").and_then(Value::as_array) {
+            for key in required.iter().filter_map(Value::as_str) {
+                if !object.contains_key(key) {
+                    errors.push(format!("{} is required", at(&join(path, key))));
+                }
+            }
+        }
+        for (key, item) in object {
+            let item_path = join(path, key);
+            match properties.and_then(|properties| properties.get(key)) {
+                Some(property) => check(property, item, &item_path, errors),
+                None => match schema.get("additionalProperties") {
+                    Some(Value::Bool(false)) => {
+                        errors.push(format!("{} is not a known argument", at(&item_path)))
+                    }
+                    Some(additional @ Value::Object(_)) => {
+                        check(additional, item, &item_path, errors)
+                    }
+                    _ => {}
+                },
+            }
+        }
+    }
+
+    if let (Some(items), Some(array)) = (schema.get("items"), value.as_array()) {
+        for (index, item) in array.iter().enumerate() {
+            check(items, item, &format!("{}[{}]", path, index), errors);
+        }
+    }
+}
+
+fn join(path: &str, key: &str) -> String {
+    if path.is_empty() {
+        key.to_string()
+    } else {
+        format!("{}.{}", path, key)
+    }
+}
+
+fn has_type(value: &Value, name: &str) -> bool {
+    match name {
+        "object" => value.is_object(),
+        "array" => value.is_array(),
+        "string" => value.is_string(),
+        "boolean" => value.is_boolean(),
+        "null" => value.is_null(),
+        "number" => value.is_number(),
+        "integer" => {
+            value.is_i64() || value.is_u64() || value.as_f64().is_some_and(|n| n.fract() == 0.0)
+        }
+        _ => true,
+    }
+}
+
+fn type_name(value: &Value) -> &'static str {
+    match value {
+        Value::Null => "null",
+        Value::Bool(_) => "boolean",
+        Value::Number(_) => "number",
+        Value::String(_) => "string",
+        Value::Array(_) => "array",
+        Value::Object(_) => "object",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn read_file_schema() -> Value {
+        json!({
+            "type": "object",
+            "properties": {
+                "file_path": { "type": "string" },
+                "start": { "type": "integer", "minimum": 0 },
+                "mode": { "type": "string", "enum": ["text", "ocr"] },
+                "lines": { "type": "array", "items": { "type": "integer" } }
+            },
+            "required": ["file_path"],
+            "additionalProperties": false
+        })
+    }
+
+    #[test]
+    fn test_valid_arguments_pass() {
+        let schema = read_file_schema();
+        assert!(validate(&schema, &json!({ "file_path": "src/main.rs" })).is_empty());
+        assert!(validate(
+            &schema,
+            &json!({ "file_path": "a", "start": 10, "mode": "ocr", "lines": [1, 2.0] })
+        )
+        .is_empty());
+    }
+
+    #[test]
+    fn test_reports_each_problem() {
+        let mut errors = validate(
+            &read_file_schema(),
+            &json!({ "path": "a", "start": "10", "mode": "binary", "lines": [1, "x"] }),
+        );
+        errors.sort();
+        assert_eq!(
+            errors,
+            vec![
+                "`file_path` is required",
+                "`lines[1]`: expected integer, got string",
+                "`mode`: \"binary\" is not one of \"text\", \"ocr\"",
+                "`path` is not a known argument",
+                "`start`: expected integer, got string",
+            ]
+        );
+        assert_eq!(
+            validate(
+                &read_file_schema(),
+                &json!({ "file_path": "a", "start": -1 })
+            ),
+            vec!["`start`: must be at least 0"]
+        );
+    }
+
+    #[test]
+    fn test_type_lists_and_nested_objects() {
+        let schema = json!({
+            "type": "object",
+            "properties": {
+                "health_check": {
+                    "type": "object",
+                    "properties": { "tcp_port": { "type": ["integer", "string"] } }
+                },
+                "env": { "type": "object", "additionalProperties": { "type": "string" } }
+            }
+        });
+        assert!(validate(
+            &schema,
+            &json!({ "health_check": { "tcp_port": "{PORT}" } })
+        )
+        .is_empty());
+        let mut errors = validate(
+            &schema,
+            &json!({ "health_check": { "tcp_port": true }, "env": { "A": 1 } }),
+        );
+        errors.sort();
+        assert_eq!(
+            errors,
+            vec![
+                "`env.A`: expected string, got number",
+                "`health_check.tcp_port`: expected integer or string, got boolean",
+            ]
+        );
+        assert_eq!(
+            validate(&schema, &json!("not an object")),
+            vec!["arguments: expected object, got string"]
+        );
+    }
+
+    #[test]
+    fn test_missing_arguments_are_an_empty_object() {
+        let schema = json!({ "type": "object", "properties": {}, "required": [] });
+        assert!(validate(&schema, &normalize_args(&Value::Null)).is_empty());
+    }
+}
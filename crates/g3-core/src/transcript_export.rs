@@ -0,0 +1,473 @@
+//! Shareable transcripts of a conversation, in Markdown or HTML.
+//!
+//! [`ContextWindow::export`] and `g3 export` turn the conversation history
+//! into a document that can be attached to a PR or a design doc: user and
+//! assistant messages in order, each tool call with its arguments and its
+//! output collapsed under a `<details>` toggle, and a header with token
+//! statistics. Exports of a journaled session (see [`export_session`]) also
+//! show how long each tool call took and whether it succeeded.
+//!
+//! The system prompt is collapsed like a tool output, and very long tool
+//! outputs are cut to [`MAX_OUTPUT_LINES`].
+
+use anyhow::{bail, Result};
+use g3_providers::MessageRole;
+use serde_json::Value;
+use std::fmt::Write;
+use std::str::FromStr;
+use std::time::Duration;
+
+use crate::paths::get_session_journal_file;
+use crate::session_journal::SessionJournal;
+use crate::ContextWindow;
+
+/// Tool output lines kept in an export
+pub const MAX_OUTPUT_LINES: usize = 200;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExportFormat {
+    Markdown,
+    Html,
+}
+
+impl ExportFormat {
+    pub fn extension(&self) -> &'static str {
+        match self {
+            ExportFormat::Markdown => "md",
+            ExportFormat::Html => "html",
+        }
+    }
+}
+
+impl FromStr for ExportFormat {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_ascii_lowercase().as_str() {
+            "md" | "markdown" => Ok(ExportFormat::Markdown),
+            "html" | "htm" => Ok(ExportFormat::Html),
+            _ => bail!("Unknown export format '{}'. Use markdown or html", s),
+        }
+    }
+}
+
+/// How one tool call went, matched to the history's tool calls in order
+#[derive(Debug, Clone)]
+pub struct ToolTiming {
+    pub tool: String,
+    pub duration: Duration,
+    pub success: bool,
+}
+
+/// One block of the transcript
+enum Entry<'a> {
+    System(&'a str),
+    User(&'a str),
+    Assistant(&'a str),
+    ToolCall {
+        tool: String,
+        args: String,
+        output: Option<&'a str>,
+        timing: Option<&'a ToolTiming>,
+    },
+}
+
+/// Export the journaled session `session_id`, with its tool timings
+pub fn export_session(session_id: &str, format: ExportFormat) -> Result<String> {
+    let (_, restored) = SessionJournal::restore(get_session_journal_file(session_id))?;
+    let timings: Vec<ToolTiming> = restored
+        .tool_calls()
+        .into_iter()
+        .map(|(tool, duration, success)| ToolTiming {
+            tool,
+            duration,
+            success,
+        })
+        .collect();
+
+    let mut context = ContextWindow::new(0);
+    context.conversation_history = restored.history;
+    context.used_tokens = restored.used_tokens;
+    context.cumulative_tokens = restored.cumulative_tokens;
+    Ok(render(&context, format, &timings, Some(session_id)))
+}
+
+/// Render the conversation in `context` as a transcript
+pub fn render(
+    context: &ContextWindow,
+    format: ExportFormat,
+    timings: &[ToolTiming],
+    session_id: Option<&str>,
+) -> String {
+    let entries = entries(context, timings);
+    let stats = stats(context, &entries);
+    let title = match session_id {
+        Some(session_id) => format!("G3 Session {}", session_id),
+        None => "G3 Session".to_string(),
+    };
+    match format {
+        ExportFormat::Markdown => render_markdown(&title, &stats, &entries),
+        ExportFormat::Html => render_html(&title, &stats, &entries),
+    }
+}
+
+fn entries<'a>(context: &'a ContextWindow, timings: &'a [ToolTiming]) -> Vec<Entry<'a>> {
+    let mut timings = timings.iter();
+    let mut entries = Vec::new();
+    for message in &context.conversation_history {
+        let content = message.content.as_str();
+        match message.role {
+            MessageRole::System => entries.push(Entry::System(content)),
+            MessageRole::User => match content.strip_prefix("Tool result:") {
+                Some(output) => {
+                    let output = output.trim();
+                    match entries.last_mut() {
+                        Some(Entry::ToolCall { output: slot, .. }) if slot.is_none() => {
+                            *slot = Some(output)
+                        }
+                        _ => entries.push(Entry::User(content)),
+                    }
+                }
+                None => entries.push(Entry::User(content)),
+            },
+            MessageRole::Assistant => {
+                let (text, call) = split_tool_call(content);
+                if !text.is_empty() {
+                    entries.push(Entry::Assistant(text));
+                }
+                if let Some((tool, args)) = call {
+                    // Timings are journaled in call order, so they pair up by tool name
+                    let timing = timings.find(|timing| timing.tool == tool);
+                    entries.push(Entry::ToolCall {
+                        tool,
+                        args,
+                        output: None,
+                        timing,
+                    });
+                }
+            }
+        }
+    }
+    entries
+}
+
+/// Split an assistant message into its prose and the trailing
+/// `{"tool": ..., "args": ...}` call, if it has one
+fn split_tool_call(content: &str) -> (&str, Option<(String, String)>) {
+    let Some(start) = content.rfind("{\"tool\"") else {
+        return (content.trim(), None);
+    };
+    let mut calls = serde_json::Deserializer::from_str(&content[start..]).into_iter::<Value>();
+    match calls.next() {
+        Some(Ok(call)) => match call.get("tool").and_then(Value::as_str) {
+            Some(tool) => {
+                let args = call.get("args").cloned().unwrap_or(Value::Null);
+                (
+                    content[..start].trim(),
+                    Some((tool.to_string(), args.to_string())),
+                )
+            }
+            None => (content.trim(), None),
+        },
+        _ => (content.trim(), None),
+    }
+}
+
+/// Header lines: (label, value)
+fn stats(context: &ContextWindow, entries: &[Entry]) -> Vec<(&'static str, String)> {
+    let messages = entries
+        .iter()
+        .filter(|entry| matches!(entry, Entry::User(_) | Entry::Assistant(_)))
+        .count();
+    let tool_calls = entries
+        .iter()
+        .filter(|entry| matches!(entry, Entry::ToolCall { .. }))
+        .count();
+    let tool_time: Duration = entries
+        .iter()
+        .filter_map(|entry| match entry {
+            Entry::ToolCall {
+                timing: Some(timing),
+                ..
+            } => Some(timing.duration),
+            _ => None,
+        })
+        .sum();
+
+    let context_usage = if context.total_tokens > 0 {
+        format!(
+            "{} / {} tokens ({:.1}%)",
+            context.used_tokens,
+            context.total_tokens,
+            context.percentage_used()
+        )
+    } else {
+        format!("{} tokens", context.used_tokens)
+    };
+    let mut stats = vec![
+        ("Messages", messages.to_string()),
+        ("Tool calls", tool_calls.to_string()),
+        ("Context", context_usage),
+        ("Tokens used", context.cumulative_tokens.to_string()),
+    ];
+    if !tool_time.is_zero() {
+        stats.push(("Tool time", format_duration(tool_time)));
+    }
+    stats
+}
+
+fn format_duration(duration: Duration) -> String {
+    if duration.as_secs() >= 1 {
+        format!("{:.1}s", duration.as_secs_f64())
+    } else {
+        format!("{}ms", duration.as_millis())
+    }
+}
+
+/// Tool output with long outputs cut, and the number of lines it had
+fn clip_output(output: &str) -> (String, usize) {
+    let total = output.lines().count();
+    if total <= MAX_OUTPUT_LINES {
+        return (output.to_string(), total);
+    }
+    let mut clipped: Vec<&str> = output.lines().take(MAX_OUTPUT_LINES).collect();
+    let note = format!("... ({} more lines)", total - MAX_OUTPUT_LINES);
+    clipped.push(&note);
+    (clipped.join("\n"), total)
+}
+
+fn timing_label(timing: Option<&ToolTiming>) -> String {
+    match timing {
+        Some(timing) => format!(
+            " · {} {}",
+            format_duration(timing.duration),
+            if timing.success { "✅" } else { "❌" }
+        ),
+        None => String::new(),
+    }
+}
+
+/// A code fence longer than any backtick run in `text`
+fn fence_for(text: &str) -> String {
+    let mut fence = "```".to_string();
+    while text.contains(&fence) {
+        fence.push('`');
+    }
+    fence
+}
+
+fn render_markdown(title: &str, stats: &[(&str, String)], entries: &[Entry]) -> String {
+    let mut out = format!("# {}\n\n", title);
+    out.push_str("| | |\n|---|---|\n");
+    for (label, value) in stats {
+        let _ = writeln!(out, "| {} | {} |", label, value);
+    }
+
+    for entry in entries {
+        out.push('\n');
+        match entry {
+            Entry::System(prompt) => {
+                let fence = fence_for(prompt);
+                let _ = write!(
+                    out,
+                    "<details><summary>System prompt</summary>\n\n{fence}\n{}\n{fence}\n\n</details>\n",
+                    prompt.trim()
+                );
+            }
+            Entry::User(text) => {
+                let _ = write!(out, "### 👤 User\n\n{}\n", text.trim());
+            }
+            Entry::Assistant(text) => {
+                let _ = write!(out, "### 🤖 Assistant\n\n{}\n", text.trim());
+            }
+            Entry::ToolCall {
+                tool,
+                args,
+                output,
+                timing,
+            } => {
+                let _ = writeln!(out, "**🔧 {}**{}\n", tool, timing_label(*timing));
+                let fence = fence_for(args);
+                let _ = writeln!(out, "{fence}json\n{}\n{fence}", args);
+                if let Some(output) = output {
+                    let (clipped, lines) = clip_output(output);
+                    let fence = fence_for(&clipped);
+                    let _ = write!(
+                        out,
+                        "\n<details><summary>Output ({} lines)</summary>\n\n{fence}\n{}\n{fence}\n\n</details>\n",
+                        lines, clipped
+                    );
+                }
+            }
+        }
+    }
+    out
+}
+
+fn escape_html(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+const HTML_STYLE: &str = "body{font-family:-apple-system,BlinkMacSystemFont,'Segoe UI',sans-serif;max-width:60rem;margin:2rem auto;padding:0 1rem;color:#1f2328}\
+table{border-collapse:collapse;margin-bottom:1.5rem}td{border:1px solid #d0d7de;padding:.25rem .75rem}\
+.message{border-left:4px solid #d0d7de;margin:1rem 0;padding:.25rem 1rem}.user{border-color:#0969da}.assistant{border-color:#1a7f37}\
+.role{font-weight:600;margin:.5rem 0}.tool{margin:1rem 0 1rem 1.25rem}\
+pre{white-space:pre-wrap;word-break:break-word;background:#f6f8fa;padding:.75rem;border-radius:6px;font-size:.85rem}\
+.text{background:none;padding:0;font-family:inherit;font-size:1rem}summary{cursor:pointer;color:#59636e}";
+
+fn render_html(title: &str, stats: &[(&str, String)], entries: &[Entry]) -> String {
+    let mut out = format!(
+        "<!DOCTYPE html>\n<html lang=\"en\">\n<head>\n<meta charset=\"utf-8\">\n<title>{}</title>\n<style>{}</style>\n</head>\n<body>\n<h1>{}</h1>\n<table>\n",
+        escape_html(title),
+        HTML_STYLE,
+        escape_html(title)
+    );
+    for (label, value) in stats {
+        let _ = writeln!(
+            out,
+            "<tr><td>{}</td><td>{}</td></tr>",
+            label,
+            escape_html(value)
+        );
+    }
+    out.push_str("</table>\n");
+
+    for entry in entries {
+        match entry {
+            Entry::System(prompt) => {
+                let _ = writeln!(
+                    out,
+                    "<details><summary>System prompt</summary><pre>{}</pre></details>",
+                    escape_html(prompt.trim())
+                );
+            }
+            Entry::User(text) => {
+                let _ = writeln!(
+                    out,
+                    "<div class=\"message user\"><div class=\"role\">👤 User</div><pre class=\"text\">{}</pre></div>",
+                    escape_html(text.trim())
+                );
+            }
+            Entry::Assistant(text) => {
+                let _ = writeln!(
+                    out,
+                    "<div class=\"message assistant\"><div class=\"role\">🤖 Assistant</div><pre class=\"text\">{}</pre></div>",
+                    escape_html(text.trim())
+                );
+            }
+            Entry::ToolCall {
+                tool,
+                args,
+                output,
+                timing,
+            } => {
+                let _ = write!(
+                    out,
+                    "<div class=\"tool\"><div class=\"role\">🔧 {}{}</div><pre>{}</pre>",
+                    escape_html(tool),
+                    escape_html(&timing_label(*timing)),
+                    escape_html(args)
+                );
+                if let Some(output) = output {
+                    let (clipped, lines) = clip_output(output);
+                    let _ = write!(
+                        out,
+                        "<details><summary>Output ({} lines)</summary><pre>{}</pre></details>",
+                        lines,
+                        escape_html(&clipped)
+                    );
+                }
+                out.push_str("</div>\n");
+            }
+        }
+    }
+    out.push_str("</body>\n</html>\n");
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use g3_providers::Message;
+
+    fn context() -> ContextWindow {
+        let mut context = ContextWindow::new(10_000);
+        context.add_message(Message::new(MessageRole::System, "You are G3".to_string()));
+        context.add_message(Message::new(
+            MessageRole::User,
+            "Task: what's in main.rs?".to_string(),
+        ));
+        context.add_message(Message::new(
+            MessageRole::Assistant,
+            r#"Let me look.
+
+{"tool": "read_file", "args": {"file_path": "src/main.rs"}}"#
+                .to_string(),
+        ));
+        context.add_message(Message::new(
+            MessageRole::User,
+            "Tool result: fn main() { println!(\"<hi>\"); }".to_string(),
+        ));
+        context.add_message(Message::new(
+            MessageRole::Assistant,
+            "It prints <hi>.".to_string(),
+        ));
+        context
+    }
+
+    #[test]
+    fn test_markdown_export() {
+        let timings = vec![ToolTiming {
+            tool: "read_file".to_string(),
+            duration: Duration::from_millis(12),
+            success: true,
+        }];
+        let markdown = render(&context(), ExportFormat::Markdown, &timings, Some("abc"));
+
+        assert!(markdown.starts_with("# G3 Session abc\n"));
+        assert!(markdown.contains("| Tool calls | 1 |"));
+        assert!(markdown.contains("| Tool time | 12ms |"));
+        assert!(markdown.contains("### 👤 User\n\nTask: what's in main.rs?"));
+        assert!(markdown.contains("### 🤖 Assistant\n\nLet me look.\n"));
+        assert!(markdown.contains("**🔧 read_file** · 12ms ✅"));
+        assert!(markdown.contains(r#"{"file_path":"src/main.rs"}"#));
+        assert!(markdown.contains("<details><summary>Output (1 lines)</summary>"));
+        assert!(markdown.contains("<details><summary>System prompt</summary>"));
+        // The tool call JSON isn't repeated as prose
+        assert!(!markdown.contains(r#"{"tool": "read_file""#));
+    }
+
+    #[test]
+    fn test_html_export_escapes_content() {
+        let html = context().export(ExportFormat::Html);
+        assert!(html.starts_with("<!DOCTYPE html>"));
+        assert!(html.contains("It prints &lt;hi&gt;."));
+        assert!(!html.contains("<hi>"));
+        assert!(html.contains("🔧 read_file</div>"));
+    }
+
+    #[test]
+    fn test_long_outputs_are_clipped() {
+        let output = (0..MAX_OUTPUT_LINES + 5)
+            .map(|i| i.to_string())
+            .collect::<Vec<_>>()
+            .join("\n");
+        let (clipped, lines) = clip_output(&output);
+        assert_eq!(lines, MAX_OUTPUT_LINES + 5);
+        assert!(clipped.ends_with("... (5 more lines)"));
+        assert_eq!(fence_for("has ``` inside"), "````");
+        assert_eq!("HTML".parse::<ExportFormat>().unwrap(), ExportFormat::Html);
+        assert!("pdf".parse::<ExportFormat>().is_err());
+    }
+}
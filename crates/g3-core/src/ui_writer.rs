@@ -1,3 +1,5 @@
+use crate::permissions::{ApprovalDecision, ApprovalRequest};
+
 /// Interface for UI output operations
 /// This trait abstracts all UI operations to allow different implementations
 /// (console, TUI, web, etc.) without coupling the core logic to specific output methods.
@@ -20,6 +22,12 @@ pub trait UiWriter: Send + Sync {
     /// Print a context thinning success message with highlight and animation
     fn print_context_thinning(&self, message: &str);
 
+    /// Print a system status notice, such as a switch to a fallback provider
+    /// Default implementation prints it as a context status message.
+    fn print_system_status(&self, message: &str) {
+        self.print_context_status(message);
+    }
+
     /// Print a tool execution header
     fn print_tool_header(&self, tool_name: &str, tool_args: Option<&serde_json::Value>);
 
@@ -66,6 +74,24 @@ pub trait UiWriter: Send + Sync {
     /// Returns the index of the selected option
     fn prompt_user_choice(&self, message: &str, options: &[&str]) -> usize;
 
+    /// Ask whether a tool call may run. UIs that can offer "always" and
+    /// "deny always" override this; the default asks a yes/no question.
+    fn prompt_tool_approval(&self, request: &ApprovalRequest) -> ApprovalDecision {
+        if self.prompt_user_yes_no(&request.prompt()) {
+            ApprovalDecision::Allow
+        } else {
+            ApprovalDecision::Deny
+        }
+    }
+
+    /// Show the changes a dry-run edit would make, as a unified diff.
+    /// The default prints it as plain tool output.
+    fn print_diff_preview(&self, diff: &str) {
+        for line in diff.lines() {
+            self.print_tool_output_line(line);
+        }
+    }
+
     /// Print the final output summary with markdown formatting
     /// Shows a spinner while formatting, then renders the markdown
     fn print_final_output(&self, summary: &str);
@@ -0,0 +1,383 @@
+//! Token usage accounting and cost ledger.
+//!
+//! Every completion is recorded as a [`UsageRecord`] holding its token counts
+//! and estimated cost, and appended as one JSON line to the ledger at
+//! `~/.g3/usage.jsonl` (see [`crate::paths::get_usage_ledger_path`]).
+//! [`UsageReport`] totals the ledger per provider and per session for the
+//! `g3 usage` command.
+
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use tracing::debug;
+
+/// USD per million input and output tokens. Entries are matched in order
+/// against the model name, so more specific names come first.
+const PRICES: &[(&str, f64, f64)] = &[
+    ("claude-opus-4-5", 5.0, 25.0),
+    ("claude-opus-4", 15.0, 75.0),
+    ("claude-3-opus", 15.0, 75.0),
+    ("claude-sonnet-4", 3.0, 15.0),
+    ("claude-3-7-sonnet", 3.0, 15.0),
+    ("claude-3-5-sonnet", 3.0, 15.0),
+    ("claude-haiku-4-5", 1.0, 5.0),
+    ("claude-3-5-haiku", 0.8, 4.0),
+    ("claude-3-haiku", 0.25, 1.25),
+    ("gpt-4o-mini", 0.15, 0.6),
+    ("gpt-4o", 2.5, 10.0),
+    ("gpt-4.1-nano", 0.1, 0.4),
+    ("gpt-4.1-mini", 0.4, 1.6),
+    ("gpt-4.1", 2.0, 8.0),
+    ("o4-mini", 1.1, 4.4),
+    ("o3-mini", 1.1, 4.4),
+    ("gemini-2.5-pro", 1.25, 10.0),
+    ("gemini-2.5-flash", 0.3, 2.5),
+    ("gemini-2.0-flash", 0.1, 0.4),
+    ("gemini-1.5-pro", 1.25, 5.0),
+    ("gemini-1.5-flash", 0.075, 0.3),
+    ("nova-pro", 0.8, 3.2),
+    ("nova-lite", 0.06, 0.24),
+    ("nova-micro", 0.035, 0.14),
+];
+
+/// Estimate the cost of a request in USD.
+///
+/// Local and embedded providers are free; models without a known price are
+/// counted as zero rather than guessed.
+pub fn estimate_cost(
+    provider: &str,
+    model: &str,
+    prompt_tokens: u32,
+    completion_tokens: u32,
+) -> f64 {
+    if provider.starts_with("local.") || provider.starts_with("embedded.") {
+        return 0.0;
+    }
+    let model = model.to_lowercase();
+    PRICES
+        .iter()
+        .find(|(name, _, _)| model.contains(name))
+        .map(|(_, input, output)| {
+            (prompt_tokens as f64 * input + completion_tokens as f64 * output) / 1_000_000.0
+        })
+        .unwrap_or(0.0)
+}
+
+/// One completion request as stored in the ledger
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct UsageRecord {
+    pub timestamp: DateTime<Utc>,
+    pub session_id: Option<String>,
+    /// Provider in "<provider_type>.<config_name>" format
+    pub provider: String,
+    pub model: String,
+    pub prompt_tokens: u32,
+    pub completion_tokens: u32,
+    pub cost_usd: f64,
+}
+
+impl UsageRecord {
+    /// Record a request made now, pricing it from the model name
+    pub fn new(
+        session_id: Option<String>,
+        provider: &str,
+        model: &str,
+        usage: &g3_providers::Usage,
+    ) -> Self {
+        Self {
+            timestamp: Utc::now(),
+            session_id,
+            provider: provider.to_string(),
+            model: model.to_string(),
+            prompt_tokens: usage.prompt_tokens,
+            completion_tokens: usage.completion_tokens,
+            cost_usd: estimate_cost(
+                provider,
+                model,
+                usage.prompt_tokens,
+                usage.completion_tokens,
+            ),
+        }
+    }
+}
+
+/// Running totals over a set of requests
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct UsageTotals {
+    pub requests: u64,
+    pub prompt_tokens: u64,
+    pub completion_tokens: u64,
+    pub cost_usd: f64,
+}
+
+impl UsageTotals {
+    pub fn add(&mut self, record: &UsageRecord) {
+        self.requests += 1;
+        self.prompt_tokens += record.prompt_tokens as u64;
+        self.completion_tokens += record.completion_tokens as u64;
+        self.cost_usd += record.cost_usd;
+    }
+
+    pub fn total_tokens(&self) -> u64 {
+        self.prompt_tokens + self.completion_tokens
+    }
+}
+
+/// Append-only JSONL file of [`UsageRecord`]s
+#[derive(Debug, Clone)]
+pub struct UsageLedger {
+    path: PathBuf,
+}
+
+impl UsageLedger {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self { path: path.into() }
+    }
+
+    /// The ledger at `~/.g3/usage.jsonl`, or `G3_USAGE_PATH` if set
+    pub fn at_default_path() -> Self {
+        Self::new(crate::paths::get_usage_ledger_path())
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn append(&self, record: &UsageRecord) -> Result<()> {
+        if let Some(parent) = self.path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let mut file = std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)
+            .with_context(|| format!("Failed to open usage ledger {}", self.path.display()))?;
+        writeln!(file, "{}", serde_json::to_string(record)?)?;
+        Ok(())
+    }
+
+    /// All records in the ledger; a missing ledger is empty and unreadable
+    /// lines are skipped
+    pub fn read(&self) -> Result<Vec<UsageRecord>> {
+        let contents = match std::fs::read_to_string(&self.path) {
+            Ok(contents) => contents,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => {
+                return Err(e).with_context(|| {
+                    format!("Failed to read usage ledger {}", self.path.display())
+                })
+            }
+        };
+
+        Ok(contents
+            .lines()
+            .filter(|line| !line.trim().is_empty())
+            .filter_map(|line| match serde_json::from_str(line) {
+                Ok(record) => Some(record),
+                Err(e) => {
+                    debug!("Skipping malformed usage record: {}", e);
+                    None
+                }
+            })
+            .collect())
+    }
+}
+
+/// Ledger totals overall, per provider and model, and per session
+#[derive(Debug, Clone, Default)]
+pub struct UsageReport {
+    pub since: Option<DateTime<Utc>>,
+    pub total: UsageTotals,
+    /// Keyed by (provider, model)
+    pub by_provider: BTreeMap<(String, String), UsageTotals>,
+    /// Keyed by session ID, with the time of the session's last request
+    pub by_session: BTreeMap<String, (DateTime<Utc>, UsageTotals)>,
+}
+
+impl UsageReport {
+    /// Number of sessions listed in the report, most recent first
+    const MAX_SESSIONS: usize = 10;
+
+    /// Total the records made at or after `since` (all of them if `None`)
+    pub fn from_records(records: &[UsageRecord], since: Option<DateTime<Utc>>) -> Self {
+        let mut report = Self {
+            since,
+            ..Self::default()
+        };
+        for record in records {
+            if since.is_some_and(|since| record.timestamp < since) {
+                continue;
+            }
+            report.total.add(record);
+            report
+                .by_provider
+                .entry((record.provider.clone(), record.model.clone()))
+                .or_default()
+                .add(record);
+            if let Some(session_id) = &record.session_id {
+                let (last_seen, totals) = report
+                    .by_session
+                    .entry(session_id.clone())
+                    .or_insert_with(|| (record.timestamp, UsageTotals::default()));
+                *last_seen = (*last_seen).max(record.timestamp);
+                totals.add(record);
+            }
+        }
+        report
+    }
+}
+
+impl fmt::Display for UsageReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.since {
+            Some(since) => writeln!(f, "📊 Token Usage since {}", since.format("%Y-%m-%d %H:%M"))?,
+            None => writeln!(f, "📊 Token Usage")?,
+        }
+        writeln!(f, "{}", "=".repeat(60))?;
+
+        if self.total.requests == 0 {
+            return writeln!(f, "\nNo usage recorded.");
+        }
+
+        writeln!(
+            f,
+            "\n💰 Total: {} requests, {} tokens ({} in / {} out), ${:.4}",
+            self.total.requests,
+            self.total.total_tokens(),
+            self.total.prompt_tokens,
+            self.total.completion_tokens,
+            self.total.cost_usd
+        )?;
+
+        writeln!(f, "\n🔌 By Provider:")?;
+        for ((provider, model), totals) in &self.by_provider {
+            writeln!(
+                f,
+                "   • {:<24} {:<32} {:>6} req {:>12} tok  ${:.4}",
+                provider,
+                model,
+                totals.requests,
+                totals.total_tokens(),
+                totals.cost_usd
+            )?;
+        }
+
+        if !self.by_session.is_empty() {
+            let mut sessions: Vec<_> = self.by_session.iter().collect();
+            sessions.sort_by_key(|(_, (last_used, _))| std::cmp::Reverse(*last_used));
+            writeln!(
+                f,
+                "\n🗂️  By Session (latest {} of {}):",
+                sessions.len().min(Self::MAX_SESSIONS),
+                sessions.len()
+            )?;
+            for (session_id, (last_seen, totals)) in sessions.into_iter().take(Self::MAX_SESSIONS) {
+                writeln!(
+                    f,
+                    "   • {:<40} {} {:>6} req {:>12} tok  ${:.4}",
+                    session_id,
+                    last_seen.format("%Y-%m-%d %H:%M"),
+                    totals.requests,
+                    totals.total_tokens(),
+                    totals.cost_usd
+                )?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+    use tempfile::TempDir;
+
+    fn record(session: &str, provider: &str, model: &str, day: u32) -> UsageRecord {
+        let usage = g3_providers::Usage {
+            prompt_tokens: 1_000_000,
+            completion_tokens: 100_000,
+            total_tokens: 1_100_000,
+        };
+        UsageRecord {
+            timestamp: Utc.with_ymd_and_hms(2026, 10, day, 12, 0, 0).unwrap(),
+            ..UsageRecord::new(Some(session.to_string()), provider, model, &usage)
+        }
+    }
+
+    #[test]
+    fn test_estimate_cost() {
+        // $3/M in, $15/M out
+        let cost = estimate_cost("anthropic.default", "claude-sonnet-4-5", 1_000_000, 100_000);
+        assert!((cost - 4.5).abs() < 1e-9);
+
+        // Bedrock and Databricks model IDs still match
+        assert!(
+            estimate_cost(
+                "bedrock.default",
+                "us.anthropic.claude-3-5-haiku-20241022-v1:0",
+                1000,
+                0
+            ) > 0.0
+        );
+        assert!(estimate_cost("databricks.default", "databricks-claude-sonnet-4", 1000, 0) > 0.0);
+
+        assert_eq!(estimate_cost("local.ollama", "gpt-4o", 1000, 1000), 0.0);
+        assert_eq!(
+            estimate_cost("openai.default", "unknown-model", 1000, 1000),
+            0.0
+        );
+    }
+
+    #[test]
+    fn test_ledger_round_trip() {
+        let temp_dir = TempDir::new().unwrap();
+        let ledger = UsageLedger::new(temp_dir.path().join("nested").join("usage.jsonl"));
+        assert!(ledger.read().unwrap().is_empty());
+
+        let first = record("s1", "anthropic.default", "claude-sonnet-4-5", 1);
+        let second = record("s2", "openai.default", "gpt-4o", 2);
+        ledger.append(&first).unwrap();
+        ledger.append(&second).unwrap();
+
+        // A torn write leaves a partial line, which is skipped
+        let mut file = std::fs::OpenOptions::new()
+            .append(true)
+            .open(ledger.path())
+            .unwrap();
+        write!(file, "{{\"timestamp\":").unwrap();
+
+        assert_eq!(ledger.read().unwrap(), vec![first, second]);
+    }
+
+    #[test]
+    fn test_report_groups_and_filters() {
+        let records = vec![
+            record("s1", "anthropic.default", "claude-sonnet-4-5", 1),
+            record("s1", "anthropic.default", "claude-sonnet-4-5", 3),
+            record("s2", "local.ollama", "qwen2.5-coder", 5),
+        ];
+
+        let report = UsageReport::from_records(&records, None);
+        assert_eq!(report.total.requests, 3);
+        assert_eq!(report.by_provider.len(), 2);
+        let (last_seen, s1) = &report.by_session["s1"];
+        assert_eq!(s1.requests, 2);
+        assert!((s1.cost_usd - 9.0).abs() < 1e-9);
+        assert_eq!(last_seen.format("%d").to_string(), "03");
+
+        let since = Utc.with_ymd_and_hms(2026, 10, 2, 0, 0, 0).unwrap();
+        let recent = UsageReport::from_records(&records, Some(since));
+        assert_eq!(recent.total.requests, 2);
+        assert_eq!(recent.total.total_tokens(), 2_200_000);
+
+        let text = report.to_string();
+        assert!(text.contains("3 requests"));
+        assert!(text.contains("local.ollama"));
+        assert!(text.contains("By Session (latest 2 of 2)"));
+    }
+}
@@ -1,15 +1,142 @@
-//! Utility functions for diff parsing, shell escaping, and JSON fixing.
+//! Utility functions for diff parsing and merging.
 //!
 //! This module contains helper functions used by the agent for:
-//! - Applying unified diffs to strings
-//! - Shell command escaping
-//! - JSON quote fixing
+//! - Applying unified diffs to strings (with optional fuzzy matching)
+//! - Generating unified diffs from two strings
+//! - Three-way merging of concurrent edits
+//! - Atomic file writes with optional backups
+//! - Telling binary files from text
+//!
+//! Repair of malformed JSON lives in [`crate::json_repair`] and shell command
+//! analysis in [`crate::shell_safety`].
 
 use anyhow::Result;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
 use tracing::debug;
 
+/// Default minimum similarity for a fuzzy hunk match.
+pub const DEFAULT_FUZZY_THRESHOLD: f64 = 0.8;
+
+/// Default number of lines around a hunk's `@@` line number that are searched.
+pub const DEFAULT_ANCHOR_TOLERANCE: usize = 100;
+
+/// Options controlling how a unified diff is applied.
+#[derive(Debug, Clone)]
+pub struct DiffApplyOptions {
+    /// Fall back to fuzzy line matching when a hunk's old block is not found verbatim
+    pub fuzzy: bool,
+    /// Minimum similarity (0.0-1.0) a region must reach to be fuzz-applied
+    pub similarity_threshold: f64,
+    /// How many lines away from the `@@` line number a hunk may match
+    pub anchor_tolerance: usize,
+    /// Search the whole region when an anchored hunk is not found near its line number
+    pub allow_global_search: bool,
+}
+
+impl Default for DiffApplyOptions {
+    fn default() -> Self {
+        Self {
+            fuzzy: true,
+            similarity_threshold: DEFAULT_FUZZY_THRESHOLD,
+            anchor_tolerance: DEFAULT_ANCHOR_TOLERANCE,
+            allow_global_search: false,
+        }
+    }
+}
+
+impl DiffApplyOptions {
+    /// Options that only accept verbatim matches.
+    pub fn exact() -> Self {
+        Self {
+            fuzzy: false,
+            ..Self::default()
+        }
+    }
+
+    /// Set a custom similarity threshold (clamped to 0.0-1.0)
+    pub fn with_similarity_threshold(mut self, threshold: f64) -> Self {
+        self.similarity_threshold = threshold.clamp(0.0, 1.0);
+        self
+    }
+
+    /// Allow hunks to match anywhere in the region when not found near their line number
+    pub fn with_global_search(mut self, allow: bool) -> Self {
+        self.allow_global_search = allow;
+        self
+    }
+}
+
+/// A single parsed hunk of a unified diff.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DiffHunk {
+    /// 1-based starting line in the old file, from the `@@ -a,b` header (if present)
+    pub old_start: Option<usize>,
+    /// Text to search for (context and removed lines)
+    pub old_block: String,
+    /// Replacement text (context and added lines)
+    pub new_block: String,
+    /// The kind of each line of the hunk, in diff order
+    pub ops: Vec<HunkOp>,
+}
+
+/// A line of a hunk: kept, removed or added
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HunkOp {
+    Context,
+    Removed,
+    Added,
+}
+
+/// A hunk that could only be applied through fuzzy matching.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FuzzyHunkMatch {
+    /// 1-based index of the hunk within the diff
+    pub hunk: usize,
+    /// 1-based line of the file where the match starts
+    pub line: usize,
+    /// Similarity score of the matched region (0.0-1.0)
+    pub similarity: f64,
+}
+
+/// Result of applying a unified diff.
+#[derive(Debug, Clone)]
+pub struct DiffApplyReport {
+    /// The modified content
+    pub content: String,
+    /// Hunks that were applied through fuzzy matching (empty if all matched exactly)
+    pub fuzzy_hunks: Vec<FuzzyHunkMatch>,
+}
+
+impl DiffApplyReport {
+    /// Human-readable summary of fuzz-applied hunks, if any.
+    pub fn fuzzy_summary(&self) -> Option<String> {
+        if self.fuzzy_hunks.is_empty() {
+            return None;
+        }
+        let parts: Vec<String> = self
+            .fuzzy_hunks
+            .iter()
+            .map(|m| {
+                format!(
+                    "hunk {} at line {} ({:.0}% similar)",
+                    m.hunk,
+                    m.line,
+                    m.similarity * 100.0
+                )
+            })
+            .collect();
+        Some(format!("fuzz-applied {}", parts.join(", ")))
+    }
+}
+
 /// Apply unified diff to an input string with optional [start, end) bounds.
 ///
+/// Only verbatim matches are accepted; use [`apply_unified_diff_with_options`]
+/// to enable the fuzzy fallback.
+///
 /// # Arguments
 /// * `file_content` - The original file content
 /// * `diff` - The unified diff to apply
@@ -24,8 +151,38 @@ pub fn apply_unified_diff_to_string(
     start_char: Option<usize>,
     end_char: Option<usize>,
 ) -> Result<String> {
+    apply_unified_diff_with_options(
+        file_content,
+        diff,
+        start_char,
+        end_char,
+        &DiffApplyOptions::exact(),
+    )
+    .map(|report| report.content)
+}
+
+/// Apply unified diff to an input string, falling back to fuzzy matching if enabled.
+///
+/// Hunks with `@@ -a,b` headers are anchored: the occurrence closest to the
+/// expected line (adjusted for earlier hunks) is used, and matches further than
+/// `options.anchor_tolerance` lines away are only accepted when
+/// `options.allow_global_search` is set. Hunks without line numbers search the
+/// whole region.
+///
+/// When a hunk's old block is not found verbatim, the region is scanned line by
+/// line for the window that best matches it, ignoring leading-whitespace
+/// differences and tolerating minor context mismatches. The best window is
+/// replaced if its similarity reaches `options.similarity_threshold`, and the
+/// hunk is recorded in [`DiffApplyReport::fuzzy_hunks`].
+pub fn apply_unified_diff_with_options(
+    file_content: &str,
+    diff: &str,
+    start_char: Option<usize>,
+    end_char: Option<usize>,
+    options: &DiffApplyOptions,
+) -> Result<DiffApplyReport> {
     // Parse full unified diff into hunks and apply sequentially.
-    let hunks = parse_unified_diff_hunks(diff);
+    let hunks = parse_diff_hunks(diff);
     if hunks.is_empty() {
         anyhow::bail!(
             "Invalid diff format. Expected unified diff with @@ hunks or +/- with context lines"
@@ -79,43 +236,87 @@ pub fn apply_unified_diff_to_string(
         .unwrap_or(content_norm.len());
 
     let mut region_content = content_norm[start_boundary..end_boundary].to_string();
+    let mut fuzzy_hunks = Vec::new();
+
+    // Line numbers in hunk headers refer to the whole file; shift them into the region
+    // and track how earlier hunks moved later ones.
+    let region_start_line = content_norm[..start_boundary].matches('\n').count() as isize;
+    let mut line_offset: isize = 0;
 
     // Apply hunks in order
-    for (idx, (old_block, new_block)) in hunks.iter().enumerate() {
+    for (idx, hunk) in hunks.iter().enumerate() {
+        let old_block = &hunk.old_block;
+        let new_block = &hunk.new_block;
         debug!(
-            "Applying hunk {}: old_len={}, new_len={}",
+            "Applying hunk {}: old_start={:?}, old_len={}, new_len={}",
             idx + 1,
+            hunk.old_start,
             old_block.len(),
             new_block.len()
         );
 
-        if let Some(pos) = region_content.find(old_block) {
-            let endpos = pos + old_block.len();
-            region_content.replace_range(pos..endpos, new_block);
-        } else {
-            // Not found; provide helpful diagnostics with a short preview
-            let preview_len = old_block.len().min(200);
-            let mut old_preview = old_block[..preview_len].to_string();
-            if old_block.len() > preview_len {
-                old_preview.push_str("...");
+        let anchor = hunk
+            .old_start
+            .filter(|_| !old_block.is_empty())
+            .map(|line| (line as isize - 1 + line_offset - region_start_line).max(0) as usize);
+
+        match locate_hunk(&region_content, old_block, anchor, options) {
+            Some(HunkMatch::Exact(pos)) => {
+                let endpos = pos + old_block.len();
+                region_content.replace_range(pos..endpos, new_block);
             }
+            Some(HunkMatch::Fuzzy { line, similarity }) => {
+                debug!(
+                    "Hunk {} fuzz-applied at line {} (similarity {:.2})",
+                    idx + 1,
+                    line + 1,
+                    similarity
+                );
+                region_content = replace_lines_fuzzy(&region_content, line, hunk);
+                fuzzy_hunks.push(FuzzyHunkMatch {
+                    hunk: idx + 1,
+                    line: (line as isize + 1 + region_start_line) as usize,
+                    similarity,
+                });
+            }
+            None => {
+                // Not found; provide helpful diagnostics with a short preview
+                let preview_len = old_block.len().min(200);
+                let mut old_preview = old_block[..preview_len].to_string();
+                if old_block.len() > preview_len {
+                    old_preview.push_str("...");
+                }
 
-            let range_note = if start_char.is_some() || end_char.is_some() {
-                format!(
-                    " (within character range {}:{})",
-                    start_boundary, end_boundary
-                )
-            } else {
-                String::new()
-            };
+                let range_note = if start_char.is_some() || end_char.is_some() {
+                    format!(
+                        " (within character range {}:{})",
+                        start_boundary, end_boundary
+                    )
+                } else {
+                    String::new()
+                };
 
-            anyhow::bail!(
-                "Pattern not found in file{}\nHunk {} failed. Searched for:\n{}",
-                range_note,
-                idx + 1,
-                old_preview
-            );
+                let anchor_note = match anchor {
+                    Some(line) if !options.allow_global_search => format!(
+                        " within {} lines of line {} (global search disabled)",
+                        options.anchor_tolerance,
+                        line as isize + 1 + region_start_line
+                    ),
+                    _ => String::new(),
+                };
+
+                anyhow::bail!(
+                    "Pattern not found in file{}{}\nHunk {} failed. Searched for:\n{}",
+                    range_note,
+                    anchor_note,
+                    idx + 1,
+                    old_preview
+                );
+            }
         }
+
+        line_offset +=
+            new_block.matches('\n').count() as isize - old_block.matches('\n').count() as isize;
     }
 
     // Reconstruct the full content with the modified region
@@ -123,16 +324,233 @@ pub fn apply_unified_diff_to_string(
     result.push_str(&content_norm[..start_boundary]);
     result.push_str(&region_content);
     result.push_str(&content_norm[end_boundary..]);
-    Ok(result)
+    Ok(DiffApplyReport {
+        content: result,
+        fuzzy_hunks,
+    })
+}
+
+/// Where a hunk's old block was found in the region.
+enum HunkMatch {
+    /// Verbatim match at a byte offset
+    Exact(usize),
+    /// Fuzzy match starting at a 0-based line
+    Fuzzy { line: usize, similarity: f64 },
+}
+
+/// Locate a hunk's old block, preferring verbatim matches near `anchor`.
+///
+/// Search order: exact near the anchor, fuzzy near the anchor, then (only when
+/// global search is allowed) exact and fuzzy over the whole region.
+fn locate_hunk(
+    region: &str,
+    old_block: &str,
+    anchor: Option<usize>,
+    options: &DiffApplyOptions,
+) -> Option<HunkMatch> {
+    let tolerance = options.anchor_tolerance;
+    if let Some(pos) = find_exact_match(region, old_block, anchor, tolerance) {
+        return Some(HunkMatch::Exact(pos));
+    }
+    if options.fuzzy {
+        if let Some((line, similarity)) = find_fuzzy_match(
+            region,
+            old_block,
+            options.similarity_threshold,
+            anchor,
+            tolerance,
+        ) {
+            return Some(HunkMatch::Fuzzy { line, similarity });
+        }
+    }
+    if anchor.is_some() && options.allow_global_search {
+        debug!("Hunk not found near its line number, falling back to global search");
+        return locate_hunk(region, old_block, None, options);
+    }
+    None
+}
+
+/// Find the byte offset of the verbatim occurrence of `old_block` closest to `anchor`.
+///
+/// Without an anchor, the first occurrence is returned. With an anchor, only
+/// occurrences starting within `tolerance` lines of it are considered.
+fn find_exact_match(
+    region: &str,
+    old_block: &str,
+    anchor: Option<usize>,
+    tolerance: usize,
+) -> Option<usize> {
+    let Some(anchor) = anchor else {
+        return region.find(old_block);
+    };
+
+    let mut best: Option<(usize, usize)> = None;
+    let mut line = 0;
+    let mut scanned = 0;
+    for (pos, _) in region.match_indices(old_block) {
+        line += region[scanned..pos].matches('\n').count();
+        scanned = pos;
+        let distance = line.abs_diff(anchor);
+        if distance <= tolerance && best.is_none_or(|(_, best_distance)| distance < best_distance) {
+            best = Some((pos, distance));
+        }
+    }
+    best.map(|(pos, _)| pos)
+}
+
+/// Find the line window in `region` that best matches `old_block`.
+///
+/// With an anchor, only windows starting within `tolerance` lines of it are
+/// considered and ties are broken by distance. Returns the 0-based starting
+/// line and similarity of the best window, or `None` if no window reaches
+/// `threshold`.
+fn find_fuzzy_match(
+    region: &str,
+    old_block: &str,
+    threshold: f64,
+    anchor: Option<usize>,
+    tolerance: usize,
+) -> Option<(usize, f64)> {
+    if old_block.trim().is_empty() {
+        return None;
+    }
+    let region_lines: Vec<&str> = region.split('\n').collect();
+    let old_lines: Vec<&str> = old_block.split('\n').collect();
+    if old_lines.len() > region_lines.len() {
+        return None;
+    }
+
+    let last_start = region_lines.len() - old_lines.len();
+    let (first, last) = match anchor {
+        Some(line) => (
+            line.saturating_sub(tolerance),
+            (line + tolerance).min(last_start),
+        ),
+        None => (0, last_start),
+    };
+    let distance = |start: usize| anchor.map_or(0, |line| start.abs_diff(line));
+
+    let mut best: Option<(usize, f64)> = None;
+    for start in first..=last {
+        let window = &region_lines[start..start + old_lines.len()];
+        let score = block_similarity(&old_lines, window);
+        let better = best.is_none_or(|(best_start, best_score)| {
+            score > best_score || (score == best_score && distance(start) < distance(best_start))
+        });
+        if score >= threshold && better {
+            best = Some((start, score));
+        }
+    }
+    best
+}
+
+/// Average per-line similarity of two equally sized line blocks.
+fn block_similarity(expected: &[&str], actual: &[&str]) -> f64 {
+    let total: f64 = expected
+        .iter()
+        .zip(actual)
+        .map(|(a, b)| line_similarity(a, b))
+        .sum();
+    total / expected.len() as f64
+}
+
+/// Similarity of two lines, ignoring leading and trailing whitespace.
+///
+/// Uses a character-level LCS ratio: `2 * lcs / (len_a + len_b)`.
+fn line_similarity(a: &str, b: &str) -> f64 {
+    let a: Vec<char> = a.trim().chars().collect();
+    let b: Vec<char> = b.trim().chars().collect();
+    if a == b {
+        return 1.0;
+    }
+    if a.is_empty() || b.is_empty() {
+        return 0.0;
+    }
+
+    let mut prev = vec![0usize; b.len() + 1];
+    let mut curr = vec![0usize; b.len() + 1];
+    for ca in &a {
+        for (j, cb) in b.iter().enumerate() {
+            curr[j + 1] = if ca == cb {
+                prev[j] + 1
+            } else {
+                prev[j + 1].max(curr[j])
+            };
+        }
+        std::mem::swap(&mut prev, &mut curr);
+    }
+    let lcs = prev[b.len()];
+    (2 * lcs) as f64 / (a.len() + b.len()) as f64
+}
+
+/// Apply a fuzzy-matched `hunk` to the lines of `region` from `start`.
+///
+/// Only the hunk's removed and added lines change the file: its context lines
+/// may differ slightly from the file's, so the file's own lines are kept for
+/// them. If the matched lines are indented differently than the hunk
+/// expected, the added lines are re-indented to follow the file.
+fn replace_lines_fuzzy(region: &str, start: usize, hunk: &DiffHunk) -> String {
+    let mut lines: Vec<String> = region.split('\n').map(str::to_string).collect();
+    let old_lines: Vec<&str> = hunk.old_block.split('\n').collect();
+    let end = start + old_lines.len();
+
+    // Derive the indentation shift from the first matched line whose indentation drifted
+    let indent_shift = old_lines
+        .iter()
+        .zip(&lines[start..end])
+        .map(|(old, actual)| (*old, leading_whitespace(old), leading_whitespace(actual)))
+        .find(|(old, expected, found)| !old.trim().is_empty() && expected != found)
+        .map(|(_, expected, found)| (expected.to_string(), found.to_string()));
+
+    let reindent = |line: &str| match &indent_shift {
+        Some((expected, found)) => match line.strip_prefix(expected.as_str()) {
+            Some(rest) if !line.trim().is_empty() => format!("{}{}", found, rest),
+            _ => line.to_string(),
+        },
+        None => line.to_string(),
+    };
+
+    let mut matched = lines[start..end].iter();
+    let mut new_lines = hunk.new_block.split('\n');
+    let mut replacement = Vec::new();
+    for op in &hunk.ops {
+        match op {
+            HunkOp::Context => {
+                replacement.extend(matched.next().cloned());
+                new_lines.next();
+            }
+            HunkOp::Removed => {
+                matched.next();
+            }
+            HunkOp::Added => replacement.extend(new_lines.next().map(&reindent)),
+        }
+    }
+
+    lines.splice(start..end, replacement);
+    lines.join("\n")
+}
+
+fn leading_whitespace(line: &str) -> &str {
+    &line[..line.len() - line.trim_start().len()]
 }
 
 /// Parse a unified diff into a list of hunks as (old_block, new_block).
 /// Each hunk contains the exact text to search for and the replacement text including context lines.
 pub fn parse_unified_diff_hunks(diff: &str) -> Vec<(String, String)> {
-    let mut hunks: Vec<(String, String)> = Vec::new();
+    parse_diff_hunks(diff)
+        .into_iter()
+        .map(|hunk| (hunk.old_block, hunk.new_block))
+        .collect()
+}
+
+/// Parse a unified diff into hunks, keeping the old-file line number from each `@@` header.
+pub fn parse_diff_hunks(diff: &str) -> Vec<DiffHunk> {
+    let mut hunks: Vec<DiffHunk> = Vec::new();
 
     let mut old_lines: Vec<String> = Vec::new();
     let mut new_lines: Vec<String> = Vec::new();
+    let mut ops: Vec<HunkOp> = Vec::new();
+    let mut old_start: Option<usize> = None;
     let mut in_hunk = false;
 
     for raw_line in diff.lines() {
@@ -155,10 +573,16 @@ pub fn parse_unified_diff_hunks(diff: &str) -> Vec<(String, String)> {
         if line.starts_with("@@") {
             // Starting a new hunk — flush previous if present
             if in_hunk && (!old_lines.is_empty() || !new_lines.is_empty()) {
-                hunks.push((old_lines.join("\n"), new_lines.join("\n")));
+                hunks.push(DiffHunk {
+                    old_start,
+                    old_block: old_lines.join("\n"),
+                    new_block: new_lines.join("\n"),
+                    ops: std::mem::take(&mut ops),
+                });
                 old_lines.clear();
                 new_lines.clear();
             }
+            old_start = parse_hunk_header_old_start(line);
             in_hunk = true;
             continue;
         }
@@ -178,10 +602,13 @@ pub fn parse_unified_diff_hunks(diff: &str) -> Vec<(String, String)> {
         if let Some(content) = line.strip_prefix(' ') {
             old_lines.push(content.to_string());
             new_lines.push(content.to_string());
+            ops.push(HunkOp::Context);
         } else if line.starts_with('+') && !line.starts_with("+++") {
             new_lines.push(line[1..].to_string());
+            ops.push(HunkOp::Added);
         } else if line.starts_with('-') && !line.starts_with("---") {
             old_lines.push(line[1..].to_string());
+            ops.push(HunkOp::Removed);
         } else if line.starts_with('\\') {
             // Example: "\\ No newline at end of file" — ignore
             continue;
@@ -191,194 +618,450 @@ pub fn parse_unified_diff_hunks(diff: &str) -> Vec<(String, String)> {
     }
 
     if in_hunk && (!old_lines.is_empty() || !new_lines.is_empty()) {
-        hunks.push((old_lines.join("\n"), new_lines.join("\n")));
+        hunks.push(DiffHunk {
+            old_start,
+            old_block: old_lines.join("\n"),
+            new_block: new_lines.join("\n"),
+            ops,
+        });
     }
 
     hunks
 }
 
-/// Helper function to properly escape shell commands.
-/// Handles file paths with spaces and other special characters.
-#[allow(dead_code)]
-pub fn shell_escape_command(command: &str) -> String {
-    let parts: Vec<&str> = command.split_whitespace().collect();
-    if parts.is_empty() {
-        return command.to_string();
-    }
+/// Extract the old-file start line from a header like `@@ -12,5 +12,6 @@`.
+///
+/// Returns `None` for headers without line numbers (e.g. a bare `@@`) or a
+/// start of 0 (used for new files).
+fn parse_hunk_header_old_start(header: &str) -> Option<usize> {
+    let range = header
+        .trim_start_matches('@')
+        .split_whitespace()
+        .find_map(|part| part.strip_prefix('-'))?;
+    let start = range.split(',').next()?.parse::<usize>().ok()?;
+    (start > 0).then_some(start)
+}
 
-    let cmd = parts[0];
+/// Default number of context lines in generated diffs.
+pub const DEFAULT_DIFF_CONTEXT: usize = 3;
 
-    // Commands that typically take file paths as arguments
-    let file_commands = [
-        "cat", "ls", "cp", "mv", "rm", "chmod", "chown", "file", "head", "tail", "wc", "grep",
-    ];
+/// Generate a unified diff turning `old` into `new`, with `context` lines around each change.
+///
+/// Returns an empty string when the inputs are identical. The output uses
+/// `--- old` / `+++ new` headers and can be applied with
+/// [`apply_unified_diff_to_string`].
+pub fn generate_unified_diff(old: &str, new: &str, context: usize) -> String {
+    generate_unified_diff_with_labels(old, new, context, "old", "new")
+}
+
+/// Same as [`generate_unified_diff`], with custom `---` / `+++` header labels (e.g. file paths).
+pub fn generate_unified_diff_with_labels(
+    old: &str,
+    new: &str,
+    context: usize,
+    old_label: &str,
+    new_label: &str,
+) -> String {
+    let old_lines: Vec<&str> = old.split_inclusive('\n').collect();
+    let new_lines: Vec<&str> = new.split_inclusive('\n').collect();
+    let ops = diff_ops(&old_lines, &new_lines);
+
+    let changes: Vec<usize> = ops
+        .iter()
+        .enumerate()
+        .filter(|(_, op)| !matches!(op, DiffOp::Equal(..)))
+        .map(|(idx, _)| idx)
+        .collect();
+    if changes.is_empty() {
+        return String::new();
+    }
 
-    if file_commands.contains(&cmd) {
-        // For file commands, we need to be smarter about escaping
-        // Check if the command already has proper quoting
-        if command.contains('"') || command.contains('\'') {
-            // Already has some quoting, use as-is
-            return command.to_string();
+    // Group changes whose context windows touch into hunks of op indices
+    let mut groups: Vec<(usize, usize)> = Vec::new();
+    for &idx in &changes {
+        let start = idx.saturating_sub(context);
+        let end = (idx + context + 1).min(ops.len());
+        match groups.last_mut() {
+            Some((_, last_end)) if start <= *last_end => *last_end = end,
+            _ => groups.push((start, end)),
         }
+    }
 
-        // Look for file paths that need escaping (contain spaces but aren't quoted)
-        let mut escaped_command = String::new();
-        let mut in_quotes = false;
-        let mut current_word = String::new();
-        let mut words = Vec::new();
-
-        for ch in command.chars() {
-            match ch {
-                ' ' if !in_quotes => {
-                    if !current_word.is_empty() {
-                        words.push(current_word.clone());
-                        current_word.clear();
-                    }
-                }
-                '"' => {
-                    in_quotes = !in_quotes;
-                    current_word.push(ch);
-                }
-                _ => {
-                    current_word.push(ch);
-                }
+    let mut out = format!("--- {}\n+++ {}\n", old_label, new_label);
+    for (start, end) in groups {
+        let hunk = &ops[start..end];
+        let (old_start, new_start) = op_position(&ops, start);
+        let old_count = hunk
+            .iter()
+            .filter(|op| !matches!(op, DiffOp::Insert(_)))
+            .count();
+        let new_count = hunk
+            .iter()
+            .filter(|op| !matches!(op, DiffOp::Delete(_)))
+            .count();
+
+        // An empty range is reported as starting at the line before it
+        let old_header = if old_count == 0 {
+            old_start
+        } else {
+            old_start + 1
+        };
+        let new_header = if new_count == 0 {
+            new_start
+        } else {
+            new_start + 1
+        };
+        out.push_str(&format!(
+            "@@ -{},{} +{},{} @@\n",
+            old_header, old_count, new_header, new_count
+        ));
+
+        for op in hunk {
+            let (prefix, line) = match *op {
+                DiffOp::Equal(i, _) => (' ', old_lines[i]),
+                DiffOp::Delete(i) => ('-', old_lines[i]),
+                DiffOp::Insert(j) => ('+', new_lines[j]),
+            };
+            out.push(prefix);
+            out.push_str(line);
+            if !line.ends_with('\n') {
+                out.push_str("\n\\ No newline at end of file\n");
             }
         }
+    }
+    out
+}
+
+/// A single line-level edit operation.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum DiffOp {
+    /// Line present in both (old index, new index)
+    Equal(usize, usize),
+    /// Line removed from old
+    Delete(usize),
+    /// Line added from new
+    Insert(usize),
+}
+
+/// Expand the LCS of two line sequences into a full edit script.
+fn diff_ops(old: &[&str], new: &[&str]) -> Vec<DiffOp> {
+    let mut ops = Vec::with_capacity(old.len().max(new.len()));
+    let (mut i, mut j) = (0, 0);
+    for (mi, mj) in diff_line_matches(old, new) {
+        ops.extend((i..mi).map(DiffOp::Delete));
+        ops.extend((j..mj).map(DiffOp::Insert));
+        ops.push(DiffOp::Equal(mi, mj));
+        i = mi + 1;
+        j = mj + 1;
+    }
+    ops.extend((i..old.len()).map(DiffOp::Delete));
+    ops.extend((j..new.len()).map(DiffOp::Insert));
+    ops
+}
+
+/// 0-based (old, new) line positions at the start of `ops[idx]`.
+fn op_position(ops: &[DiffOp], idx: usize) -> (usize, usize) {
+    ops[..idx].iter().fold((0, 0), |(old, new), op| match op {
+        DiffOp::Equal(..) => (old + 1, new + 1),
+        DiffOp::Delete(_) => (old + 1, new),
+        DiffOp::Insert(_) => (old, new + 1),
+    })
+}
 
-        if !current_word.is_empty() {
-            words.push(current_word);
+/// Conflict marker labels used by [`merge3_with_labels`].
+#[derive(Debug, Clone)]
+pub struct MergeLabels<'a> {
+    pub ours: &'a str,
+    pub base: &'a str,
+    pub theirs: &'a str,
+}
+
+impl Default for MergeLabels<'_> {
+    fn default() -> Self {
+        Self {
+            ours: "ours",
+            base: "base",
+            theirs: "theirs",
         }
+    }
+}
 
-        // Reconstruct the command with proper escaping
-        for (i, word) in words.iter().enumerate() {
-            if i > 0 {
-                escaped_command.push(' ');
-            }
+/// Result of a three-way merge.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MergeResult {
+    /// Merged content, including conflict markers for unresolved regions
+    pub content: String,
+    /// Number of conflicting regions
+    pub conflicts: usize,
+}
 
-            // If this word looks like a file path (contains / or ~) and has spaces, quote it
-            if word.contains('/') || word.starts_with('~') {
-                if word.contains(' ') && !word.starts_with('"') && !word.starts_with('\'') {
-                    escaped_command.push_str(&format!("\"{}\"", word));
-                } else {
-                    escaped_command.push_str(word);
-                }
-            } else {
-                escaped_command.push_str(word);
-            }
+impl MergeResult {
+    /// Whether the merge completed without conflicts
+    pub fn is_clean(&self) -> bool {
+        self.conflicts == 0
+    }
+}
+
+/// Merge two sets of edits (`ours`, `theirs`) made from a common `base`.
+///
+/// Uses diff3-style line merging: regions changed on only one side take that
+/// side, identical changes are taken once, and overlapping different changes
+/// are emitted as conflicts with `<<<<<<<` / `|||||||` / `=======` / `>>>>>>>` markers.
+pub fn merge3(base: &str, ours: &str, theirs: &str) -> MergeResult {
+    merge3_with_labels(base, ours, theirs, &MergeLabels::default())
+}
+
+/// Same as [`merge3`], with custom labels on the conflict markers.
+pub fn merge3_with_labels(
+    base: &str,
+    ours: &str,
+    theirs: &str,
+    labels: &MergeLabels<'_>,
+) -> MergeResult {
+    let base_lines: Vec<&str> = base.split_inclusive('\n').collect();
+    let our_lines: Vec<&str> = ours.split_inclusive('\n').collect();
+    let their_lines: Vec<&str> = theirs.split_inclusive('\n').collect();
+
+    // Map each base line to its matching line on either side
+    let mut ours_at = vec![None; base_lines.len()];
+    for (b, o) in diff_line_matches(&base_lines, &our_lines) {
+        ours_at[b] = Some(o);
+    }
+    let mut theirs_at = vec![None; base_lines.len()];
+    for (b, t) in diff_line_matches(&base_lines, &their_lines) {
+        theirs_at[b] = Some(t);
+    }
+
+    let mut content = String::with_capacity(base.len().max(ours.len()).max(theirs.len()));
+    let mut conflicts = 0;
+    let (mut b, mut o, mut t) = (0, 0, 0);
+
+    while b < base_lines.len() || o < our_lines.len() || t < their_lines.len() {
+        // Stable line: unchanged on both sides
+        if b < base_lines.len() && ours_at[b] == Some(o) && theirs_at[b] == Some(t) {
+            content.push_str(base_lines[b]);
+            b += 1;
+            o += 1;
+            t += 1;
+            continue;
         }
 
-        escaped_command
-    } else {
-        // For non-file commands, use the original command
-        command.to_string()
-    }
-}
-
-/// Helper function to fix nested quotes in shell commands within JSON.
-#[allow(dead_code)]
-pub fn fix_nested_quotes_in_shell_command(json_str: &str) -> String {
-    // Look for the pattern: "command": "
-    if let Some(command_start) = json_str.find(r#""command": ""#) {
-        let command_value_start = command_start + r#""command": ""#.len();
-
-        // Find the end of the command string by looking for the pattern "}
-        if let Some(end_marker) = json_str[command_value_start..].find(r#"" }"#) {
-            let command_end = command_value_start + end_marker;
-
-            let before = &json_str[..command_value_start];
-            let command_content = &json_str[command_value_start..command_end];
-            let after = &json_str[command_end..];
-
-            // Fix the command content by properly escaping quotes
-            let mut fixed_command = String::new();
-            let mut chars = command_content.chars().peekable();
-
-            while let Some(ch) = chars.next() {
-                match ch {
-                    '"' => {
-                        // Check if this quote is already escaped
-                        if fixed_command.ends_with('\\') {
-                            fixed_command.push(ch); // Already escaped, keep as-is
-                        } else {
-                            fixed_command.push_str(r#"\""#); // Escape the quote
-                        }
-                    }
-                    '\\' => {
-                        // Check what follows the backslash
-                        if let Some(&next_ch) = chars.peek() {
-                            if next_ch == '"' {
-                                // This is an escaped quote, keep the backslash
-                                fixed_command.push(ch);
-                            } else {
-                                // Regular backslash, escape it
-                                fixed_command.push_str(r#"\\"#);
-                            }
-                        } else {
-                            // Backslash at end, escape it
-                            fixed_command.push_str(r#"\\"#);
-                        }
-                    }
-                    _ => fixed_command.push(ch),
-                }
-            }
+        // Find the next stable line (or the end of all inputs)
+        let (next_b, next_o, next_t) = (b..base_lines.len())
+            .find_map(|i| match (ours_at[i], theirs_at[i]) {
+                (Some(oi), Some(ti)) => Some((i, oi, ti)),
+                _ => None,
+            })
+            .unwrap_or((base_lines.len(), our_lines.len(), their_lines.len()));
+
+        let base_chunk = &base_lines[b..next_b];
+        let our_chunk = &our_lines[o..next_o];
+        let their_chunk = &their_lines[t..next_t];
 
-            return format!("{}{}{}", before, fixed_command, after);
+        if our_chunk == base_chunk || our_chunk == their_chunk {
+            their_chunk.iter().for_each(|line| content.push_str(line));
+        } else if their_chunk == base_chunk {
+            our_chunk.iter().for_each(|line| content.push_str(line));
+        } else {
+            conflicts += 1;
+            push_conflict_section(&mut content, "<<<<<<<", labels.ours, our_chunk);
+            push_conflict_section(&mut content, "|||||||", labels.base, base_chunk);
+            push_conflict_section(&mut content, "=======", "", their_chunk);
+            content.push_str(&format!(">>>>>>> {}\n", labels.theirs));
         }
+
+        b = next_b;
+        o = next_o;
+        t = next_t;
     }
 
-    // Fallback: if we can't parse the structure, return as-is
-    json_str.to_string()
+    MergeResult { content, conflicts }
 }
 
-/// Helper function to fix mixed quotes in JSON (single quotes where double quotes should be).
-#[allow(dead_code)]
-pub fn fix_mixed_quotes_in_json(json_str: &str) -> String {
-    let mut result = String::new();
-    let mut chars = json_str.chars().peekable();
-    let mut in_string = false;
-    let mut string_delimiter = '"';
+fn push_conflict_section(content: &mut String, marker: &str, label: &str, lines: &[&str]) {
+    if label.is_empty() {
+        content.push_str(marker);
+    } else {
+        content.push_str(&format!("{} {}", marker, label));
+    }
+    content.push('\n');
+    for line in lines {
+        content.push_str(line);
+    }
+    if lines.last().is_some_and(|line| !line.ends_with('\n')) {
+        content.push('\n');
+    }
+}
 
-    while let Some(ch) = chars.next() {
-        match ch {
-            '"' if !in_string => {
-                // Start of a double-quoted string
-                in_string = true;
-                string_delimiter = '"';
-                result.push(ch);
-            }
-            '\'' if !in_string => {
-                // Start of a single-quoted string - convert to double quotes
-                in_string = true;
-                string_delimiter = '\'';
-                result.push('"'); // Convert single quote to double quote
-            }
-            c if in_string && c == string_delimiter => {
-                // End of current string
-                if string_delimiter == '\'' {
-                    result.push('"'); // Convert single quote to double quote
-                } else {
-                    result.push(c);
-                }
-                in_string = false;
-            }
-            '"' if in_string && string_delimiter == '\'' => {
-                // Double quote inside single-quoted string - escape it
-                result.push_str(r#"\""#);
-            }
-            '\\' if in_string => {
-                // Escape sequence - preserve it
-                result.push(ch);
-                if chars.peek().is_some() {
-                    result.push(chars.next().unwrap());
-                }
+/// Compute the longest common subsequence of two line sequences (Myers' O(ND) algorithm).
+///
+/// Returns the matched `(a_index, b_index)` pairs in increasing order.
+pub fn diff_line_matches(a: &[&str], b: &[&str]) -> Vec<(usize, usize)> {
+    let n = a.len() as isize;
+    let m = b.len() as isize;
+    let max = (n + m) as usize;
+    let offset = max as isize + 1;
+    let mut v = vec![0isize; 2 * max + 3];
+    // Before each step d, the furthest x on diagonals -(d+1)..=d+1, all the
+    // backtracking reads of it: O(D^2) memory rather than O((N+M)D)
+    let mut trace: Vec<Vec<isize>> = Vec::new();
+
+    'search: for d in 0..=max as isize {
+        trace.push(v[(offset - d - 1) as usize..=(offset + d + 1) as usize].to_vec());
+        for k in (-d..=d).step_by(2) {
+            let idx = (k + offset) as usize;
+            let mut x = if k == -d || (k != d && v[idx - 1] < v[idx + 1]) {
+                v[idx + 1]
+            } else {
+                v[idx - 1] + 1
+            };
+            let mut y = x - k;
+            while x < n && y < m && a[x as usize] == b[y as usize] {
+                x += 1;
+                y += 1;
             }
-            _ => {
-                result.push(ch);
+            v[idx] = x;
+            if x >= n && y >= m {
+                break 'search;
             }
         }
     }
 
-    result
+    // Walk the trace backwards to recover the matched diagonals
+    let mut matches = Vec::new();
+    let (mut x, mut y) = (n, m);
+    for (d, v) in trace.iter().enumerate().rev() {
+        let d = d as isize;
+        let at = |k: isize| v[(k + d + 1) as usize];
+        let k = x - y;
+        let prev_k = if k == -d || (k != d && at(k - 1) < at(k + 1)) {
+            k + 1
+        } else {
+            k - 1
+        };
+        let prev_x = at(prev_k);
+        let prev_y = prev_x - prev_k;
+        while x > prev_x && y > prev_y {
+            x -= 1;
+            y -= 1;
+            matches.push((x as usize, y as usize));
+        }
+        if d > 0 {
+            x = prev_x;
+            y = prev_y;
+        }
+    }
+    matches.reverse();
+    matches
+}
+
+/// Leading bytes inspected by [`looks_binary`], as many as git looks at.
+pub const BINARY_SNIFF_BYTES: usize = 8000;
+
+/// Whether `bytes` look like binary data rather than text: like git, a NUL
+/// byte in the first [`BINARY_SNIFF_BYTES`] bytes.
+pub fn looks_binary(bytes: &[u8]) -> bool {
+    bytes[..bytes.len().min(BINARY_SNIFF_BYTES)].contains(&0)
+}
+
+/// Whether the file at `path` looks binary, reading only its first bytes.
+pub fn is_binary_file(path: impl AsRef<Path>) -> io::Result<bool> {
+    let mut head = Vec::with_capacity(BINARY_SNIFF_BYTES);
+    File::open(path)?
+        .take(BINARY_SNIFF_BYTES as u64)
+        .read_to_end(&mut head)?;
+    Ok(looks_binary(&head))
+}
+
+/// Suffix appended to a file's name for the backup kept by [`atomic_write_with_backup`].
+pub const BACKUP_SUFFIX: &str = ".g3.bak";
+
+/// Distinguishes temp files of concurrent writes within one process.
+static TEMP_FILE_COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+/// Atomically replace the contents of `path`.
+///
+/// The content is written to a temp file in the same directory, fsynced, and
+/// renamed into place, so readers (and crashes) only ever observe the old or
+/// the new content, never a half-written file.
+pub fn atomic_write(path: impl AsRef<Path>, content: impl AsRef<[u8]>) -> io::Result<()> {
+    atomic_write_with_backup(path, content, false).map(|_| ())
+}
+
+/// Same as [`atomic_write`], optionally copying the previous content to `<path>.g3.bak`.
+///
+/// Returns the backup path if one was written (i.e. `keep_backup` was set and
+/// the file already existed). Symlinks are followed so the link itself is
+/// preserved, and the existing file's permissions are kept.
+pub fn atomic_write_with_backup(
+    path: impl AsRef<Path>,
+    content: impl AsRef<[u8]>,
+    keep_backup: bool,
+) -> io::Result<Option<PathBuf>> {
+    let path = path.as_ref();
+    let target = match fs::symlink_metadata(path) {
+        Ok(meta) if meta.file_type().is_symlink() => fs::canonicalize(path)?,
+        _ => path.to_path_buf(),
+    };
+    let file_name = target.file_name().ok_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("'{}' has no file name", path.display()),
+        )
+    })?;
+    let dir = match target.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent.to_path_buf(),
+        _ => PathBuf::from("."),
+    };
+    let existing = fs::metadata(&target).ok();
+
+    let backup = match &existing {
+        Some(_) if keep_backup => {
+            let mut backup_name = file_name.to_os_string();
+            backup_name.push(BACKUP_SUFFIX);
+            let backup_path = dir.join(backup_name);
+            fs::copy(&target, &backup_path)?;
+            Some(backup_path)
+        }
+        _ => None,
+    };
+
+    let tmp_path = dir.join(format!(
+        ".{}.{}.{}.g3tmp",
+        file_name.to_string_lossy(),
+        std::process::id(),
+        TEMP_FILE_COUNTER.fetch_add(1, Ordering::Relaxed)
+    ));
+
+    let write_result = (|| {
+        let mut file = OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(&tmp_path)?;
+        file.write_all(content.as_ref())?;
+        file.sync_all()?;
+        if let Some(meta) = &existing {
+            fs::set_permissions(&tmp_path, meta.permissions())?;
+        }
+        fs::rename(&tmp_path, &target)
+    })();
+    if let Err(e) = write_result {
+        let _ = fs::remove_file(&tmp_path);
+        return Err(e);
+    }
+
+    // Persist the rename itself; not supported on all platforms, so best effort
+    if let Ok(dir_handle) = File::open(&dir) {
+        let _ = dir_handle.sync_all();
+    }
+
+    debug!(
+        "Atomically wrote {} ({} bytes, backup: {:?})",
+        target.display(),
+        content.as_ref().len(),
+        backup
+    );
+    Ok(backup)
 }
 
 #[cfg(test)]
@@ -426,15 +1109,289 @@ mod tests {
     }
 
     #[test]
-    fn shell_escape_preserves_simple_commands() {
-        assert_eq!(shell_escape_command("ls -la"), "ls -la");
-        assert_eq!(shell_escape_command("echo hello"), "echo hello");
+    fn exact_mode_rejects_whitespace_drift() {
+        let original = "fn main() {\n    let x = 1;\n    run(x);\n}\n";
+        let diff = "@@ -1,4 +1,4 @@\n fn main() {\n-  let x = 1;\n+  let x = 2;\n   run(x);\n }\n";
+        assert!(apply_unified_diff_to_string(original, diff, None, None).is_err());
+    }
+
+    #[test]
+    fn fuzzy_fallback_tolerates_leading_whitespace_and_reindents() {
+        let original = "fn main() {\n    let x = 1;\n    run(x);\n}\n";
+        let diff = "@@ -1,4 +1,4 @@\n fn main() {\n-  let x = 1;\n+  let x = 2;\n   run(x);\n }\n";
+        let report = apply_unified_diff_with_options(
+            original,
+            diff,
+            None,
+            None,
+            &DiffApplyOptions::default(),
+        )
+        .unwrap();
+        assert_eq!(
+            report.content,
+            "fn main() {\n    let x = 2;\n    run(x);\n}\n"
+        );
+        assert_eq!(report.fuzzy_hunks.len(), 1);
+        assert_eq!(report.fuzzy_hunks[0].hunk, 1);
+        assert_eq!(report.fuzzy_hunks[0].line, 1);
+        assert!(report.fuzzy_summary().unwrap().contains("hunk 1"));
+    }
+
+    #[test]
+    fn fuzzy_fallback_tolerates_minor_context_mismatch() {
+        let original = "a\nlet total = compute(items);\nold\nb\n";
+        let diff = "@@ -1,4 +1,4 @@\n a\n let total = compute(item);\n-old\n+new\n b\n";
+        let report = apply_unified_diff_with_options(
+            original,
+            diff,
+            None,
+            None,
+            &DiffApplyOptions::default(),
+        )
+        .unwrap();
+        // The file's own context line is kept
+        assert_eq!(report.content, "a\nlet total = compute(items);\nnew\nb\n");
+        assert_eq!(report.fuzzy_hunks.len(), 1);
+        assert!(report.fuzzy_hunks[0].similarity < 1.0);
+    }
+
+    #[test]
+    fn fuzzy_hunk_line_is_a_file_line() {
+        let original = "header\nskip\na\nlet total = compute(items);\nold\nb\n";
+        let diff = "@@ -3,4 +3,4 @@\n a\n let total = compute(item);\n-old\n+new\n b\n";
+        let start = original.find("a\n").unwrap();
+        let report = apply_unified_diff_with_options(
+            original,
+            diff,
+            Some(start),
+            None,
+            &DiffApplyOptions::default(),
+        )
+        .unwrap();
+        assert_eq!(
+            report.content,
+            "header\nskip\na\nlet total = compute(items);\nnew\nb\n"
+        );
+        assert_eq!(report.fuzzy_hunks[0].line, 3);
+    }
+
+    #[test]
+    fn fuzzy_fallback_respects_threshold() {
+        let original = "alpha\nbeta\ngamma\n";
+        let diff = "@@ -1,2 +1,2 @@\n completely\n-different\n+text\n";
+        let options = DiffApplyOptions::default().with_similarity_threshold(0.95);
+        assert!(apply_unified_diff_with_options(original, diff, None, None, &options).is_err());
+    }
+
+    #[test]
+    fn exact_matches_are_not_reported_as_fuzzy() {
+        let original = "one\ntwo\n";
+        let diff = "-two\n+three\n";
+        let report = apply_unified_diff_with_options(
+            original,
+            diff,
+            None,
+            None,
+            &DiffApplyOptions::default(),
+        )
+        .unwrap();
+        assert_eq!(report.content, "one\nthree\n");
+        assert!(report.fuzzy_hunks.is_empty());
+        assert!(report.fuzzy_summary().is_none());
+    }
+
+    #[test]
+    fn parses_hunk_header_line_numbers() {
+        let diff = "@@ -12,3 +12,3 @@ fn foo()\n a\n-b\n+c\n@@ -40 +40 @@\n-x\n+y\n@@\n-p\n+q\n";
+        let hunks = parse_diff_hunks(diff);
+        assert_eq!(hunks.len(), 3);
+        assert_eq!(hunks[0].old_start, Some(12));
+        assert_eq!(hunks[1].old_start, Some(40));
+        assert_eq!(hunks[2].old_start, None);
+    }
+
+    #[test]
+    fn anchored_hunk_matches_nearest_repeated_block() {
+        let mut original = String::new();
+        for i in 1..=10 {
+            original.push_str(&format!("line {}\nvalue = 0;\n", i));
+        }
+        // Line 9 is "line 5"; the hunk should patch the occurrence after it
+        let diff = "@@ -9,2 +9,2 @@\n line 5\n-value = 0;\n+value = 5;\n";
+        let result = apply_unified_diff_to_string(&original, diff, None, None).unwrap();
+        assert!(result.contains("line 5\nvalue = 5;\n"));
+        assert_eq!(result.matches("value = 5;").count(), 1);
+
+        let diff = "@@ -10,1 +10,1 @@\n-value = 0;\n+value = 5;\n";
+        let result = apply_unified_diff_to_string(&original, diff, None, None).unwrap();
+        assert!(result.contains("line 5\nvalue = 5;\nline 6"));
+    }
+
+    #[test]
+    fn anchored_hunks_account_for_earlier_line_shifts() {
+        let original = "a\nx\nb\nx\nc\nx\n";
+        // First hunk adds two lines, so the second hunk's "x" at old line 6 moves to line 8
+        let diff = "@@ -1,2 +1,4 @@\n a\n+new1\n+new2\n x\n@@ -6,1 +8,1 @@\n-x\n+y\n";
+        let result = apply_unified_diff_to_string(original, diff, None, None).unwrap();
+        assert_eq!(result, "a\nnew1\nnew2\nx\nb\nx\nc\ny\n");
+    }
+
+    #[test]
+    fn anchored_hunk_far_from_match_requires_global_search() {
+        let mut original = String::from("target\n");
+        for i in 0..300 {
+            original.push_str(&format!("filler {}\n", i));
+        }
+        let diff = "@@ -250,1 +250,1 @@\n-target\n+changed\n";
+        let err = apply_unified_diff_to_string(&original, diff, None, None).unwrap_err();
+        assert!(err.to_string().contains("global search disabled"));
+
+        let options = DiffApplyOptions::exact().with_global_search(true);
+        let report =
+            apply_unified_diff_with_options(&original, diff, None, None, &options).unwrap();
+        assert!(report.content.starts_with("changed\n"));
+    }
+
+    #[test]
+    fn atomic_write_creates_and_replaces_files() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let path = dir.path().join("file.txt");
+
+        atomic_write(&path, "first").unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "first");
+
+        let backup = atomic_write_with_backup(&path, "second", false).unwrap();
+        assert!(backup.is_none());
+        assert_eq!(fs::read_to_string(&path).unwrap(), "second");
+
+        // No temp files are left behind
+        let entries: Vec<_> = fs::read_dir(dir.path()).unwrap().collect();
+        assert_eq!(entries.len(), 1);
+    }
+
+    #[test]
+    fn atomic_write_keeps_backup_of_previous_content() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let path = dir.path().join("main.rs");
+
+        assert!(atomic_write_with_backup(&path, "v1", true)
+            .unwrap()
+            .is_none());
+        let backup = atomic_write_with_backup(&path, "v2", true)
+            .unwrap()
+            .unwrap();
+        assert_eq!(backup, dir.path().join("main.rs.g3.bak"));
+        assert_eq!(fs::read_to_string(&backup).unwrap(), "v1");
+        assert_eq!(fs::read_to_string(&path).unwrap(), "v2");
+    }
+
+    #[test]
+    fn atomic_write_fails_cleanly_for_missing_directory() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let path = dir.path().join("missing").join("file.txt");
+        assert!(atomic_write(&path, "content").is_err());
+        assert!(!path.exists());
+    }
+
+    #[test]
+    fn diff_line_matches_finds_lcs() {
+        let a = ["a", "b", "c", "a", "b", "b", "a"];
+        let b = ["c", "b", "a", "b", "a", "c"];
+        let matches = diff_line_matches(&a, &b);
+        assert_eq!(matches.len(), 4);
+        assert!(matches
+            .windows(2)
+            .all(|w| w[0].0 < w[1].0 && w[0].1 < w[1].1));
+        assert!(matches.iter().all(|&(i, j)| a[i] == b[j]));
+        assert!(diff_line_matches(&[], &["x"]).is_empty());
+    }
+
+    #[test]
+    fn generate_unified_diff_produces_canonical_hunks() {
+        let old = "a\nb\nc\nd\ne\nf\ng\nh\ni\nj\n";
+        let new = "a\nB\nc\nd\ne\nf\ng\nh\ni\nj\nk\n";
+        let diff = generate_unified_diff(old, new, 1);
+        assert_eq!(
+            diff,
+            "--- old\n+++ new\n@@ -1,3 +1,3 @@\n a\n-b\n+B\n c\n@@ -10,1 +10,2 @@\n j\n+k\n"
+        );
+        assert!(generate_unified_diff(old, old, 3).is_empty());
+    }
+
+    #[test]
+    fn generate_unified_diff_marks_missing_trailing_newline() {
+        let diff = generate_unified_diff_with_labels("x\ny", "x\nz", 3, "a/f", "b/f");
+        assert_eq!(
+            diff,
+            "--- a/f\n+++ b/f\n@@ -1,2 +1,2 @@\n x\n-y\n\\ No newline at end of file\n+z\n\\ No newline at end of file\n"
+        );
     }
 
     #[test]
-    fn fix_mixed_quotes_converts_single_to_double() {
-        let input = "{'key': 'value'}";
-        let result = fix_mixed_quotes_in_json(input);
-        assert_eq!(result, "{\"key\": \"value\"}");
+    fn generated_diff_round_trips_through_apply() {
+        let old = "fn a() {}\n\nfn b() {\n    1\n}\n\nfn c() {}\n\nfn c2() {}\n\nfn c3() {}\n\nfn d() {}\n\nfn e() {}\n";
+        let new = "fn a() {}\n\nfn b() {\n    2\n}\n\nfn c() {}\n\nfn c2() {}\n\nfn c3() {}\n\nfn d() {}\n\nfn e() {\n    todo()\n}\n";
+        let diff = generate_unified_diff(old, new, DEFAULT_DIFF_CONTEXT);
+        assert_eq!(diff.matches("@@ -").count(), 2);
+        let applied = apply_unified_diff_to_string(old, &diff, None, None).unwrap();
+        assert_eq!(applied, new);
+    }
+
+    #[test]
+    fn merge3_combines_non_overlapping_edits() {
+        let base = "one\ntwo\nthree\nfour\nfive\n";
+        let ours = "ONE\ntwo\nthree\nfour\nfive\n";
+        let theirs = "one\ntwo\nthree\nfour\nFIVE\nsix\n";
+        let result = merge3(base, ours, theirs);
+        assert!(result.is_clean());
+        assert_eq!(result.content, "ONE\ntwo\nthree\nfour\nFIVE\nsix\n");
+    }
+
+    #[test]
+    fn merge3_takes_identical_changes_once() {
+        let base = "a\nb\nc\n";
+        let edited = "a\nB\nc\n";
+        let result = merge3(base, edited, edited);
+        assert!(result.is_clean());
+        assert_eq!(result.content, edited);
+    }
+
+    #[test]
+    fn merge3_marks_conflicting_edits() {
+        let base = "a\nb\nc\n";
+        let ours = "a\nours\nc\n";
+        let theirs = "a\ntheirs\nc\n";
+        let result = merge3(base, ours, theirs);
+        assert_eq!(result.conflicts, 1);
+        assert_eq!(
+            result.content,
+            "a\n<<<<<<< ours\nours\n||||||| base\nb\n=======\ntheirs\n>>>>>>> theirs\nc\n"
+        );
+    }
+
+    #[test]
+    fn merge3_handles_deletion_against_unchanged_side() {
+        let base = "keep\ndrop\nkeep2\n";
+        let ours = "keep\nkeep2\n";
+        let result = merge3(base, ours, base);
+        assert!(result.is_clean());
+        assert_eq!(result.content, ours);
+    }
+
+    #[test]
+    fn detects_binary_files() {
+        assert!(!looks_binary(b"fn main() {}\n"));
+        assert!(!looks_binary("caf\u{e9}\n".as_bytes()));
+        assert!(looks_binary(b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR"));
+        // Only the first bytes are inspected
+        let mut late_nul = vec![b'a'; BINARY_SNIFF_BYTES];
+        late_nul.push(0);
+        assert!(!looks_binary(&late_nul));
+
+        let dir = tempfile::TempDir::new().unwrap();
+        let path = dir.path().join("blob.bin");
+        fs::write(&path, [0u8, 1, 2, 3]).unwrap();
+        assert!(is_binary_file(&path).unwrap());
+        assert!(is_binary_file(dir.path().join("missing")).is_err());
     }
 }
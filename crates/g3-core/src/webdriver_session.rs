@@ -3,7 +3,9 @@
 //! This module provides a unified interface for browser automation
 //! that can work with either Safari or Chrome WebDriver.
 
-use g3_computer_control::{ChromeDriver, SafariDriver, WebDriverController, WebElement};
+use g3_computer_control::{
+    ChromeDriver, ConsoleLogEntry, NetworkRequest, SafariDriver, WebDriverController, WebElement,
+};
 
 /// Unified WebDriver session that can hold either Safari or Chrome driver.
 pub enum WebDriverSession {
@@ -54,6 +56,35 @@ impl WebDriverController for WebDriverSession {
         }
     }
 
+    async fn find_in_shadow(
+        &mut self,
+        host_selector: &str,
+        inner_selector: &str,
+    ) -> anyhow::Result<WebElement> {
+        match self {
+            WebDriverSession::Safari(driver) => {
+                driver.find_in_shadow(host_selector, inner_selector).await
+            }
+            WebDriverSession::Chrome(driver) => {
+                driver.find_in_shadow(host_selector, inner_selector).await
+            }
+        }
+    }
+
+    async fn switch_to_frame(&mut self, selector: &str) -> anyhow::Result<()> {
+        match self {
+            WebDriverSession::Safari(driver) => driver.switch_to_frame(selector).await,
+            WebDriverSession::Chrome(driver) => driver.switch_to_frame(selector).await,
+        }
+    }
+
+    async fn switch_to_parent_frame(&mut self) -> anyhow::Result<()> {
+        match self {
+            WebDriverSession::Safari(driver) => driver.switch_to_parent_frame().await,
+            WebDriverSession::Chrome(driver) => driver.switch_to_parent_frame().await,
+        }
+    }
+
     async fn execute_script(
         &mut self,
         script: &str,
@@ -79,6 +110,20 @@ impl WebDriverController for WebDriverSession {
         }
     }
 
+    async fn screenshot_element(&mut self, selector: &str, path: &str) -> anyhow::Result<()> {
+        match self {
+            WebDriverSession::Safari(driver) => driver.screenshot_element(selector, path).await,
+            WebDriverSession::Chrome(driver) => driver.screenshot_element(selector, path).await,
+        }
+    }
+
+    async fn screenshot_full_page(&mut self, path: &str) -> anyhow::Result<()> {
+        match self {
+            WebDriverSession::Safari(driver) => driver.screenshot_full_page(path).await,
+            WebDriverSession::Chrome(driver) => driver.screenshot_full_page(path).await,
+        }
+    }
+
     async fn close(&mut self) -> anyhow::Result<()> {
         match self {
             WebDriverSession::Safari(driver) => driver.close().await,
@@ -116,6 +161,73 @@ impl WebDriverSession {
             WebDriverSession::Chrome(driver) => driver.refresh().await,
         }
     }
+
+    /// Browser console entries logged since the last call (Chrome only)
+    pub async fn console_logs(&mut self) -> anyhow::Result<Vec<ConsoleLogEntry>> {
+        match self {
+            WebDriverSession::Safari(_) => {
+                anyhow::bail!("Console log collection is only supported with Chrome")
+            }
+            WebDriverSession::Chrome(driver) => driver.get_console_logs().await,
+        }
+    }
+
+    /// Start recording the page's network requests (Chrome only)
+    pub async fn start_network_recording(&mut self) -> anyhow::Result<()> {
+        match self {
+            WebDriverSession::Safari(_) => {
+                anyhow::bail!("Network recording is only supported with Chrome")
+            }
+            WebDriverSession::Chrome(driver) => driver.network().start_recording().await,
+        }
+    }
+
+    /// Requests recorded since `start_network_recording` (Chrome only)
+    pub async fn network_requests(&mut self) -> anyhow::Result<Vec<NetworkRequest>> {
+        match self {
+            WebDriverSession::Safari(_) => {
+                anyhow::bail!("Network recording is only supported with Chrome")
+            }
+            WebDriverSession::Chrome(driver) => driver.network().get_requests().await,
+        }
+    }
+}
+
+/// One line per console entry: level, source and message
+pub fn format_console_logs(entries: &[ConsoleLogEntry]) -> String {
+    entries
+        .iter()
+        .map(|entry| match &entry.source {
+            Some(source) => format!("[{}] ({}) {}", entry.level, source, entry.message),
+            None => format!("[{}] {}", entry.level, entry.message),
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// One line per request: method, status, URL, duration and body size
+pub fn format_network_requests(requests: &[NetworkRequest]) -> String {
+    requests
+        .iter()
+        .map(|request| {
+            let status = match (&request.error, request.status) {
+                (Some(error), _) => error.clone(),
+                (None, Some(status)) => status.to_string(),
+                (None, None) if request.finished => "-".to_string(),
+                (None, None) => "pending".to_string(),
+            };
+            let mut line = format!("{} {} {}", request.method, status, request.url);
+            if let Some(duration) = request.duration {
+                line.push_str(&format!(" ({} ms", duration.as_millis()));
+                if request.body_size > 0 {
+                    line.push_str(&format!(", {} bytes", request.body_size));
+                }
+                line.push(')');
+            }
+            line
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
 }
 
 #[cfg(test)]
@@ -130,4 +242,59 @@ mod tests {
         fn _assert_sync<T: Sync>() {}
         // WebDriverSession should be Send but not necessarily Sync due to internal state
     }
+
+    #[test]
+    fn test_format_console_logs() {
+        let entry = |level: &str, source: Option<&str>, message: &str| ConsoleLogEntry {
+            level: level.to_string(),
+            message: message.to_string(),
+            source: source.map(str::to_string),
+            timestamp: std::time::UNIX_EPOCH,
+        };
+        assert_eq!(
+            format_console_logs(&[
+                entry("SEVERE", Some("javascript"), "Uncaught TypeError: x is undefined"),
+                entry("INFO", None, "loaded"),
+            ]),
+            "[SEVERE] (javascript) Uncaught TypeError: x is undefined\n[INFO] loaded"
+        );
+    }
+
+    #[test]
+    fn test_format_network_requests() {
+        let request = |method: &str, url: &str| NetworkRequest {
+            id: "1".to_string(),
+            method: method.to_string(),
+            url: url.to_string(),
+            resource_type: None,
+            status: None,
+            mime_type: None,
+            started_at: None,
+            duration: None,
+            body_size: 0,
+            error: None,
+            finished: false,
+        };
+        let done = NetworkRequest {
+            status: Some(200),
+            duration: Some(std::time::Duration::from_millis(42)),
+            body_size: 512,
+            finished: true,
+            ..request("GET", "https://example.com/api")
+        };
+        let failed = NetworkRequest {
+            error: Some("net::ERR_FAILED".to_string()),
+            duration: Some(std::time::Duration::from_millis(3)),
+            finished: true,
+            ..request("POST", "https://example.com/save")
+        };
+        let pending = request("GET", "https://example.com/slow");
+
+        assert_eq!(
+            format_network_requests(&[done, failed, pending]),
+            "GET 200 https://example.com/api (42 ms, 512 bytes)\n\
+             POST net::ERR_FAILED https://example.com/save (3 ms)\n\
+             GET pending https://example.com/slow"
+        );
+    }
 }
@@ -543,3 +543,19 @@ fn test_content_with_phrases_in_value_accepted() {
     ));
     assert_eq!(tools.len(), 1);
 }
+
+// =============================================================================
+// Test: Malformed JSON repair
+// =============================================================================
+
+#[test]
+fn test_tool_call_with_trailing_comma_and_raw_newline_is_repaired() {
+    let mut parser = StreamingToolParser::new();
+
+    let content = "{\"tool\": \"write_file\", \"args\": {\"file_path\": \"a.txt\", \"content\": \"line1\nline2\",}}";
+    let tools = parser.process_chunk(&chunk(content, false));
+
+    assert_eq!(tools.len(), 1, "Repaired tool call should be detected");
+    assert_eq!(tools[0].tool, "write_file");
+    assert_eq!(tools[0].args["content"], "line1\nline2");
+}
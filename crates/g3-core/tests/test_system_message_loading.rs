@@ -261,3 +261,45 @@ fn test_readme_detection_without_readme() {
 
     assert!(!has_readme, "Should not detect README when none exists");
 }
+
+/// Test that a reloaded README replaces the message at index 1 in place
+#[test]
+fn test_replace_readme_keeps_position() {
+    let mut context = ContextWindow::new(10000);
+    context.add_message(Message::new(
+        MessageRole::System,
+        "You are G3, an AI programming agent...".to_string(),
+    ));
+    context.add_message(Message::new(
+        MessageRole::System,
+        "# Project README\n\nOld description.".to_string(),
+    ));
+    context.add_message(Message::new(MessageRole::User, "Task: hello".to_string()));
+    let readme_id = context.conversation_history[1].id.clone();
+
+    assert!(context.replace_readme("# Project README\n\nNew description.".to_string()));
+
+    assert_eq!(context.conversation_history.len(), 3);
+    assert_eq!(context.conversation_history[1].id, readme_id);
+    assert!(context.conversation_history[1]
+        .content
+        .contains("New description."));
+    assert!(matches!(
+        context.conversation_history[2].role,
+        MessageRole::User
+    ));
+}
+
+/// Test that replace_readme leaves a history without a README untouched
+#[test]
+fn test_replace_readme_without_readme() {
+    let mut context = ContextWindow::new(10000);
+    context.add_message(Message::new(
+        MessageRole::System,
+        "You are G3, an AI programming agent...".to_string(),
+    ));
+    context.add_message(Message::new(MessageRole::User, "Task: hello".to_string()));
+
+    assert!(!context.replace_readme("# Project README\n\nNew.".to_string()));
+    assert_eq!(context.conversation_history[1].content, "Task: hello");
+}
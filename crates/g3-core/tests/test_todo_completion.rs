@@ -1,13 +1,8 @@
 //! Tests for TODO completion detection and file deletion behavior
 
-/// Helper to check if all TODOs are complete (same logic as in lib.rs)
+/// Helper to check if all TODOs are complete (the check todo_write uses)
 fn all_todos_complete(content: &str) -> bool {
-    let has_incomplete = content.lines().any(|line| {
-        let trimmed = line.trim();
-        trimmed.starts_with("- [ ]")
-    });
-    
-    !has_incomplete && (content.contains("- [x]") || content.contains("- [X]"))
+    g3_core::todo::stats(content).all_done()
 }
 
 #[test]
@@ -1,5 +1,6 @@
 use g3_core::ContextWindow;
-use g3_providers::{Message, MessageRole, Usage};
+use g3_providers::{Message, MessageRole, TokenCounter, Usage};
+use std::sync::Arc;
 
 /// Test that used_tokens is tracked via add_message, not update_usage_from_response.
 /// This is critical for the 80% summarization threshold to work correctly.
@@ -168,3 +169,49 @@ fn test_cumulative_vs_used_independence() {
     // They should now be different
     assert!(window.cumulative_tokens > window.used_tokens, "cumulative should be greater than used");
 }
+
+/// One token per word, to tell counted tokens from estimated ones
+#[derive(Debug)]
+struct WordCounter;
+
+impl TokenCounter for WordCounter {
+    fn name(&self) -> &str {
+        "words"
+    }
+
+    fn count(&self, text: &str) -> u32 {
+        text.split_whitespace().count() as u32
+    }
+}
+
+/// Test that a provider's tokenizer replaces the estimate, and that setting
+/// one recounts the messages already in the window.
+#[test]
+fn test_token_counter_replaces_estimate() {
+    let mut window = ContextWindow::new(10000);
+    window.add_message(Message::new(
+        MessageRole::User,
+        "fix the parser please".to_string(),
+    ));
+    let estimated = window.used_tokens;
+
+    window.set_token_counter(Some(Arc::new(WordCounter)));
+    assert_eq!(
+        window.used_tokens, 4,
+        "history should be recounted with the tokenizer"
+    );
+    assert_ne!(window.used_tokens, estimated);
+
+    window.add_message(Message::new(
+        MessageRole::Assistant,
+        "Reading src/parser.rs".to_string(),
+    ));
+    assert_eq!(window.used_tokens, 6);
+    assert_eq!(window.count_tokens("one two three"), 3);
+
+    window.set_token_counter(None);
+    assert_eq!(
+        window.count_tokens("one two three"),
+        ContextWindow::new(0).count_tokens("one two three")
+    );
+}
@@ -3,14 +3,29 @@
 use anyhow::{Context, Result};
 use chrono::Utc;
 use g3_config::Config;
+use std::collections::{BTreeMap, HashMap};
 use std::path::{Path, PathBuf};
 use std::process::Stdio;
-use tokio::io::{AsyncBufReadExt, BufReader};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
 use tokio::process::Command;
-use tracing::{debug, error, warn};
+use tokio::sync::{mpsc, watch};
+use tracing::{debug, error, info_span, warn, Instrument};
 use uuid::Uuid;
 
-use crate::status::{FlockStatus, SegmentState, SegmentStatus};
+use crate::knowledge::{self, KnowledgeBase};
+use crate::plan::{self, FlockPlan, PlannedSegment, SegmentSpec, SEGMENTS_FILE};
+use crate::race::{self, Racer};
+use crate::review::{ReviewRouter, ReviewSegment};
+use crate::status::{
+    DiffStats, FlockStatus, ReviewThread, SegmentResults, SegmentState, SegmentStatus, TestResults,
+    REPORT_FILE,
+};
+use crate::supervision::{self, Approvals, Barrier};
+use crate::watchdog::{Response, StallAction, Watchdog, WatchdogConfig, NUDGE_PROMPT};
+use g3_core::notifier::{Notification, Notifier};
+use g3_core::todo::{TodoList, TodoStats};
 
 /// Configuration for flock mode
 #[derive(Debug, Clone)]
@@ -32,6 +47,20 @@ pub struct FlockConfig {
 
     /// Path to g3 binary (defaults to current executable)
     pub g3_binary: Option<PathBuf>,
+
+    /// Segments listed in flock-segments.json; empty to have an agent
+    /// partition flock-requirements.md
+    pub segments: Vec<SegmentSpec>,
+
+    /// Spend the whole flock is planned for, in USD
+    pub budget_usd: Option<f64>,
+
+    /// What to do about segments that go silent; None to let them be
+    pub watchdog: Option<WatchdogConfig>,
+
+    /// Barriers where the flock waits for the operator's approval; empty
+    /// for an unsupervised flock
+    pub barriers: Vec<Barrier>,
 }
 
 impl FlockConfig {
@@ -72,6 +101,8 @@ impl FlockConfig {
             anyhow::bail!("No G3 configuration found. Please create a .g3.toml file.")
         })?;
 
+        let segments = plan::load_segments(&project_dir)?;
+
         Ok(Self {
             project_dir,
             flock_workspace,
@@ -79,6 +110,10 @@ impl FlockConfig {
             max_turns: 5, // Default
             g3_config,
             g3_binary: None,
+            segments,
+            budget_usd: None,
+            watchdog: None,
+            barriers: Vec::new(),
         })
     }
 
@@ -117,6 +152,8 @@ impl FlockConfig {
         // Load config from specified path
         let g3_config = Config::load(config_path)?;
 
+        let segments = plan::load_segments(&project_dir)?;
+
         Ok(Self {
             project_dir,
             flock_workspace,
@@ -124,6 +161,10 @@ impl FlockConfig {
             max_turns: 5, // Default
             g3_config,
             g3_binary: None,
+            segments,
+            budget_usd: None,
+            watchdog: None,
+            barriers: Vec::new(),
         })
     }
 
@@ -144,6 +185,214 @@ impl FlockConfig {
         self.g3_config = config;
         self
     }
+
+    /// Use these segments instead of partitioning the requirements
+    pub fn with_segments(mut self, segments: Vec<SegmentSpec>) -> Self {
+        self.segments = segments;
+        self
+    }
+
+    /// Set the flock's budget in USD
+    pub fn with_budget(mut self, budget_usd: f64) -> Self {
+        self.budget_usd = Some(budget_usd);
+        self
+    }
+
+    /// Watch segments for stalls
+    pub fn with_watchdog(mut self, watchdog: WatchdogConfig) -> Self {
+        self.watchdog = Some(watchdog);
+        self
+    }
+
+    /// Supervise the flock: wait for the operator's approval at `barriers`
+    pub fn with_barriers(mut self, barriers: Vec<Barrier>) -> Self {
+        self.barriers = barriers;
+        self
+    }
+
+    /// Check the flock before any agent starts: the segment count, leftover
+    /// workspaces, the worker provider and, for listed segments, their paths,
+    /// providers, dependencies and budgets. Every problem is reported at once.
+    pub fn validate(&self) -> Result<()> {
+        let mut problems = Vec::new();
+
+        if self.num_segments == 0 {
+            problems.push("At least one segment is required".to_string());
+        }
+        if !self.segments.is_empty() && self.segments.len() != self.num_segments {
+            problems.push(format!(
+                "{} lists {} segments but {} were requested",
+                SEGMENTS_FILE,
+                self.segments.len(),
+                self.num_segments
+            ));
+        }
+
+        if self
+            .watchdog
+            .as_ref()
+            .is_some_and(|watchdog| watchdog.timeout.is_zero())
+        {
+            problems.push("The stall timeout must be longer than zero".to_string());
+        }
+
+        let worker_provider = self.g3_config.get_player_provider();
+        if let Err(e) = self.g3_config.with_provider_override(worker_provider) {
+            problems.push(format!("Worker provider: {}", e));
+        }
+
+        for segment_id in 1..=self.num_segments {
+            let attempts = self
+                .segments
+                .get(segment_id - 1)
+                .map_or(1, |spec| spec.race.len().max(1));
+            for attempt in 1..=attempts {
+                let segment_dir = self.attempt_dir(segment_id, attempt);
+                if segment_dir.exists() {
+                    problems.push(format!(
+                        "Segment workspace already exists: {}",
+                        segment_dir.display()
+                    ));
+                }
+            }
+        }
+
+        if let Err(e) = knowledge::load_project_entries(&self.project_dir) {
+            problems.push(format!("{:#}", e));
+        }
+
+        problems.extend(plan::check_segments(
+            &self.segments,
+            &self.g3_config,
+            self.budget_usd,
+        ));
+
+        if !problems.is_empty() {
+            anyhow::bail!("Invalid flock configuration:\n- {}", problems.join("\n- "));
+        }
+        Ok(())
+    }
+
+    /// The schedule, workspaces and estimated cost of running this flock
+    pub fn plan(&self) -> FlockPlan {
+        let segments = (1..=self.num_segments)
+            .map(|segment_id| {
+                let spec = self.segments.get(segment_id - 1);
+                let providers = match spec {
+                    Some(spec) if !spec.race.is_empty() => spec.race.clone(),
+                    _ => vec![spec
+                        .and_then(|spec| spec.provider.clone())
+                        .unwrap_or_else(|| self.g3_config.get_player_provider().to_string())],
+                };
+                PlannedSegment {
+                    segment_id,
+                    name: spec.map(|spec| spec.module_name.clone()),
+                    workspace: self.segment_dir(segment_id),
+                    estimated_cost_usd: providers
+                        .iter()
+                        .map(|provider| {
+                            plan::estimate_segment_cost(&self.g3_config, provider, self.max_turns)
+                        })
+                        .sum(),
+                    provider: providers.join(" vs "),
+                    max_turns: self.max_turns,
+                    dependencies: spec
+                        .map(|spec| spec.dependencies.clone())
+                        .unwrap_or_default(),
+                    paths: spec.map(|spec| spec.paths.clone()).unwrap_or_default(),
+                    budget_usd: spec.and_then(|spec| spec.budget_usd),
+                    verify: spec.map(|spec| spec.verify.clone()).unwrap_or_default(),
+                }
+            })
+            .collect();
+
+        FlockPlan {
+            project_dir: self.project_dir.clone(),
+            flock_workspace: self.flock_workspace.clone(),
+            partitioned_by_agent: self.segments.is_empty(),
+            segments,
+            budget_usd: self.budget_usd,
+        }
+    }
+
+    fn segment_dir(&self, segment_id: usize) -> PathBuf {
+        self.flock_workspace.join(format!("segment-{}", segment_id))
+    }
+
+    /// Workspace of a raced segment's attempt; the first is the segment's own
+    fn attempt_dir(&self, segment_id: usize, attempt: usize) -> PathBuf {
+        if attempt == 1 {
+            self.segment_dir(segment_id)
+        } else {
+            self.flock_workspace
+                .join(format!("segment-{}-attempt-{}", segment_id, attempt))
+        }
+    }
+}
+
+/// What a live display sees of a flock run
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FlockEvent {
+    /// A flock-level message: partitioning, workspaces, the final report
+    Progress(String),
+    /// A line a segment's worker printed
+    SegmentOutput {
+        segment_id: usize,
+        line: String,
+        is_error: bool,
+    },
+    /// A segment's worker exited
+    SegmentFinished {
+        segment_id: usize,
+        state: SegmentState,
+    },
+    /// A supervised flock is waiting for an answer through [`Approvals`]
+    AwaitingApproval { barrier: Barrier, summary: String },
+}
+
+/// Stdin of the running segments, so a live display can type into one
+#[derive(Debug, Clone, Default)]
+pub struct SegmentInputs {
+    senders: Arc<Mutex<HashMap<usize, mpsc::UnboundedSender<String>>>>,
+}
+
+impl SegmentInputs {
+    /// Send a line to a segment's stdin; false if it isn't running
+    pub fn send(&self, segment_id: usize, line: &str) -> bool {
+        let senders = self.senders.lock().unwrap_or_else(|e| e.into_inner());
+        senders
+            .get(&segment_id)
+            .is_some_and(|sender| sender.send(line.to_string()).is_ok())
+    }
+
+    pub(crate) fn register(&self, segment_id: usize) -> mpsc::UnboundedReceiver<String> {
+        let (sender, receiver) = mpsc::unbounded_channel();
+        let mut senders = self.senders.lock().unwrap_or_else(|e| e.into_inner());
+        senders.insert(segment_id, sender);
+        receiver
+    }
+
+    pub(crate) fn unregister(&self, segment_id: usize) {
+        let mut senders = self.senders.lock().unwrap_or_else(|e| e.into_inner());
+        senders.remove(&segment_id);
+    }
+}
+
+/// Where a segment's output goes: to stdout, or as events to a live display
+/// that can also write to the segment's stdin
+#[derive(Debug, Clone, Default)]
+pub(crate) struct SegmentIo {
+    pub(crate) events: Option<mpsc::UnboundedSender<FlockEvent>>,
+    pub(crate) inputs: SegmentInputs,
+}
+
+impl SegmentIo {
+    pub(crate) fn send(&self, event: FlockEvent) {
+        if let Some(events) = &self.events {
+            // The display may have been closed; the flock keeps going
+            let _ = events.send(event);
+        }
+    }
 }
 
 /// Flock mode orchestrator
@@ -151,6 +400,10 @@ pub struct FlockMode {
     config: FlockConfig,
     status: FlockStatus,
     session_id: String,
+    io: SegmentIo,
+    approvals: Approvals,
+    /// Reviews the segments asked each other for, filled in by the router
+    reviews: Arc<Mutex<Vec<ReviewThread>>>,
 }
 
 impl FlockMode {
@@ -169,9 +422,38 @@ impl FlockMode {
             config,
             status,
             session_id,
+            io: SegmentIo::default(),
+            approvals: Approvals::default(),
+            reviews: Arc::default(),
         })
     }
 
+    /// Send progress and segment output to `events` instead of printing it
+    pub fn with_events(mut self, events: mpsc::UnboundedSender<FlockEvent>) -> Self {
+        self.io.events = Some(events);
+        self
+    }
+
+    /// Handle for writing to the segments' stdin while they run
+    pub fn segment_inputs(&self) -> SegmentInputs {
+        self.io.inputs.clone()
+    }
+
+    /// Handle for answering the barriers of a supervised flock from a live
+    /// display
+    pub fn approvals(&self) -> Approvals {
+        self.approvals.clone()
+    }
+
+    /// Print a progress message, or send it to the live display
+    fn say(&self, message: String) {
+        if self.io.events.is_some() {
+            self.io.send(FlockEvent::Progress(message));
+        } else {
+            println!("{}", message);
+        }
+    }
+
     /// Run flock mode
     pub async fn run(&mut self) -> Result<()> {
         debug!(
@@ -179,37 +461,148 @@ impl FlockMode {
             self.config.num_segments
         );
 
+        self.config.validate()?;
+
         // Step 1: Partition requirements
-        println!(
-            "\n🧠 Step 1: Partitioning requirements into {} segments...",
-            self.config.num_segments
-        );
-        let partitions = self.partition_requirements().await?;
+        let (partitions, decisions) = if self.config.segments.is_empty() {
+            self.say(format!(
+                "\n🧠 Step 1: Partitioning requirements into {} segments...",
+                self.config.num_segments
+            ));
+            self.partition_requirements().await?
+        } else {
+            self.say(format!(
+                "\n🧠 Step 1: Using the {} segments listed in {}",
+                self.config.segments.len(),
+                SEGMENTS_FILE
+            ));
+            (self.config.segments.clone(), BTreeMap::new())
+        };
+        self.seed_knowledge(&partitions, decisions)?;
+        self.barrier(Barrier::AfterPlanning, planning_summary(&partitions))
+            .await?;
 
         // Step 2: Create segment workspaces
-        println!("\n📁 Step 2: Creating segment workspaces...");
+        self.say("\n📁 Step 2: Creating segment workspaces...".to_string());
         self.create_segment_workspaces(&partitions).await?;
 
         // Step 3: Run segments in parallel
-        println!(
+        self.say(format!(
             "\n🚀 Step 3: Running {} segments in parallel...",
             self.config.num_segments
-        );
-        self.run_segments_parallel().await?;
+        ));
+        self.run_segments_parallel(&partitions).await?;
+        self.barrier(Barrier::AfterSegments, self.segments_summary())
+            .await?;
 
         // Step 4: Generate final report
-        println!("\n📊 Step 4: Generating final report...");
+        self.say("\n📊 Step 4: Generating final report...".to_string());
         self.status.completed_at = Some(Utc::now());
         self.save_status()?;
 
-        let report = self.status.generate_report();
-        println!("{}", report);
+        self.say(self.status.generate_report());
+
+        let report_path = self.config.flock_workspace.join(REPORT_FILE);
+        std::fs::write(&report_path, self.status.generate_markdown_report())
+            .context("Failed to write the flock report")?;
+        self.say(format!("📝 Report written to {}", report_path.display()));
+
+        Ok(())
+    }
+
+    /// Wait at `barrier` for the operator if the flock is supervised there,
+    /// and stop the flock if they reject
+    async fn barrier(&self, barrier: Barrier, summary: String) -> Result<()> {
+        if !self.config.barriers.contains(&barrier) {
+            return Ok(());
+        }
+
+        Notifier::new(self.config.g3_config.notifications.clone()).notify(
+            &Notification::FlockAwaitingApproval {
+                barrier: barrier.to_string(),
+            },
+        );
+
+        // Once the live display is closed the operator is back at the terminal
+        let display_open = self
+            .io
+            .events
+            .as_ref()
+            .is_some_and(|events| !events.is_closed());
+        let approved = if display_open {
+            let decision = self.approvals.wait(barrier);
+            self.io
+                .send(FlockEvent::AwaitingApproval { barrier, summary });
+            decision.await.unwrap_or(false)
+        } else {
+            println!("\n⏸️  Waiting for approval ({}):\n{}", barrier, summary);
+            ask_operator().await?
+        };
 
+        if !approved {
+            anyhow::bail!(
+                "Stopped by the operator at the {} barrier; segment workspaces are left in {}",
+                barrier,
+                self.config.flock_workspace.display()
+            );
+        }
+        self.say(format!("▶️  Approved at the {} barrier", barrier));
         Ok(())
     }
 
-    /// Partition requirements using an AI agent
-    async fn partition_requirements(&mut self) -> Result<Vec<String>> {
+    /// One line per segment with the state its worker ended in
+    fn segments_summary(&self) -> String {
+        let mut segments: Vec<&SegmentStatus> = self.status.segments.values().collect();
+        segments.sort_by_key(|segment| segment.segment_id);
+        segments
+            .iter()
+            .map(|segment| {
+                format!(
+                    "Segment {}: {} ({})",
+                    segment.segment_id,
+                    segment.state,
+                    segment.workspace.display()
+                )
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    /// Start the flock's knowledge base with what planning decided; the
+    /// project's own decisions win over the partitioning agent's
+    fn seed_knowledge(
+        &self,
+        partitions: &[SegmentSpec],
+        decisions: BTreeMap<String, String>,
+    ) -> Result<()> {
+        let mut entries = knowledge::segment_entries(partitions);
+        entries.extend(decisions);
+        entries.extend(knowledge::load_project_entries(&self.config.project_dir)?);
+
+        let knowledge = KnowledgeBase::new(&self.config.flock_workspace);
+        knowledge.create(&entries)?;
+        let modules: Vec<&str> = partitions
+            .iter()
+            .map(|partition| partition.module_name.as_str())
+            .collect();
+        knowledge.append_note(&format!(
+            "Planned {} segments: {}",
+            partitions.len(),
+            modules.join(", ")
+        ))?;
+        self.say(format!(
+            "   ✓ Knowledge base seeded with {} decisions in {}",
+            entries.len(),
+            knowledge.dir().join(knowledge::KNOWLEDGE_FILE).display()
+        ));
+        Ok(())
+    }
+
+    /// Partition requirements using an AI agent, along with the decisions
+    /// every module must follow
+    async fn partition_requirements(
+        &mut self,
+    ) -> Result<(Vec<SegmentSpec>, BTreeMap<String, String>)> {
         let requirements_path = self.config.project_dir.join("flock-requirements.md");
         let requirements_content = std::fs::read_to_string(&requirements_path)
             .context("Failed to read flock-requirements.md")?;
@@ -239,7 +632,8 @@ impl FlockMode {
             5. Use the final_output tool to provide your partitioning as a JSON array of objects, where each object has:\n\
                - \"module_name\": string\n\
                - \"requirements\": string (the requirements text for this module)\n\
-               - \"dependencies\": array of strings (names of other modules this depends on)\n\n\
+               - \"dependencies\": array of strings (names of other modules this depends on)\n\
+               - \"decisions\": object mapping a convention every module must agree on (naming, shared types, the interfaces between modules) to the decision, e.g. {{\"error handling\": \"return anyhow::Result\"}}; may be empty\n\n\
             Example format:\n\
             {{{{PARTITION JSON}}}}\n\
             ```json\n\
@@ -247,12 +641,14 @@ impl FlockMode {
               {{\n\
                 \"module_name\": \"core-engine\",\n\
                 \"requirements\": \"Implement the core processing engine...\",\n\
-                \"dependencies\": []\n\
+                \"dependencies\": [],\n\
+                \"decisions\": {{\"engine entry point\": \"Engine::process(&Input) -> Result<Output>\"}}\n\
               }},\n\
               {{\n\
                 \"module_name\": \"api-server\",\n\
                 \"requirements\": \"Create REST API endpoints...\",\n\
-                \"dependencies\": [\"core-engine\"]\n\
+                \"dependencies\": [\"core-engine\"],\n\
+                \"decisions\": {{}}\n\
               }}\n\
             ]\n\
             ```\n\n\
@@ -266,7 +662,7 @@ impl FlockMode {
         let g3_binary = self.get_g3_binary()?;
 
         // Run g3 in single-shot mode to partition requirements
-        println!("   Analyzing requirements and creating partitions...");
+        self.say("   Analyzing requirements and creating partitions...".to_string());
         let output = Command::new(&g3_binary)
             .arg("--workspace")
             .arg(&partition_workspace)
@@ -300,11 +696,14 @@ impl FlockMode {
             );
         }
 
-        // Extract requirements text from each partition
-        let mut partition_texts = Vec::new();
+        // Extract the segment from each partition
+        let mut segments = Vec::new();
+        let mut decisions = BTreeMap::new();
         for (i, partition) in partitions.iter().enumerate() {
-            let default_name = format!("module-{}", i + 1);
-            let module_name = partition["module_name"].as_str().unwrap_or(&default_name);
+            let module_name = partition["module_name"]
+                .as_str()
+                .map(str::to_string)
+                .unwrap_or_else(|| format!("module-{}", i + 1));
             let requirements = partition["requirements"]
                 .as_str()
                 .context("Missing requirements field in partition")?;
@@ -313,27 +712,31 @@ impl FlockMode {
                 .map(|arr| {
                     arr.iter()
                         .filter_map(|v| v.as_str())
-                        .collect::<Vec<_>>()
-                        .join(", ")
+                        .map(str::to_string)
+                        .collect()
                 })
                 .unwrap_or_default();
 
-            let partition_text = format!(
-                "# Module: {}\n\n## Dependencies\n{}\n\n## Requirements\n\n{}",
-                module_name,
-                if dependencies.is_empty() {
-                    "None".to_string()
-                } else {
-                    dependencies
-                },
-                requirements
-            );
+            if let Some(partition_decisions) = partition["decisions"].as_object() {
+                decisions.extend(partition_decisions.iter().filter_map(|(key, value)| {
+                    value.as_str().map(|value| (key.clone(), value.to_string()))
+                }));
+            }
 
-            partition_texts.push(partition_text);
-            println!("   ✓ Created partition {}: {}", i + 1, module_name);
+            self.say(format!("   ✓ Created partition {}: {}", i + 1, module_name));
+            segments.push(SegmentSpec {
+                module_name,
+                requirements: requirements.to_string(),
+                dependencies,
+                paths: Vec::new(),
+                provider: None,
+                budget_usd: None,
+                race: Vec::new(),
+                verify: Vec::new(),
+            });
         }
 
-        Ok(partition_texts)
+        Ok((segments, decisions))
     }
 
     /// Extract JSON from agent output (looks for JSON array in output)
@@ -415,36 +818,36 @@ impl FlockMode {
     }
 
     /// Create segment workspaces by copying project directory
-    async fn create_segment_workspaces(&mut self, partitions: &[String]) -> Result<()> {
+    async fn create_segment_workspaces(&mut self, partitions: &[SegmentSpec]) -> Result<()> {
         // Ensure flock workspace exists
         std::fs::create_dir_all(&self.config.flock_workspace)?;
 
         for (i, partition) in partitions.iter().enumerate() {
             let segment_id = i + 1;
-            let segment_dir = self
-                .config
-                .flock_workspace
-                .join(format!("segment-{}", segment_id));
 
-            println!("   Creating segment {} workspace...", segment_id);
+            self.say(format!("   Creating segment {} workspace...", segment_id));
 
-            // Copy project directory to segment directory
-            self.copy_git_repo(&self.config.project_dir, &segment_dir)
-                .await
-                .context(format!("Failed to copy project to segment {}", segment_id))?;
+            // A raced segment gets a workspace per provider
+            for attempt in 1..=partition.race.len().max(1) {
+                let segment_dir = self.config.attempt_dir(segment_id, attempt);
 
-            // Write segment-requirements.md
-            let requirements_path = segment_dir.join("segment-requirements.md");
-            std::fs::write(&requirements_path, partition).context(format!(
-                "Failed to write requirements for segment {}",
-                segment_id
-            ))?;
+                // Copy project directory to segment directory
+                self.copy_git_repo(&self.config.project_dir, &segment_dir)
+                    .await
+                    .context(format!("Failed to copy project to segment {}", segment_id))?;
 
-            println!(
-                "   ✓ Segment {} workspace ready at {}",
-                segment_id,
-                segment_dir.display()
-            );
+                // Write segment-requirements.md
+                let requirements_path = segment_dir.join("segment-requirements.md");
+                std::fs::write(&requirements_path, partition.requirements_markdown()).context(
+                    format!("Failed to write requirements for segment {}", segment_id),
+                )?;
+
+                self.say(format!(
+                    "   ✓ Segment {} workspace ready at {}",
+                    segment_id,
+                    segment_dir.display()
+                ));
+            }
         }
 
         Ok(())
@@ -469,9 +872,10 @@ impl FlockMode {
         Ok(())
     }
 
-    /// Run all segments in parallel
-    async fn run_segments_parallel(&mut self) -> Result<()> {
+    /// Run all segments in parallel, routing the reviews they ask each other for
+    async fn run_segments_parallel(&mut self, partitions: &[SegmentSpec]) -> Result<()> {
         let mut handles = Vec::new();
+        let mut review_segments = Vec::new();
 
         for segment_id in 1..=self.config.num_segments {
             let segment_dir = self
@@ -482,6 +886,32 @@ impl FlockMode {
             let g3_binary = self.get_g3_binary()?;
             let status_file = self.get_status_file_path();
             let session_id = self.session_id.clone();
+            let io = self.io.clone();
+            let watchdog = self.config.watchdog.clone();
+            let partition = partitions.get(segment_id - 1);
+            let provider = partition.and_then(|partition| partition.provider.clone());
+            let knowledge_dir = self.config.flock_workspace.clone();
+            let verify = partition
+                .map(|partition| partition.verify.clone())
+                .unwrap_or_default();
+            let racers: Vec<Racer> = partition
+                .iter()
+                .flat_map(|partition| &partition.race)
+                .enumerate()
+                .map(|(i, provider)| Racer {
+                    provider: provider.clone(),
+                    workspace: self.config.attempt_dir(segment_id, i + 1),
+                })
+                .collect();
+            let g3_config = self.config.g3_config.clone();
+
+            review_segments.push(ReviewSegment {
+                segment_id,
+                module_name: partition.map(|partition| partition.module_name.clone()),
+                dir: segment_dir.clone(),
+                base_commit: git_output(&segment_dir, &["rev-parse", "HEAD"]).await.ok(),
+                provider: provider.clone(),
+            });
 
             // Initialize segment status
             let segment_status = SegmentStatus {
@@ -497,37 +927,112 @@ impl FlockMode {
                 max_turns,
                 last_message: Some("Starting...".to_string()),
                 error_message: None,
+                todo: None,
+                results: SegmentResults {
+                    module_name: partition.map(|partition| partition.module_name.clone()),
+                    ..Default::default()
+                },
             };
 
             self.status.update_segment(segment_id, segment_status);
             self.save_status()?;
 
             // Spawn a task for this segment
-            let handle = tokio::spawn(async move {
-                run_segment(
-                    segment_id,
-                    segment_dir,
-                    max_turns,
-                    g3_binary,
-                    status_file,
-                    session_id,
-                )
-                .await
-            });
+            let span = info_span!(
+                "flock_segment",
+                segment_id,
+                session_id = %session_id,
+                max_turns,
+                state = tracing::field::Empty,
+                tokens_used = tracing::field::Empty,
+                tool_calls = tracing::field::Empty,
+                errors = tracing::field::Empty,
+            );
+            let handle = tokio::spawn(
+                async move {
+                    if racers.is_empty() {
+                        return run_segment(
+                            segment_id,
+                            segment_dir,
+                            max_turns,
+                            g3_binary,
+                            status_file,
+                            session_id,
+                            provider,
+                            knowledge_dir,
+                            watchdog,
+                            verify,
+                            io,
+                        )
+                        .await;
+                    }
+                    let status = race::race(segment_id, racers, &g3_config, io, |racer, io| {
+                        run_segment(
+                            segment_id,
+                            racer.workspace.clone(),
+                            max_turns,
+                            g3_binary.clone(),
+                            status_file.clone(),
+                            session_id.clone(),
+                            Some(racer.provider.clone()),
+                            knowledge_dir.clone(),
+                            watchdog.clone(),
+                            verify.clone(),
+                            io,
+                        )
+                    })
+                    .await;
+                    Ok(status)
+                }
+                .instrument(span),
+            );
 
             handles.push((segment_id, handle));
         }
 
+        let (stop_reviews, reviews_stopped) = watch::channel(false);
+        let router = ReviewRouter {
+            flock_workspace: self.config.flock_workspace.clone(),
+            g3_binary: self.get_g3_binary()?,
+            segments: review_segments,
+            threads: self.reviews.clone(),
+        };
+        let router = tokio::spawn(router.run(reviews_stopped));
+
         // Wait for all segments to complete
+        let notifier = Notifier::new(self.config.g3_config.notifications.clone());
         for (segment_id, handle) in handles {
             match handle.await {
-                Ok(Ok(final_status)) => {
-                    println!("\n✅ Segment {} completed", segment_id);
+                Ok(Ok(mut final_status)) => {
+                    self.say(format!("\n✅ Segment {} completed", segment_id));
+                    let partition = partitions.get(segment_id - 1);
+                    let provider = partition
+                        .and_then(|partition| partition.provider.as_deref())
+                        .unwrap_or(self.config.g3_config.get_player_provider());
+                    final_status.results.module_name =
+                        partition.map(|partition| partition.module_name.clone());
+                    // A race has already priced each attempt on its own provider
+                    if final_status.results.attempts.is_empty() {
+                        final_status.results.cost_usd = plan::estimate_tokens_cost(
+                            &self.config.g3_config,
+                            provider,
+                            final_status.tokens_used,
+                        );
+                    }
+                    if final_status.state == SegmentState::Failed {
+                        notifier.notify(&Notification::SegmentFailed { segment_id });
+                    }
                     self.status.update_segment(segment_id, final_status);
                     self.save_status()?;
                 }
                 Ok(Err(e)) => {
                     error!("Segment {} failed: {}", segment_id, e);
+                    notifier.notify(&Notification::SegmentFailed { segment_id });
+                    self.io.inputs.unregister(segment_id);
+                    self.io.send(FlockEvent::SegmentFinished {
+                        segment_id,
+                        state: SegmentState::Failed,
+                    });
                     let mut segment_status = self
                         .status
                         .segments
@@ -549,6 +1054,8 @@ impl FlockMode {
                             max_turns: self.config.max_turns,
                             last_message: None,
                             error_message: Some(e.to_string()),
+                            todo: None,
+                            results: SegmentResults::default(),
                         });
                     segment_status.state = SegmentState::Failed;
                     segment_status.completed_at = Some(Utc::now());
@@ -559,6 +1066,12 @@ impl FlockMode {
                 }
                 Err(e) => {
                     error!("Segment {} task panicked: {}", segment_id, e);
+                    notifier.notify(&Notification::SegmentFailed { segment_id });
+                    self.io.inputs.unregister(segment_id);
+                    self.io.send(FlockEvent::SegmentFinished {
+                        segment_id,
+                        state: SegmentState::Failed,
+                    });
                     let mut segment_status = self
                         .status
                         .segments
@@ -580,6 +1093,8 @@ impl FlockMode {
                             max_turns: self.config.max_turns,
                             last_message: None,
                             error_message: Some(format!("Task panicked: {}", e)),
+                            todo: None,
+                            results: SegmentResults::default(),
                         });
                     segment_status.state = SegmentState::Failed;
                     segment_status.completed_at = Some(Utc::now());
@@ -591,6 +1106,11 @@ impl FlockMode {
             }
         }
 
+        // Nobody is left to wait for a review
+        let _ = stop_reviews.send(true);
+        router.await?;
+        self.save_status()?;
+
         Ok(())
     }
 
@@ -609,14 +1129,20 @@ impl FlockMode {
         self.config.flock_workspace.join("flock-status.json")
     }
 
-    /// Save current status to file
-    fn save_status(&self) -> Result<()> {
+    /// Save current status to file, with the reviews routed so far
+    fn save_status(&mut self) -> Result<()> {
+        self.status.reviews = self
+            .reviews
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .clone();
         let status_file = self.get_status_file_path();
         self.status.save_to_file(&status_file)
     }
 }
 
 /// Run a single segment worker
+#[allow(clippy::too_many_arguments)]
 async fn run_segment(
     segment_id: usize,
     segment_dir: PathBuf,
@@ -624,6 +1150,11 @@ async fn run_segment(
     g3_binary: PathBuf,
     status_file: PathBuf,
     session_id: String,
+    provider: Option<String>,
+    knowledge_dir: PathBuf,
+    watchdog: Option<WatchdogConfig>,
+    verify: Vec<String>,
+    io: SegmentIo,
 ) -> Result<SegmentStatus> {
     debug!(
         "Starting segment {} in {}",
@@ -644,98 +1175,278 @@ async fn run_segment(
         max_turns,
         last_message: Some("Starting autonomous mode...".to_string()),
         error_message: None,
+        todo: None,
+        results: SegmentResults::default(),
     };
 
-    // Run g3 in autonomous mode with segment-requirements.md
-    let mut child = Command::new(&g3_binary)
-        .arg("--workspace")
-        .arg(&segment_dir)
-        .arg("--autonomous")
-        .arg("--max-turns")
-        .arg(max_turns.to_string())
-        .arg("--requirements")
-        .arg(std::fs::read_to_string(
-            segment_dir.join("segment-requirements.md"),
-        )?)
-        .arg("--quiet") // Disable session logging for workers
-        .stdout(Stdio::piped())
-        .stderr(Stdio::piped())
-        .spawn()
-        .context("Failed to spawn g3 process")?;
-
-    // Stream output and update status
-    let stdout = child.stdout.take().context("Failed to get stdout")?;
-    let stderr = child.stderr.take().context("Failed to get stderr")?;
-
-    let stdout_reader = BufReader::new(stdout);
-    let stderr_reader = BufReader::new(stderr);
-
-    let mut stdout_lines = stdout_reader.lines();
-    let mut stderr_lines = stderr_reader.lines();
-
-    // Read output and update status
-    loop {
-        tokio::select! {
-            line = stdout_lines.next_line() => {
-                match line {
-                    Ok(Some(line)) => {
-                        println!("[Segment {}] {}", segment_id, line);
-
-                        // Parse output for status updates
-                        if line.contains("TURN") {
-                            // Extract turn number if possible
-                            if let Some(turn_str) = line.split("TURN").nth(1) {
-                                if let Ok(turn) = turn_str.trim().split('/').next().unwrap_or("0").parse::<usize>() {
-                                    segment_status.current_turn = turn;
+    // Diffs are measured against the commit the workspace was cloned at
+    let base_commit = git_output(&segment_dir, &["rev-parse", "HEAD"]).await.ok();
+    let mut new_test_run = true;
+
+    let requirements = std::fs::read_to_string(segment_dir.join("segment-requirements.md"))?;
+    let mut watchdog = watchdog.map(Watchdog::new);
+    let nudges = watchdog
+        .as_ref()
+        .is_some_and(|watchdog| watchdog.config().action == StallAction::Nudge);
+
+    // None when the watchdog aborted the worker
+    let exit_status = 'run: loop {
+        // Run g3 in autonomous mode with segment-requirements.md
+        let mut command = Command::new(&g3_binary);
+        command
+            .arg("--workspace")
+            .arg(&segment_dir)
+            .arg("--autonomous")
+            .arg("--max-turns")
+            .arg(max_turns.to_string())
+            .arg("--requirements")
+            .arg(&requirements)
+            .arg("--quiet") // Disable session logging for workers
+            .arg("--flock-knowledge")
+            .arg(&knowledge_dir)
+            .arg("--flock-segment")
+            .arg(segment_id.to_string());
+        if let Some(provider) = &provider {
+            command.arg("--provider").arg(provider);
+        }
+        // Killed if the segment is dropped, as a race's losers are
+        let mut child = command
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .kill_on_drop(true)
+            .spawn()
+            .context("Failed to spawn g3 process")?;
+
+        // Forward lines typed into the live display and the watchdog's
+        // nudges; without either, stdin is closed so a worker that asks for
+        // input gets no answer instead of competing for the terminal
+        let mut stdin = child.stdin.take().context("Failed to get stdin")?;
+        if io.events.is_some() || nudges {
+            let mut input = io.inputs.register(segment_id);
+            tokio::spawn(async move {
+                while let Some(line) = input.recv().await {
+                    let written = stdin.write_all(format!("{}\n", line).as_bytes()).await;
+                    if written.is_err() || stdin.flush().await.is_err() {
+                        break;
+                    }
+                }
+            });
+        } else {
+            drop(stdin);
+        }
+
+        // Stream output and update status
+        let stdout = child.stdout.take().context("Failed to get stdout")?;
+        let stderr = child.stderr.take().context("Failed to get stderr")?;
+
+        let stdout_reader = BufReader::new(stdout);
+        let stderr_reader = BufReader::new(stderr);
+
+        let mut stdout_lines = stdout_reader.lines();
+        let mut stderr_lines = stderr_reader.lines();
+
+        let mut liveness_check = tokio::time::interval(
+            watchdog
+                .as_ref()
+                .map_or(Duration::from_secs(60), Watchdog::check_interval),
+        );
+
+        // Read output and update status
+        loop {
+            tokio::select! {
+                line = stdout_lines.next_line() => {
+                    match line {
+                        Ok(Some(line)) => {
+                            if let Some(watchdog) = &mut watchdog {
+                                watchdog.activity(Instant::now());
+                            }
+                            segment_status.state = SegmentState::Running;
+
+                            if io.events.is_some() {
+                                io.send(FlockEvent::SegmentOutput {
+                                    segment_id,
+                                    line: line.clone(),
+                                    is_error: false,
+                                });
+                            } else {
+                                println!("[Segment {}] {}", segment_id, line);
+                            }
+
+                            // Parse output for status updates
+                            if TestResults::starts_run(&line) {
+                                new_test_run = true;
+                            }
+                            if let Some(summary) = TestResults::parse(&line) {
+                                match &mut segment_status.results.tests {
+                                    Some(tests) if !new_test_run => tests.add(summary),
+                                    tests => *tests = Some(summary),
                                 }
+                                new_test_run = false;
                             }
-                        }
 
-                        segment_status.last_message = Some(line);
-                        update_status_file(&status_file, &session_id, segment_status.clone())?;
+                            if line.contains("TURN") {
+                                // Extract turn number if possible
+                                if let Some(turn_str) = line.split("TURN").nth(1) {
+                                    if let Ok(turn) = turn_str.trim().split('/').next().unwrap_or("0").parse::<usize>() {
+                                        segment_status.current_turn = turn;
+                                    }
+                                }
+                                segment_status.todo = segment_todo_stats(&segment_dir).or(segment_status.todo);
+                            }
+
+                            segment_status.last_message = Some(line);
+                            update_status_file(&status_file, &session_id, segment_status.clone())?;
+                        }
+                        Ok(None) => break,
+                        Err(e) => {
+                            error!("Error reading stdout for segment {}: {}", segment_id, e);
+                            break;
+                        }
                     }
-                    Ok(None) => break,
-                    Err(e) => {
-                        error!("Error reading stdout for segment {}: {}", segment_id, e);
-                        break;
+                }
+                line = stderr_lines.next_line() => {
+                    match line {
+                        Ok(Some(line)) => {
+                            if let Some(watchdog) = &mut watchdog {
+                                watchdog.activity(Instant::now());
+                            }
+                            segment_status.state = SegmentState::Running;
+
+                            if io.events.is_some() {
+                                io.send(FlockEvent::SegmentOutput {
+                                    segment_id,
+                                    line,
+                                    is_error: true,
+                                });
+                            } else {
+                                eprintln!("[Segment {} ERROR] {}", segment_id, line);
+                            }
+                            segment_status.errors += 1;
+                            update_status_file(&status_file, &session_id, segment_status.clone())?;
+                        }
+                        Ok(None) => break,
+                        Err(e) => {
+                            error!("Error reading stderr for segment {}: {}", segment_id, e);
+                            break;
+                        }
                     }
                 }
-            }
-            line = stderr_lines.next_line() => {
-                match line {
-                    Ok(Some(line)) => {
-                        eprintln!("[Segment {} ERROR] {}", segment_id, line);
-                        segment_status.errors += 1;
-                        update_status_file(&status_file, &session_id, segment_status.clone())?;
+                _ = liveness_check.tick(), if watchdog.is_some() => {
+                    let Some(watchdog) = &mut watchdog else {
+                        continue;
+                    };
+                    let now = Instant::now();
+                    if !watchdog.stalled(now) {
+                        continue;
                     }
-                    Ok(None) => break,
-                    Err(e) => {
-                        error!("Error reading stderr for segment {}: {}", segment_id, e);
-                        break;
+
+                    let silent = watchdog.config().timeout.as_secs();
+                    warn!("Segment {} has been silent for {}s", segment_id, silent);
+                    segment_status.state = SegmentState::Stalled;
+                    match watchdog.respond(now) {
+                        Response::Nudge => {
+                            io.inputs.send(segment_id, NUDGE_PROMPT);
+                            segment_status.last_message =
+                                Some(format!("Silent for {}s; nudged", silent));
+                            update_status_file(&status_file, &session_id, segment_status.clone())?;
+                        }
+                        Response::Restart => {
+                            let _ = child.kill().await;
+                            segment_status.last_message = Some(format!(
+                                "Silent for {}s; restarting (restart {})",
+                                silent,
+                                watchdog.restarts()
+                            ));
+                            update_status_file(&status_file, &session_id, segment_status.clone())?;
+                            continue 'run;
+                        }
+                        Response::Abort => {
+                            let _ = child.kill().await;
+                            segment_status.error_message = Some(format!(
+                                "Stopped by the watchdog after {}s without activity",
+                                silent
+                            ));
+                            break 'run None;
+                        }
                     }
                 }
             }
         }
-    }
 
-    // Wait for process to complete
-    let status = child
-        .wait()
-        .await
-        .context("Failed to wait for g3 process")?;
+        // Wait for process to complete
+        break Some(
+            child
+                .wait()
+                .await
+                .context("Failed to wait for g3 process")?,
+        );
+    };
 
     segment_status.completed_at = Some(Utc::now());
+    // A fully completed TODO file is deleted, so keep the last stats seen
+    segment_status.todo = segment_todo_stats(&segment_dir).or(segment_status.todo);
+
+    match exit_status {
+        Some(status) if status.success() => match race::verify(&segment_dir, &verify).await {
+            Ok(()) => {
+                segment_status.state = SegmentState::Completed;
+                segment_status.last_message = Some("Completed successfully".to_string());
+            }
+            Err(e) => {
+                segment_status.state = SegmentState::Failed;
+                segment_status.error_message = Some(format!("{:#}", e));
+                segment_status.errors += 1;
+            }
+        },
+        Some(status) => {
+            segment_status.state = SegmentState::Failed;
+            segment_status.error_message = Some(format!("Process exited with status: {}", status));
+            segment_status.errors += 1;
+        }
+        None => {
+            segment_status.state = SegmentState::Failed;
+            segment_status.errors += 1;
+        }
+    }
 
-    if status.success() {
-        segment_status.state = SegmentState::Completed;
-        segment_status.last_message = Some("Completed successfully".to_string());
-    } else {
-        segment_status.state = SegmentState::Failed;
-        segment_status.error_message = Some(format!("Process exited with status: {}", status));
-        segment_status.errors += 1;
+    if let Some(base_commit) = &base_commit {
+        match diff_stats(&segment_dir, base_commit).await {
+            Ok(diff) => segment_status.results.diff = Some(diff),
+            Err(e) => warn!("Could not measure segment {}'s changes: {}", segment_id, e),
+        }
     }
+    segment_status.results.remaining_todos = segment_remaining_todos(&segment_dir);
 
-    // Try to extract metrics from session log if available
+    read_session_metrics(
+        &segment_dir,
+        &mut segment_status.tokens_used,
+        &mut segment_status.tool_calls,
+    );
+
+    update_status_file(&status_file, &session_id, segment_status.clone())?;
+    io.inputs.unregister(segment_id);
+    io.send(FlockEvent::SegmentFinished {
+        segment_id,
+        state: segment_status.state.clone(),
+    });
+
+    let span = tracing::Span::current();
+    span.record("state", tracing::field::debug(&segment_status.state));
+    span.record("tokens_used", segment_status.tokens_used);
+    span.record("tool_calls", segment_status.tool_calls);
+    span.record("errors", segment_status.errors);
+
+    Ok(segment_status)
+}
+
+/// Tokens and tool calls from the worker's session logs in `segment_dir`;
+/// left as they are if it kept none
+pub(crate) fn read_session_metrics(
+    segment_dir: &Path,
+    tokens_used: &mut u64,
+    tool_calls: &mut u64,
+) {
     let log_dir = segment_dir.join("logs");
     if log_dir.exists() {
         if let Ok(entries) = std::fs::read_dir(&log_dir) {
@@ -750,7 +1461,7 @@ async fn run_segment(
                             if let Some(context) = log_json.get("context_window") {
                                 if let Some(cumulative) = context.get("cumulative_tokens") {
                                     if let Some(tokens) = cumulative.as_u64() {
-                                        segment_status.tokens_used = tokens;
+                                        *tokens_used = tokens;
                                     }
                                 }
                             }
@@ -766,7 +1477,7 @@ async fn run_segment(
                                                     == Some("tool")
                                             })
                                             .count();
-                                        segment_status.tool_calls = tool_call_count as u64;
+                                        *tool_calls = tool_call_count as u64;
                                     }
                                 }
                             }
@@ -776,10 +1487,144 @@ async fn run_segment(
             }
         }
     }
+}
 
-    update_status_file(&status_file, &session_id, segment_status.clone())?;
+/// One line per planned segment: its name and the start of its requirements
+fn planning_summary(partitions: &[SegmentSpec]) -> String {
+    partitions
+        .iter()
+        .enumerate()
+        .map(|(index, spec)| {
+            let first_line = spec
+                .requirements
+                .lines()
+                .map(|line| line.trim_start_matches('#').trim())
+                .find(|line| !line.is_empty())
+                .unwrap_or_default();
+            format!(
+                "Segment {}: {} - {}",
+                index + 1,
+                spec.module_name,
+                first_line
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
 
-    Ok(segment_status)
+/// Ask on the terminal whether the flock may go on; end of input rejects
+async fn ask_operator() -> Result<bool> {
+    tokio::task::spawn_blocking(|| {
+        use std::io::Write as _;
+        print!("Continue? [y/N] ");
+        std::io::stdout().flush()?;
+        let mut answer = String::new();
+        std::io::stdin().read_line(&mut answer)?;
+        Ok(supervision::is_approval(&answer))
+    })
+    .await?
+}
+
+/// Completion of the newest todo.g3.md in a segment workspace: the
+/// workspace's own, or one of its sessions'
+fn segment_todo_stats(segment_dir: &Path) -> Option<TodoStats> {
+    let contents = newest_segment_todo(segment_dir)?;
+    Some(g3_core::todo::stats(&contents))
+}
+
+/// Unchecked tasks in the segment's newest todo.g3.md, as `<id> <title>`
+fn segment_remaining_todos(segment_dir: &Path) -> Vec<String> {
+    let Some(contents) = newest_segment_todo(segment_dir) else {
+        return Vec::new();
+    };
+    TodoList::parse(&contents)
+        .tasks()
+        .into_iter()
+        .filter(|task| !task.is_done())
+        .map(|task| format!("{} {}", task.id, task.title))
+        .collect()
+}
+
+/// Contents of the newest todo.g3.md in a segment workspace
+fn newest_segment_todo(segment_dir: &Path) -> Option<String> {
+    let session_todos = std::fs::read_dir(segment_dir.join(".g3").join("sessions"))
+        .into_iter()
+        .flatten()
+        .flatten()
+        .map(|entry| entry.path().join("todo.g3.md"));
+    let (_, newest) = std::iter::once(segment_dir.join("todo.g3.md"))
+        .chain(session_todos)
+        .filter_map(|path| {
+            let modified = path.metadata().and_then(|meta| meta.modified()).ok()?;
+            Some((modified, path))
+        })
+        .max()?;
+    std::fs::read_to_string(newest).ok()
+}
+
+/// Files the flock and the worker itself write into a segment workspace,
+/// left out of its diff
+pub(crate) const WORKER_FILES: &[&str] =
+    &["segment-requirements.md", "todo.g3.md", ".g3/", "logs/"];
+
+/// What a segment changed since `base_commit`: its commits, plus tracked
+/// and untracked files it changed without committing
+async fn diff_stats(segment_dir: &Path, base_commit: &str) -> Result<DiffStats> {
+    let commits = git_output(
+        segment_dir,
+        &["rev-list", "--count", &format!("{}..HEAD", base_commit)],
+    )
+    .await?;
+    let numstat = git_output(segment_dir, &["diff", "--numstat", base_commit]).await?;
+    let untracked =
+        git_output(segment_dir, &["ls-files", "--others", "--exclude-standard"]).await?;
+
+    let mut diff = parse_numstat(&numstat);
+    diff.commits = commits.parse().unwrap_or(0);
+    diff.files.extend(untracked.lines().map(str::to_string));
+    diff.files.retain(|file| {
+        !WORKER_FILES
+            .iter()
+            .any(|worker_file| file == worker_file || file.starts_with(worker_file))
+    });
+    diff.files.sort();
+    diff.files.dedup();
+    Ok(diff)
+}
+
+/// Files and line counts from `git diff --numstat`; binary files count no lines
+fn parse_numstat(numstat: &str) -> DiffStats {
+    let mut diff = DiffStats::default();
+    for line in numstat.lines() {
+        let mut fields = line.splitn(3, '\t');
+        let (Some(insertions), Some(deletions), Some(file)) =
+            (fields.next(), fields.next(), fields.next())
+        else {
+            continue;
+        };
+        diff.insertions += insertions.parse::<usize>().unwrap_or(0);
+        diff.deletions += deletions.parse::<usize>().unwrap_or(0);
+        diff.files.push(file.to_string());
+    }
+    diff
+}
+
+/// Run git in `dir` and return its trimmed stdout
+pub(crate) async fn git_output(dir: &Path, args: &[&str]) -> Result<String> {
+    let output = Command::new("git")
+        .args(args)
+        .current_dir(dir)
+        .output()
+        .await
+        .context("Failed to run git")?;
+    if !output.status.success() {
+        anyhow::bail!(
+            "git {} failed: {}",
+            args.join(" "),
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
 }
 
 /// Update the status file with new segment status
@@ -804,7 +1649,8 @@ fn update_status_file(
 
 #[cfg(test)]
 mod tests {
-    use super::FlockMode;
+    use super::{parse_numstat, planning_summary, FlockMode};
+    use crate::plan::SegmentSpec;
 
     #[test]
     fn extract_json_from_output_handles_partition_marker_and_fences() {
@@ -1011,4 +1857,54 @@ The requirements have been partitioned into two logical, largely non-overlapping
         assert_eq!(arr[0]["module_name"], "message-protocol");
         assert_eq!(arr[1]["module_name"], "observability");
     }
+
+    #[test]
+    fn segment_todo_stats_reads_session_todo() {
+        let segment_dir = tempfile::TempDir::new().unwrap();
+        assert_eq!(super::segment_todo_stats(segment_dir.path()), None);
+
+        let session_dir = segment_dir.path().join(".g3/sessions/abc");
+        std::fs::create_dir_all(&session_dir).unwrap();
+        std::fs::write(
+            session_dir.join("todo.g3.md"),
+            "- [x] Parse input\n- [ ] Write output\n",
+        )
+        .unwrap();
+
+        let stats = super::segment_todo_stats(segment_dir.path()).unwrap();
+        assert_eq!((stats.done, stats.total), (1, 2));
+    }
+
+    #[test]
+    fn parse_numstat_counts_lines_and_files() {
+        let diff = parse_numstat("12\t3\tsrc/lib.rs\n-\t-\tassets/logo.png\n4\t0\tsrc/a b.rs");
+        assert_eq!(
+            diff.files,
+            vec!["src/lib.rs", "assets/logo.png", "src/a b.rs"]
+        );
+        assert_eq!(diff.insertions, 16);
+        assert_eq!(diff.deletions, 3);
+    }
+
+    #[test]
+    fn planning_summary_names_each_segment() {
+        let spec = |name: &str, requirements: &str| SegmentSpec {
+            module_name: name.to_string(),
+            requirements: requirements.to_string(),
+            dependencies: Vec::new(),
+            paths: Vec::new(),
+            provider: None,
+            budget_usd: None,
+            race: Vec::new(),
+            verify: Vec::new(),
+        };
+        let summary = planning_summary(&[
+            spec("parser", "\n# Parse the config format\nMore detail"),
+            spec("cli", "Add the command"),
+        ]);
+        assert_eq!(
+            summary,
+            "Segment 1: parser - Parse the config format\nSegment 2: cli - Add the command"
+        );
+    }
 }
@@ -0,0 +1,212 @@
+//! Knowledge shared by every agent in a flock.
+//!
+//! The planning phase records the decisions all segments must agree on
+//! (naming, shared types, the interfaces between modules) as key-value
+//! entries in `flock-knowledge.json`, and `flock-knowledge.md` collects notes
+//! that are only ever appended to. Both live in the flock workspace, outside
+//! the segments' clones, and every segment worker gets them in its system
+//! context, so segments are given the same conventions instead of inventing
+//! their own. Workers only read the knowledge base; the flock writes it.
+//!
+//! A project can fix decisions up front in its own `flock-knowledge.json`
+//! next to `flock-requirements.md`; planning adds to those.
+
+use anyhow::{Context, Result};
+use chrono::Utc;
+use std::collections::BTreeMap;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use crate::plan::SegmentSpec;
+
+/// Key-value decisions, in the flock workspace and optionally the project
+pub const KNOWLEDGE_FILE: &str = "flock-knowledge.json";
+
+/// Append-only notes, in the flock workspace
+pub const NOTES_FILE: &str = "flock-knowledge.md";
+
+#[derive(Debug, Clone)]
+pub struct KnowledgeBase {
+    dir: PathBuf,
+}
+
+impl KnowledgeBase {
+    /// The knowledge base kept in `dir`, usually the flock workspace
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self { dir: dir.into() }
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Start the knowledge base over with `entries` and no notes
+    pub fn create(&self, entries: &BTreeMap<String, String>) -> Result<()> {
+        std::fs::create_dir_all(&self.dir)?;
+        self.write_entries(entries)?;
+        std::fs::write(self.dir.join(NOTES_FILE), "")
+            .context("Failed to create the flock notes")?;
+        Ok(())
+    }
+
+    /// The recorded decisions; empty if there are none yet
+    pub fn entries(&self) -> Result<BTreeMap<String, String>> {
+        read_entries(&self.dir.join(KNOWLEDGE_FILE))
+    }
+
+    /// Record a decision, replacing any earlier one under `key`
+    pub fn set(&self, key: &str, value: &str) -> Result<()> {
+        let mut entries = self.entries()?;
+        entries.insert(key.to_string(), value.to_string());
+        self.write_entries(&entries)
+    }
+
+    /// Add a timestamped note after the existing ones
+    pub fn append_note(&self, note: &str) -> Result<()> {
+        let mut notes = std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(self.dir.join(NOTES_FILE))
+            .context("Failed to open the flock notes")?;
+        writeln!(
+            notes,
+            "- {} {}",
+            Utc::now().format("%Y-%m-%d %H:%M:%S UTC"),
+            note.trim()
+        )?;
+        Ok(())
+    }
+
+    pub fn notes(&self) -> Result<String> {
+        match std::fs::read_to_string(self.dir.join(NOTES_FILE)) {
+            Ok(notes) => Ok(notes),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(String::new()),
+            Err(e) => Err(e).context("Failed to read the flock notes"),
+        }
+    }
+
+    /// The knowledge base as a system context section; None when it is empty
+    pub fn render(&self) -> Result<Option<String>> {
+        let entries = self.entries()?;
+        let notes = self.notes()?;
+        if entries.is_empty() && notes.trim().is_empty() {
+            return Ok(None);
+        }
+
+        let mut text = String::from(
+            "🧭 Flock Knowledge Base (shared by every segment of this flock; read-only):\n\n\
+             Follow these decisions so your module fits the ones other agents are building \
+             in parallel.\n",
+        );
+        if !entries.is_empty() {
+            text.push_str("\n## Decisions\n\n");
+            for (key, value) in &entries {
+                text.push_str(&format!("- **{}**: {}\n", key, value));
+            }
+        }
+        if !notes.trim().is_empty() {
+            text.push_str("\n## Notes\n\n");
+            text.push_str(notes.trim_end());
+            text.push('\n');
+        }
+        Ok(Some(text))
+    }
+
+    fn write_entries(&self, entries: &BTreeMap<String, String>) -> Result<()> {
+        let json = serde_json::to_string_pretty(entries)?;
+        std::fs::write(self.dir.join(KNOWLEDGE_FILE), json)
+            .context("Failed to write the flock knowledge base")
+    }
+}
+
+/// The decisions a project fixed in its own flock-knowledge.json
+pub fn load_project_entries(project_dir: &Path) -> Result<BTreeMap<String, String>> {
+    read_entries(&project_dir.join(KNOWLEDGE_FILE))
+}
+
+/// What planning knows about each segment: the module it builds, the paths it
+/// owns and the modules it builds on
+pub fn segment_entries(segments: &[SegmentSpec]) -> BTreeMap<String, String> {
+    segments
+        .iter()
+        .enumerate()
+        .map(|(index, spec)| {
+            let mut value = format!("segment {}", index + 1);
+            if !spec.paths.is_empty() {
+                value.push_str(&format!("; owns {}", spec.paths.join(", ")));
+            }
+            if !spec.dependencies.is_empty() {
+                value.push_str(&format!("; builds on {}", spec.dependencies.join(", ")));
+            }
+            (format!("module {}", spec.module_name), value)
+        })
+        .collect()
+}
+
+fn read_entries(path: &Path) -> Result<BTreeMap<String, String>> {
+    match std::fs::read_to_string(path) {
+        Ok(json) => serde_json::from_str(&json)
+            .with_context(|| format!("Invalid knowledge base {}", path.display())),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(BTreeMap::new()),
+        Err(e) => Err(e).with_context(|| format!("Failed to read {}", path.display())),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_entries_and_notes_render_as_context() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let knowledge = KnowledgeBase::new(dir.path().join("flock"));
+        assert!(knowledge.entries().unwrap().is_empty());
+
+        let mut entries = BTreeMap::new();
+        entries.insert("errors".to_string(), "anyhow::Result".to_string());
+        knowledge.create(&entries).unwrap();
+        assert!(knowledge
+            .render()
+            .unwrap()
+            .unwrap()
+            .contains("- **errors**: anyhow::Result"));
+
+        knowledge.set("errors", "thiserror enums").unwrap();
+        knowledge.append_note("Planned 2 segments").unwrap();
+        knowledge.append_note("Segment 1 owns src/parser").unwrap();
+
+        let rendered = knowledge.render().unwrap().unwrap();
+        assert!(rendered.contains("- **errors**: thiserror enums"));
+        let notes = knowledge.notes().unwrap();
+        assert_eq!(notes.lines().count(), 2);
+        assert!(notes
+            .lines()
+            .nth(1)
+            .unwrap()
+            .ends_with("Segment 1 owns src/parser"));
+
+        // Creating again starts over
+        knowledge.create(&BTreeMap::new()).unwrap();
+        assert_eq!(knowledge.render().unwrap(), None);
+    }
+
+    #[test]
+    fn test_segment_entries() {
+        let spec = |name: &str, paths: &[&str], dependencies: &[&str]| SegmentSpec {
+            module_name: name.to_string(),
+            requirements: String::new(),
+            dependencies: dependencies.iter().map(|s| s.to_string()).collect(),
+            paths: paths.iter().map(|s| s.to_string()).collect(),
+            provider: None,
+            budget_usd: None,
+            race: Vec::new(),
+            verify: Vec::new(),
+        };
+        let entries = segment_entries(&[
+            spec("core", &["src/core"], &[]),
+            spec("api", &[], &["core"]),
+        ]);
+        assert_eq!(entries["module core"], "segment 1; owns src/core");
+        assert_eq!(entries["module api"], "segment 2; builds on core");
+    }
+}
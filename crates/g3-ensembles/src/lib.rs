@@ -4,9 +4,19 @@
 //! enabling parallel development across different architectural modules.
 
 pub mod flock;
+pub mod knowledge;
+pub mod plan;
+mod race;
+mod review;
 pub mod status;
+pub mod supervision;
 mod tests;
+pub mod watchdog;
 
 /// Re-export main types for convenience
-pub use flock::{FlockConfig, FlockMode};
-pub use status::{FlockStatus, SegmentStatus};
+pub use flock::{FlockConfig, FlockEvent, FlockMode, SegmentInputs};
+pub use knowledge::KnowledgeBase;
+pub use plan::{FlockPlan, SegmentSpec};
+pub use status::{FlockStatus, RaceAttempt, ReviewThread, SegmentStatus};
+pub use supervision::{Approvals, Barrier};
+pub use watchdog::{StallAction, WatchdogConfig};
@@ -0,0 +1,537 @@
+//! Checking a flock before any agent starts, and the `g3 flock plan` dry run.
+//!
+//! Segments normally come from the partitioning agent at run time. A project
+//! can instead list them in `flock-segments.json`: the same JSON the agent
+//! produces, plus the project `paths` each segment owns, the `provider` its
+//! worker uses and a `budget_usd` it is planned for. A hard segment can
+//! instead `race` several providers, with `verify` commands deciding which
+//! attempt wins. `FlockConfig::validate`
+//! checks them against each other and the G3 configuration; [`FlockPlan`]
+//! describes what would run and roughly what it would cost.
+
+use anyhow::{Context, Result};
+use g3_config::{Config, ProviderConfigRef};
+use g3_core::usage::estimate_cost;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::path::{Component, Path, PathBuf};
+
+/// Segments listed by the project, next to flock-requirements.md
+pub const SEGMENTS_FILE: &str = "flock-segments.json";
+
+/// Rough tokens one autonomous turn (player and coach) sends and receives;
+/// only used for the dry run's cost estimate
+const ESTIMATED_PROMPT_TOKENS_PER_TURN: u32 = 150_000;
+const ESTIMATED_COMPLETION_TOKENS_PER_TURN: u32 = 6_000;
+
+/// One segment of the work, as listed in flock-segments.json or produced by
+/// the partitioning agent
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SegmentSpec {
+    pub module_name: String,
+    pub requirements: String,
+    /// Names of the segments this one builds on
+    #[serde(default)]
+    pub dependencies: Vec<String>,
+    /// Project paths this segment owns; no two segments may overlap
+    #[serde(default)]
+    pub paths: Vec<String>,
+    /// Provider for this segment's worker, "<provider_type>.<config_name>"
+    #[serde(default)]
+    pub provider: Option<String>,
+    /// Spend this segment is planned for, in USD
+    #[serde(default)]
+    pub budget_usd: Option<f64>,
+    /// Providers to give this segment to at once, each in its own workspace;
+    /// the first attempt to pass `verify` wins and the others are cancelled
+    #[serde(default)]
+    pub race: Vec<String>,
+    /// Shell commands run in the segment's workspace when its worker
+    /// finishes; the segment fails unless every one exits successfully
+    #[serde(default)]
+    pub verify: Vec<String>,
+}
+
+impl SegmentSpec {
+    /// The segment-requirements.md handed to the segment's worker
+    pub fn requirements_markdown(&self) -> String {
+        let dependencies = if self.dependencies.is_empty() {
+            "None".to_string()
+        } else {
+            self.dependencies.join(", ")
+        };
+        let mut text = format!(
+            "# Module: {}\n\n## Dependencies\n{}\n\n",
+            self.module_name, dependencies
+        );
+        if !self.paths.is_empty() {
+            text.push_str(&format!(
+                "## Owned Paths\nOnly change files under: {}\n\n",
+                self.paths.join(", ")
+            ));
+        }
+        text.push_str(&format!("## Requirements\n\n{}", self.requirements));
+        text
+    }
+}
+
+/// Read the project's flock-segments.json; empty if it has none
+pub fn load_segments(project_dir: &Path) -> Result<Vec<SegmentSpec>> {
+    let path = project_dir.join(SEGMENTS_FILE);
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+    let json = std::fs::read_to_string(&path)
+        .with_context(|| format!("Failed to read {}", path.display()))?;
+    serde_json::from_str(&json).with_context(|| format!("Failed to parse {}", path.display()))
+}
+
+/// Every problem with `segments`, in the order found: names, overlapping
+/// paths, providers, races, dependencies and budgets
+pub(crate) fn check_segments(
+    segments: &[SegmentSpec],
+    g3_config: &Config,
+    budget_usd: Option<f64>,
+) -> Vec<String> {
+    let mut problems = Vec::new();
+
+    let mut names = HashSet::new();
+    for segment in segments {
+        if segment.module_name.trim().is_empty() {
+            problems.push("A segment has an empty module_name".to_string());
+        } else if !names.insert(segment.module_name.as_str()) {
+            problems.push(format!(
+                "Segment name '{}' is used more than once",
+                segment.module_name
+            ));
+        }
+    }
+
+    let mut owned: Vec<(&str, PathBuf)> = Vec::new();
+    for segment in segments {
+        for path in &segment.paths {
+            match normalize(path) {
+                Ok(path) => owned.push((segment.module_name.as_str(), path)),
+                Err(problem) => {
+                    problems.push(format!("Segment '{}': {}", segment.module_name, problem))
+                }
+            }
+        }
+    }
+    for (i, (name, path)) in owned.iter().enumerate() {
+        for (other_name, other_path) in &owned[i + 1..] {
+            if name != other_name && (path.starts_with(other_path) || other_path.starts_with(path))
+            {
+                problems.push(format!(
+                    "Segments '{}' and '{}' overlap: {} and {}",
+                    name,
+                    other_name,
+                    path.display(),
+                    other_path.display()
+                ));
+            }
+        }
+    }
+
+    for segment in segments {
+        if let Some(provider) = &segment.provider {
+            if let Err(e) = g3_config.with_provider_override(provider) {
+                problems.push(format!("Segment '{}': {}", segment.module_name, e));
+            }
+        }
+    }
+
+    for segment in segments {
+        if segment.race.is_empty() {
+            continue;
+        }
+        let name = &segment.module_name;
+        if segment.race.len() < 2 {
+            problems.push(format!(
+                "Segment '{}' races a single provider; list at least two",
+                name
+            ));
+        }
+        if segment.provider.is_some() {
+            problems.push(format!("Segment '{}' sets both provider and race", name));
+        }
+        if segment.verify.is_empty() {
+            problems.push(format!(
+                "Segment '{}' races providers but has no verify commands to pick a winner",
+                name
+            ));
+        }
+        let mut racers = HashSet::new();
+        for provider in &segment.race {
+            if !racers.insert(provider.as_str()) {
+                problems.push(format!("Segment '{}' races '{}' twice", name, provider));
+            } else if let Err(e) = g3_config.with_provider_override(provider) {
+                problems.push(format!("Segment '{}': {}", name, e));
+            }
+        }
+    }
+
+    for segment in segments {
+        for dependency in &segment.dependencies {
+            if !names.contains(dependency.as_str()) {
+                problems.push(format!(
+                    "Segment '{}' depends on unknown segment '{}'",
+                    segment.module_name, dependency
+                ));
+            }
+        }
+    }
+    if let Some(cycle) = find_cycle(segments) {
+        problems.push(format!("Dependency cycle: {}", cycle.join(" → ")));
+    }
+
+    for segment in segments {
+        if let Some(budget) = segment.budget_usd {
+            if budget.is_nan() || budget < 0.0 {
+                problems.push(format!(
+                    "Segment '{}' has an invalid budget: {}",
+                    segment.module_name, budget
+                ));
+            }
+        }
+    }
+    if let Some(budget) = budget_usd {
+        let planned: f64 = segments.iter().filter_map(|s| s.budget_usd).sum();
+        if planned > budget {
+            problems.push(format!(
+                "Segment budgets total ${:.2}, more than the flock budget of ${:.2}",
+                planned, budget
+            ));
+        }
+    }
+
+    problems
+}
+
+/// A project-relative path without `.`, `..` or a root, so overlaps can be
+/// compared component by component
+fn normalize(path: &str) -> std::result::Result<PathBuf, String> {
+    let mut normalized = PathBuf::new();
+    for component in Path::new(path).components() {
+        match component {
+            Component::Normal(part) => normalized.push(part),
+            Component::CurDir => {}
+            _ => return Err(format!("path '{}' must stay inside the project", path)),
+        }
+    }
+    Ok(normalized)
+}
+
+/// A dependency cycle among `segments`, first segment repeated at the end
+fn find_cycle(segments: &[SegmentSpec]) -> Option<Vec<String>> {
+    let dependencies: HashMap<&str, &[String]> = segments
+        .iter()
+        .map(|s| (s.module_name.as_str(), s.dependencies.as_slice()))
+        .collect();
+
+    fn visit<'a>(
+        name: &'a str,
+        dependencies: &HashMap<&'a str, &'a [String]>,
+        path: &mut Vec<&'a str>,
+        done: &mut HashSet<&'a str>,
+    ) -> Option<Vec<String>> {
+        if let Some(start) = path.iter().position(|n| *n == name) {
+            let mut cycle: Vec<String> = path[start..].iter().map(|n| n.to_string()).collect();
+            cycle.push(name.to_string());
+            return Some(cycle);
+        }
+        if done.contains(name) {
+            return None;
+        }
+        path.push(name);
+        for dependency in dependencies.get(name).copied().unwrap_or_default() {
+            if let Some(cycle) = visit(dependency, dependencies, path, done) {
+                return Some(cycle);
+            }
+        }
+        path.pop();
+        done.insert(name);
+        None
+    }
+
+    let mut done = HashSet::new();
+    segments.iter().find_map(|segment| {
+        visit(
+            &segment.module_name,
+            &dependencies,
+            &mut Vec::new(),
+            &mut done,
+        )
+    })
+}
+
+/// Rough cost of a worker running `max_turns` turns on `provider`, zero for
+/// models without a known price
+pub fn estimate_segment_cost(g3_config: &Config, provider: &str, max_turns: usize) -> f64 {
+    let Some(model) = provider_model(g3_config, provider) else {
+        return 0.0;
+    };
+    let turns = max_turns as u32;
+    estimate_cost(
+        provider,
+        &model,
+        ESTIMATED_PROMPT_TOKENS_PER_TURN.saturating_mul(turns),
+        ESTIMATED_COMPLETION_TOKENS_PER_TURN.saturating_mul(turns),
+    )
+}
+
+/// Rough cost of `tokens` used on `provider`, priced as input tokens since
+/// the split between input and output isn't recorded
+pub(crate) fn estimate_tokens_cost(g3_config: &Config, provider: &str, tokens: u64) -> f64 {
+    let Some(model) = provider_model(g3_config, provider) else {
+        return 0.0;
+    };
+    let tokens = u32::try_from(tokens).unwrap_or(u32::MAX);
+    estimate_cost(provider, &model, tokens, 0)
+}
+
+fn provider_model(g3_config: &Config, provider: &str) -> Option<String> {
+    let config = g3_config.with_provider_override(provider).ok()?;
+    let model = match config.get_default_provider_config().ok()? {
+        ProviderConfigRef::Anthropic(c) => &c.model,
+        ProviderConfigRef::OpenAI(c) | ProviderConfigRef::OpenAICompatible(c) => &c.model,
+        ProviderConfigRef::Databricks(c) => &c.model,
+        ProviderConfigRef::Embedded(c) => &c.model_path,
+        ProviderConfigRef::Local(c) => &c.model,
+        ProviderConfigRef::Gemini(c) => &c.model,
+        ProviderConfigRef::Bedrock(c) => &c.model,
+        // Fixtures cost nothing
+        ProviderConfigRef::Mock(_) => return None,
+    };
+    Some(model.clone())
+}
+
+/// One segment as the dry run shows it
+#[derive(Debug, Clone, PartialEq)]
+pub struct PlannedSegment {
+    pub segment_id: usize,
+    /// None until the partitioning agent names it
+    pub name: Option<String>,
+    pub workspace: PathBuf,
+    /// The worker's provider; for a raced segment, each racer joined by " vs "
+    pub provider: String,
+    pub max_turns: usize,
+    pub dependencies: Vec<String>,
+    pub paths: Vec<String>,
+    pub budget_usd: Option<f64>,
+    pub verify: Vec<String>,
+    /// Includes every attempt of a raced segment
+    pub estimated_cost_usd: f64,
+}
+
+/// What `FlockMode::run` would do, without doing it
+#[derive(Debug, Clone, PartialEq)]
+pub struct FlockPlan {
+    pub project_dir: PathBuf,
+    pub flock_workspace: PathBuf,
+    /// Segments come from the partitioning agent rather than flock-segments.json
+    pub partitioned_by_agent: bool,
+    pub segments: Vec<PlannedSegment>,
+    pub budget_usd: Option<f64>,
+}
+
+impl FlockPlan {
+    pub fn estimated_cost_usd(&self) -> f64 {
+        self.segments.iter().map(|s| s.estimated_cost_usd).sum()
+    }
+}
+
+impl fmt::Display for FlockPlan {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "🗺️  Flock plan for {}", self.project_dir.display())?;
+        if self.partitioned_by_agent {
+            writeln!(
+                f,
+                "\nSegments will be partitioned from flock-requirements.md by an agent \
+                 (add {} to fix them in advance).",
+                SEGMENTS_FILE
+            )?;
+        } else {
+            writeln!(f, "\nSegments are listed in {}.", SEGMENTS_FILE)?;
+        }
+
+        writeln!(
+            f,
+            "\nSchedule: all {} segments start together; each gets its dependencies as context.",
+            self.segments.len()
+        )?;
+        for segment in &self.segments {
+            let name = segment.name.as_deref().unwrap_or("(named by the agent)");
+            writeln!(
+                f,
+                "  {}. {} · {} · up to {} turns · ~${:.2}",
+                segment.segment_id,
+                name,
+                segment.provider,
+                segment.max_turns,
+                segment.estimated_cost_usd
+            )?;
+            if !segment.dependencies.is_empty() {
+                writeln!(f, "     after: {}", segment.dependencies.join(", "))?;
+            }
+            if !segment.paths.is_empty() {
+                writeln!(f, "     owns: {}", segment.paths.join(", "))?;
+            }
+            if let Some(budget) = segment.budget_usd {
+                writeln!(f, "     budget: ${:.2}", budget)?;
+            }
+            if !segment.verify.is_empty() {
+                writeln!(f, "     verify: {}", segment.verify.join("; "))?;
+            }
+        }
+
+        writeln!(f, "\nWorkspaces (git clones of the project):")?;
+        writeln!(f, "  {}/", self.flock_workspace.display())?;
+        writeln!(f, "  ├── flock-status.json")?;
+        if self.partitioned_by_agent {
+            writeln!(f, "  ├── _partition/")?;
+        }
+        for (i, segment) in self.segments.iter().enumerate() {
+            let branch = if i + 1 == self.segments.len() {
+                "└──"
+            } else {
+                "├──"
+            };
+            let name = segment.workspace.file_name().unwrap_or_default();
+            writeln!(
+                f,
+                "  {} {}/segment-requirements.md",
+                branch,
+                name.to_string_lossy()
+            )?;
+        }
+
+        let total = self.estimated_cost_usd();
+        write!(f, "\nEstimated cost: ~${:.2}", total)?;
+        if self.partitioned_by_agent {
+            write!(f, " plus the partitioning agent")?;
+        }
+        writeln!(f, " (assumes every segment uses all its turns)")?;
+        if let Some(budget) = self.budget_usd {
+            if total > budget {
+                writeln!(f, "⚠️  Over the flock budget of ${:.2}", budget)?;
+            } else {
+                writeln!(f, "Within the flock budget of ${:.2}", budget)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn segment(name: &str, paths: &[&str], dependencies: &[&str]) -> SegmentSpec {
+        SegmentSpec {
+            module_name: name.to_string(),
+            requirements: format!("Build {}", name),
+            dependencies: dependencies.iter().map(|d| d.to_string()).collect(),
+            paths: paths.iter().map(|p| p.to_string()).collect(),
+            provider: None,
+            budget_usd: None,
+            race: Vec::new(),
+            verify: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_overlapping_paths_are_reported() {
+        let config = Config::default();
+        let segments = vec![
+            segment("core", &["crates/core"], &[]),
+            segment("api", &["./crates/core/api", "crates/api"], &["core"]),
+            segment("docs", &["docs"], &[]),
+        ];
+        let problems = check_segments(&segments, &config, None);
+        assert_eq!(
+            problems,
+            vec!["Segments 'core' and 'api' overlap: crates/core and crates/core/api"]
+        );
+
+        // Sibling directories sharing a prefix don't overlap
+        let siblings = vec![
+            segment("core", &["crates/core"], &[]),
+            segment("core-ext", &["crates/core-ext"], &[]),
+        ];
+        assert!(check_segments(&siblings, &config, None).is_empty());
+
+        let escaping = vec![segment("core", &["../elsewhere"], &[])];
+        assert_eq!(check_segments(&escaping, &config, None).len(), 1);
+    }
+
+    #[test]
+    fn test_dependency_problems_are_reported() {
+        let config = Config::default();
+        let segments = vec![
+            segment("a", &[], &["c"]),
+            segment("b", &[], &["a"]),
+            segment("c", &[], &["b", "missing"]),
+        ];
+        let problems = check_segments(&segments, &config, None);
+        assert_eq!(
+            problems,
+            vec![
+                "Segment 'c' depends on unknown segment 'missing'",
+                "Dependency cycle: a → c → b → a",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_provider_and_budget_problems_are_reported() {
+        let config = Config::default();
+        let mut core = segment("core", &[], &[]);
+        core.provider = Some("anthropic.missing".to_string());
+        core.budget_usd = Some(6.0);
+        let mut api = segment("api", &[], &[]);
+        api.budget_usd = Some(5.0);
+
+        let problems = check_segments(&[core, api], &config, Some(10.0));
+        assert_eq!(problems.len(), 2);
+        assert!(problems[0].starts_with("Segment 'core': Provider config 'anthropic.missing'"));
+        assert_eq!(
+            problems[1],
+            "Segment budgets total $11.00, more than the flock budget of $10.00"
+        );
+    }
+
+    #[test]
+    fn test_race_problems_are_reported() {
+        let config = Config::default();
+        let mut core = segment("core", &[], &[]);
+        core.race = vec!["databricks.default".to_string()];
+        core.provider = Some("databricks.default".to_string());
+        let mut api = segment("api", &[], &[]);
+        api.race = vec![
+            "databricks.default".to_string(),
+            "databricks.default".to_string(),
+        ];
+        api.verify = vec!["cargo test".to_string()];
+
+        let problems = check_segments(&[core, api], &config, None);
+        assert_eq!(
+            problems,
+            vec![
+                "Segment 'core' races a single provider; list at least two",
+                "Segment 'core' sets both provider and race",
+                "Segment 'core' races providers but has no verify commands to pick a winner",
+                "Segment 'api' races 'databricks.default' twice",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_requirements_markdown_lists_owned_paths() {
+        let text = segment("api", &["crates/api"], &["core"]).requirements_markdown();
+        assert!(text.starts_with("# Module: api\n\n## Dependencies\ncore\n\n"));
+        assert!(text.contains("## Owned Paths\nOnly change files under: crates/api\n"));
+        assert!(text.ends_with("## Requirements\n\nBuild api"));
+    }
+}
@@ -0,0 +1,399 @@
+//! Racing one segment on several providers.
+//!
+//! A segment that lists `race` providers gets a worker per provider, each in
+//! its own clone of the project. A worker only completes once the segment's
+//! `verify` commands pass in its workspace, so the first attempt to complete
+//! wins and the workers still running are killed. Every attempt's tokens are
+//! priced on its own provider, and the segment's cost covers the whole race.
+
+use anyhow::{Context, Result};
+use chrono::Utc;
+use g3_config::Config;
+use std::future::Future;
+use std::path::{Path, PathBuf};
+use tokio::process::Command;
+use tokio::sync::mpsc;
+use tracing::warn;
+
+use crate::flock::{read_session_metrics, FlockEvent, SegmentInputs, SegmentIo};
+use crate::plan;
+use crate::status::{RaceAttempt, SegmentResults, SegmentState, SegmentStatus};
+
+/// One provider's entry in a race
+#[derive(Debug, Clone)]
+pub(crate) struct Racer {
+    pub(crate) provider: String,
+    pub(crate) workspace: PathBuf,
+}
+
+/// Run every racer's worker with `start` and return the segment's status:
+/// the winner's, with every attempt recorded, or a failure if none passed
+pub(crate) async fn race<F, Fut>(
+    segment_id: usize,
+    racers: Vec<Racer>,
+    g3_config: &Config,
+    io: SegmentIo,
+    mut start: F,
+) -> SegmentStatus
+where
+    F: FnMut(&Racer, SegmentIo) -> Fut,
+    Fut: Future<Output = Result<SegmentStatus>> + Send + 'static,
+{
+    let started_at = Utc::now();
+    let (finished_tx, mut finished) = mpsc::unbounded_channel();
+    let mut workers = Vec::new();
+    let mut attempt_inputs: Vec<SegmentInputs> = Vec::new();
+
+    for (index, racer) in racers.iter().enumerate() {
+        // Each attempt's output reaches the display labelled with its
+        // provider; only the race as a whole finishes the segment
+        let events = io.events.as_ref().map(|_| {
+            let (events, mut received) = mpsc::unbounded_channel();
+            let display = io.clone();
+            let provider = racer.provider.clone();
+            tokio::spawn(async move {
+                while let Some(event) = received.recv().await {
+                    match event {
+                        FlockEvent::SegmentOutput {
+                            segment_id,
+                            line,
+                            is_error,
+                        } => display.send(FlockEvent::SegmentOutput {
+                            segment_id,
+                            line: format!("[{}] {}", provider, line),
+                            is_error,
+                        }),
+                        FlockEvent::SegmentFinished { .. } => {}
+                        event => display.send(event),
+                    }
+                }
+            });
+            events
+        });
+        let attempt_io = SegmentIo {
+            events,
+            inputs: SegmentInputs::default(),
+        };
+        attempt_inputs.push(attempt_io.inputs.clone());
+
+        let worker = start(racer, attempt_io);
+        let finished = finished_tx.clone();
+        workers.push(tokio::spawn(async move {
+            let _ = finished.send((index, worker.await));
+        }));
+    }
+    drop(finished_tx);
+
+    // Lines typed into the display go to every attempt
+    if io.events.is_some() {
+        let mut input = io.inputs.register(segment_id);
+        tokio::spawn(async move {
+            while let Some(line) = input.recv().await {
+                for inputs in &attempt_inputs {
+                    inputs.send(segment_id, &line);
+                }
+            }
+        });
+    }
+
+    let mut attempts: Vec<RaceAttempt> = racers
+        .iter()
+        .map(|racer| RaceAttempt {
+            provider: racer.provider.clone(),
+            workspace: racer.workspace.clone(),
+            state: SegmentState::Running,
+            won: false,
+            tokens_used: 0,
+            cost_usd: 0.0,
+            error: None,
+        })
+        .collect();
+    let mut statuses: Vec<Option<SegmentStatus>> = vec![None; racers.len()];
+    let mut winner = None;
+
+    while let Some((index, result)) = finished.recv().await {
+        record(&mut attempts[index], &mut statuses[index], result);
+        say(
+            &io,
+            segment_id,
+            format!(
+                "🏁 Attempt on {} finished: {}",
+                attempts[index].provider, attempts[index].state
+            ),
+        );
+        if attempts[index].won {
+            winner = Some(index);
+            break;
+        }
+    }
+
+    // Dropping a worker kills its process
+    for worker in &workers {
+        worker.abort();
+    }
+    for worker in workers {
+        let _ = worker.await;
+    }
+    io.inputs.unregister(segment_id);
+
+    // Attempts that finished alongside the winner keep their own outcome
+    while let Ok((index, result)) = finished.try_recv() {
+        record(&mut attempts[index], &mut statuses[index], result);
+        attempts[index].won = false;
+    }
+
+    for (attempt, status) in attempts.iter_mut().zip(&statuses) {
+        match status {
+            Some(status) => attempt.tokens_used = status.tokens_used,
+            None => {
+                // Stopped before it reported; its logs still show what it used
+                let mut tool_calls = 0;
+                read_session_metrics(
+                    &attempt.workspace,
+                    &mut attempt.tokens_used,
+                    &mut tool_calls,
+                );
+                if attempt.state == SegmentState::Running {
+                    attempt.state = SegmentState::Cancelled;
+                    attempt.error = match winner {
+                        Some(winner) => {
+                            Some(format!("Cancelled when {} won", racers[winner].provider))
+                        }
+                        None => Some("The worker task ended without a result".to_string()),
+                    };
+                }
+            }
+        }
+        attempt.cost_usd =
+            plan::estimate_tokens_cost(g3_config, &attempt.provider, attempt.tokens_used);
+    }
+
+    let mut status = match winner {
+        Some(winner) => statuses[winner].take(),
+        None => statuses.iter_mut().rev().find_map(Option::take),
+    }
+    .unwrap_or_else(|| SegmentStatus {
+        segment_id,
+        workspace: racers
+            .first()
+            .map(|racer| racer.workspace.clone())
+            .unwrap_or_default(),
+        state: SegmentState::Failed,
+        started_at,
+        completed_at: None,
+        tokens_used: 0,
+        tool_calls: 0,
+        errors: 0,
+        current_turn: 0,
+        max_turns: 0,
+        last_message: None,
+        error_message: None,
+        todo: None,
+        results: SegmentResults::default(),
+    });
+    if winner.is_none() {
+        status.state = SegmentState::Failed;
+        status.error_message = Some("No attempt passed verification".to_string());
+        status.errors += 1;
+    }
+    status.started_at = started_at;
+    status.completed_at = Some(Utc::now());
+    status.tokens_used = attempts.iter().map(|attempt| attempt.tokens_used).sum();
+    status.results.cost_usd = attempts.iter().map(|attempt| attempt.cost_usd).sum();
+    status.results.attempts = attempts;
+
+    io.send(FlockEvent::SegmentFinished {
+        segment_id,
+        state: status.state.clone(),
+    });
+    status
+}
+
+/// Note how an attempt's worker ended
+fn record(
+    attempt: &mut RaceAttempt,
+    status: &mut Option<SegmentStatus>,
+    result: Result<SegmentStatus>,
+) {
+    match result {
+        Ok(finished) => {
+            attempt.state = finished.state.clone();
+            attempt.error = finished.error_message.clone();
+            attempt.won = finished.state == SegmentState::Completed;
+            *status = Some(finished);
+        }
+        Err(e) => {
+            attempt.state = SegmentState::Failed;
+            attempt.error = Some(format!("{:#}", e));
+        }
+    }
+}
+
+/// Run `commands` in `dir` one after another, stopping at the first that
+/// doesn't exit successfully
+pub(crate) async fn verify(dir: &Path, commands: &[String]) -> Result<()> {
+    for command in commands {
+        let output = Command::new("sh")
+            .arg("-c")
+            .arg(command)
+            .current_dir(dir)
+            .kill_on_drop(true)
+            .output()
+            .await
+            .with_context(|| format!("Failed to run `{}`", command))?;
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            let mut message = format!(
+                "Verification failed: `{}` exited with {}",
+                command, output.status
+            );
+            if let Some(line) = stderr.lines().rev().find(|line| !line.trim().is_empty()) {
+                message.push_str(&format!(": {}", line.trim()));
+            }
+            warn!("{} in {}", message, dir.display());
+            anyhow::bail!(message);
+        }
+    }
+    Ok(())
+}
+
+fn say(io: &SegmentIo, segment_id: usize, line: String) {
+    if io.events.is_some() {
+        io.send(FlockEvent::SegmentOutput {
+            segment_id,
+            line,
+            is_error: false,
+        });
+    } else {
+        println!("[Segment {}] {}", segment_id, line);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    fn racer(provider: &str) -> Racer {
+        Racer {
+            provider: provider.to_string(),
+            workspace: PathBuf::from(format!("/nonexistent/{}", provider)),
+        }
+    }
+
+    fn finished(racer: &Racer, state: SegmentState, tokens_used: u64) -> SegmentStatus {
+        SegmentStatus {
+            segment_id: 1,
+            workspace: racer.workspace.clone(),
+            state,
+            started_at: Utc::now(),
+            completed_at: Some(Utc::now()),
+            tokens_used,
+            tool_calls: 0,
+            errors: 0,
+            current_turn: 1,
+            max_turns: 5,
+            last_message: None,
+            error_message: None,
+            todo: None,
+            results: SegmentResults::default(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_first_verified_attempt_wins() {
+        let racers = vec![racer("slow"), racer("failing"), racer("winner")];
+        let status = race(
+            1,
+            racers,
+            &Config::default(),
+            SegmentIo::default(),
+            |racer, _| {
+                let racer = racer.clone();
+                async move {
+                    match racer.provider.as_str() {
+                        "slow" => {
+                            tokio::time::sleep(Duration::from_secs(60)).await;
+                            Ok(finished(&racer, SegmentState::Completed, 1))
+                        }
+                        "failing" => {
+                            let mut status = finished(&racer, SegmentState::Failed, 300);
+                            status.error_message = Some("Verification failed".to_string());
+                            Ok(status)
+                        }
+                        _ => {
+                            tokio::time::sleep(Duration::from_millis(50)).await;
+                            Ok(finished(&racer, SegmentState::Completed, 200))
+                        }
+                    }
+                }
+            },
+        )
+        .await;
+
+        assert_eq!(status.state, SegmentState::Completed);
+        assert_eq!(status.workspace, PathBuf::from("/nonexistent/winner"));
+        assert_eq!(status.tokens_used, 500);
+        let attempts = &status.results.attempts;
+        assert_eq!(attempts[0].state, SegmentState::Cancelled);
+        assert_eq!(
+            attempts[0].error.as_deref(),
+            Some("Cancelled when winner won")
+        );
+        assert_eq!(attempts[1].state, SegmentState::Failed);
+        assert!(!attempts[1].won);
+        assert!(attempts[2].won);
+    }
+
+    #[tokio::test]
+    async fn test_race_fails_when_no_attempt_passes() {
+        let racers = vec![racer("a"), racer("b")];
+        let status = race(
+            1,
+            racers,
+            &Config::default(),
+            SegmentIo::default(),
+            |racer, _| {
+                let racer = racer.clone();
+                async move {
+                    if racer.provider == "a" {
+                        anyhow::bail!("Failed to spawn g3 process");
+                    }
+                    Ok(finished(&racer, SegmentState::Failed, 10))
+                }
+            },
+        )
+        .await;
+
+        assert_eq!(status.state, SegmentState::Failed);
+        assert_eq!(
+            status.error_message.as_deref(),
+            Some("No attempt passed verification")
+        );
+        assert_eq!(status.workspace, PathBuf::from("/nonexistent/b"));
+        assert_eq!(
+            status.results.attempts[0].error.as_deref(),
+            Some("Failed to spawn g3 process")
+        );
+    }
+
+    #[tokio::test]
+    async fn test_verify_reports_the_failing_command() {
+        let dir = tempfile::TempDir::new().unwrap();
+        assert!(verify(dir.path(), &[]).await.is_ok());
+        assert!(verify(dir.path(), &["true".to_string()]).await.is_ok());
+
+        let error = verify(
+            dir.path(),
+            &[
+                "true".to_string(),
+                "echo 'tests broke' >&2; exit 3".to_string(),
+            ],
+        )
+        .await
+        .unwrap_err()
+        .to_string();
+        assert!(error.starts_with("Verification failed: `echo 'tests broke' >&2; exit 3`"));
+        assert!(error.ends_with(": tests broke"));
+    }
+}
@@ -0,0 +1,329 @@
+//! Routing `request_review` calls between a flock's segments.
+//!
+//! While the segments run, the router watches the flock's review mailbox.
+//! For each request it collects the requester's diff since its workspace was
+//! cloned and runs a one-shot reviewer in the target segment's workspace, so
+//! the review is done with that segment's code, requirements and knowledge
+//! at hand. The reviewer's answer goes back to the waiting requester and
+//! into a [`ReviewThread`] in the flock status.
+
+use anyhow::{Context, Result};
+use chrono::Utc;
+use g3_core::peer_review::{self, ReviewRequest, ReviewResponse, REVIEWS_DIR};
+use std::collections::HashSet;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::process::Command;
+use tokio::sync::watch;
+use tokio::task::JoinSet;
+use tracing::{debug, warn};
+
+use crate::flock::{git_output, WORKER_FILES};
+use crate::status::ReviewThread;
+
+const POLL_INTERVAL: Duration = Duration::from_secs(2);
+
+/// Longest diff sent to a reviewer
+const MAX_DIFF_CHARS: usize = 60_000;
+
+/// A segment that can ask for or give reviews
+#[derive(Debug, Clone)]
+pub(crate) struct ReviewSegment {
+    pub(crate) segment_id: usize,
+    pub(crate) module_name: Option<String>,
+    pub(crate) dir: PathBuf,
+    /// Commit the workspace was cloned at
+    pub(crate) base_commit: Option<String>,
+    pub(crate) provider: Option<String>,
+}
+
+#[derive(Debug, Clone)]
+pub(crate) struct ReviewRouter {
+    pub(crate) flock_workspace: PathBuf,
+    pub(crate) g3_binary: PathBuf,
+    pub(crate) segments: Vec<ReviewSegment>,
+    pub(crate) threads: Arc<Mutex<Vec<ReviewThread>>>,
+}
+
+impl ReviewRouter {
+    /// Answer review requests until `stop` changes. Reviews still running
+    /// then are dropped, since nobody is left waiting for them.
+    pub(crate) async fn run(self, mut stop: watch::Receiver<bool>) {
+        let reviews_dir = self.flock_workspace.join(REVIEWS_DIR);
+        let mut seen = HashSet::new();
+        let mut reviews = JoinSet::new();
+        let mut poll = tokio::time::interval(POLL_INTERVAL);
+
+        loop {
+            tokio::select! {
+                _ = stop.changed() => break,
+                _ = poll.tick() => {}
+            }
+            let requests = match peer_review::unanswered_requests(&reviews_dir) {
+                Ok(requests) => requests,
+                Err(e) => {
+                    warn!("Could not read review requests: {}", e);
+                    continue;
+                }
+            };
+            for request in requests {
+                if seen.insert(request.id.clone()) {
+                    let router = self.clone();
+                    reviews.spawn(async move { router.review(request).await });
+                }
+            }
+        }
+
+        reviews.shutdown().await;
+        let mut threads = self.threads.lock().unwrap_or_else(|e| e.into_inner());
+        for thread in threads.iter_mut().filter(|t| t.completed_at.is_none()) {
+            thread.error = Some("The flock finished before the review did".to_string());
+        }
+    }
+
+    /// Review one request and answer it
+    async fn review(&self, request: ReviewRequest) {
+        debug!(
+            "Segment {} asked {} for a review",
+            request.requester, request.target
+        );
+        self.update(ReviewThread {
+            id: request.id.clone(),
+            requester: request.requester,
+            target: request.target.clone(),
+            reviewer: None,
+            note: request.note.clone(),
+            requested_at: Utc::now(),
+            completed_at: None,
+            comments: None,
+            error: None,
+        });
+
+        let reviewer = self.resolve(&request);
+        let comments = match &reviewer {
+            Ok(reviewer) => self.run_reviewer(&request, reviewer).await,
+            Err(e) => Err(anyhow::anyhow!("{}", e)),
+        };
+        let response = ReviewResponse {
+            id: request.id.clone(),
+            reviewer: reviewer.ok().map(|reviewer| reviewer.segment_id),
+            error: comments.as_ref().err().map(|e| format!("{:#}", e)),
+            comments: comments.ok(),
+        };
+        if let Err(e) = peer_review::respond(&self.flock_workspace.join(REVIEWS_DIR), &response) {
+            warn!("Could not answer review request {}: {}", request.id, e);
+        }
+
+        let mut threads = self.threads.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(thread) = threads.iter_mut().find(|thread| thread.id == request.id) {
+            thread.reviewer = response.reviewer;
+            thread.comments = response.comments;
+            thread.error = response.error;
+            thread.completed_at = Some(Utc::now());
+        }
+    }
+
+    fn update(&self, thread: ReviewThread) {
+        let mut threads = self.threads.lock().unwrap_or_else(|e| e.into_inner());
+        match threads.iter_mut().find(|existing| existing.id == thread.id) {
+            Some(existing) => *existing = thread,
+            None => threads.push(thread),
+        }
+    }
+
+    /// The segment a request names, by number or module name
+    fn resolve(&self, request: &ReviewRequest) -> Result<&ReviewSegment> {
+        let target = request.target.trim();
+        let reviewer = self
+            .segments
+            .iter()
+            .find(|segment| {
+                target.parse::<usize>().ok() == Some(segment.segment_id)
+                    || target.strip_prefix("segment-").and_then(|n| n.parse().ok())
+                        == Some(segment.segment_id)
+                    || segment
+                        .module_name
+                        .as_deref()
+                        .is_some_and(|name| name.eq_ignore_ascii_case(target))
+            })
+            .with_context(|| format!("No segment named '{}' in this flock", target))?;
+        if reviewer.segment_id == request.requester {
+            anyhow::bail!("A segment can't review its own changes");
+        }
+        Ok(reviewer)
+    }
+
+    async fn run_reviewer(
+        &self,
+        request: &ReviewRequest,
+        reviewer: &ReviewSegment,
+    ) -> Result<String> {
+        let requester = self
+            .segments
+            .iter()
+            .find(|segment| segment.segment_id == request.requester)
+            .context("Unknown requesting segment")?;
+        let diff = segment_diff(requester).await?;
+        if diff.trim().is_empty() {
+            anyhow::bail!("Segment {} has no changes to review", requester.segment_id);
+        }
+
+        let mut command = Command::new(&self.g3_binary);
+        command
+            .arg("--workspace")
+            .arg(&reviewer.dir)
+            .arg("--quiet")
+            .arg("--flock-knowledge")
+            .arg(&self.flock_workspace);
+        if let Some(provider) = &reviewer.provider {
+            command.arg("--provider").arg(provider);
+        }
+        let output = command
+            .arg(review_prompt(request, requester, reviewer, &diff))
+            .kill_on_drop(true)
+            .output()
+            .await
+            .context("Failed to run the reviewer")?;
+        if !output.status.success() {
+            anyhow::bail!(
+                "Reviewer failed: {}",
+                String::from_utf8_lossy(&output.stderr).trim()
+            );
+        }
+        Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+    }
+}
+
+/// What a segment changed since it was cloned, committed or not, leaving out
+/// the files the flock and its worker keep
+async fn segment_diff(segment: &ReviewSegment) -> Result<String> {
+    let base = segment.base_commit.as_deref().unwrap_or("HEAD");
+    let excludes: Vec<String> = WORKER_FILES
+        .iter()
+        .map(|file| format!(":(exclude){}", file))
+        .collect();
+    let mut args = vec!["diff", base, "--", "."];
+    args.extend(excludes.iter().map(String::as_str));
+    let mut diff = git_output(&segment.dir, &args).await?;
+
+    let untracked = git_output(
+        &segment.dir,
+        &["ls-files", "--others", "--exclude-standard"],
+    )
+    .await?;
+    let untracked: Vec<&str> = untracked
+        .lines()
+        .filter(|file| {
+            !WORKER_FILES
+                .iter()
+                .any(|worker_file| file.starts_with(worker_file))
+        })
+        .collect();
+    if !untracked.is_empty() {
+        diff.push_str(&format!(
+            "\n\nNew files not yet added to git: {}",
+            untracked.join(", ")
+        ));
+    }
+
+    if diff.len() > MAX_DIFF_CHARS {
+        let mut end = MAX_DIFF_CHARS;
+        while !diff.is_char_boundary(end) {
+            end -= 1;
+        }
+        diff.truncate(end);
+        diff.push_str("\n... (diff truncated)");
+    }
+    Ok(diff)
+}
+
+fn review_prompt(
+    request: &ReviewRequest,
+    requester: &ReviewSegment,
+    reviewer: &ReviewSegment,
+    diff: &str,
+) -> String {
+    let name = |segment: &ReviewSegment| match &segment.module_name {
+        Some(name) => format!("segment {} ({})", segment.segment_id, name),
+        None => format!("segment {}", segment.segment_id),
+    };
+    let note = request
+        .note
+        .as_deref()
+        .map(|note| format!("\nThey ask you to focus on: {}\n", note))
+        .unwrap_or_default();
+    format!(
+        "You are reviewing for {reviewer} of a flock: several agents are building \
+         modules of one project in parallel. This workspace holds your module; its \
+         requirements are in segment-requirements.md. {requester} changed the code \
+         below and asked for your review.\n{note}\n\
+         Check the changes against your module's interfaces and requirements and the \
+         flock's shared decisions: mismatched names, types or signatures, broken \
+         assumptions about your module, and bugs that would surface when the modules \
+         are merged. Do not modify any files. Reply with concise, specific review \
+         comments only, or say that the changes look compatible.\n\n\
+         ```diff\n{diff}\n```",
+        reviewer = name(reviewer),
+        requester = name(requester),
+        note = note,
+        diff = diff,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn router() -> ReviewRouter {
+        let segment = |segment_id: usize, name: &str| ReviewSegment {
+            segment_id,
+            module_name: Some(name.to_string()),
+            dir: PathBuf::from(format!("segment-{}", segment_id)),
+            base_commit: None,
+            provider: None,
+        };
+        ReviewRouter {
+            flock_workspace: PathBuf::from("flock"),
+            g3_binary: PathBuf::from("g3"),
+            segments: vec![segment(1, "parser"), segment(2, "api-server")],
+            threads: Arc::default(),
+        }
+    }
+
+    fn request(requester: usize, target: &str) -> ReviewRequest {
+        ReviewRequest {
+            id: "r1".to_string(),
+            requester,
+            target: target.to_string(),
+            note: None,
+        }
+    }
+
+    #[test]
+    fn test_targets_resolve_by_number_or_name() {
+        let router = router();
+        for target in ["2", "segment-2", "API-Server"] {
+            assert_eq!(router.resolve(&request(1, target)).unwrap().segment_id, 2);
+        }
+        assert!(router.resolve(&request(1, "frontend")).is_err());
+        assert!(router.resolve(&request(1, "parser")).is_err());
+    }
+
+    #[test]
+    fn test_prompt_names_both_segments() {
+        let router = router();
+        let mut request = request(1, "api-server");
+        request.note = Some("the Token type".to_string());
+        let prompt = review_prompt(
+            &request,
+            &router.segments[0],
+            &router.segments[1],
+            "+pub struct Token;",
+        );
+        assert!(prompt.starts_with("You are reviewing for segment 2 (api-server)"));
+        assert!(prompt.contains("segment 1 (parser) changed the code"));
+        assert!(prompt.contains("focus on: the Token type"));
+        assert!(prompt.ends_with("```diff\n+pub struct Token;\n```"));
+    }
+}
@@ -1,10 +1,14 @@
 //! Status tracking for flock mode
 
 use chrono::{DateTime, Utc};
+use g3_core::todo::TodoStats;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::path::PathBuf;
 
+/// Consolidated report written to the flock workspace when a flock completes
+pub const REPORT_FILE: &str = "flock_report.md";
+
 /// Status of an individual segment worker
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SegmentStatus {
@@ -43,6 +47,122 @@ pub struct SegmentStatus {
 
     /// Error message (if failed)
     pub error_message: Option<String>,
+
+    /// Completion of the segment's todo.g3.md, when it has one
+    #[serde(default)]
+    pub todo: Option<TodoStats>,
+
+    /// What the segment produced, for the final report
+    #[serde(default)]
+    pub results: SegmentResults,
+}
+
+/// What a segment produced, gathered when its worker exits
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct SegmentResults {
+    /// Module the segment was given
+    pub module_name: Option<String>,
+
+    /// Changes relative to the commit the workspace was cloned at
+    pub diff: Option<DiffStats>,
+
+    /// The last test run the worker printed
+    pub tests: Option<TestResults>,
+
+    /// Estimated from the tokens used, in USD; a raced segment's includes
+    /// every attempt
+    pub cost_usd: f64,
+
+    /// Unchecked tasks left in the segment's todo.g3.md
+    pub remaining_todos: Vec<String>,
+
+    /// Each provider's attempt, for a raced segment
+    #[serde(default)]
+    pub attempts: Vec<RaceAttempt>,
+}
+
+/// One provider's attempt at a raced segment
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RaceAttempt {
+    pub provider: String,
+    pub workspace: PathBuf,
+    /// Completed for the winner, Cancelled for attempts stopped when it won
+    pub state: SegmentState,
+    pub won: bool,
+    pub tokens_used: u64,
+    pub cost_usd: f64,
+    /// Why the attempt failed or didn't pass verification
+    pub error: Option<String>,
+}
+
+/// Changes a segment made, committed or not
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DiffStats {
+    pub commits: usize,
+    pub files: Vec<String>,
+    pub insertions: usize,
+    pub deletions: usize,
+}
+
+/// Summary of a test run, as printed by `cargo test` or pytest
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TestResults {
+    pub passed: usize,
+    pub failed: usize,
+    pub ignored: usize,
+}
+
+impl TestResults {
+    /// Parse a summary line such as `test result: ok. 12 passed; 0 failed;
+    /// 1 ignored; ...` or `==== 3 passed, 1 failed in 0.52s ====`
+    pub fn parse(line: &str) -> Option<Self> {
+        let line = line.trim();
+        let is_summary = line.starts_with("test result:")
+            || (line.starts_with('=') && (line.contains(" passed") || line.contains(" failed")));
+        if !is_summary {
+            return None;
+        }
+
+        let words: Vec<&str> = line
+            .split(|c: char| c.is_whitespace() || c == ';' || c == ',')
+            .filter(|word| !word.is_empty())
+            .collect();
+        let mut results = Self::default();
+        for pair in words.windows(2) {
+            let Ok(count) = pair[0].parse::<usize>() else {
+                continue;
+            };
+            match pair[1] {
+                "passed" => results.passed += count,
+                "failed" => results.failed += count,
+                "ignored" | "skipped" => results.ignored += count,
+                _ => {}
+            }
+        }
+        Some(results)
+    }
+
+    /// Whether `line` starts a new test run, whose summaries replace the
+    /// previous run's
+    pub fn starts_run(line: &str) -> bool {
+        line.contains("Finished `test` profile") || line.contains("test session starts")
+    }
+
+    pub fn add(&mut self, other: TestResults) {
+        self.passed += other.passed;
+        self.failed += other.failed;
+        self.ignored += other.ignored;
+    }
+}
+
+impl std::fmt::Display for TestResults {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} passed, {} failed", self.passed, self.failed)?;
+        if self.ignored > 0 {
+            write!(f, ", {} ignored", self.ignored)?;
+        }
+        Ok(())
+    }
 }
 
 /// State of a segment worker
@@ -54,6 +174,9 @@ pub enum SegmentState {
     /// Currently running
     Running,
 
+    /// Running, but silent for longer than the watchdog allows
+    Stalled,
+
     /// Completed successfully
     Completed,
 
@@ -69,6 +192,7 @@ impl std::fmt::Display for SegmentState {
         match self {
             SegmentState::Pending => write!(f, "⏳ Pending"),
             SegmentState::Running => write!(f, "🔄 Running"),
+            SegmentState::Stalled => write!(f, "🐢 Stalled"),
             SegmentState::Completed => write!(f, "✅ Completed"),
             SegmentState::Failed => write!(f, "❌ Failed"),
             SegmentState::Cancelled => write!(f, "⚠️  Cancelled"),
@@ -76,6 +200,23 @@ impl std::fmt::Display for SegmentState {
     }
 }
 
+/// One segment's `request_review` call and the answer it got
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ReviewThread {
+    pub id: String,
+    /// Segment that asked for the review
+    pub requester: usize,
+    /// The segment asked for, as the requester named it
+    pub target: String,
+    /// Segment the review ran in, once the target is resolved
+    pub reviewer: Option<usize>,
+    pub note: Option<String>,
+    pub requested_at: DateTime<Utc>,
+    pub completed_at: Option<DateTime<Utc>>,
+    pub comments: Option<String>,
+    pub error: Option<String>,
+}
+
 /// Overall flock status
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct FlockStatus {
@@ -108,6 +249,10 @@ pub struct FlockStatus {
 
     /// Total errors across all segments
     pub total_errors: u64,
+
+    /// Reviews the segments asked each other for
+    #[serde(default)]
+    pub reviews: Vec<ReviewThread>,
 }
 
 impl FlockStatus {
@@ -129,6 +274,7 @@ impl FlockStatus {
             total_tokens: 0,
             total_tool_calls: 0,
             total_errors: 0,
+            reviews: Vec::new(),
         }
     }
 
@@ -212,6 +358,10 @@ impl FlockStatus {
             "\n   • Running: {}",
             self.count_by_state(SegmentState::Running)
         ));
+        report.push_str(&format!(
+            "\n   • Stalled: {}",
+            self.count_by_state(SegmentState::Stalled)
+        ));
         report.push_str(&format!(
             "\n   • Failed: {}",
             self.count_by_state(SegmentState::Failed)
@@ -254,6 +404,10 @@ impl FlockStatus {
                 segment.current_turn, segment.max_turns
             ));
 
+            if let Some(todo) = segment.todo {
+                report.push_str(&format!("\n      TODOs: {}", todo));
+            }
+
             if let Some(ref msg) = segment.last_message {
                 report.push_str(&format!("\n      Last Message: {}", msg));
             }
@@ -267,4 +421,195 @@ impl FlockStatus {
 
         report
     }
+
+    /// The consolidated flock_report.md: per-segment outcomes, changes,
+    /// tests, tokens and cost, files changed by more than one segment, the
+    /// TODOs left and the errors
+    pub fn generate_markdown_report(&self) -> String {
+        let mut segments: Vec<&SegmentStatus> = self.segments.values().collect();
+        segments.sort_by_key(|segment| segment.segment_id);
+        let name = |segment: &SegmentStatus| {
+            segment
+                .results
+                .module_name
+                .clone()
+                .unwrap_or_else(|| format!("segment-{}", segment.segment_id))
+        };
+
+        let duration = self
+            .completed_at
+            .unwrap_or_else(Utc::now)
+            .signed_duration_since(self.started_at);
+        let cost: f64 = segments.iter().map(|s| s.results.cost_usd).sum();
+
+        let mut report = String::from("# Flock Report\n\n");
+        report.push_str(&format!("- **Session:** {}\n", self.session_id));
+        report.push_str(&format!("- **Project:** {}\n", self.project_dir.display()));
+        report.push_str(&format!(
+            "- **Duration:** {:.1}s\n",
+            duration.num_milliseconds() as f64 / 1000.0
+        ));
+        report.push_str(&format!(
+            "- **Segments:** {} ({} completed, {} failed)\n",
+            self.num_segments,
+            self.count_by_state(SegmentState::Completed),
+            self.count_by_state(SegmentState::Failed)
+        ));
+        report.push_str(&format!(
+            "- **Tokens:** {} · **Estimated cost:** ${:.2}\n",
+            self.total_tokens, cost
+        ));
+
+        report.push_str("\n## Segments\n\n");
+        report.push_str(
+            "| # | Module | Status | Turns | Commits | Files | Lines | Tests | Tokens | Cost | TODOs |\n",
+        );
+        report.push_str("|---|---|---|---|---|---|---|---|---|---|---|\n");
+        for segment in &segments {
+            let (commits, files, lines) = match &segment.results.diff {
+                Some(diff) => (
+                    diff.commits.to_string(),
+                    diff.files.len().to_string(),
+                    format!("+{}/-{}", diff.insertions, diff.deletions),
+                ),
+                None => ("-".to_string(), "-".to_string(), "-".to_string()),
+            };
+            report.push_str(&format!(
+                "| {} | {} | {} | {}/{} | {} | {} | {} | {} | {} | ${:.2} | {} |\n",
+                segment.segment_id,
+                name(segment),
+                segment.state,
+                segment.current_turn,
+                segment.max_turns,
+                commits,
+                files,
+                lines,
+                segment
+                    .results
+                    .tests
+                    .map(|tests| tests.to_string())
+                    .unwrap_or_else(|| "-".to_string()),
+                segment.tokens_used,
+                segment.results.cost_usd,
+                segment
+                    .todo
+                    .map(|todo| format!("{}/{}", todo.done, todo.total))
+                    .unwrap_or_else(|| "-".to_string()),
+            ));
+        }
+
+        // Segments work in separate clones, so a file changed by several of
+        // them will conflict when their work is merged
+        let mut changed_by: BTreeMap<&str, Vec<usize>> = BTreeMap::new();
+        for segment in &segments {
+            for file in segment.results.diff.iter().flat_map(|diff| &diff.files) {
+                changed_by.entry(file).or_default().push(segment.segment_id);
+            }
+        }
+        changed_by.retain(|_, ids| ids.len() > 1);
+        report.push_str("\n## Overlapping Changes\n\n");
+        if changed_by.is_empty() {
+            report.push_str("No file was changed by more than one segment.\n");
+        } else {
+            report.push_str("Changed by more than one segment; resolve these when merging:\n\n");
+            for (file, ids) in changed_by {
+                let ids: Vec<String> = ids.iter().map(|id| id.to_string()).collect();
+                report.push_str(&format!("- `{}`: segments {}\n", file, ids.join(", ")));
+            }
+        }
+
+        report.push_str("\n## Remaining TODOs\n\n");
+        let unfinished: Vec<&&SegmentStatus> = segments
+            .iter()
+            .filter(|segment| !segment.results.remaining_todos.is_empty())
+            .collect();
+        if unfinished.is_empty() {
+            report.push_str("None.\n");
+        }
+        for segment in unfinished {
+            report.push_str(&format!(
+                "### Segment {}: {}\n\n",
+                segment.segment_id,
+                name(segment)
+            ));
+            for todo in &segment.results.remaining_todos {
+                report.push_str(&format!("- [ ] {}\n", todo));
+            }
+            report.push('\n');
+        }
+
+        let raced: Vec<&&SegmentStatus> = segments
+            .iter()
+            .filter(|segment| !segment.results.attempts.is_empty())
+            .collect();
+        if !raced.is_empty() {
+            report.push_str("\n## Races\n\n");
+        }
+        for segment in raced {
+            report.push_str(&format!(
+                "### Segment {}: {}\n\n",
+                segment.segment_id,
+                name(segment)
+            ));
+            report.push_str("| Provider | Outcome | Tokens | Cost | Workspace |\n");
+            report.push_str("|---|---|---|---|---|\n");
+            for attempt in &segment.results.attempts {
+                let outcome = match (&attempt.error, attempt.won) {
+                    (_, true) => "🏆 Won".to_string(),
+                    (Some(error), false) => format!("{}: {}", attempt.state, error),
+                    (None, false) => attempt.state.to_string(),
+                };
+                report.push_str(&format!(
+                    "| {} | {} | {} | ${:.2} | {} |\n",
+                    attempt.provider,
+                    outcome,
+                    attempt.tokens_used,
+                    attempt.cost_usd,
+                    attempt.workspace.display()
+                ));
+            }
+            report.push('\n');
+        }
+
+        if !self.reviews.is_empty() {
+            report.push_str("\n## Reviews\n\n");
+            for review in &self.reviews {
+                let reviewer = review
+                    .reviewer
+                    .map(|reviewer| format!("segment {}", reviewer))
+                    .unwrap_or_else(|| review.target.clone());
+                report.push_str(&format!(
+                    "### Segment {} asked {}\n\n",
+                    review.requester, reviewer
+                ));
+                if let Some(note) = &review.note {
+                    report.push_str(&format!("> {}\n\n", note));
+                }
+                match (&review.comments, &review.error) {
+                    (Some(comments), _) => report.push_str(comments.trim()),
+                    (None, Some(error)) => report.push_str(&format!("❌ {}", error)),
+                    (None, None) => report.push_str("(no answer)"),
+                }
+                report.push_str("\n\n");
+            }
+        }
+
+        let failed: Vec<&&SegmentStatus> = segments
+            .iter()
+            .filter(|segment| segment.error_message.is_some())
+            .collect();
+        if !failed.is_empty() {
+            report.push_str("\n## Errors\n\n");
+            for segment in failed {
+                report.push_str(&format!(
+                    "- **Segment {} ({})**: {}\n",
+                    segment.segment_id,
+                    name(segment),
+                    segment.error_message.as_deref().unwrap_or_default()
+                ));
+            }
+        }
+
+        report
+    }
 }
@@ -0,0 +1,120 @@
+//! Operator approval between flock phases.
+//!
+//! A supervised flock stops at each configured [`Barrier`] until the operator
+//! approves, and stops for good if they reject. With a live display the
+//! answer comes through [`Approvals`]; otherwise the operator is asked on the
+//! terminal.
+
+use std::fmt;
+use std::str::FromStr;
+use std::sync::{Arc, Mutex};
+use tokio::sync::oneshot;
+
+/// A point in a flock run where a supervised flock waits for the operator
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Barrier {
+    /// The segments are decided but no workspace exists and no worker has started
+    AfterPlanning,
+    /// Every worker has exited; the segment work is ready to review and merge,
+    /// and the report is not written yet
+    AfterSegments,
+}
+
+impl Barrier {
+    pub const ALL: [Barrier; 2] = [Barrier::AfterPlanning, Barrier::AfterSegments];
+}
+
+impl FromStr for Barrier {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().replace('_', "-").as_str() {
+            "after-planning" => Ok(Self::AfterPlanning),
+            "after-segments" => Ok(Self::AfterSegments),
+            _ => anyhow::bail!(
+                "Unknown flock barrier '{}'. Use after-planning or after-segments",
+                s
+            ),
+        }
+    }
+}
+
+impl fmt::Display for Barrier {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::AfterPlanning => write!(f, "after-planning"),
+            Self::AfterSegments => write!(f, "after-segments"),
+        }
+    }
+}
+
+/// A barrier the flock waits at, with the channel that releases it
+type PendingBarrier = (Barrier, oneshot::Sender<bool>);
+
+/// The operator's side of a supervised flock's barriers
+#[derive(Debug, Clone, Default)]
+pub struct Approvals {
+    pending: Arc<Mutex<Option<PendingBarrier>>>,
+}
+
+impl Approvals {
+    /// The barrier the flock is waiting at, if any
+    pub fn pending(&self) -> Option<Barrier> {
+        let pending = self.pending.lock().unwrap_or_else(|e| e.into_inner());
+        pending.as_ref().map(|(barrier, _)| *barrier)
+    }
+
+    /// Let the flock past the barrier it is waiting at, or stop it; false if
+    /// it isn't waiting
+    pub fn answer(&self, approved: bool) -> bool {
+        let mut pending = self.pending.lock().unwrap_or_else(|e| e.into_inner());
+        match pending.take() {
+            Some((_, decision)) => decision.send(approved).is_ok(),
+            None => false,
+        }
+    }
+
+    /// Start waiting at `barrier`; the receiver gets the operator's answer
+    pub(crate) fn wait(&self, barrier: Barrier) -> oneshot::Receiver<bool> {
+        let (decision, receiver) = oneshot::channel();
+        let mut pending = self.pending.lock().unwrap_or_else(|e| e.into_inner());
+        *pending = Some((barrier, decision));
+        receiver
+    }
+}
+
+/// Whether a line typed at the terminal prompt approves
+pub(crate) fn is_approval(answer: &str) -> bool {
+    matches!(answer.trim().to_lowercase().as_str(), "y" | "yes")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_answer_reaches_waiting_flock() {
+        let approvals = Approvals::default();
+        assert!(!approvals.answer(true));
+
+        let decision = approvals.wait(Barrier::AfterPlanning);
+        assert_eq!(approvals.pending(), Some(Barrier::AfterPlanning));
+        assert!(approvals.answer(false));
+        assert_eq!(approvals.pending(), None);
+        assert!(!decision.await.unwrap());
+    }
+
+    #[test]
+    fn test_barrier_names() {
+        for barrier in Barrier::ALL {
+            assert_eq!(barrier.to_string().parse::<Barrier>().unwrap(), barrier);
+        }
+        assert_eq!(
+            "AFTER_SEGMENTS".parse::<Barrier>().unwrap(),
+            Barrier::AfterSegments
+        );
+        assert!("before-commit".parse::<Barrier>().is_err());
+        assert!(is_approval(" Yes\n"));
+        assert!(!is_approval(""));
+    }
+}
@@ -2,14 +2,20 @@
 
 #[cfg(test)]
 mod tests {
-    use crate::status::{FlockStatus, SegmentState, SegmentStatus};
+    use crate::flock::SegmentInputs;
+    use crate::status::{
+        DiffStats, FlockStatus, RaceAttempt, ReviewThread, SegmentResults, SegmentState,
+        SegmentStatus, TestResults,
+    };
     use chrono::Utc;
+    use g3_core::todo::TodoStats;
     use std::path::PathBuf;
 
     #[test]
     fn test_segment_state_display() {
         assert_eq!(format!("{}", SegmentState::Pending), "⏳ Pending");
         assert_eq!(format!("{}", SegmentState::Running), "🔄 Running");
+        assert_eq!(format!("{}", SegmentState::Stalled), "🐢 Stalled");
         assert_eq!(format!("{}", SegmentState::Completed), "✅ Completed");
         assert_eq!(format!("{}", SegmentState::Failed), "❌ Failed");
         assert_eq!(format!("{}", SegmentState::Cancelled), "⚠️  Cancelled");
@@ -55,6 +61,8 @@ mod tests {
             max_turns: 10,
             last_message: Some("Done".to_string()),
             error_message: None,
+            todo: None,
+            results: SegmentResults::default(),
         };
 
         status.update_segment(1, segment1);
@@ -87,6 +95,8 @@ mod tests {
             max_turns: 10,
             last_message: Some("Done".to_string()),
             error_message: None,
+            todo: None,
+            results: SegmentResults::default(),
         };
 
         let segment2 = SegmentStatus {
@@ -102,6 +112,8 @@ mod tests {
             max_turns: 10,
             last_message: Some("Error".to_string()),
             error_message: Some("Test error".to_string()),
+            todo: None,
+            results: SegmentResults::default(),
         };
 
         status.update_segment(1, segment1);
@@ -139,6 +151,8 @@ mod tests {
             max_turns: 10,
             last_message: None,
             error_message: None,
+            todo: None,
+            results: SegmentResults::default(),
         };
         status.update_segment(1, segment1);
 
@@ -159,6 +173,8 @@ mod tests {
             max_turns: 10,
             last_message: None,
             error_message: None,
+            todo: None,
+            results: SegmentResults::default(),
         };
         status.update_segment(2, segment2);
 
@@ -179,6 +195,8 @@ mod tests {
             max_turns: 10,
             last_message: None,
             error_message: None,
+            todo: None,
+            results: SegmentResults::default(),
         };
         status.update_segment(2, segment2_done);
 
@@ -208,6 +226,8 @@ mod tests {
             max_turns: 10,
             last_message: None,
             error_message: None,
+            todo: None,
+            results: SegmentResults::default(),
         };
 
         let segment2 = SegmentStatus {
@@ -223,6 +243,8 @@ mod tests {
             max_turns: 10,
             last_message: None,
             error_message: Some("Error".to_string()),
+            todo: None,
+            results: SegmentResults::default(),
         };
 
         let segment3 = SegmentStatus {
@@ -238,6 +260,8 @@ mod tests {
             max_turns: 10,
             last_message: None,
             error_message: None,
+            todo: None,
+            results: SegmentResults::default(),
         };
 
         status.update_segment(1, segment1);
@@ -272,6 +296,8 @@ mod tests {
             max_turns: 10,
             last_message: Some("Done".to_string()),
             error_message: None,
+            todo: None,
+            results: SegmentResults::default(),
         };
 
         status.update_segment(1, segment1);
@@ -311,6 +337,8 @@ mod tests {
             max_turns: 10,
             last_message: Some("Done".to_string()),
             error_message: None,
+            todo: Some(TodoStats { total: 4, done: 3 }),
+            results: SegmentResults::default(),
         };
 
         status.update_segment(1, segment1);
@@ -326,5 +354,153 @@ mod tests {
         assert!(report.contains("Total Tokens: 1000"));
         assert!(report.contains("Total Tool Calls: 50"));
         assert!(report.contains("Total Errors: 2"));
+        assert!(report.contains("TODOs: 3/4 tasks done (75%)"));
+    }
+
+    #[test]
+    fn test_segment_inputs_reach_registered_segments() {
+        let inputs = SegmentInputs::default();
+        assert!(!inputs.send(1, "continue"));
+
+        let mut receiver = inputs.register(1);
+        assert!(inputs.send(1, "continue"));
+        assert!(!inputs.send(2, "continue"));
+        assert_eq!(receiver.try_recv().unwrap(), "continue");
+
+        inputs.unregister(1);
+        assert!(!inputs.send(1, "continue"));
+    }
+
+    #[test]
+    fn test_test_results_parse() {
+        assert_eq!(
+            TestResults::parse(
+                "test result: ok. 12 passed; 0 failed; 1 ignored; 0 measured; 0 filtered out"
+            ),
+            Some(TestResults {
+                passed: 12,
+                failed: 0,
+                ignored: 1
+            })
+        );
+        assert_eq!(
+            TestResults::parse("===== 3 passed, 1 failed, 2 skipped in 0.52s ====="),
+            Some(TestResults {
+                passed: 3,
+                failed: 1,
+                ignored: 2
+            })
+        );
+        assert_eq!(TestResults::parse("All 3 checks passed"), None);
+    }
+
+    #[test]
+    fn test_generate_markdown_report() {
+        let mut status = FlockStatus::new(
+            "test-session".to_string(),
+            PathBuf::from("/test/project"),
+            PathBuf::from("/test/workspace"),
+            2,
+        );
+
+        let segment = |segment_id: usize, module: &str, files: &[&str]| SegmentStatus {
+            segment_id,
+            workspace: PathBuf::from(format!("/test/workspace/segment-{}", segment_id)),
+            state: SegmentState::Completed,
+            started_at: Utc::now(),
+            completed_at: Some(Utc::now()),
+            tokens_used: 1000,
+            tool_calls: 10,
+            errors: 0,
+            current_turn: 3,
+            max_turns: 5,
+            last_message: None,
+            error_message: None,
+            todo: Some(TodoStats { total: 2, done: 1 }),
+            results: SegmentResults {
+                module_name: Some(module.to_string()),
+                diff: Some(DiffStats {
+                    commits: 2,
+                    files: files.iter().map(|f| f.to_string()).collect(),
+                    insertions: 40,
+                    deletions: 5,
+                }),
+                tests: Some(TestResults {
+                    passed: 7,
+                    failed: 0,
+                    ignored: 0,
+                }),
+                cost_usd: 0.5,
+                remaining_todos: vec!["2 Write docs".to_string()],
+                attempts: Vec::new(),
+            },
+        };
+        status.update_segment(1, segment(1, "core", &["Cargo.toml", "src/core.rs"]));
+        let mut api = segment(2, "api", &["Cargo.toml", "src/api.rs"]);
+        api.state = SegmentState::Failed;
+        api.error_message = Some("Process exited with status: 1".to_string());
+        api.results.remaining_todos.clear();
+        api.results.attempts = vec![RaceAttempt {
+            provider: "openai.default".to_string(),
+            workspace: PathBuf::from("/test/workspace/segment-2"),
+            state: SegmentState::Failed,
+            won: false,
+            tokens_used: 1000,
+            cost_usd: 0.5,
+            error: Some(
+                "Verification failed: `cargo test` exited with exit status: 101".to_string(),
+            ),
+        }];
+        status.update_segment(2, api);
+
+        let review = |requester: usize, target: &str| ReviewThread {
+            id: format!("segment-{}-1", requester),
+            requester,
+            target: target.to_string(),
+            reviewer: None,
+            note: None,
+            requested_at: Utc::now(),
+            completed_at: Some(Utc::now()),
+            comments: None,
+            error: None,
+        };
+        let mut answered = review(2, "core");
+        answered.reviewer = Some(1);
+        answered.note = Some("The Engine trait".to_string());
+        answered.comments = Some("Engine::run should take &mut self".to_string());
+        let mut unknown = review(1, "frontend");
+        unknown.error = Some("No segment named 'frontend' in this flock".to_string());
+        status.reviews = vec![answered, unknown];
+
+        let report = status.generate_markdown_report();
+        assert!(report.starts_with("# Flock Report\n"));
+        assert!(report.contains("- **Segments:** 2 (1 completed, 1 failed)\n"));
+        assert!(report.contains("- **Tokens:** 2000 · **Estimated cost:** $1.00\n"));
+        assert!(report.contains(
+            "| 1 | core | ✅ Completed | 3/5 | 2 | 2 | +40/-5 | 7 passed, 0 failed | 1000 | $0.50 | 1/2 |"
+        ));
+        assert!(report.contains("- `Cargo.toml`: segments 1, 2\n"));
+        assert!(!report.contains("`src/core.rs`"));
+        // The races section has a heading per segment too
+        let todos = report
+            .split("## Remaining TODOs")
+            .nth(1)
+            .and_then(|rest| rest.split("\n## ").next())
+            .unwrap();
+        assert!(todos.contains("### Segment 1: core\n\n- [ ] 2 Write docs\n"));
+        assert!(!todos.contains("### Segment 2: api"));
+        assert!(report.contains("- **Segment 2 (api)**: Process exited with status: 1\n"));
+        assert!(report.contains(
+            "### Segment 2 asked segment 1\n\n> The Engine trait\n\nEngine::run should take &mut self\n"
+        ));
+        assert!(report.contains(
+            "### Segment 1 asked frontend\n\n❌ No segment named 'frontend' in this flock\n"
+        ));
+        assert!(report.contains(
+            "## Races\n\n### Segment 2: api\n\n| Provider | Outcome | Tokens | Cost | Workspace |\n"
+        ));
+        assert!(report.contains(
+            "| openai.default | ❌ Failed: Verification failed: `cargo test` exited with exit status: 101 | 1000 | $0.50 | /test/workspace/segment-2 |\n"
+        ));
     }
 }
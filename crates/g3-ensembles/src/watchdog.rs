@@ -0,0 +1,202 @@
+//! Liveness watchdog for segment workers.
+//!
+//! A worker prints as it streams tokens and runs tools, so a segment that
+//! prints nothing for the configured time is treated as stalled: its state
+//! becomes [`SegmentState::Stalled`](crate::status::SegmentState::Stalled)
+//! and the configured [`StallAction`] is applied. A nudge that doesn't bring
+//! the worker back, or a restart beyond the limit, aborts the segment.
+
+use std::fmt;
+use std::str::FromStr;
+use std::time::{Duration, Instant};
+
+/// Sent to a stalled worker's stdin by [`StallAction::Nudge`]
+pub const NUDGE_PROMPT: &str = "You have been inactive for a while. Continue working on the \
+    segment requirements, or finish if they are complete.";
+
+/// Restarts allowed per segment before it is aborted
+const DEFAULT_MAX_RESTARTS: usize = 2;
+
+/// What to do when a segment stalls
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum StallAction {
+    /// Write a nudge prompt to the worker; abort if it stays silent
+    #[default]
+    Nudge,
+    /// Kill the worker and start it again in the same workspace
+    Restart,
+    /// Kill the worker and mark the segment failed
+    Abort,
+}
+
+impl FromStr for StallAction {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "nudge" => Ok(Self::Nudge),
+            "restart" => Ok(Self::Restart),
+            "abort" => Ok(Self::Abort),
+            _ => anyhow::bail!("Unknown stall action '{}'. Use nudge, restart or abort", s),
+        }
+    }
+}
+
+impl fmt::Display for StallAction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Nudge => write!(f, "nudge"),
+            Self::Restart => write!(f, "restart"),
+            Self::Abort => write!(f, "abort"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WatchdogConfig {
+    /// How long a segment may go without output before it counts as stalled
+    pub timeout: Duration,
+    pub action: StallAction,
+    /// Restarts allowed before a segment is aborted
+    pub max_restarts: usize,
+}
+
+impl WatchdogConfig {
+    pub fn new(timeout: Duration, action: StallAction) -> Self {
+        Self {
+            timeout,
+            action,
+            max_restarts: DEFAULT_MAX_RESTARTS,
+        }
+    }
+}
+
+/// What the segment runner should do about a stall
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Response {
+    Nudge,
+    Restart,
+    Abort,
+}
+
+/// Watches one segment's output
+#[derive(Debug)]
+pub(crate) struct Watchdog {
+    config: WatchdogConfig,
+    last_activity: Instant,
+    nudged: bool,
+    restarts: usize,
+}
+
+impl Watchdog {
+    pub(crate) fn new(config: WatchdogConfig) -> Self {
+        Self {
+            config,
+            last_activity: Instant::now(),
+            nudged: false,
+            restarts: 0,
+        }
+    }
+
+    pub(crate) fn config(&self) -> &WatchdogConfig {
+        &self.config
+    }
+
+    /// How often to check; often enough to notice a stall soon after the timeout
+    pub(crate) fn check_interval(&self) -> Duration {
+        (self.config.timeout / 4).clamp(Duration::from_secs(1), Duration::from_secs(30))
+    }
+
+    /// The worker printed something
+    pub(crate) fn activity(&mut self, now: Instant) {
+        self.last_activity = now;
+        self.nudged = false;
+    }
+
+    pub(crate) fn restarts(&self) -> usize {
+        self.restarts
+    }
+
+    pub(crate) fn stalled(&self, now: Instant) -> bool {
+        now.duration_since(self.last_activity) >= self.config.timeout
+    }
+
+    /// Decide what to do about a stall noticed at `now`; the worker gets
+    /// another full timeout after a nudge or restart
+    pub(crate) fn respond(&mut self, now: Instant) -> Response {
+        let response = match self.config.action {
+            StallAction::Nudge if !self.nudged => {
+                self.nudged = true;
+                Response::Nudge
+            }
+            StallAction::Restart if self.restarts < self.config.max_restarts => {
+                self.restarts += 1;
+                Response::Restart
+            }
+            _ => Response::Abort,
+        };
+        self.last_activity = now;
+        response
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn new_watchdog(action: StallAction) -> (Watchdog, Instant) {
+        let watchdog = Watchdog::new(WatchdogConfig::new(Duration::from_secs(60), action));
+        let start = watchdog.last_activity;
+        (watchdog, start)
+    }
+
+    #[test]
+    fn test_stall_detection() {
+        let (mut watchdog, start) = new_watchdog(StallAction::Nudge);
+        assert!(!watchdog.stalled(start + Duration::from_secs(59)));
+        assert!(watchdog.stalled(start + Duration::from_secs(60)));
+
+        watchdog.activity(start + Duration::from_secs(50));
+        assert!(!watchdog.stalled(start + Duration::from_secs(100)));
+        assert_eq!(watchdog.check_interval(), Duration::from_secs(15));
+    }
+
+    #[test]
+    fn test_nudge_then_abort() {
+        let (mut watchdog, start) = new_watchdog(StallAction::Nudge);
+        let stalled = start + Duration::from_secs(60);
+        assert_eq!(watchdog.respond(stalled), Response::Nudge);
+        assert!(!watchdog.stalled(stalled + Duration::from_secs(30)));
+        assert_eq!(
+            watchdog.respond(stalled + Duration::from_secs(60)),
+            Response::Abort
+        );
+
+        // Output after a nudge earns another one
+        let (mut watchdog, start) = new_watchdog(StallAction::Nudge);
+        watchdog.respond(start);
+        watchdog.activity(start);
+        assert_eq!(watchdog.respond(start), Response::Nudge);
+    }
+
+    #[test]
+    fn test_restarts_are_limited() {
+        let (mut watchdog, start) = new_watchdog(StallAction::Restart);
+        assert_eq!(watchdog.respond(start), Response::Restart);
+        assert_eq!(watchdog.respond(start), Response::Restart);
+        assert_eq!(watchdog.restarts(), 2);
+        assert_eq!(watchdog.respond(start), Response::Abort);
+
+        let (mut watchdog, start) = new_watchdog(StallAction::Abort);
+        assert_eq!(watchdog.respond(start), Response::Abort);
+    }
+
+    #[test]
+    fn test_stall_action_from_str() {
+        assert_eq!(
+            "Restart".parse::<StallAction>().unwrap(),
+            StallAction::Restart
+        );
+        assert!("ignore".parse::<StallAction>().is_err());
+    }
+}
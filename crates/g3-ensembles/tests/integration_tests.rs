@@ -491,3 +491,46 @@ fn test_git_repo_independence() {
     assert!(segment2.join("file2.txt").exists());
     assert!(!segment2.join("file1.txt").exists());
 }
+
+#[test]
+fn test_listed_segments_are_validated_and_planned() {
+    let project_dir = create_test_project("plan-test");
+    let workspace_dir = TempDir::new().unwrap();
+    let config_path = create_test_config(&workspace_dir);
+    let flock_workspace = workspace_dir.path().join("flock");
+
+    fs::write(
+        project_dir.path().join("flock-segments.json"),
+        r#"[
+            {"module_name": "core", "requirements": "Build the core", "paths": ["src/core"], "budget_usd": 4.0},
+            {"module_name": "api", "requirements": "Build the API", "paths": ["src/api"], "dependencies": ["core"]}
+        ]"#,
+    )
+    .expect("Failed to write segments");
+
+    let config = FlockConfig::new_with_config(
+        project_dir.path().to_path_buf(),
+        flock_workspace.clone(),
+        2,
+        Some(config_path.to_str().unwrap()),
+    )
+    .expect("Failed to create config")
+    .with_budget(10.0);
+    assert_eq!(config.segments.len(), 2);
+    config.validate().expect("Segments should be valid");
+
+    let plan = config.plan();
+    assert!(!plan.partitioned_by_agent);
+    assert_eq!(plan.segments[1].name.as_deref(), Some("api"));
+    assert_eq!(plan.segments[1].dependencies, vec!["core".to_string()]);
+    assert_eq!(plan.segments[1].workspace, flock_workspace.join("segment-2"));
+    let text = plan.to_string();
+    assert!(text.contains("2. api · databricks.default · up to 5 turns"));
+    assert!(text.contains("segment-2/segment-requirements.md"));
+
+    // A leftover workspace and a bigger budget than the flock's are both reported
+    fs::create_dir_all(flock_workspace.join("segment-1")).unwrap();
+    let error = config.with_budget(3.0).validate().unwrap_err().to_string();
+    assert!(error.contains("Segment workspace already exists"));
+    assert!(error.contains("Segment budgets total $4.00, more than the flock budget of $3.00"));
+}
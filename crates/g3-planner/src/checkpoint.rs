@@ -0,0 +1,368 @@
+//! Workspace checkpoints for the coach/player loop
+//!
+//! A checkpoint is a content-addressed copy of every file in the codepath,
+//! taken before a player turn and stored under `.g3/checkpoints/`. Unchanged
+//! files share their stored copy across checkpoints, so taking one per turn
+//! is cheap. Restoring puts the codepath back as it was: changed files are
+//! rewritten with their permissions, new files removed and deleted files
+//! recreated, whether or not the codepath is a git repository.
+//!
+//! Build output and metadata (`.git`, `.g3`, `target`, `node_modules`,
+//! `logs`) and the plan directory are left out, as are files over
+//! [`MAX_FILE_BYTES`], which a restore leaves alone. In a git repository,
+//! files `.gitignore` excludes are left out too.
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// Directories never captured or restored
+const SKIPPED_DIRS: &[&str] = &[".git", ".g3", "target", "node_modules", "logs"];
+
+/// Files larger than this are not captured
+pub const MAX_FILE_BYTES: u64 = 10 * 1024 * 1024;
+
+/// The state of the codepath at one point in time
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Checkpoint {
+    pub id: String,
+    /// Relative path -> SHA-256 of the content
+    pub files: BTreeMap<String, String>,
+    /// Relative path -> Unix permission bits
+    #[serde(default)]
+    pub modes: BTreeMap<String, u32>,
+    /// Relative paths too large to capture
+    pub skipped: Vec<String>,
+}
+
+/// What a restore changed
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RestoreSummary {
+    /// Files rewritten or recreated
+    pub restored: usize,
+    /// Files created since the checkpoint, now removed
+    pub removed: usize,
+}
+
+/// Takes and restores checkpoints of one codepath
+#[derive(Debug, Clone)]
+pub struct CheckpointStore {
+    root: PathBuf,
+    dir: PathBuf,
+    excluded: Vec<PathBuf>,
+}
+
+impl CheckpointStore {
+    /// Checkpoints of `root`, stored in `root/.g3/checkpoints`. Paths in
+    /// `excluded` (e.g. the plan directory) are neither captured nor restored.
+    pub fn new(root: &Path, excluded: Vec<PathBuf>) -> Self {
+        Self {
+            root: root.to_path_buf(),
+            dir: root.join(".g3").join("checkpoints"),
+            excluded,
+        }
+    }
+
+    /// Capture the current state of the codepath
+    pub fn create(&self) -> Result<Checkpoint> {
+        let objects = self.dir.join("objects");
+        fs::create_dir_all(&objects).context("Failed to create checkpoint store")?;
+
+        let mut checkpoint = Checkpoint {
+            id: chrono::Local::now().format("%Y%m%d-%H%M%S%.3f").to_string(),
+            files: BTreeMap::new(),
+            modes: BTreeMap::new(),
+            skipped: Vec::new(),
+        };
+        for (relative, path) in self.files()? {
+            let metadata = fs::metadata(&path)?;
+            if metadata.len() > MAX_FILE_BYTES {
+                checkpoint.skipped.push(relative);
+                continue;
+            }
+            if let Some(mode) = file_mode(&metadata) {
+                checkpoint.modes.insert(relative.clone(), mode);
+            }
+            let content =
+                fs::read(&path).with_context(|| format!("Failed to read {}", path.display()))?;
+            let hash = hex::encode(Sha256::digest(&content));
+            let object = objects.join(&hash);
+            if !object.exists() {
+                fs::write(&object, &content)?;
+            }
+            checkpoint.files.insert(relative, hash);
+        }
+
+        fs::write(
+            self.dir.join(format!("{}.json", checkpoint.id)),
+            serde_json::to_string_pretty(&checkpoint)?,
+        )?;
+        Ok(checkpoint)
+    }
+
+    /// Put the codepath back to `checkpoint`
+    pub fn restore(&self, checkpoint: &Checkpoint) -> Result<RestoreSummary> {
+        let mut summary = RestoreSummary::default();
+        let objects = self.dir.join("objects");
+
+        for (relative, path) in self.files()? {
+            if !checkpoint.files.contains_key(&relative) && !checkpoint.skipped.contains(&relative)
+            {
+                fs::remove_file(&path)
+                    .with_context(|| format!("Failed to remove {}", path.display()))?;
+                summary.removed += 1;
+            }
+        }
+
+        for (relative, hash) in &checkpoint.files {
+            let path = self.root.join(relative);
+            let mode = checkpoint.modes.get(relative).copied();
+            let unchanged = fs::read(&path)
+                .map(|content| hex::encode(Sha256::digest(&content)) == *hash)
+                .unwrap_or(false);
+            let same_mode = mode.is_none()
+                || fs::metadata(&path)
+                    .map(|metadata| file_mode(&metadata) == mode)
+                    .unwrap_or(false);
+            if unchanged && same_mode {
+                continue;
+            }
+            if !unchanged {
+                if let Some(parent) = path.parent() {
+                    fs::create_dir_all(parent)?;
+                }
+                fs::copy(objects.join(hash), &path)
+                    .with_context(|| format!("Failed to restore {}", path.display()))?;
+            }
+            if let Some(mode) = mode {
+                set_file_mode(&path, mode)?;
+            }
+            summary.restored += 1;
+        }
+        Ok(summary)
+    }
+
+    /// Delete every stored checkpoint
+    pub fn clear(&self) -> Result<()> {
+        if self.dir.exists() {
+            fs::remove_dir_all(&self.dir).context("Failed to remove checkpoint store")?;
+        }
+        Ok(())
+    }
+
+    /// Regular files under the root, by path relative to it
+    fn files(&self) -> Result<Vec<(String, PathBuf)>> {
+        match self.git_files() {
+            Some(files) => Ok(files),
+            None => self.walk_files(),
+        }
+    }
+
+    /// Tracked and untracked files git doesn't ignore, or `None` if the root
+    /// isn't in a git repository
+    fn git_files(&self) -> Option<Vec<(String, PathBuf)>> {
+        let output = Command::new("git")
+            .args([
+                "ls-files",
+                "-z",
+                "--cached",
+                "--others",
+                "--exclude-standard",
+            ])
+            .current_dir(&self.root)
+            .output()
+            .ok()?;
+        if !output.status.success() {
+            return None;
+        }
+
+        let mut files: Vec<(String, PathBuf)> = String::from_utf8_lossy(&output.stdout)
+            .split('\0')
+            .filter(|relative| !relative.is_empty())
+            .filter(|relative| {
+                !relative
+                    .split('/')
+                    .any(|component| SKIPPED_DIRS.contains(&component))
+            })
+            .map(|relative| (relative.to_string(), self.root.join(relative)))
+            .filter(|(_, path)| {
+                !self
+                    .excluded
+                    .iter()
+                    .any(|excluded| path.starts_with(excluded))
+            })
+            // Deleted tracked files are listed too; symlinks aren't captured
+            .filter(|(_, path)| {
+                fs::symlink_metadata(path)
+                    .map(|metadata| metadata.is_file())
+                    .unwrap_or(false)
+            })
+            .collect();
+        files.dedup();
+        Some(files)
+    }
+
+    /// Every regular file under the root, outside [`SKIPPED_DIRS`]
+    fn walk_files(&self) -> Result<Vec<(String, PathBuf)>> {
+        let mut files = Vec::new();
+        let mut pending = vec![self.root.clone()];
+        while let Some(dir) = pending.pop() {
+            for entry in
+                fs::read_dir(&dir).with_context(|| format!("Failed to read {}", dir.display()))?
+            {
+                let entry = entry?;
+                let path = entry.path();
+                if self
+                    .excluded
+                    .iter()
+                    .any(|excluded| path.starts_with(excluded))
+                {
+                    continue;
+                }
+                let file_type = entry.file_type()?;
+                if file_type.is_dir() {
+                    if !SKIPPED_DIRS.contains(&entry.file_name().to_string_lossy().as_ref()) {
+                        pending.push(path);
+                    }
+                } else if file_type.is_file() {
+                    let relative = path
+                        .strip_prefix(&self.root)
+                        .unwrap_or(&path)
+                        .to_string_lossy()
+                        .replace('\\', "/");
+                    files.push((relative, path));
+                }
+            }
+        }
+        Ok(files)
+    }
+}
+
+#[cfg(unix)]
+fn file_mode(metadata: &fs::Metadata) -> Option<u32> {
+    use std::os::unix::fs::PermissionsExt;
+    Some(metadata.permissions().mode() & 0o7777)
+}
+
+#[cfg(not(unix))]
+fn file_mode(_metadata: &fs::Metadata) -> Option<u32> {
+    None
+}
+
+#[cfg(unix)]
+fn set_file_mode(path: &Path, mode: u32) -> Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+    fs::set_permissions(path, fs::Permissions::from_mode(mode))
+        .with_context(|| format!("Failed to restore the mode of {}", path.display()))
+}
+
+#[cfg(not(unix))]
+fn set_file_mode(_path: &Path, _mode: u32) -> Result<()> {
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_restore_undoes_changes_additions_and_deletions() {
+        let temp_dir = TempDir::new().unwrap();
+        let root = temp_dir.path();
+        fs::create_dir_all(root.join("src")).unwrap();
+        fs::create_dir_all(root.join("g3-plan")).unwrap();
+        fs::write(root.join("src/main.rs"), "fn main() {}").unwrap();
+        fs::write(root.join("README.md"), "# demo").unwrap();
+
+        let store = CheckpointStore::new(root, vec![root.join("g3-plan")]);
+        let checkpoint = store.create().unwrap();
+        assert_eq!(checkpoint.files.len(), 2);
+
+        fs::write(root.join("src/main.rs"), "fn main() { broken").unwrap();
+        fs::remove_file(root.join("README.md")).unwrap();
+        fs::write(root.join("src/new.rs"), "pub fn new() {}").unwrap();
+        fs::write(root.join("g3-plan/todo.g3.md"), "- [x] done").unwrap();
+
+        let summary = store.restore(&checkpoint).unwrap();
+        assert_eq!(
+            summary,
+            RestoreSummary {
+                restored: 2,
+                removed: 1
+            }
+        );
+        assert_eq!(
+            fs::read_to_string(root.join("src/main.rs")).unwrap(),
+            "fn main() {}"
+        );
+        assert_eq!(
+            fs::read_to_string(root.join("README.md")).unwrap(),
+            "# demo"
+        );
+        assert!(!root.join("src/new.rs").exists());
+        // The plan directory is excluded, so its progress survives
+        assert!(root.join("g3-plan/todo.g3.md").exists());
+
+        store.clear().unwrap();
+        assert!(!root.join(".g3/checkpoints").exists());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_restore_brings_back_file_modes() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let temp_dir = TempDir::new().unwrap();
+        let root = temp_dir.path();
+        let script = root.join("run.sh");
+        fs::write(&script, "#!/bin/sh\n").unwrap();
+        fs::set_permissions(&script, fs::Permissions::from_mode(0o755)).unwrap();
+
+        let store = CheckpointStore::new(root, Vec::new());
+        let checkpoint = store.create().unwrap();
+        assert_eq!(checkpoint.modes["run.sh"], 0o755);
+
+        // A deleted file comes back executable, and a chmod alone is undone
+        fs::remove_file(&script).unwrap();
+        store.restore(&checkpoint).unwrap();
+        assert_eq!(file_mode(&fs::metadata(&script).unwrap()), Some(0o755));
+
+        fs::set_permissions(&script, fs::Permissions::from_mode(0o644)).unwrap();
+        let summary = store.restore(&checkpoint).unwrap();
+        assert_eq!(summary.restored, 1);
+        assert_eq!(file_mode(&fs::metadata(&script).unwrap()), Some(0o755));
+    }
+
+    #[test]
+    fn test_gitignored_files_are_left_out() {
+        let temp_dir = TempDir::new().unwrap();
+        let root = temp_dir.path();
+        let init = Command::new("git")
+            .args(["init", "-q"])
+            .current_dir(root)
+            .status();
+        if !init.map(|status| status.success()).unwrap_or(false) {
+            return;
+        }
+        fs::write(root.join(".gitignore"), "*.bin\nbuild/\n").unwrap();
+        fs::create_dir_all(root.join("build")).unwrap();
+        fs::write(root.join("build/out.o"), "object").unwrap();
+        fs::write(root.join("data.bin"), "blob").unwrap();
+        fs::write(root.join("lib.rs"), "pub fn lib() {}").unwrap();
+
+        let store = CheckpointStore::new(root, Vec::new());
+        let checkpoint = store.create().unwrap();
+        let files: Vec<&str> = checkpoint.files.keys().map(String::as_str).collect();
+        assert_eq!(files, vec![".gitignore", "lib.rs"]);
+
+        // Ignored files appearing later aren't removed by a restore
+        fs::write(root.join("build/new.o"), "object").unwrap();
+        store.restore(&checkpoint).unwrap();
+        assert!(root.join("build/new.o").exists());
+    }
+}
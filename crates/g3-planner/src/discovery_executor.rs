@@ -0,0 +1,288 @@
+//! Sandboxed execution of discovery commands
+//!
+//! The discovery LLM proposes shell commands for exploring the codebase. They
+//! are run here before being handed to the agent: each command must pass the
+//! read-only check in [`g3_core::shell_safety`], runs in its own process
+//! group with a timeout (the whole group is killed when it expires), and the
+//! combined output of all commands is capped by a token budget so discovery
+//! can't flood the context window.
+
+use std::path::Path;
+use std::process::Stdio;
+use std::time::Duration;
+use tokio::process::Command;
+
+/// Marker appended when a command's output is cut to fit the budget
+pub const TRUNCATION_MARKER: &str = "... [output truncated: discovery output budget reached]";
+
+/// Limits applied to discovery command execution
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DiscoveryLimits {
+    /// Maximum run time of a single command
+    pub command_timeout: Duration,
+    /// Estimated tokens allowed for the output of all commands combined
+    pub output_token_budget: usize,
+}
+
+impl Default for DiscoveryLimits {
+    fn default() -> Self {
+        Self {
+            command_timeout: Duration::from_secs(20),
+            output_token_budget: 8000,
+        }
+    }
+}
+
+/// How a discovery command ended
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DiscoveryStatus {
+    /// The command ran to completion (exit code, if any)
+    Completed(Option<i32>),
+    /// The command was killed after the timeout
+    TimedOut,
+    /// The command failed the read-only check and was not run
+    Rejected(Vec<String>),
+    /// The output budget was used up before the command ran
+    Skipped,
+    /// The command could not be started
+    Failed(String),
+}
+
+/// Result of running one discovery command
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DiscoveryResult {
+    pub command: String,
+    pub status: DiscoveryStatus,
+    /// Combined stdout and stderr, truncated to the remaining budget
+    pub output: String,
+}
+
+impl DiscoveryResult {
+    /// Text fed back to the model as the tool result
+    pub fn to_tool_result(&self) -> String {
+        match &self.status {
+            DiscoveryStatus::Completed(Some(0)) | DiscoveryStatus::Completed(None) => {
+                if self.output.is_empty() {
+                    "✅ Command executed successfully".to_string()
+                } else {
+                    self.output.clone()
+                }
+            }
+            DiscoveryStatus::Completed(Some(code)) => {
+                format!("Exit code {}\n{}", code, self.output)
+                    .trim_end()
+                    .to_string()
+            }
+            DiscoveryStatus::TimedOut => "❌ Command timed out".to_string(),
+            DiscoveryStatus::Rejected(violations) => format!(
+                "❌ Command rejected: discovery commands must be read-only ({})",
+                violations.join("; ")
+            ),
+            DiscoveryStatus::Skipped => "[skipped: discovery output budget exhausted]".to_string(),
+            DiscoveryStatus::Failed(error) => format!("❌ Failed to run command: {}", error),
+        }
+    }
+}
+
+/// Rough token estimate (about 4 characters per token)
+pub fn estimate_tokens(text: &str) -> usize {
+    text.chars().count().div_ceil(4)
+}
+
+/// Cut `text` to roughly `max_tokens`, appending [`TRUNCATION_MARKER`] if anything was dropped
+fn truncate_to_budget(text: &str, max_tokens: usize) -> String {
+    if estimate_tokens(text) <= max_tokens {
+        return text.to_string();
+    }
+    let kept: String = text.chars().take(max_tokens * 4).collect();
+    // Prefer cutting at a line boundary
+    let kept = match kept.rfind('\n') {
+        Some(end) if end > 0 => &kept[..end],
+        _ => kept.as_str(),
+    };
+    format!("{}\n{}", kept, TRUNCATION_MARKER)
+}
+
+/// Run the discovery commands in `codebase_path`, in order, within `limits`
+pub async fn run_discovery_commands(
+    codebase_path: &Path,
+    commands: &[String],
+    limits: DiscoveryLimits,
+) -> Vec<DiscoveryResult> {
+    let mut remaining = limits.output_token_budget;
+    let mut results = Vec::with_capacity(commands.len());
+
+    for command in commands {
+        let violations = g3_core::shell_safety::read_only_violations(command);
+        if !violations.is_empty() {
+            results.push(DiscoveryResult {
+                command: command.clone(),
+                status: DiscoveryStatus::Rejected(violations),
+                output: String::new(),
+            });
+            continue;
+        }
+
+        if remaining == 0 {
+            results.push(DiscoveryResult {
+                command: command.clone(),
+                status: DiscoveryStatus::Skipped,
+                output: String::new(),
+            });
+            continue;
+        }
+
+        let (status, output) = run_command(codebase_path, command, limits.command_timeout).await;
+        let output = truncate_to_budget(&output, remaining);
+        remaining = remaining.saturating_sub(estimate_tokens(&output));
+        results.push(DiscoveryResult {
+            command: command.clone(),
+            status,
+            output,
+        });
+    }
+
+    results
+}
+
+async fn run_command(dir: &Path, command: &str, timeout: Duration) -> (DiscoveryStatus, String) {
+    let mut cmd = if cfg!(target_os = "windows") {
+        let mut cmd = Command::new("cmd");
+        cmd.args(["/C", command]);
+        cmd
+    } else {
+        let mut cmd = Command::new("sh");
+        cmd.args(["-c", command]);
+        cmd
+    };
+    cmd.current_dir(dir)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true);
+    #[cfg(unix)]
+    cmd.process_group(0);
+
+    let child = match cmd.spawn() {
+        Ok(child) => child,
+        Err(e) => return (DiscoveryStatus::Failed(e.to_string()), String::new()),
+    };
+    let pid = child.id();
+
+    match tokio::time::timeout(timeout, child.wait_with_output()).await {
+        Ok(Ok(output)) => {
+            let mut text = String::from_utf8_lossy(&output.stdout).into_owned();
+            text.push_str(&String::from_utf8_lossy(&output.stderr));
+            (
+                DiscoveryStatus::Completed(output.status.code()),
+                text.trim_end().to_string(),
+            )
+        }
+        Ok(Err(e)) => (DiscoveryStatus::Failed(e.to_string()), String::new()),
+        // Dropping the future kills the child (kill_on_drop); anything it
+        // started goes with the rest of its process group
+        Err(_) => {
+            if let Some(pid) = pid {
+                crate::verification::kill_process_group(pid);
+            }
+            (DiscoveryStatus::TimedOut, String::new())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn commands(list: &[&str]) -> Vec<String> {
+        list.iter().map(|c| c.to_string()).collect()
+    }
+
+    #[tokio::test]
+    async fn test_runs_read_only_commands_and_rejects_writes() {
+        let temp_dir = TempDir::new().unwrap();
+        std::fs::write(temp_dir.path().join("a.txt"), "hello\n").unwrap();
+
+        let results = run_discovery_commands(
+            temp_dir.path(),
+            &commands(&["cat a.txt", "touch b.txt", "ls missing"]),
+            DiscoveryLimits::default(),
+        )
+        .await;
+
+        assert_eq!(results[0].status, DiscoveryStatus::Completed(Some(0)));
+        assert_eq!(results[0].to_tool_result(), "hello");
+        assert!(matches!(results[1].status, DiscoveryStatus::Rejected(_)));
+        assert!(!temp_dir.path().join("b.txt").exists());
+        assert!(matches!(results[2].status, DiscoveryStatus::Completed(Some(code)) if code != 0));
+        assert!(results[2].to_tool_result().starts_with("Exit code"));
+    }
+
+    #[tokio::test]
+    async fn test_output_budget_truncates_and_skips() {
+        let temp_dir = TempDir::new().unwrap();
+        let lines: String = (0..200).map(|i| format!("line {}\n", i)).collect();
+        std::fs::write(temp_dir.path().join("big.txt"), lines).unwrap();
+
+        let limits = DiscoveryLimits {
+            output_token_budget: 50,
+            ..DiscoveryLimits::default()
+        };
+        let results = run_discovery_commands(
+            temp_dir.path(),
+            &commands(&["cat big.txt", "cat big.txt"]),
+            limits,
+        )
+        .await;
+
+        assert!(results[0].output.ends_with(TRUNCATION_MARKER));
+        assert!(results[0].output.starts_with("line 0\n"));
+        assert_eq!(results[1].status, DiscoveryStatus::Skipped);
+        assert_eq!(
+            results[1].to_tool_result(),
+            "[skipped: discovery output budget exhausted]"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_command_timeout() {
+        let temp_dir = TempDir::new().unwrap();
+        let limits = DiscoveryLimits {
+            command_timeout: Duration::from_millis(200),
+            ..DiscoveryLimits::default()
+        };
+        // `tail -f` never exits on its own
+        std::fs::write(temp_dir.path().join("log.txt"), "").unwrap();
+        let results =
+            run_discovery_commands(temp_dir.path(), &commands(&["tail -f log.txt"]), limits).await;
+
+        assert_eq!(results[0].status, DiscoveryStatus::TimedOut);
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_timeout_kills_the_process_group() {
+        let temp_dir = TempDir::new().unwrap();
+        let limits = DiscoveryLimits {
+            command_timeout: Duration::from_millis(200),
+            ..DiscoveryLimits::default()
+        };
+        // A log name no other process uses, so pgrep finds only this tail
+        let log = format!(
+            "{}.log",
+            temp_dir.path().file_name().unwrap().to_string_lossy()
+        );
+        std::fs::write(temp_dir.path().join(&log), "").unwrap();
+        let command = format!("tail -f {} | cat", log);
+        let results = run_discovery_commands(temp_dir.path(), &[command], limits).await;
+        assert_eq!(results[0].status, DiscoveryStatus::TimedOut);
+
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        let pgrep = std::process::Command::new("pgrep")
+            .args(["-f", &log])
+            .output()
+            .unwrap();
+        assert!(!pgrep.status.success(), "tail is still running");
+    }
+}
@@ -0,0 +1,109 @@
+//! External editor integration for planning mode
+//!
+//! Lets the user edit requirements files in `$VISUAL` / `$EDITOR` without
+//! leaving the planner.
+
+use anyhow::{Context, Result};
+use std::path::Path;
+use std::process::Command;
+
+/// Editor used when neither `$VISUAL` nor `$EDITOR` is set
+#[cfg(windows)]
+const FALLBACK_EDITOR: &str = "notepad";
+#[cfg(not(windows))]
+const FALLBACK_EDITOR: &str = "vi";
+
+/// Resolve the editor command line from `$VISUAL`, then `$EDITOR`, then the fallback.
+///
+/// The value is split on whitespace so settings like `code --wait` work.
+pub fn editor_command() -> Vec<String> {
+    resolve_editor(std::env::var("VISUAL").ok(), std::env::var("EDITOR").ok())
+}
+
+fn resolve_editor(visual: Option<String>, editor: Option<String>) -> Vec<String> {
+    [visual, editor]
+        .into_iter()
+        .flatten()
+        .map(|value| {
+            value
+                .split_whitespace()
+                .map(String::from)
+                .collect::<Vec<_>>()
+        })
+        .find(|parts| !parts.is_empty())
+        .unwrap_or_else(|| vec![FALLBACK_EDITOR.to_string()])
+}
+
+/// Open a file in the user's editor and wait for it to exit
+pub fn open_in_editor(path: &Path) -> Result<()> {
+    open_with(&editor_command(), path)
+}
+
+/// Open a file with the given editor command line and wait for it to exit
+pub fn open_with(command: &[String], path: &Path) -> Result<()> {
+    let (program, args) = command
+        .split_first()
+        .ok_or_else(|| anyhow::anyhow!("No editor configured"))?;
+
+    let status = Command::new(program)
+        .args(args)
+        .arg(path)
+        .status()
+        .with_context(|| format!("Failed to launch editor '{}'", program))?;
+
+    if !status.success() {
+        anyhow::bail!("Editor '{}' exited with {}", program, status);
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_resolve_editor_precedence() {
+        assert_eq!(
+            resolve_editor(Some("code --wait".to_string()), Some("vim".to_string())),
+            vec!["code", "--wait"]
+        );
+        assert_eq!(resolve_editor(None, Some("nano".to_string())), vec!["nano"]);
+        // Blank values are skipped
+        assert_eq!(
+            resolve_editor(Some("  ".to_string()), Some("nano".to_string())),
+            vec!["nano"]
+        );
+        assert_eq!(resolve_editor(None, None), vec![FALLBACK_EDITOR]);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_open_with_runs_editor_on_file() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let temp_dir = TempDir::new().unwrap();
+        let script = temp_dir.path().join("fake-editor.sh");
+        std::fs::write(&script, "#!/bin/sh\necho \"edited\" >> \"$1\"\n").unwrap();
+        std::fs::set_permissions(&script, std::fs::Permissions::from_mode(0o755)).unwrap();
+
+        let file = temp_dir.path().join("new_requirements.md");
+        std::fs::write(&file, "original\n").unwrap();
+
+        open_with(&[script.display().to_string()], &file).unwrap();
+
+        assert_eq!(
+            std::fs::read_to_string(&file).unwrap(),
+            "original\nedited\n"
+        );
+    }
+
+    #[test]
+    fn test_open_with_missing_editor_fails() {
+        let temp_dir = TempDir::new().unwrap();
+        let file = temp_dir.path().join("file.md");
+        let result = open_with(&["definitely-not-an-editor-g3".to_string()], &file);
+        assert!(result.is_err());
+    }
+}
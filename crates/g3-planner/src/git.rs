@@ -4,13 +4,27 @@
 //! - Repository detection
 //! - Branch information
 //! - Dirty file detection
-//! - Staging and committing
+//! - Staging and committing, recovering from commit hooks that modify files
+//!
+//! With the `gix` feature (enabled by default) repository queries, status,
+//! staging and committing run in-process on gitoxide, falling back to the
+//! `git` CLI when gix cannot handle the repository. Commits go through the
+//! CLI whenever commit hooks are installed, since gix does not run hooks.
+//!
+//! Failed git commands are reported as [`G3Error::Git`]; a failed commit as
+//! a [`CommitError`].
 
 use anyhow::{Context, Result};
-use std::path::Path;
+use g3_providers::G3Error;
+use std::collections::HashMap;
+use std::fmt;
+use std::path::{Path, PathBuf};
 use std::process::Command;
 
-/// Files and directories to exclude from staging
+#[cfg(feature = "gix")]
+mod gix_backend;
+
+/// Files and directories always excluded from staging, even without a .gitignore
 const EXCLUDE_PATTERNS: &[&str] = &[
     "target/",
     "node_modules/",
@@ -34,17 +48,34 @@ const EXCLUDE_PATTERNS: &[&str] = &[
 
 /// Check if the given path is within a git repository
 pub fn check_git_repo(codepath: &Path) -> Result<bool> {
-    let output = Command::new("git")
+    #[cfg(feature = "gix")]
+    if gix_backend::is_repo(codepath) {
+        return Ok(true);
+    }
+
+    let output = match Command::new("git")
         .args(["rev-parse", "--git-dir"])
         .current_dir(codepath)
         .output()
-        .context("Failed to execute git command")?;
+    {
+        Ok(output) => output,
+        // gix found no repository and there is no git binary to ask instead
+        #[cfg(feature = "gix")]
+        Err(_) => return Ok(false),
+        #[cfg(not(feature = "gix"))]
+        Err(e) => return Err(e).context("Failed to execute git command"),
+    };
 
     Ok(output.status.success())
 }
 
 /// Get the root directory of the git repository
 pub fn get_repo_root(codepath: &Path) -> Result<String> {
+    #[cfg(feature = "gix")]
+    if let Ok(root) = gix_backend::repo_root(codepath) {
+        return Ok(root);
+    }
+
     let output = Command::new("git")
         .args(["rev-parse", "--show-toplevel"])
         .current_dir(codepath)
@@ -52,7 +83,7 @@ pub fn get_repo_root(codepath: &Path) -> Result<String> {
         .context("Failed to get git repo root")?;
 
     if !output.status.success() {
-        anyhow::bail!("Not in a git repository");
+        return Err(G3Error::git("Not in a git repository").into());
     }
 
     let root = String::from_utf8(output.stdout)
@@ -65,6 +96,18 @@ pub fn get_repo_root(codepath: &Path) -> Result<String> {
 
 /// Get the current git branch name
 pub fn get_current_branch(codepath: &Path) -> Result<String> {
+    #[cfg(feature = "gix")]
+    if let Ok(branch) = gix_backend::current_branch(codepath) {
+        match branch {
+            Some(branch) => return Ok(branch),
+            None => {
+                if let Ok(sha) = gix_backend::short_head_sha(codepath) {
+                    return Ok(format!("(detached HEAD at {})", sha));
+                }
+            }
+        }
+    }
+
     let output = Command::new("git")
         .args(["branch", "--show-current"])
         .current_dir(codepath)
@@ -74,7 +117,7 @@ pub fn get_current_branch(codepath: &Path) -> Result<String> {
     if !output.status.success() {
         // Might be in detached HEAD state
         let stderr = String::from_utf8_lossy(&output.stderr);
-        anyhow::bail!("Failed to get branch name: {}", stderr);
+        return Err(G3Error::git(format!("Failed to get branch name: {}", stderr)).into());
     }
 
     let branch = String::from_utf8(output.stdout)
@@ -101,8 +144,56 @@ pub fn get_current_branch(codepath: &Path) -> Result<String> {
     }
 }
 
+/// Check whether a local branch with the given name exists
+pub fn branch_exists(codepath: &Path, name: &str) -> Result<bool> {
+    #[cfg(feature = "gix")]
+    if let Ok(exists) = gix_backend::branch_exists(codepath, name) {
+        return Ok(exists);
+    }
+
+    let output = Command::new("git")
+        .args([
+            "rev-parse",
+            "--verify",
+            "--quiet",
+            &format!("refs/heads/{}", name),
+        ])
+        .current_dir(codepath)
+        .output()
+        .context("Failed to check for git branch")?;
+
+    Ok(output.status.success())
+}
+
+/// Create a new branch at HEAD and switch to it.
+/// Uncommitted changes are carried over to the new branch.
+pub fn create_branch(codepath: &Path, name: &str) -> Result<()> {
+    let output = Command::new("git")
+        .args(["checkout", "-b", name])
+        .current_dir(codepath)
+        .output()
+        .context("Failed to create git branch")?;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(G3Error::git(format!(
+            "Failed to create branch {}: {}",
+            name,
+            stderr.trim()
+        ))
+        .into());
+    }
+
+    Ok(())
+}
+
 /// Get the current HEAD SHA
 pub fn get_head_sha(codepath: &Path) -> Result<String> {
+    #[cfg(feature = "gix")]
+    if let Ok(sha) = gix_backend::head_sha(codepath) {
+        return Ok(sha);
+    }
+
     let output = Command::new("git")
         .args(["rev-parse", "HEAD"])
         .current_dir(codepath)
@@ -111,7 +202,7 @@ pub fn get_head_sha(codepath: &Path) -> Result<String> {
 
     if !output.status.success() {
         let stderr = String::from_utf8_lossy(&output.stderr);
-        anyhow::bail!("Failed to get HEAD SHA: {}", stderr);
+        return Err(G3Error::git(format!("Failed to get HEAD SHA: {}", stderr)).into());
     }
 
     let sha = String::from_utf8(output.stdout)
@@ -166,29 +257,10 @@ impl DirtyFiles {
 /// Check for untracked, uncommitted, or dirty files
 /// Optionally ignores files matching a given path pattern
 pub fn check_dirty_files(codepath: &Path, ignore_pattern: Option<&str>) -> Result<DirtyFiles> {
-    let output = Command::new("git")
-        .args(["status", "--porcelain"])
-        .current_dir(codepath)
-        .output()
-        .context("Failed to check git status")?;
-
-    if !output.status.success() {
-        let stderr = String::from_utf8_lossy(&output.stderr);
-        anyhow::bail!("Failed to check git status: {}", stderr);
-    }
-
-    let status_output = String::from_utf8(output.stdout)
-        .context("Invalid UTF-8 in git output")?;
-
     let mut result = DirtyFiles::default();
 
-    for line in status_output.lines() {
-        if line.len() < 3 {
-            continue;
-        }
-
-        let status = &line[0..2];
-        let file = line[3..].trim();
+    for (status, file) in porcelain_status(codepath)? {
+        let (status, file) = (status.as_str(), file.as_str());
 
         // Check if this file should be ignored
         if let Some(pattern) = ignore_pattern {
@@ -215,82 +287,372 @@ pub fn check_dirty_files(codepath: &Path, ignore_pattern: Option<&str>) -> Resul
     Ok(result)
 }
 
-/// Check if a file should be excluded from staging based on patterns
-fn should_exclude(path: &str) -> bool {
-    for pattern in EXCLUDE_PATTERNS {
+/// Changed files as `git status --porcelain` reports them: the two-letter
+/// index/worktree code and the path
+fn porcelain_status(codepath: &Path) -> Result<Vec<(String, String)>> {
+    #[cfg(feature = "gix")]
+    if let Ok(entries) = gix_backend::status(codepath) {
+        return Ok(entries);
+    }
+
+    let output = Command::new("git")
+        .args(["status", "--porcelain"])
+        .current_dir(codepath)
+        .output()
+        .context("Failed to check git status")?;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(G3Error::git(format!("Failed to check git status: {}", stderr)).into());
+    }
+
+    let status_output = String::from_utf8(output.stdout).context("Invalid UTF-8 in git output")?;
+    Ok(status_output
+        .lines()
+        .filter(|line| line.len() >= 3)
+        .map(|line| (line[0..2].to_string(), line[3..].trim().to_string()))
+        .collect())
+}
+
+/// Work done in the repository since a recorded commit
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GitProgress {
+    /// The commit recorded when implementation started
+    pub recorded_head: String,
+    /// The current HEAD
+    pub current_head: String,
+    /// Whether the recorded commit still exists in the repository
+    pub recorded_head_found: bool,
+    /// One-line summaries of commits made since the recorded commit, newest first
+    pub commits: Vec<String>,
+    /// `git diff --stat` of the working tree against the recorded commit
+    pub diff_stat: String,
+    /// Untracked files that the diff does not include
+    pub untracked: Vec<String>,
+}
+
+impl GitProgress {
+    /// Whether anything changed since the recorded commit
+    pub fn has_changes(&self) -> bool {
+        !self.commits.is_empty() || !self.diff_stat.trim().is_empty() || !self.untracked.is_empty()
+    }
+
+    pub fn to_display_string(&self) -> String {
+        let short = |sha: &str| sha[..12.min(sha.len())].to_string();
+        let mut lines = vec![format!(
+            "Work since implementation started (GIT HEAD {}):",
+            short(&self.recorded_head)
+        )];
+
+        if !self.recorded_head_found {
+            lines.push(
+                "  The recorded commit no longer exists (history was rewritten?)".to_string(),
+            );
+            return lines.join("\n");
+        }
+
+        if !self.has_changes() {
+            lines.push("  No changes yet".to_string());
+            return lines.join("\n");
+        }
+
+        if !self.commits.is_empty() {
+            lines.push(format!(
+                "  {} commit(s), HEAD is now {}:",
+                self.commits.len(),
+                short(&self.current_head)
+            ));
+            for commit in &self.commits {
+                lines.push(format!("    {}", commit));
+            }
+        }
+
+        if !self.diff_stat.trim().is_empty() {
+            lines.push("  Changes:".to_string());
+            for line in self.diff_stat.lines() {
+                lines.push(format!("    {}", line.trim_start()));
+            }
+        }
+
+        if !self.untracked.is_empty() {
+            lines.push(format!("  Untracked files: {}", self.untracked.join(", ")));
+        }
+
+        lines.join("\n")
+    }
+}
+
+/// Summarize what changed since `recorded_head`: commits made after it and
+/// the diff of the working tree against it.
+pub fn progress_since(codepath: &Path, recorded_head: &str) -> Result<GitProgress> {
+    let current_head = get_head_sha(codepath)?;
+    let untracked = check_dirty_files(codepath, None)?.untracked;
+
+    let found = Command::new("git")
+        .args(["cat-file", "-e", &format!("{}^{{commit}}", recorded_head)])
+        .current_dir(codepath)
+        .output()
+        .context("Failed to look up recorded commit")?
+        .status
+        .success();
+
+    if !found {
+        return Ok(GitProgress {
+            recorded_head: recorded_head.to_string(),
+            current_head,
+            recorded_head_found: false,
+            commits: Vec::new(),
+            diff_stat: String::new(),
+            untracked,
+        });
+    }
+
+    let log_output = Command::new("git")
+        .args(["log", "--oneline", &format!("{}..HEAD", recorded_head)])
+        .current_dir(codepath)
+        .output()
+        .context("Failed to list commits since recorded HEAD")?;
+    let commits = String::from_utf8_lossy(&log_output.stdout)
+        .lines()
+        .map(|line| line.to_string())
+        .collect();
+
+    let diff_output = Command::new("git")
+        .args(["diff", "--stat", recorded_head])
+        .current_dir(codepath)
+        .output()
+        .context("Failed to diff against recorded HEAD")?;
+    if !diff_output.status.success() {
+        let stderr = String::from_utf8_lossy(&diff_output.stderr);
+        return Err(G3Error::git(format!(
+            "Failed to diff against {}: {}",
+            recorded_head, stderr
+        ))
+        .into());
+    }
+    let diff_stat = String::from_utf8_lossy(&diff_output.stdout)
+        .trim_end()
+        .to_string();
+
+    Ok(GitProgress {
+        recorded_head: recorded_head.to_string(),
+        current_head,
+        recorded_head_found: true,
+        commits,
+        diff_stat,
+        untracked,
+    })
+}
+
+/// Find the first pattern matching a path.
+///
+/// Patterns ending in `/` match a directory anywhere in the path, patterns
+/// starting with `*` match a suffix, and anything else matches a file name.
+fn matching_pattern<'a, I>(path: &str, patterns: I) -> Option<&'a str>
+where
+    I: IntoIterator<Item = &'a str>,
+{
+    patterns.into_iter().find(|pattern| {
         if pattern.ends_with('/') {
             // Directory pattern
             let dir_name = pattern.trim_end_matches('/');
-            if path.contains(&format!("/{}/", dir_name)) || path.starts_with(&format!("{}/", dir_name)) {
-                return true;
-            }
+            path.contains(&format!("/{}/", dir_name)) || path.starts_with(&format!("{}/", dir_name))
         } else if pattern.starts_with('*') {
             // Wildcard pattern
             let suffix = pattern.trim_start_matches('*');
-            if path.ends_with(suffix) {
-                return true;
-            }
+            path.ends_with(suffix)
         } else {
             // Exact match
-            if path == *pattern || path.ends_with(&format!("/{}", pattern)) {
-                return true;
+            path == *pattern || path.ends_with(&format!("/{}", pattern))
+        }
+    })
+}
+
+/// Get the built-in pattern excluding a file from staging, if any
+fn builtin_exclusion(path: &str) -> Option<&'static str> {
+    matching_pattern(path, EXCLUDE_PATTERNS.iter().copied())
+}
+
+/// The rule that caused a file to be excluded from staging
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ExclusionRule {
+    /// Matched a .gitignore (or other git exclude file) pattern
+    GitIgnore {
+        source: String,
+        line: usize,
+        pattern: String,
+    },
+    /// Matched a `[planner] extra_excludes` pattern
+    Config(String),
+    /// Matched one of the built-in artifact patterns
+    Builtin(&'static str),
+}
+
+impl fmt::Display for ExclusionRule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ExclusionRule::GitIgnore {
+                source,
+                line,
+                pattern,
+            } => {
+                write!(f, "{}:{}: {}", source, line, pattern)
             }
+            ExclusionRule::Config(pattern) => write!(f, "extra_excludes: {}", pattern),
+            ExclusionRule::Builtin(pattern) => write!(f, "built-in: {}", pattern),
         }
     }
-    false
 }
 
-/// Stage files for commit, excluding temporary/artifact files
-/// Stages all files in the specified directory plus any modified/new code files
-pub fn stage_files(codepath: &Path, plan_dir: &Path) -> Result<StagingResult> {
-    let mut result = StagingResult::default();
+/// A file left out of staging, with the rule that excluded it
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExcludedFile {
+    pub path: String,
+    pub rule: ExclusionRule,
+}
 
-    // First, stage all files in the g3-plan directory
-    let plan_dir_str = plan_dir.to_string_lossy();
-    let add_plan_output = Command::new("git")
-        .args(["add", &plan_dir_str])
+/// Ask git which of the given paths are ignored, and by which rule.
+///
+/// Uses `--no-index` so that tracked files matching an ignore pattern are
+/// reported too. Paths matched only by a negated (`!`) pattern are not ignored.
+fn check_ignored(codepath: &Path, paths: &[&str]) -> Result<HashMap<String, ExclusionRule>> {
+    let mut ignored = HashMap::new();
+    if paths.is_empty() {
+        return Ok(ignored);
+    }
+
+    let output = Command::new("git")
+        .args(["check-ignore", "--verbose", "--no-index", "--"])
+        .args(paths)
         .current_dir(codepath)
         .output()
-        .context("Failed to stage g3-plan directory")?;
+        .context("Failed to run git check-ignore")?;
+
+    // Exit code 1 just means nothing was ignored
+    if !output.status.success() && output.status.code() != Some(1) {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(G3Error::git(format!("git check-ignore failed: {}", stderr)).into());
+    }
 
-    if !add_plan_output.status.success() {
-        let stderr = String::from_utf8_lossy(&add_plan_output.stderr);
-        // Don't fail if directory doesn't exist yet
-        if !stderr.contains("did not match any files") {
-            anyhow::bail!("Failed to stage g3-plan directory: {}", stderr);
+    let stdout = String::from_utf8(output.stdout).context("Invalid UTF-8 in git output")?;
+    for line in stdout.lines() {
+        if let Some((path, rule)) = parse_check_ignore_line(line) {
+            ignored.insert(path, rule);
         }
     }
 
-    // Get list of all changed files
-    let status_output = Command::new("git")
-        .args(["status", "--porcelain"])
-        .current_dir(codepath)
-        .output()
-        .context("Failed to get git status")?;
+    Ok(ignored)
+}
 
-    let status_str = String::from_utf8(status_output.stdout)
-        .context("Invalid UTF-8 in git output")?;
+/// Parse a `git check-ignore --verbose` line: `<source>:<line>:<pattern>\t<path>`
+fn parse_check_ignore_line(line: &str) -> Option<(String, ExclusionRule)> {
+    let (rule_part, path) = line.split_once('\t')?;
 
-    // Stage files that aren't excluded
-    for line in status_str.lines() {
-        if line.len() < 3 {
-            continue;
+    // The source may itself contain ':' (e.g. Windows drive letters), so look
+    // for the first ":<digits>:" separator
+    let bytes = rule_part.as_bytes();
+    let mut search_from = 0;
+    while let Some(offset) = rule_part[search_from..].find(':') {
+        let colon = search_from + offset;
+        let digits_end = rule_part[colon + 1..]
+            .find(|c: char| !c.is_ascii_digit())
+            .map(|i| colon + 1 + i)
+            .unwrap_or(rule_part.len());
+        if digits_end > colon + 1 && bytes.get(digits_end) == Some(&b':') {
+            let pattern = &rule_part[digits_end + 1..];
+            if pattern.starts_with('!') {
+                return None;
+            }
+            let rule = ExclusionRule::GitIgnore {
+                source: rule_part[..colon].to_string(),
+                line: rule_part[colon + 1..digits_end].parse().ok()?,
+                pattern: pattern.to_string(),
+            };
+            return Some((path.to_string(), rule));
         }
+        search_from = colon + 1;
+    }
 
-        let status = &line[0..2];
-        let file = line[3..].trim();
+    None
+}
+
+/// Stage files for commit, excluding temporary/artifact files
+/// Stages all files in the specified directory plus any modified/new code files
+pub fn stage_files(codepath: &Path, plan_dir: &Path) -> Result<StagingResult> {
+    stage_files_with_excludes(codepath, plan_dir, &[])
+}
 
-        // Skip already staged files
-        if !status.starts_with(' ') && status != "??" {
-            continue;
+/// Stage files for commit, excluding files that match .gitignore rules,
+/// `extra_excludes` patterns, or the built-in artifact patterns.
+///
+/// Each excluded file is reported together with the rule that matched it.
+pub fn stage_files_with_excludes(
+    codepath: &Path,
+    plan_dir: &Path,
+    extra_excludes: &[String],
+) -> Result<StagingResult> {
+    let mut result = StagingResult::default();
+
+    // First, stage all files in the plan directory
+    if plan_dir_staged_with_gix(codepath, plan_dir).is_none() {
+        let plan_dir_str = plan_dir.to_string_lossy();
+        let add_plan_output = Command::new("git")
+            .args(["add", &plan_dir_str])
+            .current_dir(codepath)
+            .output()
+            .context("Failed to stage plan directory")?;
+
+        if !add_plan_output.status.success() {
+            let stderr = String::from_utf8_lossy(&add_plan_output.stderr);
+            // Don't fail if directory doesn't exist yet
+            if !stderr.contains("did not match any files") {
+                return Err(
+                    G3Error::git(format!("Failed to stage plan directory: {}", stderr)).into(),
+                );
+            }
         }
+    }
+
+    // Collect unstaged files, skipping already staged ones
+    let status = porcelain_status(codepath)?;
+    let candidates: Vec<&str> = status
+        .iter()
+        .filter(|(code, _)| code.starts_with(' ') || code == "??")
+        .map(|(_, file)| file.as_str())
+        .collect();
 
-        // Check if this file should be excluded
-        if should_exclude(file) {
-            result.excluded.push(file.to_string());
-            continue;
+    let ignored = check_ignored(codepath, &candidates)?;
+
+    // Stage files that aren't excluded
+    let mut to_stage = Vec::new();
+    for file in candidates {
+        let rule = if let Some(rule) = ignored.get(file) {
+            Some(rule.clone())
+        } else if let Some(pattern) =
+            matching_pattern(file, extra_excludes.iter().map(String::as_str))
+        {
+            Some(ExclusionRule::Config(pattern.to_string()))
+        } else {
+            builtin_exclusion(file).map(ExclusionRule::Builtin)
+        };
+
+        if let Some(rule) = rule {
+            result.excluded.push(ExcludedFile {
+                path: file.to_string(),
+                rule,
+            });
+        } else {
+            to_stage.push(file);
         }
+    }
 
+    #[cfg(feature = "gix")]
+    if gix_backend::add(codepath, &to_stage).is_ok() {
+        result.staged = to_stage.iter().map(|file| file.to_string()).collect();
+        return Ok(result);
+    }
+
+    for file in to_stage {
         // Stage the file
         let add_output = Command::new("git")
             .args(["add", file])
@@ -314,6 +676,10 @@ pub fn stage_files(codepath: &Path, plan_dir: &Path) -> Result<StagingResult> {
 /// `stage_files()` call (to write the GIT COMMIT entry) but BEFORE `git commit`.
 /// Without this re-staging, the GIT COMMIT entry would not be included in the commit.
 pub fn stage_plan_dir(codepath: &Path, plan_dir: &Path) -> Result<()> {
+    if plan_dir_staged_with_gix(codepath, plan_dir).is_some() {
+        return Ok(());
+    }
+
     let plan_dir_str = plan_dir.to_string_lossy();
     let add_output = Command::new("git")
         .args(["add", &plan_dir_str])
@@ -323,22 +689,116 @@ pub fn stage_plan_dir(codepath: &Path, plan_dir: &Path) -> Result<()> {
 
     if !add_output.status.success() {
         let stderr = String::from_utf8_lossy(&add_output.stderr);
-        anyhow::bail!("Failed to re-stage g3-plan directory: {}", stderr);
+        return Err(
+            G3Error::git(format!("Failed to re-stage g3-plan directory: {}", stderr)).into(),
+        );
     }
 
     Ok(())
 }
 
+/// Stage every change under `plan_dir` in-process, or `None` if gix could not
+/// (the caller then runs `git add` on it)
+fn plan_dir_staged_with_gix(codepath: &Path, plan_dir: &Path) -> Option<()> {
+    #[cfg(feature = "gix")]
+    {
+        let root = Path::new(&gix_backend::repo_root(codepath).ok()?)
+            .canonicalize()
+            .ok()?;
+        let dir = codepath.join(plan_dir).canonicalize().ok()?;
+        let relative = dir.strip_prefix(root).ok()?;
+        let prefix = format!("{}/", relative.to_str()?.replace('\\', "/"));
+        let status = gix_backend::status(codepath).ok()?;
+        let files: Vec<&str> = status
+            .iter()
+            .map(|(_, file)| file.as_str())
+            .filter(|file| prefix == "/" || file.starts_with(&prefix))
+            .collect();
+        gix_backend::add(codepath, &files).ok()
+    }
+    #[cfg(not(feature = "gix"))]
+    {
+        let _ = (codepath, plan_dir);
+        None
+    }
+}
+
 /// Result of staging operation
 #[derive(Debug, Default)]
 pub struct StagingResult {
     pub staged: Vec<String>,
-    pub excluded: Vec<String>,
+    pub excluded: Vec<ExcludedFile>,
     pub failed: Vec<String>,
 }
 
+/// Hooks run by `git commit` that can modify files or reject the commit
+const COMMIT_HOOKS: &[&str] = &["pre-commit", "prepare-commit-msg", "commit-msg"];
+
+/// Options for [`commit_with_options`]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct CommitOptions {
+    /// Pass `--no-verify`, skipping the pre-commit and commit-msg hooks
+    pub no_verify: bool,
+}
+
+/// A successful commit
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CommitResult {
+    pub sha: String,
+    /// Files modified by hooks that were re-staged before the commit succeeded
+    pub restaged: Vec<String>,
+}
+
+/// Why `git commit` failed
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CommitError {
+    /// A hook rejected the commit (after re-staging any files it modified)
+    HookRejected {
+        /// Hooks installed in the repository
+        hooks: Vec<String>,
+        /// Files the hooks modified
+        modified_files: Vec<String>,
+        /// Combined stdout and stderr of the failed commit
+        output: String,
+    },
+    /// The commit failed for another reason
+    Failed { output: String },
+}
+
+impl fmt::Display for CommitError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CommitError::HookRejected { hooks, output, .. } => {
+                write!(
+                    f,
+                    "Git commit rejected by hooks ({}): {}",
+                    hooks.join(", "),
+                    output
+                )
+            }
+            CommitError::Failed { output } => write!(f, "Git commit failed: {}", output),
+        }
+    }
+}
+
+impl std::error::Error for CommitError {}
+
 /// Make a git commit with the given summary and description
 pub fn commit(codepath: &Path, summary: &str, description: &str) -> Result<String> {
+    Ok(commit_with_options(codepath, summary, description, CommitOptions::default())?.sha)
+}
+
+/// Make a git commit, recovering from hooks that modify files.
+///
+/// If the commit fails and the hooks changed files in the working tree (e.g.
+/// formatters), those files are re-staged and the commit is retried once.
+/// Failures are returned as a [`CommitError`] inside the `anyhow::Error`.
+pub fn commit_with_options(
+    codepath: &Path,
+    summary: &str,
+    description: &str,
+    options: CommitOptions,
+) -> Result<CommitResult> {
     // Combine summary and description into full commit message
     let full_message = if description.is_empty() {
         summary.to_string()
@@ -346,19 +806,151 @@ pub fn commit(codepath: &Path, summary: &str, description: &str) -> Result<Strin
         format!("{}\n\n{}", summary, description)
     };
 
-    let output = Command::new("git")
-        .args(["commit", "-m", &full_message])
+    let hooks = installed_hooks(codepath);
+    // gix runs no hooks, so any installed hook sends the commit to the CLI
+    #[cfg(feature = "gix")]
+    if hooks.is_empty() && !hooks_dir(codepath).is_some_and(|dir| dir.join("post-commit").is_file())
+    {
+        if let Ok(sha) = gix_backend::commit(codepath, &full_message) {
+            return Ok(CommitResult {
+                sha,
+                restaged: Vec::new(),
+            });
+        }
+    }
+
+    let unstaged_before = unstaged_files(codepath)?;
+    let output = run_commit(codepath, &full_message, options)?;
+    if output.status.success() {
+        return Ok(CommitResult {
+            sha: get_head_sha(codepath)?,
+            restaged: Vec::new(),
+        });
+    }
+
+    let hooks = if options.no_verify { Vec::new() } else { hooks };
+    if hooks.is_empty() {
+        return Err(CommitError::Failed {
+            output: command_output(&output),
+        }
+        .into());
+    }
+
+    // Files the hooks modified in the working tree, e.g. by reformatting them
+    let modified_files: Vec<String> = unstaged_files(codepath)?
+        .into_iter()
+        .filter(|file| !unstaged_before.contains(file))
+        .collect();
+    if modified_files.is_empty() {
+        return Err(CommitError::HookRejected {
+            hooks,
+            modified_files,
+            output: command_output(&output),
+        }
+        .into());
+    }
+
+    let add_output = Command::new("git")
+        .arg("add")
+        .arg("--")
+        .args(&modified_files)
         .current_dir(codepath)
         .output()
-        .context("Failed to make git commit")?;
+        .context("Failed to re-stage files modified by git hooks")?;
+    if !add_output.status.success() {
+        return Err(G3Error::git(format!(
+            "Failed to re-stage files modified by git hooks: {}",
+            String::from_utf8_lossy(&add_output.stderr)
+        ))
+        .into());
+    }
 
+    let output = run_commit(codepath, &full_message, options)?;
     if !output.status.success() {
-        let stderr = String::from_utf8_lossy(&output.stderr);
-        anyhow::bail!("Git commit failed: {}", stderr);
+        return Err(CommitError::HookRejected {
+            hooks,
+            modified_files,
+            output: command_output(&output),
+        }
+        .into());
     }
 
-    // Get the commit SHA
-    get_head_sha(codepath)
+    Ok(CommitResult {
+        sha: get_head_sha(codepath)?,
+        restaged: modified_files,
+    })
+}
+
+fn run_commit(
+    codepath: &Path,
+    message: &str,
+    options: CommitOptions,
+) -> Result<std::process::Output> {
+    let mut command = Command::new("git");
+    command.args(["commit", "-m", message]);
+    if options.no_verify {
+        command.arg("--no-verify");
+    }
+    command
+        .current_dir(codepath)
+        .output()
+        .context("Failed to make git commit")
+}
+
+/// Combined, trimmed stdout and stderr of a command
+fn command_output(output: &std::process::Output) -> String {
+    let mut text = String::from_utf8_lossy(&output.stdout).into_owned();
+    text.push_str(&String::from_utf8_lossy(&output.stderr));
+    text.trim().to_string()
+}
+
+/// Tracked files with unstaged changes
+fn unstaged_files(codepath: &Path) -> Result<Vec<String>> {
+    #[cfg(feature = "gix")]
+    if let Ok(status) = gix_backend::status(codepath) {
+        return Ok(status
+            .into_iter()
+            .filter(|(code, _)| code != "??" && !code.ends_with(' '))
+            .map(|(_, file)| file)
+            .collect());
+    }
+
+    let output = Command::new("git")
+        .args(["diff", "--name-only"])
+        .current_dir(codepath)
+        .output()
+        .context("Failed to list unstaged changes")?;
+    Ok(String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .map(str::to_string)
+        .collect())
+}
+
+/// Commit hooks installed in the repository (honours `core.hooksPath`)
+fn installed_hooks(codepath: &Path) -> Vec<String> {
+    let Some(hooks_dir) = hooks_dir(codepath) else {
+        return Vec::new();
+    };
+    COMMIT_HOOKS
+        .iter()
+        .filter(|hook| hooks_dir.join(hook).is_file())
+        .map(|hook| hook.to_string())
+        .collect()
+}
+
+/// Directory git runs hooks from
+fn hooks_dir(codepath: &Path) -> Option<PathBuf> {
+    #[cfg(feature = "gix")]
+    if let Ok(dir) = gix_backend::hooks_dir(codepath) {
+        return Some(dir);
+    }
+
+    let output = Command::new("git")
+        .args(["rev-parse", "--git-path", "hooks"])
+        .current_dir(codepath)
+        .output()
+        .ok()?;
+    Some(codepath.join(String::from_utf8_lossy(&output.stdout).trim()))
 }
 
 #[cfg(test)]
@@ -367,35 +959,110 @@ mod tests {
 
     #[test]
     fn test_should_exclude_target() {
-        assert!(should_exclude("target/debug/something"));
-        assert!(should_exclude("some/path/target/release/bin"));
+        assert!(builtin_exclusion("target/debug/something").is_some());
+        assert!(builtin_exclusion("some/path/target/release/bin").is_some());
     }
 
     #[test]
     fn test_should_exclude_node_modules() {
-        assert!(should_exclude("node_modules/package/index.js"));
-        assert!(should_exclude("frontend/node_modules/react/index.js"));
+        assert!(builtin_exclusion("node_modules/package/index.js").is_some());
+        assert!(builtin_exclusion("frontend/node_modules/react/index.js").is_some());
     }
 
     #[test]
     fn test_should_exclude_log_files() {
-        assert!(should_exclude("app.log"));
-        assert!(should_exclude("logs/debug.log"));
+        assert!(builtin_exclusion("app.log").is_some());
+        assert!(builtin_exclusion("logs/debug.log").is_some());
     }
 
     #[test]
     fn test_should_exclude_temp_files() {
-        assert!(should_exclude("file.tmp"));
-        assert!(should_exclude("file.bak"));
-        assert!(should_exclude("file.swp"));
+        assert!(builtin_exclusion("file.tmp").is_some());
+        assert!(builtin_exclusion("file.bak").is_some());
+        assert!(builtin_exclusion("file.swp").is_some());
     }
 
     #[test]
     fn test_should_not_exclude_normal_files() {
-        assert!(!should_exclude("src/main.rs"));
-        assert!(!should_exclude("Cargo.toml"));
-        assert!(!should_exclude("README.md"));
-        assert!(!should_exclude("package.json"));
+        assert!(builtin_exclusion("src/main.rs").is_none());
+        assert!(builtin_exclusion("Cargo.toml").is_none());
+        assert!(builtin_exclusion("README.md").is_none());
+        assert!(builtin_exclusion("package.json").is_none());
+    }
+
+    #[test]
+    fn test_matching_pattern_reports_rule() {
+        let extra = ["*.generated.rs".to_string(), "fixtures/".to_string()];
+        let patterns = || extra.iter().map(String::as_str);
+        assert_eq!(
+            matching_pattern("src/api.generated.rs", patterns()),
+            Some("*.generated.rs")
+        );
+        assert_eq!(
+            matching_pattern("tests/fixtures/data.json", patterns()),
+            Some("fixtures/")
+        );
+        assert_eq!(matching_pattern("src/main.rs", patterns()), None);
+    }
+
+    #[test]
+    fn test_parse_check_ignore_line() {
+        let (path, rule) = parse_check_ignore_line(".gitignore:3:/dist/\tdist/bundle.js").unwrap();
+        assert_eq!(path, "dist/bundle.js");
+        assert_eq!(
+            rule,
+            ExclusionRule::GitIgnore {
+                source: ".gitignore".to_string(),
+                line: 3,
+                pattern: "/dist/".to_string(),
+            }
+        );
+        assert_eq!(rule.to_string(), ".gitignore:3: /dist/");
+
+        // Sources with colons (Windows paths) and patterns with colons
+        let (_, rule) = parse_check_ignore_line("C:\\repo\\.gitignore:12:a:b\ta:b").unwrap();
+        assert_eq!(
+            rule,
+            ExclusionRule::GitIgnore {
+                source: "C:\\repo\\.gitignore".to_string(),
+                line: 12,
+                pattern: "a:b".to_string(),
+            }
+        );
+
+        // Negated patterns mean the file is NOT ignored
+        assert!(parse_check_ignore_line(".gitignore:4:!keep.log\tkeep.log").is_none());
+        assert!(parse_check_ignore_line("garbage").is_none());
+    }
+
+    #[test]
+    fn test_git_progress_display() {
+        let mut progress = GitProgress {
+            recorded_head: "0123456789abcdef0123".to_string(),
+            current_head: "fedcba9876543210fedc".to_string(),
+            recorded_head_found: true,
+            commits: vec!["fedcba9 Add parser".to_string()],
+            diff_stat:
+                " src/parser.rs | 12 ++++++++----\n 1 file changed, 8 insertions(+), 4 deletions(-)"
+                    .to_string(),
+            untracked: vec!["src/new.rs".to_string()],
+        };
+
+        let display = progress.to_display_string();
+        assert!(display.contains("GIT HEAD 0123456789ab"));
+        assert!(display.contains("1 commit(s), HEAD is now fedcba987654"));
+        assert!(display.contains("fedcba9 Add parser"));
+        assert!(display.contains("src/parser.rs | 12"));
+        assert!(display.contains("Untracked files: src/new.rs"));
+
+        progress.commits.clear();
+        progress.diff_stat.clear();
+        progress.untracked.clear();
+        assert!(!progress.has_changes());
+        assert!(progress.to_display_string().contains("No changes yet"));
+
+        progress.recorded_head_found = false;
+        assert!(progress.to_display_string().contains("no longer exists"));
     }
 
     #[test]
@@ -0,0 +1,492 @@
+//! In-process git backend built on gix (gitoxide)
+//!
+//! Used for repository queries, status, staging and committing so planning
+//! mode works without a `git` executable in PATH. Every function returns an
+//! error when gix cannot answer (e.g. a split index or an unmerged path);
+//! callers then fall back to the CLI.
+
+use anyhow::{bail, Context, Result};
+use gix::bstr::{BStr, BString, ByteSlice};
+use gix::index::entry::{Flags, Mode, Stage, Stat};
+use gix::status::index_worktree::iter::Summary;
+use std::collections::{BTreeMap, HashMap};
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+fn open(codepath: &Path) -> Result<gix::Repository> {
+    gix::discover(codepath).context("Failed to open git repository with gix")
+}
+
+/// Whether the path is inside a git repository
+pub fn is_repo(codepath: &Path) -> bool {
+    open(codepath).is_ok()
+}
+
+/// Root of the working tree
+pub fn repo_root(codepath: &Path) -> Result<String> {
+    let repo = open(codepath)?;
+    let root = repo
+        .work_dir()
+        .ok_or_else(|| anyhow::anyhow!("Repository has no working tree"))?;
+    Ok(root.display().to_string())
+}
+
+/// Short name of the checked-out branch, or `None` when HEAD is detached
+pub fn current_branch(codepath: &Path) -> Result<Option<String>> {
+    let repo = open(codepath)?;
+    let head_name = repo.head_name().context("Failed to read HEAD")?;
+    Ok(head_name.map(|name| name.shorten().to_string()))
+}
+
+/// Full SHA of HEAD
+pub fn head_sha(codepath: &Path) -> Result<String> {
+    let repo = open(codepath)?;
+    let id = repo.head_id().context("Failed to resolve HEAD")?;
+    Ok(id.to_string())
+}
+
+/// Abbreviated SHA of HEAD
+pub fn short_head_sha(codepath: &Path) -> Result<String> {
+    let repo = open(codepath)?;
+    let id = repo.head_id().context("Failed to resolve HEAD")?;
+    let prefix = id.shorten().context("Failed to abbreviate HEAD SHA")?;
+    Ok(prefix.to_string())
+}
+
+/// Whether a local branch with the given name exists
+pub fn branch_exists(codepath: &Path, name: &str) -> Result<bool> {
+    let repo = open(codepath)?;
+    let reference = repo
+        .try_find_reference(format!("refs/heads/{}", name).as_str())
+        .context("Failed to look up branch")?;
+    Ok(reference.is_some())
+}
+
+/// Changed paths as `git status --porcelain` reports them: the two-letter
+/// index/worktree code and the path relative to the working tree root.
+/// Untracked directories are listed file by file.
+pub fn status(codepath: &Path) -> Result<Vec<(String, String)>> {
+    let repo = open(codepath)?;
+    let index = repo.index_or_empty().context("Failed to read the index")?;
+    let head = head_entries(&repo)?;
+
+    // (index against HEAD, worktree against index) per path
+    let mut codes: BTreeMap<BString, (char, char)> = BTreeMap::new();
+    for entry in index.entries() {
+        if entry.stage() != Stage::Unconflicted || entry.flags.contains(Flags::INTENT_TO_ADD) {
+            bail!("Unmerged and intent-to-add paths are left to the git CLI");
+        }
+        let path = entry.path(&index);
+        let code = match head.get(path) {
+            None => 'A',
+            Some(&(id, mode)) if id != entry.id || mode != entry.mode => 'M',
+            Some(_) => continue,
+        };
+        codes.entry(path.to_owned()).or_insert((' ', ' ')).0 = code;
+    }
+    for path in head.keys() {
+        if index.entry_by_path(path.as_ref()).is_none() {
+            codes.entry(path.clone()).or_insert((' ', ' ')).0 = 'D';
+        }
+    }
+
+    let changes = repo
+        .status(gix::progress::Discard)
+        .context("Failed to prepare the status")?
+        .untracked_files(gix::status::UntrackedFiles::Files)
+        .into_index_worktree_iter(Vec::new())
+        .context("Failed to compute the status")?;
+    for change in changes {
+        let change = change.context("Failed to compute the status")?;
+        let code = match change.summary() {
+            None => continue,
+            Some(Summary::Added) => {
+                codes.insert(change.rela_path().to_owned(), ('?', '?'));
+                continue;
+            }
+            Some(Summary::Removed) => 'D',
+            Some(Summary::Modified | Summary::TypeChange) => 'M',
+            Some(other) => bail!("Status {:?} is left to the git CLI", other),
+        };
+        codes
+            .entry(change.rela_path().to_owned())
+            .or_insert((' ', ' '))
+            .1 = code;
+    }
+
+    // Like git, tracked paths first, then untracked ones
+    let (untracked, mut entries): (Vec<_>, Vec<_>) = codes
+        .into_iter()
+        .map(|(path, (x, y))| (format!("{}{}", x, y), path.to_str_lossy().into_owned()))
+        .partition(|(code, _)| code == "??");
+    entries.extend(untracked);
+    Ok(entries)
+}
+
+/// Blob id and mode of every file in HEAD's tree, empty on an unborn branch
+fn head_entries(repo: &gix::Repository) -> Result<HashMap<BString, (gix::ObjectId, Mode)>> {
+    if repo.head().context("Failed to read HEAD")?.is_unborn() {
+        return Ok(HashMap::new());
+    }
+    let tree = repo
+        .head_tree_id()
+        .context("Failed to resolve HEAD's tree")?;
+    let head_index = repo
+        .index_from_tree(&tree)
+        .context("Failed to read HEAD's tree")?;
+    Ok(head_index
+        .entries()
+        .iter()
+        .map(|entry| (entry.path(&head_index).to_owned(), (entry.id, entry.mode)))
+        .collect())
+}
+
+/// Stage files like `git add -- <paths>`: write their contents (after the
+/// repository's clean filters) as blobs and record them in the index, or
+/// drop deleted files from it. Paths are relative to the working tree root.
+pub fn add(codepath: &Path, paths: &[&str]) -> Result<()> {
+    let repo = open(codepath)?;
+    let root = repo
+        .work_dir()
+        .ok_or_else(|| anyhow::anyhow!("Repository has no working tree"))?
+        .to_path_buf();
+    let mut index = repo.open_index().context("Failed to read the index")?;
+    if index.link().is_some() || index.is_sparse() {
+        bail!("Split and sparse indexes are left to the git CLI");
+    }
+    let racy_since = index.timestamp();
+    let (mut filters, _) = repo
+        .filter_pipeline(None)
+        .context("Failed to load the repository's filters")?;
+
+    for &path in paths {
+        let rela_path: &BStr = path.as_bytes().as_bstr();
+        let full_path = root.join(path);
+        let Ok(metadata) = gix::index::fs::Metadata::from_path_no_follow(&full_path) else {
+            index.remove_entries(|_, entry_path, _| entry_path == rela_path);
+            continue;
+        };
+        let (contents, mode) = if metadata.is_symlink() {
+            let target = std::fs::read_link(&full_path)?;
+            (
+                gix::path::into_bstr(target).into_owned().into(),
+                Mode::SYMLINK,
+            )
+        } else if metadata.is_file() {
+            let file = std::fs::File::open(&full_path)
+                .with_context(|| format!("Failed to read {}", path))?;
+            let mut contents = Vec::new();
+            filters
+                .convert_to_git(file, Path::new(path), &index)
+                .with_context(|| format!("Failed to filter {}", path))?
+                .read_to_end(&mut contents)?;
+            let mode = if metadata.is_executable() {
+                Mode::FILE_EXECUTABLE
+            } else {
+                Mode::FILE
+            };
+            (contents, mode)
+        } else {
+            bail!("{} is not a file", path);
+        };
+        let id = repo.write_blob(contents)?.detach();
+        let stat = Stat::from_fs(&metadata)?;
+
+        match index.entry_index_by_path(rela_path) {
+            Ok(position) => {
+                let entry = &mut index.entries_mut()[position];
+                if entry.stage() != Stage::Unconflicted {
+                    bail!("{} is unmerged", path);
+                }
+                entry.id = id;
+                entry.mode = mode;
+                entry.stat = stat;
+            }
+            Err(_) => {
+                index.dangerously_push_entry(stat, id, Flags::empty(), mode, rela_path);
+                index.sort_entries();
+            }
+        }
+    }
+
+    // Rewriting the index moves its timestamp past entries whose files may
+    // have changed within the same second, which would then look clean.
+    // Like git, zero their size so the next status hashes them.
+    let staged: Vec<&BStr> = paths.iter().map(|path| path.as_bytes().as_bstr()).collect();
+    let (entries, path_backing) = index.entries_mut_and_pathbacking();
+    for entry in entries {
+        if entry.stat.is_racy(racy_since, Default::default())
+            && !staged.contains(&entry.path_in(path_backing))
+        {
+            entry.stat.size = 0;
+        }
+    }
+
+    // The tree cache no longer matches the entries; git rebuilds it
+    index
+        .write(gix::index::write::Options {
+            extensions: gix::index::write::Extensions::Given {
+                tree_cache: false,
+                end_of_index_entry: true,
+            },
+            ..Default::default()
+        })
+        .context("Failed to write the index")?;
+    Ok(())
+}
+
+/// Commit the index onto HEAD like `git commit -m <message>`, without running
+/// hooks. Fails when nothing is staged. Returns the new commit's SHA.
+pub fn commit(codepath: &Path, message: &str) -> Result<String> {
+    let repo = open(codepath)?;
+    let index = repo.index_or_empty().context("Failed to read the index")?;
+    if index.entries().is_empty() {
+        bail!("Nothing to commit");
+    }
+
+    let mut root = TreeNode::default();
+    for entry in index.entries() {
+        if entry.stage() != Stage::Unconflicted || entry.flags.contains(Flags::INTENT_TO_ADD) {
+            bail!("Unmerged and intent-to-add paths are left to the git CLI");
+        }
+        let mode = entry
+            .mode
+            .to_tree_entry_mode()
+            .ok_or_else(|| anyhow::anyhow!("Unsupported index entry mode"))?;
+        let path = entry.path(&index);
+        let mut components = path.split_str("/").peekable();
+        let mut node = &mut root;
+        while let Some(name) = components.next() {
+            let name = BString::from(name);
+            if components.peek().is_none() {
+                node.files.insert(name, (entry.id, mode));
+            } else {
+                node = node.dirs.entry(name).or_default();
+            }
+        }
+    }
+    let tree = write_tree(&repo, &root)?;
+
+    let parent = if repo.head().context("Failed to read HEAD")?.is_unborn() {
+        None
+    } else {
+        let head_tree = repo
+            .head_tree_id()
+            .context("Failed to resolve HEAD's tree")?;
+        if head_tree == tree {
+            bail!("Nothing to commit");
+        }
+        Some(repo.head_id().context("Failed to resolve HEAD")?.detach())
+    };
+    let id = repo
+        .commit("HEAD", message, tree, parent)
+        .context("Failed to create the commit")?;
+    Ok(id.to_string())
+}
+
+/// A directory of the tree being built from the index
+#[derive(Default)]
+struct TreeNode {
+    files: BTreeMap<BString, (gix::ObjectId, gix::objs::tree::EntryMode)>,
+    dirs: BTreeMap<BString, TreeNode>,
+}
+
+fn write_tree(repo: &gix::Repository, node: &TreeNode) -> Result<gix::ObjectId> {
+    let mut entries = Vec::new();
+    for (name, (oid, mode)) in &node.files {
+        entries.push(gix::objs::tree::Entry {
+            mode: *mode,
+            filename: name.clone(),
+            oid: *oid,
+        });
+    }
+    for (name, dir) in &node.dirs {
+        entries.push(gix::objs::tree::Entry {
+            mode: gix::objs::tree::EntryKind::Tree.into(),
+            filename: name.clone(),
+            oid: write_tree(repo, dir)?,
+        });
+    }
+    // Git's order, where directories sort as if their names ended in '/'
+    entries.sort();
+    let id = repo
+        .write_object(&gix::objs::Tree { entries })
+        .context("Failed to write a tree")?;
+    Ok(id.detach())
+}
+
+/// Directory git runs hooks from, honouring `core.hooksPath`
+pub fn hooks_dir(codepath: &Path) -> Result<PathBuf> {
+    let repo = open(codepath)?;
+    let configured = repo
+        .config_snapshot()
+        .trusted_path("core.hooksPath")
+        .transpose()
+        .context("Invalid core.hooksPath")?;
+    Ok(match configured {
+        Some(path) => match repo.work_dir() {
+            Some(root) => root.join(path),
+            None => path.into_owned(),
+        },
+        None => repo.common_dir().join("hooks"),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::process::Command;
+    use tempfile::TempDir;
+
+    fn git(repo: &Path, args: &[&str]) -> String {
+        let output = Command::new("git")
+            .args(args)
+            .current_dir(repo)
+            .output()
+            .unwrap();
+        String::from_utf8_lossy(&output.stdout).trim().to_string()
+    }
+
+    fn setup_repo() -> TempDir {
+        let temp_dir = TempDir::new().unwrap();
+        let repo = temp_dir.path();
+        git(repo, &["init", "-b", "main"]);
+        git(repo, &["config", "user.name", "Test User"]);
+        git(repo, &["config", "user.email", "test@example.com"]);
+        std::fs::write(repo.join("README.md"), "test").unwrap();
+        git(repo, &["add", "."]);
+        git(repo, &["commit", "-m", "Initial commit"]);
+        temp_dir
+    }
+
+    #[test]
+    fn test_gix_matches_cli() {
+        let temp_dir = setup_repo();
+        let repo = temp_dir.path();
+
+        assert!(is_repo(repo));
+        assert_eq!(head_sha(repo).unwrap(), git(repo, &["rev-parse", "HEAD"]));
+        assert_eq!(current_branch(repo).unwrap().as_deref(), Some("main"));
+        assert!(branch_exists(repo, "main").unwrap());
+        assert!(!branch_exists(repo, "missing").unwrap());
+        assert!(git(repo, &["rev-parse", "HEAD"]).starts_with(&short_head_sha(repo).unwrap()));
+    }
+
+    #[test]
+    fn test_gix_detached_head() {
+        let temp_dir = setup_repo();
+        let repo = temp_dir.path();
+        git(repo, &["checkout", "--detach"]);
+
+        assert_eq!(current_branch(repo).unwrap(), None);
+    }
+
+    #[test]
+    fn test_gix_not_a_repo() {
+        let temp_dir = TempDir::new().unwrap();
+        // Guard against the temp dir living inside another repository
+        if Command::new("git")
+            .args(["rev-parse", "--git-dir"])
+            .current_dir(temp_dir.path())
+            .output()
+            .map(|o| o.status.success())
+            .unwrap_or(false)
+        {
+            return;
+        }
+        assert!(!is_repo(temp_dir.path()));
+    }
+
+    fn cli_status(repo: &Path) -> Vec<(String, String)> {
+        let output = Command::new("git")
+            .args(["status", "--porcelain", "--untracked-files=all"])
+            .current_dir(repo)
+            .output()
+            .unwrap();
+        String::from_utf8_lossy(&output.stdout)
+            .lines()
+            .map(|line| (line[..2].to_string(), line[3..].to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn test_gix_status_matches_cli() {
+        let temp_dir = setup_repo();
+        let repo = temp_dir.path();
+        std::fs::write(repo.join("gone.txt"), "x").unwrap();
+        std::fs::write(repo.join("both.txt"), "x").unwrap();
+        git(repo, &["add", "."]);
+        git(repo, &["commit", "-m", "More files"]);
+
+        std::fs::write(repo.join("README.md"), "changed").unwrap();
+        std::fs::remove_file(repo.join("gone.txt")).unwrap();
+        std::fs::write(repo.join("both.txt"), "staged").unwrap();
+        git(repo, &["add", "both.txt"]);
+        std::fs::write(repo.join("both.txt"), "staged then changed").unwrap();
+        std::fs::write(repo.join("new.txt"), "new").unwrap();
+        git(repo, &["add", "new.txt"]);
+        std::fs::create_dir(repo.join("dir")).unwrap();
+        std::fs::write(repo.join("dir/untracked.txt"), "?").unwrap();
+        std::fs::write(repo.join(".gitignore"), "*.log\n").unwrap();
+        std::fs::write(repo.join("build.log"), "ignored").unwrap();
+
+        assert_eq!(status(repo).unwrap(), cli_status(repo));
+    }
+
+    #[test]
+    fn test_gix_add_and_commit() {
+        let temp_dir = setup_repo();
+        let repo = temp_dir.path();
+        std::fs::create_dir_all(repo.join("src/nested")).unwrap();
+        std::fs::write(repo.join("src/nested/lib.rs"), "fn main() {}").unwrap();
+        std::fs::write(repo.join("src.txt"), "sorts before src/").unwrap();
+        std::fs::write(repo.join("README.md"), "updated").unwrap();
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            std::fs::write(repo.join("run.sh"), "#!/bin/sh").unwrap();
+            std::fs::set_permissions(repo.join("run.sh"), std::fs::Permissions::from_mode(0o755))
+                .unwrap();
+            add(repo, &["run.sh"]).unwrap();
+        }
+
+        add(repo, &["src/nested/lib.rs", "src.txt", "README.md"]).unwrap();
+        let sha = commit(repo, "Add files\n\nWith a body").unwrap();
+
+        assert_eq!(sha, git(repo, &["rev-parse", "HEAD"]));
+        assert_eq!(git(repo, &["status", "--porcelain"]), "");
+        assert_eq!(
+            git(repo, &["log", "-1", "--format=%B"]),
+            "Add files\n\nWith a body"
+        );
+        assert_eq!(
+            git(repo, &["show", "HEAD:src/nested/lib.rs"]),
+            "fn main() {}"
+        );
+        assert_eq!(git(repo, &["rev-list", "--count", "HEAD"]), "2");
+        assert_eq!(git(repo, &["fsck", "--no-progress"]), "");
+        #[cfg(unix)]
+        assert!(git(repo, &["ls-files", "-s", "run.sh"]).starts_with("100755"));
+
+        // Deleted files are dropped from the index
+        std::fs::remove_file(repo.join("src.txt")).unwrap();
+        add(repo, &["src.txt"]).unwrap();
+        commit(repo, "Remove src.txt").unwrap();
+        assert_eq!(git(repo, &["ls-files", "src.txt"]), "");
+
+        // An unchanged index has nothing to commit
+        assert!(commit(repo, "Empty").is_err());
+    }
+
+    #[test]
+    fn test_gix_hooks_dir() {
+        let temp_dir = setup_repo();
+        let repo = temp_dir.path();
+        assert_eq!(
+            hooks_dir(repo).unwrap().canonicalize().unwrap(),
+            repo.join(".git/hooks").canonicalize().unwrap()
+        );
+        git(repo, &["config", "core.hooksPath", ".githooks"]);
+        assert_eq!(hooks_dir(repo).unwrap(), repo.join(".githooks"));
+    }
+}
@@ -4,13 +4,63 @@
 //! - An audit log of planning steps
 //! - A comprehensive reference of historic requirements and implementations
 //! - A file that requires merging/resolution if updated on separate git branches
+//!
+//! Every entry is also appended to `planner_history.jsonl` as a typed
+//! [`HistoryEntry`], which can be queried with [`read_entries`]. Plans that
+//! predate the JSONL file are migrated from the text file on first access.
 
 use anyhow::{Context, Result};
 use chrono::Local;
+use serde::{Deserialize, Serialize};
 use std::fs::{self, OpenOptions};
 use std::io::Write;
 use std::path::Path;
 
+/// Name of the human-readable history file
+pub const HISTORY_FILE: &str = "planner_history.txt";
+
+/// Name of the structured history file
+pub const HISTORY_JSONL_FILE: &str = "planner_history.jsonl";
+
+/// A typed planner history event
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum HistoryEvent {
+    /// new_requirements.md was seeded from an issue (URL or file path)
+    ImportedRequirements { source: String },
+    RefiningRequirements,
+    GitHead { sha: String },
+    GitBranch { branch: String },
+    StartImplementing { summary: String },
+    /// Requirements were split into `count` milestones
+    MilestonesPlanned { count: usize },
+    /// Milestone `number` (1-based) was completed
+    MilestoneCompleted { number: usize, title: String },
+    /// A verification gate command was run
+    Verification { command: String, passed: bool },
+    /// Player turn `turn` was rolled back because it broke these commands
+    TurnRolledBack { turn: usize, commands: Vec<String> },
+    AttemptingRecovery,
+    SkippedRecovery,
+    CompletedRequirements {
+        requirements_file: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        todo_file: Option<String>,
+    },
+    GitCommit { message: String },
+    /// A legacy text line that could not be classified during migration
+    Unknown { text: String },
+}
+
+/// A timestamped entry in `planner_history.jsonl`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HistoryEntry {
+    /// Same format as the text history: YYYY-MM-DD HH:MM:SS
+    pub timestamp: String,
+    #[serde(flatten)]
+    pub event: HistoryEvent,
+}
+
 /// Format a timestamp for planner_history.txt entries
 /// Format: YYYY-MM-DD HH:MM:SS (ISO 8601 for readability)
 pub fn format_timestamp() -> String {
@@ -25,16 +75,261 @@ pub fn format_timestamp_for_filename() -> String {
 
 /// Ensure the planner_history.txt file exists, creating it if necessary
 pub fn ensure_history_file(plan_dir: &Path) -> Result<()> {
-    let history_path = plan_dir.join("planner_history.txt");
+    let history_path = plan_dir.join(HISTORY_FILE);
     
     if !history_path.exists() {
         fs::write(&history_path, "")
             .context("Failed to create planner_history.txt")?;
     }
+
+    migrate_legacy_history(plan_dir)?;
     
     Ok(())
 }
 
+/// Append a typed entry to planner_history.jsonl
+fn append_json_entry(plan_dir: &Path, timestamp: &str, event: HistoryEvent) -> Result<()> {
+    let entry = HistoryEntry {
+        timestamp: timestamp.to_string(),
+        event,
+    };
+    let line = serde_json::to_string(&entry)
+        .context("Failed to serialize history entry")?;
+
+    let mut file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(plan_dir.join(HISTORY_JSONL_FILE))
+        .context("Failed to open planner_history.jsonl for appending")?;
+    writeln!(file, "{}", line)
+        .context("Failed to write to planner_history.jsonl")?;
+    file.flush()
+        .context("Failed to flush planner_history.jsonl")?;
+
+    Ok(())
+}
+
+/// Read all entries from planner_history.jsonl, in the order they were written.
+///
+/// If only the legacy text history exists it is migrated first. Lines that
+/// fail to parse (e.g. merge conflict markers) are skipped.
+pub fn read_entries(plan_dir: &Path) -> Result<Vec<HistoryEntry>> {
+    migrate_legacy_history(plan_dir)?;
+
+    let jsonl_path = plan_dir.join(HISTORY_JSONL_FILE);
+    if !jsonl_path.exists() {
+        return Ok(Vec::new());
+    }
+
+    let content = fs::read_to_string(&jsonl_path)
+        .context("Failed to read planner_history.jsonl")?;
+    Ok(content
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .filter_map(|line| serde_json::from_str(line).ok())
+        .collect())
+}
+
+/// Get the GIT HEAD recorded for the most recent implementation, if any
+pub fn last_git_head(plan_dir: &Path) -> Result<Option<String>> {
+    Ok(read_entries(plan_dir)?
+        .into_iter()
+        .rev()
+        .find_map(|entry| match entry.event {
+            HistoryEvent::GitHead { sha } => Some(sha),
+            _ => None,
+        }))
+}
+
+/// Source of the requirements being worked on, if they were imported: the most
+/// recent IMPORTED REQUIREMENTS entry since the last COMPLETED REQUIREMENTS
+pub fn imported_source(plan_dir: &Path) -> Result<Option<String>> {
+    for entry in read_entries(plan_dir)?.into_iter().rev() {
+        match entry.event {
+            HistoryEvent::ImportedRequirements { source } => return Ok(Some(source)),
+            HistoryEvent::CompletedRequirements { .. } => return Ok(None),
+            _ => {}
+        }
+    }
+    Ok(None)
+}
+
+/// Number of milestones completed since the most recent MILESTONES PLANNED entry.
+///
+/// Returns `None` if the current implementation was not split into milestones.
+pub fn completed_milestones(plan_dir: &Path) -> Result<Option<usize>> {
+    let entries = read_entries(plan_dir)?;
+    let Some(start) = entries
+        .iter()
+        .rposition(|entry| matches!(entry.event, HistoryEvent::MilestonesPlanned { .. }))
+    else {
+        return Ok(None);
+    };
+    // A later START IMPLEMENTING means a new, unsplit implementation began
+    if entries[start..]
+        .iter()
+        .any(|entry| matches!(entry.event, HistoryEvent::StartImplementing { .. }))
+    {
+        return Ok(None);
+    }
+    Ok(Some(
+        entries[start..]
+            .iter()
+            .filter(|entry| matches!(entry.event, HistoryEvent::MilestoneCompleted { .. }))
+            .count(),
+    ))
+}
+
+/// Create planner_history.jsonl from the legacy text history if it doesn't exist yet.
+///
+/// Returns the number of migrated entries (0 if there was nothing to migrate).
+/// The text file is left untouched.
+pub fn migrate_legacy_history(plan_dir: &Path) -> Result<usize> {
+    let jsonl_path = plan_dir.join(HISTORY_JSONL_FILE);
+    let text_path = plan_dir.join(HISTORY_FILE);
+    if jsonl_path.exists() || !text_path.exists() {
+        return Ok(0);
+    }
+
+    let text = fs::read_to_string(&text_path)
+        .context("Failed to read planner_history.txt")?;
+    let entries = parse_legacy_history(&text);
+
+    // Written even when empty, so later text entries are never migrated twice
+    let mut lines = String::new();
+    for entry in &entries {
+        lines.push_str(&serde_json::to_string(entry).context("Failed to serialize history entry")?);
+        lines.push('\n');
+    }
+    fs::write(&jsonl_path, lines)
+        .context("Failed to write planner_history.jsonl")?;
+
+    Ok(entries.len())
+}
+
+/// Parse the text format of planner_history.txt into typed entries
+pub fn parse_legacy_history(text: &str) -> Vec<HistoryEntry> {
+    let mut entries: Vec<HistoryEntry> = Vec::new();
+    let mut summary_lines: Option<Vec<&str>> = None;
+
+    for line in text.lines() {
+        let trimmed = line.trim();
+
+        // Summary block following START IMPLEMENTING
+        if let Some(lines) = summary_lines.as_mut() {
+            if trimmed == ">>" {
+                let summary = lines.join("\n");
+                if let Some(HistoryEntry {
+                    event: HistoryEvent::StartImplementing { summary: s },
+                    ..
+                }) = entries.last_mut()
+                {
+                    *s = summary;
+                }
+                summary_lines = None;
+            } else {
+                lines.push(trimmed);
+            }
+            continue;
+        }
+        if trimmed == "<<" {
+            summary_lines = Some(Vec::new());
+            continue;
+        }
+        if trimmed.is_empty() {
+            continue;
+        }
+
+        // Every entry line starts with a 19-character timestamp
+        let (timestamp, rest) = match line.get(..19) {
+            Some(ts) if is_timestamp(ts) => (ts.to_string(), line[19..].trim().trim_start_matches("- ")),
+            _ => {
+                entries.push(HistoryEntry {
+                    timestamp: String::new(),
+                    event: HistoryEvent::Unknown { text: trimmed.to_string() },
+                });
+                continue;
+            }
+        };
+
+        entries.push(HistoryEntry {
+            timestamp,
+            event: parse_legacy_event(rest),
+        });
+    }
+
+    entries
+}
+
+fn is_timestamp(s: &str) -> bool {
+    s.chars().enumerate().all(|(i, c)| match i {
+        4 | 7 => c == '-',
+        10 => c == ' ',
+        13 | 16 => c == ':',
+        _ => c.is_ascii_digit(),
+    })
+}
+
+/// Parse the part of a legacy entry after the timestamp
+fn parse_legacy_event(rest: &str) -> HistoryEvent {
+    // Text between the outermost parentheses, if any
+    let arg = || {
+        let start = rest.find('(')?;
+        let end = rest.rfind(')')?;
+        (end > start).then(|| rest[start + 1..end].to_string())
+    };
+
+    if rest.starts_with("IMPORTED REQUIREMENTS") {
+        HistoryEvent::ImportedRequirements { source: arg().unwrap_or_default() }
+    } else if rest.starts_with("REFINING REQUIREMENTS") {
+        HistoryEvent::RefiningRequirements
+    } else if rest.starts_with("GIT HEAD") {
+        HistoryEvent::GitHead { sha: arg().unwrap_or_default() }
+    } else if rest.starts_with("GIT BRANCH") {
+        HistoryEvent::GitBranch { branch: arg().unwrap_or_default() }
+    } else if rest.starts_with("START IMPLEMENTING") {
+        HistoryEvent::StartImplementing { summary: String::new() }
+    } else if rest.starts_with("MILESTONES PLANNED") {
+        HistoryEvent::MilestonesPlanned {
+            count: arg().and_then(|n| n.trim().parse().ok()).unwrap_or_default(),
+        }
+    } else if rest.starts_with("COMPLETED MILESTONE") {
+        let arg = arg().unwrap_or_default();
+        let (number, title) = arg.split_once(':').unwrap_or(("", arg.as_str()));
+        HistoryEvent::MilestoneCompleted {
+            number: number.trim().parse().unwrap_or_default(),
+            title: title.trim().to_string(),
+        }
+    } else if rest.starts_with("VERIFICATION PASSED") || rest.starts_with("VERIFICATION FAILED") {
+        HistoryEvent::Verification {
+            command: arg().unwrap_or_default(),
+            passed: rest.starts_with("VERIFICATION PASSED"),
+        }
+    } else if let Some(turn) = rest.strip_prefix("ROLLED BACK TURN ") {
+        HistoryEvent::TurnRolledBack {
+            turn: turn.split_whitespace().next().and_then(|n| n.parse().ok()).unwrap_or_default(),
+            commands: arg()
+                .map(|commands| commands.split(", ").map(str::to_string).collect())
+                .unwrap_or_default(),
+        }
+    } else if rest.starts_with("ATTEMPTING RECOVERY") {
+        HistoryEvent::AttemptingRecovery
+    } else if rest.starts_with("USER SKIPPED RECOVERY") {
+        HistoryEvent::SkippedRecovery
+    } else if rest.starts_with("COMPLETED REQUIREMENTS") {
+        let files = arg().unwrap_or_default();
+        let mut parts = files.splitn(2, ',').map(|part| part.trim().to_string());
+        HistoryEvent::CompletedRequirements {
+            requirements_file: parts.next().unwrap_or_default(),
+            todo_file: parts.next().filter(|f| !f.is_empty()),
+        }
+    } else if rest.starts_with("GIT COMMIT") {
+        HistoryEvent::GitCommit { message: arg().unwrap_or_default() }
+    } else {
+        HistoryEvent::Unknown { text: rest.to_string() }
+    }
+}
+
 /// Append an entry to planner_history.txt.
 ///
 /// This function opens the file in append mode, writes a single line, and explicitly flushes
@@ -46,7 +341,11 @@ pub fn ensure_history_file(plan_dir: &Path) -> Result<()> {
 /// in this function. It's caused by incorrect call ordering where `git::commit()` is invoked
 /// before `history::write_git_commit()`. This function correctly writes to disk when called.
 fn append_entry(plan_dir: &Path, entry: &str) -> Result<()> {
-    let history_path = plan_dir.join("planner_history.txt");
+    // Bring older plans up to date before the text file grows, so the JSONL
+    // file ends up with every entry exactly once
+    migrate_legacy_history(plan_dir)?;
+
+    let history_path = plan_dir.join(HISTORY_FILE);
     
     let mut file = OpenOptions::new()
         .create(true)
@@ -64,12 +363,27 @@ fn append_entry(plan_dir: &Path, entry: &str) -> Result<()> {
     Ok(())
 }
 
+/// Write an "IMPORTED REQUIREMENTS" entry with the issue the requirements came from
+pub fn write_imported_requirements(plan_dir: &Path, source: &str) -> Result<()> {
+    let timestamp = format_timestamp();
+    let entry = "{timestamp} - IMPORTED REQUIREMENTS ({source})"
+        .replace("{timestamp}", &timestamp)
+        .replace("{source}", source);
+    append_entry(plan_dir, &entry)?;
+    append_json_entry(
+        plan_dir,
+        &timestamp,
+        HistoryEvent::ImportedRequirements { source: source.to_string() },
+    )
+}
+
 /// Write a "REFINING REQUIREMENTS" entry
 pub fn write_refining_requirements(plan_dir: &Path) -> Result<()> {
     let timestamp = format_timestamp();
     let entry = "{timestamp} - REFINING REQUIREMENTS (new_requirements.md)"
         .replace("{timestamp}", &timestamp);
-    append_entry(plan_dir, &entry)
+    append_entry(plan_dir, &entry)?;
+    append_json_entry(plan_dir, &timestamp, HistoryEvent::RefiningRequirements)
 }
 
 /// Write a "GIT HEAD" entry with the current SHA
@@ -78,7 +392,22 @@ pub fn write_git_head(plan_dir: &Path, sha: &str) -> Result<()> {
     let entry = "{timestamp} - GIT HEAD ({sha})"
         .replace("{timestamp}", &timestamp)
         .replace("{sha}", sha);
-    append_entry(plan_dir, &entry)
+    append_entry(plan_dir, &entry)?;
+    append_json_entry(plan_dir, &timestamp, HistoryEvent::GitHead { sha: sha.to_string() })
+}
+
+/// Write a "GIT BRANCH" entry when switching to a new feature branch
+pub fn write_git_branch(plan_dir: &Path, branch: &str) -> Result<()> {
+    let timestamp = format_timestamp();
+    let entry = "{timestamp} - GIT BRANCH ({branch})"
+        .replace("{timestamp}", &timestamp)
+        .replace("{branch}", branch);
+    append_entry(plan_dir, &entry)?;
+    append_json_entry(
+        plan_dir,
+        &timestamp,
+        HistoryEvent::GitBranch { branch: branch.to_string() },
+    )
 }
 
 /// Write a "START IMPLEMENTING" entry with a summary block
@@ -99,8 +428,66 @@ pub fn write_start_implementing(plan_dir: &Path, summary: &str) -> Result<()> {
     
     append_entry(plan_dir, &entry)?;
     append_entry(plan_dir, &summary_block)?;
-    
-    Ok(())
+    append_json_entry(
+        plan_dir,
+        &timestamp,
+        HistoryEvent::StartImplementing { summary: summary.to_string() },
+    )
+}
+
+/// Write a "MILESTONES PLANNED" entry
+pub fn write_milestones_planned(plan_dir: &Path, count: usize) -> Result<()> {
+    let timestamp = format_timestamp();
+    let entry = "{timestamp} - MILESTONES PLANNED ({count})"
+        .replace("{timestamp}", &timestamp)
+        .replace("{count}", &count.to_string());
+    append_entry(plan_dir, &entry)?;
+    append_json_entry(plan_dir, &timestamp, HistoryEvent::MilestonesPlanned { count })
+}
+
+/// Write a "COMPLETED MILESTONE" entry
+pub fn write_milestone_completed(plan_dir: &Path, number: usize, title: &str) -> Result<()> {
+    let timestamp = format_timestamp();
+    let entry = "{timestamp} - COMPLETED MILESTONE ({number}: {title})"
+        .replace("{timestamp}", &timestamp)
+        .replace("{number}", &number.to_string())
+        .replace("{title}", title);
+    append_entry(plan_dir, &entry)?;
+    append_json_entry(
+        plan_dir,
+        &timestamp,
+        HistoryEvent::MilestoneCompleted { number, title: title.to_string() },
+    )
+}
+
+/// Write a "VERIFICATION PASSED" or "VERIFICATION FAILED" entry for a gate command
+pub fn write_verification(plan_dir: &Path, command: &str, passed: bool) -> Result<()> {
+    let timestamp = format_timestamp();
+    let entry = "{timestamp} - VERIFICATION {result} ({command})"
+        .replace("{timestamp}", &timestamp)
+        .replace("{result}", if passed { "PASSED" } else { "FAILED" })
+        .replace("{command}", command);
+    append_entry(plan_dir, &entry)?;
+    append_json_entry(
+        plan_dir,
+        &timestamp,
+        HistoryEvent::Verification { command: command.to_string(), passed },
+    )
+}
+
+/// Write a "ROLLED BACK TURN" entry for a player turn that broke verification
+pub fn write_turn_rolled_back(plan_dir: &Path, turn: usize, commands: &[String]) -> Result<()> {
+    let timestamp = format_timestamp();
+    let entry = "{timestamp} - ROLLED BACK TURN {turn} ({commands})"
+        .replace("{timestamp}", &timestamp)
+        .replace("{turn}", &turn.to_string())
+        .replace("{commands}", &commands.join(", "));
+    append_entry(plan_dir, &entry)?;
+    append_json_entry(
+        plan_dir,
+        &timestamp,
+        HistoryEvent::TurnRolledBack { turn, commands: commands.to_vec() },
+    )
 }
 
 /// Write an "ATTEMPTING RECOVERY" entry
@@ -108,7 +495,8 @@ pub fn write_attempting_recovery(plan_dir: &Path) -> Result<()> {
     let timestamp = format_timestamp();
     let entry = "{timestamp}   ATTEMPTING RECOVERY"
         .replace("{timestamp}", &timestamp);
-    append_entry(plan_dir, &entry)
+    append_entry(plan_dir, &entry)?;
+    append_json_entry(plan_dir, &timestamp, HistoryEvent::AttemptingRecovery)
 }
 
 /// Write a "USER SKIPPED RECOVERY" entry
@@ -116,7 +504,8 @@ pub fn write_skipped_recovery(plan_dir: &Path) -> Result<()> {
     let timestamp = format_timestamp();
     let entry = "{timestamp}  USER SKIPPED RECOVERY"
         .replace("{timestamp}", &timestamp);
-    append_entry(plan_dir, &entry)
+    append_entry(plan_dir, &entry)?;
+    append_json_entry(plan_dir, &timestamp, HistoryEvent::SkippedRecovery)
 }
 
 /// Write a "COMPLETED REQUIREMENTS" entry
@@ -130,7 +519,15 @@ pub fn write_completed_requirements(
         .replace("{timestamp}", &timestamp)
         .replace("{requirements_file}", requirements_file)
         .replace("{todo_file}", todo_file);
-    append_entry(plan_dir, &entry)
+    append_entry(plan_dir, &entry)?;
+    append_json_entry(
+        plan_dir,
+        &timestamp,
+        HistoryEvent::CompletedRequirements {
+            requirements_file: requirements_file.to_string(),
+            todo_file: Some(todo_file.to_string()),
+        },
+    )
 }
 
 /// Write a "GIT COMMIT" entry
@@ -145,7 +542,12 @@ pub fn write_git_commit(plan_dir: &Path, message: &str) -> Result<()> {
     let entry = "{timestamp} - GIT COMMIT ({message})"
         .replace("{timestamp}", &timestamp)
         .replace("{message}", &truncated_message);
-    append_entry(plan_dir, &entry)
+    append_entry(plan_dir, &entry)?;
+    append_json_entry(
+        plan_dir,
+        &timestamp,
+        HistoryEvent::GitCommit { message: message.to_string() },
+    )
 }
 
 /// Generate the completed requirements filename
@@ -158,6 +560,11 @@ pub fn completed_todo_filename() -> String {
     format!("completed_todo_{}.md", format_timestamp_for_filename())
 }
 
+/// Generate the completed milestones filename
+pub fn completed_milestones_filename() -> String {
+    format!("completed_milestones_{}.md", format_timestamp_for_filename())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -211,9 +618,13 @@ mod tests {
         
         write_refining_requirements(plan_dir).unwrap();
         write_git_head(plan_dir, "abc123def456").unwrap();
+        write_git_branch(plan_dir, "g3/add-feature-x").unwrap();
         write_start_implementing(plan_dir, "Test summary line 1\nTest summary line 2").unwrap();
         write_attempting_recovery(plan_dir).unwrap();
         write_completed_requirements(plan_dir, "completed_requirements_2025-01-01_12-00-00.md", "completed_todo_2025-01-01_12-00-00.md").unwrap();
+        write_verification(plan_dir, "cargo test", true).unwrap();
+        write_verification(plan_dir, "npm test", false).unwrap();
+        write_turn_rolled_back(plan_dir, 3, &["npm test".to_string()]).unwrap();
         write_git_commit(plan_dir, "Add feature X").unwrap();
         
         let history_path = plan_dir.join("planner_history.txt");
@@ -221,11 +632,201 @@ mod tests {
         
         assert!(content.contains("REFINING REQUIREMENTS"));
         assert!(content.contains("GIT HEAD (abc123def456)"));
+        assert!(content.contains("GIT BRANCH (g3/add-feature-x)"));
         assert!(content.contains("START IMPLEMENTING"));
         assert!(content.contains("Test summary line 1"));
         assert!(content.contains("ATTEMPTING RECOVERY"));
         assert!(content.contains("COMPLETED REQUIREMENTS"));
+        assert!(content.contains("VERIFICATION PASSED (cargo test)"));
+        assert!(content.contains("VERIFICATION FAILED (npm test)"));
+        assert!(content.contains("ROLLED BACK TURN 3 (npm test)"));
         assert!(content.contains("GIT COMMIT"));
+
+        let legacy: Vec<HistoryEvent> = parse_legacy_history(&content)
+            .into_iter()
+            .map(|entry| entry.event)
+            .collect();
+        assert!(legacy.contains(&HistoryEvent::Verification { command: "npm test".to_string(), passed: false }));
+        assert!(legacy.contains(&HistoryEvent::TurnRolledBack { turn: 3, commands: vec!["npm test".to_string()] }));
+    }
+
+    #[test]
+    fn test_read_entries_matches_writes() {
+        let temp_dir = TempDir::new().unwrap();
+        let plan_dir = temp_dir.path();
+
+        ensure_history_file(plan_dir).unwrap();
+        write_refining_requirements(plan_dir).unwrap();
+        write_git_head(plan_dir, "abc123").unwrap();
+        write_start_implementing(plan_dir, "Line 1\nLine 2").unwrap();
+        write_completed_requirements(plan_dir, "req.md", "todo.md").unwrap();
+        write_git_commit(plan_dir, "Add feature X").unwrap();
+
+        let events: Vec<HistoryEvent> = read_entries(plan_dir)
+            .unwrap()
+            .into_iter()
+            .map(|entry| entry.event)
+            .collect();
+        assert_eq!(
+            events,
+            vec![
+                HistoryEvent::RefiningRequirements,
+                HistoryEvent::GitHead { sha: "abc123".to_string() },
+                HistoryEvent::StartImplementing { summary: "Line 1\nLine 2".to_string() },
+                HistoryEvent::CompletedRequirements {
+                    requirements_file: "req.md".to_string(),
+                    todo_file: Some("todo.md".to_string()),
+                },
+                HistoryEvent::GitCommit { message: "Add feature X".to_string() },
+            ]
+        );
+
+        let jsonl = fs::read_to_string(plan_dir.join(HISTORY_JSONL_FILE)).unwrap();
+        assert!(jsonl.lines().next().unwrap().contains(r#""type":"refining_requirements""#));
+
+        write_git_head(plan_dir, "def456").unwrap();
+        assert_eq!(last_git_head(plan_dir).unwrap(), Some("def456".to_string()));
+    }
+
+    #[test]
+    fn test_migrate_legacy_history() {
+        let temp_dir = TempDir::new().unwrap();
+        let plan_dir = temp_dir.path();
+        let legacy = "\
+2025-12-08 14:31:00 - REFINING REQUIREMENTS (new_requirements.md)
+2025-12-08 17:24:05 - GIT HEAD (fb2cf6f898d81d6556840d60057fc3f41855788f)
+2025-12-08 17:25:31 - START IMPLEMENTING (current_requirements.md)
+                      <<
+                      Implement planning mode.
+                      >>
+2025-12-08 18:30:00 - COMPLETED REQUIREMENTS (completed_requirements_2025-12-08_18-30-00.md)
+2025-12-09 22:41:30   ATTEMPTING RECOVERY
+2025-12-09 22:43:14  USER SKIPPED RECOVERY
+2025-12-09 16:16:51 - COMPLETED REQUIREMENTS (req_a.md,  todo_a.md)
+2025-12-08 18:30:01 - GIT COMMIT (Fix parser (again))
+garbage line
+";
+        fs::write(plan_dir.join(HISTORY_FILE), legacy).unwrap();
+
+        // ensure_history_file migrates, and leaves the text file untouched
+        ensure_history_file(plan_dir).unwrap();
+        assert_eq!(fs::read_to_string(plan_dir.join(HISTORY_FILE)).unwrap(), legacy);
+
+        let entries = read_entries(plan_dir).unwrap();
+        assert_eq!(entries.len(), 9);
+        assert_eq!(entries[0].timestamp, "2025-12-08 14:31:00");
+        assert_eq!(
+            entries[1].event,
+            HistoryEvent::GitHead { sha: "fb2cf6f898d81d6556840d60057fc3f41855788f".to_string() }
+        );
+        assert_eq!(
+            entries[2].event,
+            HistoryEvent::StartImplementing { summary: "Implement planning mode.".to_string() }
+        );
+        assert_eq!(
+            entries[3].event,
+            HistoryEvent::CompletedRequirements {
+                requirements_file: "completed_requirements_2025-12-08_18-30-00.md".to_string(),
+                todo_file: None,
+            }
+        );
+        assert_eq!(entries[4].event, HistoryEvent::AttemptingRecovery);
+        assert_eq!(entries[5].event, HistoryEvent::SkippedRecovery);
+        assert_eq!(
+            entries[6].event,
+            HistoryEvent::CompletedRequirements {
+                requirements_file: "req_a.md".to_string(),
+                todo_file: Some("todo_a.md".to_string()),
+            }
+        );
+        assert_eq!(entries[7].event, HistoryEvent::GitCommit { message: "Fix parser (again)".to_string() });
+        assert_eq!(entries[8].event, HistoryEvent::Unknown { text: "garbage line".to_string() });
+
+        // New writes append after the migrated entries, without re-migrating
+        write_git_commit(plan_dir, "Next").unwrap();
+        assert_eq!(migrate_legacy_history(plan_dir).unwrap(), 0);
+        let entries = read_entries(plan_dir).unwrap();
+        assert_eq!(entries.len(), 10);
+        assert_eq!(entries[9].event, HistoryEvent::GitCommit { message: "Next".to_string() });
+    }
+
+    #[test]
+    fn test_write_without_jsonl_migrates_once() {
+        let temp_dir = TempDir::new().unwrap();
+        let plan_dir = temp_dir.path();
+        fs::write(
+            plan_dir.join(HISTORY_FILE),
+            "2025-12-08 14:31:00 - REFINING REQUIREMENTS (new_requirements.md)\n",
+        )
+        .unwrap();
+
+        write_start_implementing(plan_dir, "Summary").unwrap();
+
+        let entries = read_entries(plan_dir).unwrap();
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].event, HistoryEvent::RefiningRequirements);
+        assert_eq!(entries[1].event, HistoryEvent::StartImplementing { summary: "Summary".to_string() });
+    }
+
+    #[test]
+    fn test_completed_milestones() {
+        let temp_dir = TempDir::new().unwrap();
+        let plan_dir = temp_dir.path();
+
+        ensure_history_file(plan_dir).unwrap();
+        write_start_implementing(plan_dir, "Summary").unwrap();
+        assert_eq!(completed_milestones(plan_dir).unwrap(), None);
+
+        write_milestones_planned(plan_dir, 3).unwrap();
+        assert_eq!(completed_milestones(plan_dir).unwrap(), Some(0));
+        write_milestone_completed(plan_dir, 1, "Config: parsing").unwrap();
+        assert_eq!(completed_milestones(plan_dir).unwrap(), Some(1));
+
+        let content = fs::read_to_string(plan_dir.join(HISTORY_FILE)).unwrap();
+        assert!(content.contains("MILESTONES PLANNED (3)"));
+        assert!(content.contains("COMPLETED MILESTONE (1: Config: parsing)"));
+
+        // The text lines parse back to the same events
+        let legacy: Vec<HistoryEvent> = parse_legacy_history(&content)
+            .into_iter()
+            .map(|entry| entry.event)
+            .collect();
+        assert_eq!(legacy[1], HistoryEvent::MilestonesPlanned { count: 3 });
+        assert_eq!(
+            legacy[2],
+            HistoryEvent::MilestoneCompleted { number: 1, title: "Config: parsing".to_string() }
+        );
+
+        // A new implementation without milestones resets the count
+        write_start_implementing(plan_dir, "Next").unwrap();
+        assert_eq!(completed_milestones(plan_dir).unwrap(), None);
+    }
+
+    #[test]
+    fn test_imported_source() {
+        let temp_dir = TempDir::new().unwrap();
+        let plan_dir = temp_dir.path();
+
+        ensure_history_file(plan_dir).unwrap();
+        assert_eq!(imported_source(plan_dir).unwrap(), None);
+
+        let url = "https://github.com/octo/widgets/issues/42";
+        write_imported_requirements(plan_dir, url).unwrap();
+        write_refining_requirements(plan_dir).unwrap();
+        write_start_implementing(plan_dir, "Summary").unwrap();
+        assert_eq!(imported_source(plan_dir).unwrap(), Some(url.to_string()));
+
+        let content = fs::read_to_string(plan_dir.join(HISTORY_FILE)).unwrap();
+        assert!(content.contains("IMPORTED REQUIREMENTS (https://github.com/octo/widgets/issues/42)"));
+        assert_eq!(
+            parse_legacy_history(&content)[0].event,
+            HistoryEvent::ImportedRequirements { source: url.to_string() }
+        );
+
+        // The next requirements were not imported
+        write_completed_requirements(plan_dir, "completed_requirements.md", "completed_todo.md").unwrap();
+        write_refining_requirements(plan_dir).unwrap();
+        assert_eq!(imported_source(plan_dir).unwrap(), None);
     }
 
     #[test]
@@ -0,0 +1,326 @@
+//! Importing requirements from an issue tracker
+//!
+//! `--import-github <issue-url>` fetches a GitHub issue (title, body and
+//! comments) and `--import-json <path>` reads an issue exported from any other
+//! tracker. Either one seeds `new_requirements.md`, and the source is recorded
+//! in the planner history so the resulting commits can reference the issue.
+
+use anyhow::{Context, Result};
+use serde::Deserialize;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Where to import requirements from
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ImportSource {
+    /// A GitHub issue URL (or `owner/repo#number`)
+    GitHub(String),
+    /// A JSON file in the [`ImportedIssue`] format
+    Json(PathBuf),
+}
+
+/// An issue to seed requirements from.
+///
+/// This is also the format accepted by `--import-json`:
+///
+/// ```json
+/// {
+///   "title": "Add dark mode",
+///   "body": "The settings page needs a theme toggle.",
+///   "url": "https://tracker.example.com/PROJ-42",
+///   "comments": [{ "author": "alice", "body": "Remember the docs." }]
+/// }
+/// ```
+///
+/// Only `title` is required.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct ImportedIssue {
+    pub title: String,
+    #[serde(default)]
+    pub body: String,
+    /// Link to the originating issue
+    #[serde(default)]
+    pub url: Option<String>,
+    #[serde(default)]
+    pub comments: Vec<IssueComment>,
+}
+
+/// A comment on an imported issue
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct IssueComment {
+    #[serde(default)]
+    pub author: Option<String>,
+    pub body: String,
+}
+
+/// A GitHub issue reference
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GitHubIssueRef {
+    pub owner: String,
+    pub repo: String,
+    pub number: u64,
+}
+
+impl GitHubIssueRef {
+    /// Parse `https://github.com/<owner>/<repo>/issues/<number>` or `<owner>/<repo>#<number>`
+    pub fn parse(input: &str) -> Result<Self> {
+        let input = input.trim();
+        let invalid = || {
+            anyhow::anyhow!(
+                "Invalid GitHub issue: {} (expected https://github.com/<owner>/<repo>/issues/<number>)",
+                input
+            )
+        };
+
+        let (owner, repo, number) = if let Some((path, number)) = input
+            .split_once('#')
+            .filter(|_| !input.contains("github.com/"))
+        {
+            let (owner, repo) = path.split_once('/').ok_or_else(invalid)?;
+            (owner, repo, number)
+        } else {
+            let path = input
+                .strip_prefix("https://github.com/")
+                .or_else(|| input.strip_prefix("http://github.com/"))
+                .or_else(|| input.strip_prefix("github.com/"))
+                .ok_or_else(invalid)?;
+            // Drop query strings and fragments such as #issuecomment-123
+            let path = path.split(['?', '#']).next().unwrap_or_default();
+            let parts: Vec<&str> = path.trim_end_matches('/').split('/').collect();
+            match parts.as_slice() {
+                [owner, repo, "issues" | "pull", number] => (*owner, *repo, *number),
+                _ => return Err(invalid()),
+            }
+        };
+
+        if owner.is_empty() || repo.is_empty() || repo.contains('/') {
+            return Err(invalid());
+        }
+        Ok(Self {
+            owner: owner.to_string(),
+            repo: repo.to_string(),
+            number: number.parse().map_err(|_| invalid())?,
+        })
+    }
+
+    /// The issue's web URL
+    pub fn html_url(&self) -> String {
+        format!(
+            "https://github.com/{}/{}/issues/{}",
+            self.owner, self.repo, self.number
+        )
+    }
+
+    fn api_url(&self) -> String {
+        format!(
+            "https://api.github.com/repos/{}/{}/issues/{}",
+            self.owner, self.repo, self.number
+        )
+    }
+}
+
+#[derive(Deserialize)]
+struct GitHubUser {
+    login: String,
+}
+
+#[derive(Deserialize)]
+struct GitHubIssue {
+    title: String,
+    body: Option<String>,
+    html_url: String,
+}
+
+#[derive(Deserialize)]
+struct GitHubComment {
+    user: Option<GitHubUser>,
+    body: Option<String>,
+}
+
+/// Fetch a GitHub issue and its comments.
+///
+/// Uses `GITHUB_TOKEN` (or `GH_TOKEN`) for authentication if set, which is
+/// needed for private repositories.
+pub async fn fetch_github_issue(issue: &GitHubIssueRef) -> Result<ImportedIssue> {
+    let client = reqwest::Client::new();
+    let token = std::env::var("GITHUB_TOKEN")
+        .or_else(|_| std::env::var("GH_TOKEN"))
+        .ok();
+    let get = |url: String| {
+        let mut request = client
+            .get(url)
+            .header("User-Agent", "g3-planner")
+            .header("Accept", "application/vnd.github+json");
+        if let Some(token) = &token {
+            request = request.bearer_auth(token);
+        }
+        request
+    };
+
+    let response = get(issue.api_url())
+        .send()
+        .await
+        .context("Failed to fetch GitHub issue")?;
+    if !response.status().is_success() {
+        anyhow::bail!(
+            "Failed to fetch {}: HTTP {}",
+            issue.html_url(),
+            response.status()
+        );
+    }
+    let github_issue: GitHubIssue = response
+        .json()
+        .await
+        .context("Failed to parse GitHub issue")?;
+
+    let response = get(format!("{}/comments?per_page=100", issue.api_url()))
+        .send()
+        .await
+        .context("Failed to fetch GitHub issue comments")?;
+    if !response.status().is_success() {
+        anyhow::bail!(
+            "Failed to fetch comments of {}: HTTP {}",
+            issue.html_url(),
+            response.status()
+        );
+    }
+    let comments: Vec<GitHubComment> = response
+        .json()
+        .await
+        .context("Failed to parse GitHub issue comments")?;
+
+    Ok(ImportedIssue {
+        title: github_issue.title,
+        body: github_issue.body.unwrap_or_default(),
+        url: Some(github_issue.html_url),
+        comments: comments
+            .into_iter()
+            .filter_map(|comment| {
+                Some(IssueComment {
+                    author: comment.user.map(|user| user.login),
+                    body: comment.body.filter(|body| !body.trim().is_empty())?,
+                })
+            })
+            .collect(),
+    })
+}
+
+/// Read an issue from a JSON file
+pub fn load_json_issue(path: &Path) -> Result<ImportedIssue> {
+    let content =
+        fs::read_to_string(path).with_context(|| format!("Failed to read {}", path.display()))?;
+    serde_json::from_str(&content)
+        .with_context(|| format!("Failed to parse issue JSON in {}", path.display()))
+}
+
+/// Load the issue for `source`. Returns the issue and the source link to record.
+pub async fn import_issue(source: &ImportSource) -> Result<(ImportedIssue, String)> {
+    match source {
+        ImportSource::GitHub(url) => {
+            let issue_ref = GitHubIssueRef::parse(url)?;
+            let issue = fetch_github_issue(&issue_ref).await?;
+            let link = issue.url.clone().unwrap_or_else(|| issue_ref.html_url());
+            Ok((issue, link))
+        }
+        ImportSource::Json(path) => {
+            let issue = load_json_issue(path)?;
+            let link = issue
+                .url
+                .clone()
+                .unwrap_or_else(|| path.display().to_string());
+            Ok((issue, link))
+        }
+    }
+}
+
+/// Render the issue as the initial content of new_requirements.md
+pub fn render_requirements(issue: &ImportedIssue, source: &str) -> String {
+    let mut out = format!("# {}\n\nImported from {}\n", issue.title.trim(), source);
+    if !issue.body.trim().is_empty() {
+        out.push_str(&format!("\n{}\n", issue.body.trim()));
+    }
+    if !issue.comments.is_empty() {
+        out.push_str("\n## Comments\n");
+        for comment in &issue.comments {
+            let author = comment.author.as_deref().unwrap_or("unknown");
+            out.push_str(&format!("\n**{}:**\n{}\n", author, comment.body.trim()));
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_parse_github_issue_ref() {
+        let expected = GitHubIssueRef {
+            owner: "octo".to_string(),
+            repo: "widgets".to_string(),
+            number: 42,
+        };
+        for input in [
+            "https://github.com/octo/widgets/issues/42",
+            "https://github.com/octo/widgets/issues/42/",
+            "https://github.com/octo/widgets/issues/42#issuecomment-1",
+            "github.com/octo/widgets/issues/42?x=y",
+            "octo/widgets#42",
+        ] {
+            assert_eq!(GitHubIssueRef::parse(input).unwrap(), expected, "{}", input);
+        }
+        assert_eq!(
+            expected.html_url(),
+            "https://github.com/octo/widgets/issues/42"
+        );
+
+        for input in [
+            "https://gitlab.com/octo/widgets/issues/42",
+            "https://github.com/octo/widgets",
+            "https://github.com/octo/widgets/issues/abc",
+            "octo#42",
+        ] {
+            assert!(GitHubIssueRef::parse(input).is_err(), "{}", input);
+        }
+    }
+
+    #[test]
+    fn test_load_json_issue() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("issue.json");
+        fs::write(
+            &path,
+            r#"{"title": "Add dark mode", "comments": [{"body": "Docs too"}]}"#,
+        )
+        .unwrap();
+
+        let issue = load_json_issue(&path).unwrap();
+        assert_eq!(issue.title, "Add dark mode");
+        assert_eq!(issue.body, "");
+        assert_eq!(issue.url, None);
+        assert_eq!(issue.comments[0].body, "Docs too");
+
+        fs::write(&path, r#"{"body": "no title"}"#).unwrap();
+        assert!(load_json_issue(&path).is_err());
+    }
+
+    #[test]
+    fn test_render_requirements() {
+        let issue = ImportedIssue {
+            title: "Add dark mode".to_string(),
+            body: "The settings page needs a theme toggle.\n".to_string(),
+            url: None,
+            comments: vec![IssueComment {
+                author: Some("alice".to_string()),
+                body: "Remember the docs.".to_string(),
+            }],
+        };
+        assert_eq!(
+            render_requirements(&issue, "https://tracker/PROJ-42"),
+            "# Add dark mode\n\nImported from https://tracker/PROJ-42\n\n\
+             The settings page needs a theme toggle.\n\n## Comments\n\n\
+             **alice:**\nRemember the docs.\n"
+        );
+    }
+}
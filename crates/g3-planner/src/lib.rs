@@ -7,23 +7,31 @@
 //! - Planner history management
 //! - Fast-discovery functionality for codebase exploration
 
+pub mod checkpoint;
 mod code_explore;
+pub mod discovery_executor;
+pub mod editor;
 pub mod git;
 pub mod history;
+pub mod issue_import;
 pub mod llm;
+pub mod milestones;
+pub mod persistence;
 pub mod planner;
 pub mod prompts;
+pub mod report;
 pub mod state;
+pub mod verification;
 
 pub use code_explore::explore_codebase;
-pub use planner::{expand_codepath, PlannerConfig, PlannerResult};
+pub use planner::{expand_codepath, validate_plan_name, PlannerConfig, PlannerResult, DEFAULT_PLAN_DIR};
 pub use state::{PlannerState, RecoveryInfo};
 pub use planner::run_planning_mode;
 
 use anyhow::Result;
 use chrono::Local;
+use g3_core::repo_map::{self, RepoMap};
 use g3_providers::{CompletionRequest, LLMProvider, Message, MessageRole};
-use prompts::{DISCOVERY_REQUIREMENTS_PROMPT, DISCOVERY_SYSTEM_PROMPT};
 use std::fs::{self, OpenOptions};
 use std::io::Write;
 
@@ -33,10 +41,15 @@ pub type StatusCallback = Box<dyn Fn(&str) + Send + Sync>;
 /// Generates initial discovery messages for fast codebase exploration.
 ///
 /// This function:
-/// 1. Runs explore_codebase to get a codebase report
-/// 2. Sends the report to the LLM with DISCOVERY_SYSTEM_PROMPT
+/// 1. Builds a repository map: the directory tree with each source file's
+///    top-level symbols (see [`g3_core::repo_map`]), falling back to
+///    explore_codebase when there are no files to map
+/// 2. Sends the map to the LLM with the `discovery_system` prompt
 /// 3. Extracts shell commands from the LLM response
-/// 4. Returns Assistant messages with tool calls for each command
+/// 4. Runs the commands read-only, with a timeout and output budget
+///    (see [`discovery_executor`])
+/// 5. Returns an Assistant tool call message for each command, followed by a
+///    User message with its result
 ///
 /// # Arguments
 ///
@@ -47,7 +60,8 @@ pub type StatusCallback = Box<dyn Fn(&str) + Send + Sync>;
 ///
 /// # Returns
 ///
-/// A `Result<Vec<Message>>` containing Assistant messages with JSON tool call strings.
+/// A `Result<Vec<Message>>` of Assistant messages with JSON tool call strings,
+/// each followed by a User message holding the command's precomputed result.
 pub async fn get_initial_discovery_messages(
     codebase_path: &str,
     requirements_text: Option<&str>,
@@ -63,30 +77,40 @@ pub async fn get_initial_discovery_messages(
 
     status("🔍 Starting code discovery...");
 
-    // Step 1: Run explore_codebase to get the codebase report
-    let codebase_report = explore_codebase(codebase_path);
+    // Step 1: Map the repository so the LLM can target its commands
+    let repo_map = RepoMap::build(std::path::Path::new(
+        shellexpand::tilde(codebase_path).as_ref(),
+    ));
+    let codebase_report = if repo_map.is_empty() {
+        explore_codebase(codebase_path)
+    } else {
+        status(&format!("🗺️ Mapped {} files", repo_map.file_count()));
+        repo_map.render(repo_map::DEFAULT_TOKEN_BUDGET)
+    };
 
     // Write the codebase report to logs directory
     write_code_report(&codebase_report)?;
 
-    // Step 2: Build the prompt with the codebase report appended
+    // Step 2: Build the prompt with the repository map appended
+    let templates = prompts::registry();
+    let discovery_prompt = templates.text("discovery")?;
     let user_prompt = if let Some(requirements) = requirements_text {
         format!(
             "{}\n\n
             === REQUIREMENTS ===\n\n{}\n\n
-            === CODEBASE REPORT ===\n\n{}",
-            DISCOVERY_REQUIREMENTS_PROMPT, requirements, codebase_report
+            === REPOSITORY MAP ===\n\n{}",
+            discovery_prompt, requirements, codebase_report
         )
     } else {
         format!(
-            "{}\n\n=== CODEBASE REPORT ===\n\n{}",
-            DISCOVERY_REQUIREMENTS_PROMPT, codebase_report
+            "{}\n\n=== REPOSITORY MAP ===\n\n{}",
+            discovery_prompt, codebase_report
         )
     };
 
     // Step 3: Create messages for the LLM
     let messages = vec![
-        Message::new(MessageRole::System, DISCOVERY_SYSTEM_PROMPT.to_string()),
+        Message::new(MessageRole::System, templates.text("discovery_system")?),
         Message::new(MessageRole::User, user_prompt),
     ];
 
@@ -115,10 +139,35 @@ pub async fn get_initial_discovery_messages(
     // Write the discovery commands to logs directory
     write_discovery_commands(&shell_commands)?;
 
-    // Step 6: Format as tool messages
-    let tool_messages = shell_commands
-        .into_iter()
-        .map(|cmd| create_tool_message("shell", &cmd))
+    // Step 6: Run the commands in the sandboxed executor
+    status("🧪 Running discovery commands...");
+    let results = discovery_executor::run_discovery_commands(
+        std::path::Path::new(codebase_path),
+        &shell_commands,
+        discovery_executor::DiscoveryLimits::default(),
+    )
+    .await;
+
+    let rejected = results
+        .iter()
+        .filter(|r| matches!(r.status, discovery_executor::DiscoveryStatus::Rejected(_)))
+        .count();
+    if rejected > 0 {
+        status(&format!(
+            "⚠️ Rejected {} discovery command(s) that were not read-only",
+            rejected
+        ));
+    }
+
+    // Step 7: Format as tool call / result message pairs
+    let tool_messages = results
+        .iter()
+        .flat_map(|result| {
+            [
+                create_tool_message("shell", &result.command),
+                Message::new(MessageRole::User, result.to_tool_result()),
+            ]
+        })
         .collect();
 
     Ok(tool_messages)
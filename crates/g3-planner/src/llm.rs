@@ -3,6 +3,8 @@
 //! This module provides LLM-based functionality for:
 //! - Requirements refinement
 //! - Generating requirements summaries
+//! - Splitting requirements into milestones
+//! - Generating dry-run plan reports
 //! - Generating git commit messages
 
 use anyhow::{anyhow, Context, Result};
@@ -13,7 +15,9 @@ use g3_core::Agent;
 use g3_core::error_handling::{classify_error, ErrorType};
 use g3_providers::{CompletionRequest, LLMProvider, Message, MessageRole};
 
+use crate::milestones::{parse_milestones, Milestone};
 use crate::prompts;
+use crate::report::PlanReport;
 
 /// Create an LLM provider for the planner based on config
 pub async fn create_planner_provider(
@@ -37,7 +41,7 @@ pub async fn create_planner_provider(
     let (provider_type, config_name) = Config::parse_provider_reference(provider_ref)?;
     
     // Create the appropriate provider
-    match provider_type.as_str() {
+    let provider: Result<Box<dyn LLMProvider>> = match provider_type.as_str() {
         "anthropic" => {
             let anthropic_config = config
                 .get_anthropic_config(&config_name)
@@ -96,13 +100,88 @@ pub async fn create_planner_provider(
             };
             Ok(Box::new(provider))
         }
+        "local" => {
+            let local_config = config
+                .get_local_config(&config_name)
+                .ok_or_else(|| anyhow!("Local config '{}' not found", config_name))?;
+            
+            let provider = g3_providers::LocalProvider::new_with_name(
+                format!("local.{}", config_name),
+                local_config.model.clone(),
+                local_config.base_url.clone(),
+                local_config.api_key.clone(),
+                local_config.max_tokens,
+                local_config.temperature,
+                local_config.native_tools.unwrap_or(true),
+            )?;
+            Ok(Box::new(provider))
+        }
+        "gemini" => {
+            let gemini_config = config
+                .get_gemini_config(&config_name)
+                .ok_or_else(|| anyhow!("Gemini config '{}' not found", config_name))?;
+            
+            let provider = g3_providers::GeminiProvider::new_with_name(
+                format!("gemini.{}", config_name),
+                gemini_config.api_key.clone(),
+                Some(gemini_config.model.clone()),
+                gemini_config.base_url.clone(),
+                gemini_config.max_tokens,
+                gemini_config.temperature,
+            )?;
+            Ok(Box::new(provider))
+        }
+        "bedrock" => {
+            let bedrock_config = config
+                .get_bedrock_config(&config_name)
+                .ok_or_else(|| anyhow!("Bedrock config '{}' not found", config_name))?;
+            
+            let credentials = g3_providers::bedrock::AwsCredentials::resolve(
+                bedrock_config.access_key_id.clone(),
+                bedrock_config.secret_access_key.clone(),
+                bedrock_config.session_token.clone(),
+                bedrock_config.profile.as_deref(),
+            )?;
+            let provider = g3_providers::BedrockProvider::new_with_name(
+                format!("bedrock.{}", config_name),
+                bedrock_config.model.clone(),
+                g3_providers::bedrock::resolve_region(bedrock_config.region.clone()),
+                credentials,
+                bedrock_config.max_tokens,
+                bedrock_config.temperature,
+                bedrock_config.native_tools.unwrap_or(true),
+            )?;
+            Ok(Box::new(provider))
+        }
+        "mock" => {
+            let mock_config = config
+                .get_mock_config(&config_name)
+                .ok_or_else(|| anyhow!("Mock config '{}' not found", config_name))?;
+
+            let provider = g3_providers::MockProvider::new_with_name(
+                format!("mock.{}", config_name),
+                &mock_config.fixtures_dir,
+                mock_config.model.clone(),
+                mock_config.max_tokens,
+                mock_config.temperature,
+                mock_config.native_tools.unwrap_or(true),
+            );
+            Ok(Box::new(provider))
+        }
         _ => {
             Err(anyhow!(
-                "Unsupported provider type '{}' for planner. Supported: anthropic, openai, databricks",
+                "Unsupported provider type '{}' for planner. Supported: anthropic, openai, databricks, local, gemini, bedrock, mock",
                 provider_type
             ))
         }
+    };
+    let provider = provider?;
+
+    if config.planner.response_cache {
+        let cache = g3_providers::cache::ResponseCache::new(g3_core::paths::get_response_cache_dir());
+        return Ok(Box::new(g3_providers::CachingProvider::new(provider, cache)));
     }
+    Ok(provider)
 }
 
 /// Generate a summary of requirements for planner_history.txt
@@ -113,15 +192,15 @@ pub async fn generate_requirements_summary(
     provider: &dyn LLMProvider,
     requirements: &str,
 ) -> Result<String> {
-    let prompt = prompts::GENERATE_REQUIREMENTS_SUMMARY_PROMPT
-        .replace("{requirements}", requirements);
+    let prompt =
+        prompts::registry().render("requirements_summary", &[("requirements", requirements)])?;
 
     let messages = vec![Message::new(MessageRole::User, prompt)];
 
     let request = CompletionRequest {
         messages,
         max_tokens: Some(500), // Summary should be short
-        temperature: Some(0.3), // Low temperature for consistent output
+        temperature: Some(0.0), // Deterministic, so the response can be cached
         stream: false,
         tools: None,
         disable_thinking: false,
@@ -150,6 +229,81 @@ pub async fn generate_requirements_summary(
     Ok(summary)
 }
 
+/// Split requirements into ordered milestones
+///
+/// Uses the planner LLM to produce milestones in the `milestones.md` format.
+/// Returns an error if the response contains no parseable milestones.
+pub async fn generate_milestones(
+    provider: &dyn LLMProvider,
+    requirements: &str,
+) -> Result<Vec<Milestone>> {
+    let prompt =
+        prompts::registry().render("split_milestones", &[("requirements", requirements)])?;
+
+    let messages = vec![Message::new(MessageRole::User, prompt)];
+
+    let request = CompletionRequest {
+        messages,
+        max_tokens: Some(4000),
+        temperature: Some(0.0),
+        stream: false,
+        tools: None,
+        disable_thinking: false,
+    };
+
+    let response = provider
+        .complete(request)
+        .await
+        .context("Failed to generate milestones")?;
+
+    let milestones = parse_milestones(&response.content);
+    if milestones.is_empty() {
+        anyhow::bail!("LLM response did not contain any milestones");
+    }
+
+    Ok(milestones)
+}
+
+/// Maximum size of the codebase report included in the plan report prompt
+const MAX_CODEBASE_REPORT_CHARS: usize = 60_000;
+
+/// Generate the analysis sections of a dry-run plan report
+pub async fn generate_plan_report(
+    provider: &dyn LLMProvider,
+    requirements: &str,
+    codebase_report: &str,
+) -> Result<PlanReport> {
+    let codebase_report = match codebase_report.char_indices().nth(MAX_CODEBASE_REPORT_CHARS) {
+        Some((end, _)) => format!("{}\n... (codebase report truncated)", &codebase_report[..end]),
+        None => codebase_report.to_string(),
+    };
+    let prompt = prompts::registry().render(
+        "plan_report",
+        &[
+            ("requirements", requirements),
+            ("codebase_report", &codebase_report),
+        ],
+    )?;
+
+    let messages = vec![Message::new(MessageRole::User, prompt)];
+
+    let request = CompletionRequest {
+        messages,
+        max_tokens: Some(4000),
+        temperature: Some(0.0),
+        stream: false,
+        tools: None,
+        disable_thinking: false,
+    };
+
+    let response = provider
+        .complete(request)
+        .await
+        .context("Failed to generate plan report")?;
+
+    Ok(PlanReport::parse(&response.content))
+}
+
 /// Generate a git commit message based on the requirements
 ///
 /// Uses the planner LLM to generate a commit summary and description.
@@ -160,17 +314,21 @@ pub async fn generate_commit_message(
     requirements_file: &str,
     todo_file: &str,
 ) -> Result<(String, String)> {
-    let prompt = prompts::GENERATE_COMMIT_MESSAGE_PROMPT
-        .replace("{requirements}", requirements)
-        .replace("{requirements_file}", requirements_file)
-        .replace("{todo_file}", todo_file);
+    let prompt = prompts::registry().render(
+        "commit_message",
+        &[
+            ("requirements", requirements),
+            ("requirements_file", requirements_file),
+            ("todo_file", todo_file),
+        ],
+    )?;
 
     let messages = vec![Message::new(MessageRole::User, prompt)];
 
     let request = CompletionRequest {
         messages,
         max_tokens: Some(1000),
-        temperature: Some(0.3),
+        temperature: Some(0.0),
         stream: false,
         tools: None,
         disable_thinking: false,
@@ -314,13 +472,14 @@ pub async fn call_refinement_llm_with_tools(
     config: &Config,
     codepath: &str,
     workspace: &str,
+    plan_dir: &str,
 ) -> Result<String> {
     // Build system message with codepath context
-    let system_prompt = prompts::REFINE_REQUIREMENTS_SYSTEM_PROMPT
-        .replace("<codepath>", codepath);
+    let system_prompt =
+        prompts::registry().render("refine_requirements", &[("plan_dir", plan_dir)])?;
 
     // Build user message
-    let user_message = build_refinement_user_message(codepath);
+    let user_message = build_refinement_user_message(codepath, plan_dir);
 
     // Create agent with planner config
     let planner_config = config.for_planner()?;
@@ -381,21 +540,22 @@ pub async fn call_refinement_llm_with_tools(
 /// Build the user message for requirements refinement
 ///
 /// This message instructs the LLM to read the codebase and refine requirements.
-pub fn build_refinement_user_message(codepath: &str) -> String {
+pub fn build_refinement_user_message(codepath: &str, plan_dir: &str) -> String {
     format!(
         r#"Please refine the requirements for the codebase at: {codepath}
 
 Before making suggestions, please:
 1. Read the codebase structure using shell commands like `ls`, `find`, or `tree`
-2. Read `{codepath}/g3-plan/planner_history.txt` to understand past planning activities
-3. Read any `{codepath}/g3-plan/completed_requirements_*.md` files to see what was implemented before
-4. Read `{codepath}/g3-plan/new_requirements.md` which contains the requirements to refine
+2. Read `{plan_dir}/planner_history.txt` to understand past planning activities
+3. Read any `{plan_dir}/completed_requirements_*.md` files to see what was implemented before
+4. Read `{plan_dir}/new_requirements.md` which contains the requirements to refine
 
-After understanding the context, update the `{codepath}/g3-plan/new_requirements.md` file by prepending
+After understanding the context, update the `{plan_dir}/new_requirements.md` file by prepending
 your refined requirements under the heading `{{{{CURRENT REQUIREMENTS}}}}`.
 
 Use final_output when you are done to indicate completion."#,
-        codepath = codepath
+        codepath = codepath,
+        plan_dir = plan_dir
     )
 }
 
@@ -405,8 +565,9 @@ mod tests {
 
     #[test]
     fn test_build_refinement_user_message() {
-        let msg = build_refinement_user_message("/test/project");
+        let msg = build_refinement_user_message("/test/project", "/test/project/g3-plan/feature-x");
         assert!(msg.contains("/test/project"));
+        assert!(msg.contains("/test/project/g3-plan/feature-x/new_requirements.md"));
         assert!(msg.contains("planner_history.txt"));
         assert!(msg.contains("new_requirements.md"));
         assert!(msg.contains("{{CURRENT REQUIREMENTS}}"));
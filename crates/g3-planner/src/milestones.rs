@@ -0,0 +1,293 @@
+//! Milestone splitting for large requirements
+//!
+//! Approved requirements can be broken into ordered milestones, each with its
+//! own acceptance criteria and todo section. The milestone plan is stored as
+//! `milestones.md` in the plan directory, in the same markdown format the LLM
+//! is asked to produce, so it can be parsed back when resuming.
+
+use anyhow::{Context, Result};
+use std::fs;
+use std::path::Path;
+
+/// File name of the milestone plan inside the plan directory
+pub const MILESTONES_FILE: &str = "milestones.md";
+
+/// A single milestone: an independently committable slice of the requirements
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Milestone {
+    pub title: String,
+    pub acceptance_criteria: Vec<String>,
+    pub tasks: Vec<String>,
+}
+
+/// Section of a milestone currently being parsed
+enum Section {
+    None,
+    AcceptanceCriteria,
+    Tasks,
+}
+
+/// Parse milestones from markdown of the form:
+///
+/// ```text
+/// ## Milestone 1: Title
+/// ### Acceptance Criteria
+/// - criterion
+/// ### Tasks
+/// - [ ] task
+/// ```
+///
+/// Text outside milestone headings is ignored.
+pub fn parse_milestones(text: &str) -> Vec<Milestone> {
+    let mut milestones: Vec<Milestone> = Vec::new();
+    let mut section = Section::None;
+
+    for line in text.lines() {
+        let trimmed = line.trim();
+
+        if let Some(heading) = trimmed.strip_prefix("## ") {
+            // Any other level-2 heading ends the current milestone's sections
+            section = Section::None;
+            if let Some(title) = parse_milestone_heading(heading) {
+                milestones.push(Milestone {
+                    title,
+                    acceptance_criteria: Vec::new(),
+                    tasks: Vec::new(),
+                });
+            }
+            continue;
+        }
+
+        let Some(milestone) = milestones.last_mut() else {
+            continue;
+        };
+
+        if let Some(heading) = trimmed.strip_prefix("### ") {
+            section = match heading.trim().to_lowercase().as_str() {
+                "acceptance criteria" => Section::AcceptanceCriteria,
+                "tasks" => Section::Tasks,
+                _ => Section::None,
+            };
+            continue;
+        }
+
+        let item = trimmed
+            .strip_prefix("- [ ] ")
+            .or_else(|| trimmed.strip_prefix("- [x] "))
+            .or_else(|| trimmed.strip_prefix("- "))
+            .or_else(|| trimmed.strip_prefix("* "))
+            .map(str::trim)
+            .filter(|item| !item.is_empty());
+
+        if let Some(item) = item {
+            match section {
+                Section::AcceptanceCriteria => milestone.acceptance_criteria.push(item.to_string()),
+                Section::Tasks => milestone.tasks.push(item.to_string()),
+                Section::None => {}
+            }
+        }
+    }
+
+    milestones
+}
+
+/// Parse `Milestone 2: Title` (the number is optional), returning the title
+fn parse_milestone_heading(heading: &str) -> Option<String> {
+    let rest = heading.trim().strip_prefix("Milestone")?;
+    let rest = rest.trim_start_matches(|c: char| c.is_ascii_digit() || c.is_whitespace());
+    let title = rest.strip_prefix(':').unwrap_or(rest).trim();
+    if title.is_empty() {
+        None
+    } else {
+        Some(title.to_string())
+    }
+}
+
+/// Render the milestone plan as markdown (the format read by [`parse_milestones`])
+pub fn render_milestones(milestones: &[Milestone]) -> String {
+    let mut out = String::from("# Milestones\n");
+    for (i, milestone) in milestones.iter().enumerate() {
+        out.push_str(&format!("\n## Milestone {}: {}\n", i + 1, milestone.title));
+        out.push_str("\n### Acceptance Criteria\n");
+        for criterion in &milestone.acceptance_criteria {
+            out.push_str(&format!("- {}\n", criterion));
+        }
+        out.push_str("\n### Tasks\n");
+        for task in &milestone.tasks {
+            out.push_str(&format!("- [ ] {}\n", task));
+        }
+    }
+    out
+}
+
+/// Render the initial todo file with one section per milestone
+pub fn render_todo(milestones: &[Milestone]) -> String {
+    let mut out = String::new();
+    for (i, milestone) in milestones.iter().enumerate() {
+        if i > 0 {
+            out.push('\n');
+        }
+        out.push_str(&format!("## Milestone {}: {}\n", i + 1, milestone.title));
+        for task in &milestone.tasks {
+            out.push_str(&format!("- [ ] {}\n", task));
+        }
+    }
+    out
+}
+
+/// Build the requirements text handed to the coach/player loop for one milestone
+pub fn milestone_requirements(
+    requirements: &str,
+    milestones: &[Milestone],
+    index: usize,
+) -> String {
+    let milestone = &milestones[index];
+    let criteria = milestone
+        .acceptance_criteria
+        .iter()
+        .map(|c| format!("- {}", c))
+        .collect::<Vec<_>>()
+        .join("\n");
+    let tasks = milestone
+        .tasks
+        .iter()
+        .map(|t| format!("- {}", t))
+        .collect::<Vec<_>>()
+        .join("\n");
+    let done = milestones[..index]
+        .iter()
+        .enumerate()
+        .map(|(i, m)| format!("- Milestone {}: {}", i + 1, m.title))
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    format!(
+        "CURRENT MILESTONE {number} of {total}: {title}\n\n\
+         Implement ONLY this milestone. In the todo file, update only the \
+         `## Milestone {number}` section and keep the other sections intact.\n\n\
+         Acceptance criteria:\n{criteria}\n\nTasks:\n{tasks}\n\n\
+         Already completed milestones:\n{done}\n\n\
+         === FULL REQUIREMENTS (for context) ===\n\n{requirements}",
+        number = index + 1,
+        total = milestones.len(),
+        title = milestone.title,
+        criteria = criteria,
+        tasks = tasks,
+        done = if done.is_empty() {
+            "(none)".to_string()
+        } else {
+            done
+        },
+        requirements = requirements,
+    )
+}
+
+/// Save the milestone plan to the plan directory
+pub fn save_milestones(plan_dir: &Path, milestones: &[Milestone]) -> Result<()> {
+    fs::write(
+        plan_dir.join(MILESTONES_FILE),
+        render_milestones(milestones),
+    )
+    .context("Failed to write milestones.md")
+}
+
+/// Load the milestone plan from the plan directory, if one exists
+pub fn load_milestones(plan_dir: &Path) -> Result<Option<Vec<Milestone>>> {
+    let path = plan_dir.join(MILESTONES_FILE);
+    if !path.exists() {
+        return Ok(None);
+    }
+    let content = fs::read_to_string(&path).context("Failed to read milestones.md")?;
+    let milestones = parse_milestones(&content);
+    Ok(if milestones.is_empty() {
+        None
+    } else {
+        Some(milestones)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    const LLM_RESPONSE: &str = r#"Here is the plan.
+
+## Milestone 1: Config parsing
+### Acceptance Criteria
+- `[planner]` section loads
+- Invalid values are rejected
+### Tasks
+- [ ] Add PlanningConfig struct
+- [ ] Add tests
+
+## Milestone 2 - no colon is fine too
+should be ignored
+### Tasks
+* Wire into CLI
+
+## Not a milestone
+- ignored
+"#;
+
+    #[test]
+    fn test_parse_milestones() {
+        let milestones = parse_milestones(LLM_RESPONSE);
+        assert_eq!(milestones.len(), 2);
+        assert_eq!(milestones[0].title, "Config parsing");
+        assert_eq!(
+            milestones[0].acceptance_criteria,
+            vec!["`[planner]` section loads", "Invalid values are rejected"]
+        );
+        assert_eq!(
+            milestones[0].tasks,
+            vec!["Add PlanningConfig struct", "Add tests"]
+        );
+        assert_eq!(milestones[1].title, "- no colon is fine too");
+        assert!(milestones[1].acceptance_criteria.is_empty());
+        assert_eq!(milestones[1].tasks, vec!["Wire into CLI"]);
+        // The trailing "## Not a milestone" section must not leak into milestone 2
+        assert!(!milestones[1].tasks.contains(&"ignored".to_string()));
+    }
+
+    #[test]
+    fn test_render_round_trip() {
+        let milestones = parse_milestones(LLM_RESPONSE);
+        assert_eq!(
+            parse_milestones(&render_milestones(&milestones)),
+            milestones
+        );
+    }
+
+    #[test]
+    fn test_render_todo_sections() {
+        let todo = render_todo(&parse_milestones(LLM_RESPONSE));
+        assert!(
+            todo.starts_with("## Milestone 1: Config parsing\n- [ ] Add PlanningConfig struct\n")
+        );
+        assert!(todo.contains("\n## Milestone 2: - no colon is fine too\n- [ ] Wire into CLI\n"));
+    }
+
+    #[test]
+    fn test_milestone_requirements() {
+        let milestones = parse_milestones(LLM_RESPONSE);
+        let text = milestone_requirements("Full requirements here", &milestones, 1);
+        assert!(text.starts_with("CURRENT MILESTONE 2 of 2"));
+        assert!(text.contains("- Milestone 1: Config parsing"));
+        assert!(text.contains("- Wire into CLI"));
+        assert!(text.ends_with("Full requirements here"));
+
+        let first = milestone_requirements("reqs", &milestones, 0);
+        assert!(first.contains("Already completed milestones:\n(none)"));
+    }
+
+    #[test]
+    fn test_save_and_load() {
+        let temp_dir = TempDir::new().unwrap();
+        assert_eq!(load_milestones(temp_dir.path()).unwrap(), None);
+
+        let milestones = parse_milestones(LLM_RESPONSE);
+        save_milestones(temp_dir.path(), &milestones).unwrap();
+        assert_eq!(load_milestones(temp_dir.path()).unwrap(), Some(milestones));
+    }
+}
@@ -0,0 +1,198 @@
+//! Persistence of the planner state machine
+//!
+//! The current [`PlannerState`] is written to `<plan_dir>/.state` on every
+//! transition, so a planner that crashes (or is killed) mid-refinement or
+//! mid-implementation resumes at the same step on the next start instead of
+//! going through the coarse [`RecoveryInfo`](crate::state::RecoveryInfo) check.
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::Path;
+
+use crate::history;
+use crate::state::PlannerState;
+
+/// File name of the persisted state inside the plan directory
+pub const STATE_FILE: &str = ".state";
+
+/// The resumable subset of [`PlannerState`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "state", rename_all = "snake_case")]
+pub enum SavedState {
+    PromptForRequirements,
+    RefineRequirements,
+    ApproveRequirements,
+    ImplementRequirements,
+    SplitMilestones,
+    ImplementMilestone { index: usize },
+    ImplementationComplete,
+}
+
+impl SavedState {
+    /// The state to persist for a transition, or `None` for transient states
+    /// (startup, recovery and quitting keep the previously saved state)
+    pub fn from_state(state: &PlannerState) -> Option<Self> {
+        match state {
+            PlannerState::Startup | PlannerState::Recovery(_) | PlannerState::Quit => None,
+            PlannerState::PromptForRequirements => Some(SavedState::PromptForRequirements),
+            PlannerState::RefineRequirements => Some(SavedState::RefineRequirements),
+            PlannerState::ApproveRequirements => Some(SavedState::ApproveRequirements),
+            PlannerState::ImplementRequirements => Some(SavedState::ImplementRequirements),
+            PlannerState::SplitMilestones => Some(SavedState::SplitMilestones),
+            PlannerState::ImplementMilestone(index) => {
+                Some(SavedState::ImplementMilestone { index: *index })
+            }
+            PlannerState::ImplementationComplete => Some(SavedState::ImplementationComplete),
+        }
+    }
+
+    /// The planner state to resume in
+    pub fn to_state(self) -> PlannerState {
+        match self {
+            SavedState::PromptForRequirements => PlannerState::PromptForRequirements,
+            SavedState::RefineRequirements => PlannerState::RefineRequirements,
+            SavedState::ApproveRequirements => PlannerState::ApproveRequirements,
+            SavedState::ImplementRequirements => PlannerState::ImplementRequirements,
+            SavedState::SplitMilestones => PlannerState::SplitMilestones,
+            SavedState::ImplementMilestone { index } => PlannerState::ImplementMilestone(index),
+            SavedState::ImplementationComplete => PlannerState::ImplementationComplete,
+        }
+    }
+
+    /// Human-readable description, for the resume prompt
+    pub fn describe(&self) -> String {
+        match self {
+            SavedState::PromptForRequirements => "waiting for new requirements".to_string(),
+            SavedState::RefineRequirements => "refining requirements".to_string(),
+            SavedState::ApproveRequirements => {
+                "waiting for approval of the refined requirements".to_string()
+            }
+            SavedState::ImplementRequirements => "implementing requirements".to_string(),
+            SavedState::SplitMilestones => "splitting requirements into milestones".to_string(),
+            SavedState::ImplementMilestone { index } => {
+                format!("implementing milestone {}", index + 1)
+            }
+            SavedState::ImplementationComplete => {
+                "confirming the implementation is complete".to_string()
+            }
+        }
+    }
+}
+
+/// Contents of the `.state` file
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PersistedState {
+    #[serde(flatten)]
+    pub state: SavedState,
+    /// Number of refinement passes for the current requirements
+    #[serde(default)]
+    pub refine_iterations: usize,
+    /// When the state was saved (YYYY-MM-DD HH:MM:SS)
+    pub updated: String,
+}
+
+impl PersistedState {
+    pub fn new(state: SavedState, refine_iterations: usize) -> Self {
+        Self {
+            state,
+            refine_iterations,
+            updated: history::format_timestamp(),
+        }
+    }
+}
+
+/// Write the state file, replacing it atomically
+pub fn save(plan_dir: &Path, state: &PersistedState) -> Result<()> {
+    let content =
+        serde_json::to_string_pretty(state).context("Failed to serialize planner state")?;
+    let tmp_path = plan_dir.join(format!("{}.tmp", STATE_FILE));
+    fs::write(&tmp_path, content).context("Failed to write planner state")?;
+    fs::rename(&tmp_path, plan_dir.join(STATE_FILE)).context("Failed to write planner state")?;
+    Ok(())
+}
+
+/// Read the state file. Returns `None` if there is none or it can't be parsed.
+pub fn load(plan_dir: &Path) -> Option<PersistedState> {
+    let content = fs::read_to_string(plan_dir.join(STATE_FILE)).ok()?;
+    serde_json::from_str(&content).ok()
+}
+
+/// Remove the state file, if any
+pub fn clear(plan_dir: &Path) -> Result<()> {
+    let path = plan_dir.join(STATE_FILE);
+    if path.exists() {
+        fs::remove_file(&path).context("Failed to remove planner state")?;
+    }
+    Ok(())
+}
+
+/// Keep the state file out of planner commits via a `.gitignore` in the plan directory
+pub fn ensure_ignored(plan_dir: &Path) -> Result<()> {
+    let gitignore = plan_dir.join(".gitignore");
+    let content = fs::read_to_string(&gitignore).unwrap_or_default();
+    if content.lines().any(|line| line.trim() == STATE_FILE) {
+        return Ok(());
+    }
+
+    let mut new_content = content;
+    if !new_content.is_empty() && !new_content.ends_with('\n') {
+        new_content.push('\n');
+    }
+    new_content.push_str(STATE_FILE);
+    new_content.push('\n');
+    fs::write(&gitignore, new_content).context("Failed to update plan directory .gitignore")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_save_and_load_round_trip() {
+        let temp_dir = TempDir::new().unwrap();
+        assert_eq!(load(temp_dir.path()), None);
+
+        let state = PersistedState::new(SavedState::ImplementMilestone { index: 2 }, 3);
+        save(temp_dir.path(), &state).unwrap();
+
+        let content = fs::read_to_string(temp_dir.path().join(STATE_FILE)).unwrap();
+        assert!(content.contains(r#""state": "implement_milestone""#));
+        assert_eq!(load(temp_dir.path()), Some(state));
+        assert!(!temp_dir.path().join(".state.tmp").exists());
+
+        clear(temp_dir.path()).unwrap();
+        assert_eq!(load(temp_dir.path()), None);
+    }
+
+    #[test]
+    fn test_corrupt_state_is_ignored() {
+        let temp_dir = TempDir::new().unwrap();
+        fs::write(temp_dir.path().join(STATE_FILE), "{not json").unwrap();
+        assert_eq!(load(temp_dir.path()), None);
+    }
+
+    #[test]
+    fn test_transient_states_are_not_saved() {
+        assert_eq!(SavedState::from_state(&PlannerState::Quit), None);
+        assert_eq!(SavedState::from_state(&PlannerState::Startup), None);
+        let saved = SavedState::from_state(&PlannerState::ImplementMilestone(1)).unwrap();
+        assert_eq!(saved.to_state(), PlannerState::ImplementMilestone(1));
+    }
+
+    #[test]
+    fn test_ensure_ignored_appends_once() {
+        let temp_dir = TempDir::new().unwrap();
+        let gitignore = temp_dir.path().join(".gitignore");
+        fs::write(&gitignore, "scratch/").unwrap();
+
+        ensure_ignored(temp_dir.path()).unwrap();
+        ensure_ignored(temp_dir.path()).unwrap();
+
+        assert_eq!(
+            fs::read_to_string(&gitignore).unwrap(),
+            "scratch/\n.state\n"
+        );
+    }
+}
@@ -4,18 +4,31 @@
 //! including the state machine transitions and user interactions.
 
 use anyhow::{Context, Result};
+use std::collections::HashSet;
 use std::fs;
 use std::io::{self, Write};
 use std::path::{Path, PathBuf};
+use std::time::Duration;
 
-use crate::git;
+use crate::checkpoint::CheckpointStore;
+use crate::editor;
+use crate::git::{self, GitProgress};
 use crate::history;
+use crate::issue_import::{self, ImportSource};
 use crate::llm;
+use crate::milestones::{self, Milestone};
+use crate::persistence::{self, PersistedState, SavedState};
+use crate::report::{self, PlanReport};
+use crate::verification::{self, VerificationReport};
 use crate::state::{
     ApprovalChoice, BranchConfirmChoice, CompletionChoice, DirtyFilesChoice,
-    PlannerState, RecoveryChoice, RecoveryInfo,
+    FeatureBranchChoice, MilestoneSplitChoice, PlannerState, RecoveryChoice, RecoveryInfo,
+    ResumeStateChoice,
 };
 
+/// Default plan directory, relative to the codepath
+pub const DEFAULT_PLAN_DIR: &str = "g3-plan";
+
 /// Configuration for planning mode
 #[derive(Debug, Clone)]
 pub struct PlannerConfig {
@@ -23,18 +36,45 @@ pub struct PlannerConfig {
     pub codepath: PathBuf,
     /// Whether git operations are disabled
     pub no_git: bool,
+    /// Stop after refinement and write a plan report instead of implementing
+    pub dry_run: bool,
     /// Maximum turns for coach/player loop
     pub max_turns: usize,
     /// Whether to run in quiet mode
     pub quiet: bool,
     /// Path to config file
     pub config_path: Option<String>,
+    /// Custom plan directory; relative paths are resolved against the codepath.
+    /// Defaults to `g3-plan`.
+    pub plan_dir: Option<PathBuf>,
+    /// Name of the plan. Named plans live in their own subdirectory of the plan
+    /// directory, so several independent plans can coexist in one repo.
+    pub name: Option<String>,
 }
 
 impl PlannerConfig {
-    /// Get the g3-plan directory path
+    /// Get the directory holding all plans (`g3-plan` unless overridden)
+    pub fn plan_root(&self) -> PathBuf {
+        match &self.plan_dir {
+            Some(dir) if dir.is_absolute() => dir.clone(),
+            Some(dir) => self.codepath.join(dir),
+            None => self.codepath.join(DEFAULT_PLAN_DIR),
+        }
+    }
+
+    /// Get the directory for this plan's history, requirements and todo files
     pub fn plan_dir(&self) -> PathBuf {
-        self.codepath.join("g3-plan")
+        match &self.name {
+            Some(name) => self.plan_root().join(name),
+            None => self.plan_root(),
+        }
+    }
+
+    /// Get a path relative to the codepath for display and git matching
+    pub fn relative_to_codepath(&self, path: &Path) -> PathBuf {
+        path.strip_prefix(&self.codepath)
+            .map(Path::to_path_buf)
+            .unwrap_or_else(|_| path.to_path_buf())
     }
 
     /// Get the path to new_requirements.md
@@ -58,6 +98,41 @@ impl PlannerConfig {
     }
 }
 
+/// Validate a plan name given with `--plan`.
+///
+/// Names become directory names, so they are restricted to ASCII letters,
+/// digits, `-`, `_` and `.`, and may not start with a dot.
+pub fn validate_plan_name(name: &str) -> Result<()> {
+    if name.is_empty() {
+        anyhow::bail!("Plan name must not be empty");
+    }
+    if name.starts_with('.') {
+        anyhow::bail!("Plan name must not start with '.': {}", name);
+    }
+    if let Some(c) = name
+        .chars()
+        .find(|c| !(c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.')))
+    {
+        anyhow::bail!("Invalid character '{}' in plan name: {}", c, name);
+    }
+    Ok(())
+}
+
+/// List the names of existing named plans under the plan root
+pub fn list_plans(plan_root: &Path) -> Vec<String> {
+    let mut plans: Vec<String> = match fs::read_dir(plan_root) {
+        Ok(entries) => entries
+            .filter_map(|entry| entry.ok())
+            .filter(|entry| entry.path().is_dir())
+            .filter(|entry| entry.path().join("planner_history.txt").exists())
+            .filter_map(|entry| entry.file_name().to_str().map(|s| s.to_string()))
+            .collect(),
+        Err(_) => Vec::new(),
+    };
+    plans.sort();
+    plans
+}
+
 /// Result of running planning mode
 #[derive(Debug)]
 pub enum PlannerResult {
@@ -130,13 +205,16 @@ pub fn initialize_plan_dir(config: &PlannerConfig) -> Result<()> {
     // Create plan directory if it doesn't exist
     if !plan_dir.exists() {
         fs::create_dir_all(&plan_dir)
-            .context("Failed to create g3-plan directory")?;
+            .context("Failed to create plan directory")?;
         print_msg(&format!("📁 Created {}", plan_dir.display()));
     }
     
     // Ensure history file exists
     history::ensure_history_file(&plan_dir)?;
     
+    // The saved state is local to this checkout and must not be committed
+    persistence::ensure_ignored(&plan_dir)?;
+    
     Ok(())
 }
 
@@ -168,8 +246,9 @@ pub fn check_git_status(config: &PlannerConfig) -> Result<()> {
     }
     
     // Check for dirty/untracked files (ignore new_requirements.md)
-    let ignore_pattern = "g3-plan/new_requirements.md";
-    let dirty_files = git::check_dirty_files(&config.codepath, Some(ignore_pattern))?;
+    let ignore_pattern = config.relative_to_codepath(&config.new_requirements_path());
+    let ignore_pattern = ignore_pattern.to_string_lossy();
+    let dirty_files = git::check_dirty_files(&config.codepath, Some(&ignore_pattern))?;
     
     if !dirty_files.is_empty() {
         let warning = r#"Warning: There are uncommitted changes in the git repository:
@@ -200,32 +279,113 @@ pub fn check_startup_state(config: &PlannerConfig) -> PlannerState {
     
     // Check for recovery situation
     if let Some(recovery_info) = RecoveryInfo::detect(&plan_dir) {
-        return PlannerState::Recovery(recovery_info);
+        let progress = if config.no_git {
+            None
+        } else {
+            recovery_progress(config)
+        };
+        return PlannerState::Recovery(recovery_info.with_progress(progress));
     }
     
     PlannerState::PromptForRequirements
 }
 
+/// Offer to resume from the state saved by a previous, interrupted run.
+///
+/// Returns the state to start in and the saved refinement pass count, or
+/// `None` to start normally. Saved states that don't match the files on disk
+/// (e.g. requirements deleted since) are discarded.
+pub fn check_saved_state(config: &PlannerConfig) -> Result<Option<(PlannerState, usize)>> {
+    let plan_dir = config.plan_dir();
+    let Some(saved) = persistence::load(&plan_dir) else {
+        return Ok(None);
+    };
+    
+    let consistent = match saved.state {
+        SavedState::PromptForRequirements => false,
+        SavedState::RefineRequirements | SavedState::ApproveRequirements => {
+            config.new_requirements_path().exists()
+        }
+        _ => config.current_requirements_path().exists() || config.new_requirements_path().exists(),
+    };
+    if !consistent {
+        persistence::clear(&plan_dir)?;
+        return Ok(None);
+    }
+    
+    let prompt = r#"Found a saved planner state from {updated}: {description}{iterations}.
+    Resume there? [Y/n/q]"#
+        .replace("{updated}", &saved.updated)
+        .replace("{description}", &saved.state.describe())
+        .replace("{iterations}", &if saved.refine_iterations > 0 {
+            format!(" (refinement pass {})", saved.refine_iterations)
+        } else {
+            String::new()
+        });
+    print_msg(&prompt);
+    print_prompt("Choice: ");
+    
+    loop {
+        let input = read_line()?;
+        match ResumeStateChoice::from_input(&input) {
+            Some(ResumeStateChoice::Resume) => {
+                return Ok(Some((saved.state.to_state(), saved.refine_iterations)));
+            }
+            Some(ResumeStateChoice::Discard) => {
+                persistence::clear(&plan_dir)?;
+                return Ok(None);
+            }
+            Some(ResumeStateChoice::Quit) => return Ok(Some((PlannerState::Quit, 0))),
+            None => {
+                print_prompt("Invalid choice. Please enter Y, N, or Q: ");
+            }
+        }
+    }
+}
+
+/// Compare the GIT HEAD recorded at the start of the interrupted implementation
+/// against the repository, so the user can see what was already done
+fn recovery_progress(config: &PlannerConfig) -> Option<GitProgress> {
+    let recorded_head = history::last_git_head(&config.plan_dir()).ok()??;
+    match git::progress_since(&config.codepath, &recorded_head) {
+        Ok(progress) => Some(progress),
+        Err(e) => {
+            print_msg(&format!("⚠️  Could not compare against recorded GIT HEAD: {}", e));
+            None
+        }
+    }
+}
+
 /// Handle recovery situation
 pub fn handle_recovery(config: &PlannerConfig, info: &RecoveryInfo) -> Result<PlannerState> {
     // Build the recovery prompt
     let datetime = info.requirements_modified.as_deref().unwrap_or("unknown time");
     let todo_info = if let Some(ref contents) = info.todo_contents {
-        "- todo.g3.md contents:\n{contents}".replace("{contents}", contents)
+        "- todo.g3.md ({stats}):\n{contents}"
+            .replace("{stats}", &g3_core::todo::stats(contents).to_string())
+            .replace("{contents}", contents)
     } else {
         String::new()
     };
+    let progress_info = info
+        .progress
+        .as_ref()
+        .map(|progress| progress.to_display_string())
+        .unwrap_or_default();
     
     let prompt = r#"The last run didn't complete successfully. Found:
     - current_requirements.md from {datetime}
     {todo_info}
+
+    {progress_info}
     
     Would you like to resume the previous implementation?
     [Y] Yes - Attempt to resume
     [N] No - Mark as complete and proceed to review new_requirements.md
     [Q] Quit - Exit and investigate manually"#
         .replace("{datetime}", datetime)
-        .replace("{todo_info}", &todo_info);
+        .replace("{todo_info}", &todo_info)
+        .replace("{progress_info}", &progress_info);
     
     print_msg(&prompt);
     print_prompt("Choice: ");
@@ -253,6 +413,36 @@ pub fn handle_recovery(config: &PlannerConfig, info: &RecoveryInfo) -> Result<Pl
     }
 }
 
+/// Seed new_requirements.md from an issue and record its source in the history
+pub async fn import_requirements(config: &PlannerConfig, source: &ImportSource) -> Result<()> {
+    let new_req_path = config.new_requirements_path();
+    let has_content = fs::read_to_string(&new_req_path)
+        .map(|content| !content.trim().is_empty())
+        .unwrap_or(false);
+    if has_content {
+        anyhow::bail!(
+            "{} already has content; move it aside before importing",
+            new_req_path.display()
+        );
+    }
+
+    print_msg("📥 Importing requirements...");
+    let (issue, link) = issue_import::import_issue(source).await?;
+    fs::write(&new_req_path, issue_import::render_requirements(&issue, &link))
+        .context("Failed to write new_requirements.md")?;
+    history::write_imported_requirements(&config.plan_dir(), &link)?;
+    print_msg(&format!("✅ Imported \"{}\" from {}", issue.title.trim(), link));
+    Ok(())
+}
+
+/// Append a reference to the originating issue to a commit description
+pub fn with_issue_reference(description: &str, source: Option<&str>) -> String {
+    match source {
+        Some(source) => format!("{}\n\nRefs: {}", description.trim_end(), source),
+        None => description.to_string(),
+    }
+}
+
 /// Prompt for new requirements
 pub fn prompt_for_new_requirements(config: &PlannerConfig) -> Result<PlannerState> {
     // Delete existing todo file since we're starting fresh
@@ -264,9 +454,9 @@ pub fn prompt_for_new_requirements(config: &PlannerConfig) -> Result<PlannerStat
     
     // Display prompt
     let prompt = r#"I will help you refine the current requirements of your project.
-    Please write or edit your requirements in `{codepath}/g3-plan/new_requirements.md`.
-    Hit enter for me to start a review of that file."#
-        .replace("{codepath}", &config.codepath.display().to_string());
+    Please write or edit your requirements in `{path}`.
+    Hit enter for me to start a review of that file, or type 'edit' to open it in your editor."#
+        .replace("{path}", &config.new_requirements_path().display().to_string());
     print_msg(&prompt);
     print_prompt("Press Enter when ready: ");
     
@@ -274,12 +464,24 @@ pub fn prompt_for_new_requirements(config: &PlannerConfig) -> Result<PlannerStat
     if input.to_lowercase() == "quit" || input.to_lowercase() == "q" {
         return Ok(PlannerState::Quit);
     }
+
+    let new_req_path = config.new_requirements_path();
+    if input.to_lowercase() == "edit" || input.to_lowercase() == "e" {
+        if !new_req_path.exists() {
+            fs::write(&new_req_path, "")
+                .context("Failed to create new_requirements.md")?;
+        }
+        if let Err(e) = editor::open_in_editor(&new_req_path) {
+            print_msg(&format!("❌ {}", e));
+            print_msg("Set $EDITOR (or $VISUAL) to your preferred editor, or edit the file manually.");
+            return Ok(PlannerState::PromptForRequirements);
+        }
+    }
     
     // Check if new_requirements.md exists
-    let new_req_path = config.new_requirements_path();
     if !new_req_path.exists() {
-        let error_msg = "File not found: {path}/g3-plan/new_requirements.md"
-            .replace("{path}", &config.codepath.display().to_string());
+        let error_msg = "File not found: {path}"
+            .replace("{path}", &new_req_path.display().to_string());
         print_msg(&format!("❌ {}", error_msg));
         print_msg("Please create the file and try again.");
         return Ok(PlannerState::PromptForRequirements);
@@ -294,6 +496,30 @@ pub fn prompt_for_new_requirements(config: &PlannerConfig) -> Result<PlannerStat
     Ok(PlannerState::RefineRequirements)
 }
 
+/// Open new_requirements.md in the user's editor and send the result back
+/// through refinement
+pub fn edit_requirements(config: &PlannerConfig) -> Result<PlannerState> {
+    let new_req_path = config.new_requirements_path();
+    print_msg(&format!("📝 Opening {} in your editor...", new_req_path.display()));
+
+    if let Err(e) = editor::open_in_editor(&new_req_path) {
+        print_msg(&format!("❌ {}", e));
+        print_msg("Set $EDITOR (or $VISUAL) to your preferred editor, or edit the file manually.");
+        return Ok(PlannerState::PromptForRequirements);
+    }
+
+    if !new_req_path.exists() {
+        print_msg(&format!("❌ File not found after editing: {}", new_req_path.display()));
+        return Ok(PlannerState::PromptForRequirements);
+    }
+
+    // Re-ingest the edited file
+    ensure_original_requirements_tag(&new_req_path)?;
+    history::write_refining_requirements(&config.plan_dir())?;
+
+    Ok(PlannerState::RefineRequirements)
+}
+
 /// Ensure the new_requirements.md file has the ORIGINAL_REQUIREMENTS tag
 fn ensure_original_requirements_tag(path: &Path) -> Result<()> {
     let content = fs::read_to_string(path)
@@ -324,10 +550,12 @@ pub fn check_current_requirements_tag(config: &PlannerConfig) -> Result<bool> {
 
 /// Prompt user to approve refined requirements
 pub fn prompt_for_approval(config: &PlannerConfig) -> Result<ApprovalChoice> {
-    let prompt = r#"The LLM has updated `{codepath}/g3-plan/new_requirements.md`.
-    Please review the file. If it's acceptable, type 'yes' to proceed with implementation.
-    Type 'no' to continue refining, or 'quit' to exit."#
-        .replace("{codepath}", &config.codepath.display().to_string());
+    let prompt = r#"The LLM has updated `{path}`.
+    Please review the file. If it's acceptable, type 'yes' to proceed with {next_step}.
+    Type 'edit' to change it in your editor and refine again,
+    'no' to continue refining, or 'quit' to exit."#
+        .replace("{path}", &config.new_requirements_path().display().to_string())
+        .replace("{next_step}", if config.dry_run { "the plan report" } else { "implementation" });
     print_msg(&prompt);
     print_prompt("Choice: ");
     
@@ -336,7 +564,114 @@ pub fn prompt_for_approval(config: &PlannerConfig) -> Result<ApprovalChoice> {
         match ApprovalChoice::from_input(&input) {
             Some(choice) => return Ok(choice),
             None => {
-                print_prompt("Invalid choice. Please enter 'yes', 'no', or 'quit': ");
+                print_prompt("Invalid choice. Please enter 'yes', 'edit', 'no', or 'quit': ");
+            }
+        }
+    }
+}
+
+/// Prefix for feature branches created by the planner
+pub const FEATURE_BRANCH_PREFIX: &str = "g3/";
+
+/// Maximum length of the slug part of a feature branch name
+const MAX_BRANCH_SLUG_LEN: usize = 40;
+
+/// Derive a branch-name slug from requirements text.
+///
+/// Uses the first meaningful line of the `{{CURRENT REQUIREMENTS}}` section
+/// (or of the whole text if the tag is absent), lowercased, with runs of
+/// non-alphanumeric characters collapsed to `-`.
+pub fn branch_slug(requirements: &str) -> String {
+    let section = match requirements.find("{{CURRENT REQUIREMENTS}}") {
+        Some(pos) => &requirements[pos + "{{CURRENT REQUIREMENTS}}".len()..],
+        None => requirements,
+    };
+
+    let title = section
+        .lines()
+        .map(|line| line.trim().trim_start_matches(['#', '-', '*', '>']).trim())
+        .find(|line| !line.is_empty() && !line.starts_with("{{"))
+        .unwrap_or("");
+
+    let mut slug = String::new();
+    for c in title.chars() {
+        if c.is_ascii_alphanumeric() {
+            slug.push(c.to_ascii_lowercase());
+        } else if !slug.is_empty() && !slug.ends_with('-') {
+            slug.push('-');
+        }
+    }
+
+    // Cut at a word boundary so the name stays readable
+    if slug.len() > MAX_BRANCH_SLUG_LEN {
+        let cut = slug[..MAX_BRANCH_SLUG_LEN].rfind('-').unwrap_or(MAX_BRANCH_SLUG_LEN);
+        slug.truncate(cut);
+    }
+    let slug = slug.trim_matches('-').to_string();
+
+    if slug.is_empty() {
+        format!("plan-{}", history::format_timestamp_for_filename().to_lowercase())
+    } else {
+        slug
+    }
+}
+
+/// Offer to create and switch to a dedicated feature branch before implementing.
+///
+/// Returns `PlannerState::Quit` if the user chose to quit, otherwise
+/// `PlannerState::ImplementRequirements`.
+pub fn offer_feature_branch(config: &PlannerConfig) -> Result<PlannerState> {
+    if config.no_git {
+        return Ok(PlannerState::ImplementRequirements);
+    }
+
+    let requirements_path = if config.new_requirements_path().exists() {
+        config.new_requirements_path()
+    } else {
+        config.current_requirements_path()
+    };
+    let requirements = fs::read_to_string(&requirements_path).unwrap_or_default();
+
+    // Pick a name that doesn't collide with an existing branch
+    let base_name = format!("{}{}", FEATURE_BRANCH_PREFIX, branch_slug(&requirements));
+    let current_branch = git::get_current_branch(&config.codepath)?;
+    if current_branch == base_name {
+        return Ok(PlannerState::ImplementRequirements);
+    }
+    let mut branch = base_name.clone();
+    let mut suffix = 2;
+    while git::branch_exists(&config.codepath, &branch)? {
+        branch = format!("{}-{}", base_name, suffix);
+        suffix += 1;
+    }
+
+    let prompt = r#"You are on branch `{current}`.
+    Create and switch to a new branch `{branch}` for this implementation?
+    [Y] Yes - Create the branch
+    [N] No - Stay on the current branch
+    [Q] Quit"#
+        .replace("{current}", &current_branch)
+        .replace("{branch}", &branch);
+    print_msg(&prompt);
+    print_prompt("Choice: ");
+
+    loop {
+        let input = read_line()?;
+        match FeatureBranchChoice::from_input(&input) {
+            Some(FeatureBranchChoice::Create) => {
+                git::create_branch(&config.codepath, &branch)?;
+                history::write_git_branch(&config.plan_dir(), &branch)?;
+                print_msg(&format!("🌿 Switched to new branch {}", branch));
+                return Ok(PlannerState::ImplementRequirements);
+            }
+            Some(FeatureBranchChoice::Skip) => {
+                return Ok(PlannerState::ImplementRequirements);
+            }
+            Some(FeatureBranchChoice::Quit) => {
+                return Ok(PlannerState::Quit);
+            }
+            None => {
+                print_prompt("Invalid choice. Please enter Y, N, or Q: ");
             }
         }
     }
@@ -377,21 +712,97 @@ pub fn read_todo(config: &PlannerConfig) -> Result<Option<String>> {
 
 /// Check if all todos are complete
 pub fn check_todos_complete(todo_contents: &str) -> bool {
-    // Check if there are any incomplete items (- [ ])
-    !todo_contents.contains("- [ ]")
+    g3_core::todo::stats(todo_contents).pending() == 0
+}
+
+/// The `[planner]` config section
+fn planning_settings(config: &PlannerConfig) -> g3_config::PlanningConfig {
+    g3_config::Config::load(config.config_path.as_deref())
+        .map(|c| c.planner)
+        .unwrap_or_default()
+}
+
+/// Run the `[planner]` verification commands, printing and recording each
+/// result in the history
+pub fn run_verification_gate(
+    config: &PlannerConfig,
+    settings: &g3_config::PlanningConfig,
+) -> Result<VerificationReport> {
+    let commands = &settings.verification_commands;
+    if commands.is_empty() {
+        return Ok(VerificationReport::default());
+    }
+    
+    print_msg("🧪 Running verification commands...");
+    let report = verification::run_commands(
+        &config.codepath,
+        commands,
+        Duration::from_secs(settings.verification_timeout_secs),
+    );
+    for outcome in &report.outcomes {
+        history::write_verification(&config.plan_dir(), &outcome.command, outcome.passed)?;
+    }
+    for line in report.summary().lines() {
+        print_msg(&format!("  {}", line));
+    }
+    
+    Ok(report)
+}
+
+/// Run the verification gate before offering completion.
+///
+/// Returns `None` if every command passed. Otherwise shows the failures and
+/// returns the user's choice between the coach/player loop and quitting.
+fn check_verification_gate(config: &PlannerConfig) -> Result<Option<CompletionChoice>> {
+    let report = run_verification_gate(config, &planning_settings(config))?;
+    if report.passed() {
+        return Ok(None);
+    }
+    
+    let prompt = r#"Verification commands failed, so this can't be marked complete yet:
+    
+    {failures}
+    
+    Enter N to return to the coach/player loop, or Q to quit."#
+        .replace("{failures}", &report.failure_report());
+    print_msg(&prompt);
+    print_prompt("Choice: ");
+    
+    loop {
+        let input = read_line()?;
+        match CompletionChoice::from_input(&input) {
+            Some(CompletionChoice::Complete) => {
+                print_prompt("Verification must pass first. Please enter N or Q: ");
+            }
+            Some(choice) => return Ok(Some(choice)),
+            None => {
+                print_prompt("Invalid choice. Please enter N or Q: ");
+            }
+        }
+    }
 }
 
 /// Prompt user to confirm implementation completion
 pub fn prompt_for_completion(config: &PlannerConfig) -> Result<CompletionChoice> {
-    let todo_contents = read_todo(config)?.unwrap_or_else(|| "(no todo file)".to_string());
+    if let Some(choice) = check_verification_gate(config)? {
+        return Ok(choice);
+    }
+    
+    let todo = read_todo(config)?;
+    let todo_stats = match &todo {
+        Some(contents) => format!(" ({})", g3_core::todo::stats(contents)),
+        None => String::new(),
+    };
+    let todo_contents = todo.unwrap_or_else(|| "(no todo file)".to_string());
     
     let prompt = r#"The coach/player loop has completed.
     
-    Todo file contents:
+    Todo file contents{todo_stats}:
     {todo_contents}
     
     Do you consider the todos and requirements completed? [Y/n]
     If not, we'll return to the coach/player loop."#
+        .replace("{todo_stats}", &todo_stats)
         .replace("{todo_contents}", &todo_contents);
     print_msg(&prompt);
     print_prompt("Choice: ");
@@ -433,12 +844,208 @@ pub fn complete_implementation(config: &PlannerConfig) -> Result<(String, String
         print_msg(&format!("📄 Renamed to {}", todo_filename));
     }
     
+    // Archive milestones.md if the implementation was split into milestones
+    let milestones_path = plan_dir.join(milestones::MILESTONES_FILE);
+    if milestones_path.exists() {
+        let milestones_filename = history::completed_milestones_filename();
+        fs::rename(&milestones_path, plan_dir.join(&milestones_filename))
+            .context("Failed to rename milestones.md")?;
+        print_msg(&format!("📄 Renamed to {}", milestones_filename));
+    }
+    
     // Log completion
     history::write_completed_requirements(&plan_dir, &req_filename, &todo_filename)?;
     
     Ok((req_filename, todo_filename))
 }
 
+/// Ask whether to split the approved requirements into milestones
+pub fn prompt_for_milestone_split() -> Result<MilestoneSplitChoice> {
+    print_msg("\nSplit the requirements into milestones, committing after each one? [y/N]");
+    print_prompt("Choice: ");
+    
+    loop {
+        let input = read_line()?;
+        match MilestoneSplitChoice::from_input(&input) {
+            Some(choice) => return Ok(choice),
+            None => {
+                print_prompt("Invalid choice. Please enter Y or N: ");
+            }
+        }
+    }
+}
+
+/// Index of the next milestone to implement, if a milestone plan is in progress
+pub fn resume_milestone(config: &PlannerConfig) -> Result<Option<usize>> {
+    let plan_dir = config.plan_dir();
+    let Some(milestones) = milestones::load_milestones(&plan_dir)? else {
+        return Ok(None);
+    };
+    Ok(history::completed_milestones(&plan_dir)?.filter(|&done| done < milestones.len()))
+}
+
+/// Save a milestone plan, seed the todo file with one section per milestone
+/// and record it in the history
+pub fn plan_milestones(config: &PlannerConfig, milestones: &[Milestone]) -> Result<PlannerState> {
+    let plan_dir = config.plan_dir();
+    milestones::save_milestones(&plan_dir, milestones)?;
+    fs::write(config.todo_path(), milestones::render_todo(milestones))
+        .context("Failed to write todo.g3.md")?;
+    history::write_milestones_planned(&plan_dir, milestones.len())?;
+    
+    print_msg(&format!("🧭 Planned {} milestones:", milestones.len()));
+    for (i, milestone) in milestones.iter().enumerate() {
+        print_msg(&format!("  {}. {}", i + 1, milestone.title));
+    }
+    
+    Ok(PlannerState::ImplementMilestone(0))
+}
+
+/// Prompt user to confirm a milestone is complete
+pub fn prompt_for_milestone_completion(
+    config: &PlannerConfig,
+    milestones: &[Milestone],
+    index: usize,
+) -> Result<CompletionChoice> {
+    if let Some(choice) = check_verification_gate(config)? {
+        return Ok(choice);
+    }
+    
+    let milestone = &milestones[index];
+    let criteria = milestone
+        .acceptance_criteria
+        .iter()
+        .map(|c| format!("  - {}", c))
+        .collect::<Vec<_>>()
+        .join("\n");
+    
+    let prompt = r#"The coach/player loop has completed milestone {number}/{total}: {title}
+    
+    Acceptance criteria:
+    {criteria}
+    
+    Do you consider this milestone completed? [Y/n]
+    If not, we'll return to the coach/player loop for this milestone."#
+        .replace("{number}", &(index + 1).to_string())
+        .replace("{total}", &milestones.len().to_string())
+        .replace("{title}", &milestone.title)
+        .replace("{criteria}", &criteria);
+    print_msg(&prompt);
+    print_prompt("Choice: ");
+    
+    loop {
+        let input = read_line()?;
+        match CompletionChoice::from_input(&input) {
+            Some(choice) => return Ok(choice),
+            None => {
+                print_prompt("Invalid choice. Please enter Y, N, or Q: ");
+            }
+        }
+    }
+}
+
+/// Record a completed milestone, commit it, and move on to the next one
+pub fn complete_milestone(
+    config: &PlannerConfig,
+    milestones: &[Milestone],
+    index: usize,
+) -> Result<PlannerState> {
+    let milestone = &milestones[index];
+    let number = index + 1;
+    history::write_milestone_completed(&config.plan_dir(), number, &milestone.title)?;
+    
+    let summary = format!("Milestone {}/{}: {}", number, milestones.len(), milestone.title);
+    let description = milestone
+        .acceptance_criteria
+        .iter()
+        .map(|c| format!("- {}", c))
+        .collect::<Vec<_>>()
+        .join("\n");
+    let source = history::imported_source(&config.plan_dir())?;
+    let description = with_issue_reference(&description, source.as_deref());
+    stage_and_commit(config, &summary, &description)?;
+    
+    if number < milestones.len() {
+        Ok(PlannerState::ImplementMilestone(number))
+    } else {
+        print_msg("🏁 All milestones completed");
+        Ok(PlannerState::ImplementationComplete)
+    }
+}
+
+/// Run the coach/player loop for one milestone and ask whether it is done
+pub async fn implement_milestone(config: &PlannerConfig, index: usize) -> Result<PlannerState> {
+    let milestones = match milestones::load_milestones(&config.plan_dir())? {
+        Some(milestones) if index < milestones.len() => milestones,
+        _ => {
+            print_msg("⚠️  Milestone plan not found, skipping to completion.");
+            return Ok(PlannerState::ImplementationComplete);
+        }
+    };
+    
+    let requirements_content = read_current_requirements(config)?;
+    let milestone_requirements =
+        milestones::milestone_requirements(&requirements_content, &milestones, index);
+    
+    print_msg(&format!(
+        "\n🚀 Milestone {}/{}: {}",
+        index + 1,
+        milestones.len(),
+        milestones[index].title
+    ));
+    run_implementation(config, &milestone_requirements).await?;
+    
+    match prompt_for_milestone_completion(config, &milestones, index)? {
+        CompletionChoice::Complete => complete_milestone(config, &milestones, index),
+        CompletionChoice::Continue => Ok(PlannerState::ImplementMilestone(index)),
+        CompletionChoice::Quit => Ok(PlannerState::Quit),
+    }
+}
+
+/// Run the coach/player loop, reporting (rather than propagating) loop errors
+async fn run_implementation(config: &PlannerConfig, requirements: &str) -> Result<()> {
+    print_msg("\n🚀 Starting coach/player implementation loop...");
+    
+    let g3_config = g3_config::Config::load(config.config_path.as_deref())?;
+    match run_coach_player_loop(config, &g3_config, requirements).await {
+        Ok(_) => print_msg("✅ Coach/player loop completed"),
+        Err(e) => {
+            print_msg(&format!("⚠️  Implementation error: {}", e));
+            print_msg("You can try to resume or mark as complete.");
+        }
+    }
+    
+    Ok(())
+}
+
+/// Write `plan_report.md` for the refined requirements instead of implementing them
+pub async fn write_plan_report(
+    config: &PlannerConfig,
+    provider: &dyn g3_providers::LLMProvider,
+) -> Result<PlannerState> {
+    let requirements = fs::read_to_string(config.new_requirements_path())
+        .context("Failed to read new_requirements.md")?;
+    
+    print_msg("🔍 Exploring codebase for the plan report...");
+    let codebase_report = crate::explore_codebase(&config.codepath.display().to_string());
+    
+    print_msg("📝 Generating plan report...");
+    let plan_report = match llm::generate_plan_report(provider, &requirements, &codebase_report).await {
+        Ok(plan_report) => plan_report,
+        Err(e) => {
+            print_msg(&format!("⚠️  Plan report generation failed: {}", e));
+            PlanReport::default()
+        }
+    };
+    
+    let content = plan_report.render(&config.codepath, &history::format_timestamp(), &requirements);
+    let path = report::write_report(&config.plan_dir(), &content)?;
+    print_msg(&format!("✅ Plan report written to {}", path.display()));
+    print_msg("Dry run: skipping implementation. Run without --dry-run to implement the requirements.");
+    
+    Ok(PlannerState::Quit)
+}
+
 /// Stage files and make git commit
 pub fn stage_and_commit(
     config: &PlannerConfig,
@@ -452,13 +1059,24 @@ pub fn stage_and_commit(
     
     // Stage files
     print_msg("📦 Staging files...");
-    let staging_result = git::stage_files(&config.codepath, &config.plan_dir())?;
+    let planner_settings = g3_config::Config::load(config.config_path.as_deref())
+        .map(|c| c.planner)
+        .unwrap_or_default();
+    let extra_excludes = planner_settings.extra_excludes;
+    let staging_result = git::stage_files_with_excludes(
+        &config.codepath,
+        &config.plan_dir(),
+        &extra_excludes,
+    )?;
     
     if !staging_result.staged.is_empty() {
         print_msg(&format!("  Staged {} files", staging_result.staged.len()));
     }
     if !staging_result.excluded.is_empty() {
-        print_msg(&format!("  Excluded {} files (temporary/artifacts)", staging_result.excluded.len()));
+        print_msg(&format!("  Excluded {} files:", staging_result.excluded.len()));
+        for excluded in &staging_result.excluded {
+            print_msg(&format!("    {} ({})", excluded.path, excluded.rule));
+        }
     }
     
     // Show pre-commit message
@@ -487,13 +1105,36 @@ pub fn stage_and_commit(
     // - Tests in commit_history_ordering_test.rs continue to pass
     history::write_git_commit(&config.plan_dir(), summary)?;
     
-    // Re-stage g3-plan directory to include the GIT COMMIT entry we just wrote
+    // Re-stage the plan directory to include the GIT COMMIT entry we just wrote
     // This ensures planner_history.txt changes are included in the commit
     git::stage_plan_dir(&config.codepath, &config.plan_dir())?;
     
     // Make commit
     print_msg("📝 Making git commit...");
-    let _commit_sha = git::commit(&config.codepath, summary, description)?;
+    let options = git::CommitOptions { no_verify: planner_settings.no_verify };
+    let result = match git::commit_with_options(&config.codepath, summary, description, options) {
+        Ok(result) => result,
+        Err(e) => {
+            if let Some(git::CommitError::HookRejected { hooks, modified_files, output }) =
+                e.downcast_ref::<git::CommitError>()
+            {
+                print_msg(&format!("❌ Commit rejected by git hooks ({})", hooks.join(", ")));
+                if !modified_files.is_empty() {
+                    print_msg(&format!("  Files modified by the hooks (re-staged): {}", modified_files.join(", ")));
+                }
+                print_msg(&format!("  Hook output:\n{}", output));
+                print_msg("  Fix the reported issues and commit manually, or set `no_verify = true` in the [planner] config section.");
+            }
+            return Err(e);
+        }
+    };
+    if !result.restaged.is_empty() {
+        print_msg(&format!(
+            "🔁 Re-staged {} file(s) modified by git hooks: {}",
+            result.restaged.len(),
+            result.restaged.join(", ")
+        ));
+    }
     print_msg("✅ Commit successful");
     
     Ok(())
@@ -596,12 +1237,30 @@ pub async fn run_coach_player_loop(
     // Set environment variable for custom todo path
     std::env::set_var("G3_TODO_PATH", planner_config.todo_path().display().to_string());
     
+    // With rollback enabled, each player turn starts from a checkpoint and is
+    // undone if it breaks a verification command that passed before it
+    let rollback = g3_config.planner.rollback_failed_turns
+        && !g3_config.planner.verification_commands.is_empty();
+    let checkpoints = CheckpointStore::new(&planner_config.codepath, vec![planner_config.plan_root()]);
+    let mut passing = if rollback {
+        let baseline = run_verification_gate(planner_config, &g3_config.planner)?;
+        passed_commands(&baseline)
+    } else {
+        HashSet::new()
+    };
+    
     let mut turn = 1;
     let mut coach_feedback = String::new();
     
     while turn <= max_turns {
         print_msg(&format!("\n=== Turn {}/{} ===", turn, max_turns));
         
+        let checkpoint = if rollback {
+            Some(checkpoints.create()?)
+        } else {
+            None
+        };
+        
         // Player phase - implement requirements
         print_msg("🎯 Player: Implementing requirements...");
         
@@ -613,6 +1272,8 @@ pub async fn run_coach_player_loop(
             None,
             planner_config.quiet,
         ).await?;
+        // The prompt carries the requirements, so keep it through context retention
+        player_agent.pin_next_task();
         
         let player_prompt = if coach_feedback.is_empty() || turn == 1 {
             format!(
@@ -660,6 +1321,47 @@ pub async fn run_coach_player_loop(
             }
         }
         
+        // Verification gate - run configured commands before the coach reviews
+        let verification = run_verification_gate(planner_config, &g3_config.planner)?;
+        let verification_failures = if verification.passed() {
+            String::new()
+        } else {
+            format!(
+                "\n\nThe following verification commands FAILED:\n\n{}",
+                verification.failure_report()
+            )
+        };
+        
+        if let Some(checkpoint) = &checkpoint {
+            let broken: Vec<String> = verification
+                .failures()
+                .map(|outcome| outcome.command.clone())
+                .filter(|command| passing.contains(command))
+                .collect();
+            if !broken.is_empty() {
+                let summary = checkpoints.restore(checkpoint)?;
+                print_msg(&format!(
+                    "⏪ Rolled back turn {}: it broke {} ({} files restored, {} removed)",
+                    turn,
+                    broken.join(", "),
+                    summary.restored,
+                    summary.removed
+                ));
+                history::write_turn_rolled_back(&planner_config.plan_dir(), turn, &broken)?;
+                coach_feedback = format!(
+                    "{}\n\nYour last changes were ROLLED BACK because they broke commands that passed before them: {}. The workspace is back to how it was before those changes; take a different approach.{}",
+                    coach_feedback,
+                    broken.join(", "),
+                    verification_failures
+                )
+                .trim_start()
+                .to_string();
+                turn += 1;
+                continue;
+            }
+            passing = passed_commands(&verification);
+        }
+        
         // Coach phase - review implementation
         print_msg("🎓 Coach: Reviewing implementation...");
         
@@ -671,10 +1373,16 @@ pub async fn run_coach_player_loop(
             None,
             planner_config.quiet,
         ).await?;
+        coach_agent.pin_next_task();
         
         let coach_prompt = format!(
-            "You are G3 in coach mode. Review the implementation against these requirements:\n\n{}\n\nCheck:\n1. Are requirements implemented correctly?\n2. Does the code compile?\n3. What's missing?\n\nUse the final_output tool to provide your feedback.\nIf implementation is COMPLETE, include 'IMPLEMENTATION_APPROVED' in your feedback.\nOtherwise, provide specific feedback for the player to fix.",
-            requirements_content
+            "You are G3 in coach mode. Review the implementation against these requirements:\n\n{}\n\nCheck:\n1. Are requirements implemented correctly?\n2. Does the code compile?\n3. What's missing?\n\nUse the final_output tool to provide your feedback.\nIf implementation is COMPLETE, include 'IMPLEMENTATION_APPROVED' in your feedback.\nOtherwise, provide specific feedback for the player to fix.{}",
+            requirements_content,
+            if verification_failures.is_empty() {
+                String::new()
+            } else {
+                format!("{}\n\nDo NOT approve the implementation until these commands pass.", verification_failures)
+            }
         );
         
         // Execute coach task with retry logic
@@ -700,11 +1408,15 @@ pub async fn run_coach_player_loop(
                 
                 // Check for approval
                 if extracted.is_approved() || result.response.contains("IMPLEMENTATION_APPROVED") {
-                    print_msg("✅ Coach approved implementation!");
-                    return Ok(());
+                    if verification.passed() {
+                        print_msg("✅ Coach approved implementation!");
+                        checkpoints.clear()?;
+                        return Ok(());
+                    }
+                    print_msg("⚠️  Coach approved, but verification commands failed");
                 }
                 
-                coach_feedback = extracted.content;
+                coach_feedback = extracted.content + &verification_failures;
                 
                 // Display first 25 lines of coach feedback
                 let lines: Vec<&str> = coach_feedback.lines().collect();
@@ -717,7 +1429,7 @@ pub async fn run_coach_player_loop(
             }
             RetryResult::MaxRetriesReached(err) => {
                 print_msg(&format!("⚠️  Coach failed after max retries: {}", err));
-                coach_feedback = "Please review and fix any issues.".to_string();
+                coach_feedback = "Please review and fix any issues.".to_string() + &verification_failures;
             }
             RetryResult::ContextLengthExceeded(err) => {
                 print_msg(&format!("⚠️  Coach context length exceeded: {}", err));
@@ -733,9 +1445,20 @@ pub async fn run_coach_player_loop(
     }
     
     print_msg(&format!("⏰ Reached max turns ({})", max_turns));
+    checkpoints.clear()?;
     Ok(())
 }
 
+/// The verification commands that passed
+fn passed_commands(report: &VerificationReport) -> HashSet<String> {
+    report
+        .outcomes
+        .iter()
+        .filter(|outcome| outcome.passed)
+        .map(|outcome| outcome.command.clone())
+        .collect()
+}
+
 /// Main entry point for planning mode
 /// 
 /// This function orchestrates the entire planning workflow:
@@ -743,14 +1466,27 @@ pub async fn run_coach_player_loop(
 /// 2. Check git status (if enabled)
 /// 3. Detect and handle recovery situations
 /// 4. Run the refinement and implementation loop
+///
+/// `plan_dir` overrides the `g3-plan` directory and `plan_name` selects a
+/// named plan inside it. With `dry_run`, planning stops after refinement and
+/// writes `plan_report.md` instead of implementing.
+#[allow(clippy::too_many_arguments)]
 pub async fn run_planning_mode(
     codepath: Option<String>,
     workspace: Option<std::path::PathBuf>,
     no_git: bool,
+    dry_run: bool,
     config_path: Option<&str>,
+    plan_dir: Option<PathBuf>,
+    plan_name: Option<String>,
+    import: Option<ImportSource>,
 ) -> anyhow::Result<()> {
     print_msg("\n🎯 G3 Planning Mode");
     print_msg("==================\n");
+
+    if let Some(ref name) = plan_name {
+        validate_plan_name(name)?;
+    }
     
     // Get codepath first (needed for setting workspace path early)
     let codepath = match codepath {
@@ -803,10 +1539,23 @@ pub async fn run_planning_mode(
     let config = PlannerConfig {
         codepath: codepath.clone(),
         no_git,
+        dry_run,
         max_turns: 5, // Default, could be made configurable
         quiet: false,
         config_path: config_path.map(|s| s.to_string()),
+        plan_dir,
+        name: plan_name,
     };
+
+    match &config.name {
+        Some(name) => print_msg(&format!("📋 Plan: {} ({})", name, config.plan_dir().display())),
+        None => {
+            let plans = list_plans(&config.plan_root());
+            if !plans.is_empty() {
+                print_msg(&format!("📋 Named plans available (use --plan <name>): {}", plans.join(", ")));
+            }
+        }
+    }
     
     // Initialize plan directory
     initialize_plan_dir(&config)?;
@@ -814,10 +1563,33 @@ pub async fn run_planning_mode(
     // Check git status
     check_git_status(&config)?;
     
-    // Main planning loop
-    let mut state = check_startup_state(&config);
+    // Main planning loop. Dry runs never implement, so there is nothing to recover
+    let mut refine_iterations = 0;
+    if config.dry_run {
+        print_msg("🧪 Dry run: a plan report will be written instead of implementing");
+    }
+    let mut state = if let Some(source) = &import {
+        // Imported requirements start a new refinement
+        import_requirements(&config, source).await?;
+        PlannerState::PromptForRequirements
+    } else if config.dry_run {
+        PlannerState::PromptForRequirements
+    } else if let Some((saved_state, saved_iterations)) = check_saved_state(&config)? {
+        refine_iterations = saved_iterations;
+        saved_state
+    } else {
+        check_startup_state(&config)
+    };
     
     loop {
+        // Persist every resumable state so an interrupted run can pick up here
+        if let Some(saved) = SavedState::from_state(&state).filter(|_| !config.dry_run) {
+            if saved == SavedState::ImplementRequirements {
+                refine_iterations = 0;
+            }
+            persistence::save(&config.plan_dir(), &PersistedState::new(saved, refine_iterations))?;
+        }
+        
         state = match state {
             PlannerState::Startup => {
                 // Startup state transitions to checking for recovery
@@ -831,10 +1603,12 @@ pub async fn run_planning_mode(
             }
             PlannerState::RefineRequirements => {
                 // Call LLM for refinement with full tool execution
-                print_msg("\n🔄 Refinement phase - calling LLM...");
+                refine_iterations += 1;
+                print_msg(&format!("\n🔄 Refinement phase (pass {}) - calling LLM...", refine_iterations));
                 
                 let codepath_str = config.codepath.display().to_string();
                 let workspace_str = workspace_dir.display().to_string();
+                let plan_dir_str = config.plan_dir().display().to_string();
                 
                 // Load config and call LLM with full tool execution capability
                 let g3_config = g3_config::Config::load(config.config_path.as_deref())?;
@@ -842,6 +1616,7 @@ pub async fn run_planning_mode(
                     &g3_config,
                     &codepath_str,
                     &workspace_str,
+                    &plan_dir_str,
                 ).await;
                 
                 match response {
@@ -850,72 +1625,100 @@ pub async fn run_planning_mode(
                 }
                 
                 if check_current_requirements_tag(&config)? {
-                    match prompt_for_approval(&config)? {
-                        ApprovalChoice::Approve => PlannerState::ImplementRequirements,
-                        ApprovalChoice::Refine => PlannerState::PromptForRequirements,
-                        ApprovalChoice::Quit => PlannerState::Quit,
-                    }
+                    PlannerState::ApproveRequirements
                 } else {
                     print_msg(&format!("❌ {}", "The LLM didn't update the requirements file with {{CURRENT REQUIREMENTS}}. Please restart the app."));
                     PlannerState::Quit
                 }
             }
+            PlannerState::ApproveRequirements => {
+                match prompt_for_approval(&config)? {
+                    ApprovalChoice::Approve if config.dry_run => {
+                        write_plan_report(&config, provider.as_ref()).await?
+                    }
+                    ApprovalChoice::Approve => offer_feature_branch(&config)?,
+                    ApprovalChoice::Refine => PlannerState::PromptForRequirements,
+                    ApprovalChoice::Edit => edit_requirements(&config)?,
+                    ApprovalChoice::Quit => PlannerState::Quit,
+                }
+            }
             PlannerState::ImplementRequirements => {
                 // Promote requirements and run coach/player
                 if config.new_requirements_path().exists() {
                     promote_requirements(&config)?;
                 }
                 
-                // Write git HEAD to history before implementation
-                if !config.no_git {
-                    let head_sha = git::get_head_sha(&config.codepath)?;
-                    history::write_git_head(&config.plan_dir(), &head_sha)?;
-                    print_msg(&format!("📝 Recorded git HEAD: {}", &head_sha[..12.min(head_sha.len())]));
+                if let Some(index) = resume_milestone(&config)? {
+                    print_msg(&format!("🧭 Resuming milestone plan at milestone {}", index + 1));
+                    PlannerState::ImplementMilestone(index)
+                } else {
+                    // Write git HEAD to history before implementation
+                    if !config.no_git {
+                        let head_sha = git::get_head_sha(&config.codepath)?;
+                        history::write_git_head(&config.plan_dir(), &head_sha)?;
+                        print_msg(&format!("📝 Recorded git HEAD: {}", &head_sha[..12.min(head_sha.len())]));
+                    }
+                    
+                    // Read requirements and generate summary
+                    let requirements_content = read_current_requirements(&config)?;
+                    
+                    print_msg("📝 Generating requirements summary...");
+                    let summary = match llm::generate_requirements_summary(
+                        provider.as_ref(),
+                        &requirements_content,
+                    ).await {
+                        Ok(s) => s,
+                        Err(e) => {
+                            print_msg(&format!("⚠️  Summary generation failed: {}", e));
+                            "Requirements implementation in progress".to_string()
+                        }
+                    };
+                    
+                    // Write start implementing entry with summary
+                    history::write_start_implementing(&config.plan_dir(), &summary)?;
+                    print_msg("📝 Recorded implementation start in history");
+                    
+                    // A leftover milestone plan means all milestones are done and
+                    // the user chose to continue; don't offer to split again
+                    let split = if config.plan_dir().join(milestones::MILESTONES_FILE).exists() {
+                        MilestoneSplitChoice::Single
+                    } else {
+                        prompt_for_milestone_split()?
+                    };
+                    
+                    match split {
+                        MilestoneSplitChoice::Split => PlannerState::SplitMilestones,
+                        MilestoneSplitChoice::Single => {
+                            // Run the actual coach/player loop
+                            run_implementation(&config, &requirements_content).await?;
+                            PlannerState::ImplementationComplete
+                        }
+                    }
                 }
-                
-                // Read requirements and generate summary
+            }
+            PlannerState::SplitMilestones => {
+                print_msg("🧭 Splitting requirements into milestones...");
                 let requirements_content = read_current_requirements(&config)?;
                 
-                print_msg("📝 Generating requirements summary...");
-                let summary = match llm::generate_requirements_summary(
-                    provider.as_ref(),
-                    &requirements_content,
-                ).await {
-                    Ok(s) => s,
+                match llm::generate_milestones(provider.as_ref(), &requirements_content).await {
+                    Ok(milestones) => plan_milestones(&config, &milestones)?,
                     Err(e) => {
-                        print_msg(&format!("⚠️  Summary generation failed: {}", e));
-                        "Requirements implementation in progress".to_string()
-                    }
-                };
-                
-                // Write start implementing entry with summary
-                history::write_start_implementing(&config.plan_dir(), &summary)?;
-                print_msg("📝 Recorded implementation start in history");
-                
-                // Run the actual coach/player loop
-                print_msg("\n🚀 Starting coach/player implementation loop...");
-                
-                let g3_config = g3_config::Config::load(config.config_path.as_deref())?;
-                let implementation_result = run_coach_player_loop(
-                    &config,
-                    &g3_config,
-                    &requirements_content,
-                ).await;
-                
-                match implementation_result {
-                    Ok(_) => print_msg("✅ Coach/player loop completed"),
-                    Err(e) => {
-                        print_msg(&format!("⚠️  Implementation error: {}", e));
-                        print_msg("You can try to resume or mark as complete.");
+                        print_msg(&format!("⚠️  Milestone split failed: {}", e));
+                        print_msg("Implementing the requirements in a single pass.");
+                        run_implementation(&config, &requirements_content).await?;
+                        PlannerState::ImplementationComplete
                     }
                 }
-                
-                PlannerState::ImplementationComplete
+            }
+            PlannerState::ImplementMilestone(index) => {
+                implement_milestone(&config, index).await?
             }
             PlannerState::ImplementationComplete => {
                 // Check completion and commit
                 match prompt_for_completion(&config)? {
                     CompletionChoice::Complete => {
+                        // Read before archiving, which closes the requirements cycle
+                        let source = history::imported_source(&config.plan_dir())?;
                         let (req_file, todo_file) = complete_implementation(&config)?;
 
                         // Read requirements for LLM context
@@ -941,6 +1744,7 @@ pub async fn run_planning_mode(
                                  format!("Requirements: {}\nTodo: {}", req_file, todo_file))
                             }
                         };
+                        let description = with_issue_reference(&description, source.as_deref());
 
                         stage_and_commit(&config, &summary, &description)?;
                         PlannerState::PromptForRequirements
@@ -976,9 +1780,12 @@ mod tests {
         let config = PlannerConfig {
             codepath: PathBuf::from("/test/project"),
             no_git: false,
+            dry_run: false,
             max_turns: 5,
             quiet: false,
             config_path: None,
+            plan_dir: None,
+            name: None,
         };
 
         assert_eq!(config.plan_dir(), PathBuf::from("/test/project/g3-plan"));
@@ -987,6 +1794,82 @@ mod tests {
         assert_eq!(config.todo_path(), PathBuf::from("/test/project/g3-plan/todo.g3.md"));
     }
 
+    #[test]
+    fn test_named_plan_paths() {
+        let mut config = PlannerConfig {
+            codepath: PathBuf::from("/test/project"),
+            no_git: false,
+            dry_run: false,
+            max_turns: 5,
+            quiet: false,
+            config_path: None,
+            plan_dir: None,
+            name: Some("auth-rewrite".to_string()),
+        };
+
+        assert_eq!(config.plan_root(), PathBuf::from("/test/project/g3-plan"));
+        assert_eq!(config.plan_dir(), PathBuf::from("/test/project/g3-plan/auth-rewrite"));
+        assert_eq!(config.history_path(), PathBuf::from("/test/project/g3-plan/auth-rewrite/planner_history.txt"));
+        assert_eq!(
+            config.relative_to_codepath(&config.new_requirements_path()),
+            PathBuf::from("g3-plan/auth-rewrite/new_requirements.md")
+        );
+
+        config.plan_dir = Some(PathBuf::from("plans"));
+        assert_eq!(config.plan_dir(), PathBuf::from("/test/project/plans/auth-rewrite"));
+
+        config.plan_dir = Some(PathBuf::from("/elsewhere"));
+        config.name = None;
+        assert_eq!(config.todo_path(), PathBuf::from("/elsewhere/todo.g3.md"));
+    }
+
+    #[test]
+    fn test_validate_plan_name() {
+        assert!(validate_plan_name("feature-x").is_ok());
+        assert!(validate_plan_name("v2.1_cleanup").is_ok());
+        assert!(validate_plan_name("").is_err());
+        assert!(validate_plan_name(".hidden").is_err());
+        assert!(validate_plan_name("../escape").is_err());
+        assert!(validate_plan_name("a/b").is_err());
+    }
+
+    #[test]
+    fn test_list_plans() {
+        let temp_dir = TempDir::new().unwrap();
+        for name in ["beta", "alpha"] {
+            let config = PlannerConfig {
+                codepath: temp_dir.path().to_path_buf(),
+                no_git: true,
+                dry_run: false,
+                max_turns: 5,
+                quiet: false,
+                config_path: None,
+                plan_dir: None,
+                name: Some(name.to_string()),
+            };
+            initialize_plan_dir(&config).unwrap();
+        }
+        fs::create_dir_all(temp_dir.path().join("g3-plan/not-a-plan")).unwrap();
+
+        assert_eq!(list_plans(&temp_dir.path().join("g3-plan")), vec!["alpha", "beta"]);
+    }
+
+    #[test]
+    fn test_branch_slug() {
+        let requirements = "{{CURRENT REQUIREMENTS}}\n\n## Add OAuth login (GitHub & Google)\n\nDetails...\n\n{{ORIGINAL USER REQUIREMENTS -- THIS SECTION WILL BE IGNORED BY THE IMPLEMENTATION}}\nlogin stuff";
+        assert_eq!(branch_slug(requirements), "add-oauth-login-github-google");
+
+        assert_eq!(branch_slug("Fix   the   bug!"), "fix-the-bug");
+
+        let long = branch_slug("Implement a very long requirement title that keeps going and going");
+        assert!(long.len() <= MAX_BRANCH_SLUG_LEN);
+        assert!(!long.ends_with('-'));
+        assert!(long.starts_with("implement-a-very-long"));
+
+        assert!(branch_slug("").starts_with("plan-"));
+        assert!(branch_slug("### !!!").starts_with("plan-"));
+    }
+
     #[test]
     fn test_check_todos_complete() {
         assert!(check_todos_complete("- [x] Task 1\n- [x] Task 2"));
@@ -1059,14 +1942,126 @@ Implements the planning workflow including:
         let config = PlannerConfig {
             codepath: temp_dir.path().to_path_buf(),
             no_git: true,
+            dry_run: false,
             max_turns: 5,
             quiet: false,
             config_path: None,
+            plan_dir: None,
+            name: None,
         };
 
         initialize_plan_dir(&config).unwrap();
 
         assert!(config.plan_dir().exists());
         assert!(config.history_path().exists());
+        let gitignore = fs::read_to_string(config.plan_dir().join(".gitignore")).unwrap();
+        assert!(gitignore.lines().any(|line| line == persistence::STATE_FILE));
+    }
+
+    #[test]
+    fn test_saved_state_inconsistent_with_files_is_discarded() {
+        let temp_dir = TempDir::new().unwrap();
+        let config = PlannerConfig {
+            codepath: temp_dir.path().to_path_buf(),
+            no_git: true,
+            dry_run: false,
+            max_turns: 5,
+            quiet: false,
+            config_path: None,
+            plan_dir: None,
+            name: None,
+        };
+        initialize_plan_dir(&config).unwrap();
+
+        // Approval was pending, but new_requirements.md is gone: no prompt, nothing to resume
+        persistence::save(
+            &config.plan_dir(),
+            &PersistedState::new(SavedState::ApproveRequirements, 2),
+        )
+        .unwrap();
+        assert_eq!(check_saved_state(&config).unwrap(), None);
+        assert!(persistence::load(&config.plan_dir()).is_none());
+    }
+
+    #[test]
+    fn test_milestone_plan_progress() {
+        let temp_dir = TempDir::new().unwrap();
+        let config = PlannerConfig {
+            codepath: temp_dir.path().to_path_buf(),
+            no_git: true,
+            dry_run: false,
+            max_turns: 5,
+            quiet: false,
+            config_path: None,
+            plan_dir: None,
+            name: None,
+        };
+        initialize_plan_dir(&config).unwrap();
+        history::write_start_implementing(&config.plan_dir(), "Summary").unwrap();
+        assert_eq!(resume_milestone(&config).unwrap(), None);
+
+        let plan = vec![
+            Milestone {
+                title: "Parse config".to_string(),
+                acceptance_criteria: vec!["Config loads".to_string()],
+                tasks: vec!["Add struct".to_string()],
+            },
+            Milestone {
+                title: "Wire CLI".to_string(),
+                acceptance_criteria: vec!["Flag works".to_string()],
+                tasks: vec!["Add flag".to_string()],
+            },
+        ];
+        assert_eq!(plan_milestones(&config, &plan).unwrap(), PlannerState::ImplementMilestone(0));
+        assert!(fs::read_to_string(config.todo_path()).unwrap().contains("## Milestone 2: Wire CLI"));
+        assert_eq!(resume_milestone(&config).unwrap(), Some(0));
+
+        // no_git skips the commit, so completing needs no user input
+        assert_eq!(complete_milestone(&config, &plan, 0).unwrap(), PlannerState::ImplementMilestone(1));
+        assert_eq!(resume_milestone(&config).unwrap(), Some(1));
+        assert_eq!(complete_milestone(&config, &plan, 1).unwrap(), PlannerState::ImplementationComplete);
+        assert_eq!(resume_milestone(&config).unwrap(), None);
+
+        complete_implementation(&config).unwrap();
+        assert!(!config.plan_dir().join(milestones::MILESTONES_FILE).exists());
+    }
+
+    #[tokio::test]
+    async fn test_import_requirements_from_json() {
+        let temp_dir = TempDir::new().unwrap();
+        let config = PlannerConfig {
+            codepath: temp_dir.path().to_path_buf(),
+            no_git: true,
+            dry_run: false,
+            max_turns: 5,
+            quiet: false,
+            config_path: None,
+            plan_dir: None,
+            name: None,
+        };
+        initialize_plan_dir(&config).unwrap();
+
+        let issue_path = temp_dir.path().join("issue.json");
+        fs::write(
+            &issue_path,
+            r#"{"title": "Add dark mode", "body": "Theme toggle", "url": "https://tracker/PROJ-42"}"#,
+        )
+        .unwrap();
+        let source = ImportSource::Json(issue_path);
+
+        import_requirements(&config, &source).await.unwrap();
+        let content = fs::read_to_string(config.new_requirements_path()).unwrap();
+        assert!(content.starts_with("# Add dark mode\n\nImported from https://tracker/PROJ-42\n"));
+        assert_eq!(
+            history::imported_source(&config.plan_dir()).unwrap(),
+            Some("https://tracker/PROJ-42".to_string())
+        );
+        assert_eq!(
+            with_issue_reference("Adds a toggle\n", Some("https://tracker/PROJ-42")),
+            "Adds a toggle\n\nRefs: https://tracker/PROJ-42"
+        );
+
+        // Existing requirements are never overwritten
+        assert!(import_requirements(&config, &source).await.is_err());
     }
 }
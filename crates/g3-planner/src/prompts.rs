@@ -1,7 +1,10 @@
 //! Prompts used for planning mode and discovery phase
 //!
 //! This module contains all LLM prompts used in the planner crate.
-//! All prompts are defined as constants to ensure consistency and maintainability.
+//! The constants are the built-in texts of the [`TEMPLATES`]; callers go
+//! through [`registry`] so users can override them in `~/.g3/prompts/`.
+
+use g3_core::prompt_templates::{PromptRegistry, PromptTemplate};
 
 // =============================================================================
 // DISCOVERY PHASE PROMPTS (existing)
@@ -12,7 +15,7 @@ pub const DISCOVERY_SYSTEM_PROMPT: &str = r#"You are an expert code analyst. You
 
 You will receive:
 1. User requirements describing what needs to be implemented
-2. A codebase report showing the structure and key elements of the codebase
+2. A repository map: the directory tree, with the top-level symbols (functions, types, classes...) of each source file
 
 Your job is to:
 1. Understand the requirements and identify what parts of the codebase are relevant
@@ -20,14 +23,15 @@ Your job is to:
 
 IMPORTANT: Do NOT attempt to implement anything. Only generate exploration commands."#;
 
-/// Discovery prompt template - used when we have a codebase report.
-/// The codebase report should be appended after this prompt.
+/// Discovery prompt template - used when we have a repository map.
+/// The repository map should be appended after this prompt.
 pub const DISCOVERY_REQUIREMENTS_PROMPT: &str = r#"**CRITICAL**: DO ABSOLUTELY NOT ATTEMPT TO IMPLEMENT THESE REQUIREMENTS AT THIS POINT. ONLY USE THEM TO
 UNDERSTAND WHICH PARTS OF THE CODE YOU MIGHT BE INTERESTED IN, AND WHAT SEARCH/GREP EXPRESSIONS YOU MIGHT WANT TO USE
 TO GET A BETTER UNDERSTANDING OF THE CODEBASE.
 
-Your task is to analyze the codebase overview provided below and generate shell commands to explore it further - in particular, those
-you deem most relevant to the requirements given below.
+Your task is to analyze the repository map provided below and generate shell commands to explore it further - in particular, those
+you deem most relevant to the requirements given below. The map already lists every file and its top-level symbols, so don't spend
+commands on listing directories or finding where things are defined; read the specific code the requirements touch instead.
 
 Your output MUST include:
 1. A summary report.  Use the heading {{SUMMARY BASED ON INITIAL INFO}}.
@@ -38,7 +42,7 @@ Your output MUST include:
    - Carefully consider which commands give you the most relevant information, pick the top 25 commands.
    - Use tools like `ls`, `rg` (ripgrep), `grep`, `sed`, `cat`, `head`, `tail` etc.
    - Focus on commands that will help understand the code STRUCTURE without dumping large sections of file.
-   - e.g. for Rust you might try `rg --no-heading --line-number --with-filename --max-filesize 500K -g '*.rs' '^(pub )?(struct|enum|type|union)`
+   - e.g. `sed -n '/pub struct Agent/,/^}/p' src/lib.rs` to read a type the map lists, or `rg -n 'Agent::new' -g '*.rs'` to find its callers
    - Mark the beginning and end of the commands with "```".
 
 DO NOT ADD ANY COMMENTS OR OTHER EXPLANATION IN THE COMMANDS SECTION, JUST INCLUDE THE SHELL COMMANDS."#;
@@ -54,7 +58,7 @@ The requirements will later be used by an LLM.
 
 IMPORTANT: Before suggesting changes, you MUST:
 1. Read and understand the existing codebase at the specified codepath using read_file, shell commands, and code_search
-2. Read the `<codepath>/g3-plan/` directory to understand past requirements and implementation history
+2. Read the `{plan_dir}/` directory to understand past requirements and implementation history
    - Pay particular attention to `planner_history.txt` which contains a chronological record of all planning activities
    - Review any `completed_requirements_*.md` files to understand what has been implemented before
 3. Use this context to ensure your suggestions are consistent with the existing codebase architecture
@@ -65,7 +69,7 @@ DO NOT DO A RE-WRITE, UNLESS THE USER EXPLICITLY ASKS FOR THAT.
 If you think the requirements are totally incoherent and unusable, write constructive feedback on
 why that is, and suggest (very briefly) that you could rewrite it if explicitly asked to do so.
 If the requirements are usable, make some edits/changes/additions as you deem necessary, and
-PREPEND them under the heading `{{CURRENT REQUIREMENTS}}` to the `<codepath>/g3-plan/new_requirements.md` file.
+PREPEND them under the heading `{{CURRENT REQUIREMENTS}}` to the `{plan_dir}/new_requirements.md` file.
 
 The codepath will be provided in the user message."#;
 
@@ -79,6 +83,44 @@ Output ONLY the summary text, no headers or formatting.
 Requirements:
 {requirements}"#;
 
+/// Prompt for splitting approved requirements into ordered milestones
+pub const SPLIT_MILESTONES_PROMPT: &str = r#"Split the following requirements into a small number of ordered milestones.
+Each milestone must be independently implementable, testable and committable,
+and later milestones may build on earlier ones. Use between 2 and 8 milestones.
+Do not add work that is not in the requirements.
+
+Output ONLY the milestones, in exactly this markdown format:
+
+## Milestone 1: <short title>
+### Acceptance Criteria
+- <observable criterion>
+### Tasks
+- [ ] <task>
+
+Requirements:
+{requirements}"#;
+
+/// Prompt for the plan report written by `--dry-run`
+pub const PLAN_REPORT_PROMPT: &str = r#"Prepare a plan report for reviewing and estimating the following requirements.
+DO NOT IMPLEMENT ANYTHING. Base the report on the requirements and the codebase report below.
+
+Output ONLY these four sections, each starting with its marker on its own line:
+
+{{SUMMARY}}
+<at most 5 lines summarizing what will be built>
+{{IMPACTED FILES}}
+<one path per line: existing files that will change, and new files followed by "(new)">
+{{ESTIMATED SCOPE}}
+<size S, M, L or XL, a rough number of changed lines, and a one-line justification>
+{{EXPLORATION FINDINGS}}
+<relevant findings: existing code to reuse, risks, and open questions>
+
+Requirements:
+{requirements}
+
+Codebase report:
+{codebase_report}"#;
+
 /// System prompt for generating git commit message
 pub const GENERATE_COMMIT_MESSAGE_PROMPT: &str = r#"Generate a git commit message for the following implementation.
 
@@ -105,6 +147,57 @@ Output format:
 {{COMMIT_DESCRIPTION}}
 <description here>"#;
 
+/// The planner's prompts, by the name of their override file
+pub const TEMPLATES: &[PromptTemplate] = &[
+    PromptTemplate {
+        name: "discovery_system",
+        description: "System prompt for exploring the codebase before planning",
+        text: DISCOVERY_SYSTEM_PROMPT,
+        placeholders: &[],
+    },
+    PromptTemplate {
+        name: "discovery",
+        description: "Discovery instructions; requirements and repository map are appended",
+        text: DISCOVERY_REQUIREMENTS_PROMPT,
+        placeholders: &[],
+    },
+    PromptTemplate {
+        name: "refine_requirements",
+        description: "System prompt for the requirements refinement (coaching) phase",
+        text: REFINE_REQUIREMENTS_SYSTEM_PROMPT,
+        placeholders: &["plan_dir"],
+    },
+    PromptTemplate {
+        name: "requirements_summary",
+        description: "Summary of the requirements for planner_history.txt",
+        text: GENERATE_REQUIREMENTS_SUMMARY_PROMPT,
+        placeholders: &["requirements"],
+    },
+    PromptTemplate {
+        name: "split_milestones",
+        description: "Splitting approved requirements into milestones",
+        text: SPLIT_MILESTONES_PROMPT,
+        placeholders: &["requirements"],
+    },
+    PromptTemplate {
+        name: "plan_report",
+        description: "The --dry-run plan report",
+        text: PLAN_REPORT_PROMPT,
+        placeholders: &["requirements", "codebase_report"],
+    },
+    PromptTemplate {
+        name: "commit_message",
+        description: "Commit message for implemented requirements",
+        text: GENERATE_COMMIT_MESSAGE_PROMPT,
+        placeholders: &["requirements", "requirements_file", "todo_file"],
+    },
+];
+
+/// The planner's prompts, with user overrides from `~/.g3/prompts/`
+pub fn registry() -> PromptRegistry {
+    PromptRegistry::new(TEMPLATES)
+}
+
 // =============================================================================
 // CONFIG ERROR MESSAGES
 // =============================================================================
@@ -112,7 +205,8 @@ Output format:
 /// Error message for old config format
 pub const OLD_CONFIG_FORMAT_ERROR: &str = r#"Your configuration file uses an old format that is no longer supported.
 
-Please update your configuration to use the new provider format:
+Run `g3 config migrate` to convert it (the original is kept as a backup), or
+update it by hand to the new provider format:
 
 ```toml
 [providers]
@@ -140,3 +234,15 @@ model = "gpt-5"
 Each mode (planner, coach, player) can specify a full path like "<provider_type>.<config_name>".
 If not specified, they fall back to `default_provider`."#;
 
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use g3_core::prompt_templates::validate;
+
+    #[test]
+    fn test_builtin_prompts_have_their_placeholders() {
+        for template in TEMPLATES {
+            validate(template, template.text).unwrap();
+        }
+    }
+}
@@ -0,0 +1,173 @@
+//! Plan reports for planning dry runs
+//!
+//! With `--dry-run`, planning mode stops after refinement and writes
+//! `plan_report.md` to the plan directory instead of implementing, so the
+//! refined requirements can be reviewed and estimated first.
+
+use anyhow::{Context, Result};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// File name of the report inside the plan directory
+pub const REPORT_FILE: &str = "plan_report.md";
+
+/// Section markers the plan report prompt asks the LLM to emit
+const SUMMARY_MARKER: &str = "{{SUMMARY}}";
+const IMPACTED_FILES_MARKER: &str = "{{IMPACTED FILES}}";
+const ESTIMATED_SCOPE_MARKER: &str = "{{ESTIMATED SCOPE}}";
+const FINDINGS_MARKER: &str = "{{EXPLORATION FINDINGS}}";
+
+/// Analysis part of a plan report, as produced by the planner LLM
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PlanReport {
+    pub summary: String,
+    pub impacted_files: Vec<String>,
+    pub estimated_scope: String,
+    pub findings: String,
+}
+
+impl PlanReport {
+    /// Parse the LLM response; missing sections are left empty
+    pub fn parse(response: &str) -> Self {
+        let impacted_files = extract_section(response, IMPACTED_FILES_MARKER)
+            .lines()
+            .map(|line| {
+                line.trim()
+                    .trim_start_matches("- ")
+                    .trim_start_matches("* ")
+                    .trim_matches('`')
+                    .to_string()
+            })
+            .filter(|line| !line.is_empty())
+            .collect();
+
+        Self {
+            summary: extract_section(response, SUMMARY_MARKER),
+            impacted_files,
+            estimated_scope: extract_section(response, ESTIMATED_SCOPE_MARKER),
+            findings: extract_section(response, FINDINGS_MARKER),
+        }
+    }
+
+    /// Render the full report, including the refined requirements
+    pub fn render(&self, codepath: &Path, timestamp: &str, requirements: &str) -> String {
+        let impacted_files = if self.impacted_files.is_empty() {
+            or_unavailable("")
+        } else {
+            self.impacted_files
+                .iter()
+                .map(|file| format!("- `{}`", file))
+                .collect::<Vec<_>>()
+                .join("\n")
+        };
+
+        format!(
+            "# Plan Report\n\n\
+             - Generated: {timestamp}\n\
+             - Codepath: {codepath}\n\n\
+             ## Summary\n\n{summary}\n\n\
+             ## Impacted Files\n\n{impacted_files}\n\n\
+             ## Estimated Scope\n\n{scope}\n\n\
+             ## Exploration Findings\n\n{findings}\n\n\
+             ## Requirements\n\n{requirements}\n",
+            timestamp = timestamp,
+            codepath = codepath.display(),
+            summary = or_unavailable(&self.summary),
+            impacted_files = impacted_files,
+            scope = or_unavailable(&self.estimated_scope),
+            findings = or_unavailable(&self.findings),
+            requirements = requirements.trim(),
+        )
+    }
+}
+
+fn or_unavailable(text: &str) -> String {
+    if text.is_empty() {
+        "(not available)".to_string()
+    } else {
+        text.to_string()
+    }
+}
+
+/// Text between `marker` and the next `{{` marker (or the end), trimmed
+fn extract_section(response: &str, marker: &str) -> String {
+    let Some(start) = response.find(marker) else {
+        return String::new();
+    };
+    let content = &response[start + marker.len()..];
+    let end = content.find("{{").unwrap_or(content.len());
+    content[..end].trim().to_string()
+}
+
+/// Write the rendered report to the plan directory, returning its path
+pub fn write_report(plan_dir: &Path, content: &str) -> Result<PathBuf> {
+    let path = plan_dir.join(REPORT_FILE);
+    fs::write(&path, content).context("Failed to write plan_report.md")?;
+    Ok(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    const RESPONSE: &str = r#"{{SUMMARY}}
+Add a --dry-run flag to planning mode.
+
+{{IMPACTED FILES}}
+- `crates/g3-cli/src/lib.rs`
+* crates/g3-planner/src/report.rs (new)
+
+{{ESTIMATED SCOPE}}
+M - about 300 changed lines
+
+{{EXPLORATION FINDINGS}}
+The planner state machine lives in planner.rs.
+"#;
+
+    #[test]
+    fn test_parse_plan_report() {
+        let report = PlanReport::parse(RESPONSE);
+        assert_eq!(report.summary, "Add a --dry-run flag to planning mode.");
+        assert_eq!(
+            report.impacted_files,
+            vec![
+                "crates/g3-cli/src/lib.rs",
+                "crates/g3-planner/src/report.rs (new)"
+            ]
+        );
+        assert_eq!(report.estimated_scope, "M - about 300 changed lines");
+        assert_eq!(
+            report.findings,
+            "The planner state machine lives in planner.rs."
+        );
+    }
+
+    #[test]
+    fn test_parse_missing_sections() {
+        let report = PlanReport::parse("no markers here");
+        assert_eq!(report, PlanReport::default());
+    }
+
+    #[test]
+    fn test_render_and_write() {
+        let temp_dir = TempDir::new().unwrap();
+        let content = PlanReport::parse(RESPONSE).render(
+            Path::new("/work/project"),
+            "2025-01-01 12:00:00",
+            "{{CURRENT REQUIREMENTS}}\nAdd dry run\n",
+        );
+
+        assert!(content.starts_with("# Plan Report\n"));
+        assert!(content.contains("- Codepath: /work/project\n"));
+        assert!(content.contains("## Impacted Files\n\n- `crates/g3-cli/src/lib.rs`\n"));
+        assert!(content.ends_with("## Requirements\n\n{{CURRENT REQUIREMENTS}}\nAdd dry run\n"));
+
+        let empty = PlanReport::default().render(Path::new("/p"), "ts", "reqs");
+        assert!(empty.contains("## Impacted Files\n\n(not available)\n"));
+
+        let path = write_report(temp_dir.path(), &content).unwrap();
+        assert_eq!(path, temp_dir.path().join(REPORT_FILE));
+        assert_eq!(fs::read_to_string(path).unwrap(), content);
+    }
+}
@@ -13,10 +13,23 @@
 //! |                                                                                                         |
 //! +---------------------------------------------------------------------------------------------------------+
 //! ```
+//!
+//! When the user chooses to split large requirements, IMPLEMENT REQUIREMENTS
+//! detours through milestones before reaching IMPLEMENTATION COMPLETE:
+//!
+//! ```text
+//! IMPLEMENT REQUIREMENTS -> SPLIT MILESTONES -> IMPLEMENT MILESTONE 1 -> ... -> IMPLEMENT MILESTONE N -> IMPLEMENTATION COMPLETE
+//! ```
+//!
+//! REFINE REQUIREMENTS runs the LLM and then hands over to APPROVE REQUIREMENTS,
+//! which waits for the user. Every resumable state is saved to `<plan_dir>/.state`
+//! (see [`crate::persistence`]) so an interrupted planner restarts in the same state.
 
 use std::path::Path;
 use chrono::{DateTime, Local};
 
+use crate::git::GitProgress;
+
 /// The state of the planning mode
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum PlannerState {
@@ -28,8 +41,14 @@ pub enum PlannerState {
     PromptForRequirements,
     /// Refining requirements with LLM
     RefineRequirements,
+    /// Waiting for the user to approve the refined requirements
+    ApproveRequirements,
     /// Implementing requirements (coach/player loop)
     ImplementRequirements,
+    /// Splitting approved requirements into ordered milestones
+    SplitMilestones,
+    /// Implementing the milestone at this (0-based) index, committing when done
+    ImplementMilestone(usize),
     /// Implementation completed successfully
     ImplementationComplete,
     /// User quit the application
@@ -47,6 +66,9 @@ pub struct RecoveryInfo {
     pub has_todo: bool,
     /// Contents of todo.g3.md if it exists
     pub todo_contents: Option<String>,
+    /// Repository changes since the recorded GIT HEAD (None if git is disabled
+    /// or no HEAD was recorded)
+    pub progress: Option<GitProgress>,
 }
 
 impl RecoveryInfo {
@@ -80,8 +102,15 @@ impl RecoveryInfo {
             requirements_modified,
             has_todo,
             todo_contents,
+            progress: None,
         })
     }
+
+    /// Attach the work done since the GIT HEAD recorded in planner history
+    pub fn with_progress(mut self, progress: Option<GitProgress>) -> Self {
+        self.progress = progress;
+        self
+    }
 }
 
 /// Get the modified time of a file as a formatted string
@@ -116,6 +145,30 @@ impl RecoveryChoice {
     }
 }
 
+/// User's choice when a saved planner state is found on startup
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ResumeStateChoice {
+    /// Resume in the saved state
+    Resume,
+    /// Discard the saved state and start normally
+    Discard,
+    /// Quit the application
+    Quit,
+}
+
+impl ResumeStateChoice {
+    /// Parse user input into a resume choice
+    pub fn from_input(input: &str) -> Option<Self> {
+        let input = input.trim().to_lowercase();
+        match input.as_str() {
+            "y" | "yes" | "" => Some(ResumeStateChoice::Resume),
+            "n" | "no" => Some(ResumeStateChoice::Discard),
+            "q" | "quit" => Some(ResumeStateChoice::Quit),
+            _ => None,
+        }
+    }
+}
+
 /// User's choice when asked to approve requirements
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum ApprovalChoice {
@@ -123,6 +176,8 @@ pub enum ApprovalChoice {
     Approve,
     /// Continue refining
     Refine,
+    /// Edit the requirements in $EDITOR, then refine again
+    Edit,
     /// Quit the application
     Quit,
 }
@@ -134,6 +189,7 @@ impl ApprovalChoice {
         match input.as_str() {
             "y" | "yes" => Some(ApprovalChoice::Approve),
             "n" | "no" => Some(ApprovalChoice::Refine),
+            "e" | "edit" => Some(ApprovalChoice::Edit),
             "q" | "quit" => Some(ApprovalChoice::Quit),
             _ => None,
         }
@@ -185,6 +241,51 @@ impl BranchConfirmChoice {
     }
 }
 
+/// User's choice when offered a dedicated feature branch
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FeatureBranchChoice {
+    /// Create and switch to the proposed branch
+    Create,
+    /// Stay on the current branch
+    Skip,
+    /// Quit the application
+    Quit,
+}
+
+impl FeatureBranchChoice {
+    /// Parse user input into a feature branch choice
+    pub fn from_input(input: &str) -> Option<Self> {
+        let input = input.trim().to_lowercase();
+        match input.as_str() {
+            "y" | "yes" | "" => Some(FeatureBranchChoice::Create),
+            "n" | "no" => Some(FeatureBranchChoice::Skip),
+            "q" | "quit" => Some(FeatureBranchChoice::Quit),
+            _ => None,
+        }
+    }
+}
+
+/// User's choice when offered to split requirements into milestones
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MilestoneSplitChoice {
+    /// Split into milestones, committing after each one
+    Split,
+    /// Implement the requirements in a single pass
+    Single,
+}
+
+impl MilestoneSplitChoice {
+    /// Parse user input into a milestone split choice
+    pub fn from_input(input: &str) -> Option<Self> {
+        let input = input.trim().to_lowercase();
+        match input.as_str() {
+            "y" | "yes" => Some(MilestoneSplitChoice::Split),
+            "n" | "no" | "" => Some(MilestoneSplitChoice::Single),
+            _ => None,
+        }
+    }
+}
+
 /// User's choice when warned about dirty files
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum DirtyFilesChoice {
@@ -263,6 +364,8 @@ mod tests {
         assert_eq!(ApprovalChoice::from_input("yes"), Some(ApprovalChoice::Approve));
         assert_eq!(ApprovalChoice::from_input("no"), Some(ApprovalChoice::Refine));
         assert_eq!(ApprovalChoice::from_input("quit"), Some(ApprovalChoice::Quit));
+        assert_eq!(ApprovalChoice::from_input("e"), Some(ApprovalChoice::Edit));
+        assert_eq!(ApprovalChoice::from_input("EDIT"), Some(ApprovalChoice::Edit));
     }
 
     #[test]
@@ -280,6 +383,31 @@ mod tests {
         assert_eq!(BranchConfirmChoice::from_input("n"), Some(BranchConfirmChoice::Quit));
     }
 
+    #[test]
+    fn test_feature_branch_choice_parsing() {
+        assert_eq!(FeatureBranchChoice::from_input(""), Some(FeatureBranchChoice::Create)); // Default
+        assert_eq!(FeatureBranchChoice::from_input("Yes"), Some(FeatureBranchChoice::Create));
+        assert_eq!(FeatureBranchChoice::from_input("n"), Some(FeatureBranchChoice::Skip));
+        assert_eq!(FeatureBranchChoice::from_input("q"), Some(FeatureBranchChoice::Quit));
+        assert_eq!(FeatureBranchChoice::from_input("maybe"), None);
+    }
+
+    #[test]
+    fn test_resume_state_choice_parsing() {
+        assert_eq!(ResumeStateChoice::from_input(""), Some(ResumeStateChoice::Resume)); // Default
+        assert_eq!(ResumeStateChoice::from_input("n"), Some(ResumeStateChoice::Discard));
+        assert_eq!(ResumeStateChoice::from_input("QUIT"), Some(ResumeStateChoice::Quit));
+        assert_eq!(ResumeStateChoice::from_input("later"), None);
+    }
+
+    #[test]
+    fn test_milestone_split_choice_parsing() {
+        assert_eq!(MilestoneSplitChoice::from_input(""), Some(MilestoneSplitChoice::Single)); // Default
+        assert_eq!(MilestoneSplitChoice::from_input("y"), Some(MilestoneSplitChoice::Split));
+        assert_eq!(MilestoneSplitChoice::from_input("No"), Some(MilestoneSplitChoice::Single));
+        assert_eq!(MilestoneSplitChoice::from_input("maybe"), None);
+    }
+
     #[test]
     fn test_dirty_files_choice_parsing() {
         assert_eq!(DirtyFilesChoice::from_input("y"), Some(DirtyFilesChoice::Proceed));
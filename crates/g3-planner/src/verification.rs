@@ -0,0 +1,257 @@
+//! Verification gate for the coach/player loop
+//!
+//! Runs the commands configured in `[planner] verification_commands` inside
+//! the codepath. An implementation can only be marked complete once every
+//! command exits successfully; failing output is handed back to the coach.
+//! Each command leads its own process group, so a command that runs past
+//! `[planner] verification_timeout_secs` is killed along with everything it
+//! started.
+
+use std::io::Read;
+use std::path::Path;
+use std::process::{Child, Command, Stdio};
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+
+/// Maximum number of output lines kept per command (the tail is kept)
+pub const MAX_OUTPUT_LINES: usize = 100;
+
+/// Result of running a single verification command
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CommandOutcome {
+    pub command: String,
+    pub passed: bool,
+    /// Combined stdout and stderr, truncated to [`MAX_OUTPUT_LINES`]
+    pub output: String,
+}
+
+/// Results of running all verification commands
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct VerificationReport {
+    pub outcomes: Vec<CommandOutcome>,
+}
+
+impl VerificationReport {
+    /// Whether every command passed (true when no commands are configured)
+    pub fn passed(&self) -> bool {
+        self.outcomes.iter().all(|outcome| outcome.passed)
+    }
+
+    /// The commands that failed
+    pub fn failures(&self) -> impl Iterator<Item = &CommandOutcome> {
+        self.outcomes.iter().filter(|outcome| !outcome.passed)
+    }
+
+    /// One line per command, for display
+    pub fn summary(&self) -> String {
+        self.outcomes
+            .iter()
+            .map(|outcome| {
+                format!(
+                    "{} {}",
+                    if outcome.passed { "✅" } else { "❌" },
+                    outcome.command
+                )
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    /// Failing commands with their output, for the coach prompt
+    pub fn failure_report(&self) -> String {
+        self.failures()
+            .map(|outcome| format!("$ {}\n{}", outcome.command, outcome.output))
+            .collect::<Vec<_>>()
+            .join("\n\n")
+    }
+}
+
+/// Run each command through the shell in `codepath`, in order, killing any
+/// that runs longer than `timeout`
+pub fn run_commands(codepath: &Path, commands: &[String], timeout: Duration) -> VerificationReport {
+    VerificationReport {
+        outcomes: commands
+            .iter()
+            .map(|command| run_command(codepath, command, timeout))
+            .collect(),
+    }
+}
+
+fn run_command(codepath: &Path, command: &str, timeout: Duration) -> CommandOutcome {
+    #[cfg(windows)]
+    let mut cmd = Command::new("cmd");
+    #[cfg(windows)]
+    cmd.args(["/C", command]);
+    #[cfg(not(windows))]
+    let mut cmd = Command::new("sh");
+    #[cfg(not(windows))]
+    cmd.args(["-c", command]);
+    cmd.current_dir(codepath)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped());
+    #[cfg(unix)]
+    {
+        use std::os::unix::process::CommandExt;
+        cmd.process_group(0);
+    }
+
+    let failed = |e: std::io::Error| CommandOutcome {
+        command: command.to_string(),
+        passed: false,
+        output: format!("Failed to run command: {}", e),
+    };
+    let mut child = match cmd.spawn() {
+        Ok(child) => child,
+        Err(e) => return failed(e),
+    };
+    let stdout = read_to_end(child.stdout.take());
+    let stderr = read_to_end(child.stderr.take());
+
+    let deadline = Instant::now() + timeout;
+    let status = loop {
+        match child.try_wait() {
+            Ok(Some(status)) => break Some(status),
+            Ok(None) if Instant::now() >= deadline => break None,
+            Ok(None) => std::thread::sleep(Duration::from_millis(50)),
+            Err(e) => {
+                kill(&mut child);
+                return failed(e);
+            }
+        }
+    };
+    // Also reaps anything the command left running in the background, which
+    // would otherwise hold the output pipes open
+    kill(&mut child);
+
+    let mut combined = join_output(stdout);
+    combined.push_str(&join_output(stderr));
+    let mut output = tail(&combined, MAX_OUTPUT_LINES);
+    if status.is_none() {
+        if !output.is_empty() {
+            output.push('\n');
+        }
+        output.push_str(&format!("Timed out after {:?}", timeout));
+    }
+    CommandOutcome {
+        command: command.to_string(),
+        passed: status.is_some_and(|status| status.success()),
+        output,
+    }
+}
+
+/// Read `pipe` to the end on a separate thread
+fn read_to_end(pipe: Option<impl Read + Send + 'static>) -> Option<JoinHandle<Vec<u8>>> {
+    pipe.map(|mut pipe| {
+        std::thread::spawn(move || {
+            let mut buffer = Vec::new();
+            let _ = pipe.read_to_end(&mut buffer);
+            buffer
+        })
+    })
+}
+
+fn join_output(reader: Option<JoinHandle<Vec<u8>>>) -> String {
+    reader
+        .and_then(|reader| reader.join().ok())
+        .map(|buffer| String::from_utf8_lossy(&buffer).into_owned())
+        .unwrap_or_default()
+}
+
+/// Kill the process group `child` leads, then reap `child`
+fn kill(child: &mut Child) {
+    kill_process_group(child.id());
+    let _ = child.kill();
+    let _ = child.wait();
+}
+
+/// SIGKILL the process group led by `pid`
+pub(crate) fn kill_process_group(pid: u32) {
+    #[cfg(unix)]
+    if let Ok(pgid) = libc::pid_t::try_from(pid) {
+        // SAFETY: kill() has no memory-safety preconditions; a negative PID
+        // addresses the process group
+        unsafe {
+            libc::kill(-pgid, libc::SIGKILL);
+        }
+    }
+    #[cfg(not(unix))]
+    let _ = pid;
+}
+
+/// Keep the last `max_lines` lines, noting how many were dropped
+fn tail(output: &str, max_lines: usize) -> String {
+    let lines: Vec<&str> = output.trim_end().lines().collect();
+    if lines.len() <= max_lines {
+        return lines.join("\n");
+    }
+    let dropped = lines.len() - max_lines;
+    format!(
+        "... ({} lines truncated)\n{}",
+        dropped,
+        lines[dropped..].join("\n")
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_tail_truncates_from_the_start() {
+        assert_eq!(tail("a\nb\n", 5), "a\nb");
+        assert_eq!(tail("1\n2\n3\n4", 2), "... (2 lines truncated)\n3\n4");
+    }
+
+    const TIMEOUT: Duration = Duration::from_secs(30);
+
+    #[cfg(unix)]
+    #[test]
+    fn test_run_commands_reports_failures() {
+        let temp_dir = TempDir::new().unwrap();
+        std::fs::write(temp_dir.path().join("marker.txt"), "").unwrap();
+
+        let report = run_commands(
+            temp_dir.path(),
+            &[
+                "ls marker.txt".to_string(),
+                "echo broken >&2; exit 3".to_string(),
+            ],
+            TIMEOUT,
+        );
+
+        assert!(!report.passed());
+        assert!(report.outcomes[0].passed);
+        assert_eq!(report.outcomes[0].output, "marker.txt");
+        assert_eq!(report.failures().count(), 1);
+        assert_eq!(report.failure_report(), "$ echo broken >&2; exit 3\nbroken");
+        assert_eq!(
+            report.summary(),
+            "✅ ls marker.txt\n❌ echo broken >&2; exit 3"
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_timeout_kills_process_group() {
+        let temp_dir = TempDir::new().unwrap();
+        let started = Instant::now();
+
+        // The background sleep keeps the output pipe open until it is killed
+        let report = run_commands(
+            temp_dir.path(),
+            &["echo started; sleep 30 & sleep 30".to_string()],
+            Duration::from_millis(500),
+        );
+
+        assert!(started.elapsed() < Duration::from_secs(10));
+        assert!(!report.passed());
+        assert_eq!(report.outcomes[0].output, "started\nTimed out after 500ms");
+    }
+
+    #[test]
+    fn test_empty_report_passes() {
+        assert!(run_commands(Path::new("."), &[], TIMEOUT).passed());
+    }
+}
@@ -56,9 +56,12 @@ fn test_history_written_before_commit_on_empty_staging() {
     let config = PlannerConfig {
         codepath: repo_path.to_path_buf(),
         no_git: false,
+        dry_run: false,
         max_turns: 5,
         quiet: true,
         config_path: None,
+        plan_dir: None,
+        name: None,
     };
     
     // Write a history entry as would happen in stage_and_commit
@@ -0,0 +1,77 @@
+//! Tests for planner feature branch creation
+
+use anyhow::Result;
+use std::fs;
+use std::process::Command;
+use tempfile::TempDir;
+
+use g3_planner::git;
+
+/// Helper to create a test git repository with an initial commit
+fn setup_test_git_repo() -> Result<TempDir> {
+    let temp_dir = TempDir::new()?;
+    let repo_path = temp_dir.path();
+
+    for args in [
+        vec!["init"],
+        vec!["config", "user.name", "Test User"],
+        vec!["config", "user.email", "test@example.com"],
+    ] {
+        Command::new("git")
+            .args(&args)
+            .current_dir(repo_path)
+            .output()?;
+    }
+
+    fs::write(repo_path.join("README.md"), "test")?;
+    Command::new("git")
+        .args(["add", "."])
+        .current_dir(repo_path)
+        .output()?;
+    Command::new("git")
+        .args(["commit", "-m", "Initial commit"])
+        .current_dir(repo_path)
+        .output()?;
+
+    Ok(temp_dir)
+}
+
+#[test]
+fn test_create_branch_switches_to_new_branch() {
+    let temp_dir = setup_test_git_repo().expect("Failed to setup test repo");
+    let repo_path = temp_dir.path();
+
+    assert!(!git::branch_exists(repo_path, "g3/add-feature").unwrap());
+
+    git::create_branch(repo_path, "g3/add-feature").expect("Failed to create branch");
+
+    assert!(git::branch_exists(repo_path, "g3/add-feature").unwrap());
+    assert_eq!(
+        git::get_current_branch(repo_path).unwrap(),
+        "g3/add-feature"
+    );
+}
+
+#[test]
+fn test_create_branch_keeps_uncommitted_changes() {
+    let temp_dir = setup_test_git_repo().expect("Failed to setup test repo");
+    let repo_path = temp_dir.path();
+
+    fs::write(repo_path.join("wip.txt"), "work in progress").unwrap();
+    git::create_branch(repo_path, "g3/wip").expect("Failed to create branch");
+
+    assert!(repo_path.join("wip.txt").exists());
+    let dirty = git::check_dirty_files(repo_path, None).unwrap();
+    assert_eq!(dirty.untracked, vec!["wip.txt".to_string()]);
+}
+
+#[test]
+fn test_create_existing_branch_fails() {
+    let temp_dir = setup_test_git_repo().expect("Failed to setup test repo");
+    let repo_path = temp_dir.path();
+
+    git::create_branch(repo_path, "g3/dup").unwrap();
+    let result = git::create_branch(repo_path, "g3/dup");
+
+    assert!(result.is_err(), "Creating an existing branch should fail");
+}
@@ -0,0 +1,133 @@
+//! Tests for planner commits in repositories with git hooks
+
+#![cfg(unix)]
+
+use anyhow::Result;
+use std::fs;
+use std::os::unix::fs::PermissionsExt;
+use std::path::Path;
+use std::process::Command;
+use tempfile::TempDir;
+
+use g3_planner::git::{self, CommitError, CommitOptions};
+
+/// Helper to create a test git repository with an initial commit
+fn setup_test_git_repo() -> Result<TempDir> {
+    let temp_dir = TempDir::new()?;
+    let repo_path = temp_dir.path();
+
+    for args in [
+        vec!["init"],
+        vec!["config", "user.name", "Test User"],
+        vec!["config", "user.email", "test@example.com"],
+    ] {
+        Command::new("git")
+            .args(&args)
+            .current_dir(repo_path)
+            .output()?;
+    }
+
+    fs::write(repo_path.join("README.md"), "# Test\n")?;
+    git_add(repo_path, "README.md");
+    Command::new("git")
+        .args(["commit", "-m", "Initial commit"])
+        .current_dir(repo_path)
+        .output()?;
+
+    Ok(temp_dir)
+}
+
+fn install_pre_commit_hook(repo_path: &Path, script: &str) {
+    let hook = repo_path.join(".git/hooks/pre-commit");
+    fs::write(&hook, format!("#!/bin/sh\n{}\n", script)).unwrap();
+    fs::set_permissions(&hook, fs::Permissions::from_mode(0o755)).unwrap();
+}
+
+fn git_add(repo_path: &Path, file: &str) {
+    Command::new("git")
+        .args(["add", file])
+        .current_dir(repo_path)
+        .output()
+        .unwrap();
+}
+
+fn head_subject(repo_path: &Path) -> String {
+    let output = Command::new("git")
+        .args(["log", "-1", "--format=%s"])
+        .current_dir(repo_path)
+        .output()
+        .unwrap();
+    String::from_utf8_lossy(&output.stdout).trim().to_string()
+}
+
+#[test]
+fn test_hook_modified_files_are_restaged_and_committed() {
+    let temp_dir = setup_test_git_repo().expect("Failed to setup test repo");
+    let repo_path = temp_dir.path();
+
+    // A formatter-style hook: rewrites the file and fails, like pre-commit does
+    install_pre_commit_hook(
+        repo_path,
+        "if grep -q messy main.rs; then sed 's/messy/tidy/' main.rs > main.rs.new && mv main.rs.new main.rs; exit 1; fi",
+    );
+    fs::write(repo_path.join("main.rs"), "// messy\n").unwrap();
+    git_add(repo_path, "main.rs");
+
+    let result = git::commit_with_options(repo_path, "Add main", "", CommitOptions::default())
+        .expect("Commit should succeed after re-staging");
+
+    assert_eq!(result.restaged, vec!["main.rs"]);
+    assert_eq!(head_subject(repo_path), "Add main");
+    let committed = Command::new("git")
+        .args(["show", "HEAD:main.rs"])
+        .current_dir(repo_path)
+        .output()
+        .unwrap();
+    assert_eq!(String::from_utf8_lossy(&committed.stdout), "// tidy\n");
+}
+
+#[test]
+fn test_hook_rejection_is_reported_with_output() {
+    let temp_dir = setup_test_git_repo().expect("Failed to setup test repo");
+    let repo_path = temp_dir.path();
+
+    install_pre_commit_hook(repo_path, "echo 'lint: missing docs' >&2; exit 1");
+    fs::write(repo_path.join("lib.rs"), "pub fn f() {}\n").unwrap();
+    git_add(repo_path, "lib.rs");
+
+    let error = git::commit_with_options(repo_path, "Add lib", "", CommitOptions::default())
+        .expect_err("Hook should reject the commit");
+    match error.downcast_ref::<CommitError>() {
+        Some(CommitError::HookRejected {
+            hooks,
+            modified_files,
+            output,
+        }) => {
+            assert_eq!(hooks, &vec!["pre-commit".to_string()]);
+            assert!(modified_files.is_empty());
+            assert!(output.contains("lint: missing docs"));
+        }
+        other => panic!("Expected HookRejected, got {:?}", other),
+    }
+    assert_eq!(head_subject(repo_path), "Initial commit");
+
+    // no_verify skips the hook
+    let result =
+        git::commit_with_options(repo_path, "Add lib", "", CommitOptions { no_verify: true })
+            .expect("Commit with no_verify should succeed");
+    assert!(result.restaged.is_empty());
+    assert_eq!(head_subject(repo_path), "Add lib");
+}
+
+#[test]
+fn test_commit_failure_without_hooks() {
+    let temp_dir = setup_test_git_repo().expect("Failed to setup test repo");
+
+    // Nothing staged
+    let error = git::commit_with_options(temp_dir.path(), "Empty", "", CommitOptions::default())
+        .expect_err("Commit with nothing staged should fail");
+    assert!(matches!(
+        error.downcast_ref::<CommitError>(),
+        Some(CommitError::Failed { .. })
+    ));
+}
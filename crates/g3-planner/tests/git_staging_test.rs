@@ -0,0 +1,107 @@
+//! Tests for planner staging exclusion rules
+
+use anyhow::Result;
+use std::fs;
+use std::process::Command;
+use tempfile::TempDir;
+
+use g3_planner::git::{self, ExclusionRule};
+
+/// Helper to create a test git repository with a .gitignore
+fn setup_test_git_repo() -> Result<TempDir> {
+    let temp_dir = TempDir::new()?;
+    let repo_path = temp_dir.path();
+
+    for args in [
+        vec!["init"],
+        vec!["config", "user.name", "Test User"],
+        vec!["config", "user.email", "test@example.com"],
+    ] {
+        Command::new("git")
+            .args(&args)
+            .current_dir(repo_path)
+            .output()?;
+    }
+
+    fs::write(
+        repo_path.join(".gitignore"),
+        "# build output\ndist/\n*.cache\n",
+    )?;
+    fs::create_dir_all(repo_path.join("g3-plan"))?;
+    fs::write(repo_path.join("g3-plan").join("planner_history.txt"), "")?;
+
+    Ok(temp_dir)
+}
+
+fn rule_for<'a>(result: &'a git::StagingResult, path: &str) -> Option<&'a ExclusionRule> {
+    result
+        .excluded
+        .iter()
+        .find(|excluded| excluded.path == path)
+        .map(|excluded| &excluded.rule)
+}
+
+#[test]
+fn test_stage_files_reports_exclusion_rules() {
+    let temp_dir = setup_test_git_repo().expect("Failed to setup test repo");
+    let repo_path = temp_dir.path();
+    let plan_dir = repo_path.join("g3-plan");
+
+    fs::write(repo_path.join("main.rs"), "fn main() {}").unwrap();
+    fs::write(repo_path.join("api.generated.rs"), "// generated").unwrap();
+    fs::write(repo_path.join("debug.log"), "log").unwrap();
+
+    // A tracked file that now matches an ignore pattern
+    fs::write(repo_path.join("index.cache"), "v1").unwrap();
+    Command::new("git")
+        .args(["add", "-f", "index.cache", ".gitignore"])
+        .current_dir(repo_path)
+        .output()
+        .unwrap();
+    Command::new("git")
+        .args(["commit", "-m", "Initial commit"])
+        .current_dir(repo_path)
+        .output()
+        .unwrap();
+    fs::write(repo_path.join("index.cache"), "v2").unwrap();
+
+    let extra_excludes = vec!["*.generated.rs".to_string()];
+    let result = git::stage_files_with_excludes(repo_path, &plan_dir, &extra_excludes)
+        .expect("Failed to stage files");
+
+    assert!(result.staged.contains(&"main.rs".to_string()));
+    assert_eq!(
+        rule_for(&result, "index.cache"),
+        Some(&ExclusionRule::GitIgnore {
+            source: ".gitignore".to_string(),
+            line: 3,
+            pattern: "*.cache".to_string(),
+        })
+    );
+    assert_eq!(
+        rule_for(&result, "api.generated.rs"),
+        Some(&ExclusionRule::Config("*.generated.rs".to_string()))
+    );
+    assert_eq!(
+        rule_for(&result, "debug.log"),
+        Some(&ExclusionRule::Builtin("*.log"))
+    );
+}
+
+#[test]
+fn test_stage_files_without_excludes_keeps_builtin_rules() {
+    let temp_dir = setup_test_git_repo().expect("Failed to setup test repo");
+    let repo_path = temp_dir.path();
+    let plan_dir = repo_path.join("g3-plan");
+
+    fs::write(repo_path.join("notes.tmp"), "scratch").unwrap();
+    fs::write(repo_path.join("lib.rs"), "").unwrap();
+
+    let result = git::stage_files(repo_path, &plan_dir).expect("Failed to stage files");
+
+    assert!(result.staged.contains(&"lib.rs".to_string()));
+    assert_eq!(
+        rule_for(&result, "notes.tmp"),
+        Some(&ExclusionRule::Builtin("*.tmp"))
+    );
+}
@@ -0,0 +1,70 @@
+//! Tests for diff-aware recovery: comparing the recorded GIT HEAD with the repository
+
+use anyhow::Result;
+use std::fs;
+use std::process::Command;
+use tempfile::TempDir;
+
+use g3_planner::git;
+
+fn run_git(repo_path: &std::path::Path, args: &[&str]) -> Result<()> {
+    Command::new("git")
+        .args(args)
+        .current_dir(repo_path)
+        .output()?;
+    Ok(())
+}
+
+/// Helper to create a test git repository with an initial commit
+fn setup_test_git_repo() -> Result<TempDir> {
+    let temp_dir = TempDir::new()?;
+    let repo_path = temp_dir.path();
+
+    run_git(repo_path, &["init"])?;
+    run_git(repo_path, &["config", "user.name", "Test User"])?;
+    run_git(repo_path, &["config", "user.email", "test@example.com"])?;
+
+    fs::write(repo_path.join("lib.rs"), "fn a() {}\n")?;
+    run_git(repo_path, &["add", "."])?;
+    run_git(repo_path, &["commit", "-m", "Initial commit"])?;
+
+    Ok(temp_dir)
+}
+
+#[test]
+fn test_progress_since_reports_commits_and_changes() {
+    let temp_dir = setup_test_git_repo().expect("Failed to setup test repo");
+    let repo_path = temp_dir.path();
+    let recorded = git::get_head_sha(repo_path).unwrap();
+
+    let progress = git::progress_since(repo_path, &recorded).unwrap();
+    assert!(progress.recorded_head_found);
+    assert!(!progress.has_changes());
+
+    // One commit, one uncommitted change and one untracked file
+    fs::write(repo_path.join("parser.rs"), "fn parse() {}\n").unwrap();
+    run_git(repo_path, &["add", "parser.rs"]).unwrap();
+    run_git(repo_path, &["commit", "-m", "Add parser"]).unwrap();
+    fs::write(repo_path.join("lib.rs"), "fn a() {}\nfn b() {}\n").unwrap();
+    fs::write(repo_path.join("notes.md"), "wip").unwrap();
+
+    let progress = git::progress_since(repo_path, &recorded).unwrap();
+    assert_eq!(progress.recorded_head, recorded);
+    assert_eq!(progress.current_head, git::get_head_sha(repo_path).unwrap());
+    assert_eq!(progress.commits.len(), 1);
+    assert!(progress.commits[0].ends_with("Add parser"));
+    assert!(progress.diff_stat.contains("parser.rs"));
+    assert!(progress.diff_stat.contains("lib.rs"));
+    assert_eq!(progress.untracked, vec!["notes.md".to_string()]);
+}
+
+#[test]
+fn test_progress_since_unknown_commit() {
+    let temp_dir = setup_test_git_repo().expect("Failed to setup test repo");
+    let repo_path = temp_dir.path();
+
+    let progress =
+        git::progress_since(repo_path, "0000000000000000000000000000000000000000").unwrap();
+    assert!(!progress.recorded_head_found);
+    assert!(progress.commits.is_empty());
+}
@@ -104,14 +104,16 @@ use bytes::Bytes;
 use futures_util::stream::StreamExt;
 use reqwest::{Client, RequestBuilder};
 use serde::{Deserialize, Serialize};
+use std::sync::Arc;
 use std::time::Duration;
 use tokio::sync::mpsc;
 use tokio_stream::wrappers::ReceiverStream;
 use tracing::{debug, error};
 
+use crate::tokenizer::{self, TokenCounter};
 use crate::{
-    CompletionChunk, CompletionRequest, CompletionResponse, CompletionStream, LLMProvider, Message,
-    MessageRole, Tool, ToolCall, Usage,
+    CompletionChunk, CompletionRequest, CompletionResponse, CompletionStream, G3Error, LLMProvider,
+    Message, MessageRole, Tool, ToolCall, Usage,
 };
 
 const ANTHROPIC_API_URL: &str = "https://api.anthropic.com/v1/messages";
@@ -727,7 +729,7 @@ impl LLMProvider for AnthropicProvider {
             .json(&request_body)
             .send()
             .await
-            .map_err(|e| anyhow!("Failed to send request to Anthropic API: {}", e))?;
+            .map_err(|e| G3Error::transport("Failed to send request to Anthropic API", &e))?;
 
         let status = response.status();
         if !status.is_success() {
@@ -735,7 +737,7 @@ impl LLMProvider for AnthropicProvider {
                 .text()
                 .await
                 .unwrap_or_else(|_| "Unknown error".to_string());
-            return Err(anyhow!("Anthropic API error {}: {}", status, error_text));
+            return Err(G3Error::api("Anthropic", status, error_text).into());
         }
 
         let anthropic_response: AnthropicResponse = response
@@ -808,7 +810,9 @@ impl LLMProvider for AnthropicProvider {
             .json(&request_body)
             .send()
             .await
-            .map_err(|e| anyhow!("Failed to send streaming request to Anthropic API: {}", e))?;
+            .map_err(|e| {
+                G3Error::transport("Failed to send streaming request to Anthropic API", &e)
+            })?;
 
         let status = response.status();
         if !status.is_success() {
@@ -816,7 +820,7 @@ impl LLMProvider for AnthropicProvider {
                 .text()
                 .await
                 .unwrap_or_else(|_| "Unknown error".to_string());
-            return Err(anyhow!("Anthropic API error {}: {}", status, error_text));
+            return Err(G3Error::api("Anthropic", status, error_text).into());
         }
 
         let stream = response.bytes_stream();
@@ -856,6 +860,10 @@ impl LLMProvider for AnthropicProvider {
         true
     }
 
+    fn supports_vision(&self) -> bool {
+        true
+    }
+
     fn max_tokens(&self) -> u32 {
         self.max_tokens
     }
@@ -863,6 +871,10 @@ impl LLMProvider for AnthropicProvider {
     fn temperature(&self) -> f32 {
         self.temperature
     }
+
+    fn token_counter(&self) -> Option<Arc<dyn TokenCounter>> {
+        tokenizer::approximate_for_model(&self.model)
+    }
 }
 
 // Anthropic API request/response structures
@@ -0,0 +1,172 @@
+//! Decoder for the AWS event stream encoding (`application/vnd.amazon.eventstream`)
+//! used by Bedrock's streaming responses
+//!
+//! Each message is a 12-byte prelude (total length, headers length, prelude
+//! CRC), the headers, the payload and a trailing message CRC, all big-endian.
+//! TLS already protects the bytes, so the CRCs are not checked.
+
+use anyhow::{anyhow, Result};
+
+const PRELUDE_LEN: usize = 12;
+const CRC_LEN: usize = 4;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EventMessage {
+    pub headers: Vec<(String, String)>,
+    pub payload: Vec<u8>,
+}
+
+impl EventMessage {
+    /// Value of a string header such as `:event-type`
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value.as_str())
+    }
+}
+
+/// Collects bytes from the response body and yields complete messages
+#[derive(Debug, Default)]
+pub struct EventStreamDecoder {
+    buffer: Vec<u8>,
+}
+
+impl EventStreamDecoder {
+    pub fn push(&mut self, bytes: &[u8]) {
+        self.buffer.extend_from_slice(bytes);
+    }
+
+    /// The next complete message, or `None` until more bytes arrive
+    pub fn next_message(&mut self) -> Result<Option<EventMessage>> {
+        if self.buffer.len() < PRELUDE_LEN {
+            return Ok(None);
+        }
+        let total_len = read_u32(&self.buffer[0..4]) as usize;
+        let headers_len = read_u32(&self.buffer[4..8]) as usize;
+        if total_len < PRELUDE_LEN + headers_len + CRC_LEN {
+            return Err(anyhow!("Malformed event stream message"));
+        }
+        if self.buffer.len() < total_len {
+            return Ok(None);
+        }
+
+        let message: Vec<u8> = self.buffer.drain(..total_len).collect();
+        let headers_end = PRELUDE_LEN + headers_len;
+        Ok(Some(EventMessage {
+            headers: parse_headers(&message[PRELUDE_LEN..headers_end])?,
+            payload: message[headers_end..total_len - CRC_LEN].to_vec(),
+        }))
+    }
+}
+
+/// Parse the header block, keeping string headers and skipping the others
+fn parse_headers(mut bytes: &[u8]) -> Result<Vec<(String, String)>> {
+    fn take<'a>(bytes: &mut &'a [u8], len: usize) -> Result<&'a [u8]> {
+        if bytes.len() < len {
+            return Err(anyhow!("Truncated event stream header"));
+        }
+        let (head, rest) = bytes.split_at(len);
+        *bytes = rest;
+        Ok(head)
+    }
+
+    let mut headers = Vec::new();
+    while !bytes.is_empty() {
+        let name_len = take(&mut bytes, 1)?[0] as usize;
+        let name = String::from_utf8_lossy(take(&mut bytes, name_len)?).into_owned();
+        let value_type = take(&mut bytes, 1)?[0];
+        let value_len = match value_type {
+            // bool true, bool false
+            0 | 1 => 0,
+            // byte, short, int, long, timestamp
+            2 => 1,
+            3 => 2,
+            4 => 4,
+            5 | 8 => 8,
+            // byte array, string: u16 length prefix
+            6 | 7 => {
+                let len = take(&mut bytes, 2)?;
+                u16::from_be_bytes([len[0], len[1]]) as usize
+            }
+            // uuid
+            9 => 16,
+            other => return Err(anyhow!("Unknown event stream header type {}", other)),
+        };
+        let value = take(&mut bytes, value_len)?;
+        if value_type == 7 {
+            headers.push((name, String::from_utf8_lossy(value).into_owned()));
+        }
+    }
+    Ok(headers)
+}
+
+fn read_u32(bytes: &[u8]) -> u32 {
+    u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]])
+}
+
+/// Encode a message with string headers, as Bedrock sends them
+#[cfg(test)]
+pub fn encode(headers: &[(&str, &str)], payload: &[u8]) -> Vec<u8> {
+    let mut header_bytes = Vec::new();
+    for (name, value) in headers {
+        header_bytes.push(name.len() as u8);
+        header_bytes.extend_from_slice(name.as_bytes());
+        header_bytes.push(7);
+        header_bytes.extend_from_slice(&(value.len() as u16).to_be_bytes());
+        header_bytes.extend_from_slice(value.as_bytes());
+    }
+
+    let total_len = PRELUDE_LEN + header_bytes.len() + payload.len() + CRC_LEN;
+    let mut message = Vec::with_capacity(total_len);
+    message.extend_from_slice(&(total_len as u32).to_be_bytes());
+    message.extend_from_slice(&(header_bytes.len() as u32).to_be_bytes());
+    message.extend_from_slice(&[0; 4]);
+    message.extend_from_slice(&header_bytes);
+    message.extend_from_slice(payload);
+    message.extend_from_slice(&[0; 4]);
+    message
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_decode_split_messages() {
+        let mut bytes = encode(
+            &[
+                (":event-type", "contentBlockDelta"),
+                (":message-type", "event"),
+            ],
+            br#"{"delta":{"text":"Hi"}}"#,
+        );
+        bytes.extend(encode(&[(":event-type", "messageStop")], b"{}"));
+
+        let mut decoder = EventStreamDecoder::default();
+        decoder.push(&bytes[..20]);
+        assert_eq!(decoder.next_message().unwrap(), None);
+        decoder.push(&bytes[20..]);
+
+        let first = decoder.next_message().unwrap().unwrap();
+        assert_eq!(first.header(":event-type"), Some("contentBlockDelta"));
+        assert_eq!(first.header(":message-type"), Some("event"));
+        assert_eq!(first.payload, br#"{"delta":{"text":"Hi"}}"#);
+
+        let second = decoder.next_message().unwrap().unwrap();
+        assert_eq!(second.header(":event-type"), Some("messageStop"));
+        assert_eq!(decoder.next_message().unwrap(), None);
+    }
+
+    #[test]
+    fn test_skips_non_string_headers() {
+        // A bool header followed by a string header
+        let mut headers = vec![4, b'f', b'l', b'a', b'g', 0];
+        headers.extend_from_slice(&[2, b':', b'x', 7, 0, 1, b'y']);
+        assert_eq!(
+            parse_headers(&headers).unwrap(),
+            vec![(":x".to_string(), "y".to_string())]
+        );
+        assert!(parse_headers(&[3, b'a']).is_err());
+    }
+}
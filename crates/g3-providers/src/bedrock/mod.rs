@@ -0,0 +1,655 @@
+//! AWS Bedrock provider
+//!
+//! Uses Bedrock's Converse API, which takes the same request shape for every
+//! hosted model family (Anthropic Claude, Amazon Nova, Meta Llama, Mistral),
+//! so Claude-on-Bedrock and the other models share one code path. Requests are
+//! signed with SigV4 ([`sigv4`]) and streamed responses arrive in the AWS
+//! event stream encoding ([`event_stream`]); their events are translated into
+//! the same [`CompletionChunk`]s the SSE-based providers produce.
+
+pub mod event_stream;
+pub mod sigv4;
+
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use bytes::Bytes;
+use futures_util::stream::StreamExt;
+use reqwest::Client;
+use serde_json::{json, Value};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::mpsc;
+use tokio_stream::wrappers::ReceiverStream;
+use tracing::{debug, error};
+
+use crate::tokenizer::{self, TokenCounter};
+use crate::{
+    CompletionChunk, CompletionRequest, CompletionResponse, CompletionStream, G3Error, LLMProvider,
+    Message, MessageRole, Tool, ToolCall, Usage,
+};
+use event_stream::{EventMessage, EventStreamDecoder};
+pub use sigv4::AwsCredentials;
+
+const DEFAULT_REGION: &str = "us-east-1";
+const DEFAULT_MAX_TOKENS: u32 = 4096;
+
+/// Context windows by model family, most specific first
+const CONTEXT_WINDOWS: &[(&str, u32)] = &[
+    ("anthropic.claude", 200_000),
+    ("amazon.nova-micro", 128_000),
+    ("amazon.nova", 300_000),
+    ("meta.llama3-1", 128_000),
+    ("meta.llama3-2", 128_000),
+    ("meta.llama3-3", 128_000),
+    ("meta.llama4", 128_000),
+    ("mistral.mistral-large", 128_000),
+];
+
+/// Context window of a Bedrock model, if known; `model` may carry a
+/// cross-region inference profile prefix such as `us.`
+pub fn context_window(model: &str) -> Option<u32> {
+    CONTEXT_WINDOWS
+        .iter()
+        .find(|(family, _)| model.contains(family))
+        .map(|(_, tokens)| *tokens)
+}
+
+/// The region from the config, else `AWS_REGION` / `AWS_DEFAULT_REGION`
+pub fn resolve_region(configured: Option<String>) -> String {
+    configured
+        .or_else(|| std::env::var("AWS_REGION").ok())
+        .or_else(|| std::env::var("AWS_DEFAULT_REGION").ok())
+        .filter(|region| !region.is_empty())
+        .unwrap_or_else(|| DEFAULT_REGION.to_string())
+}
+
+#[derive(Clone)]
+pub struct BedrockProvider {
+    client: Client,
+    name: String,
+    model: String,
+    region: String,
+    credentials: AwsCredentials,
+    max_tokens: u32,
+    temperature: f32,
+    native_tools: bool,
+}
+
+impl BedrockProvider {
+    pub fn new_with_name(
+        name: String,
+        model: String,
+        region: String,
+        credentials: AwsCredentials,
+        max_tokens: Option<u32>,
+        temperature: Option<f32>,
+        native_tools: bool,
+    ) -> Result<Self> {
+        let client = Client::builder()
+            .timeout(Duration::from_secs(300))
+            .build()
+            .map_err(|e| anyhow!("Failed to create HTTP client: {}", e))?;
+
+        Ok(Self {
+            client,
+            name,
+            model,
+            region,
+            credentials,
+            max_tokens: max_tokens.unwrap_or(DEFAULT_MAX_TOKENS),
+            temperature: temperature.unwrap_or(0.1),
+            native_tools,
+        })
+    }
+
+    fn host(&self) -> String {
+        format!("bedrock-runtime.{}.amazonaws.com", self.region)
+    }
+
+    fn path(&self, streaming: bool) -> String {
+        let action = if streaming {
+            "converse-stream"
+        } else {
+            "converse"
+        };
+        format!("/model/{}/{}", sigv4::uri_encode(&self.model), action)
+    }
+
+    fn create_request_body(
+        &self,
+        messages: &[Message],
+        tools: Option<&[Tool]>,
+        max_tokens: u32,
+        temperature: f32,
+    ) -> Result<Value> {
+        let (system, messages) = convert_messages(messages);
+        if messages.is_empty() {
+            return Err(anyhow!("At least one user message is required for Bedrock"));
+        }
+
+        let mut body = json!({
+            "messages": messages,
+            "inferenceConfig": {
+                "maxTokens": max_tokens,
+                "temperature": temperature,
+            },
+        });
+        if !system.is_empty() {
+            body["system"] = json!(system);
+        }
+        if let Some(tools) = tools.filter(|tools| self.native_tools && !tools.is_empty()) {
+            body["toolConfig"] = json!({ "tools": convert_tools(tools) });
+        }
+        Ok(body)
+    }
+
+    async fn send(&self, body: &Value, streaming: bool) -> Result<reqwest::Response> {
+        let host = self.host();
+        let path = self.path(streaming);
+        let body = serde_json::to_vec(body)?;
+        let headers = sigv4::sign_json_post(
+            &self.credentials,
+            &self.region,
+            "bedrock",
+            &host,
+            &path,
+            &body,
+            chrono::Utc::now(),
+        );
+
+        // The body is sent as signed, byte for byte
+        let mut request = self.client.post(format!("https://{}{}", host, path));
+        for (name, value) in headers {
+            request = request.header(name, value);
+        }
+        let response = request
+            .body(body)
+            .send()
+            .await
+            .map_err(|e| G3Error::transport("Failed to send request to Bedrock", &e))?;
+
+        let status = response.status();
+        if !status.is_success() {
+            let error_text = response
+                .text()
+                .await
+                .unwrap_or_else(|_| "Unknown error".to_string());
+            return Err(G3Error::api("Bedrock", status, error_text).into());
+        }
+        Ok(response)
+    }
+
+    async fn parse_streaming_response(
+        &self,
+        mut stream: impl futures_util::Stream<Item = reqwest::Result<Bytes>> + Unpin,
+        tx: mpsc::Sender<Result<CompletionChunk>>,
+    ) -> Option<Usage> {
+        let mut decoder = EventStreamDecoder::default();
+        let mut translator = StreamTranslator::default();
+
+        while let Some(chunk_result) = stream.next().await {
+            let chunk = match chunk_result {
+                Ok(chunk) => chunk,
+                Err(e) => {
+                    error!("Stream error: {}", e);
+                    let _ = tx.send(Err(anyhow!("Stream error: {}", e))).await;
+                    return translator.usage;
+                }
+            };
+            decoder.push(&chunk);
+
+            loop {
+                let message = match decoder.next_message() {
+                    Ok(Some(message)) => message,
+                    Ok(None) => break,
+                    Err(e) => {
+                        error!("Bedrock stream error: {}", e);
+                        let _ = tx.send(Err(e)).await;
+                        return translator.usage;
+                    }
+                };
+                match translator.translate(&message) {
+                    Ok(Some(chunk)) => {
+                        if tx.send(Ok(chunk)).await.is_err() {
+                            debug!("Receiver dropped, stopping stream");
+                            return translator.usage;
+                        }
+                    }
+                    Ok(None) => {}
+                    Err(e) => {
+                        error!("Bedrock stream error: {}", e);
+                        let _ = tx.send(Err(e)).await;
+                        return translator.usage;
+                    }
+                }
+            }
+        }
+
+        // The usage arrives in the metadata event after messageStop
+        let final_chunk = CompletionChunk {
+            content: String::new(),
+            finished: true,
+            usage: translator.usage.clone(),
+            tool_calls: None,
+        };
+        let _ = tx.send(Ok(final_chunk)).await;
+        translator.usage
+    }
+}
+
+#[async_trait]
+impl LLMProvider for BedrockProvider {
+    async fn complete(&self, request: CompletionRequest) -> Result<CompletionResponse> {
+        debug!(
+            "Processing Bedrock completion request with {} messages",
+            request.messages.len()
+        );
+
+        let body = self.create_request_body(
+            &request.messages,
+            request.tools.as_deref(),
+            request.max_tokens.unwrap_or(self.max_tokens),
+            request.temperature.unwrap_or(self.temperature),
+        )?;
+
+        let response: Value = self
+            .send(&body, false)
+            .await?
+            .json()
+            .await
+            .map_err(|e| anyhow!("Failed to parse Bedrock response: {}", e))?;
+
+        let content = response["output"]["message"]["content"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .filter_map(|block| block["text"].as_str())
+            .collect::<Vec<_>>()
+            .join("");
+        let usage = parse_usage(&response["usage"]).unwrap_or(Usage {
+            prompt_tokens: 0,
+            completion_tokens: 0,
+            total_tokens: 0,
+        });
+
+        debug!(
+            "Bedrock completion successful: {} tokens generated",
+            usage.completion_tokens
+        );
+
+        Ok(CompletionResponse {
+            content,
+            usage,
+            model: self.model.clone(),
+        })
+    }
+
+    async fn stream(&self, request: CompletionRequest) -> Result<CompletionStream> {
+        debug!(
+            "Processing Bedrock streaming request with {} messages",
+            request.messages.len()
+        );
+
+        let body = self.create_request_body(
+            &request.messages,
+            request.tools.as_deref(),
+            request.max_tokens.unwrap_or(self.max_tokens),
+            request.temperature.unwrap_or(self.temperature),
+        )?;
+
+        let stream = self.send(&body, true).await?.bytes_stream();
+        let (tx, rx) = mpsc::channel(100);
+
+        let provider = self.clone();
+        tokio::spawn(async move {
+            if let Some(usage) = provider.parse_streaming_response(stream, tx).await {
+                debug!(
+                    "Stream completed with usage - prompt: {}, completion: {}, total: {}",
+                    usage.prompt_tokens, usage.completion_tokens, usage.total_tokens
+                );
+            }
+        });
+
+        Ok(ReceiverStream::new(rx))
+    }
+
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn model(&self) -> &str {
+        &self.model
+    }
+
+    fn has_native_tool_calling(&self) -> bool {
+        self.native_tools
+    }
+
+    fn supports_vision(&self) -> bool {
+        true
+    }
+
+    fn max_tokens(&self) -> u32 {
+        self.max_tokens
+    }
+
+    fn temperature(&self) -> f32 {
+        self.temperature
+    }
+
+    fn token_counter(&self) -> Option<Arc<dyn TokenCounter>> {
+        tokenizer::approximate_for_model(&self.model)
+    }
+}
+
+/// Turns Converse stream events into completion chunks
+#[derive(Debug, Default)]
+struct StreamTranslator {
+    /// Id, name and accumulated JSON input of the tool call being streamed
+    tool_use: Option<(String, String, String)>,
+    usage: Option<Usage>,
+}
+
+impl StreamTranslator {
+    fn translate(&mut self, message: &EventMessage) -> Result<Option<CompletionChunk>> {
+        let payload: Value = serde_json::from_slice(&message.payload).unwrap_or(Value::Null);
+
+        if message.header(":message-type") != Some("event") {
+            let kind = message
+                .header(":exception-type")
+                .or_else(|| message.header(":error-code"))
+                .unwrap_or("unknown error");
+            let detail = payload["message"]
+                .as_str()
+                .or_else(|| message.header(":error-message"))
+                .unwrap_or_default();
+            return Err(anyhow!("Bedrock stream error ({}): {}", kind, detail));
+        }
+
+        match message.header(":event-type").unwrap_or_default() {
+            "contentBlockStart" => {
+                let tool_use = &payload["start"]["toolUse"];
+                if let (Some(id), Some(name)) =
+                    (tool_use["toolUseId"].as_str(), tool_use["name"].as_str())
+                {
+                    self.tool_use = Some((id.to_string(), name.to_string(), String::new()));
+                }
+                Ok(None)
+            }
+            "contentBlockDelta" => {
+                let delta = &payload["delta"];
+                if let Some(text) = delta["text"].as_str() {
+                    return Ok(Some(CompletionChunk {
+                        content: text.to_string(),
+                        finished: false,
+                        usage: None,
+                        tool_calls: None,
+                    }));
+                }
+                if let (Some(input), Some((_, _, buffer))) =
+                    (delta["toolUse"]["input"].as_str(), self.tool_use.as_mut())
+                {
+                    buffer.push_str(input);
+                }
+                // Reasoning deltas are not part of the answer
+                Ok(None)
+            }
+            "contentBlockStop" => {
+                let Some((id, tool, input)) = self.tool_use.take() else {
+                    return Ok(None);
+                };
+                let args = if input.trim().is_empty() {
+                    json!({})
+                } else {
+                    serde_json::from_str(&input).map_err(|e| {
+                        anyhow!("Invalid tool input from Bedrock for {}: {}", tool, e)
+                    })?
+                };
+                Ok(Some(CompletionChunk {
+                    content: String::new(),
+                    finished: false,
+                    usage: None,
+                    tool_calls: Some(vec![ToolCall { id, tool, args }]),
+                }))
+            }
+            "metadata" => {
+                self.usage = parse_usage(&payload["usage"]);
+                Ok(None)
+            }
+            event_type => {
+                debug!("Ignoring Bedrock event: {}", event_type);
+                Ok(None)
+            }
+        }
+    }
+}
+
+/// Split messages into Converse system blocks and conversation messages
+fn convert_messages(messages: &[Message]) -> (Vec<Value>, Vec<Value>) {
+    let mut system = Vec::new();
+    let mut converted: Vec<Value> = Vec::new();
+
+    for message in messages {
+        let role = match message.role {
+            MessageRole::System => {
+                system.push(json!({ "text": message.content }));
+                continue;
+            }
+            MessageRole::User => "user",
+            MessageRole::Assistant => "assistant",
+        };
+
+        // Converse rejects blank text blocks
+        let mut content = Vec::new();
+        if !message.content.trim().is_empty() {
+            content.push(json!({ "text": message.content }));
+        }
+        for image in &message.images {
+            let format = image.media_type.trim_start_matches("image/");
+            content.push(json!({
+                "image": { "format": format, "source": { "bytes": image.data } }
+            }));
+        }
+        if content.is_empty() {
+            continue;
+        }
+
+        // Roles must alternate, so merge consecutive messages of one role
+        match converted.last_mut() {
+            Some(last) if last["role"] == role => {
+                if let Some(blocks) = last["content"].as_array_mut() {
+                    blocks.extend(content);
+                }
+            }
+            _ => converted.push(json!({ "role": role, "content": content })),
+        }
+    }
+    (system, converted)
+}
+
+fn convert_tools(tools: &[Tool]) -> Vec<Value> {
+    tools
+        .iter()
+        .map(|tool| {
+            json!({
+                "toolSpec": {
+                    "name": tool.name,
+                    "description": tool.description,
+                    "inputSchema": { "json": tool.input_schema },
+                }
+            })
+        })
+        .collect()
+}
+
+fn parse_usage(usage: &Value) -> Option<Usage> {
+    let count = |key: &str| usage[key].as_u64().map(|n| n as u32);
+    let prompt_tokens = count("inputTokens")?;
+    let completion_tokens = count("outputTokens")?;
+    Some(Usage {
+        prompt_tokens,
+        completion_tokens,
+        total_tokens: count("totalTokens").unwrap_or(prompt_tokens + completion_tokens),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ImageContent;
+
+    fn provider(native_tools: bool) -> BedrockProvider {
+        BedrockProvider::new_with_name(
+            "bedrock.default".to_string(),
+            "us.anthropic.claude-3-7-sonnet-20250219-v1:0".to_string(),
+            "us-west-2".to_string(),
+            AwsCredentials {
+                access_key_id: "AKIDEXAMPLE".to_string(),
+                secret_access_key: "secret".to_string(),
+                session_token: None,
+            },
+            Some(2048),
+            None,
+            native_tools,
+        )
+        .unwrap()
+    }
+
+    fn event(event_type: &str, payload: Value) -> Vec<u8> {
+        event_stream::encode(
+            &[(":event-type", event_type), (":message-type", "event")],
+            payload.to_string().as_bytes(),
+        )
+    }
+
+    #[test]
+    fn test_context_window() {
+        assert_eq!(
+            context_window("us.anthropic.claude-3-7-sonnet-20250219-v1:0"),
+            Some(200_000)
+        );
+        assert_eq!(context_window("amazon.nova-pro-v1:0"), Some(300_000));
+        assert_eq!(context_window("amazon.nova-micro-v1:0"), Some(128_000));
+        assert_eq!(
+            context_window("meta.llama3-1-70b-instruct-v1:0"),
+            Some(128_000)
+        );
+        assert_eq!(context_window("cohere.command-r-v1:0"), None);
+    }
+
+    #[test]
+    fn test_endpoint() {
+        let bedrock = provider(true);
+        assert_eq!(bedrock.host(), "bedrock-runtime.us-west-2.amazonaws.com");
+        assert_eq!(
+            bedrock.path(true),
+            "/model/us.anthropic.claude-3-7-sonnet-20250219-v1%3A0/converse-stream"
+        );
+    }
+
+    #[test]
+    fn test_request_body() {
+        let mut screenshot = Message::new(MessageRole::User, String::new());
+        screenshot
+            .images
+            .push(ImageContent::new("image/jpeg", "aGk=".to_string()));
+        let messages = vec![
+            Message::new(MessageRole::System, "You are G3.".to_string()),
+            Message::new(MessageRole::User, "What is on screen?".to_string()),
+            screenshot,
+            Message::new(MessageRole::Assistant, "A terminal.".to_string()),
+        ];
+        let tools = vec![Tool {
+            name: "shell".to_string(),
+            description: "Run a command".to_string(),
+            input_schema: json!({"type": "object", "properties": {"command": {"type": "string"}}}),
+        }];
+
+        let body = provider(true)
+            .create_request_body(&messages, Some(&tools), 2048, 0.1)
+            .unwrap();
+        assert_eq!(body["system"], json!([{"text": "You are G3."}]));
+        assert_eq!(body["messages"].as_array().unwrap().len(), 2);
+        assert_eq!(body["messages"][0]["content"][1]["image"]["format"], "jpeg");
+        assert_eq!(body["messages"][1]["role"], "assistant");
+        assert_eq!(body["inferenceConfig"]["maxTokens"], 2048);
+        assert_eq!(
+            body["toolConfig"]["tools"][0]["toolSpec"]["inputSchema"]["json"]["type"],
+            "object"
+        );
+
+        let without_tools = provider(false)
+            .create_request_body(&messages, Some(&tools), 2048, 0.1)
+            .unwrap();
+        assert!(without_tools.get("toolConfig").is_none());
+    }
+
+    #[tokio::test]
+    async fn test_stream_translation() {
+        let mut bytes = event("messageStart", json!({"role": "assistant"}));
+        bytes.extend(event(
+            "contentBlockDelta",
+            json!({"contentBlockIndex": 0, "delta": {"text": "Listing files."}}),
+        ));
+        bytes.extend(event("contentBlockStop", json!({"contentBlockIndex": 0})));
+        bytes.extend(event(
+            "contentBlockStart",
+            json!({"contentBlockIndex": 1, "start": {"toolUse": {"toolUseId": "tooluse_1", "name": "shell"}}}),
+        ));
+        for part in ["{\"comm", "and\": \"ls\"}"] {
+            bytes.extend(event(
+                "contentBlockDelta",
+                json!({"contentBlockIndex": 1, "delta": {"toolUse": {"input": part}}}),
+            ));
+        }
+        bytes.extend(event("contentBlockStop", json!({"contentBlockIndex": 1})));
+        bytes.extend(event("messageStop", json!({"stopReason": "tool_use"})));
+        bytes.extend(event(
+            "metadata",
+            json!({"usage": {"inputTokens": 50, "outputTokens": 12, "totalTokens": 62}}),
+        ));
+
+        // Deliver in small pieces to exercise frame buffering
+        let pieces: Vec<reqwest::Result<Bytes>> = bytes
+            .chunks(37)
+            .map(|piece| Ok(Bytes::copy_from_slice(piece)))
+            .collect();
+        let (tx, mut rx) = mpsc::channel(20);
+        let usage = provider(true)
+            .parse_streaming_response(futures_util::stream::iter(pieces), tx)
+            .await
+            .unwrap();
+        assert_eq!(usage.total_tokens, 62);
+
+        let mut chunks = Vec::new();
+        while let Some(chunk) = rx.recv().await {
+            chunks.push(chunk.unwrap());
+        }
+        assert_eq!(chunks.len(), 3);
+        assert_eq!(chunks[0].content, "Listing files.");
+        let calls = chunks[1].tool_calls.as_ref().unwrap();
+        assert_eq!(calls[0].id, "tooluse_1");
+        assert_eq!(calls[0].args["command"], "ls");
+        assert!(chunks[2].finished);
+        assert_eq!(chunks[2].usage.as_ref().unwrap().prompt_tokens, 50);
+    }
+
+    #[tokio::test]
+    async fn test_stream_exception() {
+        let bytes = event_stream::encode(
+            &[
+                (":message-type", "exception"),
+                (":exception-type", "throttlingException"),
+            ],
+            br#"{"message":"Too many requests"}"#,
+        );
+        let (tx, mut rx) = mpsc::channel(5);
+        provider(true)
+            .parse_streaming_response(futures_util::stream::iter(vec![Ok(Bytes::from(bytes))]), tx)
+            .await;
+
+        let error = rx.recv().await.unwrap().unwrap_err().to_string();
+        assert!(error.contains("throttlingException"));
+        assert!(error.contains("Too many requests"));
+    }
+}
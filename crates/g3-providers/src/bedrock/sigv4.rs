@@ -0,0 +1,365 @@
+//! AWS Signature Version 4 request signing and credential lookup
+
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, Utc};
+use sha2::{Digest, Sha256};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AwsCredentials {
+    pub access_key_id: String,
+    pub secret_access_key: String,
+    pub session_token: Option<String>,
+}
+
+impl AwsCredentials {
+    /// Configured keys if given, else the environment, else `profile` (or
+    /// `AWS_PROFILE`, or "default") in the shared credentials file
+    pub fn resolve(
+        access_key_id: Option<String>,
+        secret_access_key: Option<String>,
+        session_token: Option<String>,
+        profile: Option<&str>,
+    ) -> Result<Self> {
+        if let (Some(access_key_id), Some(secret_access_key)) = (access_key_id, secret_access_key) {
+            return Ok(Self {
+                access_key_id,
+                secret_access_key,
+                session_token,
+            });
+        }
+        if profile.is_none() {
+            if let Some(credentials) = Self::from_env() {
+                return Ok(credentials);
+            }
+        }
+
+        let profile = profile
+            .map(str::to_string)
+            .or_else(|| std::env::var("AWS_PROFILE").ok())
+            .unwrap_or_else(|| "default".to_string());
+        Self::from_profile(&profile).ok_or_else(|| {
+            anyhow!(
+                "No AWS credentials found: set access_key_id and secret_access_key, \
+                 the AWS_ACCESS_KEY_ID and AWS_SECRET_ACCESS_KEY variables, or \
+                 profile '{}' in ~/.aws/credentials",
+                profile
+            )
+        })
+    }
+
+    /// Credentials from `AWS_ACCESS_KEY_ID`, `AWS_SECRET_ACCESS_KEY` and
+    /// `AWS_SESSION_TOKEN`
+    pub fn from_env() -> Option<Self> {
+        let non_empty = |name: &str| std::env::var(name).ok().filter(|v| !v.is_empty());
+        Some(Self {
+            access_key_id: non_empty("AWS_ACCESS_KEY_ID")?,
+            secret_access_key: non_empty("AWS_SECRET_ACCESS_KEY")?,
+            session_token: non_empty("AWS_SESSION_TOKEN"),
+        })
+    }
+
+    /// Credentials of `profile` in the shared credentials file
+    /// (`AWS_SHARED_CREDENTIALS_FILE`, or `~/.aws/credentials`)
+    pub fn from_profile(profile: &str) -> Option<Self> {
+        let path = match std::env::var_os("AWS_SHARED_CREDENTIALS_FILE") {
+            Some(path) => path.into(),
+            None => dirs::home_dir()?.join(".aws").join("credentials"),
+        };
+        parse_credentials_file(&std::fs::read_to_string(path).ok()?, profile)
+    }
+}
+
+/// Read one profile of an INI-style credentials file
+fn parse_credentials_file(contents: &str, profile: &str) -> Option<AwsCredentials> {
+    let mut in_profile = false;
+    let mut access_key_id = None;
+    let mut secret_access_key = None;
+    let mut session_token = None;
+
+    for line in contents.lines().map(str::trim) {
+        if line.is_empty() || line.starts_with('#') || line.starts_with(';') {
+            continue;
+        }
+        if let Some(section) = line.strip_prefix('[').and_then(|l| l.strip_suffix(']')) {
+            in_profile = section.trim() == profile;
+            continue;
+        }
+        if !in_profile {
+            continue;
+        }
+        if let Some((key, value)) = line.split_once('=') {
+            let value = Some(value.trim().to_string());
+            match key.trim() {
+                "aws_access_key_id" => access_key_id = value,
+                "aws_secret_access_key" => secret_access_key = value,
+                "aws_session_token" => session_token = value,
+                _ => {}
+            }
+        }
+    }
+
+    Some(AwsCredentials {
+        access_key_id: access_key_id?,
+        secret_access_key: secret_access_key?,
+        session_token,
+    })
+}
+
+/// Headers that sign a JSON POST of `body` to `https://{host}{path}`; `path`
+/// must already be percent-encoded and the request must send exactly these
+/// headers' values
+pub fn sign_json_post(
+    credentials: &AwsCredentials,
+    region: &str,
+    service: &str,
+    host: &str,
+    path: &str,
+    body: &[u8],
+    now: DateTime<Utc>,
+) -> Vec<(String, String)> {
+    let amz_date = now.format("%Y%m%dT%H%M%SZ").to_string();
+    let mut headers = vec![
+        ("content-type".to_string(), "application/json".to_string()),
+        ("host".to_string(), host.to_string()),
+        ("x-amz-date".to_string(), amz_date),
+    ];
+    if let Some(token) = &credentials.session_token {
+        headers.push(("x-amz-security-token".to_string(), token.clone()));
+    }
+
+    let authorization = authorization(
+        credentials,
+        region,
+        service,
+        "POST",
+        path,
+        &headers,
+        body,
+        now,
+    );
+    headers.push(("authorization".to_string(), authorization));
+    // reqwest derives Host from the URL
+    headers.retain(|(name, _)| name != "host");
+    headers
+}
+
+/// The `Authorization` header value; `headers` must be lowercase and sorted
+#[allow(clippy::too_many_arguments)]
+fn authorization(
+    credentials: &AwsCredentials,
+    region: &str,
+    service: &str,
+    method: &str,
+    path: &str,
+    headers: &[(String, String)],
+    body: &[u8],
+    now: DateTime<Utc>,
+) -> String {
+    let date = now.format("%Y%m%d").to_string();
+    let amz_date = now.format("%Y%m%dT%H%M%SZ").to_string();
+    let scope = format!("{}/{}/{}/aws4_request", date, region, service);
+
+    let signed_headers = headers
+        .iter()
+        .map(|(name, _)| name.as_str())
+        .collect::<Vec<_>>()
+        .join(";");
+    let canonical_headers: String = headers
+        .iter()
+        .map(|(name, value)| format!("{}:{}\n", name, value.trim()))
+        .collect();
+    let canonical_request = format!(
+        "{}\n{}\n\n{}\n{}\n{}",
+        method,
+        canonical_uri(path),
+        canonical_headers,
+        signed_headers,
+        hex(&Sha256::digest(body))
+    );
+    let string_to_sign = format!(
+        "AWS4-HMAC-SHA256\n{}\n{}\n{}",
+        amz_date,
+        scope,
+        hex(&Sha256::digest(canonical_request.as_bytes()))
+    );
+
+    let mut key = hmac_sha256(
+        format!("AWS4{}", credentials.secret_access_key).as_bytes(),
+        date.as_bytes(),
+    );
+    for part in [region, service, "aws4_request"] {
+        key = hmac_sha256(&key, part.as_bytes());
+    }
+    let signature = hex(&hmac_sha256(&key, string_to_sign.as_bytes()));
+
+    format!(
+        "AWS4-HMAC-SHA256 Credential={}/{}, SignedHeaders={}, Signature={}",
+        credentials.access_key_id, scope, signed_headers, signature
+    )
+}
+
+/// Services other than S3 sign each path segment encoded a second time
+fn canonical_uri(path: &str) -> String {
+    path.split('/')
+        .map(uri_encode)
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+/// Percent-encode everything except RFC 3986 unreserved characters
+pub fn uri_encode(segment: &str) -> String {
+    segment
+        .bytes()
+        .map(|byte| match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => {
+                (byte as char).to_string()
+            }
+            _ => format!("%{:02X}", byte),
+        })
+        .collect()
+}
+
+fn hmac_sha256(key: &[u8], message: &[u8]) -> Vec<u8> {
+    const BLOCK_SIZE: usize = 64;
+    let mut block = [0u8; BLOCK_SIZE];
+    if key.len() > BLOCK_SIZE {
+        block[..32].copy_from_slice(&Sha256::digest(key));
+    } else {
+        block[..key.len()].copy_from_slice(key);
+    }
+
+    let mut inner = Sha256::new();
+    inner.update(block.map(|b| b ^ 0x36));
+    inner.update(message);
+    let mut outer = Sha256::new();
+    outer.update(block.map(|b| b ^ 0x5c));
+    outer.update(inner.finalize());
+    outer.finalize().to_vec()
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn example_credentials() -> AwsCredentials {
+        AwsCredentials {
+            access_key_id: "AKIDEXAMPLE".to_string(),
+            secret_access_key: "wJalrXUtnFEMI/K7MDENG+bPxRfiCYEXAMPLEKEY".to_string(),
+            session_token: None,
+        }
+    }
+
+    #[test]
+    fn test_hmac_sha256() {
+        // RFC 4231 test case 2
+        assert_eq!(
+            hex(&hmac_sha256(b"Jefe", b"what do ya want for nothing?")),
+            "5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843"
+        );
+    }
+
+    #[test]
+    fn test_signature_matches_aws_test_suite() {
+        // "post-vanilla" from the AWS SigV4 test suite
+        let now = Utc.with_ymd_and_hms(2015, 8, 30, 12, 36, 0).unwrap();
+        let headers = vec![
+            ("host".to_string(), "example.amazonaws.com".to_string()),
+            ("x-amz-date".to_string(), "20150830T123600Z".to_string()),
+        ];
+        let authorization = authorization(
+            &example_credentials(),
+            "us-east-1",
+            "service",
+            "POST",
+            "/",
+            &headers,
+            b"",
+            now,
+        );
+        assert_eq!(
+            authorization,
+            "AWS4-HMAC-SHA256 Credential=AKIDEXAMPLE/20150830/us-east-1/service/aws4_request, \
+             SignedHeaders=host;x-amz-date, \
+             Signature=5da7c1a2acd57cee7505fc6676e4e544621c30862966e37dddb68e92efbe5d6b"
+        );
+    }
+
+    #[test]
+    fn test_sign_json_post_headers() {
+        let mut credentials = example_credentials();
+        credentials.session_token = Some("token".to_string());
+        let now = Utc.with_ymd_and_hms(2024, 5, 1, 8, 0, 0).unwrap();
+        let headers = sign_json_post(
+            &credentials,
+            "us-west-2",
+            "bedrock",
+            "bedrock-runtime.us-west-2.amazonaws.com",
+            "/model/anthropic.claude-3-5-sonnet-20240620-v1%3A0/converse",
+            b"{}",
+            now,
+        );
+        let names: Vec<_> = headers.iter().map(|(name, _)| name.as_str()).collect();
+        assert_eq!(
+            names,
+            [
+                "content-type",
+                "x-amz-date",
+                "x-amz-security-token",
+                "authorization"
+            ]
+        );
+        assert!(headers[3].1.contains(
+            "/20240501/us-west-2/bedrock/aws4_request, \
+             SignedHeaders=content-type;host;x-amz-date;x-amz-security-token,"
+        ));
+    }
+
+    #[test]
+    fn test_canonical_uri_double_encodes() {
+        assert_eq!(
+            canonical_uri("/model/us.anthropic.claude-v2%3A1/converse-stream"),
+            "/model/us.anthropic.claude-v2%253A1/converse-stream"
+        );
+        assert_eq!(uri_encode("a:b/c d"), "a%3Ab%2Fc%20d");
+    }
+
+    #[test]
+    fn test_resolve_prefers_configured_keys() {
+        let credentials = AwsCredentials::resolve(
+            Some("AKIDCONFIG".to_string()),
+            Some("secret".to_string()),
+            None,
+            Some("unused"),
+        )
+        .unwrap();
+        assert_eq!(credentials.access_key_id, "AKIDCONFIG");
+    }
+
+    #[test]
+    fn test_parse_credentials_file() {
+        let contents = "\
+[default]
+aws_access_key_id = AKIDDEFAULT
+aws_secret_access_key = secret1
+
+[work]
+# temporary credentials
+aws_access_key_id=AKIDWORK
+aws_secret_access_key=secret2
+aws_session_token=token2
+";
+        let work = parse_credentials_file(contents, "work").unwrap();
+        assert_eq!(work.access_key_id, "AKIDWORK");
+        assert_eq!(work.session_token.as_deref(), Some("token2"));
+
+        let default = parse_credentials_file(contents, "default").unwrap();
+        assert_eq!(default.secret_access_key, "secret1");
+        assert!(default.session_token.is_none());
+        assert!(parse_credentials_file(contents, "missing").is_none());
+    }
+}
@@ -0,0 +1,291 @@
+//! On-disk cache for deterministic completions
+//!
+//! [`CachingProvider`] wraps a provider and stores the responses of
+//! deterministic requests (temperature 0, no tools) in a [`ResponseCache`],
+//! keyed by a hash of the provider, model and request. An identical request
+//! is then answered from disk without calling the model, so re-running a plan
+//! doesn't pay for the same call twice. Streaming requests always go to the
+//! provider.
+
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use sha2::{Digest, Sha256};
+use std::path::PathBuf;
+use std::sync::Arc;
+use tracing::debug;
+
+use crate::{
+    CompletionRequest, CompletionResponse, CompletionStream, LLMProvider, TokenCounter, Usage,
+};
+
+/// A directory of cached responses, one JSON file per request hash
+#[derive(Debug, Clone)]
+pub struct ResponseCache {
+    dir: PathBuf,
+}
+
+impl ResponseCache {
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self { dir: dir.into() }
+    }
+
+    /// Whether a request's response is worth caching: repeating it should
+    /// give the same answer, and it has no tools that would be called
+    pub fn is_cacheable(request: &CompletionRequest) -> bool {
+        request.temperature == Some(0.0) && request.tools.is_none()
+    }
+
+    /// Hash of everything that determines the response
+    pub fn key(provider: &str, model: &str, request: &CompletionRequest) -> String {
+        let mut hasher = Sha256::new();
+        // Length-prefix each field so adjacent fields can't run together
+        let mut field = |value: &[u8]| {
+            hasher.update((value.len() as u64).to_be_bytes());
+            hasher.update(value);
+        };
+        field(provider.as_bytes());
+        field(model.as_bytes());
+        field(format!("{:?}", request.max_tokens).as_bytes());
+        field(format!("{}", request.disable_thinking).as_bytes());
+        for message in &request.messages {
+            field(format!("{:?}", message.role).as_bytes());
+            field(message.content.as_bytes());
+            for image in &message.images {
+                field(image.media_type.as_bytes());
+                field(image.data.as_bytes());
+            }
+        }
+        hasher
+            .finalize()
+            .iter()
+            .map(|b| format!("{:02x}", b))
+            .collect()
+    }
+
+    fn path(&self, key: &str) -> PathBuf {
+        self.dir.join(format!("{}.json", key))
+    }
+
+    pub fn get(&self, key: &str) -> Option<CompletionResponse> {
+        let contents = std::fs::read_to_string(self.path(key)).ok()?;
+        match serde_json::from_str(&contents) {
+            Ok(response) => Some(response),
+            Err(e) => {
+                debug!("Ignoring unreadable cached response {}: {}", key, e);
+                None
+            }
+        }
+    }
+
+    pub fn put(&self, key: &str, response: &CompletionResponse) -> Result<()> {
+        std::fs::create_dir_all(&self.dir)
+            .with_context(|| format!("Failed to create response cache {}", self.dir.display()))?;
+        // Write to a temporary file first so readers never see a partial response
+        let path = self.path(key);
+        let tmp_path = path.with_extension("json.tmp");
+        std::fs::write(&tmp_path, serde_json::to_string(response)?)?;
+        std::fs::rename(&tmp_path, &path)?;
+        Ok(())
+    }
+}
+
+/// Answers deterministic requests from a [`ResponseCache`] when it can
+pub struct CachingProvider {
+    inner: Box<dyn LLMProvider>,
+    cache: ResponseCache,
+}
+
+impl CachingProvider {
+    pub fn new(inner: Box<dyn LLMProvider>, cache: ResponseCache) -> Self {
+        Self { inner, cache }
+    }
+}
+
+#[async_trait]
+impl LLMProvider for CachingProvider {
+    async fn complete(&self, request: CompletionRequest) -> Result<CompletionResponse> {
+        if !ResponseCache::is_cacheable(&request) {
+            return self.inner.complete(request).await;
+        }
+
+        let key = ResponseCache::key(self.inner.name(), self.inner.model(), &request);
+        if let Some(mut response) = self.cache.get(&key) {
+            debug!("Response cache hit: {}", key);
+            // Nothing was spent on this response
+            response.usage = Usage {
+                prompt_tokens: 0,
+                completion_tokens: 0,
+                total_tokens: 0,
+            };
+            return Ok(response);
+        }
+
+        let response = self.inner.complete(request).await?;
+        if let Err(e) = self.cache.put(&key, &response) {
+            debug!("Failed to cache response {}: {}", key, e);
+        }
+        Ok(response)
+    }
+
+    async fn stream(&self, request: CompletionRequest) -> Result<CompletionStream> {
+        self.inner.stream(request).await
+    }
+
+    fn name(&self) -> &str {
+        self.inner.name()
+    }
+
+    fn model(&self) -> &str {
+        self.inner.model()
+    }
+
+    fn has_native_tool_calling(&self) -> bool {
+        self.inner.has_native_tool_calling()
+    }
+
+    fn supports_cache_control(&self) -> bool {
+        self.inner.supports_cache_control()
+    }
+
+    fn supports_vision(&self) -> bool {
+        self.inner.supports_vision()
+    }
+
+    fn max_tokens(&self) -> u32 {
+        self.inner.max_tokens()
+    }
+
+    fn temperature(&self) -> f32 {
+        self.inner.temperature()
+    }
+
+    fn token_counter(&self) -> Option<Arc<dyn TokenCounter>> {
+        self.inner.token_counter()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ImageContent, Message, MessageRole};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+    use tempfile::TempDir;
+
+    /// Counts its calls and answers with the call number
+    struct CountingProvider {
+        calls: Arc<AtomicUsize>,
+    }
+
+    #[async_trait]
+    impl LLMProvider for CountingProvider {
+        async fn complete(&self, _request: CompletionRequest) -> Result<CompletionResponse> {
+            let call = self.calls.fetch_add(1, Ordering::SeqCst) + 1;
+            Ok(CompletionResponse {
+                content: format!("response {}", call),
+                usage: Usage {
+                    prompt_tokens: 10,
+                    completion_tokens: 5,
+                    total_tokens: 15,
+                },
+                model: "test-model".to_string(),
+            })
+        }
+
+        async fn stream(&self, _request: CompletionRequest) -> Result<CompletionStream> {
+            anyhow::bail!("not used")
+        }
+
+        fn name(&self) -> &str {
+            "anthropic.default"
+        }
+
+        fn model(&self) -> &str {
+            "test-model"
+        }
+
+        fn max_tokens(&self) -> u32 {
+            1000
+        }
+
+        fn temperature(&self) -> f32 {
+            0.0
+        }
+    }
+
+    fn request(content: &str, temperature: f32) -> CompletionRequest {
+        CompletionRequest {
+            messages: vec![Message::new(MessageRole::User, content.to_string())],
+            max_tokens: Some(500),
+            temperature: Some(temperature),
+            stream: false,
+            tools: None,
+            disable_thinking: false,
+        }
+    }
+
+    fn caching_provider(dir: &TempDir) -> (CachingProvider, Arc<AtomicUsize>) {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let inner = Box::new(CountingProvider {
+            calls: calls.clone(),
+        });
+        (
+            CachingProvider::new(inner, ResponseCache::new(dir.path().join("responses"))),
+            calls,
+        )
+    }
+
+    #[test]
+    fn test_key_ignores_message_ids() {
+        // Each Message::new gets a fresh id, which must not change the key
+        let first = ResponseCache::key("anthropic.default", "m", &request("hi", 0.0));
+        let second = ResponseCache::key("anthropic.default", "m", &request("hi", 0.0));
+        assert_eq!(first, second);
+        assert_ne!(
+            first,
+            ResponseCache::key("openai.default", "m", &request("hi", 0.0))
+        );
+        assert_ne!(
+            first,
+            ResponseCache::key("anthropic.default", "m", &request("hello", 0.0))
+        );
+
+        let mut with_image = request("hi", 0.0);
+        with_image.messages[0]
+            .images
+            .push(ImageContent::new("image/png", "AAAA".to_string()));
+        assert_ne!(
+            first,
+            ResponseCache::key("anthropic.default", "m", &with_image)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_deterministic_requests_are_cached() {
+        let dir = TempDir::new().unwrap();
+        let (provider, calls) = caching_provider(&dir);
+
+        let first = provider.complete(request("summarize", 0.0)).await.unwrap();
+        let second = provider.complete(request("summarize", 0.0)).await.unwrap();
+        assert_eq!(first.content, "response 1");
+        assert_eq!(second.content, "response 1");
+        assert_eq!(second.usage.total_tokens, 0);
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+
+        // The cache outlives the provider
+        let (provider, calls) = caching_provider(&dir);
+        provider.complete(request("summarize", 0.0)).await.unwrap();
+        assert_eq!(calls.load(Ordering::SeqCst), 0);
+    }
+
+    #[tokio::test]
+    async fn test_sampled_requests_are_not_cached() {
+        let dir = TempDir::new().unwrap();
+        let (provider, calls) = caching_provider(&dir);
+
+        provider.complete(request("brainstorm", 0.7)).await.unwrap();
+        let second = provider.complete(request("brainstorm", 0.7)).await.unwrap();
+        assert_eq!(second.content, "response 2");
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+    }
+}
@@ -61,14 +61,16 @@ use bytes::Bytes;
 use futures_util::stream::StreamExt;
 use reqwest::{Client, RequestBuilder};
 use serde::{Deserialize, Serialize};
+use std::sync::Arc;
 use std::time::Duration;
 use tokio::sync::mpsc;
 use tokio_stream::wrappers::ReceiverStream;
 use tracing::{debug, error, warn};
 
+use crate::tokenizer::{self, TokenCounter};
 use crate::{
-    CompletionChunk, CompletionRequest, CompletionResponse, CompletionStream, LLMProvider, Message,
-    MessageRole, Tool, ToolCall, Usage,
+    CompletionChunk, CompletionRequest, CompletionResponse, CompletionStream, G3Error, LLMProvider,
+    Message, MessageRole, Tool, ToolCall, Usage,
 };
 
 const DEFAULT_CLIENT_ID: &str = "databricks-cli";
@@ -844,7 +846,7 @@ impl LLMProvider for DatabricksProvider {
             .json(&request_body)
             .send()
             .await
-            .map_err(|e| anyhow!("Failed to send request to Databricks API: {}", e))?;
+            .map_err(|e| G3Error::transport("Failed to send request to Databricks API", &e))?;
 
         let status = response.status();
         if !status.is_success() {
@@ -876,7 +878,7 @@ impl LLMProvider for DatabricksProvider {
                                 .json(&request_body)
                                 .send()
                                 .await
-                                .map_err(|e| anyhow!("Failed to send request to Databricks API after token refresh: {}", e))?;
+                                .map_err(|e| G3Error::transport("Failed to send request to Databricks API after token refresh", &e))?;
 
                             let retry_status = response.status();
                             if !retry_status.is_success() {
@@ -884,11 +886,12 @@ impl LLMProvider for DatabricksProvider {
                                     .text()
                                     .await
                                     .unwrap_or_else(|_| "Unknown error".to_string());
-                                return Err(anyhow!(
-                                    "Databricks API error {} after token refresh: {}",
+                                return Err(G3Error::api(
+                                    "Databricks",
                                     retry_status,
-                                    retry_error_text
-                                ));
+                                    format!("after token refresh: {}", retry_error_text),
+                                )
+                                .into());
                             }
                         }
                         Err(e) => {
@@ -900,10 +903,10 @@ impl LLMProvider for DatabricksProvider {
                         }
                     }
                 } else {
-                    return Err(anyhow!("Databricks API error {}: {}", status, error_text));
+                    return Err(G3Error::api("Databricks", status, error_text).into());
                 }
             } else {
-                return Err(anyhow!("Databricks API error {}: {}", status, error_text));
+                return Err(G3Error::api("Databricks", status, error_text).into());
             }
         }
 
@@ -1025,7 +1028,9 @@ impl LLMProvider for DatabricksProvider {
             .json(&request_body)
             .send()
             .await
-            .map_err(|e| anyhow!("Failed to send streaming request to Databricks API: {}", e))?;
+            .map_err(|e| {
+                G3Error::transport("Failed to send streaming request to Databricks API", &e)
+            })?;
 
         let status = response.status();
         if !status.is_success() {
@@ -1057,7 +1062,7 @@ impl LLMProvider for DatabricksProvider {
                                 .json(&request_body)
                                 .send()
                                 .await
-                                .map_err(|e| anyhow!("Failed to send streaming request to Databricks API after token refresh: {}", e))?;
+                                .map_err(|e| G3Error::transport("Failed to send streaming request to Databricks API after token refresh", &e))?;
 
                             let retry_status = response.status();
                             if !retry_status.is_success() {
@@ -1065,11 +1070,12 @@ impl LLMProvider for DatabricksProvider {
                                     .text()
                                     .await
                                     .unwrap_or_else(|_| "Unknown error".to_string());
-                                return Err(anyhow!(
-                                    "Databricks API error {} after token refresh: {}",
+                                return Err(G3Error::api(
+                                    "Databricks",
                                     retry_status,
-                                    retry_error_text
-                                ));
+                                    format!("after token refresh: {}", retry_error_text),
+                                )
+                                .into());
                             }
                         }
                         Err(e) => {
@@ -1081,10 +1087,10 @@ impl LLMProvider for DatabricksProvider {
                         }
                     }
                 } else {
-                    return Err(anyhow!("Databricks API error {}: {}", status, error_text));
+                    return Err(G3Error::api("Databricks", status, error_text).into());
                 }
             } else {
-                return Err(anyhow!("Databricks API error {}: {}", status, error_text));
+                return Err(G3Error::api("Databricks", status, error_text).into());
             }
         }
 
@@ -1125,6 +1131,10 @@ impl LLMProvider for DatabricksProvider {
     fn temperature(&self) -> f32 {
         self.temperature
     }
+
+    fn token_counter(&self) -> Option<Arc<dyn TokenCounter>> {
+        tokenizer::approximate_for_model(&self.model)
+    }
 }
 
 // Databricks API request/response structures
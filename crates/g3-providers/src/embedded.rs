@@ -1,12 +1,14 @@
+use crate::tokenizer;
 use crate::{
     CompletionChunk, CompletionRequest, CompletionResponse, CompletionStream, LLMProvider, Message,
-    MessageRole, Usage,
+    MessageRole, TokenCounter, Usage,
 };
 use anyhow::Result;
 use llama_cpp::{
     standard_sampler::{SamplerStage, StandardSampler},
     LlamaModel, LlamaParams, LlamaSession, SessionParams,
 };
+use std::fmt;
 use std::path::{Path, PathBuf};
 use std::sync::Arc;
 use tokio::sync::mpsc;
@@ -20,6 +22,37 @@ pub struct EmbeddedProvider {
     max_tokens: u32,
     temperature: f32,
     context_length: u32,
+    token_counter: Arc<dyn TokenCounter>,
+}
+
+/// Counts with the loaded model's own vocabulary
+struct LlamaCounter {
+    name: String,
+    model: LlamaModel,
+}
+
+impl fmt::Debug for LlamaCounter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("LlamaCounter")
+            .field("name", &self.name)
+            .finish_non_exhaustive()
+    }
+}
+
+impl TokenCounter for LlamaCounter {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn count(&self, text: &str) -> u32 {
+        match self.model.tokenize_bytes(text, false, false) {
+            Ok(tokens) => tokens.len() as u32,
+            Err(e) => {
+                debug!("{} failed to tokenize: {}", self.name, e);
+                tokenizer::estimate(text)
+            }
+        }
+    }
 }
 
 impl EmbeddedProvider {
@@ -81,12 +114,18 @@ impl EmbeddedProvider {
 
         debug!("Successfully loaded {} model", model_type);
 
+        let token_counter = Arc::new(LlamaCounter {
+            name: format!("{} vocabulary", model_type),
+            model,
+        });
+
         Ok(Self {
             session: Arc::new(Mutex::new(session)),
             model_name: format!("embedded-{}", model_type),
             max_tokens: max_tokens.unwrap_or(2048),
             temperature: temperature.unwrap_or(0.1),
             context_length: context_size,
+            token_counter,
         })
     }
 
@@ -825,4 +864,8 @@ impl LLMProvider for EmbeddedProvider {
     fn temperature(&self) -> f32 {
         self.temperature
     }
+
+    fn token_counter(&self) -> Option<Arc<dyn TokenCounter>> {
+        Some(self.token_counter.clone())
+    }
 }
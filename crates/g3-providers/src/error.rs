@@ -0,0 +1,262 @@
+//! Typed errors for failures that cross crate boundaries.
+//!
+//! Errors stay `anyhow::Error`s, but the ones raised by providers, the
+//! agent's tools and the planner's git operations carry a [`G3Error`], so
+//! callers classify them by type rather than by matching message text:
+//! [`G3Error::find`] gets it back out of any error chain. Each error has a
+//! [`ErrorCategory`], which says whether retrying can help and gives a hint
+//! on what the user can do about it.
+
+use std::fmt;
+
+/// What kind of failure an error is
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ErrorCategory {
+    /// The provider refused the request for now (HTTP 429)
+    RateLimit,
+    /// The provider couldn't be reached, or the connection dropped
+    Network,
+    /// The provider took too long to answer
+    Timeout,
+    /// The provider failed on its side (HTTP 5xx)
+    Server,
+    /// The model is overloaded (HTTP 503, 529)
+    Overloaded,
+    /// The request doesn't fit the model's context window
+    ContextLength,
+    /// The provider rejected the credentials (HTTP 401, 403)
+    Auth,
+    /// The provider rejected the request itself (other HTTP 4xx)
+    InvalidRequest,
+    /// A tool failed outside the model's control
+    Tool,
+    /// A git command failed
+    Git,
+}
+
+impl ErrorCategory {
+    /// Whether sending the same request again can succeed
+    pub fn is_retryable(self) -> bool {
+        matches!(
+            self,
+            Self::RateLimit | Self::Network | Self::Timeout | Self::Server | Self::Overloaded
+        )
+    }
+
+    /// What the user can do about it
+    pub fn hint(self) -> &'static str {
+        match self {
+            Self::RateLimit => {
+                "The provider is rate limiting requests. Wait a moment, set \
+                 [providers.rate_limits], or configure fallback_providers."
+            }
+            Self::Network => "Check the network connection and the provider's host or base_url.",
+            Self::Timeout => {
+                "The provider took too long to answer. Try again, or raise \
+                 [agent] timeout_seconds."
+            }
+            Self::Server => {
+                "The provider failed on its side. Try again shortly, or configure \
+                 fallback_providers."
+            }
+            Self::Overloaded => {
+                "The model is overloaded. Try again shortly, or switch providers \
+                 with /model."
+            }
+            Self::ContextLength => {
+                "The conversation no longer fits the model. Run /compact or \
+                 /thinnify, or switch to a model with a larger context window."
+            }
+            Self::Auth => {
+                "The provider rejected the credentials. Check the api_key, or \
+                 store one with `g3 auth login <provider>`."
+            }
+            Self::InvalidRequest => {
+                "The provider rejected the request. Check the model name and \
+                 settings of the provider config."
+            }
+            Self::Tool => "A tool failed. Fix the cause shown above and send the request again.",
+            Self::Git => "Check `git status` in the codebase and resolve what git reports.",
+        }
+    }
+}
+
+impl fmt::Display for ErrorCategory {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Self::RateLimit => "rate_limit",
+            Self::Network => "network",
+            Self::Timeout => "timeout",
+            Self::Server => "server",
+            Self::Overloaded => "overloaded",
+            Self::ContextLength => "context_length",
+            Self::Auth => "auth",
+            Self::InvalidRequest => "invalid_request",
+            Self::Tool => "tool",
+            Self::Git => "git",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// A failure of a provider, tool or git operation
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum G3Error {
+    /// A provider's API answered with an error status
+    #[error("{provider} API error {}: {message}", status_text(.status))]
+    Api {
+        provider: String,
+        status: u16,
+        message: String,
+    },
+    /// A request couldn't be sent, or its response not received
+    #[error("{message}")]
+    Transport { message: String, timeout: bool },
+    /// A tool failed
+    #[error("{tool}: {message}")]
+    Tool { tool: String, message: String },
+    /// A git command failed
+    #[error("{message}")]
+    Git { message: String },
+}
+
+impl G3Error {
+    /// An error status from `provider`'s API, with the response body
+    pub fn api(provider: &str, status: reqwest::StatusCode, message: impl Into<String>) -> Self {
+        Self::Api {
+            provider: provider.to_string(),
+            status: status.as_u16(),
+            message: message.into(),
+        }
+    }
+
+    /// A request that failed in transit, described by `context`
+    pub fn transport(context: &str, error: &reqwest::Error) -> Self {
+        Self::Transport {
+            message: format!("{}: {}", context, error),
+            timeout: error.is_timeout(),
+        }
+    }
+
+    pub fn tool(tool: &str, message: impl Into<String>) -> Self {
+        Self::Tool {
+            tool: tool.to_string(),
+            message: message.into(),
+        }
+    }
+
+    pub fn git(message: impl Into<String>) -> Self {
+        Self::Git {
+            message: message.into(),
+        }
+    }
+
+    pub fn category(&self) -> ErrorCategory {
+        match self {
+            Self::Api {
+                status, message, ..
+            } => api_category(*status, message),
+            Self::Transport { timeout: true, .. } => ErrorCategory::Timeout,
+            Self::Transport { .. } => ErrorCategory::Network,
+            Self::Tool { .. } => ErrorCategory::Tool,
+            Self::Git { .. } => ErrorCategory::Git,
+        }
+    }
+
+    pub fn is_retryable(&self) -> bool {
+        self.category().is_retryable()
+    }
+
+    pub fn hint(&self) -> &'static str {
+        self.category().hint()
+    }
+
+    /// The G3Error in `error` or the errors it wraps
+    pub fn find(error: &anyhow::Error) -> Option<&G3Error> {
+        error.downcast_ref::<G3Error>().or_else(|| {
+            error
+                .chain()
+                .find_map(|cause| cause.downcast_ref::<G3Error>())
+        })
+    }
+}
+
+fn api_category(status: u16, message: &str) -> ErrorCategory {
+    let message = message.to_lowercase();
+    match status {
+        429 => ErrorCategory::RateLimit,
+        401 | 403 => ErrorCategory::Auth,
+        408 | 504 => ErrorCategory::Timeout,
+        503 | 529 => ErrorCategory::Overloaded,
+        500..=599 => ErrorCategory::Server,
+        400 | 413
+            if [
+                "context length",
+                "context_length_exceeded",
+                "prompt is too long",
+                "too many tokens",
+            ]
+            .iter()
+            .any(|pattern| message.contains(pattern)) =>
+        {
+            ErrorCategory::ContextLength
+        }
+        _ => ErrorCategory::InvalidRequest,
+    }
+}
+
+/// "429 Too Many Requests", as reqwest prints a status
+fn status_text(status: &u16) -> String {
+    match reqwest::StatusCode::from_u16(*status) {
+        Ok(status) => status.to_string(),
+        Err(_) => status.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::Context;
+
+    #[test]
+    fn test_api_errors() {
+        let error = G3Error::api(
+            "Anthropic",
+            reqwest::StatusCode::TOO_MANY_REQUESTS,
+            "slow down",
+        );
+        assert_eq!(
+            error.to_string(),
+            "Anthropic API error 429 Too Many Requests: slow down"
+        );
+        assert_eq!(error.category(), ErrorCategory::RateLimit);
+        assert!(error.is_retryable());
+
+        let error = G3Error::api(
+            "OpenAI",
+            reqwest::StatusCode::BAD_REQUEST,
+            "This model's maximum context length is 128000 tokens",
+        );
+        assert_eq!(error.category(), ErrorCategory::ContextLength);
+        assert!(!error.is_retryable());
+
+        let error = G3Error::api("Gemini", reqwest::StatusCode::UNAUTHORIZED, "bad key");
+        assert_eq!(error.category(), ErrorCategory::Auth);
+        assert!(error.hint().contains("g3 auth login"));
+    }
+
+    #[test]
+    fn test_find() {
+        let error = anyhow::Error::new(G3Error::git("Not in a git repository"));
+        let error = Err::<(), _>(error).context("Planning failed").unwrap_err();
+        assert_eq!(
+            G3Error::find(&error).map(G3Error::category),
+            Some(ErrorCategory::Git)
+        );
+        assert_eq!(
+            G3Error::find(&anyhow::anyhow!("429 Too Many Requests")),
+            None
+        );
+        assert_eq!(ErrorCategory::ContextLength.to_string(), "context_length");
+    }
+}
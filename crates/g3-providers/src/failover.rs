@@ -0,0 +1,484 @@
+//! Retry and failover across an ordered list of providers
+//!
+//! [`FailoverProvider`] sends each request to the active provider and retries
+//! rate limits, server errors and timeouts with exponential backoff. When the
+//! retries run out (or the provider rejects its credentials) it switches to
+//! the next provider in the list and keeps using it for later requests. Each
+//! switch is reported through the status callback so the UI can show it.
+//!
+//! Errors carrying a [`G3Error`] are classified by its category; others by
+//! their message.
+//!
+//! The providers should agree on native tool calling, since the system prompt
+//! is chosen for the primary provider when the session starts.
+
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use futures_util::stream::StreamExt;
+use std::future::Future;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::mpsc;
+use tokio_stream::wrappers::ReceiverStream;
+use tracing::{debug, warn};
+
+use crate::{
+    CompletionRequest, CompletionResponse, CompletionStream, ErrorCategory, G3Error, LLMProvider,
+    TokenCounter,
+};
+
+/// Receives a message whenever the active provider changes
+pub type StatusCallback = Arc<dyn Fn(&str) + Send + Sync>;
+
+const DEFAULT_RETRIES: u32 = 2;
+const BASE_DELAY: Duration = Duration::from_millis(500);
+const MAX_DELAY: Duration = Duration::from_secs(8);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Failure {
+    /// Worth retrying on the same provider
+    Transient,
+    /// This provider can't serve the request, but another might
+    Provider,
+    /// The request itself is bad; no provider will accept it
+    Request,
+}
+
+fn classify(error: &anyhow::Error) -> Failure {
+    if let Some(error) = G3Error::find(error) {
+        return match error.category() {
+            category if category.is_retryable() => Failure::Transient,
+            ErrorCategory::Auth => Failure::Provider,
+            _ => Failure::Request,
+        };
+    }
+
+    let message = error.to_string().to_lowercase();
+    let mentions = |patterns: &[&str]| patterns.iter().any(|p| message.contains(p));
+
+    if mentions(&[
+        "429",
+        "rate limit",
+        "rate_limit",
+        "too many requests",
+        "throttl",
+        "500",
+        "502",
+        "503",
+        "504",
+        "529",
+        "overloaded",
+        "unavailable",
+        "timeout",
+        "timed out",
+        "connection",
+        "stream error",
+    ]) {
+        Failure::Transient
+    } else if mentions(&[
+        "401",
+        "403",
+        "unauthorized",
+        "forbidden",
+        "api key",
+        "credentials",
+    ]) {
+        Failure::Provider
+    } else {
+        Failure::Request
+    }
+}
+
+pub struct FailoverProvider {
+    providers: Vec<Box<dyn LLMProvider>>,
+    active: AtomicUsize,
+    retries: u32,
+    base_delay: Duration,
+    on_switch: Option<StatusCallback>,
+}
+
+impl FailoverProvider {
+    /// Fail over through `providers` in order; the first is the primary
+    pub fn new(providers: Vec<Box<dyn LLMProvider>>) -> Result<Self> {
+        if providers.is_empty() {
+            anyhow::bail!("Failover needs at least one provider");
+        }
+        Ok(Self {
+            providers,
+            active: AtomicUsize::new(0),
+            retries: DEFAULT_RETRIES,
+            base_delay: BASE_DELAY,
+            on_switch: None,
+        })
+    }
+
+    /// Retries per provider before switching to the next one
+    pub fn with_retries(mut self, retries: u32) -> Self {
+        self.retries = retries;
+        self
+    }
+
+    pub fn with_status_callback(mut self, on_switch: StatusCallback) -> Self {
+        self.on_switch = Some(on_switch);
+        self
+    }
+
+    fn active(&self) -> &dyn LLMProvider {
+        self.providers[self.active.load(Ordering::SeqCst)].as_ref()
+    }
+
+    fn switch_to(&self, index: usize, error: Option<&anyhow::Error>) {
+        let from = self.active.swap(index, Ordering::SeqCst);
+        let reason = error
+            .map(|e| e.to_string())
+            .unwrap_or_default()
+            .lines()
+            .next()
+            .unwrap_or_default()
+            .chars()
+            .take(120)
+            .collect::<String>();
+        let message = format!(
+            "⚠️ {} failed ({}), switching to {}",
+            self.providers[from].name(),
+            reason,
+            self.providers[index].name()
+        );
+        warn!("{}", message);
+        if let Some(on_switch) = &self.on_switch {
+            on_switch(&message);
+        }
+    }
+
+    /// Run `call` against the active provider, then each of the others in
+    /// turn, until one succeeds
+    async fn with_failover<T, F, Fut>(&self, call: F) -> Result<T>
+    where
+        F: Fn(usize) -> Fut,
+        Fut: Future<Output = Result<T>>,
+    {
+        let start = self.active.load(Ordering::SeqCst);
+        let count = self.providers.len();
+        let mut last_error = None;
+
+        for index in (0..count).map(|offset| (start + offset) % count) {
+            if index != start {
+                self.switch_to(index, last_error.as_ref());
+            }
+
+            let mut attempt = 0;
+            loop {
+                let error = match call(index).await {
+                    Ok(result) => return Ok(result),
+                    Err(error) => error,
+                };
+                match classify(&error) {
+                    Failure::Transient if attempt < self.retries => {
+                        attempt += 1;
+                        let delay = (self.base_delay * 2u32.pow(attempt - 1)).min(MAX_DELAY);
+                        warn!(
+                            "{} failed (attempt {}/{}): {}. Retrying in {:?}...",
+                            self.providers[index].name(),
+                            attempt,
+                            self.retries + 1,
+                            error,
+                            delay
+                        );
+                        tokio::time::sleep(delay).await;
+                    }
+                    Failure::Request => return Err(error),
+                    Failure::Transient | Failure::Provider => {
+                        last_error = Some(error);
+                        break;
+                    }
+                }
+            }
+        }
+
+        Err(last_error.unwrap_or_else(|| anyhow!("All providers failed")))
+    }
+
+    /// Start a stream, treating an error as its first item like a failed
+    /// request so we can still fail over before anything reaches the caller
+    async fn start_stream(
+        &self,
+        index: usize,
+        request: CompletionRequest,
+    ) -> Result<CompletionStream> {
+        let mut stream = self.providers[index].stream(request).await?;
+        let first = match stream.next().await {
+            Some(Err(error)) => return Err(error),
+            first => first,
+        };
+
+        let (tx, rx) = mpsc::channel(100);
+        tokio::spawn(async move {
+            if let Some(first) = first {
+                if tx.send(first).await.is_err() {
+                    return;
+                }
+            }
+            while let Some(item) = stream.next().await {
+                if tx.send(item).await.is_err() {
+                    debug!("Receiver dropped, stopping failover stream");
+                    return;
+                }
+            }
+        });
+        Ok(ReceiverStream::new(rx))
+    }
+}
+
+#[async_trait]
+impl LLMProvider for FailoverProvider {
+    async fn complete(&self, request: CompletionRequest) -> Result<CompletionResponse> {
+        self.with_failover(|index| self.providers[index].complete(request.clone()))
+            .await
+    }
+
+    async fn stream(&self, request: CompletionRequest) -> Result<CompletionStream> {
+        self.with_failover(|index| self.start_stream(index, request.clone()))
+            .await
+    }
+
+    /// The active provider's name, so config lookups follow a switch
+    fn name(&self) -> &str {
+        self.active().name()
+    }
+
+    fn model(&self) -> &str {
+        self.active().model()
+    }
+
+    fn has_native_tool_calling(&self) -> bool {
+        self.active().has_native_tool_calling()
+    }
+
+    fn supports_cache_control(&self) -> bool {
+        self.active().supports_cache_control()
+    }
+
+    fn supports_vision(&self) -> bool {
+        self.active().supports_vision()
+    }
+
+    fn max_tokens(&self) -> u32 {
+        self.active().max_tokens()
+    }
+
+    fn temperature(&self) -> f32 {
+        self.active().temperature()
+    }
+
+    fn token_counter(&self) -> Option<Arc<dyn TokenCounter>> {
+        self.active().token_counter()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{CompletionChunk, Message, MessageRole, Usage};
+    use std::collections::VecDeque;
+    use std::sync::Mutex;
+
+    /// Replies with a scripted sequence of results ("ok" or an error message)
+    struct ScriptedProvider {
+        name: String,
+        script: Mutex<VecDeque<Result<&'static str, &'static str>>>,
+        calls: Arc<AtomicUsize>,
+    }
+
+    impl ScriptedProvider {
+        fn boxed(
+            name: &str,
+            script: Vec<Result<&'static str, &'static str>>,
+        ) -> (Box<dyn LLMProvider>, Arc<AtomicUsize>) {
+            let calls = Arc::new(AtomicUsize::new(0));
+            let provider = Self {
+                name: name.to_string(),
+                script: Mutex::new(script.into()),
+                calls: calls.clone(),
+            };
+            (Box::new(provider), calls)
+        }
+
+        fn next(&self) -> Result<&'static str> {
+            self.calls.fetch_add(1, Ordering::SeqCst);
+            match self.script.lock().unwrap().pop_front() {
+                Some(Ok(text)) => Ok(text),
+                Some(Err(error)) => Err(anyhow!("{}", error)),
+                None => Ok("done"),
+            }
+        }
+    }
+
+    #[async_trait]
+    impl LLMProvider for ScriptedProvider {
+        async fn complete(&self, _request: CompletionRequest) -> Result<CompletionResponse> {
+            Ok(CompletionResponse {
+                content: self.next()?.to_string(),
+                usage: Usage {
+                    prompt_tokens: 1,
+                    completion_tokens: 1,
+                    total_tokens: 2,
+                },
+                model: "test".to_string(),
+            })
+        }
+
+        async fn stream(&self, _request: CompletionRequest) -> Result<CompletionStream> {
+            let (tx, rx) = mpsc::channel(2);
+            // Errors arrive inside the stream, as when a connection drops
+            let item = self.next().map(|text| CompletionChunk {
+                content: text.to_string(),
+                finished: true,
+                tool_calls: None,
+                usage: None,
+            });
+            tx.send(item).await.unwrap();
+            Ok(ReceiverStream::new(rx))
+        }
+
+        fn name(&self) -> &str {
+            &self.name
+        }
+
+        fn model(&self) -> &str {
+            "test"
+        }
+
+        fn max_tokens(&self) -> u32 {
+            1000
+        }
+
+        fn temperature(&self) -> f32 {
+            0.1
+        }
+    }
+
+    fn request() -> CompletionRequest {
+        CompletionRequest {
+            messages: vec![Message::new(MessageRole::User, "hi".to_string())],
+            max_tokens: None,
+            temperature: None,
+            stream: false,
+            tools: None,
+            disable_thinking: false,
+        }
+    }
+
+    fn failover(
+        providers: Vec<Box<dyn LLMProvider>>,
+    ) -> (FailoverProvider, Arc<Mutex<Vec<String>>>) {
+        let statuses = Arc::new(Mutex::new(Vec::new()));
+        let sink = statuses.clone();
+        let mut provider = FailoverProvider::new(providers)
+            .unwrap()
+            .with_status_callback(Arc::new(move |status: &str| {
+                sink.lock().unwrap().push(status.to_string())
+            }));
+        provider.base_delay = Duration::from_millis(1);
+        (provider, statuses)
+    }
+
+    #[test]
+    fn test_classify() {
+        let kind = |message: &str| classify(&anyhow!("{}", message));
+        assert_eq!(
+            kind("Anthropic API error 529: overloaded"),
+            Failure::Transient
+        );
+        assert_eq!(
+            kind("error sending request: operation timed out"),
+            Failure::Transient
+        );
+        assert_eq!(
+            kind("OpenAI API error 401: invalid api key"),
+            Failure::Provider
+        );
+        assert_eq!(
+            kind("Anthropic API error 400: messages: field required"),
+            Failure::Request
+        );
+
+        let typed = |status: reqwest::StatusCode| {
+            classify(&G3Error::api("Gemini", status, "error with connection pool").into())
+        };
+        assert_eq!(typed(reqwest::StatusCode::BAD_GATEWAY), Failure::Transient);
+        assert_eq!(typed(reqwest::StatusCode::FORBIDDEN), Failure::Provider);
+        assert_eq!(typed(reqwest::StatusCode::NOT_FOUND), Failure::Request);
+    }
+
+    #[tokio::test]
+    async fn test_retries_before_switching() {
+        let (primary, primary_calls) = ScriptedProvider::boxed(
+            "anthropic.default",
+            vec![Err("429 rate limited"), Ok("hello")],
+        );
+        let (backup, backup_calls) = ScriptedProvider::boxed("openai.default", vec![]);
+        let (provider, statuses) = failover(vec![primary, backup]);
+
+        let response = provider.complete(request()).await.unwrap();
+        assert_eq!(response.content, "hello");
+        assert_eq!(primary_calls.load(Ordering::SeqCst), 2);
+        assert_eq!(backup_calls.load(Ordering::SeqCst), 0);
+        assert!(statuses.lock().unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_switches_and_stays_on_backup() {
+        let (primary, primary_calls) = ScriptedProvider::boxed(
+            "anthropic.default",
+            vec![
+                Err("503 unavailable"),
+                Err("503 unavailable"),
+                Err("503 unavailable"),
+            ],
+        );
+        let (backup, _) = ScriptedProvider::boxed("openai.default", vec![Ok("from backup")]);
+        let (provider, statuses) = failover(vec![primary, backup]);
+
+        let response = provider.complete(request()).await.unwrap();
+        assert_eq!(response.content, "from backup");
+        assert_eq!(provider.name(), "openai.default");
+
+        {
+            let statuses = statuses.lock().unwrap();
+            assert_eq!(statuses.len(), 1);
+            assert!(statuses[0].contains("anthropic.default failed (503 unavailable)"));
+            assert!(statuses[0].ends_with("switching to openai.default"));
+        }
+
+        // Later requests go straight to the backup
+        provider.complete(request()).await.unwrap();
+        assert_eq!(primary_calls.load(Ordering::SeqCst), 3);
+    }
+
+    #[tokio::test]
+    async fn test_bad_request_is_not_retried() {
+        let (primary, primary_calls) =
+            ScriptedProvider::boxed("anthropic.default", vec![Err("400 invalid request")]);
+        let (backup, backup_calls) = ScriptedProvider::boxed("openai.default", vec![]);
+        let (provider, _) = failover(vec![primary, backup]);
+
+        assert!(provider.complete(request()).await.is_err());
+        assert_eq!(primary_calls.load(Ordering::SeqCst), 1);
+        assert_eq!(backup_calls.load(Ordering::SeqCst), 0);
+    }
+
+    #[tokio::test]
+    async fn test_stream_fails_over_on_first_chunk_error() {
+        let (primary, _) =
+            ScriptedProvider::boxed("anthropic.default", vec![Err("401 unauthorized")]);
+        let (backup, _) = ScriptedProvider::boxed("openai.default", vec![Ok("streamed")]);
+        let (provider, statuses) = failover(vec![primary, backup]);
+
+        let mut stream = provider.stream(request()).await.unwrap();
+        let chunk = stream.next().await.unwrap().unwrap();
+        assert_eq!(chunk.content, "streamed");
+        assert!(stream.next().await.is_none());
+        assert_eq!(statuses.lock().unwrap().len(), 1);
+    }
+}
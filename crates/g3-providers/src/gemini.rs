@@ -0,0 +1,761 @@
+//! Google Gemini provider (Generative Language API)
+//!
+//! G3's messages map onto Gemini's `contents` as follows:
+//!
+//! - system messages are joined into the request's `systemInstruction`
+//! - assistant messages use the `model` role, and consecutive messages with
+//!   the same role are merged because older models require alternating turns
+//! - tools are sent as function declarations, and `functionCall` parts in the
+//!   response become G3 tool calls (Gemini doesn't assign call ids, so they
+//!   are numbered per response)
+//!
+//! [`context_window`] gives the context size of each model family so the
+//! agent's context percentages are right for Gemini's large windows.
+
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use bytes::Bytes;
+use futures_util::stream::StreamExt;
+use reqwest::Client;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::time::Duration;
+use tokio::sync::mpsc;
+use tokio_stream::wrappers::ReceiverStream;
+use tracing::{debug, error, warn};
+
+use crate::{
+    CompletionChunk, CompletionRequest, CompletionResponse, CompletionStream, G3Error, LLMProvider,
+    Message, MessageRole, Tool, ToolCall, Usage,
+};
+
+pub const GEMINI_BASE_URL: &str = "https://generativelanguage.googleapis.com/v1beta";
+const DEFAULT_MODEL: &str = "gemini-2.5-pro";
+const DEFAULT_MAX_TOKENS: u32 = 8192;
+
+/// Context window of a Gemini model, in tokens
+pub fn context_window(model: &str) -> u32 {
+    let model = model.trim_start_matches("models/");
+    if model.starts_with("gemini-1.5-pro") {
+        2_097_152
+    } else if model.starts_with("gemini-1.0") || model == "gemini-pro" {
+        32_768
+    } else {
+        // gemini-1.5-flash and every 2.x model
+        1_048_576
+    }
+}
+
+#[derive(Clone)]
+pub struct GeminiProvider {
+    client: Client,
+    name: String,
+    api_key: String,
+    model: String,
+    base_url: String,
+    max_tokens: u32,
+    temperature: f32,
+}
+
+impl GeminiProvider {
+    pub fn new_with_name(
+        name: String,
+        api_key: String,
+        model: Option<String>,
+        base_url: Option<String>,
+        max_tokens: Option<u32>,
+        temperature: Option<f32>,
+    ) -> Result<Self> {
+        let client = Client::builder()
+            .timeout(Duration::from_secs(300))
+            .build()
+            .map_err(|e| anyhow!("Failed to create HTTP client: {}", e))?;
+
+        Ok(Self {
+            client,
+            name,
+            api_key,
+            model: model.unwrap_or_else(|| DEFAULT_MODEL.to_string()),
+            base_url: base_url
+                .unwrap_or_else(|| GEMINI_BASE_URL.to_string())
+                .trim_end_matches('/')
+                .to_string(),
+            max_tokens: max_tokens.unwrap_or(DEFAULT_MAX_TOKENS),
+            temperature: temperature.unwrap_or(0.1),
+        })
+    }
+
+    fn endpoint(&self, streaming: bool) -> String {
+        let model = self.model.trim_start_matches("models/");
+        if streaming {
+            format!(
+                "{}/models/{}:streamGenerateContent?alt=sse",
+                self.base_url, model
+            )
+        } else {
+            format!("{}/models/{}:generateContent", self.base_url, model)
+        }
+    }
+
+    fn create_request_body(
+        &self,
+        messages: &[Message],
+        tools: Option<&[Tool]>,
+        max_tokens: u32,
+        temperature: f32,
+    ) -> Result<GeminiRequest> {
+        let (system_instruction, contents) = convert_messages(messages);
+        if contents.is_empty() {
+            return Err(anyhow!("At least one user message is required for Gemini"));
+        }
+
+        Ok(GeminiRequest {
+            contents,
+            system_instruction,
+            tools: tools
+                .filter(|tools| !tools.is_empty())
+                .map(|tools| vec![convert_tools(tools)]),
+            generation_config: GenerationConfig {
+                max_output_tokens: max_tokens,
+                temperature,
+            },
+        })
+    }
+
+    async fn send(&self, body: &GeminiRequest, streaming: bool) -> Result<reqwest::Response> {
+        let response = self
+            .client
+            .post(self.endpoint(streaming))
+            .header("x-goog-api-key", &self.api_key)
+            .json(body)
+            .send()
+            .await
+            .map_err(|e| G3Error::transport("Failed to send request to Gemini API", &e))?;
+
+        let status = response.status();
+        if !status.is_success() {
+            let error_text = response
+                .text()
+                .await
+                .unwrap_or_else(|_| "Unknown error".to_string());
+            return Err(G3Error::api("Gemini", status, error_text).into());
+        }
+        Ok(response)
+    }
+
+    async fn parse_streaming_response(
+        &self,
+        mut stream: impl futures_util::Stream<Item = reqwest::Result<Bytes>> + Unpin,
+        tx: mpsc::Sender<Result<CompletionChunk>>,
+    ) -> Option<Usage> {
+        let mut buffer = String::new();
+        let mut byte_buffer = Vec::new();
+        let mut accumulated_usage: Option<Usage> = None;
+        let mut tool_calls: Vec<ToolCall> = Vec::new();
+
+        while let Some(chunk_result) = stream.next().await {
+            let chunk = match chunk_result {
+                Ok(chunk) => chunk,
+                Err(e) => {
+                    error!("Stream error: {}", e);
+                    let _ = tx.send(Err(anyhow!("Stream error: {}", e))).await;
+                    return accumulated_usage;
+                }
+            };
+
+            // Keep bytes of a character split across chunks for the next one
+            byte_buffer.extend_from_slice(&chunk);
+            match std::str::from_utf8(&byte_buffer) {
+                Ok(s) => {
+                    buffer.push_str(s);
+                    byte_buffer.clear();
+                }
+                Err(e) => {
+                    let valid = e.valid_up_to();
+                    buffer.push_str(std::str::from_utf8(&byte_buffer[..valid]).unwrap_or_default());
+                    byte_buffer.drain(..valid);
+                }
+            }
+
+            while let Some(line_end) = buffer.find('\n') {
+                let line = buffer[..line_end].trim().to_string();
+                buffer.drain(..line_end + 1);
+
+                let Some(data) = line.strip_prefix("data: ") else {
+                    continue;
+                };
+                let response: GeminiResponse = match serde_json::from_str(data) {
+                    Ok(response) => response,
+                    Err(e) => {
+                        debug!(
+                            "Failed to parse Gemini stream event: {} - Data: {}",
+                            e, data
+                        );
+                        continue;
+                    }
+                };
+
+                if let Some(usage) = &response.usage_metadata {
+                    accumulated_usage = Some(usage.to_usage());
+                }
+                if let Err(e) = response.check_blocked() {
+                    let _ = tx.send(Err(e)).await;
+                    return accumulated_usage;
+                }
+
+                let (text, calls) = response.into_parts(tool_calls.len());
+                tool_calls.extend(calls);
+                if !text.is_empty() {
+                    let chunk = CompletionChunk {
+                        content: text,
+                        finished: false,
+                        usage: None,
+                        tool_calls: None,
+                    };
+                    if tx.send(Ok(chunk)).await.is_err() {
+                        debug!("Receiver dropped, stopping stream");
+                        return accumulated_usage;
+                    }
+                }
+            }
+        }
+
+        // Gemini has no end-of-stream event; the connection simply closes
+        let final_chunk = CompletionChunk {
+            content: String::new(),
+            finished: true,
+            usage: accumulated_usage.clone(),
+            tool_calls: if tool_calls.is_empty() {
+                None
+            } else {
+                Some(tool_calls)
+            },
+        };
+        let _ = tx.send(Ok(final_chunk)).await;
+        accumulated_usage
+    }
+}
+
+#[async_trait]
+impl LLMProvider for GeminiProvider {
+    async fn complete(&self, request: CompletionRequest) -> Result<CompletionResponse> {
+        debug!(
+            "Processing Gemini completion request with {} messages",
+            request.messages.len()
+        );
+
+        let body = self.create_request_body(
+            &request.messages,
+            request.tools.as_deref(),
+            request.max_tokens.unwrap_or(self.max_tokens),
+            request.temperature.unwrap_or(self.temperature),
+        )?;
+
+        let gemini_response: GeminiResponse = self
+            .send(&body, false)
+            .await?
+            .json()
+            .await
+            .map_err(|e| anyhow!("Failed to parse Gemini response: {}", e))?;
+        gemini_response.check_blocked()?;
+
+        let usage = gemini_response
+            .usage_metadata
+            .as_ref()
+            .map(UsageMetadata::to_usage)
+            .unwrap_or(Usage {
+                prompt_tokens: 0,
+                completion_tokens: 0,
+                total_tokens: 0,
+            });
+        let model = gemini_response
+            .model_version
+            .clone()
+            .unwrap_or_else(|| self.model.clone());
+        let (content, _) = gemini_response.into_parts(0);
+
+        debug!(
+            "Gemini completion successful: {} tokens generated",
+            usage.completion_tokens
+        );
+
+        Ok(CompletionResponse {
+            content,
+            usage,
+            model,
+        })
+    }
+
+    async fn stream(&self, request: CompletionRequest) -> Result<CompletionStream> {
+        debug!(
+            "Processing Gemini streaming request with {} messages",
+            request.messages.len()
+        );
+
+        let body = self.create_request_body(
+            &request.messages,
+            request.tools.as_deref(),
+            request.max_tokens.unwrap_or(self.max_tokens),
+            request.temperature.unwrap_or(self.temperature),
+        )?;
+
+        let stream = self.send(&body, true).await?.bytes_stream();
+        let (tx, rx) = mpsc::channel(100);
+
+        let provider = self.clone();
+        tokio::spawn(async move {
+            if let Some(usage) = provider.parse_streaming_response(stream, tx).await {
+                debug!(
+                    "Stream completed with usage - prompt: {}, completion: {}, total: {}",
+                    usage.prompt_tokens, usage.completion_tokens, usage.total_tokens
+                );
+            }
+        });
+
+        Ok(ReceiverStream::new(rx))
+    }
+
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn model(&self) -> &str {
+        &self.model
+    }
+
+    fn has_native_tool_calling(&self) -> bool {
+        true
+    }
+
+    fn supports_vision(&self) -> bool {
+        true
+    }
+
+    fn max_tokens(&self) -> u32 {
+        self.max_tokens
+    }
+
+    fn temperature(&self) -> f32 {
+        self.temperature
+    }
+}
+
+/// Split messages into the system instruction and the conversation turns
+fn convert_messages(messages: &[Message]) -> (Option<GeminiContent>, Vec<GeminiContent>) {
+    let mut system = Vec::new();
+    let mut contents: Vec<GeminiContent> = Vec::new();
+
+    for message in messages {
+        let role = match message.role {
+            MessageRole::System => {
+                system.push(message.content.as_str());
+                continue;
+            }
+            MessageRole::User => "user",
+            MessageRole::Assistant => "model",
+        };
+
+        let mut parts = Vec::new();
+        // Gemini rejects empty text parts
+        if !message.content.is_empty() {
+            parts.push(GeminiPart::text(&message.content));
+        }
+        for image in &message.images {
+            parts.push(GeminiPart {
+                inline_data: Some(InlineData {
+                    mime_type: image.media_type.clone(),
+                    data: image.data.clone(),
+                }),
+                ..Default::default()
+            });
+        }
+        if parts.is_empty() {
+            continue;
+        }
+
+        match contents.last_mut() {
+            Some(last) if last.role.as_deref() == Some(role) => last.parts.extend(parts),
+            _ => contents.push(GeminiContent {
+                role: Some(role.to_string()),
+                parts,
+            }),
+        }
+    }
+
+    let system_instruction = (!system.is_empty()).then(|| GeminiContent {
+        role: None,
+        parts: vec![GeminiPart::text(&system.join("\n\n"))],
+    });
+    (system_instruction, contents)
+}
+
+fn convert_tools(tools: &[Tool]) -> GeminiTools {
+    GeminiTools {
+        function_declarations: tools
+            .iter()
+            .map(|tool| FunctionDeclaration {
+                name: tool.name.clone(),
+                description: tool.description.clone(),
+                parameters: convert_schema(&tool.input_schema),
+            })
+            .collect(),
+    }
+}
+
+/// Gemini accepts a subset of JSON Schema: drop the keywords it rejects, and
+/// leave out the parameters of tools that take none (an object schema
+/// without properties is an error)
+fn convert_schema(schema: &Value) -> Option<Value> {
+    fn strip(value: &Value) -> Value {
+        match value {
+            Value::Object(map) => Value::Object(
+                map.iter()
+                    .filter(|(key, _)| !matches!(key.as_str(), "additionalProperties" | "$schema"))
+                    .map(|(key, value)| (key.clone(), strip(value)))
+                    .collect(),
+            ),
+            Value::Array(items) => Value::Array(items.iter().map(strip).collect()),
+            other => other.clone(),
+        }
+    }
+
+    let has_properties = schema["properties"]
+        .as_object()
+        .is_some_and(|properties| !properties.is_empty());
+    if schema["type"] == "object" && !has_properties {
+        return None;
+    }
+    Some(strip(schema))
+}
+
+// Gemini API request/response structures
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct GeminiRequest {
+    contents: Vec<GeminiContent>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    system_instruction: Option<GeminiContent>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    tools: Option<Vec<GeminiTools>>,
+    generation_config: GenerationConfig,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct GeminiContent {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    role: Option<String>,
+    #[serde(default)]
+    parts: Vec<GeminiPart>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct GeminiPart {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    text: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    inline_data: Option<InlineData>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    function_call: Option<FunctionCall>,
+    /// Set on thought summaries of thinking models, which aren't answer text
+    #[serde(default, skip_serializing)]
+    thought: bool,
+}
+
+impl GeminiPart {
+    fn text(text: &str) -> Self {
+        Self {
+            text: Some(text.to_string()),
+            ..Default::default()
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct InlineData {
+    mime_type: String,
+    data: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct FunctionCall {
+    name: String,
+    #[serde(default)]
+    args: Value,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct GeminiTools {
+    function_declarations: Vec<FunctionDeclaration>,
+}
+
+#[derive(Debug, Serialize)]
+struct FunctionDeclaration {
+    name: String,
+    description: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    parameters: Option<Value>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct GenerationConfig {
+    max_output_tokens: u32,
+    temperature: f32,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct GeminiResponse {
+    #[serde(default)]
+    candidates: Vec<Candidate>,
+    usage_metadata: Option<UsageMetadata>,
+    prompt_feedback: Option<PromptFeedback>,
+    model_version: Option<String>,
+}
+
+impl GeminiResponse {
+    /// Fail if Gemini refused the prompt instead of answering
+    fn check_blocked(&self) -> Result<()> {
+        match self
+            .prompt_feedback
+            .as_ref()
+            .and_then(|f| f.block_reason.as_ref())
+        {
+            Some(reason) if self.candidates.is_empty() => {
+                Err(anyhow!("Gemini blocked the prompt: {}", reason))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Text and tool calls of the first candidate; tool call ids continue
+    /// from `first_call` so they stay unique across a stream
+    fn into_parts(self, first_call: usize) -> (String, Vec<ToolCall>) {
+        let mut text = String::new();
+        let mut calls = Vec::new();
+        let Some(candidate) = self.candidates.into_iter().next() else {
+            return (text, calls);
+        };
+
+        if let Some(reason) = candidate.finish_reason.as_deref() {
+            if !matches!(reason, "STOP" | "MAX_TOKENS") {
+                warn!("Gemini stopped generating: {}", reason);
+            }
+        }
+
+        for part in candidate.content.map(|c| c.parts).unwrap_or_default() {
+            if part.thought {
+                continue;
+            }
+            if let Some(part_text) = part.text {
+                text.push_str(&part_text);
+            }
+            if let Some(call) = part.function_call {
+                calls.push(ToolCall {
+                    id: format!("gemini_call_{}", first_call + calls.len()),
+                    tool: call.name,
+                    args: call.args,
+                });
+            }
+        }
+        (text, calls)
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Candidate {
+    content: Option<GeminiContent>,
+    finish_reason: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct PromptFeedback {
+    block_reason: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct UsageMetadata {
+    #[serde(default)]
+    prompt_token_count: u32,
+    #[serde(default)]
+    total_token_count: u32,
+}
+
+impl UsageMetadata {
+    fn to_usage(&self) -> Usage {
+        Usage {
+            prompt_tokens: self.prompt_token_count,
+            // The total also counts thinking tokens, which aren't candidates
+            completion_tokens: self
+                .total_token_count
+                .saturating_sub(self.prompt_token_count),
+            total_tokens: self.total_token_count,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ImageContent;
+    use serde_json::json;
+
+    fn provider() -> GeminiProvider {
+        GeminiProvider::new_with_name(
+            "gemini.default".to_string(),
+            "test-key".to_string(),
+            Some("gemini-2.5-flash".to_string()),
+            None,
+            Some(1000),
+            Some(0.2),
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_context_window() {
+        assert_eq!(context_window("gemini-1.5-pro-002"), 2_097_152);
+        assert_eq!(context_window("models/gemini-2.5-pro"), 1_048_576);
+        assert_eq!(context_window("gemini-2.0-flash"), 1_048_576);
+        assert_eq!(context_window("gemini-1.0-pro"), 32_768);
+    }
+
+    #[test]
+    fn test_endpoints() {
+        let gemini = provider();
+        assert_eq!(
+            gemini.endpoint(false),
+            "https://generativelanguage.googleapis.com/v1beta/models/gemini-2.5-flash:generateContent"
+        );
+        assert!(gemini
+            .endpoint(true)
+            .ends_with("/models/gemini-2.5-flash:streamGenerateContent?alt=sse"));
+    }
+
+    #[test]
+    fn test_request_body() {
+        let mut screenshot = Message::new(MessageRole::User, String::new());
+        screenshot
+            .images
+            .push(ImageContent::new("image/png", "aGk=".to_string()));
+        let messages = vec![
+            Message::new(MessageRole::System, "You are G3.".to_string()),
+            Message::new(MessageRole::System, "Be brief.".to_string()),
+            Message::new(MessageRole::User, "Look at this".to_string()),
+            screenshot,
+            Message::new(MessageRole::Assistant, "A login form.".to_string()),
+        ];
+        let tools = vec![
+            Tool {
+                name: "shell".to_string(),
+                description: "Run a command".to_string(),
+                input_schema: json!({
+                    "type": "object",
+                    "properties": {"command": {"type": "string"}},
+                    "required": ["command"],
+                    "additionalProperties": false
+                }),
+            },
+            Tool {
+                name: "list_windows".to_string(),
+                description: "List windows".to_string(),
+                input_schema: json!({"type": "object", "properties": {}}),
+            },
+        ];
+
+        let body = provider()
+            .create_request_body(&messages, Some(&tools), 1000, 0.2)
+            .unwrap();
+        let body = serde_json::to_value(&body).unwrap();
+
+        assert_eq!(
+            body["systemInstruction"],
+            json!({"parts": [{"text": "You are G3.\n\nBe brief."}]})
+        );
+        // The two user messages become one turn
+        assert_eq!(body["contents"].as_array().unwrap().len(), 2);
+        assert_eq!(body["contents"][0]["role"], "user");
+        assert_eq!(body["contents"][0]["parts"][0]["text"], "Look at this");
+        assert_eq!(
+            body["contents"][0]["parts"][1]["inlineData"]["mimeType"],
+            "image/png"
+        );
+        assert_eq!(body["contents"][1]["role"], "model");
+
+        let declarations = &body["tools"][0]["functionDeclarations"];
+        assert_eq!(declarations[0]["name"], "shell");
+        assert!(declarations[0]["parameters"]
+            .get("additionalProperties")
+            .is_none());
+        assert!(declarations[1].get("parameters").is_none());
+        assert_eq!(body["generationConfig"]["maxOutputTokens"], 1000);
+    }
+
+    #[test]
+    fn test_request_needs_a_turn() {
+        let messages = vec![Message::new(
+            MessageRole::System,
+            "Only a system prompt".to_string(),
+        )];
+        assert!(provider()
+            .create_request_body(&messages, None, 100, 0.1)
+            .is_err());
+    }
+
+    #[tokio::test]
+    async fn test_stream_translates_function_calls() {
+        let events = concat!(
+            "data: {\"candidates\":[{\"content\":{\"role\":\"model\",\"parts\":[",
+            "{\"text\":\"Planning\",\"thought\":true},{\"text\":\"Let me check.\"}]}}]}\r\n\r\n",
+            "data: {\"candidates\":[{\"content\":{\"role\":\"model\",\"parts\":[",
+            "{\"functionCall\":{\"name\":\"shell\",\"args\":{\"command\":\"ls\"}}},",
+            "{\"functionCall\":{\"name\":\"read_file\",\"args\":{\"file_path\":\"a.rs\"}}}",
+            "]},\"finishReason\":\"STOP\"}],",
+            "\"usageMetadata\":{\"promptTokenCount\":120,\"candidatesTokenCount\":30,",
+            "\"thoughtsTokenCount\":10,\"totalTokenCount\":160}}\r\n\r\n",
+        );
+        // Split inside the stream to exercise line buffering
+        let (first, second) = events.split_at(70);
+        let stream = futures_util::stream::iter(vec![
+            Ok(Bytes::from(first.to_string())),
+            Ok(Bytes::from(second.to_string())),
+        ]);
+        let (tx, mut rx) = mpsc::channel(10);
+
+        let usage = provider()
+            .parse_streaming_response(stream, tx)
+            .await
+            .unwrap();
+        assert_eq!(usage.prompt_tokens, 120);
+        assert_eq!(usage.completion_tokens, 40);
+
+        let mut chunks = Vec::new();
+        while let Some(chunk) = rx.recv().await {
+            chunks.push(chunk.unwrap());
+        }
+        assert_eq!(chunks.len(), 2);
+        assert_eq!(chunks[0].content, "Let me check.");
+
+        let last = chunks.last().unwrap();
+        assert!(last.finished);
+        let calls = last.tool_calls.as_ref().unwrap();
+        assert_eq!(calls.len(), 2);
+        assert_eq!(calls[0].tool, "shell");
+        assert_eq!(calls[0].args["command"], "ls");
+        assert_eq!(calls[1].id, "gemini_call_1");
+    }
+
+    #[test]
+    fn test_blocked_prompt() {
+        let response: GeminiResponse =
+            serde_json::from_str(r#"{"promptFeedback":{"blockReason":"SAFETY"}}"#).unwrap();
+        assert!(response.check_blocked().is_err());
+    }
+}
@@ -2,6 +2,7 @@ use anyhow::Result;
 use rand::Rng;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
+use std::sync::Arc;
 
 /// Trait for LLM providers
 #[async_trait::async_trait]
@@ -28,11 +29,22 @@ pub trait LLMProvider: Send + Sync {
         false
     }
 
+    /// Check if the provider accepts images attached to user messages
+    fn supports_vision(&self) -> bool {
+        false
+    }
+
     /// Get the configured max_tokens for this provider
     fn max_tokens(&self) -> u32;
 
     /// Get the configured temperature for this provider
     fn temperature(&self) -> f32;
+
+    /// The tokenizer of this provider's model, if G3 has one; without it
+    /// the context window estimates token counts from text length
+    fn token_counter(&self) -> Option<Arc<dyn TokenCounter>> {
+        None
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -95,7 +107,7 @@ pub struct Message {
     pub cache_control: Option<CacheControl>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 #[serde(rename_all = "lowercase")]
 pub enum MessageRole {
     System,
@@ -201,15 +213,33 @@ pub struct Tool {
 }
 
 pub mod anthropic;
+pub mod bedrock;
+pub mod cache;
 pub mod databricks;
 pub mod embedded;
+pub mod error;
+pub mod failover;
+pub mod gemini;
+pub mod local;
+pub mod mock;
 pub mod oauth;
 pub mod openai;
+pub mod rate_limit;
+pub mod tokenizer;
 
 pub use anthropic::AnthropicProvider;
+pub use bedrock::BedrockProvider;
+pub use cache::CachingProvider;
 pub use databricks::DatabricksProvider;
 pub use embedded::EmbeddedProvider;
+pub use error::{ErrorCategory, G3Error};
+pub use failover::FailoverProvider;
+pub use gemini::GeminiProvider;
+pub use local::LocalProvider;
+pub use mock::MockProvider;
 pub use openai::OpenAIProvider;
+pub use rate_limit::{RateLimit, RateLimitedProvider};
+pub use tokenizer::TokenCounter;
 
 impl Message {
     /// Generate a unique message ID in format HHMMSS-XXX
@@ -291,8 +321,13 @@ impl ProviderRegistry {
     }
 
     pub fn register<P: LLMProvider + 'static>(&mut self, provider: P) {
+        self.register_boxed(Box::new(provider));
+    }
+
+    /// Register a provider taken out of another registry
+    pub fn register_boxed(&mut self, provider: Box<dyn LLMProvider>) {
         let name = provider.name().to_string();
-        self.providers.insert(name.clone(), Box::new(provider));
+        self.providers.insert(name.clone(), provider);
 
         if self.default_provider.is_empty() {
             self.default_provider = name;
@@ -315,6 +350,11 @@ impl ProviderRegistry {
             .ok_or_else(|| anyhow::anyhow!("Provider '{}' not found", name))
     }
 
+    /// Take a provider out of the registry, e.g. to wrap it
+    pub fn remove(&mut self, provider_name: &str) -> Option<Box<dyn LLMProvider>> {
+        self.providers.remove(provider_name)
+    }
+
     pub fn list_providers(&self) -> Vec<&str> {
         self.providers.keys().map(|s| s.as_str()).collect()
     }
@@ -0,0 +1,279 @@
+//! Provider for OpenAI-compatible servers running locally or on the LAN
+//!
+//! Ollama, vLLM, LM Studio and llama.cpp's server all expose the OpenAI chat
+//! completions API, so requests and streaming go through [`OpenAIProvider`].
+//! What differs is handled here:
+//!
+//! - no API key is needed, so none is sent unless configured
+//! - the older `max_tokens` parameter is sent instead of `max_completion_tokens`
+//! - models without tool-calling support can fall back to G3's JSON tool
+//!   calls by disabling native tools
+//! - the server's models can be listed with [`LocalProvider::list_models`]
+//! - token counts come from the model's `tokenizer.json`, when configured
+
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use serde::Deserialize;
+use std::path::Path;
+use std::sync::Arc;
+use tracing::debug;
+
+use crate::openai::OpenAIProvider;
+use crate::tokenizer::HuggingFaceCounter;
+use crate::{CompletionRequest, CompletionResponse, CompletionStream, LLMProvider, TokenCounter};
+
+/// Ollama's OpenAI-compatible endpoint
+pub const DEFAULT_LOCAL_BASE_URL: &str = "http://localhost:11434/v1";
+
+#[derive(Clone)]
+pub struct LocalProvider {
+    inner: OpenAIProvider,
+    native_tools: bool,
+    token_counter: Option<Arc<dyn TokenCounter>>,
+}
+
+impl LocalProvider {
+    pub fn new_with_name(
+        name: String,
+        model: String,
+        base_url: Option<String>,
+        api_key: Option<String>,
+        max_tokens: Option<u32>,
+        temperature: Option<f32>,
+        native_tools: bool,
+    ) -> Result<Self> {
+        let base_url = base_url
+            .unwrap_or_else(|| DEFAULT_LOCAL_BASE_URL.to_string())
+            .trim_end_matches('/')
+            .to_string();
+        let inner = OpenAIProvider::new_with_name(
+            name,
+            api_key.unwrap_or_default(),
+            Some(model),
+            Some(base_url),
+            max_tokens,
+            temperature,
+        )?
+        .with_legacy_max_tokens();
+
+        Ok(Self {
+            inner,
+            native_tools,
+            token_counter: None,
+        })
+    }
+
+    /// Count tokens with the Hugging Face `tokenizer.json` at `path`
+    pub fn with_tokenizer(mut self, path: &Path) -> Result<Self> {
+        self.token_counter = Some(Arc::new(HuggingFaceCounter::from_file(path)?));
+        Ok(self)
+    }
+
+    /// Ids of the models the server can run
+    pub async fn list_models(&self) -> Result<Vec<String>> {
+        let url = format!("{}/models", self.inner.base_url());
+        debug!("Listing models from {}", url);
+
+        let response = self
+            .inner
+            .authorize(self.inner.client().get(&url))
+            .send()
+            .await
+            .with_context(|| format!("Could not reach {}", url))?;
+
+        let status = response.status();
+        if !status.is_success() {
+            let error_text = response
+                .text()
+                .await
+                .unwrap_or_else(|_| "Unknown error".to_string());
+            anyhow::bail!("Model listing failed with {}: {}", status, error_text);
+        }
+
+        parse_model_list(&response.text().await?)
+    }
+
+    /// Drop tool definitions when the model can't use them natively; G3 then
+    /// describes the tools in the system prompt instead
+    fn prepare(&self, mut request: CompletionRequest) -> CompletionRequest {
+        if !self.native_tools {
+            request.tools = None;
+        }
+        request
+    }
+}
+
+#[async_trait]
+impl LLMProvider for LocalProvider {
+    async fn complete(&self, request: CompletionRequest) -> Result<CompletionResponse> {
+        self.inner.complete(self.prepare(request)).await
+    }
+
+    async fn stream(&self, request: CompletionRequest) -> Result<CompletionStream> {
+        self.inner.stream(self.prepare(request)).await
+    }
+
+    fn name(&self) -> &str {
+        self.inner.name()
+    }
+
+    fn model(&self) -> &str {
+        self.inner.model()
+    }
+
+    fn has_native_tool_calling(&self) -> bool {
+        self.native_tools
+    }
+
+    fn max_tokens(&self) -> u32 {
+        self.inner.max_tokens()
+    }
+
+    fn temperature(&self) -> f32 {
+        self.inner.temperature()
+    }
+
+    fn token_counter(&self) -> Option<Arc<dyn TokenCounter>> {
+        self.token_counter
+            .clone()
+            .or_else(|| self.inner.token_counter())
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct ModelList {
+    data: Vec<ModelEntry>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ModelEntry {
+    id: String,
+}
+
+fn parse_model_list(body: &str) -> Result<Vec<String>> {
+    let list: ModelList =
+        serde_json::from_str(body).context("Unexpected response from the models endpoint")?;
+    Ok(list.data.into_iter().map(|model| model.id).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Message, MessageRole, Tool};
+    use bytes::Bytes;
+    use serde_json::json;
+    use tokio::sync::mpsc;
+
+    fn provider(native_tools: bool) -> LocalProvider {
+        LocalProvider::new_with_name(
+            "local.default".to_string(),
+            "qwen2.5-coder:14b".to_string(),
+            Some("http://localhost:8000/v1/".to_string()),
+            None,
+            Some(2048),
+            None,
+            native_tools,
+        )
+        .unwrap()
+    }
+
+    fn request() -> CompletionRequest {
+        CompletionRequest {
+            messages: vec![Message::new(MessageRole::User, "hi".to_string())],
+            max_tokens: None,
+            temperature: None,
+            stream: true,
+            tools: Some(vec![Tool {
+                name: "shell".to_string(),
+                description: "Run a command".to_string(),
+                input_schema: json!({"type": "object"}),
+            }]),
+            disable_thinking: false,
+        }
+    }
+
+    #[test]
+    fn test_defaults() {
+        let local = provider(true);
+        assert_eq!(local.name(), "local.default");
+        assert_eq!(local.inner.base_url(), "http://localhost:8000/v1");
+        assert!(local.has_native_tool_calling());
+
+        let ollama = LocalProvider::new_with_name(
+            "local.ollama".to_string(),
+            "llama3.1".to_string(),
+            None,
+            None,
+            None,
+            None,
+            true,
+        )
+        .unwrap();
+        assert_eq!(ollama.inner.base_url(), DEFAULT_LOCAL_BASE_URL);
+    }
+
+    #[test]
+    fn test_request_body() {
+        let local = provider(true);
+        let prepared = local.prepare(request());
+        let body = local.inner.create_request_body(
+            &prepared.messages,
+            prepared.tools.as_deref(),
+            true,
+            None,
+            None,
+        );
+        assert_eq!(body["max_tokens"], 2048);
+        assert!(body.get("max_completion_tokens").is_none());
+        assert_eq!(body["tools"][0]["function"]["name"], "shell");
+
+        let without_tools = provider(false);
+        assert!(!without_tools.has_native_tool_calling());
+        assert!(without_tools.prepare(request()).tools.is_none());
+    }
+
+    #[test]
+    fn test_parse_model_list() {
+        let body = r#"{"object":"list","data":[
+            {"id":"llama3.1:8b","object":"model","owned_by":"library"},
+            {"id":"qwen2.5-coder:14b","object":"model","owned_by":"library"}
+        ]}"#;
+        assert_eq!(
+            parse_model_list(body).unwrap(),
+            vec!["llama3.1:8b", "qwen2.5-coder:14b"]
+        );
+        assert!(parse_model_list("<html>").is_err());
+    }
+
+    #[tokio::test]
+    async fn test_stream_translates_unindexed_tool_calls() {
+        // Two whole calls without index or id, as sent by older Ollama versions
+        let events = concat!(
+            "data: {\"choices\":[{\"delta\":{\"content\":\"\",\"tool_calls\":[",
+            "{\"function\":{\"name\":\"shell\",\"arguments\":\"{\\\"command\\\":\\\"ls\\\"}\"}},",
+            "{\"function\":{\"name\":\"read_file\",\"arguments\":\"{\\\"file_path\\\":\\\"a.rs\\\"}\"}}",
+            "]}}]}\n\n",
+            "data: [DONE]\n\n",
+        );
+        let stream = futures_util::stream::iter(vec![Ok(Bytes::from(events))]);
+        let (tx, mut rx) = mpsc::channel(10);
+
+        provider(true)
+            .inner
+            .parse_streaming_response(stream, tx)
+            .await;
+
+        let mut last = None;
+        while let Some(chunk) = rx.recv().await {
+            last = Some(chunk.unwrap());
+        }
+        let chunk = last.unwrap();
+        assert!(chunk.finished);
+        let calls = chunk.tool_calls.unwrap();
+        assert_eq!(calls.len(), 2);
+        assert_eq!(calls[0].tool, "shell");
+        assert_eq!(calls[0].args["command"], "ls");
+        assert_eq!(calls[1].id, "call_1");
+        assert_eq!(calls[1].args["file_path"], "a.rs");
+    }
+}
@@ -0,0 +1,309 @@
+//! Deterministic provider for end-to-end tests, answering from fixture files
+//!
+//! [`MockProvider`] never touches the network. Each request is hashed (see
+//! [`request_hash`]) and answered with the [`Fixture`] stored as
+//! `<hash>.json` in the provider's fixture directory, so the agent loop, the
+//! planner and flock runs can be driven through whole tasks in CI without
+//! API keys, getting the same responses every time.
+//!
+//! A request with no fixture fails with an error naming the file to write,
+//! and the request itself is saved next to it as `<hash>.request.json` to
+//! write the fixture against.
+
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::path::{Path, PathBuf};
+use tokio::sync::mpsc;
+use tracing::debug;
+
+use crate::{
+    CompletionChunk, CompletionRequest, CompletionResponse, CompletionStream, LLMProvider,
+    ToolCall, Usage,
+};
+
+/// Model name reported when none is configured
+pub const DEFAULT_MOCK_MODEL: &str = "mock";
+
+const DEFAULT_MAX_TOKENS: u32 = 8192;
+
+/// A canned response
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Fixture {
+    #[serde(default)]
+    pub content: String,
+    /// Native tool calls, returned by streaming requests
+    #[serde(default)]
+    pub tool_calls: Vec<ToolCall>,
+    /// Defaults to an estimate from the lengths of the request and content
+    #[serde(default)]
+    pub usage: Option<Usage>,
+}
+
+pub struct MockProvider {
+    name: String,
+    model: String,
+    fixtures_dir: PathBuf,
+    max_tokens: u32,
+    temperature: f32,
+    native_tools: bool,
+}
+
+impl MockProvider {
+    pub fn new_with_name(
+        name: String,
+        fixtures_dir: impl Into<PathBuf>,
+        model: Option<String>,
+        max_tokens: Option<u32>,
+        temperature: Option<f32>,
+        native_tools: bool,
+    ) -> Self {
+        Self {
+            name,
+            model: model.unwrap_or_else(|| DEFAULT_MOCK_MODEL.to_string()),
+            fixtures_dir: fixtures_dir.into(),
+            max_tokens: max_tokens.unwrap_or(DEFAULT_MAX_TOKENS),
+            temperature: temperature.unwrap_or(0.0),
+            native_tools,
+        }
+    }
+
+    /// The fixture answering `request`, and its usage
+    fn fixture(&self, request: &CompletionRequest) -> Result<(Fixture, Usage)> {
+        let hash = request_hash(request);
+        let path = self.fixtures_dir.join(format!("{}.json", hash));
+        if !path.exists() {
+            let request_path = self.fixtures_dir.join(format!("{}.request.json", hash));
+            if let Err(e) = save_request(&request_path, request) {
+                debug!("Failed to save unmatched request {}: {}", hash, e);
+            }
+            anyhow::bail!(
+                "No mock fixture for request {}: write its response to {} (the request is in {})",
+                hash,
+                path.display(),
+                request_path.display()
+            );
+        }
+
+        debug!("Mock fixture hit: {}", hash);
+        let contents = std::fs::read_to_string(&path)
+            .with_context(|| format!("Failed to read mock fixture {}", path.display()))?;
+        let fixture: Fixture = serde_json::from_str(&contents)
+            .with_context(|| format!("Invalid mock fixture {}", path.display()))?;
+        let usage = fixture
+            .usage
+            .clone()
+            .unwrap_or_else(|| estimate_usage(request, &fixture.content));
+        Ok((fixture, usage))
+    }
+}
+
+/// Hash of what a response depends on: the messages, in order, and the
+/// names of the tools offered. Sampling settings are left out, so the same
+/// fixtures serve any max_tokens or temperature.
+pub fn request_hash(request: &CompletionRequest) -> String {
+    let mut hasher = Sha256::new();
+    // Length-prefix each field so adjacent fields can't run together
+    let mut field = |value: &[u8]| {
+        hasher.update((value.len() as u64).to_be_bytes());
+        hasher.update(value);
+    };
+    for message in &request.messages {
+        field(format!("{:?}", message.role).as_bytes());
+        field(message.content.as_bytes());
+        for image in &message.images {
+            field(image.data.as_bytes());
+        }
+    }
+    for tool in request.tools.iter().flatten() {
+        field(tool.name.as_bytes());
+    }
+    hasher
+        .finalize()
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect()
+}
+
+fn save_request(path: &Path, request: &CompletionRequest) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    std::fs::write(path, serde_json::to_string_pretty(request)?)?;
+    Ok(())
+}
+
+/// Roughly four characters per token, like the agent's own estimates
+fn estimate_usage(request: &CompletionRequest, content: &str) -> Usage {
+    let prompt_chars: usize = request.messages.iter().map(|m| m.content.len()).sum();
+    let prompt_tokens = (prompt_chars / 4) as u32;
+    let completion_tokens = (content.len() / 4) as u32;
+    Usage {
+        prompt_tokens,
+        completion_tokens,
+        total_tokens: prompt_tokens + completion_tokens,
+    }
+}
+
+#[async_trait]
+impl LLMProvider for MockProvider {
+    async fn complete(&self, request: CompletionRequest) -> Result<CompletionResponse> {
+        let (fixture, usage) = self.fixture(&request)?;
+        Ok(CompletionResponse {
+            content: fixture.content,
+            usage,
+            model: self.model.clone(),
+        })
+    }
+
+    async fn stream(&self, request: CompletionRequest) -> Result<CompletionStream> {
+        let (fixture, usage) = self.fixture(&request)?;
+        let (tx, rx) = mpsc::channel(2);
+        let chunks = [
+            CompletionChunk {
+                content: fixture.content,
+                finished: false,
+                tool_calls: None,
+                usage: None,
+            },
+            CompletionChunk {
+                content: String::new(),
+                finished: true,
+                tool_calls: (!fixture.tool_calls.is_empty()).then_some(fixture.tool_calls),
+                usage: Some(usage),
+            },
+        ];
+        for chunk in chunks {
+            // The channel holds both chunks, so this never waits
+            let _ = tx.send(Ok(chunk)).await;
+        }
+        Ok(tokio_stream::wrappers::ReceiverStream::new(rx))
+    }
+
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn model(&self) -> &str {
+        &self.model
+    }
+
+    fn has_native_tool_calling(&self) -> bool {
+        self.native_tools
+    }
+
+    fn max_tokens(&self) -> u32 {
+        self.max_tokens
+    }
+
+    fn temperature(&self) -> f32 {
+        self.temperature
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Message, MessageRole, Tool};
+    use tempfile::TempDir;
+    use tokio_stream::StreamExt;
+
+    fn request(task: &str) -> CompletionRequest {
+        CompletionRequest {
+            messages: vec![
+                Message::new(MessageRole::System, "You are G3".to_string()),
+                Message::new(MessageRole::User, task.to_string()),
+            ],
+            max_tokens: Some(1000),
+            temperature: Some(0.2),
+            stream: true,
+            tools: None,
+            disable_thinking: false,
+        }
+    }
+
+    fn provider(dir: &TempDir) -> MockProvider {
+        MockProvider::new_with_name(
+            "mock.default".to_string(),
+            dir.path(),
+            None,
+            None,
+            None,
+            true,
+        )
+    }
+
+    #[test]
+    fn test_request_hash() {
+        let hash = request_hash(&request("fix the parser"));
+        assert_eq!(hash.len(), 64);
+        let mut other_settings = request("fix the parser");
+        other_settings.max_tokens = None;
+        other_settings.temperature = Some(0.0);
+        assert_eq!(request_hash(&other_settings), hash);
+        assert_ne!(request_hash(&request("fix the lexer")), hash);
+
+        let mut with_tools = request("fix the parser");
+        with_tools.tools = Some(vec![Tool {
+            name: "shell".to_string(),
+            description: String::new(),
+            input_schema: serde_json::json!({}),
+        }]);
+        assert_ne!(request_hash(&with_tools), hash);
+    }
+
+    #[tokio::test]
+    async fn test_answers_from_fixture() {
+        let dir = TempDir::new().unwrap();
+        let provider = provider(&dir);
+        let fixture = Fixture {
+            content: "Reading the parser.".to_string(),
+            tool_calls: vec![ToolCall {
+                id: "call_1".to_string(),
+                tool: "read_file".to_string(),
+                args: serde_json::json!({"file_path": "src/parser.rs"}),
+            }],
+            usage: None,
+        };
+        let hash = request_hash(&request("fix the parser"));
+        std::fs::write(
+            dir.path().join(format!("{}.json", hash)),
+            serde_json::to_string(&fixture).unwrap(),
+        )
+        .unwrap();
+
+        let response = provider.complete(request("fix the parser")).await.unwrap();
+        assert_eq!(response.content, "Reading the parser.");
+        assert_eq!(response.model, DEFAULT_MOCK_MODEL);
+        assert!(response.usage.total_tokens > 0);
+
+        let chunks: Vec<CompletionChunk> = provider
+            .stream(request("fix the parser"))
+            .await
+            .unwrap()
+            .map(|chunk| chunk.unwrap())
+            .collect()
+            .await;
+        assert_eq!(chunks.len(), 2);
+        assert_eq!(chunks[0].content, "Reading the parser.");
+        assert!(chunks[1].finished);
+        assert_eq!(chunks[1].tool_calls.as_ref().unwrap()[0].tool, "read_file");
+    }
+
+    #[tokio::test]
+    async fn test_missing_fixture_saves_request() {
+        let dir = TempDir::new().unwrap();
+        let provider = provider(&dir);
+        let hash = request_hash(&request("fix the lexer"));
+
+        let error = provider
+            .complete(request("fix the lexer"))
+            .await
+            .unwrap_err()
+            .to_string();
+        assert!(error.contains(&format!("{}.json", hash)));
+        let saved = std::fs::read_to_string(dir.path().join(format!("{}.request.json", hash)));
+        assert!(saved.unwrap().contains("fix the lexer"));
+    }
+}
@@ -5,13 +5,15 @@ use futures_util::stream::StreamExt;
 use reqwest::Client;
 use serde::Deserialize;
 use serde_json::json;
+use std::sync::Arc;
 use tokio::sync::mpsc;
 use tokio_stream::wrappers::ReceiverStream;
 use tracing::{debug, error};
 
+use crate::tokenizer::{self, TokenCounter};
 use crate::{
-    CompletionChunk, CompletionRequest, CompletionResponse, CompletionStream, LLMProvider, Message,
-    MessageRole, Tool, ToolCall, Usage,
+    CompletionChunk, CompletionRequest, CompletionResponse, CompletionStream, G3Error, LLMProvider,
+    Message, MessageRole, Tool, ToolCall, Usage,
 };
 
 #[derive(Clone)]
@@ -23,6 +25,9 @@ pub struct OpenAIProvider {
     max_tokens: Option<u32>,
     _temperature: Option<f32>,
     name: String,
+    /// Send `max_tokens` instead of `max_completion_tokens`, for servers that
+    /// only implement the older parameter
+    legacy_max_tokens: bool,
 }
 
 impl OpenAIProvider {
@@ -59,10 +64,33 @@ impl OpenAIProvider {
             max_tokens,
             _temperature: temperature,
             name,
+            legacy_max_tokens: false,
         })
     }
 
-    fn create_request_body(
+    pub(crate) fn with_legacy_max_tokens(mut self) -> Self {
+        self.legacy_max_tokens = true;
+        self
+    }
+
+    pub(crate) fn base_url(&self) -> &str {
+        &self.base_url
+    }
+
+    /// Add the bearer token, unless there is none (local servers without auth)
+    pub(crate) fn authorize(&self, request: reqwest::RequestBuilder) -> reqwest::RequestBuilder {
+        if self.api_key.is_empty() {
+            request
+        } else {
+            request.header("Authorization", format!("Bearer {}", self.api_key))
+        }
+    }
+
+    pub(crate) fn client(&self) -> &Client {
+        &self.client
+    }
+
+    pub(crate) fn create_request_body(
         &self,
         messages: &[Message],
         tools: Option<&[Tool]>,
@@ -77,7 +105,12 @@ impl OpenAIProvider {
         });
 
         if let Some(max_tokens) = max_tokens.or(self.max_tokens) {
-            body["max_completion_tokens"] = json!(max_tokens);
+            let param = if self.legacy_max_tokens {
+                "max_tokens"
+            } else {
+                "max_completion_tokens"
+            };
+            body[param] = json!(max_tokens);
         }
 
         // OpenAI calls with temp setting seem to fail, so don't send one.
@@ -100,7 +133,7 @@ impl OpenAIProvider {
         body
     }
 
-    async fn parse_streaming_response(
+    pub(crate) async fn parse_streaming_response(
         &self,
         mut stream: impl futures_util::Stream<Item = reqwest::Result<Bytes>> + Unpin,
         tx: mpsc::Sender<Result<CompletionChunk>>,
@@ -146,7 +179,8 @@ impl OpenAIProvider {
                                         Some(
                                             current_tool_calls
                                                 .iter()
-                                                .filter_map(|tc| tc.to_tool_call())
+                                                .enumerate()
+                                                .filter_map(|(i, tc)| tc.to_tool_call(i))
                                                 .collect(),
                                         )
                                     };
@@ -186,30 +220,36 @@ impl OpenAIProvider {
                                         // Handle tool calls
                                         if let Some(delta_tool_calls) = &choice.delta.tool_calls {
                                             for delta_tool_call in delta_tool_calls {
-                                                if let Some(index) = delta_tool_call.index {
-                                                    // Ensure we have enough tool calls in our vector
-                                                    while current_tool_calls.len() <= index {
+                                                // Calls without an index (e.g. from older
+                                                // Ollama versions) arrive whole, one per entry
+                                                let index = match delta_tool_call.index {
+                                                    Some(index) => index,
+                                                    None => {
                                                         current_tool_calls.push(
                                                             OpenAIStreamingToolCall::default(),
                                                         );
+                                                        current_tool_calls.len() - 1
                                                     }
+                                                };
 
-                                                    let tool_call = &mut current_tool_calls[index];
+                                                // Ensure we have enough tool calls in our vector
+                                                while current_tool_calls.len() <= index {
+                                                    current_tool_calls
+                                                        .push(OpenAIStreamingToolCall::default());
+                                                }
 
-                                                    if let Some(id) = &delta_tool_call.id {
-                                                        tool_call.id = Some(id.clone());
-                                                    }
+                                                let tool_call = &mut current_tool_calls[index];
 
-                                                    if let Some(function) =
-                                                        &delta_tool_call.function
-                                                    {
-                                                        if let Some(name) = &function.name {
-                                                            tool_call.name = Some(name.clone());
-                                                        }
-                                                        if let Some(arguments) = &function.arguments
-                                                        {
-                                                            tool_call.arguments.push_str(arguments);
-                                                        }
+                                                if let Some(id) = &delta_tool_call.id {
+                                                    tool_call.id = Some(id.clone());
+                                                }
+
+                                                if let Some(function) = &delta_tool_call.function {
+                                                    if let Some(name) = &function.name {
+                                                        tool_call.name = Some(name.clone());
+                                                    }
+                                                    if let Some(arguments) = &function.arguments {
+                                                        tool_call.arguments.push_str(arguments);
                                                     }
                                                 }
                                             }
@@ -247,7 +287,8 @@ impl OpenAIProvider {
             Some(
                 current_tool_calls
                     .iter()
-                    .filter_map(|tc| tc.to_tool_call())
+                    .enumerate()
+                    .filter_map(|(i, tc)| tc.to_tool_call(i))
                     .collect(),
             )
         };
@@ -282,13 +323,15 @@ impl LLMProvider for OpenAIProvider {
 
         debug!("Sending request to OpenAI API: model={}", self.model);
 
-        let response = self
+        let request = self
             .client
-            .post(format!("{}/chat/completions", self.base_url))
-            .header("Authorization", format!("Bearer {}", self.api_key))
+            .post(format!("{}/chat/completions", self.base_url));
+        let response = self
+            .authorize(request)
             .json(&body)
             .send()
-            .await?;
+            .await
+            .map_err(|e| G3Error::transport("Failed to send request to OpenAI API", &e))?;
 
         let status = response.status();
         if !status.is_success() {
@@ -296,11 +339,7 @@ impl LLMProvider for OpenAIProvider {
                 .text()
                 .await
                 .unwrap_or_else(|_| "Unknown error".to_string());
-            return Err(anyhow::anyhow!(
-                "OpenAI API error {}: {}",
-                status,
-                error_text
-            ));
+            return Err(G3Error::api("OpenAI", status, error_text).into());
         }
 
         let openai_response: OpenAIResponse = response.json().await?;
@@ -348,13 +387,15 @@ impl LLMProvider for OpenAIProvider {
             self.model
         );
 
-        let response = self
+        let request = self
             .client
-            .post(format!("{}/chat/completions", self.base_url))
-            .header("Authorization", format!("Bearer {}", self.api_key))
+            .post(format!("{}/chat/completions", self.base_url));
+        let response = self
+            .authorize(request)
             .json(&body)
             .send()
-            .await?;
+            .await
+            .map_err(|e| G3Error::transport("Failed to send request to OpenAI API", &e))?;
 
         let status = response.status();
         if !status.is_success() {
@@ -362,11 +403,7 @@ impl LLMProvider for OpenAIProvider {
                 .text()
                 .await
                 .unwrap_or_else(|_| "Unknown error".to_string());
-            return Err(anyhow::anyhow!(
-                "OpenAI API error {}: {}",
-                status,
-                error_text
-            ));
+            return Err(G3Error::api("OpenAI", status, error_text).into());
         }
 
         let stream = response.bytes_stream();
@@ -401,6 +438,10 @@ impl LLMProvider for OpenAIProvider {
         true
     }
 
+    fn supports_vision(&self) -> bool {
+        true
+    }
+
     fn max_tokens(&self) -> u32 {
         self.max_tokens.unwrap_or(16000)
     }
@@ -408,20 +449,35 @@ impl LLMProvider for OpenAIProvider {
     fn temperature(&self) -> f32 {
         self._temperature.unwrap_or(0.1)
     }
+
+    fn token_counter(&self) -> Option<Arc<dyn TokenCounter>> {
+        tokenizer::tiktoken_for_model(&self.model)
+    }
 }
 
 fn convert_messages(messages: &[Message]) -> Vec<serde_json::Value> {
     messages
         .iter()
         .map(|msg| {
-            json!({
-                "role": match msg.role {
-                    MessageRole::System => "system",
-                    MessageRole::User => "user",
-                    MessageRole::Assistant => "assistant",
-                },
-                "content": msg.content,
-            })
+            let role = match msg.role {
+                MessageRole::System => "system",
+                MessageRole::User => "user",
+                MessageRole::Assistant => "assistant",
+            };
+            // Only user messages may carry images, as content parts
+            if msg.images.is_empty() || !matches!(msg.role, MessageRole::User) {
+                return json!({ "role": role, "content": msg.content });
+            }
+            let mut parts = vec![json!({ "type": "text", "text": msg.content })];
+            for image in &msg.images {
+                parts.push(json!({
+                    "type": "image_url",
+                    "image_url": {
+                        "url": format!("data:{};base64,{}", image.media_type, image.data),
+                    },
+                }));
+            }
+            json!({ "role": role, "content": parts })
         })
         .collect()
 }
@@ -485,14 +541,14 @@ struct OpenAIStreamingToolCall {
 }
 
 impl OpenAIStreamingToolCall {
-    fn to_tool_call(&self) -> Option<ToolCall> {
-        let id = self.id.as_ref()?;
+    fn to_tool_call(&self, index: usize) -> Option<ToolCall> {
         let name = self.name.as_ref()?;
 
         let args = serde_json::from_str(&self.arguments).unwrap_or(serde_json::Value::Null);
 
         Some(ToolCall {
-            id: id.clone(),
+            // Not every OpenAI-compatible server assigns call ids
+            id: self.id.clone().unwrap_or_else(|| format!("call_{}", index)),
             tool: name.clone(),
             args,
         })
@@ -537,3 +593,25 @@ struct OpenAIDeltaFunction {
     name: Option<String>,
     arguments: Option<String>,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ImageContent;
+
+    #[test]
+    fn test_images_become_content_parts() {
+        let mut message = Message::new(MessageRole::User, "Tool result: saved".to_string());
+        message
+            .images
+            .push(ImageContent::new("image/png", "aGk=".to_string()));
+        let plain = Message::new(MessageRole::Assistant, "Done".to_string());
+
+        let converted = convert_messages(&[message, plain]);
+        let parts = converted[0]["content"].as_array().unwrap();
+        assert_eq!(parts[0]["text"], "Tool result: saved");
+        assert_eq!(parts[1]["type"], "image_url");
+        assert_eq!(parts[1]["image_url"]["url"], "data:image/png;base64,aGk=");
+        assert_eq!(converted[1]["content"], "Done");
+    }
+}
@@ -0,0 +1,387 @@
+//! Client-side rate limiting per provider
+//!
+//! [`RateLimitedProvider`] wraps a provider with token buckets for its
+//! requests per minute and tokens per minute. A call that would exceed either
+//! quota waits until the buckets have refilled instead of being sent and
+//! rejected with a 429; concurrent callers queue in arrival order. Whenever a
+//! call has to wait, the status callback reports for how long and how many
+//! calls are queued, so the UI can show that the provider is being throttled.
+//!
+//! Token use isn't known until the response arrives, so each call is charged
+//! an estimate of its prompt up front and the difference is settled once the
+//! provider reports its usage.
+
+use anyhow::Result;
+use async_trait::async_trait;
+use futures_util::stream::StreamExt;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::sync::mpsc;
+use tokio_stream::wrappers::ReceiverStream;
+use tracing::{debug, info};
+
+use crate::failover::StatusCallback;
+use crate::{CompletionRequest, CompletionResponse, CompletionStream, LLMProvider, TokenCounter};
+
+/// Quotas of one provider; `None` leaves that dimension unlimited
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct RateLimit {
+    pub requests_per_minute: Option<u32>,
+    pub tokens_per_minute: Option<u32>,
+}
+
+const WINDOW: Duration = Duration::from_secs(60);
+
+/// Holds up to one window's quota and refills continuously
+#[derive(Debug)]
+struct TokenBucket {
+    capacity: f64,
+    available: f64,
+    per_second: f64,
+    updated: Instant,
+}
+
+impl TokenBucket {
+    fn new(limit: u32, window: Duration, now: Instant) -> Self {
+        let capacity = limit.max(1) as f64;
+        Self {
+            capacity,
+            available: capacity,
+            per_second: capacity / window.as_secs_f64(),
+            updated: now,
+        }
+    }
+
+    fn refill(&mut self, now: Instant) {
+        let elapsed = now.saturating_duration_since(self.updated).as_secs_f64();
+        self.available = (self.available + elapsed * self.per_second).min(self.capacity);
+        self.updated = now;
+    }
+
+    /// How long until `amount` is available. An amount larger than the
+    /// bucket only waits for a full bucket, or it could never be sent.
+    fn wait_for(&mut self, amount: f64, now: Instant) -> Duration {
+        self.refill(now);
+        let missing = amount.min(self.capacity) - self.available;
+        if missing <= 0.0 {
+            Duration::ZERO
+        } else {
+            Duration::from_secs_f64(missing / self.per_second)
+        }
+    }
+
+    /// Charge `amount`; a negative amount refunds an overestimate. The
+    /// balance may go below zero, which later calls wait off.
+    fn charge(&mut self, amount: f64) {
+        self.available = (self.available - amount).min(self.capacity);
+    }
+}
+
+#[derive(Debug)]
+struct Buckets {
+    requests: Option<TokenBucket>,
+    tokens: Option<TokenBucket>,
+}
+
+/// Token buckets for one provider, shared by all its calls
+#[derive(Debug)]
+pub struct RateLimiter {
+    buckets: Mutex<Buckets>,
+    /// Held while a call waits for its quota, so calls go in arrival order
+    turn: tokio::sync::Mutex<()>,
+    queued: AtomicUsize,
+}
+
+/// Counts a call as queued until it is dropped, even if it's cancelled
+struct QueueSlot<'a>(&'a AtomicUsize);
+
+impl<'a> QueueSlot<'a> {
+    fn new(queued: &'a AtomicUsize) -> Self {
+        queued.fetch_add(1, Ordering::SeqCst);
+        Self(queued)
+    }
+}
+
+impl Drop for QueueSlot<'_> {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+impl RateLimiter {
+    pub fn new(limit: RateLimit) -> Self {
+        Self::with_window(limit, WINDOW)
+    }
+
+    fn with_window(limit: RateLimit, window: Duration) -> Self {
+        let now = Instant::now();
+        Self {
+            buckets: Mutex::new(Buckets {
+                requests: limit
+                    .requests_per_minute
+                    .map(|limit| TokenBucket::new(limit, window, now)),
+                tokens: limit
+                    .tokens_per_minute
+                    .map(|limit| TokenBucket::new(limit, window, now)),
+            }),
+            turn: tokio::sync::Mutex::new(()),
+            queued: AtomicUsize::new(0),
+        }
+    }
+
+    /// Calls waiting for their quota
+    pub fn queued(&self) -> usize {
+        self.queued.load(Ordering::SeqCst)
+    }
+
+    /// Wait until a call estimated at `tokens` fits both quotas, then charge
+    /// it. `on_wait` is told each delay and the current queue depth.
+    pub async fn acquire(&self, tokens: u32, on_wait: impl Fn(Duration, usize)) {
+        let _slot = QueueSlot::new(&self.queued);
+        let _turn = self.turn.lock().await;
+        loop {
+            let delay = {
+                let mut buckets = self.buckets.lock().unwrap_or_else(|e| e.into_inner());
+                let now = Instant::now();
+                let request_delay = buckets
+                    .requests
+                    .as_mut()
+                    .map_or(Duration::ZERO, |bucket| bucket.wait_for(1.0, now));
+                let token_delay = buckets
+                    .tokens
+                    .as_mut()
+                    .map_or(Duration::ZERO, |bucket| bucket.wait_for(tokens as f64, now));
+                let delay = request_delay.max(token_delay);
+                if delay.is_zero() {
+                    if let Some(bucket) = buckets.requests.as_mut() {
+                        bucket.charge(1.0);
+                    }
+                    if let Some(bucket) = buckets.tokens.as_mut() {
+                        bucket.charge(tokens as f64);
+                    }
+                }
+                delay
+            };
+            if delay.is_zero() {
+                return;
+            }
+            on_wait(delay, self.queued());
+            tokio::time::sleep(delay).await;
+        }
+    }
+
+    /// Correct the token charge of a finished call from its estimate to
+    /// what the provider reported
+    pub fn settle(&self, estimated: u32, actual: u32) {
+        let mut buckets = self.buckets.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(bucket) = buckets.tokens.as_mut() {
+            bucket.charge(actual as f64 - estimated as f64);
+        }
+    }
+}
+
+/// Rough prompt size of a request, charged before its usage is known
+pub fn estimate_tokens(request: &CompletionRequest) -> u32 {
+    let chars: usize = request
+        .messages
+        .iter()
+        .map(|message| message.content.len())
+        .sum();
+    (chars / 4) as u32
+}
+
+/// Delays calls to a provider to stay within its [`RateLimit`]
+pub struct RateLimitedProvider {
+    inner: Box<dyn LLMProvider>,
+    limiter: Arc<RateLimiter>,
+    on_throttle: Option<StatusCallback>,
+}
+
+impl RateLimitedProvider {
+    pub fn new(inner: Box<dyn LLMProvider>, limit: RateLimit) -> Self {
+        Self {
+            inner,
+            limiter: Arc::new(RateLimiter::new(limit)),
+            on_throttle: None,
+        }
+    }
+
+    pub fn with_status_callback(mut self, on_throttle: StatusCallback) -> Self {
+        self.on_throttle = Some(on_throttle);
+        self
+    }
+
+    async fn acquire(&self, tokens: u32) {
+        self.limiter
+            .acquire(tokens, |delay, queued| {
+                let message = format!(
+                    "⏳ {} rate limit reached, waiting {:.1}s ({} request{} queued)",
+                    self.inner.name(),
+                    delay.as_secs_f64(),
+                    queued,
+                    if queued == 1 { "" } else { "s" }
+                );
+                info!("{}", message);
+                if let Some(on_throttle) = &self.on_throttle {
+                    on_throttle(&message);
+                }
+            })
+            .await;
+    }
+}
+
+#[async_trait]
+impl LLMProvider for RateLimitedProvider {
+    async fn complete(&self, request: CompletionRequest) -> Result<CompletionResponse> {
+        let estimated = estimate_tokens(&request);
+        self.acquire(estimated).await;
+        let response = self.inner.complete(request).await?;
+        self.limiter.settle(estimated, response.usage.total_tokens);
+        Ok(response)
+    }
+
+    async fn stream(&self, request: CompletionRequest) -> Result<CompletionStream> {
+        let estimated = estimate_tokens(&request);
+        self.acquire(estimated).await;
+        let mut stream = self.inner.stream(request).await?;
+
+        // Forward the stream, settling the charge once usage comes through
+        let limiter = self.limiter.clone();
+        let (tx, rx) = mpsc::channel(100);
+        tokio::spawn(async move {
+            let mut settled = false;
+            while let Some(item) = stream.next().await {
+                if let Ok(chunk) = &item {
+                    if let Some(usage) = chunk.usage.as_ref().filter(|_| !settled) {
+                        limiter.settle(estimated, usage.total_tokens);
+                        settled = true;
+                    }
+                }
+                if tx.send(item).await.is_err() {
+                    debug!("Receiver dropped, stopping rate-limited stream");
+                    return;
+                }
+            }
+        });
+        Ok(ReceiverStream::new(rx))
+    }
+
+    fn name(&self) -> &str {
+        self.inner.name()
+    }
+
+    fn model(&self) -> &str {
+        self.inner.model()
+    }
+
+    fn has_native_tool_calling(&self) -> bool {
+        self.inner.has_native_tool_calling()
+    }
+
+    fn supports_cache_control(&self) -> bool {
+        self.inner.supports_cache_control()
+    }
+
+    fn supports_vision(&self) -> bool {
+        self.inner.supports_vision()
+    }
+
+    fn max_tokens(&self) -> u32 {
+        self.inner.max_tokens()
+    }
+
+    fn temperature(&self) -> f32 {
+        self.inner.temperature()
+    }
+
+    fn token_counter(&self) -> Option<Arc<dyn TokenCounter>> {
+        self.inner.token_counter()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bucket_refills_over_the_window() {
+        let start = Instant::now();
+        let mut bucket = TokenBucket::new(60, WINDOW, start);
+        assert_eq!(bucket.wait_for(60.0, start), Duration::ZERO);
+        bucket.charge(60.0);
+
+        assert_eq!(bucket.wait_for(1.0, start), Duration::from_secs(1));
+        let later = start + Duration::from_millis(500);
+        assert_eq!(bucket.wait_for(1.0, later), Duration::from_millis(500));
+        // Never refills past its capacity
+        let much_later = start + Duration::from_secs(600);
+        assert_eq!(bucket.wait_for(60.0, much_later), Duration::ZERO);
+        assert_eq!(bucket.available, 60.0);
+    }
+
+    #[test]
+    fn test_oversized_amounts_and_settling() {
+        let start = Instant::now();
+        let mut bucket = TokenBucket::new(1000, WINDOW, start);
+        // Bigger than the whole quota: only waits for a full bucket
+        assert_eq!(bucket.wait_for(5000.0, start), Duration::ZERO);
+        bucket.charge(5000.0);
+        // 4001 tokens short at 1000 per minute
+        let delay = bucket.wait_for(1.0, start).as_secs_f64();
+        assert!((delay - 240.06).abs() < 1e-6);
+
+        // Refunding an overestimate is capped at the capacity
+        bucket.charge(-10_000.0);
+        assert_eq!(bucket.available, 1000.0);
+    }
+
+    #[tokio::test]
+    async fn test_acquire_waits_and_reports_queue_depth() {
+        let limiter = RateLimiter::with_window(
+            RateLimit {
+                requests_per_minute: Some(2),
+                tokens_per_minute: None,
+            },
+            Duration::from_millis(200),
+        );
+        let waits = Mutex::new(Vec::new());
+        let on_wait = |delay: Duration, queued: usize| waits.lock().unwrap().push((delay, queued));
+
+        let start = Instant::now();
+        limiter.acquire(10, on_wait).await;
+        limiter.acquire(10, on_wait).await;
+        assert!(waits.lock().unwrap().is_empty());
+
+        limiter.acquire(10, on_wait).await;
+        assert!(start.elapsed() >= Duration::from_millis(90));
+        let waits = waits.lock().unwrap();
+        assert!(!waits.is_empty());
+        assert_eq!(waits[0].1, 1);
+        assert_eq!(limiter.queued(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_tokens_per_minute_limits_large_prompts() {
+        let limiter = RateLimiter::with_window(
+            RateLimit {
+                requests_per_minute: None,
+                tokens_per_minute: Some(100),
+            },
+            Duration::from_millis(200),
+        );
+        let waited = AtomicUsize::new(0);
+        let on_wait = |_: Duration, _: usize| {
+            waited.fetch_add(1, Ordering::SeqCst);
+        };
+
+        limiter.acquire(80, on_wait).await;
+        // Used less than estimated, so the next call fits right away
+        limiter.settle(80, 20);
+        limiter.acquire(60, on_wait).await;
+        assert_eq!(waited.load(Ordering::SeqCst), 0);
+
+        limiter.acquire(60, on_wait).await;
+        assert!(waited.load(Ordering::SeqCst) > 0);
+    }
+}
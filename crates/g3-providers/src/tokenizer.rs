@@ -0,0 +1,220 @@
+//! Token counting with the tokenizer of the provider's model
+//!
+//! The agent's context window counts the tokens of every message to show
+//! how full the context is and to decide when to thin, compact or apply the
+//! retention policy. Providers hand it a [`TokenCounter`] through
+//! [`crate::LLMProvider::token_counter`]:
+//!
+//! - OpenAI models use their tiktoken encoding ([`tiktoken_for_model`])
+//! - Claude models, whose tokenizer isn't public, use `cl100k_base` counts
+//!   scaled by a per-family ratio ([`approximate_for_model`])
+//! - local models use the `tokenizer.json` they ship with
+//!   ([`HuggingFaceCounter`]), embedded models their own vocabulary
+//!
+//! Providers without a counter leave the context window to its
+//! characters-per-token estimate.
+
+use anyhow::Result;
+use std::collections::HashMap;
+use std::fmt;
+use std::path::Path;
+use std::sync::{Arc, Mutex, OnceLock};
+use tiktoken_rs::tokenizer::{get_tokenizer, Tokenizer};
+use tiktoken_rs::CoreBPE;
+use tracing::debug;
+
+/// Counts the tokens a text takes up in a model's context
+pub trait TokenCounter: fmt::Debug + Send + Sync {
+    /// What counts, e.g. `tiktoken O200kBase`, for logs and `/stats`
+    fn name(&self) -> &str;
+
+    fn count(&self, text: &str) -> u32;
+}
+
+/// Rough tokens per `cl100k_base` token for model families whose tokenizer
+/// isn't public, matched against the lowercased model name. They lean high,
+/// so the context fills up early rather than overflowing.
+const APPROXIMATIONS: &[(&str, f64)] = &[("claude", 1.15)];
+
+/// A tiktoken encoding, as OpenAI models use
+pub struct TiktokenCounter {
+    name: String,
+    bpe: CoreBPE,
+}
+
+impl TiktokenCounter {
+    fn new(tokenizer: Tokenizer) -> Result<Self> {
+        Ok(Self {
+            name: format!("tiktoken {:?}", tokenizer),
+            bpe: tiktoken_rs::get_bpe_from_tokenizer(tokenizer)?,
+        })
+    }
+}
+
+impl fmt::Debug for TiktokenCounter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TiktokenCounter")
+            .field("name", &self.name)
+            .finish_non_exhaustive()
+    }
+}
+
+impl TokenCounter for TiktokenCounter {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn count(&self, text: &str) -> u32 {
+        self.bpe.encode_with_special_tokens(text).len() as u32
+    }
+}
+
+/// The counter for `tokenizer`; encodings take a while to build, so each is
+/// built once
+fn tiktoken(tokenizer: Tokenizer) -> Option<Arc<dyn TokenCounter>> {
+    static COUNTERS: OnceLock<Mutex<HashMap<String, Arc<TiktokenCounter>>>> = OnceLock::new();
+
+    let key = format!("{:?}", tokenizer);
+    let mut counters = COUNTERS
+        .get_or_init(Default::default)
+        .lock()
+        .unwrap_or_else(|e| e.into_inner());
+    if let Some(counter) = counters.get(&key) {
+        return Some(counter.clone());
+    }
+    match TiktokenCounter::new(tokenizer) {
+        Ok(counter) => {
+            let counter = Arc::new(counter);
+            counters.insert(key, counter.clone());
+            Some(counter)
+        }
+        Err(e) => {
+            debug!("Failed to build tiktoken {}: {}", key, e);
+            None
+        }
+    }
+}
+
+/// The tiktoken encoding of an OpenAI `model`, if it has a known one
+pub fn tiktoken_for_model(model: &str) -> Option<Arc<dyn TokenCounter>> {
+    let tokenizer = get_tokenizer(model)
+        // GPT models newer than tiktoken-rs's table use o200k_base too
+        .or_else(|| model.starts_with("gpt-").then_some(Tokenizer::O200kBase))?;
+    tiktoken(tokenizer)
+}
+
+/// `cl100k_base` counts scaled by a ratio from [`APPROXIMATIONS`]
+#[derive(Debug)]
+pub struct ApproximateCounter {
+    name: String,
+    base: Arc<dyn TokenCounter>,
+    ratio: f64,
+}
+
+impl TokenCounter for ApproximateCounter {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn count(&self, text: &str) -> u32 {
+        (self.base.count(text) as f64 * self.ratio).ceil() as u32
+    }
+}
+
+/// An approximation for `model`, if its family has a ratio in
+/// [`APPROXIMATIONS`]
+pub fn approximate_for_model(model: &str) -> Option<Arc<dyn TokenCounter>> {
+    let model = model.to_lowercase();
+    let (family, ratio) = APPROXIMATIONS
+        .iter()
+        .find(|(family, _)| model.contains(family))?;
+    Some(Arc::new(ApproximateCounter {
+        name: format!("{} approximation ({} x cl100k_base)", family, ratio),
+        base: tiktoken(Tokenizer::Cl100kBase)?,
+        ratio: *ratio,
+    }))
+}
+
+/// A Hugging Face `tokenizer.json`, as local models ship with
+pub struct HuggingFaceCounter {
+    name: String,
+    tokenizer: tokenizers::Tokenizer,
+}
+
+impl HuggingFaceCounter {
+    pub fn from_file(path: &Path) -> Result<Self> {
+        let tokenizer = tokenizers::Tokenizer::from_file(path)
+            .map_err(|e| anyhow::anyhow!("Failed to load tokenizer {}: {}", path.display(), e))?;
+        Ok(Self {
+            name: format!("tokenizer {}", path.display()),
+            tokenizer,
+        })
+    }
+}
+
+impl fmt::Debug for HuggingFaceCounter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("HuggingFaceCounter")
+            .field("name", &self.name)
+            .finish_non_exhaustive()
+    }
+}
+
+impl TokenCounter for HuggingFaceCounter {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn count(&self, text: &str) -> u32 {
+        match self.tokenizer.encode(text, false) {
+            Ok(encoding) => encoding.len() as u32,
+            Err(e) => {
+                debug!("{} failed to encode: {}", self.name, e);
+                estimate(text)
+            }
+        }
+    }
+}
+
+/// Four characters per token, for text a tokenizer fails on
+pub(crate) fn estimate(text: &str) -> u32 {
+    text.len().div_ceil(4) as u32
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tiktoken_for_model() {
+        let counter = tiktoken_for_model("gpt-4o").unwrap();
+        assert_eq!(counter.name(), "tiktoken O200kBase");
+        assert_eq!(counter.count("hello world"), 2);
+        assert_eq!(counter.count(""), 0);
+
+        // Unknown GPT versions fall back to o200k_base
+        let newer = tiktoken_for_model("gpt-99").unwrap();
+        assert_eq!(newer.name(), "tiktoken O200kBase");
+        assert!(tiktoken_for_model("qwen2.5-coder:14b").is_none());
+    }
+
+    #[test]
+    fn test_approximate_for_model() {
+        let counter = approximate_for_model("claude-sonnet-4-5").unwrap();
+        let cl100k = tiktoken(Tokenizer::Cl100kBase).unwrap();
+        let text = "fn main() { println!(\"hello world\"); }";
+        assert_eq!(
+            counter.count(text),
+            (cl100k.count(text) as f64 * 1.15).ceil() as u32
+        );
+        assert!(approximate_for_model("gemini-2.5-pro").is_none());
+    }
+
+    #[test]
+    fn test_missing_tokenizer_file() {
+        let error = HuggingFaceCounter::from_file(Path::new("/nonexistent/tokenizer.json"))
+            .unwrap_err()
+            .to_string();
+        assert!(error.contains("/nonexistent/tokenizer.json"));
+    }
+}